@@ -0,0 +1,13 @@
+// Engine AI Behavior - behavior trees for NPC logic
+//
+// A tree is a RON asset of composites (Selector, Sequence), decorators
+// (Inverter, Succeeder, Repeat, Cooldown) and leaf tasks: functions in the
+// entity's script, or built-in actions (MoveTo drives the entity's NavAgent,
+// PlayAnimation its Animator, Wait). Every entity with a BehaviorAgent
+// component runs its own instance of the tree, ticked once per step.
+
+pub mod runner;
+pub mod tree;
+
+pub use runner::{BehaviorStatus, BehaviorSystem};
+pub use tree::{BehaviorNode, BehaviorTree, MoveTarget};
@@ -0,0 +1,494 @@
+// Behavior tree execution
+//
+// Each BehaviorAgent gets an instance of its tree: one slot of memory per
+// node, numbered in depth-first order, holding where a sequence is up to,
+// how long a Wait has run and so on. A node that finishes clears its own
+// memory; a running branch a Selector abandons for a higher priority one is
+// cleared by the Selector, which also stops any MoveTo in it.
+
+use crate::tree::{BehaviorNode, BehaviorTree, MoveTarget};
+use engine_scene::animator::Animator;
+use engine_scene::components::BehaviorAgent;
+use engine_scene::entity::EntityId;
+use engine_scene::navigation::NavAgent;
+use engine_scene::scene::Scene;
+use glam::Vec3;
+use std::collections::HashMap;
+
+/// How far a followed entity moves before MoveTo asks for a new path
+const RETARGET_DISTANCE: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BehaviorStatus {
+    Success,
+    Failure,
+    Running,
+}
+
+impl BehaviorStatus {
+    fn is_done(self) -> bool {
+        self != Self::Running
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct NodeMemory {
+    /// Sequence: child to resume at
+    child: usize,
+    /// Selector: child that was running
+    running_child: Option<usize>,
+    /// Repeat: finished runs of the child
+    count: u32,
+    /// Wait, PlayAnimation: seconds running
+    elapsed: f32,
+    /// PlayAnimation: length of the clip
+    duration: Option<f32>,
+    /// MoveTo: destination given to the agent
+    destination: Option<Vec3>,
+}
+
+/// One entity's run of a tree
+struct TreeInstance {
+    tree: String,
+    memory: Vec<NodeMemory>,
+    /// Cooldown node -> time it can run again; kept when branches reset
+    cooldowns: HashMap<usize, f32>,
+    time: f32,
+}
+
+/// Runs every BehaviorAgent's tree, loading trees from their files once
+#[derive(Default)]
+pub struct BehaviorSystem {
+    /// Loaded trees by path (None if the file failed to load)
+    trees: HashMap<String, Option<BehaviorTree>>,
+    instances: HashMap<EntityId, TreeInstance>,
+}
+
+impl BehaviorSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `tree` for agents that reference `path` instead of loading it
+    pub fn insert_tree(&mut self, path: impl Into<String>, tree: BehaviorTree) {
+        let path = path.into();
+        self.instances.retain(|_, instance| instance.tree != path);
+        self.trees.insert(path, Some(tree));
+    }
+
+    /// Load a tree file again (after it was edited); agents running it restart
+    pub fn reload(&mut self, path: &str) {
+        self.instances.retain(|_, instance| instance.tree != path);
+        self.trees.remove(path);
+    }
+
+    /// Forget every running tree, e.g. when play stops
+    pub fn clear(&mut self) {
+        self.instances.clear();
+    }
+
+    /// Tick every enabled agent's tree once. `run_script` calls a function
+    /// in an entity's script for Script leaves.
+    pub fn update(
+        &mut self,
+        scene: &mut Scene,
+        delta_time: f32,
+        mut run_script: impl FnMut(&mut Scene, EntityId, &str) -> BehaviorStatus,
+    ) {
+        let mut agents: Vec<(EntityId, String)> = scene
+            .entities()
+            .filter_map(|entity| {
+                let agent = entity.get_component::<BehaviorAgent>()?;
+                (agent.enabled && !agent.tree.is_empty()).then(|| (entity.id, agent.tree.clone()))
+            })
+            .collect();
+        agents.sort_by_key(|(id, _)| id.0);
+        self.instances.retain(|id, instance| {
+            agents
+                .iter()
+                .any(|(a, tree)| a == id && *tree == instance.tree)
+        });
+
+        for (entity, path) in agents {
+            let tree = self.trees.entry(path.clone()).or_insert_with(|| {
+                BehaviorTree::load(&path)
+                    .map_err(|e| log::warn!("{:#}", e))
+                    .ok()
+            });
+            let Some(tree) = tree.as_ref() else {
+                continue;
+            };
+            let instance = self
+                .instances
+                .entry(entity)
+                .or_insert_with(|| TreeInstance {
+                    tree: path,
+                    memory: vec![NodeMemory::default(); tree.root.size()],
+                    cooldowns: HashMap::new(),
+                    time: 0.0,
+                });
+            instance.time += delta_time;
+
+            let mut ticker = Ticker {
+                scene: &mut *scene,
+                entity,
+                delta_time,
+                time: instance.time,
+                memory: &mut instance.memory,
+                cooldowns: &mut instance.cooldowns,
+                run_script: &mut run_script,
+                path: Vec::new(),
+                branch: Vec::new(),
+            };
+            ticker.tick(&tree.root, 0);
+            let branch = ticker.branch;
+            if let Some(agent) = scene
+                .get_entity_mut(entity)
+                .and_then(|e| e.get_component_mut::<BehaviorAgent>())
+            {
+                agent.active_branch = branch;
+            }
+        }
+    }
+}
+
+/// Walks one instance's tree for a tick
+struct Ticker<'a, F> {
+    scene: &'a mut Scene,
+    entity: EntityId,
+    delta_time: f32,
+    time: f32,
+    memory: &'a mut [NodeMemory],
+    cooldowns: &'a mut HashMap<usize, f32>,
+    run_script: &'a mut F,
+    /// Labels from the root to the node being ticked
+    path: Vec<String>,
+    /// Path to the last leaf ticked, with its status
+    branch: Vec<String>,
+}
+
+impl<F: FnMut(&mut Scene, EntityId, &str) -> BehaviorStatus> Ticker<'_, F> {
+    fn tick(&mut self, node: &BehaviorNode, index: usize) -> BehaviorStatus {
+        self.path.push(node.label());
+        let status = match node {
+            BehaviorNode::Selector(children) => self.tick_selector(children, index),
+            BehaviorNode::Sequence(children) => self.tick_sequence(children, index),
+            BehaviorNode::Inverter(child) => match self.tick(child, index + 1) {
+                BehaviorStatus::Success => BehaviorStatus::Failure,
+                BehaviorStatus::Failure => BehaviorStatus::Success,
+                BehaviorStatus::Running => BehaviorStatus::Running,
+            },
+            BehaviorNode::Succeeder(child) => match self.tick(child, index + 1) {
+                BehaviorStatus::Running => BehaviorStatus::Running,
+                _ => BehaviorStatus::Success,
+            },
+            BehaviorNode::Repeat { times, child } => match self.tick(child, index + 1) {
+                BehaviorStatus::Success => {
+                    self.memory[index].count += 1;
+                    if times.is_some_and(|times| self.memory[index].count >= times) {
+                        self.memory[index] = NodeMemory::default();
+                        BehaviorStatus::Success
+                    } else {
+                        BehaviorStatus::Running
+                    }
+                }
+                BehaviorStatus::Failure => {
+                    self.memory[index] = NodeMemory::default();
+                    BehaviorStatus::Failure
+                }
+                BehaviorStatus::Running => BehaviorStatus::Running,
+            },
+            BehaviorNode::Cooldown { seconds, child } => {
+                if self
+                    .cooldowns
+                    .get(&index)
+                    .is_some_and(|ready| self.time < *ready)
+                {
+                    self.leaf_done(BehaviorStatus::Failure)
+                } else {
+                    let status = self.tick(child, index + 1);
+                    if status.is_done() {
+                        self.cooldowns.insert(index, self.time + seconds);
+                    }
+                    status
+                }
+            }
+            BehaviorNode::Script(function) => {
+                let status = (self.run_script)(self.scene, self.entity, function);
+                self.leaf_done(status)
+            }
+            BehaviorNode::MoveTo(target) => {
+                let status = self.move_to(target, index);
+                self.leaf_done(status)
+            }
+            BehaviorNode::PlayAnimation { state, blend_time } => {
+                let status = self.play_animation(state, *blend_time, index);
+                self.leaf_done(status)
+            }
+            BehaviorNode::Wait(seconds) => {
+                self.memory[index].elapsed += self.delta_time;
+                let status = if self.memory[index].elapsed >= *seconds {
+                    self.memory[index] = NodeMemory::default();
+                    BehaviorStatus::Success
+                } else {
+                    BehaviorStatus::Running
+                };
+                self.leaf_done(status)
+            }
+        };
+        self.path.pop();
+        status
+    }
+
+    /// Record the path to a leaf (or a skipped node) for the debugger
+    fn leaf_done(&mut self, status: BehaviorStatus) -> BehaviorStatus {
+        self.branch = self.path.clone();
+        if let Some(label) = self.branch.last_mut() {
+            label.push_str(&format!(" [{:?}]", status));
+        }
+        status
+    }
+
+    fn tick_selector(&mut self, children: &[BehaviorNode], index: usize) -> BehaviorStatus {
+        let running = self.memory[index].running_child;
+        let mut child_index = index + 1;
+        for (i, child) in children.iter().enumerate() {
+            let status = self.tick(child, child_index);
+            if status != BehaviorStatus::Failure {
+                // A higher priority child took over from the running one
+                if let Some(previous) = running.filter(|previous| *previous > i) {
+                    let start = index
+                        + 1
+                        + children[..previous]
+                            .iter()
+                            .map(BehaviorNode::size)
+                            .sum::<usize>();
+                    self.abort(start, children[previous].size());
+                }
+                self.memory[index].running_child = (status == BehaviorStatus::Running).then_some(i);
+                return status;
+            }
+            child_index += child.size();
+        }
+        self.memory[index].running_child = None;
+        BehaviorStatus::Failure
+    }
+
+    fn tick_sequence(&mut self, children: &[BehaviorNode], index: usize) -> BehaviorStatus {
+        let resume = self.memory[index].child;
+        let mut child_index = index
+            + 1
+            + children[..resume.min(children.len())]
+                .iter()
+                .map(BehaviorNode::size)
+                .sum::<usize>();
+        for (i, child) in children.iter().enumerate().skip(resume) {
+            match self.tick(child, child_index) {
+                BehaviorStatus::Success => child_index += child.size(),
+                BehaviorStatus::Running => {
+                    self.memory[index].child = i;
+                    return BehaviorStatus::Running;
+                }
+                BehaviorStatus::Failure => {
+                    self.memory[index] = NodeMemory::default();
+                    return BehaviorStatus::Failure;
+                }
+            }
+        }
+        self.memory[index] = NodeMemory::default();
+        BehaviorStatus::Success
+    }
+
+    /// Clear an abandoned branch's memory and stop its agent if it was moving
+    fn abort(&mut self, start: usize, size: usize) {
+        for memory in &mut self.memory[start..start + size] {
+            if memory.destination.is_some() {
+                if let Some(agent) = self
+                    .scene
+                    .get_entity_mut(self.entity)
+                    .and_then(|e| e.get_component_mut::<NavAgent>())
+                {
+                    agent.stop();
+                }
+            }
+            *memory = NodeMemory::default();
+        }
+    }
+
+    fn move_to(&mut self, target: &MoveTarget, index: usize) -> BehaviorStatus {
+        let target = match target {
+            MoveTarget::Position(position) => Some(*position),
+            MoveTarget::Entity(name) => self
+                .scene
+                .entities()
+                .filter(|e| e.name == *name)
+                .min_by_key(|e| e.id.0)
+                .map(|e| self.scene.world_matrix(e.id).w_axis.truncate()),
+        };
+        let memory = &mut self.memory[index];
+        let Some(agent) = self
+            .scene
+            .get_entity_mut(self.entity)
+            .and_then(|e| e.get_component_mut::<NavAgent>())
+        else {
+            *memory = NodeMemory::default();
+            return BehaviorStatus::Failure;
+        };
+        let Some(target) = target else {
+            if memory.destination.is_some() {
+                agent.stop();
+            }
+            *memory = NodeMemory::default();
+            return BehaviorStatus::Failure;
+        };
+
+        match memory.destination {
+            None => {
+                agent.set_destination(target);
+                memory.destination = Some(target);
+                BehaviorStatus::Running
+            }
+            // Done walking: arrived, or stopped because there was no path
+            Some(_) if !agent.is_moving() => {
+                *memory = NodeMemory::default();
+                if agent.arrived {
+                    BehaviorStatus::Success
+                } else {
+                    BehaviorStatus::Failure
+                }
+            }
+            Some(destination) => {
+                if destination.distance(target) > RETARGET_DISTANCE {
+                    agent.set_destination(target);
+                    memory.destination = Some(target);
+                }
+                BehaviorStatus::Running
+            }
+        }
+    }
+
+    fn play_animation(&mut self, state: &str, blend_time: f32, index: usize) -> BehaviorStatus {
+        let memory = &mut self.memory[index];
+        match memory.duration {
+            None => {
+                let duration = self
+                    .scene
+                    .get_entity_mut(self.entity)
+                    .and_then(|e| e.get_component_mut::<Animator>())
+                    .and_then(|animator| animator.play(state, blend_time));
+                match duration {
+                    Some(duration) => {
+                        memory.duration = Some(duration);
+                        BehaviorStatus::Running
+                    }
+                    None => BehaviorStatus::Failure,
+                }
+            }
+            Some(duration) => {
+                memory.elapsed += self.delta_time;
+                if memory.elapsed >= duration {
+                    *memory = NodeMemory::default();
+                    BehaviorStatus::Success
+                } else {
+                    BehaviorStatus::Running
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard_scene() -> (Scene, EntityId) {
+        let mut scene = Scene::new("Test".to_string());
+        let guard = scene.create_entity("Guard".to_string());
+        let entity = scene.get_entity_mut(guard).unwrap();
+        entity.add_component(BehaviorAgent::new("guard.ron"));
+        entity.add_component(NavAgent::new(4.0));
+        (scene, guard)
+    }
+
+    #[test]
+    fn test_sequence_walks_waits_and_walks_back() {
+        let (mut scene, guard) = guard_scene();
+        let mut system = BehaviorSystem::new();
+        system.insert_tree(
+            "guard.ron",
+            BehaviorTree::from_ron(
+                "(name: \"Patrol\", root: Sequence([
+                    MoveTo(Position((4.0, 0.0, 0.0))),
+                    Wait(0.5),
+                    MoveTo(Position((0.0, 0.0, 0.0))),
+                ]))",
+            )
+            .unwrap(),
+        );
+
+        let dt = 1.0 / 60.0;
+        let mut farthest: f32 = 0.0;
+        let mut came_back = false;
+        for _ in 0..300 {
+            system.update(&mut scene, dt, |_, _, _| BehaviorStatus::Failure);
+            engine_scene::navigation::update_nav_agents(&mut scene, dt, |from, to| {
+                Some(vec![from, to])
+            });
+            let position = scene.get_entity(guard).unwrap().transform.position;
+            farthest = farthest.max(position.x);
+            came_back |= farthest > 3.7 && position.length() < 0.3;
+        }
+
+        assert!(farthest > 3.7, "only got to x = {}", farthest);
+        assert!(came_back);
+    }
+
+    #[test]
+    fn test_selector_interrupts_running_branch() {
+        let (mut scene, guard) = guard_scene();
+        let mut system = BehaviorSystem::new();
+        system.insert_tree(
+            "guard.ron",
+            BehaviorTree::from_ron(
+                "(name: \"Guard\", root: Selector([
+                    Sequence([Script(\"sees_player\"), Script(\"attack\")]),
+                    MoveTo(Position((20.0, 0.0, 0.0))),
+                ]))",
+            )
+            .unwrap(),
+        );
+
+        let mut sees_player = false;
+        let mut attacks = 0;
+        let mut run = |scene: &mut Scene, sees: bool, attacks: &mut u32| {
+            system.update(scene, 0.1, |_, _, function| match function {
+                "sees_player" if sees => BehaviorStatus::Success,
+                "attack" => {
+                    *attacks += 1;
+                    BehaviorStatus::Running
+                }
+                _ => BehaviorStatus::Failure,
+            });
+        };
+        run(&mut scene, sees_player, &mut attacks);
+        let agent = scene
+            .get_entity(guard)
+            .unwrap()
+            .get_component::<NavAgent>()
+            .unwrap();
+        assert!(agent.is_moving());
+
+        sees_player = true;
+        run(&mut scene, sees_player, &mut attacks);
+        let entity = scene.get_entity(guard).unwrap();
+        assert!(!entity.get_component::<NavAgent>().unwrap().is_moving());
+        assert_eq!(attacks, 1);
+        assert_eq!(
+            entity
+                .get_component::<BehaviorAgent>()
+                .unwrap()
+                .active_branch,
+            vec!["Selector", "Sequence", "Script attack() [Running]"]
+        );
+    }
+}
@@ -0,0 +1,170 @@
+// Behavior tree assets
+//
+//   (
+//       name: "Guard",
+//       root: Selector([
+//           Sequence([
+//               Script("can_see_player"),
+//               MoveTo(Entity("Player")),
+//               PlayAnimation(state: "Attack"),
+//           ]),
+//           Sequence([
+//               MoveTo(Position((10.0, 0.0, 4.0))),
+//               Wait(2.0),
+//               MoveTo(Position((0.0, 0.0, 0.0))),
+//           ]),
+//       ]),
+//   )
+
+use anyhow::{Context, Result};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Where a MoveTo action walks to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MoveTarget {
+    Position(Vec3),
+    /// The entity with this name, followed as it moves
+    Entity(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BehaviorNode {
+    /// Tries children in order until one doesn't fail. Starts from the
+    /// first child every tick, so a higher priority branch interrupts a
+    /// running one.
+    Selector(Vec<BehaviorNode>),
+    /// Runs children in order while they succeed, resuming at a running child
+    Sequence(Vec<BehaviorNode>),
+    /// Swaps success and failure
+    Inverter(Box<BehaviorNode>),
+    /// Succeeds once the child finishes either way
+    Succeeder(Box<BehaviorNode>),
+    /// Runs the child `times` times (forever if None), stopping if it fails
+    Repeat {
+        times: Option<u32>,
+        child: Box<BehaviorNode>,
+    },
+    /// Fails without running the child for `seconds` after it last finished
+    Cooldown {
+        seconds: f32,
+        child: Box<BehaviorNode>,
+    },
+    /// Calls the function in the entity's script, which returns true or
+    /// "success", false or "failure", or "running" to be called again
+    Script(String),
+    /// Walks the entity's NavAgent to the target; fails if it can't get there
+    MoveTo(MoveTarget),
+    /// Cross-fades the entity's Animator to a state and waits for its clip
+    PlayAnimation {
+        state: String,
+        #[serde(default = "default_blend_time")]
+        blend_time: f32,
+    },
+    /// Keeps running for this many seconds
+    Wait(f32),
+}
+
+fn default_blend_time() -> f32 {
+    0.2
+}
+
+impl BehaviorNode {
+    /// Child nodes, in order
+    pub fn children(&self) -> &[BehaviorNode] {
+        match self {
+            Self::Selector(children) | Self::Sequence(children) => children,
+            Self::Inverter(child)
+            | Self::Succeeder(child)
+            | Self::Repeat { child, .. }
+            | Self::Cooldown { child, .. } => std::slice::from_ref(child.as_ref()),
+            Self::Script(_) | Self::MoveTo(_) | Self::PlayAnimation { .. } | Self::Wait(_) => &[],
+        }
+    }
+
+    /// Short description for the debugger
+    pub fn label(&self) -> String {
+        match self {
+            Self::Selector(_) => "Selector".to_string(),
+            Self::Sequence(_) => "Sequence".to_string(),
+            Self::Inverter(_) => "Inverter".to_string(),
+            Self::Succeeder(_) => "Succeeder".to_string(),
+            Self::Repeat {
+                times: Some(times), ..
+            } => format!("Repeat x{}", times),
+            Self::Repeat { times: None, .. } => "Repeat".to_string(),
+            Self::Cooldown { seconds, .. } => format!("Cooldown {}s", seconds),
+            Self::Script(function) => format!("Script {}()", function),
+            Self::MoveTo(MoveTarget::Position(p)) => {
+                format!("MoveTo ({:.1}, {:.1}, {:.1})", p.x, p.y, p.z)
+            }
+            Self::MoveTo(MoveTarget::Entity(name)) => format!("MoveTo {}", name),
+            Self::PlayAnimation { state, .. } => format!("PlayAnimation {}", state),
+            Self::Wait(seconds) => format!("Wait {}s", seconds),
+        }
+    }
+
+    /// Number of nodes in this subtree, itself included
+    pub fn size(&self) -> usize {
+        1 + self.children().iter().map(Self::size).sum::<usize>()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BehaviorTree {
+    pub name: String,
+    pub root: BehaviorNode,
+}
+
+impl BehaviorTree {
+    pub fn from_ron(source: &str) -> Result<Self> {
+        Ok(ron::from_str(source)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read behavior tree {}", path.display()))?;
+        Self::from_ron(&source)
+            .with_context(|| format!("Failed to parse behavior tree {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tree_from_ron() {
+        let tree = BehaviorTree::from_ron(
+            r#"(
+                name: "Guard",
+                root: Selector([
+                    Cooldown(seconds: 5.0, child: Script("shout")),
+                    Sequence([
+                        MoveTo(Position((10.0, 0.0, 4.0))),
+                        PlayAnimation(state: "LookAround"),
+                        Inverter(Wait(1.0)),
+                    ]),
+                ]),
+            )"#,
+        )
+        .unwrap();
+
+        assert_eq!(tree.name, "Guard");
+        assert_eq!(tree.root.size(), 8);
+        let patrol = &tree.root.children()[1];
+        assert_eq!(
+            patrol.children()[0],
+            BehaviorNode::MoveTo(MoveTarget::Position(Vec3::new(10.0, 0.0, 4.0)))
+        );
+        assert_eq!(
+            patrol.children()[1],
+            BehaviorNode::PlayAnimation {
+                state: "LookAround".to_string(),
+                blend_time: 0.2,
+            }
+        );
+    }
+}
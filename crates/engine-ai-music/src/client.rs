@@ -2,12 +2,15 @@
 
 use super::{
     AceStepConfig, AceStepError, GenerationMetadata, GenerationResult, HealthResponse,
-    MusicGenerationRequest,
+    MusicFallback, MusicGenerationRequest,
 };
 use reqwest::Client;
+use std::future::Future;
 use std::path::Path;
 use std::time::Duration;
 
+const DEFAULT_SAMPLE_RATE: u32 = 44100; // ACE-Step default
+
 /// Client for ACE-Step music generation API
 pub struct AceStepClient {
     config: AceStepConfig,
@@ -30,16 +33,43 @@ impl AceStepClient {
         Self { config, client }
     }
 
+    /// Access the client configuration
+    pub fn config(&self) -> &AceStepConfig {
+        &self.config
+    }
+
     /// Check if the ACE-Step service is available
     pub async fn health_check(&self) -> Result<HealthResponse, AceStepError> {
-        let url = format!("{}/health", self.config.base_url);
+        self.health_check_at(&self.config.base_url).await
+    }
+
+    /// Quick yes/no availability check (no retries)
+    pub async fn is_available(&self) -> bool {
+        matches!(self.health_check().await, Ok(health) if health.model_loaded)
+    }
+
+    /// Health check with the configured retry policy
+    pub async fn wait_until_healthy(&self) -> Result<HealthResponse, AceStepError> {
+        self.with_retry(|| self.health_check_at(&self.config.base_url))
+            .await
+    }
+
+    async fn health_check_at(&self, base_url: &str) -> Result<HealthResponse, AceStepError> {
+        let url = format!("{}/health", base_url);
 
         let response = self
             .client
             .get(&url)
+            .timeout(Duration::from_secs(self.config.health_timeout_secs))
             .send()
             .await
-            .map_err(|e| AceStepError::NetworkError(e.to_string()))?;
+            .map_err(|e| {
+                if e.is_timeout() {
+                    AceStepError::Timeout
+                } else {
+                    AceStepError::NetworkError(e.to_string())
+                }
+            })?;
 
         if !response.status().is_success() {
             return Err(AceStepError::ServiceUnavailable);
@@ -51,14 +81,109 @@ impl AceStepClient {
             .map_err(|e| AceStepError::ApiError(e.to_string()))
     }
 
+    /// Run `op`, retrying transient failures with exponential backoff
+    async fn with_retry<T, F, Fut>(&self, mut op: F) -> Result<T, AceStepError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, AceStepError>>,
+    {
+        let policy = &self.config.retry;
+        let mut attempt = 0;
+        loop {
+            match op().await {
+                Ok(value) => return Ok(value),
+                Err(e) if e.is_transient() && attempt < policy.max_retries => {
+                    let delay = policy.delay_for(attempt);
+                    log::warn!(
+                        "ACE-Step request failed ({}), retrying in {:?} ({}/{})",
+                        e,
+                        delay,
+                        attempt + 1,
+                        policy.max_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
     /// Generate music from a text prompt
+    ///
+    /// Transient failures are retried according to the configured
+    /// [`RetryPolicy`](super::RetryPolicy); if the service is still
+    /// unreachable the configured [`MusicFallback`] is used.
     pub async fn generate_music(
         &self,
         request: MusicGenerationRequest,
     ) -> Result<GenerationResult, AceStepError> {
         log::info!("Generating music: '{}'", request.prompt);
 
-        let url = format!("{}/generate", self.config.base_url);
+        let primary = self
+            .with_retry(|| self.generate_music_at(&self.config.base_url, &request))
+            .await;
+
+        match primary {
+            Err(e) if e.is_transient() => self.fallback(&request, e).await,
+            other => other,
+        }
+    }
+
+    async fn fallback(
+        &self,
+        request: &MusicGenerationRequest,
+        error: AceStepError,
+    ) -> Result<GenerationResult, AceStepError> {
+        match &self.config.fallback {
+            MusicFallback::None => Err(error),
+            MusicFallback::Server(url) => {
+                log::warn!(
+                    "ACE-Step unavailable ({}), trying fallback server {}",
+                    error,
+                    url
+                );
+                self.with_retry(|| self.generate_music_at(url, request))
+                    .await
+            }
+            MusicFallback::CachedFile(path) => {
+                log::warn!(
+                    "ACE-Step unavailable ({}), using cached audio {:?}",
+                    error,
+                    path
+                );
+                let audio_data = std::fs::read(path).map_err(|io| {
+                    AceStepError::ApiError(format!(
+                        "Fallback file {:?} unreadable: {} (after: {})",
+                        path, io, error
+                    ))
+                })?;
+                Ok(GenerationResult {
+                    audio_data,
+                    duration_secs: requested_duration(request),
+                    sample_rate: DEFAULT_SAMPLE_RATE,
+                    metadata: fallback_metadata(request),
+                })
+            }
+            MusicFallback::Silence => {
+                log::warn!("ACE-Step unavailable ({}), returning silence", error);
+                let duration_secs = requested_duration(request);
+                Ok(GenerationResult {
+                    audio_data: silent_wav(duration_secs, DEFAULT_SAMPLE_RATE),
+                    duration_secs,
+                    sample_rate: DEFAULT_SAMPLE_RATE,
+                    metadata: fallback_metadata(request),
+                })
+            }
+        }
+    }
+
+    async fn generate_music_at(
+        &self,
+        base_url: &str,
+        request: &MusicGenerationRequest,
+    ) -> Result<GenerationResult, AceStepError> {
+        let url = format!("{}/generate", base_url);
 
         let response = self
             .client
@@ -74,6 +199,10 @@ impl AceStepClient {
                 }
             })?;
 
+        if response.status().is_server_error() {
+            return Err(AceStepError::ServiceUnavailable);
+        }
+
         if !response.status().is_success() {
             let error_text = response
                 .text()
@@ -117,11 +246,8 @@ impl AceStepClient {
 
         Ok(GenerationResult {
             audio_data,
-            duration_secs: request
-                .duration
-                .map(|d| d.as_secs() as f32)
-                .unwrap_or(30.0),
-            sample_rate: 44100, // ACE-Step default
+            duration_secs: requested_duration(request),
+            sample_rate: DEFAULT_SAMPLE_RATE,
             metadata: api_response.metadata.unwrap_or(GenerationMetadata {
                 prompt: request.prompt.clone(),
                 seed: request.seed,
@@ -147,32 +273,36 @@ impl AceStepClient {
         Ok(())
     }
 
-    /// Generate a variation of existing audio
-    pub async fn generate_variation(
+    /// Upload audio plus form fields and return the response body
+    async fn post_audio(
         &self,
-        original_audio: &[u8],
-        variation_strength: f32, // 0.0 to 1.0
-    ) -> Result<GenerationResult, AceStepError> {
-        let url = format!("{}/variation", self.config.base_url);
-
-        let form = reqwest::multipart::Form::new()
-            .part(
-                "audio",
-                reqwest::multipart::Part::bytes(original_audio.to_vec())
-                    .file_name("audio.wav")
-                    .mime_str("audio/wav")
-                    .map_err(|e| AceStepError::InvalidRequest(e.to_string()))?,
-            )
-            .text("strength", variation_strength.to_string());
+        url: &str,
+        audio: Vec<u8>,
+        fields: &[(&'static str, String)],
+    ) -> Result<Vec<u8>, AceStepError> {
+        let mut form = reqwest::multipart::Form::new().part(
+            "audio",
+            reqwest::multipart::Part::bytes(audio)
+                .file_name("audio.wav")
+                .mime_str("audio/wav")
+                .map_err(|e| AceStepError::InvalidRequest(e.to_string()))?,
+        );
+        for (name, value) in fields {
+            form = form.text(*name, value.clone());
+        }
 
         let response = self
             .client
-            .post(&url)
+            .post(url)
             .multipart(form)
             .send()
             .await
             .map_err(|e| AceStepError::NetworkError(e.to_string()))?;
 
+        if response.status().is_server_error() {
+            return Err(AceStepError::ServiceUnavailable);
+        }
+
         if !response.status().is_success() {
             return Err(AceStepError::ApiError(
                 response
@@ -182,16 +312,32 @@ impl AceStepClient {
             ));
         }
 
-        let audio_data = response
+        Ok(response
             .bytes()
             .await
             .map_err(|e| AceStepError::NetworkError(e.to_string()))?
-            .to_vec();
+            .to_vec())
+    }
+
+    /// Generate a variation of existing audio
+    pub async fn generate_variation(
+        &self,
+        original_audio: &[u8],
+        variation_strength: f32, // 0.0 to 1.0
+    ) -> Result<GenerationResult, AceStepError> {
+        let url = format!("{}/variation", self.config.base_url);
+
+        let audio_part = original_audio.to_vec();
+        let fields = [("strength", variation_strength.to_string())];
+
+        let audio_data = self
+            .with_retry(|| self.post_audio(&url, audio_part.clone(), &fields))
+            .await?;
 
         Ok(GenerationResult {
             audio_data,
             duration_secs: 30.0,
-            sample_rate: 44100,
+            sample_rate: DEFAULT_SAMPLE_RATE,
             metadata: GenerationMetadata {
                 prompt: format!("Variation (strength: {})", variation_strength),
                 seed: None,
@@ -210,44 +356,23 @@ impl AceStepClient {
     ) -> Result<GenerationResult, AceStepError> {
         let url = format!("{}/extend", self.config.base_url);
 
-        let form = reqwest::multipart::Form::new()
-            .part(
-                "audio",
-                reqwest::multipart::Part::bytes(original_audio.to_vec())
-                    .file_name("audio.wav")
-                    .mime_str("audio/wav")
-                    .map_err(|e| AceStepError::InvalidRequest(e.to_string()))?,
-            )
-            .text("direction", if extend_before { "before" } else { "after" })
-            .text("duration", duration_secs.to_string());
+        let audio_part = original_audio.to_vec();
+        let fields = [
+            (
+                "direction",
+                (if extend_before { "before" } else { "after" }).to_string(),
+            ),
+            ("duration", duration_secs.to_string()),
+        ];
 
-        let response = self
-            .client
-            .post(&url)
-            .multipart(form)
-            .send()
-            .await
-            .map_err(|e| AceStepError::NetworkError(e.to_string()))?;
-
-        if !response.status().is_success() {
-            return Err(AceStepError::ApiError(
-                response
-                    .text()
-                    .await
-                    .unwrap_or_else(|_| "Unknown error".to_string()),
-            ));
-        }
-
-        let audio_data = response
-            .bytes()
-            .await
-            .map_err(|e| AceStepError::NetworkError(e.to_string()))?
-            .to_vec();
+        let audio_data = self
+            .with_retry(|| self.post_audio(&url, audio_part.clone(), &fields))
+            .await?;
 
         Ok(GenerationResult {
             audio_data,
             duration_secs: duration_secs as f32,
-            sample_rate: 44100,
+            sample_rate: DEFAULT_SAMPLE_RATE,
             metadata: GenerationMetadata {
                 prompt: format!(
                     "Extended {} by {}s",
@@ -268,9 +393,86 @@ impl Default for AceStepClient {
     }
 }
 
+fn requested_duration(request: &MusicGenerationRequest) -> f32 {
+    request.duration.map(|d| d.as_secs() as f32).unwrap_or(30.0)
+}
+
+fn fallback_metadata(request: &MusicGenerationRequest) -> GenerationMetadata {
+    GenerationMetadata {
+        prompt: request.prompt.clone(),
+        seed: request.seed,
+        inference_steps: 0,
+        generation_time_secs: None,
+    }
+}
+
+/// Build a 16-bit mono PCM WAV containing silence
+pub(crate) fn silent_wav(duration_secs: f32, sample_rate: u32) -> Vec<u8> {
+    let num_samples = (duration_secs.max(0.0) * sample_rate as f32) as u32;
+    let data_len = num_samples * 2;
+
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes()); // fmt chunk size
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * 2).to_le_bytes()); // byte rate
+    wav.extend_from_slice(&2u16.to_le_bytes()); // block align
+    wav.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    wav.resize(44 + data_len as usize, 0);
+    wav
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{MusicDuration, RetryPolicy};
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            base_delay_ms: 100,
+            max_delay_ms: 1000,
+        };
+        assert_eq!(policy.delay_for(0), Duration::from_millis(100));
+        assert_eq!(policy.delay_for(1), Duration::from_millis(200));
+        assert_eq!(policy.delay_for(3), Duration::from_millis(800));
+        assert_eq!(policy.delay_for(4), Duration::from_millis(1000));
+        assert_eq!(policy.delay_for(80), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn test_silent_wav_layout() {
+        let wav = silent_wav(1.0, 8000);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(&wav[8..12], b"WAVE");
+        assert_eq!(wav.len(), 44 + 16000);
+        assert!(wav[44..].iter().all(|&b| b == 0));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_server_falls_back_to_silence() {
+        // Port 9 (discard) is not expected to host an HTTP service
+        let config = AceStepConfig::with_url("http://127.0.0.1:9")
+            .with_retry(RetryPolicy::none())
+            .with_fallback(MusicFallback::Silence);
+        let client = AceStepClient::with_config(config);
+
+        let request = MusicGenerationRequest::new("test").with_duration(MusicDuration::Custom(1));
+        let result = client.generate_music(request).await.unwrap();
+        assert_eq!(result.duration_secs, 1.0);
+        assert_eq!(
+            result.audio_data.len(),
+            44 + DEFAULT_SAMPLE_RATE as usize * 2
+        );
+    }
 
     #[tokio::test]
     #[ignore] // Only run when ACE-Step service is running
@@ -8,11 +8,16 @@
 
 
 pub mod client;
+pub mod moments;
 pub mod types;
 
 pub use client::AceStepClient;
+pub use moments::{MomentTrack, MusicMoment, MusicMoments, MusicMomentsConfig};
 pub use types::*;
 
+use std::path::PathBuf;
+use std::time::Duration;
+
 /// Configuration for ACE-Step service
 #[derive(Debug, Clone)]
 pub struct AceStepConfig {
@@ -20,6 +25,12 @@ pub struct AceStepConfig {
     pub base_url: String,
     /// Default timeout for requests (in seconds)
     pub timeout_secs: u64,
+    /// Timeout for health checks (in seconds)
+    pub health_timeout_secs: u64,
+    /// Retry behaviour for transient failures
+    pub retry: RetryPolicy,
+    /// What to do when the service stays unreachable after all retries
+    pub fallback: MusicFallback,
 }
 
 impl Default for AceStepConfig {
@@ -27,8 +38,65 @@ impl Default for AceStepConfig {
         Self {
             base_url: "http://localhost:7865".to_string(),
             timeout_secs: 300, // 5 minutes for music generation
+            health_timeout_secs: 5,
+            retry: RetryPolicy::default(),
+            fallback: MusicFallback::None,
+        }
+    }
+}
+
+/// Exponential backoff settings for retrying transient errors
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt (0 = no retries)
+    pub max_retries: u32,
+    /// Delay before the first retry (in milliseconds)
+    pub base_delay_ms: u64,
+    /// Upper bound on any single delay (in milliseconds)
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 8_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy that never retries
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
         }
     }
+
+    /// Delay before retry number `attempt` (0-based), doubling each time
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let factor = 1u64.checked_shl(attempt).unwrap_or(u64::MAX);
+        let delay = self
+            .base_delay_ms
+            .saturating_mul(factor)
+            .min(self.max_delay_ms);
+        Duration::from_millis(delay)
+    }
+}
+
+/// Fallback used when the primary service is unreachable
+#[derive(Debug, Clone, PartialEq)]
+pub enum MusicFallback {
+    /// Return the error to the caller
+    None,
+    /// Try a secondary ACE-Step server (same retry policy)
+    Server(String),
+    /// Use a previously generated audio file from disk
+    CachedFile(PathBuf),
+    /// Return a silent WAV of the requested duration
+    Silence,
 }
 
 impl AceStepConfig {
@@ -45,12 +113,24 @@ impl AceStepConfig {
         self.timeout_secs = timeout_secs;
         self
     }
+
+    /// Set retry policy
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Set fallback used when the service is unreachable
+    pub fn with_fallback(mut self, fallback: MusicFallback) -> Self {
+        self.fallback = fallback;
+        self
+    }
 }
 
 /// Convenience re-exports
 pub mod prelude {
     pub use super::{
-        AceStepClient, AceStepConfig, MusicGenerationRequest, MusicStyle, MusicDuration,
-        GenerationResult, AceStepError,
+        AceStepClient, AceStepConfig, AceStepError, GenerationResult, MusicDuration, MusicFallback,
+        MusicGenerationRequest, MusicStyle, RetryPolicy,
     };
 }
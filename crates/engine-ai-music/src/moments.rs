@@ -0,0 +1,337 @@
+// Music moments - named game events mapped to generated tracks
+//
+// A small RON file maps moments such as "main_menu" or "boss_fight" to a
+// style, duration and prompt. Tracks are generated once with ACE-Step and
+// cached under the asset directory so later plays are instant.
+
+use super::{AceStepClient, AceStepError, MusicDuration, MusicGenerationRequest, MusicStyle};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+
+/// A single named music moment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MusicMoment {
+    /// Description of the music (substituted into the prompt template)
+    pub prompt: String,
+    /// Music style/genre
+    #[serde(default)]
+    pub style: Option<MusicStyle>,
+    /// Track length
+    #[serde(default = "default_duration")]
+    pub duration: MusicDuration,
+    /// Tempo (BPM)
+    #[serde(default)]
+    pub tempo: Option<u32>,
+    /// Fixed seed so regenerating gives the same track
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Playback volume (0.0 to 1.0)
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+    /// Loop the track while the moment is active
+    #[serde(default = "default_looping")]
+    pub looping: bool,
+    /// Pre-made track (relative to asset root) used instead of generating
+    #[serde(default)]
+    pub track: Option<String>,
+}
+
+fn default_duration() -> MusicDuration {
+    MusicDuration::Long
+}
+
+fn default_volume() -> f32 {
+    0.7
+}
+
+fn default_looping() -> bool {
+    true
+}
+
+/// Declarative moment configuration (loaded from RON)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MusicMomentsConfig {
+    /// Directory (relative to asset root) where generated tracks are cached
+    #[serde(default = "default_cache_dir")]
+    pub cache_dir: String,
+    /// Prompt template; supports `{prompt}`, `{moment}`, `{style}` and `{duration}`
+    #[serde(default = "default_template")]
+    pub prompt_template: String,
+    /// Named moments
+    #[serde(default)]
+    pub moments: HashMap<String, MusicMoment>,
+}
+
+fn default_cache_dir() -> String {
+    "music/generated".to_string()
+}
+
+fn default_template() -> String {
+    "{prompt}".to_string()
+}
+
+impl Default for MusicMomentsConfig {
+    fn default() -> Self {
+        Self {
+            cache_dir: default_cache_dir(),
+            prompt_template: default_template(),
+            moments: HashMap::new(),
+        }
+    }
+}
+
+impl MusicMomentsConfig {
+    /// Parse configuration from a RON string
+    pub fn from_ron(source: &str) -> anyhow::Result<Self> {
+        Ok(ron::from_str(source)?)
+    }
+
+    /// Load configuration from a RON file
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let source = std::fs::read_to_string(path.as_ref())?;
+        Self::from_ron(&source)
+    }
+}
+
+/// A resolved track ready for playback
+#[derive(Debug, Clone, PartialEq)]
+pub struct MomentTrack {
+    /// Moment name
+    pub moment: String,
+    /// Path relative to the asset root
+    pub path: String,
+    /// Playback volume
+    pub volume: f32,
+    /// Whether to loop
+    pub looping: bool,
+}
+
+/// Resolves moments to tracks, generating and caching them as needed
+pub struct MusicMoments {
+    config: MusicMomentsConfig,
+    asset_root: PathBuf,
+    client: Arc<AceStepClient>,
+}
+
+impl MusicMoments {
+    /// Create a moment resolver for the given asset root
+    pub fn new(
+        config: MusicMomentsConfig,
+        asset_root: impl Into<PathBuf>,
+        client: AceStepClient,
+    ) -> Self {
+        Self {
+            config,
+            asset_root: asset_root.into(),
+            client: Arc::new(client),
+        }
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> &MusicMomentsConfig {
+        &self.config
+    }
+
+    /// Look up a moment by name
+    pub fn moment(&self, name: &str) -> Option<&MusicMoment> {
+        self.config.moments.get(name)
+    }
+
+    /// Build the generation request for a moment
+    pub fn build_request(&self, name: &str) -> Option<MusicGenerationRequest> {
+        let moment = self.moment(name)?;
+        let style = moment
+            .style
+            .as_ref()
+            .map(|s| s.to_string())
+            .unwrap_or_default();
+        let prompt = self
+            .config
+            .prompt_template
+            .replace("{prompt}", &moment.prompt)
+            .replace("{moment}", &name.replace('_', " "))
+            .replace("{style}", &style)
+            .replace("{duration}", &moment.duration.as_secs().to_string());
+
+        let mut request = MusicGenerationRequest::new(prompt)
+            .with_duration(moment.duration)
+            .instrumental();
+        if let Some(style) = &moment.style {
+            request = request.with_style(style.clone());
+        }
+        if let Some(tempo) = moment.tempo {
+            request = request.with_tempo(tempo);
+        }
+        if let Some(seed) = moment.seed {
+            request = request.with_seed(seed);
+        }
+        Some(request)
+    }
+
+    /// Relative cache path for a moment's generated track
+    ///
+    /// The file name includes a hash of the request so editing a moment
+    /// in the config produces a fresh track instead of a stale one.
+    pub fn cache_path(&self, name: &str) -> Option<String> {
+        let request = self.build_request(name)?;
+        let key = format!(
+            "{}|{:?}|{:?}|{:?}|{:?}",
+            request.prompt, request.style, request.duration, request.tempo, request.seed
+        );
+        Some(format!(
+            "{}/{}_{:016x}.wav",
+            self.config.cache_dir.trim_end_matches('/'),
+            name,
+            fnv1a(key.as_bytes())
+        ))
+    }
+
+    /// Return the track for a moment if it needs no generation
+    pub fn cached_track(&self, name: &str) -> Option<MomentTrack> {
+        let moment = self.moment(name)?;
+        let path = match &moment.track {
+            Some(track) => track.clone(),
+            None => self.cache_path(name)?,
+        };
+        if self.asset_root.join(&path).exists() {
+            Some(self.track(name, moment, path))
+        } else {
+            None
+        }
+    }
+
+    /// Fetch a moment's track from cache, generating it if missing
+    pub async fn fetch_or_generate(&self, name: &str) -> Result<MomentTrack, AceStepError> {
+        if let Some(track) = self.cached_track(name) {
+            return Ok(track);
+        }
+
+        let moment = self.moment(name).ok_or_else(|| {
+            AceStepError::InvalidRequest(format!("Unknown music moment '{}'", name))
+        })?;
+        let request = self.build_request(name).expect("moment exists");
+        let path = self.cache_path(name).expect("moment exists");
+        let full_path = self.asset_root.join(&path);
+
+        if let Some(parent) = full_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                AceStepError::ApiError(format!("Failed to create cache dir: {}", e))
+            })?;
+        }
+
+        log::info!("Generating track for music moment '{}'", name);
+        self.client.generate_and_save(request, &full_path).await?;
+
+        Ok(self.track(name, moment, path))
+    }
+
+    /// Resolve a moment on a background thread
+    ///
+    /// Returns immediately; the receiver yields the track once it has been
+    /// fetched or generated. Suitable for calling from a frame loop.
+    pub fn request(self: &Arc<Self>, name: &str) -> Receiver<Result<MomentTrack, AceStepError>> {
+        let (tx, rx) = mpsc::channel();
+        let moments = Arc::clone(self);
+        let name = name.to_string();
+        std::thread::spawn(move || {
+            let result = match tokio::runtime::Runtime::new() {
+                Ok(rt) => rt.block_on(moments.fetch_or_generate(&name)),
+                Err(e) => Err(AceStepError::ApiError(format!(
+                    "Failed to start runtime: {}",
+                    e
+                ))),
+            };
+            let _ = tx.send(result);
+        });
+        rx
+    }
+
+    fn track(&self, name: &str, moment: &MusicMoment, path: String) -> MomentTrack {
+        MomentTrack {
+            moment: name.to_string(),
+            path,
+            volume: moment.volume,
+            looping: moment.looping,
+        }
+    }
+}
+
+/// 64-bit FNV-1a hash (stable across runs, unlike `DefaultHasher`)
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &b in bytes {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        (
+            cache_dir: "music/generated",
+            prompt_template: "{prompt}, {style} game soundtrack",
+            moments: {
+                "boss_fight": (
+                    prompt: "intense orchestral battle",
+                    style: Some(cinematic),
+                    duration: medium,
+                    tempo: Some(150),
+                ),
+                "main_menu": (
+                    prompt: "calm overture",
+                    track: Some("music/menu.ogg"),
+                    volume: 0.4,
+                ),
+            },
+        )
+    "#;
+
+    fn moments() -> MusicMoments {
+        let config = MusicMomentsConfig::from_ron(CONFIG).unwrap();
+        MusicMoments::new(config, "/nonexistent-assets", AceStepClient::new())
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config = MusicMomentsConfig::from_ron(CONFIG).unwrap();
+        assert_eq!(config.moments.len(), 2);
+        let menu = &config.moments["main_menu"];
+        assert_eq!(menu.volume, 0.4);
+        assert!(menu.looping);
+        assert_eq!(menu.duration.as_secs(), 60);
+    }
+
+    #[test]
+    fn test_prompt_template() {
+        let request = moments().build_request("boss_fight").unwrap();
+        assert_eq!(
+            request.prompt,
+            "intense orchestral battle, cinematic game soundtrack"
+        );
+        assert_eq!(request.tempo, Some(150));
+        assert!(request.instrumental);
+        assert!(moments().build_request("missing").is_none());
+    }
+
+    #[test]
+    fn test_cache_path_is_stable() {
+        let a = moments().cache_path("boss_fight").unwrap();
+        let b = moments().cache_path("boss_fight").unwrap();
+        assert_eq!(a, b);
+        assert!(a.starts_with("music/generated/boss_fight_"));
+        assert!(a.ends_with(".wav"));
+    }
+
+    #[test]
+    fn test_missing_track_is_not_cached() {
+        assert!(moments().cached_track("main_menu").is_none());
+        assert!(moments().cached_track("boss_fight").is_none());
+    }
+}
@@ -32,6 +32,16 @@ impl fmt::Display for AceStepError {
 
 impl std::error::Error for AceStepError {}
 
+impl AceStepError {
+    /// Whether the error is likely transient and worth retrying
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::NetworkError(_) | Self::Timeout | Self::ServiceUnavailable
+        )
+    }
+}
+
 /// Music generation request parameters
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MusicGenerationRequest {
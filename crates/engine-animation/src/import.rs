@@ -0,0 +1,390 @@
+// glTF skin import - skeleton, skinned primitives and animations
+//
+// Only the first skin is imported, with the primitives of every mesh node
+// that uses it. Each animation becomes a SkeletalClip of the channels that
+// target skeleton nodes; cubic spline keys keep their values and are
+// interpolated linearly like every other track.
+
+use crate::skeleton::{
+    SkeletalClip, Skeleton, SkeletonNode, SkinnedModel, SkinnedPrimitive, SkinnedVertex,
+};
+use anyhow::{Context, Result};
+use engine_scene::animation::{AnimatedProperty, AnimationClip};
+use engine_scene::transform::Transform;
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+use gltf::animation::util::ReadOutputs;
+use gltf::animation::Interpolation;
+use std::collections::HashMap;
+use std::path::Path;
+
+impl SkinnedModel {
+    /// Import the first skin of a glTF file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let (document, buffers, _images) =
+            gltf::import(path).with_context(|| format!("Failed to load GLTF file: {:?}", path))?;
+        Self::from_gltf(&document, &buffers)
+            .with_context(|| format!("Failed to import skin from {:?}", path))
+    }
+
+    /// Import the first skin of a glTF file already in memory (.gltf with
+    /// embedded buffers, or .glb)
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        let (document, buffers, _images) = gltf::import_slice(bytes)?;
+        Self::from_gltf(&document, &buffers)
+    }
+
+    /// Import the first skin of an already parsed glTF document
+    pub fn from_gltf(document: &gltf::Document, buffers: &[gltf::buffer::Data]) -> Result<Self> {
+        let skin = document.skins().next().context("Model has no skin")?;
+        let (skeleton, node_index) = import_skeleton(document, buffers, &skin);
+
+        let mut primitives = Vec::new();
+        for node in document.nodes() {
+            let (Some(mesh), Some(node_skin)) = (node.mesh(), node.skin()) else {
+                continue;
+            };
+            if node_skin.index() != skin.index() {
+                continue;
+            }
+            for primitive in mesh.primitives() {
+                primitives.push(import_primitive(&primitive, buffers)?);
+            }
+        }
+        anyhow::ensure!(!primitives.is_empty(), "No mesh uses the skin");
+
+        let clips = document
+            .animations()
+            .enumerate()
+            .map(|(index, animation)| import_clip(&animation, index, buffers, &node_index))
+            .filter(|clip| !clip.channels.is_empty())
+            .collect();
+
+        Ok(Self {
+            skeleton,
+            primitives,
+            clips,
+        })
+    }
+}
+
+/// The skin's joints and every node above them, parents first, with the
+/// skeleton node index of each glTF node in it
+fn import_skeleton(
+    document: &gltf::Document,
+    buffers: &[gltf::buffer::Data],
+    skin: &gltf::Skin,
+) -> (Skeleton, HashMap<usize, usize>) {
+    let mut parents = HashMap::new();
+    for node in document.nodes() {
+        for child in node.children() {
+            parents.insert(child.index(), node.index());
+        }
+    }
+
+    // Joints and their ancestors, ordered from the roots down
+    let mut members: Vec<usize> = Vec::new();
+    for joint in skin.joints() {
+        let mut chain = vec![joint.index()];
+        while let Some(&parent) = parents.get(chain.last().unwrap()) {
+            chain.push(parent);
+        }
+        for node in chain.into_iter().rev() {
+            if !members.contains(&node) {
+                members.push(node);
+            }
+        }
+    }
+    let depth = |mut node: usize| {
+        let mut depth = 0;
+        while let Some(&parent) = parents.get(&node) {
+            node = parent;
+            depth += 1;
+        }
+        depth
+    };
+    members.sort_by_key(|&node| depth(node));
+    let node_index: HashMap<usize, usize> = members
+        .iter()
+        .enumerate()
+        .map(|(index, &node)| (node, index))
+        .collect();
+
+    let gltf_nodes: Vec<gltf::Node> = document.nodes().collect();
+    let nodes = members
+        .iter()
+        .map(|&node| {
+            let gltf_node = &gltf_nodes[node];
+            let (translation, rotation, scale) = gltf_node.transform().decomposed();
+            SkeletonNode {
+                name: gltf_node
+                    .name()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("Bone {}", node)),
+                parent: parents.get(&node).and_then(|p| node_index.get(p)).copied(),
+                rest: Transform {
+                    position: Vec3::from(translation),
+                    rotation: Quat::from_array(rotation),
+                    scale: Vec3::from(scale),
+                },
+            }
+        })
+        .collect();
+
+    let joints: Vec<usize> = skin
+        .joints()
+        .map(|joint| node_index[&joint.index()])
+        .collect();
+    let inverse_bind = skin
+        .reader(|buffer| Some(&buffers[buffer.index()]))
+        .read_inverse_bind_matrices()
+        .map(|matrices| matrices.map(|m| Mat4::from_cols_array_2d(&m)).collect())
+        .unwrap_or_else(|| vec![Mat4::IDENTITY; joints.len()]);
+
+    let skeleton = Skeleton {
+        nodes,
+        joints,
+        inverse_bind,
+    };
+    (skeleton, node_index)
+}
+
+fn import_primitive(
+    primitive: &gltf::Primitive,
+    buffers: &[gltf::buffer::Data],
+) -> Result<SkinnedPrimitive> {
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+    let positions: Vec<Vec3> = reader
+        .read_positions()
+        .context("GLTF mesh missing positions")?
+        .map(Vec3::from)
+        .collect();
+    let normals: Vec<Vec3> = reader
+        .read_normals()
+        .map(|normals| normals.map(Vec3::from).collect())
+        .unwrap_or_default();
+    let tex_coords: Vec<Vec2> = reader
+        .read_tex_coords(0)
+        .map(|uvs| uvs.into_f32().map(Vec2::from).collect())
+        .unwrap_or_default();
+    let joints: Vec<[u16; 4]> = reader
+        .read_joints(0)
+        .context("Skinned mesh missing JOINTS_0")?
+        .into_u16()
+        .collect();
+    let weights: Vec<[f32; 4]> = reader
+        .read_weights(0)
+        .context("Skinned mesh missing WEIGHTS_0")?
+        .into_f32()
+        .collect();
+
+    let vertices = positions
+        .iter()
+        .enumerate()
+        .map(|(i, &position)| {
+            let mut weights = Vec4::from(weights.get(i).copied().unwrap_or([1.0, 0.0, 0.0, 0.0]));
+            let total = weights.element_sum();
+            weights = if total > 0.0 {
+                weights / total
+            } else {
+                Vec4::X
+            };
+            SkinnedVertex {
+                position,
+                normal: normals.get(i).copied().unwrap_or(Vec3::Y),
+                tex_coord: tex_coords.get(i).copied().unwrap_or(Vec2::ZERO),
+                joints: joints.get(i).copied().unwrap_or_default().map(u32::from),
+                weights: weights.to_array(),
+            }
+        })
+        .collect();
+    let indices = reader
+        .read_indices()
+        .map(|indices| indices.into_u32().collect())
+        .unwrap_or_else(|| (0..positions.len() as u32).collect());
+
+    Ok(SkinnedPrimitive { vertices, indices })
+}
+
+fn import_clip(
+    animation: &gltf::Animation,
+    index: usize,
+    buffers: &[gltf::buffer::Data],
+    node_index: &HashMap<usize, usize>,
+) -> SkeletalClip {
+    let mut channels: Vec<(usize, AnimationClip)> = Vec::new();
+    let mut duration: f32 = 0.0;
+
+    for channel in animation.channels() {
+        let Some(&node) = node_index.get(&channel.target().node().index()) else {
+            continue;
+        };
+        let reader = channel.reader(|buffer| Some(&buffers[buffer.index()]));
+        let (Some(inputs), Some(outputs)) = (reader.read_inputs(), reader.read_outputs()) else {
+            continue;
+        };
+        let times: Vec<f32> = inputs.collect();
+        let (property, mut values): (AnimatedProperty, Vec<Vec4>) = match outputs {
+            ReadOutputs::Translations(v) => (
+                AnimatedProperty::Position,
+                v.map(|t| Vec3::from(t).extend(0.0)).collect(),
+            ),
+            ReadOutputs::Rotations(v) => (
+                AnimatedProperty::Rotation,
+                v.into_f32().map(Vec4::from).collect(),
+            ),
+            ReadOutputs::Scales(v) => (
+                AnimatedProperty::Scale,
+                v.map(|s| Vec3::from(s).extend(0.0)).collect(),
+            ),
+            ReadOutputs::MorphTargetWeights(_) => continue,
+        };
+        // Cubic spline keys are (in-tangent, value, out-tangent)
+        if channel.sampler().interpolation() == Interpolation::CubicSpline {
+            values = values
+                .chunks(3)
+                .filter_map(|key| key.get(1).copied())
+                .collect();
+        }
+
+        let clip = match channels.iter_mut().find(|(n, _)| *n == node) {
+            Some((_, clip)) => clip,
+            None => {
+                channels.push((node, AnimationClip::new(0.0)));
+                &mut channels.last_mut().unwrap().1
+            }
+        };
+        for (&time, &value) in times.iter().zip(&values) {
+            clip.set_key(property, time, value);
+            duration = duration.max(time);
+        }
+    }
+
+    for (_, clip) in &mut channels {
+        clip.duration = duration;
+    }
+    SkeletalClip {
+        name: animation
+            .name()
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("Animation {}", index)),
+        duration,
+        channels,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A two-joint skinned triangle with one animation, as a .glb
+    fn skinned_triangle_glb() -> Vec<u8> {
+        let mut bin: Vec<u8> = Vec::new();
+        let floats = |bin: &mut Vec<u8>, values: &[f32]| {
+            for value in values {
+                bin.extend_from_slice(&value.to_le_bytes());
+            }
+        };
+        floats(&mut bin, &[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+        for joints in [[0u16, 0, 0, 0], [0, 0, 0, 0], [1, 0, 0, 0]] {
+            for joint in joints {
+                bin.extend_from_slice(&joint.to_le_bytes());
+            }
+        }
+        floats(
+            &mut bin,
+            &[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0],
+        );
+        floats(&mut bin, &Mat4::IDENTITY.to_cols_array());
+        floats(
+            &mut bin,
+            &Mat4::from_translation(Vec3::NEG_Y).to_cols_array(),
+        );
+        floats(&mut bin, &[0.0, 1.0]);
+        floats(&mut bin, &[0.0, 1.0, 0.0, 0.0, 3.0, 0.0]);
+
+        let json = format!(
+            r#"{{
+            "asset": {{"version": "2.0"}},
+            "buffers": [{{"byteLength": {len}}}],
+            "bufferViews": [
+                {{"buffer": 0, "byteOffset": 0, "byteLength": 36}},
+                {{"buffer": 0, "byteOffset": 36, "byteLength": 24}},
+                {{"buffer": 0, "byteOffset": 60, "byteLength": 48}},
+                {{"buffer": 0, "byteOffset": 108, "byteLength": 128}},
+                {{"buffer": 0, "byteOffset": 236, "byteLength": 8}},
+                {{"buffer": 0, "byteOffset": 244, "byteLength": 24}}
+            ],
+            "accessors": [
+                {{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                  "min": [0, 0, 0], "max": [1, 1, 0]}},
+                {{"bufferView": 1, "componentType": 5123, "count": 3, "type": "VEC4"}},
+                {{"bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC4"}},
+                {{"bufferView": 3, "componentType": 5126, "count": 2, "type": "MAT4"}},
+                {{"bufferView": 4, "componentType": 5126, "count": 2, "type": "SCALAR",
+                  "min": [0], "max": [1]}},
+                {{"bufferView": 5, "componentType": 5126, "count": 2, "type": "VEC3"}}
+            ],
+            "meshes": [{{"primitives": [{{"attributes":
+                {{"POSITION": 0, "JOINTS_0": 1, "WEIGHTS_0": 2}}}}]}}],
+            "skins": [{{"joints": [0, 1], "inverseBindMatrices": 3}}],
+            "nodes": [
+                {{"name": "Root", "children": [1]}},
+                {{"name": "Tip", "translation": [0, 1, 0]}},
+                {{"name": "Body", "mesh": 0, "skin": 0}}
+            ],
+            "animations": [{{"name": "Lift",
+                "samplers": [{{"input": 4, "output": 5}}],
+                "channels": [{{"sampler": 0, "target": {{"node": 1, "path": "translation"}}}}]}}],
+            "scenes": [{{"nodes": [0, 2]}}]
+        }}"#,
+            len = bin.len()
+        );
+
+        let mut json = json.into_bytes();
+        while !json.len().is_multiple_of(4) {
+            json.push(b' ');
+        }
+        while !bin.len().is_multiple_of(4) {
+            bin.push(0);
+        }
+        let total = 12 + 8 + json.len() + 8 + bin.len();
+        let mut glb = Vec::with_capacity(total);
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(total as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json);
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&bin);
+        glb
+    }
+
+    #[test]
+    fn test_import_skin_and_animation() {
+        let model = SkinnedModel::from_slice(&skinned_triangle_glb()).unwrap();
+
+        let skeleton = &model.skeleton;
+        assert_eq!(skeleton.nodes.len(), 2);
+        assert_eq!(skeleton.nodes[1].name, "Tip");
+        assert_eq!(skeleton.nodes[1].parent, Some(0));
+        assert_eq!(skeleton.nodes[1].rest.position, Vec3::Y);
+        assert_eq!(skeleton.joints, vec![0, 1]);
+        assert_eq!(
+            skeleton.inverse_bind[1],
+            Mat4::from_translation(Vec3::NEG_Y)
+        );
+
+        let primitive = &model.primitives[0];
+        assert_eq!(primitive.vertices.len(), 3);
+        assert_eq!(primitive.vertices[2].joints, [1, 0, 0, 0]);
+        assert_eq!(primitive.indices, vec![0, 1, 2]);
+
+        let clip = model.clip("Lift").unwrap();
+        assert_eq!(clip.duration, 1.0);
+        let poses = clip.sample(0.5, skeleton.nodes.len());
+        assert_eq!(poses[1].position, Some(Vec3::new(0.0, 2.0, 0.0)));
+    }
+}
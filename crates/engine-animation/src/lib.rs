@@ -0,0 +1,16 @@
+// Engine Animation - skeletal animation from glTF skins
+//
+// Imports a model's skin, skinned meshes and animations, spawns the
+// skeleton as bone entities under a SkinnedMesh entity, and plays the
+// animations through the entity's Animator: a state with a skeletal clip
+// poses the bones instead of the entity. The renderer skins the mesh with
+// the joint matrices of the posed bones.
+
+pub mod import;
+pub mod skeleton;
+pub mod system;
+
+pub use skeleton::{
+    SkeletalClip, Skeleton, SkeletonNode, SkinnedModel, SkinnedPrimitive, SkinnedVertex,
+};
+pub use system::SkeletalAnimationSystem;
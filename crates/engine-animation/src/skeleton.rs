@@ -0,0 +1,158 @@
+// Skeletons, skeletal clips and skinned meshes imported from glTF
+//
+// A skeleton is the skin's joints plus the nodes above them (an armature
+// node usually carries a rotation or scale), each spawned as an entity so
+// clips, IK and ragdolls pose them like any other transform. Vertices are
+// skinned by the joint matrices: a joint's world transform times its
+// inverse bind matrix.
+
+use engine_scene::animation::{AnimationClip, AnimationPose};
+use engine_scene::entity::EntityId;
+use engine_scene::scene::Scene;
+use engine_scene::transform::Transform;
+use glam::{Mat4, Vec2, Vec3};
+
+/// A node of the skeleton, spawned as an entity
+#[derive(Debug, Clone)]
+pub struct SkeletonNode {
+    pub name: String,
+    /// Index of the parent node; None for the root, which is parented to
+    /// the entity with the SkinnedMesh
+    pub parent: Option<usize>,
+    /// Local transform in the bind pose
+    pub rest: Transform,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Skeleton {
+    /// Parents come before their children
+    pub nodes: Vec<SkeletonNode>,
+    /// Node index of each of the skin's joints
+    pub joints: Vec<usize>,
+    /// Inverse bind matrix of each joint
+    pub inverse_bind: Vec<Mat4>,
+}
+
+impl Skeleton {
+    /// Joint matrices for the GPU from the world transforms of the spawned
+    /// node entities (`bones`, in node order)
+    pub fn joint_matrices(&self, scene: &Scene, bones: &[EntityId]) -> Vec<Mat4> {
+        self.joints
+            .iter()
+            .zip(&self.inverse_bind)
+            .map(|(&node, inverse_bind)| match bones.get(node) {
+                Some(&bone) => scene.world_matrix(bone) * *inverse_bind,
+                None => Mat4::IDENTITY,
+            })
+            .collect()
+    }
+}
+
+/// One animation of a model, as a transform clip per animated node
+#[derive(Debug, Clone)]
+pub struct SkeletalClip {
+    pub name: String,
+    pub duration: f32,
+    /// Node index and its position/rotation/scale tracks
+    pub channels: Vec<(usize, AnimationClip)>,
+}
+
+impl SkeletalClip {
+    /// Pose of every node at `time`; nodes the clip doesn't animate get an
+    /// empty pose, so they keep whatever a lower layer gave them
+    pub fn sample(&self, time: f32, node_count: usize) -> Vec<AnimationPose> {
+        let mut poses = vec![AnimationPose::default(); node_count];
+        for (node, clip) in &self.channels {
+            if let Some(pose) = poses.get_mut(*node) {
+                *pose = clip.pose_at(time);
+            }
+        }
+        poses
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SkinnedVertex {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub tex_coord: Vec2,
+    /// Skin joint indices and their weights, which sum to 1
+    pub joints: [u32; 4],
+    pub weights: [f32; 4],
+}
+
+/// A mesh primitive bound to the skin
+#[derive(Debug, Clone)]
+pub struct SkinnedPrimitive {
+    pub vertices: Vec<SkinnedVertex>,
+    pub indices: Vec<u32>,
+}
+
+/// Everything imported from a skinned glTF model
+#[derive(Debug, Clone)]
+pub struct SkinnedModel {
+    pub skeleton: Skeleton,
+    pub primitives: Vec<SkinnedPrimitive>,
+    pub clips: Vec<SkeletalClip>,
+}
+
+impl SkinnedModel {
+    pub fn clip(&self, name: &str) -> Option<&SkeletalClip> {
+        self.clips.iter().find(|clip| clip.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_scene::animation::AnimatedProperty;
+    use glam::Vec4;
+
+    #[test]
+    fn test_joint_matrices_follow_bones() {
+        let skeleton = Skeleton {
+            nodes: vec![SkeletonNode {
+                name: "Hips".to_string(),
+                parent: None,
+                rest: Transform::from_position(Vec3::new(0.0, 1.0, 0.0)),
+            }],
+            joints: vec![0],
+            inverse_bind: vec![Mat4::from_translation(Vec3::new(0.0, -1.0, 0.0))],
+        };
+        let mut scene = Scene::new("Test".to_string());
+        let hips = scene.create_entity_with_transform(
+            "Hips".to_string(),
+            Transform::from_position(Vec3::new(0.0, 1.0, 0.0)),
+        );
+
+        // In the bind pose the joint matrix leaves vertices where they are
+        let matrices = skeleton.joint_matrices(&scene, &[hips]);
+        assert!(matrices[0].abs_diff_eq(Mat4::IDENTITY, 1e-5));
+
+        scene.get_entity_mut(hips).unwrap().transform.position = Vec3::new(2.0, 1.0, 0.0);
+        let matrices = skeleton.joint_matrices(&scene, &[hips]);
+        let moved = matrices[0].transform_point3(Vec3::new(0.0, 1.5, 0.0));
+        assert!(moved.abs_diff_eq(Vec3::new(2.0, 1.5, 0.0), 1e-5));
+    }
+
+    #[test]
+    fn test_clip_samples_animated_nodes_only() {
+        let mut spine = AnimationClip::new(1.0);
+        spine.set_key(AnimatedProperty::Position, 0.0, Vec4::ZERO);
+        spine.set_key(
+            AnimatedProperty::Position,
+            1.0,
+            Vec4::new(0.0, 2.0, 0.0, 0.0),
+        );
+        let clip = SkeletalClip {
+            name: "Bob".to_string(),
+            duration: 1.0,
+            channels: vec![(1, spine)],
+        };
+
+        let poses = clip.sample(0.5, 3);
+        assert_eq!(poses.len(), 3);
+        assert!(poses[0].position.is_none());
+        assert_eq!(poses[1].position, Some(Vec3::new(0.0, 1.0, 0.0)));
+    }
+}
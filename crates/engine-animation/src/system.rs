@@ -0,0 +1,312 @@
+// Skeletal animation system - plays Animator states' skeletal clips on the
+// bones of SkinnedMesh entities
+//
+// Runs after engine_scene's animation system has advanced the animators:
+// each layer samples its current state's clip (cross-faded with the state
+// it's leaving) and the layers are blended by weight, the same way the
+// animator blends transform clips, then the result is written to the bone
+// entities. IK and ragdolls run afterwards on the posed bones.
+
+use crate::skeleton::SkinnedModel;
+use engine_scene::animation::{AnimationClip, AnimationPose};
+use engine_scene::animator::{blend_poses, Animator, AnimatorLayer, AnimatorState};
+use engine_scene::components::SkinnedMesh;
+use engine_scene::entity::EntityId;
+use engine_scene::scene::Scene;
+use engine_scene::transform::Transform;
+use glam::Vec3;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Loads skinned models and poses the bones of every SkinnedMesh
+pub struct SkeletalAnimationSystem {
+    asset_root: PathBuf,
+    /// Loaded models by path (None if the file has no skin or failed to load)
+    models: HashMap<String, Option<Arc<SkinnedModel>>>,
+}
+
+impl SkeletalAnimationSystem {
+    /// Model paths are relative to `asset_root`
+    pub fn new(asset_root: impl Into<PathBuf>) -> Self {
+        Self {
+            asset_root: asset_root.into(),
+            models: HashMap::new(),
+        }
+    }
+
+    /// The skinned model at `path`, loading it the first time; None if it
+    /// has no skin
+    pub fn model(&mut self, path: &str) -> Option<Arc<SkinnedModel>> {
+        let asset_root = &self.asset_root;
+        self.models
+            .entry(path.to_string())
+            .or_insert_with(|| match SkinnedModel::load(asset_root.join(path)) {
+                Ok(model) => Some(Arc::new(model)),
+                Err(e) => {
+                    log::debug!("{:#}", e);
+                    None
+                }
+            })
+            .clone()
+    }
+
+    /// Use `model` for SkinnedMeshes that reference `path` instead of loading it
+    pub fn insert_model(&mut self, path: impl Into<String>, model: SkinnedModel) {
+        self.models.insert(path.into(), Some(Arc::new(model)));
+    }
+
+    /// Whether `path` is known to be skinned, or None if it hasn't been
+    /// loaded yet
+    pub fn is_skinned(&self, path: &str) -> Option<bool> {
+        self.models.get(path).map(Option::is_some)
+    }
+
+    /// Remember that `path` has no skin so it isn't loaded again
+    pub fn insert_unskinned(&mut self, path: impl Into<String>) {
+        self.models.insert(path.into(), None);
+    }
+
+    /// Forget a model so it is loaded again from its file
+    pub fn reload(&mut self, path: &str) {
+        self.models.remove(path);
+    }
+
+    /// Create an entity for a skinned model at `position`: its bones as
+    /// child entities in the bind pose, and an Animator with a looping state
+    /// for each of the model's animations. Returns None if the model has no
+    /// skin.
+    pub fn spawn(&mut self, scene: &mut Scene, path: &str, position: Vec3) -> Option<EntityId> {
+        let model = self.model(path)?;
+        let name = std::path::Path::new(path)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("Character")
+            .to_string();
+        let entity_id =
+            scene.create_entity_with_transform(name, Transform::from_position(position));
+
+        let mut bones: Vec<EntityId> = Vec::with_capacity(model.skeleton.nodes.len());
+        for node in &model.skeleton.nodes {
+            let bone = scene.create_entity_with_transform(node.name.clone(), node.rest);
+            let parent = node
+                .parent
+                .and_then(|p| bones.get(p).copied())
+                .unwrap_or(entity_id);
+            scene.set_parent(bone, Some(parent));
+            bones.push(bone);
+        }
+
+        let mut layer = AnimatorLayer::new("Base");
+        for clip in &model.clips {
+            let state = AnimatorState::new(&clip.name, AnimationClip::new(clip.duration))
+                .with_skeletal_clip(&clip.name);
+            layer = layer.with_state(state);
+        }
+
+        if let Some(entity) = scene.get_entity_mut(entity_id) {
+            entity.add_component(SkinnedMesh::new(path, bones));
+            if !model.clips.is_empty() {
+                entity.add_component(Animator::new().with_layer(layer));
+            }
+        }
+        Some(entity_id)
+    }
+
+    /// Pose the bones of every SkinnedMesh from its Animator
+    pub fn update(&mut self, scene: &mut Scene) {
+        let skinned: Vec<(EntityId, String)> = scene
+            .entities()
+            .filter(|e| e.has_component::<Animator>())
+            .filter_map(|e| {
+                let mesh = e.get_component::<SkinnedMesh>()?;
+                Some((e.id, mesh.model_path.clone()))
+            })
+            .collect();
+
+        for (id, path) in skinned {
+            let Some(model) = self.model(&path) else {
+                continue;
+            };
+            let Some(entity) = scene.get_entity_mut(id) else {
+                continue;
+            };
+            let Some(animator) = entity.get_component_mut::<Animator>() else {
+                continue;
+            };
+            sync_clip_lengths(animator, &model);
+            let poses = skeletal_pose(animator, &model);
+            let bones = entity
+                .get_component::<SkinnedMesh>()
+                .map(|mesh| mesh.bones.clone())
+                .unwrap_or_default();
+
+            for (bone, pose) in bones.iter().zip(&poses) {
+                if let Some(bone) = scene.get_entity_mut(*bone) {
+                    let mut transform = bone.transform;
+                    pose.apply(&mut transform, None);
+                    bone.transform = transform;
+                }
+            }
+        }
+    }
+}
+
+/// States and blend tree motions playing a skeletal clip take its length,
+/// so they loop and fire exit-time transitions at the end of the animation
+fn sync_clip_lengths(animator: &mut Animator, model: &SkinnedModel) {
+    for state in animator
+        .layers
+        .iter_mut()
+        .flat_map(|layer| &mut layer.states)
+    {
+        if let Some(clip) = state
+            .skeletal_clip
+            .as_deref()
+            .and_then(|name| model.clip(name))
+        {
+            state.clip.duration = clip.duration;
+        }
+        for motion in state
+            .blend_tree
+            .iter_mut()
+            .flat_map(|tree| &mut tree.motions)
+        {
+            if let Some(clip) = motion
+                .skeletal_clip
+                .as_deref()
+                .and_then(|name| model.clip(name))
+            {
+                motion.clip.duration = clip.duration;
+            }
+        }
+    }
+}
+
+/// Pose of every skeleton node from the animator's layers
+fn skeletal_pose(animator: &Animator, model: &SkinnedModel) -> Vec<AnimationPose> {
+    let node_count = model.skeleton.nodes.len();
+    let sample_clip = |name: Option<&str>, time: f32| match name.and_then(|name| model.clip(name)) {
+        Some(clip) => clip.sample(time, node_count),
+        None => vec![AnimationPose::default(); node_count],
+    };
+    let blend = |a: Vec<AnimationPose>, b: Vec<AnimationPose>, t: f32| -> Vec<AnimationPose> {
+        a.iter()
+            .zip(&b)
+            .map(|(a, b)| blend_poses(a, b, t))
+            .collect()
+    };
+    let sample = |state: &AnimatorState, time: f32| {
+        let Some(tree) = &state.blend_tree else {
+            return sample_clip(state.skeletal_clip.as_deref(), time);
+        };
+        // Both motions at the same fraction of their cycles, as in BlendTree::pose_at
+        let progress = time / state.clip.duration.max(f32::EPSILON);
+        let Some((a, b, t)) = tree.blend(state.blend_value(&animator.parameters)) else {
+            return vec![AnimationPose::default(); node_count];
+        };
+        let motion = |index: usize| {
+            let motion = &tree.motions[index];
+            sample_clip(
+                motion.skeletal_clip.as_deref(),
+                progress * motion.clip.duration,
+            )
+        };
+        blend(motion(a), motion(b), t)
+    };
+
+    let mut poses = vec![AnimationPose::default(); node_count];
+    for (index, layer) in animator.layers.iter().enumerate() {
+        let Some(state) = layer.states.get(layer.playback.state) else {
+            continue;
+        };
+        let mut layer_poses = sample(state, layer.playback.time);
+        if let Some((previous, time, t)) = layer.fading_from() {
+            layer_poses = blend(sample(previous, time), layer_poses, t);
+        }
+        let weight = if index == 0 {
+            1.0
+        } else {
+            layer.weight.clamp(0.0, 1.0)
+        };
+        poses = blend(poses, layer_poses, weight);
+    }
+    poses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skeleton::{SkeletalClip, Skeleton, SkeletonNode, SkinnedPrimitive};
+    use engine_scene::animation::AnimatedProperty;
+    use glam::{Mat4, Vec4};
+
+    fn arm_model() -> SkinnedModel {
+        let mut wave = AnimationClip::new(1.0);
+        wave.set_key(
+            AnimatedProperty::Position,
+            0.0,
+            Vec4::new(0.0, 1.0, 0.0, 0.0),
+        );
+        wave.set_key(
+            AnimatedProperty::Position,
+            1.0,
+            Vec4::new(0.0, 3.0, 0.0, 0.0),
+        );
+        let node = |name: &str, parent| SkeletonNode {
+            name: name.to_string(),
+            parent,
+            rest: Transform::from_position(Vec3::Y),
+        };
+        SkinnedModel {
+            skeleton: Skeleton {
+                nodes: vec![node("Shoulder", None), node("Hand", Some(0))],
+                joints: vec![0, 1],
+                inverse_bind: vec![Mat4::IDENTITY; 2],
+            },
+            primitives: vec![SkinnedPrimitive {
+                vertices: Vec::new(),
+                indices: Vec::new(),
+            }],
+            clips: vec![SkeletalClip {
+                name: "Wave".to_string(),
+                duration: 1.0,
+                channels: vec![(1, wave)],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_spawn_and_play_skeletal_clip() {
+        let mut system = SkeletalAnimationSystem::new("assets");
+        system.insert_model("models/arm.glb", arm_model());
+        let mut scene = Scene::new("Test".to_string());
+        let id = system
+            .spawn(&mut scene, "models/arm.glb", Vec3::ZERO)
+            .unwrap();
+
+        let bones = scene
+            .get_entity(id)
+            .unwrap()
+            .get_component::<SkinnedMesh>()
+            .unwrap()
+            .bones
+            .clone();
+        assert_eq!(bones.len(), 2);
+        assert_eq!(scene.get_entity(bones[1]).unwrap().name, "Hand");
+        assert_eq!(scene.world_matrix(bones[1]).w_axis.y, 2.0);
+
+        engine_scene::animation::start_animations(&mut scene);
+        engine_scene::animation::update_animations(&mut scene, 0.5);
+        system.update(&mut scene);
+
+        // The hand follows the clip, the shoulder keeps its rest pose
+        let hand = scene.get_entity(bones[1]).unwrap().transform.position;
+        assert!((hand.y - 2.0).abs() < 1e-4);
+        assert_eq!(
+            scene.get_entity(bones[0]).unwrap().transform.position,
+            Vec3::Y
+        );
+        assert_eq!(scene.get_entity(id).unwrap().transform.position, Vec3::ZERO);
+    }
+}
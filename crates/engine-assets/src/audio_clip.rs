@@ -0,0 +1,218 @@
+// Audio clips - sound files as assets
+//
+// A clip either holds decoded PCM, so playing it again costs no decoding, or
+// keeps the encoded file and decodes while it plays, which suits long music.
+// The load policy picks one per clip; by default small files decode up front.
+// WAV, Ogg Vorbis and FLAC decode here; other formats (MP3) always stay
+// encoded for the audio system's decoder.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use std::sync::Arc;
+
+/// Files up to this size decode into memory under `AudioLoadPolicy::Auto`
+pub const STREAM_THRESHOLD_BYTES: usize = 1024 * 1024;
+
+/// How a clip is held once loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioLoadPolicy {
+    /// Memory for files up to STREAM_THRESHOLD_BYTES, stream above
+    #[default]
+    Auto,
+    /// Decode once and play from memory
+    Memory,
+    /// Keep the encoded file and decode while playing
+    Stream,
+}
+
+/// Decoded samples, interleaved by channel
+#[derive(Debug, Clone)]
+pub struct PcmData {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub samples: Arc<[f32]>,
+}
+
+impl PcmData {
+    /// Length in seconds
+    pub fn duration(&self) -> f32 {
+        let frames = self.samples.len() / self.channels.max(1) as usize;
+        frames as f32 / self.sample_rate.max(1) as f32
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum AudioClipData {
+    Decoded(PcmData),
+    Encoded(Arc<[u8]>),
+}
+
+#[derive(Debug, Clone)]
+pub struct AudioClip {
+    pub name: String,
+    pub data: AudioClipData,
+}
+
+impl AudioClip {
+    /// A clip from an audio file's contents, decoded or not as `policy` says
+    pub fn from_bytes(name: String, bytes: Vec<u8>, policy: AudioLoadPolicy) -> Result<Self> {
+        let decode = match policy {
+            AudioLoadPolicy::Auto => bytes.len() <= STREAM_THRESHOLD_BYTES,
+            AudioLoadPolicy::Memory => true,
+            AudioLoadPolicy::Stream => false,
+        };
+        let data = if decode && can_decode(&bytes) {
+            let pcm =
+                decode_pcm(&bytes).with_context(|| format!("Failed to decode audio: {}", name))?;
+            AudioClipData::Decoded(pcm)
+        } else {
+            AudioClipData::Encoded(bytes.into())
+        };
+        Ok(Self { name, data })
+    }
+
+    pub fn is_decoded(&self) -> bool {
+        matches!(self.data, AudioClipData::Decoded(_))
+    }
+
+    /// Bytes the clip holds in memory
+    pub fn memory_bytes(&self) -> usize {
+        match &self.data {
+            AudioClipData::Decoded(pcm) => pcm.samples.len() * std::mem::size_of::<f32>(),
+            AudioClipData::Encoded(bytes) => bytes.len(),
+        }
+    }
+}
+
+/// Whether `bytes` are in a format decoded here (by its magic number)
+pub fn can_decode(bytes: &[u8]) -> bool {
+    is_wav(bytes) || bytes.starts_with(b"OggS") || bytes.starts_with(b"fLaC")
+}
+
+fn is_wav(bytes: &[u8]) -> bool {
+    bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WAVE"
+}
+
+/// Decode a WAV, Ogg Vorbis or FLAC file to f32 samples
+pub fn decode_pcm(bytes: &[u8]) -> Result<PcmData> {
+    if is_wav(bytes) {
+        decode_wav(bytes)
+    } else if bytes.starts_with(b"OggS") {
+        decode_vorbis(bytes)
+    } else if bytes.starts_with(b"fLaC") {
+        decode_flac(bytes)
+    } else {
+        bail!("Unsupported audio format")
+    }
+}
+
+fn decode_wav(bytes: &[u8]) -> Result<PcmData> {
+    let mut reader = hound::WavReader::new(Cursor::new(bytes))?;
+    let spec = reader.spec();
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = int_scale(spec.bits_per_sample);
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 * scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+    Ok(PcmData {
+        sample_rate: spec.sample_rate,
+        channels: spec.channels,
+        samples: samples.into(),
+    })
+}
+
+fn decode_vorbis(bytes: &[u8]) -> Result<PcmData> {
+    let mut reader = lewton::inside_ogg::OggStreamReader::new(Cursor::new(bytes))?;
+    let mut samples = Vec::new();
+    while let Some(packet) = reader.read_dec_packet_itl()? {
+        samples.extend(packet.into_iter().map(|s: i16| s as f32 / 32768.0));
+    }
+    Ok(PcmData {
+        sample_rate: reader.ident_hdr.audio_sample_rate,
+        channels: reader.ident_hdr.audio_channels as u16,
+        samples: samples.into(),
+    })
+}
+
+fn decode_flac(bytes: &[u8]) -> Result<PcmData> {
+    let mut reader = claxon::FlacReader::new(Cursor::new(bytes))?;
+    let info = reader.streaminfo();
+    let scale = int_scale(info.bits_per_sample as u16);
+    let samples: Vec<f32> = reader
+        .samples()
+        .map(|s| s.map(|s| s as f32 * scale))
+        .collect::<Result<_, _>>()?;
+    Ok(PcmData {
+        sample_rate: info.sample_rate,
+        channels: info.channels as u16,
+        samples: samples.into(),
+    })
+}
+
+/// Factor taking signed `bits`-bit samples to -1..1
+fn int_scale(bits: u16) -> f32 {
+    1.0 / (1u64 << bits.clamp(1, 32).saturating_sub(1)) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A short stereo 16-bit WAV file
+    fn test_wav(frames: usize) -> Vec<u8> {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 22050,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut bytes = Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut bytes, spec).unwrap();
+        for i in 0..frames {
+            writer.write_sample(i16::MAX).unwrap();
+            writer.write_sample(-(i as i16)).unwrap();
+        }
+        writer.finalize().unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn test_policy_decodes_or_keeps_encoded() {
+        let wav = test_wav(2205);
+        let clip =
+            AudioClip::from_bytes("beep.wav".into(), wav.clone(), AudioLoadPolicy::Auto).unwrap();
+        let AudioClipData::Decoded(pcm) = &clip.data else {
+            panic!("small clips decode");
+        };
+        assert_eq!(
+            (pcm.channels, pcm.sample_rate, pcm.samples.len()),
+            (2, 22050, 4410)
+        );
+        assert!((pcm.duration() - 0.1).abs() < 1e-4);
+        assert!((pcm.samples[0] - 1.0).abs() < 1e-3);
+        assert_eq!(clip.memory_bytes(), 4410 * 4);
+
+        let streamed =
+            AudioClip::from_bytes("beep.wav".into(), wav.clone(), AudioLoadPolicy::Stream).unwrap();
+        assert!(!streamed.is_decoded());
+        assert_eq!(streamed.memory_bytes(), wav.len());
+
+        // Formats not decoded here stay encoded even when asked for memory
+        let mp3 = b"ID3\x04\0\0\0\0\0\0".to_vec();
+        let clip = AudioClip::from_bytes("music.mp3".into(), mp3, AudioLoadPolicy::Memory).unwrap();
+        assert!(!clip.is_decoded());
+        assert!(AudioClip::from_bytes(
+            "bad.wav".into(),
+            b"RIFF\0\0\0\0WAVE".to_vec(),
+            AudioLoadPolicy::Memory
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,228 @@
+// Triangle BVH - ray queries against mesh triangles
+//
+// Triangles are split at the median centroid along the longest axis until
+// leaves hold a handful of them. Used for ambient occlusion baking (any-hit
+// queries against the whole static scene) and viewport picking (closest-hit
+// queries against a single mesh in its local space).
+
+use crate::mesh::Mesh;
+use glam::{Mat4, Vec3};
+
+/// Triangles per BVH leaf
+const LEAF_SIZE: usize = 4;
+
+#[derive(Clone, Copy)]
+struct BvhNode {
+    min: Vec3,
+    max: Vec3,
+    /// Leaf: first triangle and count. Inner: right child and 0 (the left
+    /// child is the next node).
+    start: usize,
+    count: usize,
+}
+
+/// Triangles of one or more meshes with a BVH over them
+#[derive(Default)]
+pub struct TriangleBvh {
+    triangles: Vec<[Vec3; 3]>,
+    nodes: Vec<BvhNode>,
+}
+
+impl TriangleBvh {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// BVH over a single mesh in its own space
+    pub fn from_mesh(mesh: &Mesh) -> Self {
+        let mut bvh = Self::new();
+        bvh.add_mesh(mesh, Mat4::IDENTITY);
+        bvh.build();
+        bvh
+    }
+
+    /// Add a mesh placed with `transform`. Call `build` after adding meshes.
+    pub fn add_mesh(&mut self, mesh: &Mesh, transform: Mat4) {
+        self.triangles
+            .extend(mesh.indices.chunks_exact(3).map(|tri| {
+                [0, 1, 2]
+                    .map(|i| transform.transform_point3(mesh.vertices[tri[i] as usize].position))
+            }));
+        self.nodes.clear();
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+
+    /// Corners of the box around all triangles (None before `build` or if empty)
+    pub fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        self.nodes.first().map(|root| (root.min, root.max))
+    }
+
+    /// Build the BVH over the added triangles
+    pub fn build(&mut self) {
+        self.nodes.clear();
+        if !self.triangles.is_empty() {
+            self.build_node(0, self.triangles.len());
+        }
+    }
+
+    fn build_node(&mut self, start: usize, count: usize) -> usize {
+        let tris = &self.triangles[start..start + count];
+        let (min, max) = tris.iter().flatten().fold(
+            (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+            |(min, max), &p| (min.min(p), max.max(p)),
+        );
+        let index = self.nodes.len();
+        self.nodes.push(BvhNode {
+            min,
+            max,
+            start,
+            count,
+        });
+        if count <= LEAF_SIZE {
+            return index;
+        }
+
+        // Split at the median centroid along the longest axis
+        let extent = max - min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+        let centroid = |tri: &[Vec3; 3]| (tri[0] + tri[1] + tri[2])[axis];
+        self.triangles[start..start + count]
+            .sort_unstable_by(|a, b| centroid(a).total_cmp(&centroid(b)));
+        let half = count / 2;
+        self.build_node(start, half);
+        let right = self.build_node(start + half, count - half);
+        self.nodes[index].start = right;
+        self.nodes[index].count = 0;
+        index
+    }
+
+    /// True if the ray hits any triangle closer than `max_distance`
+    pub fn occluded(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> bool {
+        self.traverse(origin, direction, max_distance, true)
+            .is_some()
+    }
+
+    /// Distance to the closest triangle the ray hits within `max_distance`.
+    /// Distances are in units of `direction`'s length.
+    pub fn closest_hit(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<f32> {
+        self.traverse(origin, direction, max_distance, false)
+    }
+
+    fn traverse(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+        any_hit: bool,
+    ) -> Option<f32> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let inv_direction = direction.recip();
+        let mut closest = max_distance;
+        let mut hit = None;
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = self.nodes[index];
+            if !ray_hits_box(origin, inv_direction, node.min, node.max, closest) {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(index + 1);
+                stack.push(node.start);
+                continue;
+            }
+            for tri in &self.triangles[node.start..node.start + node.count] {
+                if let Some(t) = ray_triangle(origin, direction, tri).filter(|&t| t < closest) {
+                    if any_hit {
+                        return Some(t);
+                    }
+                    closest = t;
+                    hit = Some(t);
+                }
+            }
+        }
+        hit
+    }
+}
+
+fn ray_hits_box(
+    origin: Vec3,
+    inv_direction: Vec3,
+    min: Vec3,
+    max: Vec3,
+    max_distance: f32,
+) -> bool {
+    let t1 = (min - origin) * inv_direction;
+    let t2 = (max - origin) * inv_direction;
+    let near = t1.min(t2).max_element();
+    let far = t1.max(t2).min_element();
+    far >= near.max(0.0) && near <= max_distance
+}
+
+/// Möller-Trumbore intersection, returns the hit distance
+fn ray_triangle(origin: Vec3, direction: Vec3, tri: &[Vec3; 3]) -> Option<f32> {
+    let edge1 = tri[1] - tri[0];
+    let edge2 = tri[2] - tri[0];
+    let p = direction.cross(edge2);
+    let det = edge1.dot(p);
+    if det.abs() < 1e-8 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let s = origin - tri[0];
+    let u = s.dot(p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(edge1);
+    let v = direction.dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = edge2.dot(q) * inv_det;
+    (t > 0.0).then_some(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bvh_finds_hits_across_many_triangles() {
+        let cube = Mesh::cube();
+        let mut bvh = TriangleBvh::new();
+        for i in 0..20 {
+            bvh.add_mesh(
+                &cube,
+                Mat4::from_translation(Vec3::new(i as f32 * 3.0, 0.0, 0.0)),
+            );
+        }
+        bvh.build();
+        assert!(bvh.occluded(Vec3::new(30.0, 5.0, 0.0), Vec3::NEG_Y, 10.0));
+        assert!(!bvh.occluded(Vec3::new(31.5, 5.0, 0.0), Vec3::NEG_Y, 10.0));
+        assert!(!bvh.occluded(Vec3::new(30.0, 5.0, 0.0), Vec3::NEG_Y, 4.0));
+    }
+
+    #[test]
+    fn test_closest_hit_returns_nearest_face() {
+        let bvh = TriangleBvh::from_mesh(&Mesh::cube());
+        let hit = bvh
+            .closest_hit(Vec3::new(0.1, 5.0, 0.1), Vec3::NEG_Y, f32::INFINITY)
+            .unwrap();
+        assert!((hit - 4.5).abs() < 1e-4, "{}", hit);
+        assert_eq!(
+            bvh.closest_hit(Vec3::new(2.0, 5.0, 0.0), Vec3::NEG_Y, f32::INFINITY),
+            None
+        );
+    }
+}
@@ -0,0 +1,364 @@
+// Terrain erosion - hydraulic droplets and thermal slumping on a height map
+//
+// Hydraulic erosion follows the particle model: each droplet starts on a
+// random cell and runs downhill along the interpolated gradient, picking up
+// sediment while it has spare capacity and dropping it where it slows down
+// or climbs, which carves valleys and fills basins. Thermal erosion then
+// moves material off cells steeper than the talus slope onto their lower
+// neighbours, so cliffs left behind slump into scree.
+
+use crate::terrain::HeightMap;
+use engine_core::determinism::SimRng;
+use glam::Vec2;
+
+/// Parameters of an erosion pass. Heights are in world units, droplets move
+/// in grid cells.
+#[derive(Debug, Clone)]
+pub struct ErosionSettings {
+    pub seed: u32,
+    /// Droplets simulated over the whole map by `HeightMap::erode`
+    pub droplets: usize,
+    /// Steps before a droplet evaporates
+    pub max_lifetime: usize,
+    /// How much a droplet keeps its direction instead of turning downhill (0-1)
+    pub inertia: f32,
+    /// Sediment carried per unit of slope, speed and water
+    pub sediment_capacity: f32,
+    /// Slope used for the capacity on flat ground, so droplets still carry some
+    pub min_slope: f32,
+    /// Fraction of the spare capacity eroded per step
+    pub erode_speed: f32,
+    /// Fraction of the excess sediment deposited per step
+    pub deposit_speed: f32,
+    /// Fraction of the water lost per step
+    pub evaporate_speed: f32,
+    pub gravity: f32,
+    /// Cells around a droplet it erodes from, so it cuts channels not pits
+    pub erosion_radius: usize,
+    /// Thermal passes after the droplets
+    pub thermal_iterations: usize,
+    /// Steepest stable slope (rise over run) before material slides
+    pub talus: f32,
+    /// Fraction of the excess height moved per thermal pass (0-1)
+    pub thermal_rate: f32,
+}
+
+impl Default for ErosionSettings {
+    fn default() -> Self {
+        Self {
+            seed: 1,
+            droplets: 50_000,
+            max_lifetime: 30,
+            inertia: 0.05,
+            sediment_capacity: 4.0,
+            min_slope: 0.01,
+            erode_speed: 0.3,
+            deposit_speed: 0.3,
+            evaporate_speed: 0.01,
+            gravity: 4.0,
+            erosion_radius: 3,
+            thermal_iterations: 20,
+            talus: 0.8,
+            thermal_rate: 0.5,
+        }
+    }
+}
+
+/// Inclusive grid rectangle (min_x, min_z, max_x, max_z)
+type Region = (usize, usize, usize, usize);
+
+impl HeightMap {
+    /// Erode the whole map: `settings.droplets` droplets, then the thermal
+    /// passes. `scale` is the map's world size, as for `apply_brush`.
+    pub fn erode(&mut self, settings: &ErosionSettings, scale: f32) {
+        if self.width < 2 || self.depth < 2 {
+            return;
+        }
+        let region = (0, 0, self.width - 1, self.depth - 1);
+        let mut rng = SimRng::new(settings.seed as u64);
+        self.erode_hydraulic(settings, &mut rng, settings.droplets, region);
+        self.erode_thermal(settings, scale, settings.thermal_iterations, region);
+    }
+
+    /// Erosion brush: droplets and slumping inside the brush, faded out to
+    /// its edge. More strength runs more droplets. Returns true if any
+    /// heights were modified.
+    pub fn erode_brush(
+        &mut self,
+        world_x: f32,
+        world_z: f32,
+        scale: f32,
+        radius: f32,
+        strength: f32,
+        settings: &ErosionSettings,
+    ) -> bool {
+        let Some(region) = self.brush_bounds(world_x, world_z, scale, radius) else {
+            return false;
+        };
+        let (min_x, min_z, max_x, max_z) = region;
+        if min_x == max_x || min_z == max_z {
+            return false;
+        }
+        let (center_x, center_z) = self.world_to_grid(world_x, world_z, scale);
+        let grid_radius = (radius / scale) * self.width as f32;
+
+        let region_width = max_x - min_x + 1;
+        let before: Vec<f32> = (min_z..=max_z)
+            .flat_map(|z| (min_x..=max_x).map(move |x| (x, z)))
+            .map(|(x, z)| self.get_height(x, z))
+            .collect();
+
+        // A different droplet pattern for every dab
+        let seed = settings.seed
+            ^ (center_x as u32).wrapping_mul(73856093)
+            ^ (center_z as u32).wrapping_mul(19349663);
+        let mut rng = SimRng::new(seed as u64);
+        let cells = region_width * (max_z - min_z + 1);
+        let droplets = (cells as f32 * strength * 0.5).ceil() as usize;
+        let thermal_iterations = (strength * 2.0).ceil() as usize;
+        self.erode_hydraulic(settings, &mut rng, droplets, region);
+        self.erode_thermal(settings, scale, thermal_iterations, region);
+
+        // Fade the change out toward the brush edge
+        for z in min_z..=max_z {
+            for x in min_x..=max_x {
+                let dist = Vec2::new(x as f32 - center_x, z as f32 - center_z).length();
+                let falloff = (1.0 - dist / grid_radius).max(0.0);
+                let old = before[(z - min_z) * region_width + (x - min_x)];
+                let eroded = self.get_height(x, z);
+                self.set_height(x, z, old + (eroded - old) * falloff * falloff);
+            }
+        }
+        true
+    }
+
+    /// Height and downhill-facing gradient at a point between grid cells
+    fn height_and_gradient(&self, pos: Vec2) -> (f32, Vec2) {
+        let (x, z) = (pos.x as usize, pos.y as usize);
+        let (fx, fz) = (pos.x - x as f32, pos.y - z as f32);
+        let h00 = self.get_height(x, z);
+        let h10 = self.get_height(x + 1, z);
+        let h01 = self.get_height(x, z + 1);
+        let h11 = self.get_height(x + 1, z + 1);
+        let gradient = Vec2::new(
+            (h10 - h00) * (1.0 - fz) + (h11 - h01) * fz,
+            (h01 - h00) * (1.0 - fx) + (h11 - h10) * fx,
+        );
+        let height =
+            (h00 * (1.0 - fx) + h10 * fx) * (1.0 - fz) + (h01 * (1.0 - fx) + h11 * fx) * fz;
+        (height, gradient)
+    }
+
+    fn add_height(&mut self, x: usize, z: usize, amount: f32) {
+        let index = z * self.width + x;
+        self.heights[index] += amount;
+    }
+
+    fn erode_hydraulic(
+        &mut self,
+        settings: &ErosionSettings,
+        rng: &mut SimRng,
+        droplets: usize,
+        region: Region,
+    ) {
+        let (min_x, min_z, max_x, max_z) = region;
+        if min_x >= max_x || min_z >= max_z {
+            return;
+        }
+        // Droplets stay where all four corners of their cell are in the region
+        let (low, high) = (
+            Vec2::new(min_x as f32, min_z as f32),
+            Vec2::new(max_x as f32, max_z as f32),
+        );
+        let radius = settings.erosion_radius.max(1) as i32;
+        let brush: Vec<(i32, i32, f32)> = (-radius..=radius)
+            .flat_map(|dz| (-radius..=radius).map(move |dx| (dx, dz)))
+            .filter_map(|(dx, dz)| {
+                let weight = radius as f32 - ((dx * dx + dz * dz) as f32).sqrt();
+                (weight > 0.0).then_some((dx, dz, weight))
+            })
+            .collect();
+        let inertia = settings.inertia.clamp(0.0, 1.0);
+
+        for _ in 0..droplets {
+            let mut pos = Vec2::new(rng.range(low.x, high.x), rng.range(low.y, high.y));
+            let mut direction = Vec2::ZERO;
+            let mut speed = 1.0_f32;
+            let mut water = 1.0_f32;
+            let mut sediment = 0.0_f32;
+
+            for _ in 0..settings.max_lifetime {
+                let (cell_x, cell_z) = (pos.x as usize, pos.y as usize);
+                let (fx, fz) = (pos.x - cell_x as f32, pos.y - cell_z as f32);
+                let (height, gradient) = self.height_and_gradient(pos);
+
+                direction = direction * inertia - gradient * (1.0 - inertia);
+                if direction.length_squared() < 1e-12 {
+                    break;
+                }
+                direction = direction.normalize();
+                let next = pos + direction;
+                if next.x < low.x || next.y < low.y || next.x >= high.x || next.y >= high.y {
+                    break;
+                }
+
+                let delta = self.height_and_gradient(next).0 - height;
+                let capacity =
+                    (-delta).max(settings.min_slope) * speed * water * settings.sediment_capacity;
+
+                if sediment > capacity || delta > 0.0 {
+                    // Uphill: fill the pit behind, else drop part of the excess
+                    let deposit = if delta > 0.0 {
+                        delta.min(sediment)
+                    } else {
+                        (sediment - capacity) * settings.deposit_speed
+                    };
+                    sediment -= deposit;
+                    self.add_height(cell_x, cell_z, deposit * (1.0 - fx) * (1.0 - fz));
+                    self.add_height(cell_x + 1, cell_z, deposit * fx * (1.0 - fz));
+                    self.add_height(cell_x, cell_z + 1, deposit * (1.0 - fx) * fz);
+                    self.add_height(cell_x + 1, cell_z + 1, deposit * fx * fz);
+                } else {
+                    // Never dig deeper than the drop to the next point
+                    let amount = ((capacity - sediment) * settings.erode_speed).min(-delta);
+                    let cells: Vec<(usize, usize, f32)> = brush
+                        .iter()
+                        .filter_map(|&(dx, dz, weight)| {
+                            let x = cell_x as i32 + dx;
+                            let z = cell_z as i32 + dz;
+                            let inside = x >= min_x as i32
+                                && x <= max_x as i32
+                                && z >= min_z as i32
+                                && z <= max_z as i32;
+                            inside.then_some((x as usize, z as usize, weight))
+                        })
+                        .collect();
+                    let total: f32 = cells.iter().map(|cell| cell.2).sum();
+                    for (x, z, weight) in cells {
+                        let removed = amount * weight / total;
+                        self.add_height(x, z, -removed);
+                        sediment += removed;
+                    }
+                }
+
+                speed = (speed * speed - delta * settings.gravity).max(0.0).sqrt();
+                water *= 1.0 - settings.evaporate_speed;
+                pos = next;
+            }
+        }
+    }
+
+    fn erode_thermal(
+        &mut self,
+        settings: &ErosionSettings,
+        scale: f32,
+        iterations: usize,
+        region: Region,
+    ) {
+        let (min_x, min_z, max_x, max_z) = region;
+        let max_step = settings.talus * scale / self.width as f32;
+        let rate = settings.thermal_rate.clamp(0.0, 1.0);
+        let mut changes = vec![0.0; self.heights.len()];
+
+        for _ in 0..iterations {
+            changes.iter_mut().for_each(|change| *change = 0.0);
+            for z in min_z..=max_z {
+                for x in min_x..=max_x {
+                    let height = self.get_height(x, z);
+                    let neighbours = [
+                        (x.wrapping_sub(1), z),
+                        (x + 1, z),
+                        (x, z.wrapping_sub(1)),
+                        (x, z + 1),
+                    ];
+                    for (nx, nz) in neighbours {
+                        if nx < min_x || nx > max_x || nz < min_z || nz > max_z {
+                            continue;
+                        }
+                        let excess = height - self.get_height(nx, nz) - max_step;
+                        if excess > 0.0 {
+                            // A quarter each way keeps a cell from overshooting
+                            let moved = excess * rate * 0.25;
+                            changes[z * self.width + x] -= moved;
+                            changes[nz * self.width + nx] += moved;
+                        }
+                    }
+                }
+            }
+            for (height, change) in self.heights.iter_mut().zip(&changes) {
+                *height += change;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::TerrainConfig;
+
+    #[test]
+    fn test_erosion_moves_material_downhill_without_creating_any() {
+        let config = TerrainConfig {
+            width: 48,
+            depth: 48,
+            scale: 48.0,
+            height_scale: 8.0,
+            ..Default::default()
+        };
+        let mut height_map = HeightMap::generate(&config);
+        let original = height_map.clone();
+        let settings = ErosionSettings {
+            droplets: 5_000,
+            ..Default::default()
+        };
+        height_map.erode(&settings, config.scale);
+
+        let total = |map: &HeightMap| map.heights.iter().sum::<f32>();
+        let changed = height_map
+            .heights
+            .iter()
+            .zip(&original.heights)
+            .filter(|(a, b)| (*a - *b).abs() > 1e-4)
+            .count();
+        assert!(
+            changed > height_map.heights.len() / 4,
+            "only {} cells changed",
+            changed
+        );
+        // Droplets that run off keep their sediment, so mass can only drop
+        assert!(total(&height_map) <= total(&original) + 1e-2);
+        assert!(height_map.heights.iter().all(|h| h.is_finite()));
+    }
+
+    #[test]
+    fn test_thermal_slumps_a_spike_and_the_brush_stays_in_its_radius() {
+        let mut height_map = HeightMap {
+            width: 17,
+            depth: 17,
+            heights: vec![0.0; 17 * 17],
+            holes: Vec::new(),
+        };
+        height_map.set_height(8, 8, 10.0);
+        let settings = ErosionSettings {
+            droplets: 0,
+            thermal_iterations: 400,
+            ..Default::default()
+        };
+        height_map.erode(&settings, 17.0);
+
+        let peak = height_map.get_height(8, 8);
+        assert!(peak < 5.0, "peak still at {}", peak);
+        let total: f32 = height_map.heights.iter().sum();
+        assert!(
+            (total - 10.0).abs() < 1e-3,
+            "thermal erosion conserves material"
+        );
+
+        // A brush far from the spike leaves it alone
+        let before = height_map.clone();
+        height_map.erode_brush(-6.0, -6.0, 17.0, 2.0, 1.0, &ErosionSettings::default());
+        assert_eq!(height_map.get_height(8, 8), before.get_height(8, 8));
+        assert_eq!(height_map.get_height(16, 0), before.get_height(16, 0));
+    }
+}
@@ -0,0 +1,192 @@
+// Heightmap import and export for external terrain tools
+//
+// 16-bit PNG and RAW store heights normalized over a height range, the way
+// World Machine and Gaea write them; EXR stores heights in world units as-is.
+// RAW files are square, little-endian u16 with no header. Image row z holds
+// heightmap row z, so north is up in the image. Holes are not stored.
+
+use crate::terrain::HeightMap;
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeightMapFormat {
+    Png16,
+    Raw16,
+    Exr,
+}
+
+impl HeightMapFormat {
+    /// Pick the format from a file extension (png, raw/r16, exr)
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Some(Self::Png16),
+            "raw" | "r16" => Some(Self::Raw16),
+            "exr" => Some(Self::Exr),
+            _ => None,
+        }
+    }
+
+    /// Whether heights are normalized over a height range in this format
+    pub fn is_normalized(self) -> bool {
+        self != Self::Exr
+    }
+}
+
+impl HeightMap {
+    /// Lowest and highest height on the map
+    pub fn height_range(&self) -> (f32, f32) {
+        self.heights
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(min, max), &height| {
+                (min.min(height), max.max(height))
+            })
+    }
+
+    /// Load a heightmap, mapping normalized formats onto min_height..max_height
+    pub fn import(path: &Path, min_height: f32, max_height: f32) -> Result<Self> {
+        let format = HeightMapFormat::from_path(path)
+            .with_context(|| format!("Unknown heightmap format: {}", path.display()))?;
+        let to_height =
+            |value: u16| min_height + value as f32 / 65535.0 * (max_height - min_height);
+
+        let (width, depth, heights) = match format {
+            HeightMapFormat::Png16 => {
+                let image = image::open(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?
+                    .into_luma16();
+                let heights = image.pixels().map(|pixel| to_height(pixel.0[0])).collect();
+                (image.width() as usize, image.height() as usize, heights)
+            }
+            HeightMapFormat::Raw16 => {
+                let bytes = std::fs::read(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                let samples = bytes.len() / 2;
+                let size = (samples as f64).sqrt().round() as usize;
+                if bytes.len() % 2 != 0 || size * size != samples {
+                    bail!(
+                        "{} is not a square 16-bit RAW heightmap ({} bytes)",
+                        path.display(),
+                        bytes.len()
+                    );
+                }
+                let heights = bytes
+                    .chunks_exact(2)
+                    .map(|pair| to_height(u16::from_le_bytes([pair[0], pair[1]])))
+                    .collect();
+                (size, size, heights)
+            }
+            HeightMapFormat::Exr => {
+                let image = image::open(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?
+                    .into_rgb32f();
+                let heights = image.pixels().map(|pixel| pixel.0[0]).collect();
+                (image.width() as usize, image.height() as usize, heights)
+            }
+        };
+        if width < 2 || depth < 2 {
+            bail!(
+                "{} is too small for a heightmap ({}x{})",
+                path.display(),
+                width,
+                depth
+            );
+        }
+
+        Ok(Self {
+            width,
+            depth,
+            heights,
+            holes: Vec::new(),
+        })
+    }
+
+    /// Save the heightmap; normalized formats clamp to min_height..max_height
+    pub fn export(&self, path: &Path, min_height: f32, max_height: f32) -> Result<()> {
+        let format = HeightMapFormat::from_path(path)
+            .with_context(|| format!("Unknown heightmap format: {}", path.display()))?;
+        let span = (max_height - min_height).max(f32::EPSILON);
+        let to_u16 =
+            |height: f32| (((height - min_height) / span).clamp(0.0, 1.0) * 65535.0).round() as u16;
+        let (width, depth) = (self.width as u32, self.depth as u32);
+
+        match format {
+            HeightMapFormat::Png16 => {
+                let pixels = self.heights.iter().map(|&height| to_u16(height)).collect();
+                image::ImageBuffer::<image::Luma<u16>, Vec<u16>>::from_raw(width, depth, pixels)
+                    .context("Heightmap size does not match its heights")?
+                    .save(path)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+            }
+            HeightMapFormat::Raw16 => {
+                if self.width != self.depth {
+                    bail!(
+                        "RAW heightmaps must be square ({}x{})",
+                        self.width,
+                        self.depth
+                    );
+                }
+                let bytes: Vec<u8> = self
+                    .heights
+                    .iter()
+                    .flat_map(|&height| to_u16(height).to_le_bytes())
+                    .collect();
+                std::fs::write(path, bytes)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+            }
+            HeightMapFormat::Exr => {
+                let pixels = self
+                    .heights
+                    .iter()
+                    .flat_map(|&height| [height; 3])
+                    .collect();
+                image::Rgb32FImage::from_raw(width, depth, pixels)
+                    .context("Heightmap size does not match its heights")?
+                    .save(path)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(width: usize, depth: usize) -> HeightMap {
+        HeightMap {
+            width,
+            depth,
+            heights: (0..width * depth).map(|i| i as f32 * 0.25 - 3.0).collect(),
+            holes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_formats_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, size) in [("h.png", (12, 9)), ("h.r16", (10, 10)), ("h.exr", (12, 9))] {
+            let original = ramp(size.0, size.1);
+            let (min, max) = original.height_range();
+            let path = dir.path().join(name);
+            original.export(&path, min, max).unwrap();
+
+            let loaded = HeightMap::import(&path, min, max).unwrap();
+            assert_eq!((loaded.width, loaded.depth), size, "{}", name);
+            for (a, b) in original.heights.iter().zip(&loaded.heights) {
+                assert!((a - b).abs() < 0.01, "{}: {} != {}", name, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_raw_must_be_square() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("h.raw");
+        assert!(ramp(12, 9).export(&path, 0.0, 1.0).is_err());
+        std::fs::write(&path, [0u8; 6]).unwrap();
+        assert!(HeightMap::import(&path, 0.0, 1.0).is_err());
+    }
+}
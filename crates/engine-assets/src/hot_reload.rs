@@ -19,6 +19,21 @@ pub enum ReloadEvent {
     AssetChanged(PathBuf),
     TextureChanged(PathBuf),
     ModelChanged(PathBuf),
+    /// A native game module (.so, .dll, .dylib) was rebuilt
+    LibraryChanged(PathBuf),
+}
+
+impl ReloadEvent {
+    /// The file that changed
+    pub fn path(&self) -> &Path {
+        match self {
+            ReloadEvent::ScriptChanged(path)
+            | ReloadEvent::AssetChanged(path)
+            | ReloadEvent::TextureChanged(path)
+            | ReloadEvent::ModelChanged(path)
+            | ReloadEvent::LibraryChanged(path) => path,
+        }
+    }
 }
 
 impl HotReloadWatcher {
@@ -98,6 +113,10 @@ impl HotReloadWatcher {
                                 log::info!("Texture changed: {:?}", path);
                                 Some(ReloadEvent::TextureChanged(path))
                             }
+                            "so" | "dll" | "dylib" => {
+                                log::info!("Library changed: {:?}", path);
+                                Some(ReloadEvent::LibraryChanged(path))
+                            }
                             _ => {
                                 log::debug!("Asset changed: {:?}", path);
                                 Some(ReloadEvent::AssetChanged(path))
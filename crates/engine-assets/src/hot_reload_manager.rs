@@ -178,7 +178,7 @@ impl HotReloadManager {
                         result.scripts_changed.push(relative_path.to_string());
                     }
                 }
-                ReloadEvent::AssetChanged(path) => {
+                ReloadEvent::AssetChanged(path) | ReloadEvent::LibraryChanged(path) => {
                     log::debug!("Generic asset changed: {:?}", path);
                 }
             }
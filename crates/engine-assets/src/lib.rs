@@ -1,22 +1,51 @@
 // Engine Assets - Asset loading and management
 
+pub mod audio_clip;
+pub mod bvh;
+pub mod erosion;
+pub mod heightmap_io;
 pub mod hot_reload;
 pub mod hot_reload_manager;
+pub mod lightmap;
 pub mod loaders;
+pub mod loading;
 pub mod manager;
 pub mod material;
 pub mod mesh;
+pub mod navmesh;
+pub mod simplify;
+pub mod splines;
 pub mod terrain;
+pub mod terrain_chunks;
+pub mod terrain_streaming;
 pub mod texture;
+pub mod texture_compression;
 pub mod vegetation;
 pub mod water_fill;
 
+pub use audio_clip::{AudioClip, AudioClipData, AudioLoadPolicy, PcmData};
+pub use bvh::TriangleBvh;
+pub use erosion::ErosionSettings;
+pub use heightmap_io::HeightMapFormat;
 pub use hot_reload::{HotReloadWatcher, ReloadEvent};
 pub use hot_reload_manager::{AssetRegistry, AssetRegistryStats, HotReloadManager, HotReloadResult};
+pub use lightmap::{bake_vertex_ao, AoBakeSettings, BakedAo};
+pub use loading::LoadState;
 pub use manager::{AssetHandle, AssetManager};
 pub use material::{AlphaMode, Material};
 pub use mesh::{Mesh, Vertex};
-pub use terrain::{HeightMap, Terrain, TerrainConfig};
+pub use navmesh::{NavAgentSettings, NavMesh, NavMeshInput, NavObstacle, NavPolygon};
+pub use simplify::{generate_lods, simplify_mesh, LodSettings};
+pub use splines::{flow_downhill, river_mesh, road_mesh};
+pub use terrain::{HeightMap, SplatMap, Terrain, TerrainConfig, TerrainLayer, MAX_TERRAIN_LAYERS};
+pub use terrain_chunks::{TerrainChunk, TerrainChunks, TERRAIN_CHUNK_CELLS, TERRAIN_LOD_LEVELS};
+pub use terrain_streaming::{TerrainStreamConfig, TerrainStreamer, TileCoord, TileEvent};
 pub use texture::{Texture, TextureFormat};
+pub use texture_compression::{
+    compress_texture, CompressedFormat, CompressedTexture, CompressedTextureCache, MipLevel, TextureKind,
+};
 pub use vegetation::{VegetationType, TreeConfig, BushConfig, generate_tree, generate_bush};
-pub use water_fill::{compute_water_fill, generate_water_mesh, ComputedWaterBody, WaterFillResult};
+pub use water_fill::{
+    apply_flow_overrides, compute_water_fill, compute_water_fill_masked, exclusion_mask, generate_water_mesh,
+    ComputedWaterBody, FlowSample, WaterFillResult,
+};
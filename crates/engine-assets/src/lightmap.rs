@@ -0,0 +1,232 @@
+// Ambient occlusion baking for static geometry
+//
+// Occlusion is path traced on the CPU: every vertex casts cosine-weighted rays
+// over its hemisphere against all static triangles (through a BVH) and keeps
+// the fraction that escape. The result is stored as a small asset per mesh
+// instance and multiplied into the vertex colors when the mesh is uploaded.
+
+use crate::bvh::TriangleBvh;
+use crate::mesh::Mesh;
+use anyhow::{Context, Result};
+use glam::{Mat3, Mat4, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Period of the per-vertex rotation of the ray pattern
+const PATTERN_ROTATIONS: u32 = 97;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AoBakeSettings {
+    /// Rays per vertex
+    pub samples: u32,
+    /// Occluders further away than this don't darken
+    pub max_distance: f32,
+    /// Ray start offset along the normal, avoids self-hits
+    pub bias: f32,
+}
+
+impl Default for AoBakeSettings {
+    fn default() -> Self {
+        Self {
+            samples: 64,
+            max_distance: 4.0,
+            bias: 0.01,
+        }
+    }
+}
+
+/// Point `i` of a Hammersley set of `n`, shifted by `offset` so neighboring
+/// vertices don't share the same ray pattern
+fn hammersley(i: u32, n: u32, offset: Vec2) -> Vec2 {
+    let radical_inverse = i.reverse_bits() as f32 / 4_294_967_296.0;
+    let point = Vec2::new((i as f32 + 0.5) / n as f32, radical_inverse) + offset;
+    point - point.floor()
+}
+
+/// Ambient occlusion (1 = fully open) for each vertex of `mesh` placed with `transform`
+pub fn bake_vertex_ao(
+    mesh: &Mesh,
+    transform: Mat4,
+    geometry: &TriangleBvh,
+    settings: &AoBakeSettings,
+) -> Vec<f32> {
+    let samples = settings.samples.max(1);
+    let normal_matrix = Mat3::from_mat4(transform).inverse().transpose();
+
+    mesh.vertices
+        .iter()
+        .enumerate()
+        .map(|(index, vertex)| {
+            let normal = (normal_matrix * vertex.normal).normalize_or_zero();
+            if normal == Vec3::ZERO {
+                return 1.0;
+            }
+            let origin = transform.transform_point3(vertex.position) + normal * settings.bias;
+            let (tangent, bitangent) = normal.any_orthonormal_pair();
+            let offset = hammersley(index as u32, PATTERN_ROTATIONS, Vec2::ZERO);
+
+            let open = (0..samples)
+                .filter(|&i| {
+                    // Cosine-weighted direction over the hemisphere
+                    let point = hammersley(i, samples, offset);
+                    let radius = point.x.sqrt();
+                    let angle = point.y * std::f32::consts::TAU;
+                    let direction = tangent * radius * angle.cos()
+                        + bitangent * radius * angle.sin()
+                        + normal * (1.0 - point.x).sqrt();
+                    !geometry.occluded(origin, direction, settings.max_distance)
+                })
+                .count();
+            open as f32 / samples as f32
+        })
+        .collect()
+}
+
+/// Baked ambient occlusion for one mesh instance, referenced by its MeshRenderer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BakedAo {
+    /// Mesh the values were baked for
+    pub mesh_path: String,
+    pub settings: AoBakeSettings,
+    pub vertex_ao: Vec<f32>,
+}
+
+impl BakedAo {
+    /// Darken the mesh's vertex colors by the baked occlusion. Returns false
+    /// (leaving the mesh untouched) if it no longer matches the bake.
+    pub fn apply(&self, mesh: &mut Mesh) -> bool {
+        if mesh.vertices.len() != self.vertex_ao.len() {
+            return false;
+        }
+        for (vertex, &ao) in mesh.vertices.iter_mut().zip(&self.vertex_ao) {
+            vertex.color = Some(vertex.color.unwrap_or(Vec3::ONE) * ao);
+        }
+        true
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string(self)?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write baked AO {}", path.display()))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read(path)
+            .with_context(|| format!("Failed to read baked AO {}", path.display()))?;
+        Self::from_bytes(&json)
+    }
+
+    /// Parse a saved baked AO's JSON (e.g. read from an asset pack)
+    pub fn from_bytes(json: &[u8]) -> Result<Self> {
+        Ok(serde_json::from_slice(json)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn geometry(meshes: &[(&Mesh, Mat4)]) -> TriangleBvh {
+        let mut geometry = TriangleBvh::new();
+        for (mesh, transform) in meshes {
+            geometry.add_mesh(mesh, *transform);
+        }
+        geometry.build();
+        geometry
+    }
+
+    #[test]
+    fn test_open_floor_is_unoccluded() {
+        let floor = Mesh::plane(10.0);
+        let geometry = geometry(&[(&floor, Mat4::IDENTITY)]);
+        let ao = bake_vertex_ao(
+            &floor,
+            Mat4::IDENTITY,
+            &geometry,
+            &AoBakeSettings::default(),
+        );
+        assert_eq!(ao.len(), 4);
+        assert!(ao.iter().all(|&value| value == 1.0));
+    }
+
+    #[test]
+    fn test_low_ceiling_darkens_floor() {
+        let floor = Mesh::plane(2.0);
+        let ceiling = Mesh::plane(40.0);
+        let ceiling_transform = Mat4::from_translation(Vec3::new(0.0, 0.5, 0.0));
+        let geometry = geometry(&[(&floor, Mat4::IDENTITY), (&ceiling, ceiling_transform)]);
+        assert_eq!(geometry.triangle_count(), 4);
+
+        let ao = bake_vertex_ao(
+            &floor,
+            Mat4::IDENTITY,
+            &geometry,
+            &AoBakeSettings::default(),
+        );
+        assert!(ao.iter().all(|&value| value < 0.05), "{:?}", ao);
+
+        // Out of reach, the ceiling no longer counts
+        let settings = AoBakeSettings {
+            max_distance: 0.25,
+            ..Default::default()
+        };
+        let ao = bake_vertex_ao(&floor, Mat4::IDENTITY, &geometry, &settings);
+        assert!(ao.iter().all(|&value| value > 0.5), "{:?}", ao);
+    }
+
+    #[test]
+    fn test_apply_and_save_baked_ao() {
+        let mut mesh = Mesh::plane(1.0);
+        let baked = BakedAo {
+            mesh_path: "plane".to_string(),
+            settings: AoBakeSettings::default(),
+            vertex_ao: vec![1.0, 0.5, 0.5, 0.25],
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lightmaps").join("plane.ao.json");
+        baked.save(&path).unwrap();
+        let loaded = BakedAo::load(&path).unwrap();
+        assert!(loaded.apply(&mut mesh));
+        assert_eq!(mesh.vertices[3].color, Some(Vec3::splat(0.25)));
+
+        // A mesh with a different vertex count is left alone
+        let mut cube = Mesh::cube();
+        assert!(!loaded.apply(&mut cube));
+    }
+
+    #[test]
+    fn test_lightmap_uvs_pack_triangles_apart() {
+        let mut mesh = Mesh::cube();
+        assert!(!mesh.has_lightmap_uvs());
+        mesh.generate_lightmap_uvs(0.1);
+        assert!(mesh.has_lightmap_uvs());
+        assert_eq!(mesh.vertices.len(), 36);
+
+        let uvs: Vec<Vec2> = mesh
+            .vertices
+            .iter()
+            .map(|v| v.lightmap_uv.unwrap())
+            .collect();
+        assert!(uvs
+            .iter()
+            .all(|uv| uv.cmpge(Vec2::ZERO).all() && uv.cmple(Vec2::ONE).all()));
+        // Each of the 12 triangles sits in its own cell of a 4x4 grid
+        let cells: Vec<(u32, u32)> = uvs
+            .chunks_exact(3)
+            .map(|tri| {
+                let center = (tri[0] + tri[1] + tri[2]) / 3.0 * 4.0;
+                (center.x as u32, center.y as u32)
+            })
+            .collect();
+        let mut unique = cells.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), cells.len());
+    }
+}
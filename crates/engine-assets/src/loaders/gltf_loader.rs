@@ -5,10 +5,70 @@ use anyhow::{Context, Result};
 use glam::{Vec2, Vec3};
 use std::path::Path;
 
+/// A parsed GLTF file with its buffers
+#[derive(Clone)]
+pub struct GltfData {
+    pub document: gltf::Document,
+    pub buffers: Vec<gltf::buffer::Data>,
+}
+
+impl GltfData {
+    /// True if the file has a skin (a skeletal model)
+    pub fn has_skin(&self) -> bool {
+        self.document.skins().next().is_some()
+    }
+}
+
 pub fn load_gltf<P: AsRef<Path>>(path: P) -> Result<Vec<Mesh>> {
+    read_meshes(&import_gltf(path)?)
+}
+
+/// Load a GLTF/GLB file already in memory. `read_file` gives the contents of
+/// external buffers by their URI, relative to the model.
+pub fn load_gltf_slice(
+    bytes: &[u8],
+    read_file: impl Fn(&str) -> Result<Vec<u8>>,
+) -> Result<Vec<Mesh>> {
+    read_meshes(&import_gltf_slice(bytes, read_file)?)
+}
+
+/// Parse a GLTF file and read its buffers
+pub fn import_gltf<P: AsRef<Path>>(path: P) -> Result<GltfData> {
     let (document, buffers, _images) = gltf::import(path.as_ref())
         .with_context(|| format!("Failed to load GLTF file: {:?}", path.as_ref()))?;
+    Ok(GltfData { document, buffers })
+}
 
+/// Parse a GLTF/GLB file already in memory, like load_gltf_slice
+pub fn import_gltf_slice(
+    bytes: &[u8],
+    read_file: impl Fn(&str) -> Result<Vec<u8>>,
+) -> Result<GltfData> {
+    let gltf = gltf::Gltf::from_slice(bytes).context("Failed to parse GLTF")?;
+    let mut blob = gltf.blob.clone();
+    let mut buffers = Vec::new();
+    for buffer in gltf.document.buffers() {
+        let data = match buffer.source() {
+            gltf::buffer::Source::Uri(uri) if !uri.starts_with("data:") => {
+                gltf::buffer::Data(read_file(uri)?)
+            }
+            source => gltf::buffer::Data::from_source_and_blob(source, None, &mut blob)
+                .context("Failed to read GLTF buffer")?,
+        };
+        if data.len() < buffer.length() {
+            anyhow::bail!("GLTF buffer {} is too short", buffer.index());
+        }
+        buffers.push(data);
+    }
+    Ok(GltfData {
+        document: gltf.document,
+        buffers,
+    })
+}
+
+/// Every primitive of a parsed file as a Mesh
+pub fn read_meshes(gltf: &GltfData) -> Result<Vec<Mesh>> {
+    let GltfData { document, buffers } = gltf;
     let mut meshes = Vec::new();
 
     for mesh in document.meshes() {
@@ -39,6 +99,14 @@ pub fn load_gltf<P: AsRef<Path>>(path: P) -> Result<Vec<Mesh>> {
                 })
                 .unwrap_or_else(|| vec![Vec2::ZERO; positions.len()]);
 
+            // Second UV set, used for baked lighting
+            let lightmap_uvs = reader.read_tex_coords(1).map(|tex_coords| {
+                tex_coords
+                    .into_f32()
+                    .map(Vec2::from_array)
+                    .collect::<Vec<_>>()
+            });
+
             // Read colors if available
             let colors = reader.read_colors(0).map(|colors| {
                 colors
@@ -54,6 +122,10 @@ pub fn load_gltf<P: AsRef<Path>>(path: P) -> Result<Vec<Mesh>> {
                     .with_normal(normals.get(i).copied().unwrap_or(Vec3::Y))
                     .with_tex_coord(tex_coords.get(i).copied().unwrap_or(Vec2::ZERO));
 
+                if let Some(&uv) = lightmap_uvs.as_ref().and_then(|uvs| uvs.get(i)) {
+                    vertex = vertex.with_lightmap_uv(uv);
+                }
+
                 if let Some(ref colors) = colors {
                     if let Some(&color) = colors.get(i) {
                         vertex = vertex.with_color(color);
@@ -9,26 +9,27 @@ pub fn load_material<P: AsRef<Path>>(path: P) -> Result<Material> {
     let path_ref = path.as_ref();
     let content = std::fs::read_to_string(path_ref)
         .with_context(|| format!("Failed to read material file: {:?}", path_ref))?;
+    parse_material(&content, path_ref)
+}
 
+/// Parse a material file's contents; `path` picks the format
+pub fn parse_material(content: &str, path: &Path) -> Result<Material> {
     // Determine format by file extension
-    let extension = path_ref
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("");
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
 
     let material: Material = match extension.to_lowercase().as_str() {
-        "yaml" | "yml" => serde_yaml::from_str(&content)
-            .with_context(|| format!("Failed to parse YAML material: {:?}", path_ref))?,
-        "json" => serde_json::from_str(&content)
-            .with_context(|| format!("Failed to parse JSON material: {:?}", path_ref))?,
+        "yaml" | "yml" => serde_yaml::from_str(content)
+            .with_context(|| format!("Failed to parse YAML material: {:?}", path))?,
+        "json" => serde_json::from_str(content)
+            .with_context(|| format!("Failed to parse JSON material: {:?}", path))?,
         _ => {
             // Try YAML first, then JSON
-            serde_yaml::from_str(&content)
-                .or_else(|_| serde_json::from_str(&content))
+            serde_yaml::from_str(content)
+                .or_else(|_| serde_json::from_str(content))
                 .with_context(|| {
                     format!(
                         "Failed to parse material file (tried YAML and JSON): {:?}",
-                        path_ref
+                        path
                     )
                 })?
         }
@@ -3,5 +3,5 @@
 pub mod gltf_loader;
 pub mod material_loader;
 
-pub use gltf_loader::load_gltf;
-pub use material_loader::{load_material, save_material};
+pub use gltf_loader::{import_gltf, import_gltf_slice, load_gltf, load_gltf_slice, read_meshes, GltfData};
+pub use material_loader::{load_material, parse_material, save_material};
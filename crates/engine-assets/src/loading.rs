@@ -0,0 +1,72 @@
+// Background asset loading
+//
+// A few worker threads take load jobs off a shared queue, so reading and
+// decoding files never stalls the frame. A job fills the slot behind an
+// AssetHandle when it finishes; handles given out while the asset loads see
+// it appear and report their LoadState in the meantime.
+
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+
+/// Worker threads the asset manager starts for background loads
+pub const LOADER_THREADS: usize = 4;
+
+/// Where an asset is in loading
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadState {
+    Loading,
+    Loaded,
+    /// Loading failed, with the error
+    Failed(String),
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Worker threads running queued jobs in the order they were queued
+pub(crate) struct LoaderPool {
+    jobs: Sender<Job>,
+    threads: usize,
+}
+
+impl LoaderPool {
+    pub fn new(threads: usize) -> Self {
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let mut started = 0;
+        for index in 0..threads {
+            let receiver = Arc::clone(&receiver);
+            let spawned = std::thread::Builder::new()
+                .name(format!("asset-loader-{}", index))
+                .spawn(move || loop {
+                    // Runs until the pool drops its sender
+                    let job = match receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => break,
+                    };
+                    let Ok(job) = job else {
+                        break;
+                    };
+                    job();
+                });
+            match spawned {
+                Ok(_) => started += 1,
+                Err(e) => log::error!("Failed to start an asset loader thread: {}", e),
+            }
+        }
+        Self {
+            jobs,
+            threads: started,
+        }
+    }
+
+    /// Queue a job. Runs it right away if no worker could be started.
+    pub fn spawn(&self, job: impl FnOnce() + Send + 'static) {
+        if self.threads == 0 {
+            job();
+            return;
+        }
+        if let Err(mpsc::SendError(job)) = self.jobs.send(Box::new(job)) {
+            job();
+        }
+    }
+}
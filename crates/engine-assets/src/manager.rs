@@ -1,37 +1,300 @@
 // Asset Manager - handles loading and caching of assets
-
-use crate::loaders::{gltf_loader, material_loader};
+//
+// Assets load either right away (`load_*`, blocking) or on the loader
+// threads (`load_*_async`), which hand back a handle that is still loading.
+// Both share one cache per asset type, so a blocking load of an asset that
+// is loading in the background finishes the handles already given out.
+//
+// Packs (engine_core::pack archives) can be mounted so a shipped game needs
+// no loose asset folder: a path with no file under the asset root is looked
+// up in the mounted packs, newest first.
+//
+// Generated LODs of a loaded model are simplified on the loader threads too
+// (load_lods_async), cached by the model's path until it is reloaded, and so
+// are block-compressed encodes of loaded textures (compress_texture_async).
+//
+// Audio clips decode to PCM or stay encoded by their load policy (see
+// audio_clip); a preload list decodes the clips a project names up front.
+
+use crate::audio_clip::{AudioClip, AudioLoadPolicy};
+use crate::loaders::gltf_loader::{self, GltfData};
+use crate::loaders::material_loader;
+use crate::loading::{LoadState, LoaderPool, LOADER_THREADS};
 use crate::material::Material;
 use crate::mesh::Mesh;
+use crate::simplify::{generate_lods, LodSettings};
 use crate::texture::Texture;
+use crate::texture_compression::{CompressedFormat, CompressedTexture, CompressedTextureCache};
 use anyhow::{Context, Result};
+use engine_core::pack::{AssetPack, PACK_EXTENSION};
 use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::panic::AssertUnwindSafe;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, OnceLock};
 
-/// Asset handle - cheap to clone, points to cached asset
-#[derive(Debug, Clone)]
+/// Asset handle - cheap to clone, points to a cached asset that may still be loading
+#[derive(Debug)]
 pub struct AssetHandle<T> {
-    pub inner: Arc<T>,
+    slot: Arc<OnceLock<Result<T, String>>>,
+}
+
+impl<T> Clone for AssetHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            slot: Arc::clone(&self.slot),
+        }
+    }
 }
 
 impl<T> AssetHandle<T> {
+    /// A handle to an asset that is already loaded
     pub fn new(asset: T) -> Self {
         Self {
-            inner: Arc::new(asset),
+            slot: Arc::new(OnceLock::from(Ok(asset))),
+        }
+    }
+
+    fn loading() -> Self {
+        Self {
+            slot: Arc::new(OnceLock::new()),
         }
     }
 
+    /// Fill a loading handle. Gives the result back if it already finished.
+    fn finish(&self, result: Result<T, String>) -> Result<(), Result<T, String>> {
+        self.slot.set(result)
+    }
+
+    pub fn state(&self) -> LoadState {
+        match self.slot.get() {
+            None => LoadState::Loading,
+            Some(Ok(_)) => LoadState::Loaded,
+            Some(Err(e)) => LoadState::Failed(e.clone()),
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.slot.get().is_none()
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        matches!(self.slot.get(), Some(Ok(_)))
+    }
+
+    /// The asset, once loaded
+    pub fn try_get(&self) -> Option<&T> {
+        self.slot.get()?.as_ref().ok()
+    }
+
+    /// The asset. Handles from the blocking `load_*` calls are always loaded.
+    ///
+    /// # Panics
+    ///
+    /// If the asset is still loading or failed to load
     pub fn get(&self) -> &T {
-        &self.inner
+        match self.slot.get() {
+            Some(Ok(asset)) => asset,
+            Some(Err(e)) => panic!("Asset failed to load: {}", e),
+            None => panic!("Asset is still loading"),
+        }
     }
 }
 
+/// Where an asset's bytes come from
+#[derive(Debug)]
+enum AssetSource {
+    File(PathBuf),
+    /// A file in a mounted pack, by its path in the pack
+    Pack(Arc<AssetPack>, String),
+}
+
+impl AssetSource {
+    /// The loose file under `asset_root` if there is one, else the newest
+    /// pack holding `relative_path`
+    fn find(asset_root: &Path, packs: &[Arc<AssetPack>], relative_path: &str) -> Self {
+        let full_path = asset_root.join(relative_path);
+        if !full_path.exists() {
+            let packed = pack_path("", relative_path);
+            if let Some(pack) = packs.iter().rev().find(|pack| pack.contains(&packed)) {
+                return Self::Pack(Arc::clone(pack), packed);
+            }
+        }
+        Self::File(full_path)
+    }
+
+    fn read(&self) -> Result<Vec<u8>> {
+        match self {
+            Self::File(path) => {
+                std::fs::read(path).with_context(|| format!("Failed to read {:?}", path))
+            }
+            Self::Pack(pack, path) => pack.read(path),
+        }
+    }
+
+    fn import_gltf(&self) -> Result<GltfData> {
+        match self {
+            Self::File(path) => gltf_loader::import_gltf(path),
+            Self::Pack(pack, path) => {
+                // External buffers sit next to the model in the pack
+                let dir = path.rsplit_once('/').map_or("", |(dir, _)| dir);
+                gltf_loader::import_gltf_slice(&pack.read(path)?, |uri| {
+                    pack.read(&pack_path(dir, uri))
+                })
+            }
+        }
+    }
+
+    /// The file's meshes; a skinned file's parse is kept in `skinned`
+    fn load_gltf(&self, skinned: &SkinnedGltf, full_path: PathBuf) -> Result<Vec<Mesh>> {
+        let gltf = self.import_gltf()?;
+        let meshes = gltf_loader::read_meshes(&gltf)?;
+        if gltf.has_skin() {
+            skinned.lock().unwrap().insert(full_path, Arc::new(gltf));
+        }
+        Ok(meshes)
+    }
+
+    fn load_texture(&self) -> Result<Texture> {
+        match self {
+            Self::File(path) => Texture::from_file(path),
+            Self::Pack(_, path) => {
+                let name = path.rsplit('/').next().unwrap_or(path).to_string();
+                Texture::from_bytes(name, &self.read()?)
+            }
+        }
+    }
+
+    fn load_audio(&self, policy: AudioLoadPolicy) -> Result<AudioClip> {
+        let name = match self {
+            Self::File(path) => path
+                .file_name()
+                .map_or_else(String::new, |n| n.to_string_lossy().into_owned()),
+            Self::Pack(_, path) => path.rsplit('/').next().unwrap_or(path).to_string(),
+        };
+        AudioClip::from_bytes(name, self.read()?, policy)
+    }
+
+    fn load_material(&self) -> Result<Material> {
+        match self {
+            Self::File(path) => material_loader::load_material(path),
+            Self::Pack(_, path) => {
+                let content = String::from_utf8(self.read()?)
+                    .with_context(|| format!("{} is not UTF-8", path))?;
+                material_loader::parse_material(&content, Path::new(path))
+            }
+        }
+    }
+}
+
+impl fmt::Display for AssetSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Pack(pack, path) => write!(f, "{} in {}", path, pack.path().display()),
+        }
+    }
+}
+
+/// `relative` joined onto the pack directory `dir`, as a pack path: '/'
+/// separators, no "." or ".." parts
+fn pack_path(dir: &str, relative: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in dir.split('/').chain(relative.split(['/', '\\'])) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// Cached handle for a loaded asset, or load it now and cache it
+fn load_blocking<T>(
+    cache: &mut HashMap<PathBuf, AssetHandle<T>>,
+    full_path: PathBuf,
+    source: impl FnOnce() -> AssetSource,
+    load: impl FnOnce(&AssetSource) -> Result<T>,
+) -> Result<AssetHandle<T>> {
+    if let Some(handle) = cache.get(&full_path).filter(|handle| handle.is_loaded()) {
+        return Ok(handle.clone());
+    }
+
+    let mut asset = load(&source())?;
+    // Finish a background load of the same file early for its handles
+    if let Some(handle) = cache.get(&full_path).filter(|handle| handle.is_loading()) {
+        let Err(Ok(returned)) = handle.finish(Ok(asset)) else {
+            return Ok(handle.clone());
+        };
+        // The background load won the race
+        if handle.is_loaded() {
+            return Ok(handle.clone());
+        }
+        asset = returned;
+    }
+
+    let handle = AssetHandle::new(asset);
+    cache.insert(full_path, handle.clone());
+    Ok(handle)
+}
+
+/// Cached handle in whatever state it is, or queue a load on the pool.
+/// Failed loads are cached too, so a missing file isn't read every frame.
+fn load_in_background<K: Eq + Hash, T: Send + Sync + 'static>(
+    pool: &LoaderPool,
+    cache: &mut HashMap<K, AssetHandle<T>>,
+    key: K,
+    source: impl FnOnce() -> AssetSource,
+    load: impl FnOnce(&AssetSource) -> Result<T> + Send + 'static,
+) -> AssetHandle<T> {
+    if let Some(handle) = cache.get(&key) {
+        return handle.clone();
+    }
+
+    let handle = AssetHandle::loading();
+    cache.insert(key, handle.clone());
+    let job_handle = handle.clone();
+    let source = source();
+    pool.spawn(move || {
+        let result = match std::panic::catch_unwind(AssertUnwindSafe(|| load(&source))) {
+            Ok(Ok(asset)) => Ok(asset),
+            Ok(Err(e)) => {
+                log::warn!("{:#}", e);
+                Err(format!("{:#}", e))
+            }
+            Err(_) => Err(format!("Loading {} panicked", source)),
+        };
+        // A blocking load may have finished it first
+        let _ = job_handle.finish(result);
+    });
+    handle
+}
+
+/// Parsed glTF files with a skin, by full path
+type SkinnedGltf = Arc<Mutex<HashMap<PathBuf, Arc<GltfData>>>>;
+
 pub struct AssetManager {
     asset_root: PathBuf,
     meshes: HashMap<PathBuf, AssetHandle<Vec<Mesh>>>,
+    /// Generated LODs of each mesh of a model, by the model's path
+    lods: HashMap<PathBuf, AssetHandle<Vec<Vec<Mesh>>>>,
+    /// Skinned models' parsed files, kept for the skeletal importer
+    skinned_gltf: SkinnedGltf,
     textures: HashMap<PathBuf, AssetHandle<Texture>>,
+    /// Block-compressed encodes of loaded textures, by path and format
+    compressed_textures: HashMap<(PathBuf, CompressedFormat), AssetHandle<CompressedTexture>>,
     materials: HashMap<PathBuf, AssetHandle<Material>>,
+    audio: HashMap<PathBuf, AssetHandle<AudioClip>>,
+    /// Load policies of clips that don't use the default
+    audio_policies: HashMap<String, AudioLoadPolicy>,
+    /// Started on the first background load
+    loader: Option<LoaderPool>,
+    /// Mounted packs, oldest first
+    packs: Vec<Arc<AssetPack>>,
 }
 
 impl AssetManager {
@@ -39,9 +302,96 @@ impl AssetManager {
         Self {
             asset_root: asset_root.as_ref().to_path_buf(),
             meshes: HashMap::new(),
+            lods: HashMap::new(),
+            skinned_gltf: SkinnedGltf::default(),
             textures: HashMap::new(),
+            compressed_textures: HashMap::new(),
             materials: HashMap::new(),
+            audio: HashMap::new(),
+            audio_policies: HashMap::new(),
+            loader: None,
+            packs: Vec::new(),
+        }
+    }
+
+    /// Mount a pack. Its files are found by the same paths as loose files
+    /// under the asset root, which still win when both exist.
+    pub fn mount_pack(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let pack = AssetPack::open(path.as_ref())?;
+        log::info!(
+            "Mounted asset pack {:?} ({} files)",
+            path.as_ref(),
+            pack.entries().count()
+        );
+        self.packs.push(Arc::new(pack));
+        Ok(())
+    }
+
+    /// Mount every pack in `dir`, in name order. Returns how many were mounted.
+    pub fn mount_packs_in(&mut self, dir: impl AsRef<Path>) -> Result<usize> {
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(dir.as_ref())? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == PACK_EXTENSION) {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        for path in &paths {
+            self.mount_pack(path)?;
         }
+        Ok(paths.len())
+    }
+
+    /// Number of mounted packs
+    pub fn pack_count(&self) -> usize {
+        self.packs.len()
+    }
+
+    /// Whether an asset file exists, loose or in a mounted pack
+    pub fn exists(&self, path: &str) -> bool {
+        match AssetSource::find(&self.asset_root, &self.packs, path) {
+            AssetSource::File(full_path) => full_path.exists(),
+            AssetSource::Pack(..) => true,
+        }
+    }
+
+    /// Contents of any asset file (scenes, scripts, ...), loose or packed
+    pub fn read(&self, path: &str) -> Result<Vec<u8>> {
+        AssetSource::find(&self.asset_root, &self.packs, path).read()
+    }
+
+    /// Contents of a file by its path on disk (relative to the working
+    /// directory, as scene paths are, or absolute). Files under the asset
+    /// root are read through the packs; anything else is read from disk.
+    pub fn read_file(&self, path: impl AsRef<Path>) -> Result<Vec<u8>> {
+        let path = path.as_ref();
+        match self.asset_path(path)? {
+            Some(asset_path) => self.read(&asset_path),
+            None => std::fs::read(path).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    /// Whether a file given by its path on disk exists, loose or packed
+    pub fn file_exists(&self, path: impl AsRef<Path>) -> bool {
+        let path = path.as_ref();
+        match self.asset_path(path) {
+            Ok(Some(asset_path)) => self.exists(&asset_path),
+            _ => path.exists(),
+        }
+    }
+
+    /// `path` relative to the asset root, '/'-separated, if it is under it
+    fn asset_path(&self, path: &Path) -> Result<Option<String>> {
+        let full_path = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            std::env::current_dir()?.join(path)
+        };
+        Ok(full_path
+            .strip_prefix(&self.asset_root)
+            .ok()
+            .map(|relative| relative.to_string_lossy().replace('\\', "/")))
     }
 
     /// Get the full path for an asset
@@ -57,61 +407,221 @@ impl AssetManager {
     /// Load a GLTF model (with caching)
     pub fn load_gltf(&mut self, path: &str) -> Result<AssetHandle<Vec<Mesh>>> {
         let full_path = self.full_path(path);
+        let source = || AssetSource::find(&self.asset_root, &self.packs, path);
+        let skinned = &self.skinned_gltf;
+        load_blocking(&mut self.meshes, full_path.clone(), source, |source| {
+            log::info!("Loading GLTF: {}", source);
+            source
+                .load_gltf(skinned, full_path)
+                .with_context(|| format!("Failed to load GLTF: {}", path))
+        })
+    }
 
-        // Check cache
-        if let Some(handle) = self.meshes.get(&full_path) {
-            return Ok(handle.clone());
-        }
-
-        // Load from disk
-        log::info!("Loading GLTF: {:?}", full_path);
-        let meshes = gltf_loader::load_gltf(&full_path)
-            .with_context(|| format!("Failed to load GLTF: {}", path))?;
-
-        let handle = AssetHandle::new(meshes);
-        self.meshes.insert(full_path, handle.clone());
-
-        Ok(handle)
+    /// The parsed file of a model loaded with load_gltf or load_gltf_async
+    /// that has a skin, so the skeleton is imported without reading it again
+    pub fn skinned_gltf(&self, path: &str) -> Option<Arc<GltfData>> {
+        self.skinned_gltf
+            .lock()
+            .unwrap()
+            .get(&self.full_path(path))
+            .cloned()
     }
 
     /// Load a texture (with caching)
     pub fn load_texture(&mut self, path: &str) -> Result<AssetHandle<Texture>> {
         let full_path = self.full_path(path);
+        let source = || AssetSource::find(&self.asset_root, &self.packs, path);
+        load_blocking(&mut self.textures, full_path, source, |source| {
+            log::info!("Loading texture: {}", source);
+            source
+                .load_texture()
+                .with_context(|| format!("Failed to load texture: {}", path))
+        })
+    }
 
-        // Check cache
-        if let Some(handle) = self.textures.get(&full_path) {
-            return Ok(handle.clone());
-        }
+    /// Load a material (with caching)
+    pub fn load_material(&mut self, path: &str) -> Result<AssetHandle<Material>> {
+        let full_path = self.full_path(path);
+        let source = || AssetSource::find(&self.asset_root, &self.packs, path);
+        load_blocking(&mut self.materials, full_path, source, |source| {
+            log::info!("Loading material: {}", source);
+            source
+                .load_material()
+                .with_context(|| format!("Failed to load material: {}", path))
+        })
+    }
 
-        // Load from disk
-        log::info!("Loading texture: {:?}", full_path);
-        let texture = Texture::from_file(&full_path)
-            .with_context(|| format!("Failed to load texture: {}", path))?;
+    /// Load a GLTF model on the loader threads (with caching)
+    pub fn load_gltf_async(&mut self, path: &str) -> AssetHandle<Vec<Mesh>> {
+        let full_path = self.full_path(path);
+        let source = || AssetSource::find(&self.asset_root, &self.packs, path);
+        let path = path.to_string();
+        let skinned = Arc::clone(&self.skinned_gltf);
+        let skinned_path = full_path.clone();
+        let loader = self
+            .loader
+            .get_or_insert_with(|| LoaderPool::new(LOADER_THREADS));
+        load_in_background(loader, &mut self.meshes, full_path, source, move |source| {
+            log::info!("Loading GLTF in the background: {}", source);
+            source
+                .load_gltf(&skinned, skinned_path)
+                .with_context(|| format!("Failed to load GLTF: {}", path))
+        })
+    }
 
-        let handle = AssetHandle::new(texture);
-        self.textures.insert(full_path, handle.clone());
+    /// LOD meshes (see generate_lods) for each mesh of a model loaded with
+    /// load_gltf, simplified on the loader threads (with caching, so the
+    /// settings of the first call are kept until the model is reloaded)
+    pub fn load_lods_async(&mut self, path: &str, settings: LodSettings) -> AssetHandle<Vec<Vec<Mesh>>> {
+        let full_path = self.full_path(path);
+        let model = self.meshes.get(&full_path).cloned();
+        let source = || AssetSource::File(full_path.clone());
+        let path = path.to_string();
+        let loader = self
+            .loader
+            .get_or_insert_with(|| LoaderPool::new(LOADER_THREADS));
+        load_in_background(loader, &mut self.lods, full_path.clone(), source, move |source| {
+            log::info!("Generating LODs in the background: {}", source);
+            let meshes = model
+                .as_ref()
+                .and_then(AssetHandle::try_get)
+                .with_context(|| format!("Model {} isn't loaded", path))?;
+            Ok(meshes.iter().map(|mesh| generate_lods(mesh, &settings)).collect())
+        })
+    }
 
-        Ok(handle)
+    /// Load a texture on the loader threads (with caching)
+    pub fn load_texture_async(&mut self, path: &str) -> AssetHandle<Texture> {
+        let full_path = self.full_path(path);
+        let source = || AssetSource::find(&self.asset_root, &self.packs, path);
+        let path = path.to_string();
+        let loader = self
+            .loader
+            .get_or_insert_with(|| LoaderPool::new(LOADER_THREADS));
+        load_in_background(
+            loader,
+            &mut self.textures,
+            full_path,
+            source,
+            move |source| {
+                log::info!("Loading texture in the background: {}", source);
+                source
+                    .load_texture()
+                    .with_context(|| format!("Failed to load texture: {}", path))
+            },
+        )
     }
 
-    /// Load a material (with caching)
-    pub fn load_material(&mut self, path: &str) -> Result<AssetHandle<Material>> {
+    /// A texture loaded with load_texture_async, block-compressed in `format`
+    /// on the loader threads. The encode goes through `cache`, so each
+    /// texture is only compressed once across runs.
+    pub fn compress_texture_async(
+        &mut self,
+        path: &str,
+        format: CompressedFormat,
+        cache: &CompressedTextureCache,
+    ) -> AssetHandle<CompressedTexture> {
+        let full_path = self.full_path(path);
+        let texture = self.textures.get(&full_path).cloned();
+        let source = || AssetSource::File(full_path.clone());
+        let (path, cache) = (path.to_string(), cache.clone());
+        let loader = self
+            .loader
+            .get_or_insert_with(|| LoaderPool::new(LOADER_THREADS));
+        load_in_background(
+            loader,
+            &mut self.compressed_textures,
+            (full_path.clone(), format),
+            source,
+            move |_| {
+                let texture = texture
+                    .as_ref()
+                    .and_then(AssetHandle::try_get)
+                    .with_context(|| format!("Texture {} isn't loaded", path))?;
+                Ok(cache.load_or_compress(texture, format))
+            },
+        )
+    }
+
+    /// Load a material on the loader threads (with caching)
+    pub fn load_material_async(&mut self, path: &str) -> AssetHandle<Material> {
         let full_path = self.full_path(path);
+        let source = || AssetSource::find(&self.asset_root, &self.packs, path);
+        let path = path.to_string();
+        let loader = self
+            .loader
+            .get_or_insert_with(|| LoaderPool::new(LOADER_THREADS));
+        load_in_background(
+            loader,
+            &mut self.materials,
+            full_path,
+            source,
+            move |source| {
+                log::info!("Loading material in the background: {}", source);
+                source
+                    .load_material()
+                    .with_context(|| format!("Failed to load material: {}", path))
+            },
+        )
+    }
 
-        // Check cache
-        if let Some(handle) = self.materials.get(&full_path) {
-            return Ok(handle.clone());
-        }
+    /// Set how an audio clip is held once loaded. Takes effect the next time
+    /// it loads.
+    pub fn set_audio_policy(&mut self, path: &str, policy: AudioLoadPolicy) {
+        self.audio_policies.insert(path.to_string(), policy);
+    }
+
+    pub fn audio_policy(&self, path: &str) -> AudioLoadPolicy {
+        self.audio_policies.get(path).copied().unwrap_or_default()
+    }
+
+    /// Load an audio clip (with caching)
+    pub fn load_audio(&mut self, path: &str) -> Result<AssetHandle<AudioClip>> {
+        let full_path = self.full_path(path);
+        let policy = self.audio_policy(path);
+        let source = || AssetSource::find(&self.asset_root, &self.packs, path);
+        load_blocking(&mut self.audio, full_path, source, |source| {
+            log::info!("Loading audio: {}", source);
+            source
+                .load_audio(policy)
+                .with_context(|| format!("Failed to load audio: {}", path))
+        })
+    }
 
-        // Load from disk
-        log::info!("Loading material: {:?}", full_path);
-        let material = material_loader::load_material(&full_path)
-            .with_context(|| format!("Failed to load material: {}", path))?;
+    /// Load an audio clip on the loader threads (with caching)
+    pub fn load_audio_async(&mut self, path: &str) -> AssetHandle<AudioClip> {
+        let full_path = self.full_path(path);
+        let policy = self.audio_policy(path);
+        let source = || AssetSource::find(&self.asset_root, &self.packs, path);
+        let path = path.to_string();
+        let loader = self
+            .loader
+            .get_or_insert_with(|| LoaderPool::new(LOADER_THREADS));
+        load_in_background(loader, &mut self.audio, full_path, source, move |source| {
+            log::info!("Loading audio in the background: {}", source);
+            source
+                .load_audio(policy)
+                .with_context(|| format!("Failed to load audio: {}", path))
+        })
+    }
 
-        let handle = AssetHandle::new(material);
-        self.materials.insert(full_path, handle.clone());
+    /// Start loading a list of clips on the loader threads, so their first
+    /// play doesn't wait on the disk or the decoder
+    pub fn preload_audio<S: AsRef<str>>(&mut self, paths: &[S]) -> Vec<AssetHandle<AudioClip>> {
+        paths
+            .iter()
+            .map(|path| self.load_audio_async(path.as_ref()))
+            .collect()
+    }
 
-        Ok(handle)
+    /// Number of assets still loading in the background
+    pub fn pending_loads(&self) -> usize {
+        self.meshes.values().filter(|h| h.is_loading()).count()
+            + self.lods.values().filter(|h| h.is_loading()).count()
+            + self.textures.values().filter(|h| h.is_loading()).count()
+            + self.compressed_textures.values().filter(|h| h.is_loading()).count()
+            + self.materials.values().filter(|h| h.is_loading()).count()
+            + self.audio.values().filter(|h| h.is_loading()).count()
     }
 
     /// Create a mesh directly (and cache it)
@@ -137,11 +647,30 @@ impl AssetManager {
         self.materials.len()
     }
 
-    /// Clear all caches
+    /// Get cached audio clip count
+    pub fn audio_cache_size(&self) -> usize {
+        self.audio.len()
+    }
+
+    /// Bytes held by loaded audio clips
+    pub fn audio_memory(&self) -> usize {
+        self.audio
+            .values()
+            .filter_map(|h| h.try_get())
+            .map(AudioClip::memory_bytes)
+            .sum()
+    }
+
+    /// Clear all caches. Background loads still running fill only the
+    /// handles already given out.
     pub fn clear_cache(&mut self) {
         self.meshes.clear();
+        self.lods.clear();
+        self.skinned_gltf.lock().unwrap().clear();
         self.textures.clear();
+        self.compressed_textures.clear();
         self.materials.clear();
+        self.audio.clear();
         log::info!("Asset cache cleared");
     }
 
@@ -151,6 +680,7 @@ impl AssetManager {
 
         // Remove from cache
         self.textures.remove(&full_path);
+        self.compressed_textures.retain(|(path, _), _| *path != full_path);
 
         // Force reload
         log::info!("Hot-reloading texture: {:?}", full_path);
@@ -163,6 +693,8 @@ impl AssetManager {
 
         // Remove from cache
         self.meshes.remove(&full_path);
+        self.lods.remove(&full_path);
+        self.skinned_gltf.lock().unwrap().remove(&full_path);
 
         // Force reload
         log::info!("Hot-reloading GLTF: {:?}", full_path);
@@ -181,22 +713,36 @@ impl AssetManager {
         self.load_material(path)
     }
 
+    /// Reload an audio clip (invalidate cache and reload from disk)
+    pub fn reload_audio(&mut self, path: &str) -> Result<AssetHandle<AudioClip>> {
+        let full_path = self.full_path(path);
+
+        // Remove from cache
+        self.audio.remove(&full_path);
+
+        // Force reload
+        log::info!("Hot-reloading audio: {:?}", full_path);
+        self.load_audio(path)
+    }
+
     /// Check if a texture is loaded in cache
     pub fn has_texture(&self, path: &str) -> bool {
         let full_path = self.full_path(path);
-        self.textures.contains_key(&full_path)
+        self.textures.get(&full_path).is_some_and(|h| h.is_loaded())
     }
 
     /// Check if a model is loaded in cache
     pub fn has_model(&self, path: &str) -> bool {
         let full_path = self.full_path(path);
-        self.meshes.contains_key(&full_path)
+        self.meshes.get(&full_path).is_some_and(|h| h.is_loaded())
     }
 
     /// Check if a material is loaded in cache
     pub fn has_material(&self, path: &str) -> bool {
         let full_path = self.full_path(path);
-        self.materials.contains_key(&full_path)
+        self.materials
+            .get(&full_path)
+            .is_some_and(|h| h.is_loaded())
     }
 }
 
@@ -205,3 +751,116 @@ impl Default for AssetManager {
         Self::new("assets")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loaders::save_material;
+    use std::time::{Duration, Instant};
+
+    fn wait_for<T>(handle: &AssetHandle<T>) -> LoadState {
+        let start = Instant::now();
+        while handle.is_loading() && start.elapsed() < Duration::from_secs(10) {
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        handle.state()
+    }
+
+    #[test]
+    fn test_background_loads_finish_or_fail() {
+        let dir = tempfile::tempdir().unwrap();
+        save_material(&Material::default(), dir.path().join("stone.mat")).unwrap();
+        let mut assets = AssetManager::new(dir.path());
+
+        let material = assets.load_material_async("stone.mat");
+        let missing = assets.load_texture_async("missing.png");
+        assert_eq!(wait_for(&material), LoadState::Loaded);
+        assert!(matches!(wait_for(&missing), LoadState::Failed(_)));
+        assert!(missing.try_get().is_none());
+        // LODs are only made from a model that loaded
+        let lods = assets.load_lods_async("missing.gltf", LodSettings::default());
+        assert!(matches!(wait_for(&lods), LoadState::Failed(_)));
+        // And compressed textures from a texture that loaded
+        let cache = CompressedTextureCache::new(dir.path().join("cache"));
+        image::RgbaImage::new(8, 8).save(dir.path().join("white.png")).unwrap();
+        assert_eq!(wait_for(&assets.load_texture_async("white.png")), LoadState::Loaded);
+        let compressed = assets.compress_texture_async("white.png", CompressedFormat::Bc7, &cache);
+        let missing_compressed = assets.compress_texture_async("missing.png", CompressedFormat::Bc7, &cache);
+        assert_eq!(wait_for(&compressed), LoadState::Loaded);
+        assert_eq!(compressed.get().levels.len(), 4);
+        assert!(matches!(wait_for(&missing_compressed), LoadState::Failed(_)));
+        assert_eq!(assets.pending_loads(), 0);
+
+        // The cached handle is shared with blocking loads
+        let again = assets.load_material("stone.mat").unwrap();
+        assert!(Arc::ptr_eq(&again.slot, &material.slot));
+        assert!(assets.has_material("stone.mat"));
+        assert!(!assets.has_texture("missing.png"));
+        assert!(assets.load_texture("missing.png").is_err());
+    }
+
+    #[test]
+    fn test_mounted_pack_backs_missing_files() {
+        let source = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(source.path().join("materials")).unwrap();
+        std::fs::create_dir_all(source.path().join("textures")).unwrap();
+        save_material(
+            &Material::default(),
+            source.path().join("materials/stone.mat"),
+        )
+        .unwrap();
+        image::RgbaImage::new(2, 2)
+            .save(source.path().join("textures/white.png"))
+            .unwrap();
+        std::fs::write(source.path().join("intro.scene"), "packed").unwrap();
+        let files = [
+            PathBuf::from("materials/stone.mat"),
+            PathBuf::from("textures/white.png"),
+            PathBuf::from("intro.scene"),
+        ];
+        let packs = tempfile::tempdir().unwrap();
+        engine_core::pack::write_pack(source.path(), &files, &packs.path().join("game.cpack"))
+            .unwrap();
+
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("intro.scene"), "loose").unwrap();
+        let mut assets = AssetManager::new(root.path());
+        assert!(!assets.exists("materials/stone.mat"));
+        assert_eq!(assets.mount_packs_in(packs.path()).unwrap(), 1);
+
+        assert!(assets.exists("./materials/stone.mat"));
+        assert!(assets.load_material("materials/stone.mat").is_ok());
+        let texture = assets.load_texture_async("textures/white.png");
+        assert_eq!(wait_for(&texture), LoadState::Loaded);
+        assert_eq!(texture.get().width, 2);
+        // Loose files win over packed ones
+        assert_eq!(assets.read("intro.scene").unwrap(), b"loose");
+        assert!(assets.read("missing.txt").is_err());
+        // Paths on disk under the asset root go through the packs too
+        assert!(assets.file_exists(root.path().join("materials/stone.mat")));
+        assert!(assets.read_file(root.path().join("textures/white.png")).is_ok());
+        assert_eq!(assets.read_file(source.path().join("intro.scene")).unwrap(), b"packed");
+
+        assert_eq!(pack_path("models", "../textures/./a.bin"), "textures/a.bin");
+    }
+
+    #[test]
+    fn test_audio_policy_and_preload() {
+        let dir = tempfile::tempdir().unwrap();
+        // A WAV header with no data decodes to an error, but loads encoded
+        std::fs::write(dir.path().join("broken.wav"), b"RIFF\0\0\0\0WAVE").unwrap();
+        std::fs::write(dir.path().join("music.mp3"), b"ID3").unwrap();
+        let mut assets = AssetManager::new(dir.path());
+
+        let handles = assets.preload_audio(&["broken.wav", "music.mp3"]);
+        assert!(matches!(wait_for(&handles[0]), LoadState::Failed(_)));
+        assert_eq!(wait_for(&handles[1]), LoadState::Loaded);
+        assert_eq!(assets.audio_cache_size(), 2);
+        assert_eq!(assets.audio_memory(), 3);
+
+        assets.set_audio_policy("broken.wav", AudioLoadPolicy::Stream);
+        let clip = assets.reload_audio("broken.wav").unwrap();
+        assert!(!clip.get().is_decoded());
+        assert_eq!(clip.get().name, "broken.wav");
+    }
+}
@@ -7,6 +7,7 @@ pub struct Vertex {
     pub position: Vec3,
     pub normal: Vec3,
     pub tex_coord: Vec2,
+    pub lightmap_uv: Option<Vec2>,  // Second UV channel for baked lighting
     pub color: Option<Vec3>,
     pub tangent: Option<Vec4>,      // w component is handedness (+1 or -1)
     pub bitangent: Option<Vec3>,
@@ -18,6 +19,7 @@ impl Vertex {
             position,
             normal: Vec3::Y,
             tex_coord: Vec2::ZERO,
+            lightmap_uv: None,
             color: None,
             tangent: None,
             bitangent: None,
@@ -34,6 +36,11 @@ impl Vertex {
         self
     }
 
+    pub fn with_lightmap_uv(mut self, lightmap_uv: Vec2) -> Self {
+        self.lightmap_uv = Some(lightmap_uv);
+        self
+    }
+
     pub fn with_color(mut self, color: Vec3) -> Self {
         self.color = Some(color);
         self
@@ -161,6 +168,58 @@ impl Mesh {
         Self::new("Plane".to_string(), vertices, indices)
     }
 
+    /// True if every vertex has a lightmap UV
+    pub fn has_lightmap_uvs(&self) -> bool {
+        !self.vertices.is_empty() && self.vertices.iter().all(|v| v.lightmap_uv.is_some())
+    }
+
+    /// Generate non-overlapping lightmap UVs: every triangle is flattened onto
+    /// its plane and packed into its own cell of a square grid, at the same
+    /// texel density for the whole mesh. Triangles stop sharing vertices.
+    /// `padding` is the gap kept around each triangle, as a fraction of a cell.
+    pub fn generate_lightmap_uvs(&mut self, padding: f32) {
+        let triangle_count = self.indices.len() / 3;
+        if triangle_count == 0 {
+            return;
+        }
+
+        // Triangle corners in 2D, in the triangle's own plane
+        let flattened: Vec<[Vec2; 3]> = self
+            .indices
+            .chunks_exact(3)
+            .map(|tri| {
+                let [a, b, c] = [0, 1, 2].map(|i| self.vertices[tri[i] as usize].position);
+                let u = (b - a).normalize_or_zero();
+                let v = (b - a).cross(c - a).cross(u).normalize_or_zero();
+                let flat = [a, b, c].map(|p| Vec2::new((p - a).dot(u), (p - a).dot(v)));
+                let min = flat[0].min(flat[1]).min(flat[2]);
+                flat.map(|p| p - min)
+            })
+            .collect();
+        let largest = flattened
+            .iter()
+            .flat_map(|tri| tri.iter().map(|p| p.max_element()))
+            .fold(0.0_f32, f32::max)
+            .max(f32::EPSILON);
+
+        let grid = (triangle_count as f32).sqrt().ceil() as usize;
+        let cell = 1.0 / grid as f32;
+        let padding = padding.clamp(0.0, 0.45) * cell;
+        let scale = (cell - padding * 2.0) / largest;
+
+        let mut vertices = Vec::with_capacity(triangle_count * 3);
+        for (i, (tri, flat)) in self.indices.chunks_exact(3).zip(&flattened).enumerate() {
+            let origin = Vec2::new((i % grid) as f32, (i / grid) as f32) * cell + Vec2::splat(padding);
+            for (&index, &point) in tri.iter().zip(flat) {
+                let mut vertex = self.vertices[index as usize].clone();
+                vertex.lightmap_uv = Some(origin + point * scale);
+                vertices.push(vertex);
+            }
+        }
+        self.vertices = vertices;
+        self.indices = (0..self.vertices.len() as u32).collect();
+    }
+
     /// Calculate tangents and bitangents using mikktspace algorithm
     /// This is the industry-standard approach used by most 3D tools
     pub fn calculate_tangents(&mut self) {
@@ -0,0 +1,803 @@
+// Navigation mesh baking - walkable surfaces for AI agents
+//
+// The ground (terrain plus the tops of static meshes low enough to step onto)
+// is sampled on a grid. Cells an agent can stand on are kept, the walkable
+// area is shrunk by the agent radius, and the remaining cells are merged into
+// rectangular polygons linked to their neighbors.
+//
+// Paths are found with A* over the polygons, then pulled tight through the
+// edges they cross (the funnel algorithm) so agents walk straight lines
+// between corners.
+
+use crate::terrain::{HeightMap, TerrainConfig};
+use anyhow::{Context, Result};
+use glam::{Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::path::{Path, PathBuf};
+
+/// Walkable ground around meshes when there is no terrain to bound the bake
+const GROUND_MARGIN: f32 = 10.0;
+/// Largest grid the bake samples per axis (the cell size grows to fit)
+const MAX_CELLS_PER_AXIS: usize = 1024;
+/// Longest polygon side in cells, keeps polygons usable for path costs
+const MAX_POLYGON_CELLS: usize = 32;
+
+/// Agent dimensions a navmesh is baked for
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NavAgentSettings {
+    pub radius: f32,
+    pub height: f32,
+    /// Steepest walkable slope in degrees
+    pub max_slope: f32,
+    /// Highest ledge the agent can step up or down
+    pub max_climb: f32,
+    /// Sampling resolution in world units
+    pub cell_size: f32,
+}
+
+impl Default for NavAgentSettings {
+    fn default() -> Self {
+        Self {
+            radius: 0.5,
+            height: 2.0,
+            max_slope: 45.0,
+            max_climb: 0.4,
+            cell_size: 0.5,
+        }
+    }
+}
+
+/// World-space bounds of a static mesh. Agents walk on its top if they can
+/// step onto it, otherwise it blocks them.
+#[derive(Debug, Clone, Copy)]
+pub struct NavObstacle {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl NavObstacle {
+    fn contains_xz(&self, x: f32, z: f32) -> bool {
+        x >= self.min.x && x <= self.max.x && z >= self.min.z && z <= self.max.z
+    }
+}
+
+/// Geometry a navmesh is baked from
+#[derive(Clone, Copy)]
+pub struct NavMeshInput<'a> {
+    pub terrain: Option<(&'a HeightMap, &'a TerrainConfig)>,
+    pub obstacles: &'a [NavObstacle],
+}
+
+/// A convex walkable polygon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NavPolygon {
+    /// Corners in order around the polygon
+    pub vertices: Vec<[f32; 3]>,
+    /// Polygons reachable across a shared edge
+    pub neighbors: Vec<usize>,
+}
+
+impl NavPolygon {
+    /// Area of the polygon projected onto the ground plane
+    pub fn area(&self) -> f32 {
+        let n = self.vertices.len();
+        let twice_area: f32 = (0..n)
+            .map(|i| {
+                let a = self.vertices[i];
+                let b = self.vertices[(i + 1) % n];
+                a[0] * b[2] - b[0] * a[2]
+            })
+            .sum();
+        twice_area.abs() * 0.5
+    }
+
+    /// True if the point lies inside the polygon seen from above
+    pub fn contains_xz(&self, x: f32, z: f32) -> bool {
+        let n = self.vertices.len();
+        if n < 3 {
+            return false;
+        }
+        let point = Vec2::new(x, z);
+        let mut sign = 0.0_f32;
+        for i in 0..n {
+            let a = Vec2::new(self.vertices[i][0], self.vertices[i][2]);
+            let b = Vec2::new(self.vertices[(i + 1) % n][0], self.vertices[(i + 1) % n][2]);
+            let cross = (b - a).perp_dot(point - a);
+            if cross.abs() <= f32::EPSILON {
+                continue;
+            }
+            if sign == 0.0 {
+                sign = cross.signum();
+            } else if cross.signum() != sign {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Corners of the polygon's bounds seen from above
+    pub fn bounds_xz(&self) -> (Vec2, Vec2) {
+        self.vertices.iter().fold(
+            (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+            |(min, max), v| {
+                (
+                    min.min(Vec2::new(v[0], v[2])),
+                    max.max(Vec2::new(v[0], v[2])),
+                )
+            },
+        )
+    }
+
+    pub fn center(&self) -> Vec3 {
+        let sum = self
+            .vertices
+            .iter()
+            .fold(Vec3::ZERO, |sum, v| sum + Vec3::from_array(*v));
+        sum / self.vertices.len().max(1) as f32
+    }
+
+    /// Ground height at a point, interpolated between the corners of the
+    /// baked rectangle
+    pub fn height_at(&self, x: f32, z: f32) -> f32 {
+        if self.vertices.len() != 4 {
+            return self.center().y;
+        }
+        let (min, max) = self.bounds_xz();
+        let u = ((x - min.x) / (max.x - min.x).max(1e-6)).clamp(0.0, 1.0);
+        let v = ((z - min.y) / (max.y - min.y).max(1e-6)).clamp(0.0, 1.0);
+        let [a, b, c, d] = [0, 1, 2, 3].map(|i| self.vertices[i][1]);
+        let near = a + (b - a) * u;
+        let far = d + (c - d) * u;
+        near + (far - near) * v
+    }
+}
+
+/// Polygon waiting in the A* open list, cheapest estimate first
+struct OpenPolygon {
+    estimate: f32,
+    polygon: usize,
+}
+
+impl PartialEq for OpenPolygon {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OpenPolygon {}
+
+impl PartialOrd for OpenPolygon {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenPolygon {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate
+            .total_cmp(&self.estimate)
+            .then_with(|| other.polygon.cmp(&self.polygon))
+    }
+}
+
+/// Baked navigation mesh, saved next to its scene
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NavMesh {
+    /// Agent the mesh was baked for
+    pub agent: NavAgentSettings,
+    pub polygons: Vec<NavPolygon>,
+}
+
+impl NavMesh {
+    /// Bake a navmesh from terrain and static mesh bounds
+    pub fn bake(input: &NavMeshInput, agent: &NavAgentSettings) -> Self {
+        let mut navmesh = Self {
+            agent: *agent,
+            polygons: Vec::new(),
+        };
+        let Some((min, max)) = bake_bounds(input) else {
+            return navmesh;
+        };
+
+        let extent = max - min;
+        let cell_size = agent
+            .cell_size
+            .max(extent.max_element() / MAX_CELLS_PER_AXIS as f32)
+            .max(0.01);
+        let nx = (extent.x / cell_size).ceil().max(1.0) as usize;
+        let nz = (extent.y / cell_size).ceil().max(1.0) as usize;
+
+        let mut obstacles = input.obstacles.to_vec();
+        obstacles.sort_by(|a, b| a.max.y.total_cmp(&b.max.y));
+        let max_slope_tan = agent.max_slope.clamp(0.0, 89.9).to_radians().tan();
+
+        // Standing height and walkability of each cell
+        let mut surface = vec![0.0_f32; nx * nz];
+        let mut walkable = vec![false; nx * nz];
+        for j in 0..nz {
+            for i in 0..nx {
+                let x = min.x + (i as f32 + 0.5) * cell_size;
+                let z = min.y + (j as f32 + 0.5) * cell_size;
+                let (mut height, mut ok) = match input.terrain {
+                    Some((heightmap, config)) => {
+                        // Clamped so slope samples at the far edge stay on the height map
+                        let h = |x: f32, z: f32| {
+                            heightmap.sample_height(
+                                x.clamp(min.x, max.x - 1e-3),
+                                z.clamp(min.y, max.y - 1e-3),
+                                config.scale,
+                            )
+                        };
+                        let half = cell_size * 0.5;
+                        let dx = (h(x + half, z) - h(x - half, z)) / cell_size;
+                        let dz = (h(x, z + half) - h(x, z - half)) / cell_size;
+                        (h(x, z), dx.hypot(dz) <= max_slope_tan)
+                    }
+                    None => (0.0, true),
+                };
+
+                // Step onto mesh tops in height order, so stairs of boxes stack
+                let overlapping: Vec<&NavObstacle> =
+                    obstacles.iter().filter(|o| o.contains_xz(x, z)).collect();
+                for obstacle in &overlapping {
+                    let rise = obstacle.max.y - height;
+                    if rise > 0.0 && rise <= agent.max_climb {
+                        height = obstacle.max.y;
+                        ok = true;
+                    }
+                }
+                let blocked = overlapping
+                    .iter()
+                    .any(|o| o.max.y > height + 1e-3 && o.min.y < height + agent.height);
+
+                surface[j * nx + i] = height;
+                walkable[j * nx + i] = ok && !blocked;
+            }
+        }
+
+        let open = erode(&walkable, &surface, nx, nz, agent, cell_size);
+
+        // Merge open cells into rectangles whose heights stay within half a
+        // step, so the polygons follow the ground and steps split them
+        let tolerance = agent.max_climb * 0.5;
+        let mut owner: Vec<Option<usize>> = vec![None; nx * nz];
+        for j in 0..nz {
+            for i in 0..nx {
+                if !open[j * nx + i] || owner[j * nx + i].is_some() {
+                    continue;
+                }
+                let mut low = surface[j * nx + i];
+                let mut high = low;
+                let free =
+                    |index: usize, owner: &[Option<usize>]| open[index] && owner[index].is_none();
+
+                let mut width = 1;
+                while i + width < nx && width < MAX_POLYGON_CELLS {
+                    let index = j * nx + i + width;
+                    let h = surface[index];
+                    if !free(index, &owner) || h.max(high) - h.min(low) > tolerance {
+                        break;
+                    }
+                    low = low.min(h);
+                    high = high.max(h);
+                    width += 1;
+                }
+
+                let mut depth = 1;
+                'rows: while j + depth < nz && depth < MAX_POLYGON_CELLS {
+                    let row = (j + depth) * nx;
+                    let (mut row_low, mut row_high) = (low, high);
+                    let start = row + i;
+                    for (offset, &h) in surface[start..start + width].iter().enumerate() {
+                        if !free(start + offset, &owner) {
+                            break 'rows;
+                        }
+                        row_low = row_low.min(h);
+                        row_high = row_high.max(h);
+                    }
+                    if row_high - row_low > tolerance {
+                        break;
+                    }
+                    low = row_low;
+                    high = row_high;
+                    depth += 1;
+                }
+
+                let polygon = navmesh.polygons.len();
+                for row in j..j + depth {
+                    owner[row * nx + i..row * nx + i + width].fill(Some(polygon));
+                }
+                let corner = |ci: usize, cj: usize| {
+                    let x = min.x + ci as f32 * cell_size;
+                    let z = min.y + cj as f32 * cell_size;
+                    let index = cj.min(j + depth - 1) * nx + ci.min(i + width - 1);
+                    [x, surface[index], z]
+                };
+                navmesh.polygons.push(NavPolygon {
+                    vertices: vec![
+                        corner(i, j),
+                        corner(i + width, j),
+                        corner(i + width, j + depth),
+                        corner(i, j + depth),
+                    ],
+                    neighbors: Vec::new(),
+                });
+            }
+        }
+
+        // Link polygons whose touching cells are within a step of each other
+        for j in 0..nz {
+            for i in 0..nx {
+                let index = j * nx + i;
+                let Some(a) = owner[index] else {
+                    continue;
+                };
+                let right = (i + 1 < nx).then_some(index + 1);
+                let below = (j + 1 < nz).then_some(index + nx);
+                for other in [right, below].into_iter().flatten() {
+                    let Some(b) = owner[other] else {
+                        continue;
+                    };
+                    if a != b && (surface[index] - surface[other]).abs() <= agent.max_climb {
+                        navmesh.polygons[a].neighbors.push(b);
+                        navmesh.polygons[b].neighbors.push(a);
+                    }
+                }
+            }
+        }
+        for polygon in &mut navmesh.polygons {
+            polygon.neighbors.sort_unstable();
+            polygon.neighbors.dedup();
+        }
+
+        navmesh
+    }
+
+    /// Total walkable area
+    pub fn walkable_area(&self) -> f32 {
+        self.polygons.iter().map(NavPolygon::area).sum()
+    }
+
+    /// Index of the polygon under a point, if it is walkable
+    pub fn polygon_at(&self, x: f32, z: f32) -> Option<usize> {
+        self.polygons
+            .iter()
+            .position(|polygon| polygon.contains_xz(x, z))
+    }
+
+    /// Closest walkable point to `point` and the polygon it lies in
+    pub fn nearest_point(&self, point: Vec3) -> Option<(usize, Vec3)> {
+        let mut best: Option<(f32, usize, Vec3)> = None;
+        for (index, polygon) in self.polygons.iter().enumerate() {
+            let (min, max) = polygon.bounds_xz();
+            let xz = Vec2::new(point.x, point.z).clamp(min, max);
+            let on_mesh = Vec3::new(xz.x, polygon.height_at(xz.x, xz.y), xz.y);
+            let distance = on_mesh.distance_squared(point);
+            if best.is_none_or(|(closest, _, _)| distance < closest) {
+                best = Some((distance, index, on_mesh));
+            }
+        }
+        best.map(|(_, index, point)| (index, point))
+    }
+
+    /// Shortest walkable route from `start` to `end`, both included, with a
+    /// point at each corner it turns. Points off the mesh are moved to the
+    /// nearest walkable spot. None if the two aren't connected.
+    pub fn find_path(&self, start: Vec3, end: Vec3) -> Option<Vec<Vec3>> {
+        let (from, start) = self.nearest_point(start)?;
+        let (to, end) = self.nearest_point(end)?;
+        let route = self.polygon_route(from, to)?;
+
+        let mut portals = vec![(start, start)];
+        for pair in route.windows(2) {
+            portals.push(self.portal(pair[0], pair[1])?);
+        }
+        portals.push((end, end));
+        Some(pull_string(&portals))
+    }
+
+    /// Polygons crossed on the cheapest route between two polygons (A* on
+    /// the distances between their centers)
+    fn polygon_route(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let goal = self.polygons[to].center();
+        let mut cost = vec![f32::INFINITY; self.polygons.len()];
+        let mut came_from = vec![usize::MAX; self.polygons.len()];
+        let mut open = BinaryHeap::new();
+        cost[from] = 0.0;
+        open.push(OpenPolygon {
+            estimate: self.polygons[from].center().distance(goal),
+            polygon: from,
+        });
+
+        while let Some(OpenPolygon { polygon, estimate }) = open.pop() {
+            if polygon == to {
+                let mut route = vec![to];
+                while let Some(&last) = route.last().filter(|&&last| last != from) {
+                    route.push(came_from[last]);
+                }
+                route.reverse();
+                return Some(route);
+            }
+            let center = self.polygons[polygon].center();
+            if estimate > cost[polygon] + center.distance(goal) + 1e-3 {
+                continue;
+            }
+            for &neighbor in &self.polygons[polygon].neighbors {
+                let next = self.polygons[neighbor].center();
+                let through = cost[polygon] + center.distance(next);
+                if through < cost[neighbor] {
+                    cost[neighbor] = through;
+                    came_from[neighbor] = polygon;
+                    open.push(OpenPolygon {
+                        estimate: through + next.distance(goal),
+                        polygon: neighbor,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Shared edge crossed going from polygon `a` into `b`, as its (left,
+    /// right) ends seen walking across
+    fn portal(&self, a: usize, b: usize) -> Option<(Vec3, Vec3)> {
+        let (a_min, a_max) = self.polygons[a].bounds_xz();
+        let (b_min, b_max) = self.polygons[b].bounds_xz();
+        let touching = |p: f32, q: f32| (p - q).abs() < 1e-3;
+        let (p, q) = if touching(a_max.x, b_min.x) || touching(a_min.x, b_max.x) {
+            let x = if touching(a_max.x, b_min.x) {
+                a_max.x
+            } else {
+                a_min.x
+            };
+            (
+                Vec2::new(x, a_min.y.max(b_min.y)),
+                Vec2::new(x, a_max.y.min(b_max.y)),
+            )
+        } else if touching(a_max.y, b_min.y) || touching(a_min.y, b_max.y) {
+            let z = if touching(a_max.y, b_min.y) {
+                a_max.y
+            } else {
+                a_min.y
+            };
+            (
+                Vec2::new(a_min.x.max(b_min.x), z),
+                Vec2::new(a_max.x.min(b_max.x), z),
+            )
+        } else {
+            return None;
+        };
+
+        let point = |v: Vec2| {
+            let height =
+                (self.polygons[a].height_at(v.x, v.y) + self.polygons[b].height_at(v.x, v.y)) * 0.5;
+            Vec3::new(v.x, height, v.y)
+        };
+        let center = self.polygons[a].center();
+        let across = self.polygons[b].center() - center;
+        let (p, q) = (point(p), point(q));
+        if cross_xz(center, center + across, p) > 0.0 {
+            Some((p, q))
+        } else {
+            Some((q, p))
+        }
+    }
+
+    /// Navmesh file stored next to a scene (`castle.ron` -> `castle.navmesh.json`)
+    pub fn path_for_scene(scene_path: impl AsRef<Path>) -> PathBuf {
+        scene_path.as_ref().with_extension("navmesh.json")
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let json = serde_json::to_string(self)?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write navmesh {}", path.display()))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read(path)
+            .with_context(|| format!("Failed to read navmesh {}", path.display()))?;
+        Self::from_bytes(&json)
+    }
+
+    /// Parse a saved navmesh's JSON (e.g. read from an asset pack)
+    pub fn from_bytes(json: &[u8]) -> Result<Self> {
+        Ok(serde_json::from_slice(json)?)
+    }
+}
+
+/// XZ area to bake: the terrain, or the meshes plus a margin of flat ground
+fn bake_bounds(input: &NavMeshInput) -> Option<(Vec2, Vec2)> {
+    if let Some((_, config)) = input.terrain {
+        let half = config.scale * 0.5;
+        return Some((Vec2::splat(-half), Vec2::splat(half)));
+    }
+    let xz = |v: Vec3| Vec2::new(v.x, v.z);
+    let first = input.obstacles.first()?;
+    let (min, max) = input
+        .obstacles
+        .iter()
+        .fold((xz(first.min), xz(first.max)), |(min, max), o| {
+            (min.min(xz(o.min)), max.max(xz(o.max)))
+        });
+    Some((
+        min - Vec2::splat(GROUND_MARGIN),
+        max + Vec2::splat(GROUND_MARGIN),
+    ))
+}
+
+/// Twice the signed area of the triangle seen from above; positive when `c`
+/// is left of the line from `a` to `b`
+fn cross_xz(a: Vec3, b: Vec3, c: Vec3) -> f32 {
+    (b.x - a.x) * (a.z - c.z) - (b.z - a.z) * (a.x - c.x)
+}
+
+/// Straightest line through a chain of (left, right) portals, starting and
+/// ending with single-point portals: a funnel from the last corner narrows
+/// portal by portal, and where one side crosses the other that side's end
+/// becomes the next corner.
+fn pull_string(portals: &[(Vec3, Vec3)]) -> Vec<Vec3> {
+    let mut points = vec![portals[0].0];
+    let (mut apex, mut left, mut right) = (portals[0].0, portals[0].0, portals[0].1);
+    let (mut left_index, mut right_index) = (0, 0);
+
+    let mut i = 1;
+    while i < portals.len() {
+        let (portal_left, portal_right) = portals[i];
+
+        if cross_xz(apex, right, portal_right) >= 0.0 {
+            if apex == right || cross_xz(apex, left, portal_right) < 0.0 {
+                right = portal_right;
+                right_index = i;
+            } else {
+                points.push(left);
+                apex = left;
+                right = apex;
+                right_index = left_index;
+                i = left_index + 1;
+                continue;
+            }
+        }
+
+        if cross_xz(apex, left, portal_left) <= 0.0 {
+            if apex == left || cross_xz(apex, right, portal_left) > 0.0 {
+                left = portal_left;
+                left_index = i;
+            } else {
+                points.push(right);
+                apex = right;
+                left = apex;
+                left_index = right_index;
+                i = right_index + 1;
+                continue;
+            }
+        }
+        i += 1;
+    }
+
+    let end = portals[portals.len() - 1].0;
+    if points.last() != Some(&end) {
+        points.push(end);
+    }
+    points
+}
+
+/// Remove cells closer than the agent radius to anything unwalkable: blocked
+/// cells, ledges higher than a step and the edge of the baked area
+fn erode(
+    walkable: &[bool],
+    surface: &[f32],
+    nx: usize,
+    nz: usize,
+    agent: &NavAgentSettings,
+    cell_size: f32,
+) -> Vec<bool> {
+    let reach = (agent.radius / cell_size).floor().max(0.0) as i64;
+    let reach_sq = (agent.radius / cell_size).powi(2);
+    let mut open = walkable.to_vec();
+
+    let is_ledge = |i: usize, j: usize| {
+        let height = surface[j * nx + i];
+        [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .into_iter()
+            .any(|(di, dj)| {
+                let (ni, nj) = (i as i64 + di, j as i64 + dj);
+                if ni < 0 || nj < 0 || ni as usize >= nx || nj as usize >= nz {
+                    return false;
+                }
+                let n = nj as usize * nx + ni as usize;
+                walkable[n] && (surface[n] - height).abs() > agent.max_climb
+            })
+    };
+
+    for j in 0..nz {
+        for i in 0..nx {
+            let index = j * nx + i;
+            if walkable[index] && !is_ledge(i, j) {
+                continue;
+            }
+            open[index] = false;
+            for dj in -reach..=reach {
+                for di in -reach..=reach {
+                    if (di * di + dj * dj) as f32 > reach_sq {
+                        continue;
+                    }
+                    let (ni, nj) = (i as i64 + di, j as i64 + dj);
+                    if ni >= 0 && nj >= 0 && (ni as usize) < nx && (nj as usize) < nz {
+                        open[nj as usize * nx + ni as usize] = false;
+                    }
+                }
+            }
+        }
+    }
+
+    // The edge of the area counts as a wall
+    let border = reach as usize;
+    for j in 0..nz {
+        for i in 0..nx {
+            if i < border || j < border || i + border >= nx || j + border >= nz {
+                open[j * nx + i] = false;
+            }
+        }
+    }
+    open
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat_terrain(height: f32) -> (HeightMap, TerrainConfig) {
+        let config = TerrainConfig {
+            width: 17,
+            depth: 17,
+            scale: 16.0,
+            ..Default::default()
+        };
+        let heightmap = HeightMap {
+            width: 17,
+            depth: 17,
+            heights: vec![height; 17 * 17],
+            holes: Vec::new(),
+        };
+        (heightmap, config)
+    }
+
+    #[test]
+    fn test_bake_flat_terrain_shrinks_by_agent_radius() {
+        let (heightmap, config) = flat_terrain(1.0);
+        let agent = NavAgentSettings::default();
+        let navmesh = NavMesh::bake(
+            &NavMeshInput {
+                terrain: Some((&heightmap, &config)),
+                obstacles: &[],
+            },
+            &agent,
+        );
+
+        assert!(!navmesh.polygons.is_empty());
+        // 16x16 area minus one cell of radius on each side
+        assert!((navmesh.walkable_area() - 15.0 * 15.0).abs() < 1e-3);
+        assert!(navmesh.polygon_at(0.0, 0.0).is_some());
+        assert!(navmesh.polygon_at(7.9, 0.0).is_none());
+        let polygon = &navmesh.polygons[navmesh.polygon_at(0.0, 0.0).unwrap()];
+        assert!(polygon.vertices.iter().all(|v| (v[1] - 1.0).abs() < 1e-5));
+    }
+
+    #[test]
+    fn test_bake_blocks_tall_meshes_and_walks_on_low_ones() {
+        let (heightmap, config) = flat_terrain(0.0);
+        let obstacles = [
+            // Wall the agent can't pass
+            NavObstacle {
+                min: Vec3::new(-4.0, 0.0, -4.0),
+                max: Vec3::new(-2.0, 3.0, -2.0),
+            },
+            // Low platform it can step onto
+            NavObstacle {
+                min: Vec3::new(2.0, 0.0, 2.0),
+                max: Vec3::new(6.0, 0.3, 6.0),
+            },
+        ];
+        let navmesh = NavMesh::bake(
+            &NavMeshInput {
+                terrain: Some((&heightmap, &config)),
+                obstacles: &obstacles,
+            },
+            &NavAgentSettings::default(),
+        );
+
+        assert!(navmesh.polygon_at(-3.0, -3.0).is_none());
+        let on_platform = navmesh.polygon_at(4.0, 4.0).expect("platform is walkable");
+        assert!(navmesh.polygons[on_platform]
+            .vertices
+            .iter()
+            .all(|v| (v[1] - 0.3).abs() < 1e-5));
+        assert!(!navmesh.polygons[on_platform].neighbors.is_empty());
+    }
+
+    #[test]
+    fn test_bake_rejects_steep_slopes() {
+        let (mut heightmap, config) = flat_terrain(0.0);
+        // Right half is a steep ramp (two units up per unit across)
+        for z in 0..17 {
+            for x in 9..17 {
+                heightmap.set_height(x, z, (x - 8) as f32 * 2.0);
+            }
+        }
+        let navmesh = NavMesh::bake(
+            &NavMeshInput {
+                terrain: Some((&heightmap, &config)),
+                obstacles: &[],
+            },
+            &NavAgentSettings::default(),
+        );
+        assert!(navmesh.polygon_at(-4.0, 0.0).is_some());
+        assert!(navmesh.polygon_at(4.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn test_find_path_goes_around_walls() {
+        let (heightmap, config) = flat_terrain(0.0);
+        // Wall across the middle, open only near z = -8
+        let obstacles = [NavObstacle {
+            min: Vec3::new(-1.0, 0.0, -5.0),
+            max: Vec3::new(1.0, 3.0, 8.0),
+        }];
+        let navmesh = NavMesh::bake(
+            &NavMeshInput {
+                terrain: Some((&heightmap, &config)),
+                obstacles: &obstacles,
+            },
+            &NavAgentSettings::default(),
+        );
+
+        let start = Vec3::new(-5.0, 0.0, 2.0);
+        let end = Vec3::new(5.0, 0.0, 2.0);
+        let path = navmesh.find_path(start, end).expect("both sides connect");
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&end));
+        assert!(path.iter().any(|p| p.z < -5.0));
+        assert!(path.iter().all(|p| navmesh.polygon_at(p.x, p.z).is_some()));
+        for pair in path.windows(2) {
+            // Each leg stays on one side of the wall or passes below it
+            let crosses = pair[0].x.signum() != pair[1].x.signum();
+            assert!(!crosses || pair[0].z.max(pair[1].z) < -5.0);
+        }
+
+        // Open ground is walked in a straight line
+        let straight = navmesh
+            .find_path(Vec3::new(-6.0, 0.0, 4.0), Vec3::new(-3.0, 0.0, -2.0))
+            .unwrap();
+        assert_eq!(straight.len(), 2);
+    }
+
+    #[test]
+    fn test_save_and_load_next_to_scene() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = NavMesh::path_for_scene(dir.path().join("castle.ron"));
+        assert!(path.ends_with("castle.navmesh.json"));
+
+        let navmesh = NavMesh::bake(
+            &NavMeshInput {
+                terrain: None,
+                obstacles: &[NavObstacle {
+                    min: Vec3::new(-1.0, 0.0, -1.0),
+                    max: Vec3::new(1.0, 2.0, 1.0),
+                }],
+            },
+            &NavAgentSettings::default(),
+        );
+        assert!(!navmesh.polygons.is_empty());
+        navmesh.save(&path).unwrap();
+
+        let loaded = NavMesh::load(&path).unwrap();
+        assert_eq!(loaded.agent, navmesh.agent);
+        assert_eq!(loaded.polygons.len(), navmesh.polygons.len());
+    }
+}
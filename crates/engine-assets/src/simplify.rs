@@ -0,0 +1,520 @@
+// Mesh simplification for generated LODs
+//
+// Quadric error metrics (Garland-Heckbert) with half-edge collapses: every
+// vertex sums the planes of its triangles, and the edge whose collapse moves
+// the surface least is folded onto one of its ends until the triangle budget
+// is met. Kept vertices never move, so their attributes stay exact.
+//
+// The topology is built on the positions alone: vertices split at UV or
+// normal seams (and every corner of a flat-shaded mesh) are welded into one,
+// while each triangle keeps its own corners' attributes. A corner folded
+// across an edge takes the attributes the collapsed triangles had at the
+// other end, so seams stay where they were. Vertices on open edges (mesh
+// borders) are never removed, which keeps silhouettes.
+
+use crate::mesh::Mesh;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+/// A level that keeps more than this share of the previous level's
+/// triangles isn't worth switching to
+const MIN_USEFUL_REDUCTION: f32 = 0.9;
+
+/// Smallest cosine between a triangle's normal before and after a collapse
+const MAX_NORMAL_CHANGE: f32 = 0.2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LodSettings {
+    /// LOD levels generated below the source mesh
+    pub levels: usize,
+    /// Share of the previous level's triangles each level keeps
+    pub reduction: f32,
+    /// Meshes with fewer triangles than this get no LODs
+    pub min_triangles: usize,
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            levels: 3,
+            reduction: 0.5,
+            min_triangles: 512,
+        }
+    }
+}
+
+/// Symmetric 4x4 plane quadric, upper triangle
+#[derive(Debug, Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn plane(normal: Vec3, point: Vec3, weight: f64) -> Self {
+        let (a, b, c) = (normal.x as f64, normal.y as f64, normal.z as f64);
+        let d = -normal.dot(point) as f64;
+        Self(
+            [
+                a * a,
+                a * b,
+                a * c,
+                a * d,
+                b * b,
+                b * c,
+                b * d,
+                c * c,
+                c * d,
+                d * d,
+            ]
+            .map(|q| q * weight),
+        )
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for (q, o) in self.0.iter_mut().zip(other.0) {
+            *q += o;
+        }
+    }
+
+    /// Weighted squared distance of a point to the planes
+    fn error(&self, p: Vec3) -> f64 {
+        let q = &self.0;
+        let (x, y, z) = (p.x as f64, p.y as f64, p.z as f64);
+        q[0] * x * x
+            + 2.0 * q[1] * x * y
+            + 2.0 * q[2] * x * z
+            + 2.0 * q[3] * x
+            + q[4] * y * y
+            + 2.0 * q[5] * y * z
+            + 2.0 * q[6] * y
+            + q[7] * z * z
+            + 2.0 * q[8] * z
+            + q[9]
+    }
+}
+
+/// Folding `from` onto `to`, valid while neither vertex changed since
+#[derive(Debug, Clone, Copy)]
+struct Collapse {
+    cost: f64,
+    /// Squared edge length, breaks ties so flat areas collapse evenly
+    length: f32,
+    from: u32,
+    to: u32,
+    stamps: (u32, u32),
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    // Reversed, so the heap pops the cheapest collapse
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then(other.length.total_cmp(&self.length))
+    }
+}
+
+struct Simplifier {
+    positions: Vec<Vec3>,
+    /// Welded vertices of each triangle
+    triangles: Vec<[u32; 3]>,
+    /// Source vertices each triangle takes its corners' attributes from
+    corners: Vec<[u32; 3]>,
+    alive: Vec<bool>,
+    live: usize,
+    /// Triangles around each vertex, may include dead ones
+    vertex_triangles: Vec<Vec<usize>>,
+    quadrics: Vec<Quadric>,
+    locked: Vec<bool>,
+    stamps: Vec<u32>,
+    heap: BinaryHeap<Collapse>,
+}
+
+impl Simplifier {
+    fn new(mesh: &Mesh) -> Self {
+        let vertex_count = mesh.vertices.len();
+        let positions: Vec<Vec3> = mesh.vertices.iter().map(|v| v.position).collect();
+
+        // Every vertex stands in for the first one at its position
+        let mut first_at: HashMap<[u32; 3], u32> = HashMap::new();
+        let weld: Vec<u32> = positions
+            .iter()
+            .enumerate()
+            .map(|(i, p)| *first_at.entry(p.to_array().map(f32::to_bits)).or_insert(i as u32))
+            .collect();
+        // Triangles that welded to a line or a point cover nothing
+        let (triangles, corners): (Vec<[u32; 3]>, Vec<[u32; 3]>) = mesh
+            .indices
+            .chunks_exact(3)
+            .map(|t| [t[0], t[1], t[2]])
+            .filter(|t| t.iter().all(|&i| (i as usize) < vertex_count))
+            .map(|t| (t.map(|i| weld[i as usize]), t))
+            .filter(|([a, b, c], _)| a != b && b != c && a != c)
+            .unzip();
+
+        let mut quadrics = vec![Quadric::default(); vertex_count];
+        let mut vertex_triangles = vec![Vec::new(); vertex_count];
+        let mut edge_uses: HashMap<(u32, u32), u32> = HashMap::new();
+        for (index, triangle) in triangles.iter().enumerate() {
+            let [a, b, c] = triangle.map(|i| positions[i as usize]);
+            let cross = (b - a).cross(c - a);
+            let double_area = cross.length();
+            if double_area > 0.0 {
+                let plane = Quadric::plane(cross / double_area, a, double_area as f64 * 0.5);
+                for &i in triangle {
+                    quadrics[i as usize].add(&plane);
+                }
+            }
+            for (k, &i) in triangle.iter().enumerate() {
+                vertex_triangles[i as usize].push(index);
+                *edge_uses
+                    .entry(edge_key(i, triangle[(k + 1) % 3]))
+                    .or_default() += 1;
+            }
+        }
+
+        // Open and non-manifold edges stay where they are
+        let mut locked = vec![false; vertex_count];
+        for (&(a, b), &uses) in &edge_uses {
+            if uses != 2 {
+                locked[a as usize] = true;
+                locked[b as usize] = true;
+            }
+        }
+
+        let mut simplifier = Self {
+            positions,
+            alive: vec![true; triangles.len()],
+            live: triangles.len(),
+            triangles,
+            corners,
+            vertex_triangles,
+            quadrics,
+            locked,
+            stamps: vec![0; vertex_count],
+            heap: BinaryHeap::new(),
+        };
+        for &(a, b) in edge_uses.keys() {
+            simplifier.push(a, b);
+            simplifier.push(b, a);
+        }
+        simplifier
+    }
+
+    fn push(&mut self, from: u32, to: u32) {
+        if self.locked[from as usize] {
+            return;
+        }
+        let mut quadric = self.quadrics[from as usize];
+        quadric.add(&self.quadrics[to as usize]);
+        self.heap.push(Collapse {
+            cost: quadric.error(self.positions[to as usize]),
+            length: self.positions[from as usize].distance_squared(self.positions[to as usize]),
+            from,
+            to,
+            stamps: (self.stamps[from as usize], self.stamps[to as usize]),
+        });
+    }
+
+    fn live_triangles(&self, vertex: u32) -> impl Iterator<Item = usize> + '_ {
+        self.vertex_triangles[vertex as usize]
+            .iter()
+            .copied()
+            .filter(|&t| self.alive[t])
+    }
+
+    fn neighbors(&self, vertex: u32) -> Vec<u32> {
+        let mut neighbors: Vec<u32> = self
+            .live_triangles(vertex)
+            .flat_map(|t| self.triangles[t])
+            .filter(|&i| i != vertex)
+            .collect();
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        neighbors
+    }
+
+    /// Whether folding `from` onto `to` keeps the surface manifold and
+    /// doesn't turn any triangle over
+    fn can_collapse(&self, from: u32, to: u32) -> bool {
+        // Vertices next to both ends may only be the tips of the shared triangles
+        let shared = self
+            .live_triangles(from)
+            .filter(|&t| self.triangles[t].contains(&to))
+            .count();
+        if shared == 0 {
+            return false;
+        }
+        let to_neighbors = self.neighbors(to);
+        let common = self
+            .neighbors(from)
+            .iter()
+            .filter(|n| to_neighbors.binary_search(n).is_ok())
+            .count();
+        if common != shared {
+            return false;
+        }
+
+        let target = self.positions[to as usize];
+        self.live_triangles(from)
+            .filter(|&t| !self.triangles[t].contains(&to))
+            .all(|t| {
+                let [a, b, c] = self.triangles[t].map(|i| self.positions[i as usize]);
+                let moved = self.triangles[t].map(|i| {
+                    if i == from {
+                        target
+                    } else {
+                        self.positions[i as usize]
+                    }
+                });
+                let before = (b - a).cross(c - a).normalize_or_zero();
+                let after = (moved[1] - moved[0])
+                    .cross(moved[2] - moved[0])
+                    .normalize_or_zero();
+                before.dot(after) >= MAX_NORMAL_CHANGE
+            })
+    }
+
+    fn collapse(&mut self, from: u32, to: u32) {
+        let fan = std::mem::take(&mut self.vertex_triangles[from as usize]);
+        // The attributes each corner at `from` has at `to` in the triangles
+        // that fold away, so corners on the same side of a seam stay on it
+        let mut folded: Vec<(u32, u32)> = Vec::new();
+        for &t in &fan {
+            if !self.alive[t] {
+                continue;
+            }
+            let triangle = self.triangles[t];
+            if let (Some(a), Some(b)) = (
+                triangle.iter().position(|&i| i == from),
+                triangle.iter().position(|&i| i == to),
+            ) {
+                folded.push((self.corners[t][a], self.corners[t][b]));
+                self.alive[t] = false;
+                self.live -= 1;
+            }
+        }
+        for t in fan {
+            if !self.alive[t] {
+                continue;
+            }
+            for k in 0..3 {
+                if self.triangles[t][k] == from {
+                    self.triangles[t][k] = to;
+                    let corner = self.corners[t][k];
+                    if let Some(&(_, moved)) = folded.iter().find(|(c, _)| *c == corner) {
+                        self.corners[t][k] = moved;
+                    }
+                }
+            }
+            self.vertex_triangles[to as usize].push(t);
+        }
+        let alive = &self.alive;
+        self.vertex_triangles[to as usize].retain(|&t| alive[t]);
+        let removed = self.quadrics[from as usize];
+        self.quadrics[to as usize].add(&removed);
+        self.stamps[from as usize] += 1;
+        self.stamps[to as usize] += 1;
+
+        for neighbor in self.neighbors(to) {
+            self.push(to, neighbor);
+            self.push(neighbor, to);
+        }
+    }
+
+    fn run(&mut self, target_triangles: usize) {
+        while self.live > target_triangles {
+            let Some(collapse) = self.heap.pop() else {
+                break;
+            };
+            let (from, to) = (collapse.from, collapse.to);
+            if collapse.stamps != (self.stamps[from as usize], self.stamps[to as usize]) {
+                continue;
+            }
+            if self.can_collapse(from, to) {
+                self.collapse(from, to);
+            }
+        }
+    }
+}
+
+fn edge_key(a: u32, b: u32) -> (u32, u32) {
+    (a.min(b), a.max(b))
+}
+
+/// `mesh` reduced to about `target_triangles`. Fewer may be left out if the
+/// locked border vertices don't allow more collapses.
+pub fn simplify_mesh(mesh: &Mesh, target_triangles: usize) -> Mesh {
+    if mesh.indices.len() / 3 <= target_triangles {
+        return mesh.clone();
+    }
+    let mut simplifier = Simplifier::new(mesh);
+    simplifier.run(target_triangles);
+
+    // One vertex per position and attributes the remaining triangles use
+    let mut remap: HashMap<(u32, u32), u32> = HashMap::new();
+    let mut vertices = Vec::new();
+    let mut indices = Vec::with_capacity(simplifier.live * 3);
+    for t in (0..simplifier.triangles.len()).filter(|&t| simplifier.alive[t]) {
+        for (&position, &corner) in simplifier.triangles[t].iter().zip(&simplifier.corners[t]) {
+            let index = *remap.entry((position, corner)).or_insert_with(|| {
+                let mut vertex = mesh.vertices[corner as usize].clone();
+                vertex.position = simplifier.positions[position as usize];
+                vertices.push(vertex);
+                vertices.len() as u32 - 1
+            });
+            indices.push(index);
+        }
+    }
+    Mesh::new(mesh.name.clone(), vertices, indices)
+}
+
+/// Simplified copies of `mesh` for LOD 1 onwards, each made from the level
+/// before and keeping `reduction` of its triangles. Stops early once a level
+/// would barely be any lighter.
+pub fn generate_lods(mesh: &Mesh, settings: &LodSettings) -> Vec<Mesh> {
+    let mut lods: Vec<Mesh> = Vec::new();
+    if mesh.indices.len() / 3 < settings.min_triangles {
+        return lods;
+    }
+    for level in 1..=settings.levels {
+        let source = lods.last().unwrap_or(mesh);
+        let previous = source.indices.len() / 3;
+        let target = previous as f32 * settings.reduction.clamp(0.0, 1.0);
+        let mut lod = simplify_mesh(source, target as usize);
+        let triangles = lod.indices.len() / 3;
+        if triangles == 0 || triangles as f32 > previous as f32 * MIN_USEFUL_REDUCTION {
+            break;
+        }
+        lod.name = format!("{} LOD{}", mesh.name, level);
+        lods.push(lod);
+    }
+    lods
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::Vertex;
+    use glam::Vec2;
+
+    /// Flat `cells` x `cells` grid of unit quads in the XZ plane
+    fn grid(cells: u32) -> Mesh {
+        let side = cells + 1;
+        let vertices = (0..side * side)
+            .map(|i| Vertex::new(Vec3::new((i % side) as f32, 0.0, (i / side) as f32)))
+            .collect();
+        let mut indices = Vec::new();
+        for z in 0..cells {
+            for x in 0..cells {
+                let i = z * side + x;
+                indices.extend([i, i + side, i + 1, i + 1, i + side, i + side + 1]);
+            }
+        }
+        Mesh::new("grid".to_string(), vertices, indices)
+    }
+
+    /// The grid with every quad given its own four vertices, as a
+    /// flat-shaded or fully UV-split mesh would be
+    fn split_grid(cells: u32) -> Mesh {
+        let source = grid(cells);
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for quad in source.indices.chunks_exact(6) {
+            let base = vertices.len() as u32;
+            // Corners i, i + side, i + 1, i + side + 1
+            for &i in &[quad[0], quad[1], quad[2], quad[5]] {
+                let position = source.vertices[i as usize].position;
+                vertices.push(Vertex::new(position).with_tex_coord(Vec2::new(base as f32, 0.0)));
+            }
+            indices.extend([base, base + 1, base + 2, base + 2, base + 1, base + 3]);
+        }
+        Mesh::new("split".to_string(), vertices, indices)
+    }
+
+    fn area_and_facing(mesh: &Mesh) -> (f32, bool) {
+        let mut area = 0.0;
+        let mut facing_up = true;
+        for t in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [t[0], t[1], t[2]].map(|i| mesh.vertices[i as usize].position);
+            let cross = (b - a).cross(c - a);
+            area += cross.length() * 0.5;
+            facing_up &= cross.y > 0.0;
+        }
+        (area, facing_up)
+    }
+
+    #[test]
+    fn test_flat_grid_collapses_to_its_border() {
+        let mesh = grid(16);
+        let simplified = simplify_mesh(&mesh, 0);
+        assert!(simplified.indices.len() / 3 < 100);
+        // The border stays whole and nearly all of the inside folds away
+        let border = simplified
+            .vertices
+            .iter()
+            .filter(|v| {
+                [v.position.x, v.position.z]
+                    .iter()
+                    .any(|&c| c == 0.0 || c == 16.0)
+            })
+            .count();
+        assert_eq!(border, 64);
+        assert!(simplified.vertices.len() < 70);
+
+        let (area, facing_up) = area_and_facing(&simplified);
+        assert!((area - 256.0).abs() < 1e-3);
+        assert!(facing_up);
+    }
+
+    #[test]
+    fn test_lod_levels_get_lighter() {
+        let mesh = grid(16);
+        let lods = generate_lods(&mesh, &LodSettings::default());
+        assert!(!lods.is_empty());
+        let mut previous = mesh.indices.len();
+        for (level, lod) in lods.iter().enumerate() {
+            assert_eq!(lod.name, format!("grid LOD{}", level + 1));
+            assert!(lod.indices.len() < previous);
+            previous = lod.indices.len();
+            let (area, facing_up) = area_and_facing(lod);
+            assert!((area - 256.0).abs() < 1e-3);
+            assert!(facing_up);
+        }
+        // Nothing to gain past the border
+        assert!(simplify_mesh(&mesh, 0).indices.len() <= previous);
+
+        // Small meshes aren't worth it
+        assert!(generate_lods(&grid(4), &LodSettings::default()).is_empty());
+    }
+
+    #[test]
+    fn test_split_vertices_still_simplify() {
+        let mesh = split_grid(16);
+        let lods = generate_lods(&mesh, &LodSettings::default());
+        assert!(!lods.is_empty());
+        let last = lods.last().unwrap();
+        assert!(last.indices.len() * 4 <= mesh.indices.len());
+        let (area, facing_up) = area_and_facing(last);
+        assert!((area - 256.0).abs() < 1e-3);
+        assert!(facing_up);
+
+        // Every corner keeps attributes from the source, never blended
+        let source_uvs: Vec<Vec2> = mesh.vertices.iter().map(|v| v.tex_coord).collect();
+        assert!(last.vertices.iter().all(|v| source_uvs.contains(&v.tex_coord)));
+    }
+}
@@ -0,0 +1,230 @@
+// Spline terrain shaping - riverbeds, road beds and the meshes along them
+//
+// Paths are curves already sampled into closely spaced world-space points.
+// Carving pulls each heightmap point near a path toward the path's height at
+// the closest spot, fully within half the width and fading out over the
+// falloff. Ribbon meshes run along a path with v counting world units down
+// its length, so a scrolling water texture flows from the first point on.
+
+use crate::mesh::{Mesh, Vertex};
+use crate::terrain::HeightMap;
+use glam::{Vec2, Vec3};
+
+/// Road surfaces sit this far above the ground to avoid z-fighting
+const ROAD_LIFT: f32 = 0.05;
+
+/// Make a river path run downhill: no point is higher than the one before
+pub fn flow_downhill(path: &mut [Vec3]) {
+    let mut lowest = f32::MAX;
+    for point in path {
+        lowest = lowest.min(point.y);
+        point.y = lowest;
+    }
+}
+
+impl HeightMap {
+    /// Pull the terrain toward the path's height within `half_width` of it,
+    /// blending back over `falloff`. With `lower_only` nothing is raised.
+    /// Returns true if any height changed.
+    pub fn carve_path(
+        &mut self,
+        path: &[Vec3],
+        half_width: f32,
+        falloff: f32,
+        scale: f32,
+        lower_only: bool,
+    ) -> bool {
+        let reach = half_width + falloff.max(0.0);
+        // Closest path distance and height for each grid point in reach
+        let mut nearest: Vec<Option<(f32, f32)>> = vec![None; self.heights.len()];
+        let segments: Vec<(Vec3, Vec3)> = match path {
+            [] => return false,
+            [point] => vec![(*point, *point)],
+            _ => path.windows(2).map(|pair| (pair[0], pair[1])).collect(),
+        };
+
+        for (a, b) in segments {
+            let min = a.min(b) - Vec3::splat(reach);
+            let max = a.max(b) + Vec3::splat(reach);
+            let (min_x, min_z) = self.world_to_grid(min.x, min.z, scale);
+            let (max_x, max_z) = self.world_to_grid(max.x, max.z, scale);
+            let x_range = (min_x.floor().max(0.0) as usize)
+                ..=(max_x.ceil().max(0.0) as usize).min(self.width - 1);
+            let z_range = (min_z.floor().max(0.0) as usize)
+                ..=(max_z.ceil().max(0.0) as usize).min(self.depth - 1);
+
+            let (a_xz, ab) = (Vec2::new(a.x, a.z), Vec2::new(b.x - a.x, b.z - a.z));
+            for z in z_range {
+                for x in x_range.clone() {
+                    let point = Vec2::new(
+                        x as f32 * scale / self.width as f32 - scale * 0.5,
+                        z as f32 * scale / self.depth as f32 - scale * 0.5,
+                    );
+                    let t = if ab.length_squared() > 0.0 {
+                        ((point - a_xz).dot(ab) / ab.length_squared()).clamp(0.0, 1.0)
+                    } else {
+                        0.0
+                    };
+                    let distance = point.distance(a_xz + ab * t);
+                    let slot = &mut nearest[z * self.width + x];
+                    if distance <= reach && slot.is_none_or(|(closest, _)| distance < closest) {
+                        *slot = Some((distance, a.y + (b.y - a.y) * t));
+                    }
+                }
+            }
+        }
+
+        let mut modified = false;
+        for (height, nearest) in self.heights.iter_mut().zip(nearest) {
+            let Some((distance, target)) = nearest else {
+                continue;
+            };
+            let weight = if distance <= half_width {
+                1.0
+            } else {
+                let t = 1.0 - (distance - half_width) / falloff;
+                t * t * (3.0 - 2.0 * t)
+            };
+            let mut carved = *height + (target - *height) * weight;
+            if lower_only {
+                carved = carved.min(*height);
+            }
+            if carved != *height {
+                *height = carved;
+                modified = true;
+            }
+        }
+        modified
+    }
+}
+
+/// Flat water surface along a river path, at the path's height
+pub fn river_mesh(name: &str, path: &[Vec3], width: f32) -> Mesh {
+    ribbon(name, path, width, 1, |point| (point.y, Vec3::Y))
+}
+
+/// Road surface along a path, following the terrain across its width
+pub fn road_mesh(name: &str, path: &[Vec3], width: f32, heightmap: &HeightMap, scale: f32) -> Mesh {
+    let step = scale / heightmap.width as f32;
+    let ground = |x: f32, z: f32| heightmap.sample_height(x, z, scale);
+    // Enough columns across that the strip bends with the ground
+    let across = ((width / step).ceil() as usize).clamp(1, 16);
+    ribbon(name, path, width, across, |point| {
+        let normal = Vec3::new(
+            ground(point.x - step, point.z) - ground(point.x + step, point.z),
+            2.0 * step,
+            ground(point.x, point.z - step) - ground(point.x, point.z + step),
+        )
+        .normalize();
+        (ground(point.x, point.z) + ROAD_LIFT, normal)
+    })
+}
+
+/// A strip `across` quads wide along the path; `surface` gives each vertex's
+/// height and normal from its position
+fn ribbon(
+    name: &str,
+    path: &[Vec3],
+    width: f32,
+    across: usize,
+    surface: impl Fn(Vec3) -> (f32, Vec3),
+) -> Mesh {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    if path.len() < 2 {
+        return Mesh::new(name.to_string(), vertices, indices);
+    }
+
+    let mut distance = 0.0;
+    for (i, &point) in path.iter().enumerate() {
+        if i > 0 {
+            distance += Vec2::new(point.x - path[i - 1].x, point.z - path[i - 1].z).length();
+        }
+        let ahead = path[(i + 1).min(path.len() - 1)] - path[i.saturating_sub(1)];
+        let tangent = Vec3::new(ahead.x, 0.0, ahead.z).normalize_or(Vec3::X);
+        let side = tangent.cross(Vec3::Y);
+        for j in 0..=across {
+            let offset = (j as f32 / across as f32 - 0.5) * width;
+            let mut position = point + side * offset;
+            let (height, normal) = surface(position);
+            position.y = height;
+            vertices.push(
+                Vertex::new(position)
+                    .with_normal(normal)
+                    .with_tex_coord(Vec2::new(
+                        j as f32 / across as f32,
+                        distance / width.max(0.01),
+                    ))
+                    .with_color(Vec3::ONE),
+            );
+        }
+    }
+
+    let row = across as u32 + 1;
+    for i in 0..path.len() as u32 - 1 {
+        for j in 0..across as u32 {
+            let here = i * row + j;
+            let next = here + row;
+            indices.extend_from_slice(&[here, here + 1, next, here + 1, next + 1, next]);
+        }
+    }
+    Mesh::new(name.to_string(), vertices, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat(size: usize, height: f32) -> HeightMap {
+        HeightMap {
+            width: size,
+            depth: size,
+            heights: vec![height; size * size],
+            holes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_carve_path_cuts_a_channel() {
+        // 32 points over 32 units: grid x = world x + 16
+        let mut heightmap = flat(32, 5.0);
+        let mut path = vec![Vec3::new(-10.0, 4.0, 0.0), Vec3::new(10.0, 2.0, 0.0)];
+        flow_downhill(&mut path);
+        assert!(heightmap.carve_path(&path, 2.0, 2.0, 32.0, true));
+
+        // Full depth on the path, blended on the banks, untouched past them
+        assert!((heightmap.get_height(16, 16) - 3.0).abs() < 1e-4);
+        let bank = heightmap.get_height(16, 19);
+        assert!(bank > 3.0 && bank < 5.0);
+        assert_eq!(heightmap.get_height(16, 21), 5.0);
+        assert_eq!(heightmap.get_height(30, 16), 5.0);
+
+        // Lower-only carving never raises the ground
+        let mut low = flat(32, 1.0);
+        assert!(!low.carve_path(&path, 2.0, 2.0, 32.0, true));
+    }
+
+    #[test]
+    fn test_ribbons_face_up_and_flow_along_the_path() {
+        let path = [
+            Vec3::ZERO,
+            Vec3::new(0.0, 0.0, 4.0),
+            Vec3::new(3.0, 0.0, 8.0),
+        ];
+        let river = river_mesh("river", &path, 2.0);
+        assert_eq!(river.vertices.len(), 6);
+        for triangle in river.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|k| river.vertices[triangle[k] as usize].position);
+            assert!((b - a).cross(c - a).y > 0.0);
+        }
+        let v = |i: usize| river.vertices[i].tex_coord[1];
+        assert!(v(0) < v(2) && v(2) < v(4));
+
+        let heightmap = flat(17, 2.0);
+        let road = road_mesh("road", &path, 2.0, &heightmap, 32.0);
+        assert!(road
+            .vertices
+            .iter()
+            .all(|vertex| (vertex.position[1] - 2.0 - ROAD_LIFT).abs() < 1e-4));
+    }
+}
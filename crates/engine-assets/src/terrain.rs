@@ -1,8 +1,11 @@
 // Terrain generation with height maps
 
+use crate::erosion::ErosionSettings;
 use crate::mesh::{Mesh, Vertex};
+use anyhow::{bail, Context, Result};
 use glam::{Vec2, Vec3};
 use noise::{NoiseFn, Perlin, Seedable};
+use std::path::{Path, PathBuf};
 
 #[derive(Clone)]
 pub struct TerrainConfig {
@@ -33,10 +36,14 @@ impl Default for TerrainConfig {
     }
 }
 
+#[derive(Clone)]
 pub struct HeightMap {
     pub width: usize,
     pub depth: usize,
     pub heights: Vec<f32>,
+    /// Cells cut out of the surface for caves and tunnels, indexed like
+    /// `heights` by each cell's corner with the lowest x and z. Empty if none.
+    pub holes: Vec<bool>,
 }
 
 impl HeightMap {
@@ -76,6 +83,7 @@ impl HeightMap {
             width: config.width,
             depth: config.depth,
             heights,
+            holes: Vec::new(),
         }
     }
 
@@ -146,6 +154,7 @@ impl HeightMap {
             width: config.width,
             depth: config.depth,
             heights,
+            holes: Vec::new(),
         }
     }
 
@@ -190,6 +199,7 @@ impl HeightMap {
             width: config.width,
             depth: config.depth,
             heights,
+            holes: Vec::new(),
         }
     }
 
@@ -229,6 +239,74 @@ impl HeightMap {
         h0 * (1.0 - fz) + h1 * fz
     }
 
+    /// Whether the cell between grid points (x, z) and (x + 1, z + 1) is cut out
+    pub fn is_hole(&self, x: usize, z: usize) -> bool {
+        x < self.width
+            && z < self.depth
+            && self.holes.get(z * self.width + x).copied().unwrap_or(false)
+    }
+
+    /// Cut out (or restore) the cell at (x, z)
+    pub fn set_hole(&mut self, x: usize, z: usize, hole: bool) {
+        if x >= self.width || z >= self.depth {
+            return;
+        }
+        if self.holes.len() != self.heights.len() {
+            if !hole {
+                return;
+            }
+            self.holes = vec![false; self.heights.len()];
+        }
+        self.holes[z * self.width + x] = hole;
+    }
+
+    pub fn has_holes(&self) -> bool {
+        self.holes.iter().any(|&hole| hole)
+    }
+
+    /// Whether any cell from (min_x, min_z) up to but not including
+    /// (max_x, max_z) is cut out
+    pub fn region_has_hole(&self, min_x: usize, min_z: usize, max_x: usize, max_z: usize) -> bool {
+        !self.holes.is_empty() && (min_z..max_z).any(|z| (min_x..max_x).any(|x| self.is_hole(x, z)))
+    }
+
+    /// Whether a world position falls in a hole
+    pub fn hole_at(&self, world_x: f32, world_z: f32, scale: f32) -> bool {
+        let (grid_x, grid_z) = self.world_to_grid(world_x, world_z, scale);
+        grid_x >= 0.0 && grid_z >= 0.0 && self.is_hole(grid_x as usize, grid_z as usize)
+    }
+
+    /// Cut out (or restore) the cells whose centers are under a circular
+    /// brush. Returns true if any cell changed.
+    pub fn paint_holes(
+        &mut self,
+        world_x: f32,
+        world_z: f32,
+        scale: f32,
+        radius: f32,
+        hole: bool,
+    ) -> bool {
+        let Some((min_x, min_z, max_x, max_z)) = self.brush_bounds(world_x, world_z, scale, radius)
+        else {
+            return false;
+        };
+        let (center_x, center_z) = self.world_to_grid(world_x, world_z, scale);
+        let grid_radius = (radius / scale) * self.width as f32;
+
+        let mut modified = false;
+        for z in min_z..=max_z {
+            for x in min_x..=max_x {
+                let dx = x as f32 + 0.5 - center_x;
+                let dz = z as f32 + 0.5 - center_z;
+                if dx * dx + dz * dz <= grid_radius * grid_radius && self.is_hole(x, z) != hole {
+                    self.set_hole(x, z, hole);
+                    modified = true;
+                }
+            }
+        }
+        modified
+    }
+
     /// Set height at grid position
     pub fn set_height(&mut self, x: usize, z: usize, height: f32) {
         if x < self.width && z < self.depth {
@@ -243,9 +321,32 @@ impl HeightMap {
         (grid_x, grid_z)
     }
 
+    /// Grid cells a brush at world position can touch, as the inclusive
+    /// rectangle (min_x, min_z, max_x, max_z). None if the brush is off the map.
+    pub fn brush_bounds(
+        &self,
+        world_x: f32,
+        world_z: f32,
+        scale: f32,
+        radius: f32,
+    ) -> Option<(usize, usize, usize, usize)> {
+        let (center_x, center_z) = self.world_to_grid(world_x, world_z, scale);
+        let grid_radius = (radius / scale) * self.width as f32;
+
+        let min_x = ((center_x - grid_radius).floor() as i32).max(0);
+        let max_x = ((center_x + grid_radius).ceil() as i32).min(self.width as i32 - 1);
+        let min_z = ((center_z - grid_radius).floor() as i32).max(0);
+        let max_z = ((center_z + grid_radius).ceil() as i32).min(self.depth as i32 - 1);
+        if min_x > max_x || min_z > max_z {
+            return None;
+        }
+        Some((min_x as usize, min_z as usize, max_x as usize, max_z as usize))
+    }
+
     /// Apply a brush operation at world position
-    /// brush_mode: 0=raise, 1=lower, 2=smooth, 3=flatten
-    /// Returns true if any heights were modified
+    /// brush_mode: 0=raise, 1=lower, 2=smooth, 3=flatten, 4=erode,
+    /// 5=cut holes, 6=fill holes
+    /// Returns true if any heights (or holes) were modified
     pub fn apply_brush(
         &mut self,
         world_x: f32,
@@ -255,6 +356,14 @@ impl HeightMap {
         strength: f32,
         brush_mode: u8,
     ) -> bool {
+        if brush_mode == 4 {
+            let settings = ErosionSettings::default();
+            return self.erode_brush(world_x, world_z, scale, radius, strength, &settings);
+        }
+        if brush_mode == 5 || brush_mode == 6 {
+            return self.paint_holes(world_x, world_z, scale, radius, brush_mode == 5);
+        }
+
         // Convert world position to grid position
         let (center_x, center_z) = self.world_to_grid(world_x, world_z, scale);
 
@@ -262,10 +371,11 @@ impl HeightMap {
         let grid_radius = (radius / scale) * self.width as f32;
 
         // Calculate affected grid cells
-        let min_x = ((center_x - grid_radius).floor() as i32).max(0) as usize;
-        let max_x = ((center_x + grid_radius).ceil() as i32).min(self.width as i32 - 1) as usize;
-        let min_z = ((center_z - grid_radius).floor() as i32).max(0) as usize;
-        let max_z = ((center_z + grid_radius).ceil() as i32).min(self.depth as i32 - 1) as usize;
+        let Some((min_x, min_z, max_x, max_z)) =
+            self.brush_bounds(world_x, world_z, scale, radius)
+        else {
+            return false;
+        };
 
         // For flatten mode, get the center height first
         let flatten_height = if brush_mode == 3 {
@@ -359,6 +469,242 @@ impl HeightMap {
     }
 }
 
+/// Most layers a splat map (and the terrain shader) blends between
+pub const MAX_TERRAIN_LAYERS: usize = 8;
+
+/// A paintable terrain surface layer
+#[derive(Debug, Clone)]
+pub struct TerrainLayer {
+    pub name: String,
+    /// Tint applied to the terrain where this layer is painted
+    pub color: [f32; 3],
+    /// Albedo texture tiled across the ground (asset path), tinted by `color`
+    pub albedo_texture: Option<String>,
+    /// Tangent space normal map tiled with the albedo (asset path)
+    pub normal_texture: Option<String>,
+    /// World units covered by one repeat of the textures
+    pub tiling: f32,
+}
+
+impl TerrainLayer {
+    pub fn new(name: &str, color: [f32; 3]) -> Self {
+        Self {
+            name: name.to_string(),
+            color,
+            albedo_texture: None,
+            normal_texture: None,
+            tiling: 4.0,
+        }
+    }
+
+    pub fn with_textures(mut self, albedo: Option<&str>, normal: Option<&str>) -> Self {
+        self.albedo_texture = albedo.map(str::to_string);
+        self.normal_texture = normal.map(str::to_string);
+        self
+    }
+
+    pub fn with_tiling(mut self, tiling: f32) -> Self {
+        self.tiling = tiling;
+        self
+    }
+
+    /// Built-in layers; layer 0 is the untinted base surface
+    pub fn defaults() -> Vec<TerrainLayer> {
+        vec![
+            TerrainLayer::new("Base", [1.0, 1.0, 1.0])
+                .with_textures(Some("textures/grass.png"), None),
+            TerrainLayer::new("Grass", [0.35, 0.55, 0.2])
+                .with_textures(Some("textures/grass.png"), None),
+            TerrainLayer::new("Rock", [0.45, 0.43, 0.4])
+                .with_textures(Some("textures/stone_bricks.png"), None)
+                .with_tiling(6.0),
+            TerrainLayer::new("Dirt", [0.45, 0.32, 0.2]),
+            TerrainLayer::new("Sand", [0.85, 0.78, 0.55]),
+        ]
+    }
+}
+
+const SPLAT_MAGIC: &[u8; 4] = b"SPLT";
+
+/// Per-vertex layer weights on the same grid as a height map.
+/// The weights at each grid point always sum to 1.
+#[derive(Debug, Clone)]
+pub struct SplatMap {
+    pub width: usize,
+    pub depth: usize,
+    pub layer_count: usize,
+    /// `layer_count` weights per grid point, row-major
+    pub weights: Vec<f32>,
+}
+
+impl SplatMap {
+    /// Create a splat map fully covered by layer 0, with at most
+    /// `MAX_TERRAIN_LAYERS` layers
+    pub fn new(width: usize, depth: usize, layer_count: usize) -> Self {
+        let layer_count = layer_count.clamp(1, MAX_TERRAIN_LAYERS);
+        let mut weights = vec![0.0; width * depth * layer_count];
+        for point in weights.chunks_mut(layer_count) {
+            point[0] = 1.0;
+        }
+        Self {
+            width,
+            depth,
+            layer_count,
+            weights,
+        }
+    }
+
+    /// Layer weights at grid position
+    pub fn weights(&self, x: usize, z: usize) -> &[f32] {
+        let start = (z * self.width + x) * self.layer_count;
+        &self.weights[start..start + self.layer_count]
+    }
+
+    /// Layer colors blended by the weights at grid position
+    pub fn blend_color(&self, x: usize, z: usize, layers: &[TerrainLayer]) -> Vec3 {
+        self.weights(x, z)
+            .iter()
+            .zip(layers)
+            .fold(Vec3::ZERO, |color, (weight, layer)| {
+                color + Vec3::from(layer.color) * *weight
+            })
+    }
+
+    /// Weights as two RGBA8 images on the grid: layers 0-3, then 4-7
+    pub fn pack_rgba8(&self) -> [Vec<u8>; 2] {
+        let mut images = [
+            vec![0u8; self.width * self.depth * 4],
+            vec![0u8; self.width * self.depth * 4],
+        ];
+        for (i, point) in self.weights.chunks(self.layer_count).enumerate() {
+            for (layer, weight) in point.iter().enumerate() {
+                images[layer / 4][i * 4 + layer % 4] =
+                    (weight.clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+        }
+        images
+    }
+
+    /// Splat map file stored next to a scene (`castle.ron` -> `castle.splat`)
+    pub fn path_for_scene(scene_path: impl AsRef<Path>) -> PathBuf {
+        scene_path.as_ref().with_extension("splat")
+    }
+
+    /// Write the weights as `SPLT`, then width, depth and layer count as
+    /// little-endian u32, then the weights as little-endian f32
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut bytes = Vec::with_capacity(16 + self.weights.len() * 4);
+        bytes.extend_from_slice(SPLAT_MAGIC);
+        for value in [self.width, self.depth, self.layer_count] {
+            bytes.extend_from_slice(&(value as u32).to_le_bytes());
+        }
+        for weight in &self.weights {
+            bytes.extend_from_slice(&weight.to_le_bytes());
+        }
+        std::fs::write(path, bytes)
+            .with_context(|| format!("Failed to write splat map {}", path.display()))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read splat map {}", path.display()))?;
+        Self::from_bytes(&bytes).with_context(|| format!("Splat map {}", path.display()))
+    }
+
+    /// Parse a splat map file's contents (e.g. read from an asset pack)
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 16 || &bytes[..4] != SPLAT_MAGIC {
+            bail!("Not a splat map");
+        }
+        let header = |i: usize| u32::from_le_bytes(bytes[4 + i * 4..8 + i * 4].try_into().unwrap()) as usize;
+        let (width, depth, layer_count) = (header(0), header(1), header(2));
+        if !(1..=MAX_TERRAIN_LAYERS).contains(&layer_count)
+            || bytes.len() != 16 + width * depth * layer_count * 4
+        {
+            bail!("Splat map is truncated or corrupt");
+        }
+        let weights = bytes[16..]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        Ok(Self {
+            width,
+            depth,
+            layer_count,
+            weights,
+        })
+    }
+
+    /// Paint `layer` at world position, blending toward it by `opacity` with
+    /// a falloff from the center. `falloff` is 0 for a hard edge and 1 for a
+    /// fade across the whole radius. Returns true if any weights were modified.
+    #[allow(clippy::too_many_arguments)]
+    pub fn paint(
+        &mut self,
+        world_x: f32,
+        world_z: f32,
+        scale: f32,
+        radius: f32,
+        layer: usize,
+        opacity: f32,
+        falloff: f32,
+    ) -> bool {
+        if layer >= self.layer_count || self.width == 0 || self.depth == 0 {
+            return false;
+        }
+
+        // Same grid mapping as HeightMap::world_to_grid
+        let center_x = (world_x / scale) * self.width as f32 + (self.width as f32 * 0.5);
+        let center_z = (world_z / scale) * self.depth as f32 + (self.depth as f32 * 0.5);
+        let grid_radius = (radius / scale) * self.width as f32;
+        let hard_radius = grid_radius * (1.0 - falloff.clamp(0.0, 1.0));
+
+        let min_x = ((center_x - grid_radius).floor() as i32).max(0) as usize;
+        let max_x = ((center_x + grid_radius).ceil() as i32).min(self.width as i32 - 1);
+        let min_z = ((center_z - grid_radius).floor() as i32).max(0) as usize;
+        let max_z = ((center_z + grid_radius).ceil() as i32).min(self.depth as i32 - 1);
+        if max_x < 0 || max_z < 0 {
+            return false;
+        }
+
+        let mut modified = false;
+        for z in min_z..=max_z as usize {
+            for x in min_x..=max_x as usize {
+                let dx = x as f32 - center_x;
+                let dz = z as f32 - center_z;
+                let dist = (dx * dx + dz * dz).sqrt();
+                if dist > grid_radius {
+                    continue;
+                }
+
+                let edge = if dist <= hard_radius {
+                    1.0
+                } else {
+                    let t = 1.0 - (dist - hard_radius) / (grid_radius - hard_radius);
+                    t * t
+                };
+                let amount = (opacity * edge).clamp(0.0, 1.0);
+                if amount <= 0.0 {
+                    continue;
+                }
+
+                // Move every weight toward the target layer; the sum stays 1
+                let start = (z * self.width + x) * self.layer_count;
+                let point = &mut self.weights[start..start + self.layer_count];
+                for (i, weight) in point.iter_mut().enumerate() {
+                    let target = if i == layer { 1.0 } else { 0.0 };
+                    *weight += (target - *weight) * amount;
+                }
+                modified = true;
+            }
+        }
+
+        modified
+    }
+}
+
 pub struct Terrain;
 
 impl Terrain {
@@ -367,36 +713,19 @@ impl Terrain {
         let mut vertices = Vec::new();
         let mut indices = Vec::new();
 
-        let cell_size = config.scale / config.width as f32;
-        let offset_x = -config.scale * 0.5;
-        let offset_z = -config.scale * 0.5;
-
         // Generate vertices
         for z in 0..config.depth {
             for x in 0..config.width {
-                let world_x = offset_x + x as f32 * cell_size;
-                let world_z = offset_z + z as f32 * cell_size;
-                let height = height_map.get_height(x, z);
-
-                // Calculate normal using neighboring heights
-                let normal = Self::calculate_normal(height_map, x, z, cell_size);
-
-                // UV coordinates
-                let u = x as f32 / (config.width - 1) as f32;
-                let v = z as f32 / (config.depth - 1) as f32;
-
-                vertices.push(
-                    Vertex::new(Vec3::new(world_x, height, world_z))
-                        .with_normal(normal)
-                        .with_tex_coord(Vec2::new(u, v))
-                        .with_color(Vec3::ONE),
-                );
+                vertices.push(Self::grid_vertex(height_map, config, x, z));
             }
         }
 
-        // Generate indices (two triangles per quad)
+        // Generate indices (two triangles per quad, none over holes)
         for z in 0..(config.depth - 1) {
             for x in 0..(config.width - 1) {
+                if height_map.is_hole(x, z) {
+                    continue;
+                }
                 let top_left = (z * config.width + x) as u32;
                 let top_right = top_left + 1;
                 let bottom_left = ((z + 1) * config.width + x) as u32;
@@ -417,12 +746,55 @@ impl Terrain {
         Mesh::new("Terrain".to_string(), vertices, indices)
     }
 
+    /// Generate terrain mesh from an existing height map, tinted by the
+    /// splat map's painted layers
+    pub fn generate_mesh_with_splatmap(
+        height_map: &HeightMap,
+        config: &TerrainConfig,
+        splat_map: &SplatMap,
+        layers: &[TerrainLayer],
+    ) -> Mesh {
+        let mut mesh = Self::generate_mesh_from_heightmap(height_map, config);
+        if splat_map.width == config.width && splat_map.depth == config.depth {
+            for (i, vertex) in mesh.vertices.iter_mut().enumerate() {
+                let color = splat_map.blend_color(i % config.width, i / config.width, layers);
+                vertex.color = Some(color);
+            }
+        }
+        mesh
+    }
+
     /// Generate terrain mesh from height map (generates a new heightmap)
     pub fn generate_mesh(config: &TerrainConfig) -> Mesh {
         let height_map = HeightMap::generate(config);
         Self::generate_mesh_from_heightmap(&height_map, config)
     }
 
+    /// The mesh vertex at a height map grid point
+    pub(crate) fn grid_vertex(
+        height_map: &HeightMap,
+        config: &TerrainConfig,
+        x: usize,
+        z: usize,
+    ) -> Vertex {
+        let cell_size = config.scale / config.width as f32;
+        let world_x = -config.scale * 0.5 + x as f32 * cell_size;
+        let world_z = -config.scale * 0.5 + z as f32 * cell_size;
+        let height = height_map.get_height(x, z);
+
+        // Calculate normal using neighboring heights
+        let normal = Self::calculate_normal(height_map, x, z, cell_size);
+
+        // UV coordinates
+        let u = x as f32 / (config.width - 1) as f32;
+        let v = z as f32 / (config.depth - 1) as f32;
+
+        Vertex::new(Vec3::new(world_x, height, world_z))
+            .with_normal(normal)
+            .with_tex_coord(Vec2::new(u, v))
+            .with_color(Vec3::ONE)
+    }
+
     /// Calculate vertex normal using finite differences
     fn calculate_normal(height_map: &HeightMap, x: usize, z: usize, cell_size: f32) -> Vec3 {
         let h_center = height_map.get_height(x, z);
@@ -457,3 +829,98 @@ impl Terrain {
         dx.cross(dz).normalize()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weight_sums_to_one(splat_map: &SplatMap) -> bool {
+        splat_map
+            .weights
+            .chunks(splat_map.layer_count)
+            .all(|point| (point.iter().sum::<f32>() - 1.0).abs() < 1e-4)
+    }
+
+    #[test]
+    fn test_splatmap_paint_blends_toward_layer() {
+        let mut splat_map = SplatMap::new(16, 16, 3);
+        assert_eq!(splat_map.weights(8, 8), &[1.0, 0.0, 0.0]);
+
+        // World origin is the grid center; radius covers ~4 cells
+        assert!(splat_map.paint(0.0, 0.0, 16.0, 4.0, 2, 0.5, 0.0));
+        assert!((splat_map.weights(8, 8)[2] - 0.5).abs() < 1e-4);
+        assert!(splat_map.paint(0.0, 0.0, 16.0, 4.0, 2, 1.0, 0.0));
+        assert!((splat_map.weights(8, 8)[2] - 1.0).abs() < 1e-4);
+
+        // Outside the radius is untouched
+        assert_eq!(splat_map.weights(0, 0), &[1.0, 0.0, 0.0]);
+        assert!(weight_sums_to_one(&splat_map));
+    }
+
+    #[test]
+    fn test_splatmap_falloff_and_invalid_layer() {
+        let mut splat_map = SplatMap::new(16, 16, 2);
+        assert!(!splat_map.paint(0.0, 0.0, 16.0, 4.0, 5, 1.0, 1.0));
+
+        splat_map.paint(0.0, 0.0, 16.0, 4.0, 1, 1.0, 1.0);
+        let center = splat_map.weights(8, 8)[1];
+        let edge = splat_map.weights(11, 8)[1];
+        assert!(center > edge && edge > 0.0);
+        assert!(weight_sums_to_one(&splat_map));
+    }
+
+    #[test]
+    fn test_splatmap_save_load_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = SplatMap::path_for_scene(dir.path().join("castle.ron"));
+        assert_eq!(path, dir.path().join("castle.splat"));
+
+        let mut splat_map = SplatMap::new(8, 6, 4);
+        splat_map.paint(0.0, 0.0, 8.0, 2.0, 3, 0.7, 0.5);
+        splat_map.save(&path).unwrap();
+        let loaded = SplatMap::load(&path).unwrap();
+        assert_eq!((loaded.width, loaded.depth, loaded.layer_count), (8, 6, 4));
+        assert_eq!(loaded.weights, splat_map.weights);
+
+        std::fs::write(&path, b"SPLT").unwrap();
+        assert!(SplatMap::load(&path).is_err());
+    }
+
+    #[test]
+    fn test_splatmap_packs_into_two_images() {
+        let mut splat_map = SplatMap::new(4, 4, 12);
+        assert_eq!(splat_map.layer_count, MAX_TERRAIN_LAYERS);
+        splat_map.paint(0.0, 0.0, 4.0, 0.5, 5, 1.0, 0.0);
+
+        let [low, high] = splat_map.pack_rgba8();
+        assert_eq!(low.len(), 4 * 4 * 4);
+        assert_eq!(&low[0..4], &[255, 0, 0, 0]);
+        let center = (2 * 4 + 2) * 4;
+        assert_eq!(&low[center..center + 4], &[0, 0, 0, 0]);
+        assert_eq!(&high[center..center + 4], &[0, 255, 0, 0]);
+    }
+
+    #[test]
+    fn test_mesh_colors_follow_splatmap() {
+        let config = TerrainConfig {
+            width: 8,
+            depth: 8,
+            scale: 8.0,
+            ..Default::default()
+        };
+        let height_map = HeightMap {
+            width: 8,
+            depth: 8,
+            heights: vec![0.0; 64],
+            holes: Vec::new(),
+        };
+        let layers = TerrainLayer::defaults();
+        let mut splat_map = SplatMap::new(8, 8, layers.len());
+        splat_map.paint(0.0, 0.0, 8.0, 1.0, 1, 1.0, 0.0);
+
+        let mesh = Terrain::generate_mesh_with_splatmap(&height_map, &config, &splat_map, &layers);
+        let grass = Vec3::from(layers[1].color);
+        assert!((mesh.vertices[4 * 8 + 4].color.unwrap() - grass).length() < 1e-4);
+        assert_eq!(mesh.vertices[0].color, Some(Vec3::ONE));
+    }
+}
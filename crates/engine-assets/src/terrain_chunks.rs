@@ -0,0 +1,319 @@
+// Terrain chunks - the heightmap split into tiles with geomipmapped LODs
+//
+// Each chunk covers up to TERRAIN_CHUNK_CELLS x TERRAIN_CHUNK_CELLS grid cells
+// and is meshed once per LOD level, level n keeping every 2^n-th vertex.
+// Neighbouring chunks drawn at different levels don't share all their edge
+// vertices, so each chunk hangs a skirt down from its border to cover the
+// cracks. A brush stroke only rebuilds the chunks under it.
+
+use crate::mesh::{Mesh, Vertex};
+use crate::terrain::{HeightMap, SplatMap, Terrain, TerrainConfig, TerrainLayer};
+use glam::Vec3;
+
+/// Grid cells along each side of a chunk
+pub const TERRAIN_CHUNK_CELLS: usize = 32;
+/// LOD levels meshed per chunk; the last samples every 8th vertex
+pub const TERRAIN_LOD_LEVELS: usize = 4;
+/// Chunk sizes from the camera that LOD 0 reaches; each level after doubles it
+const LOD_DISTANCE: f32 = 2.0;
+
+/// A chunk by its column (x) and row (z) in the chunk grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TerrainChunk {
+    pub x: usize,
+    pub z: usize,
+}
+
+impl TerrainChunk {
+    /// Name the chunk's mesh at a LOD level is uploaded under
+    pub fn mesh_name(&self, lod: usize) -> String {
+        format!("terrain_chunk_{}_{}_lod{}", self.x, self.z, lod)
+    }
+}
+
+/// How a height map grid is divided into chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerrainChunks {
+    /// Height map vertices in X and Z
+    pub width: usize,
+    pub depth: usize,
+    /// Chunks in X and Z
+    pub chunks_x: usize,
+    pub chunks_z: usize,
+}
+
+impl TerrainChunks {
+    pub fn new(width: usize, depth: usize) -> Self {
+        let chunks = |vertices: usize| {
+            vertices
+                .saturating_sub(1)
+                .div_ceil(TERRAIN_CHUNK_CELLS)
+                .max(1)
+        };
+        Self {
+            width,
+            depth,
+            chunks_x: chunks(width),
+            chunks_z: chunks(depth),
+        }
+    }
+
+    /// Every chunk, row by row
+    pub fn chunks(&self) -> impl Iterator<Item = TerrainChunk> + '_ {
+        (0..self.chunks_z).flat_map(move |z| (0..self.chunks_x).map(move |x| TerrainChunk { x, z }))
+    }
+
+    /// Grid vertices a chunk covers, as the inclusive rectangle
+    /// (min_x, min_z, max_x, max_z). Neighbours share their edge vertices.
+    pub fn vertex_bounds(&self, chunk: TerrainChunk) -> (usize, usize, usize, usize) {
+        let last_x = self.width.saturating_sub(1);
+        let last_z = self.depth.saturating_sub(1);
+        let min_x = (chunk.x * TERRAIN_CHUNK_CELLS).min(last_x);
+        let min_z = (chunk.z * TERRAIN_CHUNK_CELLS).min(last_z);
+        (
+            min_x,
+            min_z,
+            (min_x + TERRAIN_CHUNK_CELLS).min(last_x),
+            (min_z + TERRAIN_CHUNK_CELLS).min(last_z),
+        )
+    }
+
+    /// Chunks whose meshes change when the heights or paint in `bounds` (as
+    /// from `HeightMap::brush_bounds`) change. Normals read the neighbouring
+    /// heights, so the bounds grow by a vertex first.
+    pub fn chunks_touching(&self, bounds: (usize, usize, usize, usize)) -> Vec<TerrainChunk> {
+        let (min_x, min_z, max_x, max_z) = bounds;
+        // A vertex on a chunk edge belongs to the chunks on both sides
+        let first = |v: usize| v.saturating_sub(2) / TERRAIN_CHUNK_CELLS;
+        let last = |v: usize, chunks: usize| ((v + 1) / TERRAIN_CHUNK_CELLS).min(chunks - 1);
+        (first(min_z)..=last(max_z, self.chunks_z))
+            .flat_map(|z| {
+                (first(min_x)..=last(max_x, self.chunks_x)).map(move |x| TerrainChunk { x, z })
+            })
+            .collect()
+    }
+
+    /// World units along a chunk's side
+    pub fn chunk_size(config: &TerrainConfig) -> f32 {
+        config.scale / config.width as f32 * TERRAIN_CHUNK_CELLS as f32
+    }
+
+    /// LOD level for a chunk `distance` world units from the camera
+    pub fn lod_for_distance(distance: f32, chunk_size: f32) -> usize {
+        let ratio = distance / (chunk_size * LOD_DISTANCE);
+        if ratio < 1.0 {
+            0
+        } else {
+            (ratio.log2() as usize + 1).min(TERRAIN_LOD_LEVELS - 1)
+        }
+    }
+}
+
+impl Terrain {
+    /// Mesh of one chunk at a LOD level, with a skirt around its border.
+    /// Tinted by the painted layers when a splat map is given.
+    pub fn generate_chunk_mesh(
+        height_map: &HeightMap,
+        config: &TerrainConfig,
+        splat: Option<(&SplatMap, &[TerrainLayer])>,
+        chunks: &TerrainChunks,
+        chunk: TerrainChunk,
+        lod: usize,
+    ) -> Mesh {
+        let (min_x, min_z, max_x, max_z) = chunks.vertex_bounds(chunk);
+        let step = 1 << lod.min(TERRAIN_LOD_LEVELS - 1);
+        // Every step-th vertex, always ending on the chunk's far edge
+        let samples = |min: usize, max: usize| {
+            let mut samples: Vec<usize> = (min..max).step_by(step).collect();
+            samples.push(max);
+            samples
+        };
+        let xs = samples(min_x, max_x);
+        let zs = samples(min_z, max_z);
+        let splat = splat.filter(|(splat_map, _)| {
+            splat_map.width == height_map.width && splat_map.depth == height_map.depth
+        });
+
+        let mut vertices = Vec::with_capacity(xs.len() * zs.len());
+        for &z in &zs {
+            for &x in &xs {
+                let mut vertex = Terrain::grid_vertex(height_map, config, x, z);
+                if let Some((splat_map, layers)) = splat {
+                    vertex.color = Some(splat_map.blend_color(x, z, layers));
+                }
+                vertices.push(vertex);
+            }
+        }
+
+        // A quad is cut out if any cell under it is a hole, so holes stay
+        // open at every level
+        let quad_is_hole = |x: usize, z: usize| match (xs.get(x + 1), zs.get(z + 1)) {
+            (Some(&max_x), Some(&max_z)) => height_map.region_has_hole(xs[x], zs[z], max_x, max_z),
+            _ => false,
+        };
+
+        let row = xs.len() as u32;
+        let mut indices = Vec::with_capacity((xs.len() - 1) * (zs.len() - 1) * 6);
+        for z in 0..(zs.len() as u32).saturating_sub(1) {
+            for x in 0..row.saturating_sub(1) {
+                if quad_is_hole(x as usize, z as usize) {
+                    continue;
+                }
+                let top_left = z * row + x;
+                let top_right = top_left + 1;
+                let bottom_left = top_left + row;
+                let bottom_right = bottom_left + 1;
+                indices.extend_from_slice(&[top_left, bottom_left, top_right]);
+                indices.extend_from_slice(&[top_right, bottom_left, bottom_right]);
+            }
+        }
+
+        // Skirt: deep enough to reach a neighbour at any level
+        let (low, high) = vertices
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(low, high), v| {
+                (low.min(v.position.y), high.max(v.position.y))
+            });
+        let skirt_depth = (high - low) + config.scale / config.width as f32 * step as f32;
+        let (columns, rows) = (xs.len() as u32, zs.len() as u32);
+        let border: [Vec<u32>; 4] = [
+            (0..columns).collect(),
+            (0..columns).map(|x| (rows - 1) * row + x).collect(),
+            (0..rows).map(|z| z * row).collect(),
+            (0..rows).map(|z| z * row + columns - 1).collect(),
+        ];
+        // The quad each edge segment borders; no skirt hangs into a hole
+        let (last_x, last_z) = (xs.len().saturating_sub(2), zs.len().saturating_sub(2));
+        let edge_quad = |edge: usize, i: usize| match edge {
+            0 => (i, 0),
+            1 => (i, last_z),
+            2 => (0, i),
+            _ => (last_x, i),
+        };
+        for (edge, indices_along) in border.iter().enumerate() {
+            for (i, pair) in indices_along.windows(2).enumerate() {
+                let (x, z) = edge_quad(edge, i);
+                if quad_is_hole(x, z) {
+                    continue;
+                }
+                let base = vertices.len() as u32;
+                for &index in pair {
+                    let vertex = &vertices[index as usize];
+                    let lowered = Vertex {
+                        position: vertex.position - Vec3::Y * skirt_depth,
+                        ..vertex.clone()
+                    };
+                    vertices.push(lowered);
+                }
+                indices.extend_from_slice(&[pair[0], base, pair[1]]);
+                indices.extend_from_slice(&[pair[1], base, base + 1]);
+            }
+        }
+
+        Mesh::new(chunk.mesh_name(lod), vertices, indices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat_terrain(size: usize) -> (HeightMap, TerrainConfig) {
+        let config = TerrainConfig {
+            width: size,
+            depth: size,
+            scale: size as f32,
+            ..Default::default()
+        };
+        let height_map = HeightMap {
+            width: size,
+            depth: size,
+            heights: (0..size * size).map(|i| (i % 7) as f32 * 0.1).collect(),
+            holes: Vec::new(),
+        };
+        (height_map, config)
+    }
+
+    #[test]
+    fn test_chunks_cover_the_grid_and_lods_thin_out() {
+        let (height_map, config) = flat_terrain(65);
+        let chunks = TerrainChunks::new(65, 65);
+        assert_eq!((chunks.chunks_x, chunks.chunks_z), (2, 2));
+        assert_eq!(
+            chunks.vertex_bounds(TerrainChunk { x: 1, z: 1 }),
+            (32, 32, 64, 64)
+        );
+
+        let chunk = TerrainChunk { x: 1, z: 0 };
+        let full = Terrain::generate_chunk_mesh(&height_map, &config, None, &chunks, chunk, 0);
+        let coarse = Terrain::generate_chunk_mesh(&height_map, &config, None, &chunks, chunk, 3);
+        // 33x33 grid plus two skirt vertices per edge segment
+        assert_eq!(full.vertices.len(), 33 * 33 + 4 * 32 * 2);
+        assert_eq!(coarse.vertices.len(), 5 * 5 + 4 * 4 * 2);
+        assert!(coarse.indices.len() < full.indices.len() / 16);
+
+        // Corners match the full terrain mesh
+        let whole = Terrain::generate_mesh_from_heightmap(&height_map, &config);
+        assert_eq!(full.vertices[0].position, whole.vertices[32].position);
+        assert_eq!(
+            coarse.vertices[5 * 5 - 1].position,
+            whole.vertices[32 * 65 + 64].position
+        );
+        let lowest = full
+            .vertices
+            .iter()
+            .map(|v| v.position.y)
+            .fold(f32::MAX, f32::min);
+        assert!(lowest < 0.0, "skirt hangs below the terrain");
+    }
+
+    #[test]
+    fn test_holes_cut_quads_at_every_lod() {
+        let (mut height_map, config) = flat_terrain(65);
+        let chunks = TerrainChunks::new(65, 65);
+        let chunk = TerrainChunk { x: 1, z: 1 };
+        let whole = Terrain::generate_chunk_mesh(&height_map, &config, None, &chunks, chunk, 0);
+
+        // Cut one cell out of the middle of the chunk with a brush
+        let center = -config.scale * 0.5 + 48.5;
+        assert!(height_map.paint_holes(center, center, config.scale, 0.4, true));
+        assert!(height_map.is_hole(48, 48) && !height_map.is_hole(47, 48));
+        assert!(height_map.hole_at(center, center, config.scale));
+
+        let holed = Terrain::generate_chunk_mesh(&height_map, &config, None, &chunks, chunk, 0);
+        assert_eq!(holed.indices.len(), whole.indices.len() - 6);
+        // At the coarsest level the hole takes out its whole 8x8 quad
+        let coarse = Terrain::generate_chunk_mesh(&height_map, &config, None, &chunks, chunk, 3);
+        let coarse_whole =
+            Terrain::generate_chunk_mesh(&flat_terrain(65).0, &config, None, &chunks, chunk, 3);
+        assert_eq!(coarse.indices.len(), coarse_whole.indices.len() - 6);
+
+        // Filling restores the surface
+        assert!(height_map.paint_holes(center, center, config.scale, 0.4, false));
+        assert!(!height_map.has_holes());
+    }
+
+    #[test]
+    fn test_brush_touches_only_nearby_chunks_and_lod_grows_with_distance() {
+        let chunks = TerrainChunks::new(129, 129);
+        assert_eq!(
+            chunks.chunks_touching((40, 40, 50, 50)),
+            vec![TerrainChunk { x: 1, z: 1 }]
+        );
+        // On the shared edge both sides rebuild
+        let edge = chunks.chunks_touching((60, 10, 64, 12));
+        assert_eq!(
+            edge,
+            vec![TerrainChunk { x: 1, z: 0 }, TerrainChunk { x: 2, z: 0 }]
+        );
+        assert_eq!(chunks.chunks_touching((0, 0, 128, 128)).len(), 16);
+
+        assert_eq!(TerrainChunks::lod_for_distance(10.0, 32.0), 0);
+        assert_eq!(TerrainChunks::lod_for_distance(70.0, 32.0), 1);
+        assert_eq!(TerrainChunks::lod_for_distance(140.0, 32.0), 2);
+        assert_eq!(
+            TerrainChunks::lod_for_distance(10_000.0, 32.0),
+            TERRAIN_LOD_LEVELS - 1
+        );
+    }
+}
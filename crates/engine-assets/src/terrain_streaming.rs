@@ -0,0 +1,536 @@
+// Terrain streaming - heightmap tiles loaded around the camera
+//
+// A streamed world is a directory of square heightmap tiles, `tile_X_Z.height`,
+// each `tile_size` world units across with neighbours sharing their edge
+// vertices. The streamer keeps the tiles within `load_radius` of the camera in
+// memory, nearest first and never more than `max_loaded_tiles`. Files are
+// read and written on a worker thread so the frame never waits on the disk;
+// edited tiles are written back when they unload. A tile with no file yet is
+// flat ground, so the world grows wherever it is sculpted.
+
+use crate::terrain::{HeightMap, TerrainConfig};
+use crate::terrain_chunks::TerrainChunk;
+use anyhow::{bail, Context, Result};
+use glam::{Vec2, Vec3};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::JoinHandle;
+
+/// First bytes of a tile file
+const TILE_MAGIC: &[u8; 4] = b"CHTL";
+/// Version 2 adds the hole mask after the heights
+const TILE_VERSION: u32 = 2;
+/// Tiles stay loaded out to this much past the load radius, so one at the
+/// edge doesn't load and unload as the camera moves back and forth
+const UNLOAD_MARGIN: f32 = 1.25;
+
+/// A tile by its column (x) and row (z); tile (0, 0) starts at the world origin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TileCoord {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl TileCoord {
+    pub fn new(x: i32, z: i32) -> Self {
+        Self { x, z }
+    }
+
+    /// Tile file name within the world directory
+    pub fn file_name(&self) -> String {
+        format!("tile_{}_{}.height", self.x, self.z)
+    }
+
+    /// Name a chunk mesh of this tile is uploaded under
+    pub fn chunk_mesh_name(&self, chunk: TerrainChunk, lod: usize) -> String {
+        format!(
+            "terrain_tile_{}_{}/{}",
+            self.x,
+            self.z,
+            chunk.mesh_name(lod)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerrainStreamConfig {
+    /// Directory holding the tile files
+    pub directory: PathBuf,
+    /// Heightmap vertices along each side of a tile
+    pub tile_resolution: usize,
+    /// World units along each side of a tile
+    pub tile_size: f32,
+    /// Tiles closer than this to the camera (in XZ) are loaded
+    pub load_radius: f32,
+    /// Most tiles in memory at once
+    pub max_loaded_tiles: usize,
+    /// Most tile reads queued on the worker at once
+    pub max_pending_loads: usize,
+}
+
+impl TerrainStreamConfig {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            tile_resolution: 65,
+            tile_size: 64.0,
+            load_radius: 160.0,
+            max_loaded_tiles: 25,
+            max_pending_loads: 4,
+        }
+    }
+
+    /// Terrain config a tile's mesh is built with: its vertices span exactly
+    /// `tile_size`, so the edges of neighbouring tiles meet
+    pub fn tile_config(&self) -> TerrainConfig {
+        let resolution = self.tile_resolution.max(2);
+        TerrainConfig {
+            width: resolution,
+            depth: resolution,
+            scale: self.tile_size * resolution as f32 / (resolution - 1) as f32,
+            ..Default::default()
+        }
+    }
+
+    /// Translation placing a tile's mesh (built around the origin, see
+    /// `Terrain::generate_mesh_from_heightmap`) at its spot in the world
+    pub fn tile_offset(&self, coord: TileCoord) -> Vec3 {
+        let half = self.tile_config().scale * 0.5;
+        Vec3::new(
+            coord.x as f32 * self.tile_size + half,
+            0.0,
+            coord.z as f32 * self.tile_size + half,
+        )
+    }
+
+    /// The tile a world position is over
+    pub fn tile_at(&self, world_x: f32, world_z: f32) -> TileCoord {
+        TileCoord::new(
+            (world_x / self.tile_size).floor() as i32,
+            (world_z / self.tile_size).floor() as i32,
+        )
+    }
+
+    /// XZ distance from a point to the nearest edge of a tile, 0 inside it
+    fn distance_to(&self, coord: TileCoord, point: Vec2) -> f32 {
+        let min = Vec2::new(coord.x as f32, coord.z as f32) * self.tile_size;
+        let max = min + Vec2::splat(self.tile_size);
+        point.clamp(min, max).distance(point)
+    }
+}
+
+/// What changed in an update
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileEvent {
+    Loaded(TileCoord),
+    Unloaded(TileCoord),
+}
+
+struct StreamedTile {
+    height_map: HeightMap,
+    /// Edited since it was loaded
+    dirty: bool,
+}
+
+enum TileJob {
+    Load(TileCoord),
+    Save(TileCoord, HeightMap),
+}
+
+pub struct TerrainStreamer {
+    config: TerrainStreamConfig,
+    tiles: HashMap<TileCoord, StreamedTile>,
+    /// Reads queued on the worker
+    pending: HashSet<TileCoord>,
+    /// Tiles whose file couldn't be read; not asked for again
+    failed: HashSet<TileCoord>,
+    jobs: Option<Sender<TileJob>>,
+    results: Receiver<(TileCoord, Result<HeightMap>)>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl TerrainStreamer {
+    /// Start the worker thread for a world directory
+    pub fn new(config: TerrainStreamConfig) -> Result<Self> {
+        if config.tile_resolution < 2 || config.tile_size <= 0.0 {
+            bail!("Terrain tiles need at least 2 vertices and a positive size");
+        }
+        let (jobs, job_receiver) = mpsc::channel::<TileJob>();
+        let (result_sender, results) = mpsc::channel();
+        let directory = config.directory.clone();
+        let resolution = config.tile_resolution;
+        let worker = std::thread::Builder::new()
+            .name("terrain-streaming".to_string())
+            .spawn(move || {
+                // Runs until the streamer drops its sender
+                for job in job_receiver {
+                    match job {
+                        TileJob::Load(coord) => {
+                            let path = directory.join(coord.file_name());
+                            let result = read_tile_or_flat(&path, resolution);
+                            if result_sender.send((coord, result)).is_err() {
+                                break;
+                            }
+                        }
+                        TileJob::Save(coord, height_map) => {
+                            let path = directory.join(coord.file_name());
+                            if let Err(e) = write_tile(&path, &height_map) {
+                                log::error!(
+                                    "Failed to save terrain tile {}: {}",
+                                    path.display(),
+                                    e
+                                );
+                            }
+                        }
+                    }
+                }
+            })
+            .context("Failed to start the terrain streaming thread")?;
+
+        Ok(Self {
+            config,
+            tiles: HashMap::new(),
+            pending: HashSet::new(),
+            failed: HashSet::new(),
+            jobs: Some(jobs),
+            results,
+            worker: Some(worker),
+        })
+    }
+
+    pub fn config(&self) -> &TerrainStreamConfig {
+        &self.config
+    }
+
+    /// Take in finished reads, unload tiles that fell out of range and queue
+    /// reads for the nearest missing ones
+    pub fn update(&mut self, camera_position: Vec3) -> Vec<TileEvent> {
+        let camera = Vec2::new(camera_position.x, camera_position.z);
+        let keep_radius = self.config.load_radius * UNLOAD_MARGIN;
+        let mut events = Vec::new();
+
+        while let Ok((coord, result)) = self.results.try_recv() {
+            self.pending.remove(&coord);
+            match result {
+                // The camera may have moved on while it loaded
+                Ok(height_map) if self.config.distance_to(coord, camera) <= keep_radius => {
+                    self.tiles.insert(
+                        coord,
+                        StreamedTile {
+                            height_map,
+                            dirty: false,
+                        },
+                    );
+                    events.push(TileEvent::Loaded(coord));
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    log::warn!(
+                        "Failed to load terrain tile ({}, {}): {:#}",
+                        coord.x,
+                        coord.z,
+                        e
+                    );
+                    self.failed.insert(coord);
+                }
+            }
+        }
+
+        // Out of range, then the farthest while over budget
+        let mut loaded: Vec<(TileCoord, f32)> = self
+            .tiles
+            .keys()
+            .map(|&coord| (coord, self.config.distance_to(coord, camera)))
+            .collect();
+        loaded.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let budget = self.config.max_loaded_tiles;
+        for (index, &(coord, distance)) in loaded.iter().enumerate() {
+            if distance > keep_radius || index >= budget {
+                self.unload(coord);
+                events.push(TileEvent::Unloaded(coord));
+            }
+        }
+
+        for coord in self.tiles_in_range(camera) {
+            if self.pending.len() >= self.config.max_pending_loads
+                || self.tiles.len() + self.pending.len() >= budget
+            {
+                break;
+            }
+            if self.tiles.contains_key(&coord)
+                || self.pending.contains(&coord)
+                || self.failed.contains(&coord)
+            {
+                continue;
+            }
+            if let Some(jobs) = &self.jobs {
+                if jobs.send(TileJob::Load(coord)).is_ok() {
+                    self.pending.insert(coord);
+                }
+            }
+        }
+        events
+    }
+
+    /// Tiles within the load radius, nearest first
+    fn tiles_in_range(&self, camera: Vec2) -> Vec<TileCoord> {
+        let radius = self.config.load_radius;
+        let min = self.config.tile_at(camera.x - radius, camera.y - radius);
+        let max = self.config.tile_at(camera.x + radius, camera.y + radius);
+        let mut tiles: Vec<(TileCoord, f32)> = (min.z..=max.z)
+            .flat_map(|z| (min.x..=max.x).map(move |x| TileCoord::new(x, z)))
+            .map(|coord| (coord, self.config.distance_to(coord, camera)))
+            .filter(|&(_, distance)| distance <= radius)
+            .collect();
+        tiles.sort_by(|a, b| a.1.total_cmp(&b.1));
+        tiles.into_iter().map(|(coord, _)| coord).collect()
+    }
+
+    fn unload(&mut self, coord: TileCoord) {
+        if let Some(tile) = self.tiles.remove(&coord) {
+            if tile.dirty {
+                self.save(coord, tile.height_map);
+            }
+        }
+    }
+
+    fn save(&self, coord: TileCoord, height_map: HeightMap) {
+        if let Some(jobs) = &self.jobs {
+            let _ = jobs.send(TileJob::Save(coord, height_map));
+        }
+    }
+
+    /// Loaded tiles
+    pub fn tiles(&self) -> impl Iterator<Item = (TileCoord, &HeightMap)> {
+        self.tiles
+            .iter()
+            .map(|(&coord, tile)| (coord, &tile.height_map))
+    }
+
+    pub fn tile(&self, coord: TileCoord) -> Option<&HeightMap> {
+        self.tiles.get(&coord).map(|tile| &tile.height_map)
+    }
+
+    /// A loaded tile to edit; it is written back to disk when it unloads
+    pub fn tile_mut(&mut self, coord: TileCoord) -> Option<&mut HeightMap> {
+        let tile = self.tiles.get_mut(&coord)?;
+        tile.dirty = true;
+        Some(&mut tile.height_map)
+    }
+
+    /// Terrain height at a world position, if its tile is loaded
+    pub fn height_at(&self, world_x: f32, world_z: f32) -> Option<f32> {
+        let coord = self.config.tile_at(world_x, world_z);
+        let offset = self.config.tile_offset(coord);
+        let scale = self.config.tile_config().scale;
+        self.tile(coord).map(|height_map| {
+            height_map.sample_height(world_x - offset.x, world_z - offset.z, scale)
+        })
+    }
+
+    pub fn loading(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Queue writes for every edited tile, keeping them loaded
+    pub fn save_dirty(&mut self) -> usize {
+        let dirty: Vec<(TileCoord, HeightMap)> = self
+            .tiles
+            .iter_mut()
+            .filter(|(_, tile)| tile.dirty)
+            .map(|(&coord, tile)| {
+                tile.dirty = false;
+                (coord, tile.height_map.clone())
+            })
+            .collect();
+        let count = dirty.len();
+        for (coord, height_map) in dirty {
+            self.save(coord, height_map);
+        }
+        count
+    }
+}
+
+impl Drop for TerrainStreamer {
+    /// Write back edited tiles and wait for the worker to finish them
+    fn drop(&mut self) {
+        self.save_dirty();
+        self.jobs = None;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Write a heightmap as a tile file
+pub fn write_tile(path: &Path, height_map: &HeightMap) -> Result<()> {
+    let mut bytes = Vec::with_capacity(16 + height_map.heights.len() * 4);
+    bytes.extend_from_slice(TILE_MAGIC);
+    bytes.extend_from_slice(&TILE_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(height_map.width as u32).to_le_bytes());
+    bytes.extend_from_slice(&(height_map.depth as u32).to_le_bytes());
+    for height in &height_map.heights {
+        bytes.extend_from_slice(&height.to_le_bytes());
+    }
+    // One byte per grid point, only if there are holes
+    let holes = height_map.has_holes();
+    bytes.extend_from_slice(&u32::from(holes).to_le_bytes());
+    if holes {
+        bytes.extend(height_map.holes.iter().map(|&hole| u8::from(hole)));
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // Written aside and renamed, so a tile is never left half written
+    let temp = path.with_extension("height.tmp");
+    std::fs::write(&temp, bytes)?;
+    std::fs::rename(&temp, path)?;
+    Ok(())
+}
+
+/// Read a tile file
+pub fn read_tile(path: &Path) -> Result<HeightMap> {
+    let bytes = std::fs::read(path)?;
+    let word = |index: usize| -> Result<u32> {
+        let chunk = bytes
+            .get(index * 4..index * 4 + 4)
+            .context("Tile file is truncated")?;
+        Ok(u32::from_le_bytes(chunk.try_into()?))
+    };
+    if bytes.get(0..4) != Some(TILE_MAGIC.as_slice()) {
+        bail!("Not a terrain tile file");
+    }
+    let version = word(1)?;
+    if version == 0 || version > TILE_VERSION {
+        bail!("Unsupported terrain tile version {}", version);
+    }
+    let (width, depth) = (word(2)? as usize, word(3)? as usize);
+    let heights = (0..width * depth)
+        .map(|i| word(4 + i).map(f32::from_bits))
+        .collect::<Result<Vec<f32>>>()?;
+    let holes_at = 4 + width * depth;
+    let holes = if version >= 2 && word(holes_at)? != 0 {
+        let start = (holes_at + 1) * 4;
+        bytes
+            .get(start..start + width * depth)
+            .context("Tile file is truncated")?
+            .iter()
+            .map(|&byte| byte != 0)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    Ok(HeightMap {
+        width,
+        depth,
+        heights,
+        holes,
+    })
+}
+
+/// Read a tile, or flat ground if it has no file yet
+fn read_tile_or_flat(path: &Path, resolution: usize) -> Result<HeightMap> {
+    if !path.exists() {
+        return Ok(HeightMap {
+            width: resolution,
+            depth: resolution,
+            heights: vec![0.0; resolution * resolution],
+            holes: Vec::new(),
+        });
+    }
+    let height_map = read_tile(path).with_context(|| path.display().to_string())?;
+    if height_map.width != resolution || height_map.depth != resolution {
+        bail!(
+            "{} is {}x{}, the world's tiles are {}x{}",
+            path.display(),
+            height_map.width,
+            height_map.depth,
+            resolution,
+            resolution
+        );
+    }
+    Ok(height_map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    /// Update until nothing is loading (the worker is another thread)
+    fn settle(streamer: &mut TerrainStreamer, camera: Vec3) -> Vec<TileEvent> {
+        let start = Instant::now();
+        let mut events = streamer.update(camera);
+        while streamer.loading() > 0 && start.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(2));
+            events.extend(streamer.update(camera));
+        }
+        events
+    }
+
+    #[test]
+    fn test_tile_files_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(TileCoord::new(-1, 2).file_name());
+        let height_map = HeightMap {
+            width: 3,
+            depth: 2,
+            heights: vec![0.0, 1.5, -2.0, 3.25, 4.0, 5.0],
+            holes: Vec::new(),
+        };
+        write_tile(&path, &height_map).unwrap();
+        let read = read_tile(&path).unwrap();
+        assert_eq!((read.width, read.depth), (3, 2));
+        assert_eq!(read.heights, height_map.heights);
+        assert!(read.holes.is_empty());
+
+        let mut holed = height_map.clone();
+        holed.set_hole(1, 0, true);
+        write_tile(&path, &holed).unwrap();
+        let read = read_tile(&path).unwrap();
+        assert_eq!(read.heights, height_map.heights);
+        assert!(read.is_hole(1, 0) && !read.is_hole(0, 0));
+
+        std::fs::write(&path, b"CHTL").unwrap();
+        assert!(read_tile(&path).is_err());
+    }
+
+    #[test]
+    fn test_streams_nearby_tiles_within_budget_and_saves_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = TerrainStreamConfig::new(dir.path());
+        config.tile_resolution = 5;
+        config.tile_size = 10.0;
+        config.load_radius = 12.0;
+        config.max_loaded_tiles = 9;
+
+        // Vertices of neighbouring tiles line up
+        let offset = config.tile_offset(TileCoord::new(1, 0));
+        let tile_config = config.tile_config();
+        let first = -tile_config.scale * 0.5 + offset.x;
+        let cell = tile_config.scale / tile_config.width as f32;
+        assert!((first - 10.0).abs() < 1e-4 && (first + 4.0 * cell - 20.0).abs() < 1e-4);
+
+        let mut streamer = TerrainStreamer::new(config).unwrap();
+        let events = settle(&mut streamer, Vec3::new(5.0, 0.0, 5.0));
+        assert_eq!(streamer.tiles().count(), 9);
+        assert!(events.contains(&TileEvent::Loaded(TileCoord::new(-1, -1))));
+        assert_eq!(streamer.height_at(5.0, 5.0), Some(0.0));
+
+        // Edit a tile, then move far enough away that it unloads and saves
+        streamer
+            .tile_mut(TileCoord::new(0, 0))
+            .unwrap()
+            .heights
+            .fill(2.0);
+        let events = settle(&mut streamer, Vec3::new(205.0, 0.0, 5.0));
+        assert!(events.contains(&TileEvent::Unloaded(TileCoord::new(0, 0))));
+        assert!(streamer.tile(TileCoord::new(0, 0)).is_none());
+        assert!(streamer.tiles().count() <= 9);
+
+        // Coming back reads the edit from disk
+        settle(&mut streamer, Vec3::new(5.0, 0.0, 5.0));
+        assert_eq!(streamer.height_at(5.0, 5.0), Some(2.0));
+    }
+}
@@ -46,6 +46,13 @@ impl Texture {
         Self::from_dynamic_image(name, img)
     }
 
+    /// Decode an image file already in memory (format from its contents)
+    pub fn from_bytes(name: String, bytes: &[u8]) -> Result<Self> {
+        let img = image::load_from_memory(bytes)
+            .with_context(|| format!("Failed to decode texture: {}", name))?;
+        Self::from_dynamic_image(name, img)
+    }
+
     /// Convert from DynamicImage
     pub fn from_dynamic_image(name: String, img: DynamicImage) -> Result<Self> {
         let (width, height) = img.dimensions();
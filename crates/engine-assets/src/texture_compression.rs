@@ -0,0 +1,681 @@
+// Texture compression - block-compressed textures with their mip chains
+//
+// Color textures become BC7 (mode 6: one endpoint pair per 4x4 block, 4-bit
+// indices), two-channel normal maps BC5 and single-channel textures BC4.
+// GPUs without BC support (mobile) get ASTC 4x4 instead. Encoding takes far
+// longer than an upload, so CompressedTextureCache keeps results on disk,
+// keyed by a hash of the source pixels and the format.
+
+use crate::texture::{Texture, TextureFormat};
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Bumped whenever an encoder changes its output
+pub const COMPRESSED_CACHE_VERSION: u32 = 1;
+
+const CACHE_MAGIC: &[u8; 4] = b"CTEX";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompressedFormat {
+    /// RGBA color, 8 bits per pixel
+    Bc7,
+    /// Two channels (normal map X and Y), 8 bits per pixel
+    Bc5,
+    /// One channel, 4 bits per pixel
+    Bc4,
+    /// RGBA color for GPUs without BC, 8 bits per pixel
+    Astc4x4,
+}
+
+/// What a texture holds, which decides how it is compressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureKind {
+    Color,
+    /// Tangent-space normal map; only X and Y are kept where the format allows
+    Normal,
+}
+
+impl CompressedFormat {
+    /// Bytes per 4x4 block
+    pub fn block_bytes(self) -> usize {
+        match self {
+            Self::Bc4 => 8,
+            Self::Bc7 | Self::Bc5 | Self::Astc4x4 => 16,
+        }
+    }
+
+    /// Format for `texture` on a GPU with BC support (`bc`) or only ASTC.
+    /// None if the texture can't be block compressed: its size has to be a
+    /// multiple of the 4x4 block.
+    pub fn choose(texture: &Texture, kind: TextureKind, bc: bool) -> Option<Self> {
+        if !texture.width.is_multiple_of(4) || !texture.height.is_multiple_of(4) {
+            return None;
+        }
+        if !bc {
+            return Some(Self::Astc4x4);
+        }
+        Some(match (kind, texture.format) {
+            (_, TextureFormat::R8) => Self::Bc4,
+            (TextureKind::Normal, _) => Self::Bc5,
+            (TextureKind::Color, _) => Self::Bc7,
+        })
+    }
+
+    fn id(self) -> u8 {
+        match self {
+            Self::Bc7 => 0,
+            Self::Bc5 => 1,
+            Self::Bc4 => 2,
+            Self::Astc4x4 => 3,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        [Self::Bc7, Self::Bc5, Self::Bc4, Self::Astc4x4]
+            .into_iter()
+            .find(|format| format.id() == id)
+    }
+}
+
+/// One mip level of blocks
+#[derive(Debug, Clone, PartialEq)]
+pub struct MipLevel {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+/// A block-compressed texture, largest mip level first
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressedTexture {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub format: CompressedFormat,
+    pub levels: Vec<MipLevel>,
+}
+
+impl CompressedTexture {
+    /// Total size of all levels
+    pub fn byte_size(&self) -> usize {
+        self.levels.iter().map(|level| level.data.len()).sum()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.byte_size() + 32);
+        bytes.extend_from_slice(CACHE_MAGIC);
+        bytes.extend_from_slice(&COMPRESSED_CACHE_VERSION.to_le_bytes());
+        bytes.push(self.format.id());
+        bytes.extend_from_slice(&(self.levels.len() as u32).to_le_bytes());
+        for level in &self.levels {
+            bytes.extend_from_slice(&level.width.to_le_bytes());
+            bytes.extend_from_slice(&level.height.to_le_bytes());
+            bytes.extend_from_slice(&(level.data.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&level.data);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(name: String, bytes: &[u8]) -> Result<Self> {
+        let mut reader = bytes;
+        if take(&mut reader, 4)? != CACHE_MAGIC {
+            bail!("Not a compressed texture");
+        }
+        let version = take_u32(&mut reader)?;
+        if version != COMPRESSED_CACHE_VERSION {
+            bail!("Compressed texture version {} is out of date", version);
+        }
+        let format = CompressedFormat::from_id(take(&mut reader, 1)?[0])
+            .context("Unknown compressed texture format")?;
+        let count = take_u32(&mut reader)?;
+        let mut levels = Vec::with_capacity(count.min(16) as usize);
+        for _ in 0..count {
+            let width = take_u32(&mut reader)?;
+            let height = take_u32(&mut reader)?;
+            let size = take_u32(&mut reader)? as usize;
+            let data = take(&mut reader, size)?.to_vec();
+            if data.len() != blocks(width) * blocks(height) * format.block_bytes() {
+                bail!(
+                    "Compressed mip level {}x{} has the wrong size",
+                    width,
+                    height
+                );
+            }
+            levels.push(MipLevel {
+                width,
+                height,
+                data,
+            });
+        }
+        let Some(first) = levels.first() else {
+            bail!("Compressed texture has no mip levels");
+        };
+        Ok(Self {
+            name,
+            width: first.width,
+            height: first.height,
+            format,
+            levels,
+        })
+    }
+}
+
+fn take<'a>(reader: &mut &'a [u8], count: usize) -> Result<&'a [u8]> {
+    if reader.len() < count {
+        bail!("Compressed texture is truncated");
+    }
+    let (head, rest) = reader.split_at(count);
+    *reader = rest;
+    Ok(head)
+}
+
+fn take_u32(reader: &mut &[u8]) -> Result<u32> {
+    let bytes = take(reader, 4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Blocks along one side of a level
+fn blocks(size: u32) -> usize {
+    size.div_ceil(4) as usize
+}
+
+/// RGBA8 levels down to 1x1, each a 2x2 box filter of the one before
+pub fn generate_mipmaps(texture: &Texture) -> Vec<MipLevel> {
+    let mut levels = vec![MipLevel {
+        width: texture.width,
+        height: texture.height,
+        data: rgba8(texture),
+    }];
+    loop {
+        let previous = &levels[levels.len() - 1];
+        if previous.width == 1 && previous.height == 1 {
+            break;
+        }
+        let width = (previous.width / 2).max(1);
+        let height = (previous.height / 2).max(1);
+        let mut data = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0u32; 4];
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let px = (x * 2 + dx).min(previous.width - 1);
+                    let py = (y * 2 + dy).min(previous.height - 1);
+                    let index = ((py * previous.width + px) * 4) as usize;
+                    for (channel, total) in sum.iter_mut().enumerate() {
+                        *total += previous.data[index + channel] as u32;
+                    }
+                }
+                data.extend(sum.map(|total| ((total + 2) / 4) as u8));
+            }
+        }
+        levels.push(MipLevel {
+            width,
+            height,
+            data,
+        });
+    }
+    levels
+}
+
+fn rgba8(texture: &Texture) -> Vec<u8> {
+    match texture.format {
+        TextureFormat::Rgba8 => texture.data.clone(),
+        TextureFormat::Rgb8 => texture
+            .data
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        TextureFormat::R8 => texture.data.iter().flat_map(|&r| [r, r, r, 255]).collect(),
+    }
+}
+
+/// Compress a texture and its full mip chain
+pub fn compress_texture(texture: &Texture, format: CompressedFormat) -> CompressedTexture {
+    let levels = generate_mipmaps(texture)
+        .into_iter()
+        .map(|level| MipLevel {
+            width: level.width,
+            height: level.height,
+            data: compress_level(&level, format),
+        })
+        .collect();
+    CompressedTexture {
+        name: texture.name.clone(),
+        width: texture.width,
+        height: texture.height,
+        format,
+        levels,
+    }
+}
+
+fn compress_level(level: &MipLevel, format: CompressedFormat) -> Vec<u8> {
+    let (columns, rows) = (blocks(level.width), blocks(level.height));
+    let mut out = Vec::with_capacity(columns * rows * format.block_bytes());
+    for by in 0..rows {
+        for bx in 0..columns {
+            let block = read_block(level, bx, by);
+            match format {
+                CompressedFormat::Bc7 => out.extend_from_slice(&encode_bc7(&block)),
+                CompressedFormat::Bc5 => {
+                    out.extend_from_slice(&encode_bc4(&block.map(|p| p[0])));
+                    out.extend_from_slice(&encode_bc4(&block.map(|p| p[1])));
+                }
+                CompressedFormat::Bc4 => out.extend_from_slice(&encode_bc4(&block.map(|p| p[0]))),
+                CompressedFormat::Astc4x4 => out.extend_from_slice(&encode_astc(&block)),
+            }
+        }
+    }
+    out
+}
+
+/// The 4x4 pixels of a block, row by row; edges repeat past the level's size
+fn read_block(level: &MipLevel, bx: usize, by: usize) -> [[u8; 4]; 16] {
+    let mut block = [[0u8; 4]; 16];
+    for (i, pixel) in block.iter_mut().enumerate() {
+        let x = (bx * 4 + i % 4).min(level.width as usize - 1);
+        let y = (by * 4 + i / 4).min(level.height as usize - 1);
+        let index = (y * level.width as usize + x) * 4;
+        pixel.copy_from_slice(&level.data[index..index + 4]);
+    }
+    block
+}
+
+/// Little-endian bit writer for a block
+struct BlockBits<const N: usize> {
+    bytes: [u8; N],
+    position: usize,
+}
+
+impl<const N: usize> BlockBits<N> {
+    fn new() -> Self {
+        Self {
+            bytes: [0; N],
+            position: 0,
+        }
+    }
+
+    fn push(&mut self, value: u32, bits: usize) {
+        for bit in 0..bits {
+            self.set(self.position + bit, (value >> bit) & 1 != 0);
+        }
+        self.position += bits;
+    }
+
+    fn set(&mut self, position: usize, on: bool) {
+        if on {
+            self.bytes[position / 8] |= 1 << (position % 8);
+        }
+    }
+}
+
+/// BC4: two 8-bit endpoints and a 3-bit index per pixel
+fn encode_bc4(values: &[u8; 16]) -> [u8; 8] {
+    let max = *values.iter().max().unwrap_or(&0);
+    let min = *values.iter().min().unwrap_or(&0);
+    // max > min selects the eight-value palette; equal endpoints need no ramp
+    let palette: [u8; 8] = if max == min {
+        [max; 8]
+    } else {
+        let (a, b) = (max as u32, min as u32);
+        let mut palette = [max, min, 0, 0, 0, 0, 0, 0];
+        for k in 1..7 {
+            palette[k + 1] = ((a * (7 - k as u32) + b * k as u32 + 3) / 7) as u8;
+        }
+        palette
+    };
+
+    let mut bits = BlockBits::<8>::new();
+    bits.push(max as u32, 8);
+    bits.push(min as u32, 8);
+    for &value in values {
+        let index = (0..8)
+            .min_by_key(|&i| (palette[i] as i32 - value as i32).abs())
+            .unwrap_or(0);
+        bits.push(index as u32, 3);
+    }
+    bits.bytes
+}
+
+/// Endpoints of the line through `pixels` that best fits them (principal
+/// axis), over the first `channels` channels
+fn fit_endpoints(pixels: &[[u8; 4]; 16], channels: usize) -> ([f32; 4], [f32; 4]) {
+    let mut mean = [0.0f32; 4];
+    for pixel in pixels {
+        for c in 0..channels {
+            mean[c] += pixel[c] as f32 / 16.0;
+        }
+    }
+    let mut covariance = [[0.0f32; 4]; 4];
+    for pixel in pixels {
+        for i in 0..channels {
+            for j in 0..channels {
+                covariance[i][j] += (pixel[i] as f32 - mean[i]) * (pixel[j] as f32 - mean[j]);
+            }
+        }
+    }
+    // Power iteration from the longest diagonal
+    let mut axis = [1.0f32; 4];
+    for _ in 0..8 {
+        let mut next = [0.0f32; 4];
+        for i in 0..channels {
+            for j in 0..channels {
+                next[i] += covariance[i][j] * axis[j];
+            }
+        }
+        let length = next.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if length < 1e-6 {
+            break;
+        }
+        axis = next.map(|v| v / length);
+    }
+
+    let (mut low, mut high) = (f32::MAX, f32::MIN);
+    for pixel in pixels {
+        let t: f32 = (0..channels)
+            .map(|c| (pixel[c] as f32 - mean[c]) * axis[c])
+            .sum();
+        low = low.min(t);
+        high = high.max(t);
+    }
+    if low > high {
+        (low, high) = (0.0, 0.0);
+    }
+    let mut e0 = [255.0f32; 4];
+    let mut e1 = [255.0f32; 4];
+    for c in 0..channels {
+        e0[c] = (mean[c] + axis[c] * low).clamp(0.0, 255.0);
+        e1[c] = (mean[c] + axis[c] * high).clamp(0.0, 255.0);
+    }
+    (e0, e1)
+}
+
+/// Index of the palette color closest to `pixel`
+fn nearest(palette: &[[i32; 4]], pixel: &[u8; 4]) -> usize {
+    let distance =
+        |color: &[i32; 4]| -> i32 { (0..4).map(|c| (color[c] - pixel[c] as i32).pow(2)).sum() };
+    (0..palette.len())
+        .min_by_key(|&i| distance(&palette[i]))
+        .unwrap_or(0)
+}
+
+const BC7_WEIGHTS: [i32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/// BC7 mode 6: RGBA endpoints at 7 bits plus a shared low bit each
+fn encode_bc7(pixels: &[[u8; 4]; 16]) -> [u8; 16] {
+    let (e0, e1) = fit_endpoints(pixels, 4);
+    let mut endpoints = [quantize_bc7_endpoint(&e0), quantize_bc7_endpoint(&e1)];
+    let expand = |(colors, p): &([u8; 4], u8)| colors.map(|c| ((c << 1) | p) as i32);
+
+    let mut indices = [0usize; 16];
+    let (c0, c1) = (expand(&endpoints[0]), expand(&endpoints[1]));
+    let palette: Vec<[i32; 4]> = BC7_WEIGHTS
+        .iter()
+        .map(|&w| std::array::from_fn(|c| ((64 - w) * c0[c] + w * c1[c] + 32) >> 6))
+        .collect();
+    for (index, pixel) in indices.iter_mut().zip(pixels) {
+        *index = nearest(&palette, pixel);
+    }
+    // The first pixel's index is stored without its top bit
+    if indices[0] >= 8 {
+        endpoints.swap(0, 1);
+        indices = indices.map(|i| 15 - i);
+    }
+
+    let mut bits = BlockBits::<16>::new();
+    bits.push(1 << 6, 7);
+    for c in 0..4 {
+        bits.push(endpoints[0].0[c] as u32, 7);
+        bits.push(endpoints[1].0[c] as u32, 7);
+    }
+    bits.push(endpoints[0].1 as u32, 1);
+    bits.push(endpoints[1].1 as u32, 1);
+    for (i, &index) in indices.iter().enumerate() {
+        bits.push(index as u32, if i == 0 { 3 } else { 4 });
+    }
+    bits.bytes
+}
+
+/// 7-bit channels and the shared low bit that round `color` best
+fn quantize_bc7_endpoint(color: &[f32; 4]) -> ([u8; 4], u8) {
+    let candidate = |p: u8| {
+        let channels = color.map(|c| ((c - p as f32) / 2.0).round().clamp(0.0, 127.0) as u8);
+        let error: f32 = (0..4)
+            .map(|c| (((channels[c] << 1) | p) as f32 - color[c]).powi(2))
+            .sum();
+        (channels, p, error)
+    };
+    let (zero, one) = (candidate(0), candidate(1));
+    let best = if one.2 < zero.2 { one } else { zero };
+    (best.0, best.1)
+}
+
+/// ASTC 4x4, one partition, a 4x4 weight grid and 8-bit endpoints. Opaque
+/// blocks use RGB endpoints with 3-bit weights, others RGBA with 2-bit weights.
+fn encode_astc(pixels: &[[u8; 4]; 16]) -> [u8; 16] {
+    let opaque = pixels.iter().all(|p| p[3] == 255);
+    let (channels, block_mode, endpoint_mode, weights): (usize, u32, u32, &[i32]) = if opaque {
+        (3, 83, 8, &[0, 9, 18, 27, 37, 46, 55, 64])
+    } else {
+        (4, 66, 12, &[0, 21, 43, 64])
+    };
+    let weight_bits = weights.len().trailing_zeros() as usize;
+
+    let (e0, e1) = fit_endpoints(pixels, channels);
+    let mut endpoints = [e0.map(|c| c.round() as u8), e1.map(|c| c.round() as u8)];
+    // A second endpoint with the smaller RGB sum would decode blue-contracted
+    let sum = |e: &[u8; 4]| e[0] as u32 + e[1] as u32 + e[2] as u32;
+    if sum(&endpoints[1]) < sum(&endpoints[0]) {
+        endpoints.swap(0, 1);
+    }
+
+    let (c0, c1) = (
+        endpoints[0].map(|c| c as i32),
+        endpoints[1].map(|c| c as i32),
+    );
+    let palette: Vec<[i32; 4]> = weights
+        .iter()
+        .map(|&w| std::array::from_fn(|c| ((64 - w) * c0[c] + w * c1[c] + 32) >> 6))
+        .collect();
+
+    let mut bits = BlockBits::<16>::new();
+    bits.push(block_mode, 11);
+    bits.push(0, 2); // One partition
+    bits.push(endpoint_mode, 4);
+    for (&low, &high) in endpoints[0].iter().zip(&endpoints[1]).take(channels) {
+        bits.push(low as u32, 8);
+        bits.push(high as u32, 8);
+    }
+    // Weights fill the block from the top bit down
+    for (i, pixel) in pixels.iter().enumerate() {
+        let weight = nearest(&palette, pixel) as u32;
+        for bit in 0..weight_bits {
+            bits.set(127 - (i * weight_bits + bit), (weight >> bit) & 1 != 0);
+        }
+    }
+    bits.bytes
+}
+
+/// FNV-1a, stable across builds unlike std's hasher
+fn content_hash(parts: &[&[u8]]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for part in parts {
+        for &byte in *part {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// Compressed textures saved on disk, so each is encoded once
+#[derive(Debug, Clone)]
+pub struct CompressedTextureCache {
+    dir: PathBuf,
+}
+
+impl CompressedTextureCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Cache file for `texture` in `format`
+    pub fn path_for(&self, texture: &Texture, format: CompressedFormat) -> PathBuf {
+        let header = [
+            &texture.width.to_le_bytes()[..],
+            &texture.height.to_le_bytes(),
+            &[texture.bytes_per_pixel() as u8, format.id()],
+            &COMPRESSED_CACHE_VERSION.to_le_bytes(),
+        ]
+        .concat();
+        let hash = content_hash(&[&header, &texture.data]);
+        self.dir.join(format!("{:016x}.ctex", hash))
+    }
+
+    /// The cached compressed texture, or compress it now and save it. A cache
+    /// that can't be written only costs the next run another encode.
+    pub fn load_or_compress(
+        &self,
+        texture: &Texture,
+        format: CompressedFormat,
+    ) -> CompressedTexture {
+        let path = self.path_for(texture, format);
+        if let Ok(bytes) = std::fs::read(&path) {
+            match CompressedTexture::from_bytes(texture.name.clone(), &bytes) {
+                Ok(compressed) if compressed.format == format => return compressed,
+                Ok(_) => {}
+                Err(e) => log::warn!("Ignoring cached texture {:?}: {}", path, e),
+            }
+        }
+
+        let start = std::time::Instant::now();
+        let compressed = compress_texture(texture, format);
+        log::info!(
+            "Compressed {} ({}x{}) to {:?} in {:.2?}",
+            texture.name,
+            texture.width,
+            texture.height,
+            format,
+            start.elapsed()
+        );
+        let saved = std::fs::create_dir_all(&self.dir)
+            .and_then(|_| std::fs::write(&path, compressed.to_bytes()));
+        if let Err(e) = saved {
+            log::warn!("Failed to cache compressed texture {:?}: {}", path, e);
+        }
+        compressed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads bits back out of a block, low bit first
+    fn bits(block: &[u8], start: usize, count: usize) -> u32 {
+        (0..count)
+            .map(|i| ((block[(start + i) / 8] >> ((start + i) % 8)) as u32 & 1) << i)
+            .sum()
+    }
+
+    /// Orange to blue along x, so each block's colors lie on one line
+    fn gradient(width: u32, height: u32) -> Texture {
+        let mut data = Vec::new();
+        for _ in 0..height {
+            for x in 0..width {
+                let t = x * 255 / (width - 1);
+                data.extend_from_slice(&[(255 - t) as u8, (128 - t / 2) as u8, t as u8, 255]);
+            }
+        }
+        Texture::new(
+            "gradient".to_string(),
+            width,
+            height,
+            data,
+            TextureFormat::Rgba8,
+        )
+    }
+
+    #[test]
+    fn test_bc7_mode6_decodes_close_to_the_source() {
+        let texture = gradient(8, 8);
+        let compressed = compress_texture(&texture, CompressedFormat::Bc7);
+        assert_eq!(compressed.levels.len(), 4);
+        assert_eq!(compressed.levels[0].data.len(), 4 * 16);
+        assert_eq!(compressed.levels[3].data.len(), 16);
+
+        // Decode the first block by hand
+        let block = &compressed.levels[0].data[..16];
+        assert_eq!(bits(block, 0, 7), 1 << 6);
+        let endpoint = |e: usize, c: usize| {
+            (bits(block, 7 + c * 14 + e * 7, 7) << 1 | bits(block, 63 + e, 1)) as i32
+        };
+        for i in 0..16 {
+            let index = if i == 0 {
+                bits(block, 65, 3)
+            } else {
+                bits(block, 68 + (i - 1) * 4, 4)
+            };
+            let w = BC7_WEIGHTS[index as usize];
+            let source = &texture.data[((i / 4) * 8 + i % 4) * 4..][..4];
+            for (c, &expected) in source.iter().enumerate() {
+                let decoded = ((64 - w) * endpoint(0, c) + w * endpoint(1, c) + 32) >> 6;
+                assert!(
+                    (decoded - expected as i32).abs() <= 12,
+                    "pixel {} channel {}",
+                    i,
+                    c
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_bc4_and_astc_blocks_and_cache_round_trip() {
+        let flat = [[200u8, 10, 0, 255]; 16];
+        let bc4 = encode_bc4(&flat.map(|p| p[0]));
+        assert_eq!((bc4[0], bc4[1]), (200, 200));
+
+        // Opaque ASTC blocks: 3-bit weight grid, RGB endpoints after the header
+        let astc = encode_astc(&flat);
+        assert_eq!(bits(&astc, 0, 11), 83);
+        assert_eq!(bits(&astc, 13, 4), 8);
+        for (c, &channel) in flat[0][..3].iter().enumerate() {
+            assert_eq!(bits(&astc, 17 + c * 16, 8), channel as u32);
+        }
+
+        let texture = gradient(16, 12);
+        assert_eq!(
+            CompressedFormat::choose(&texture, TextureKind::Normal, true),
+            Some(CompressedFormat::Bc5)
+        );
+        assert_eq!(
+            CompressedFormat::choose(&texture, TextureKind::Color, false),
+            Some(CompressedFormat::Astc4x4)
+        );
+        assert_eq!(
+            CompressedFormat::choose(&gradient(10, 8), TextureKind::Color, true),
+            None
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let cache = CompressedTextureCache::new(dir.path());
+        let compressed = cache.load_or_compress(&texture, CompressedFormat::Bc5);
+        assert!(cache.path_for(&texture, CompressedFormat::Bc5).exists());
+        assert_eq!(
+            cache.load_or_compress(&texture, CompressedFormat::Bc5),
+            compressed
+        );
+        assert_eq!(
+            compressed
+                .levels
+                .iter()
+                .map(|l| (l.width, l.height))
+                .collect::<Vec<_>>(),
+            [(16, 12), (8, 6), (4, 3), (2, 1), (1, 1)]
+        );
+    }
+}
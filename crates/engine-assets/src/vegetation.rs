@@ -1,6 +1,8 @@
 // Procedural vegetation mesh generation
 //
 // Generates realistic tree and bush meshes with branches and leaf clusters.
+// Each vegetation type also has a low detail version with the same shape for
+// distant instances.
 
 use crate::mesh::{Mesh, Vertex};
 use glam::{Vec2, Vec3};
@@ -33,6 +35,8 @@ pub struct TreeConfig {
     pub leaves_per_branch: u32,
     /// Color of the leaves
     pub leaf_color: Vec3,
+    /// Number of segments around each leaf cluster
+    pub leaf_segments: u32,
     /// Random seed for variation
     pub seed: u32,
 }
@@ -52,11 +56,61 @@ impl Default for TreeConfig {
             leaf_size: 0.3,
             leaves_per_branch: 3,
             leaf_color: Vec3::new(0.2, 0.5, 0.15),
+            leaf_segments: 8,
             seed: 12345,
         }
     }
 }
 
+impl TreeConfig {
+    /// Pine tree with tiered, nearly horizontal branches
+    pub fn pine() -> Self {
+        Self {
+            trunk_height: 4.0,
+            trunk_radius: 0.12,
+            trunk_taper: 0.5,
+            trunk_segments: 8,
+            trunk_color: Vec3::new(0.35, 0.2, 0.1),
+            branch_levels: 5,
+            branches_per_level: 6,
+            branch_length: 0.35,
+            branch_angle: 0.9, // More horizontal for pine
+            leaf_size: 0.25,
+            leaves_per_branch: 2,
+            leaf_color: Vec3::new(0.1, 0.35, 0.15),
+            leaf_segments: 8,
+            seed: 11111,
+        }
+    }
+
+    /// Oak-style deciduous tree
+    pub fn oak() -> Self {
+        Self {
+            trunk_height: 3.5,
+            trunk_radius: 0.2,
+            trunk_taper: 0.35,
+            trunk_segments: 10,
+            trunk_color: Vec3::new(0.45, 0.3, 0.15),
+            branch_levels: 4,
+            branches_per_level: 4,
+            branch_length: 0.5,
+            branch_angle: 0.6, // More upward for oak
+            leaf_size: 0.4,
+            leaves_per_branch: 4,
+            leaf_color: Vec3::new(0.25, 0.5, 0.2),
+            leaf_segments: 8,
+            seed: 22222,
+        }
+    }
+
+    /// Same tree with far fewer triangles
+    pub fn low_detail(mut self) -> Self {
+        self.trunk_segments = 4;
+        self.leaf_segments = 4;
+        self
+    }
+}
+
 /// Configuration for procedural bush generation
 #[derive(Debug, Clone)]
 pub struct BushConfig {
@@ -70,6 +124,8 @@ pub struct BushConfig {
     pub cluster_size: f32,
     /// Base color of the bush
     pub color: Vec3,
+    /// Number of segments around each leaf cluster
+    pub leaf_segments: u32,
     /// Random seed
     pub seed: u32,
 }
@@ -82,11 +138,46 @@ impl Default for BushConfig {
             cluster_count: 12,
             cluster_size: 0.35,
             color: Vec3::new(0.25, 0.45, 0.2),
+            leaf_segments: 8,
             seed: 54321,
         }
     }
 }
 
+impl BushConfig {
+    /// Small bush
+    pub fn small() -> Self {
+        Self {
+            radius: 0.5,
+            height: 0.4,
+            cluster_count: 8,
+            cluster_size: 0.25,
+            color: Vec3::new(0.3, 0.5, 0.25),
+            leaf_segments: 8,
+            seed: 33333,
+        }
+    }
+
+    /// Larger shrub
+    pub fn shrub() -> Self {
+        Self {
+            radius: 1.0,
+            height: 0.8,
+            cluster_count: 16,
+            cluster_size: 0.35,
+            color: Vec3::new(0.2, 0.4, 0.15),
+            leaf_segments: 8,
+            seed: 44444,
+        }
+    }
+
+    /// Same bush with far fewer triangles
+    pub fn low_detail(mut self) -> Self {
+        self.leaf_segments = 4;
+        self
+    }
+}
+
 // Simple pseudo-random number generator
 struct SimpleRng {
     state: u32,
@@ -165,7 +256,7 @@ pub fn generate_tree(config: &TreeConfig) -> Mesh {
                 branch_len,
                 branch_radius,
                 branch_radius * 0.3,
-                6,
+                config.trunk_segments.min(6),
                 config.trunk_color * 0.9,
             );
 
@@ -192,6 +283,7 @@ pub fn generate_tree(config: &TreeConfig) -> Mesh {
                     leaf_pos + offset,
                     cluster_size,
                     color_var.clamp(Vec3::ZERO, Vec3::ONE),
+                    config.leaf_segments,
                     &mut rng,
                 );
             }
@@ -203,6 +295,7 @@ pub fn generate_tree(config: &TreeConfig) -> Mesh {
                 branch_end + Vec3::new(rng.range(-0.1, 0.1), 0.05, rng.range(-0.1, 0.1)),
                 config.leaf_size * 1.2,
                 config.leaf_color * rng.range(0.9, 1.1),
+                config.leaf_segments,
                 &mut rng,
             );
         }
@@ -223,6 +316,7 @@ pub fn generate_tree(config: &TreeConfig) -> Mesh {
             top_pos + offset,
             config.leaf_size * rng.range(0.8, 1.2),
             config.leaf_color * rng.range(0.9, 1.1),
+            config.leaf_segments,
             &mut rng,
         );
     }
@@ -263,6 +357,7 @@ pub fn generate_bush(config: &BushConfig) -> Mesh {
             pos,
             size,
             color.clamp(Vec3::ZERO, Vec3::ONE),
+            config.leaf_segments,
             &mut rng,
         );
     }
@@ -282,6 +377,7 @@ pub fn generate_bush(config: &BushConfig) -> Mesh {
             pos,
             config.cluster_size * 0.8,
             config.color * rng.range(0.8, 1.0),
+            config.leaf_segments,
             &mut rng,
         );
     }
@@ -293,68 +389,22 @@ pub fn generate_bush(config: &BushConfig) -> Mesh {
 
 /// Generate a pine tree with tiered branches
 pub fn generate_pine_tree() -> Mesh {
-    let config = TreeConfig {
-        trunk_height: 4.0,
-        trunk_radius: 0.12,
-        trunk_taper: 0.5,
-        trunk_segments: 8,
-        trunk_color: Vec3::new(0.35, 0.2, 0.1),
-        branch_levels: 5,
-        branches_per_level: 6,
-        branch_length: 0.35,
-        branch_angle: 0.9, // More horizontal for pine
-        leaf_size: 0.25,
-        leaves_per_branch: 2,
-        leaf_color: Vec3::new(0.1, 0.35, 0.15),
-        seed: 11111,
-    };
-    generate_tree(&config)
+    generate_tree(&TreeConfig::pine())
 }
 
 /// Generate an oak-style deciduous tree
 pub fn generate_oak_tree() -> Mesh {
-    let config = TreeConfig {
-        trunk_height: 3.5,
-        trunk_radius: 0.2,
-        trunk_taper: 0.35,
-        trunk_segments: 10,
-        trunk_color: Vec3::new(0.45, 0.3, 0.15),
-        branch_levels: 4,
-        branches_per_level: 4,
-        branch_length: 0.5,
-        branch_angle: 0.6, // More upward for oak
-        leaf_size: 0.4,
-        leaves_per_branch: 4,
-        leaf_color: Vec3::new(0.25, 0.5, 0.2),
-        seed: 22222,
-    };
-    generate_tree(&config)
+    generate_tree(&TreeConfig::oak())
 }
 
 /// Generate a small bush
 pub fn generate_small_bush() -> Mesh {
-    let config = BushConfig {
-        radius: 0.5,
-        height: 0.4,
-        cluster_count: 8,
-        cluster_size: 0.25,
-        color: Vec3::new(0.3, 0.5, 0.25),
-        seed: 33333,
-    };
-    generate_bush(&config)
+    generate_bush(&BushConfig::small())
 }
 
 /// Generate a larger shrub
 pub fn generate_shrub() -> Mesh {
-    let config = BushConfig {
-        radius: 1.0,
-        height: 0.8,
-        cluster_count: 16,
-        cluster_size: 0.35,
-        color: Vec3::new(0.2, 0.4, 0.15),
-        seed: 44444,
-    };
-    generate_bush(&config)
+    generate_bush(&BushConfig::shrub())
 }
 
 // ============ Internal generation functions ============
@@ -428,13 +478,17 @@ fn generate_leaf_cluster(
     center: Vec3,
     size: f32,
     color: Vec3,
+    segments: u32,
     rng: &mut SimpleRng,
 ) {
     let base_index = vertices.len() as u32;
+    // Bumps come from a stream of their own, so the vertex count doesn't move
+    // the clusters generated after this one and every detail level keeps its shape
+    let mut rng = SimpleRng::new(rng.state ^ 0x9e37_79b9);
 
     // Generate a bumpy spheroid for leaf cluster
-    let segments = 8u32;
-    let rings = 5u32;
+    let segments = segments.max(3);
+    let rings = (segments * 5 / 8).max(2);
 
     for ring in 0..=rings {
         let v = ring as f32 / rings as f32;
@@ -525,4 +579,43 @@ impl VegetationType {
             VegetationType::Shrub => generate_shrub(),
         }
     }
+
+    /// Name of the low detail mesh drawn for distant instances
+    pub fn lod_mesh_name(&self) -> String {
+        format!("{}_lod", self.mesh_name())
+    }
+
+    pub fn generate_lod_mesh(&self) -> Mesh {
+        match self {
+            VegetationType::PineTree => generate_tree(&TreeConfig::pine().low_detail()),
+            VegetationType::OakTree => generate_tree(&TreeConfig::oak().low_detail()),
+            VegetationType::Bush => generate_bush(&BushConfig::small().low_detail()),
+            VegetationType::Shrub => generate_bush(&BushConfig::shrub().low_detail()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lod_mesh_keeps_shape_with_fewer_triangles() {
+        let bounds = |mesh: &Mesh| {
+            mesh.vertices.iter().fold((Vec3::MAX, Vec3::MIN), |(min, max), vertex| {
+                (min.min(vertex.position), max.max(vertex.position))
+            })
+        };
+        for vegetation_type in VegetationType::all() {
+            let full = vegetation_type.generate_mesh();
+            let lod = vegetation_type.generate_lod_mesh();
+            assert!(lod.indices.len() * 2 < full.indices.len());
+
+            let (full_min, full_max) = bounds(&full);
+            let (lod_min, lod_max) = bounds(&lod);
+            let size = (full_max - full_min).length();
+            assert!((full_min - lod_min).length() < size * 0.2);
+            assert!((full_max - lod_max).length() < size * 0.2);
+        }
+    }
 }
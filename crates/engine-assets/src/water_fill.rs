@@ -67,6 +67,16 @@ const NEIGHBORS: [(i32, i32); 8] = [
     (-1,  1), (0,  1), (1,  1),
 ];
 
+/// A painted flow vector used to override a water body's flow
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlowSample {
+    /// World-space XZ position
+    pub position: [f32; 2],
+    /// XZ direction
+    pub direction: [f32; 2],
+    pub speed: f32,
+}
+
 /// Priority-flood algorithm for depression filling
 ///
 /// This algorithm fills terrain depressions below the ground_water_level.
@@ -76,6 +86,41 @@ pub fn compute_water_fill(
     ground_water_level: f32,
     min_depth: f32,
     min_area: usize,
+) -> WaterFillResult {
+    compute_water_fill_masked(heightmap, ground_water_level, min_depth, min_area, &[])
+}
+
+/// Grid cells covered by world-space circles (center XZ, radius), for
+/// excluding areas from the water fill
+pub fn exclusion_mask(heightmap: &HeightMap, scale: f32, circles: &[([f32; 2], f32)]) -> Vec<bool> {
+    let mut mask = vec![false; heightmap.width * heightmap.depth];
+    if circles.is_empty() {
+        return mask;
+    }
+    let cell_size = scale / heightmap.width as f32;
+    for z in 0..heightmap.depth {
+        for x in 0..heightmap.width {
+            let world_x = -scale * 0.5 + x as f32 * cell_size;
+            let world_z = -scale * 0.5 + z as f32 * cell_size;
+            mask[z * heightmap.width + x] = circles.iter().any(|(center, radius)| {
+                let dx = world_x - center[0];
+                let dz = world_z - center[1];
+                dx * dx + dz * dz <= radius * radius
+            });
+        }
+    }
+    mask
+}
+
+/// Like `compute_water_fill`, but cells set in `excluded` are kept dry. The
+/// surrounding water keeps its level; excluded cells just cut holes in it.
+/// An empty mask excludes nothing.
+pub fn compute_water_fill_masked(
+    heightmap: &HeightMap,
+    ground_water_level: f32,
+    min_depth: f32,
+    min_area: usize,
+    excluded: &[bool],
 ) -> WaterFillResult {
     let width = heightmap.width;
     let depth = heightmap.depth;
@@ -138,6 +183,13 @@ pub fn compute_water_fill(
         }
     }
 
+    // Remove water from excluded cells
+    for (idx, &is_excluded) in excluded.iter().enumerate().take(water_levels.len()) {
+        if is_excluded {
+            water_levels[idx] = heightmap.heights[idx];
+        }
+    }
+
     // Step 3: Identify water bodies (connected components where water_level > terrain_height)
     let water_bodies = identify_water_bodies(
         &water_levels,
@@ -380,6 +432,42 @@ fn detect_overflow_connections(
     }
 }
 
+/// Point painted flow samples at the water bodies that contain them. A body
+/// with samples flows along their averaged direction at their average speed.
+pub fn apply_flow_overrides(
+    bodies: &mut [ComputedWaterBody],
+    heightmap: &HeightMap,
+    scale: f32,
+    samples: &[FlowSample],
+) {
+    if samples.is_empty() {
+        return;
+    }
+    for body in bodies.iter_mut() {
+        let cells: HashSet<(usize, usize)> = body.cells.iter().copied().collect();
+        let mut direction = Vec2::ZERO;
+        let mut speed = 0.0;
+        let mut count = 0;
+        for sample in samples {
+            let (grid_x, grid_z) =
+                heightmap.world_to_grid(sample.position[0], sample.position[1], scale);
+            let (grid_x, grid_z) = (grid_x.round(), grid_z.round());
+            if grid_x < 0.0 || grid_z < 0.0 || !cells.contains(&(grid_x as usize, grid_z as usize))
+            {
+                continue;
+            }
+            direction += Vec2::from(sample.direction).normalize_or_zero();
+            speed += sample.speed;
+            count += 1;
+        }
+        if count > 0 {
+            let direction = direction.normalize_or_zero();
+            body.flow_direction = Some([direction.x, direction.y]);
+            body.flow_speed = speed / count as f32;
+        }
+    }
+}
+
 /// Generate a water mesh for a water body
 /// Creates surface quads and extends edges outward to meet terrain at water level
 pub fn generate_water_mesh(
@@ -566,3 +654,65 @@ pub fn generate_water_mesh(
     mesh.calculate_tangents();
     mesh
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 9x9 bowl: a 5x5 basin at height 0 surrounded by a rim at height 2
+    fn bowl() -> HeightMap {
+        let mut heights = vec![2.0; 81];
+        for z in 2..7 {
+            for x in 2..7 {
+                heights[z * 9 + x] = 0.0;
+            }
+        }
+        HeightMap {
+            width: 9,
+            depth: 9,
+            heights,
+            holes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_exclusion_cuts_hole_without_draining() {
+        let heightmap = bowl();
+        let full = compute_water_fill(&heightmap, 5.0, 0.1, 1);
+        assert_eq!(full.water_bodies.len(), 1);
+        assert_eq!(full.water_bodies[0].cells.len(), 25);
+
+        // Scale 9 makes one grid cell one world unit; grid (4, 4) is world (-0.5, -0.5)
+        let mask = exclusion_mask(&heightmap, 9.0, &[([-0.5, -0.5], 0.5)]);
+        assert_eq!(mask.iter().filter(|&&m| m).count(), 1);
+        let masked = compute_water_fill_masked(&heightmap, 5.0, 0.1, 1, &mask);
+        assert_eq!(masked.water_bodies.len(), 1);
+        assert_eq!(masked.water_bodies[0].cells.len(), 24);
+        assert_eq!(
+            masked.water_bodies[0].surface_level,
+            full.water_bodies[0].surface_level
+        );
+    }
+
+    #[test]
+    fn test_flow_override_applies_to_containing_body() {
+        let heightmap = bowl();
+        let mut result = compute_water_fill(&heightmap, 5.0, 0.1, 1);
+        let samples = [
+            FlowSample {
+                position: [-0.5, -0.5],
+                direction: [2.0, 0.0],
+                speed: 1.0,
+            },
+            // Outside the basin, ignored
+            FlowSample {
+                position: [-4.5, -4.5],
+                direction: [0.0, 1.0],
+                speed: 9.0,
+            },
+        ];
+        apply_flow_overrides(&mut result.water_bodies, &heightmap, 9.0, &samples);
+        assert_eq!(result.water_bodies[0].flow_direction, Some([1.0, 0.0]));
+        assert_eq!(result.water_bodies[0].flow_speed, 1.0);
+    }
+}
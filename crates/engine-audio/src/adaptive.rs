@@ -0,0 +1,379 @@
+// Adaptive music - tracks made of stems whose volumes follow game parameters
+//
+// An adaptive track is a set of stems (drums, strings, choir...) that play in
+// sync, each faded in by a named parameter such as "combat_intensity". When a
+// parameter changes, the stems' new volumes take effect on the next beat or
+// bar and ramp over a few beats, so layers enter and leave in time with the
+// music. Tracks are declared in a RON file (assets/music/adaptive.ron); stems
+// can be hand-made or tracks generated by engine_ai_music.
+
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// When a parameter change takes effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransitionSync {
+    Immediate,
+    #[default]
+    Beat,
+    Bar,
+}
+
+/// One stem of an adaptive track
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MusicLayer {
+    /// Audio file (relative to the asset root)
+    pub stem: String,
+    /// Parameter that fades the layer in (None = always playing)
+    #[serde(default)]
+    pub parameter: Option<String>,
+    /// Parameter value where the layer starts to be heard
+    #[serde(default)]
+    pub enter: f32,
+    /// Parameter value where the layer is at full volume (below `enter`
+    /// to fade the layer out as the parameter rises)
+    #[serde(default = "default_full")]
+    pub full: f32,
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+}
+
+fn default_full() -> f32 {
+    1.0
+}
+
+fn default_volume() -> f32 {
+    1.0
+}
+
+impl MusicLayer {
+    /// The layer's volume for the given parameters (missing ones are 0)
+    pub fn gain(&self, parameters: &HashMap<String, f32>) -> f32 {
+        let Some(parameter) = &self.parameter else {
+            return self.volume;
+        };
+        let value = parameters.get(parameter).copied().unwrap_or(0.0);
+        let t = if self.full == self.enter {
+            if value >= self.enter {
+                1.0
+            } else {
+                0.0
+            }
+        } else {
+            (value - self.enter) / (self.full - self.enter)
+        };
+        self.volume * t.clamp(0.0, 1.0)
+    }
+}
+
+/// A track of stems mixed by game parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdaptiveTrack {
+    /// Tempo the stems were written at
+    pub bpm: f32,
+    #[serde(default = "default_beats_per_bar")]
+    pub beats_per_bar: u32,
+    #[serde(default = "default_track_volume")]
+    pub volume: f32,
+    /// Where parameter changes wait for
+    #[serde(default)]
+    pub sync: TransitionSync,
+    /// Beats a layer takes to reach its new volume
+    #[serde(default = "default_transition_beats")]
+    pub transition_beats: f32,
+    pub layers: Vec<MusicLayer>,
+}
+
+fn default_beats_per_bar() -> u32 {
+    4
+}
+
+fn default_track_volume() -> f32 {
+    0.7
+}
+
+fn default_transition_beats() -> f32 {
+    1.0
+}
+
+impl AdaptiveTrack {
+    /// Every layer's volume for the given parameters
+    pub fn gains(&self, parameters: &HashMap<String, f32>) -> Vec<f32> {
+        self.layers
+            .iter()
+            .map(|layer| layer.gain(parameters))
+            .collect()
+    }
+
+    /// Frames in `beats` beats at `sample_rate`
+    fn frames(&self, beats: f32, sample_rate: u32) -> u64 {
+        (beats.max(0.0) * 60.0 / self.bpm.max(1.0) * sample_rate as f32) as u64
+    }
+}
+
+/// Named adaptive tracks (loaded from RON)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AdaptiveMusicConfig {
+    #[serde(default)]
+    pub tracks: HashMap<String, AdaptiveTrack>,
+}
+
+impl AdaptiveMusicConfig {
+    /// Parse configuration from a RON string
+    pub fn from_ron(source: &str) -> anyhow::Result<Self> {
+        Ok(ron::from_str(source)?)
+    }
+
+    /// Load configuration from a RON file
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let source = std::fs::read_to_string(path.as_ref())?;
+        Self::from_ron(&source)
+    }
+
+    pub fn track(&self, name: &str) -> Option<&AdaptiveTrack> {
+        self.tracks.get(name)
+    }
+}
+
+/// New layer volumes for a playing LayerMixer, set from the game thread
+#[derive(Debug, Default)]
+pub struct LayerControl {
+    pending: Mutex<Option<Vec<f32>>>,
+    has_pending: AtomicBool,
+}
+
+impl LayerControl {
+    /// Move the layers to `gains` on the track's next transition point
+    pub fn set_gains(&self, gains: Vec<f32>) {
+        *self.pending.lock().unwrap() = Some(gains);
+        self.has_pending.store(true, Ordering::Release);
+    }
+
+    fn take(&self) -> Option<Vec<f32>> {
+        if !self.has_pending.swap(false, Ordering::AcqRel) {
+            return None;
+        }
+        self.pending.lock().unwrap().take()
+    }
+}
+
+/// A layer's volume ramp, in frames since the track started
+#[derive(Debug, Clone, Copy)]
+struct Ramp {
+    from: f32,
+    to: f32,
+    start: u64,
+    end: u64,
+}
+
+impl Ramp {
+    fn gain(&self, frame: u64) -> f32 {
+        if frame <= self.start {
+            self.from
+        } else if frame >= self.end {
+            self.to
+        } else {
+            let t = (frame - self.start) as f32 / (self.end - self.start) as f32;
+            self.from + (self.to - self.from) * t
+        }
+    }
+}
+
+/// Mixes an adaptive track's stems, which must share a channel count and
+/// sample rate. Ends when every stem has.
+pub struct LayerMixer {
+    stems: Vec<Option<Box<dyn Source<Item = f32> + Send>>>,
+    ramps: Vec<Ramp>,
+    control: Arc<LayerControl>,
+    channels: u16,
+    sample_rate: u32,
+    /// Frames between transition points
+    sync_frames: u64,
+    transition_frames: u64,
+    frame: u64,
+    /// Samples left in the current frame
+    frame_left: usize,
+    gains: Vec<f32>,
+}
+
+impl LayerMixer {
+    pub fn new(
+        track: &AdaptiveTrack,
+        stems: Vec<Box<dyn Source<Item = f32> + Send>>,
+        gains: Vec<f32>,
+        control: Arc<LayerControl>,
+    ) -> Self {
+        let channels = stems.first().map_or(1, |stem| stem.channels()).max(1);
+        let sample_rate = stems.first().map_or(1, |stem| stem.sample_rate()).max(1);
+        let sync_frames = match track.sync {
+            TransitionSync::Immediate => 1,
+            TransitionSync::Beat => track.frames(1.0, sample_rate),
+            TransitionSync::Bar => track.frames(track.beats_per_bar as f32, sample_rate),
+        };
+        let ramps = gains
+            .iter()
+            .map(|&gain| Ramp {
+                from: gain,
+                to: gain,
+                start: 0,
+                end: 0,
+            })
+            .collect();
+        Self {
+            stems: stems.into_iter().map(Some).collect(),
+            ramps,
+            control,
+            channels,
+            sample_rate,
+            sync_frames: sync_frames.max(1),
+            transition_frames: track.frames(track.transition_beats, sample_rate),
+            frame: 0,
+            frame_left: 0,
+            gains,
+        }
+    }
+
+    /// Start ramping to `targets` at the next transition point
+    fn transition(&mut self, targets: Vec<f32>) {
+        let start = self.frame.div_ceil(self.sync_frames) * self.sync_frames;
+        for (ramp, to) in self.ramps.iter_mut().zip(targets) {
+            *ramp = Ramp {
+                from: ramp.gain(start),
+                to,
+                start,
+                end: start + self.transition_frames,
+            };
+        }
+    }
+}
+
+impl Iterator for LayerMixer {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.frame_left == 0 {
+            if let Some(targets) = self.control.take() {
+                self.transition(targets);
+            }
+            for (gain, ramp) in self.gains.iter_mut().zip(&self.ramps) {
+                *gain = ramp.gain(self.frame);
+            }
+            self.frame += 1;
+            self.frame_left = self.channels as usize;
+        }
+        self.frame_left -= 1;
+
+        let mut mixed = None;
+        for (stem, gain) in self.stems.iter_mut().zip(&self.gains) {
+            let Some(source) = stem else { continue };
+            match source.next() {
+                Some(sample) => *mixed.get_or_insert(0.0) += sample * gain,
+                None => *stem = None,
+            }
+        }
+        mixed
+    }
+}
+
+impl Source for LayerMixer {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::{LoopPoints, PcmLoop};
+    use engine_assets::PcmData;
+
+    const CONFIG: &str = r#"
+        (
+            tracks: {
+                "battle": (
+                    bpm: 120,
+                    sync: bar,
+                    layers: [
+                        (stem: "music/battle_pads.ogg"),
+                        (stem: "music/battle_drums.ogg", parameter: Some("combat_intensity"), enter: 0.25, full: 0.75),
+                        (stem: "music/battle_calm.ogg", parameter: Some("combat_intensity"), enter: 0.5, full: 0.0),
+                    ],
+                ),
+            },
+        )
+    "#;
+
+    #[test]
+    fn test_layer_gains_follow_parameters() {
+        let config = AdaptiveMusicConfig::from_ron(CONFIG).unwrap();
+        let track = config.track("battle").unwrap();
+        assert_eq!(track.beats_per_bar, 4);
+        assert_eq!(track.sync, TransitionSync::Bar);
+
+        let mut parameters = HashMap::new();
+        assert_eq!(track.gains(&parameters), [1.0, 0.0, 1.0]);
+        parameters.insert("combat_intensity".to_string(), 0.5);
+        assert_eq!(track.gains(&parameters), [1.0, 0.5, 0.0]);
+        parameters.insert("combat_intensity".to_string(), 1.0);
+        assert_eq!(track.gains(&parameters), [1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_transitions_wait_for_the_beat() {
+        // 60 bpm at 4 Hz: a beat is 4 frames
+        let track = AdaptiveTrack {
+            bpm: 60.0,
+            beats_per_bar: 4,
+            volume: 1.0,
+            sync: TransitionSync::Beat,
+            transition_beats: 1.0,
+            layers: Vec::new(),
+        };
+        let stem = || -> Box<dyn Source<Item = f32> + Send> {
+            let pcm = PcmData {
+                sample_rate: 4,
+                channels: 1,
+                samples: vec![1.0; 4].into(),
+            };
+            Box::new(PcmLoop::new(
+                pcm,
+                LoopPoints {
+                    start: 0.0,
+                    end: None,
+                },
+            ))
+        };
+        let control = Arc::new(LayerControl::default());
+        let mut mixer = LayerMixer::new(
+            &track,
+            vec![stem(), stem()],
+            vec![1.0, 0.0],
+            control.clone(),
+        );
+
+        assert_eq!(mixer.next(), Some(1.0));
+        control.set_gains(vec![1.0, 1.0]);
+        let played: Vec<f32> = mixer.by_ref().take(9).collect();
+        // Held until the beat at frame 4, then the second layer comes in
+        // over a beat
+        assert_eq!(played, [1.0, 1.0, 1.0, 1.0, 1.25, 1.5, 1.75, 2.0, 2.0]);
+    }
+}
@@ -0,0 +1,166 @@
+// Audio buses - groups of sounds mixed and run through one effect chain
+//
+// Sound effects mix into the SFX bus (a rodio dynamic mixer that never ends);
+// music plays on its own sink through the music bus's chain. Effect settings
+// are shared with the audio thread, which picks up changes every
+// EFFECT_UPDATE_FRAMES frames.
+
+use rodio::dynamic_mixer::DynamicMixer;
+use rodio::Source;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::effects::{EffectChain, EffectSettings};
+
+/// Sample rate buses mix at; sources are resampled to it
+pub const BUS_SAMPLE_RATE: u32 = 48000;
+/// Channels buses mix in
+pub const BUS_CHANNELS: u16 = 2;
+/// Frames between checks for new effect settings
+pub const EFFECT_UPDATE_FRAMES: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AudioBus {
+    /// Sound effects, 2D and 3D
+    Sfx,
+    Music,
+}
+
+/// A bus's effect settings, shared with the audio thread
+#[derive(Debug, Clone, Default)]
+pub struct BusEffects {
+    settings: Arc<Mutex<EffectSettings>>,
+    version: Arc<AtomicU64>,
+}
+
+impl BusEffects {
+    pub fn get(&self) -> EffectSettings {
+        *self.settings.lock().unwrap()
+    }
+
+    pub fn set(&self, settings: EffectSettings) {
+        let mut current = self.settings.lock().unwrap();
+        if *current != settings {
+            *current = settings;
+            self.version.fetch_add(1, Ordering::Release);
+        }
+    }
+
+    fn version(&self) -> u64 {
+        self.version.load(Ordering::Acquire)
+    }
+}
+
+/// Runs a source through a bus's effect chain. The source's channel count
+/// and sample rate must not change while it plays.
+pub struct EffectSource<S> {
+    input: S,
+    chain: EffectChain,
+    effects: BusEffects,
+    version: u64,
+    frame: Vec<f32>,
+    position: usize,
+    frames_until_update: usize,
+}
+
+impl<S: Source<Item = f32>> EffectSource<S> {
+    pub fn new(input: S, effects: BusEffects) -> Self {
+        let channels = input.channels().max(1);
+        let chain = EffectChain::new(channels, input.sample_rate(), effects.get());
+        Self {
+            version: effects.version(),
+            input,
+            chain,
+            effects,
+            frame: vec![0.0; channels as usize],
+            position: channels as usize,
+            frames_until_update: EFFECT_UPDATE_FRAMES,
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for EffectSource<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.position == self.frame.len() {
+            // The next frame; a frame cut short is padded with silence
+            self.frame[0] = self.input.next()?;
+            for sample in &mut self.frame[1..] {
+                *sample = self.input.next().unwrap_or(0.0);
+            }
+
+            self.frames_until_update -= 1;
+            if self.frames_until_update == 0 {
+                self.frames_until_update = EFFECT_UPDATE_FRAMES;
+                let version = self.effects.version();
+                if version != self.version {
+                    self.version = version;
+                    self.chain.set_settings(self.effects.get());
+                }
+            }
+            self.chain.process_frame(&mut self.frame);
+            self.position = 0;
+        }
+        let sample = self.frame[self.position];
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for EffectSource<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.frame.len() as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+/// A bus mixer that plays silence while no sounds are on it, instead of
+/// ending
+pub struct BusMixer {
+    mixer: DynamicMixer<f32>,
+}
+
+impl BusMixer {
+    pub fn new(mixer: DynamicMixer<f32>) -> Self {
+        Self { mixer }
+    }
+}
+
+impl Iterator for BusMixer {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        Some(self.mixer.next().unwrap_or(0.0))
+    }
+}
+
+impl Source for BusMixer {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.mixer.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.mixer.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
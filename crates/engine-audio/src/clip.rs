@@ -0,0 +1,93 @@
+// Clip playback - rodio sources over engine_assets audio clips
+//
+// Decoded clips play straight from their shared PCM; encoded ones decode
+// while they play.
+
+use anyhow::{Context, Result};
+use engine_assets::{AudioClip, AudioClipData, PcmData};
+use rodio::{Decoder, Source};
+use std::io::Cursor;
+use std::time::Duration;
+
+/// A source for playing `clip` once
+pub fn clip_source(clip: &AudioClip) -> Result<Box<dyn Source<Item = f32> + Send>> {
+    clip_source_at(clip, 0.0)
+}
+
+/// A source for playing `clip` once from `seconds` in (encoded clips decode
+/// their way there)
+pub fn clip_source_at(
+    clip: &AudioClip,
+    seconds: f32,
+) -> Result<Box<dyn Source<Item = f32> + Send>> {
+    match &clip.data {
+        AudioClipData::Decoded(pcm) => {
+            let mut source = PcmSource::new(pcm.clone());
+            source.position =
+                sample_index(seconds, pcm.channels, pcm.sample_rate).min(pcm.samples.len());
+            Ok(Box::new(source))
+        }
+        AudioClipData::Encoded(bytes) => {
+            let decoder = Decoder::new(Cursor::new(bytes.clone()))
+                .with_context(|| format!("Failed to decode audio: {}", clip.name))?;
+            let skip = sample_index(seconds, decoder.channels(), decoder.sample_rate());
+            let mut source = decoder.convert_samples::<f32>();
+            for _ in 0..skip {
+                if source.next().is_none() {
+                    break;
+                }
+            }
+            Ok(Box::new(source))
+        }
+    }
+}
+
+/// Sample index (interleaved) of `seconds` into audio with this format
+pub(crate) fn sample_index(seconds: f32, channels: u16, sample_rate: u32) -> usize {
+    (seconds.max(0.0) as f64 * sample_rate as f64) as usize * channels.max(1) as usize
+}
+
+/// Plays decoded samples without copying them
+pub struct PcmSource {
+    pcm: PcmData,
+    position: usize,
+}
+
+impl PcmSource {
+    pub fn new(pcm: PcmData) -> Self {
+        Self { pcm, position: 0 }
+    }
+}
+
+impl Iterator for PcmSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.pcm.samples.get(self.position).copied()?;
+        self.position += 1;
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.pcm.samples.len() - self.position;
+        (left, Some(left))
+    }
+}
+
+impl Source for PcmSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.pcm.samples.len() - self.position)
+    }
+
+    fn channels(&self) -> u16 {
+        self.pcm.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.pcm.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f32(self.pcm.duration()))
+    }
+}
@@ -0,0 +1,547 @@
+// Audio effects - the DSP chain each audio bus runs its mix through
+//
+// In order: high-pass, low-pass, three-band EQ, reverb, then a compressor
+// (at high ratios a limiter). Every stage is skipped while it's neutral.
+// Settings come from EffectSettings, which ReverbZones blend between by
+// listener position.
+
+use engine_scene::components::ReverbZone;
+use glam::Vec3;
+use std::f32::consts::PI;
+
+/// Low-pass cutoffs at or above this are off
+pub const FILTER_OPEN_HIGH_HZ: f32 = 20000.0;
+/// High-pass cutoffs at or below this are off
+pub const FILTER_OPEN_LOW_HZ: f32 = 20.0;
+/// EQ band centres: low shelf, peak, high shelf
+pub const EQ_BANDS_HZ: [f32; 3] = [250.0, 1000.0, 4000.0];
+
+/// Reverb on a bus
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReverbSettings {
+    /// Wet level (0 = off)
+    pub mix: f32,
+    /// Tail length 0..1
+    pub room_size: f32,
+    /// High-frequency loss in the tail 0..1
+    pub damping: f32,
+}
+
+/// Dynamic range compression on a bus
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompressorSettings {
+    pub threshold_db: f32,
+    /// Input dB over the threshold per output dB (1 = off, 20+ = limiter)
+    pub ratio: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+    pub makeup_db: f32,
+}
+
+impl CompressorSettings {
+    /// A limiter keeping peaks under `ceiling_db`
+    pub fn limiter(ceiling_db: f32) -> Self {
+        Self {
+            threshold_db: ceiling_db,
+            ratio: 20.0,
+            attack_ms: 1.0,
+            release_ms: 100.0,
+            makeup_db: 0.0,
+        }
+    }
+}
+
+/// Everything a bus's effect chain does
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EffectSettings {
+    pub high_pass_hz: f32,
+    pub low_pass_hz: f32,
+    /// Gains in dB at EQ_BANDS_HZ
+    pub eq_db: [f32; 3],
+    pub reverb: ReverbSettings,
+    pub compressor: CompressorSettings,
+}
+
+impl Default for EffectSettings {
+    /// No filtering or reverb; a limiter stops many sounds at once clipping
+    fn default() -> Self {
+        Self {
+            high_pass_hz: FILTER_OPEN_LOW_HZ,
+            low_pass_hz: FILTER_OPEN_HIGH_HZ,
+            eq_db: [0.0; 3],
+            reverb: ReverbSettings {
+                mix: 0.0,
+                room_size: 0.5,
+                damping: 0.5,
+            },
+            compressor: CompressorSettings::limiter(-1.0),
+        }
+    }
+}
+
+impl EffectSettings {
+    /// The default chain with a zone's filters and reverb
+    pub fn from_zone(zone: &ReverbZone) -> Self {
+        Self {
+            high_pass_hz: zone.high_pass_hz,
+            low_pass_hz: zone.low_pass_hz,
+            eq_db: zone.eq_db,
+            reverb: ReverbSettings {
+                mix: zone.reverb_mix,
+                room_size: zone.room_size,
+                damping: zone.damping,
+            },
+            ..Self::default()
+        }
+    }
+
+    /// Blend towards `other` by `t`; cutoffs blend in pitch, not in Hz
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        let mix_hz =
+            |a: f32, b: f32| (a.max(1.0).ln() + (b.max(1.0).ln() - a.max(1.0).ln()) * t).exp();
+        Self {
+            high_pass_hz: mix_hz(self.high_pass_hz, other.high_pass_hz),
+            low_pass_hz: mix_hz(self.low_pass_hz, other.low_pass_hz),
+            eq_db: [0, 1, 2].map(|i| mix(self.eq_db[i], other.eq_db[i])),
+            reverb: ReverbSettings {
+                mix: mix(self.reverb.mix, other.reverb.mix),
+                room_size: mix(self.reverb.room_size, other.reverb.room_size),
+                damping: mix(self.reverb.damping, other.reverb.damping),
+            },
+            compressor: CompressorSettings {
+                threshold_db: mix(self.compressor.threshold_db, other.compressor.threshold_db),
+                ratio: mix(self.compressor.ratio, other.compressor.ratio),
+                attack_ms: mix(self.compressor.attack_ms, other.compressor.attack_ms),
+                release_ms: mix(self.compressor.release_ms, other.compressor.release_ms),
+                makeup_db: mix(self.compressor.makeup_db, other.compressor.makeup_db),
+            },
+        }
+    }
+}
+
+/// Effects for a listener at `listener` among reverb zones (zone centre,
+/// zone): the zones it is in or near blend by weight, and the result fades
+/// back to `base` as the listener leaves the strongest one
+pub fn zone_effects<'a>(
+    base: &EffectSettings,
+    listener: Vec3,
+    zones: impl IntoIterator<Item = (Vec3, &'a ReverbZone)>,
+) -> EffectSettings {
+    let mut blended: Option<EffectSettings> = None;
+    let mut total_weight = 0.0;
+    let mut strongest: f32 = 0.0;
+    for (centre, zone) in zones {
+        let weight = zone.weight(listener.distance(centre));
+        if weight <= 0.0 {
+            continue;
+        }
+        let settings = EffectSettings {
+            compressor: base.compressor,
+            ..EffectSettings::from_zone(zone)
+        };
+        total_weight += weight;
+        strongest = strongest.max(weight);
+        blended = Some(match blended {
+            Some(acc) => acc.lerp(&settings, weight / total_weight),
+            None => settings,
+        });
+    }
+    match blended {
+        Some(zones) => base.lerp(&zones, strongest),
+        None => *base,
+    }
+}
+
+/// Biquad filter (RBJ cookbook) on one channel, transposed direct form II
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum FilterKind {
+    LowPass,
+    HighPass,
+    LowShelf(f32),
+    Peak(f32),
+    HighShelf(f32),
+}
+
+impl Biquad {
+    /// Change the response, keeping the filter's state
+    pub(crate) fn set(&mut self, kind: FilterKind, frequency: f32, sample_rate: u32) {
+        let frequency = frequency.clamp(1.0, sample_rate as f32 * 0.49);
+        let w0 = 2.0 * PI * frequency / sample_rate as f32;
+        let (sin, cos) = w0.sin_cos();
+        let q = std::f32::consts::FRAC_1_SQRT_2;
+        let alpha = sin / (2.0 * q);
+        let (b0, b1, b2, a0, a1, a2) = match kind {
+            FilterKind::LowPass => (
+                (1.0 - cos) / 2.0,
+                1.0 - cos,
+                (1.0 - cos) / 2.0,
+                1.0 + alpha,
+                -2.0 * cos,
+                1.0 - alpha,
+            ),
+            FilterKind::HighPass => (
+                (1.0 + cos) / 2.0,
+                -(1.0 + cos),
+                (1.0 + cos) / 2.0,
+                1.0 + alpha,
+                -2.0 * cos,
+                1.0 - alpha,
+            ),
+            FilterKind::Peak(gain_db) => {
+                let a = 10f32.powf(gain_db / 40.0);
+                (
+                    1.0 + alpha * a,
+                    -2.0 * cos,
+                    1.0 - alpha * a,
+                    1.0 + alpha / a,
+                    -2.0 * cos,
+                    1.0 - alpha / a,
+                )
+            }
+            FilterKind::LowShelf(gain_db) | FilterKind::HighShelf(gain_db) => {
+                let a = 10f32.powf(gain_db / 40.0);
+                let k = 2.0 * a.sqrt() * alpha;
+                let (p, m) = (a + 1.0, a - 1.0);
+                if matches!(kind, FilterKind::LowShelf(_)) {
+                    (
+                        a * (p - m * cos + k),
+                        2.0 * a * (m - p * cos),
+                        a * (p - m * cos - k),
+                        p + m * cos + k,
+                        -2.0 * (m + p * cos),
+                        p + m * cos - k,
+                    )
+                } else {
+                    (
+                        a * (p + m * cos + k),
+                        -2.0 * a * (m + p * cos),
+                        a * (p + m * cos - k),
+                        p - m * cos + k,
+                        2.0 * (m - p * cos),
+                        p - m * cos - k,
+                    )
+                }
+            }
+        };
+        self.b0 = b0 / a0;
+        self.b1 = b1 / a0;
+        self.b2 = b2 / a0;
+        self.a1 = a1 / a0;
+        self.a2 = a2 / a0;
+    }
+
+    pub(crate) fn process(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+}
+
+/// Freeverb comb filter with a damped feedback path
+struct Comb {
+    buffer: Vec<f32>,
+    index: usize,
+    filtered: f32,
+}
+
+impl Comb {
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output = self.buffer[self.index];
+        self.filtered = output * (1.0 - damping) + self.filtered * damping;
+        self.buffer[self.index] = input + self.filtered * feedback;
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+}
+
+struct Allpass {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl Allpass {
+    fn process(&mut self, input: f32) -> f32 {
+        let delayed = self.buffer[self.index];
+        self.buffer[self.index] = input + delayed * 0.5;
+        self.index = (self.index + 1) % self.buffer.len();
+        delayed - input
+    }
+}
+
+/// Freeverb delay lengths at 44.1 kHz
+const COMB_TUNING: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+const ALLPASS_TUNING: [usize; 4] = [556, 441, 341, 225];
+/// Extra delay on odd channels, for a wide stereo tail
+const STEREO_SPREAD: usize = 23;
+
+/// Algorithmic (Freeverb) reverb: parallel combs into series allpasses, one
+/// bank per channel, fed the mono sum
+struct Reverb {
+    combs: Vec<Vec<Comb>>,
+    allpasses: Vec<Vec<Allpass>>,
+    active: bool,
+}
+
+impl Reverb {
+    fn new(channels: usize, sample_rate: u32) -> Self {
+        let scale = sample_rate as f32 / 44100.0;
+        let length = |tuning: usize, channel: usize| {
+            (((tuning + (channel % 2) * STEREO_SPREAD) as f32 * scale) as usize).max(1)
+        };
+        Self {
+            combs: (0..channels)
+                .map(|c| {
+                    COMB_TUNING
+                        .iter()
+                        .map(|&t| Comb {
+                            buffer: vec![0.0; length(t, c)],
+                            index: 0,
+                            filtered: 0.0,
+                        })
+                        .collect()
+                })
+                .collect(),
+            allpasses: (0..channels)
+                .map(|c| {
+                    ALLPASS_TUNING
+                        .iter()
+                        .map(|&t| Allpass {
+                            buffer: vec![0.0; length(t, c)],
+                            index: 0,
+                        })
+                        .collect()
+                })
+                .collect(),
+            active: false,
+        }
+    }
+
+    fn clear(&mut self) {
+        for comb in self.combs.iter_mut().flatten() {
+            comb.buffer.fill(0.0);
+            comb.filtered = 0.0;
+        }
+        for allpass in self.allpasses.iter_mut().flatten() {
+            allpass.buffer.fill(0.0);
+        }
+    }
+
+    fn process(&mut self, frame: &mut [f32], settings: &ReverbSettings) {
+        if settings.mix <= 0.0 {
+            // Start from silence when it comes back on
+            if self.active {
+                self.clear();
+                self.active = false;
+            }
+            return;
+        }
+        self.active = true;
+        let feedback = settings.room_size.clamp(0.0, 1.0) * 0.28 + 0.7;
+        let damping = settings.damping.clamp(0.0, 1.0) * 0.4;
+        let input = frame.iter().sum::<f32>() * 0.015;
+        let mix = settings.mix.clamp(0.0, 1.0);
+        for (channel, sample) in frame.iter_mut().enumerate() {
+            let mut wet: f32 = self.combs[channel]
+                .iter_mut()
+                .map(|comb| comb.process(input, feedback, damping))
+                .sum();
+            for allpass in &mut self.allpasses[channel] {
+                wet = allpass.process(wet);
+            }
+            *sample = *sample * (1.0 - mix) + wet * mix * 3.0;
+        }
+    }
+}
+
+/// Peak compressor, one gain for all channels so the image doesn't shift
+struct Compressor {
+    envelope: f32,
+}
+
+impl Compressor {
+    fn process(&mut self, frame: &mut [f32], settings: &CompressorSettings, sample_rate: u32) {
+        if settings.ratio <= 1.0 && settings.makeup_db == 0.0 {
+            return;
+        }
+        let coefficient = |ms: f32| (-1.0 / (ms.max(0.01) * 0.001 * sample_rate as f32)).exp();
+        let peak = frame.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        let c = if peak > self.envelope {
+            coefficient(settings.attack_ms)
+        } else {
+            coefficient(settings.release_ms)
+        };
+        self.envelope = c * self.envelope + (1.0 - c) * peak;
+
+        let level_db = 20.0 * self.envelope.max(1e-6).log10();
+        let over = level_db - settings.threshold_db;
+        let reduction_db = if over > 0.0 {
+            over - over / settings.ratio.max(1.0)
+        } else {
+            0.0
+        };
+        let gain = 10f32.powf((settings.makeup_db - reduction_db) / 20.0);
+        for sample in frame {
+            *sample *= gain;
+        }
+    }
+}
+
+/// A bus's effects for interleaved audio with a fixed channel count and rate
+pub struct EffectChain {
+    sample_rate: u32,
+    settings: EffectSettings,
+    high_pass: Vec<Biquad>,
+    low_pass: Vec<Biquad>,
+    eq: Vec<[Biquad; 3]>,
+    reverb: Reverb,
+    compressor: Compressor,
+}
+
+impl EffectChain {
+    pub fn new(channels: u16, sample_rate: u32, settings: EffectSettings) -> Self {
+        let channels = channels.max(1) as usize;
+        let mut chain = Self {
+            sample_rate,
+            settings,
+            high_pass: vec![Biquad::default(); channels],
+            low_pass: vec![Biquad::default(); channels],
+            eq: vec![[Biquad::default(); 3]; channels],
+            reverb: Reverb::new(channels, sample_rate),
+            compressor: Compressor { envelope: 0.0 },
+        };
+        chain.set_settings(settings);
+        chain
+    }
+
+    pub fn settings(&self) -> &EffectSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: EffectSettings) {
+        let rate = self.sample_rate;
+        for filter in &mut self.high_pass {
+            filter.set(FilterKind::HighPass, settings.high_pass_hz, rate);
+        }
+        for filter in &mut self.low_pass {
+            filter.set(FilterKind::LowPass, settings.low_pass_hz, rate);
+        }
+        for bands in &mut self.eq {
+            bands[0].set(
+                FilterKind::LowShelf(settings.eq_db[0]),
+                EQ_BANDS_HZ[0],
+                rate,
+            );
+            bands[1].set(FilterKind::Peak(settings.eq_db[1]), EQ_BANDS_HZ[1], rate);
+            bands[2].set(
+                FilterKind::HighShelf(settings.eq_db[2]),
+                EQ_BANDS_HZ[2],
+                rate,
+            );
+        }
+        self.settings = settings;
+    }
+
+    /// Run one frame (a sample per channel) through the chain
+    pub fn process_frame(&mut self, frame: &mut [f32]) {
+        let settings = self.settings;
+        let high_pass = settings.high_pass_hz > FILTER_OPEN_LOW_HZ;
+        let low_pass = settings.low_pass_hz < FILTER_OPEN_HIGH_HZ;
+        for (channel, sample) in frame.iter_mut().enumerate().take(self.low_pass.len()) {
+            if high_pass {
+                *sample = self.high_pass[channel].process(*sample);
+            }
+            if low_pass {
+                *sample = self.low_pass[channel].process(*sample);
+            }
+            for (band, gain_db) in self.eq[channel].iter_mut().zip(settings.eq_db) {
+                if gain_db != 0.0 {
+                    *sample = band.process(*sample);
+                }
+            }
+        }
+        self.reverb.process(frame, &settings.reverb);
+        self.compressor
+            .process(frame, &settings.compressor, self.sample_rate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Peak output level of a sine through `chain` once it has settled
+    fn sine_level(chain: &mut EffectChain, frequency: f32) -> f32 {
+        let mut peak: f32 = 0.0;
+        for i in 0..48000 {
+            let x = (2.0 * PI * frequency * i as f32 / 48000.0).sin() * 0.5;
+            let mut frame = [x, x];
+            chain.process_frame(&mut frame);
+            if i > 24000 {
+                peak = peak.max(frame[0].abs());
+            }
+        }
+        peak
+    }
+
+    #[test]
+    fn test_low_pass_and_limiter() {
+        let settings = EffectSettings::from_zone(&ReverbZone {
+            reverb_mix: 0.0,
+            ..ReverbZone::underwater()
+        });
+        let mut chain = EffectChain::new(2, 48000, settings);
+        let low = sine_level(&mut chain, 100.0);
+        let high = sine_level(&mut chain, 5000.0);
+        assert!(
+            low > 0.5,
+            "lows pass (and the low shelf boosts them): {}",
+            low
+        );
+        assert!(high < 0.02, "highs are cut: {}", high);
+
+        // A loud signal is held near the -1 dB ceiling
+        let mut chain = EffectChain::new(2, 48000, EffectSettings::default());
+        let mut peak: f32 = 0.0;
+        for i in 0..48000 {
+            let mut frame = [2.0 * (i as f32 * 0.05).sin(); 2];
+            chain.process_frame(&mut frame);
+            if i > 4800 {
+                peak = peak.max(frame[0].abs());
+            }
+        }
+        assert!(peak < 1.0, "limited to {}", peak);
+    }
+
+    #[test]
+    fn test_zones_blend_by_listener_position() {
+        let base = EffectSettings::default();
+        let underwater = ReverbZone::underwater()
+            .with_radius(5.0)
+            .with_blend_distance(5.0);
+        let zones = [(Vec3::ZERO, &underwater)];
+
+        assert_eq!(zone_effects(&base, Vec3::new(20.0, 0.0, 0.0), zones), base);
+        let inside = zone_effects(&base, Vec3::new(1.0, 0.0, 0.0), zones);
+        assert!((inside.low_pass_hz - 600.0).abs() < 0.1);
+        assert_eq!(inside.reverb.mix, 0.2);
+
+        // Halfway through the blend the cutoff is halfway in pitch
+        let edge = zone_effects(&base, Vec3::new(7.5, 0.0, 0.0), zones);
+        assert!((edge.low_pass_hz - (600.0f32 * 20000.0).sqrt()).abs() < 1.0);
+        assert!((edge.reverb.mix - 0.1).abs() < 1e-5);
+    }
+}
@@ -1,9 +1,25 @@
 // Audio System - 3D spatial audio with rodio
 
+pub mod adaptive;
+pub mod bus;
+pub mod clip;
+pub mod effects;
 pub mod listener;
+pub mod music;
+pub mod occlusion;
 pub mod source;
 pub mod system;
+pub mod voices;
 
+pub use adaptive::{
+    AdaptiveMusicConfig, AdaptiveTrack, LayerControl, LayerMixer, MusicLayer, TransitionSync,
+};
+pub use bus::{AudioBus, BusEffects};
+pub use clip::{clip_source, clip_source_at, PcmSource};
+pub use effects::{zone_effects, CompressorSettings, EffectChain, EffectSettings, ReverbSettings};
 pub use listener::AudioListener;
+pub use music::{track_source, Fade, FadeControl, FadeCurve, FadeSource, LoopPoints, MusicTrack};
+pub use occlusion::{occlusion, Occlusion};
 pub use source::{AudioSource, SoundType};
-pub use system::AudioSystem;
+pub use system::{AudioSystem, PlayingMusic};
+pub use voices::{distance_attenuation, EntityVoices, Voice, VoiceSource};
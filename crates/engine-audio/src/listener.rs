@@ -1,5 +1,6 @@
 // Audio Listener - represents the player's ears (typically camera position)
 
+use engine_scene::entity::EntityId;
 use glam::{Quat, Vec3};
 
 /// Audio listener - represents where the player hears sound from
@@ -11,6 +12,9 @@ pub struct AudioListener {
     pub forward: Vec3,
     /// Up direction
     pub up: Vec3,
+    /// Entity the listener is on (the game camera); its colliders and those
+    /// of the entities it is parented to don't muffle what it hears
+    pub entity: Option<EntityId>,
 }
 
 impl AudioListener {
@@ -20,9 +24,16 @@ impl AudioListener {
             position,
             forward: forward.normalize(),
             up: up.normalize(),
+            entity: None,
         }
     }
 
+    /// The same listener, attached to `entity`
+    pub fn with_entity(mut self, entity: Option<EntityId>) -> Self {
+        self.entity = entity;
+        self
+    }
+
     /// Create from position and rotation
     pub fn from_transform(position: Vec3, rotation: Quat) -> Self {
         let forward = rotation * Vec3::NEG_Z;
@@ -0,0 +1,541 @@
+// Music playback - tracks that fade, crossfade and loop between loop points
+//
+// A looping track plays from its start to the loop end, then from the loop
+// start to the loop end for as long as it plays, without a gap: decoded clips
+// jump within their PCM, streamed ones seek their decoder back to the loop
+// start. Formats the decoder can't seek in swap in a second decoder, made
+// ready at the loop start on its own thread while the loop plays. Fades run inside the source, per frame, on the
+// curve the track asks for, and can be changed while it plays (fade out on
+// stop, crossfade into the next track).
+
+use anyhow::Result;
+use engine_assets::{AudioClip, AudioClipData, PcmData};
+use rodio::{Decoder, Source};
+use std::f32::consts::FRAC_PI_2;
+use std::io::Cursor;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::clip::{clip_source, sample_index};
+
+/// Shape of a fade from silence to full volume (fades out run it backwards)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FadeCurve {
+    Linear,
+    /// Constant loudness across a crossfade
+    #[default]
+    EqualPower,
+    /// Slow at both ends
+    SCurve,
+}
+
+impl FadeCurve {
+    /// Gain `t` (0..1) of the way through a fade in
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::EqualPower => (t * FRAC_PI_2).sin(),
+            Self::SCurve => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// A gain change over time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fade {
+    pub from: f32,
+    pub to: f32,
+    /// Seconds
+    pub duration: f32,
+    pub curve: FadeCurve,
+    /// End playback once the fade is done
+    pub stop: bool,
+    elapsed: f32,
+}
+
+impl Fade {
+    pub fn new(from: f32, to: f32, duration: f32, curve: FadeCurve) -> Self {
+        Self {
+            from,
+            to,
+            duration: duration.max(0.0),
+            curve,
+            stop: false,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Full volume, no fading
+    pub fn none() -> Self {
+        Self::new(1.0, 1.0, 0.0, FadeCurve::Linear)
+    }
+
+    pub fn fade_in(duration: f32, curve: FadeCurve) -> Self {
+        Self::new(0.0, 1.0, duration, curve)
+    }
+
+    /// Fade to silence from wherever the gain is, then stop
+    pub fn fade_out(duration: f32, curve: FadeCurve) -> Self {
+        Self {
+            stop: true,
+            ..Self::new(1.0, 0.0, duration, curve)
+        }
+    }
+
+    pub fn gain(&self) -> f32 {
+        let t = if self.duration > 0.0 {
+            self.elapsed / self.duration
+        } else {
+            1.0
+        };
+        if self.to >= self.from {
+            self.from + (self.to - self.from) * self.curve.apply(t)
+        } else {
+            self.to + (self.from - self.to) * self.curve.apply(1.0 - t)
+        }
+    }
+
+    pub fn advance(&mut self, seconds: f32) {
+        self.elapsed = (self.elapsed + seconds).min(self.duration);
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+/// Where a looping track jumps back to, and from, in seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoopPoints {
+    pub start: f32,
+    /// None = the end of the track
+    pub end: Option<f32>,
+}
+
+/// A music track and how to play it
+#[derive(Debug, Clone, PartialEq)]
+pub struct MusicTrack {
+    pub path: String,
+    pub volume: f32,
+    pub looping: bool,
+    /// Seconds to fade in over, crossfading from the track playing before
+    pub fade_in: f32,
+    pub curve: FadeCurve,
+    /// Loop range of a looping track (None = the whole track)
+    pub loop_points: Option<LoopPoints>,
+}
+
+impl MusicTrack {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            volume: 1.0,
+            looping: true,
+            fade_in: 0.0,
+            curve: FadeCurve::default(),
+            loop_points: None,
+        }
+    }
+
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    pub fn with_fade_in(mut self, seconds: f32) -> Self {
+        self.fade_in = seconds.max(0.0);
+        self
+    }
+
+    pub fn with_curve(mut self, curve: FadeCurve) -> Self {
+        self.curve = curve;
+        self
+    }
+
+    pub fn with_loop_points(mut self, start: f32, end: Option<f32>) -> Self {
+        self.loop_points = Some(LoopPoints { start, end });
+        self
+    }
+}
+
+/// A fade for a playing FadeSource to switch to, set from the game thread
+#[derive(Debug, Default)]
+pub struct FadeControl {
+    pending: Mutex<Option<Fade>>,
+    has_pending: AtomicBool,
+}
+
+impl FadeControl {
+    /// Start `fade` from the gain the source is at now
+    pub fn start(&self, fade: Fade) {
+        *self.pending.lock().unwrap() = Some(fade);
+        self.has_pending.store(true, Ordering::Release);
+    }
+
+    fn take(&self) -> Option<Fade> {
+        if !self.has_pending.swap(false, Ordering::AcqRel) {
+            return None;
+        }
+        self.pending.lock().unwrap().take()
+    }
+}
+
+/// Applies a fade to a source; ends it when a stopping fade finishes
+pub struct FadeSource<S> {
+    input: S,
+    control: Arc<FadeControl>,
+    fade: Fade,
+    gain: f32,
+    /// Samples left in the current frame
+    frame_left: usize,
+}
+
+impl<S: Source<Item = f32>> FadeSource<S> {
+    pub fn new(input: S, fade: Fade, control: Arc<FadeControl>) -> Self {
+        Self {
+            input,
+            control,
+            gain: fade.gain(),
+            fade,
+            frame_left: 0,
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for FadeSource<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.frame_left == 0 {
+            if let Some(fade) = self.control.take() {
+                self.fade = Fade {
+                    from: self.gain,
+                    ..fade
+                };
+            }
+            if self.fade.stop && self.fade.is_done() {
+                return None;
+            }
+            self.gain = self.fade.gain();
+            self.fade
+                .advance(1.0 / self.input.sample_rate().max(1) as f32);
+            self.frame_left = self.input.channels().max(1) as usize;
+        }
+        self.frame_left -= 1;
+        Some(self.input.next()? * self.gain)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for FadeSource<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+/// A source for a music track: the clip once, or looping between its loop
+/// points
+pub fn track_source(
+    clip: &AudioClip,
+    looping: bool,
+    loop_points: Option<LoopPoints>,
+) -> Result<Box<dyn Source<Item = f32> + Send>> {
+    if !looping {
+        return clip_source(clip);
+    }
+    let points = loop_points.unwrap_or(LoopPoints {
+        start: 0.0,
+        end: None,
+    });
+    match &clip.data {
+        AudioClipData::Decoded(pcm) => Ok(Box::new(PcmLoop::new(pcm.clone(), points))),
+        AudioClipData::Encoded(bytes) => Ok(Box::new(StreamLoop::new(
+            clip.name.clone(),
+            bytes.clone(),
+            points,
+        )?)),
+    }
+}
+
+/// Decoded samples looping between two points
+pub struct PcmLoop {
+    pcm: PcmData,
+    position: usize,
+    start: usize,
+    end: usize,
+}
+
+impl PcmLoop {
+    pub fn new(pcm: PcmData, points: LoopPoints) -> Self {
+        let len = pcm.samples.len();
+        let at = |seconds: f32| sample_index(seconds, pcm.channels, pcm.sample_rate).min(len);
+        let end = points.end.map_or(len, at);
+        let start = at(points.start).min(end.saturating_sub(pcm.channels as usize));
+        Self {
+            pcm,
+            position: 0,
+            start,
+            end,
+        }
+    }
+}
+
+impl Iterator for PcmLoop {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.position >= self.end {
+            if self.start >= self.end {
+                return None;
+            }
+            self.position = self.start;
+        }
+        let sample = self.pcm.samples.get(self.position).copied();
+        self.position += 1;
+        sample
+    }
+}
+
+impl Source for PcmLoop {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.pcm.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.pcm.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// An encoded clip decoded as it plays, looping between two points
+type LoopDecoder = Box<dyn Source<Item = f32> + Send>;
+
+pub struct StreamLoop {
+    name: String,
+    bytes: Arc<[u8]>,
+    decoder: Option<LoopDecoder>,
+    /// Decoder at the loop start, for formats that can't seek
+    spare: Option<Receiver<Option<LoopDecoder>>>,
+    channels: u16,
+    sample_rate: u32,
+    position: usize,
+    start: usize,
+    end: Option<usize>,
+}
+
+impl StreamLoop {
+    pub fn new(name: String, bytes: Arc<[u8]>, points: LoopPoints) -> Result<Self> {
+        let mut decoder = Decoder::new(Cursor::new(bytes.clone()))?;
+        let (channels, sample_rate) = (decoder.channels(), decoder.sample_rate());
+        let start = sample_index(points.start, channels, sample_rate);
+        let spare = decoder
+            .try_seek(Duration::ZERO)
+            .is_err()
+            .then(|| prepare_decoder(name.clone(), bytes.clone(), start));
+        Ok(Self {
+            name,
+            bytes,
+            decoder: Some(Box::new(decoder.convert_samples::<f32>())),
+            spare,
+            channels,
+            sample_rate,
+            position: 0,
+            start,
+            end: points
+                .end
+                .map(|end| sample_index(end, channels, sample_rate)),
+        })
+    }
+
+    /// Go back to the loop start. Runs on the audio thread, so it never
+    /// decodes its way there.
+    fn restart(&mut self) {
+        let frame = (self.start / self.channels.max(1) as usize) as f64;
+        let start = Duration::from_secs_f64(frame / self.sample_rate.max(1) as f64);
+        self.position = self.start;
+        if let Some(decoder) = self.decoder.as_mut() {
+            if decoder.try_seek(start).is_ok() {
+                return;
+            }
+        }
+        // The spare was made ready while the loop played
+        self.decoder = self.spare.take().and_then(|spare| spare.recv().ok().flatten());
+        if self.decoder.is_some() {
+            self.spare = Some(prepare_decoder(self.name.clone(), self.bytes.clone(), self.start));
+        }
+    }
+}
+
+/// Open a decoder and decode up to sample `start` on another thread
+fn prepare_decoder(name: String, bytes: Arc<[u8]>, start: usize) -> Receiver<Option<LoopDecoder>> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let decoder = match Decoder::new(Cursor::new(bytes)) {
+            Ok(decoder) => {
+                let mut decoder = decoder.convert_samples::<f32>();
+                for _ in 0..start {
+                    if decoder.next().is_none() {
+                        break;
+                    }
+                }
+                Some(Box::new(decoder) as LoopDecoder)
+            }
+            Err(e) => {
+                log::warn!("Failed to reopen '{}' to loop: {}", name, e);
+                None
+            }
+        };
+        let _ = sender.send(decoder);
+    });
+    receiver
+}
+
+impl Iterator for StreamLoop {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.end.is_some_and(|end| self.position >= end) {
+            self.restart();
+        }
+        let sample = match self.decoder.as_mut()?.next() {
+            Some(sample) => sample,
+            None => {
+                // Ran off the end: loop, unless there is nothing after the loop start
+                self.restart();
+                self.decoder.as_mut()?.next()?
+            }
+        };
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+impl Source for StreamLoop {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fades_and_curves() {
+        let mut fade = Fade::fade_in(1.0, FadeCurve::EqualPower);
+        assert_eq!(fade.gain(), 0.0);
+        fade.advance(0.5);
+        let half_in = fade.gain();
+        // Equal power: the two halves of a crossfade sum to constant power
+        let mut out = Fade::fade_out(1.0, FadeCurve::EqualPower);
+        out.advance(0.5);
+        assert!((half_in * half_in + out.gain() * out.gain() - 1.0).abs() < 1e-5);
+        fade.advance(1.0);
+        assert!(fade.is_done() && fade.gain() == 1.0);
+        assert_eq!(FadeCurve::SCurve.apply(0.5), 0.5);
+        assert_eq!(Fade::none().gain(), 1.0);
+
+        // A stopping fade ends the source once it's done
+        let pcm = PcmData {
+            sample_rate: 4,
+            channels: 1,
+            samples: vec![1.0; 100].into(),
+        };
+        let control = Arc::new(FadeControl::default());
+        let mut source = FadeSource::new(
+            PcmLoop::new(
+                pcm,
+                LoopPoints {
+                    start: 0.0,
+                    end: None,
+                },
+            ),
+            Fade::none(),
+            control.clone(),
+        );
+        assert_eq!(source.next(), Some(1.0));
+        control.start(Fade::fade_out(1.0, FadeCurve::Linear));
+        let rest: Vec<f32> = source.by_ref().collect();
+        assert_eq!(rest, [1.0, 0.75, 0.5, 0.25]);
+    }
+
+    #[test]
+    fn test_pcm_loop_points() {
+        let pcm = PcmData {
+            sample_rate: 2,
+            channels: 1,
+            samples: (0..6).map(|i| i as f32).collect::<Vec<_>>().into(),
+        };
+        let points = LoopPoints {
+            start: 1.0,
+            end: Some(2.5),
+        };
+        let played: Vec<f32> = PcmLoop::new(pcm, points).take(10).collect();
+        // Intro 0..5 (2.5 s = sample 5), then 2..5 over and over
+        assert_eq!(played, [0.0, 1.0, 2.0, 3.0, 4.0, 2.0, 3.0, 4.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_stream_loop_seeks_back_to_the_loop_start() {
+        // 16-bit mono WAV at 2 Hz holding 0..6 (in thousandths)
+        let data: Vec<u8> = (0..6i16).flat_map(|i| (i * 1000).to_le_bytes()).collect();
+        let mut wav = b"RIFF".to_vec();
+        wav.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        for field in [16u32, 1 | 1 << 16, 2, 4, 2 | 16 << 16] {
+            wav.extend_from_slice(&field.to_le_bytes());
+        }
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        wav.extend_from_slice(&data);
+
+        let points = LoopPoints {
+            start: 1.0,
+            end: Some(2.5),
+        };
+        let source = StreamLoop::new("loop".to_string(), wav.into(), points).unwrap();
+        let played: Vec<i32> = source
+            .take(10)
+            .map(|sample| (sample * 32768.0 / 1000.0).round() as i32)
+            .collect();
+        assert_eq!(played, [0, 1, 2, 3, 4, 2, 3, 4, 2, 3]);
+    }
+}
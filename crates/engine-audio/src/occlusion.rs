@@ -0,0 +1,189 @@
+// Occlusion - muffling sounds that have geometry between them and the listener
+//
+// Rays are cast through the physics world from the listener to the sound:
+// one straight at it and a few to points around it. The sound's own entity
+// and the listener's (with the rig it is parented to) are not in the way. Every collider a ray
+// passes through takes its AudioOccluder's share of the level and low-pass
+// filters what is left. A sound fully behind a wall is occluded on every ray;
+// one behind a corner (obstructed) only on some, so it is muffled less.
+
+use engine_physics::{Collider, PhysicsWorld, RaycastQuery};
+use engine_scene::components::AudioOccluder;
+use engine_scene::entity::EntityId;
+use engine_scene::scene::Scene;
+use glam::Vec3;
+
+use crate::effects::FILTER_OPEN_HIGH_HZ;
+use crate::listener::AudioListener;
+
+/// Distance around a sound that the side rays aim at
+pub const OCCLUSION_SPREAD: f32 = 0.75;
+/// Fraction of the way a voice's occlusion moves to its new value each
+/// update, so sounds don't jump as things pass in front of them
+pub const OCCLUSION_SMOOTHING: f32 = 0.2;
+
+/// How a sound is heard through what is between it and the listener
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Occlusion {
+    /// Level let through (1 = clear path)
+    pub gain: f32,
+    /// Low-pass cutoff in Hz (FILTER_OPEN_HIGH_HZ = unfiltered)
+    pub low_pass_hz: f32,
+}
+
+impl Occlusion {
+    pub const NONE: Self = Self {
+        gain: 1.0,
+        low_pass_hz: FILTER_OPEN_HIGH_HZ,
+    };
+
+    /// Through each of `occluders` in turn
+    pub fn through<'a>(occluders: impl IntoIterator<Item = &'a AudioOccluder>) -> Self {
+        occluders
+            .into_iter()
+            .fold(Self::NONE, |occlusion, occluder| Self {
+                gain: occlusion.gain * occluder.transmission(),
+                low_pass_hz: occlusion.low_pass_hz.min(occluder.low_pass_hz),
+            })
+    }
+
+    /// The blend of several paths to a sound (cutoffs average on a log scale)
+    pub fn average(paths: &[Self]) -> Self {
+        if paths.is_empty() {
+            return Self::NONE;
+        }
+        let n = paths.len() as f32;
+        Self {
+            gain: paths.iter().map(|path| path.gain).sum::<f32>() / n,
+            low_pass_hz: (paths.iter().map(|path| path.low_pass_hz.ln()).sum::<f32>() / n).exp(),
+        }
+    }
+
+    /// `t` of the way to `target` (cutoffs move on a log scale)
+    pub fn lerp(&self, target: &Self, t: f32) -> Self {
+        let (from, to) = (self.low_pass_hz.ln(), target.low_pass_hz.ln());
+        Self {
+            gain: self.gain + (target.gain - self.gain) * t,
+            low_pass_hz: (from + (to - from) * t).exp(),
+        }
+    }
+}
+
+impl Default for Occlusion {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+/// Occlusion of a sound at `source` (played by `source_entity`, whose own
+/// colliders don't count) heard by `listener`
+pub fn occlusion(
+    physics: &PhysicsWorld,
+    scene: &Scene,
+    source: Vec3,
+    source_entity: EntityId,
+    listener: &AudioListener,
+) -> Occlusion {
+    // The listener's entity and the ones it is parented to, like a camera on a player rig
+    let mut ignored = vec![source_entity];
+    let mut next = listener.entity;
+    while let Some(entity) = next {
+        ignored.push(entity);
+        next = scene.get_entity(entity).and_then(|e| e.parent);
+    }
+
+    let listener = listener.position;
+    let direction = source - listener;
+    if direction.length_squared() < 1e-6 {
+        return Occlusion::NONE;
+    }
+    let forward = direction.normalize();
+    let side = forward.any_orthonormal_vector();
+    let up = forward.cross(side);
+    let targets = [
+        source,
+        source + side * OCCLUSION_SPREAD,
+        source - side * OCCLUSION_SPREAD,
+        source + up * OCCLUSION_SPREAD,
+        source - up * OCCLUSION_SPREAD,
+    ];
+
+    let paths: Vec<Occlusion> = targets
+        .iter()
+        .map(|&target| {
+            let ray = target - listener;
+            let hits = physics.raycast_all(&RaycastQuery::new(listener, ray, ray.length()));
+            let occluders: Vec<AudioOccluder> = hits
+                .iter()
+                .filter(|hit| !ignored.contains(&hit.entity_id))
+                .filter_map(|hit| occluder(scene, hit.entity_id))
+                .collect();
+            Occlusion::through(&occluders)
+        })
+        .collect();
+    Occlusion::average(&paths)
+}
+
+/// How an entity a ray hit blocks sound (None for triggers)
+fn occluder(scene: &Scene, entity: EntityId) -> Option<AudioOccluder> {
+    let entity = scene.get_entity(entity)?;
+    if entity
+        .get_component::<Collider>()
+        .is_some_and(|collider| collider.is_sensor)
+    {
+        return None;
+    }
+    Some(
+        entity
+            .get_component::<AudioOccluder>()
+            .cloned()
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_occluders_stack_and_paths_average() {
+        let wall = [AudioOccluder::concrete(), AudioOccluder::glass()];
+        let behind = Occlusion::through(&wall);
+        assert!((behind.gain - 0.1 * 0.6).abs() < 1e-6);
+        assert_eq!(behind.low_pass_hz, 500.0);
+        assert_eq!(Occlusion::through([]), Occlusion::NONE);
+
+        // Obstructed: blocked on one path of two
+        let corner = Occlusion::average(&[behind, Occlusion::NONE]);
+        assert!((corner.gain - 0.53).abs() < 1e-6);
+        assert!((corner.low_pass_hz - (500.0f32 * FILTER_OPEN_HIGH_HZ).sqrt()).abs() < 1.0);
+
+        let halfway = Occlusion::NONE.lerp(&behind, 0.5);
+        assert!((halfway.low_pass_hz - corner.low_pass_hz).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_listener_rig_does_not_occlude() {
+        use engine_physics::{ColliderGeometry, PhysicsSync, RigidBody};
+
+        let mut scene = Scene::new("Occlusion".to_string());
+        let player = scene.create_entity("Player".to_string());
+        let camera = scene.create_entity("Camera".to_string());
+        scene.set_parent(camera, Some(player));
+        let source = scene.create_entity("Radio".to_string());
+        scene.get_entity_mut(source).unwrap().transform.position = Vec3::new(0.0, 0.0, -10.0);
+        let entity = scene.get_entity_mut(player).unwrap();
+        entity.add_component(RigidBody::static_body());
+        entity.add_component(Collider::capsule(0.5, 0.4));
+        entity.add_component(AudioOccluder::concrete());
+        let mut physics = PhysicsWorld::new(Vec3::ZERO);
+        PhysicsSync::initialize_physics(&mut physics, &scene, &ColliderGeometry::default()).unwrap();
+        physics.query_pipeline.update(&physics.collider_set);
+
+        let position = Vec3::new(0.0, 0.0, -10.0);
+        let listener = AudioListener::default();
+        assert!(occlusion(&physics, &scene, position, source, &listener).gain < 0.5);
+        let listener = listener.with_entity(Some(camera));
+        assert_eq!(occlusion(&physics, &scene, position, source, &listener).gain, 1.0);
+    }
+}
@@ -1,20 +1,54 @@
 // Audio System - manages audio output and playback
+//
+// Clips come from an AssetManager of their own, which caches them decoded or
+// encoded by their load policy (see engine_assets::audio_clip). Sound effects
+// mix into the SFX bus and music plays through the music bus, each with its
+// own effect chain (see bus). A new track crossfades from the one playing;
+// tracks fading out keep playing until their fade ends (see music). Adaptive
+// tracks mix their stems by the music parameters (see adaptive).
 
 use anyhow::{Context, Result};
+use engine_assets::{AssetManager, AudioClip};
 use glam::Vec3;
-use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+use rodio::dynamic_mixer::{self, DynamicMixerController};
+use rodio::source::UniformSourceIterator;
+use rodio::{OutputStream, OutputStreamHandle, Sink, Source};
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::BufReader;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use std::sync::Arc;
 
+use crate::adaptive::{AdaptiveTrack, LayerControl, LayerMixer};
+use crate::bus::{AudioBus, BusEffects, BusMixer, EffectSource, BUS_CHANNELS, BUS_SAMPLE_RATE};
+use crate::clip::clip_source;
+use crate::effects::EffectSettings;
 use crate::listener::AudioListener;
+use crate::music::{track_source, Fade, FadeControl, FadeCurve, FadeSource, MusicTrack};
 use crate::source::{AudioSource, SoundType};
+use crate::voices::{distance_attenuation, Voice, VoiceSource};
+
+/// The background music track currently playing
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayingMusic {
+    pub path: String,
+    pub volume: f32,
+    pub looping: bool,
+}
+
+/// A music sink and the control of its fade
+struct MusicPlayer {
+    sink: Sink,
+    volume: f32,
+    fade: Arc<FadeControl>,
+    curve: FadeCurve,
+    /// The adaptive track playing, and the control of its layers
+    adaptive: Option<(AdaptiveTrack, Arc<LayerControl>)>,
+}
 
-/// Cached audio data
-pub struct AudioData {
-    pub data: Arc<Vec<u8>>,
+impl MusicPlayer {
+    fn fade_out(self, seconds: f32) -> Self {
+        self.fade.start(Fade::fade_out(seconds, self.curve));
+        self
+    }
 }
 
 /// Audio system - manages audio playback and 3D spatial audio
@@ -23,12 +57,22 @@ pub struct AudioSystem {
     _stream: OutputStream,
     /// Audio output stream handle
     stream_handle: OutputStreamHandle,
-    /// Asset root directory
-    asset_root: PathBuf,
-    /// Cached audio files
-    cache: HashMap<PathBuf, AudioData>,
-    /// Active music sink
-    music_sink: Option<Sink>,
+    /// Cached audio clips
+    clips: AssetManager,
+    /// Where sound effects are added to the SFX bus mix
+    sfx_mixer: Arc<DynamicMixerController<f32>>,
+    /// Plays the SFX bus
+    sfx_sink: Sink,
+    sfx_effects: BusEffects,
+    music_effects: BusEffects,
+    /// Active music
+    music: Option<MusicPlayer>,
+    /// What the music is playing
+    playing_music: Option<PlayingMusic>,
+    /// Music fading out, until it ends
+    fading_music: Vec<MusicPlayer>,
+    /// Parameters adaptive tracks mix their layers by
+    music_parameters: HashMap<String, f32>,
     /// Active sound effects
     active_sounds: Vec<Sink>,
     /// Global volume (0.0 to 1.0)
@@ -41,114 +85,225 @@ impl AudioSystem {
         let (_stream, stream_handle) = OutputStream::try_default()
             .context("Failed to create audio output stream")?;
 
+        let sfx_effects = BusEffects::default();
+        let (sfx_mixer, mixer) = dynamic_mixer::mixer(BUS_CHANNELS, BUS_SAMPLE_RATE);
+        let sfx_sink = Sink::try_new(&stream_handle)?;
+        sfx_sink.append(EffectSource::new(BusMixer::new(mixer), sfx_effects.clone()));
+
         Ok(Self {
             _stream,
             stream_handle,
-            asset_root: asset_root.as_ref().to_path_buf(),
-            cache: HashMap::new(),
-            music_sink: None,
+            clips: AssetManager::new(asset_root),
+            sfx_mixer,
+            sfx_sink,
+            sfx_effects,
+            music_effects: BusEffects::default(),
+            music: None,
+            playing_music: None,
+            fading_music: Vec::new(),
+            music_parameters: HashMap::new(),
             active_sounds: Vec::new(),
             master_volume: 1.0,
         })
     }
 
-    /// Get the full path for an audio asset
-    fn full_path(&self, relative_path: &str) -> PathBuf {
-        self.asset_root.join(relative_path)
+    /// The clip cache, for mounting packs, setting clip load policies and
+    /// preloading
+    pub fn clips(&mut self) -> &mut AssetManager {
+        &mut self.clips
     }
 
-    /// Load audio data from file (with caching)
-    fn load_audio_data(&mut self, path: &str) -> Result<AudioData> {
-        let full_path = self.full_path(path);
-
-        // Check cache
-        if let Some(data) = self.cache.get(&full_path) {
-            return Ok(AudioData {
-                data: Arc::clone(&data.data),
-            });
+    fn bus(&self, bus: AudioBus) -> &BusEffects {
+        match bus {
+            AudioBus::Sfx => &self.sfx_effects,
+            AudioBus::Music => &self.music_effects,
         }
+    }
 
-        // Load from disk
-        log::info!("Loading audio: {:?}", full_path);
-        let file = File::open(&full_path)
-            .with_context(|| format!("Failed to open audio file: {}", path))?;
-
-        let mut reader = BufReader::new(file);
-        let mut data = Vec::new();
-        std::io::copy(&mut reader, &mut data)?;
-
-        let audio_data = AudioData {
-            data: Arc::new(data),
-        };
+    /// A bus's effect chain settings
+    pub fn bus_effects(&self, bus: AudioBus) -> EffectSettings {
+        self.bus(bus).get()
+    }
 
-        self.cache.insert(full_path, AudioData {
-            data: Arc::clone(&audio_data.data),
-        });
+    /// Change a bus's effects; sounds already playing pick them up
+    pub fn set_bus_effects(&self, bus: AudioBus, settings: EffectSettings) {
+        self.bus(bus).set(settings);
+    }
 
-        Ok(audio_data)
+    /// Load a clip (with caching); one still preloading finishes here
+    fn load_clip(&mut self, path: &str) -> Result<AudioClip> {
+        let handle = self
+            .clips
+            .load_audio(path)
+            .with_context(|| format!("Failed to load audio: {}", path))?;
+        Ok(handle.get().clone())
     }
 
     /// Play a sound effect (non-looping)
     pub fn play_sound(&mut self, path: &str, volume: f32) -> Result<()> {
-        let audio_data = self.load_audio_data(path)?;
-        let cursor = std::io::Cursor::new((*audio_data.data).clone());
+        let source = clip_source(&self.load_clip(path)?)?;
+        self.sfx_mixer.add(source.amplify(volume));
 
-        let source = Decoder::new(cursor)
-            .with_context(|| format!("Failed to decode audio: {}", path))?;
+        Ok(())
+    }
 
-        let sink = Sink::try_new(&self.stream_handle)?;
-        sink.set_volume(volume * self.master_volume);
-        sink.append(source);
-        sink.detach();
+    /// Play a sound on the SFX bus that can be paused, sought, stopped and
+    /// have its gain changed while it plays
+    pub fn play_voice(&mut self, path: &str, looping: bool, gain: f32) -> Result<Voice> {
+        let (source, voice) = VoiceSource::new(self.load_clip(path)?, looping, gain)?;
+        self.sfx_mixer.add(source);
+        Ok(voice)
+    }
+
+    /// Play background music
+    pub fn play_music(&mut self, path: &str, volume: f32, looping: bool) -> Result<()> {
+        self.play_track(
+            MusicTrack::new(path)
+                .with_volume(volume)
+                .with_looping(looping),
+        )
+    }
+
+    /// Play a music track, crossfading from the current one over the
+    /// track's fade in (or cutting it off when there is none)
+    pub fn play_track(&mut self, track: MusicTrack) -> Result<()> {
+        let clip = self.load_clip(&track.path)?;
+        let source = track_source(&clip, track.looping, track.loop_points)?;
+
+        self.start_music(source, track.volume, track.fade_in, track.curve, None)?;
+        self.playing_music = Some(PlayingMusic {
+            path: track.path,
+            volume: track.volume,
+            looping: track.looping,
+        });
 
         Ok(())
     }
 
-    /// Play background music (looping)
-    pub fn play_music(&mut self, path: &str, volume: f32, looping: bool) -> Result<()> {
-        // Stop current music if playing
-        if let Some(sink) = self.music_sink.take() {
-            sink.stop();
+    /// Play an adaptive track, its layers mixed by the music parameters,
+    /// crossfading from the current music over `fade_in` seconds
+    pub fn play_adaptive(&mut self, track: &AdaptiveTrack, fade_in: f32) -> Result<()> {
+        // Stems are resampled to one format so they mix sample for sample
+        let mut stems: Vec<Box<dyn Source<Item = f32> + Send>> = Vec::new();
+        for layer in &track.layers {
+            let clip = self.load_clip(&layer.stem)?;
+            let stem = track_source(&clip, true, None)?;
+            stems.push(Box::new(UniformSourceIterator::<_, f32>::new(
+                stem,
+                BUS_CHANNELS,
+                BUS_SAMPLE_RATE,
+            )));
         }
 
-        let audio_data = self.load_audio_data(path)?;
-        let cursor = std::io::Cursor::new((*audio_data.data).clone());
+        let control = Arc::new(LayerControl::default());
+        let gains = track.gains(&self.music_parameters);
+        let source = LayerMixer::new(track, stems, gains, control.clone());
+        self.start_music(
+            Box::new(source),
+            track.volume,
+            fade_in,
+            FadeCurve::default(),
+            Some((track.clone(), control)),
+        )?;
+        self.playing_music = None;
 
-        let source = Decoder::new(cursor)
-            .with_context(|| format!("Failed to decode audio: {}", path))?;
+        Ok(())
+    }
 
+    /// Play `source` on a new music sink, fading out the old one
+    fn start_music(
+        &mut self,
+        source: Box<dyn Source<Item = f32> + Send>,
+        volume: f32,
+        fade_in: f32,
+        curve: FadeCurve,
+        adaptive: Option<(AdaptiveTrack, Arc<LayerControl>)>,
+    ) -> Result<()> {
         let sink = Sink::try_new(&self.stream_handle)?;
         sink.set_volume(volume * self.master_volume);
 
-        if looping {
-            sink.append(source.repeat_infinite());
-        } else {
-            sink.append(source);
+        if let Some(music) = self.music.take() {
+            if fade_in > 0.0 {
+                self.fading_music.push(music.fade_out(fade_in));
+            } else {
+                music.sink.stop();
+            }
         }
 
-        self.music_sink = Some(sink);
+        let fade = Arc::new(FadeControl::default());
+        let start = if fade_in > 0.0 {
+            Fade::fade_in(fade_in, curve)
+        } else {
+            Fade::none()
+        };
+        sink.append(EffectSource::new(
+            FadeSource::new(source, start, fade.clone()),
+            self.music_effects.clone(),
+        ));
+
+        self.music = Some(MusicPlayer {
+            sink,
+            volume,
+            fade,
+            curve,
+            adaptive,
+        });
 
         Ok(())
     }
 
+    /// Set a music parameter (e.g. "combat_intensity"); the adaptive track
+    /// playing moves its layers to match on its next beat or bar
+    pub fn set_music_parameter(&mut self, name: &str, value: f32) {
+        self.music_parameters.insert(name.to_string(), value);
+        if let Some((track, control)) = self.music.as_ref().and_then(|m| m.adaptive.as_ref()) {
+            control.set_gains(track.gains(&self.music_parameters));
+        }
+    }
+
+    /// A music parameter's value (0 if never set)
+    pub fn music_parameter(&self, name: &str) -> f32 {
+        self.music_parameters.get(name).copied().unwrap_or(0.0)
+    }
+
     /// Stop background music
     pub fn stop_music(&mut self) {
-        if let Some(sink) = self.music_sink.take() {
-            sink.stop();
+        if let Some(music) = self.music.take() {
+            music.sink.stop();
+        }
+        self.playing_music = None;
+    }
+
+    /// Fade background music out over `seconds`, then stop it
+    pub fn fade_out_music(&mut self, seconds: f32) {
+        if seconds <= 0.0 {
+            return self.stop_music();
+        }
+        if let Some(music) = self.music.take() {
+            self.fading_music.push(music.fade_out(seconds));
         }
+        self.playing_music = None;
+    }
+
+    /// The music track playing, if any (a finished non-looping track
+    /// counts as stopped)
+    pub fn playing_music(&self) -> Option<&PlayingMusic> {
+        self.playing_music
+            .as_ref()
+            .filter(|_| self.music.as_ref().is_some_and(|music| !music.sink.empty()))
     }
 
     /// Pause background music
     pub fn pause_music(&mut self) {
-        if let Some(sink) = &self.music_sink {
-            sink.pause();
+        for music in self.music.iter().chain(&self.fading_music) {
+            music.sink.pause();
         }
     }
 
     /// Resume background music
     pub fn resume_music(&mut self) {
-        if let Some(sink) = &self.music_sink {
-            sink.play();
+        for music in self.music.iter().chain(&self.fading_music) {
+            music.sink.play();
         }
     }
 
@@ -157,9 +312,10 @@ impl AudioSystem {
         self.master_volume = volume.clamp(0.0, 1.0);
 
         // Update music volume
-        if let Some(sink) = &self.music_sink {
-            sink.set_volume(self.master_volume);
+        if let Some(music) = &self.music {
+            music.sink.set_volume(music.volume * self.master_volume);
         }
+        self.sfx_sink.set_volume(self.master_volume);
     }
 
     /// Get master volume
@@ -176,27 +332,17 @@ impl AudioSystem {
         volume: f32,
         max_distance: f32,
     ) -> Result<()> {
-        let audio_data = self.load_audio_data(path)?;
-        let cursor = std::io::Cursor::new((*audio_data.data).clone());
-
-        let source = Decoder::new(cursor)
-            .with_context(|| format!("Failed to decode audio: {}", path))?;
+        let source = clip_source(&self.load_clip(path)?)?;
 
         // Calculate distance attenuation
         let distance = (position - listener.position).length();
-        let attenuation = if distance < max_distance {
-            1.0 - (distance / max_distance).powi(2)
-        } else {
-            0.0
-        };
+        let attenuation = distance_attenuation(distance, max_distance);
 
-        let final_volume = (volume * attenuation * self.master_volume).clamp(0.0, 1.0);
+        // The SFX bus applies the master volume
+        let final_volume = (volume * attenuation).clamp(0.0, 1.0);
 
-        if final_volume > 0.01 {
-            let sink = Sink::try_new(&self.stream_handle)?;
-            sink.set_volume(final_volume);
-            sink.append(source);
-            sink.detach();
+        if final_volume * self.master_volume > 0.01 {
+            self.sfx_mixer.add(source.amplify(final_volume));
         }
 
         Ok(())
@@ -206,16 +352,18 @@ impl AudioSystem {
     pub fn update(&mut self) {
         // Remove finished sound effects
         self.active_sounds.retain(|sink| !sink.empty());
+        // Remove music that has faded out
+        self.fading_music.retain(|music| !music.sink.empty());
     }
 
     /// Get cache size
     pub fn cache_size(&self) -> usize {
-        self.cache.len()
+        self.clips.audio_cache_size()
     }
 
     /// Clear cache
     pub fn clear_cache(&mut self) {
-        self.cache.clear();
+        self.clips.clear_cache();
         log::info!("Audio cache cleared");
     }
 }
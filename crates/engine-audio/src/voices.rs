@@ -0,0 +1,453 @@
+// Voices - sounds that keep playing under the game's control
+//
+// A voice is a clip playing on the SFX bus whose gain, pause state and
+// position can change while it plays: the game thread sets them on the
+// voice's shared control and the audio thread picks them up each frame.
+// EntityVoices gives every entity with an AudioSource its own voice, kept at
+// the entity's position, muffled by what is between it and the listener
+// (see occlusion) and reflected in the component's `playing` flag.
+
+use anyhow::Result;
+use engine_assets::AudioClip;
+use engine_physics::PhysicsWorld;
+use engine_scene::components::AudioSource;
+use engine_scene::entity::EntityId;
+use engine_scene::scene::Scene;
+use rodio::Source;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::clip::clip_source_at;
+use crate::effects::{Biquad, FilterKind, FILTER_OPEN_HIGH_HZ};
+use crate::listener::AudioListener;
+use crate::occlusion::{occlusion, Occlusion, OCCLUSION_SMOOTHING};
+use crate::system::AudioSystem;
+
+/// Volume of a sound `distance` from the listener (quadratic falloff)
+pub fn distance_attenuation(distance: f32, max_distance: f32) -> f32 {
+    if distance < max_distance {
+        1.0 - (distance / max_distance).powi(2)
+    } else {
+        0.0
+    }
+}
+
+/// State shared between a voice's source and its handle
+#[derive(Debug, Default)]
+struct VoiceControl {
+    gain: AtomicU32,
+    low_pass_hz: AtomicU32,
+    paused: AtomicBool,
+    stopped: AtomicBool,
+    finished: AtomicBool,
+    seek: Mutex<Option<f32>>,
+    has_seek: AtomicBool,
+    /// Frames played from the start of the clip
+    frames: AtomicU64,
+}
+
+/// Handle to a playing voice
+#[derive(Debug, Clone)]
+pub struct Voice {
+    control: Arc<VoiceControl>,
+    sample_rate: u32,
+    duration: Option<f32>,
+}
+
+impl Voice {
+    pub fn set_gain(&self, gain: f32) {
+        self.control.gain.store(gain.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn gain(&self) -> f32 {
+        f32::from_bits(self.control.gain.load(Ordering::Relaxed))
+    }
+
+    /// Low-pass the voice at `hz` (FILTER_OPEN_HIGH_HZ = unfiltered)
+    pub fn set_low_pass(&self, hz: f32) {
+        self.control
+            .low_pass_hz
+            .store(hz.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn low_pass(&self) -> f32 {
+        f32::from_bits(self.control.low_pass_hz.load(Ordering::Relaxed))
+    }
+
+    pub fn pause(&self) {
+        self.control.paused.store(true, Ordering::Relaxed);
+    }
+
+    pub fn resume(&self) {
+        self.control.paused.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.control.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn stop(&self) {
+        self.control.stopped.store(true, Ordering::Relaxed);
+    }
+
+    /// Jump to `seconds` into the clip
+    pub fn seek(&self, seconds: f32) {
+        *self.control.seek.lock().unwrap() = Some(seconds.max(0.0));
+        self.control.has_seek.store(true, Ordering::Release);
+    }
+
+    /// Seconds into the clip
+    pub fn position(&self) -> f32 {
+        let frames = self.control.frames.load(Ordering::Relaxed);
+        frames as f32 / self.sample_rate.max(1) as f32
+    }
+
+    /// Length of the clip in seconds, when known
+    pub fn duration(&self) -> Option<f32> {
+        self.duration
+    }
+
+    /// Whether the voice has played to the end or been stopped
+    pub fn is_finished(&self) -> bool {
+        self.control.finished.load(Ordering::Relaxed)
+            || self.control.stopped.load(Ordering::Relaxed)
+    }
+}
+
+/// Plays a clip under a voice's control
+pub struct VoiceSource {
+    clip: AudioClip,
+    looping: bool,
+    input: Box<dyn Source<Item = f32> + Send>,
+    control: Arc<VoiceControl>,
+    channels: u16,
+    sample_rate: u32,
+    gain: f32,
+    low_pass_hz: f32,
+    /// Low-pass filter per channel
+    filters: Vec<Biquad>,
+    paused: bool,
+    frames: u64,
+    /// Samples left in the current frame
+    frame_left: usize,
+}
+
+impl VoiceSource {
+    /// A voice source for `clip`, and the handle that controls it
+    pub fn new(clip: AudioClip, looping: bool, gain: f32) -> Result<(Self, Voice)> {
+        let input = clip_source_at(&clip, 0.0)?;
+        let (channels, sample_rate) = (input.channels().max(1), input.sample_rate().max(1));
+        let duration = input.total_duration().map(|d| d.as_secs_f32());
+        let control = Arc::new(VoiceControl::default());
+        let voice = Voice {
+            control: control.clone(),
+            sample_rate,
+            duration,
+        };
+        voice.set_gain(gain);
+        voice.set_low_pass(FILTER_OPEN_HIGH_HZ);
+        let source = Self {
+            clip,
+            looping,
+            input,
+            control,
+            channels,
+            sample_rate,
+            gain,
+            low_pass_hz: FILTER_OPEN_HIGH_HZ,
+            filters: vec![Biquad::default(); channels as usize],
+            paused: false,
+            frames: 0,
+            frame_left: 0,
+        };
+        Ok((source, voice))
+    }
+
+    /// Restart the clip `seconds` in
+    fn restart(&mut self, seconds: f32) -> Option<()> {
+        match clip_source_at(&self.clip, seconds) {
+            Ok(input) => {
+                self.input = input;
+                self.frames = (seconds as f64 * self.sample_rate as f64) as u64;
+                Some(())
+            }
+            Err(e) => {
+                log::warn!("Failed to restart '{}': {}", self.clip.name, e);
+                None
+            }
+        }
+    }
+
+    fn finish(&self) -> Option<f32> {
+        self.control.finished.store(true, Ordering::Relaxed);
+        None
+    }
+}
+
+impl Iterator for VoiceSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.frame_left == 0 {
+            if self.control.stopped.load(Ordering::Relaxed) {
+                return self.finish();
+            }
+            let seek = match self.control.has_seek.swap(false, Ordering::AcqRel) {
+                true => self.control.seek.lock().unwrap().take(),
+                false => None,
+            };
+            if let Some(seconds) = seek {
+                if self.restart(seconds).is_none() {
+                    return self.finish();
+                }
+            }
+            self.gain = f32::from_bits(self.control.gain.load(Ordering::Relaxed));
+            self.paused = self.control.paused.load(Ordering::Relaxed);
+            let low_pass_hz = f32::from_bits(self.control.low_pass_hz.load(Ordering::Relaxed));
+            if (low_pass_hz - self.low_pass_hz).abs() > self.low_pass_hz * 0.01 {
+                self.low_pass_hz = low_pass_hz;
+                for filter in &mut self.filters {
+                    filter.set(FilterKind::LowPass, low_pass_hz, self.sample_rate);
+                }
+            }
+            if !self.paused {
+                self.frames += 1;
+                self.control.frames.store(self.frames, Ordering::Relaxed);
+            }
+            self.frame_left = self.channels as usize;
+        }
+        self.frame_left -= 1;
+
+        // A paused voice keeps its place, playing silence
+        if self.paused {
+            return Some(0.0);
+        }
+        let sample = match self.input.next() {
+            Some(sample) => sample,
+            None if self.looping => {
+                if self.restart(0.0).is_none() {
+                    return self.finish();
+                }
+                // This frame is the first of the new pass
+                self.frames += 1;
+                self.control.frames.store(self.frames, Ordering::Relaxed);
+                self.input.next().or_else(|| self.finish())?
+            }
+            None => return self.finish(),
+        };
+        if self.low_pass_hz < FILTER_OPEN_HIGH_HZ {
+            let channel = self.channels as usize - 1 - self.frame_left;
+            return Some(self.filters[channel].process(sample) * self.gain);
+        }
+        Some(sample * self.gain)
+    }
+}
+
+impl Source for VoiceSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// The voices of entities' AudioSource components
+#[derive(Default)]
+pub struct EntityVoices {
+    voices: HashMap<EntityId, Voice>,
+    /// Entities whose play_on_start has been handled
+    started: HashSet<EntityId>,
+    /// Each voice's occlusion, eased towards what the rays find
+    occlusion: HashMap<EntityId, Occlusion>,
+}
+
+impl EntityVoices {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Play an entity's AudioSource from the start, replacing its voice
+    pub fn play(&mut self, audio: &mut AudioSystem, scene: &Scene, entity: EntityId) -> Result<()> {
+        let source = scene
+            .get_entity(entity)
+            .and_then(|e| e.get_component::<AudioSource>())
+            .ok_or_else(|| anyhow::anyhow!("Entity {:?} has no AudioSource", entity))?;
+        self.stop(entity);
+        let voice = audio.play_voice(&source.audio_path, source.looping, 0.0)?;
+        self.started.insert(entity);
+        self.voices.insert(entity, voice);
+        Ok(())
+    }
+
+    pub fn stop(&mut self, entity: EntityId) {
+        if let Some(voice) = self.voices.remove(&entity) {
+            voice.stop();
+        }
+        self.occlusion.remove(&entity);
+    }
+
+    pub fn pause(&self, entity: EntityId) {
+        if let Some(voice) = self.voices.get(&entity) {
+            voice.pause();
+        }
+    }
+
+    pub fn resume(&self, entity: EntityId) {
+        if let Some(voice) = self.voices.get(&entity) {
+            voice.resume();
+        }
+    }
+
+    pub fn seek(&self, entity: EntityId, seconds: f32) {
+        if let Some(voice) = self.voices.get(&entity) {
+            voice.seek(seconds);
+        }
+    }
+
+    pub fn voice(&self, entity: EntityId) -> Option<&Voice> {
+        self.voices.get(&entity)
+    }
+
+    /// Start play_on_start sources (while simulating), drop finished
+    /// voices, set each voice's volume from its entity's world position (and
+    /// its occlusion, given the physics world) and mark the components
+    /// playing or not
+    pub fn update(
+        &mut self,
+        audio: &mut AudioSystem,
+        scene: &mut Scene,
+        physics: Option<&PhysicsWorld>,
+        listener: &AudioListener,
+        simulating: bool,
+    ) {
+        if simulating {
+            let to_start: Vec<EntityId> = scene
+                .entities()
+                .filter(|entity| !self.started.contains(&entity.id))
+                .filter(|entity| {
+                    entity
+                        .get_component::<AudioSource>()
+                        .is_some_and(|source| source.play_on_start)
+                })
+                .map(|entity| entity.id)
+                .collect();
+            for entity in to_start {
+                self.started.insert(entity);
+                if let Err(e) = self.play(audio, scene, entity) {
+                    log::warn!("Failed to play audio for {:?}: {}", entity, e);
+                }
+            }
+        }
+
+        self.voices.retain(|&entity, voice| {
+            let position = scene.world_matrix(entity).w_axis.truncate();
+            let target = match physics {
+                Some(physics) if !voice.is_finished() => {
+                    occlusion(physics, scene, position, entity, listener)
+                }
+                _ => Occlusion::NONE,
+            };
+            let Some(source) = scene
+                .get_entity_mut(entity)
+                .and_then(|e| e.get_component_mut::<AudioSource>())
+            else {
+                voice.stop();
+                self.occlusion.remove(&entity);
+                return false;
+            };
+            let playing = !voice.is_finished();
+            source.playing = playing && !voice.is_paused();
+            if playing {
+                let occlusion = self.occlusion.entry(entity).or_insert(target);
+                *occlusion = occlusion.lerp(&target, OCCLUSION_SMOOTHING);
+                let distance = (position - listener.position).length();
+                voice.set_gain(voice_gain(source, distance) * occlusion.gain);
+                voice.set_low_pass(occlusion.low_pass_hz);
+            } else {
+                self.occlusion.remove(&entity);
+            }
+            playing
+        });
+    }
+
+    /// Stop every voice and forget which sources have started (leaving play
+    /// mode)
+    pub fn clear(&mut self, scene: &mut Scene) {
+        for (entity, voice) in self.voices.drain() {
+            voice.stop();
+            if let Some(source) = scene
+                .get_entity_mut(entity)
+                .and_then(|e| e.get_component_mut::<AudioSource>())
+            {
+                source.playing = false;
+            }
+        }
+        self.started.clear();
+        self.occlusion.clear();
+    }
+}
+
+/// An AudioSource's volume `distance` from the listener
+fn voice_gain(source: &AudioSource, distance: f32) -> f32 {
+    (source.volume * distance_attenuation(distance, source.max_distance)).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_assets::{AudioClipData, PcmData};
+
+    fn clip() -> AudioClip {
+        AudioClip {
+            name: "ramp.wav".to_string(),
+            data: AudioClipData::Decoded(PcmData {
+                sample_rate: 4,
+                channels: 1,
+                samples: (0..8).map(|i| i as f32).collect::<Vec<_>>().into(),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_voice_pause_seek_and_stop() {
+        let (mut source, voice) = VoiceSource::new(clip(), false, 1.0).unwrap();
+        assert_eq!(source.by_ref().take(2).collect::<Vec<_>>(), [0.0, 1.0]);
+        assert_eq!(voice.position(), 0.5);
+        assert_eq!(voice.duration(), Some(2.0));
+
+        voice.pause();
+        assert_eq!(source.by_ref().take(2).collect::<Vec<_>>(), [0.0, 0.0]);
+        assert_eq!(voice.position(), 0.5);
+        voice.resume();
+        voice.seek(1.5);
+        voice.set_gain(0.5);
+        assert_eq!(source.by_ref().collect::<Vec<_>>(), [3.0, 3.5]);
+        assert!(voice.is_finished());
+
+        let (mut source, voice) = VoiceSource::new(clip(), true, 1.0).unwrap();
+        assert_eq!(source.by_ref().take(10).last(), Some(1.0));
+        voice.stop();
+        assert_eq!(source.next(), None);
+    }
+
+    #[test]
+    fn test_distance_attenuation() {
+        assert_eq!(distance_attenuation(0.0, 10.0), 1.0);
+        assert_eq!(distance_attenuation(5.0, 10.0), 0.75);
+        assert_eq!(distance_attenuation(12.0, 10.0), 0.0);
+        let source = AudioSource::new("a.wav".to_string()).with_volume(2.0);
+        assert_eq!(voice_gain(&source, 0.0), 1.0);
+    }
+}
@@ -0,0 +1,192 @@
+// Diff - what changed between two versions of a scene
+//
+// Entities are matched by id. Each change prints on one line:
+//
+//   + Tower (12)
+//   - Crate (7)
+//   ~ Wall (3): position [0, 0, 0] -> [1, 0, 0], MeshRenderer changed
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use engine_scene::entity::{Entity, EntityId};
+use engine_scene::scene::Scene;
+use engine_scene::SerializedComponent;
+use glam::Vec3;
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SceneChange {
+    Renamed {
+        from: String,
+        to: String,
+    },
+    Added {
+        id: EntityId,
+        name: String,
+    },
+    Removed {
+        id: EntityId,
+        name: String,
+    },
+    Modified {
+        id: EntityId,
+        name: String,
+        details: Vec<String>,
+    },
+}
+
+impl fmt::Display for SceneChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Renamed { from, to } => write!(f, "scene renamed '{}' -> '{}'", from, to),
+            Self::Added { id, name } => write!(f, "+ {} ({})", name, id.0),
+            Self::Removed { id, name } => write!(f, "- {} ({})", name, id.0),
+            Self::Modified { id, name, details } => {
+                write!(f, "~ {} ({}): {}", name, id.0, details.join(", "))
+            }
+        }
+    }
+}
+
+/// Changes from `old` to `new`, in entity id order
+pub fn diff_scenes(old: &Scene, new: &Scene) -> Vec<SceneChange> {
+    let mut changes = Vec::new();
+    if old.name != new.name {
+        changes.push(SceneChange::Renamed {
+            from: old.name.clone(),
+            to: new.name.clone(),
+        });
+    }
+
+    let mut ids: Vec<EntityId> = old.entities().chain(new.entities()).map(|e| e.id).collect();
+    ids.sort_by_key(|id| id.0);
+    ids.dedup();
+    for id in ids {
+        match (old.get_entity(id), new.get_entity(id)) {
+            (None, Some(entity)) => changes.push(SceneChange::Added {
+                id,
+                name: entity.name.clone(),
+            }),
+            (Some(entity), None) => changes.push(SceneChange::Removed {
+                id,
+                name: entity.name.clone(),
+            }),
+            (Some(before), Some(after)) => {
+                let details = entity_changes(before, after);
+                if !details.is_empty() {
+                    changes.push(SceneChange::Modified {
+                        id,
+                        name: after.name.clone(),
+                        details,
+                    });
+                }
+            }
+            (None, None) => {}
+        }
+    }
+    changes
+}
+
+fn entity_changes(before: &Entity, after: &Entity) -> Vec<String> {
+    let mut details = Vec::new();
+    if before.name != after.name {
+        details.push(format!("renamed from '{}'", before.name));
+    }
+    let (old, new) = (&before.transform, &after.transform);
+    if old.position != new.position {
+        details.push(format!(
+            "position {} -> {}",
+            vec3(old.position),
+            vec3(new.position)
+        ));
+    }
+    if old.rotation != new.rotation {
+        details.push("rotation changed".to_string());
+    }
+    if old.scale != new.scale {
+        details.push(format!("scale {} -> {}", vec3(old.scale), vec3(new.scale)));
+    }
+    if before.parent != after.parent {
+        let parent =
+            |parent: Option<EntityId>| parent.map_or("none".to_string(), |id| id.0.to_string());
+        details.push(format!(
+            "parent {} -> {}",
+            parent(before.parent),
+            parent(after.parent)
+        ));
+    }
+
+    let (old, new) = (components(before), components(after));
+    for (name, value) in &new {
+        match old.get(name) {
+            None => details.push(format!("added {}", name)),
+            Some(previous) if previous != value => details.push(format!("{} changed", name)),
+            Some(_) => {}
+        }
+    }
+    for name in old.keys().filter(|name| !new.contains_key(*name)) {
+        details.push(format!("removed {}", name));
+    }
+    details
+}
+
+/// The entity's saved components by type name
+fn components(entity: &Entity) -> BTreeMap<String, Value> {
+    SerializedComponent::from_entity(entity)
+        .into_iter()
+        .filter_map(|component| {
+            let value = serde_json::to_value(component).ok()?;
+            let name = value.get("type")?.as_str()?.to_string();
+            Some((name, value))
+        })
+        .collect()
+}
+
+fn vec3(v: Vec3) -> String {
+    format!("[{}, {}, {}]", v.x, v.y, v.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_scene::components::{Light, MeshRenderer};
+
+    #[test]
+    fn test_diff_lists_entity_changes() {
+        let mut old = Scene::new("Castle".to_string());
+        let wall = old.create_entity("Wall".to_string());
+        let crate_id = old.create_entity("Crate".to_string());
+        old.get_entity_mut(wall)
+            .unwrap()
+            .add_component(MeshRenderer::new("stone_cube".to_string()));
+
+        let mut new = Scene::from_serialized(old.to_serialized());
+        new.remove_entity(crate_id);
+        let tower = new.create_entity("Tower".to_string());
+        let entity = new.get_entity_mut(wall).unwrap();
+        entity.transform.position = Vec3::new(1.0, 0.0, 0.0);
+        entity
+            .get_component_mut::<MeshRenderer>()
+            .unwrap()
+            .mesh_path = "grass_cube".to_string();
+        entity.add_component(Light::point([1.0; 3], 1.0, 10.0));
+
+        let changes: Vec<String> = diff_scenes(&old, &new)
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                format!(
+                    "~ Wall ({}): position [0, 0, 0] -> [1, 0, 0], added Light, MeshRenderer changed",
+                    wall.0
+                ),
+                format!("- Crate ({})", crate_id.0),
+                format!("+ Tower ({})", tower.0),
+            ]
+        );
+        assert!(diff_scenes(&old, &old).is_empty());
+    }
+}
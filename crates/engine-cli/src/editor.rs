@@ -0,0 +1,116 @@
+// Editor - bakes and smoke tests run by the editor executable
+//
+// The bakes and the simulation live in the editor, so these commands run
+// `editor --bake` and `editor --headless` (no window or GPU needed). The
+// editor is looked for next to causality-cli, where cargo and installs put it.
+
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+
+/// File name of the editor executable (without extension)
+const EDITOR_NAME: &str = "editor";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum BakeTarget {
+    Navmesh,
+    Lightmaps,
+}
+
+impl BakeTarget {
+    fn arg(&self) -> &'static str {
+        match self {
+            BakeTarget::Navmesh => "navmesh",
+            BakeTarget::Lightmaps => "lightmaps",
+        }
+    }
+}
+
+fn editor_command() -> Result<Command> {
+    let exe_name = format!("{}{}", EDITOR_NAME, std::env::consts::EXE_SUFFIX);
+    let path = std::env::current_exe()?
+        .parent()
+        .map(|dir| dir.join(&exe_name))
+        .filter(|path| path.is_file())
+        .with_context(|| format!("No {} next to causality-cli", exe_name))?;
+    Ok(Command::new(path))
+}
+
+pub fn bake(target: BakeTarget, scene: &str) -> Result<()> {
+    let status = editor_command()?
+        .args(["--bake", target.arg(), "--scene", scene])
+        .status()?;
+    if !status.success() {
+        bail!("Baking {} for {} failed ({})", target.arg(), scene, status);
+    }
+    Ok(())
+}
+
+/// Simulate the scene for `frames` frames; the problems found, if any
+pub fn smoke_test(scene: &str, frames: u32) -> Result<Vec<String>> {
+    let output = std::env::temp_dir().join(format!("causality-smoke-{}.json", std::process::id()));
+    let status = editor_command()?
+        .args(["--headless", "--scene", scene, "--frames"])
+        .arg(frames.to_string())
+        .arg("--output")
+        .arg(&output)
+        .status()?;
+    if !status.success() {
+        return Ok(vec![format!("simulation failed ({})", status)]);
+    }
+    let report = std::fs::read_to_string(&output)
+        .with_context(|| format!("Failed to read {}", output.display()))?;
+    let _ = std::fs::remove_file(&output);
+    Ok(check_report(&serde_json::from_str(&report)?, frames))
+}
+
+/// Problems with a headless run's report: missing frames, or entities whose
+/// transform went NaN or infinite (written as null)
+fn check_report(report: &Value, frames: u32) -> Vec<String> {
+    let mut problems = Vec::new();
+    if report["frames"].as_u64() != Some(frames as u64) {
+        problems.push(format!(
+            "simulated {} frames, expected {}",
+            report["frames"], frames
+        ));
+    }
+    for entity in report["final"]["entities"].as_array().into_iter().flatten() {
+        let finite = ["position", "rotation", "scale", "velocity"]
+            .iter()
+            .all(|key| {
+                entity[*key]
+                    .as_array()
+                    .is_none_or(|values| values.iter().all(Value::is_number))
+            });
+        if !finite {
+            problems.push(format!(
+                "'{}' ended with a NaN or infinite transform",
+                entity["name"].as_str().unwrap_or("?")
+            ));
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_check_report_finds_broken_entities() {
+        let report = json!({
+            "frames": 60,
+            "final": { "entities": [
+                { "name": "Ball", "position": [0.0, 1.0, 0.0], "velocity": [0.0, -1.0, 0.0] },
+                { "name": "Rocket", "position": [null, 1.0, 0.0] },
+            ]},
+        });
+        assert_eq!(
+            check_report(&report, 60),
+            ["'Rocket' ended with a NaN or infinite transform"]
+        );
+        assert_eq!(check_report(&report, 120).len(), 2);
+    }
+}
@@ -0,0 +1,202 @@
+// causality-cli - scene and asset operations without opening the editor
+//
+//   causality-cli validate assets/scenes/*.ron
+//   causality-cli migrate assets/scenes/*.ron --check
+//   causality-cli pack --assets assets --output build/assets.cpack
+//   causality-cli bake navmesh assets/scenes/castle.ron
+//   causality-cli diff old/castle.ron assets/scenes/castle.ron
+//   causality-cli smoke assets/scenes/castle.ron --frames 600
+//
+// Every command exits with a non-zero status when it finds a problem, so CI
+// and scripts can run them as they are. Bakes and smoke tests run the editor
+// executable installed next to this one with --bake and --headless, which
+// need no window or GPU.
+
+mod diff;
+mod editor;
+mod pack;
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+use engine_core::logging::{self, LogConfig};
+use engine_core::project;
+use engine_scene::scene::Scene;
+use engine_scene::{SerializedScene, SCENE_FORMAT_VERSION};
+
+#[derive(Parser, Debug)]
+#[command(name = "causality-cli")]
+#[command(about = "Scene and asset operations for Causality Engine projects", long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check scenes for broken hierarchy, bad transforms and missing assets
+    Validate {
+        scenes: Vec<String>,
+        /// Asset directory referenced paths are relative to (default: the
+        /// project's asset_root)
+        #[arg(long)]
+        assets: Option<PathBuf>,
+    },
+    /// Upgrade scenes saved by older versions of the engine
+    Migrate {
+        scenes: Vec<String>,
+        /// Only report scenes that need migrating, don't rewrite them
+        #[arg(long)]
+        check: bool,
+    },
+    /// Pack the asset directory into one archive
+    Pack {
+        /// Directory to pack (default: the project's asset_root)
+        #[arg(long)]
+        assets: Option<PathBuf>,
+        #[arg(short, long, default_value = "assets.cpack")]
+        output: PathBuf,
+    },
+    /// Bake a scene's navmesh or lightmaps and save them
+    Bake {
+        #[arg(value_enum)]
+        target: editor::BakeTarget,
+        scene: String,
+    },
+    /// Show what changed between two versions of a scene
+    Diff { old: String, new: String },
+    /// Simulate scenes headless and check that they run
+    Smoke {
+        scenes: Vec<String>,
+        /// Frames to simulate (60 per second)
+        #[arg(long, default_value_t = 300)]
+        frames: u32,
+    },
+}
+
+fn main() -> ExitCode {
+    if let Err(e) = logging::init(&LogConfig::default().with_directory(None)) {
+        eprintln!("Logging disabled: {:#}", e);
+    }
+    match run(Cli::parse().command) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Run a command; false if it found problems
+fn run(command: Command) -> Result<bool> {
+    match command {
+        Command::Validate { scenes, assets } => {
+            let assets = assets.unwrap_or_else(|| project::current().asset_root.clone());
+            Ok(for_each_scene(&scenes, |path| validate(path, &assets)))
+        }
+        Command::Migrate { scenes, check } => {
+            Ok(for_each_scene(&scenes, |path| migrate(path, check)))
+        }
+        Command::Pack { assets, output } => {
+            let assets = assets.unwrap_or_else(|| project::current().asset_root.clone());
+            let (files, size) = pack::pack_directory(&assets, &output)?;
+            println!(
+                "Packed {} files ({} bytes) into {}",
+                files,
+                size,
+                output.display()
+            );
+            Ok(true)
+        }
+        Command::Bake { target, scene } => {
+            editor::bake(target, &scene)?;
+            Ok(true)
+        }
+        Command::Diff { old, new } => {
+            let changes = diff::diff_scenes(&load_scene(&old)?, &load_scene(&new)?);
+            for change in &changes {
+                println!("{}", change);
+            }
+            Ok(changes.is_empty())
+        }
+        Command::Smoke { scenes, frames } => Ok(for_each_scene(&scenes, |path| {
+            let problems = editor::smoke_test(path, frames)?;
+            if problems.is_empty() {
+                println!("{}: ran {} frames", path, frames);
+            }
+            for problem in &problems {
+                println!("{}: {}", path, problem);
+            }
+            Ok(problems.is_empty())
+        })),
+    }
+}
+
+/// Run `check` on every scene, carrying on past failures; true if all passed
+fn for_each_scene(scenes: &[String], mut check: impl FnMut(&str) -> Result<bool>) -> bool {
+    let mut passed = true;
+    for path in scenes {
+        match check(path) {
+            Ok(ok) => passed &= ok,
+            Err(e) => {
+                println!("{}: {:#}", path, e);
+                passed = false;
+            }
+        }
+    }
+    passed
+}
+
+fn load_scene(path: &str) -> Result<Scene> {
+    Scene::load_from_file(path).map_err(|e| anyhow!("Failed to load scene {}: {}", path, e))
+}
+
+/// Print a scene's issues; true if it has none
+fn validate(path: &str, asset_root: &Path) -> Result<bool> {
+    let scene = load_scene(path)?;
+    let issues = scene.validate(|asset| asset_exists(asset_root, asset));
+    if issues.is_empty() {
+        println!("{}: ok ({} entities)", path, scene.entity_count());
+    }
+    for issue in &issues {
+        println!("{}: {} [{}]", path, issue.message, issue.kind.name());
+    }
+    Ok(issues.is_empty())
+}
+
+/// Whether a referenced asset is there. Names without an extension are
+/// meshes the editor builds at startup (cubes, terrain, vegetation), not files.
+fn asset_exists(asset_root: &Path, asset: &str) -> bool {
+    let file = Path::new(asset.split('#').next().unwrap_or(asset));
+    file.extension().is_none() || asset_root.join(file).is_file()
+}
+
+/// Rewrite a scene in the current format. With `check` only reports it;
+/// true if it was current already.
+fn migrate(path: &str, check: bool) -> Result<bool> {
+    let mut scene =
+        SerializedScene::load(path).map_err(|e| anyhow!("Failed to load scene {}: {}", path, e))?;
+    let from = scene.migrate().map_err(|e| anyhow!("{}: {}", path, e))?;
+    if from == SCENE_FORMAT_VERSION {
+        println!("{}: version {}, up to date", path, from);
+        return Ok(true);
+    }
+    if check {
+        println!(
+            "{}: version {}, needs migrating to {}",
+            path, from, SCENE_FORMAT_VERSION
+        );
+        return Ok(false);
+    }
+    scene
+        .save(path)
+        .map_err(|e| anyhow!("Failed to save scene {}: {}", path, e))?;
+    println!(
+        "{}: migrated from version {} to {}",
+        path, from, SCENE_FORMAT_VERSION
+    );
+    Ok(true)
+}
@@ -0,0 +1,50 @@
+// Pack - the asset directory as one engine_core::pack archive
+//
+// Editor-only sources (the same ones build export leaves out) stay behind, and
+// so do other packs, including one being written into the asset directory.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use engine_core::pack::{write_pack, PACK_EXTENSION};
+
+/// Source files and notes that are only useful while editing
+const EDITOR_ONLY_EXTENSIONS: [&str; 4] = ["md", "blend", "psd", "kra"];
+
+/// Pack everything under `assets` into `output`. Returns the number of
+/// files and the pack's size.
+pub fn pack_directory(assets: &Path, output: &Path) -> Result<(usize, u64)> {
+    let mut files = Vec::new();
+    collect_files(assets, Path::new(""), &mut files)?;
+    files.sort();
+    let size = write_pack(assets, &files, output)?;
+    Ok((files.len(), size))
+}
+
+/// Files under `root.join(relative)`, relative to `root`
+fn collect_files(root: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let dir = root.join(relative);
+    let entries =
+        std::fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let path = relative.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if !is_editor_only(&path) && !is_pack(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_editor_only(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| EDITOR_ONLY_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+fn is_pack(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e == PACK_EXTENSION || e == "pak")
+}
@@ -0,0 +1,189 @@
+// Crash reports - what the engine was doing when it panicked
+//
+// install() sets a panic hook that writes crashes/crash-<time>.txt with the
+// panic message and where it happened, a backtrace, the crash context the
+// app keeps up to date (the open scene, where it was last autosaved, ...)
+// and the most recent log records, then hands the panic on to the previous
+// hook. The report's path is logged, so it lands in the log file as well.
+
+use std::backtrace::Backtrace;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+
+use crate::logging::{self, LogEntry};
+
+/// Directory crash reports go in, relative to the working directory
+pub const DEFAULT_CRASH_DIR: &str = "crashes";
+
+/// Log records included in a report
+pub const REPORT_LOG_LINES: usize = 200;
+
+/// Context key for the scene file being edited or played
+pub const SCENE_KEY: &str = "scene";
+
+/// Context key for the scene's most recent autosave
+pub const AUTOSAVE_KEY: &str = "autosave";
+
+static CONTEXT: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+/// Record something a crash report should mention
+pub fn set_context(key: &str, value: impl Into<String>) {
+    if let Ok(mut context) = CONTEXT.lock() {
+        context.insert(key.to_string(), value.into());
+    }
+}
+
+pub fn clear_context(key: &str) {
+    if let Ok(mut context) = CONTEXT.lock() {
+        context.remove(key);
+    }
+}
+
+/// Write crash reports into `directory` when a thread panics
+pub fn install(directory: impl Into<PathBuf>) {
+    let directory = directory.into();
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "(non-string panic payload)".to_string());
+        let location = info
+            .location()
+            .map(|location| location.to_string())
+            .unwrap_or_default();
+        let thread = std::thread::current()
+            .name()
+            .unwrap_or("unnamed")
+            .to_string();
+        let backtrace = Backtrace::force_capture().to_string();
+        // A panic while the context is locked leaves it poisoned; the
+        // report goes out without it rather than not at all
+        let context = CONTEXT.lock().map(|c| c.clone()).unwrap_or_default();
+        let log_lines = logging::logger()
+            .map(|logger| logger.recent(REPORT_LOG_LINES))
+            .unwrap_or_default();
+
+        let report = CrashReport {
+            time: SystemTime::now(),
+            thread,
+            message,
+            location,
+            backtrace,
+            context,
+            log_lines,
+        };
+        match report.write(&directory) {
+            Ok(path) => log::error!("Crash report written to {}", path.display()),
+            Err(e) => log::error!("Failed to write crash report: {:#}", e),
+        }
+        log::logger().flush();
+        previous(info);
+    }));
+}
+
+/// Everything a crash report says
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+    pub time: SystemTime,
+    pub thread: String,
+    pub message: String,
+    /// file:line:column of the panic
+    pub location: String,
+    pub backtrace: String,
+    pub context: BTreeMap<String, String>,
+    pub log_lines: Vec<LogEntry>,
+}
+
+impl CrashReport {
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "Causality crash report");
+        let _ = writeln!(
+            text,
+            "Time:     {} UTC",
+            logging::format_timestamp(self.time)
+        );
+        let _ = writeln!(text, "Version:  {}", env!("CARGO_PKG_VERSION"));
+        let _ = writeln!(text, "Thread:   {}", self.thread);
+        let _ = writeln!(text, "Panic:    {}", self.message);
+        let _ = writeln!(text, "Location: {}", self.location);
+        if !self.context.is_empty() {
+            let _ = writeln!(text, "\nContext:");
+            for (key, value) in &self.context {
+                let _ = writeln!(text, "  {}: {}", key, value);
+            }
+        }
+        let _ = writeln!(text, "\nBacktrace:\n{}", self.backtrace);
+        let _ = writeln!(text, "Recent log ({} records):", self.log_lines.len());
+        for entry in &self.log_lines {
+            let _ = writeln!(text, "{}", entry);
+        }
+        text
+    }
+
+    /// Write the report as crash-<time>.txt in `directory`
+    pub fn write(&self, directory: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(directory)
+            .with_context(|| format!("Failed to create {}", directory.display()))?;
+        let stamp: String = logging::format_timestamp(self.time)
+            .chars()
+            .map(|c| match c {
+                ' ' => '_',
+                ':' => '-',
+                c => c,
+            })
+            .collect();
+        let path = directory.join(format!("crash-{}.txt", stamp));
+        std::fs::write(&path, self.to_text())
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+
+    #[test]
+    fn test_report_lists_context_and_log() {
+        let mut context = BTreeMap::new();
+        context.insert(AUTOSAVE_KEY.to_string(), "autosave/castle.ron".to_string());
+        let report = CrashReport {
+            time: SystemTime::UNIX_EPOCH,
+            thread: "main".to_string(),
+            message: "index out of bounds".to_string(),
+            location: "src/main.rs:10:5".to_string(),
+            backtrace: "0: main".to_string(),
+            context,
+            log_lines: vec![LogEntry {
+                sequence: 1,
+                time: SystemTime::UNIX_EPOCH,
+                level: Level::Warn,
+                target: "editor".to_string(),
+                message: "about to fail".to_string(),
+            }],
+        };
+        let text = report.to_text();
+        assert!(text.contains("Panic:    index out of bounds"));
+        assert!(text.contains("autosave: autosave/castle.ron"));
+        assert!(text.contains("WARN  editor: about to fail"));
+
+        let directory =
+            std::env::temp_dir().join(format!("causality-crash-test-{}", std::process::id()));
+        let path = report.write(&directory).unwrap();
+        assert_eq!(
+            path.file_name().unwrap(),
+            "crash-1970-01-01_00-00-00.000.txt"
+        );
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
@@ -0,0 +1,122 @@
+// Determinism - seeded random numbers for reproducible simulations
+//
+// Everything in the simulation that rolls dice (scripts' random(), particle
+// emitters, foliage scattering) draws from a SimRng instead of the thread
+// RNG. A SimRng is a SplitMix64 generator: the same seed gives the same
+// numbers on every platform and build, so a play session started from the
+// same scene, seed and input replays exactly. Systems that own their own
+// generator fork it from the session's with a stream id (an entity id, a
+// rule index), so how often one of them draws doesn't shift the others.
+//
+// With `determinism.enabled` in project.ron, play mode also steps at a
+// fixed timestep, runs its systems one at a time and records a replay.
+
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Seeded, portable random number generator
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SimRng {
+    state: u64,
+}
+
+/// Random numbers shared between the game loop and scripts
+pub type SharedRng = Arc<Mutex<SimRng>>;
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Seeded from the clock, for sessions that don't need to replay
+    pub fn from_time() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        Self::new(nanos)
+    }
+
+    /// An independent generator for `stream`, derived from this one's seed
+    /// without drawing from it
+    pub fn fork(&self, stream: u64) -> Self {
+        let mut rng = Self::new(self.state ^ stream.wrapping_mul(GOLDEN_GAMMA).rotate_left(17));
+        rng.next_u64();
+        rng
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Uniform in [min, max); min if the range is empty
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        if max <= min {
+            return min;
+        }
+        min + (max - min) * self.next_f32()
+    }
+
+    /// Uniform integer in [min, max]
+    pub fn range_i64(&mut self, min: i64, max: i64) -> i64 {
+        if max <= min {
+            return min;
+        }
+        let span = max.wrapping_sub(min) as u64 + 1;
+        if span == 0 {
+            return self.next_u64() as i64;
+        }
+        min.wrapping_add((self.next_u64() % span) as i64)
+    }
+
+    pub fn shared(self) -> SharedRng {
+        Arc::new(Mutex::new(self))
+    }
+}
+
+impl Default for SimRng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_numbers() {
+        let mut a = SimRng::new(42);
+        let mut b = SimRng::new(42);
+        let first: Vec<u64> = (0..8).map(|_| a.next_u64()).collect();
+        let second: Vec<u64> = (0..8).map(|_| b.next_u64()).collect();
+        assert_eq!(first, second);
+        assert_ne!(SimRng::new(43).next_u64(), first[0]);
+
+        // Forks don't draw from the parent and differ per stream
+        let parent = SimRng::new(7);
+        assert_eq!(parent.fork(1), parent.fork(1));
+        assert_ne!(parent.fork(1), parent.fork(2));
+        assert_eq!(parent, SimRng::new(7));
+
+        let mut rng = SimRng::new(1);
+        for _ in 0..1000 {
+            let x = rng.range(-2.0, 3.0);
+            assert!((-2.0..3.0).contains(&x));
+            let n = rng.range_i64(1, 6);
+            assert!((1..=6).contains(&n));
+        }
+        assert_eq!(rng.range(5.0, 5.0), 5.0);
+    }
+}
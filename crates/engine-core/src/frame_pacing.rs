@@ -0,0 +1,221 @@
+// Frame pacing - presentation mode, frame rate cap and fixed updates
+//
+// The game loop asks a FramePacer when the next frame is due, so a frame
+// rate cap becomes a wait instead of a busy loop, and feeds it each frame's
+// real time. What comes back is smoothed: the average of the last few
+// frame times, so a single late frame doesn't jerk the camera. With fixed
+// updates on, the simulation advances in steps of the project's physics
+// timestep however fast frames are rendered; the pacer accumulates the
+// game's (scaled) time and says how many steps are due each frame.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Most fixed steps run in one frame; a longer backlog is dropped so a
+/// slow frame can't snowball into slower ones
+pub const MAX_FIXED_STEPS: u32 = 8;
+
+/// How rendered frames reach the screen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PresentMode {
+    /// Wait for vertical blank; no tearing (supported everywhere)
+    #[default]
+    Vsync,
+    /// Vsync, but a late frame is shown immediately instead of waiting a
+    /// whole refresh
+    AdaptiveVsync,
+    /// No tearing and no waiting: the newest frame replaces a queued one
+    Mailbox,
+    /// Show frames as soon as they are ready (may tear)
+    Immediate,
+}
+
+impl PresentMode {
+    pub const ALL: [PresentMode; 4] = [
+        PresentMode::Vsync,
+        PresentMode::AdaptiveVsync,
+        PresentMode::Mailbox,
+        PresentMode::Immediate,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PresentMode::Vsync => "VSync",
+            PresentMode::AdaptiveVsync => "Adaptive VSync",
+            PresentMode::Mailbox => "Mailbox",
+            PresentMode::Immediate => "Immediate",
+        }
+    }
+
+    /// True if frame presentation waits for the display
+    pub fn is_vsync(&self) -> bool {
+        matches!(self, PresentMode::Vsync | PresentMode::AdaptiveVsync)
+    }
+}
+
+/// Frame pacing settings
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FramePacing {
+    pub present_mode: PresentMode,
+    /// Most frames rendered per second (None = as many as presentation allows)
+    pub fps_cap: Option<u32>,
+    /// Advance the simulation in fixed steps rather than once per rendered frame
+    pub fixed_update: bool,
+    /// Frames averaged for the smoothed frame time (1 = no smoothing)
+    pub smoothing_frames: u32,
+}
+
+impl Default for FramePacing {
+    fn default() -> Self {
+        Self {
+            present_mode: PresentMode::Vsync,
+            fps_cap: None,
+            fixed_update: true,
+            smoothing_frames: 4,
+        }
+    }
+}
+
+pub struct FramePacer {
+    config: FramePacing,
+    last_frame: Instant,
+    /// Recent real frame times, newest last
+    frame_times: VecDeque<f32>,
+    raw_delta: f32,
+    smoothed_delta: f32,
+    /// Game time not yet consumed by fixed steps
+    accumulator: f32,
+}
+
+impl FramePacer {
+    pub fn new(config: FramePacing) -> Self {
+        Self {
+            config,
+            last_frame: Instant::now(),
+            frame_times: VecDeque::new(),
+            raw_delta: 0.0,
+            smoothed_delta: 0.0,
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn config(&self) -> &FramePacing {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: FramePacing) {
+        if !config.fixed_update {
+            self.accumulator = 0.0;
+        }
+        self.config = config;
+        self.trim_history();
+    }
+
+    pub fn set_fps_cap(&mut self, fps_cap: Option<u32>) {
+        self.config.fps_cap = fps_cap;
+    }
+
+    /// When the next frame should start under the frame rate cap (None
+    /// when uncapped)
+    pub fn next_frame_at(&self) -> Option<Instant> {
+        let cap = self.config.fps_cap.filter(|&cap| cap > 0)?;
+        Some(self.last_frame + Duration::from_secs_f64(1.0 / cap as f64))
+    }
+
+    /// Start a frame: measure the real time since the last one and return
+    /// it smoothed
+    pub fn begin_frame(&mut self) -> f32 {
+        let now = Instant::now();
+        let real_delta = (now - self.last_frame).as_secs_f32();
+        self.last_frame = now;
+        self.record(real_delta)
+    }
+
+    /// Add a frame of `real_delta` seconds, returning the smoothed frame time
+    pub fn record(&mut self, real_delta: f32) -> f32 {
+        self.raw_delta = real_delta.max(0.0);
+        self.frame_times.push_back(self.raw_delta);
+        self.trim_history();
+        self.smoothed_delta = self.frame_times.iter().sum::<f32>() / self.frame_times.len() as f32;
+        self.smoothed_delta
+    }
+
+    fn trim_history(&mut self) {
+        let frames = self.config.smoothing_frames.max(1) as usize;
+        while self.frame_times.len() > frames {
+            self.frame_times.pop_front();
+        }
+    }
+
+    /// Last frame's real time, unsmoothed
+    pub fn raw_delta(&self) -> f32 {
+        self.raw_delta
+    }
+
+    pub fn smoothed_delta(&self) -> f32 {
+        self.smoothed_delta
+    }
+
+    /// Steps of `step` seconds due after `delta` seconds of game time (at
+    /// most MAX_FIXED_STEPS)
+    pub fn fixed_steps(&mut self, delta: f32, step: f32) -> u32 {
+        self.accumulator += delta.max(0.0);
+        let steps = (self.accumulator / step) as u32;
+        if steps > MAX_FIXED_STEPS {
+            self.accumulator = 0.0;
+            MAX_FIXED_STEPS
+        } else {
+            self.accumulator -= steps as f32 * step;
+            steps
+        }
+    }
+
+    /// Drop leftover game time, e.g. when a new play session starts
+    pub fn reset_accumulator(&mut self) {
+        self.accumulator = 0.0;
+    }
+}
+
+impl Default for FramePacer {
+    fn default() -> Self {
+        Self::new(FramePacing::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smoothing_fixed_steps_and_cap() {
+        let mut pacer = FramePacer::new(FramePacing {
+            smoothing_frames: 4,
+            ..Default::default()
+        });
+
+        // One slow frame among fast ones is averaged out
+        for _ in 0..3 {
+            pacer.record(0.01);
+        }
+        assert!((pacer.record(0.05) - 0.02).abs() < 1e-6);
+        assert_eq!(pacer.raw_delta(), 0.05);
+
+        // 0.03s in 0.02s steps is one step with 0.01s left over
+        assert_eq!(pacer.fixed_steps(0.03, 0.02), 1);
+        assert!((pacer.accumulator - 0.01).abs() < 1e-4);
+        assert_eq!(pacer.fixed_steps(0.015, 0.02), 1);
+        assert_eq!(pacer.fixed_steps(0.0, 0.02), 0);
+
+        // A huge backlog is capped and dropped
+        assert_eq!(pacer.fixed_steps(10.0, 0.02), MAX_FIXED_STEPS);
+        assert_eq!(pacer.accumulator, 0.0);
+
+        assert!(pacer.next_frame_at().is_none());
+        pacer.set_fps_cap(Some(100));
+        let wait = pacer.next_frame_at().unwrap() - pacer.last_frame;
+        assert_eq!(wait, Duration::from_millis(10));
+    }
+}
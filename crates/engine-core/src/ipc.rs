@@ -0,0 +1,475 @@
+// IPC between the editor and the MCP server
+//
+// The editor listens on a local TCP socket and the MCP server connects to it;
+// both sides exchange newline-delimited JSON. Every command carries an id that
+// its response echoes, so the client can match replies to requests and skip
+// stale ones. The editor reads a bounded number of commands per poll and
+// leaves the rest in the socket, so a client that floods it is held back by
+// TCP flow control instead of growing a queue. The client reconnects on its
+// own when the editor restarts.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+/// Address the editor listens on unless project.ron or `ADDRESS_ENV` changes it
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:47621";
+/// Environment variable that overrides the IPC address on both sides
+pub const ADDRESS_ENV: &str = "CAUSALITY_IPC_ADDR";
+/// Longest message either side accepts (larger ones close the connection)
+pub const MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+
+/// How long a client waits for a response by default
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Pause between connection attempts while the editor is unreachable
+const RECONNECT_DELAY: Duration = Duration::from_millis(100);
+/// Commands the editor takes per poll by default
+const DEFAULT_COMMANDS_PER_POLL: usize = 16;
+/// How long the editor blocks writing a response before dropping the client
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// IPC address for this process (see ProjectSettings::ipc_address)
+pub fn address() -> String {
+    crate::project::current().ipc_address()
+}
+
+/// Command sent from the MCP server to the editor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IpcCommand {
+    pub id: u64,
+    pub command: String,
+    pub args: Value,
+}
+
+/// Response sent from the editor back to the MCP server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IpcResponse {
+    /// Id of the command this answers
+    pub id: u64,
+    pub success: bool,
+    pub result: Value,
+}
+
+impl IpcResponse {
+    pub fn ok(id: u64, result: Value) -> Self {
+        Self {
+            id,
+            success: true,
+            result,
+        }
+    }
+
+    pub fn error(id: u64, message: impl Into<String>) -> Self {
+        Self {
+            id,
+            success: false,
+            result: json!({ "error": message.into() }),
+        }
+    }
+}
+
+/// A client connected to an `IpcListener`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConnectionId(u64);
+
+struct Connection {
+    id: ConnectionId,
+    stream: TcpStream,
+    /// Bytes read but not yet split into lines
+    buffer: Vec<u8>,
+    closed: bool,
+}
+
+impl Connection {
+    /// Take the next complete line out of the buffer
+    fn next_line(&mut self) -> Option<Vec<u8>> {
+        let end = self.buffer.iter().position(|&b| b == b'\n')?;
+        let mut line: Vec<u8> = self.buffer.drain(..=end).collect();
+        line.pop();
+        Some(line)
+    }
+
+    /// Read whatever the socket has without blocking. False when nothing
+    /// more can be read right now.
+    fn fill(&mut self) -> bool {
+        let mut chunk = [0u8; 4096];
+        match self.stream.read(&mut chunk) {
+            Ok(0) => {
+                self.closed = true;
+                false
+            }
+            Ok(n) => {
+                self.buffer.extend_from_slice(&chunk[..n]);
+                if self.buffer.len() > MAX_MESSAGE_BYTES {
+                    log::warn!(
+                        "IPC message over {} bytes, dropping client",
+                        MAX_MESSAGE_BYTES
+                    );
+                    self.closed = true;
+                    return false;
+                }
+                true
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => true,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => false,
+            Err(e) => {
+                log::warn!("IPC read failed: {}", e);
+                self.closed = true;
+                false
+            }
+        }
+    }
+}
+
+/// Editor side: accepts MCP server connections and reads their commands
+pub struct IpcListener {
+    listener: TcpListener,
+    connections: Vec<Connection>,
+    next_connection: u64,
+    commands_per_poll: usize,
+}
+
+impl IpcListener {
+    /// Listen on `address` (non-blocking)
+    pub fn bind(address: &str) -> Result<Self> {
+        let listener = TcpListener::bind(address)
+            .with_context(|| format!("Failed to listen on {}", address))?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            connections: Vec::new(),
+            next_connection: 1,
+            commands_per_poll: DEFAULT_COMMANDS_PER_POLL,
+        })
+    }
+
+    /// Limit how many commands a single `poll` returns
+    pub fn with_commands_per_poll(mut self, commands: usize) -> Self {
+        self.commands_per_poll = commands.max(1);
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    pub fn connection_count(&self) -> usize {
+        self.connections.len()
+    }
+
+    /// Accept new clients and collect complete commands (non-blocking).
+    /// Malformed lines are answered with an error right away.
+    pub fn poll(&mut self) -> Vec<(ConnectionId, IpcCommand)> {
+        self.accept();
+
+        let mut commands = Vec::new();
+        let mut malformed = Vec::new();
+        for connection in &mut self.connections {
+            while commands.len() < self.commands_per_poll {
+                if let Some(line) = connection.next_line() {
+                    if line.iter().all(u8::is_ascii_whitespace) {
+                        continue;
+                    }
+                    match serde_json::from_slice::<IpcCommand>(&line) {
+                        Ok(command) => commands.push((connection.id, command)),
+                        Err(e) => malformed.push((connection.id, e.to_string())),
+                    }
+                } else if !connection.fill() {
+                    break;
+                }
+            }
+        }
+
+        for (connection, error) in malformed {
+            log::error!("Failed to parse IPC command: {}", error);
+            let response = IpcResponse::error(0, format!("Malformed command: {}", error));
+            let _ = self.respond(connection, &response);
+        }
+        self.connections.retain(|connection| !connection.closed);
+        commands
+    }
+
+    /// Send a response to the client that sent the command
+    pub fn respond(&mut self, connection: ConnectionId, response: &IpcResponse) -> Result<()> {
+        let client = self
+            .connections
+            .iter_mut()
+            .find(|client| client.id == connection)
+            .ok_or_else(|| anyhow!("IPC client disconnected"))?;
+
+        // Responses are written blocking (bounded by the write timeout) so a
+        // large one is never cut off halfway
+        client.stream.set_nonblocking(false)?;
+        let written = write_message(&mut client.stream, response);
+        client.stream.set_nonblocking(true)?;
+        if written.is_err() {
+            client.closed = true;
+        }
+        written
+    }
+
+    fn accept(&mut self) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, peer)) => {
+                    if let Err(e) = configure_accepted(&stream) {
+                        log::warn!("Rejected IPC client {}: {}", peer, e);
+                        continue;
+                    }
+                    log::info!("MCP client connected from {}", peer);
+                    self.connections.push(Connection {
+                        id: ConnectionId(self.next_connection),
+                        stream,
+                        buffer: Vec::new(),
+                        closed: false,
+                    });
+                    self.next_connection += 1;
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    log::warn!("Failed to accept IPC client: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn configure_accepted(stream: &TcpStream) -> std::io::Result<()> {
+    stream.set_nonblocking(true)?;
+    stream.set_nodelay(true)?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))
+}
+
+/// MCP server side: sends commands to the editor and waits for the answers
+pub struct IpcClient {
+    address: String,
+    stream: Option<BufReader<TcpStream>>,
+    next_id: u64,
+    timeout: Duration,
+}
+
+impl IpcClient {
+    /// Client for the editor at `address` (connects on the first request)
+    pub fn new(address: impl Into<String>) -> Self {
+        Self {
+            address: address.into(),
+            stream: None,
+            next_id: 1,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// How long `request` waits for the editor, connecting included
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Send a command and wait for its response, reconnecting first if the
+    /// editor restarted or has not started yet
+    pub fn request(&mut self, command: &str, args: Value) -> Result<IpcResponse> {
+        self.request_with_timeout(command, args, self.timeout)
+    }
+
+    /// Like `request`, for commands the editor takes longer than usual to answer
+    pub fn request_with_timeout(
+        &mut self,
+        command: &str,
+        args: Value,
+        timeout: Duration,
+    ) -> Result<IpcResponse> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let message = IpcCommand {
+            id,
+            command: command.to_string(),
+            args,
+        };
+        let deadline = Instant::now() + timeout;
+
+        // A connection left over from an editor that has since exited only
+        // fails on use, so a failed write gets one retry on a fresh connection
+        let mut retried = false;
+        loop {
+            let stream = self.connect(deadline)?;
+            match write_message(stream.get_mut(), &message) {
+                Ok(()) => break,
+                Err(e) if !retried => {
+                    log::debug!("IPC connection lost ({}), reconnecting", e);
+                    self.stream = None;
+                    retried = true;
+                }
+                Err(e) => {
+                    self.stream = None;
+                    return Err(e);
+                }
+            }
+        }
+
+        loop {
+            let result = self.read_response(deadline);
+            match result {
+                Ok(response) if response.id == id => return Ok(response),
+                Ok(response) => log::debug!("Skipping stale IPC response {}", response.id),
+                Err(e) => {
+                    // Whatever arrives later would be out of step with the next request
+                    self.stream = None;
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    fn connect(&mut self, deadline: Instant) -> Result<&mut BufReader<TcpStream>> {
+        if self.stream.is_none() {
+            let address = self
+                .address
+                .to_socket_addrs()?
+                .next()
+                .ok_or_else(|| anyhow!("Invalid IPC address {}", self.address))?;
+            let stream = loop {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                match TcpStream::connect_timeout(&address, remaining.max(RECONNECT_DELAY)) {
+                    Ok(stream) => break stream,
+                    Err(e) if Instant::now() + RECONNECT_DELAY >= deadline => {
+                        return Err(anyhow!(
+                            "Editor not reachable at {} ({}) - is it running?",
+                            self.address,
+                            e
+                        ));
+                    }
+                    Err(_) => std::thread::sleep(RECONNECT_DELAY),
+                }
+            };
+            stream.set_nodelay(true)?;
+            log::info!("Connected to editor at {}", self.address);
+            self.stream = Some(BufReader::new(stream));
+        }
+        Ok(self.stream.as_mut().expect("connected above"))
+    }
+
+    fn read_response(&mut self, deadline: Instant) -> Result<IpcResponse> {
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or_else(|| anyhow!("Not connected to the editor"))?;
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(anyhow!("IPC timeout waiting for editor response"));
+        }
+        stream.get_ref().set_read_timeout(Some(remaining))?;
+
+        let mut line = String::new();
+        match stream.read_line(&mut line) {
+            Ok(0) => Err(anyhow!("Editor closed the connection")),
+            Ok(_) => Ok(serde_json::from_str(&line)?),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                Err(anyhow!("IPC timeout waiting for editor response"))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Write one message as a JSON line
+fn write_message<T: Serialize>(stream: &mut TcpStream, message: &T) -> Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    stream.write_all(&line)?;
+    stream.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Poll until `count` commands arrived (or give up after a few seconds)
+    fn poll_commands(listener: &mut IpcListener, count: usize) -> Vec<(ConnectionId, IpcCommand)> {
+        let start = Instant::now();
+        let mut commands = Vec::new();
+        while commands.len() < count && start.elapsed() < Duration::from_secs(5) {
+            commands.extend(listener.poll());
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        commands
+    }
+
+    #[test]
+    fn test_request_round_trip() {
+        let mut listener = IpcListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let client = std::thread::spawn(move || {
+            let mut client = IpcClient::new(address);
+            let first = client.request("list_entities", json!({})).unwrap();
+            let second = client.request("missing", json!({ "x": 1 })).unwrap();
+            (first, second)
+        });
+
+        for expected in ["list_entities", "missing"] {
+            let commands = poll_commands(&mut listener, 1);
+            assert_eq!(commands.len(), 1);
+            let (connection, command) = &commands[0];
+            assert_eq!(command.command, expected);
+            let response = if expected == "missing" {
+                IpcResponse::error(command.id, "Unknown command")
+            } else {
+                IpcResponse::ok(command.id, json!({ "count": 0 }))
+            };
+            listener.respond(*connection, &response).unwrap();
+        }
+
+        let (first, second) = client.join().unwrap();
+        assert!(first.success);
+        assert_eq!(first.result, json!({ "count": 0 }));
+        assert!(!second.success);
+        assert_eq!(second.id, first.id + 1);
+    }
+
+    #[test]
+    fn test_commands_per_poll_and_malformed_lines() {
+        let mut listener = IpcListener::bind("127.0.0.1:0")
+            .unwrap()
+            .with_commands_per_poll(1);
+        let mut stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        stream
+            .write_all(b"{\"id\":1,\"command\":\"a\",\"args\":null}\nnot json\n{\"id\":2,\"command\":\"b\",\"args\":null}\n")
+            .unwrap();
+
+        let first = poll_commands(&mut listener, 1);
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].1.id, 1);
+
+        // The malformed line is answered, then the next command is taken
+        let second = poll_commands(&mut listener, 1);
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].1.id, 2);
+
+        let mut reply = String::new();
+        BufReader::new(stream).read_line(&mut reply).unwrap();
+        let reply: IpcResponse = serde_json::from_str(&reply).unwrap();
+        assert!(!reply.success);
+    }
+
+    #[test]
+    fn test_unreachable_editor_times_out() {
+        // Bind and drop to find a port nothing listens on
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut client =
+            IpcClient::new(address.to_string()).with_timeout(Duration::from_millis(300));
+        assert!(client.request("list_entities", json!({})).is_err());
+        assert!(!client.is_connected());
+    }
+}
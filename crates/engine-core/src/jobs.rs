@@ -0,0 +1,296 @@
+// Job system - parallel loops and a scheduler for per-frame systems
+//
+// Systems declare the resources they read and write, by name. A
+// SystemGraph puts each system in a stage after every earlier system it
+// conflicts with (either one writes something the other touches); systems
+// in the same stage run at the same time on scoped threads. Systems reach
+// shared data through locks, and because conflicting systems never share
+// a stage those locks are never contended - the declarations are what
+// keeps that true, so they must match what the closures lock. A
+// sequential graph (determinism mode) runs one system per stage, in the
+// order they were added, so nothing depends on thread timing.
+
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+
+/// Threads to split work across (the number of CPU cores)
+pub fn worker_count() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+/// Call `f` on every item, splitting the slice across worker threads
+pub fn parallel_for_each<T: Send>(items: &mut [T], f: impl Fn(&mut T) + Sync) {
+    let workers = worker_count().min(items.len());
+    if workers <= 1 {
+        items.iter_mut().for_each(f);
+        return;
+    }
+    let chunk_size = items.len().div_ceil(workers);
+    let f = &f;
+    std::thread::scope(|scope| {
+        for chunk in items.chunks_mut(chunk_size) {
+            scope.spawn(move || chunk.iter_mut().for_each(f));
+        }
+    });
+}
+
+/// `items.iter().map(f).collect()`, with the work split across worker
+/// threads; results keep the items' order
+pub fn parallel_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let workers = worker_count().min(items.len());
+    if workers <= 1 {
+        return items.iter().map(f).collect();
+    }
+    let chunk_size = items.len().div_ceil(workers);
+    let f = &f;
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(f).collect::<Vec<R>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("parallel_map worker panicked"))
+            .collect()
+    })
+}
+
+/// When a system ran and for how long
+#[derive(Debug, Clone, Copy)]
+pub struct SystemTiming {
+    pub name: &'static str,
+    pub start: Instant,
+    pub duration: Duration,
+}
+
+struct System<'a> {
+    name: &'static str,
+    reads: Vec<&'static str>,
+    writes: Vec<&'static str>,
+    run: Box<dyn FnMut() -> Result<()> + Send + 'a>,
+}
+
+impl System<'_> {
+    /// True if the two can't run at the same time
+    fn conflicts_with(&self, other: &System) -> bool {
+        let touches = |system: &System, resource: &str| {
+            system.reads.contains(&resource) || system.writes.contains(&resource)
+        };
+        self.writes.iter().any(|resource| touches(other, resource))
+            || other.writes.iter().any(|resource| touches(self, resource))
+    }
+}
+
+/// Per-frame systems with their data access, run stage by stage
+#[derive(Default)]
+pub struct SystemGraph<'a> {
+    systems: Vec<System<'a>>,
+    sequential: bool,
+}
+
+impl<'a> SystemGraph<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a system. Systems that conflict run in the order they were added.
+    pub fn add_system(
+        &mut self,
+        name: &'static str,
+        reads: &[&'static str],
+        writes: &[&'static str],
+        run: impl FnMut() -> Result<()> + Send + 'a,
+    ) -> &mut Self {
+        self.systems.push(System {
+            name,
+            reads: reads.to_vec(),
+            writes: writes.to_vec(),
+            run: Box::new(run),
+        });
+        self
+    }
+
+    /// Run the systems one at a time in the order they were added
+    pub fn set_sequential(&mut self, sequential: bool) -> &mut Self {
+        self.sequential = sequential;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.systems.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.systems.is_empty()
+    }
+
+    /// Stage of each system: one past the latest earlier system it
+    /// conflicts with
+    fn stage_indices(&self) -> Vec<usize> {
+        if self.sequential {
+            return (0..self.systems.len()).collect();
+        }
+        let mut stages: Vec<usize> = Vec::with_capacity(self.systems.len());
+        for (i, system) in self.systems.iter().enumerate() {
+            let stage = self.systems[..i]
+                .iter()
+                .zip(&stages)
+                .filter(|(earlier, _)| system.conflicts_with(earlier))
+                .map(|(_, &stage)| stage + 1)
+                .max()
+                .unwrap_or(0);
+            stages.push(stage);
+        }
+        stages
+    }
+
+    /// System names grouped by the stage they run in
+    pub fn stages(&self) -> Vec<Vec<&'static str>> {
+        let mut stages: Vec<Vec<&'static str>> = Vec::new();
+        for (system, stage) in self.systems.iter().zip(self.stage_indices()) {
+            if stages.len() <= stage {
+                stages.resize_with(stage + 1, Vec::new);
+            }
+            stages[stage].push(system.name);
+        }
+        stages
+    }
+
+    /// Run every system once, stage by stage. Stops after the first stage
+    /// in which a system fails, returning that error.
+    pub fn run(&mut self) -> Result<Vec<SystemTiming>> {
+        let stage_indices = self.stage_indices();
+        let stage_count = stage_indices.iter().max().map_or(0, |&s| s + 1);
+        let mut timings = Vec::with_capacity(self.systems.len());
+
+        for stage in 0..stage_count {
+            let mut systems: Vec<&mut System<'a>> = self
+                .systems
+                .iter_mut()
+                .zip(&stage_indices)
+                .filter(|(_, &s)| s == stage)
+                .map(|(system, _)| system)
+                .collect();
+
+            let results: Vec<(SystemTiming, Result<()>)> = if systems.len() == 1 {
+                vec![run_timed(systems.pop().unwrap())]
+            } else {
+                // The first system runs on this thread, the rest on workers
+                std::thread::scope(|scope| {
+                    let mut iter = systems.into_iter();
+                    let first = iter.next();
+                    let handles: Vec<_> = iter
+                        .map(|system| {
+                            let name = system.name;
+                            (name, scope.spawn(move || run_timed(system)))
+                        })
+                        .collect();
+                    let mut results: Vec<_> = first.into_iter().map(run_timed).collect();
+                    for (name, handle) in handles {
+                        results.push(handle.join().unwrap_or_else(|_| {
+                            let timing = SystemTiming {
+                                name,
+                                start: Instant::now(),
+                                duration: Duration::ZERO,
+                            };
+                            (timing, Err(anyhow!("System '{}' panicked", name)))
+                        }));
+                    }
+                    results
+                })
+            };
+
+            let mut error = None;
+            for (timing, result) in results {
+                timings.push(timing);
+                if let Err(e) = result {
+                    error.get_or_insert(e.context(format!("System '{}' failed", timing.name)));
+                }
+            }
+            if let Some(e) = error {
+                return Err(e);
+            }
+        }
+
+        Ok(timings)
+    }
+}
+
+fn run_timed(system: &mut System) -> (SystemTiming, Result<()>) {
+    let _span = tracing::info_span!("system", name = system.name).entered();
+    let start = Instant::now();
+    let result = (system.run)();
+    let timing = SystemTiming {
+        name: system.name,
+        start,
+        duration: start.elapsed(),
+    };
+    (timing, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Mutex, RwLock};
+
+    #[test]
+    fn test_graph_stages_and_runs_systems() {
+        let position = RwLock::new(0.0f32);
+        let velocity = Mutex::new(0.0f32);
+        let log = Mutex::new(Vec::new());
+
+        let mut graph = SystemGraph::new();
+        graph
+            .add_system("input", &[], &["velocity"], || {
+                *velocity.lock().unwrap() = 2.0;
+                Ok(())
+            })
+            .add_system("move", &["velocity"], &["position"], || {
+                *position.write().unwrap() += *velocity.lock().unwrap();
+                Ok(())
+            })
+            .add_system("render", &["position"], &["log"], || {
+                log.lock().unwrap().push(*position.read().unwrap());
+                Ok(())
+            })
+            .add_system("audio", &["position"], &[], || Ok(()))
+            .add_system("particles", &[], &["particles"], || Ok(()));
+
+        assert_eq!(
+            graph.stages(),
+            vec![
+                vec!["input", "particles"],
+                vec!["move"],
+                vec!["render", "audio"],
+            ]
+        );
+        let timings = graph.run().unwrap();
+        assert_eq!(timings.len(), 5);
+        graph.run().unwrap();
+        assert_eq!(*log.lock().unwrap(), vec![2.0, 4.0]);
+
+        let mut failing = SystemGraph::new();
+        failing.add_system("broken", &[], &[], || Err(anyhow!("out of fuel")));
+        let error = failing.run().unwrap_err();
+        assert!(format!("{:#}", error).contains("out of fuel"));
+
+        let mut numbers: Vec<u64> = (0..1000).collect();
+        parallel_for_each(&mut numbers, |n| *n *= 2);
+        assert_eq!(parallel_map(&numbers, |n| n + 1)[999], 1999);
+    }
+
+    #[test]
+    fn test_sequential_graph_runs_in_added_order() {
+        let mut graph = SystemGraph::new();
+        graph
+            .set_sequential(true)
+            .add_system("a", &[], &["a"], || Ok(()))
+            .add_system("b", &[], &["b"], || Ok(()));
+        assert_eq!(graph.stages(), vec![vec!["a"], vec!["b"]]);
+        let names: Vec<&str> = graph.run().unwrap().iter().map(|t| t.name).collect();
+        assert_eq!(names, ["a", "b"]);
+    }
+}
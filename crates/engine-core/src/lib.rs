@@ -1,6 +1,17 @@
-// Engine Core - Application lifecycle, timing, input
+// Engine Core - Application lifecycle, timing, frame pacing, jobs, input, editor IPC,
+// logging, tracing captures, crash reports, asset packs, project settings and
+// deterministic random numbers
 
 pub mod app;
 pub mod time;
+pub mod frame_pacing;
+pub mod jobs;
 pub mod input;
 pub mod builder;
+pub mod ipc;
+pub mod logging;
+pub mod trace;
+pub mod crash;
+pub mod pack;
+pub mod project;
+pub mod determinism;
@@ -0,0 +1,522 @@
+// Logging - the backend behind the log macros
+//
+// log::info!/warn!/error! calls anywhere in the engine end up here. A record
+// that passes the level filter is written to stderr and to a rotating log
+// file (logs/causality.log, earlier sessions in causality.1.log and so on),
+// and kept in a ring buffer that the editor console and crash reports read
+// from. Levels are set per module with a RUST_LOG style filter
+// ("info,wgpu_core=warn,engine_net=debug") and can be changed while running.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// Filter used when RUST_LOG isn't set; wgpu and naga are chatty at info
+pub const DEFAULT_FILTER: &str = "info,wgpu_core=warn,wgpu_hal=warn,naga=warn";
+
+/// Directory log files go in, relative to the working directory
+pub const DEFAULT_LOG_DIR: &str = "logs";
+
+/// Log file name without extension (causality.log, causality.1.log, ...)
+const LOG_FILE_STEM: &str = "causality";
+
+/// A log file is rotated when it would grow past this
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 5 * 1024 * 1024;
+
+/// Log files kept, the current one included
+pub const DEFAULT_MAX_FILES: usize = 5;
+
+/// Records kept in memory for the console and crash reports
+pub const DEFAULT_BUFFER_CAPACITY: usize = 1000;
+
+/// How the logger is set up
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    /// RUST_LOG style filter
+    pub filter: String,
+    /// Where log files go (None = no files)
+    pub directory: Option<PathBuf>,
+    pub max_file_size: u64,
+    pub max_files: usize,
+    pub buffer_capacity: usize,
+    /// Also print records to stderr
+    pub stderr: bool,
+}
+
+impl Default for LogConfig {
+    /// RUST_LOG if set, otherwise DEFAULT_FILTER; files in logs/
+    fn default() -> Self {
+        Self {
+            filter: std::env::var("RUST_LOG").unwrap_or_else(|_| DEFAULT_FILTER.to_string()),
+            directory: Some(PathBuf::from(DEFAULT_LOG_DIR)),
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            max_files: DEFAULT_MAX_FILES,
+            buffer_capacity: DEFAULT_BUFFER_CAPACITY,
+            stderr: true,
+        }
+    }
+}
+
+impl LogConfig {
+    pub fn with_filter(mut self, filter: impl Into<String>) -> Self {
+        self.filter = filter.into();
+        self
+    }
+
+    pub fn with_directory(mut self, directory: Option<PathBuf>) -> Self {
+        self.directory = directory;
+        self
+    }
+
+    pub fn with_stderr(mut self, stderr: bool) -> Self {
+        self.stderr = stderr;
+        self
+    }
+}
+
+/// Log level per module: a default plus overrides for module paths
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFilter {
+    default: LevelFilter,
+    /// (module path, level), most specific match wins
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    pub fn new(default: LevelFilter) -> Self {
+        Self {
+            default,
+            modules: Vec::new(),
+        }
+    }
+
+    /// Parse "info,engine_net=debug,wgpu_core=warn". A bare level sets the
+    /// default; a bare module name turns everything on for it.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut filter = Self::new(LevelFilter::Error);
+        for part in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+        {
+            match part.split_once('=') {
+                Some((module, level)) => {
+                    filter.set(Some(module.trim()), parse_level(level.trim())?);
+                }
+                None => match parse_level(part) {
+                    Ok(level) => filter.default = level,
+                    Err(_) => filter.set(Some(part), LevelFilter::Trace),
+                },
+            }
+        }
+        Ok(filter)
+    }
+
+    /// Set the level of a module and everything under it (None = the default)
+    pub fn set(&mut self, module: Option<&str>, level: LevelFilter) {
+        let Some(module) = module else {
+            self.default = level;
+            return;
+        };
+        match self.modules.iter_mut().find(|(name, _)| name == module) {
+            Some((_, existing)) => *existing = level,
+            None => self.modules.push((module.to_string(), level)),
+        }
+    }
+
+    /// Level for records from `target` (a module path)
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .filter(|(module, _)| {
+                target == module
+                    || target
+                        .strip_prefix(module.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    pub fn enabled(&self, level: Level, target: &str) -> bool {
+        level <= self.level_for(target)
+    }
+
+    /// The most verbose level any module logs at
+    pub fn max_level(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+impl fmt::Display for LogFilter {
+    /// In the form parse() reads
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.default.as_str().to_lowercase())?;
+        for (module, level) in &self.modules {
+            write!(f, ",{}={}", module, level.as_str().to_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter> {
+    level.parse().map_err(|_| {
+        anyhow!(
+            "Unknown log level '{}' (off, error, warn, info, debug, trace)",
+            level
+        )
+    })
+}
+
+/// One logged record
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    /// Numbered from 1 in the order records were logged
+    pub sequence: u64,
+    pub time: SystemTime,
+    pub level: Level,
+    /// Module path the record came from
+    pub target: String,
+    pub message: String,
+}
+
+impl fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {:<5} {}: {}",
+            format_timestamp(self.time),
+            self.level,
+            self.target,
+            self.message
+        )
+    }
+}
+
+/// "2026-03-01 14:05:09.250" (UTC)
+pub fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    let of_day = seconds % 86_400;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+        year,
+        month,
+        day,
+        of_day / 3600,
+        of_day / 60 % 60,
+        of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Year, month and day of a count of days since 1970-01-01
+/// (Howard Hinnant's civil_from_days)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// A log file that moves aside when it gets too big
+struct RotatingFile {
+    directory: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    /// Open a fresh log file; the previous session's moves to causality.1.log
+    fn open(directory: &Path, max_size: u64, max_files: usize) -> Result<Self> {
+        fs::create_dir_all(directory)?;
+        let mut rotating = Self {
+            directory: directory.to_path_buf(),
+            max_size,
+            max_files: max_files.max(1),
+            file: None,
+            size: 0,
+        };
+        rotating.rotate()?;
+        Ok(rotating)
+    }
+
+    fn path(&self, index: usize) -> PathBuf {
+        let name = match index {
+            0 => format!("{}.log", LOG_FILE_STEM),
+            index => format!("{}.{}.log", LOG_FILE_STEM, index),
+        };
+        self.directory.join(name)
+    }
+
+    /// Shift every file up one (dropping the oldest) and start a new one
+    fn rotate(&mut self) -> Result<()> {
+        self.file = None;
+        let _ = fs::remove_file(self.path(self.max_files - 1));
+        for index in (0..self.max_files - 1).rev() {
+            let from = self.path(index);
+            if from.exists() {
+                fs::rename(&from, self.path(index + 1))?;
+            }
+        }
+        self.file = Some(
+            OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(self.path(0))?,
+        );
+        self.size = 0;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.size > 0 && self.size + len > self.max_size {
+            self.rotate()?;
+        }
+        if let Some(file) = &mut self.file {
+            writeln!(file, "{}", line)?;
+            self.size += len;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) {
+        if let Some(file) = &mut self.file {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// The engine's logger
+pub struct Logger {
+    filter: RwLock<LogFilter>,
+    buffer: Mutex<VecDeque<LogEntry>>,
+    capacity: usize,
+    next_sequence: AtomicU64,
+    file: Mutex<Option<RotatingFile>>,
+    log_path: Option<PathBuf>,
+    stderr: bool,
+}
+
+impl Logger {
+    /// A logger as configured. A log file that can't be opened is reported
+    /// on stderr and skipped rather than failing startup.
+    pub fn new(config: &LogConfig) -> Result<Self> {
+        let filter = LogFilter::parse(&config.filter)?;
+        let file = config.directory.as_ref().and_then(|directory| {
+            RotatingFile::open(directory, config.max_file_size, config.max_files)
+                .map_err(|e| eprintln!("Log file disabled ({}): {:#}", directory.display(), e))
+                .ok()
+        });
+        Ok(Self {
+            filter: RwLock::new(filter),
+            buffer: Mutex::new(VecDeque::with_capacity(config.buffer_capacity)),
+            capacity: config.buffer_capacity.max(1),
+            next_sequence: AtomicU64::new(1),
+            log_path: file.as_ref().map(|file| file.path(0)),
+            file: Mutex::new(file),
+            stderr: config.stderr,
+        })
+    }
+
+    pub fn filter(&self) -> LogFilter {
+        self.filter.read().unwrap().clone()
+    }
+
+    /// Replace the whole filter
+    pub fn set_filter(&self, filter: LogFilter) {
+        log::set_max_level(filter.max_level());
+        *self.filter.write().unwrap() = filter;
+    }
+
+    /// Change one module's level (None = the default level)
+    pub fn set_level(&self, module: Option<&str>, level: LevelFilter) {
+        let mut filter = self.filter();
+        filter.set(module, level);
+        self.set_filter(filter);
+    }
+
+    /// The current log file, if files are written
+    pub fn log_path(&self) -> Option<&Path> {
+        self.log_path.as_deref()
+    }
+
+    /// Buffered records logged after `sequence`, oldest first
+    pub fn entries_since(&self, sequence: u64) -> Vec<LogEntry> {
+        let buffer = self.buffer.lock().unwrap();
+        let start = buffer.partition_point(|entry| entry.sequence <= sequence);
+        buffer.range(start..).cloned().collect()
+    }
+
+    /// The last `count` buffered records, oldest first
+    pub fn recent(&self, count: usize) -> Vec<LogEntry> {
+        let buffer = self.buffer.lock().unwrap();
+        buffer
+            .range(buffer.len().saturating_sub(count)..)
+            .cloned()
+            .collect()
+    }
+
+    fn push(&self, entry: LogEntry) {
+        let line = entry.to_string();
+        if self.stderr {
+            eprintln!("{}", line);
+        }
+        if let Some(file) = self.file.lock().unwrap().as_mut() {
+            if let Err(e) = file.write_line(&line) {
+                eprintln!("Failed to write log file: {:#}", e);
+            }
+        }
+        let mut buffer = self.buffer.lock().unwrap();
+        if buffer.len() == self.capacity {
+            buffer.pop_front();
+        }
+        buffer.push_back(entry);
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter
+            .read()
+            .unwrap()
+            .enabled(metadata.level(), metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        self.push(LogEntry {
+            sequence: self.next_sequence.fetch_add(1, Ordering::Relaxed),
+            time: SystemTime::now(),
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        });
+    }
+
+    fn flush(&self) {
+        if let Some(file) = self.file.lock().unwrap().as_mut() {
+            file.flush();
+        }
+    }
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Install the logger for the process. Fails if a logger is already set.
+pub fn init(config: &LogConfig) -> Result<&'static Logger> {
+    LOGGER
+        .set(Logger::new(config)?)
+        .map_err(|_| anyhow!("Logger already initialized"))?;
+    let logger = LOGGER.get().expect("logger was just set");
+    log::set_logger(logger).map_err(|e| anyhow!("{}", e))?;
+    log::set_max_level(logger.filter().max_level());
+    Ok(logger)
+}
+
+/// The installed logger, if init() has run
+pub fn logger() -> Option<&'static Logger> {
+    LOGGER.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_per_module_filter() {
+        let mut filter =
+            LogFilter::parse("warn,engine_net=debug,engine_net::transport=error").unwrap();
+        assert!(filter.enabled(Level::Debug, "engine_net::server"));
+        assert!(!filter.enabled(Level::Warn, "engine_net::transport"));
+        assert!(!filter.enabled(Level::Info, "engine_network"));
+        assert!(filter.enabled(Level::Warn, "editor"));
+        assert_eq!(filter.max_level(), LevelFilter::Debug);
+
+        filter.set(None, LevelFilter::Info);
+        filter.set(Some("engine_net"), LevelFilter::Off);
+        assert!(filter.enabled(Level::Info, "editor"));
+        assert!(!filter.enabled(Level::Error, "engine_net"));
+        assert_eq!(LogFilter::parse(&filter.to_string()).unwrap(), filter);
+        assert!(LogFilter::parse("loud")
+            .unwrap()
+            .enabled(Level::Trace, "loud::x"));
+        assert!(LogFilter::parse("editor=chatty").is_err());
+    }
+
+    #[test]
+    fn test_logger_buffers_and_rotates() {
+        let directory =
+            std::env::temp_dir().join(format!("causality-log-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        let config = LogConfig {
+            filter: "info".to_string(),
+            directory: Some(directory.clone()),
+            max_file_size: 100,
+            max_files: 3,
+            buffer_capacity: 4,
+            stderr: false,
+        };
+        let logger = Logger::new(&config).unwrap();
+        for i in 0..6 {
+            logger.log(
+                &Record::builder()
+                    .level(Level::Info)
+                    .target("test")
+                    .args(format_args!("message number {}", i))
+                    .build(),
+            );
+        }
+        logger.log(
+            &Record::builder()
+                .level(Level::Debug)
+                .target("test")
+                .args(format_args!("hidden"))
+                .build(),
+        );
+
+        // The buffer keeps the newest records, numbered in order
+        let recent = logger.recent(10);
+        assert_eq!(recent.len(), 4);
+        assert_eq!(recent[0].message, "message number 2");
+        let since: Vec<_> = logger.entries_since(5).iter().map(|e| e.sequence).collect();
+        assert_eq!(since, vec![6]);
+
+        // ~60 byte lines in 100 byte files: one line per file, three files kept
+        logger.flush();
+        assert!(directory.join("causality.2.log").exists());
+        assert!(!directory.join("causality.3.log").exists());
+        let current = fs::read_to_string(directory.join("causality.log")).unwrap();
+        assert!(current.contains("message number 5"), "{}", current);
+        let _ = fs::remove_dir_all(&directory);
+
+        assert_eq!(
+            format_timestamp(UNIX_EPOCH + std::time::Duration::from_millis(1_709_301_909_250)),
+            "2024-03-01 14:05:09.250"
+        );
+    }
+}
@@ -0,0 +1,250 @@
+// Asset packs - many asset files in one archive for shipping
+//
+// A pack is "CPAK", the format version and an index of (path, offset, size,
+// stored size) entries, followed by the file contents back to back. Files are
+// deflate-compressed unless that doesn't make them smaller; a stored size
+// equal to the size means the bytes are stored as they are. Paths are
+// relative to the asset directory the pack was made from, '/'-separated.
+// Reading a file seeks straight to its bytes, so a pack never has to be
+// loaded whole. Version 1 packs (uncompressed, no stored size) still open.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+
+pub const PACK_MAGIC: &[u8; 4] = b"CPAK";
+pub const PACK_VERSION: u32 = 2;
+
+/// File extension of asset packs
+pub const PACK_EXTENSION: &str = "cpack";
+
+/// A file stored in a pack
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackEntry {
+    pub path: String,
+    pub offset: u64,
+    /// Size of the file's contents
+    pub size: u64,
+    /// Bytes the file takes up in the pack
+    pub stored_size: u64,
+}
+
+impl PackEntry {
+    pub fn is_compressed(&self) -> bool {
+        self.stored_size != self.size
+    }
+}
+
+/// Write `files` (relative to `root`) into a pack at `output`. Returns the
+/// pack's size in bytes. Each file is streamed into the pack, so packing
+/// never holds more than one file's buffers in memory.
+pub fn write_pack(root: &Path, files: &[PathBuf], output: &Path) -> Result<u64> {
+    let paths: Vec<String> = files
+        .iter()
+        .map(|file| file.to_string_lossy().replace('\\', "/"))
+        .collect();
+    let header_size = (PACK_MAGIC.len() + 8) as u64
+        + paths.iter().map(|path| 4 + path.len() as u64 + 24).sum::<u64>();
+
+    let mut writer = BufWriter::new(
+        File::create(output).with_context(|| format!("Failed to create {}", output.display()))?,
+    );
+    // Contents go after the index, which is written once their sizes are known
+    writer.seek(SeekFrom::Start(header_size))?;
+    let mut entries = Vec::with_capacity(files.len());
+    let mut offset = header_size;
+    for (file, path) in files.iter().zip(paths) {
+        let source = root.join(file);
+        let size = std::fs::metadata(&source)
+            .with_context(|| format!("Failed to read {}", source.display()))?
+            .len();
+        let mut encoder = DeflateEncoder::new(&mut writer, Compression::default());
+        copy_file(&source, size, &mut encoder)?;
+        encoder.finish()?;
+        let mut stored_size = writer.stream_position()? - offset;
+        if stored_size >= size {
+            // Deflate didn't make it smaller, store the bytes as they are
+            writer.seek(SeekFrom::Start(offset))?;
+            copy_file(&source, size, &mut writer)?;
+            stored_size = size;
+        }
+        entries.push(PackEntry {
+            path,
+            offset,
+            size,
+            stored_size,
+        });
+        offset += stored_size;
+    }
+
+    writer.seek(SeekFrom::Start(0))?;
+    writer.write_all(PACK_MAGIC)?;
+    writer.write_all(&PACK_VERSION.to_le_bytes())?;
+    writer.write_all(&(entries.len() as u32).to_le_bytes())?;
+    for entry in &entries {
+        writer.write_all(&(entry.path.len() as u32).to_le_bytes())?;
+        writer.write_all(entry.path.as_bytes())?;
+        writer.write_all(&entry.offset.to_le_bytes())?;
+        writer.write_all(&entry.size.to_le_bytes())?;
+        writer.write_all(&entry.stored_size.to_le_bytes())?;
+    }
+    let file = writer.into_inner().map_err(|e| e.into_error())?;
+    // A file stored as-is may leave a longer compressed attempt behind it
+    file.set_len(offset)?;
+    Ok(offset)
+}
+
+/// Copy the file at `source` into `writer`, checking it is still `size` bytes
+fn copy_file(source: &Path, size: u64, writer: &mut impl Write) -> Result<()> {
+    let mut reader =
+        File::open(source).with_context(|| format!("Failed to read {}", source.display()))?;
+    let copied = std::io::copy(&mut reader, writer)?;
+    if copied != size {
+        bail!("{} changed while it was being packed", source.display());
+    }
+    Ok(())
+}
+
+/// An opened pack; file contents are read on demand
+#[derive(Debug)]
+pub struct AssetPack {
+    path: PathBuf,
+    entries: BTreeMap<String, PackEntry>,
+}
+
+impl AssetPack {
+    /// Read a pack's index
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let mut file =
+            File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let mut magic = [0u8; 4];
+        file.read_exact(&mut magic)?;
+        if &magic != PACK_MAGIC {
+            bail!("{} is not an asset pack", path.display());
+        }
+        let version = read_u32(&mut file)?;
+        if version == 0 || version > PACK_VERSION {
+            bail!(
+                "{} is pack version {}, expected at most {}",
+                path.display(),
+                version,
+                PACK_VERSION
+            );
+        }
+
+        let count = read_u32(&mut file)?;
+        let mut entries = BTreeMap::new();
+        for _ in 0..count {
+            let mut name = vec![0u8; read_u32(&mut file)? as usize];
+            file.read_exact(&mut name)?;
+            let path = String::from_utf8(name).context("Pack path is not UTF-8")?;
+            let offset = read_u64(&mut file)?;
+            let size = read_u64(&mut file)?;
+            let stored_size = if version >= 2 {
+                read_u64(&mut file)?
+            } else {
+                size
+            };
+            let entry = PackEntry {
+                path,
+                offset,
+                size,
+                stored_size,
+            };
+            entries.insert(entry.path.clone(), entry);
+        }
+        Ok(Self { path, entries })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Entries sorted by path
+    pub fn entries(&self) -> impl Iterator<Item = &PackEntry> {
+        self.entries.values()
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.entries.contains_key(path)
+    }
+
+    /// Contents of the file stored under `path`
+    pub fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let entry = self
+            .entries
+            .get(path)
+            .with_context(|| format!("{} is not in {}", path, self.path.display()))?;
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(entry.offset))?;
+        let mut stored = vec![0u8; entry.stored_size as usize];
+        file.read_exact(&mut stored)?;
+        if !entry.is_compressed() {
+            return Ok(stored);
+        }
+        let mut data = Vec::with_capacity(entry.size as usize);
+        DeflateDecoder::new(stored.as_slice())
+            .read_to_end(&mut data)
+            .with_context(|| format!("Failed to decompress {}", path))?;
+        if data.len() as u64 != entry.size {
+            bail!("{} in {} is corrupt", path, self.path.display());
+        }
+        Ok(data)
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_round_trip() {
+        let root = std::env::temp_dir().join(format!("causality-pack-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("scripts")).unwrap();
+        std::fs::write(root.join("scripts/rotate.rhai"), "fn update() {}").unwrap();
+        std::fs::write(root.join("readme.txt"), "").unwrap();
+        let level = "entity { position 0 0 0 }\n".repeat(200);
+        std::fs::write(root.join("level.scene"), &level).unwrap();
+
+        let files = vec![
+            PathBuf::from("scripts/rotate.rhai"),
+            PathBuf::from("readme.txt"),
+            PathBuf::from("level.scene"),
+        ];
+        let output = root.join("assets.cpack");
+        let size = write_pack(&root, &files, &output).unwrap();
+        assert_eq!(size, std::fs::metadata(&output).unwrap().len());
+
+        let pack = AssetPack::open(&output).unwrap();
+        let paths: Vec<&str> = pack.entries().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["level.scene", "readme.txt", "scripts/rotate.rhai"]);
+        assert_eq!(pack.read("scripts/rotate.rhai").unwrap(), b"fn update() {}");
+        assert!(pack.read("readme.txt").unwrap().is_empty());
+        assert!(pack.read("missing.png").is_err());
+
+        // Repetitive text compresses; tiny files are stored as they are
+        let scene = pack.entries().find(|e| e.path == "level.scene").unwrap();
+        assert!(scene.is_compressed() && scene.stored_size < scene.size / 4);
+        assert_eq!(pack.read("level.scene").unwrap(), level.as_bytes());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,257 @@
+// Project settings - per-project configuration in project.ron
+//
+// The editor, headless runs, causality-cli and the MCP server read project.ron
+// from the project root (the working directory) through current(). Every
+// field is optional: missing ones keep the defaults below, which are the
+// values the engine used before the file existed.
+//
+//   (
+//       name: "Castle",
+//       start_scene: "assets/scenes/castle.ron",
+//       physics: (gravity: (0.0, -9.81, 0.0), timestep: 0.016666668),
+//       rendering: (msaa_samples: 4, shadow_resolution: 4096),
+//       ai: (texture_url: "http://gpu-box:7860"),
+//   )
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{bail, Context, Result};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::ipc;
+
+/// Settings file in the project root
+pub const PROJECT_FILE: &str = "project.ron";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PhysicsSettings {
+    pub gravity: Vec3,
+    /// Length of a simulation step in seconds
+    pub timestep: f32,
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        Self {
+            gravity: Vec3::new(0.0, -9.81, 0.0),
+            timestep: 1.0 / 60.0,
+        }
+    }
+}
+
+impl PhysicsSettings {
+    /// Simulation steps per second
+    pub fn step_rate(&self) -> u32 {
+        (1.0 / self.timestep).round().max(1.0) as u32
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderSettings {
+    /// MSAA sample count (1 = off); editor preferences can override it
+    pub msaa_samples: u32,
+    /// Width and height of the directional shadow map
+    pub shadow_resolution: u32,
+    /// GPU memory for meshes, textures and materials before streamed ones
+    /// are evicted, in megabytes (0 = no limit)
+    pub gpu_memory_budget_mb: u64,
+    /// GPU memory for the finer mips of streamed textures, in megabytes
+    /// (0 = no limit)
+    pub texture_stream_budget_mb: u64,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            msaa_samples: 4,
+            shadow_resolution: 2048,
+            gpu_memory_budget_mb: 0,
+            texture_stream_budget_mb: 512,
+        }
+    }
+}
+
+/// Reproducible play sessions (see determinism)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeterminismSettings {
+    /// Fixed timestep, ordered systems and replay recording in play mode
+    pub enabled: bool,
+    /// Random seed for every session (scripts, particles)
+    pub seed: u64,
+}
+
+impl Default for DeterminismSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            seed: 1,
+        }
+    }
+}
+
+/// How audio clips load (see engine_assets::audio_clip); paths are relative
+/// to the asset root
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    /// Clips loaded in the background when the project opens, so their
+    /// first play doesn't wait on the disk or the decoder
+    pub preload: Vec<String>,
+    /// Clips decoded into memory whatever their size
+    pub in_memory: Vec<String>,
+    /// Clips kept encoded and decoded as they play (long music)
+    pub streamed: Vec<String>,
+}
+
+/// Where the editor reaches the AI services and the MCP server reaches it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AiSettings {
+    /// Stable Diffusion web UI for textures and skyboxes
+    pub texture_url: String,
+    /// ACE-Step server for music
+    pub music_url: String,
+    /// Editor IPC address (the CAUSALITY_IPC_ADDR environment variable wins)
+    pub ipc_address: String,
+}
+
+impl Default for AiSettings {
+    fn default() -> Self {
+        Self {
+            texture_url: "http://localhost:7860".to_string(),
+            music_url: "http://localhost:7865".to_string(),
+            ipc_address: ipc::DEFAULT_ADDRESS.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectSettings {
+    pub name: String,
+    /// Directory asset paths are relative to
+    pub asset_root: PathBuf,
+    /// Scene opened when none is given on the command line
+    pub start_scene: String,
+    pub physics: PhysicsSettings,
+    pub rendering: RenderSettings,
+    pub determinism: DeterminismSettings,
+    pub audio: AudioSettings,
+    /// Input action map for games (engine_input::InputActionMap RON file)
+    pub input_map: Option<String>,
+    /// Game code libraries (engine_plugin native modules), reloaded by the
+    /// editor when they are rebuilt
+    pub native_modules: Vec<PathBuf>,
+    pub ai: AiSettings,
+}
+
+impl Default for ProjectSettings {
+    fn default() -> Self {
+        Self {
+            name: "Causality Project".to_string(),
+            asset_root: PathBuf::from("assets"),
+            start_scene: "assets/scenes/castle.ron".to_string(),
+            physics: PhysicsSettings::default(),
+            rendering: RenderSettings::default(),
+            determinism: DeterminismSettings::default(),
+            audio: AudioSettings::default(),
+            input_map: None,
+            native_modules: Vec::new(),
+            ai: AiSettings::default(),
+        }
+    }
+}
+
+impl ProjectSettings {
+    /// Read `project_dir`'s project.ron; defaults if there is none
+    pub fn load(project_dir: &Path) -> Result<Self> {
+        let path = project_dir.join(PROJECT_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let settings: Self =
+            ron::de::from_str(&text).with_context(|| format!("Invalid {}", path.display()))?;
+        let timestep = settings.physics.timestep;
+        if !timestep.is_finite() || timestep <= 0.0 {
+            bail!(
+                "Invalid {}: physics.timestep must be a positive number of seconds, not {}",
+                path.display(),
+                timestep
+            );
+        }
+        Ok(settings)
+    }
+
+    pub fn save(&self, project_dir: &Path) -> Result<()> {
+        let path = project_dir.join(PROJECT_FILE);
+        let text = ron::ser::to_string_pretty(self, Default::default())?;
+        std::fs::write(&path, text).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Editor IPC address: CAUSALITY_IPC_ADDR if set, else `ai.ipc_address`
+    pub fn ipc_address(&self) -> String {
+        std::env::var(ipc::ADDRESS_ENV).unwrap_or_else(|_| self.ai.ipc_address.clone())
+    }
+}
+
+static CURRENT: OnceLock<ProjectSettings> = OnceLock::new();
+
+/// This process's project settings, read from the working directory on
+/// first use. A broken file is reported and the defaults are used.
+pub fn current() -> &'static ProjectSettings {
+    CURRENT.get_or_init(|| match ProjectSettings::load(Path::new(".")) {
+        Ok(settings) => settings,
+        Err(e) => {
+            log::warn!("{:#}; using default project settings", e);
+            ProjectSettings::default()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_file_keeps_defaults() {
+        let dir = std::env::temp_dir().join(format!("causality-project-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(
+            ProjectSettings::load(&dir).unwrap(),
+            ProjectSettings::default()
+        );
+
+        std::fs::write(
+            dir.join(PROJECT_FILE),
+            "(start_scene: \"scenes/arena.ron\", physics: (timestep: 0.01), rendering: (shadow_resolution: 4096))",
+        )
+        .unwrap();
+        let settings = ProjectSettings::load(&dir).unwrap();
+        assert_eq!(settings.start_scene, "scenes/arena.ron");
+        assert_eq!(settings.physics.step_rate(), 100);
+        assert_eq!(settings.physics.gravity, Vec3::new(0.0, -9.81, 0.0));
+        assert_eq!(settings.rendering.shadow_resolution, 4096);
+        assert_eq!(settings.rendering.msaa_samples, 4);
+
+        settings.save(&dir).unwrap();
+        assert_eq!(ProjectSettings::load(&dir).unwrap(), settings);
+
+        // A timestep that would stall or run the simulation backwards is refused
+        for timestep in ["0.0", "-0.01", "inf", "NaN"] {
+            std::fs::write(
+                dir.join(PROJECT_FILE),
+                format!("(physics: (timestep: {}))", timestep),
+            )
+            .unwrap();
+            assert!(ProjectSettings::load(&dir).is_err(), "timestep {}", timestep);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,34 +1,178 @@
 // Time and delta time tracking
+//
+// One Time per running game. Each frame it measures the real frame time,
+// clamps it (so a hitch or a breakpoint doesn't launch everything across
+// the map) and scales it; physics, scripts, animation and particles all
+// advance by the scaled delta. A time scale of 0.25 is slow motion, and
+// pausing makes the scaled delta zero while the unscaled one (for cameras
+// and UI) keeps going.
 
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
-pub struct TimeState {
+/// Longest frame the game advances by, in seconds
+pub const DEFAULT_MAX_DELTA: f32 = 0.1;
+
+/// Largest allowed time scale
+pub const MAX_TIME_SCALE: f32 = 100.0;
+
+pub struct Time {
     start: Instant,
     last_frame: Instant,
+    /// Clamped real frame time
+    unscaled_delta: f32,
+    /// unscaled_delta * time_scale, or 0 while paused
     delta: f32,
+    time_scale: f32,
+    paused: bool,
+    max_delta: f32,
+    /// Scaled seconds since start
+    elapsed: f64,
+    frame: u64,
 }
 
-impl TimeState {
+/// Time shared between the game loop and scripts
+pub type SharedTime = Arc<Mutex<Time>>;
+
+impl Time {
     pub fn new() -> Self {
         let now = Instant::now();
         Self {
             start: now,
             last_frame: now,
+            unscaled_delta: 0.0,
             delta: 0.0,
+            time_scale: 1.0,
+            paused: false,
+            max_delta: DEFAULT_MAX_DELTA,
+            elapsed: 0.0,
+            frame: 0,
         }
     }
 
+    pub fn with_max_delta(mut self, max_delta: f32) -> Self {
+        self.max_delta = max_delta.max(0.0);
+        self
+    }
+
+    /// Start a frame: measure the real time since the last one and advance
     pub fn tick(&mut self) {
         let now = Instant::now();
-        self.delta = (now - self.last_frame).as_secs_f32();
+        let real_delta = (now - self.last_frame).as_secs_f32();
         self.last_frame = now;
+        self.advance(real_delta);
     }
 
+    /// Start a frame of exactly `step` real seconds (frame stepping,
+    /// headless runs), keeping the clock in sync for the next tick()
+    pub fn tick_fixed(&mut self, step: f32) {
+        self.last_frame = Instant::now();
+        self.advance(step);
+    }
+
+    /// Advance by `real_delta` seconds of real time
+    pub fn advance(&mut self, real_delta: f32) {
+        self.unscaled_delta = real_delta.clamp(0.0, self.max_delta);
+        self.delta = if self.paused {
+            0.0
+        } else {
+            self.unscaled_delta * self.time_scale
+        };
+        self.elapsed += self.delta as f64;
+        self.frame += 1;
+    }
+
+    /// Scaled frame time: what the game advances by this frame
     pub fn delta(&self) -> f32 {
         self.delta
     }
 
+    /// Real frame time (clamped), unaffected by time scale and pause
+    pub fn unscaled_delta(&self) -> f32 {
+        self.unscaled_delta
+    }
+
+    /// Scaled seconds since start
     pub fn elapsed(&self) -> f32 {
+        self.elapsed as f32
+    }
+
+    /// Real seconds since start
+    pub fn unscaled_elapsed(&self) -> f32 {
         (Instant::now() - self.start).as_secs_f32()
     }
+
+    /// Frames since start
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// 1.0 is normal speed, 0.5 half speed; clamped to 0..=MAX_TIME_SCALE
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.time_scale = if scale.is_finite() {
+            scale.clamp(0.0, MAX_TIME_SCALE)
+        } else {
+            1.0
+        };
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Back to normal speed and unpaused, e.g. for a new play session
+    pub fn reset_scale(&mut self) {
+        self.time_scale = 1.0;
+        self.paused = false;
+    }
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale_pause_and_clamp() {
+        let mut time = Time::new().with_max_delta(0.1);
+
+        time.advance(0.02);
+        assert_eq!(time.delta(), 0.02);
+
+        time.set_time_scale(0.5);
+        time.advance(0.02);
+        assert_eq!(time.delta(), 0.01);
+        assert_eq!(time.unscaled_delta(), 0.02);
+
+        // A long hitch is clamped before scaling
+        time.advance(2.0);
+        assert_eq!(time.unscaled_delta(), 0.1);
+        assert_eq!(time.delta(), 0.05);
+
+        time.set_paused(true);
+        time.advance(0.02);
+        assert_eq!(time.delta(), 0.0);
+        assert_eq!(time.unscaled_delta(), 0.02);
+        assert!((time.elapsed() - 0.08).abs() < 1e-6);
+        assert_eq!(time.frame(), 4);
+
+        time.set_time_scale(-3.0);
+        assert_eq!(time.time_scale(), 0.0);
+        time.reset_scale();
+        assert_eq!(time.time_scale(), 1.0);
+        assert!(!time.is_paused());
+    }
 }
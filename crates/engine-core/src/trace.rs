@@ -0,0 +1,315 @@
+// Trace - tracing spans captured to Chrome trace files
+//
+// Engine systems open tracing spans: `tracing::info_span!("physics_step")`,
+// or a span with a `name` field (SystemGraph systems, plugin systems) named
+// after the field. install() makes the recorder the global tracing
+// subscriber. It records nothing until start_capture(); then it records
+// every span on every thread for the next frames (end_frame() marks them)
+// and writes them as Chrome trace JSON for chrome://tracing or Perfetto.
+// Unlike the Profiler tab this sees spans nested inside systems and the
+// work on worker threads.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Event, Metadata, Subscriber};
+
+/// A span that was entered and exited during a capture
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceSpan {
+    pub name: String,
+    /// Trace thread id (see TraceCapture::threads)
+    pub thread: u64,
+    /// Microseconds from the start of the capture
+    pub start_us: f64,
+    pub duration_us: f64,
+}
+
+/// The spans recorded over a frame range
+#[derive(Debug, Clone, Default)]
+pub struct TraceCapture {
+    pub spans: Vec<TraceSpan>,
+    /// When each frame ended, in microseconds from the start of the capture
+    pub frame_ends_us: Vec<f64>,
+    /// Names of the threads spans were recorded on
+    pub threads: BTreeMap<u64, String>,
+}
+
+impl TraceCapture {
+    pub fn to_chrome_trace(&self) -> Value {
+        let mut events: Vec<Value> = self
+            .threads
+            .iter()
+            .map(|(tid, name)| {
+                json!({ "name": "thread_name", "ph": "M", "pid": 1, "tid": tid, "args": { "name": name } })
+            })
+            .collect();
+        for (frame, end_us) in self.frame_ends_us.iter().enumerate() {
+            events.push(json!({
+                "name": format!("Frame {}", frame),
+                "ph": "i",
+                "s": "g",
+                "ts": end_us,
+                "pid": 1,
+                "tid": 0,
+            }));
+        }
+        for span in &self.spans {
+            events.push(json!({
+                "name": span.name,
+                "ph": "X",
+                "ts": span.start_us,
+                "dur": span.duration_us,
+                "pid": 1,
+                "tid": span.thread,
+            }));
+        }
+        json!({ "traceEvents": events, "displayTimeUnit": "ms" })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(&self.to_chrome_trace())?)?;
+        Ok(())
+    }
+}
+
+struct ActiveCapture {
+    output: PathBuf,
+    frames_left: u32,
+    start: Instant,
+    data: TraceCapture,
+}
+
+/// Shared by every thread; spans only go through the locks while capturing
+static CAPTURING: AtomicBool = AtomicBool::new(false);
+static NEXT_SPAN_ID: AtomicU64 = AtomicU64::new(1);
+static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+/// Name and reference count of each live span
+static SPANS: Mutex<BTreeMap<u64, (String, usize)>> = Mutex::new(BTreeMap::new());
+static THREADS: Mutex<BTreeMap<u64, String>> = Mutex::new(BTreeMap::new());
+static CAPTURE: Mutex<Option<ActiveCapture>> = Mutex::new(None);
+
+thread_local! {
+    static THREAD_ID: u64 = {
+        let id = NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed);
+        let name = std::thread::current()
+            .name()
+            .map_or_else(|| format!("worker {}", id), str::to_string);
+        THREADS.lock().unwrap().insert(id, name);
+        id
+    };
+    /// Spans entered on this thread and when
+    static ENTERED: RefCell<Vec<(u64, Instant)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The tracing subscriber behind captures
+pub struct TraceRecorder;
+
+/// Reads a span's `name` field
+struct NameVisitor(Option<String>);
+
+impl Visit for NameVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "name" {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "name" {
+            self.0 = Some(format!("{:?}", value));
+        }
+    }
+}
+
+impl Subscriber for TraceRecorder {
+    fn register_callsite(&self, _metadata: &'static Metadata<'static>) -> Interest {
+        // Whether spans are wanted changes with CAPTURING, so ask every time
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.is_span() && CAPTURING.load(Ordering::Relaxed)
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut visitor = NameVisitor(None);
+        span.record(&mut visitor);
+        let name = visitor
+            .0
+            .unwrap_or_else(|| span.metadata().name().to_string());
+        let id = NEXT_SPAN_ID.fetch_add(1, Ordering::Relaxed);
+        SPANS.lock().unwrap().insert(id, (name, 1));
+        Id::from_u64(id)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push((span.into_u64(), Instant::now())));
+    }
+
+    fn exit(&self, span: &Id) {
+        let id = span.into_u64();
+        let Some(entered_at) = ENTERED.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            let index = entered
+                .iter()
+                .rposition(|(entered_id, _)| *entered_id == id)?;
+            Some(entered.remove(index).1)
+        }) else {
+            return;
+        };
+        let duration = entered_at.elapsed();
+        let Some(name) = SPANS.lock().unwrap().get(&id).map(|(name, _)| name.clone()) else {
+            return;
+        };
+        let thread = THREAD_ID.with(|id| *id);
+        if let Some(capture) = CAPTURE.lock().unwrap().as_mut() {
+            capture.data.spans.push(TraceSpan {
+                name,
+                thread,
+                start_us: entered_at
+                    .saturating_duration_since(capture.start)
+                    .as_secs_f64()
+                    * 1e6,
+                duration_us: duration.as_secs_f64() * 1e6,
+            });
+        }
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        if let Some((_, refs)) = SPANS.lock().unwrap().get_mut(&span.into_u64()) {
+            *refs += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        let mut spans = SPANS.lock().unwrap();
+        let id = span.into_u64();
+        match spans.get_mut(&id) {
+            Some((_, refs)) if *refs > 1 => {
+                *refs -= 1;
+                false
+            }
+            Some(_) => {
+                spans.remove(&id);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Make the recorder the process's tracing subscriber. False if another
+/// subscriber was installed first.
+pub fn install() -> bool {
+    tracing::subscriber::set_global_default(TraceRecorder).is_ok()
+}
+
+/// Record the next `frames` frames and write them to `output` (replaces a
+/// capture in progress)
+pub fn start_capture(frames: u32, output: impl Into<PathBuf>) {
+    *CAPTURE.lock().unwrap() = Some(ActiveCapture {
+        output: output.into(),
+        frames_left: frames.max(1),
+        start: Instant::now(),
+        data: TraceCapture::default(),
+    });
+    CAPTURING.store(true, Ordering::Relaxed);
+}
+
+pub fn is_capturing() -> bool {
+    CAPTURING.load(Ordering::Relaxed)
+}
+
+/// Mark the end of a frame. When it completes a capture, the trace is
+/// written and its path (or the write error) returned.
+pub fn end_frame() -> Option<Result<PathBuf>> {
+    if !is_capturing() {
+        return None;
+    }
+    let mut capture = CAPTURE.lock().unwrap();
+    let active = capture.as_mut()?;
+    active
+        .data
+        .frame_ends_us
+        .push(active.start.elapsed().as_secs_f64() * 1e6);
+    active.frames_left -= 1;
+    if active.frames_left > 0 {
+        return None;
+    }
+
+    CAPTURING.store(false, Ordering::Relaxed);
+    let mut finished = capture.take()?;
+    drop(capture);
+    let threads = THREADS.lock().unwrap();
+    finished.data.threads = finished
+        .data
+        .spans
+        .iter()
+        .filter_map(|span| Some((span.thread, threads.get(&span.thread)?.clone())))
+        .collect();
+    Some(
+        finished
+            .data
+            .write(&finished.output)
+            .map(|()| finished.output),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_records_spans_for_frame_range() {
+        tracing::subscriber::with_default(TraceRecorder, || {
+            // Not capturing: nothing is recorded or written
+            tracing::info_span!("ignored").in_scope(|| {});
+            assert!(end_frame().is_none());
+
+            let path =
+                std::env::temp_dir().join(format!("causality-trace-{}.json", std::process::id()));
+            start_capture(2, &path);
+            tracing::info_span!("system", name = "Physics").in_scope(|| {
+                tracing::info_span!("physics_step").in_scope(|| {});
+            });
+            assert!(end_frame().is_none());
+            let written = end_frame().unwrap().unwrap();
+            assert_eq!(written, path);
+            assert!(!is_capturing());
+
+            let trace: Value =
+                serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+            let events = trace["traceEvents"].as_array().unwrap();
+            let names: Vec<&str> = events
+                .iter()
+                .filter(|event| event["ph"] == "X")
+                .map(|event| event["name"].as_str().unwrap())
+                .collect();
+            assert_eq!(names, ["physics_step", "Physics"]);
+            assert_eq!(events.iter().filter(|event| event["ph"] == "i").count(), 2);
+            assert!(events.iter().any(|event| event["ph"] == "M"));
+            let _ = std::fs::remove_file(&path);
+        });
+    }
+}
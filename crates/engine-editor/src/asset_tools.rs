@@ -0,0 +1,217 @@
+// Asset tools - listing and inspecting existing assets for MCP commands
+//
+// Lets an MCP client look for content it can reuse (models, textures,
+// materials and previously generated images) before generating new assets.
+
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Result};
+use engine_ai_assets::AssetCache;
+use engine_assets::loaders::{load_gltf, load_material};
+use engine_assets::texture::Texture;
+use glam::Vec3;
+use serde_json::{json, Value};
+
+use crate::material_tools::{asset_file, GENERATED_ASSETS_DIR};
+use crate::ui::asset_browser::{search_assets, AssetEntry, AssetKind};
+
+/// Default number of assets list_assets returns
+pub const DEFAULT_MAX_ASSETS: usize = 200;
+
+/// Asset kinds list_assets filters by, as named in tool arguments
+pub const ASSET_TYPES: [&str; 6] = ["model", "texture", "material", "scene", "script", "audio"];
+
+/// Parse an asset type argument ("model", "texture", ...)
+pub fn parse_asset_kind(name: &str) -> Result<AssetKind> {
+    match name.to_lowercase().as_str() {
+        "model" => Ok(AssetKind::Model),
+        "texture" => Ok(AssetKind::Texture),
+        "material" => Ok(AssetKind::Material),
+        "scene" => Ok(AssetKind::Scene),
+        "script" => Ok(AssetKind::Script),
+        "audio" => Ok(AssetKind::Audio),
+        _ => bail!(
+            "Unknown asset type '{}' (expected one of: {})",
+            name,
+            ASSET_TYPES.join(", ")
+        ),
+    }
+}
+
+/// Match a path against a glob: `*` matches within one folder, `**` across
+/// folders and `?` one character. Patterns without a `/` match the file name.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let subject = if pattern.contains('/') {
+        path
+    } else {
+        path.rsplit('/').next().unwrap_or(path)
+    };
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let subject: Vec<char> = subject.to_lowercase().chars().collect();
+    glob_match_chars(&pattern, &subject)
+}
+
+fn glob_match_chars(pattern: &[char], subject: &[char]) -> bool {
+    match pattern {
+        [] => subject.is_empty(),
+        ['*', '*', rest @ ..] => {
+            // "**/" also matches no folders at all
+            let rest_after_slash = rest.strip_prefix(&['/']).unwrap_or(rest);
+            (0..=subject.len()).any(|i| {
+                glob_match_chars(rest, &subject[i..])
+                    || glob_match_chars(rest_after_slash, &subject[i..])
+            })
+        }
+        ['*', rest @ ..] => (0..=subject.len())
+            .take_while(|&i| i == 0 || subject[i - 1] != '/')
+            .any(|i| glob_match_chars(rest, &subject[i..])),
+        ['?', rest @ ..] => {
+            matches!(subject, [c, ..] if *c != '/') && glob_match_chars(rest, &subject[1..])
+        }
+        [c, rest @ ..] => subject.first() == Some(c) && glob_match_chars(rest, &subject[1..]),
+    }
+}
+
+/// All files under the asset root, sorted by path, optionally of one kind
+/// and matching a glob
+pub fn list_assets(root: &Path, kind: Option<AssetKind>, pattern: Option<&str>) -> Vec<AssetEntry> {
+    search_assets(root, "")
+        .into_iter()
+        .filter(|entry| kind.is_none_or(|kind| entry.kind == kind))
+        .filter(|entry| pattern.is_none_or(|pattern| glob_match(pattern, &entry.path_string())))
+        .collect()
+}
+
+/// Size and kind of an asset, plus its dimensions (textures), mesh
+/// statistics (models) or fields (materials)
+pub fn asset_info(root: &Path, path: &str) -> Result<Value> {
+    let file = asset_file(root, path)?;
+    let metadata = std::fs::metadata(&file).map_err(|_| anyhow!("Asset '{}' not found", path))?;
+    if !metadata.is_file() {
+        bail!("'{}' is a folder", path);
+    }
+    let kind = AssetKind::from_path(Path::new(path));
+
+    let mut info = json!({
+        "path": path,
+        "type": kind.label().to_lowercase(),
+        "size_bytes": metadata.len(),
+    });
+    match kind {
+        AssetKind::Texture => {
+            let texture = Texture::from_file(&file)?;
+            info["width"] = json!(texture.width);
+            info["height"] = json!(texture.height);
+            info["format"] = json!(format!("{:?}", texture.format));
+        }
+        AssetKind::Model => {
+            let meshes = load_gltf(&file)?;
+            let positions = || {
+                meshes
+                    .iter()
+                    .flat_map(|m| m.vertices.iter().map(|v| v.position))
+            };
+            let min = positions().fold(Vec3::splat(f32::MAX), Vec3::min);
+            let max = positions().fold(Vec3::splat(f32::MIN), Vec3::max);
+            info["meshes"] = json!(meshes.iter().map(|m| m.name.clone()).collect::<Vec<_>>());
+            info["vertex_count"] = json!(meshes.iter().map(|m| m.vertices.len()).sum::<usize>());
+            info["triangle_count"] =
+                json!(meshes.iter().map(|m| m.indices.len() / 3).sum::<usize>());
+            if min.cmple(max).all() {
+                info["bounds"] = json!({
+                    "min": [min.x, min.y, min.z],
+                    "max": [max.x, max.y, max.z],
+                    "size": [max.x - min.x, max.y - min.y, max.z - min.z],
+                });
+            }
+        }
+        AssetKind::Material => {
+            info["material"] = serde_json::to_value(load_material(&file)?)?;
+        }
+        _ => {}
+    }
+    Ok(info)
+}
+
+/// Images generate_texture and generate_skybox have made, newest first,
+/// optionally only those whose prompt contains `query`. Images already
+/// imported into the asset root report their asset path.
+pub fn generated_assets(root: &Path, query: Option<&str>) -> Result<Vec<Value>> {
+    let cache = AssetCache::new(GENERATED_ASSETS_DIR)?;
+    let query = query.map(|q| q.to_lowercase());
+    let mut assets = cache.list_assets("")?;
+    assets.retain(|asset| {
+        query
+            .as_ref()
+            .is_none_or(|query| asset.prompt.to_lowercase().contains(query))
+    });
+    // generated_at is seconds since the epoch
+    assets.sort_by_key(|asset| std::cmp::Reverse(asset.generated_at.parse::<u64>().unwrap_or(0)));
+
+    Ok(assets
+        .into_iter()
+        .map(|asset| {
+            let imported = format!("textures/generated/{}.{}", asset.id, asset.format);
+            let asset_path = root.join(&imported).is_file().then_some(imported);
+            json!({
+                "id": asset.id,
+                "prompt": asset.prompt,
+                "negative_prompt": asset.negative_prompt,
+                "seed": asset.seed,
+                "width": asset.dimensions.0,
+                "height": asset.dimensions.1,
+                "model": asset.model,
+                "steps": asset.steps,
+                "guidance_scale": asset.guidance_scale,
+                "generated_at": asset.generated_at,
+                "asset_path": asset_path,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_assets::loaders::save_material;
+    use engine_assets::Material;
+
+    #[test]
+    fn test_list_and_inspect_assets() {
+        let root =
+            std::env::temp_dir().join(format!("causality_asset_tools_test_{}", std::process::id()));
+        std::fs::create_dir_all(root.join("models/trees")).unwrap();
+        std::fs::create_dir_all(root.join("materials")).unwrap();
+        std::fs::write(root.join("models/rock.glb"), b"glb").unwrap();
+        std::fs::write(root.join("models/trees/oak.glb"), b"glb").unwrap();
+        save_material(
+            &Material::new("Bark".to_string()),
+            root.join("materials/bark.mat"),
+        )
+        .unwrap();
+
+        let paths = |kind, pattern| {
+            list_assets(&root, kind, pattern)
+                .iter()
+                .map(|e| e.path_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            paths(Some(AssetKind::Model), None),
+            vec!["models/rock.glb", "models/trees/oak.glb"]
+        );
+        assert_eq!(paths(None, Some("models/*.glb")), vec!["models/rock.glb"]);
+        assert_eq!(paths(None, Some("models/**/*.glb")).len(), 2);
+        assert_eq!(paths(None, Some("b?rk.*")), vec!["materials/bark.mat"]);
+        assert!(parse_asset_kind("Texture").is_ok());
+        assert!(parse_asset_kind("mesh").is_err());
+
+        let info = asset_info(&root, "materials/bark.mat").unwrap();
+        assert_eq!(info["type"], "material");
+        assert_eq!(info["material"]["name"], "Bark");
+        assert!(asset_info(&root, "materials/missing.mat").is_err());
+        assert!(asset_info(&root, "../outside.mat").is_err());
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
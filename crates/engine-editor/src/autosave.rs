@@ -0,0 +1,123 @@
+// Autosave - a recovery copy of the scene being edited
+//
+// While editing (not playing), a scene with unsaved changes is written to
+// autosave/<scene name>.ron every couple of minutes, never over the scene
+// file itself. Crash reports name the scene and its latest autosave so the
+// work can be picked up again after a crash.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use engine_core::crash;
+use engine_scene::scene::Scene;
+
+/// Directory autosaves go in, relative to the working directory
+pub const AUTOSAVE_DIR: &str = "autosave";
+
+/// Time between autosaves of a modified scene
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(120);
+
+pub struct Autosave {
+    directory: PathBuf,
+    interval: Duration,
+    last_save: Instant,
+    /// Scene file being edited, as last given to crash reports
+    scene_path: Option<String>,
+}
+
+impl Autosave {
+    pub fn new() -> Self {
+        Self {
+            directory: PathBuf::from(AUTOSAVE_DIR),
+            interval: AUTOSAVE_INTERVAL,
+            last_save: Instant::now(),
+            scene_path: None,
+        }
+    }
+
+    /// Tell crash reports which scene file is open
+    pub fn set_scene_path(&mut self, path: Option<&str>) {
+        if self.scene_path.as_deref() == path {
+            return;
+        }
+        self.scene_path = path.map(str::to_string);
+        match path {
+            Some(path) => crash::set_context(crash::SCENE_KEY, path),
+            None => crash::clear_context(crash::SCENE_KEY),
+        }
+    }
+
+    /// Where `scene` is autosaved
+    pub fn path_for(&self, scene: &Scene) -> PathBuf {
+        let name: String = scene
+            .name
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let name = if name.is_empty() {
+            "untitled".to_string()
+        } else {
+            name
+        };
+        self.directory.join(format!("{}.ron", name))
+    }
+
+    /// Autosave the scene if it has unsaved changes and the interval has
+    /// passed. Returns the file written.
+    pub fn update(&mut self, scene: &Scene, modified: bool) -> Option<PathBuf> {
+        if !modified || self.last_save.elapsed() < self.interval {
+            return None;
+        }
+        self.last_save = Instant::now();
+        let path = self.path_for(scene);
+        let result = std::fs::create_dir_all(&self.directory)
+            .map_err(|e| e.to_string())
+            .and_then(|_| {
+                scene
+                    .save_to_file(&path.to_string_lossy())
+                    .map_err(|e| e.to_string())
+            });
+        match result {
+            Ok(()) => {
+                log::info!("Autosaved scene to {}", path.display());
+                crash::set_context(crash::AUTOSAVE_KEY, path.display().to_string());
+                Some(path)
+            }
+            Err(e) => {
+                log::warn!("Autosave to {} failed: {}", path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_autosaves_only_modified_scenes() {
+        let directory =
+            std::env::temp_dir().join(format!("causality-autosave-{}", std::process::id()));
+        let mut autosave = Autosave {
+            directory: directory.clone(),
+            interval: Duration::ZERO,
+            ..Autosave::new()
+        };
+        let mut scene = Scene::new("Castle: Keep".to_string());
+        scene.create_entity("Tower".to_string());
+
+        assert_eq!(autosave.update(&scene, false), None);
+        let path = autosave.update(&scene, true).unwrap();
+        assert_eq!(path, directory.join("Castle__Keep.ron"));
+        let restored = Scene::load_from_file(&path.to_string_lossy()).unwrap();
+        assert_eq!(restored.entity_count(), 1);
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
@@ -0,0 +1,89 @@
+// Bake - navmesh and lighting bakes with no window
+//
+//   editor --bake navmesh --scene level.ron
+//   editor --bake lightmaps --scene level.ron
+//
+// run the Navigation and Lighting panels' bakes with their default settings.
+// The navmesh is written next to the scene; ambient occlusion goes to
+// assets/lightmaps/<scene> and the scene is saved with its MeshRenderers
+// pointing at it. `causality-cli bake` runs these.
+
+use anyhow::{anyhow, bail, Result};
+use engine_assets::{AoBakeSettings, AssetManager, NavAgentSettings, NavMesh, NavMeshInput};
+use engine_scene::components::{MeshRenderer, TerrainGenerator};
+use engine_scene::scene::Scene;
+
+use crate::{lighting, navigation, terrain_tools};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum BakeTarget {
+    Navmesh,
+    Lightmaps,
+}
+
+/// Load the scene, bake and save the result
+pub fn run(scene_path: &str, target: BakeTarget) -> Result<()> {
+    let mut scene = Scene::load_from_file(scene_path)
+        .map_err(|e| anyhow!("Failed to load scene {}: {}", scene_path, e))?;
+    let asset_root = std::env::current_dir()?.join(&engine_core::project::current().asset_root);
+    let terrain = scene
+        .entities()
+        .find_map(|e| e.get_component::<TerrainGenerator>())
+        .map(|generator| terrain_tools::generate_heightmap(generator, &asset_root));
+    let terrain = terrain
+        .as_ref()
+        .map(|(heightmap, config)| (heightmap, config));
+
+    match target {
+        BakeTarget::Navmesh => {
+            let obstacles = navigation::collect_obstacles(&scene);
+            let input = NavMeshInput {
+                terrain,
+                obstacles: &obstacles,
+            };
+            let navmesh = NavMesh::bake(&input, &NavAgentSettings::default());
+            if navmesh.polygons.is_empty() {
+                bail!("Navmesh bake found no walkable area");
+            }
+            let path = NavMesh::path_for_scene(scene_path);
+            navmesh.save(&path)?;
+            log::info!(
+                "Baked navmesh: {} polygons, {:.0} m² walkable, saved to {}",
+                navmesh.polygons.len(),
+                navmesh.walkable_area(),
+                path.display()
+            );
+        }
+        BakeTarget::Lightmaps => {
+            let mut asset_manager = AssetManager::new(asset_root.clone());
+            let baked = lighting::bake_scene_ao(
+                &scene,
+                &mut asset_manager,
+                terrain,
+                &AoBakeSettings::default(),
+                &lighting::lightmap_dir(Some(scene_path)),
+            )?;
+            if baked.is_empty() {
+                bail!("No static meshes to bake");
+            }
+            let count = baked.len();
+            for baked_mesh in baked {
+                if let Some(mesh_renderer) = scene
+                    .get_entity_mut(baked_mesh.entity)
+                    .and_then(|e| e.get_component_mut::<MeshRenderer>())
+                {
+                    mesh_renderer.lightmap_path = Some(baked_mesh.lightmap_path);
+                }
+            }
+            scene
+                .save_to_file(scene_path)
+                .map_err(|e| anyhow!("Failed to save scene {}: {}", scene_path, e))?;
+            log::info!(
+                "Baked ambient occlusion for {} meshes in {}",
+                count,
+                scene_path
+            );
+        }
+    }
+    Ok(())
+}
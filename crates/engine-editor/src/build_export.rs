@@ -0,0 +1,531 @@
+// Build export - package scenes, assets and the game runtime into a
+// distributable folder per platform
+//
+// The assets go in one assets.cpack next to the runtime, which mounts it and
+// reads scene paths through it (so they stay valid); scripts ship inside the
+// scenes that use them. The build gets a game.json manifest the runtime
+// reads on startup, next to a copy of the project's project.ron. The runtime
+// is the editor executable itself: started next to a game.json it plays that
+// game instead of opening the editor. For other platforms, put an editor build for that platform in
+// `runtimes/<platform>/` in the project, named causality-runtime.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use engine_assets::{AssetManager, NavMesh, SplatMap};
+use engine_core::pack::{write_pack, PACK_EXTENSION};
+use engine_core::project::PROJECT_FILE;
+use engine_scene::scene::Scene;
+use serde::{Deserialize, Serialize};
+
+use crate::lighting::lightmap_dir;
+
+/// Build settings file in the project root
+pub const BUILD_SETTINGS_FILE: &str = "build_settings.json";
+/// Manifest written next to the runtime executable
+pub const MANIFEST_FILE: &str = "game.json";
+/// File name of a runtime in `runtimes/<platform>/` (without extension)
+pub const RUNTIME_NAME: &str = "causality-runtime";
+
+/// Source files and notes that are only useful while editing
+const EDITOR_ONLY_EXTENSIONS: [&str; 4] = ["md", "blend", "psd", "kra"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BuildPlatform {
+    Windows,
+    Linux,
+    MacOs,
+}
+
+impl BuildPlatform {
+    pub const ALL: [BuildPlatform; 3] = [
+        BuildPlatform::Windows,
+        BuildPlatform::Linux,
+        BuildPlatform::MacOs,
+    ];
+
+    /// Platform the editor is running on
+    pub fn host() -> Self {
+        if cfg!(target_os = "windows") {
+            BuildPlatform::Windows
+        } else if cfg!(target_os = "macos") {
+            BuildPlatform::MacOs
+        } else {
+            BuildPlatform::Linux
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BuildPlatform::Windows => "Windows",
+            BuildPlatform::Linux => "Linux",
+            BuildPlatform::MacOs => "macOS",
+        }
+    }
+
+    /// Folder name used for runtimes and build output
+    pub fn dir_name(&self) -> &'static str {
+        match self {
+            BuildPlatform::Windows => "windows",
+            BuildPlatform::Linux => "linux",
+            BuildPlatform::MacOs => "macos",
+        }
+    }
+
+    pub fn executable_name(&self, name: &str) -> String {
+        match self {
+            BuildPlatform::Windows => format!("{}.exe", name),
+            BuildPlatform::Linux | BuildPlatform::MacOs => name.to_string(),
+        }
+    }
+}
+
+/// What to put in a build and how the game starts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BuildSettings {
+    pub game_name: String,
+    /// Scene files to ship, relative to the project root
+    pub scenes: Vec<String>,
+    /// Index into `scenes` of the scene loaded on startup
+    pub start_scene: usize,
+    /// Window size in pixels
+    pub resolution: [u32; 2],
+    pub fullscreen: bool,
+    /// Window/application icon (PNG)
+    pub icon: Option<String>,
+    pub output_dir: String,
+    pub platforms: Vec<BuildPlatform>,
+}
+
+impl Default for BuildSettings {
+    fn default() -> Self {
+        Self {
+            game_name: "Game".to_string(),
+            scenes: Vec::new(),
+            start_scene: 0,
+            resolution: [1280, 720],
+            fullscreen: false,
+            icon: None,
+            output_dir: "builds".to_string(),
+            platforms: vec![BuildPlatform::host()],
+        }
+    }
+}
+
+impl BuildSettings {
+    /// Load the project's build settings, falling back to defaults
+    pub fn load(project_root: &Path) -> Self {
+        let path = project_root.join(BUILD_SETTINGS_FILE);
+        if !path.exists() {
+            return Self::default();
+        }
+        match std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| Ok(serde_json::from_str(&contents)?))
+        {
+            Ok(settings) => settings,
+            Err(e) => {
+                log::warn!("Failed to load build settings from {:?}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, project_root: &Path) -> Result<()> {
+        std::fs::write(
+            project_root.join(BUILD_SETTINGS_FILE),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+
+    /// Why the settings can't be exported, if anything
+    pub fn validate(&self) -> Result<()> {
+        if self.game_name.trim().is_empty() {
+            bail!("The game needs a name");
+        }
+        if self.scenes.is_empty() {
+            bail!("Add at least one scene to the build");
+        }
+        if self.start_scene >= self.scenes.len() {
+            bail!("Pick a start scene");
+        }
+        if self.platforms.is_empty() {
+            bail!("Select at least one platform");
+        }
+        if self.resolution[0] == 0 || self.resolution[1] == 0 {
+            bail!("Resolution must not be zero");
+        }
+        Ok(())
+    }
+
+    /// Build folder for a platform
+    pub fn platform_dir(&self, project_root: &Path, platform: BuildPlatform) -> PathBuf {
+        project_root.join(&self.output_dir).join(format!(
+            "{}-{}",
+            sanitize_name(&self.game_name),
+            platform.dir_name()
+        ))
+    }
+}
+
+/// Startup settings read by the runtime (game.json)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameManifest {
+    pub name: String,
+    pub start_scene: String,
+    pub scenes: Vec<String>,
+    pub resolution: [u32; 2],
+    pub fullscreen: bool,
+    pub icon: Option<String>,
+}
+
+impl GameManifest {
+    /// Read the manifest of the build in `dir`
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        let json = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&json).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+}
+
+/// Folder of the exported build this executable runs from, if there is a
+/// game.json next to it
+pub fn exported_build_dir() -> Option<PathBuf> {
+    let dir = executable_dir().ok()?;
+    dir.join(MANIFEST_FILE).is_file().then_some(dir)
+}
+
+/// Folder the running executable is in, where a shipped build's packs are
+pub fn executable_dir() -> Result<PathBuf> {
+    let exe = std::env::current_exe()?;
+    exe.parent()
+        .map(Path::to_path_buf)
+        .with_context(|| format!("{} has no parent folder", exe.display()))
+}
+
+/// The project's assets, plus any packs shipped next to the executable
+pub fn project_assets() -> Result<AssetManager> {
+    let mut asset_manager =
+        AssetManager::new(std::env::current_dir()?.join(&engine_core::project::current().asset_root));
+    match asset_manager.mount_packs_in(executable_dir()?) {
+        Ok(0) => {}
+        Ok(count) => log::info!("Mounted {} asset pack(s)", count),
+        Err(e) => log::warn!("Failed to mount asset packs: {}", e),
+    }
+    Ok(asset_manager)
+}
+
+/// Load a scene file, from the packs if it isn't a loose file
+pub fn load_scene(asset_manager: &AssetManager, path: &str) -> Result<Scene> {
+    let ron = asset_manager.read_file(path)?;
+    let ron = String::from_utf8(ron).with_context(|| format!("{} is not UTF-8", path))?;
+    Scene::load_from_str(&ron).map_err(|e| anyhow!("Failed to load scene {}: {}", path, e))
+}
+
+/// Window icon from a PNG (the manifest's icon)
+pub fn load_icon(path: &Path) -> Result<winit::window::Icon> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut decoder = png::Decoder::new(std::io::BufReader::new(file));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data)?;
+    data.truncate(info.buffer_size());
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => data,
+        png::ColorType::Rgb => data
+            .chunks(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => data
+            .chunks(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale | png::ColorType::Indexed => {
+            data.iter().flat_map(|&g| [g, g, g, 255]).collect()
+        }
+    };
+    Ok(winit::window::Icon::from_rgba(rgba, info.width, info.height)?)
+}
+
+/// Result of exporting one platform
+#[derive(Debug, Clone)]
+pub struct ExportSummary {
+    pub platform: BuildPlatform,
+    pub dir: PathBuf,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Runtime executable for a platform: `runtimes/<platform>/` in the project
+/// first, then (host platform only) the causality-runtime built next to the
+/// editor
+pub fn find_runtime(project_root: &Path, platform: BuildPlatform) -> Option<PathBuf> {
+    let exe_name = platform.executable_name(RUNTIME_NAME);
+    let bundled = project_root
+        .join("runtimes")
+        .join(platform.dir_name())
+        .join(&exe_name);
+    if bundled.is_file() {
+        return Some(bundled);
+    }
+    if platform != BuildPlatform::host() {
+        return None;
+    }
+    std::env::current_exe()
+        .ok()?
+        .parent()
+        .map(|dir| dir.join(&exe_name))
+        .filter(|path| path.is_file())
+}
+
+/// Export the build for one platform, replacing any previous export there
+pub fn export_platform(
+    settings: &BuildSettings,
+    project_root: &Path,
+    platform: BuildPlatform,
+) -> Result<ExportSummary> {
+    settings.validate()?;
+    let runtime = find_runtime(project_root, platform).with_context(|| {
+        format!(
+            "No {} runtime found (build it with `cargo build -p engine-editor --bin {}`, \
+             or place one in runtimes/{}/)",
+            platform.label(),
+            RUNTIME_NAME,
+            platform.dir_name()
+        )
+    })?;
+
+    let dir = settings.platform_dir(project_root, platform);
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)
+            .with_context(|| format!("Failed to clear {}", dir.display()))?;
+    }
+    std::fs::create_dir_all(&dir)?;
+    let mut summary = ExportSummary {
+        platform,
+        dir: dir.clone(),
+        files: 0,
+        bytes: 0,
+    };
+
+    // Runtime, named after the game
+    let exe_path = dir.join(platform.executable_name(&sanitize_name(&settings.game_name)));
+    copy_file(&runtime, &exe_path, &mut summary)?;
+
+    // Scenes with their baked navmesh, painted splatmap and lighting. Those
+    // under assets/ go in the pack; any elsewhere are copied as they are.
+    let assets_dir = project_root.join("assets");
+    let skipped_dirs = [assets_dir.join("scenes"), assets_dir.join("lightmaps")];
+    let mut packed = Vec::new();
+    for scene in &settings.scenes {
+        let source = project_root.join(scene);
+        if !source.is_file() {
+            bail!("Scene {} not found", scene);
+        }
+        let sidecars = [NavMesh::path_for_scene(scene), SplatMap::path_for_scene(scene)];
+        for file in std::iter::once(PathBuf::from(scene)).chain(sidecars) {
+            if !project_root.join(&file).is_file() {
+                continue;
+            }
+            match file.strip_prefix("assets") {
+                Ok(relative) => packed.push(relative.to_path_buf()),
+                Err(_) => copy_file(&project_root.join(&file), &dir.join(&file), &mut summary)?,
+            }
+        }
+        let lightmaps = lightmap_dir(Some(scene));
+        collect_tree(&assets_dir, &project_root.join(lightmaps), &[], &mut packed)?;
+    }
+
+    // Everything else in assets/, minus editor-only files. Streamed terrain
+    // tiles are read by path as the camera moves, so they stay loose files.
+    let mut rest = Vec::new();
+    collect_tree(&assets_dir, &assets_dir, &skipped_dirs, &mut rest)?;
+    for file in rest {
+        if file.extension().is_some_and(|e| e == "height") {
+            copy_file(&assets_dir.join(&file), &dir.join("assets").join(&file), &mut summary)?;
+        } else {
+            packed.push(file);
+        }
+    }
+    let pack_path = dir.join(format!("assets.{}", PACK_EXTENSION));
+    summary.bytes += write_pack(&assets_dir, &packed, &pack_path)?;
+    summary.files += 1;
+
+    // Physics, rendering and input settings for the runtime
+    let project_file = project_root.join(PROJECT_FILE);
+    if project_file.is_file() {
+        copy_file(&project_file, &dir.join(PROJECT_FILE), &mut summary)?;
+    }
+
+    let icon = match &settings.icon {
+        Some(icon) => {
+            let source = project_root.join(icon);
+            let extension = source.extension().and_then(|e| e.to_str()).unwrap_or("png");
+            let name = format!("icon.{}", extension);
+            copy_file(&source, &dir.join(&name), &mut summary)
+                .with_context(|| format!("Failed to copy icon {}", icon))?;
+            Some(name)
+        }
+        None => None,
+    };
+
+    let manifest = GameManifest {
+        name: settings.game_name.clone(),
+        start_scene: settings.scenes[settings.start_scene].clone(),
+        scenes: settings.scenes.clone(),
+        resolution: settings.resolution,
+        fullscreen: settings.fullscreen,
+        icon,
+    };
+    let json = serde_json::to_string_pretty(&manifest)?;
+    summary.bytes += json.len() as u64;
+    summary.files += 1;
+    std::fs::write(dir.join(MANIFEST_FILE), json)?;
+
+    Ok(summary)
+}
+
+fn copy_file(source: &Path, target: &Path, summary: &mut ExportSummary) -> Result<()> {
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    summary.bytes += std::fs::copy(source, target)
+        .with_context(|| format!("Failed to copy {}", source.display()))?;
+    summary.files += 1;
+    Ok(())
+}
+
+/// Add the files under `source` to `files`, as paths relative to `root`,
+/// leaving out `skip` folders and editor-only files. A missing source is not
+/// an error.
+fn collect_tree(
+    root: &Path,
+    source: &Path,
+    skip: &[PathBuf],
+    files: &mut Vec<PathBuf>,
+) -> Result<()> {
+    if !source.is_dir() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(source)? {
+        let path = entry?.path();
+        if skip.contains(&path) {
+            continue;
+        }
+        if path.is_dir() {
+            collect_tree(root, &path, skip, files)?;
+        } else if !is_editor_only(&path) {
+            files.push(path.strip_prefix(root)?.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+fn is_editor_only(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| EDITOR_ONLY_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Game name usable as a file name
+fn sanitize_name(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() {
+        "game".to_string()
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, contents: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_export_packages_selected_scenes() {
+        let root =
+            std::env::temp_dir().join(format!("causality_build_test_{}", std::process::id()));
+        let platform = BuildPlatform::Linux;
+        write(&root.join("runtimes/linux").join(RUNTIME_NAME), "runtime");
+        write(
+            &root.join("assets/scenes/level1.ron"),
+            "(name: \"Level 1\")",
+        );
+        write(&root.join("assets/scenes/level1.navmesh.json"), "{}");
+        write(
+            &root.join("assets/scenes/sandbox.ron"),
+            "(name: \"Sandbox\")",
+        );
+        write(&root.join("assets/lightmaps/level1/entity_1.ao.json"), "{}");
+        write(
+            &root.join("assets/lightmaps/sandbox/entity_1.ao.json"),
+            "{}",
+        );
+        write(&root.join("assets/textures/grass.png"), "png");
+        write(&root.join("assets/textures/README.md"), "notes");
+        write(&root.join("assets/terrain/tile_0_0.height"), "tile");
+        write(&root.join("scripts/rotate.rhai"), "fn update() {}");
+
+        let settings = BuildSettings {
+            game_name: "My Game".to_string(),
+            scenes: vec!["assets/scenes/level1.ron".to_string()],
+            platforms: vec![platform],
+            ..Default::default()
+        };
+        let summary = export_platform(&settings, &root, platform).unwrap();
+        let dir = summary.dir.clone();
+        let exists = |path: &str| dir.join(path).exists();
+        let manifest = GameManifest::load(&dir).unwrap();
+        let pack = engine_core::pack::AssetPack::open(dir.join("assets.cpack")).unwrap();
+        let checks = [
+            exists("My_Game"),
+            pack.contains("scenes/level1.ron"),
+            pack.contains("scenes/level1.navmesh.json"),
+            !pack.contains("scenes/sandbox.ron"),
+            pack.contains("lightmaps/level1/entity_1.ao.json"),
+            !pack.contains("lightmaps/sandbox/entity_1.ao.json"),
+            pack.contains("textures/grass.png"),
+            !pack.contains("textures/README.md"),
+            !pack.contains("terrain/tile_0_0.height"),
+            exists("assets/terrain/tile_0_0.height"),
+            !exists("assets/scenes"),
+            !exists("scripts"),
+        ];
+        std::fs::remove_dir_all(&root).ok();
+
+        assert_eq!(checks, [true; 12]);
+        assert_eq!(manifest.start_scene, "assets/scenes/level1.ron");
+        // Runtime, pack, terrain tile and manifest
+        assert_eq!(summary.files, 4);
+    }
+
+    #[test]
+    fn test_validate_requires_a_start_scene() {
+        let mut settings = BuildSettings::default();
+        assert!(settings.validate().is_err());
+        settings.scenes.push("assets/scenes/castle.ron".to_string());
+        assert!(settings.validate().is_ok());
+        settings.start_scene = 3;
+        assert!(settings.validate().is_err());
+    }
+}
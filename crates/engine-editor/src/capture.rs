@@ -0,0 +1,442 @@
+// Screenshot capture - native readback of the rendered scene
+//
+// Screenshots copy the scene out of the swapchain before the editor UI is
+// drawn over it. Supersampled shots render the view as an N x N grid of tiles
+// over consecutive frames and stitch them into one large image, which is
+// saved as is or filtered back down to the view size for anti-aliasing.
+// Turntables orbit the editor camera once around its target and flythroughs
+// move it through the saved camera bookmarks, saving a numbered PNG per step.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use engine_render::{tile_projection, CapturedImage, FrameCapture};
+use glam::Mat4;
+
+use crate::ui::viewport::{CameraBookmark, ViewportControls};
+
+/// Folder (under the project) screenshots are saved to
+pub const SCREENSHOT_DIR: &str = "screenshots";
+
+/// File viewport captures requested over MCP IPC are written to
+pub fn mcp_capture_path() -> PathBuf {
+    std::env::temp_dir().join(format!("causality_mcp_capture_{}.png", std::process::id()))
+}
+
+/// Most tiles per side of a supersampled capture
+pub const MAX_SUPERSAMPLE: u32 = 4;
+
+/// Options shared by all capture modes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptureSettings {
+    /// Tiles per side (1 = plain screenshot)
+    pub supersample: u32,
+    /// Keep the stitched image at full resolution instead of filtering it down
+    pub high_resolution: bool,
+    /// Images in a turntable or flythrough sequence
+    pub sequence_frames: u32,
+}
+
+impl Default for CaptureSettings {
+    fn default() -> Self {
+        Self {
+            supersample: 1,
+            high_resolution: false,
+            sequence_frames: 72,
+        }
+    }
+}
+
+/// What a capture job renders
+#[derive(Debug, Clone)]
+pub enum CaptureMode {
+    /// The current view
+    Screenshot,
+    /// One full orbit around the camera target
+    Turntable,
+    /// Through the camera bookmarks, in slot order
+    Flythrough(Vec<CameraBookmark>),
+}
+
+impl CaptureMode {
+    fn name(&self) -> &'static str {
+        match self {
+            CaptureMode::Screenshot => "screenshot",
+            CaptureMode::Turntable => "turntable",
+            CaptureMode::Flythrough(_) => "flythrough",
+        }
+    }
+}
+
+/// Capture asked for from the File menu, a shortcut or the screenshot trigger file
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaptureRequest {
+    /// Screenshot to the given file, or to a new file in the screenshots folder
+    Screenshot(Option<PathBuf>),
+    Turntable,
+    Flythrough,
+}
+
+/// Tiles of one output image
+struct Stitch {
+    image: Option<CapturedImage>,
+    received: u32,
+}
+
+/// A screenshot or sequence being rendered and read back over several frames
+pub struct CaptureJob {
+    mode: CaptureMode,
+    settings: CaptureSettings,
+    /// Output file for a single screenshot, or the folder of a sequence
+    output: PathBuf,
+    frame_count: u32,
+    /// Sequence image being rendered
+    frame: u32,
+    /// Tile of the current image being rendered
+    tile: u32,
+    /// View when the job started (restored when a sequence ends)
+    start_view: CameraBookmark,
+    /// Readbacks in flight: (image, tile, copy)
+    pending: Vec<(u32, u32, FrameCapture)>,
+    stitches: HashMap<u32, Stitch>,
+    saved: Vec<PathBuf>,
+    error: Option<String>,
+}
+
+impl CaptureJob {
+    /// Start a capture. Screenshots are written to `output`; sequences write
+    /// numbered images into the folder `output`.
+    pub fn new(
+        mode: CaptureMode,
+        settings: CaptureSettings,
+        output: PathBuf,
+        controls: &ViewportControls,
+    ) -> Self {
+        let settings = CaptureSettings {
+            supersample: settings.supersample.clamp(1, MAX_SUPERSAMPLE),
+            sequence_frames: settings.sequence_frames.max(2),
+            ..settings
+        };
+        let frame_count = match mode {
+            CaptureMode::Screenshot => 1,
+            _ => settings.sequence_frames,
+        };
+        Self {
+            mode,
+            settings,
+            output,
+            frame_count,
+            frame: 0,
+            tile: 0,
+            start_view: controls.bookmark(),
+            pending: Vec::new(),
+            stitches: HashMap::new(),
+            saved: Vec::new(),
+            error: None,
+        }
+    }
+
+    fn tiles(&self) -> u32 {
+        self.settings.supersample * self.settings.supersample
+    }
+
+    /// True once every image has been rendered (readbacks may still be in flight)
+    fn rendered(&self) -> bool {
+        self.frame >= self.frame_count
+    }
+
+    /// True once every image has been saved, or the capture failed
+    pub fn is_finished(&self) -> bool {
+        self.error.is_some() || (self.rendered() && self.pending.is_empty())
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn saved(&self) -> &[PathBuf] {
+        &self.saved
+    }
+
+    /// Screenshot file or sequence folder the job writes to
+    pub fn output(&self) -> &Path {
+        &self.output
+    }
+
+    /// Progress for the status bar, e.g. "Capturing turntable 12/72"
+    pub fn status(&self) -> String {
+        let done = self.saved.len() as u32;
+        if self.frame_count > 1 {
+            format!(
+                "Capturing {} {}/{}",
+                self.mode.name(),
+                done,
+                self.frame_count
+            )
+        } else {
+            format!("Capturing {}", self.mode.name())
+        }
+    }
+
+    /// Move the editor camera to the current sequence image's viewpoint
+    pub fn apply_view(&self, controls: &mut ViewportControls) {
+        if self.rendered() {
+            return;
+        }
+        let t = self.frame as f32 / self.frame_count as f32;
+        match &self.mode {
+            CaptureMode::Screenshot => {}
+            CaptureMode::Turntable => {
+                let mut view = self.start_view;
+                view.orbit_yaw += t * std::f32::consts::TAU;
+                controls.apply_bookmark(&view);
+            }
+            CaptureMode::Flythrough(bookmarks) => {
+                // The last image lands on the last bookmark
+                let t = self.frame as f32 / (self.frame_count - 1) as f32;
+                if let Some(view) = flythrough_view(bookmarks, t) {
+                    controls.apply_bookmark(&view);
+                }
+            }
+        }
+    }
+
+    /// Put the camera back where it was before a sequence
+    pub fn restore_view(&self, controls: &mut ViewportControls) {
+        if !matches!(self.mode, CaptureMode::Screenshot) {
+            controls.apply_bookmark(&self.start_view);
+        }
+    }
+
+    /// Matrix to premultiply into this frame's view-projection (picks the tile)
+    pub fn tile_matrix(&self) -> Mat4 {
+        let n = self.settings.supersample;
+        if self.rendered() || n <= 1 {
+            return Mat4::IDENTITY;
+        }
+        tile_projection(n, self.tile % n, self.tile / n)
+    }
+
+    /// Copy this frame's scene region (`origin` / `size` in pixels) for the
+    /// current tile and move on to the next one
+    pub fn record(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        origin: [u32; 2],
+        size: [u32; 2],
+    ) {
+        if self.rendered() || self.error.is_some() {
+            return;
+        }
+        let Some(copy) = FrameCapture::record(device, encoder, texture, origin, size) else {
+            self.error = Some(format!(
+                "Frame readback is not supported for {:?} surfaces",
+                texture.format()
+            ));
+            return;
+        };
+        self.pending.push((self.frame, self.tile, copy));
+        self.tile += 1;
+        if self.tile == self.tiles() {
+            self.tile = 0;
+            self.frame += 1;
+        }
+    }
+
+    /// Start reading back this frame's copy (after the encoder was submitted)
+    pub fn after_submit(&mut self) {
+        for (_, _, copy) in &mut self.pending {
+            copy.after_submit();
+        }
+    }
+
+    /// Collect finished readbacks, saving images whose tiles have all arrived
+    pub fn poll(&mut self, device: &wgpu::Device) {
+        let mut index = 0;
+        while index < self.pending.len() {
+            let Some(result) = self.pending[index].2.poll(device) else {
+                index += 1;
+                continue;
+            };
+            let (frame, tile, _) = self.pending.remove(index);
+            let Some(tile_image) = result else {
+                self.error = Some("Frame readback failed".to_string());
+                return;
+            };
+            if let Err(e) = self.add_tile(frame, tile, tile_image) {
+                self.error = Some(format!("{:#}", e));
+                return;
+            }
+        }
+    }
+
+    fn add_tile(&mut self, frame: u32, tile: u32, tile_image: CapturedImage) -> Result<()> {
+        let n = self.settings.supersample;
+        let tiles = self.tiles();
+        let stitch = self.stitches.entry(frame).or_insert(Stitch {
+            image: None,
+            received: 0,
+        });
+        let image = stitch
+            .image
+            .get_or_insert_with(|| CapturedImage::new(tile_image.width * n, tile_image.height * n));
+        image.blit(
+            &tile_image,
+            (tile % n) * tile_image.width,
+            (tile / n) * tile_image.height,
+        );
+        stitch.received += 1;
+        if stitch.received < tiles {
+            return Ok(());
+        }
+
+        let Some(image) = self.stitches.remove(&frame).and_then(|s| s.image) else {
+            return Ok(());
+        };
+        let image = if self.settings.high_resolution {
+            image
+        } else {
+            image.downsample(n)
+        };
+        let path = if self.frame_count > 1 {
+            std::fs::create_dir_all(&self.output)
+                .with_context(|| format!("Failed to create {}", self.output.display()))?;
+            self.output
+                .join(format!("{}_{:04}.png", self.mode.name(), frame))
+        } else {
+            self.output.clone()
+        };
+        save_png(&image, &path)?;
+        self.saved.push(path);
+        Ok(())
+    }
+}
+
+/// Camera view `t` (0..=1) of the way through `bookmarks`, moving at an even
+/// pace from one bookmark to the next
+pub fn flythrough_view(bookmarks: &[CameraBookmark], t: f32) -> Option<CameraBookmark> {
+    let last = bookmarks.len().checked_sub(1)?;
+    if last == 0 {
+        return bookmarks.first().copied();
+    }
+    let position = t.clamp(0.0, 1.0) * last as f32;
+    let index = (position.floor() as usize).min(last - 1);
+    let (a, b) = (bookmarks[index], bookmarks[index + 1]);
+    let s = position - index as f32;
+    // Turn the short way round
+    let yaw_delta = (b.orbit_yaw - a.orbit_yaw + std::f32::consts::PI)
+        .rem_euclid(std::f32::consts::TAU)
+        - std::f32::consts::PI;
+    Some(CameraBookmark {
+        orbit_distance: a.orbit_distance + (b.orbit_distance - a.orbit_distance) * s,
+        orbit_pitch: a.orbit_pitch + (b.orbit_pitch - a.orbit_pitch) * s,
+        orbit_yaw: a.orbit_yaw + yaw_delta * s,
+        pan_offset: a.pan_offset.lerp(b.pan_offset, s),
+        orthographic: if s < 0.5 {
+            a.orthographic
+        } else {
+            b.orthographic
+        },
+    })
+}
+
+/// Unused path for a new capture, e.g. `screenshots/screenshot_1735732800.png`.
+/// An empty `extension` names a folder (for sequences).
+pub fn capture_path(dir: &Path, prefix: &str, extension: &str) -> PathBuf {
+    let stamp = unix_seconds();
+    let mut path = dir.join(format!("{}_{}{}", prefix, stamp, extension));
+    let mut counter = 1;
+    while path.exists() {
+        path = dir.join(format!("{}_{}_{}{}", prefix, stamp, counter, extension));
+        counter += 1;
+    }
+    path
+}
+
+/// Seconds since the Unix epoch, which keeps capture names unique and sortable
+fn unix_seconds() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Write an image as an opaque 8-bit RGBA PNG
+pub fn save_png(image: &CapturedImage, path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    // The swapchain's alpha is whatever the passes left behind; screenshots are opaque
+    let mut rgba = image.rgba.clone();
+    for texel in rgba.chunks_exact_mut(4) {
+        texel[3] = 255;
+    }
+
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), image.width, image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&rgba)?;
+    writer.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec3;
+
+    fn view(yaw: f32, pan: Vec3) -> CameraBookmark {
+        CameraBookmark {
+            orbit_distance: 10.0,
+            orbit_pitch: 0.5,
+            orbit_yaw: yaw,
+            pan_offset: pan,
+            orthographic: false,
+        }
+    }
+
+    #[test]
+    fn test_flythrough_passes_through_bookmarks() {
+        let bookmarks = [
+            view(0.0, Vec3::ZERO),
+            view(1.0, Vec3::X * 10.0),
+            view(1.0, Vec3::Z * 10.0),
+        ];
+        assert_eq!(flythrough_view(&bookmarks, 0.0), Some(bookmarks[0]));
+        assert_eq!(flythrough_view(&bookmarks, 0.5), Some(bookmarks[1]));
+        assert_eq!(flythrough_view(&bookmarks, 1.0), Some(bookmarks[2]));
+        let quarter = flythrough_view(&bookmarks, 0.25).unwrap();
+        assert!((quarter.orbit_yaw - 0.5).abs() < 1e-5);
+        assert!((quarter.pan_offset - Vec3::X * 5.0).length() < 1e-5);
+        assert_eq!(flythrough_view(&[], 0.5), None);
+
+        // Yaw wraps the short way round
+        let wrap = [view(3.0, Vec3::ZERO), view(-3.0, Vec3::ZERO)];
+        let middle = flythrough_view(&wrap, 0.5).unwrap();
+        assert!((middle.orbit_yaw.abs() - std::f32::consts::PI).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_save_png_is_opaque() {
+        let dir = std::env::temp_dir().join(format!("capture_test_{}", std::process::id()));
+        let path = dir.join("shot.png");
+        let mut image = CapturedImage::new(3, 2);
+        image.rgba[0] = 200;
+        save_png(&image, &path).unwrap();
+
+        let decoder = png::Decoder::new(std::fs::File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).unwrap();
+        assert_eq!((info.width, info.height), (3, 2));
+        assert_eq!(&data[..4], &[200, 0, 0, 255]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,485 @@
+// Component registry - reflection over component types by name
+//
+// Each registered type can be read from and written to an entity as JSON
+// through its serde representation, so tools (the MCP server in particular)
+// can inspect and edit any component without per-type code. Field edits
+// round-trip through the type's Deserialize impl, which rejects values of
+// the wrong shape before they reach the entity.
+
+use anyhow::{anyhow, bail, Result};
+use engine_physics::{CharacterController, Collider, RigidBody};
+use engine_plugin::PluginComponent;
+use engine_scene::animation::AnimationClip;
+use engine_scene::animator::Animator;
+use engine_scene::components::{
+    AudioListener, AudioOccluder, AudioSource, BehaviorAgent, Camera, Foliage, Light, MeshRenderer,
+    ParticleEmitter, RagdollRig, Replicated, ReverbZone, TerrainGenerator, TerrainStreaming,
+    TerrainWater, Water,
+};
+use engine_scene::entity::{Component, Entity};
+use engine_scene::grass::Grass;
+use engine_scene::ik::{FootPlacement, LookAt};
+use engine_scene::navigation::NavAgent;
+use engine_scene::spline::Spline;
+use engine_scene::wind::Wind;
+use engine_scripting::Script;
+use glam::Vec3;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+/// Reflection entry for one component type
+pub struct ComponentType {
+    pub name: &'static str,
+    get: fn(&Entity) -> Option<Value>,
+    set: fn(&mut Entity, Value) -> Result<()>,
+    remove: fn(&mut Entity) -> bool,
+    /// Starting values for `add` (None = the caller has to give every field)
+    default: Option<Box<dyn Fn() -> Value + Send + Sync>>,
+}
+
+impl ComponentType {
+    /// The entity's component as JSON
+    pub fn get(&self, entity: &Entity) -> Option<Value> {
+        (self.get)(entity)
+    }
+
+    /// Replace (or add) the entity's component with one parsed from JSON
+    pub fn set(&self, entity: &mut Entity, value: Value) -> Result<()> {
+        (self.set)(entity, value)
+    }
+
+    pub fn remove(&self, entity: &mut Entity) -> bool {
+        (self.remove)(entity)
+    }
+
+    /// Add the component, starting from the type's defaults with `fields`
+    /// merged over them
+    pub fn add(&self, entity: &mut Entity, fields: Option<Value>) -> Result<()> {
+        if self.get(entity).is_some() {
+            bail!("Entity '{}' already has a {}", entity.name, self.name);
+        }
+        let value = match (&self.default, fields) {
+            (Some(default), Some(fields)) => {
+                let mut value = default();
+                merge(&mut value, fields);
+                value
+            }
+            (Some(default), None) => default(),
+            (None, Some(fields)) => fields,
+            (None, None) => bail!("{} has no defaults, give all of its fields", self.name),
+        };
+        self.set(entity, value)
+    }
+
+    /// Set one field, addressed by a dotted path ("color", "light_type.Point.range",
+    /// "color.0"), leaving the rest of the component as it was
+    pub fn set_field(&self, entity: &mut Entity, path: &str, field: Value) -> Result<Value> {
+        let mut value = self
+            .get(entity)
+            .ok_or_else(|| anyhow!("Entity '{}' has no {}", entity.name, self.name))?;
+        *field_mut(&mut value, path)? = field;
+        self.set(entity, value.clone())?;
+        Ok(value)
+    }
+}
+
+/// Comparison a `FieldFilter` makes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    /// Substring of a string, or element of an array
+    Contains,
+}
+
+impl FilterOp {
+    pub fn parse(op: &str) -> Result<Self> {
+        Ok(match op {
+            "==" | "=" | "eq" => FilterOp::Eq,
+            "!=" | "ne" => FilterOp::Ne,
+            ">" | "gt" => FilterOp::Gt,
+            ">=" | "ge" => FilterOp::Ge,
+            "<" | "lt" => FilterOp::Lt,
+            "<=" | "le" => FilterOp::Le,
+            "contains" => FilterOp::Contains,
+            _ => bail!(
+                "Unknown filter op '{}' (expected ==, !=, >, >=, <, <= or contains)",
+                op
+            ),
+        })
+    }
+}
+
+/// A condition on one field of a component, e.g. `intensity > 2`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldFilter {
+    /// Dotted path, as for `ComponentType::set_field`
+    pub field: String,
+    pub op: FilterOp,
+    pub value: Value,
+}
+
+impl FieldFilter {
+    /// Parse `{"field": ..., "op": ..., "value": ...}` (op defaults to ==)
+    pub fn from_json(filter: &Value) -> Result<Self> {
+        let field = filter
+            .get("field")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Filter is missing field"))?;
+        let op = filter.get("op").and_then(|v| v.as_str()).unwrap_or("==");
+        let value = filter
+            .get("value")
+            .cloned()
+            .ok_or_else(|| anyhow!("Filter on '{}' is missing value", field))?;
+        Ok(Self {
+            field: field.to_string(),
+            op: FilterOp::parse(op)?,
+            value,
+        })
+    }
+
+    /// Whether a component's JSON passes the filter. A missing field, or
+    /// an ordering comparison between values that aren't both numbers or
+    /// both strings, fails it.
+    pub fn matches(&self, component: &Value) -> bool {
+        let Some(actual) = field(component, &self.field) else {
+            return false;
+        };
+        match self.op {
+            FilterOp::Eq => values_equal(actual, &self.value),
+            FilterOp::Ne => !values_equal(actual, &self.value),
+            FilterOp::Contains => match (actual, &self.value) {
+                (Value::String(text), Value::String(part)) => {
+                    text.to_lowercase().contains(&part.to_lowercase())
+                }
+                (Value::Array(items), value) => items.iter().any(|item| values_equal(item, value)),
+                _ => false,
+            },
+            op => {
+                let ordering = match (actual, &self.value) {
+                    (Value::Number(a), Value::Number(b)) => a
+                        .as_f64()
+                        .zip(b.as_f64())
+                        .and_then(|(a, b)| a.partial_cmp(&b)),
+                    (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+                    _ => None,
+                };
+                ordering.is_some_and(|ordering| match op {
+                    FilterOp::Gt => ordering.is_gt(),
+                    FilterOp::Ge => ordering.is_ge(),
+                    FilterOp::Lt => ordering.is_lt(),
+                    _ => ordering.is_le(),
+                })
+            }
+        }
+    }
+}
+
+/// Numbers compare by value (so 2 == 2.0), everything else structurally
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| values_equal(a, b))
+        }
+        _ => a == b,
+    }
+}
+
+/// Component types addressable by name
+pub struct ComponentRegistry {
+    types: Vec<ComponentType>,
+}
+
+impl ComponentRegistry {
+    /// Registry with every engine component type
+    pub fn with_engine_components() -> Self {
+        let mut registry = Self { types: Vec::new() };
+        registry.register::<MeshRenderer>("MeshRenderer");
+        registry.register_with::<Camera>("Camera", Camera::default);
+        registry.register_with::<Light>("Light", || Light::point([1.0, 1.0, 1.0], 1.0, 10.0));
+        registry.register::<AudioSource>("AudioSource");
+        registry.register_with::<AudioListener>("AudioListener", AudioListener::default);
+        registry.register_with::<ReverbZone>("ReverbZone", ReverbZone::default);
+        registry.register_with::<AudioOccluder>("AudioOccluder", AudioOccluder::default);
+        registry.register_with::<ParticleEmitter>("ParticleEmitter", ParticleEmitter::default);
+        registry.register_with::<Water>("Water", Water::default);
+        registry.register_with::<TerrainWater>("TerrainWater", TerrainWater::default);
+        registry.register_with::<TerrainGenerator>("TerrainGenerator", TerrainGenerator::default);
+        registry.register_with::<TerrainStreaming>("TerrainStreaming", TerrainStreaming::default);
+        registry.register_with::<Spline>("Spline", Spline::default);
+        registry.register_with::<Foliage>("Foliage", Foliage::default);
+        registry.register_with::<Wind>("Wind", Wind::default);
+        registry.register_with::<Grass>("Grass", Grass::default);
+        registry.register_with::<AnimationClip>("AnimationClip", AnimationClip::default);
+        registry.register_with::<Animator>("Animator", Animator::default);
+        registry.register_with::<LookAt>("LookAt", LookAt::default);
+        registry.register_with::<FootPlacement>("FootPlacement", FootPlacement::default);
+        registry.register_with::<RagdollRig>("RagdollRig", RagdollRig::default);
+        registry.register_with::<NavAgent>("NavAgent", NavAgent::default);
+        registry.register_with::<BehaviorAgent>("BehaviorAgent", BehaviorAgent::default);
+        registry.register_with::<Replicated>("Replicated", Replicated::default);
+        registry.register_with::<RigidBody>("RigidBody", || RigidBody::dynamic(1.0));
+        registry.register_with::<Collider>("Collider", || Collider::box_collider(Vec3::splat(0.5)));
+        registry.register_with::<CharacterController>(
+            "CharacterController",
+            CharacterController::default,
+        );
+        registry.register_with::<Script>("Script", || Script::new(String::new()));
+        registry
+    }
+
+    /// Register a type that can only be added with all of its fields given
+    pub fn register<T>(&mut self, name: &'static str)
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        self.types.push(ComponentType {
+            name,
+            get: get_json::<T>,
+            set: set_json::<T>,
+            remove: remove::<T>,
+            default: None,
+        });
+    }
+
+    /// Register a type with the values `add` starts from
+    pub fn register_with<T>(&mut self, name: &'static str, default: fn() -> T)
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        self.register::<T>(name);
+        if let Some(entry) = self.types.last_mut() {
+            entry.default = Some(Box::new(move || {
+                serde_json::to_value(default()).unwrap_or(Value::Null)
+            }));
+        }
+    }
+
+    /// Register a component type added by a plugin
+    pub fn register_plugin(&mut self, component: PluginComponent) {
+        if self
+            .types
+            .iter()
+            .any(|entry| normalize(entry.name) == normalize(component.name))
+        {
+            log::warn!(
+                "Plugin component '{}' is already registered",
+                component.name
+            );
+            return;
+        }
+        self.types.push(ComponentType {
+            name: component.name,
+            get: component.get,
+            set: component.set,
+            remove: component.remove,
+            default: component.default,
+        });
+    }
+
+    /// Look a type up by name, ignoring case and underscores ("rigid_body" finds RigidBody)
+    pub fn find(&self, name: &str) -> Result<&ComponentType> {
+        let key = normalize(name);
+        self.types
+            .iter()
+            .find(|entry| normalize(entry.name) == key)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown component type '{}' (known: {})",
+                    name,
+                    self.names().join(", ")
+                )
+            })
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.types.iter().map(|entry| entry.name).collect()
+    }
+
+    /// Names of the registered components the entity has
+    pub fn components_of(&self, entity: &Entity) -> Vec<&'static str> {
+        self.types
+            .iter()
+            .filter(|entry| entry.get(entity).is_some())
+            .map(|entry| entry.name)
+            .collect()
+    }
+}
+
+fn get_json<T: Component + Serialize>(entity: &Entity) -> Option<Value> {
+    let component = entity.get_component::<T>()?;
+    serde_json::to_value(component).ok()
+}
+
+fn set_json<T: Component + DeserializeOwned>(entity: &mut Entity, value: Value) -> Result<()> {
+    let component: T = serde_json::from_value(value)?;
+    match entity.get_component_mut::<T>() {
+        Some(existing) => *existing = component,
+        None => entity.add_component(component),
+    }
+    Ok(())
+}
+
+fn remove<T: Component>(entity: &mut Entity) -> bool {
+    entity.remove_component::<T>()
+}
+
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| *c != '_')
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Overlay `patch` onto `base`, recursing into objects present in both
+fn merge(base: &mut Value, patch: Value) {
+    match (base, patch) {
+        (Value::Object(base), Value::Object(patch)) => {
+            for (key, value) in patch {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, patch) => *base = patch,
+    }
+}
+
+/// The value at a dotted path of object keys and array indices, if any
+pub fn field<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(value, |current, segment| match current {
+            Value::Object(map) => map.get(segment),
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+/// The value at a dotted path of object keys and array indices
+fn field_mut<'a>(value: &'a mut Value, path: &str) -> Result<&'a mut Value> {
+    let mut current = value;
+    for segment in path.split('.').filter(|segment| !segment.is_empty()) {
+        current = match current {
+            Value::Object(map) => map
+                .get_mut(segment)
+                .ok_or_else(|| anyhow!("No field '{}' in '{}'", segment, path))?,
+            Value::Array(items) => {
+                let index: usize = segment
+                    .parse()
+                    .map_err(|_| anyhow!("'{}' in '{}' is not an index", segment, path))?;
+                items
+                    .get_mut(index)
+                    .ok_or_else(|| anyhow!("Index {} out of range in '{}'", index, path))?
+            }
+            _ => bail!(
+                "'{}' in '{}' is not inside an object or array",
+                segment,
+                path
+            ),
+        };
+    }
+    Ok(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_scene::entity::EntityId;
+    use serde_json::json;
+
+    #[test]
+    fn test_add_get_and_set_field() {
+        let registry = ComponentRegistry::with_engine_components();
+        let mut entity = Entity::new(EntityId(1), "Lamp".to_string());
+
+        let light = registry.find("light").unwrap();
+        light
+            .add(&mut entity, Some(json!({ "intensity": 3.0 })))
+            .unwrap();
+        assert_eq!(entity.get_component::<Light>().unwrap().intensity, 3.0);
+        assert!(light.add(&mut entity, None).is_err());
+
+        light
+            .set_field(&mut entity, "color", json!([1.0, 0.5, 0.0]))
+            .unwrap();
+        light
+            .set_field(&mut entity, "color.2", json!(0.25))
+            .unwrap();
+        assert_eq!(
+            entity.get_component::<Light>().unwrap().color,
+            [1.0, 0.5, 0.25]
+        );
+
+        // Wrong shapes are rejected and leave the component untouched
+        assert!(light.set_field(&mut entity, "color", json!("red")).is_err());
+        assert!(light.set_field(&mut entity, "missing", json!(1)).is_err());
+        assert_eq!(entity.get_component::<Light>().unwrap().intensity, 3.0);
+
+        assert_eq!(registry.components_of(&entity), vec!["Light"]);
+        assert!(light.remove(&mut entity));
+        assert!(light.get(&entity).is_none());
+    }
+
+    #[test]
+    fn test_field_filters() {
+        let light = json!({ "intensity": 3.0, "color": [1.0, 0.5, 0.0], "light_type": { "Point": { "range": 10.0 } } });
+        let filter = |filter| FieldFilter::from_json(&filter).unwrap().matches(&light);
+
+        assert!(filter(
+            json!({ "field": "intensity", "op": ">", "value": 2 })
+        ));
+        assert!(!filter(
+            json!({ "field": "intensity", "op": "<=", "value": 2.5 })
+        ));
+        assert!(filter(
+            json!({ "field": "light_type.Point.range", "value": 10 })
+        ));
+        assert!(filter(
+            json!({ "field": "color.1", "op": "!=", "value": 1.0 })
+        ));
+        assert!(filter(
+            json!({ "field": "color", "op": "contains", "value": 0 })
+        ));
+        // Missing fields and mismatched types never match
+        assert!(!filter(
+            json!({ "field": "light_type.Spot.range", "op": ">", "value": 0 })
+        ));
+        assert!(!filter(
+            json!({ "field": "intensity", "op": ">", "value": "2" })
+        ));
+        assert!(
+            FieldFilter::from_json(&json!({ "field": "intensity", "op": "~", "value": 1 }))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_lookup_and_required_fields() {
+        let registry = ComponentRegistry::with_engine_components();
+        assert_eq!(registry.find("rigid_body").unwrap().name, "RigidBody");
+        assert!(registry.find("Teapot").is_err());
+
+        // MeshRenderer has no defaults
+        let mut entity = Entity::new(EntityId(2), "Crate".to_string());
+        let mesh = registry.find("MeshRenderer").unwrap();
+        assert!(mesh.add(&mut entity, None).is_err());
+        mesh.add(
+            &mut entity,
+            Some(json!({ "mesh_path": "models/crate.glb", "material_path": null, "lightmap_path": null })),
+        )
+        .unwrap();
+        assert_eq!(
+            entity.get_component::<MeshRenderer>().unwrap().mesh_path,
+            "models/crate.glb"
+        );
+    }
+}
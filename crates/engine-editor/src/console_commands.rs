@@ -0,0 +1,329 @@
+// Console commands - the command line under the console panel
+//
+// A line is a command name followed by whitespace-separated arguments (`rhai`
+// takes the rest of the line verbatim). Parsing and Tab completion live here;
+// the editor applies parsed commands in its frame loop, where the scene,
+// renderer and script system are at hand.
+
+use glam::Vec3;
+use log::LevelFilter;
+
+use crate::ui::hierarchy::QuickEntityType;
+
+/// A built-in console command
+pub struct CommandInfo {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub help: &'static str,
+}
+
+/// Every built-in command, in the order `help` lists them
+pub const COMMANDS: &[CommandInfo] = &[
+    CommandInfo {
+        name: "help",
+        usage: "help [command]",
+        help: "List commands, or show how to use one",
+    },
+    CommandInfo {
+        name: "clear",
+        usage: "clear",
+        help: "Clear the console",
+    },
+    CommandInfo {
+        name: "spawn",
+        usage: "spawn <preset|model path> [x y z]",
+        help: "Create an entity (cube, sphere, point_light, ...) or place a model",
+    },
+    CommandInfo {
+        name: "teleport",
+        usage: "teleport <x y z | entity name>",
+        help: "Move the camera to a point or an entity",
+    },
+    CommandInfo {
+        name: "fps",
+        usage: "fps <cap|off>",
+        help: "Cap the editor frame rate",
+    },
+    CommandInfo {
+        name: "shadows",
+        usage: "shadows [on|off]",
+        help: "Toggle shadow rendering",
+    },
+    CommandInfo {
+        name: "log",
+        usage: "log [module] [off|error|warn|info|debug|trace]",
+        help: "Show or set the log level, for everything or one module",
+    },
+    CommandInfo {
+        name: "rhai",
+        usage: "rhai <snippet>",
+        help: "Evaluate a Rhai snippet and print the result",
+    },
+];
+
+/// Entities `spawn` creates by name (anything else is taken as a model path)
+const SPAWN_PRESETS: &[(&str, QuickEntityType)] = &[
+    ("empty", QuickEntityType::Empty),
+    ("cube", QuickEntityType::Cube),
+    ("sphere", QuickEntityType::Sphere),
+    ("point_light", QuickEntityType::PointLight),
+    ("directional_light", QuickEntityType::DirectionalLight),
+    ("camera", QuickEntityType::Camera),
+    ("particles", QuickEntityType::ParticleEmitter),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpawnTarget {
+    Preset(QuickEntityType),
+    Model(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TeleportTarget {
+    Position(Vec3),
+    /// First entity with this name
+    Entity(String),
+}
+
+/// A parsed console line
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConsoleCommand {
+    Help(Option<String>),
+    Clear,
+    Spawn {
+        target: SpawnTarget,
+        /// None = in front of the camera
+        position: Option<Vec3>,
+    },
+    Teleport(TeleportTarget),
+    /// Frames per second cap (None = uncapped)
+    FpsCap(Option<u32>),
+    /// Shadows on or off (None = toggle)
+    Shadows(Option<bool>),
+    /// Set (or with no level, show) the log level of a module (None = the default)
+    Log {
+        module: Option<String>,
+        level: Option<LevelFilter>,
+    },
+    Rhai(String),
+}
+
+pub fn find_command(name: &str) -> Option<&'static CommandInfo> {
+    COMMANDS.iter().find(|command| command.name == name)
+}
+
+/// Parse a console line. Errors are messages for the console.
+pub fn parse(line: &str) -> Result<ConsoleCommand, String> {
+    let line = line.trim();
+    let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let name = match name {
+        "tp" => "teleport",
+        "eval" => "rhai",
+        name => name,
+    };
+    let rest = rest.trim();
+    let args: Vec<&str> = rest.split_whitespace().collect();
+    let usage = || {
+        format!(
+            "Usage: {}",
+            find_command(name).map_or(name, |command| command.usage)
+        )
+    };
+
+    match name {
+        "help" => Ok(ConsoleCommand::Help(args.first().map(|s| s.to_string()))),
+        "clear" => Ok(ConsoleCommand::Clear),
+        "spawn" => {
+            let (&what, coords) = args.split_first().ok_or_else(usage)?;
+            let position = match coords {
+                [] => None,
+                _ => Some(parse_vec3(coords).ok_or_else(usage)?),
+            };
+            let target = SPAWN_PRESETS
+                .iter()
+                .find(|(preset, _)| preset.eq_ignore_ascii_case(what))
+                .map_or_else(
+                    || SpawnTarget::Model(what.to_string()),
+                    |&(_, kind)| SpawnTarget::Preset(kind),
+                );
+            Ok(ConsoleCommand::Spawn { target, position })
+        }
+        "teleport" => {
+            if args.is_empty() {
+                return Err(usage());
+            }
+            Ok(ConsoleCommand::Teleport(match parse_vec3(&args) {
+                Some(position) => TeleportTarget::Position(position),
+                None => TeleportTarget::Entity(rest.to_string()),
+            }))
+        }
+        "fps" => match args.as_slice() {
+            ["off"] | ["0"] => Ok(ConsoleCommand::FpsCap(None)),
+            [cap] => cap
+                .parse::<u32>()
+                .map(|cap| ConsoleCommand::FpsCap(Some(cap)))
+                .map_err(|_| usage()),
+            _ => Err(usage()),
+        },
+        "shadows" => match args.as_slice() {
+            [] | ["toggle"] => Ok(ConsoleCommand::Shadows(None)),
+            ["on"] => Ok(ConsoleCommand::Shadows(Some(true))),
+            ["off"] => Ok(ConsoleCommand::Shadows(Some(false))),
+            _ => Err(usage()),
+        },
+        "log" => {
+            let level = |level: &str| level.parse::<LevelFilter>().ok();
+            let (module, level) = match args.as_slice() {
+                [] => (None, None),
+                [only] => match level(only) {
+                    Some(level) => (None, Some(level)),
+                    None => (Some(only.to_string()), None),
+                },
+                [module, value] => (
+                    Some(module.to_string()),
+                    Some(level(value).ok_or_else(usage)?),
+                ),
+                _ => return Err(usage()),
+            };
+            Ok(ConsoleCommand::Log { module, level })
+        }
+        "rhai" => {
+            if rest.is_empty() {
+                return Err(usage());
+            }
+            Ok(ConsoleCommand::Rhai(rest.to_string()))
+        }
+        "" => Err("Type `help` for a list of commands".to_string()),
+        _ => Err(format!(
+            "Unknown command '{}' (type `help` for a list)",
+            name
+        )),
+    }
+}
+
+fn parse_vec3(args: &[&str]) -> Option<Vec3> {
+    let [x, y, z] = args else {
+        return None;
+    };
+    Some(Vec3::new(x.parse().ok()?, y.parse().ok()?, z.parse().ok()?))
+}
+
+/// Completed lines for the partial `line` (command names, then spawn presets
+/// and keyword arguments)
+pub fn complete(line: &str) -> Vec<String> {
+    let Some((name, arg)) = line.split_once(' ') else {
+        return COMMANDS
+            .iter()
+            .filter(|command| command.name.starts_with(line))
+            .map(|command| format!("{} ", command.name))
+            .collect();
+    };
+    let options: Vec<&str> = match name {
+        "spawn" => SPAWN_PRESETS.iter().map(|(preset, _)| *preset).collect(),
+        "shadows" => vec!["on", "off", "toggle"],
+        "fps" => vec!["off", "30", "60", "144"],
+        "log" => vec!["off", "error", "warn", "info", "debug", "trace"],
+        "help" => COMMANDS.iter().map(|command| command.name).collect(),
+        _ => Vec::new(),
+    };
+    if arg.contains(' ') {
+        return Vec::new();
+    }
+    options
+        .into_iter()
+        .filter(|option| option.starts_with(arg))
+        .map(|option| format!("{} {}", name, option))
+        .collect()
+}
+
+/// Longest prefix shared by all `lines`
+pub fn common_prefix(lines: &[String]) -> String {
+    let Some(first) = lines.first() else {
+        return String::new();
+    };
+    let mut prefix = first.as_str();
+    for line in &lines[1..] {
+        let shared = prefix
+            .char_indices()
+            .zip(line.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(prefix.len().min(line.len()), |((i, _), _)| i);
+        prefix = &prefix[..shared];
+    }
+    prefix.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(
+            parse("spawn cube 1 2 3"),
+            Ok(ConsoleCommand::Spawn {
+                target: SpawnTarget::Preset(QuickEntityType::Cube),
+                position: Some(Vec3::new(1.0, 2.0, 3.0)),
+            })
+        );
+        assert_eq!(
+            parse("spawn models/tree.glb"),
+            Ok(ConsoleCommand::Spawn {
+                target: SpawnTarget::Model("models/tree.glb".to_string()),
+                position: None,
+            })
+        );
+        assert!(parse("spawn cube 1 2").is_err());
+        assert_eq!(
+            parse("tp  Main Camera "),
+            Ok(ConsoleCommand::Teleport(TeleportTarget::Entity(
+                "Main Camera".to_string()
+            )))
+        );
+        assert_eq!(
+            parse("teleport 0 10 -5"),
+            Ok(ConsoleCommand::Teleport(TeleportTarget::Position(
+                Vec3::new(0.0, 10.0, -5.0)
+            )))
+        );
+        assert_eq!(parse("fps 60"), Ok(ConsoleCommand::FpsCap(Some(60))));
+        assert_eq!(parse("fps off"), Ok(ConsoleCommand::FpsCap(None)));
+        assert_eq!(parse("shadows"), Ok(ConsoleCommand::Shadows(None)));
+        assert_eq!(
+            parse("log engine_net debug"),
+            Ok(ConsoleCommand::Log {
+                module: Some("engine_net".to_string()),
+                level: Some(LevelFilter::Debug),
+            })
+        );
+        assert_eq!(
+            parse("log warn"),
+            Ok(ConsoleCommand::Log {
+                module: None,
+                level: Some(LevelFilter::Warn),
+            })
+        );
+        assert!(parse("log engine_net loud").is_err());
+        assert_eq!(
+            parse("rhai let x = 2;  x * 21"),
+            Ok(ConsoleCommand::Rhai("let x = 2;  x * 21".to_string()))
+        );
+        assert!(parse("rhai").unwrap_err().contains("Usage"));
+        assert!(parse("frobnicate").unwrap_err().contains("Unknown"));
+    }
+
+    #[test]
+    fn test_completion() {
+        assert_eq!(complete("sh"), vec!["shadows ".to_string()]);
+        assert_eq!(complete("spawn po"), vec!["spawn point_light".to_string()]);
+        assert_eq!(complete("spawn cube 1"), Vec::<String>::new());
+
+        let candidates = complete("s");
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(common_prefix(&candidates), "s");
+        assert_eq!(
+            common_prefix(&["spawn cube".to_string(), "spawn camera".to_string()]),
+            "spawn c"
+        );
+    }
+}
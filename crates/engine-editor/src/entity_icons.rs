@@ -0,0 +1,353 @@
+// Entity icons - viewport markers for entities without a visible mesh
+//
+// Lights, cameras, audio sources and particle emitters get a camera-facing
+// icon drawn over the viewport, and the icons take part in click picking.
+// Selected entities also show their light range, audio range or camera
+// frustum as a wireframe. Everything is projected to screen space here and
+// painted by the editor UI.
+
+use engine_render::camera::Camera;
+use engine_scene::{
+    components::{AudioSource, Camera as CameraComponent, Light, LightType, ParticleEmitter},
+    entity::{Entity, EntityId},
+    scene::Scene,
+};
+use glam::{Mat4, Quat, Vec2, Vec3};
+
+use crate::water_edit::project_to_screen;
+
+/// Clicks within this distance (pixels) of an icon select its entity
+pub const ICON_PICK_RADIUS: f32 = 14.0;
+/// Camera frustums are drawn at most this deep so long far planes don't fill the view
+const FRUSTUM_DISPLAY_DEPTH: f32 = 4.0;
+/// Directional lights show an arrow this long along their direction
+const DIRECTION_ARROW_LENGTH: f32 = 2.0;
+const CIRCLE_SEGMENTS: usize = 32;
+
+/// What an icon stands for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconKind {
+    Light,
+    Camera,
+    Audio,
+    Particles,
+}
+
+impl IconKind {
+    pub fn glyph(&self) -> &'static str {
+        match self {
+            IconKind::Light => "💡",
+            IconKind::Camera => "📷",
+            IconKind::Audio => "🔊",
+            IconKind::Particles => "✨",
+        }
+    }
+
+    /// Icon and wireframe color (RGB)
+    pub fn color(&self) -> [u8; 3] {
+        match self {
+            IconKind::Light => [255, 220, 110],
+            IconKind::Camera => [170, 200, 255],
+            IconKind::Audio => [140, 230, 160],
+            IconKind::Particles => [255, 160, 220],
+        }
+    }
+
+    /// The icon an entity gets, if any (cameras first, then lights, audio, particles)
+    pub fn for_entity(entity: &Entity) -> Option<IconKind> {
+        if entity.has_component::<CameraComponent>() {
+            Some(IconKind::Camera)
+        } else if entity.has_component::<Light>() {
+            Some(IconKind::Light)
+        } else if entity.has_component::<AudioSource>() {
+            Some(IconKind::Audio)
+        } else if entity.has_component::<ParticleEmitter>() {
+            Some(IconKind::Particles)
+        } else {
+            None
+        }
+    }
+}
+
+/// An icon projected into the viewport
+#[derive(Debug, Clone)]
+pub struct EntityIcon {
+    pub entity: EntityId,
+    pub kind: IconKind,
+    /// Screen position in pixels
+    pub position: Vec2,
+    /// Distance from the viewport camera
+    pub distance: f32,
+    pub selected: bool,
+}
+
+/// Screen-space (pixel) icons and wireframes drawn over the viewport
+#[derive(Debug, Clone, Default)]
+pub struct IconOverlay {
+    /// Icons sorted far to near, so nearer icons are painted on top
+    pub icons: Vec<EntityIcon>,
+    /// Light range, audio range and camera frustum lines with their color
+    pub wires: Vec<(Vec<Vec2>, IconKind)>,
+}
+
+/// Build the icon overlay for every visible entity with an icon.
+/// Wireframes are only built for selected entities.
+pub fn build_overlay(
+    scene: &Scene,
+    camera: &Camera,
+    screen_size: Vec2,
+    is_visible: impl Fn(EntityId) -> bool,
+    is_selected: impl Fn(EntityId) -> bool,
+) -> IconOverlay {
+    let view_proj = camera.view_projection_matrix();
+    let mut overlay = IconOverlay::default();
+
+    for entity in scene.entities() {
+        let Some(kind) = IconKind::for_entity(entity).filter(|_| is_visible(entity.id)) else {
+            continue;
+        };
+        let (_, rotation, world_position) = scene
+            .world_matrix(entity.id)
+            .to_scale_rotation_translation();
+        let selected = is_selected(entity.id);
+
+        if let Some(position) = project_to_screen(view_proj, world_position, screen_size) {
+            overlay.icons.push(EntityIcon {
+                entity: entity.id,
+                kind,
+                position,
+                distance: world_position.distance(camera.position),
+                selected,
+            });
+        }
+
+        if selected {
+            let wires = entity_wires(entity, world_position, rotation, camera.aspect);
+            for (points, wire_kind) in wires {
+                for line in project_polyline(view_proj, &points, screen_size) {
+                    overlay.wires.push((line, wire_kind));
+                }
+            }
+        }
+    }
+
+    overlay
+        .icons
+        .sort_by(|a, b| b.distance.total_cmp(&a.distance));
+    overlay
+}
+
+/// The entity whose icon is under `point` (pixels), nearest to the camera first
+pub fn pick_icon(overlay: &IconOverlay, point: Vec2) -> Option<EntityId> {
+    overlay
+        .icons
+        .iter()
+        .rev()
+        .find(|icon| icon.position.distance(point) <= ICON_PICK_RADIUS)
+        .map(|icon| icon.entity)
+}
+
+/// World-space wireframe polylines for an entity's components
+fn entity_wires(
+    entity: &Entity,
+    position: Vec3,
+    rotation: Quat,
+    aspect: f32,
+) -> Vec<(Vec<Vec3>, IconKind)> {
+    let mut wires = Vec::new();
+
+    if let Some(lens) = entity.get_component::<CameraComponent>() {
+        for line in frustum_lines(position, rotation, lens, aspect) {
+            wires.push((line, IconKind::Camera));
+        }
+    }
+
+    if let Some(light) = entity.get_component::<Light>() {
+        let lines = match light.light_type {
+            LightType::Point { range, .. } => sphere_lines(position, range),
+            LightType::Spot {
+                direction,
+                angle,
+                range,
+            } => cone_lines(
+                position,
+                Vec3::from(direction),
+                (angle * 0.5).to_radians(),
+                range,
+            ),
+            LightType::Directional { direction } => {
+                let direction = Vec3::from(direction).normalize_or_zero();
+                vec![vec![
+                    position,
+                    position + direction * DIRECTION_ARROW_LENGTH,
+                ]]
+            }
+        };
+        for line in lines {
+            wires.push((line, IconKind::Light));
+        }
+    }
+
+    if let Some(source) = entity.get_component::<AudioSource>() {
+        wires.push((
+            circle(position, Vec3::X, Vec3::Z, source.max_distance),
+            IconKind::Audio,
+        ));
+    }
+
+    wires
+}
+
+/// Closed circle around `center` in the plane spanned by `u` and `v`
+fn circle(center: Vec3, u: Vec3, v: Vec3, radius: f32) -> Vec<Vec3> {
+    (0..=CIRCLE_SEGMENTS)
+        .map(|i| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            center + (u * angle.cos() + v * angle.sin()) * radius
+        })
+        .collect()
+}
+
+/// Three axis circles outlining a sphere
+fn sphere_lines(center: Vec3, radius: f32) -> Vec<Vec<Vec3>> {
+    vec![
+        circle(center, Vec3::X, Vec3::Y, radius),
+        circle(center, Vec3::X, Vec3::Z, radius),
+        circle(center, Vec3::Y, Vec3::Z, radius),
+    ]
+}
+
+/// Spot light cone (half angle in radians): the base circle and four edges from the apex
+fn cone_lines(apex: Vec3, direction: Vec3, half_angle: f32, range: f32) -> Vec<Vec<Vec3>> {
+    let direction = direction.normalize_or(Vec3::NEG_Y);
+    let (u, v) = direction.any_orthonormal_pair();
+    let base = apex + direction * range;
+    let radius = range * half_angle.tan();
+
+    let mut lines = vec![circle(base, u, v, radius)];
+    for edge in [u, -u, v, -v] {
+        lines.push(vec![apex, base + edge * radius]);
+    }
+    lines
+}
+
+/// Camera frustum from the near plane to a capped far plane.
+/// Cameras look down -Z in local space.
+fn frustum_lines(
+    position: Vec3,
+    rotation: Quat,
+    lens: &CameraComponent,
+    aspect: f32,
+) -> Vec<Vec<Vec3>> {
+    let to_world = Mat4::from_rotation_translation(rotation, position);
+    let rect = |depth: f32| -> [Vec3; 4] {
+        let half_height = (lens.fov * 0.5).tan() * depth;
+        let half_width = half_height * aspect;
+        [
+            Vec3::new(-half_width, -half_height, -depth),
+            Vec3::new(half_width, -half_height, -depth),
+            Vec3::new(half_width, half_height, -depth),
+            Vec3::new(-half_width, half_height, -depth),
+        ]
+        .map(|corner| to_world.transform_point3(corner))
+    };
+    let near = rect(lens.near);
+    let far = rect(lens.far.min(FRUSTUM_DISPLAY_DEPTH).max(lens.near));
+
+    let mut lines = vec![
+        vec![near[0], near[1], near[2], near[3], near[0]],
+        vec![far[0], far[1], far[2], far[3], far[0]],
+    ];
+    for (n, f) in near.into_iter().zip(far) {
+        lines.push(vec![n, f]);
+    }
+    lines
+}
+
+/// Project a polyline, splitting it where points fall behind the camera
+fn project_polyline(view_proj: Mat4, points: &[Vec3], screen_size: Vec2) -> Vec<Vec<Vec2>> {
+    let mut lines = Vec::new();
+    let mut current = Vec::new();
+    for &point in points {
+        match project_to_screen(view_proj, point, screen_size) {
+            Some(screen) => current.push(screen),
+            None => {
+                if current.len() > 1 {
+                    lines.push(std::mem::take(&mut current));
+                }
+                current.clear();
+            }
+        }
+    }
+    if current.len() > 1 {
+        lines.push(current);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_scene::components::MeshRenderer;
+    use engine_scene::transform::Transform;
+
+    fn test_camera() -> Camera {
+        let mut camera = Camera::new(800, 600);
+        camera.position = Vec3::new(0.0, 0.0, 10.0);
+        camera.target = Vec3::ZERO;
+        camera
+    }
+
+    #[test]
+    fn test_overlay_has_icons_for_non_mesh_components() {
+        let mut scene = Scene::new("Test".to_string());
+        let lamp = scene.create_entity("Lamp".to_string());
+        scene
+            .get_entity_mut(lamp)
+            .unwrap()
+            .add_component(Light::point([1.0, 1.0, 1.0], 1.0, 5.0));
+        let cube = scene.create_entity("Cube".to_string());
+        scene
+            .get_entity_mut(cube)
+            .unwrap()
+            .add_component(MeshRenderer::new("cube".to_string()));
+
+        let screen = Vec2::new(800.0, 600.0);
+        let overlay = build_overlay(&scene, &test_camera(), screen, |_| true, |_| false);
+        assert_eq!(overlay.icons.len(), 1);
+        assert_eq!(overlay.icons[0].kind, IconKind::Light);
+        assert!(overlay.icons[0].position.distance(screen * 0.5) < 1.0);
+        assert!(overlay.wires.is_empty());
+
+        // Selecting the light adds its range wireframe
+        let overlay = build_overlay(&scene, &test_camera(), screen, |_| true, |id| id == lamp);
+        assert_eq!(overlay.wires.len(), 3);
+
+        // Hidden entities get no icon
+        let overlay = build_overlay(&scene, &test_camera(), screen, |id| id != lamp, |_| false);
+        assert!(overlay.icons.is_empty());
+    }
+
+    #[test]
+    fn test_pick_icon_prefers_nearest() {
+        let mut scene = Scene::new("Test".to_string());
+        let far = scene.create_entity_with_transform(
+            "Far Camera".to_string(),
+            Transform::from_position(Vec3::new(0.0, 0.0, -5.0)),
+        );
+        let near = scene.create_entity("Speaker".to_string());
+        scene
+            .get_entity_mut(far)
+            .unwrap()
+            .add_component(CameraComponent::default());
+        scene
+            .get_entity_mut(near)
+            .unwrap()
+            .add_component(AudioSource::new("music.ogg".to_string()));
+
+        let screen = Vec2::new(800.0, 600.0);
+        let overlay = build_overlay(&scene, &test_camera(), screen, |_| true, |_| false);
+        assert_eq!(pick_icon(&overlay, screen * 0.5), Some(near));
+        assert_eq!(pick_icon(&overlay, Vec2::ZERO), None);
+    }
+}
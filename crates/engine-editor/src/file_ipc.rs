@@ -1,86 +1,403 @@
-// File-based IPC handler for MCP server communication
+// IPC handler for MCP server communication
+//
+// Commands arrive over the socket transport in `engine_core::ipc`; each poll
+// executes what has arrived against the scene and answers on the same
+// connection.
 
-use anyhow::Result;
-use serde::{Deserialize, Serialize};
+use anyhow::{anyhow, Result};
 use serde_json::{json, Value};
-use std::fs;
-use std::path::PathBuf;
+use engine_core::ipc::{ConnectionId, IpcListener, IpcResponse};
+use engine_scene::entity::{Entity, EntityId};
 use engine_scene::Scene;
-use engine_scene::components::MeshRenderer;
-use engine_scripting::Script;
+use engine_scene::components::{Light, LightType, MeshRenderer, TerrainGenerator};
+use engine_scripting::{AudioCommand, Script};
 use engine_physics::{RigidBody, RigidBodyType, Collider, ColliderShape};
 use engine_ai_assets::{AssetGenerator, AssetCache, TextureGenerationRequest, LocalClient, AiAssetConfig};
+use engine_ai_music::{AceStepClient, AceStepConfig, MusicGenerationRequest, MusicStyle};
+use engine_plugin::{PluginRegistry, PluginTool};
 use glam::Vec3;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use engine_assets::{AssetManager, TerrainChunk};
+use engine_render::mesh_manager::MeshManager;
+
+use crate::asset_tools;
+use crate::component_registry::{ComponentRegistry, FieldFilter};
+use crate::generation_jobs::{GenerationJobs, JobKind};
+use crate::material_tools;
+use crate::picking::MeshPicker;
+use crate::resources::{self, ResourceRoots};
+use crate::play_mode::{fixed_dt, PlaySession, PlayState};
+use crate::placement::terrain_ref;
+use crate::selection::{is_ancestor, set_parent_keep_world};
+use crate::spatial_tools::{self, DEFAULT_MAX_DISTANCE};
+use crate::terrain_tools::{self, TerrainData};
+use crate::ui::viewport::ViewportControls;
+use crate::undo::{Checkpoint, UndoHistory, UndoTarget};
+
+/// Commands execute_batch accepts: the ones that only touch the scene, so a
+/// failed batch can be rolled back completely
+const BATCH_COMMANDS: &[&str] = &[
+    "create_entity",
+    "delete_entity",
+    "set_transform",
+    "add_script",
+    "load_model",
+    "add_rigidbody",
+    "add_collider",
+    "add_light",
+    "add_component",
+    "remove_component",
+    "set_component_field",
+    "list_entities",
+    "get_scene_info",
+    "get_entity_info",
+    "get_component",
+    "raycast",
+    "overlap_sphere",
+    "find_entities_near",
+    "find_entities_by_component",
+    "set_parent",
+    "get_children",
+    "get_scene_tree",
+];
+
+/// Commands that leave the scene as it is
+const READ_ONLY_COMMANDS: &[&str] = &[
+    "list_entities",
+    "get_scene_info",
+    "get_entity_info",
+    "get_component",
+    "raycast",
+    "overlap_sphere",
+    "find_entities_near",
+    "find_entities_by_component",
+    "get_children",
+    "get_scene_tree",
+];
+
+/// Longest step_simulation, in frames
+const MAX_STEP_FRAMES: u32 = 1800;
+
+/// Closest set_editor_camera gets when framing an entity
+const CAMERA_MIN_FRAME_DISTANCE: f32 = 3.0;
+
+/// Most entities a spatial query lists unless asked for more
+const DEFAULT_MAX_RESULTS: usize = 50;
+
+/// Most commands in one execute_batch
+const MAX_BATCH_COMMANDS: usize = 1000;
+
+/// Editor state besides the scene that commands use
+pub struct CommandContext<'a> {
+    pub asset_manager: &'a mut AssetManager,
+    /// Meshes uploaded for rendering (built-in, generated and loaded glTF)
+    pub meshes: &'a MeshManager,
+    pub mesh_picker: &'a mut MeshPicker,
+    pub terrain: TerrainData<'a>,
+    pub viewport: &'a mut ViewportControls,
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct IpcCommand {
-    pub id: u64,
-    pub command: String,
-    pub args: Value,
+/// Play mode command from the MCP server. These need the whole editor, so
+/// they are run at the start of the next frame rather than during the poll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimulationCommand {
+    Play,
+    Pause,
+    Stop,
+    /// Run this many frames, then pause (answered once they have run)
+    Step { frames: u32 },
+    State,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct IpcResponse {
-    pub id: u64,
-    pub success: bool,
-    pub result: Value,
+/// A simulation command waiting to be run, and who to answer
+pub struct SimulationRequest {
+    connection: ConnectionId,
+    id: u64,
+    pub command: SimulationCommand,
 }
 
-pub struct FileIpcHandler {
-    command_file: PathBuf,
-    response_file: PathBuf,
+pub struct McpIpcHandler {
+    listener: IpcListener,
+    components: ComponentRegistry,
+    /// MCP tools added by plugins (list_plugin_tools, call_plugin_tool)
+    plugin_tools: Vec<PluginTool>,
+    /// Material files written since the last `take_changed_materials`
+    changed_materials: Vec<String>,
+    /// The terrain heightmap was regenerated or sculpted
+    terrain_changed: bool,
+    /// capture_viewport requests waiting for the screenshot: (connection, request id)
+    pending_captures: Vec<(ConnectionId, u64)>,
+    capture_requested: bool,
+    /// set_editor_camera moved the orbit camera
+    camera_moved: bool,
+    /// Simulation commands for the editor to run next frame
+    simulation_requests: Vec<SimulationRequest>,
+    /// step_simulation requests waiting for their frames to run
+    pending_steps: Vec<SimulationRequest>,
+    /// Named points in the undo history (create_checkpoint)
+    checkpoints: BTreeMap<String, Checkpoint>,
+    /// undo, redo or restore_checkpoint changed the scene
+    history_changed: bool,
+    /// Script files attached since the last `take_attached_scripts`
+    attached_scripts: Vec<(EntityId, PathBuf)>,
+    /// play_music and stop_music commands for the editor's audio system
+    audio_commands: Vec<AudioCommand>,
+    /// Texture, skybox and music generation running in the background
+    generation_jobs: GenerationJobs,
+    /// Resource URIs the MCP server subscribed to
+    subscribed_resources: BTreeSet<String>,
+    /// Subscribed resources that changed since the last take_resource_updates
+    updated_resources: BTreeSet<String>,
 }
 
-impl FileIpcHandler {
-    pub fn new() -> Self {
-        let tmp_dir = std::env::temp_dir();
-        Self {
-            command_file: tmp_dir.join("game-engine-mcp-command.json"),
-            response_file: tmp_dir.join("game-engine-mcp-response.json"),
-        }
+impl McpIpcHandler {
+    /// Listen for the MCP server on `address`
+    pub fn bind(address: &str) -> Result<Self> {
+        let listener = IpcListener::bind(address)?;
+        log::info!("Listening for MCP server on {}", address);
+        Ok(Self {
+            listener,
+            components: ComponentRegistry::with_engine_components(),
+            plugin_tools: Vec::new(),
+            changed_materials: Vec::new(),
+            terrain_changed: false,
+            pending_captures: Vec::new(),
+            capture_requested: false,
+            camera_moved: false,
+            simulation_requests: Vec::new(),
+            pending_steps: Vec::new(),
+            checkpoints: BTreeMap::new(),
+            history_changed: false,
+            attached_scripts: Vec::new(),
+            audio_commands: Vec::new(),
+            generation_jobs: GenerationJobs::new(),
+            subscribed_resources: BTreeSet::new(),
+            updated_resources: BTreeSet::new(),
+        })
     }
 
-    /// Check for incoming commands (non-blocking)
-    pub fn poll_commands(&mut self, scene: &mut Scene) -> Result<()> {
-        if !self.command_file.exists() {
-            return Ok(());
+    /// Take the plugins' components and MCP tools
+    pub fn add_plugins(&mut self, plugins: &mut PluginRegistry) {
+        for component in plugins.components.drain(..) {
+            self.components.register_plugin(component);
         }
+        self.plugin_tools.append(&mut plugins.tools);
+    }
 
-        // Read command
-        let command_json = match fs::read_to_string(&self.command_file) {
-            Ok(json) => json,
-            Err(_) => return Ok(()), // File might be being written
-        };
+    /// Execute the commands that have arrived (non-blocking). Each command
+    /// that edits the scene becomes one undo step.
+    pub fn poll_commands(
+        &mut self,
+        scene: &mut Scene,
+        mut context: CommandContext,
+        undo: &mut UndoHistory,
+    ) -> Result<()> {
+        for (connection, command) in self.listener.poll() {
+            log::info!("Processing IPC command: {}", command.command);
+
+            // Screenshots take a few frames; the response is sent from finish_capture
+            if command.command == "capture_viewport" {
+                self.capture_requested |= self.pending_captures.is_empty();
+                self.pending_captures.push((connection, command.id));
+                continue;
+            }
 
-        let command: IpcCommand = match serde_json::from_str(&command_json) {
-            Ok(cmd) => cmd,
-            Err(e) => {
-                log::error!("Failed to parse IPC command: {}", e);
-                return Ok(());
+            if let Some(simulation) = simulation_command(&command.command, &command.args) {
+                match simulation {
+                    Ok(simulation) => self.simulation_requests.push(SimulationRequest {
+                        connection,
+                        id: command.id,
+                        command: simulation,
+                    }),
+                    Err(e) => {
+                        let response = IpcResponse::error(command.id, e.to_string());
+                        if let Err(e) = self.listener.respond(connection, &response) {
+                            log::warn!("Failed to send IPC response: {}", e);
+                        }
+                    }
+                }
+                continue;
             }
-        };
 
-        // Remove command file
-        let _ = fs::remove_file(&self.command_file);
+            // Commands that step through the undo history itself
+            if matches!(
+                command.command.as_str(),
+                "undo" | "redo" | "create_checkpoint" | "restore_checkpoint"
+            ) {
+                let response = match self.execute_history_command(
+                    &command.command,
+                    &command.args,
+                    scene,
+                    &mut context.terrain,
+                    undo,
+                ) {
+                    Ok(result) => IpcResponse::ok(command.id, result),
+                    Err(e) => IpcResponse::error(command.id, e.to_string()),
+                };
+                if let Err(e) = self.listener.respond(connection, &response) {
+                    log::warn!("Failed to send IPC response: {}", e);
+                }
+                continue;
+            }
 
-        log::info!("Processing IPC command: {}", command.command);
+            // Terrain commands also change the heightmap: a terrain step
+            // covering the whole map, like the brush panel's erosion, joined
+            // with the entity step into one
+            let terrain_command = matches!(command.command.as_str(), "sculpt_terrain" | "generate_terrain");
+            let start = terrain_command.then(|| undo.checkpoint(scene));
+            let terrain_size = context
+                .terrain
+                .heightmap
+                .as_ref()
+                .filter(|_| terrain_command)
+                .map(|heightmap| {
+                    undo.begin_terrain_stroke(scene);
+                    let bounds = (0, 0, heightmap.width - 1, heightmap.depth - 1);
+                    undo.track_terrain_region(heightmap, context.terrain.splatmap.as_ref(), bounds);
+                    (heightmap.width, heightmap.depth)
+                });
+
+            let records_undo = edits_scene(&command.command, &command.args);
+            if records_undo {
+                let ids: Vec<EntityId> = scene.entities().map(|e| e.id).collect();
+                undo.record_entities(scene, &ids);
+            }
 
-        // Execute command
-        let response = self.execute_command(command.id, &command.command, command.args, scene);
+            let response = self.execute_command(
+                command.id,
+                &command.command,
+                command.args,
+                scene,
+                &mut context,
+            );
+
+            if records_undo {
+                if response.success {
+                    undo.commit(scene);
+                } else {
+                    undo.cancel();
+                }
+            }
+            if let Some(size) = terrain_size {
+                match context.terrain.heightmap.as_ref() {
+                    Some(heightmap) if (heightmap.width, heightmap.depth) == size => {
+                        undo.end_terrain_stroke(heightmap, context.terrain.splatmap.as_ref());
+                    }
+                    // A terrain of another size: the saved tiles no longer fit
+                    _ => undo.forget_terrain(),
+                }
+            }
+            if let Some(start) = start {
+                undo.merge_since(scene, start);
+            }
 
-        // Write response
-        let response_json = serde_json::to_string(&response)?;
-        fs::write(&self.response_file, response_json)?;
+            if let Err(e) = self.listener.respond(connection, &response) {
+                log::warn!("Failed to send IPC response: {}", e);
+            }
+        }
 
         Ok(())
     }
 
+    /// Material paths (relative to the asset root) rewritten by commands;
+    /// cached copies of these need reloading
+    pub fn take_changed_materials(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.changed_materials)
+    }
+
+    /// Whether a capture_viewport request is waiting for a screenshot to be started
+    pub fn take_capture_request(&mut self) -> bool {
+        std::mem::take(&mut self.capture_requested)
+    }
+
+    /// Answer the waiting capture_viewport requests with the saved screenshot
+    pub fn finish_capture(&mut self, result: std::result::Result<&Path, String>) {
+        for (connection, id) in std::mem::take(&mut self.pending_captures) {
+            let response = match &result {
+                Ok(path) => IpcResponse::ok(id, json!({ "path": path.display().to_string() })),
+                Err(e) => IpcResponse::error(id, format!("Capture failed: {}", e)),
+            };
+            if let Err(e) = self.listener.respond(connection, &response) {
+                log::warn!("Failed to send IPC response: {}", e);
+            }
+        }
+    }
+
+    /// Simulation commands received since the last call, to run in order
+    pub fn take_simulation_requests(&mut self) -> Vec<SimulationRequest> {
+        std::mem::take(&mut self.simulation_requests)
+    }
+
+    /// Answer a simulation command with the simulation state
+    pub fn answer_simulation_request(&mut self, request: SimulationRequest, state: Value) {
+        let response = IpcResponse::ok(request.id, state);
+        if let Err(e) = self.listener.respond(request.connection, &response) {
+            log::warn!("Failed to send IPC response: {}", e);
+        }
+    }
+
+    /// Hold a step_simulation request until `finish_simulation_step`
+    pub fn wait_for_step(&mut self, request: SimulationRequest) {
+        self.pending_steps.push(request);
+    }
+
+    /// Answer the waiting step_simulation requests with the simulation state
+    pub fn finish_simulation_step(&mut self, state: Value) {
+        for request in std::mem::take(&mut self.pending_steps) {
+            self.answer_simulation_request(request, state.clone());
+        }
+    }
+
+    /// Whether the terrain heightmap changed since the last call; its mesh
+    /// and water need rebuilding
+    pub fn take_terrain_changed(&mut self) -> bool {
+        std::mem::take(&mut self.terrain_changed)
+    }
+
+    /// Script files attach_script_file attached (entity, path relative to the
+    /// working directory), to reload when the file changes
+    pub fn take_attached_scripts(&mut self) -> Vec<(EntityId, PathBuf)> {
+        std::mem::take(&mut self.attached_scripts)
+    }
+
+    /// Music commands play_music and stop_music queued since the last call
+    pub fn take_audio_commands(&mut self) -> Vec<AudioCommand> {
+        std::mem::take(&mut self.audio_commands)
+    }
+
+    /// Note a file the hot-reload watcher saw change, for resource subscribers
+    pub fn resource_changed(&mut self, asset_root: &Path, file: &Path) {
+        if self.subscribed_resources.is_empty() {
+            return;
+        }
+        if let Some(uri) = resources::resource_uri(&ResourceRoots::new(asset_root), file) {
+            if self.subscribed_resources.contains(&uri) {
+                self.updated_resources.insert(uri);
+            }
+        }
+    }
+
+    /// Whether undo, redo or restore_checkpoint changed the scene since the
+    /// last call (entity selection may be stale)
+    pub fn take_history_changed(&mut self) -> bool {
+        std::mem::take(&mut self.history_changed)
+    }
+
+    /// Whether set_editor_camera moved the orbit camera since the last call
+    pub fn take_camera_moved(&mut self) -> bool {
+        std::mem::take(&mut self.camera_moved)
+    }
+
     fn execute_command(
-        &self,
+        &mut self,
         id: u64,
         command: &str,
         args: Value,
         scene: &mut Scene,
+        context: &mut CommandContext,
     ) -> IpcResponse {
         match command {
             "create_entity" => {
@@ -160,12 +477,12 @@ impl FileIpcHandler {
                     .find(|e| e.name == name)
                     .map(|e| e.id);
 
-                if let Some(id) = entity_id {
-                    scene.remove_entity(id);
+                if let Some(entity_id) = entity_id {
+                    scene.remove_entity(entity_id);
                     log::info!("Deleted entity '{}'", name);
 
                     IpcResponse {
-                        id: id.0,
+                        id,
                         success: true,
                         result: json!({
                             "deleted": true,
@@ -174,7 +491,7 @@ impl FileIpcHandler {
                     }
                 } else {
                     IpcResponse {
-                        id: id.try_into().unwrap_or(0),
+                        id,
                         success: false,
                         result: json!({
                             "error": format!("Entity '{}' not found", name)
@@ -265,6 +582,7 @@ impl FileIpcHandler {
                                 "position": [entity.transform.position.x, entity.transform.position.y, entity.transform.position.z],
                                 "rotation": [entity.transform.rotation.x, entity.transform.rotation.y, entity.transform.rotation.z, entity.transform.rotation.w],
                                 "scale": [entity.transform.scale.x, entity.transform.scale.y, entity.transform.scale.z],
+                                "components": self.components.components_of(entity),
                             }),
                         }
                     } else {
@@ -303,6 +621,15 @@ impl FileIpcHandler {
                     .find(|e| e.name == entity_name)
                     .map(|e| e.id);
 
+                // Refuse scripts that don't compile, pointing at the error
+                let functions = match engine_scripting::check_script(script_source) {
+                    Ok(functions) => functions,
+                    Err(diagnostic) => {
+                        log::warn!("Script for entity '{}' does not compile: {}", entity_name, diagnostic);
+                        return diagnostic_response(id, &diagnostic);
+                    }
+                };
+
                 if let Some(entity_id) = entity_id_opt {
                     if let Some(entity) = scene.get_entity_mut(entity_id) {
                         // Create and attach script component
@@ -317,7 +644,8 @@ impl FileIpcHandler {
                             result: json!({
                                 "script_added": true,
                                 "entity_name": entity_name,
-                                "script_size": script_source.len()
+                                "script_size": script_source.len(),
+                                "functions": functions
                             }),
                         }
                     } else {
@@ -370,10 +698,7 @@ impl FileIpcHandler {
                     entity.transform.position = position;
 
                     // Add mesh renderer component
-                    let mesh_renderer = MeshRenderer {
-                        mesh_path: model_path.to_string(),
-                        material_path: None,
-                    };
+                    let mesh_renderer = MeshRenderer::new(model_path.to_string());
                     entity.add_component(mesh_renderer);
 
                     log::info!("Loaded model '{}' as entity '{}' at position {:?}",
@@ -493,14 +818,40 @@ impl FileIpcHandler {
 
                         let radius = args.get("radius").and_then(|v| v.as_f64()).unwrap_or(0.5) as f32;
 
-                        // Create collider based on type
+                        // Create collider based on type. Mesh shapes reference the entity's
+                        // MeshRenderer mesh, heightfields the terrain; both are built when
+                        // physics is set up.
+                        let mesh_path = match shape_type {
+                            "trimesh" | "convex" => entity
+                                .get_component::<MeshRenderer>()
+                                .map(|m| m.mesh_path.clone())
+                                .filter(|path| crate::lighting::cpu_mesh(context.asset_manager, path).is_some()),
+                            _ => None,
+                        };
                         let collider = match shape_type {
-                            "sphere" => Collider::sphere(radius),
+                            "sphere" => Some(Collider::sphere(radius)),
                             "capsule" => {
                                 let height = args.get("height").and_then(|v| v.as_f64()).unwrap_or(1.0) as f32;
-                                Collider::capsule(height / 2.0, radius)
+                                Some(Collider::capsule(height / 2.0, radius))
                             },
-                            "box" | _ => Collider::box_collider(size),
+                            "trimesh" => mesh_path.map(Collider::trimesh),
+                            "convex" => mesh_path.map(Collider::convex_decomposition),
+                            "heightfield" => terrain_ref(context.terrain.heightmap, context.terrain.config)
+                                .map(|_| Collider::heightfield()),
+                            _ => Some(Collider::box_collider(size)),
+                        };
+                        let Some(collider) = collider else {
+                            let needs = if shape_type == "heightfield" { "terrain" } else { "a mesh with triangles" };
+                            return IpcResponse {
+                                id,
+                                success: false,
+                                result: json!({
+                                    "error": format!(
+                                        "A {} collider needs {}, which '{}' doesn't have",
+                                        shape_type, needs, entity_name
+                                    )
+                                }),
+                            };
                         };
 
                         entity.add_component(collider);
@@ -673,6 +1024,172 @@ impl FileIpcHandler {
                     }
                 }
             }
+            "add_light" => {
+                let entity_name = args
+                    .get("entity_name")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("");
+
+                // Lights go on an existing entity, or a new one created for them
+                let existing = scene
+                    .entities()
+                    .find(|e| e.name == entity_name)
+                    .map(|e| e.id);
+                let created = existing.is_none();
+                let entity_id = existing.unwrap_or_else(|| {
+                    let name = if entity_name.is_empty() { "Light" } else { entity_name };
+                    scene.create_entity(name.to_string())
+                });
+                let entity = scene
+                    .get_entity_mut(entity_id)
+                    .expect("entity was found or created above");
+                if let Some(position) = args.get("position").and_then(|v| v.as_array()) {
+                    entity.transform.position = Vec3::new(
+                        position.first().and_then(|v| v.as_f64()).unwrap_or(0.0) as f32,
+                        position.get(1).and_then(|v| v.as_f64()).unwrap_or(0.0) as f32,
+                        position.get(2).and_then(|v| v.as_f64()).unwrap_or(0.0) as f32,
+                    );
+                }
+
+                match light_from_args(entity.get_component::<Light>(), &args) {
+                    Ok(light) => {
+                        let light_json = serde_json::to_value(&light).unwrap_or(Value::Null);
+                        if let Some(existing) = entity.get_component_mut::<Light>() {
+                            *existing = light;
+                        } else {
+                            entity.add_component(light);
+                        }
+                        log::info!("Set light on entity '{}'", entity.name);
+
+                        IpcResponse {
+                            id,
+                            success: true,
+                            result: json!({
+                                "entity_name": entity.name,
+                                "created": created,
+                                "light": light_json
+                            }),
+                        }
+                    }
+                    Err(e) => {
+                        if created {
+                            scene.remove_entity(entity_id);
+                        }
+                        IpcResponse::error(id, e)
+                    }
+                }
+            }
+            "add_component" | "remove_component" | "get_component" | "set_component_field" => {
+                match self.execute_component_command(command, &args, scene) {
+                    Ok(result) => IpcResponse::ok(id, result),
+                    Err(e) => IpcResponse::error(id, e.to_string()),
+                }
+            }
+            "find_entities_by_component" => match self.find_entities_by_component(&args, scene) {
+                Ok(result) => IpcResponse::ok(id, result),
+                Err(e) => IpcResponse::error(id, e.to_string()),
+            },
+            "execute_batch" => self.execute_batch(id, &args, scene, context),
+            "set_editor_camera" => match set_editor_camera(&args, scene, context) {
+                Ok(result) => {
+                    self.camera_moved = true;
+                    IpcResponse::ok(id, result)
+                }
+                Err(e) => IpcResponse::error(id, e.to_string()),
+            },
+            "set_parent" | "get_children" | "get_scene_tree" => {
+                match self.execute_hierarchy_command(command, &args, scene) {
+                    Ok(result) => IpcResponse::ok(id, result),
+                    Err(e) => IpcResponse::error(id, e.to_string()),
+                }
+            }
+            "raycast" | "overlap_sphere" | "find_entities_near" => {
+                match execute_spatial_query(command, &args, scene, context) {
+                    Ok(result) => IpcResponse::ok(id, result),
+                    Err(e) => IpcResponse::error(id, e.to_string()),
+                }
+            }
+            "generate_terrain" | "sculpt_terrain" => {
+                let asset_root = context.asset_manager.asset_root();
+                match self.execute_terrain_command(command, &args, scene, asset_root, &mut context.terrain) {
+                    Ok(result) => IpcResponse::ok(id, result),
+                    Err(e) => IpcResponse::error(id, e.to_string()),
+                }
+            }
+            "set_material" | "assign_texture" => {
+                let asset_root = context.asset_manager.asset_root();
+                match self.execute_material_command(command, &args, scene, asset_root) {
+                    Ok(result) => IpcResponse::ok(id, result),
+                    Err(e) => IpcResponse::error(id, e.to_string()),
+                }
+            }
+            "run_script_snippet" => {
+                let source = args.get("script").and_then(|v| v.as_str()).unwrap_or("");
+                match engine_scripting::run_snippet(source, scene) {
+                    Ok(output) => IpcResponse::ok(id, json!({
+                        "value": output.value,
+                        "output": output.output
+                    })),
+                    Err(diagnostic) => diagnostic_response(id, &diagnostic),
+                }
+            }
+            "attach_script_file" => match self.attach_script_file(&args, scene, context.asset_manager) {
+                Ok(result) => IpcResponse::ok(id, result),
+                Err(e) => match e.downcast::<engine_scripting::ScriptDiagnostic>() {
+                    Ok(diagnostic) => diagnostic_response(id, &diagnostic),
+                    Err(e) => IpcResponse::error(id, e.to_string()),
+                },
+            },
+            "validate_scene" => {
+                let asset_root = context.asset_manager.asset_root();
+                IpcResponse::ok(id, validate_scene(scene, asset_root, context.meshes))
+            }
+            "list_assets" | "get_asset_info" | "list_generated_assets" => {
+                let asset_root = context.asset_manager.asset_root();
+                match execute_asset_command(command, &args, asset_root) {
+                    Ok(result) => IpcResponse::ok(id, result),
+                    Err(e) => IpcResponse::error(id, e.to_string()),
+                }
+            }
+            "list_resources" | "read_resource" | "subscribe_resource" | "unsubscribe_resource"
+            | "take_resource_updates" => {
+                let asset_root = context.asset_manager.asset_root();
+                match self.execute_resource_command(command, &args, asset_root) {
+                    Ok(result) => IpcResponse::ok(id, result),
+                    Err(e) => IpcResponse::error(id, e.to_string()),
+                }
+            }
+            "play_music" => match args.get("file_path").and_then(|v| v.as_str()) {
+                Some(path) => {
+                    self.audio_commands.push(AudioCommand::PlayMusic {
+                        path: path.to_string(),
+                        volume: args.get("volume").and_then(|v| v.as_f64()).unwrap_or(0.8) as f32,
+                        looping: args.get("loop").and_then(|v| v.as_bool()).unwrap_or(true),
+                        fade_in: args.get("fade_in").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32,
+                    });
+                    IpcResponse::ok(id, json!({ "playing": true, "file_path": path }))
+                }
+                None => IpcResponse::error(id, "Missing file_path"),
+            },
+            "stop_music" => {
+                let fade_out = args.get("fade_out").and_then(|v| v.as_f64()).unwrap_or(0.0);
+                self.audio_commands.push(AudioCommand::StopMusic {
+                    fade_out: fade_out as f32,
+                });
+                IpcResponse::ok(id, json!({ "stopped": true, "fade_out": fade_out }))
+            }
+            "list_plugin_tools" => IpcResponse::ok(id, self.list_plugin_tools()),
+            "call_plugin_tool" => match self.call_plugin_tool(&args, scene) {
+                Ok(result) => IpcResponse::ok(id, result),
+                Err(e) => IpcResponse::error(id, e.to_string()),
+            },
+            "start_generation" | "get_job_status" | "cancel_job" | "list_jobs" => {
+                let asset_root = context.asset_manager.asset_root();
+                match self.execute_job_command(command, &args, asset_root) {
+                    Ok(result) => IpcResponse::ok(id, result),
+                    Err(e) => IpcResponse::error(id, e.to_string()),
+                }
+            }
             _ => IpcResponse {
                 id,
                 success: false,
@@ -685,15 +1202,1257 @@ impl FileIpcHandler {
 
 }
 
+impl McpIpcHandler {
+    /// Plugin tools in the form MCP lists tools
+    fn list_plugin_tools(&self) -> Value {
+        let tools: Vec<Value> = self
+            .plugin_tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "name": tool.name,
+                    "description": tool.description,
+                    "inputSchema": tool.input_schema
+                })
+            })
+            .collect();
+        json!({ "tools": tools })
+    }
+
+    fn call_plugin_tool(&self, args: &Value, scene: &mut Scene) -> Result<Value> {
+        let name = args
+            .get("name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing name"))?;
+        let tool = self
+            .plugin_tools
+            .iter()
+            .find(|tool| tool.name == name)
+            .ok_or_else(|| anyhow!("Unknown plugin tool '{}'", name))?;
+        let arguments = args.get("arguments").cloned().unwrap_or_else(|| json!({}));
+        tool.call(&arguments, scene)
+    }
+
+    /// Generic component access through the component registry
+    fn execute_component_command(&self, command: &str, args: &Value, scene: &mut Scene) -> Result<Value> {
+        let entity_name = args
+            .get("entity_name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing entity_name"))?;
+        let component_name = args
+            .get("component")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing component"))?;
+        let component = self.components.find(component_name)?;
+        let entity_id = scene
+            .entities()
+            .find(|e| e.name == entity_name)
+            .map(|e| e.id)
+            .ok_or_else(|| anyhow!("Entity '{}' not found", entity_name))?;
+        let entity = scene
+            .get_entity_mut(entity_id)
+            .ok_or_else(|| anyhow!("Failed to get entity '{}'", entity_name))?;
+
+        match command {
+            "add_component" => {
+                component.add(entity, args.get("fields").cloned())?;
+                log::info!("Added {} to entity '{}'", component.name, entity_name);
+            }
+            "remove_component" => {
+                if !component.remove(entity) {
+                    return Err(anyhow!("Entity '{}' has no {}", entity_name, component.name));
+                }
+                log::info!("Removed {} from entity '{}'", component.name, entity_name);
+                return Ok(json!({
+                    "entity_name": entity_name,
+                    "component": component.name,
+                    "removed": true
+                }));
+            }
+            "set_component_field" => {
+                let field = args
+                    .get("field")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow!("Missing field"))?;
+                let value = args
+                    .get("value")
+                    .cloned()
+                    .ok_or_else(|| anyhow!("Missing value"))?;
+                component.set_field(entity, field, value)?;
+                log::info!("Set {}.{} on entity '{}'", component.name, field, entity_name);
+            }
+            _ => {}
+        }
+
+        let data = component
+            .get(entity)
+            .ok_or_else(|| anyhow!("Entity '{}' has no {}", entity_name, component.name))?;
+        Ok(json!({
+            "entity_name": entity_name,
+            "component": component.name,
+            "data": data
+        }))
+    }
+
+    /// Entities that have a component, optionally only those whose fields
+    /// pass every filter, sorted by ID
+    fn find_entities_by_component(&self, args: &Value, scene: &Scene) -> Result<Value> {
+        let component_name = args
+            .get("component")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing component"))?;
+        let component = self.components.find(component_name)?;
+        let filters = match args.get("filters") {
+            None | Some(Value::Null) => Vec::new(),
+            Some(Value::Array(filters)) => filters
+                .iter()
+                .map(FieldFilter::from_json)
+                .collect::<Result<Vec<_>>>()?,
+            Some(_) => return Err(anyhow!("'filters' must be an array")),
+        };
+        let include_data = args.get("include_data").and_then(|v| v.as_bool()).unwrap_or(true);
+
+        let mut entities: Vec<_> = scene.entities().collect();
+        entities.sort_by_key(|e| e.id.0);
+        let matches: Vec<Value> = entities
+            .into_iter()
+            .filter_map(|entity| {
+                let data = component.get(entity)?;
+                if !filters.iter().all(|filter| filter.matches(&data)) {
+                    return None;
+                }
+                let mut entry = json!({
+                    "id": entity.id.0,
+                    "name": entity.name,
+                });
+                if include_data {
+                    entry["data"] = data;
+                }
+                Some(entry)
+            })
+            .collect();
+
+        Ok(json!({
+            "component": component.name,
+            "count": matches.len(),
+            "entities": matches
+        }))
+    }
+}
+
+impl McpIpcHandler {
+    /// MCP resources: listing, resolving a URI to its file for the server to
+    /// read, and change subscriptions
+    fn execute_resource_command(&mut self, command: &str, args: &Value, asset_root: &Path) -> Result<Value> {
+        let roots = ResourceRoots::new(asset_root);
+        if command == "list_resources" {
+            return Ok(json!({ "resources": resources::list_resources(&roots)? }));
+        }
+        if command == "take_resource_updates" {
+            let uris: Vec<String> = std::mem::take(&mut self.updated_resources).into_iter().collect();
+            return Ok(json!({ "uris": uris }));
+        }
+
+        let uri = args
+            .get("uri")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing uri"))?;
+        let file = resources::resource_file(&roots, uri)?;
+        match command {
+            "subscribe_resource" => {
+                self.subscribed_resources.insert(uri.to_string());
+            }
+            "unsubscribe_resource" => {
+                self.subscribed_resources.remove(uri);
+                self.updated_resources.remove(uri);
+            }
+            _ => {}
+        }
+
+        let path = file.canonicalize().unwrap_or(file);
+        Ok(json!({
+            "uri": uri,
+            "path": path.to_string_lossy(),
+            "mimeType": resources::mime_type(&path)
+        }))
+    }
+}
+
+impl McpIpcHandler {
+    /// Background generation: start a job, poll or cancel it, list jobs
+    fn execute_job_command(&mut self, command: &str, args: &Value, asset_root: &Path) -> Result<Value> {
+        let job_id = || {
+            args.get("job_id")
+                .and_then(|v| v.as_u64())
+                .ok_or_else(|| anyhow!("Missing job_id"))
+        };
+        match command {
+            "get_job_status" => self.generation_jobs.status(job_id()?),
+            "cancel_job" => self.generation_jobs.cancel(job_id()?),
+            "list_jobs" => Ok(json!({ "jobs": self.generation_jobs.list() })),
+            _ => self.start_generation(args, asset_root),
+        }
+    }
+
+    /// Start generating a texture, skybox or music track, returning the job ID
+    fn start_generation(&mut self, args: &Value, asset_root: &Path) -> Result<Value> {
+        let kind = args
+            .get("kind")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing kind"))?;
+        let kind = JobKind::parse(kind)?;
+        let prompt = args
+            .get("prompt")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| anyhow!("Missing prompt"))?
+            .to_string();
+        let quality = args
+            .get("quality")
+            .and_then(|v| v.as_str())
+            .unwrap_or("high")
+            .to_string();
+        let seed = args.get("seed").and_then(|v| v.as_u64());
+
+        let job_id = match kind {
+            JobKind::Texture => {
+                let width = args.get("width").and_then(|v| v.as_u64()).unwrap_or(512) as u32;
+                let height = args.get("height").and_then(|v| v.as_u64()).unwrap_or(512) as u32;
+                let estimated = image_generation_estimate(quality_steps(&quality), width, height);
+                let job_prompt = prompt.clone();
+                self.generation_jobs.start(kind, &prompt, estimated, move || async move {
+                    let (asset_id, file_path) =
+                        generate_texture(&job_prompt, width, height, &quality, seed).await?;
+                    Ok(json!({
+                        "generated": true,
+                        "asset_id": asset_id,
+                        "file_path": file_path,
+                        "prompt": job_prompt,
+                        "dimensions": [width, height]
+                    }))
+                })
+            }
+            JobKind::Skybox => {
+                // generate_skybox always renders 2048x1024 at 50 steps
+                let estimated = image_generation_estimate(50, 2048, 1024);
+                let job_prompt = prompt.clone();
+                self.generation_jobs.start(kind, &prompt, estimated, move || async move {
+                    let (asset_id, file_path) = generate_skybox(&job_prompt, seed).await?;
+                    Ok(json!({
+                        "generated": true,
+                        "asset_id": asset_id,
+                        "file_path": file_path,
+                        "prompt": job_prompt,
+                        "dimensions": [2048, 1024]
+                    }))
+                })
+            }
+            JobKind::Music => {
+                let output_path = args
+                    .get("output_path")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow!("Missing output_path"))?
+                    .to_string();
+                let file = material_tools::asset_file(asset_root, &output_path)?;
+                let request = music_request(&prompt, args)?;
+                let duration_secs = request.duration.map_or(30, |d| d.as_secs());
+                // Roughly real time on a consumer GPU, plus model warm-up
+                let estimated = Duration::from_secs(10 + duration_secs as u64);
+                self.generation_jobs.start(kind, &prompt, estimated, move || async move {
+                    if let Some(parent) = file.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    AceStepClient::with_config(AceStepConfig::with_url(&engine_core::project::current().ai.music_url))
+                        .generate_and_save(request, &file)
+                        .await?;
+                    Ok(json!({
+                        "generated": true,
+                        "file_path": output_path,
+                        "duration_secs": duration_secs
+                    }))
+                })
+            }
+        };
+
+        Ok(json!({
+            "job_id": job_id,
+            "kind": kind.name(),
+            "status": "running"
+        }))
+    }
+}
+
+impl McpIpcHandler {
+    /// Material file edits and texture assignment
+    fn execute_material_command(
+        &mut self,
+        command: &str,
+        args: &Value,
+        scene: &mut Scene,
+        asset_root: &Path,
+    ) -> Result<Value> {
+        let entity_name = args.get("entity_name").and_then(|v| v.as_str());
+        let entity_id = match entity_name {
+            Some(name) => Some(
+                scene
+                    .entities()
+                    .find(|e| e.name == name)
+                    .map(|e| e.id)
+                    .ok_or_else(|| anyhow!("Entity '{}' not found", name))?,
+            ),
+            None => None,
+        };
+        let current_material = match entity_id {
+            Some(entity_id) => {
+                let renderer = scene
+                    .get_entity(entity_id)
+                    .and_then(|e| e.get_component::<MeshRenderer>())
+                    .ok_or_else(|| {
+                        anyhow!("Entity '{}' has no MeshRenderer", entity_name.unwrap_or_default())
+                    })?;
+                Some(renderer.material_path.clone())
+            }
+            None => None,
+        };
+
+        let (material_path, material, created) = match command {
+            "set_material" => {
+                let material_path = args.get("material_path").and_then(|v| v.as_str());
+                let material_path = match (material_path, entity_name) {
+                    (Some(path), _) => path.to_string(),
+                    (None, Some(name)) => material_tools::entity_material_path(name),
+                    (None, None) => return Err(anyhow!("Give material_path, entity_name or both")),
+                };
+                let (mut material, created) = material_tools::load_or_new(asset_root, &material_path)?;
+                material_tools::apply_material_args(&mut material, args, asset_root)?;
+                (material_path, material, created)
+            }
+            _ => {
+                let entity_name = entity_name.ok_or_else(|| anyhow!("Missing entity_name"))?;
+                let texture = args
+                    .get("texture")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow!("Missing texture"))?;
+                let slot = args.get("slot").and_then(|v| v.as_str()).unwrap_or("albedo");
+
+                // The texture goes into a material of the entity's own, starting
+                // from the one it renders with now, so shared materials stay as they are
+                let material_path = material_tools::entity_material_path(entity_name);
+                let source = current_material
+                    .flatten()
+                    .unwrap_or_else(|| "materials/default.mat".to_string());
+                let (mut material, _) = material_tools::load_or_new(asset_root, &source)?;
+                if source != material_path {
+                    material.name = entity_name.to_string();
+                }
+                let created = !asset_root.join(&material_path).is_file();
+                let texture_path = material_tools::resolve_texture(asset_root, texture)?;
+                material_tools::set_texture_slot(&mut material, slot, Some(texture_path))?;
+                (material_path, material, created)
+            }
+        };
+
+        material_tools::save(asset_root, &material_path, &material)?;
+        self.changed_materials.push(material_path.clone());
+        log::info!("Saved material {}", material_path);
+
+        if let Some(entity_id) = entity_id {
+            if let Some(renderer) = scene
+                .get_entity_mut(entity_id)
+                .and_then(|e| e.get_component_mut::<MeshRenderer>())
+            {
+                renderer.material_path = Some(material_path.clone());
+            }
+        }
+
+        Ok(json!({
+            "material_path": material_path,
+            "created": created,
+            "entity_name": entity_name,
+            "material": serde_json::to_value(&material)?
+        }))
+    }
+}
+
+impl McpIpcHandler {
+    /// set_parent, get_children and get_scene_tree
+    fn execute_hierarchy_command(&self, command: &str, args: &Value, scene: &mut Scene) -> Result<Value> {
+        let find = |name: &str| {
+            scene
+                .entities()
+                .find(|e| e.name == name)
+                .map(|e| e.id)
+                .ok_or_else(|| anyhow!("Entity '{}' not found", name))
+        };
+        let name_arg = |key: &str| args.get(key).and_then(|v| v.as_str()).filter(|name| !name.is_empty());
+        let max_depth = args.get("max_depth").and_then(|v| v.as_u64()).map(|d| d as usize);
+
+        match command {
+            "set_parent" => {
+                let entity_name = name_arg("entity_name").ok_or_else(|| anyhow!("Missing entity_name"))?;
+                let entity_id = find(entity_name)?;
+                let parent_name = name_arg("parent_name");
+                let parent_id = parent_name.map(find).transpose()?;
+                if let Some(parent_id) = parent_id {
+                    if parent_id == entity_id || is_ancestor(scene, entity_id, parent_id) {
+                        return Err(anyhow!(
+                            "Cannot parent '{}' to '{}': it would become its own ancestor",
+                            entity_name,
+                            parent_name.unwrap_or_default()
+                        ));
+                    }
+                }
+
+                if args.get("keep_world_transform").and_then(|v| v.as_bool()).unwrap_or(true) {
+                    set_parent_keep_world(scene, entity_id, parent_id);
+                } else {
+                    scene.set_parent(entity_id, parent_id);
+                }
+                log::info!("Parented '{}' to {}", entity_name, parent_name.unwrap_or("the scene root"));
+
+                let position = scene
+                    .get_entity(entity_id)
+                    .map(|e| e.transform.position)
+                    .unwrap_or_default();
+                Ok(json!({
+                    "entity_name": entity_name,
+                    "parent_name": parent_name,
+                    "local_position": [position.x, position.y, position.z]
+                }))
+            }
+            "get_children" => {
+                let entity_name = name_arg("entity_name").ok_or_else(|| anyhow!("Missing entity_name"))?;
+                let entity_id = find(entity_name)?;
+                let recursive = args.get("recursive").and_then(|v| v.as_bool()).unwrap_or(false);
+                let depth = if recursive { max_depth.map(|d| d.max(1)) } else { Some(1) };
+                let children = self.entity_tree(scene, entity_id, depth);
+                Ok(json!({
+                    "entity_name": entity_name,
+                    "children": children["children"]
+                }))
+            }
+            _ => {
+                let roots: Vec<EntityId> = match name_arg("root_name") {
+                    Some(name) => vec![find(name)?],
+                    None => scene.root_entities().to_vec(),
+                };
+                let tree: Vec<Value> = roots
+                    .into_iter()
+                    .map(|id| self.entity_tree(scene, id, max_depth))
+                    .collect();
+                Ok(json!({
+                    "scene": scene.name,
+                    "entity_count": scene.entity_count(),
+                    "roots": tree
+                }))
+            }
+        }
+    }
+
+    /// An entity and its descendants (down to `max_depth` levels below it)
+    /// as nested JSON, with local transforms and component names
+    fn entity_tree(&self, scene: &Scene, entity_id: EntityId, max_depth: Option<usize>) -> Value {
+        let Some(entity) = scene.get_entity(entity_id) else {
+            return Value::Null;
+        };
+        let transform = &entity.transform;
+        let mut node = json!({
+            "id": entity.id.0,
+            "name": entity.name,
+            "position": [transform.position.x, transform.position.y, transform.position.z],
+            "components": self.components.components_of(entity),
+        });
+        if transform.rotation != glam::Quat::IDENTITY {
+            node["rotation"] = json!([transform.rotation.x, transform.rotation.y, transform.rotation.z, transform.rotation.w]);
+        }
+        if transform.scale != Vec3::ONE {
+            node["scale"] = json!([transform.scale.x, transform.scale.y, transform.scale.z]);
+        }
+        match max_depth {
+            Some(0) => node["child_count"] = json!(entity.children.len()),
+            _ => {
+                let depth = max_depth.map(|d| d - 1);
+                node["children"] = entity
+                    .children
+                    .iter()
+                    .map(|&child| self.entity_tree(scene, child, depth))
+                    .filter(|child| !child.is_null())
+                    .collect();
+            }
+        }
+        node
+    }
+
+    /// Attach a .rhai file as an entity's script and remember the file so
+    /// later edits to it are picked up
+    fn attach_script_file(&mut self, args: &Value, scene: &mut Scene, asset_manager: &AssetManager) -> Result<Value> {
+        let entity_name = args
+            .get("entity_name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing entity_name"))?;
+        let path = args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing path"))?;
+        let file = script_file(path)?;
+        let entity_id = scene
+            .entities()
+            .find(|e| e.name == entity_name)
+            .map(|e| e.id)
+            .ok_or_else(|| anyhow!("Entity '{}' not found", entity_name))?;
+
+        let source = asset_manager
+            .read_file(&file)
+            .and_then(|bytes| Ok(String::from_utf8(bytes)?))
+            .map_err(|e| anyhow!("Failed to read script '{}': {}", path, e))?;
+        let functions = engine_scripting::check_script(&source)?;
+
+        let entity = scene
+            .get_entity_mut(entity_id)
+            .ok_or_else(|| anyhow!("Failed to get entity '{}'", entity_name))?;
+        entity.add_component(Script::new(source.clone()));
+        self.attached_scripts.push((entity_id, file.clone()));
+        log::info!("Attached script {} to entity '{}'", file.display(), entity_name);
+
+        Ok(json!({
+            "entity_name": entity_name,
+            "path": file.to_string_lossy().replace('\\', "/"),
+            "script_size": source.len(),
+            "functions": functions
+        }))
+    }
+}
+
+impl McpIpcHandler {
+    /// undo, redo, create_checkpoint and restore_checkpoint, on the editor's
+    /// undo history (shared with edits made in the editor)
+    fn execute_history_command(
+        &mut self,
+        command: &str,
+        args: &Value,
+        scene: &mut Scene,
+        terrain: &mut TerrainData,
+        undo: &mut UndoHistory,
+    ) -> Result<Value> {
+        let name = || {
+            args.get("name")
+                .and_then(|v| v.as_str())
+                .filter(|name| !name.is_empty())
+                .ok_or_else(|| anyhow!("Missing checkpoint name"))
+        };
+
+        let (targets, result) = match command {
+            "create_checkpoint" => {
+                let name = name()?;
+                self.checkpoints.insert(name.to_string(), undo.checkpoint(scene));
+                log::info!("Created checkpoint '{}'", name);
+                (Vec::new(), json!({ "name": name }))
+            }
+            "restore_checkpoint" => {
+                let name = name()?;
+                let checkpoint = *self
+                    .checkpoints
+                    .get(name)
+                    .ok_or_else(|| anyhow!("No checkpoint named '{}'", name))?;
+                let terrain_mut = terrain
+                    .heightmap
+                    .as_mut()
+                    .map(|heightmap| (heightmap, terrain.splatmap.as_mut()));
+                let targets = undo
+                    .restore_checkpoint(checkpoint, scene, terrain_mut)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "Checkpoint '{}' is no longer in the undo history (the scene was reloaded, the history was trimmed, or edits were made after undoing past it)",
+                            name
+                        )
+                    })?;
+                log::info!("Restored checkpoint '{}' ({} steps)", name, targets.len());
+                let steps = targets.len();
+                (targets, json!({ "name": name, "steps": steps }))
+            }
+            _ => {
+                let redo = command == "redo";
+                let steps = args.get("steps").and_then(|v| v.as_u64()).unwrap_or(1).max(1);
+                let mut targets = Vec::new();
+                for _ in 0..steps {
+                    let terrain_mut = terrain
+                        .heightmap
+                        .as_mut()
+                        .map(|heightmap| (heightmap, terrain.splatmap.as_mut()));
+                    let target = if redo {
+                        undo.redo(scene, terrain_mut)
+                    } else {
+                        undo.undo(scene, terrain_mut)
+                    };
+                    match target {
+                        Some(target) => targets.push(target),
+                        None => break,
+                    }
+                }
+                if targets.is_empty() {
+                    return Err(anyhow!("Nothing to {}", command));
+                }
+                log::info!("{} {} steps over IPC", if redo { "Redid" } else { "Undid" }, targets.len());
+                let steps = targets.len();
+                (targets, json!({ "steps": steps }))
+            }
+        };
+
+        self.history_changed |= targets.contains(&UndoTarget::Scene);
+        self.terrain_changed |= targets.contains(&UndoTarget::Terrain);
+
+        let mut result = result;
+        result["undo_count"] = json!(undo.undo_count());
+        result["redo_count"] = json!(undo.redo_count());
+        result["checkpoints"] = json!(self.checkpoints.keys().collect::<Vec<_>>());
+        Ok(result)
+    }
+
+    /// Run a list of scene commands as one transaction: if any fails, the
+    /// scene is put back as it was and the whole batch fails
+    fn execute_batch(
+        &mut self,
+        id: u64,
+        args: &Value,
+        scene: &mut Scene,
+        context: &mut CommandContext,
+    ) -> IpcResponse {
+        let commands = match batch_commands(args) {
+            Ok(commands) => commands,
+            Err(e) => return IpcResponse::error(id, e.to_string()),
+        };
+
+        let snapshot = SceneSnapshot::take(scene);
+        let mut results = Vec::with_capacity(commands.len());
+        for (index, (command, command_args)) in commands.into_iter().enumerate() {
+            let response = self.execute_command(id, &command, command_args, scene, context);
+            if !response.success {
+                snapshot.restore(scene);
+                log::warn!("Batch command {} ({}) failed; batch rolled back", index, command);
+                return IpcResponse {
+                    id,
+                    success: false,
+                    result: json!({
+                        "error": format!("Command {} ({}) failed; no changes were applied", index, command),
+                        "failed_index": index,
+                        "failed_command": command,
+                        "failure": response.result,
+                        "completed": results
+                    }),
+                };
+            }
+            results.push(json!({
+                "command": command,
+                "result": response.result
+            }));
+        }
+
+        log::info!("Executed batch of {} commands", results.len());
+        IpcResponse::ok(id, json!({
+            "count": results.len(),
+            "results": results
+        }))
+    }
+
+    /// TerrainGenerator edits and scripted sculpting of the live heightmap
+    fn execute_terrain_command(
+        &mut self,
+        command: &str,
+        args: &Value,
+        scene: &mut Scene,
+        asset_root: &Path,
+        terrain: &mut TerrainData,
+    ) -> Result<Value> {
+        if command == "sculpt_terrain" {
+            let (Some(heightmap), Some(config)) =
+                (terrain.heightmap.as_mut(), terrain.config.as_ref())
+            else {
+                return Err(anyhow!("The scene has no terrain; create one with generate_terrain"));
+            };
+            let result = terrain_tools::sculpt(heightmap, config, args, asset_root)?;
+            self.terrain_changed |= result.modified > 0;
+            log::info!("Sculpted terrain: {} points changed", result.modified);
+            return Ok(json!({
+                "modified": result.modified,
+                "center_height": result.center_height
+            }));
+        }
+
+        // The named entity, else the first one with a TerrainGenerator, else a new "Terrain"
+        let entity_name = args.get("entity_name").and_then(|v| v.as_str());
+        let existing = scene
+            .entities()
+            .find(|e| match entity_name {
+                Some(name) => e.name == name,
+                None => e.has_component::<TerrainGenerator>(),
+            })
+            .map(|e| e.id);
+        let created = existing.is_none();
+        let entity_id = existing
+            .unwrap_or_else(|| scene.create_entity(entity_name.unwrap_or("Terrain").to_string()));
+        let entity = scene
+            .get_entity_mut(entity_id)
+            .ok_or_else(|| anyhow!("Failed to get terrain entity"))?;
+
+        let mut generator = entity
+            .get_component::<TerrainGenerator>()
+            .cloned()
+            .unwrap_or_default();
+        if let Err(e) = terrain_tools::apply_generator_args(&mut generator, args) {
+            if created {
+                scene.remove_entity(entity_id);
+            }
+            return Err(e);
+        }
+
+        if !entity.has_component::<MeshRenderer>() {
+            entity.add_component(MeshRenderer::new("terrain".to_string()));
+        }
+        match entity.get_component_mut::<TerrainGenerator>() {
+            Some(existing) => *existing = generator.clone(),
+            None => entity.add_component(generator.clone()),
+        }
+        let name = entity.name.clone();
+
+        let (heightmap, config) = terrain_tools::generate_heightmap(&generator, asset_root);
+        let (min_height, max_height) = heightmap
+            .heights
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(lo, hi), &h| (lo.min(h), hi.max(h)));
+        *terrain.heightmap = Some(heightmap);
+        *terrain.config = Some(config);
+        self.terrain_changed = true;
+        log::info!("Generated terrain on entity '{}'", name);
+
+        Ok(json!({
+            "entity_name": name,
+            "created": created,
+            "generator": serde_json::to_value(&generator)?,
+            "height_range": [min_height, max_height]
+        }))
+    }
+}
+
+/// raycast, overlap_sphere and find_entities_near
+fn execute_spatial_query(
+    command: &str,
+    args: &Value,
+    scene: &Scene,
+    context: &mut CommandContext,
+) -> Result<Value> {
+    let max_results = args
+        .get("max_results")
+        .and_then(|v| v.as_u64())
+        .map_or(DEFAULT_MAX_RESULTS, |n| n as usize);
+    let radius = || -> Result<f32> {
+        let radius = args
+            .get("radius")
+            .and_then(|v| v.as_f64())
+            .ok_or_else(|| anyhow!("Missing radius"))? as f32;
+        if radius < 0.0 {
+            return Err(anyhow!("'radius' must not be negative"));
+        }
+        Ok(radius)
+    };
+    let entity_list = |hits: Vec<(EntityId, f32)>| -> Value {
+        let total = hits.len();
+        let entities: Vec<Value> = hits
+            .into_iter()
+            .take(max_results)
+            .filter_map(|(id, distance)| {
+                let entity = scene.get_entity(id)?;
+                let position = crate::selection::world_position(scene, id);
+                Some(json!({
+                    "name": entity.name,
+                    "id": id.0,
+                    "position": [position.x, position.y, position.z],
+                    "distance": distance
+                }))
+            })
+            .collect();
+        json!({ "count": total, "entities": entities })
+    };
+
+    match command {
+        "raycast" => {
+            let origin = spatial_tools::vec3_arg(args, "origin")?.ok_or_else(|| anyhow!("Missing origin"))?;
+            let direction = spatial_tools::vec3_arg(args, "direction")?.unwrap_or(Vec3::NEG_Y);
+            let max_distance = args
+                .get("max_distance")
+                .and_then(|v| v.as_f64())
+                .map_or(DEFAULT_MAX_DISTANCE, |d| d as f32);
+            let terrain = terrain_ref(context.terrain.heightmap, context.terrain.config);
+            let hit = spatial_tools::raycast(
+                scene,
+                context.asset_manager,
+                context.mesh_picker,
+                terrain,
+                origin,
+                direction,
+                max_distance,
+            );
+            Ok(match hit {
+                Some(hit) => {
+                    let entity = hit.entity.and_then(|id| scene.get_entity(id));
+                    let surface = if entity.is_some() { "entity" } else { "terrain" };
+                    json!({
+                        "hit": true,
+                        "surface": surface,
+                        "entity_name": entity.map(|e| e.name.clone()),
+                        "point": [hit.point.x, hit.point.y, hit.point.z],
+                        "distance": hit.distance,
+                        "normal": hit.normal.map(|n| [n.x, n.y, n.z])
+                    })
+                }
+                None => json!({ "hit": false }),
+            })
+        }
+        "overlap_sphere" => {
+            let center = spatial_tools::vec3_arg(args, "center")?.ok_or_else(|| anyhow!("Missing center"))?;
+            let hits = spatial_tools::overlap_sphere(
+                scene,
+                context.asset_manager,
+                context.mesh_picker,
+                center,
+                radius()?,
+            );
+            Ok(entity_list(hits))
+        }
+        _ => {
+            let position = spatial_tools::vec3_arg(args, "position")?.ok_or_else(|| anyhow!("Missing position"))?;
+            let horizontal = args.get("horizontal").and_then(|v| v.as_bool()).unwrap_or(false);
+            let hits = spatial_tools::entities_near(scene, position, radius()?, horizontal);
+            Ok(entity_list(hits))
+        }
+    }
+}
+
+/// Point the orbit camera: at an entity (framing it), at `target`, or from
+/// `position`. Without `position` the view direction is kept.
+fn set_editor_camera(args: &Value, scene: &Scene, context: &mut CommandContext) -> Result<Value> {
+    let position = spatial_tools::vec3_arg(args, "position")?;
+    let distance = match args.get("distance") {
+        None | Some(Value::Null) => None,
+        Some(value) => match value.as_f64() {
+            Some(distance) if distance > 0.0 => Some(distance as f32),
+            _ => return Err(anyhow!("'distance' must be a positive number")),
+        },
+    };
+
+    let (target, fit_distance) = match args.get("entity_name").and_then(|v| v.as_str()) {
+        Some(name) => {
+            let entity_id = scene
+                .entities()
+                .find(|e| e.name == name)
+                .map(|e| e.id)
+                .ok_or_else(|| anyhow!("Entity '{}' not found", name))?;
+            let bounds =
+                spatial_tools::entity_bounds(scene, context.asset_manager, context.mesh_picker, entity_id);
+            // Far enough back that the bounding sphere fills most of the view
+            let radius = bounds.half_extents().length();
+            (bounds.center(), Some((radius * 2.5).max(CAMERA_MIN_FRAME_DISTANCE)))
+        }
+        None => match spatial_tools::vec3_arg(args, "target")? {
+            Some(target) => (target, None),
+            None if position.is_some() || distance.is_some() => (context.viewport.pan_offset, None),
+            None => return Err(anyhow!("Give entity_name, target, position or distance")),
+        },
+    };
+
+    let viewport = &mut *context.viewport;
+    match position {
+        Some(position) => {
+            viewport.look_at(position, target);
+            if let Some(distance) = distance {
+                viewport.orbit_around(target, distance);
+            }
+        }
+        None => {
+            let distance = distance.or(fit_distance).unwrap_or(viewport.orbit_distance);
+            viewport.orbit_around(target, distance);
+        }
+    }
+
+    let mut camera = engine_render::camera::Camera::new(1, 1);
+    viewport.update_camera(&mut camera);
+    log::info!("Moved editor camera to look at {:?}", target);
+    Ok(json!({
+        "position": [camera.position.x, camera.position.y, camera.position.z],
+        "target": [target.x, target.y, target.z],
+        "distance": viewport.orbit_distance
+    }))
+}
+
+/// Parse a play mode command (None for other commands)
+fn simulation_command(command: &str, args: &Value) -> Option<Result<SimulationCommand>> {
+    let simulation = match command {
+        "play_simulation" => SimulationCommand::Play,
+        "pause_simulation" => SimulationCommand::Pause,
+        "stop_simulation" => SimulationCommand::Stop,
+        "get_simulation_state" => SimulationCommand::State,
+        "step_simulation" => {
+            let frames = match (args.get("frames").and_then(|v| v.as_u64()), args.get("seconds").and_then(|v| v.as_f64())) {
+                (Some(frames), _) => frames,
+                (None, Some(seconds)) if seconds >= 0.0 => (seconds / fixed_dt() as f64).round() as u64,
+                (None, Some(_)) => return Some(Err(anyhow!("'seconds' must not be negative"))),
+                (None, None) => 1,
+            };
+            if frames == 0 || frames > MAX_STEP_FRAMES as u64 {
+                return Some(Err(anyhow!(
+                    "A step must run 1 to {} frames ({} seconds)",
+                    MAX_STEP_FRAMES,
+                    MAX_STEP_FRAMES as f32 * fixed_dt()
+                )));
+            }
+            SimulationCommand::Step { frames: frames as u32 }
+        }
+        _ => return None,
+    };
+    Some(Ok(simulation))
+}
+
+/// What simulation commands answer with
+pub fn simulation_state(state: PlayState, session: Option<&PlaySession>, frames_to_step: Option<u32>) -> Value {
+    json!({
+        "state": state.name(),
+        "simulated_time": session.map_or(0.0, |s| s.simulated_time),
+        "frames": session.map_or(0, |s| s.simulated_frames),
+        "frames_to_step": frames_to_step.unwrap_or(0)
+    })
+}
+
+/// Whether a command can change the scene, and so gets an undo step
+fn edits_scene(command: &str, args: &Value) -> bool {
+    match command {
+        "execute_batch" => batch_commands(args)
+            .map(|commands| commands.iter().any(|(command, _)| !READ_ONLY_COMMANDS.contains(&command.as_str())))
+            .unwrap_or(false),
+        "generate_terrain" | "assign_texture" | "attach_script_file" | "call_plugin_tool" => true,
+        "set_material" => args.get("entity_name").is_some(),
+        _ => BATCH_COMMANDS.contains(&command) && !READ_ONLY_COMMANDS.contains(&command),
+    }
+}
+
+/// A .rhai path relative to the working directory (where `scripts/` is),
+/// refusing anything outside it
+fn script_file(path: &str) -> Result<PathBuf> {
+    let relative = Path::new(path);
+    if relative.is_absolute()
+        || relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(anyhow!("'{}' is not a path inside the project directory", path));
+    }
+    if relative.extension().and_then(|e| e.to_str()) != Some("rhai") {
+        return Err(anyhow!("'{}' is not a .rhai script", path));
+    }
+    Ok(relative
+        .components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .collect())
+}
+
+/// Failed response for a script that does not compile or run, with the
+/// position of the error
+fn diagnostic_response(id: u64, diagnostic: &engine_scripting::ScriptDiagnostic) -> IpcResponse {
+    IpcResponse {
+        id,
+        success: false,
+        result: json!({
+            "error": format!("Script error: {}", diagnostic),
+            "message": diagnostic.message,
+            "line": diagnostic.line,
+            "column": diagnostic.column
+        }),
+    }
+}
+
+/// Scene::validate, counting meshes as present when they are uploaded
+/// (built-in and generated meshes have no file) or their file exists.
+/// The terrain is uploaded as chunks rather than under its own name.
+fn validate_scene(scene: &Scene, asset_root: &Path, meshes: &MeshManager) -> Value {
+    let terrain_chunk = TerrainChunk { x: 0, z: 0 }.mesh_name(0);
+    let issues = scene.validate(|path| {
+        let file = path.split('#').next().unwrap_or(path);
+        meshes.get_handle(path).is_some()
+            || (path == "terrain" && meshes.get_handle(&terrain_chunk).is_some())
+            || material_tools::asset_file(asset_root, file).is_ok_and(|file| file.is_file())
+    });
+    let issues: Vec<Value> = issues
+        .iter()
+        .map(|issue| {
+            json!({
+                "kind": issue.kind.name(),
+                "entity_id": issue.entity.0,
+                "entity_name": scene.get_entity(issue.entity).map(|e| e.name.clone()),
+                "message": issue.message
+            })
+        })
+        .collect();
+    log::info!("Validated scene: {} issues", issues.len());
+    json!({
+        "valid": issues.is_empty(),
+        "issue_count": issues.len(),
+        "issues": issues
+    })
+}
+
+/// list_assets, get_asset_info and list_generated_assets (read-only, so
+/// they need no handler state)
+fn execute_asset_command(command: &str, args: &Value, asset_root: &Path) -> Result<Value> {
+    let string_arg = |key: &str| args.get(key).and_then(|v| v.as_str()).filter(|s| !s.is_empty());
+    match command {
+        "list_assets" => {
+            let kind = string_arg("type").map(asset_tools::parse_asset_kind).transpose()?;
+            let max_results = args
+                .get("max_results")
+                .and_then(|v| v.as_u64())
+                .map(|n| n as usize)
+                .unwrap_or(asset_tools::DEFAULT_MAX_ASSETS);
+            let assets = asset_tools::list_assets(asset_root, kind, string_arg("pattern"));
+            let listed: Vec<Value> = assets
+                .iter()
+                .take(max_results)
+                .map(|asset| json!({ "path": asset.path_string(), "type": asset.kind.label().to_lowercase() }))
+                .collect();
+            Ok(json!({
+                "count": assets.len(),
+                "truncated": assets.len() > listed.len(),
+                "assets": listed
+            }))
+        }
+        "get_asset_info" => {
+            let path = string_arg("path").ok_or_else(|| anyhow!("Missing path"))?;
+            asset_tools::asset_info(asset_root, path)
+        }
+        _ => {
+            let assets = asset_tools::generated_assets(asset_root, string_arg("query"))?;
+            Ok(json!({ "count": assets.len(), "assets": assets }))
+        }
+    }
+}
+
+/// The (command, args) list of an execute_batch, checked before anything runs
+fn batch_commands(args: &Value) -> Result<Vec<(String, Value)>> {
+    let commands = args
+        .get("commands")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| anyhow!("Missing commands array"))?;
+    if commands.is_empty() {
+        return Err(anyhow!("The batch has no commands"));
+    }
+    if commands.len() > MAX_BATCH_COMMANDS {
+        return Err(anyhow!("A batch can have at most {} commands", MAX_BATCH_COMMANDS));
+    }
+
+    commands
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let command = entry
+                .get("command")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| anyhow!("Command {} has no 'command' name", index))?;
+            if !BATCH_COMMANDS.contains(&command) {
+                return Err(anyhow!(
+                    "Command {} ({}) can't run in a batch (allowed: {})",
+                    index,
+                    command,
+                    BATCH_COMMANDS.join(", ")
+                ));
+            }
+            let args = entry.get("args").cloned().unwrap_or_else(|| json!({}));
+            Ok((command.to_string(), args))
+        })
+        .collect()
+}
+
+/// Every entity of a scene, for rolling back a failed batch
+struct SceneSnapshot {
+    entities: Vec<Entity>,
+    roots: Vec<EntityId>,
+    next_id: EntityId,
+}
+
+impl SceneSnapshot {
+    fn take(scene: &Scene) -> Self {
+        Self {
+            entities: scene.entities().cloned().collect(),
+            roots: scene.root_entities().to_vec(),
+            next_id: scene.next_entity_id(),
+        }
+    }
+
+    fn restore(self, scene: &mut Scene) {
+        let created: Vec<EntityId> = scene
+            .entities()
+            .filter(|e| e.id.0 >= self.next_id.0)
+            .map(|e| e.id)
+            .collect();
+        for id in created {
+            scene.take_entity(id);
+        }
+        for entity in self.entities {
+            scene.take_entity(entity.id);
+            scene.insert_entity(entity);
+        }
+        scene.set_root_entities(self.roots);
+    }
+}
+
+/// Build a light from add_light arguments, keeping whatever `existing` had
+/// for fields that were not given
+fn light_from_args(existing: Option<&Light>, args: &Value) -> std::result::Result<Light, String> {
+    let mut light = existing
+        .cloned()
+        .unwrap_or_else(|| Light::point([1.0, 1.0, 1.0], 1.0, 10.0));
+    let number = |key: &str| args.get(key).and_then(|v| v.as_f64()).map(|v| v as f32);
+    let triple = |key: &str| {
+        args.get(key).and_then(|v| v.as_array()).map(|arr| {
+            [
+                arr.first().and_then(|v| v.as_f64()).unwrap_or(0.0) as f32,
+                arr.get(1).and_then(|v| v.as_f64()).unwrap_or(0.0) as f32,
+                arr.get(2).and_then(|v| v.as_f64()).unwrap_or(0.0) as f32,
+            ]
+        })
+    };
+
+    if let Some(kind) = args.get("light_type").and_then(|v| v.as_str()) {
+        let light_type = match kind {
+            "directional" => LightType::Directional { direction: [0.0, -1.0, 0.0] },
+            "point" => LightType::Point { range: 10.0, intensity: light.intensity },
+            "spot" => LightType::Spot { direction: [0.0, -1.0, 0.0], angle: 45.0, range: 10.0 },
+            other => {
+                return Err(format!(
+                    "Unknown light type '{}' (expected directional, point or spot)",
+                    other
+                ))
+            }
+        };
+        // Switching type starts from that type's defaults; same type keeps its settings
+        if std::mem::discriminant(&light_type) != std::mem::discriminant(&light.light_type) {
+            light.light_type = light_type;
+        }
+    }
+    if let Some(color) = triple("color") {
+        light.color = color;
+    }
+    if let Some(intensity) = number("intensity") {
+        light.intensity = intensity;
+    }
+    if let Some(cast_shadows) = args.get("cast_shadows").and_then(|v| v.as_bool()) {
+        light.cast_shadows = cast_shadows;
+    }
+
+    match &mut light.light_type {
+        LightType::Directional { direction } => {
+            if let Some(value) = triple("direction") {
+                *direction = value;
+            }
+        }
+        LightType::Point { range, intensity } => {
+            if let Some(value) = number("range") {
+                *range = value;
+            }
+            *intensity = light.intensity;
+        }
+        LightType::Spot { direction, angle, range } => {
+            if let Some(value) = triple("direction") {
+                *direction = value;
+            }
+            if let Some(value) = number("angle") {
+                *angle = value;
+            }
+            if let Some(value) = number("range") {
+                *range = value;
+            }
+        }
+    }
+    Ok(light)
+}
+
 fn generate_texture_blocking(
     prompt: &str,
     width: u32,
     height: u32,
     quality: &str,
     seed: Option<u64>,
+) -> Result<(String, String)> {
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(generate_texture(prompt, width, height, quality, seed))
+}
+
+fn generate_skybox_blocking(
+    prompt: &str,
+    _quality: &str,
+    seed: Option<u64>,
+) -> Result<(String, String)> {
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(generate_skybox(prompt, seed))
+}
+
+/// Diffusion steps for a quality setting
+fn quality_steps(quality: &str) -> u32 {
+    match quality {
+        "fast" => 20,
+        "standard" => 35,
+        "high" => 50,
+        "best" => 75,
+        _ => 50,
+    }
+}
+
+/// Rough time to diffuse an image: a fifth of a second per step at 512x512
+fn image_generation_estimate(steps: u32, width: u32, height: u32) -> Duration {
+    let pixels = (width as f32 * height as f32) / (512.0 * 512.0);
+    Duration::from_secs_f32((steps as f32 * 0.2 * pixels).max(5.0))
+}
+
+/// ACE-Step request from generate_music's arguments
+fn music_request(prompt: &str, args: &Value) -> Result<MusicGenerationRequest> {
+    let mut request = MusicGenerationRequest::new(prompt);
+    if let Some(duration) = args.get("duration").and_then(|v| v.as_str()) {
+        let duration = serde_json::from_value(json!(duration.to_lowercase())).map_err(|_| {
+            anyhow!(
+                "Unknown duration '{}' (expected short, medium, long or extended)",
+                duration
+            )
+        })?;
+        request = request.with_duration(duration);
+    }
+    if let Some(style) = args.get("style").and_then(|v| v.as_str()) {
+        // Anything that isn't a known genre is passed on as a description
+        let style = serde_json::from_value(json!(style.to_lowercase()))
+            .unwrap_or_else(|_| MusicStyle::Custom(style.to_string()));
+        request = request.with_style(style);
+    }
+    if let Some(tempo) = args.get("tempo").and_then(|v| v.as_u64()) {
+        request = request.with_tempo(tempo as u32);
+    }
+    if args.get("instrumental").and_then(|v| v.as_bool()).unwrap_or(true) {
+        request = request.instrumental();
+    }
+    if let Some(seed) = args.get("seed").and_then(|v| v.as_u64()) {
+        request = request.with_seed(seed);
+    }
+    Ok(request)
+}
+
+/// Stable Diffusion web UI at the project's texture_url
+fn texture_client() -> LocalClient {
+    LocalClient::new(engine_core::project::current().ai.texture_url.clone(), 300)
+}
+
+async fn generate_texture(
+    prompt: &str,
+    width: u32,
+    height: u32,
+    quality: &str,
+    seed: Option<u64>,
 ) -> Result<(String, String)> {
     // Get or create asset generator
-    let local_client = LocalClient::localhost(7860, 300); // Default Stable Diffusion web UI port
+    let local_client = texture_client();
     let cache_dir = "./generated_assets";
     let cache = AssetCache::new(cache_dir)?;
     let generator = AssetGenerator::new(Box::new(local_client), cache)?;
@@ -704,13 +2463,7 @@ fn generate_texture_blocking(
         negative_prompt: Some("blurry, low quality, distorted, ugly".to_string()),
         width,
         height,
-        steps: match quality {
-            "fast" => 20,
-            "standard" => 35,
-            "high" => 50,
-            "best" => 75,
-            _ => 50,
-        },
+        steps: quality_steps(quality),
         guidance_scale: match quality {
             "fast" => 5.0,
             "standard" => 7.0,
@@ -722,27 +2475,79 @@ fn generate_texture_blocking(
         use_cache: true,
     };
 
-    // Run async generation in blocking context
-    let rt = tokio::runtime::Runtime::new()?;
-    let asset = rt.block_on(generator.generate_texture(&request))?;
+    let asset = generator.generate_texture(&request).await?;
 
     Ok((asset.metadata.id.clone(), asset.metadata.file_path.clone()))
 }
 
-fn generate_skybox_blocking(
-    prompt: &str,
-    quality: &str,
-    seed: Option<u64>,
-) -> Result<(String, String)> {
+async fn generate_skybox(prompt: &str, seed: Option<u64>) -> Result<(String, String)> {
     // Get or create asset generator
-    let local_client = LocalClient::localhost(7860, 300); // Default Stable Diffusion web UI port
+    let local_client = texture_client();
     let cache_dir = "./generated_assets";
     let cache = AssetCache::new(cache_dir)?;
     let generator = AssetGenerator::new(Box::new(local_client), cache)?;
 
-    // Run async generation in blocking context
-    let rt = tokio::runtime::Runtime::new()?;
-    let asset = rt.block_on(generator.generate_skybox(prompt, seed))?;
+    let asset = generator.generate_skybox(prompt, seed).await?;
 
     Ok((asset.metadata.id.clone(), asset.metadata.file_path.clone()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_simulation_args() {
+        let step = |args: Value| simulation_command("step_simulation", &args).unwrap();
+        assert_eq!(step(json!({})).unwrap(), SimulationCommand::Step { frames: 1 });
+        assert_eq!(step(json!({ "seconds": 2.0 })).unwrap(), SimulationCommand::Step { frames: 120 });
+        assert_eq!(step(json!({ "frames": 5, "seconds": 2.0 })).unwrap(), SimulationCommand::Step { frames: 5 });
+        assert!(step(json!({ "frames": 0 })).is_err());
+        assert!(step(json!({ "seconds": 3600.0 })).is_err());
+        assert!(simulation_command("create_entity", &json!({})).is_none());
+    }
+
+    #[test]
+    fn test_music_request_args() {
+        let request = music_request("battle drums", &json!({ "duration": "Long", "style": "cinematic", "tempo": 140 })).unwrap();
+        assert_eq!(request.duration.map(|d| d.as_secs()), Some(60));
+        assert_eq!(request.style.map(|s| s.to_string()).as_deref(), Some("cinematic"));
+        assert_eq!(request.tempo, Some(140));
+        assert!(request.instrumental);
+
+        let request = music_request("lullaby", &json!({ "style": "music box waltz", "instrumental": false })).unwrap();
+        assert_eq!(request.style.map(|s| s.to_string()).as_deref(), Some("music box waltz"));
+        assert!(!request.instrumental);
+        assert!(music_request("lullaby", &json!({ "duration": "forever" })).is_err());
+    }
+
+    #[test]
+    fn test_batch_validation_and_rollback() {
+        let batch = json!({ "commands": [
+            { "command": "create_entity", "args": { "name": "Hut" } },
+            { "command": "list_entities" }
+        ]});
+        assert_eq!(batch_commands(&batch).unwrap().len(), 2);
+        assert!(edits_scene("execute_batch", &batch));
+        assert!(!edits_scene("execute_batch", &json!({ "commands": [{ "command": "list_entities" }] })));
+        assert!(batch_commands(&json!({ "commands": [{ "command": "execute_batch" }] })).is_err());
+        assert!(batch_commands(&json!({ "commands": [] })).is_err());
+        assert!(batch_commands(&json!({ "commands": [{ "command": "step_simulation" }] })).is_err());
+        assert!(edits_scene("set_parent", &json!({ "entity_name": "Hut" })));
+        assert!(!edits_scene("get_scene_tree", &json!({})));
+        assert!(!edits_scene("find_entities_by_component", &json!({ "component": "Light" })));
+
+        let mut scene = Scene::new("Test".to_string());
+        let well = scene.create_entity("Well".to_string());
+        let snapshot = SceneSnapshot::take(&scene);
+        scene.get_entity_mut(well).unwrap().name = "Renamed".to_string();
+        let hut = scene.create_entity("Hut".to_string());
+        scene.set_parent(hut, Some(well));
+
+        snapshot.restore(&mut scene);
+        assert_eq!(scene.entity_count(), 1);
+        assert_eq!(scene.get_entity(well).unwrap().name, "Well");
+        assert!(scene.get_entity(well).unwrap().children.is_empty());
+        assert_eq!(scene.root_entities(), &[well]);
+    }
+}
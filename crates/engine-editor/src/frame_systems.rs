@@ -0,0 +1,196 @@
+// Frame systems - per-frame work that can run alongside other systems
+//
+// Each function only reads the scene, so the render loop schedules them
+// in the same stage of its SystemGraph (see engine_core::jobs).
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+use engine_core::determinism::SimRng;
+use engine_core::jobs::parallel_for_each;
+use engine_particles::{EmitterProperties, EmitterShape, ParticleComputePipeline, ParticleSystem};
+use engine_physics::{BuoyancySystem, PhysicsWorld, WaterVolume};
+use engine_render::foliage_culling::{FoliageChunks, FoliageLodRange};
+use engine_render::foliage_renderer::FoliageInstanceGpu;
+use engine_scene::components::{Foliage, ParticleEmitter, Water};
+use engine_scene::entity::EntityId;
+use engine_scene::scene::Scene;
+use glam::Vec3;
+
+/// Rebuild water volumes from Water components and apply buoyancy and
+/// drag to the physics bodies in them
+pub fn update_buoyancy(buoyancy: &mut BuoyancySystem, scene: &Scene, physics: &mut PhysicsWorld) {
+    buoyancy.water_volumes.clear();
+    for entity in scene.entities() {
+        if let Some(water) = entity.get_component::<Water>() {
+            let transform = &entity.transform;
+            let flow_dir = Vec3::new(water.flow_direction[0], 0.0, water.flow_direction[1]);
+            let water_volume = WaterVolume::new(
+                transform.position,
+                transform.scale * 2.0, // Scale is half-extents, volume needs full size
+                transform.position.y + transform.scale.y, // Top of water
+            )
+            .with_flow(flow_dir, water.flow_speed);
+            buoyancy.add_water_volume(water_volume);
+        }
+    }
+
+    buoyancy.update(&mut physics.rigid_body_set, scene);
+}
+
+/// CPU side of the particle emitters: create systems (and their compute
+/// pipelines) for new emitters, then spawn particles and upload them.
+/// Each emitter draws from `session_seed` forked by its entity id.
+pub fn update_particles(
+    scene: &Scene,
+    dt: f32,
+    session_seed: u64,
+    systems: &mut HashMap<EntityId, ParticleSystem>,
+    pipelines: &mut HashMap<EntityId, ParticleComputePipeline>,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) {
+    let mut emitters = Vec::new();
+    for entity in scene.entities() {
+        let Some(particle_emitter) = entity.get_component::<ParticleEmitter>() else {
+            continue;
+        };
+        if !particle_emitter.enabled {
+            continue;
+        }
+        let entity_id = entity.id;
+        emitters.push((entity_id, entity.transform.position));
+
+        // Initialize particle system if it doesn't exist
+        if let Entry::Vacant(slot) = systems.entry(entity_id) {
+            log::info!(
+                "Initializing particle emitter for entity {} ({})",
+                entity_id.0,
+                entity.name
+            );
+            log::info!(
+                "  Rate: {}, Max particles: {}",
+                particle_emitter.rate,
+                particle_emitter.max_particles
+            );
+
+            // Parse emitter shape
+            let shape = match particle_emitter.shape.as_str() {
+                "sphere" => EmitterShape::Sphere { radius: 1.0 },
+                "cone" => EmitterShape::Cone {
+                    angle: 30.0,
+                    radius: 1.0,
+                },
+                "box" => EmitterShape::Box { size: Vec3::ONE },
+                "circle" => EmitterShape::Circle { radius: 1.0 },
+                _ => EmitterShape::Point,
+            };
+
+            let properties = EmitterProperties {
+                shape,
+                rate: particle_emitter.rate,
+                initial_velocity: Vec3::from(particle_emitter.initial_velocity),
+                velocity_randomness: particle_emitter.velocity_randomness,
+                lifetime: particle_emitter.lifetime,
+                lifetime_randomness: particle_emitter.lifetime_randomness,
+                initial_size: particle_emitter.initial_size,
+                size_over_lifetime: vec![1.0, 0.1], // Linear shrink
+                initial_color: particle_emitter.initial_color,
+                color_over_lifetime: vec![],
+                gravity: Vec3::from(particle_emitter.gravity),
+            };
+
+            let position = entity.transform.position;
+            let mut system = ParticleSystem::new(particle_emitter.max_particles, properties)
+                .with_rng(SimRng::new(session_seed).fork(entity_id.0));
+            system.position = position;
+
+            // Spawn initial batch of particles for immediate visibility
+            for _ in 0..50 {
+                system.update(0.016); // Simulate ~3 frames worth of spawning
+            }
+
+            log::info!(
+                "Created particle system at position {:?} with {} initial particles",
+                position,
+                system.active_particle_count()
+            );
+
+            let system = slot.insert(system);
+
+            // Create compute pipeline with initial particles
+            // After this, GPU takes over - no more uploads!
+            if let Ok(compute_pipeline) = ParticleComputePipeline::new(
+                device,
+                particle_emitter.max_particles,
+                &system.particles,
+            ) {
+                pipelines.insert(entity_id, compute_pipeline);
+            }
+        }
+    }
+
+    // Spawn new particles, one system per worker
+    let positions: HashMap<EntityId, Vec3> = emitters.into_iter().collect();
+    let mut active: Vec<(&EntityId, &mut ParticleSystem)> = systems
+        .iter_mut()
+        .filter(|(id, _)| positions.contains_key(id))
+        .collect();
+    parallel_for_each(&mut active, |(id, system)| {
+        system.position = positions[*id];
+        system.update(dt);
+    });
+
+    // Upload particles to GPU (includes newly spawned ones)
+    // GPU compute will update them immediately after
+    for (id, system) in active {
+        if let Some(compute_pipeline) = pipelines.get(id) {
+            compute_pipeline.upload_particles(queue, &system.particles);
+        }
+    }
+}
+
+/// World-space foliage instances grouped by vegetation type and bucketed
+/// into culling chunks, skipping hidden entities
+pub fn gather_foliage(
+    scene: &Scene,
+    hidden_entities: Option<&HashSet<EntityId>>,
+) -> HashMap<String, FoliageChunks> {
+    let mut foliage_by_type: HashMap<String, FoliageChunks> = HashMap::new();
+
+    for entity in scene.entities() {
+        if hidden_entities.is_some_and(|hidden| hidden.contains(&entity.id)) {
+            continue;
+        }
+        if let Some(foliage) = entity.get_component::<Foliage>() {
+            let world_matrix = scene.world_matrix(entity.id);
+            let color_tint = Vec3::from(foliage.color_tint);
+            let lod_range = FoliageLodRange {
+                lod_distance: foliage.lod_distance,
+                impostor_distance: foliage.impostor_distance,
+            };
+
+            for instance in &foliage.instances {
+                let local_pos = Vec3::from(instance.position);
+                // Transform local position by entity's world matrix
+                let world_pos = world_matrix.transform_point3(local_pos);
+
+                let gpu_instance = FoliageInstanceGpu::new(
+                    world_pos,
+                    instance.rotation_y,
+                    instance.scale,
+                    color_tint,
+                )
+                .with_fade_range(foliage.draw_distance, foliage.fade_width)
+                .with_stiffness(foliage.stiffness);
+
+                foliage_by_type
+                    .entry(foliage.vegetation_type.clone())
+                    .or_default()
+                    .insert(gpu_instance, lod_range);
+            }
+        }
+    }
+
+    foliage_by_type
+}
@@ -0,0 +1,308 @@
+// Generation jobs - texture, skybox and music generation in the background
+//
+// Generating an asset takes anywhere from seconds to minutes, far longer
+// than an IPC command should hold up the editor. Each job runs on its own
+// thread and is polled by ID; cancelling drops the in-flight request. None
+// of the backends report how far along they are, so progress is an
+// estimate from the elapsed time.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::{json, Value};
+
+/// Finished jobs kept for polling; older ones are forgotten
+const MAX_FINISHED_JOBS: usize = 50;
+
+/// Running jobs report at most this much progress until they finish
+const MAX_ESTIMATED_PROGRESS: f32 = 0.95;
+
+/// What a job generates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+    Texture,
+    Skybox,
+    Music,
+}
+
+impl JobKind {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "texture" => Ok(JobKind::Texture),
+            "skybox" => Ok(JobKind::Skybox),
+            "music" => Ok(JobKind::Music),
+            _ => bail!(
+                "Unknown generation kind '{}' (expected texture, skybox or music)",
+                name
+            ),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobKind::Texture => "texture",
+            JobKind::Skybox => "skybox",
+            JobKind::Music => "music",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobStatus::Running => "running",
+            JobStatus::Completed => "completed",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// What the job's thread reports back
+struct JobState {
+    status: JobStatus,
+    result: Option<Value>,
+    error: Option<String>,
+    /// How long the job ran, once it has finished
+    elapsed: Option<Duration>,
+}
+
+struct Job {
+    kind: JobKind,
+    prompt: String,
+    started: Instant,
+    estimated: Duration,
+    cancel: Arc<AtomicBool>,
+    state: Arc<Mutex<JobState>>,
+}
+
+impl Job {
+    fn status(&self) -> JobStatus {
+        self.state.lock().unwrap().status
+    }
+
+    fn to_json(&self, id: u64) -> Value {
+        let state = self.state.lock().unwrap();
+        let elapsed = state.elapsed.unwrap_or_else(|| self.started.elapsed());
+        let progress = match state.status {
+            JobStatus::Completed => 1.0,
+            _ => (elapsed.as_secs_f32() / self.estimated.as_secs_f32().max(1.0))
+                .min(MAX_ESTIMATED_PROGRESS),
+        };
+
+        let mut job = json!({
+            "job_id": id,
+            "kind": self.kind.name(),
+            "prompt": self.prompt,
+            "status": state.status.name(),
+            "progress": progress,
+            "elapsed_secs": elapsed.as_secs_f32(),
+            "estimated_secs": self.estimated.as_secs_f32(),
+        });
+        if let Some(result) = &state.result {
+            job["result"] = result.clone();
+        }
+        if let Some(error) = &state.error {
+            job["error"] = json!(error);
+        }
+        job
+    }
+}
+
+/// Background generation jobs, by ID
+#[derive(Default)]
+pub struct GenerationJobs {
+    next_id: u64,
+    jobs: BTreeMap<u64, Job>,
+}
+
+impl GenerationJobs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `work` on a new thread and return the job's ID. `estimated` is
+    /// how long the job is expected to take, for its progress.
+    pub fn start<F, Fut>(
+        &mut self,
+        kind: JobKind,
+        prompt: &str,
+        estimated: Duration,
+        work: F,
+    ) -> u64
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Value>>,
+    {
+        self.prune();
+        self.next_id += 1;
+        let id = self.next_id;
+
+        let job = Job {
+            kind,
+            prompt: prompt.to_string(),
+            started: Instant::now(),
+            estimated,
+            cancel: Arc::new(AtomicBool::new(false)),
+            state: Arc::new(Mutex::new(JobState {
+                status: JobStatus::Running,
+                result: None,
+                error: None,
+                elapsed: None,
+            })),
+        };
+
+        let cancel = job.cancel.clone();
+        let state = job.state.clone();
+        let started = job.started;
+        std::thread::spawn(move || {
+            // None when cancelled
+            let outcome = match tokio::runtime::Runtime::new() {
+                Ok(rt) => rt.block_on(async {
+                    tokio::select! {
+                        result = work() => Some(result),
+                        _ = cancelled(&cancel) => None,
+                    }
+                }),
+                Err(e) => Some(Err(anyhow!("Failed to start runtime: {}", e))),
+            };
+
+            let mut state = state.lock().unwrap();
+            if state.status != JobStatus::Running {
+                return;
+            }
+            match outcome {
+                Some(Ok(result)) => {
+                    state.status = JobStatus::Completed;
+                    state.result = Some(result);
+                }
+                Some(Err(e)) => {
+                    log::error!("Generation job {} failed: {}", id, e);
+                    state.status = JobStatus::Failed;
+                    state.error = Some(e.to_string());
+                }
+                None => state.status = JobStatus::Cancelled,
+            }
+            state.elapsed = Some(started.elapsed());
+        });
+
+        log::info!("Started {} generation job {}", kind.name(), id);
+        self.jobs.insert(id, job);
+        id
+    }
+
+    /// A job's status, progress and (once finished) result or error
+    pub fn status(&self, id: u64) -> Result<Value> {
+        self.jobs
+            .get(&id)
+            .map(|job| job.to_json(id))
+            .ok_or_else(|| anyhow!("No generation job {}", id))
+    }
+
+    /// Stop a running job; its result is discarded
+    pub fn cancel(&mut self, id: u64) -> Result<Value> {
+        let job = self
+            .jobs
+            .get(&id)
+            .ok_or_else(|| anyhow!("No generation job {}", id))?;
+        {
+            let mut state = job.state.lock().unwrap();
+            if state.status != JobStatus::Running {
+                bail!("Job {} has already {}", id, state.status.name());
+            }
+            state.status = JobStatus::Cancelled;
+            state.elapsed = Some(job.started.elapsed());
+        }
+        job.cancel.store(true, Ordering::Relaxed);
+        log::info!("Cancelled generation job {}", id);
+        Ok(job.to_json(id))
+    }
+
+    /// Every job still known, oldest first
+    pub fn list(&self) -> Vec<Value> {
+        self.jobs.iter().map(|(&id, job)| job.to_json(id)).collect()
+    }
+
+    /// Forget the oldest finished jobs beyond MAX_FINISHED_JOBS
+    fn prune(&mut self) {
+        let finished: Vec<u64> = self
+            .jobs
+            .iter()
+            .filter(|(_, job)| job.status() != JobStatus::Running)
+            .map(|(&id, _)| id)
+            .collect();
+        for id in finished
+            .iter()
+            .take(finished.len().saturating_sub(MAX_FINISHED_JOBS))
+        {
+            self.jobs.remove(id);
+        }
+    }
+}
+
+/// Resolves once the flag is set
+async fn cancelled(flag: &AtomicBool) {
+    while !flag.load(Ordering::Relaxed) {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait_until_finished(jobs: &GenerationJobs, id: u64) -> Value {
+        for _ in 0..200 {
+            let status = jobs.status(id).unwrap();
+            if status["status"] != "running" {
+                return status;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("job {} did not finish", id);
+    }
+
+    #[test]
+    fn test_jobs_complete_fail_and_cancel() {
+        let mut jobs = GenerationJobs::new();
+
+        let done = jobs.start(
+            JobKind::Texture,
+            "moss",
+            Duration::from_secs(10),
+            || async { Ok(json!({ "asset_id": "abc" })) },
+        );
+        let status = wait_until_finished(&jobs, done);
+        assert_eq!(status["status"], "completed");
+        assert_eq!(status["progress"], 1.0);
+        assert_eq!(status["result"]["asset_id"], "abc");
+
+        let failed = jobs.start(JobKind::Music, "drums", Duration::from_secs(10), || async {
+            Err(anyhow!("service unavailable"))
+        });
+        let status = wait_until_finished(&jobs, failed);
+        assert_eq!(status["status"], "failed");
+        assert_eq!(status["error"], "service unavailable");
+
+        let slow = jobs.start(JobKind::Skybox, "dusk", Duration::from_secs(60), || {
+            std::future::pending::<Result<Value>>()
+        });
+        assert_eq!(jobs.status(slow).unwrap()["status"], "running");
+        assert_eq!(jobs.cancel(slow).unwrap()["status"], "cancelled");
+        assert!(jobs.cancel(slow).is_err());
+        assert!(jobs.status(99).is_err());
+        assert_eq!(jobs.list().len(), 3);
+    }
+}
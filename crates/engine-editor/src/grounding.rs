@@ -0,0 +1,422 @@
+// Grounding tools - settle selected props onto terrain and other meshes
+//
+// "Snap to Ground" casts a ray straight down from each selected entity and
+// moves it so the lowest point of its meshes rests on the first surface hit.
+// "Simulate Drop" runs a short physics simulation (terrain heightfield plus
+// convex hulls of nearby meshes) and bakes the resting poses, so props tip
+// over and settle naturally on slopes and on each other.
+
+use std::collections::HashSet;
+
+use engine_assets::AssetManager;
+use engine_physics::{Collider, ColliderGeometry, DropSimulation};
+use engine_scene::{components::MeshRenderer, entity::EntityId, scene::Scene};
+use glam::{Mat4, Quat, Vec3};
+
+use crate::lighting::cpu_mesh;
+use crate::picking::MeshPicker;
+use crate::placement::{set_world_position, TerrainRef};
+use crate::selection::{is_ancestor, world_position};
+use crate::ui::EditorUi;
+use crate::undo::UndoHistory;
+
+/// Longest time a drop is simulated for (seconds)
+const MAX_DROP_TIME: f32 = 10.0;
+/// Static meshes this far (horizontally) outside the dropped entities are left out of the simulation
+const DROP_OBSTACLE_MARGIN: f32 = 5.0;
+/// Rays start this far above an entity's top so surfaces it touches are still found
+const SNAP_RAY_OFFSET: f32 = 0.01;
+
+/// Outcome of a drop simulation
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DropReport {
+    /// Entities baked at their resting pose
+    pub settled: usize,
+    /// Entities still moving when time ran out (left where they were)
+    pub unsettled: usize,
+    /// Entities without a mesh or collider to simulate
+    pub skipped: usize,
+}
+
+/// Local-space points of a mesh: its vertices, or the corners of its rough
+/// bounds (scale as half-extents) if it has no CPU data
+fn mesh_points(asset_manager: &mut AssetManager, mesh_path: &str) -> Vec<Vec3> {
+    match cpu_mesh(asset_manager, mesh_path) {
+        Some(mesh) => mesh.vertices.iter().map(|v| v.position).collect(),
+        None => {
+            let mut corners = Vec::with_capacity(8);
+            for x in [-1.0, 1.0] {
+                for y in [-1.0, 1.0] {
+                    for z in [-1.0, 1.0] {
+                        corners.push(Vec3::new(x, y, z));
+                    }
+                }
+            }
+            corners
+        }
+    }
+}
+
+/// An entity and all its descendants
+fn subtree(scene: &Scene, root: EntityId) -> HashSet<EntityId> {
+    scene
+        .entities()
+        .map(|e| e.id)
+        .filter(|&id| id == root || is_ancestor(scene, root, id))
+        .collect()
+}
+
+/// Mesh points of an entity and its descendants, transformed by `to_space * world`
+fn subtree_points(
+    scene: &Scene,
+    asset_manager: &mut AssetManager,
+    ids: &HashSet<EntityId>,
+    to_space: Mat4,
+) -> Vec<Vec3> {
+    let mut points = Vec::new();
+    for &id in ids {
+        let Some(mesh_renderer) = scene
+            .get_entity(id)
+            .and_then(|e| e.get_component::<MeshRenderer>())
+        else {
+            continue;
+        };
+        let transform = to_space * scene.world_matrix(id);
+        points.extend(
+            mesh_points(asset_manager, &mesh_renderer.mesh_path)
+                .into_iter()
+                .map(|p| transform.transform_point3(p)),
+        );
+    }
+    points
+}
+
+/// Highest point straight below `origin` on terrain or a mesh (ignoring `exclude`)
+fn ground_below(
+    scene: &Scene,
+    asset_manager: &mut AssetManager,
+    picker: &mut MeshPicker,
+    terrain: TerrainRef,
+    origin: Vec3,
+    exclude: &HashSet<EntityId>,
+) -> Option<f32> {
+    let mesh_hit = picker
+        .pick_excluding(scene, asset_manager, origin, Vec3::NEG_Y, exclude)
+        .map(|(_, distance)| origin.y - distance);
+    let terrain_hit = terrain.and_then(|(heightmap, config)| {
+        crate::raycast_terrain(origin, Vec3::NEG_Y, heightmap, config).map(|hit| hit.y)
+    });
+    match (mesh_hit, terrain_hit) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (hit, None) | (None, hit) => hit,
+    }
+}
+
+/// Move each root down (or up) so its lowest mesh point rests on the surface
+/// below its origin. Lower entities are snapped first so stacked selections
+/// land on each other. Returns how many entities were moved.
+pub fn snap_to_ground(
+    scene: &mut Scene,
+    asset_manager: &mut AssetManager,
+    picker: &mut MeshPicker,
+    terrain: TerrainRef,
+    roots: &[EntityId],
+) -> usize {
+    let mut entries: Vec<(EntityId, HashSet<EntityId>, f32, f32)> = roots
+        .iter()
+        .map(|&root| {
+            let ids = subtree(scene, root);
+            let origin_y = world_position(scene, root).y;
+            let points = subtree_points(scene, asset_manager, &ids, Mat4::IDENTITY);
+            let bottom = points.iter().map(|p| p.y).fold(origin_y, f32::min);
+            let top = points.iter().map(|p| p.y).fold(origin_y, f32::max);
+            (root, ids, bottom, top)
+        })
+        .collect();
+    entries.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+    let mut moved = 0;
+    for (root, ids, bottom, top) in entries {
+        let position = world_position(scene, root);
+        let origin = Vec3::new(position.x, top + SNAP_RAY_OFFSET, position.z);
+        let Some(ground) = ground_below(scene, asset_manager, picker, terrain, origin, &ids) else {
+            continue;
+        };
+        let offset = ground - bottom;
+        if offset.abs() > 1e-5 {
+            set_world_position(scene, root, position + Vec3::Y * offset);
+            moved += 1;
+        }
+    }
+    moved
+}
+
+/// Give an entity a world rotation and position, keeping its parent and scale
+fn set_world_pose(scene: &mut Scene, entity_id: EntityId, rotation: Quat, position: Vec3) {
+    let Some(entity) = scene.get_entity(entity_id) else {
+        return;
+    };
+    let parent_world = entity
+        .parent
+        .map(|parent_id| scene.world_matrix(parent_id))
+        .unwrap_or(Mat4::IDENTITY);
+    let (world_scale, _, _) = scene
+        .world_matrix(entity_id)
+        .to_scale_rotation_translation();
+    let world = Mat4::from_scale_rotation_translation(world_scale, rotation, position);
+    let (_, local_rotation, local_position) =
+        (parent_world.inverse() * world).to_scale_rotation_translation();
+    if let Some(entity) = scene.get_entity_mut(entity_id) {
+        entity.transform.rotation = local_rotation;
+        entity.transform.position = local_position;
+    }
+}
+
+/// Drop the roots with physics until they come to rest, then bake their poses.
+/// Roots use their own Collider if they have one, else the convex hull of
+/// their meshes (children included). Terrain and nearby static meshes are
+/// the ground; the dropped entities also collide with each other.
+pub fn simulate_drop(
+    scene: &mut Scene,
+    asset_manager: &mut AssetManager,
+    terrain: TerrainRef,
+    roots: &[EntityId],
+) -> DropReport {
+    let mut report = DropReport::default();
+    let mut sim = DropSimulation::new();
+    let mut dropped = HashSet::new();
+    let mut region_min = Vec3::splat(f32::MAX);
+    let mut region_max = Vec3::splat(f32::MIN);
+
+    for &root in roots {
+        let ids = subtree(scene, root);
+        let (scale, rotation, position) = scene.world_matrix(root).to_scale_rotation_translation();
+        let body_space = Mat4::from_rotation_translation(rotation, position).inverse();
+        let collider = scene
+            .get_entity(root)
+            .and_then(|e| e.get_component::<Collider>())
+            .cloned();
+        let world_points = subtree_points(scene, asset_manager, &ids, Mat4::IDENTITY);
+        let points: Vec<Vec3> = world_points
+            .iter()
+            .map(|&p| body_space.transform_point3(p))
+            .collect();
+
+        // Mesh colliders are built from their mesh; what can't be built drops as a hull
+        let added = match collider {
+            Some(collider) => {
+                let geometry =
+                    ColliderGeometry::for_colliders([&collider], |path| cpu_mesh(asset_manager, path), || None);
+                sim.add_body(root, position, rotation, &collider, &geometry, scale)
+                    || sim.add_hull_body(root, position, rotation, &points)
+            }
+            None => sim.add_hull_body(root, position, rotation, &points),
+        };
+        if !added {
+            report.skipped += 1;
+            continue;
+        }
+        for p in world_points.into_iter().chain([position]) {
+            region_min = region_min.min(p);
+            region_max = region_max.max(p);
+        }
+        dropped.extend(ids);
+    }
+    if sim.body_count() == 0 {
+        return report;
+    }
+
+    if let Some((heightmap, config)) = terrain {
+        let cell_size = config.scale / config.width as f32;
+        let origin = Vec3::new(-config.scale * 0.5, 0.0, -config.scale * 0.5);
+        sim.add_terrain(
+            &heightmap.heights,
+            &heightmap.holes,
+            heightmap.width,
+            heightmap.depth,
+            origin,
+            cell_size,
+        );
+    }
+
+    // Everything else with a mesh near the drop is static ground
+    let region_min = region_min - Vec3::splat(DROP_OBSTACLE_MARGIN);
+    let region_max = region_max + Vec3::splat(DROP_OBSTACLE_MARGIN);
+    let obstacles: Vec<EntityId> = scene
+        .entities()
+        .filter(|e| e.has_component::<MeshRenderer>() && !dropped.contains(&e.id))
+        .map(|e| e.id)
+        .collect();
+    for id in obstacles {
+        let points = subtree_points(scene, asset_manager, &HashSet::from([id]), Mat4::IDENTITY);
+        let (min, max) = points.iter().fold(
+            (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+            |(min, max), &p| (min.min(p), max.max(p)),
+        );
+        let overlaps = min.x <= region_max.x
+            && max.x >= region_min.x
+            && min.z <= region_max.z
+            && max.z >= region_min.z
+            && min.y <= region_max.y;
+        if overlaps {
+            sim.add_static_hull(&points);
+        }
+    }
+
+    for body in sim.run(MAX_DROP_TIME) {
+        if body.settled {
+            set_world_pose(scene, body.entity_id, body.rotation, body.position);
+            report.settled += 1;
+        } else {
+            report.unsettled += 1;
+        }
+    }
+    report
+}
+
+/// Snap the selected entities to the ground (one undo step)
+pub fn snap_selection_to_ground(
+    scene: &mut Scene,
+    ui: &mut EditorUi,
+    undo_history: &mut UndoHistory,
+    asset_manager: &mut AssetManager,
+    picker: &mut MeshPicker,
+    terrain: TerrainRef,
+) {
+    let roots = ui.selection_roots(scene);
+    if roots.is_empty() {
+        return;
+    }
+    undo_history.record_transforms(scene, &roots);
+    let moved = snap_to_ground(scene, asset_manager, picker, terrain, &roots);
+    if moved > 0 {
+        ui.mark_scene_modified();
+    }
+    ui.log_info(format!(
+        "Snapped {} of {} entities to the ground",
+        moved,
+        roots.len()
+    ));
+}
+
+/// Drop the selected entities with physics and bake where they land (one undo step)
+pub fn drop_selection(
+    scene: &mut Scene,
+    ui: &mut EditorUi,
+    undo_history: &mut UndoHistory,
+    asset_manager: &mut AssetManager,
+    terrain: TerrainRef,
+) {
+    let roots = ui.selection_roots(scene);
+    if roots.is_empty() {
+        return;
+    }
+    undo_history.record_transforms(scene, &roots);
+    let report = simulate_drop(scene, asset_manager, terrain, &roots);
+    if report.settled > 0 {
+        ui.mark_scene_modified();
+    }
+    ui.log_info(format!("Dropped {} entities", report.settled));
+    if report.unsettled > 0 {
+        ui.log_warning(format!(
+            "{} entities were still falling after {}s and were left in place",
+            report.unsettled, MAX_DROP_TIME
+        ));
+    }
+    if report.skipped > 0 {
+        ui.log_warning(format!(
+            "Skipped {} entities without a mesh or collider",
+            report.skipped
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_scene::transform::Transform;
+
+    fn add_cube(scene: &mut Scene, name: &str, transform: Transform) -> EntityId {
+        let id = scene.create_entity_with_transform(name.to_string(), transform);
+        scene
+            .get_entity_mut(id)
+            .unwrap()
+            .add_component(MeshRenderer::new("stone_cube".to_string()));
+        id
+    }
+
+    #[test]
+    fn test_snap_rests_bottom_on_mesh_below() {
+        let mut scene = Scene::new("Test".to_string());
+        let mut slab = Transform::from_position(Vec3::new(0.0, 1.0, 0.0));
+        slab.scale = Vec3::new(4.0, 1.0, 4.0);
+        add_cube(&mut scene, "Slab", slab);
+        let crate_id = add_cube(
+            &mut scene,
+            "Crate",
+            Transform::from_position(Vec3::new(0.5, 6.0, 0.0)),
+        );
+        let mut asset_manager = AssetManager::new(std::env::temp_dir());
+        let mut picker = MeshPicker::new();
+
+        let moved = snap_to_ground(
+            &mut scene,
+            &mut asset_manager,
+            &mut picker,
+            None,
+            &[crate_id],
+        );
+        assert_eq!(moved, 1);
+        // Slab top is at 1.5, crate half height is 0.5
+        let position = world_position(&scene, crate_id);
+        assert!(
+            (position - Vec3::new(0.5, 2.0, 0.0)).length() < 1e-4,
+            "{}",
+            position
+        );
+
+        // Nothing below: left alone
+        let floating = add_cube(
+            &mut scene,
+            "Floating",
+            Transform::from_position(Vec3::new(20.0, 6.0, 0.0)),
+        );
+        assert_eq!(
+            snap_to_ground(
+                &mut scene,
+                &mut asset_manager,
+                &mut picker,
+                None,
+                &[floating]
+            ),
+            0
+        );
+    }
+
+    #[test]
+    fn test_drop_settles_on_static_mesh() {
+        let mut scene = Scene::new("Test".to_string());
+        let mut slab = Transform::from_position(Vec3::new(0.0, 1.0, 0.0));
+        slab.scale = Vec3::new(4.0, 1.0, 4.0);
+        add_cube(&mut scene, "Slab", slab);
+        let crate_id = add_cube(
+            &mut scene,
+            "Crate",
+            Transform::from_position(Vec3::new(0.0, 4.0, 0.0)),
+        );
+        let empty = scene.create_entity("Empty".to_string());
+        let mut asset_manager = AssetManager::new(std::env::temp_dir());
+
+        let report = simulate_drop(&mut scene, &mut asset_manager, None, &[crate_id, empty]);
+        assert_eq!(
+            report,
+            DropReport {
+                settled: 1,
+                unsettled: 0,
+                skipped: 1
+            }
+        );
+        let position = world_position(&scene, crate_id);
+        assert!((position.y - 2.0).abs() < 0.05, "{}", position);
+    }
+}
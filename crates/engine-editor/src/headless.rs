@@ -0,0 +1,620 @@
+// Headless mode - run a scene's simulation with no window or GPU
+//
+//   editor --headless --scene level.ron --frames 600 --output state.json
+//
+// loads the scene, runs the scripts' start() and then a number of fixed
+// frames (60 Hz unless project.ron sets another physics timestep) of scripts,
+// behavior trees, navigation, animation, plugin systems, buoyancy, ragdolls
+// and physics (the same steps play mode runs, with the navmesh baked next to
+// the scene), and writes where every entity ended up as JSON. CI can run a
+// level this way and check the result. Audio commands from scripts are
+// dropped.
+//
+//   editor --headless --scene level.ron --frames 600 --screenshot end.png
+//
+// also renders the last frame through the scene's Camera into a PNG, with an
+// offscreen renderer (see offscreen); with --snapshot-every each snapshot
+// gets a numbered image next to it.
+//
+//   editor --serve 0.0.0.0:7777 --scene arena.ron
+//
+// runs the same simulation in real time as a dedicated server that clients
+// join with `editor --connect <addr>` (see net_session).
+//
+// Runs use the project's determinism seed, so the same scene always ends
+// the same way. --record writes the run as a replay, and --replay plays one
+// back (from a headless run or an editor play session) and checks every
+// frame against the recording (see replay).
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use engine_ai_behavior::BehaviorSystem;
+use engine_animation::SkeletalAnimationSystem;
+use engine_assets::navmesh::NavMesh;
+use engine_assets::AssetManager;
+use engine_core::determinism::SimRng;
+use engine_core::time::SharedTime;
+use engine_physics::{
+    from_rapier_vec, BuoyancySystem, CharacterSystem, ColliderGeometry, PhysicsSync, PhysicsWorld,
+    RagdollSystem,
+};
+use engine_plugin::{NativeModules, PluginRegistry};
+use engine_scene::scene::Scene;
+use engine_scripting::{AudioCommandQueue, GameInput, ScriptSystem, SharedGameInput};
+use serde_json::{json, Value};
+
+use crate::build_export;
+use crate::frame_systems;
+use crate::net_session::NetSession;
+use crate::offscreen::OffscreenRenderer;
+use crate::play_mode::{self, fixed_dt};
+use crate::plugins;
+use crate::replay::{scene_checksum, Replay, ReplayCheck};
+use crate::save_games::SaveGames;
+use crate::terrain_tools;
+
+/// What a headless run does
+#[derive(Debug, Clone)]
+pub struct HeadlessOptions {
+    pub scene_path: String,
+    /// Frames to simulate
+    pub frames: u32,
+    /// Also record the state every this many frames
+    pub snapshot_every: Option<u32>,
+    /// Where to write the JSON report (stdout if None)
+    pub output: Option<PathBuf>,
+    /// Record a Chrome trace of the run to this file
+    pub trace: Option<PathBuf>,
+    /// Write the run as a replay to this file
+    pub record: Option<PathBuf>,
+    /// Render the final frame to this PNG
+    pub screenshot: Option<PathBuf>,
+    /// Width and height of screenshots
+    pub screenshot_size: [u32; 2],
+}
+
+/// A scene with physics and scripts running, stepped by hand
+pub struct HeadlessSimulation {
+    scene: Scene,
+    physics: PhysicsWorld,
+    scripts: ScriptSystem,
+    buoyancy: BuoyancySystem,
+    ragdolls: RagdollSystem,
+    characters: CharacterSystem,
+    behaviors: BehaviorSystem,
+    skeletal_animation: SkeletalAnimationSystem,
+    /// Navmesh agents path on (None = they walk straight to their destination)
+    navmesh: Option<NavMesh>,
+    audio_commands: AudioCommandQueue,
+    saves: SaveGames,
+    /// Meshes and terrain the mesh and heightfield colliders are built from
+    collider_geometry: ColliderGeometry,
+    net: NetSession,
+    /// Plugin systems (nothing is rendered, so their other parts go unused)
+    plugins: PluginRegistry,
+    native_modules: NativeModules,
+    time: SharedTime,
+    input: SharedGameInput,
+    /// Length of a frame in seconds
+    timestep: f32,
+    /// Game seconds the last frame stepped
+    last_dt: f32,
+    frames: u64,
+}
+
+impl HeadlessSimulation {
+    /// Set up physics and scripts for the scene and run start()
+    pub fn new(scene: Scene) -> Result<Self> {
+        Self::with_net(scene, |_| Ok(()))
+    }
+
+    /// Like new(), but lets `connect` host or join a session before the
+    /// scripts start
+    pub fn with_net(
+        scene: Scene,
+        connect: impl FnOnce(&mut NetSession) -> Result<()>,
+    ) -> Result<Self> {
+        let seed = engine_core::project::current().determinism.seed;
+        Self::build(scene, seed, fixed_dt(), connect)
+    }
+
+    /// Set up the scene a replay starts from, with its seed and timestep
+    pub fn from_replay(replay: &Replay) -> Result<Self> {
+        Self::build(replay.start_scene()?, replay.seed, replay.timestep, |_| Ok(()))
+    }
+
+    fn build(
+        mut scene: Scene,
+        seed: u64,
+        timestep: f32,
+        connect: impl FnOnce(&mut NetSession) -> Result<()>,
+    ) -> Result<Self> {
+        let collider_geometry = scene_collider_geometry(&scene)?;
+        let mut physics = PhysicsWorld::default();
+        PhysicsSync::initialize_physics(&mut physics, &scene, &collider_geometry)?;
+
+        let audio_commands = AudioCommandQueue::default();
+        let mut scripts = ScriptSystem::new();
+        scripts.register_audio_api(audio_commands.clone());
+        let time = SharedTime::default();
+        scripts.register_time_api(time.clone());
+        let rng = SimRng::new(seed).shared();
+        scripts.register_random_api(rng.clone());
+        let input = SharedGameInput::default();
+        scripts.register_game_input_api(input.clone());
+        let saves = SaveGames::new();
+        saves.register(&mut scripts);
+        let mut net = NetSession::new();
+        net.register(&mut scripts);
+        connect(&mut net)?;
+        scripts.initialize(&scene)?;
+        // Scripts can query the world before it first steps
+        physics.query_pipeline.update(&physics.collider_set);
+        scripts.with_physics(&mut physics, |scripts| scripts.start(&mut scene))?;
+
+        Ok(Self {
+            scene,
+            physics,
+            scripts,
+            buoyancy: BuoyancySystem::new(),
+            ragdolls: RagdollSystem::new(),
+            characters: CharacterSystem::new(timestep),
+            behaviors: BehaviorSystem::new(),
+            skeletal_animation: SkeletalAnimationSystem::new(
+                engine_core::project::current().asset_root.clone(),
+            ),
+            navmesh: None,
+            audio_commands,
+            saves,
+            collider_geometry,
+            net,
+            plugins: plugins::load(),
+            native_modules: plugins::native_modules(),
+            time,
+            input,
+            timestep,
+            last_dt: 0.0,
+            frames: 0,
+        })
+    }
+
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Game seconds the last step() advanced (0 while the game is paused)
+    pub fn last_dt(&self) -> f32 {
+        self.last_dt
+    }
+
+    /// Navmesh NavAgents find their paths on
+    pub fn set_navmesh(&mut self, navmesh: Option<NavMesh>) {
+        self.navmesh = navmesh;
+    }
+
+    /// Input the next frame runs with
+    pub fn set_input(&mut self, input: &GameInput) {
+        *self.input.lock().unwrap() = input.clone();
+    }
+
+    /// Advance one fixed frame (scaled if a script changed the time scale)
+    pub fn step(&mut self) -> Result<()> {
+        let dt = {
+            let mut time = self.time.lock().unwrap();
+            time.advance(self.timestep);
+            time.delta()
+        };
+        self.run_frame(dt)
+    }
+
+    /// Advance one frame that steps exactly `dt` game seconds, as a replay
+    /// recorded it; the clock scripts read still ticks one timestep
+    pub fn step_by(&mut self, dt: f32) -> Result<()> {
+        self.time.lock().unwrap().advance(self.timestep);
+        self.run_frame(dt)
+    }
+
+    fn run_frame(&mut self, dt: f32) -> Result<()> {
+        let _span = tracing::info_span!("frame").entered();
+        self.last_dt = dt;
+
+        tracing::info_span!("scripts").in_scope(|| {
+            let scene = &mut self.scene;
+            self.scripts
+                .with_physics(&mut self.physics, |scripts| scripts.update(scene, dt))
+        })?;
+        tracing::info_span!("behavior").in_scope(|| {
+            self.behaviors
+                .update(&mut self.scene, dt, |scene, entity, function| {
+                    self.scripts.run_behavior_task(scene, entity, function, dt)
+                })
+        });
+        tracing::info_span!("navigation").in_scope(|| {
+            let navmesh = self.navmesh.as_ref();
+            engine_scene::navigation::update_nav_agents(&mut self.scene, dt, |from, to| {
+                match navmesh {
+                    Some(navmesh) => navmesh.find_path(from, to),
+                    None => Some(vec![from, to]),
+                }
+            })
+        });
+        tracing::info_span!("animation").in_scope(|| {
+            engine_scene::animation::update_animations(&mut self.scene, dt);
+            self.skeletal_animation.update(&mut self.scene);
+        });
+        tracing::info_span!("ik").in_scope(|| {
+            engine_scene::ik::update_ik(&mut self.scene, |origin, distance, ignore| {
+                self.physics
+                    .ground_below(origin, distance, ignore)
+                    .map(|hit| (hit.point, hit.normal))
+            })
+        });
+        self.plugins.run_systems(&mut self.scene, dt)?;
+        self.native_modules.run_systems(&mut self.scene, dt)?;
+
+        frame_systems::update_buoyancy(&mut self.buoyancy, &self.scene, &mut self.physics);
+        // A network client's characters are moved by the server
+        if !self.net.is_client() {
+            tracing::info_span!("characters").in_scope(|| {
+                self.characters
+                    .update(&mut self.scene, &mut self.physics, dt)
+            });
+        }
+        tracing::info_span!("ragdoll")
+            .in_scope(|| self.ragdolls.update(&mut self.scene, &mut self.physics, dt));
+
+        if dt > 0.0 {
+            self.physics.step(dt);
+        }
+        PhysicsSync::sync_to_scene(&self.physics, &mut self.scene)?;
+
+        for error in self.saves.process_requests(
+            &mut self.scene,
+            &mut self.physics,
+            &mut self.scripts,
+            None,
+            &self.audio_commands,
+            &self.collider_geometry,
+        ) {
+            log::warn!("Save game error: {}", error);
+        }
+
+        self.net.update(&mut self.scene, &mut self.physics, dt);
+
+        // There is no audio device; drop what scripts asked for
+        self.audio_commands.lock().unwrap().clear();
+        self.input.lock().unwrap().end_frame();
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Every entity's transform, and velocity if it has a rigid body
+    pub fn state(&self) -> Value {
+        let mut entities: Vec<_> = self.scene.entities().collect();
+        entities.sort_by_key(|entity| entity.id.0);
+        let entities: Vec<Value> = entities
+            .into_iter()
+            .map(|entity| {
+                let t = &entity.transform;
+                let mut state = json!({
+                    "id": entity.id.0,
+                    "name": entity.name,
+                    "position": [t.position.x, t.position.y, t.position.z],
+                    "rotation": [t.rotation.x, t.rotation.y, t.rotation.z, t.rotation.w],
+                    "scale": [t.scale.x, t.scale.y, t.scale.z],
+                });
+                let body = self
+                    .physics
+                    .get_body_handle(entity.id)
+                    .and_then(|handle| self.physics.get_rigid_body(handle));
+                if let Some(body) = body {
+                    let velocity = from_rapier_vec(*body.linvel());
+                    state["velocity"] = json!([velocity.x, velocity.y, velocity.z]);
+                }
+                state
+            })
+            .collect();
+
+        json!({
+            "frame": self.frames,
+            "time": self.time.lock().unwrap().elapsed(),
+            "entities": entities,
+        })
+    }
+}
+
+/// Geometry for the scene's mesh and heightfield colliders: meshes from the
+/// project's assets, the heightfield from the terrain its TerrainGenerator
+/// describes
+fn scene_collider_geometry(scene: &Scene) -> Result<ColliderGeometry> {
+    let mut asset_manager = build_export::project_assets()?;
+    let asset_root = asset_manager.asset_root().to_path_buf();
+    Ok(play_mode::collider_geometry(scene, &mut asset_manager, || {
+        terrain_tools::scene_terrain(scene, &asset_root)
+    }))
+}
+
+/// The navmesh baked next to the scene, if there is one
+fn load_navmesh(asset_manager: &AssetManager, scene_path: &str) -> Result<Option<NavMesh>> {
+    let path = NavMesh::path_for_scene(scene_path);
+    if !asset_manager.file_exists(&path) {
+        return Ok(None);
+    }
+    NavMesh::from_bytes(&asset_manager.read_file(&path)?).map(Some)
+}
+
+/// `shot.png` as `shot_000060.png` for frame 60
+fn numbered_path(path: &Path, frame: u32) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().unwrap_or("png".as_ref()).to_string_lossy();
+    path.with_file_name(format!("{}_{:06}.{}", stem, frame, extension))
+}
+
+/// Load the scene, simulate and write the report
+pub fn run(options: &HeadlessOptions) -> Result<()> {
+    let asset_manager = build_export::project_assets()?;
+    let scene = build_export::load_scene(&asset_manager, &options.scene_path)?;
+    log::info!(
+        "Headless: simulating '{}' ({} entities) for {} frames",
+        scene.name,
+        scene.entity_count(),
+        options.frames
+    );
+
+    let mut recording = options.record.as_ref().map(|_| {
+        Replay::new(
+            &scene,
+            engine_core::project::current().determinism.seed,
+            fixed_dt(),
+        )
+    });
+    let mut simulation = HeadlessSimulation::new(scene)?;
+    simulation.set_navmesh(load_navmesh(&asset_manager, &options.scene_path)?);
+    let mut offscreen = match &options.screenshot {
+        Some(_) => {
+            let [width, height] = options.screenshot_size;
+            let mut renderer = OffscreenRenderer::new(width, height)?;
+            renderer.load_scene(simulation.scene());
+            Some(renderer)
+        }
+        None => None,
+    };
+    if let Some(path) = &options.trace {
+        engine_core::trace::start_capture(options.frames, path);
+    }
+    let mut snapshots = Vec::new();
+    for frame in 1..=options.frames {
+        simulation
+            .step()
+            .with_context(|| format!("Simulation failed on frame {}", frame))?;
+        if let Some(replay) = &mut recording {
+            replay.record(simulation.last_dt(), &GameInput::default(), simulation.scene());
+        }
+        if let Some(result) = engine_core::trace::end_frame() {
+            log::info!("Headless: wrote trace to {}", result?.display());
+        }
+        if options
+            .snapshot_every
+            .is_some_and(|every| every > 0 && frame % every == 0)
+        {
+            snapshots.push(simulation.state());
+            if let (Some(renderer), Some(path)) = (&mut offscreen, &options.screenshot) {
+                renderer.capture(simulation.scene(), &numbered_path(path, frame))?;
+            }
+        }
+    }
+    if let (Some(renderer), Some(path)) = (&mut offscreen, &options.screenshot) {
+        renderer.capture(simulation.scene(), path)?;
+        log::info!("Headless: wrote screenshot to {}", path.display());
+    }
+
+    let mut report = json!({
+        "scene": simulation.scene().name,
+        "frames": simulation.frames(),
+        "final": simulation.state(),
+    });
+    if options.snapshot_every.is_some() {
+        report["snapshots"] = json!(snapshots);
+    }
+
+    let text = serde_json::to_string_pretty(&report)?;
+    match &options.output {
+        Some(path) => {
+            std::fs::write(path, text)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            log::info!("Headless: wrote state to {}", path.display());
+        }
+        None => println!("{}", text),
+    }
+    if let (Some(replay), Some(path)) = (&recording, &options.record) {
+        replay.save(path)?;
+        log::info!("Headless: wrote replay to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Run a replay's frames with its input and compare each frame's checksum
+/// with the recorded one
+pub fn play_replay(replay: &Replay) -> Result<(HeadlessSimulation, ReplayCheck)> {
+    let mut simulation = HeadlessSimulation::from_replay(replay)?;
+    let mut first_mismatch = None;
+    for (index, frame) in replay.frames.iter().enumerate() {
+        simulation.set_input(&frame.input);
+        simulation
+            .step_by(frame.dt)
+            .with_context(|| format!("Replay failed on frame {}", index + 1))?;
+        if first_mismatch.is_none() && scene_checksum(simulation.scene()) != frame.checksum {
+            first_mismatch = Some(index + 1);
+        }
+    }
+    let check = ReplayCheck {
+        frames: replay.frames.len(),
+        first_mismatch,
+    };
+    Ok((simulation, check))
+}
+
+/// Play a replay file back and write the final state like run(); fails if
+/// the playback diverged from the recording
+pub fn run_replay(path: &std::path::Path, output: Option<&std::path::Path>) -> Result<()> {
+    let replay = Replay::load(path)?;
+    log::info!(
+        "Headless: replaying {} ({} frames, seed {})",
+        path.display(),
+        replay.frames.len(),
+        replay.seed
+    );
+    let (simulation, check) = play_replay(&replay)?;
+
+    let report = json!({
+        "replay": path.display().to_string(),
+        "frames": check.frames,
+        "first_mismatch": check.first_mismatch,
+        "final": simulation.state(),
+    });
+    let text = serde_json::to_string_pretty(&report)?;
+    match output {
+        Some(path) => std::fs::write(path, text)
+            .with_context(|| format!("Failed to write {}", path.display()))?,
+        None => println!("{}", text),
+    }
+    match check.first_mismatch {
+        None => {
+            log::info!(
+                "Headless: replay matched the recording on all {} frames",
+                check.frames
+            );
+            Ok(())
+        }
+        Some(frame) => Err(anyhow!(
+            "Replay diverged from the recording on frame {} of {}",
+            frame,
+            check.frames
+        )),
+    }
+}
+
+/// Load the scene and serve it until the process is stopped, stepping the
+/// simulation in real time
+pub fn serve(scene_path: &str, addr: &str) -> Result<()> {
+    let asset_manager = build_export::project_assets()?;
+    let scene = build_export::load_scene(&asset_manager, scene_path)?;
+    log::info!(
+        "Serving '{}' ({} entities) on {}",
+        scene.name,
+        scene.entity_count(),
+        addr
+    );
+
+    // Host before start() so scripts see is_server()
+    let mut simulation = HeadlessSimulation::with_net(scene, |net| net.host(addr))?;
+    simulation.set_navmesh(load_navmesh(&asset_manager, scene_path)?);
+    let frame = Duration::from_secs_f32(fixed_dt());
+    let mut next_frame = Instant::now();
+    loop {
+        simulation
+            .step()
+            .with_context(|| format!("Simulation failed on frame {}", simulation.frames() + 1))?;
+        next_frame += frame;
+        let now = Instant::now();
+        if next_frame > now {
+            std::thread::sleep(next_frame - now);
+        } else {
+            // Running behind; don't try to catch up on lost frames
+            next_frame = now;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_physics::{Collider, RigidBody};
+    use engine_scene::transform::Transform;
+    use engine_scripting::Script;
+    use glam::Vec3;
+
+    #[test]
+    fn test_headless_steps_physics_and_scripts() {
+        let mut scene = Scene::new("Test".to_string());
+        let ball = scene.create_entity_with_transform(
+            "Ball".to_string(),
+            Transform::from_position(Vec3::new(0.0, 10.0, 0.0)),
+        );
+        let ball_entity = scene.get_entity_mut(ball).unwrap();
+        ball_entity.add_component(RigidBody::dynamic(1.0));
+        ball_entity.add_component(Collider::sphere(0.5));
+
+        // Moves one unit a second along its (unit) scale
+        let mover = scene.create_entity("Mover".to_string());
+        scene
+            .get_entity_mut(mover)
+            .unwrap()
+            .add_component(Script::new(
+                "fn update(ctx) { ctx.position = ctx.position + ctx.scale * ctx.dt; ctx }"
+                    .to_string(),
+            ));
+
+        let mut simulation = HeadlessSimulation::new(scene).unwrap();
+        for _ in 0..30 {
+            simulation.step().unwrap();
+        }
+
+        let state = simulation.state();
+        assert_eq!(state["frame"], 30);
+        let ball = &state["entities"][0];
+        assert!(ball["position"][1].as_f64().unwrap() < 10.0);
+        assert!(ball["velocity"][1].as_f64().unwrap() < 0.0);
+        let mover = &state["entities"][1];
+        assert!((mover["position"][0].as_f64().unwrap() - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_replay_reproduces_random_and_input_driven_scripts() {
+        let mut scene = Scene::new("Test".to_string());
+        let wanderer = scene.create_entity("Wanderer".to_string());
+        scene
+            .get_entity_mut(wanderer)
+            .unwrap()
+            .add_component(Script::new(
+                r#"fn update(ctx) {
+                    if is_key_down("KeyD") { ctx.position = ctx.position + ctx.scale * ctx.dt; }
+                    if random() < 0.5 { ctx.rotation = ctx.rotation * quat_from_rotation_y(ctx.dt); }
+                    ctx
+                }"#
+                .to_string(),
+            ));
+
+        let mut replay = Replay::new(&scene, 42, 1.0 / 60.0);
+        let mut simulation = HeadlessSimulation::from_replay(&replay).unwrap();
+        for frame in 0..20 {
+            let mut input = GameInput::default();
+            if frame >= 10 {
+                input.press_key("KeyD");
+            }
+            simulation.set_input(&input);
+            simulation.step().unwrap();
+            replay.record(simulation.last_dt(), &input, simulation.scene());
+        }
+
+        let (_, check) = play_replay(&replay).unwrap();
+        assert_eq!(check.first_mismatch, None);
+
+        // Different input diverges where it starts to matter
+        let mut edited = replay.clone();
+        edited.frames[12].input = GameInput::default();
+        let (_, check) = play_replay(&edited).unwrap();
+        assert_eq!(check.first_mismatch, Some(13));
+
+        // So do different random numbers
+        let mut reseeded = replay.clone();
+        reseeded.seed = 43;
+        assert!(play_replay(&reseeded).unwrap().1.first_mismatch.is_some());
+    }
+}
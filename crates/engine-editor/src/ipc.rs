@@ -1,4 +1,7 @@
 // IPC (Inter-Process Communication) between editor and MCP server
+//
+// Typed commands for an in-process channel. The MCP server process talks to
+// the editor over the socket transport in `engine_core::ipc` (see file_ipc.rs).
 
 use anyhow::Result;
 use crossbeam_channel::{Receiver, Sender, unbounded};
@@ -0,0 +1,211 @@
+// Lighting bake - ambient occlusion for the scene's static meshes
+//
+// Static meshes and the terrain all occlude. Every static mesh instance gets
+// its own BakedAo asset, referenced from its MeshRenderer; the app uploads the
+// darkened copy of the mesh under the asset path and draws that instead.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use engine_assets::{bake_vertex_ao, AoBakeSettings, AssetManager, BakedAo, Mesh, Terrain, TriangleBvh};
+use engine_scene::{
+    components::{MeshRenderer, TerrainGenerator},
+    entity::EntityId,
+    scene::Scene,
+};
+use glam::{Mat4, Vec3};
+
+use crate::navigation::is_static_mesh;
+use crate::placement::TerrainRef;
+
+/// Built-in meshes the editor uploads at startup (white cubes)
+const BUILTIN_CUBES: [&str; 3] = ["stone_cube", "grass_cube", "water_cube"];
+
+/// CPU copy of a MeshRenderer's mesh: a built-in cube or a glTF mesh
+/// (`path` or `path#index`)
+pub fn cpu_mesh(asset_manager: &mut AssetManager, mesh_path: &str) -> Option<Mesh> {
+    if BUILTIN_CUBES.contains(&mesh_path) {
+        return Some(Mesh::cube_with_color(Vec3::ONE));
+    }
+    let (path, index) = match mesh_path.split_once('#') {
+        Some((path, index)) => (path, index.parse().ok()?),
+        None => (mesh_path, 0),
+    };
+    if !(path.ends_with(".gltf") || path.ends_with(".glb")) {
+        return None;
+    }
+    let handle = asset_manager.load_gltf(path).ok()?;
+    handle.get().get(index).cloned()
+}
+
+/// Folder the baked assets of a scene go to (`assets/lightmaps/<scene name>`)
+pub fn lightmap_dir(scene_path: Option<&str>) -> PathBuf {
+    let name = scene_path
+        .and_then(|path| Path::new(path).file_stem())
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("untitled");
+    Path::new("assets").join("lightmaps").join(name)
+}
+
+/// A static mesh instance with baked occlusion, ready to upload
+pub struct BakedMesh {
+    pub entity: EntityId,
+    pub lightmap_path: String,
+    pub mesh: Mesh,
+}
+
+/// Bake ambient occlusion for every static mesh in the scene, writing one
+/// asset per instance to `output_dir`
+pub fn bake_scene_ao(
+    scene: &Scene,
+    asset_manager: &mut AssetManager,
+    terrain: TerrainRef,
+    settings: &AoBakeSettings,
+    output_dir: &Path,
+) -> Result<Vec<BakedMesh>> {
+    let mut instances = Vec::new();
+    for entity in scene.entities().filter(|e| is_static_mesh(e)) {
+        let Some(mesh_renderer) = entity.get_component::<MeshRenderer>() else {
+            continue;
+        };
+        match cpu_mesh(asset_manager, &mesh_renderer.mesh_path) {
+            Some(mesh) => instances.push((
+                entity.id,
+                mesh_renderer.mesh_path.clone(),
+                mesh,
+                scene.world_matrix(entity.id),
+            )),
+            None => log::warn!(
+                "Skipping '{}': no mesh data for '{}'",
+                entity.name,
+                mesh_renderer.mesh_path
+            ),
+        }
+    }
+
+    let mut geometry = TriangleBvh::new();
+    for (_, _, mesh, transform) in &instances {
+        geometry.add_mesh(mesh, *transform);
+    }
+    if let Some((heightmap, config)) = terrain {
+        let terrain_transform = scene
+            .entities()
+            .find(|e| e.has_component::<TerrainGenerator>())
+            .map(|e| scene.world_matrix(e.id))
+            .unwrap_or(Mat4::IDENTITY);
+        geometry.add_mesh(
+            &Terrain::generate_mesh_from_heightmap(heightmap, config),
+            terrain_transform,
+        );
+    }
+    geometry.build();
+
+    let mut baked = Vec::with_capacity(instances.len());
+    for (entity, mesh_path, mut mesh, transform) in instances {
+        let baked_ao = BakedAo {
+            vertex_ao: bake_vertex_ao(&mesh, transform, &geometry, settings),
+            mesh_path,
+            settings: *settings,
+        };
+        let path = output_dir.join(format!("entity_{}.ao.json", entity.0));
+        baked_ao.save(&path)?;
+        baked_ao.apply(&mut mesh);
+        baked.push(BakedMesh {
+            entity,
+            lightmap_path: path.to_string_lossy().replace('\\', "/"),
+            mesh,
+        });
+    }
+    Ok(baked)
+}
+
+/// Mesh with the baked occlusion a MeshRenderer references applied, or None
+/// if it has no bake
+pub fn load_baked_mesh(
+    asset_manager: &mut AssetManager,
+    mesh_renderer: &MeshRenderer,
+) -> Result<Option<Mesh>> {
+    let Some(lightmap_path) = &mesh_renderer.lightmap_path else {
+        return Ok(None);
+    };
+    let baked = BakedAo::from_bytes(&asset_manager.read_file(lightmap_path)?)?;
+    let mut mesh = cpu_mesh(asset_manager, &mesh_renderer.mesh_path)
+        .with_context(|| format!("No mesh data for '{}'", mesh_renderer.mesh_path))?;
+    anyhow::ensure!(
+        baked.mesh_path == mesh_renderer.mesh_path && baked.apply(&mut mesh),
+        "{} was baked for a different mesh, bake lighting again",
+        lightmap_path
+    );
+    Ok(Some(mesh))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_physics::RigidBody;
+
+    #[test]
+    fn test_bake_darkens_meshes_near_each_other() {
+        let dir = std::env::temp_dir().join(format!("causality_ao_test_{}", std::process::id()));
+        let mut asset_manager = AssetManager::new(&dir);
+        let mut scene = Scene::new("Test".to_string());
+
+        // A cube resting on a wide slab, plus a dynamic crate that isn't baked
+        let slab = scene.create_entity("Slab".to_string());
+        let cube = scene.create_entity("Cube".to_string());
+        let crate_id = scene.create_entity("Crate".to_string());
+        for (id, position, scale) in [
+            (slab, Vec3::new(0.0, -0.5, 0.0), Vec3::new(10.0, 1.0, 10.0)),
+            (cube, Vec3::new(0.0, 0.51, 0.0), Vec3::ONE),
+            (crate_id, Vec3::new(3.0, 0.5, 0.0), Vec3::ONE),
+        ] {
+            let entity = scene.get_entity_mut(id).unwrap();
+            entity.add_component(MeshRenderer::new("stone_cube".to_string()));
+            entity.transform.position = position;
+            entity.transform.scale = scale;
+        }
+        scene
+            .get_entity_mut(crate_id)
+            .unwrap()
+            .add_component(RigidBody::dynamic(1.0));
+
+        let baked = bake_scene_ao(
+            &scene,
+            &mut asset_manager,
+            None,
+            &AoBakeSettings::default(),
+            &dir,
+        )
+        .unwrap();
+        assert_eq!(baked.len(), 2);
+        let cube_bake = baked.iter().find(|b| b.entity == cube).unwrap();
+        // Bottom corners of the cube's sides touch the slab, the top face is open
+        let colors: Vec<f32> = cube_bake
+            .mesh
+            .vertices
+            .iter()
+            .map(|v| v.color.unwrap().x)
+            .collect();
+        assert!(colors.iter().any(|&c| c < 0.7));
+        let top = &cube_bake.mesh.vertices[16..20];
+        assert!(top.iter().all(|v| v.color.unwrap().x == 1.0));
+
+        // The asset reloads onto a fresh copy of the mesh
+        let mut renderer = MeshRenderer::new("stone_cube".to_string());
+        renderer.lightmap_path = Some(cube_bake.lightmap_path.clone());
+        let reloaded = load_baked_mesh(&mut asset_manager, &renderer)
+            .unwrap()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(reloaded.vertices[0].color, cube_bake.mesh.vertices[0].color);
+    }
+
+    #[test]
+    fn test_lightmap_dir_uses_scene_name() {
+        assert_eq!(
+            lightmap_dir(Some("assets/scenes/castle.ron")),
+            Path::new("assets/lightmaps/castle")
+        );
+        assert_eq!(lightmap_dir(None), Path::new("assets/lightmaps/untitled"));
+    }
+}
@@ -2,41 +2,110 @@
 
 mod ui;
 pub mod ipc;
+mod asset_tools;
+mod autosave;
+mod bake;
+mod build_export;
+mod capture;
+mod component_registry;
+mod console_commands;
+mod entity_icons;
 mod file_ipc;
+mod frame_systems;
+mod generation_jobs;
+mod headless;
+mod grounding;
+mod material_tools;
 mod undo;
+mod selection;
+mod play_mode;
+mod placement;
+mod picking;
+mod lighting;
+mod measure;
+mod navigation;
+mod net_session;
+mod offscreen;
+mod plugins;
+mod prefs;
+mod profiler;
+mod replay;
+mod resources;
+mod save_games;
+mod scatter;
+mod spatial_tools;
+mod spline_edit;
+mod streamed_terrain;
+mod terrain_tools;
+mod water_edit;
 
 use anyhow::Result;
-use undo::UndoHistory;
+use undo::{UndoHistory, UndoTarget};
+use play_mode::{PlayRequest, PlaySession, PlayState};
+use prefs::EditorPrefs;
+use profiler::FrameTimer;
 use clap::Parser;
-use engine_assets::{manager::{AssetHandle, AssetManager}, material::Material, mesh::Mesh, texture::Texture, HotReloadWatcher, ReloadEvent, HeightMap, TerrainConfig, Terrain, compute_water_fill, generate_water_mesh, vegetation::VegetationType};
+use engine_assets::{manager::{AssetHandle, AssetManager}, material::Material, mesh::Mesh, texture::Texture, HotReloadWatcher, ReloadEvent, ErosionSettings, HeightMap, NavMesh, NavMeshInput, SplatMap, TerrainChunk, TerrainChunks, TerrainConfig, TerrainLayer, Terrain, TERRAIN_LOD_LEVELS, generate_water_mesh, vegetation::VegetationType, LodSettings, LoadState, CompressedTexture, CompressedTextureCache, TextureKind, AudioLoadPolicy};
 use wgpu::util::DeviceExt;
-use engine_audio::AudioSystem;
-use engine_physics::{Collider, PhysicsSync, PhysicsWorld, RigidBody, BuoyancySystem, WaterVolume};
+use engine_ai_behavior::BehaviorSystem;
+use engine_animation::{SkeletalAnimationSystem, SkinnedModel};
+use engine_audio::{AdaptiveMusicConfig, AudioBus, AudioSystem, EffectSettings, EntityVoices, MusicTrack};
+use engine_core::determinism::{SharedRng, SimRng};
+use engine_core::frame_pacing::FramePacer;
+use engine_core::jobs::SystemGraph;
+use engine_core::time::{SharedTime, Time};
+use engine_ai_music::{
+    AceStepClient, AceStepConfig, AceStepError, MomentTrack, MusicFallback, MusicMoments,
+    MusicMomentsConfig,
+};
+use engine_physics::{
+    ColliderGeometry, PhysicsSync, PhysicsWorld, BuoyancySystem, CharacterSystem, RagdollSystem,
+};
 use engine_render::{
     camera::Camera,
-    foliage_renderer::{FoliageRenderer, FoliageInstanceGpu, FoliageRenderData},
-    frustum::AABB,
-    gpu_mesh::GpuVertex,
+    culling::CullingStats,
+    foliage_culling::FoliageBatches,
+    foliage_renderer::{FoliageDraw, FoliageRenderer, FoliageWind},
+    grass_renderer::{GrassRenderer, GrassSettings},
+    frustum::Frustum,
+    grid::GridRenderer,
+    instancing::DrawBatches,
+    lod::{distance_squared, LodConfig, LodDraw},
+    gpu_material::MaterialHandle,
+    gpu_mesh::{GpuVertex, MeshHandle},
+    gpu_profiler::GpuProfiler,
     material_manager::MaterialManager,
+    memory_budget::MemoryBudget,
     mesh_manager::MeshManager,
     particle_renderer::ParticleRenderer,
-    postprocess::{Framebuffer, PostProcessPipeline},
+    postprocess::{jitter_matrix, AntiAliasingMode, BloomChain, Framebuffer, MotionInstance, ObjectMotion, PostProcessPipeline, TemporalAa, HDR_FORMAT},
+    render_graph::{GraphPass, RenderGraph, TransientDesc, TransientPool},
+    render_stats::RenderStats,
     renderer::Renderer,
     shadow::ShadowMap,
+    skinned_renderer::{SkinnedRenderer, SkinnedVertexGpu},
+    sky::ProceduralSky,
     skybox::Skybox,
+    terrain_renderer::TerrainRenderer,
     texture_manager::TextureManager,
-    water::WaterRenderer,
+    texture_streaming::StreamedMips,
+    water::{reflection_matrix, reflection_view_proj, WaterReflection, WaterRenderer},
+    TextureHandle,
 };
 use engine_scene::{
-    components::{AudioListener, AudioSource, Camera as CameraComponent, Light, MeshRenderer, ParticleEmitter, Water, TerrainWater, WaterBody, TerrainGenerator, Foliage, FoliageInstance},
+    components::{ReverbZone, Camera as CameraComponent, Light, MeshRenderer, ParticleEmitter, Water, TerrainWater, WaterExclusionZone, TerrainGenerator, Foliage, FoliageInstance, SkinnedMesh},
     entity::EntityId,
+    grass::Grass,
     scene::Scene,
     transform::Transform,
+    Spline, SplineKind,
 };
-use engine_scripting::{AudioCommand, AudioCommandQueue, Script, ScriptSystem};
+use engine_scripting::{AudioCommand, AudioCommandQueue, Script, ScriptSystem, SharedGameInput};
 use glam::{Quat, Vec3, Vec4};
-use std::sync::{Arc, Mutex};
-use ui::{viewport::ViewportControls, EditorUi, EditorResult, BrushMode};
+use std::sync::{Arc, Mutex, RwLock};
+use replay::Replay;
+use streamed_terrain::StreamedTerrain;
+use ui::{viewport::{ViewPreset, ViewportControls}, EditorUi, EditorResult, BrushMode, HeightmapRequest};
 use winit::{
     application::ApplicationHandler,
     event::*,
@@ -45,6 +114,9 @@ use winit::{
     window::{Window, WindowId},
 };
 
+/// Minimum time between preference saves while settings keep changing
+const PREFS_SAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
 /// Camera modes for the editor
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum CameraMode {
@@ -61,6 +133,66 @@ struct Args {
     /// Scene file to load (.ron format)
     #[arg(short, long)]
     scene: Option<String>,
+
+    /// Run the scene's simulation without a window and print its final state
+    #[arg(long, requires = "scene")]
+    headless: bool,
+
+    /// Frames to simulate in headless mode (60 per second)
+    #[arg(long, default_value_t = 60)]
+    frames: u32,
+
+    /// In headless mode, also record the state every N frames
+    #[arg(long)]
+    snapshot_every: Option<u32>,
+
+    /// Write the headless state to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<std::path::PathBuf>,
+
+    /// Record a Chrome trace of the first --frames frames to this file
+    #[arg(long)]
+    trace: Option<std::path::PathBuf>,
+
+    /// In headless mode, write the run as a replay to this file
+    #[arg(long)]
+    record: Option<std::path::PathBuf>,
+
+    /// In headless mode, render the last frame through the scene's camera to this PNG
+    #[arg(long, requires = "headless")]
+    screenshot: Option<std::path::PathBuf>,
+
+    /// Width of headless screenshots
+    #[arg(long, default_value_t = 1280)]
+    screenshot_width: u32,
+
+    /// Height of headless screenshots
+    #[arg(long, default_value_t = 720)]
+    screenshot_height: u32,
+
+    /// Play a replay back without a window and check it against the recording
+    #[arg(long)]
+    replay: Option<std::path::PathBuf>,
+
+    /// Bake the scene's navmesh or lightmaps without a window and save them
+    #[arg(long, value_enum, requires = "scene")]
+    bake: Option<bake::BakeTarget>,
+
+    /// Run the scene headless as a dedicated server on this address (e.g. 0.0.0.0:7777)
+    #[arg(long)]
+    serve: Option<String>,
+
+    /// Host a session on this address while in play mode
+    #[arg(long, conflicts_with = "connect")]
+    host: Option<String>,
+
+    /// Join the server at this address while in play mode
+    #[arg(long)]
+    connect: Option<String>,
+
+    /// Player name sent to the server
+    #[arg(long, default_value = "player")]
+    name: String,
 }
 
 struct EditorApp {
@@ -71,28 +203,75 @@ struct EditorApp {
     asset_manager: Option<AssetManager>,
     physics_world: Option<PhysicsWorld>,
     buoyancy_system: Option<BuoyancySystem>,
+    ragdoll_system: Option<RagdollSystem>,
+    character_system: Option<CharacterSystem>,
+    /// Behavior trees of entities with a BehaviorAgent, restarted each play session
+    behavior_system: BehaviorSystem,
+    /// Skinned models and the bone poses of SkinnedMesh entities
+    skeletal_animation: SkeletalAnimationSystem,
     script_system: Option<ScriptSystem>,
     audio_system: Option<AudioSystem>,
     audio_command_queue: AudioCommandQueue,
+    /// Voices of the scene's AudioSource components
+    audio_voices: EntityVoices,
+    /// Save slots and game data for scripts' save_game/load_game
+    save_games: save_games::SaveGames,
+    /// Meshes and terrain the scene's mesh and heightfield colliders are built
+    /// from, gathered when physics is set up
+    collider_geometry: ColliderGeometry,
+    /// Network session for scripts' net_send/net_receive (hosted or joined in play mode)
+    net: net_session::NetSession,
+    /// Whether play mode hosts or joins a session (command line)
+    net_launch: net_session::NetLaunch,
+    /// Manifest of the exported build being played; the editor UI and
+    /// shortcuts are off and play starts as soon as the scene is loaded
+    game: Option<build_export::GameManifest>,
+    /// Systems, render passes and panels from engine plugins (their
+    /// components and MCP tools go to file_ipc)
+    plugins: engine_plugin::PluginRegistry,
+    /// Game code dylibs, reloaded when rebuilt (project.ron native_modules)
+    native_modules: engine_plugin::NativeModules,
+    /// Named music moments (assets/music/moments.ron)
+    music_moments: Option<Arc<MusicMoments>>,
+    /// Music moment being generated in the background
+    pending_music_moment: Option<std::sync::mpsc::Receiver<Result<MomentTrack, AceStepError>>>,
+    /// Named adaptive music tracks (assets/music/adaptive.ron)
+    adaptive_music: Option<AdaptiveMusicConfig>,
     entity_ids: Vec<EntityId>,
-    time: f32,
+    /// Frame time, time scale and pause (shared with scripts)
+    time: SharedTime,
+    /// Random numbers for scripts, reseeded for each play session
+    rng: SharedRng,
+    /// Seed of the current play session (particle emitters fork from it)
+    session_seed: u64,
+    /// Keys and mouse buttons the game sees while playing (shared with scripts)
+    game_input: SharedGameInput,
+    /// Play session being recorded (determinism mode)
+    replay: Option<Replay>,
     ui: Option<EditorUi>,
     egui_state: Option<EguiState>,
     viewport_controls: ViewportControls,
     hot_reload: Option<HotReloadWatcher>,
     script_paths: std::collections::HashMap<EntityId, std::path::PathBuf>,
     ipc_channel: Option<ipc::IpcChannel>,
-    file_ipc: Option<file_ipc::FileIpcHandler>,
+    file_ipc: Option<file_ipc::McpIpcHandler>,
     scene_file_path: Option<String>,
     modifiers: winit::keyboard::ModifiersState,
     undo_history: UndoHistory,
+    /// Mesh BVHs for precise viewport picking
+    mesh_picker: picking::MeshPicker,
+    /// Selected entities being dragged in the viewport
+    entity_drag: Option<placement::EntityDrag>,
     /// Clipboard for copy/paste of entities
     clipboard: Option<engine_scene::scene_data::SerializedEntity>,
-    /// Terrain heightmap undo stack
-    terrain_undo_stack: Vec<Vec<f32>>,
-    terrain_redo_stack: Vec<Vec<f32>>,
-    /// Flag to track if we're currently sculpting (to save undo state on first brush stroke)
+    /// Flag to track if we're currently sculpting (one undo step per brush stroke)
     terrain_sculpt_started: bool,
+    /// An inspector value drag is in progress (its edits merge into one undo step)
+    inspector_edit_dragging: bool,
+    /// Water tool drag state (level handle, flow stroke)
+    water_edit: water_edit::WaterEditState,
+    /// Spline tool drag state (control point being moved)
+    spline_edit: spline_edit::SplineEditState,
     /// Current camera mode (Editor, Player, or TopDown)
     camera_mode: CameraMode,
     /// Player position in world space
@@ -121,6 +300,34 @@ struct EditorApp {
     topdown_edge_scroll_margin: f32,
     /// Top-down rotation key states: [Q (left), E (right)]
     topdown_rotation_keys: [bool; 2],
+    /// Play-in-editor state (physics, scripts and particles only run while playing)
+    play_state: PlayState,
+    /// Snapshot of the authored scene while a play session is active
+    play_session: Option<PlaySession>,
+    /// Undo history of the authored scene, set aside while a play session
+    /// has its own so Stop doesn't leave play-mode edits to undo
+    edit_undo_history: Option<UndoHistory>,
+    /// Play/Pause/Stop request from the toolbar, applied at the start of the next frame
+    pending_play_request: Option<PlayRequest>,
+    /// Frames left in an MCP step_simulation; the simulation pauses when it runs out
+    frames_to_step: Option<u32>,
+    /// Screenshot or sequence requested from the menu, F12 or the trigger file
+    pending_capture: Option<capture::CaptureRequest>,
+    /// Capture being rendered and read back over the next frames
+    capture_job: Option<capture::CaptureJob>,
+    /// Frame rate cap, smoothed frame time and fixed-step accumulator
+    /// (settings come from the preferences)
+    frame_pacer: FramePacer,
+    /// Shadow map rendering (console `shadows` command)
+    shadows_enabled: bool,
+    /// Editor preferences as last loaded/saved
+    prefs: EditorPrefs,
+    /// When preferences were last saved (saves are throttled)
+    prefs_saved_at: std::time::Instant,
+    /// Recovery copies of the scene while editing
+    autosave: autosave::Autosave,
+    /// Last log record shown in the console
+    console_log_sequence: u64,
 }
 
 struct EguiState {
@@ -134,28 +341,76 @@ struct WgpuState {
     surface: wgpu::Surface<'static>,
     renderer: Renderer,
     mesh_manager: MeshManager,
+    /// Models whose LODs are still being simplified on the loader threads
+    pending_model_lods: Vec<String>,
     texture_manager: TextureManager,
     material_manager: MaterialManager,
+    /// Materials drawing with white in place of textures still loading, by path
+    loading_materials: Vec<(String, Vec<AssetHandle<Texture>>)>,
+    /// Block-compressed material textures saved between runs
+    texture_cache: CompressedTextureCache,
+    /// Textures drawing uncompressed while their block-compressed encode runs
+    compressing_textures: Vec<(TextureHandle, AssetHandle<CompressedTexture>)>,
     depth_texture: wgpu::TextureView,
     msaa_texture: wgpu::TextureView,
     skybox: Option<Skybox>,
     shadow_map: Option<ShadowMap>,
     water_renderer: Option<WaterRenderer>,
+    /// Mirrored scene the water samples its reflections from
+    water_reflection: WaterReflection,
     camera_bind_group_layout: wgpu::BindGroupLayout,
     camera_bind_group: Option<wgpu::BindGroup>,
     camera_uniform_buffer: wgpu::Buffer,
-    framebuffer: Option<Framebuffer>,
-    post_process_pipeline: Option<PostProcessPipeline>,
+    /// HDR target the scene renders into before post-processing
+    framebuffer: Framebuffer,
+    bloom_chain: BloomChain,
+    /// This frame's mesh draws grouped by mesh and material
+    mesh_batches: DrawBatches,
+    /// This frame's water reflection draws, culled against the mirrored camera
+    reflection_batches: DrawBatches,
+    post_process_pipeline: PostProcessPipeline,
+    /// Transient targets of the render graph (e.g. the tone mapped scene before FXAA)
+    render_targets: TransientPool,
+    taa: TemporalAa,
+    /// World matrices of last frame's visible meshes, for TAA motion vectors
+    previous_world_matrices: std::collections::HashMap<EntityId, glam::Mat4>,
     particle_renderer: Option<ParticleRenderer>,
     particle_systems: std::collections::HashMap<EntityId, engine_particles::ParticleSystem>,
     particle_compute_pipelines: std::collections::HashMap<EntityId, engine_particles::ParticleComputePipeline>,
     /// Foliage renderer for instanced vegetation
     foliage_renderer: Option<FoliageRenderer>,
+    grass_renderer: Option<GrassRenderer>,
+    /// GPU skinning for animated characters
+    skinned_renderer: Option<SkinnedRenderer>,
+    /// Infinite reference grid drawn on the ground plane
+    grid_renderer: Option<GridRenderer>,
+    /// GPU pass timings for the profiler (no-op without timestamp query support)
+    gpu_profiler: GpuProfiler,
+    /// Evicts streamed meshes, textures and materials over the project's budget
+    memory_budget: MemoryBudget,
     /// Terrain heightmap for terrain-aware water
     terrain_heightmap: Option<HeightMap>,
     terrain_config: Option<TerrainConfig>,
-    /// Computed terrain water bodies for rendering
+    /// Painted texture layer weights, on the heightmap grid
+    terrain_splatmap: Option<SplatMap>,
+    /// Texture layers the splatmap blends between
+    terrain_layers: Vec<TerrainLayer>,
+    /// How the uploaded terrain meshes divide the heightmap into chunks
+    terrain_chunks: Option<TerrainChunks>,
+    /// Draws the terrain mesh with its painted layers blended
+    terrain_renderer: Option<TerrainRenderer>,
+    /// Splatmap changed since it was last uploaded to the terrain renderer
+    terrain_splatmap_dirty: bool,
+    /// Heightmap changed since it was last uploaded to the grass renderer
+    grass_terrain_dirty: bool,
+    /// Heightmap tiles streamed around the camera for a TerrainStreaming world
+    streamed_terrain: Option<StreamedTerrain>,
+    /// Computed terrain water bodies for rendering, spline rivers included
     terrain_water_bodies: Vec<TerrainWaterBodyInfo>,
+    /// Road meshes along splines with their material paths
+    spline_roads: Vec<(String, String)>,
+    /// Names of the uploaded spline meshes, dropped when they are rebuilt
+    spline_meshes: Vec<String>,
     /// Flag to regenerate terrain on next frame
     terrain_needs_regeneration: bool,
     /// Flag to regenerate water on next frame
@@ -168,6 +423,176 @@ struct TerrainWaterBodyInfo {
     surface_level: f32,
     flow_direction: Option<[f32; 2]>,
     flow_speed: f32,
+    /// Bounds in heightmap grid cells (x, z) with the surface level as y
+    bounds_min: Vec3,
+    bounds_max: Vec3,
+}
+
+/// Height of the water that gets a planar reflection this frame: the highest
+/// surface below the camera (reflections are drawn for one plane)
+fn reflection_plane_height(heights: impl IntoIterator<Item = f32>, camera_y: f32) -> Option<f32> {
+    heights.into_iter().filter(|&height| height < camera_y).reduce(f32::max)
+}
+
+/// Upload the meshes of some terrain chunks at every LOD level, tinted by
+/// the painted layers if there is a splatmap
+#[allow(clippy::too_many_arguments)]
+fn upload_terrain_chunks(
+    mesh_manager: &mut MeshManager,
+    device: &wgpu::Device,
+    heightmap: &HeightMap,
+    config: &TerrainConfig,
+    splatmap: Option<&SplatMap>,
+    layers: &[TerrainLayer],
+    chunks: impl IntoIterator<Item = TerrainChunk>,
+    mesh_name: impl Fn(&TerrainChunk, usize) -> String,
+) {
+    let layout = TerrainChunks::new(heightmap.width, heightmap.depth);
+    for chunk in chunks {
+        for lod in 0..TERRAIN_LOD_LEVELS {
+            let mesh = Terrain::generate_chunk_mesh(heightmap, config, splatmap.map(|splatmap| (splatmap, layers)), &layout, chunk, lod);
+            let gpu_vertices = convert_mesh_to_gpu(&mesh);
+            mesh_manager.replace_mesh(device, mesh_name(&chunk, lod), &gpu_vertices, &mesh.indices);
+        }
+    }
+}
+
+/// Write the painted splatmap next to a saved scene, or remove a stale one
+/// if the scene has no terrain
+fn save_scene_splatmap(splatmap: Option<&SplatMap>, scene_path: &str) {
+    let path = SplatMap::path_for_scene(scene_path);
+    let saved = match splatmap {
+        Some(splatmap) => splatmap.save(&path),
+        None if path.exists() => std::fs::remove_file(&path).map_err(Into::into),
+        None => Ok(()),
+    };
+    if let Err(e) = saved {
+        log::error!("Failed to save splatmap: {:#}", e);
+    }
+}
+
+/// Upload every chunk of the editor's terrain, dropping the chunks of a
+/// terrain of another size
+fn rebuild_terrain_chunks(wgpu_state: &mut WgpuState) {
+    let (Some(heightmap), Some(config)) = (&wgpu_state.terrain_heightmap, &wgpu_state.terrain_config) else {
+        return;
+    };
+    let layout = TerrainChunks::new(heightmap.width, heightmap.depth);
+    if let Some(old) = wgpu_state.terrain_chunks.filter(|old| *old != layout) {
+        for chunk in old.chunks() {
+            for lod in 0..TERRAIN_LOD_LEVELS {
+                if let Some(handle) = wgpu_state.mesh_manager.get_handle(&chunk.mesh_name(lod)) {
+                    wgpu_state.mesh_manager.evict(handle);
+                }
+            }
+        }
+    }
+    upload_terrain_chunks(
+        &mut wgpu_state.mesh_manager,
+        &wgpu_state.renderer.device,
+        heightmap,
+        config,
+        wgpu_state.terrain_splatmap.as_ref(),
+        &wgpu_state.terrain_layers,
+        layout.chunks(),
+        TerrainChunk::mesh_name,
+    );
+    wgpu_state.terrain_chunks = Some(layout);
+    wgpu_state.grass_terrain_dirty = true;
+}
+
+/// Upload the river and road meshes along the scene's splines, replacing the
+/// previous ones. Rivers join the terrain water bodies.
+fn rebuild_spline_meshes(wgpu_state: &mut WgpuState, scene: &Scene) {
+    for name in wgpu_state.spline_meshes.drain(..) {
+        if let Some(handle) = wgpu_state.mesh_manager.get_handle(&name) {
+            wgpu_state.mesh_manager.evict(handle);
+        }
+    }
+    wgpu_state.spline_roads.clear();
+    let (Some(heightmap), Some(config)) = (&wgpu_state.terrain_heightmap, &wgpu_state.terrain_config) else {
+        return;
+    };
+
+    let cell_size = config.scale / config.width as f32;
+    for spline_mesh in spline_edit::build_spline_meshes(scene, heightmap, config) {
+        let mesh = spline_mesh.mesh;
+        if mesh.indices.is_empty() {
+            continue;
+        }
+        let gpu_vertices = convert_mesh_to_gpu(&mesh);
+        wgpu_state.mesh_manager.replace_mesh(&wgpu_state.renderer.device, mesh.name.clone(), &gpu_vertices, &mesh.indices);
+        match spline_mesh.kind {
+            SplineKind::River => {
+                // Bounds in grid cells like the filled water bodies
+                let (min, max) = mesh.vertices.iter().fold((Vec3::MAX, Vec3::MIN), |(min, max), vertex| {
+                    (min.min(vertex.position), max.max(vertex.position))
+                });
+                let to_grid = |point: Vec3| Vec3::new((point.x + config.scale * 0.5) / cell_size, point.y, (point.z + config.scale * 0.5) / cell_size);
+                wgpu_state.terrain_water_bodies.push(TerrainWaterBodyInfo {
+                    mesh_name: mesh.name.clone(),
+                    surface_level: max.y,
+                    // Texture v runs downstream; the water shader scrolls against the flow vector
+                    flow_direction: Some([0.0, -1.0]),
+                    flow_speed: spline_mesh.flow_speed,
+                    bounds_min: to_grid(min),
+                    bounds_max: to_grid(max),
+                });
+            }
+            SplineKind::Road => {
+                let material_path = spline_mesh.material_path.unwrap_or_else(|| "materials/default.mat".to_string());
+                wgpu_state.spline_roads.push((mesh.name.clone(), material_path));
+            }
+        }
+        wgpu_state.spline_meshes.push(mesh.name);
+    }
+}
+
+/// Terrain chunk meshes to draw, each at the LOD level for its distance
+/// from the camera. With a frustum, chunks outside it are culled.
+fn terrain_chunk_meshes(
+    mesh_manager: &MeshManager,
+    layout: &TerrainChunks,
+    config: &TerrainConfig,
+    world_matrix: glam::Mat4,
+    camera_position: Vec3,
+    mut frustum: Option<(&Frustum, &mut CullingStats)>,
+    mesh_name: impl Fn(&TerrainChunk, usize) -> String,
+) -> Vec<MeshHandle> {
+    let chunk_size = TerrainChunks::chunk_size(config);
+    layout
+        .chunks()
+        .filter_map(|chunk| {
+            let bounds = mesh_manager.get_mesh(mesh_manager.get_handle(&mesh_name(&chunk, 0))?)?.bounds.transform(world_matrix);
+            if let Some((frustum, culling)) = frustum.as_mut() {
+                let visible = frustum.contains_aabb(bounds.min, bounds.max);
+                culling.record(visible);
+                if !visible {
+                    return None;
+                }
+            }
+            let distance = camera_position.clamp(bounds.min, bounds.max).distance(camera_position);
+            let lod = TerrainChunks::lod_for_distance(distance, chunk_size);
+            mesh_manager.get_handle(&mesh_name(&chunk, lod))
+        })
+        .collect()
+}
+
+/// Undo (or redo) one step of the history and refresh what it changed:
+/// the terrain mesh for brush strokes, the water fill for scene edits
+fn step_undo_history(history: &mut UndoHistory, scene: &mut Scene, wgpu_state: &mut WgpuState, redo: bool) -> Option<UndoTarget> {
+    let terrain = wgpu_state.terrain_heightmap.as_mut().map(|heightmap| (heightmap, wgpu_state.terrain_splatmap.as_mut()));
+    let target = if redo { history.redo(scene, terrain)? } else { history.undo(scene, terrain)? };
+
+    match target {
+        UndoTarget::Terrain => {
+            rebuild_terrain_chunks(wgpu_state);
+            wgpu_state.terrain_splatmap_dirty = true;
+        }
+        // Water tool edits live in the scene; recompute the fill
+        UndoTarget::Scene => wgpu_state.water_needs_regeneration = true,
+    }
+    Some(target)
 }
 
 // Uniforms and push constants now handled by renderer
@@ -208,8 +633,10 @@ fn raycast_terrain(
             // Clamp to terrain bounds
             let half_width = (config.width as f32 * config.scale) / 2.0;
             let half_depth = (config.depth as f32 * config.scale) / 2.0;
+            // Rays fall through holes into whatever is below
             if hit_point.x >= -half_width && hit_point.x <= half_width
                 && hit_point.z >= -half_depth && hit_point.z <= half_depth
+                && !heightmap.hole_at(hit_point.x, hit_point.z, config.scale)
             {
                 return Some(hit_point);
             }
@@ -223,45 +650,6 @@ fn raycast_terrain(
     None
 }
 
-/// Pick an entity by raycasting against entity bounding boxes
-/// Returns the EntityId and distance of the closest entity hit, if any
-fn pick_entity(
-    ray_origin: Vec3,
-    ray_direction: Vec3,
-    scene: &Scene,
-) -> Option<(EntityId, f32)> {
-    let mut closest: Option<(EntityId, f32)> = None;
-
-    for entity in scene.entities() {
-        // Skip entities without mesh renderers (they have no visual representation)
-        if !entity.has_component::<MeshRenderer>() {
-            continue;
-        }
-
-        // Create bounding box from entity transform
-        // Use scale as half-extents for a rough bounding box
-        let pos = entity.transform.position;
-        let scale = entity.transform.scale;
-
-        // Create AABB centered at entity position with scale as half-extents
-        let aabb = AABB::from_center_extents(pos, scale);
-
-        // Test ray intersection
-        if let Some(t) = aabb.ray_intersect(ray_origin, ray_direction) {
-            // Check if this is closer than the current closest hit
-            if let Some((_, closest_t)) = closest {
-                if t < closest_t {
-                    closest = Some((entity.id, t));
-                }
-            } else {
-                closest = Some((entity.id, t));
-            }
-        }
-    }
-
-    closest
-}
-
 // Helper function to convert CPU mesh to GPU vertex format
 fn convert_mesh_to_gpu(mesh: &Mesh) -> Vec<GpuVertex> {
     mesh.vertices
@@ -280,8 +668,535 @@ fn convert_mesh_to_gpu(mesh: &Mesh) -> Vec<GpuVertex> {
         .collect()
 }
 
+/// Share of the view height below which each generated model LOD draws
+const MODEL_LOD_SCREEN_SIZES: [f32; 3] = [0.25, 0.1, 0.04];
+
+/// How model LODs are simplified, one level per screen size
+fn model_lod_settings() -> LodSettings {
+    LodSettings { levels: MODEL_LOD_SCREEN_SIZES.len(), ..LodSettings::default() }
+}
+
+/// Name a model's mesh is uploaded under: the model's path, with the mesh's
+/// index for models of several meshes
+fn model_mesh_name(path: &str, index: usize, mesh_count: usize) -> String {
+    if mesh_count == 1 {
+        path.to_string()
+    } else {
+        format!("{}#{}", path, index)
+    }
+}
+
+/// Upload a model mesh (or one of its LODs) the memory budget may evict,
+/// since models can be loaded again from their file
+fn upload_model_mesh(mesh_manager: &mut MeshManager, device: &wgpu::Device, name: String, mut mesh: Mesh) {
+    mesh.calculate_tangents();
+    let gpu_vertices = convert_mesh_to_gpu(&mesh);
+    let handle = mesh_manager.upload_mesh(device, name, &gpu_vertices, &mesh.indices);
+    mesh_manager.set_streamed(handle, true);
+}
+
+/// Whether the glTF model at `path` is skinned. The skin is imported from the
+/// asset manager's parsed document, so the file is only read once.
+fn load_skinned_model(
+    asset_manager: &mut AssetManager,
+    skeletal_animation: &mut SkeletalAnimationSystem,
+    path: &str,
+) -> bool {
+    if let Some(skinned) = skeletal_animation.is_skinned(path) {
+        return skinned;
+    }
+    if let Err(e) = asset_manager.load_gltf(path) {
+        log::debug!("{:#}", e);
+        return false;
+    }
+    let model = asset_manager.skinned_gltf(path).and_then(|gltf| {
+        SkinnedModel::from_gltf(&gltf.document, &gltf.buffers)
+            .map_err(|e| log::warn!("Failed to import skin from '{}': {:#}", path, e))
+            .ok()
+    });
+    match model {
+        Some(model) => {
+            skeletal_animation.insert_model(path, model);
+            true
+        }
+        None => {
+            skeletal_animation.insert_unskinned(path);
+            false
+        }
+    }
+}
+
+/// A mesh of an uploaded model and the names of its generated LODs, nearest first
+struct ModelMesh {
+    name: String,
+    lods: Vec<String>,
+}
+
+/// Load a glTF model through the asset manager and upload its meshes to the GPU.
+/// Single-mesh models are registered under their path, multi-mesh models as
+/// `path#index`. Their simplified LODs, `<mesh name>#lod<level>`, are made on the
+/// loader threads; the model waits in `pending_lods` until upload_model_lods
+/// uploads them, and draws without them until then.
+fn upload_model_meshes(
+    asset_manager: &mut AssetManager,
+    mesh_manager: &mut MeshManager,
+    device: &wgpu::Device,
+    path: &str,
+    pending_lods: &mut Vec<String>,
+) -> Result<Vec<ModelMesh>> {
+    let handle = asset_manager.load_gltf(path)?;
+    let meshes = handle.get();
+    let lod_settings = model_lod_settings();
+    let mut uploaded = Vec::with_capacity(meshes.len());
+    for (i, mesh) in meshes.iter().enumerate() {
+        let name = model_mesh_name(path, i, meshes.len());
+        // Named up front: levels the simplifier stops short of are skipped
+        // like ones still being made
+        let lods = if mesh.indices.len() / 3 < lod_settings.min_triangles {
+            Vec::new()
+        } else {
+            (1..=lod_settings.levels).map(|level| format!("{}#lod{}", name, level)).collect()
+        };
+        upload_model_mesh(mesh_manager, device, name.clone(), mesh.clone());
+        uploaded.push(ModelMesh { name, lods });
+    }
+    asset_manager.load_lods_async(path, lod_settings);
+    if !pending_lods.iter().any(|pending| pending == path) {
+        pending_lods.push(path.to_string());
+    }
+    Ok(uploaded)
+}
+
+/// Upload the LODs of the models in `pending_lods` whose simplification finished
+fn upload_model_lods(
+    asset_manager: &mut AssetManager,
+    mesh_manager: &mut MeshManager,
+    device: &wgpu::Device,
+    pending_lods: &mut Vec<String>,
+) {
+    pending_lods.retain(|path| {
+        let handle = asset_manager.load_lods_async(path, model_lod_settings());
+        match handle.state() {
+            LoadState::Loading => return true,
+            LoadState::Failed(e) => log::error!("Failed to generate LODs for {}: {}", path, e),
+            LoadState::Loaded => {
+                let models = handle.get();
+                for (i, lods) in models.iter().enumerate() {
+                    let name = model_mesh_name(path, i, models.len());
+                    for (level, lod) in lods.iter().enumerate() {
+                        upload_model_mesh(mesh_manager, device, format!("{}#lod{}", name, level + 1), lod.clone());
+                    }
+                }
+            }
+        }
+        false
+    });
+}
+
+/// Light space of the shadow map: cast away from the `sky`'s sun when the
+/// scene has a SunLight, fitted around every entity
+fn shadow_light_space_matrix(scene: &Scene, has_sun: bool, sky: &ProceduralSky) -> glam::Mat4 {
+    // Shadows are cast away from the sun, kept from lying flat near the horizon;
+    // without a SunLight the light comes from a lower angle for more visible shadows
+    let light_direction = if has_sun {
+        let direction = -sky.sun_direction;
+        glam::Vec3::new(direction.x, direction.y.min(-0.1), direction.z).normalize()
+    } else {
+        glam::Vec3::new(0.8, -0.5, 0.4).normalize()
+    };
+
+    // Calculate actual scene bounds from all entities
+    let mut min_bounds = glam::Vec3::splat(f32::MAX);
+    let mut max_bounds = glam::Vec3::splat(f32::MIN);
+    let mut has_entities = false;
+
+    for entity in scene.entities() {
+        let pos = entity.transform.position;
+        min_bounds = min_bounds.min(pos);
+        max_bounds = max_bounds.max(pos);
+        has_entities = true;
+    }
+
+    // Fallback if no entities
+    if !has_entities {
+        min_bounds = glam::Vec3::splat(-10.0);
+        max_bounds = glam::Vec3::splat(10.0);
+    }
+
+    // Expand bounds slightly for margin
+    min_bounds -= glam::Vec3::splat(2.0);
+    max_bounds += glam::Vec3::splat(2.0);
+
+    let scene_center = (min_bounds + max_bounds) * 0.5;
+    let scene_radius = (max_bounds - min_bounds).length() * 0.5;
+
+    ShadowMap::calculate_light_space_matrix(
+        light_direction,
+        scene_center,
+        scene_radius,
+    )
+}
+
+/// Upload all glTF models referenced by MeshRenderers in the scene
+fn upload_scene_models(
+    scene: &Scene,
+    asset_manager: &mut AssetManager,
+    mesh_manager: &mut MeshManager,
+    device: &wgpu::Device,
+    pending_lods: &mut Vec<String>,
+) {
+    let mut model_paths: Vec<String> = scene
+        .entities()
+        .filter_map(|e| e.get_component::<MeshRenderer>())
+        .flat_map(mesh_renderer_paths)
+        .map(|path| path.split('#').next().unwrap_or_default().to_string())
+        .filter(|p| p.ends_with(".gltf") || p.ends_with(".glb"))
+        .collect();
+    model_paths.sort();
+    model_paths.dedup();
+
+    for path in model_paths {
+        if let Err(e) = upload_model_meshes(asset_manager, mesh_manager, device, &path, pending_lods) {
+            log::error!("Failed to load model {}: {}", path, e);
+        }
+    }
+
+    // Baked lighting replaces the plain mesh of each instance that has one
+    for mesh_renderer in scene.entities().filter_map(|e| e.get_component::<MeshRenderer>()) {
+        let Some(lightmap_path) = &mesh_renderer.lightmap_path else {
+            continue;
+        };
+        match lighting::load_baked_mesh(asset_manager, mesh_renderer) {
+            Ok(Some(mesh)) => upload_baked_mesh(mesh_manager, device, lightmap_path, mesh),
+            Ok(None) => {}
+            Err(e) => log::error!("Failed to load baked lighting {}: {}", lightmap_path, e),
+        }
+    }
+}
+
+/// Upload again the models the memory budget evicted that the scene still uses
+fn reupload_evicted_models(
+    scene: &Scene,
+    asset_manager: &mut AssetManager,
+    mesh_manager: &mut MeshManager,
+    device: &wgpu::Device,
+    pending_lods: &mut Vec<String>,
+) {
+    let mut model_paths: Vec<String> = scene
+        .entities()
+        .filter_map(|e| e.get_component::<MeshRenderer>())
+        .flat_map(mesh_renderer_paths)
+        .filter(|path| mesh_manager.was_evicted(path))
+        .map(|path| path.split('#').next().unwrap_or_default().to_string())
+        .collect();
+    model_paths.sort();
+    model_paths.dedup();
+    for path in model_paths {
+        if let Err(e) = upload_model_meshes(asset_manager, mesh_manager, device, &path, pending_lods) {
+            log::error!("Failed to reload model {}: {}", path, e);
+        }
+    }
+}
+
+/// Upload a mesh with baked lighting under its lightmap asset path
+fn upload_baked_mesh(mesh_manager: &mut MeshManager, device: &wgpu::Device, lightmap_path: &str, mut mesh: Mesh) {
+    mesh.calculate_tangents();
+    let gpu_vertices = convert_mesh_to_gpu(&mesh);
+    mesh_manager.replace_mesh(device, lightmap_path.to_string(), &gpu_vertices, &mesh.indices);
+}
+
+/// GPU mesh to draw for a MeshRenderer, preferring its baked lighting once uploaded
+fn mesh_renderer_handle(mesh_manager: &MeshManager, mesh_renderer: &MeshRenderer) -> Option<MeshHandle> {
+    mesh_renderer
+        .lightmap_path
+        .as_deref()
+        .and_then(|path| mesh_manager.get_handle(path))
+        .or_else(|| mesh_manager.get_handle(&mesh_renderer.mesh_path))
+}
+
+/// Where block-compressed material textures are cached, relative to the project
+const TEXTURE_CACHE_DIR: &str = ".cache/textures";
+
+/// A scene material's GPU handle. The material and its textures load in the background:
+/// the default material draws until the material file is in, and white stands in for
+/// textures still loading until `refresh_loaded_materials` uploads the material again.
+fn scene_material(wgpu_state: &mut WgpuState, asset_manager: &mut AssetManager, material_path: &str) -> MaterialHandle {
+    if let Some(handle) = wgpu_state.material_manager.get_handle(material_path) {
+        return handle;
+    }
+
+    // Load material from asset manager
+    let material_asset = asset_manager.load_material_async(material_path);
+    if material_asset.is_loading() {
+        return wgpu_state.material_manager.default_material_handle();
+    }
+    if let LoadState::Failed(e) = material_asset.state() {
+        log::warn!("Failed to load material '{}': {}, using default", material_path, e);
+    }
+    let default_material = Material::default();
+    let material = material_asset.try_get().unwrap_or(&default_material);
+
+    // Load required textures, white until they are in
+    let mut loading = Vec::new();
+    let mut texture = |path: &Option<String>, kind: TextureKind| {
+        let white = wgpu_state.texture_manager.white_texture_handle();
+        let Some(path) = path.as_deref() else {
+            return white;
+        };
+        if let Some(handle) = wgpu_state.texture_manager.get_handle(path) {
+            return handle;
+        }
+        let tex_handle = asset_manager.load_texture_async(path);
+        match tex_handle.try_get() {
+            Some(texture) => {
+                let (device, queue) = (&wgpu_state.renderer.device, &wgpu_state.renderer.queue);
+                // Block-compressed where the GPU supports it, encoded once on the loader threads
+                // and cached on disk; mips past the base stream in as the texture grows on screen
+                let compressed = TextureManager::compressed_format(device, texture, kind)
+                    .map(|format| asset_manager.compress_texture_async(path, format, &wgpu_state.texture_cache));
+                let mips = match compressed.as_ref().and_then(AssetHandle::try_get) {
+                    Some(compressed) => Some(StreamedMips::from_compressed(compressed.clone())),
+                    None => StreamedMips::from_texture(texture),
+                };
+                let handle = match mips {
+                    Some(mips) => wgpu_state.texture_manager.upload_streamed(device, queue, path.to_string(), mips),
+                    None => wgpu_state.texture_manager.upload_texture(device, queue, path.to_string(), texture),
+                };
+                // The uncompressed texture draws until the encode is done
+                if let Some(compressed) = compressed.filter(AssetHandle::is_loading) {
+                    wgpu_state.compressing_textures.push((handle, compressed));
+                }
+                handle
+            }
+            None => {
+                if tex_handle.is_loading() {
+                    loading.push(tex_handle.clone());
+                }
+                white
+            }
+        }
+    };
+    let albedo_handle = texture(&material.albedo_texture, TextureKind::Color);
+    let normal_handle = texture(&material.normal_texture, TextureKind::Normal);
+    let metallic_roughness_handle = texture(&material.metallic_roughness_texture, TextureKind::Color);
+    let ao_handle = texture(&material.ao_texture, TextureKind::Color);
+    if !loading.is_empty() {
+        wgpu_state.loading_materials.push((material_path.to_string(), loading));
+    }
+
+    // Upload material to GPU
+    let material_handle = wgpu_state.material_manager.upload_material(
+        &wgpu_state.renderer.device,
+        &wgpu_state.texture_manager,
+        material_path.to_string(),
+        material,
+        albedo_handle,
+        Some(normal_handle),
+        Some(metallic_roughness_handle),
+        Some(ao_handle),
+    );
+
+    // Loaded from asset files, so the memory budget may evict them
+    wgpu_state
+        .material_manager
+        .set_streamed(material_handle, true);
+    for texture in [
+        albedo_handle,
+        normal_handle,
+        metallic_roughness_handle,
+        ao_handle,
+    ] {
+        if texture != wgpu_state.texture_manager.white_texture_handle() {
+            wgpu_state.texture_manager.set_streamed(texture, true);
+        }
+    }
+    material_handle
+}
+
+/// Ask for the texture mips a material needs on an object `radius` across at `distance`
+fn request_material_mips(wgpu_state: &mut WgpuState, material: MaterialHandle, radius: f32, distance: f32, fov: f32) {
+    let viewport_height = wgpu_state.renderer.surface_config.height as f32;
+    let screen_pixels = radius / (distance.max(0.01) * (fov * 0.5).tan()) * viewport_height;
+    if let Some(gpu_material) = wgpu_state.material_manager.get_material(material) {
+        for texture in gpu_material.textures() {
+            wgpu_state.texture_manager.request_mips(texture, screen_pixels);
+        }
+    }
+}
+
+/// Upload again the materials that drew with stand-in textures, once those textures are in
+fn refresh_loaded_materials(wgpu_state: &mut WgpuState) {
+    let material_manager = &mut wgpu_state.material_manager;
+    wgpu_state.loading_materials.retain(|(material_path, textures)| {
+        let still_loading = textures.iter().any(|texture| texture.is_loading());
+        if !still_loading {
+            material_manager.invalidate(material_path);
+        }
+        still_loading
+    });
+}
+
+/// Swap in the block-compressed textures whose encode finished for the
+/// uncompressed ones drawing meanwhile
+fn swap_compressed_textures(wgpu_state: &mut WgpuState) {
+    let (device, queue) = (&wgpu_state.renderer.device, &wgpu_state.renderer.queue);
+    let texture_manager = &mut wgpu_state.texture_manager;
+    let mut swapped = Vec::new();
+    wgpu_state.compressing_textures.retain(|(handle, compressed)| {
+        if compressed.is_loading() {
+            return true;
+        }
+        // A failed encode keeps the uncompressed texture
+        if let Some(compressed) = compressed.try_get() {
+            if texture_manager.replace_streamed(device, queue, *handle, StreamedMips::from_compressed(compressed.clone())) {
+                swapped.push(*handle);
+            }
+        }
+        false
+    });
+    wgpu_state.material_manager.rebind_textures(device, &wgpu_state.texture_manager, &swapped);
+}
+
+/// Mesh paths a MeshRenderer draws, its LOD meshes included
+fn mesh_renderer_paths(mesh_renderer: &MeshRenderer) -> impl Iterator<Item = &str> {
+    std::iter::once(mesh_renderer.mesh_path.as_str())
+        .chain(mesh_renderer.lods.iter().map(|lod| lod.mesh_path.as_str()))
+}
+
+/// The LOD of a mesh renderer to draw from `camera_position`, and near a LOD
+/// switch the level it is fading into
+fn mesh_renderer_lod_draws(
+    mesh_manager: &MeshManager,
+    mesh_renderer: &MeshRenderer,
+    base: MeshHandle,
+    radius: f32,
+    fov: f32,
+    camera_position: Vec3,
+    position: Vec3,
+) -> (LodDraw, Option<LodDraw>) {
+    if mesh_renderer.lods.is_empty() {
+        return (LodDraw { mesh: base, level: 0, fade: 1.0 }, None);
+    }
+    mesh_renderer_lod(mesh_manager, mesh_renderer, base, radius, fov)
+        .select_lod_crossfade(distance_squared(camera_position, position))
+        .expect("LOD0 is always present")
+}
+
+/// LOD levels of a MeshRenderer drawing `base` up close, skipping LOD meshes not uploaded yet.
+/// Screen size levels switch by the `radius` of the mesh bounds seen with a vertical `fov`.
+fn mesh_renderer_lod(mesh_manager: &MeshManager, mesh_renderer: &MeshRenderer, base: MeshHandle, radius: f32, fov: f32) -> LodConfig {
+    let mut lod = LodConfig::new()
+        .add_level(base, 0.0)
+        .with_transition_width(mesh_renderer.lod_transition);
+    for level in &mesh_renderer.lods {
+        if let Some(handle) = mesh_manager.get_handle(&level.mesh_path) {
+            lod = match level.screen_size {
+                Some(screen_size) => lod.add_level_at_screen_size(handle, screen_size, radius, fov),
+                None => lod.add_level(handle, level.distance),
+            };
+        }
+    }
+    lod
+}
+
+/// MeshRenderer for a model mesh, switching to its generated LODs as it shrinks on screen
+fn model_mesh_renderer(mesh: &ModelMesh) -> MeshRenderer {
+    mesh.lods
+        .iter()
+        .zip(MODEL_LOD_SCREEN_SIZES)
+        .fold(MeshRenderer::new(mesh.name.clone()), |renderer, (lod, screen_size)| {
+            renderer.with_screen_lod(lod.clone(), screen_size)
+        })
+}
+
+/// Whether the shadow map's light casts shadows: the sun's Light, else the
+/// first directional Light, with Cast Shadows on (or no such Light at all)
+fn sun_casts_shadows(scene: &Scene) -> bool {
+    let light = match engine_scene::time_of_day::find_sun_light(scene) {
+        Some((id, _)) => scene.get_entity(id).and_then(|e| e.get_component::<Light>()),
+        None => scene
+            .entities()
+            .filter_map(|e| e.get_component::<Light>().map(|light| (e.id, light)))
+            .filter(|(_, light)| matches!(light.light_type, engine_scene::components::LightType::Directional { .. }))
+            .min_by_key(|(id, _)| id.0)
+            .map(|(_, light)| light),
+    };
+    light.is_none_or(|light| light.cast_shadows)
+}
+
+/// Adds a quick-create preset's components to its new entity
+type AddComponents = Box<dyn FnOnce(&mut engine_scene::entity::Entity)>;
+
+/// Create a preset entity (hierarchy quick-create, console `spawn`). Returns its id and name.
+fn create_quick_entity(scene: &mut Scene, quick_type: ui::hierarchy::QuickEntityType, position: Vec3) -> (EntityId, &'static str) {
+    use ui::hierarchy::QuickEntityType;
+    let (name, add_components): (&'static str, AddComponents) = match quick_type {
+        QuickEntityType::Empty => ("Empty", Box::new(|_| {})),
+        QuickEntityType::Cube => ("Cube", Box::new(|entity| {
+            entity.add_component(MeshRenderer::new("cube".to_string()));
+        })),
+        QuickEntityType::Sphere => ("Sphere", Box::new(|entity| {
+            entity.add_component(MeshRenderer::new("sphere".to_string()));
+        })),
+        QuickEntityType::PointLight => ("Point Light", Box::new(|entity| {
+            entity.add_component(Light::point([1.0, 1.0, 1.0], 1.0, 10.0));
+        })),
+        QuickEntityType::DirectionalLight => ("Directional Light", Box::new(|entity| {
+            entity.add_component(Light::directional([0.0, -1.0, 0.0], [1.0, 1.0, 1.0], 1.0));
+        })),
+        QuickEntityType::Camera => ("Camera", Box::new(|entity| {
+            entity.add_component(CameraComponent::default());
+        })),
+        QuickEntityType::ParticleEmitter => ("Particles", Box::new(|entity| {
+            entity.add_component(ParticleEmitter::default());
+        })),
+    };
+
+    let new_id = scene.create_entity_with_transform(name.to_string(), Transform::from_position(position));
+    if let Some(entity) = scene.get_entity_mut(new_id) {
+        add_components(entity);
+    }
+    (new_id, name)
+}
+
+/// Create the entity for a model whose meshes were uploaded with `upload_model_meshes`.
+/// Multi-mesh models get one child entity per mesh.
+fn create_model_entity(scene: &mut Scene, model_path: &str, meshes: &[ModelMesh], position: Vec3) -> EntityId {
+    let name = std::path::Path::new(model_path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Model")
+        .to_string();
+    let entity_id = scene.create_entity_with_transform(name.clone(), Transform::from_position(position));
+    if let [mesh] = meshes {
+        if let Some(entity) = scene.get_entity_mut(entity_id) {
+            entity.add_component(model_mesh_renderer(mesh));
+        }
+    } else {
+        for (i, mesh) in meshes.iter().enumerate() {
+            let child_id = scene.create_entity(format!("{} {}", name, i));
+            if let Some(child) = scene.get_entity_mut(child_id) {
+                child.add_component(model_mesh_renderer(mesh));
+            }
+            scene.set_parent(child_id, Some(entity_id));
+        }
+    }
+    entity_id
+}
+
 impl EditorApp {
     fn new(scene_file_path: Option<String>) -> Self {
+        let mut plugins = plugins::load();
+        let file_ipc = match file_ipc::McpIpcHandler::bind(&engine_core::ipc::address()) {
+            Ok(mut handler) => {
+                handler.add_plugins(&mut plugins);
+                Some(handler)
+            }
+            Err(e) => {
+                log::warn!("MCP IPC disabled: {:#}", e);
+                None
+            }
+        };
         Self {
             window: None,
             wgpu_state: None,
@@ -290,11 +1205,32 @@ impl EditorApp {
             asset_manager: None,
             physics_world: None,
             buoyancy_system: None,
+            ragdoll_system: None,
+            character_system: None,
+            behavior_system: BehaviorSystem::new(),
+            skeletal_animation: SkeletalAnimationSystem::new(
+                engine_core::project::current().asset_root.clone(),
+            ),
             script_system: None,
             audio_system: None,
             audio_command_queue: Arc::new(Mutex::new(Vec::new())),
+            audio_voices: EntityVoices::new(),
+            save_games: save_games::SaveGames::new(),
+            collider_geometry: ColliderGeometry::default(),
+            net: net_session::NetSession::new(),
+            net_launch: net_session::NetLaunch::Offline,
+            game: None,
+            plugins,
+            native_modules: plugins::native_modules(),
+            music_moments: None,
+            pending_music_moment: None,
+            adaptive_music: None,
             entity_ids: Vec::new(),
-            time: 0.0,
+            time: Arc::new(Mutex::new(Time::new())),
+            rng: SimRng::default().shared(),
+            session_seed: 0,
+            game_input: SharedGameInput::default(),
+            replay: None,
             ui: None,
             egui_state: None,
             viewport_controls: ViewportControls::new(),
@@ -302,13 +1238,16 @@ impl EditorApp {
             script_paths: std::collections::HashMap::new(),
             ipc_channel: None,
             modifiers: winit::keyboard::ModifiersState::empty(),
-            file_ipc: Some(file_ipc::FileIpcHandler::new()),
+            file_ipc,
             scene_file_path,
             undo_history: UndoHistory::new(50),
+            mesh_picker: picking::MeshPicker::new(),
+            entity_drag: None,
             clipboard: None,
-            terrain_undo_stack: Vec::new(),
-            terrain_redo_stack: Vec::new(),
             terrain_sculpt_started: false,
+            inspector_edit_dragging: false,
+            water_edit: water_edit::WaterEditState::default(),
+            spline_edit: spline_edit::SplineEditState::default(),
             camera_mode: CameraMode::Editor,
             player_position: glam::Vec3::new(0.0, 5.0, 10.0),
             player_yaw: 0.0,
@@ -323,6 +1262,19 @@ impl EditorApp {
             topdown_height: 20.0,
             topdown_edge_scroll_margin: 20.0,
             topdown_rotation_keys: [false; 2],
+            play_state: PlayState::Editing,
+            play_session: None,
+            edit_undo_history: None,
+            pending_play_request: None,
+            frames_to_step: None,
+            pending_capture: None,
+            capture_job: None,
+            frame_pacer: FramePacer::default(),
+            shadows_enabled: true,
+            prefs: EditorPrefs::load(),
+            prefs_saved_at: std::time::Instant::now(),
+            autosave: autosave::Autosave::new(),
+            console_log_sequence: 0,
         }
     }
 
@@ -342,26 +1294,30 @@ impl EditorApp {
             &surface,
             size.width,
             size.height,
+            self.prefs.anti_aliasing.sample_count(self.prefs.msaa_samples),
+            self.prefs.frame_pacing.present_mode,
         ))?;
 
         // Create texture manager with the renderer's device
         let mut texture_manager = TextureManager::new(&renderer.device, &renderer.queue);
+        texture_manager.set_stream_budget(engine_core::project::current().rendering.texture_stream_budget_mb * 1024 * 1024);
 
         // Create material manager
         let material_manager = MaterialManager::new(&renderer.device, &texture_manager);
 
         let depth_texture = renderer.create_depth_texture(size.width, size.height);
-        let msaa_texture = renderer.create_msaa_texture(size.width, size.height, renderer.surface_config.format);
+        let msaa_texture = renderer.create_msaa_texture(size.width, size.height, HDR_FORMAT);
         let camera = Camera::new(size.width, size.height);
 
         // Create asset and mesh managers
-        let asset_manager = AssetManager::new(std::env::current_dir()?.join("assets"));
+        // Shipped builds carry their assets in .cpack archives next to the executable
+        let mut asset_manager = build_export::project_assets()?;
         let mut mesh_manager = MeshManager::new();
 
         // Load scene from file or create empty scene
         let mut scene = if let Some(ref scene_path) = self.scene_file_path {
             log::info!("Loading scene from: {}", scene_path);
-            match Scene::load_from_file(scene_path) {
+            match build_export::load_scene(&asset_manager, scene_path) {
                 Ok(loaded_scene) => {
                     log::info!("Loaded scene '{}' with {} entities",
                         loaded_scene.name,
@@ -384,24 +1340,27 @@ impl EditorApp {
         log::info!("Loading sample textures...");
 
         // Load stone texture for castle walls (1024x1024 high-res with variation)
-        if let Ok(stone_tex) = Texture::from_file("assets/textures/stone_bricks.png") {
-            texture_manager.upload_texture(&renderer.device, &renderer.queue, "stone".to_string(), &stone_tex);
+        if let Ok(stone_tex) = asset_manager.load_texture("textures/stone_bricks.png") {
+            let stone_tex = stone_tex.get();
+            texture_manager.upload_texture(&renderer.device, &renderer.queue, "stone".to_string(), stone_tex);
             log::info!("Loaded stone texture: {}x{}", stone_tex.width, stone_tex.height);
         } else {
             log::error!("Failed to load stone texture from assets/textures/stone_bricks.png");
         }
 
         // Load grass texture for terrain
-        if let Ok(grass_tex) = Texture::from_file("assets/textures/grass.png") {
-            texture_manager.upload_texture(&renderer.device, &renderer.queue, "grass".to_string(), &grass_tex);
+        if let Ok(grass_tex) = asset_manager.load_texture("textures/grass.png") {
+            let grass_tex = grass_tex.get();
+            texture_manager.upload_texture(&renderer.device, &renderer.queue, "grass".to_string(), grass_tex);
             log::info!("Loaded grass texture: {}x{}", grass_tex.width, grass_tex.height);
         } else {
             log::error!("Failed to load grass texture from assets/textures/grass.png");
         }
 
         // Load water texture for moat
-        if let Ok(water_tex) = Texture::from_file("assets/textures/water.png") {
-            texture_manager.upload_texture(&renderer.device, &renderer.queue, "water".to_string(), &water_tex);
+        if let Ok(water_tex) = asset_manager.load_texture("textures/water.png") {
+            let water_tex = water_tex.get();
+            texture_manager.upload_texture(&renderer.device, &renderer.queue, "water".to_string(), water_tex);
             log::info!("Loaded water texture: {}x{}", water_tex.width, water_tex.height);
         } else {
             log::error!("Failed to load water texture from assets/textures/water.png");
@@ -434,6 +1393,9 @@ impl EditorApp {
         // Process TerrainGenerator and TerrainWater components
         let mut terrain_heightmap = None;
         let mut terrain_config = None;
+        let mut terrain_splatmap = None;
+        let mut terrain_chunks = None;
+        let terrain_layers = TerrainLayer::defaults();
         let mut terrain_water_bodies = Vec::new();
 
         // First, look for TerrainGenerator component to get terrain config
@@ -442,41 +1404,21 @@ impl EditorApp {
                 log::info!("Found TerrainGenerator component: {}x{}, scale={}, moat={}",
                     terrain_gen.width, terrain_gen.depth, terrain_gen.scale, terrain_gen.moat_enabled);
 
-                // Build TerrainConfig from component
-                let config = TerrainConfig {
-                    width: terrain_gen.width,
-                    depth: terrain_gen.depth,
-                    scale: terrain_gen.scale,
-                    height_scale: terrain_gen.height_scale,
-                    seed: terrain_gen.seed,
-                    octaves: terrain_gen.octaves,
-                    frequency: terrain_gen.frequency,
-                    lacunarity: terrain_gen.lacunarity,
-                    persistence: terrain_gen.persistence,
-                };
-
-                // Generate heightmap based on moat setting
-                let heightmap = if terrain_gen.moat_enabled {
-                    HeightMap::generate_with_moat(
-                        &config,
-                        terrain_gen.moat_inner_radius,
-                        terrain_gen.moat_outer_radius,
-                        terrain_gen.moat_depth,
-                    )
-                } else {
-                    HeightMap::generate(&config)
-                };
+                let (mut heightmap, config) = terrain_tools::generate_heightmap(terrain_gen, asset_manager.asset_root());
+                spline_edit::carve_splines(&scene, &mut heightmap, &config);
 
                 log::info!("Generated terrain heightmap {}x{}", config.width, config.depth);
 
                 // Generate and upload terrain mesh
-                let terrain_mesh = Terrain::generate_mesh_from_heightmap(&heightmap, &config);
-                log::info!("Generated terrain mesh '{}' with {} vertices", terrain_mesh.name, terrain_mesh.vertices.len());
-                let gpu_vertices = convert_mesh_to_gpu(&terrain_mesh);
-                mesh_manager.upload_mesh(&renderer.device, "terrain".to_string(), &gpu_vertices, &terrain_mesh.indices);
+                let splatmap = terrain_tools::scene_splatmap(&asset_manager, self.scene_file_path.as_deref(), &config, terrain_layers.len());
+                let layout = TerrainChunks::new(heightmap.width, heightmap.depth);
+                upload_terrain_chunks(&mut mesh_manager, &renderer.device, &heightmap, &config, Some(&splatmap), &terrain_layers, layout.chunks(), TerrainChunk::mesh_name);
+                log::info!("Generated terrain mesh in {}x{} chunks", layout.chunks_x, layout.chunks_z);
 
                 terrain_heightmap = Some(heightmap);
                 terrain_config = Some(config);
+                terrain_splatmap = Some(splatmap);
+                terrain_chunks = Some(layout);
                 break; // Only process first TerrainGenerator
             }
         }
@@ -488,12 +1430,7 @@ impl EditorApp {
 
                 // Compute water fill if we have terrain
                 if let (Some(ref heightmap), Some(ref config)) = (&terrain_heightmap, &terrain_config) {
-                    let result = compute_water_fill(
-                        heightmap,
-                        terrain_water.ground_water_level,
-                        terrain_water.min_water_depth,
-                        terrain_water.min_water_area,
-                    );
+                    let result = water_edit::compute_terrain_water(heightmap, config, terrain_water);
                     log::info!("Computed water fill: {} water bodies found", result.water_bodies.len());
 
                     // Generate and upload meshes for each water body
@@ -511,6 +1448,8 @@ impl EditorApp {
                             surface_level: computed_body.surface_level,
                             flow_direction: computed_body.flow_direction,
                             flow_speed: computed_body.flow_speed,
+                            bounds_min: computed_body.bounds_min,
+                            bounds_max: computed_body.bounds_max,
                         });
                     }
                 }
@@ -518,20 +1457,72 @@ impl EditorApp {
         }
 
         // Initialize physics world
-        let mut physics_world = PhysicsWorld::default(); // Default gravity is (0, -9.81, 0)
-        PhysicsSync::initialize_physics(&mut physics_world, &scene)?;
+        let mut physics_world = PhysicsWorld::default(); // Gravity from project.ron
+        self.collider_geometry = play_mode::collider_geometry(&scene, &mut asset_manager, || {
+            terrain_heightmap.clone().zip(terrain_config.clone())
+        });
+        PhysicsSync::initialize_physics(&mut physics_world, &scene, &self.collider_geometry)?;
 
         // Initialize audio system
-        let assets_path = std::env::current_dir()?.join("assets");
-        let audio_system = AudioSystem::new(assets_path)?;
+        let assets_path = std::env::current_dir()?.join(&engine_core::project::current().asset_root);
+        let mut audio_system = AudioSystem::new(&assets_path)?;
+        // Clips from packs too, each loaded by the project's policy for it
+        if let Err(e) = audio_system.clips().mount_packs_in(build_export::executable_dir()?) {
+            log::warn!("Failed to mount asset packs for audio: {}", e);
+        }
+        let audio_settings = &engine_core::project::current().audio;
+        for path in &audio_settings.in_memory {
+            audio_system.clips().set_audio_policy(path, AudioLoadPolicy::Memory);
+        }
+        for path in &audio_settings.streamed {
+            audio_system.clips().set_audio_policy(path, AudioLoadPolicy::Stream);
+        }
+        audio_system.clips().preload_audio(&audio_settings.preload);
         log::info!("Audio system initialized");
 
-        // Initialize script system
+        // Load music moments if the project defines any
+        if asset_manager.exists("music/moments.ron") {
+            let config = asset_manager
+                .read("music/moments.ron")
+                .and_then(|ron| MusicMomentsConfig::from_ron(&String::from_utf8(ron)?));
+            match config {
+                Ok(config) => {
+                    log::info!("Loaded {} music moments", config.moments.len());
+                    // Fall back to silence so an offline music service never breaks play
+                    let client = AceStepClient::with_config(
+                        AceStepConfig::with_url(&engine_core::project::current().ai.music_url).with_fallback(MusicFallback::Silence),
+                    );
+                    self.music_moments =
+                        Some(Arc::new(MusicMoments::new(config, &assets_path, client)));
+                }
+                Err(e) => log::warn!("Failed to load music moments: {}", e),
+            }
+        }
+
+        // Load adaptive music tracks if the project defines any
+        if asset_manager.exists("music/adaptive.ron") {
+            let config = asset_manager
+                .read("music/adaptive.ron")
+                .and_then(|ron| AdaptiveMusicConfig::from_ron(&String::from_utf8(ron)?));
+            match config {
+                Ok(config) => {
+                    log::info!("Loaded {} adaptive music tracks", config.tracks.len());
+                    self.adaptive_music = Some(config);
+                }
+                Err(e) => log::warn!("Failed to load adaptive music: {}", e),
+            }
+        }
+
+        // Initialize script system (start() runs when entering play mode)
         let mut script_system = ScriptSystem::new();
         script_system.initialize(&scene)?;
-        script_system.start(&mut scene)?;
-        // Register audio API with scripts
+        // Register audio and save game APIs with scripts
         script_system.register_audio_api(self.audio_command_queue.clone());
+        script_system.register_time_api(self.time.clone());
+        script_system.register_random_api(self.rng.clone());
+        script_system.register_game_input_api(self.game_input.clone());
+        self.save_games.register(&mut script_system);
+        self.net.register(&mut script_system);
         log::info!("Script system initialized");
 
         // Initialize egui
@@ -597,69 +1588,137 @@ impl EditorApp {
         });
 
         // Create skybox
-        let skybox = Skybox::new(
+        let mut skybox = Skybox::new(
             &renderer.device,
-            renderer.surface_config.format,
+            &renderer.queue,
+            HDR_FORMAT,
+            renderer.sample_count,
             &camera_bind_group_layout,
         ).ok();
 
-        // Initialize skybox with gradient
-        if let Some(ref skybox) = skybox {
-            Skybox::create_gradient_skybox(&renderer.queue, &skybox.texture);
+        // Start with the default afternoon sky (and its ambient lighting)
+        if let Some(skybox) = skybox.as_mut() {
+            skybox.set_procedural_sky(&renderer.queue, &ProceduralSky::default());
         }
 
         // Create shadow map
-        let shadow_map = ShadowMap::new(&renderer.device).ok();
+        let shadow_map = ShadowMap::with_size(&renderer.device, engine_core::project::current().rendering.shadow_resolution).ok();
 
-        // Create framebuffer for post-processing
+        // Create HDR framebuffer for post-processing
         let framebuffer = Framebuffer::new(
             &renderer.device,
             size.width,
             size.height,
-            renderer.surface_config.format,
-            true, // with depth
-        ).ok();
+            HDR_FORMAT,
+            false, // scene passes share depth_texture
+        )?;
+        let bloom_chain = BloomChain::new(&renderer.device, size.width, size.height)?;
 
         // Create post-processing pipeline
         let post_process_pipeline = PostProcessPipeline::new(
             &renderer.device,
             renderer.surface_config.format,
-        ).ok();
+        )?;
+        let taa = TemporalAa::new(&renderer.device, size.width, size.height)?;
 
         // Create particle renderer
         let particle_renderer = ParticleRenderer::new(
             &renderer.device,
             &renderer.queue,
-            renderer.surface_config.format,
+            HDR_FORMAT,
+            renderer.sample_count,
             engine_render::particle_renderer::ParticleBlendMode::Alpha,
         ).ok();
 
         // Create foliage renderer for instanced vegetation
-        let foliage_renderer = FoliageRenderer::new(
+        let mut foliage_renderer = FoliageRenderer::new(
             &renderer.device,
-            renderer.surface_config.format,
+            HDR_FORMAT,
+            renderer.sample_count,
+        ).ok();
+
+        // Create grass renderer (blades grown on the GPU from a density map)
+        let grass_renderer = GrassRenderer::new(
+            &renderer.device,
+            HDR_FORMAT,
+            renderer.sample_count,
+        ).ok();
+
+        // Create skinned renderer for animated characters
+        let skinned_renderer = SkinnedRenderer::new(
+            &renderer.device,
+            HDR_FORMAT,
+            renderer.sample_count,
+        ).ok();
+
+        // Create editor grid renderer
+        let grid_renderer = GridRenderer::new(
+            &renderer.device,
+            HDR_FORMAT,
+            renderer.sample_count,
         ).ok();
 
-        // Generate and upload vegetation meshes
+        let gpu_profiler = GpuProfiler::new(&renderer.device, &renderer.queue);
+
+        // Generate and upload vegetation meshes, their low detail versions and impostors
         for veg_type in VegetationType::all() {
             let mesh = veg_type.generate_mesh();
             let gpu_vertices = convert_mesh_to_gpu(&mesh);
-            mesh_manager.upload_mesh(&renderer.device, veg_type.mesh_name().to_string(), &gpu_vertices, &mesh.indices);
+            let handle = mesh_manager.upload_mesh(&renderer.device, veg_type.mesh_name().to_string(), &gpu_vertices, &mesh.indices);
             log::info!("Generated vegetation mesh '{}' with {} vertices", veg_type.mesh_name(), mesh.vertices.len());
+
+            let lod_mesh = veg_type.generate_lod_mesh();
+            mesh_manager.upload_mesh(&renderer.device, veg_type.lod_mesh_name(), &convert_mesh_to_gpu(&lod_mesh), &lod_mesh.indices);
+
+            if let (Some(foliage_renderer), Some(gpu_mesh)) = (foliage_renderer.as_mut(), mesh_manager.get_mesh(handle)) {
+                foliage_renderer.bake_impostor(&renderer.device, &renderer.queue, veg_type.mesh_name(), gpu_mesh);
+            }
         }
 
+        // Upload glTF models referenced by the scene (e.g. placed from the asset browser)
+        let mut pending_model_lods = Vec::new();
+        upload_scene_models(
+            &scene,
+            &mut asset_manager,
+            &mut mesh_manager,
+            &renderer.device,
+            &mut pending_model_lods,
+        );
+
         // Create water renderer
-        let water_renderer = if let Some(ref shadow_map) = shadow_map {
+        let mut water_renderer = if shadow_map.is_some() {
             let shadow_sampling_layout = ShadowMap::create_sampling_bind_group_layout(&renderer.device);
             WaterRenderer::new(
                 &renderer.device,
-                renderer.surface_config.format,
+                HDR_FORMAT,
+                renderer.sample_count,
                 texture_manager.bind_group_layout(),
                 &shadow_sampling_layout,
             ).ok()
         } else {
             None
         };
+        let water_reflection = WaterReflection::new(
+            &renderer.device,
+            size.width,
+            size.height,
+            self.prefs.water_reflections,
+        )?;
+        if let Some(water_renderer) = &mut water_renderer {
+            water_renderer.set_reflection(&renderer.device, &water_reflection);
+        }
+
+        // Terrain layers blended by the painted splatmap
+        let terrain_renderer = match TerrainRenderer::new(&renderer.device, &renderer.queue, HDR_FORMAT, renderer.sample_count) {
+            Ok(mut terrain_renderer) => {
+                terrain_renderer.set_layers(&renderer.queue, &terrain_layers, &mut asset_manager);
+                Some(terrain_renderer)
+            }
+            Err(e) => {
+                log::warn!("Failed to create terrain renderer, terrain will use its material: {}", e);
+                None
+            }
+        };
 
         self.window = Some(window.clone());
         self.wgpu_state = Some(WgpuState {
@@ -667,37 +1726,76 @@ impl EditorApp {
             surface,
             renderer,
             mesh_manager,
+            pending_model_lods,
             texture_manager,
             material_manager,
+            loading_materials: Vec::new(),
+            texture_cache: CompressedTextureCache::new(TEXTURE_CACHE_DIR),
+            compressing_textures: Vec::new(),
             depth_texture,
             msaa_texture,
             skybox,
             shadow_map,
             water_renderer,
+            water_reflection,
             camera_bind_group_layout,
             camera_bind_group: Some(camera_bind_group),
             camera_uniform_buffer,
             framebuffer,
+            bloom_chain,
+            mesh_batches: DrawBatches::new(),
+            reflection_batches: DrawBatches::new(),
             post_process_pipeline,
+            render_targets: TransientPool::new(),
+            taa,
+            previous_world_matrices: std::collections::HashMap::new(),
             particle_renderer,
             particle_systems: std::collections::HashMap::new(),
             particle_compute_pipelines: std::collections::HashMap::new(),
             foliage_renderer,
+            grass_renderer,
+            skinned_renderer,
+            grid_renderer,
+            gpu_profiler,
+            memory_budget: MemoryBudget::new(
+                engine_core::project::current()
+                    .rendering
+                    .gpu_memory_budget_mb
+                    * 1024
+                    * 1024,
+            ),
             terrain_heightmap,
             terrain_config,
+            terrain_splatmap,
+            terrain_layers,
+            terrain_chunks,
+            terrain_renderer,
+            terrain_splatmap_dirty: true,
+            grass_terrain_dirty: true,
+            streamed_terrain: None,
             terrain_water_bodies,
+            spline_roads: Vec::new(),
+            spline_meshes: Vec::new(),
             terrain_needs_regeneration: false,
-            water_needs_regeneration: false,
+            // Spline meshes are built along with the water
+            water_needs_regeneration: scene.entities().any(|entity| entity.has_component::<Spline>()),
         });
         self.camera = Some(camera);
         self.scene = Some(scene);
         self.asset_manager = Some(asset_manager);
         self.physics_world = Some(physics_world);
         self.buoyancy_system = Some(BuoyancySystem::new());
+        self.ragdoll_system = Some(RagdollSystem::new());
+        self.character_system = Some(CharacterSystem::new(play_mode::fixed_dt()));
         self.script_system = Some(script_system);
         self.audio_system = Some(audio_system);
         self.entity_ids = Vec::new(); // Scene loaded from file, not tracking individual entity IDs
-        self.ui = Some(EditorUi::new());
+        let mut ui = EditorUi::new();
+        self.prefs.apply(&mut ui);
+        if let (Some(scene_path), Some(asset_manager)) = (&self.scene_file_path, &self.asset_manager) {
+            ui.load_navmesh(asset_manager, scene_path);
+        }
+        self.ui = Some(ui);
         self.egui_state = Some(EguiState {
             context: egui_context,
             winit_state: egui_winit_state,
@@ -723,8 +1821,18 @@ impl EditorApp {
             }
         }
 
-        self.hot_reload = Some(hot_reload);
-        log::info!("Hot reload system initialized");
+        // Watch the directories native modules are built into; cargo replaces
+        // the library file, so watching the file itself would lose track of it
+        for module in self.native_modules.modules() {
+            if let Some(dir) = module.path.parent() {
+                if let Err(e) = hot_reload.watch_file(dir) {
+                    log::warn!("Failed to watch native module directory: {}", e);
+                }
+            }
+        }
+
+        self.hot_reload = Some(hot_reload);
+        log::info!("Hot reload system initialized");
 
         Ok(())
     }
@@ -734,13 +1842,416 @@ impl EditorApp {
             if let (Some(wgpu_state), Some(camera)) = (&mut self.wgpu_state, &mut self.camera) {
                 wgpu_state.renderer.resize(&wgpu_state.surface, new_size.width, new_size.height);
                 wgpu_state.depth_texture = wgpu_state.renderer.create_depth_texture(new_size.width, new_size.height);
-                wgpu_state.msaa_texture = wgpu_state.renderer.create_msaa_texture(new_size.width, new_size.height, wgpu_state.renderer.surface_config.format);
+                wgpu_state.msaa_texture = wgpu_state.renderer.create_msaa_texture(new_size.width, new_size.height, HDR_FORMAT);
+                if let Ok(framebuffer) = Framebuffer::new(&wgpu_state.renderer.device, new_size.width, new_size.height, HDR_FORMAT, false) {
+                    wgpu_state.framebuffer = framebuffer;
+                }
+                if let Ok(bloom_chain) = BloomChain::new(&wgpu_state.renderer.device, new_size.width, new_size.height) {
+                    wgpu_state.bloom_chain = bloom_chain;
+                }
+                if let Err(e) = wgpu_state.taa.resize(&wgpu_state.renderer.device, new_size.width, new_size.height) {
+                    log::warn!("Failed to resize TAA buffers: {}", e);
+                }
+                let quality = wgpu_state.water_reflection.quality;
+                if let Ok(reflection) = WaterReflection::new(&wgpu_state.renderer.device, new_size.width, new_size.height, quality) {
+                    if let Some(water_renderer) = &mut wgpu_state.water_renderer {
+                        water_renderer.set_reflection(&wgpu_state.renderer.device, &reflection);
+                    }
+                    wgpu_state.water_reflection = reflection;
+                }
                 camera.update_aspect(new_size.width, new_size.height);
             }
         }
     }
 
+    /// Apply changed preferences (camera speed, frame pacing) and save them to the config file
+    fn sync_prefs(&mut self) {
+        let Some(ui) = &self.ui else {
+            return;
+        };
+        self.viewport_controls.camera_speed = ui.camera_speed;
+        if let Some(wgpu_state) = self.wgpu_state.as_mut() {
+            if wgpu_state.renderer.present_mode() != ui.frame_pacing.present_mode {
+                wgpu_state
+                    .renderer
+                    .set_present_mode(&wgpu_state.surface, ui.frame_pacing.present_mode);
+            }
+        }
+        if *self.frame_pacer.config() != ui.frame_pacing {
+            self.frame_pacer.set_config(ui.frame_pacing);
+        }
+
+        let prefs = EditorPrefs::capture(ui);
+        if prefs != self.prefs && self.prefs_saved_at.elapsed() >= PREFS_SAVE_INTERVAL {
+            self.save_prefs(prefs);
+        }
+    }
+
+    /// Show warnings and errors logged since the last frame in the console
+    fn forward_log_to_console(&mut self) {
+        let (Some(logger), Some(ui)) = (engine_core::logging::logger(), self.ui.as_mut()) else {
+            return;
+        };
+        for entry in logger.entries_since(self.console_log_sequence) {
+            self.console_log_sequence = entry.sequence;
+            let message = format!("[{}] {}", entry.target, entry.message);
+            match entry.level {
+                log::Level::Error => ui.log_error(message),
+                log::Level::Warn => ui.log_warning(message),
+                _ => {}
+            }
+        }
+    }
+
+    fn save_prefs(&mut self, prefs: EditorPrefs) {
+        if let Err(e) = prefs.save() {
+            log::warn!("Failed to save editor preferences: {}", e);
+        }
+        self.prefs = prefs;
+        self.prefs_saved_at = std::time::Instant::now();
+    }
+
+    /// Pass a window event on to the game input scripts read
+    fn feed_game_input(&mut self, event: &WindowEvent) {
+        let mut input = self.game_input.lock().unwrap();
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state,
+                        physical_key: PhysicalKey::Code(key_code),
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                let name = format!("{:?}", key_code);
+                match state {
+                    ElementState::Pressed => input.press_key(&name),
+                    ElementState::Released => input.release_key(&name),
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                input.set_mouse_button(&format!("{:?}", button), *state == ElementState::Pressed);
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                input.add_scroll(match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(pos) => pos.y as f32 * 0.01,
+                });
+            }
+            // Keys released while unfocused would otherwise stay held
+            WindowEvent::Focused(false) => input.clear(),
+            _ => {}
+        }
+    }
+
+    /// Pointer events meant for the 3D viewport even though the dock area covers it.
+    /// Button releases always reach the viewport so drags never get stuck.
+    fn viewport_receives(&self, event: &WindowEvent) -> bool {
+        let over_viewport = self.ui.as_ref().is_some_and(|ui| ui.viewport_hovered);
+        match event {
+            WindowEvent::MouseInput { state: ElementState::Released, .. } => true,
+            WindowEvent::MouseInput { .. }
+            | WindowEvent::MouseWheel { .. }
+            | WindowEvent::CursorMoved { .. } => over_viewport,
+            _ => false,
+        }
+    }
+
+    /// Start a screenshot or capture sequence (rendered over the next frames).
+    /// Returns false if it could not start.
+    fn start_capture(&mut self, request: capture::CaptureRequest) -> bool {
+        let Some(ui) = &mut self.ui else {
+            return false;
+        };
+        if self.capture_job.is_some() {
+            ui.log_warning("A capture is already in progress".to_string());
+            return false;
+        }
+        if !self.wgpu_state.as_ref().is_some_and(|state| state.renderer.can_capture()) {
+            ui.log_error("Screenshots are not supported by this display surface".to_string());
+            return false;
+        }
+        if !matches!(request, capture::CaptureRequest::Screenshot(_)) && self.camera_mode != CameraMode::Editor {
+            ui.log_warning("Turntable and flythrough captures need the editor camera".to_string());
+            return false;
+        }
+
+        let folder = std::env::current_dir().unwrap_or_default().join(capture::SCREENSHOT_DIR);
+        let (mode, output) = match request {
+            capture::CaptureRequest::Screenshot(path) => (
+                capture::CaptureMode::Screenshot,
+                path.unwrap_or_else(|| capture::capture_path(&folder, "screenshot", ".png")),
+            ),
+            capture::CaptureRequest::Turntable => (
+                capture::CaptureMode::Turntable,
+                capture::capture_path(&folder, "turntable", ""),
+            ),
+            capture::CaptureRequest::Flythrough => {
+                let bookmarks: Vec<_> = self.viewport_controls.bookmarks.iter().flatten().copied().collect();
+                if bookmarks.len() < 2 {
+                    ui.log_warning("Flythrough needs at least two camera bookmarks (Ctrl+1-9 to save)".to_string());
+                    return false;
+                }
+                (
+                    capture::CaptureMode::Flythrough(bookmarks),
+                    capture::capture_path(&folder, "flythrough", ""),
+                )
+            }
+        };
+        self.capture_job = Some(capture::CaptureJob::new(
+            mode,
+            ui.capture_settings,
+            output,
+            &self.viewport_controls,
+        ));
+        true
+    }
+
+    /// Start, pause, resume or stop the play-in-editor simulation
+    /// Geometry for the scene's colliders: meshes from the assets and the
+    /// terrain as sculpted
+    fn scene_collider_geometry(&mut self) -> ColliderGeometry {
+        let (Some(scene), Some(asset_manager)) = (&self.scene, &mut self.asset_manager) else {
+            return ColliderGeometry::default();
+        };
+        let terrain = self
+            .wgpu_state
+            .as_ref()
+            .and_then(|wgpu_state| placement::terrain_ref(&wgpu_state.terrain_heightmap, &wgpu_state.terrain_config));
+        play_mode::collider_geometry(scene, asset_manager, || {
+            terrain.map(|(heightmap, config)| (heightmap.clone(), config.clone()))
+        })
+    }
+
+    fn handle_play_request(&mut self, request: PlayRequest) {
+        // Colliders are built from the meshes and terrain as they are now
+        if (request, self.play_state) == (PlayRequest::Play, PlayState::Editing) {
+            self.collider_geometry = self.scene_collider_geometry();
+        }
+        let (Some(scene), Some(camera), Some(physics_world), Some(script_system)) = (
+            &mut self.scene,
+            &mut self.camera,
+            &mut self.physics_world,
+            &mut self.script_system,
+        ) else {
+            return;
+        };
+
+        match (request, self.play_state) {
+            (PlayRequest::Play, PlayState::Editing) => {
+                let selected = self.ui.as_ref().and_then(|ui| ui.selected_entity);
+                self.play_session = Some(PlaySession::begin(scene, camera, selected));
+                self.edit_undo_history =
+                    Some(std::mem::replace(&mut self.undo_history, UndoHistory::new(50)));
+                engine_scene::animation::start_animations(scene);
+
+                // Seed the session; in determinism mode the same seed every
+                // time, and the session is recorded from here
+                let determinism = &engine_core::project::current().determinism;
+                self.session_seed = if determinism.enabled {
+                    determinism.seed
+                } else {
+                    SimRng::from_time().next_u64()
+                };
+                *self.rng.lock().unwrap() = SimRng::new(self.session_seed);
+                self.game_input.lock().unwrap().clear();
+                self.replay = determinism.enabled.then(|| {
+                    Replay::new(
+                        scene,
+                        self.session_seed,
+                        play_mode::fixed_dt(),
+                    )
+                });
+
+                // Rebuild physics and scripts so the simulation starts from the authored scene
+                *physics_world = PhysicsWorld::default();
+                *script_system = ScriptSystem::new();
+                script_system.register_audio_api(self.audio_command_queue.clone());
+                script_system.register_time_api(self.time.clone());
+                script_system.register_random_api(self.rng.clone());
+                script_system.register_game_input_api(self.game_input.clone());
+                self.save_games.register(script_system);
+                self.save_games.reset();
+                self.net.register(script_system);
+                // Trees are loaded again, so edits to them apply
+                self.behavior_system = BehaviorSystem::new();
+                if let Err(e) = self.net.start(&self.net_launch) {
+                    log::error!("Failed to start network session: {}", e);
+                }
+                self.time.lock().unwrap().reset_scale();
+                self.frame_pacer.reset_accumulator();
+                let result = PhysicsSync::initialize_physics(physics_world, scene, &self.collider_geometry)
+                    .and_then(|_| script_system.initialize(scene))
+                    .and_then(|_| {
+                        // Scripts can query the world before it first steps
+                        physics_world.query_pipeline.update(&physics_world.collider_set);
+                        script_system.with_physics(physics_world, |scripts| scripts.start(scene))
+                    });
+                if let Err(e) = result {
+                    log::error!("Failed to start play mode: {}", e);
+                }
+
+                self.play_state = PlayState::Playing;
+                log::info!("Play mode started");
+            }
+            (PlayRequest::Play, PlayState::Paused) => {
+                self.play_state = PlayState::Playing;
+            }
+            (PlayRequest::Pause, PlayState::Playing) => {
+                self.play_state = PlayState::Paused;
+            }
+            (PlayRequest::Stop, PlayState::Playing | PlayState::Paused) => {
+                if let Some(replay) = self.replay.take().filter(|r| !r.frames.is_empty()) {
+                    match replay.save_new() {
+                        Ok(path) => log::info!("Replay saved to {}", path.display()),
+                        Err(e) => log::error!("Failed to save replay: {:#}", e),
+                    }
+                }
+                self.game_input.lock().unwrap().clear();
+                let restored_selection = self
+                    .play_session
+                    .take()
+                    .and_then(|session| session.end(scene, camera));
+                // Edits made while playing went with the simulation
+                if let Some(history) = self.edit_undo_history.take() {
+                    self.undo_history = history;
+                }
+
+                // Reset simulation state back to the authored scene
+                *physics_world = PhysicsWorld::default();
+                if let Err(e) = PhysicsSync::initialize_physics(physics_world, scene, &self.collider_geometry) {
+                    log::error!("Failed to reset physics: {}", e);
+                }
+                *script_system = ScriptSystem::new();
+                script_system.register_audio_api(self.audio_command_queue.clone());
+                script_system.register_time_api(self.time.clone());
+                script_system.register_random_api(self.rng.clone());
+                script_system.register_game_input_api(self.game_input.clone());
+                self.save_games.register(script_system);
+                self.save_games.reset();
+                self.net.close();
+                self.net.register(script_system);
+                self.time.lock().unwrap().reset_scale();
+                self.frame_pacer.reset_accumulator();
+                if let Err(e) = script_system.initialize(scene) {
+                    log::error!("Failed to reload scripts: {}", e);
+                }
+                if let Some(wgpu_state) = &mut self.wgpu_state {
+                    wgpu_state.particle_systems.clear();
+                    wgpu_state.particle_compute_pipelines.clear();
+                }
+                self.audio_command_queue.lock().unwrap().clear();
+                self.pending_music_moment = None;
+                if let Some(audio_system) = &mut self.audio_system {
+                    audio_system.stop_music();
+                }
+                self.audio_voices.clear(scene);
+
+                if let Some(ui) = &mut self.ui {
+                    ui.selected_entity = restored_selection;
+                }
+                self.play_state = PlayState::Editing;
+                log::info!("Play mode stopped, edited scene restored");
+            }
+            _ => {}
+        }
+
+        if let Some(ui) = &mut self.ui {
+            ui.play_state = self.play_state;
+        }
+    }
+
+    /// Run a play mode command from the MCP server and answer it
+    fn handle_simulation_request(&mut self, request: file_ipc::SimulationRequest) {
+        use file_ipc::SimulationCommand;
+
+        // Any other play mode change ends a running step
+        if !matches!(request.command, SimulationCommand::Step { .. } | SimulationCommand::State) {
+            self.finish_simulation_step();
+        }
+
+        match request.command {
+            SimulationCommand::Play => self.handle_play_request(PlayRequest::Play),
+            SimulationCommand::Pause => self.handle_play_request(PlayRequest::Pause),
+            SimulationCommand::Stop => self.handle_play_request(PlayRequest::Stop),
+            SimulationCommand::Step { frames } => {
+                self.handle_play_request(PlayRequest::Play);
+                self.frames_to_step = Some(self.frames_to_step.unwrap_or(0) + frames);
+                if let Some(file_ipc) = &mut self.file_ipc {
+                    file_ipc.wait_for_step(request);
+                }
+                return;
+            }
+            SimulationCommand::State => {}
+        }
+
+        let state = self.simulation_state();
+        if let Some(file_ipc) = &mut self.file_ipc {
+            file_ipc.answer_simulation_request(request, state);
+        }
+    }
+
+    /// End a running step_simulation: pause and answer the waiting requests
+    fn finish_simulation_step(&mut self) {
+        if self.frames_to_step.take().is_none() {
+            return;
+        }
+        if self.play_state.is_simulating() {
+            self.handle_play_request(PlayRequest::Pause);
+        }
+        let state = self.simulation_state();
+        if let Some(file_ipc) = &mut self.file_ipc {
+            file_ipc.finish_simulation_step(state);
+        }
+    }
+
+    fn simulation_state(&self) -> serde_json::Value {
+        file_ipc::simulation_state(self.play_state, self.play_session.as_ref(), self.frames_to_step)
+    }
+
     fn render(&mut self) -> Result<()> {
+        // Apply play mode transitions requested from the toolbar last frame
+        if let Some(request) = self.pending_play_request.take() {
+            self.handle_play_request(request);
+        }
+
+        // A step_simulation is done once its frames have run, or if the
+        // simulation was paused or stopped in the meantime
+        if self.frames_to_step.is_some_and(|frames| frames == 0) || !self.play_state.is_simulating() {
+            self.finish_simulation_step();
+        }
+
+        // Play mode commands the MCP server sent last frame
+        let simulation_requests = self
+            .file_ipc
+            .as_mut()
+            .map(|file_ipc| file_ipc.take_simulation_requests())
+            .unwrap_or_default();
+        for request in simulation_requests {
+            self.handle_simulation_request(request);
+        }
+
+        // External tools (e.g. the MCP server) request a screenshot with a trigger file
+        let screenshot_trigger = std::env::temp_dir().join("game-engine-screenshot-trigger");
+        if screenshot_trigger.exists() {
+            let _ = std::fs::remove_file(&screenshot_trigger);
+            self.pending_capture = Some(capture::CaptureRequest::Screenshot(Some(
+                std::env::temp_dir().join("editor_screenshot.png"),
+            )));
+        }
+        if let Some(request) = self.pending_capture.take() {
+            let for_mcp = request == capture::CaptureRequest::Screenshot(Some(capture::mcp_capture_path()));
+            if !self.start_capture(request) && for_mcp {
+                if let Some(file_ipc) = &mut self.file_ipc {
+                    file_ipc.finish_capture(Err("the editor could not start a screenshot (see its console)".to_string()));
+                }
+            }
+        }
+
+        self.forward_log_to_console();
+
         let Some(wgpu_state) = &mut self.wgpu_state else {
             return Ok(());
         };
@@ -763,35 +2274,111 @@ impl EditorApp {
             return Ok(());
         };
 
-        // Fixed time step for physics (60fps)
-        let dt = 1.0 / 60.0;
-
-        // Update elapsed time for animations
-        self.time += dt;
+        // Per-system CPU timings for the profiler; GPU timings arrive a frame or two late
+        let mut frame_timer = FrameTimer::new();
+        let _frame_span = tracing::info_span!("frame").entered();
+        wgpu_state.gpu_profiler.poll(&wgpu_state.renderer.device);
+
+        // Real frame time (smoothed and clamped) for the editor camera, scaled
+        // by the game's time scale for the simulation; exactly one fixed frame
+        // while stepping
+        let frame_delta = self.frame_pacer.begin_frame();
+        let (dt, real_dt, elapsed) = {
+            let mut time = self.time.lock().unwrap();
+            if self.frames_to_step.is_some() {
+                time.tick_fixed(play_mode::fixed_dt());
+            } else {
+                time.advance(frame_delta);
+            }
+            (time.delta(), time.unscaled_delta(), time.elapsed())
+        };
 
         // Process file-based IPC commands from MCP server
         if let Some(file_ipc) = &mut self.file_ipc {
-            if let Err(e) = file_ipc.poll_commands(scene) {
+            let context = file_ipc::CommandContext {
+                asset_manager,
+                meshes: &wgpu_state.mesh_manager,
+                mesh_picker: &mut self.mesh_picker,
+                terrain: terrain_tools::TerrainData {
+                    heightmap: &mut wgpu_state.terrain_heightmap,
+                    config: &mut wgpu_state.terrain_config,
+                    splatmap: &mut wgpu_state.terrain_splatmap,
+                },
+                viewport: &mut self.viewport_controls,
+            };
+            if let Err(e) = file_ipc.poll_commands(scene, context, &mut self.undo_history) {
                 log::error!("File IPC error: {}", e);
             }
-        }
 
-        // Check for screenshot trigger and capture using system tool
-        let screenshot_trigger = std::env::temp_dir().join("game-engine-screenshot-trigger");
-        if screenshot_trigger.exists() {
-            let _ = std::fs::remove_file(&screenshot_trigger);
-            let screenshot_path = "/tmp/editor_screenshot.png";
-            // Use import (ImageMagick) to capture the active window
-            let _ = std::process::Command::new("import")
-                .args(&["-window", "root", screenshot_path])
-                .output();
-            log::info!("Screenshot captured to {}", screenshot_path);
+            // The MCP camera moves the orbit camera, so show it
+            if file_ipc.take_camera_moved() && self.camera_mode != CameraMode::Editor {
+                self.camera_mode = CameraMode::Editor;
+                log::info!("Switched to the editor camera for an MCP camera move");
+            }
+
+            if file_ipc.take_capture_request() {
+                self.pending_capture = Some(capture::CaptureRequest::Screenshot(Some(capture::mcp_capture_path())));
+            }
+
+            if file_ipc.take_terrain_changed() {
+                if let Some(heightmap) = &wgpu_state.terrain_heightmap {
+                    // A regenerated terrain may have a new size; its paint doesn't carry over
+                    let splatmap_fits = wgpu_state.terrain_splatmap.as_ref()
+                        .is_some_and(|splatmap| splatmap.width == heightmap.width && splatmap.depth == heightmap.depth);
+                    if !splatmap_fits {
+                        wgpu_state.terrain_splatmap = Some(SplatMap::new(heightmap.width, heightmap.depth, wgpu_state.terrain_layers.len()));
+                    }
+                    wgpu_state.terrain_splatmap_dirty = true;
+                }
+                rebuild_terrain_chunks(wgpu_state);
+                wgpu_state.water_needs_regeneration = true;
+            }
+
+            // Same as undo from the Edit menu
+            if file_ipc.take_history_changed() {
+                wgpu_state.water_needs_regeneration = true;
+                if let Some(ui) = self.ui.as_mut() {
+                    ui.selected_entity = None;
+                    ui.mark_scene_modified();
+                }
+            }
+
+            // Attached script files reload when edited, like the scripts/ folder
+            if let Ok(working_dir) = std::env::current_dir() {
+                for (entity_id, path) in file_ipc.take_attached_scripts() {
+                    let path = working_dir.join(path);
+                    let watched = path.starts_with(working_dir.join("scripts"))
+                        || path.starts_with(working_dir.join("assets"));
+                    if !watched {
+                        if let Some(hot_reload) = &mut self.hot_reload {
+                            if let Err(e) = hot_reload.watch_file(&path) {
+                                log::warn!("Failed to watch script {:?}: {}", path, e);
+                            }
+                        }
+                    }
+                    self.script_paths.insert(entity_id, path);
+                }
+            }
+
+            // Rewritten materials are reloaded and re-uploaded on next use
+            for material_path in file_ipc.take_changed_materials() {
+                if let Err(e) = asset_manager.reload_material(&material_path) {
+                    log::warn!("Failed to reload material '{}': {}", material_path, e);
+                }
+                wgpu_state.material_manager.invalidate(&material_path);
+            }
+
+            // Music started and stopped over MCP plays like script music
+            self.audio_command_queue.lock().unwrap().extend(file_ipc.take_audio_commands());
         }
 
         // Process hot reload events
         if let Some(hot_reload) = &mut self.hot_reload {
             let events = hot_reload.poll_events();
             for event in events {
+                if let Some(ipc) = &mut self.file_ipc {
+                    ipc.resource_changed(asset_manager.asset_root(), event.path());
+                }
                 match event {
                     ReloadEvent::ScriptChanged(path) => {
                         log::info!("Reloading script: {:?}", path);
@@ -806,13 +2393,14 @@ impl EditorApp {
 
                         if let Some(entity_id) = entity_to_reload {
                             // Reload the script
-                            match std::fs::read_to_string(&path) {
+                            match asset_manager.read_file(&path).and_then(|bytes| Ok(String::from_utf8(bytes)?)) {
                                 Ok(source) => {
+                                    // Keep the component in step, so the next play session and save use the new source
+                                    if let Some(script) = scene.get_entity_mut(entity_id).and_then(|e| e.get_component_mut::<Script>()) {
+                                        script.source = source.clone();
+                                    }
                                     if let Err(e) = script_system.reload_script(entity_id, source) {
                                         log::error!("Failed to reload script for entity {:?}: {}", entity_id, e);
-                                        if let Some(ui) = &mut self.ui {
-                                            ui.log_error(format!("Script reload failed: {}", e));
-                                        }
                                     } else {
                                         log::info!("Successfully reloaded script for entity {:?}", entity_id);
                                         if let Some(ui) = &mut self.ui {
@@ -849,11 +2437,29 @@ impl EditorApp {
                                     log::error!("Failed to reload model {:?}: {}", path, e);
                                 } else {
                                     log::info!("Successfully reloaded model: {:?}", path);
+                                    self.mesh_picker.invalidate(&path_str);
                                     // TODO: Re-upload to GPU and update entities
                                 }
                             }
                         }
                     }
+                    ReloadEvent::LibraryChanged(path) => match self.native_modules.reload(&path) {
+                        Ok(Some(name)) => {
+                            if let Some(ui) = &mut self.ui {
+                                ui.log_info(format!("Reloaded native module '{}'", name));
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            log::error!("Failed to reload native module: {:#}", e);
+                            if let Some(ui) = &mut self.ui {
+                                ui.log_error(format!(
+                                    "Native module reload failed, keeping the old build: {:#}",
+                                    e
+                                ));
+                            }
+                        }
+                    },
                     ReloadEvent::AssetChanged(path) => {
                         log::info!("Generic asset changed: {:?}", path);
                         // Generic asset change - determine type by extension
@@ -889,35 +2495,16 @@ impl EditorApp {
                 if let Some(terrain_gen) = entity.get_component::<TerrainGenerator>() {
                     log::info!("Regenerating terrain: {}x{}, moat={}", terrain_gen.width, terrain_gen.depth, terrain_gen.moat_enabled);
 
-                    let config = TerrainConfig {
-                        width: terrain_gen.width,
-                        depth: terrain_gen.depth,
-                        scale: terrain_gen.scale,
-                        height_scale: terrain_gen.height_scale,
-                        seed: terrain_gen.seed,
-                        octaves: terrain_gen.octaves,
-                        frequency: terrain_gen.frequency,
-                        lacunarity: terrain_gen.lacunarity,
-                        persistence: terrain_gen.persistence,
-                    };
-
-                    let heightmap = if terrain_gen.moat_enabled {
-                        HeightMap::generate_with_moat(
-                            &config,
-                            terrain_gen.moat_inner_radius,
-                            terrain_gen.moat_outer_radius,
-                            terrain_gen.moat_depth,
-                        )
-                    } else {
-                        HeightMap::generate(&config)
-                    };
-
-                    let terrain_mesh = Terrain::generate_mesh_from_heightmap(&heightmap, &config);
-                    let gpu_vertices = convert_mesh_to_gpu(&terrain_mesh);
-                    wgpu_state.mesh_manager.upload_mesh(&wgpu_state.renderer.device, "terrain".to_string(), &gpu_vertices, &terrain_mesh.indices);
+                    let (mut heightmap, config) = terrain_tools::generate_heightmap(terrain_gen, asset_manager.asset_root());
+                    spline_edit::carve_splines(scene, &mut heightmap, &config);
 
+                    let scene_path = self.ui.as_ref().and_then(|ui| ui.current_scene_path.as_deref());
+                    let splatmap = terrain_tools::scene_splatmap(asset_manager, scene_path, &config, wgpu_state.terrain_layers.len());
                     wgpu_state.terrain_heightmap = Some(heightmap);
                     wgpu_state.terrain_config = Some(config);
+                    wgpu_state.terrain_splatmap = Some(splatmap);
+                    wgpu_state.terrain_splatmap_dirty = true;
+                    rebuild_terrain_chunks(wgpu_state);
 
                     if let Some(ui) = &mut self.ui {
                         ui.log_info("Terrain regenerated".to_string());
@@ -938,12 +2525,7 @@ impl EditorApp {
                     if let Some(terrain_water) = entity.get_component::<TerrainWater>() {
                         log::info!("Regenerating water: ground_level={}", terrain_water.ground_water_level);
 
-                        let result = compute_water_fill(
-                            heightmap,
-                            terrain_water.ground_water_level,
-                            terrain_water.min_water_depth,
-                            terrain_water.min_water_area,
-                        );
+                        let result = water_edit::compute_terrain_water(heightmap, config, terrain_water);
                         log::info!("Water fill: {} bodies found", result.water_bodies.len());
 
                         for computed_body in &result.water_bodies {
@@ -956,6 +2538,8 @@ impl EditorApp {
                                 surface_level: computed_body.surface_level,
                                 flow_direction: computed_body.flow_direction,
                                 flow_speed: computed_body.flow_speed,
+                                bounds_min: computed_body.bounds_min,
+                                bounds_max: computed_body.bounds_max,
                             });
                         }
 
@@ -966,6 +2550,12 @@ impl EditorApp {
                     }
                 }
             }
+            rebuild_spline_meshes(wgpu_state, scene);
+        }
+
+        // Turntable and flythrough captures drive the editor camera
+        if let Some(job) = &self.capture_job {
+            job.apply_view(&mut self.viewport_controls);
         }
 
         // Update camera based on mode (player, editor, or top-down)
@@ -998,17 +2588,18 @@ impl EditorApp {
                 );
 
                 // Apply horizontal movement
-                let move_speed = 10.0; // units per second
-                let horizontal_movement = (forward * move_dir.z + right * move_dir.x) * move_speed * dt;
+                let camera_speed = self.ui.as_ref().map(|ui| ui.camera_speed).unwrap_or(1.0);
+                let move_speed = 10.0 * camera_speed; // units per second
+                let horizontal_movement = (forward * move_dir.z + right * move_dir.x) * move_speed * real_dt;
                 self.player_position.x += horizontal_movement.x;
                 self.player_position.z += horizontal_movement.z;
 
                 // Apply gravity
                 let gravity = -20.0; // units per second squared
-                self.player_velocity.y += gravity * dt;
+                self.player_velocity.y += gravity * real_dt;
 
                 // Apply vertical velocity
-                self.player_position.y += self.player_velocity.y * dt;
+                self.player_position.y += self.player_velocity.y * real_dt;
 
                 // Terrain collision detection
                 let player_height = 1.8; // Player eye height above ground
@@ -1058,6 +2649,7 @@ impl EditorApp {
                 camera.position = self.player_position;
                 camera.target = self.player_position + camera_forward;
                 camera.up = glam::Vec3::Y;  // Always use world up for FPS camera
+                camera.orthographic = false;
 
                 // Update camera info in UI for status bar
                 if let Some(ui) = &mut self.ui {
@@ -1092,7 +2684,7 @@ impl EditorApp {
                     );
 
                     let pan_speed = 10.0; // units per second
-                    let panning = (forward * pan_dir.z + right * pan_dir.x) * pan_speed * dt;
+                    let panning = (forward * pan_dir.z + right * pan_dir.x) * pan_speed * real_dt;
                     self.topdown_center += panning;
                 }
 
@@ -1102,7 +2694,7 @@ impl EditorApp {
                 if self.topdown_rotation_keys[1] { rotation_input += 1.0; } // E - rotate right
 
                 let rotation_speed = 1.5; // radians per second
-                self.topdown_yaw += rotation_input * rotation_speed * dt;
+                self.topdown_yaw += rotation_input * rotation_speed * real_dt;
 
                 // 3. Mouse edge scrolling
                 let screen_width = wgpu_state.renderer.surface_config.width as f32;
@@ -1137,7 +2729,7 @@ impl EditorApp {
                         0.0,
                         -self.topdown_yaw.sin(),
                     );
-                    let edge_panning = (forward * edge_scroll.z + right * edge_scroll.x) * 15.0 * dt;
+                    let edge_panning = (forward * edge_scroll.z + right * edge_scroll.x) * 15.0 * real_dt;
                     self.topdown_center += edge_panning;
                 }
 
@@ -1150,6 +2742,7 @@ impl EditorApp {
                 camera.position = self.topdown_center + glam::Vec3::new(x, y, z);
                 camera.target = self.topdown_center;
                 camera.up = glam::Vec3::Y;
+                camera.orthographic = false;
 
                 // Update camera info in UI
                 if let Some(ui) = &mut self.ui {
@@ -1168,23 +2761,19 @@ impl EditorApp {
             }
         }
 
+        // While playing, optionally view the scene through its active Camera component
+        if self.play_state.in_session() && self.ui.as_ref().is_some_and(|ui| ui.use_game_camera) {
+            play_mode::apply_game_camera(scene, camera);
+        }
+
         // Handle terrain sculpting (separate borrow scope for mutable access)
         if let Some(ui) = &self.ui {
             if ui.brush_tool.mode.is_terrain_mode() && self.viewport_controls.brush_held {
                 let brush_tool = ui.brush_tool.clone();
 
-                // Save undo state when starting to sculpt (first brush stroke)
+                // Start an undo step when starting to sculpt or paint (first brush stroke)
                 if !self.terrain_sculpt_started {
-                    if let Some(ref heightmap) = wgpu_state.terrain_heightmap {
-                        // Save current heightmap state for undo
-                        self.terrain_undo_stack.push(heightmap.heights.clone());
-                        // Limit undo stack size
-                        if self.terrain_undo_stack.len() > 20 {
-                            self.terrain_undo_stack.remove(0);
-                        }
-                        // Clear redo stack on new edit
-                        self.terrain_redo_stack.clear();
-                    }
+                    self.undo_history.begin_terrain_stroke(scene);
                     self.terrain_sculpt_started = true;
                 }
 
@@ -1196,62 +2785,113 @@ impl EditorApp {
                 // Convert screen position to ray
                 let (ray_origin, ray_direction) = camera.screen_to_ray(mouse_x, mouse_y, screen_width, screen_height);
 
+                // Throttle to avoid too many updates: only apply once moved
+                // more than 1/4 of the brush radius
+                let last_sculpt_pos = self.viewport_controls.last_terrain_sculpt_pos;
+                let should_sculpt = |hit_point: Vec3| {
+                    last_sculpt_pos.is_none_or(|(last_x, last_z)| {
+                        let dx = hit_point.x - last_x;
+                        let dz = hit_point.z - last_z;
+                        (dx * dx + dz * dz).sqrt() > brush_tool.radius * 0.25
+                    })
+                };
+
                 if let (Some(ref mut heightmap), Some(ref config)) = (&mut wgpu_state.terrain_heightmap, &wgpu_state.terrain_config) {
                     if let Some(hit_point) = raycast_terrain(ray_origin, ray_direction, heightmap, config) {
-                        if let Some(terrain_mode) = brush_tool.mode.terrain_mode_code() {
-                            // Check if we should apply (throttle to avoid too many updates)
-                            let should_sculpt = if let Some((last_x, last_z)) = self.viewport_controls.last_terrain_sculpt_pos {
-                                let dx = hit_point.x - last_x;
-                                let dz = hit_point.z - last_z;
-                                let dist = (dx * dx + dz * dz).sqrt();
-                                // Only apply if moved more than 1/4 of brush radius
-                                dist > brush_tool.radius * 0.25
-                            } else {
-                                true
-                            };
+                        if should_sculpt(hit_point) {
+                            // Save the tiles under the brush before they change
+                            let bounds = heightmap.brush_bounds(hit_point.x, hit_point.z, config.scale, brush_tool.radius);
+                            if let Some(bounds) = bounds {
+                                self.undo_history.track_terrain_region(heightmap, wgpu_state.terrain_splatmap.as_ref(), bounds);
+                            }
 
-                            if should_sculpt {
-                                let modified = heightmap.apply_brush(
+                            let modified = match brush_tool.mode.terrain_mode_code() {
+                                Some(terrain_mode) => heightmap.apply_brush(
                                     hit_point.x,
                                     hit_point.z,
                                     config.scale,
                                     brush_tool.radius,
                                     brush_tool.terrain_strength,
                                     terrain_mode,
+                                ),
+                                // Texture painting edits the splatmap instead of the heights
+                                None => wgpu_state.terrain_splatmap.as_mut().is_some_and(|splatmap| {
+                                    splatmap.paint(
+                                        hit_point.x,
+                                        hit_point.z,
+                                        config.scale,
+                                        brush_tool.radius,
+                                        brush_tool.paint_layer,
+                                        brush_tool.paint_opacity,
+                                        brush_tool.paint_falloff,
+                                    )
+                                }),
+                            };
+
+                            if let Some(bounds) = bounds.filter(|_| modified) {
+                                // Regenerate only the chunks under the brush
+                                let layout = TerrainChunks::new(heightmap.width, heightmap.depth);
+                                upload_terrain_chunks(
+                                    &mut wgpu_state.mesh_manager,
+                                    &wgpu_state.renderer.device,
+                                    heightmap,
+                                    config,
+                                    wgpu_state.terrain_splatmap.as_ref(),
+                                    &wgpu_state.terrain_layers,
+                                    layout.chunks_touching(bounds),
+                                    TerrainChunk::mesh_name,
                                 );
+                                wgpu_state.terrain_splatmap_dirty |= brush_tool.mode.terrain_mode_code().is_none();
+                                wgpu_state.grass_terrain_dirty |= brush_tool.mode.terrain_mode_code().is_some();
 
-                                if modified {
-                                    // Regenerate terrain mesh
-                                    let terrain_mesh = Terrain::generate_mesh_from_heightmap(heightmap, config);
-                                    let gpu_vertices = convert_mesh_to_gpu(&terrain_mesh);
-                                    wgpu_state.mesh_manager.upload_mesh(
-                                        &wgpu_state.renderer.device,
-                                        "terrain".to_string(),
-                                        &gpu_vertices,
-                                        &terrain_mesh.indices,
-                                    );
-
-                                    // Update last sculpt position
-                                    self.viewport_controls.last_terrain_sculpt_pos = Some((hit_point.x, hit_point.z));
-
-                                    // Mark scene as modified
-                                    if let Some(ui) = self.ui.as_mut() {
-                                        ui.mark_scene_modified();
-                                    }
+                                // Update last sculpt position
+                                self.viewport_controls.last_terrain_sculpt_pos = Some((hit_point.x, hit_point.z));
+
+                                // Mark scene as modified
+                                if let Some(ui) = self.ui.as_mut() {
+                                    ui.mark_scene_modified();
                                 }
                             }
                         }
                     }
+                } else if let Some(streamed) = wgpu_state.streamed_terrain.as_mut() {
+                    // Streamed tiles take sculpt strokes only (no painted layers)
+                    let hit_point = streamed.raycast(ray_origin, ray_direction).filter(|&hit_point| should_sculpt(hit_point));
+                    if let (Some(hit_point), Some(terrain_mode)) = (hit_point, brush_tool.mode.terrain_mode_code()) {
+                        let modified = streamed.sculpt(
+                            hit_point,
+                            brush_tool.radius,
+                            brush_tool.terrain_strength,
+                            terrain_mode,
+                            &mut wgpu_state.mesh_manager,
+                            &wgpu_state.renderer.device,
+                        );
+                        if modified {
+                            self.viewport_controls.last_terrain_sculpt_pos = Some((hit_point.x, hit_point.z));
+                            if let Some(ui) = self.ui.as_mut() {
+                                ui.mark_scene_modified();
+                            }
+                        }
+                    }
+                }
+            } else if self.terrain_sculpt_started {
+                // Stroke finished: push it as one undo step
+                if let Some(ref heightmap) = wgpu_state.terrain_heightmap {
+                    self.undo_history.end_terrain_stroke(heightmap, wgpu_state.terrain_splatmap.as_ref());
                 }
-            } else {
-                // Reset sculpt started flag when not sculpting
                 self.terrain_sculpt_started = false;
             }
         }
 
         // Handle vegetation brush tool placement (separate borrow scope for immutable access)
         if let Some(ui) = &self.ui {
-            if ui.brush_tool.mode.is_vegetation_mode() && self.viewport_controls.brush_active {
+            // Grass paints continuously while held, like terrain; other vegetation once per click
+            let brush_down = if ui.brush_tool.mode.is_grass_mode() {
+                self.viewport_controls.brush_held
+            } else {
+                self.viewport_controls.brush_active
+            };
+            if ui.brush_tool.mode.is_vegetation_mode() && brush_down {
                 // Get screen dimensions
                 let screen_width = wgpu_state.renderer.surface_config.width as f32;
                 let screen_height = wgpu_state.renderer.surface_config.height as f32;
@@ -1268,40 +2908,8 @@ impl EditorApp {
 
                         match brush_tool.mode {
                             BrushMode::Place => {
-                                // Get vegetation mesh name
-                                let veg_mesh_name = match brush_tool.vegetation_type {
-                                    crate::ui::VegetationType::PineTree => "vegetation_pine",
-                                    crate::ui::VegetationType::OakTree => "vegetation_oak",
-                                    crate::ui::VegetationType::Bush => "vegetation_bush",
-                                    crate::ui::VegetationType::Shrub => "vegetation_shrub",
-                                };
-
                                 // Find or create foliage entity for this vegetation type
-                                let mut foliage_entity_id = None;
-                                for entity in scene.entities() {
-                                    if let Some(foliage) = entity.get_component::<Foliage>() {
-                                        if foliage.vegetation_type == veg_mesh_name {
-                                            foliage_entity_id = Some(entity.id);
-                                            break;
-                                        }
-                                    }
-                                }
-
-                                let entity_id = if let Some(id) = foliage_entity_id {
-                                    id
-                                } else {
-                                    // Create new foliage entity
-                                    let new_id = scene.create_entity(format!("Foliage - {}", brush_tool.vegetation_type.name()));
-                                    if let Some(entity) = scene.get_entity_mut(new_id) {
-                                        entity.add_component(Foliage {
-                                            vegetation_type: veg_mesh_name.to_string(),
-                                            instances: Vec::new(),
-                                            cast_shadows: true,
-                                            color_tint: [1.0, 1.0, 1.0],
-                                        });
-                                    }
-                                    new_id
-                                };
+                                let entity_id = scatter::foliage_entity(scene, brush_tool.vegetation_type);
 
                                 // Add instances based on brush settings
                                 if let Some(entity) = scene.get_entity_mut(entity_id) {
@@ -1392,6 +3000,23 @@ impl EditorApp {
                                     ui.mark_scene_modified();
                                 }
                             }
+                            BrushMode::GrassPaint | BrushMode::GrassErase => {
+                                // Grass density is painted on while the button is held
+                                let entity_id = scatter::grass_entity(scene, config.scale);
+                                let target = if brush_tool.mode == BrushMode::GrassPaint { 1.0 } else { 0.0 };
+                                let painted = scene
+                                    .get_entity_mut(entity_id)
+                                    .and_then(|entity| entity.get_component_mut::<Grass>())
+                                    .is_some_and(|grass| {
+                                        grass.paint(hit_point.x, hit_point.z, brush_tool.radius, target, brush_tool.grass_opacity)
+                                    });
+
+                                if painted {
+                                    if let Some(ui) = self.ui.as_mut() {
+                                        ui.mark_scene_modified();
+                                    }
+                                }
+                            }
                             // Select and terrain modes are not handled here
                             _ => {}
                         }
@@ -1403,9 +3028,44 @@ impl EditorApp {
             }
         }
 
+        // Measure tool: clicks place measurement points, the open end follows the cursor
+        if let Some(ui) = self.ui.as_mut() {
+            if ui.is_measuring() {
+                let screen_size = glam::Vec2::new(
+                    wgpu_state.renderer.surface_config.width as f32,
+                    wgpu_state.renderer.surface_config.height as f32,
+                );
+                let (mouse_x, mouse_y) = self.viewport_controls.current_mouse_pos;
+                let (ray_origin, ray_direction) = camera.screen_to_ray(mouse_x, mouse_y, screen_size.x, screen_size.y);
+                let terrain = placement::terrain_ref(&wgpu_state.terrain_heightmap, &wgpu_state.terrain_config);
+                let cursor_point = measure::surface_point(
+                    scene,
+                    asset_manager,
+                    &mut self.mesh_picker,
+                    terrain,
+                    ray_origin,
+                    ray_direction,
+                );
+                if self.viewport_controls.brush_active {
+                    if let Some(point) = cursor_point {
+                        ui.measure.click(point);
+                    }
+                    self.viewport_controls.brush_active = false;
+                }
+                ui.measure_overlay = measure::build_overlay(
+                    &ui.measure,
+                    cursor_point,
+                    camera.view_projection_matrix(),
+                    screen_size,
+                );
+            } else {
+                ui.measure_overlay = None;
+            }
+        }
+
         // Handle entity selection by clicking in viewport (when in Select mode or brush panel hidden)
         if let Some(ui) = &self.ui {
-            let in_select_mode = ui.brush_tool.mode == BrushMode::Select || !ui.show_brush_panel;
+            let in_select_mode = (ui.brush_tool.mode == BrushMode::Select || !ui.show_brush_panel) && !ui.is_measuring();
             if in_select_mode && self.viewport_controls.brush_active {
                 // Get screen dimensions
                 let screen_width = wgpu_state.renderer.surface_config.width as f32;
@@ -1415,18 +3075,38 @@ impl EditorApp {
                 // Convert screen position to ray
                 let (ray_origin, ray_direction) = camera.screen_to_ray(mouse_x, mouse_y, screen_width, screen_height);
 
-                // Try to pick an entity
-                if let Some((entity_id, _distance)) = pick_entity(ray_origin, ray_direction, scene) {
+                // Try to pick an entity (Ctrl+click adds/removes from the selection).
+                // Icons are drawn over the scene, so they are picked first.
+                let additive = self.modifiers.control_key();
+                let picked_icon = entity_icons::pick_icon(&ui.icon_overlay, glam::Vec2::new(mouse_x, mouse_y));
+                let picked = picked_icon.or_else(|| {
+                    self.mesh_picker
+                        .pick(scene, asset_manager, ray_origin, ray_direction)
+                        .map(|(id, _)| id)
+                });
+                if let Some(entity_id) = picked {
                     if let Some(ui) = self.ui.as_mut() {
-                        ui.selected_entity = Some(entity_id);
+                        if !additive && ui.is_selected(entity_id) && !ui.is_entity_locked(entity_id) {
+                            // Clicked an already selected entity - drag the selection
+                            let roots: Vec<EntityId> = ui
+                                .selection_roots(scene)
+                                .into_iter()
+                                .filter(|&id| !ui.is_entity_locked(id))
+                                .collect();
+                            self.entity_drag = Some(placement::EntityDrag::begin(scene, entity_id, &roots));
+                        } else if additive {
+                            ui.toggle_selection(entity_id);
+                        } else {
+                            ui.select_only(entity_id);
+                        }
                         if let Some(entity) = scene.get_entity(entity_id) {
                             log::info!("Selected entity: {}", entity.name);
                         }
                     }
-                } else {
+                } else if !additive {
                     // Clicked on nothing - deselect
                     if let Some(ui) = self.ui.as_mut() {
-                        ui.selected_entity = None;
+                        ui.clear_selection();
                     }
                 }
 
@@ -1435,178 +3115,766 @@ impl EditorApp {
             }
         }
 
-        // Update scripts
-        script_system.update(scene, dt)?;
-
-        // Sync Water components to buoyancy system
-        if let Some(buoyancy_system) = &mut self.buoyancy_system {
-            // Clear and rebuild water volumes from Water components
-            buoyancy_system.water_volumes.clear();
-            for entity in scene.entities() {
-                if let Some(water) = entity.get_component::<Water>() {
-                    let transform = &entity.transform;
-                    // Create water volume from Water component with flow
-                    let flow_dir = Vec3::new(water.flow_direction[0], 0.0, water.flow_direction[1]);
-                    let water_volume = WaterVolume::new(
-                        transform.position,
-                        transform.scale * 2.0, // Scale is half-extents, volume needs full size
-                        transform.position.y + transform.scale.y, // Top of water
-                    ).with_flow(flow_dir, water.flow_speed);
-                    buoyancy_system.add_water_volume(water_volume);
+        // Drag selected entities with the cursor, snapping to the grid and surfaces
+        if let Some(drag) = self.entity_drag.as_mut() {
+            if !self.viewport_controls.brush_held {
+                self.entity_drag = None;
+                self.viewport_controls.completed_drag = None;
+            } else if let (Some(_), Some(ui)) = (self.viewport_controls.drag_rect(), self.ui.as_mut()) {
+                let (mouse_x, mouse_y) = self.viewport_controls.current_mouse_pos;
+                let (ray_origin, ray_direction) = camera.screen_to_ray(
+                    mouse_x,
+                    mouse_y,
+                    wgpu_state.renderer.surface_config.width as f32,
+                    wgpu_state.renderer.surface_config.height as f32,
+                );
+                let terrain = placement::terrain_ref(&wgpu_state.terrain_heightmap, &wgpu_state.terrain_config);
+                if let Some(point) = placement::placement_point(
+                    scene,
+                    ray_origin,
+                    ray_direction,
+                    terrain,
+                    drag.excluded(),
+                    &ui.inspector_state,
+                    drag.plane_y,
+                ) {
+                    if !drag.moved {
+                        self.undo_history.record_transforms(scene, &drag.roots());
+                        drag.moved = true;
+                    } else {
+                        self.undo_history.continue_edit();
+                    }
+                    drag.move_to(scene, point);
+                    ui.mark_scene_modified();
                 }
             }
-
-            // Apply buoyancy forces to physics bodies
-            buoyancy_system.update(&mut physics_world.rigid_body_set, scene);
         }
 
-        // Step physics simulation
-        physics_world.step(dt);
-
-        // Sync physics world back to scene transforms
-        PhysicsSync::sync_to_scene(physics_world, scene)?;
+        // Handle water editing tools (level handle, flow painting, exclusion zones)
+        let water_tool = self
+            .ui
+            .as_ref()
+            .filter(|ui| ui.show_brush_panel && ui.brush_tool.mode.is_water_mode())
+            .map(|ui| ui.brush_tool.clone());
+        // Edits go to a copy of the first TerrainWater, written back when it changes
+        let water_entity = scene
+            .entities()
+            .find_map(|e| e.get_component::<TerrainWater>().map(|water| (e.id, water.clone())));
+        match (water_tool, water_entity, &wgpu_state.terrain_heightmap, &wgpu_state.terrain_config) {
+            (Some(brush_tool), Some((water_id, mut water)), Some(heightmap), Some(config)) => {
+                let screen_size = glam::Vec2::new(
+                    wgpu_state.renderer.surface_config.width as f32,
+                    wgpu_state.renderer.surface_config.height as f32,
+                );
+                let (mouse_x, mouse_y) = self.viewport_controls.current_mouse_pos;
+                let (ray_origin, ray_direction) = camera.screen_to_ray(mouse_x, mouse_y, screen_size.x, screen_size.y);
+                let pressed = self.viewport_controls.brush_active;
+                let held = self.viewport_controls.brush_held;
+                self.viewport_controls.brush_active = false;
 
-        // Update audio system
-        if let Some(audio_system) = &mut self.audio_system {
-            // Process audio commands from scripts
-            let mut commands = self.audio_command_queue.lock().unwrap();
-            for command in commands.drain(..) {
-                match command {
-                    AudioCommand::PlaySound { path, volume } => {
-                        if let Err(e) = audio_system.play_sound(&path, volume) {
-                            log::warn!("Failed to play sound '{}': {}", path, e);
+                // Level handle sits over the first water body (or the terrain center)
+                // and stays put while it is dragged
+                let handle_anchor = self.water_edit.anchor.unwrap_or_else(|| {
+                    let cell_size = config.scale / config.width as f32;
+                    wgpu_state
+                        .terrain_water_bodies
+                        .first()
+                        .map(|body| {
+                            let center = (body.bounds_min + body.bounds_max) * 0.5;
+                            Vec3::new(
+                                -config.scale * 0.5 + center.x * cell_size,
+                                0.0,
+                                -config.scale * 0.5 + center.z * cell_size,
+                            )
+                        })
+                        .unwrap_or(Vec3::ZERO)
+                });
+                let handle_at = |level: f32| Vec3::new(handle_anchor.x, level, handle_anchor.z);
+
+                let mut changed = false;
+                match brush_tool.mode {
+                    BrushMode::WaterLevel => {
+                        if pressed {
+                            let handle_pos = water_edit::project_to_screen(
+                                camera.view_projection_matrix(),
+                                handle_at(water.ground_water_level),
+                                screen_size,
+                            );
+                            if handle_pos.is_some_and(|pos| {
+                                pos.distance(glam::Vec2::new(mouse_x, mouse_y)) <= water_edit::HANDLE_GRAB_RADIUS
+                            }) {
+                                self.undo_history.record_entities(scene, &[water_id]);
+                                self.water_edit.dragging_level = true;
+                                self.water_edit.anchor = Some(handle_anchor);
+                            }
+                        }
+                        if !held {
+                            self.water_edit.dragging_level = false;
+                            self.water_edit.anchor = None;
+                        }
+                        if self.water_edit.dragging_level {
+                            let anchor = handle_at(water.ground_water_level);
+                            if let Some(level) = water_edit::level_from_ray(anchor, camera, ray_origin, ray_direction) {
+                                let level = (level * 100.0).round() / 100.0;
+                                if level != water.ground_water_level {
+                                    water.ground_water_level = level;
+                                    changed = true;
+                                }
+                            }
                         }
                     }
-                    AudioCommand::PlayMusic { path, volume, looping } => {
-                        if let Err(e) = audio_system.play_music(&path, volume, looping) {
-                            log::warn!("Failed to play music '{}': {}", path, e);
+                    BrushMode::WaterFlow => {
+                        if !held {
+                            self.water_edit.last_flow_point = None;
+                        } else if let Some(hit) = raycast_terrain(ray_origin, ray_direction, heightmap, config) {
+                            match self.water_edit.last_flow_point {
+                                None => {
+                                    self.undo_history.record_entities(scene, &[water_id]);
+                                    self.water_edit.last_flow_point = Some(hit);
+                                }
+                                Some(last) => {
+                                    let delta = glam::Vec2::new(hit.x - last.x, hit.z - last.z);
+                                    // Throttle like terrain sculpting: a vector every 1/4 brush radius
+                                    if delta.length() > brush_tool.radius * 0.25 {
+                                        let mid = (hit + last) * 0.5;
+                                        water_edit::paint_flow(
+                                            &mut water.flow_strokes,
+                                            [mid.x, mid.z],
+                                            delta,
+                                            brush_tool.water_flow_speed,
+                                            brush_tool.radius * 0.5,
+                                        );
+                                        self.water_edit.last_flow_point = Some(hit);
+                                        changed = true;
+                                    }
+                                }
+                            }
                         }
                     }
-                    AudioCommand::StopMusic => {
-                        audio_system.stop_music();
+                    BrushMode::WaterExclude => {
+                        if let Some(hit) = raycast_terrain(ray_origin, ray_direction, heightmap, config).filter(|_| pressed) {
+                            let point = [hit.x, hit.z];
+                            if self.modifiers.shift_key() {
+                                changed = water_edit::remove_exclusion_zones_at(&mut water.exclusion_zones, point);
+                            } else {
+                                water.exclusion_zones.push(WaterExclusionZone {
+                                    center: point,
+                                    radius: brush_tool.radius,
+                                });
+                                changed = true;
+                            }
+                            if changed {
+                                self.undo_history.record_entities(scene, &[water_id]);
+                            }
+                        }
                     }
+                    _ => {}
                 }
-            }
-            drop(commands); // Release the lock
 
-            // Create audio listener from camera transform
-            let listener = engine_audio::AudioListener::from_transform(camera.position, Quat::IDENTITY);
+                if changed {
+                    // Level drags and flow strokes stay one undo step
+                    self.undo_history.continue_edit();
+                    if let Some(component) = scene.get_entity_mut(water_id).and_then(|e| e.get_component_mut::<TerrainWater>()) {
+                        *component = water.clone();
+                    }
+                    // Live recompute of the fill with the edited settings
+                    wgpu_state.water_needs_regeneration = true;
+                    if let Some(ui) = self.ui.as_mut() {
+                        ui.mark_scene_modified();
+                    }
+                }
 
-            // Process AudioSource components
-            for entity in scene.entities() {
-                if let Some(audio_source) = entity.get_component::<AudioSource>() {
-                    let position = entity.transform.position;
-
-                    // Play audio if marked to play on start and not currently playing
-                    if audio_source.play_on_start && !audio_source.playing {
-                        // Play 3D sound
-                        if let Err(e) = audio_system.play_3d_sound(
-                            &audio_source.audio_path,
-                            position,
-                            &listener,
-                            audio_source.volume,
-                            audio_source.max_distance,
-                        ) {
-                            log::warn!("Failed to play audio '{}': {}", audio_source.audio_path, e);
-                        } else {
-                            // Mark as playing (note: this is read-only view, so we can't actually update it)
-                            // TODO: Need mutable access to components to track playing state
-                        }
+                if let Some(ui) = self.ui.as_mut() {
+                    let mut overlay = water_edit::build_overlay(
+                        &water,
+                        heightmap,
+                        config,
+                        handle_at(water.ground_water_level),
+                        camera,
+                        screen_size,
+                    );
+                    if brush_tool.mode != BrushMode::WaterLevel {
+                        overlay.handle = None;
                     }
+                    overlay.handle_active = self.water_edit.dragging_level;
+                    ui.water_overlay = Some(overlay);
+                }
+            }
+            _ => {
+                self.water_edit = water_edit::WaterEditState::default();
+                if let Some(ui) = self.ui.as_mut() {
+                    ui.water_overlay = None;
                 }
             }
         }
 
-        // Initialize and update particle systems
-        for entity in scene.entities() {
-            if let Some(particle_emitter) = entity.get_component::<ParticleEmitter>() {
-                if !particle_emitter.enabled {
-                    continue;
+        // Spline tool: edits the selected entity's Spline over the terrain
+        let spline_tool = self.ui.as_ref().is_some_and(|ui| ui.show_brush_panel && ui.brush_tool.mode == BrushMode::SplineEdit);
+        let spline_entity = self
+            .ui
+            .as_ref()
+            .and_then(|ui| ui.selected_entity)
+            .and_then(|id| Some((id, scene.get_entity(id)?.get_component::<Spline>()?.clone())));
+        match (spline_tool, spline_entity, &wgpu_state.terrain_heightmap, &wgpu_state.terrain_config) {
+            (true, Some((spline_id, mut spline)), Some(heightmap), Some(config)) => {
+                let screen_size = glam::Vec2::new(
+                    wgpu_state.renderer.surface_config.width as f32,
+                    wgpu_state.renderer.surface_config.height as f32,
+                );
+                let (mouse_x, mouse_y) = self.viewport_controls.current_mouse_pos;
+                let (ray_origin, ray_direction) = camera.screen_to_ray(mouse_x, mouse_y, screen_size.x, screen_size.y);
+                let pressed = self.viewport_controls.brush_active;
+                let held = self.viewport_controls.brush_held;
+                self.viewport_controls.brush_active = false;
+
+                let mut changed = false;
+                if pressed {
+                    let picked = spline_edit::pick_point(&spline, camera.view_projection_matrix(), screen_size, glam::Vec2::new(mouse_x, mouse_y));
+                    match picked {
+                        Some(index) if self.modifiers.shift_key() => {
+                            self.undo_history.record_entities(scene, &[spline_id]);
+                            spline.points.remove(index);
+                            changed = true;
+                        }
+                        Some(index) => {
+                            self.undo_history.record_entities(scene, &[spline_id]);
+                            self.spline_edit.dragging = Some(index);
+                        }
+                        None if !self.modifiers.shift_key() => {
+                            if let Some(hit) = raycast_terrain(ray_origin, ray_direction, heightmap, config) {
+                                self.undo_history.record_entities(scene, &[spline_id]);
+                                spline.points.push(hit.to_array());
+                                self.spline_edit.dragging = Some(spline.points.len() - 1);
+                                changed = true;
+                            }
+                        }
+                        None => {}
+                    }
+                }
+                if !held {
+                    self.spline_edit.dragging = None;
+                }
+                if let Some(index) = self.spline_edit.dragging.filter(|&index| index < spline.points.len()) {
+                    if let Some(hit) = raycast_terrain(ray_origin, ray_direction, heightmap, config) {
+                        if spline.points[index] != hit.to_array() {
+                            spline.points[index] = hit.to_array();
+                            changed = true;
+                        }
+                    }
                 }
 
-                let entity_id = entity.id;
+                if changed {
+                    // A point drag stays one undo step
+                    self.undo_history.continue_edit();
+                    if let Some(component) = scene.get_entity_mut(spline_id).and_then(|e| e.get_component_mut::<Spline>()) {
+                        *component = spline.clone();
+                    }
+                    // Live rebuild of the river or road mesh
+                    wgpu_state.water_needs_regeneration = true;
+                    if let Some(ui) = self.ui.as_mut() {
+                        ui.mark_scene_modified();
+                    }
+                }
 
-                // Initialize particle system if it doesn't exist
-                if !wgpu_state.particle_systems.contains_key(&entity_id) {
-                    use engine_particles::{ParticleSystem, EmitterProperties, EmitterShape};
+                if let Some(ui) = self.ui.as_mut() {
+                    let mut overlay = spline_edit::build_overlay(&spline, camera, screen_size, config.scale / config.width as f32);
+                    overlay.active = self.spline_edit.dragging;
+                    ui.spline_overlay = Some(overlay);
+                }
+            }
+            _ => {
+                self.spline_edit = spline_edit::SplineEditState::default();
+                if let Some(ui) = self.ui.as_mut() {
+                    ui.spline_overlay = None;
+                }
+            }
+        }
 
-                    log::info!("Initializing particle emitter for entity {} ({})", entity_id.0, entity.name);
-                    log::info!("  Rate: {}, Max particles: {}", particle_emitter.rate, particle_emitter.max_particles);
+        // Baked navmesh shown while the Navigation panel is open
+        if let Some(ui) = self.ui.as_mut() {
+            ui.navmesh_overlay = match &ui.navigation.navmesh {
+                Some(navmesh) if ui.show_navigation && ui.navigation.show_navmesh => {
+                    let screen_size = glam::Vec2::new(
+                        wgpu_state.renderer.surface_config.width as f32,
+                        wgpu_state.renderer.surface_config.height as f32,
+                    );
+                    navigation::build_overlay(navmesh, camera, screen_size)
+                }
+                _ => Vec::new(),
+            };
+        }
 
-                    // Parse emitter shape
-                    let shape = match particle_emitter.shape.as_str() {
-                        "sphere" => EmitterShape::Sphere { radius: 1.0 },
-                        "cone" => EmitterShape::Cone { angle: 30.0, radius: 1.0 },
-                        "box" => EmitterShape::Box { size: Vec3::ONE },
-                        "circle" => EmitterShape::Circle { radius: 1.0 },
-                        _ => EmitterShape::Point,
-                    };
+        // Icons for lights, cameras, audio and particles (hidden while looking through the game camera)
+        if let Some(ui) = self.ui.as_mut() {
+            let game_camera_view = self.play_state.in_session() && ui.use_game_camera;
+            ui.icon_overlay = if ui.show_entity_icons && !game_camera_view {
+                let screen_size = glam::Vec2::new(
+                    wgpu_state.renderer.surface_config.width as f32,
+                    wgpu_state.renderer.surface_config.height as f32,
+                );
+                entity_icons::build_overlay(scene, camera, screen_size, |id| ui.is_entity_visible(id), |id| ui.is_selected(id))
+            } else {
+                entity_icons::IconOverlay::default()
+            };
+        }
 
-                    let properties = EmitterProperties {
-                        shape,
-                        rate: particle_emitter.rate,
-                        initial_velocity: Vec3::from(particle_emitter.initial_velocity),
-                        velocity_randomness: particle_emitter.velocity_randomness,
-                        lifetime: particle_emitter.lifetime,
-                        lifetime_randomness: particle_emitter.lifetime_randomness,
-                        initial_size: particle_emitter.initial_size,
-                        size_over_lifetime: vec![1.0, 0.1], // Linear shrink
-                        initial_color: particle_emitter.initial_color,
-                        color_over_lifetime: vec![],
-                        gravity: Vec3::from(particle_emitter.gravity),
-                    };
+        // Box selection by dragging in the viewport (Ctrl adds to the selection)
+        let in_select_mode = self
+            .ui
+            .as_ref()
+            .map(|ui| (ui.brush_tool.mode == BrushMode::Select || !ui.show_brush_panel) && !ui.is_measuring())
+            .unwrap_or(false);
+        let completed_drag = self.viewport_controls.completed_drag.take();
+        if let Some(ui) = self.ui.as_mut() {
+            ui.box_select_rect = self
+                .viewport_controls
+                .drag_rect()
+                .filter(|_| in_select_mode && self.entity_drag.is_none())
+                .map(|(start, end)| {
+                    (
+                        glam::Vec2::new(start.0, start.1),
+                        glam::Vec2::new(end.0, end.1),
+                    )
+                });
 
-                    let position = entity.transform.position;
-                    let mut system = ParticleSystem::new(particle_emitter.max_particles, properties);
-                    system.position = position;
+            if let (Some((start, end)), true) = (completed_drag, in_select_mode) {
+                let screen_size = glam::Vec2::new(
+                    wgpu_state.renderer.surface_config.width as f32,
+                    wgpu_state.renderer.surface_config.height as f32,
+                );
+                let hits = selection::entities_in_screen_rect(
+                    scene,
+                    camera,
+                    glam::Vec2::new(start.0, start.1),
+                    glam::Vec2::new(end.0, end.1),
+                    screen_size,
+                    &ui.hidden_entities,
+                );
+                if self.modifiers.control_key() {
+                    ui.add_to_selection(&hits);
+                } else {
+                    ui.set_selection(&hits);
+                }
+                log::info!("Box selected {} entities", hits.len());
+            }
+        }
 
-                    // Spawn initial batch of particles for immediate visibility
-                    for _ in 0..50 {
-                        system.update(0.016); // Simulate ~3 frames worth of spawning
+        // Advance the simulation only while playing (see play_mode). With
+        // fixed updates the game logic (scripts, behavior trees, navigation, animation, plugin systems,
+        // buoyancy, characters, ragdolls, physics) runs in fixed steps, as many as the frame's game time adds up to;
+        // particles and foliage update once per rendered frame. While the
+        // game is paused one pass runs with a zero delta so scripts can
+        // resume it. Systems declare what they read and write so the ones
+        // that don't conflict (buoyancy, particles, foliage gathering) run in
+        // parallel. Particle emitters therefore follow their entity as of
+        // before the last physics step. A network client doesn't step
+        // physics: the server's snapshots move its entities. In determinism
+        // mode the steps are always fixed, the systems run one at a time and
+        // each step's length, input and outcome go into the replay. Frame
+        // stepping advances one project timestep per rendered frame.
+        let simulating = self.play_state.is_simulating();
+        let net_client = self.net.is_client();
+        let deterministic = self.replay.is_some();
+        let (steps, step_dt) = if !simulating {
+            (0, 0.0)
+        } else if dt <= 0.0 {
+            // Game paused: one pass with nothing to integrate
+            (1, 0.0)
+        } else if self.frames_to_step.is_some() {
+            (1, play_mode::fixed_dt())
+        } else if self.frame_pacer.config().fixed_update || deterministic {
+            (
+                self.frame_pacer.fixed_steps(dt, play_mode::fixed_dt()),
+                play_mode::fixed_dt(),
+            )
+        } else {
+            (1, dt)
+        };
+        let simulation_start = std::time::Instant::now();
+        let simulation_span = tracing::info_span!("simulation").entered();
+        let mut foliage_by_type = std::collections::HashMap::new();
+        let mut timings = Vec::new();
+        {
+            let scene_lock = RwLock::new(&mut *scene);
+            let physics_lock = Mutex::new(&mut *physics_world);
+            let scripts_lock = Mutex::new(&mut *script_system);
+            let behavior_system = &mut self.behavior_system;
+            let skeletal_animation = &mut self.skeletal_animation;
+            let hidden_entities = self.ui.as_ref().map(|ui| &ui.hidden_entities);
+            let navmesh = self
+                .ui
+                .as_ref()
+                .and_then(|ui| ui.navigation.navmesh.as_ref());
+            let particle_systems = &mut wgpu_state.particle_systems;
+            let particle_pipelines = &mut wgpu_state.particle_compute_pipelines;
+            let device = &wgpu_state.renderer.device;
+            let queue = &wgpu_state.renderer.queue;
+            let session_seed = self.session_seed;
+
+            let passes = steps.max(1);
+            for pass in 0..passes {
+                let step = pass < steps;
+                let last_pass = pass + 1 == passes;
+
+                let mut graph = SystemGraph::new();
+                graph.set_sequential(deterministic);
+                let step_input = self.game_input.lock().unwrap().clone();
+                if step {
+                    graph
+                        // Writes physics only to lend it to scripts' queries, so nothing else sees it meanwhile
+                        .add_system("Scripts", &[], &["scene", "scripts", "physics"], || {
+                            let mut scripts = scripts_lock.lock().unwrap();
+                            let mut physics_world = physics_lock.lock().unwrap();
+                            scripts.with_physics(&mut physics_world, |scripts| {
+                                scripts.update(&mut scene_lock.write().unwrap(), step_dt)
+                            })
+                        })
+                        .add_system("Behavior", &[], &["scene", "scripts"], || {
+                            let mut scripts = scripts_lock.lock().unwrap();
+                            behavior_system.update(
+                                &mut scene_lock.write().unwrap(),
+                                step_dt,
+                                |scene, entity, function| {
+                                    scripts.run_behavior_task(scene, entity, function, step_dt)
+                                },
+                            );
+                            Ok(())
+                        })
+                        .add_system("Navigation", &[], &["scene"], || {
+                            // Without a baked navmesh agents walk straight there
+                            engine_scene::navigation::update_nav_agents(
+                                &mut scene_lock.write().unwrap(),
+                                step_dt,
+                                |from, to| match navmesh {
+                                    Some(navmesh) => navmesh.find_path(from, to),
+                                    None => Some(vec![from, to]),
+                                },
+                            );
+                            Ok(())
+                        })
+                        .add_system("Time of Day", &[], &["scene"], || {
+                            engine_scene::time_of_day::update_sun_lights(&mut scene_lock.write().unwrap(), step_dt);
+                            Ok(())
+                        })
+                        .add_system("Animation", &[], &["scene"], || {
+                            let mut scene = scene_lock.write().unwrap();
+                            engine_scene::animation::update_animations(&mut scene, step_dt);
+                            skeletal_animation.update(&mut scene);
+                            Ok(())
+                        })
+                        .add_system("IK", &["physics"], &["scene"], || {
+                            let physics_world = physics_lock.lock().unwrap();
+                            engine_scene::ik::update_ik(
+                                &mut scene_lock.write().unwrap(),
+                                |origin, distance, ignore| {
+                                    physics_world
+                                        .ground_below(origin, distance, ignore)
+                                        .map(|hit| (hit.point, hit.normal))
+                                },
+                            );
+                            Ok(())
+                        });
+                    for system in &mut self.plugins.systems {
+                        graph.add_system(system.name, &[], &["scene"], || {
+                            system.run(&mut scene_lock.write().unwrap(), step_dt)
+                        });
+                    }
+                    if !self.native_modules.is_empty() {
+                        let native_modules = &self.native_modules;
+                        graph.add_system("Native Modules", &[], &["scene"], || {
+                            native_modules.run_systems(&mut scene_lock.write().unwrap(), step_dt)
+                        });
+                    }
+                    if let Some(buoyancy_system) = self.buoyancy_system.as_mut() {
+                        graph.add_system("Buoyancy", &["scene"], &["physics"], || {
+                            frame_systems::update_buoyancy(
+                                buoyancy_system,
+                                &scene_lock.read().unwrap(),
+                                &mut physics_lock.lock().unwrap(),
+                            );
+                            Ok(())
+                        });
                     }
+                }
+                if last_pass && simulating {
+                    graph.add_system(
+                        "Particle Update",
+                        &["scene", "renderer"],
+                        &["particles"],
+                        || {
+                            frame_systems::update_particles(
+                                &scene_lock.read().unwrap(),
+                                dt,
+                                session_seed,
+                                particle_systems,
+                                particle_pipelines,
+                                device,
+                                queue,
+                            );
+                            Ok(())
+                        },
+                    );
+                }
+                if last_pass && wgpu_state.foliage_renderer.is_some() {
+                    graph.add_system("Foliage Gather", &["scene"], &["foliage"], || {
+                        foliage_by_type = frame_systems::gather_foliage(
+                            &scene_lock.read().unwrap(),
+                            hidden_entities,
+                        );
+                        Ok(())
+                    });
+                }
+                if step && !net_client {
+                    if let Some(character_system) = self.character_system.as_mut() {
+                        graph.add_system("Characters", &[], &["scene", "physics"], || {
+                            character_system.update(
+                                &mut scene_lock.write().unwrap(),
+                                &mut physics_lock.lock().unwrap(),
+                                step_dt,
+                            );
+                            Ok(())
+                        });
+                    }
+                    if let Some(ragdoll_system) = self.ragdoll_system.as_mut() {
+                        graph.add_system("Ragdoll", &[], &["scene", "physics"], || {
+                            ragdoll_system.update(
+                                &mut scene_lock.write().unwrap(),
+                                &mut physics_lock.lock().unwrap(),
+                                step_dt,
+                            );
+                            Ok(())
+                        });
+                    }
+                    graph.add_system("Physics", &[], &["physics", "scene"], || {
+                        let mut physics_world = physics_lock.lock().unwrap();
+                        // Nothing to integrate while the game is paused or frozen
+                        if step_dt > 0.0 {
+                            physics_world.step(step_dt);
+                        }
+                        // Sync physics world back to scene transforms
+                        PhysicsSync::sync_to_scene(&physics_world, &mut scene_lock.write().unwrap())
+                    });
+                }
+                timings.extend(graph.run()?);
+                drop(graph);
+                if step {
+                    if let Some(replay) = &mut self.replay {
+                        replay.record(step_dt, &step_input, &scene_lock.read().unwrap());
+                    }
+                    // Presses and mouse movement count for one step only
+                    self.game_input.lock().unwrap().end_frame();
+                }
+            }
+        }
+        for timing in timings {
+            frame_timer.record_duration(timing.name, timing.start, timing.duration);
+        }
+
+        if simulating {
+            if let Some(session) = &mut self.play_session {
+                for _ in 0..steps {
+                    session.advance(step_dt);
+                }
+            }
+            if let Some(frames) = &mut self.frames_to_step {
+                *frames = frames.saturating_sub(1);
+            }
+        }
+        drop(simulation_span);
+        frame_timer.record("Simulation", simulation_start);
 
-                    log::info!("Created particle system at position {:?} with {} initial particles",
-                              position, system.active_particle_count());
+        // Exchange script messages and snapshots with the network session
+        if simulating {
+            self.net.set_view(camera.position);
+            self.net.update(scene, physics_world, dt);
+        }
 
-                    let initial_particles = system.particles.clone();
-                    wgpu_state.particle_systems.insert(entity_id, system);
+        // Carry out save_game/load_game calls scripts made this frame
+        if simulating {
+            let music = self.audio_system.as_ref().and_then(|audio| audio.playing_music());
+            let errors = self.save_games.process_requests(
+                scene,
+                physics_world,
+                script_system,
+                music,
+                &self.audio_command_queue,
+                &self.collider_geometry,
+            );
+            for error in errors {
+                log::error!("Save game error: {}", error);
+            }
+        }
 
-                    // Create compute pipeline with initial particles
-                    // After this, GPU takes over - no more uploads!
-                    if let Ok(compute_pipeline) = engine_particles::ParticleComputePipeline::new(
-                        &wgpu_state.renderer.device,
-                        particle_emitter.max_particles,
-                        &initial_particles,
-                    ) {
-                        wgpu_state.particle_compute_pipelines.insert(entity_id, compute_pipeline);
-                    }
+        // Keep a recovery copy of the scene being edited
+        if self.play_session.is_none() {
+            if let Some(ui) = &self.ui {
+                self.autosave.set_scene_path(
+                    ui.current_scene_path
+                        .as_deref()
+                        .or(self.scene_file_path.as_deref()),
+                );
+                if let Some(path) = self.autosave.update(scene, ui.scene_modified) {
+                    save_scene_splatmap(wgpu_state.terrain_splatmap.as_ref(), &path.to_string_lossy());
                 }
+            }
+        }
 
-                // Update particle system and spawn new particles
-                if let Some(system) = wgpu_state.particle_systems.get_mut(&entity_id) {
-                    system.position = entity.transform.position;
-                    system.update(dt);
+        // Update audio system
+        if let Some(audio_system) = &mut self.audio_system {
+            // Process audio commands from scripts
+            let mut commands = self.audio_command_queue.lock().unwrap();
+            for command in commands.drain(..) {
+                match command {
+                    AudioCommand::PlaySound { path, volume } => {
+                        if let Err(e) = audio_system.play_sound(&path, volume) {
+                            log::warn!("Failed to play sound '{}': {}", path, e);
+                        }
+                    }
+                    AudioCommand::PlayMusic {
+                        path,
+                        volume,
+                        looping,
+                        fade_in,
+                    } => {
+                        let track = MusicTrack::new(path.clone())
+                            .with_volume(volume)
+                            .with_looping(looping)
+                            .with_fade_in(fade_in);
+                        if let Err(e) = audio_system.play_track(track) {
+                            log::warn!("Failed to play music '{}': {}", path, e);
+                        }
+                    }
+                    AudioCommand::StopMusic { fade_out } => {
+                        self.pending_music_moment = None;
+                        audio_system.fade_out_music(fade_out);
+                    }
+                    AudioCommand::PlayMoment { name } => {
+                        let Some(moments) = &self.music_moments else {
+                            log::warn!("No music moments configured (assets/music/moments.ron)");
+                            continue;
+                        };
+                        if let Some(track) = moments.cached_track(&name) {
+                            self.pending_music_moment = None;
+                            if let Err(e) =
+                                audio_system.play_music(&track.path, track.volume, track.looping)
+                            {
+                                log::warn!("Failed to play music moment '{}': {}", name, e);
+                            }
+                        } else if moments.moment(&name).is_some() {
+                            log::info!("Generating music for moment '{}'", name);
+                            self.pending_music_moment = Some(moments.request(&name));
+                        } else {
+                            log::warn!("Unknown music moment '{}'", name);
+                        }
+                    }
+                    AudioCommand::PlayAdaptive { name, fade_in } => {
+                        let Some(track) =
+                            self.adaptive_music.as_ref().and_then(|config| config.track(&name))
+                        else {
+                            log::warn!(
+                                "Unknown adaptive music track '{}' (assets/music/adaptive.ron)",
+                                name
+                            );
+                            continue;
+                        };
+                        self.pending_music_moment = None;
+                        if let Err(e) = audio_system.play_adaptive(track, fade_in) {
+                            log::warn!("Failed to play adaptive music '{}': {}", name, e);
+                        }
+                    }
+                    AudioCommand::SetMusicParameter { name, value } => {
+                        audio_system.set_music_parameter(&name, value);
+                    }
+                    AudioCommand::PlaySource { entity } => {
+                        if let Err(e) = self.audio_voices.play(audio_system, scene, entity) {
+                            log::warn!("Failed to play audio for {:?}: {}", entity, e);
+                        }
+                    }
+                    AudioCommand::StopSource { entity } => self.audio_voices.stop(entity),
+                    AudioCommand::PauseSource { entity } => self.audio_voices.pause(entity),
+                    AudioCommand::ResumeSource { entity } => self.audio_voices.resume(entity),
+                    AudioCommand::SeekSource { entity, seconds } => {
+                        self.audio_voices.seek(entity, seconds)
+                    }
+                }
+            }
+            drop(commands); // Release the lock
 
-                    // Upload particles to GPU (includes newly spawned ones)
-                    // GPU compute will update them immediately after
-                    if let Some(compute_pipeline) = wgpu_state.particle_compute_pipelines.get(&entity_id) {
-                        compute_pipeline.upload_particles(
-                            &wgpu_state.renderer.queue,
-                            &system.particles,
-                        );
+            // Play a music moment once its background generation finishes
+            if let Some(receiver) = &self.pending_music_moment {
+                if let Ok(result) = receiver.try_recv() {
+                    self.pending_music_moment = None;
+                    match result {
+                        Ok(track) => {
+                            if let Err(e) =
+                                audio_system.play_music(&track.path, track.volume, track.looping)
+                            {
+                                log::warn!("Failed to play music moment '{}': {}", track.moment, e);
+                            }
+                        }
+                        Err(e) => log::warn!("Music moment generation failed: {}", e),
                     }
                 }
             }
+
+            // Create audio listener from camera transform, on the game camera's entity when
+            // viewing through it so the rig it sits on doesn't muffle everything
+            let game_camera = self.play_state.in_session() && self.ui.as_ref().is_some_and(|ui| ui.use_game_camera);
+            let listener_entity = game_camera
+                .then(|| play_mode::find_game_camera(scene))
+                .flatten()
+                .map(|(entity, ..)| entity);
+            let listener = engine_audio::AudioListener::from_transform(camera.position, Quat::IDENTITY)
+                .with_entity(listener_entity);
+
+            // World sounds take on the reverb zones around the listener
+            let zones: Vec<_> = scene
+                .entities()
+                .filter_map(|entity| {
+                    let zone = entity.get_component::<ReverbZone>()?;
+                    Some((scene.world_matrix(entity.id).w_axis.truncate(), zone))
+                })
+                .collect();
+            let effects = engine_audio::zone_effects(&EffectSettings::default(), listener.position, zones);
+            audio_system.set_bus_effects(AudioBus::Sfx, effects);
+
+            // AudioSource components play on their own voices, following their
+            // entities and muffled by the colliders between them and the listener
+            self.audio_voices.update(audio_system, scene, Some(&*physics_world), &listener, simulating);
         }
 
+        reupload_evicted_models(
+            scene,
+            asset_manager,
+            &mut wgpu_state.mesh_manager,
+            &wgpu_state.renderer.device,
+            &mut wgpu_state.pending_model_lods,
+        );
+        upload_model_lods(
+            asset_manager,
+            &mut wgpu_state.mesh_manager,
+            &wgpu_state.renderer.device,
+            &mut wgpu_state.pending_model_lods,
+        );
+        refresh_loaded_materials(wgpu_state);
+        swap_compressed_textures(wgpu_state);
+
         // Begin frame
-        let (output, mut encoder, view) = wgpu_state.renderer.begin_frame(
+        let render_start = std::time::Instant::now();
+        let render_span = tracing::info_span!("scene_render").entered();
+        let (output, mut encoder, surface_view) = wgpu_state.renderer.begin_frame(
             &wgpu_state.surface,
             &wgpu_state.depth_texture,
         )?;
+        wgpu_state.gpu_profiler.begin_frame(&mut encoder);
+        // Scene passes draw into the HDR framebuffer; post-processing tone maps it to the swapchain
+        let view = wgpu_state.framebuffer.view.clone();
+        // With MSAA off, scene passes draw straight to the framebuffer instead of resolving
+        let msaa = wgpu_state.renderer.sample_count > 1;
 
         // Update and dispatch particle compute shaders
-        for (entity_id, particle_system) in &mut wgpu_state.particle_systems {
+        // (frozen while paused or editing)
+        for (entity_id, particle_system) in wgpu_state.particle_systems.iter_mut().filter(|_| simulating) {
             if let Some(compute_pipeline) = wgpu_state.particle_compute_pipelines.get(entity_id) {
                 // DON'T upload particles here - let GPU maintain state
                 // Only upload uniforms for simulation
@@ -1615,7 +3883,7 @@ impl EditorApp {
                 compute_pipeline.update_uniforms(
                     &wgpu_state.renderer.queue,
                     dt,
-                    self.time,
+                    elapsed,
                     particle_system.properties.gravity,
                 );
 
@@ -1627,9 +3895,61 @@ impl EditorApp {
             }
         }
 
-        let view_proj = camera.view_projection_matrix();
+        // The Game tab shows the scene through its Camera component, drawn into the tab's frame
+        let game_viewport = self.ui.as_ref().and_then(|ui| ui.game_view_viewport);
+        let game_camera = game_viewport.and_then(|_| {
+            let mut game_camera = Camera::new(1, 1);
+            play_mode::apply_game_camera(scene, &mut game_camera).then_some(game_camera)
+        });
+        wgpu_state.renderer.set_scene_viewport(game_camera.as_ref().and(game_viewport));
+        let game_camera = game_camera
+            .zip(wgpu_state.renderer.scene_viewport)
+            .map(|(mut game_camera, [_, _, width, height])| {
+                game_camera.aspect = width / height;
+                game_camera
+            });
+        let render_camera: &Camera = game_camera.as_ref().unwrap_or(camera);
+
+        // Stream terrain tiles around whichever camera is drawn
+        StreamedTerrain::sync(&mut wgpu_state.streamed_terrain, scene, asset_manager.asset_root(), &mut wgpu_state.mesh_manager);
+        if let Some(streamed) = wgpu_state.streamed_terrain.as_mut() {
+            streamed.update(render_camera.position, &mut wgpu_state.mesh_manager, &wgpu_state.renderer.device);
+            // Edited tiles go to disk with the scene
+            if self.ui.as_ref().is_some_and(|ui| !ui.scene_modified) {
+                streamed.save_dirty();
+            }
+        }
+        StreamedTerrain::sync_colliders(wgpu_state.streamed_terrain.as_mut(), physics_world);
+
+        // Supersampled captures render one tile of the view per frame
+        let tile_matrix = self.capture_job.as_ref().map_or(glam::Mat4::IDENTITY, |job| job.tile_matrix());
+        let camera_view_proj = tile_matrix * render_camera.view_projection_matrix();
+
+        // TAA history would blend capture tiles together, so captures go without it
+        let selected_anti_aliasing = self.ui.as_ref().map(|ui| ui.anti_aliasing).unwrap_or_default();
+        let anti_aliasing = match selected_anti_aliasing.effective(wgpu_state.renderer.sample_count) {
+            AntiAliasingMode::Taa if self.capture_job.is_some() => AntiAliasingMode::None,
+            mode => mode,
+        };
+        let scene_viewport = wgpu_state.renderer.scene_viewport.unwrap_or([
+            0.0,
+            0.0,
+            wgpu_state.renderer.surface_config.width as f32,
+            wgpu_state.renderer.surface_config.height as f32,
+        ]);
+        // With TAA every frame is drawn with a sub-pixel offset, and the history fills in between
+        let view_proj = if anti_aliasing == AntiAliasingMode::Taa {
+            jitter_matrix(wgpu_state.taa.jitter(), glam::Vec2::new(scene_viewport[2], scene_viewport[3])) * camera_view_proj
+        } else {
+            // Stale history would ghost when TAA comes back on
+            wgpu_state.taa.reset();
+            camera_view_proj
+        };
         let view_proj_inverse = view_proj.inverse();
 
+        // Draw counters for the statistics window and viewport overlay
+        let mut render_stats = RenderStats::default();
+
         // Update camera uniform buffer for skybox
         #[repr(C)]
         #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
@@ -1649,41 +3969,23 @@ impl EditorApp {
             bytemuck::cast_slice(&[camera_uniforms]),
         );
 
-        // Render shadow map (depth pass from light's perspective)
-        if let Some(ref shadow_map) = wgpu_state.shadow_map {
-            // Directional light coming from a lower angle for more visible shadows
-            let light_direction = glam::Vec3::new(0.8, -0.5, 0.4).normalize();
+        wgpu_state.gpu_profiler.mark(&mut encoder, "Particle Compute");
 
-            // Calculate actual scene bounds from all entities
-            let mut min_bounds = glam::Vec3::splat(f32::MAX);
-            let mut max_bounds = glam::Vec3::splat(f32::MIN);
-            let mut has_entities = false;
-
-            for entity in scene.entities() {
-                let pos = entity.transform.position;
-                min_bounds = min_bounds.min(pos);
-                max_bounds = max_bounds.max(pos);
-                has_entities = true;
-            }
-
-            // Fallback if no entities
-            if !has_entities {
-                min_bounds = glam::Vec3::splat(-10.0);
-                max_bounds = glam::Vec3::splat(10.0);
-            }
-
-            // Expand bounds slightly for margin
-            min_bounds -= glam::Vec3::splat(2.0);
-            max_bounds += glam::Vec3::splat(2.0);
-
-            let scene_center = (min_bounds + max_bounds) * 0.5;
-            let scene_radius = (max_bounds - min_bounds).length() * 0.5;
+        // Sky and sunlight follow the scene's SunLight, or a fixed afternoon sun without one
+        let sun_light = engine_scene::time_of_day::find_sun_light(scene).map(|(_, sun)| sun.clone());
+        let sky = sun_light.as_ref().map_or_else(ProceduralSky::default, |sun| ProceduralSky {
+            sun_direction: sun.sun_direction(),
+            turbidity: sun.turbidity,
+            intensity: sun.intensity,
+        });
+        if let Some(skybox) = wgpu_state.skybox.as_mut() {
+            skybox.set_procedural_sky(&wgpu_state.renderer.queue, &sky);
+        }
+        wgpu_state.renderer.set_sun(sky.sun_direction, sky.sun_radiance());
 
-            let light_space_matrix = ShadowMap::calculate_light_space_matrix(
-                light_direction,
-                scene_center,
-                scene_radius,
-            );
+        // Render shadow map (depth pass from light's perspective)
+        if let Some(ref shadow_map) = wgpu_state.shadow_map {
+            let light_space_matrix = shadow_light_space_matrix(scene, sun_light.is_some(), &sky);
 
             // Update shadow uniforms once (light space matrix only)
             shadow_map.update_uniforms(
@@ -1711,8 +4013,11 @@ impl EditorApp {
                 shadow_pass.set_pipeline(&shadow_map.render_pipeline);
                 shadow_pass.set_bind_group(0, &shadow_map.bind_group, &[]);
 
-                // Render all meshes to shadow map (skip hidden entities)
-                for entity in scene.entities() {
+                // Render all meshes to shadow map (skip hidden entities).
+                // With shadows off, or a light that doesn't cast them, the map
+                // is only cleared, so nothing is in shadow.
+                let casts_shadows = self.shadows_enabled && sun_casts_shadows(scene);
+                for entity in scene.entities().filter(|_| casts_shadows) {
                     // Skip hidden entities
                     if let Some(ui) = &self.ui {
                         if ui.hidden_entities.contains(&entity.id) {
@@ -1720,7 +4025,20 @@ impl EditorApp {
                         }
                     }
                     if let Some(mesh_renderer) = entity.get_component::<MeshRenderer>() {
-                        if let Some(mesh_handle) = wgpu_state.mesh_manager.get_handle(&mesh_renderer.mesh_path) {
+                        // The terrain casts shadows from its chunks, at the LODs the camera sees
+                        let mesh_handles = match (mesh_renderer.mesh_path == "terrain", &wgpu_state.terrain_chunks, &wgpu_state.terrain_config) {
+                            (true, Some(layout), Some(config)) => terrain_chunk_meshes(
+                                &wgpu_state.mesh_manager,
+                                layout,
+                                config,
+                                scene.world_matrix(entity.id),
+                                render_camera.position,
+                                None,
+                                TerrainChunk::mesh_name,
+                            ),
+                            _ => mesh_renderer_handle(&wgpu_state.mesh_manager, mesh_renderer).into_iter().collect(),
+                        };
+                        for mesh_handle in mesh_handles {
                             if let Some(gpu_mesh) = wgpu_state.mesh_manager.get_mesh(mesh_handle) {
                                 let world_matrix = scene.world_matrix(entity.id);
 
@@ -1738,36 +4056,480 @@ impl EditorApp {
                                 shadow_pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
                                 shadow_pass.set_index_buffer(gpu_mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
                                 shadow_pass.draw_indexed(0..gpu_mesh.num_indices, 0, 0..1);
+                                render_stats.record_draw(gpu_mesh.num_indices, 1);
+                            }
+                        }
+                    }
+                }
+
+                // Streamed terrain tiles
+                let streamed_chunks = wgpu_state.streamed_terrain.as_ref().filter(|_| casts_shadows).map(|streamed| {
+                    streamed.chunk_meshes(&wgpu_state.mesh_manager, render_camera.position, None)
+                });
+                for (mesh_handle, world_matrix) in streamed_chunks.into_iter().flatten() {
+                    if let Some(gpu_mesh) = wgpu_state.mesh_manager.get_mesh(mesh_handle) {
+                        use engine_render::shadow::ShadowPushConstants;
+                        let push_constants = ShadowPushConstants {
+                            model: world_matrix.to_cols_array_2d(),
+                        };
+                        shadow_pass.set_push_constants(
+                            wgpu::ShaderStages::VERTEX,
+                            0,
+                            bytemuck::cast_slice(&[push_constants]),
+                        );
+                        shadow_pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+                        shadow_pass.set_index_buffer(gpu_mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                        shadow_pass.draw_indexed(0..gpu_mesh.num_indices, 0, 0..1);
+                        render_stats.record_draw(gpu_mesh.num_indices, 1);
+                    }
+                }
+            } // shadow_pass dropped here
+        }
+
+        wgpu_state.gpu_profiler.mark(&mut encoder, "Shadows");
+
+        // Create shadow sampling bind group for main render pass
+        let shadow_sampling_bind_group = if let Some(ref shadow_map) = wgpu_state.shadow_map {
+            let layout = ShadowMap::create_sampling_bind_group_layout(&wgpu_state.renderer.device);
+            Some(shadow_map.create_sampling_bind_group(&wgpu_state.renderer.device, &layout))
+        } else {
+            None
+        };
+
+        // Render skybox first (background) - with MSAA
+        if let Some(ref skybox) = wgpu_state.skybox {
+            if let Some(ref camera_bind_group) = wgpu_state.camera_bind_group {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Skybox Render Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: if msaa { &wgpu_state.msaa_texture } else { &view },
+                        resolve_target: msaa.then_some(&view),
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color {
+                                r: 0.1,
+                                g: 0.2,
+                                b: 0.3,
+                                a: 1.0,
+                            }),
+                            store: wgpu::StoreOp::Store,
+                        },
+                        depth_slice: None,
+                    })],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &wgpu_state.depth_texture,
+                        depth_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(1.0),
+                            store: wgpu::StoreOp::Store,
+                        }),
+                        stencil_ops: None,
+                    }),
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+
+                wgpu_state.renderer.apply_scene_viewport(&mut render_pass);
+                render_pass.set_pipeline(&skybox.render_pipeline);
+                render_pass.set_bind_group(0, camera_bind_group, &[]);
+                render_pass.set_bind_group(1, &skybox.bind_group, &[]);
+                render_pass.draw(0..3, 0..1); // Fullscreen triangle
+            }
+        }
+
+        wgpu_state.gpu_profiler.mark(&mut encoder, "Skybox");
+
+        // Water reflections: the reflection target follows the quality preference
+        let reflection_quality = self.ui.as_ref().map(|ui| ui.water_reflections).unwrap_or_default();
+        if reflection_quality != wgpu_state.water_reflection.quality {
+            let config = &wgpu_state.renderer.surface_config;
+            match WaterReflection::new(&wgpu_state.renderer.device, config.width, config.height, reflection_quality) {
+                Ok(reflection) => {
+                    if let Some(water_renderer) = &mut wgpu_state.water_renderer {
+                        water_renderer.set_reflection(&wgpu_state.renderer.device, &reflection);
+                    }
+                    wgpu_state.water_reflection = reflection;
+                }
+                Err(e) => log::warn!("Failed to create water reflection target: {}", e),
+            }
+        }
+        // The mirrored view of the highest water surface under the camera
+        let water_heights = scene
+            .entities()
+            .filter(|entity| !self.ui.as_ref().is_some_and(|ui| ui.hidden_entities.contains(&entity.id)))
+            .filter_map(|entity| {
+                let water = entity.get_component::<Water>()?;
+                let handle = wgpu_state.mesh_manager.get_handle(&water.mesh_path)?;
+                let bounds = wgpu_state.mesh_manager.get_mesh(handle)?.bounds;
+                let top = Vec3::new(bounds.center().x, bounds.max.y, bounds.center().z);
+                Some(scene.world_matrix(entity.id).transform_point3(top).y)
+            })
+            .chain(wgpu_state.terrain_water_bodies.iter().map(|body| body.surface_level));
+        let water_reflection = (wgpu_state.water_reflection.enabled() && wgpu_state.water_renderer.is_some())
+            .then(|| reflection_plane_height(water_heights, render_camera.position.y))
+            .flatten()
+            .and_then(|height| {
+                let projection = tile_matrix * render_camera.projection_matrix();
+                let matrix = reflection_view_proj(render_camera.view_matrix(), projection, render_camera.position, height)?;
+                Some((matrix, height))
+            });
+
+        // Render all entities with textures (skip hidden entities and those outside the view),
+        // batched by mesh and material into instanced draws. The water reflection gets its own
+        // batches of what the mirrored camera sees, which the main view may have culled.
+        let view_frustum = Frustum::from_view_projection(view_proj);
+        let reflection_view = water_reflection.map(|(matrix, height)| {
+            (Frustum::from_view_projection(matrix), reflection_matrix(height).transform_point3(render_camera.position))
+        });
+        wgpu_state.mesh_batches.clear();
+        wgpu_state.reflection_batches.clear();
+        // With a splatmap the terrain renderer draws the terrain instead of its material
+        let splat_terrain = wgpu_state.terrain_renderer.is_some() && wgpu_state.terrain_splatmap.is_some();
+        let mut terrain_draws = Vec::new();
+        let mut reflection_terrain_draws = Vec::new();
+        // Meshes that moved since last frame get their own TAA motion vectors
+        let mut world_matrices = std::collections::HashMap::new();
+        let mut moved_meshes = Vec::new();
+        for entity in scene.entities() {
+            // Skip hidden entities
+            if let Some(ui) = &self.ui {
+                if ui.hidden_entities.contains(&entity.id) {
+                    continue;
+                }
+            }
+            if let Some(mesh_renderer) = entity.get_component::<MeshRenderer>() {
+                // The terrain draws its chunks, each at the LOD for its distance
+                if let (true, Some(layout), Some(config)) = (mesh_renderer.mesh_path == "terrain", &wgpu_state.terrain_chunks, &wgpu_state.terrain_config) {
+                    let world_matrix = scene.world_matrix(entity.id);
+                    let chunks = terrain_chunk_meshes(
+                        &wgpu_state.mesh_manager,
+                        layout,
+                        config,
+                        world_matrix,
+                        render_camera.position,
+                        Some((&view_frustum, &mut render_stats.culling)),
+                        TerrainChunk::mesh_name,
+                    );
+                    let reflected_chunks = reflection_view.as_ref().map_or_else(Vec::new, |(frustum, camera_position)| {
+                        terrain_chunk_meshes(
+                            &wgpu_state.mesh_manager,
+                            layout,
+                            config,
+                            world_matrix,
+                            *camera_position,
+                            Some((frustum, &mut CullingStats::default())),
+                            TerrainChunk::mesh_name,
+                        )
+                    });
+                    if splat_terrain {
+                        terrain_draws.extend(chunks.into_iter().map(|chunk| (chunk, world_matrix)));
+                        reflection_terrain_draws.extend(reflected_chunks.into_iter().map(|chunk| (chunk, world_matrix)));
+                    } else {
+                        let material_path = mesh_renderer.material_path.as_deref().unwrap_or("materials/default.mat");
+                        let material_handle = scene_material(wgpu_state, asset_manager, material_path);
+                        for chunk in chunks {
+                            wgpu_state.mesh_batches.push(chunk, material_handle, world_matrix, 1.0);
+                        }
+                        for chunk in reflected_chunks {
+                            wgpu_state.reflection_batches.push(chunk, material_handle, world_matrix, 1.0);
+                        }
+                    }
+                    continue;
+                }
+                if let Some(mesh_handle) = mesh_renderer_handle(&wgpu_state.mesh_manager, mesh_renderer) {
+                    if let Some(gpu_mesh) = wgpu_state.mesh_manager.get_mesh(mesh_handle) {
+                        let world_matrix = scene.world_matrix(entity.id);
+
+                        let world_bounds = gpu_mesh.bounds.transform(world_matrix);
+                        let visible = view_frustum.contains_aabb(world_bounds.min, world_bounds.max);
+                        render_stats.culling.record(visible);
+                        let reflected_from = reflection_view
+                            .as_ref()
+                            .filter(|(frustum, _)| frustum.contains_aabb(world_bounds.min, world_bounds.max))
+                            .map(|(_, camera_position)| *camera_position);
+                        if !visible && reflected_from.is_none() {
+                            continue;
+                        }
+
+                        // Get material path (use default if not specified)
+                        let material_path = mesh_renderer.material_path.as_deref()
+                            .unwrap_or("materials/default.mat");
+                        let material_handle = scene_material(wgpu_state, asset_manager, material_path);
+                        let radius = (world_bounds.max - world_bounds.min).length() * 0.5;
+                        let position = world_matrix.w_axis.truncate();
+
+                        if let Some(camera_position) = reflected_from {
+                            let (lod_draw, lod_incoming) = mesh_renderer_lod_draws(&wgpu_state.mesh_manager, mesh_renderer, mesh_handle, radius, render_camera.fov, camera_position, position);
+                            for draw in std::iter::once(lod_draw).chain(lod_incoming) {
+                                wgpu_state.reflection_batches.push(draw.mesh, material_handle, world_matrix, draw.fade);
                             }
                         }
+                        if !visible {
+                            continue;
+                        }
+                        let distance = render_camera.position.distance(position);
+                        request_material_mips(wgpu_state, material_handle, radius, distance, render_camera.fov);
+
+                        // Near a LOD switch both levels draw, dithered into each other
+                        let (lod_draw, lod_incoming) = mesh_renderer_lod_draws(&wgpu_state.mesh_manager, mesh_renderer, mesh_handle, radius, render_camera.fov, render_camera.position, position);
+                        for draw in std::iter::once(lod_draw).chain(lod_incoming) {
+                            wgpu_state.mesh_batches.push(draw.mesh, material_handle, world_matrix, draw.fade);
+                        }
+                        if let Some(previous) = wgpu_state.previous_world_matrices.get(&entity.id).filter(|m| **m != world_matrix) {
+                            moved_meshes.push((lod_draw.mesh, MotionInstance::new(world_matrix, *previous)));
+                        }
+                        world_matrices.insert(entity.id, world_matrix);
+                    }
+                }
+            }
+        }
+        wgpu_state.previous_world_matrices = world_matrices;
+        if let Some(streamed) = &wgpu_state.streamed_terrain {
+            let chunks = streamed.chunk_meshes(&wgpu_state.mesh_manager, render_camera.position, Some((&view_frustum, &mut render_stats.culling)));
+            let reflected_chunks = reflection_view.as_ref().map_or_else(Vec::new, |(frustum, camera_position)| {
+                streamed.chunk_meshes(&wgpu_state.mesh_manager, *camera_position, Some((frustum, &mut CullingStats::default())))
+            });
+            let material_path = streamed.material_path().to_string();
+            let material_handle = scene_material(wgpu_state, asset_manager, &material_path);
+            for (chunk, world_matrix) in chunks {
+                wgpu_state.mesh_batches.push(chunk, material_handle, world_matrix, 1.0);
+            }
+            for (chunk, world_matrix) in reflected_chunks {
+                wgpu_state.reflection_batches.push(chunk, material_handle, world_matrix, 1.0);
+            }
+        }
+        // Roads along splines are already in world space
+        for (mesh_name, material_path) in wgpu_state.spline_roads.clone() {
+            let Some(mesh_handle) = wgpu_state.mesh_manager.get_handle(&mesh_name) else {
+                continue;
+            };
+            let Some(bounds) = wgpu_state.mesh_manager.get_mesh(mesh_handle).map(|mesh| mesh.bounds) else {
+                continue;
+            };
+            let visible = view_frustum.contains_aabb(bounds.min, bounds.max);
+            render_stats.culling.record(visible);
+            let reflected = reflection_view.as_ref().is_some_and(|(frustum, _)| frustum.contains_aabb(bounds.min, bounds.max));
+            if visible || reflected {
+                let material_handle = scene_material(wgpu_state, asset_manager, &material_path);
+                if visible {
+                    wgpu_state.mesh_batches.push(mesh_handle, material_handle, glam::Mat4::IDENTITY, 1.0);
+                }
+                if reflected {
+                    wgpu_state.reflection_batches.push(mesh_handle, material_handle, glam::Mat4::IDENTITY, 1.0);
+                }
+            }
+        }
+
+        // Painted weights changed since the last upload
+        if wgpu_state.terrain_splatmap_dirty {
+            if let (Some(terrain_renderer), Some(splatmap)) = (&mut wgpu_state.terrain_renderer, &wgpu_state.terrain_splatmap) {
+                terrain_renderer.update_splatmap(&wgpu_state.renderer.device, &wgpu_state.renderer.queue, splatmap);
+            }
+            wgpu_state.terrain_splatmap_dirty = false;
+        }
+
+        // Shadow bind group is always present since shadow_map is created in init
+        if let Some(ref shadow_bind_group) = shadow_sampling_bind_group {
+            // Ambient light and reflections come from the sky, if there is one
+            let environment_bind_group = wgpu_state.skybox.as_ref().map_or(
+                &wgpu_state.renderer.default_environment.bind_group,
+                |skybox| &skybox.ibl.bind_group,
+            ).clone();
+            if let Some((reflection_view_proj, height)) = water_reflection {
+                let draws = wgpu_state.renderer.render_reflection(
+                    &mut encoder,
+                    &wgpu_state.water_reflection.target.view,
+                    &wgpu_state.water_reflection.depth,
+                    &wgpu_state.reflection_batches,
+                    &wgpu_state.mesh_manager,
+                    &wgpu_state.material_manager,
+                    reflection_view_proj,
+                    reflection_matrix(height).transform_point3(render_camera.position),
+                    shadow_bind_group,
+                    &environment_bind_group,
+                );
+                for (index_count, instance_count) in draws {
+                    render_stats.record_draw(index_count, instance_count);
+                }
+                if let Some(terrain_renderer) = wgpu_state.terrain_renderer.as_ref().filter(|_| !reflection_terrain_draws.is_empty()) {
+                    let mut terrain_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("Terrain Reflection Pass"),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                            view: &wgpu_state.water_reflection.target.view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Load,
+                                store: wgpu::StoreOp::Store,
+                            },
+                            depth_slice: None,
+                        })],
+                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                            view: &wgpu_state.water_reflection.depth,
+                            depth_ops: Some(wgpu::Operations {
+                                load: wgpu::LoadOp::Load,
+                                store: wgpu::StoreOp::Discard,
+                            }),
+                            stencil_ops: None,
+                        }),
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                    });
+                    for &(mesh_handle, world_matrix) in &reflection_terrain_draws {
+                        if let Some(gpu_mesh) = wgpu_state.mesh_manager.get_mesh(mesh_handle) {
+                            terrain_renderer.render_reflection(
+                                &mut terrain_pass,
+                                gpu_mesh,
+                                world_matrix,
+                                &wgpu_state.renderer.reflection_uniform_bind_group,
+                                shadow_bind_group,
+                                &environment_bind_group,
+                            );
+                            render_stats.record_draw(gpu_mesh.num_indices, 1);
+                        }
+                    }
+                }
+                wgpu_state.gpu_profiler.mark(&mut encoder, "Water Reflection");
+            }
+            // Without a skybox this pass clears the targets
+            let draws = wgpu_state.renderer.render_batches(
+                &mut encoder,
+                if msaa { &wgpu_state.msaa_texture } else { &view },
+                msaa.then_some(&view),
+                &wgpu_state.depth_texture,
+                &wgpu_state.mesh_batches,
+                &wgpu_state.mesh_manager,
+                &wgpu_state.material_manager,
+                view_proj,
+                render_camera.position,
+                shadow_bind_group,
+                &environment_bind_group,
+                wgpu_state.skybox.is_none(),
+            );
+            for (index_count, instance_count) in draws {
+                render_stats.record_draw(index_count, instance_count);
+            }
+            wgpu_state.gpu_profiler.mark(&mut encoder, "Meshes");
+
+            // Terrain with its painted layers, after the meshes so it shares their depth
+            if let Some(terrain_renderer) = wgpu_state.terrain_renderer.as_ref().filter(|_| !terrain_draws.is_empty()) {
+                let mut terrain_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Terrain Render Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: if msaa { &wgpu_state.msaa_texture } else { &view },
+                        resolve_target: msaa.then_some(&view),
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                        depth_slice: None,
+                    })],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &wgpu_state.depth_texture,
+                        depth_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        }),
+                        stencil_ops: None,
+                    }),
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                wgpu_state.renderer.apply_scene_viewport(&mut terrain_pass);
+                for &(mesh_handle, world_matrix) in &terrain_draws {
+                    if let Some(gpu_mesh) = wgpu_state.mesh_manager.get_mesh(mesh_handle) {
+                        terrain_renderer.render(
+                            &mut terrain_pass,
+                            gpu_mesh,
+                            world_matrix,
+                            &wgpu_state.renderer.uniform_bind_group,
+                            shadow_bind_group,
+                            &environment_bind_group,
+                        );
+                        render_stats.record_draw(gpu_mesh.num_indices, 1);
                     }
                 }
-            } // shadow_pass dropped here
+                drop(terrain_pass);
+                wgpu_state.gpu_profiler.mark(&mut encoder, "Terrain");
+            }
+        } else {
+            wgpu_state.gpu_profiler.mark(&mut encoder, "Meshes");
         }
 
-        // Create shadow sampling bind group for main render pass
-        let shadow_sampling_bind_group = if let Some(ref shadow_map) = wgpu_state.shadow_map {
-            let layout = ShadowMap::create_sampling_bind_group_layout(&wgpu_state.renderer.device);
-            Some(shadow_map.create_sampling_bind_group(&wgpu_state.renderer.device, &layout))
-        } else {
-            None
-        };
+        // Render skinned meshes with their bones' joint matrices (skip hidden entities)
+        let mut skinned_draws = Vec::new();
+        if let Some(ref mut skinned_renderer) = wgpu_state.skinned_renderer {
+            skinned_renderer.update_camera(
+                &wgpu_state.renderer.queue,
+                view_proj,
+                render_camera.position,
+            );
 
-        // Render skybox first (background) - with MSAA
-        if let Some(ref skybox) = wgpu_state.skybox {
-            if let Some(ref camera_bind_group) = wgpu_state.camera_bind_group {
-                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("Skybox Render Pass"),
+            let draws = &mut skinned_draws;
+            for entity in scene.entities() {
+                if self
+                    .ui
+                    .as_ref()
+                    .is_some_and(|ui| ui.hidden_entities.contains(&entity.id))
+                {
+                    continue;
+                }
+                let Some(mesh) = entity.get_component::<SkinnedMesh>() else {
+                    continue;
+                };
+                if !load_skinned_model(asset_manager, &mut self.skeletal_animation, &mesh.model_path) {
+                    continue;
+                }
+                let Some(model) = self.skeletal_animation.model(&mesh.model_path) else {
+                    continue;
+                };
+                if !skinned_renderer.has_model(&mesh.model_path) {
+                    let primitives: Vec<(Vec<SkinnedVertexGpu>, &[u32])> = model
+                        .primitives
+                        .iter()
+                        .map(|primitive| {
+                            let vertices = primitive
+                                .vertices
+                                .iter()
+                                .map(|v| SkinnedVertexGpu {
+                                    position: v.position.to_array(),
+                                    normal: v.normal.to_array(),
+                                    tex_coord: v.tex_coord.to_array(),
+                                    joints: v.joints,
+                                    weights: v.weights,
+                                })
+                                .collect();
+                            (vertices, primitive.indices.as_slice())
+                        })
+                        .collect();
+                    skinned_renderer.upload_model(
+                        &wgpu_state.renderer.device,
+                        &mesh.model_path,
+                        primitives
+                            .iter()
+                            .map(|(vertices, indices)| (vertices.as_slice(), *indices)),
+                    );
+                }
+                skinned_renderer.update_joints(
+                    &wgpu_state.renderer.device,
+                    &wgpu_state.renderer.queue,
+                    entity.id.0,
+                    &model.skeleton.joint_matrices(scene, &mesh.bones),
+                );
+                draws.push((mesh.model_path.clone(), entity.id.0));
+            }
+            skinned_renderer
+                .retain_instances(|instance| draws.iter().any(|(_, id)| *id == instance));
+
+            if !draws.is_empty() {
+                let mut skinned_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Skinned Render Pass"),
                     color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view: &wgpu_state.msaa_texture,
-                        resolve_target: Some(&view),
+                        view: if msaa {
+                            &wgpu_state.msaa_texture
+                        } else {
+                            &view
+                        },
+                        resolve_target: msaa.then_some(&view),
                         ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color {
-                                r: 0.1,
-                                g: 0.2,
-                                b: 0.3,
-                                a: 1.0,
-                            }),
+                            load: wgpu::LoadOp::Load,
                             store: wgpu::StoreOp::Store,
                         },
                         depth_slice: None,
@@ -1775,7 +4537,7 @@ impl EditorApp {
                     depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
                         view: &wgpu_state.depth_texture,
                         depth_ops: Some(wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(1.0),
+                            load: wgpu::LoadOp::Load,
                             store: wgpu::StoreOp::Store,
                         }),
                         stencil_ops: None,
@@ -1784,203 +4546,209 @@ impl EditorApp {
                     occlusion_query_set: None,
                 });
 
-                render_pass.set_pipeline(&skybox.render_pipeline);
-                render_pass.set_bind_group(0, camera_bind_group, &[]);
-                render_pass.set_bind_group(1, &skybox.bind_group, &[]);
-                render_pass.draw(0..3, 0..1); // Fullscreen triangle
-            }
-        }
-
-        // Render all entities with textures (skip hidden entities)
-        let mut first_mesh = wgpu_state.skybox.is_none();
-        for entity in scene.entities() {
-            // Skip hidden entities
-            if let Some(ui) = &self.ui {
-                if ui.hidden_entities.contains(&entity.id) {
-                    continue;
+                wgpu_state.renderer.apply_scene_viewport(&mut skinned_pass);
+                for (path, instance) in draws.iter() {
+                    let indices = skinned_renderer.render(&mut skinned_pass, path, *instance);
+                    render_stats.record_draw(indices, 1);
                 }
             }
-            if let Some(mesh_renderer) = entity.get_component::<MeshRenderer>() {
-                if let Some(mesh_handle) = wgpu_state.mesh_manager.get_handle(&mesh_renderer.mesh_path) {
-                    if let Some(gpu_mesh) = wgpu_state.mesh_manager.get_mesh(mesh_handle) {
-                        let world_matrix = scene.world_matrix(entity.id);
-
-                        // Get material path (use default if not specified)
-                        let material_path = mesh_renderer.material_path.as_deref()
-                            .unwrap_or("materials/default.mat");
-
-                        // Get or upload material
-                        let material_handle = if let Some(handle) = wgpu_state.material_manager.get_handle(material_path) {
-                            handle
-                        } else {
-                            // Load material from asset manager
-                            let material_handle = asset_manager.load_material(material_path)
-                                .unwrap_or_else(|e| {
-                                    log::warn!("Failed to load material '{}': {}, using default", material_path, e);
-                                    AssetHandle::new(Material::default())
-                                });
-                            let material = material_handle.inner.as_ref();
+        }
 
-                            // Load required textures
-                            let albedo_handle = material.albedo_texture.as_ref()
-                                .and_then(|path| {
-                                    asset_manager.load_texture(path).ok()
-                                        .map(|tex_handle| wgpu_state.texture_manager.upload_texture(&wgpu_state.renderer.device, &wgpu_state.renderer.queue, path.to_string(), tex_handle.inner.as_ref()))
-                                })
-                                .unwrap_or_else(|| wgpu_state.texture_manager.white_texture_handle());
+        wgpu_state.gpu_profiler.mark(&mut encoder, "Skinned");
 
-                            let normal_handle = material.normal_texture.as_ref()
-                                .and_then(|path| {
-                                    asset_manager.load_texture(path).ok()
-                                        .map(|tex_handle| wgpu_state.texture_manager.upload_texture(&wgpu_state.renderer.device, &wgpu_state.renderer.queue, path.to_string(), tex_handle.inner.as_ref()))
-                                })
-                                .unwrap_or_else(|| wgpu_state.texture_manager.white_texture_handle());
+        // Foliage and grass sway in the scene's Wind; still air without one
+        let wind = engine_scene::wind::find_wind(scene).map_or_else(FoliageWind::default, |(_, wind)| FoliageWind {
+            direction: wind.direction(),
+            strength: wind.strength,
+            gust_strength: wind.gust_strength,
+            gust_frequency: wind.gust_frequency,
+            gust_scale: wind.gust_scale,
+        });
 
-                            let metallic_roughness_handle = material.metallic_roughness_texture.as_ref()
-                                .and_then(|path| {
-                                    asset_manager.load_texture(path).ok()
-                                        .map(|tex_handle| wgpu_state.texture_manager.upload_texture(&wgpu_state.renderer.device, &wgpu_state.renderer.queue, path.to_string(), tex_handle.inner.as_ref()))
-                                })
-                                .unwrap_or_else(|| wgpu_state.texture_manager.white_texture_handle());
+        // Render foliage (instanced vegetation, skip hidden entities)
+        if let Some(ref mut foliage_renderer) = wgpu_state.foliage_renderer {
+            // (instances were gathered in foliage_by_type alongside the simulation)
 
-                            let ao_handle = material.ao_texture.as_ref()
-                                .and_then(|path| {
-                                    asset_manager.load_texture(path).ok()
-                                        .map(|tex_handle| wgpu_state.texture_manager.upload_texture(&wgpu_state.renderer.device, &wgpu_state.renderer.queue, path.to_string(), tex_handle.inner.as_ref()))
-                                })
-                                .unwrap_or_else(|| wgpu_state.texture_manager.white_texture_handle());
+            // Update camera and wind uniforms for foliage
+            foliage_renderer.update_camera(&wgpu_state.renderer.queue, view_proj, render_camera.position, &wind, elapsed);
 
-                            // Upload material to GPU
-                            wgpu_state.material_manager.upload_material(
-                                &wgpu_state.renderer.device,
-                                &wgpu_state.texture_manager,
-                                material_path.to_string(),
-                                &material,
-                                albedo_handle,
-                                Some(normal_handle),
-                                Some(metallic_roughness_handle),
-                                Some(ao_handle),
-                            )
-                        };
+            // Cull every vegetation type, then upload the visible instances in one buffer:
+            // near ones get the full mesh, farther ones the low detail mesh, then impostors
+            let mut batches = FoliageBatches::default();
+            for (veg_type, chunks) in &foliage_by_type {
+                let Some(mesh_handle) = wgpu_state.mesh_manager.get_handle(veg_type) else {
+                    continue;
+                };
+                let Some(gpu_mesh) = wgpu_state.mesh_manager.get_mesh(mesh_handle) else {
+                    continue;
+                };
+                let radius = gpu_mesh.bounds.min.length().max(gpu_mesh.bounds.max.length());
+                let visible = chunks.cull(&view_frustum, render_camera.position, radius);
+
+                let lod_handle = VegetationType::all()
+                    .iter()
+                    .find(|t| t.mesh_name() == veg_type)
+                    .and_then(|t| wgpu_state.mesh_manager.get_handle(&t.lod_mesh_name()))
+                    .unwrap_or(mesh_handle);
+                batches.push(FoliageDraw::Mesh(mesh_handle), &visible.full);
+                batches.push(FoliageDraw::Mesh(lod_handle), &visible.lod);
+                if foliage_renderer.has_impostor(veg_type) {
+                    batches.push(FoliageDraw::Impostor(veg_type.clone()), &visible.impostor);
+                } else {
+                    batches.push(FoliageDraw::Mesh(lod_handle), &visible.impostor);
+                }
+            }
 
-                        // Get material bind group
-                        let material_bind_group = wgpu_state.material_manager.get_material(material_handle)
-                            .map(|mat| &mat.bind_group)
-                            .expect("Material bind group should exist");
+            if !batches.draws.is_empty() {
+                foliage_renderer.update_instances(
+                    &wgpu_state.renderer.device,
+                    &wgpu_state.renderer.queue,
+                    &batches.instances,
+                );
 
-                        // Get shadow bind group (or create a dummy one if shadows disabled)
-                        let shadow_bind_group = if let Some(ref shadow_bg) = shadow_sampling_bind_group {
-                            shadow_bg
-                        } else {
-                            // Create a fallback bind group if shadows are disabled
-                            // (this shouldn't happen since shadow_map is created in init)
-                            continue;
-                        };
+                // Create foliage render pass
+                let mut foliage_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Foliage Render Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: if msaa { &wgpu_state.msaa_texture } else { &view },
+                        resolve_target: msaa.then_some(&view),
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                        depth_slice: None,
+                    })],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &wgpu_state.depth_texture,
+                        depth_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        }),
+                        stencil_ops: None,
+                    }),
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
 
-                        wgpu_state.renderer.render_mesh(
-                            &mut encoder,
-                            &wgpu_state.msaa_texture,
-                            Some(&view),
-                            &wgpu_state.depth_texture,
-                            gpu_mesh,
-                            view_proj,
-                            camera.position,
-                            world_matrix,
-                            material_bind_group,
-                            shadow_bind_group,
-                            first_mesh,
-                        );
-                        first_mesh = false;
+                wgpu_state.renderer.apply_scene_viewport(&mut foliage_pass);
+                for (draw, instances) in &batches.draws {
+                    let count = instances.len() as u32;
+                    match draw {
+                        FoliageDraw::Mesh(handle) => {
+                            if let Some(gpu_mesh) = wgpu_state.mesh_manager.get_mesh(*handle) {
+                                foliage_renderer.render(&mut foliage_pass, gpu_mesh, instances.clone());
+                                render_stats.record_draw(gpu_mesh.num_indices, count);
+                            }
+                        }
+                        FoliageDraw::Impostor(name) => {
+                            foliage_renderer.render_impostors(&mut foliage_pass, name, instances.clone());
+                            render_stats.record_draw(6, count);
+                        }
                     }
                 }
             }
         }
 
-        // Render foliage (instanced vegetation, skip hidden entities)
-        if let Some(ref mut foliage_renderer) = wgpu_state.foliage_renderer {
-            // Collect all foliage instances grouped by vegetation type
-            let mut foliage_by_type: std::collections::HashMap<String, Vec<FoliageInstanceGpu>> = std::collections::HashMap::new();
-
-            for entity in scene.entities() {
-                // Skip hidden entities
-                if let Some(ui) = &self.ui {
-                    if ui.hidden_entities.contains(&entity.id) {
-                        continue;
-                    }
-                }
-                if let Some(foliage) = entity.get_component::<Foliage>() {
-                    let world_matrix = scene.world_matrix(entity.id);
-                    let color_tint = Vec3::from(foliage.color_tint);
-
-                    for instance in &foliage.instances {
-                        let local_pos = Vec3::from(instance.position);
-                        // Transform local position by entity's world matrix
-                        let world_pos = world_matrix.transform_point3(local_pos);
-
-                        let gpu_instance = FoliageInstanceGpu::new(
-                            world_pos,
-                            instance.rotation_y,
-                            instance.scale,
-                            color_tint,
-                        );
-
-                        foliage_by_type
-                            .entry(foliage.vegetation_type.clone())
-                            .or_insert_with(Vec::new)
-                            .push(gpu_instance);
-                    }
+        wgpu_state.gpu_profiler.mark(&mut encoder, "Foliage");
+
+        // Grow grass from the scene's Grass density map on the GPU, then draw it
+        let grass = engine_scene::grass::find_grass(scene)
+            .filter(|(id, _)| !self.ui.as_ref().is_some_and(|ui| ui.hidden_entities.contains(id)))
+            .map(|(_, grass)| grass);
+        if let (Some(grass), Some(grass_renderer)) = (grass, wgpu_state.grass_renderer.as_mut()) {
+            let device = &wgpu_state.renderer.device;
+            let queue = &wgpu_state.renderer.queue;
+            grass_renderer.set_density(device, queue, &grass.density, grass.resolution, grass.size, grass.generation());
+            if let (Some(heightmap), Some(config)) = (&wgpu_state.terrain_heightmap, &wgpu_state.terrain_config) {
+                if std::mem::take(&mut wgpu_state.grass_terrain_dirty) {
+                    grass_renderer.set_terrain(device, queue, &heightmap.heights, &heightmap.holes, heightmap.width, heightmap.depth, config.scale);
                 }
             }
+            let settings = GrassSettings {
+                blades_per_m2: grass.blades_per_m2,
+                blade_height: grass.blade_height,
+                blade_width: grass.blade_width,
+                base_color: Vec3::from(grass.base_color),
+                tip_color: Vec3::from(grass.tip_color),
+                draw_distance: grass.draw_distance,
+                fade_width: grass.fade_width,
+                stiffness: grass.stiffness,
+            };
+            grass_renderer.update(queue, view_proj, render_camera.position, &wind, elapsed, &settings);
+            grass_renderer.generate(&mut encoder);
 
-            // Update camera uniforms for foliage
-            foliage_renderer.update_camera(&wgpu_state.renderer.queue, view_proj, camera.position);
-
-            // Render each vegetation type
-            for (veg_type, instances) in &foliage_by_type {
-                if instances.is_empty() {
-                    continue;
-                }
+            let mut grass_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Grass Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: if msaa { &wgpu_state.msaa_texture } else { &view },
+                    resolve_target: msaa.then_some(&view),
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &wgpu_state.depth_texture,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            wgpu_state.renderer.apply_scene_viewport(&mut grass_pass);
+            grass_renderer.render(&mut grass_pass);
+        }
 
-                // Get the mesh for this vegetation type
-                if let Some(mesh_handle) = wgpu_state.mesh_manager.get_handle(veg_type) {
-                    if let Some(gpu_mesh) = wgpu_state.mesh_manager.get_mesh(mesh_handle) {
-                        // Update instance buffer
-                        foliage_renderer.update_instances(
-                            &wgpu_state.renderer.device,
-                            &wgpu_state.renderer.queue,
-                            instances,
-                        );
+        wgpu_state.gpu_profiler.mark(&mut encoder, "Grass");
+
+        // Render editor grid (transparent, depth tested against the scene, hidden through the game camera)
+        let show_grid = self.ui.as_ref().map(|ui| {
+            ui.show_grid && !(self.play_state.in_session() && ui.use_game_camera) && game_camera.is_none()
+        }).unwrap_or(false) && self.capture_job.is_none();
+        if let (true, Some(grid_renderer)) = (show_grid, &wgpu_state.grid_renderer) {
+            let cell_size = self.ui.as_ref().map(|ui| ui.inspector_state.position_grid).unwrap_or(1.0);
+            // Fade further out the higher the camera is, so the grid stays visible from above
+            let fade_distance = (render_camera.position.y.abs() * 8.0).max(40.0).min(render_camera.far);
+            grid_renderer.update_uniforms(
+                &wgpu_state.renderer.queue,
+                view_proj,
+                render_camera.position,
+                cell_size,
+                fade_distance,
+            );
 
-                        // Create foliage render pass
-                        let mut foliage_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                            label: Some("Foliage Render Pass"),
-                            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                                view: &wgpu_state.msaa_texture,
-                                resolve_target: Some(&view),
-                                ops: wgpu::Operations {
-                                    load: wgpu::LoadOp::Load,
-                                    store: wgpu::StoreOp::Store,
-                                },
-                                depth_slice: None,
-                            })],
-                            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                                view: &wgpu_state.depth_texture,
-                                depth_ops: Some(wgpu::Operations {
-                                    load: wgpu::LoadOp::Load,
-                                    store: wgpu::StoreOp::Store,
-                                }),
-                                stencil_ops: None,
-                            }),
-                            timestamp_writes: None,
-                            occlusion_query_set: None,
-                        });
+            let mut grid_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Grid Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: if msaa { &wgpu_state.msaa_texture } else { &view },
+                    resolve_target: msaa.then_some(&view),
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &wgpu_state.depth_texture,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
 
-                        foliage_renderer.render(&mut foliage_pass, gpu_mesh, instances.len() as u32);
-                    }
-                }
-            }
+            grid_renderer.render(&mut grid_pass);
         }
 
+        wgpu_state.gpu_profiler.mark(&mut encoder, "Grid");
+
         // Render water (transparent, after opaque objects, skip hidden entities)
         if let Some(ref water_renderer) = wgpu_state.water_renderer {
             for entity in scene.entities() {
@@ -1995,10 +4763,11 @@ impl EditorApp {
                     water_renderer.update_uniforms(
                         &wgpu_state.renderer.queue,
                         view_proj,
-                        camera.position,
-                        self.time,
+                        render_camera.position,
+                        elapsed,
                         water.flow_direction,
                         water.flow_speed,
+                        water_reflection.map(|(matrix, _)| matrix),
                     );
                     if let Some(mesh_handle) = wgpu_state.mesh_manager.get_handle(&water.mesh_path) {
                         if let Some(gpu_mesh) = wgpu_state.mesh_manager.get_mesh(mesh_handle) {
@@ -2017,8 +4786,8 @@ impl EditorApp {
                                 let mut water_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                                     label: Some("Water Render Pass"),
                                     color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                                        view: &wgpu_state.msaa_texture,
-                                        resolve_target: Some(&view),
+                                        view: if msaa { &wgpu_state.msaa_texture } else { &view },
+                                        resolve_target: msaa.then_some(&view),
                                         ops: wgpu::Operations {
                                             load: wgpu::LoadOp::Load, // Preserve previous content
                                             store: wgpu::StoreOp::Store,
@@ -2037,6 +4806,7 @@ impl EditorApp {
                                     occlusion_query_set: None,
                                 });
 
+                                wgpu_state.renderer.apply_scene_viewport(&mut water_pass);
                                 water_renderer.render(
                                     &mut water_pass,
                                     gpu_mesh,
@@ -2062,10 +4832,11 @@ impl EditorApp {
                         water_renderer.update_uniforms(
                             &wgpu_state.renderer.queue,
                             view_proj,
-                            camera.position,
-                            self.time,
+                            render_camera.position,
+                            elapsed,
                             flow_dir,
                             water_body.flow_speed,
+                            water_reflection.map(|(matrix, _)| matrix),
                         );
 
                         // Get water texture
@@ -2080,8 +4851,8 @@ impl EditorApp {
                             let mut water_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                                 label: Some("Terrain Water Render Pass"),
                                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                                    view: &wgpu_state.msaa_texture,
-                                    resolve_target: Some(&view),
+                                    view: if msaa { &wgpu_state.msaa_texture } else { &view },
+                                    resolve_target: msaa.then_some(&view),
                                     ops: wgpu::Operations {
                                         load: wgpu::LoadOp::Load,
                                         store: wgpu::StoreOp::Store,
@@ -2100,6 +4871,7 @@ impl EditorApp {
                                 occlusion_query_set: None,
                             });
 
+                            wgpu_state.renderer.apply_scene_viewport(&mut water_pass);
                             // Terrain water is already in world space (identity transform)
                             water_renderer.render(
                                 &mut water_pass,
@@ -2114,18 +4886,20 @@ impl EditorApp {
             }
         }
 
+        wgpu_state.gpu_profiler.mark(&mut encoder, "Water");
+
         // Render particles after opaque geometry
         if let Some(ref particle_renderer) = wgpu_state.particle_renderer {
             // Calculate camera basis vectors for billboard rendering
-            let camera_forward = (camera.target - camera.position).normalize();
-            let camera_right = camera_forward.cross(camera.up).normalize();
+            let camera_forward = (render_camera.target - render_camera.position).normalize();
+            let camera_right = camera_forward.cross(render_camera.up).normalize();
             let camera_up = camera_right.cross(camera_forward).normalize();
 
             // Update particle renderer camera uniforms
             particle_renderer.update_camera(
                 &wgpu_state.renderer.queue,
                 view_proj,
-                camera.position,
+                render_camera.position,
                 camera_right,
                 camera_up,
             );
@@ -2152,8 +4926,8 @@ impl EditorApp {
                         let mut particle_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                             label: Some("Particle Render Pass"),
                             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                                view: &wgpu_state.msaa_texture,
-                                resolve_target: Some(&view),
+                                view: if msaa { &wgpu_state.msaa_texture } else { &view },
+                                resolve_target: msaa.then_some(&view),
                                 ops: wgpu::Operations {
                                     load: wgpu::LoadOp::Load, // Don't clear - preserve geometry
                                     store: wgpu::StoreOp::Store,
@@ -2172,6 +4946,7 @@ impl EditorApp {
                             occlusion_query_set: None,
                         });
 
+                        wgpu_state.renderer.apply_scene_viewport(&mut particle_pass);
                         particle_renderer.render(
                             &mut particle_pass,
                             compute_pipeline.particle_buffer(),
@@ -2182,8 +4957,131 @@ impl EditorApp {
             }
         }
 
+        wgpu_state.gpu_profiler.mark(&mut encoder, "Particles");
+
+        // Everything after the scene passes goes through the render graph:
+        // TAA, bloom and tone mapping, FXAA, then plugin passes over the result
+        let taa_enabled = anti_aliasing == AntiAliasingMode::Taa;
+        if taa_enabled {
+            wgpu_state.taa.prepare(&wgpu_state.renderer.queue, &wgpu_state.framebuffer, camera_view_proj, scene_viewport);
+        }
+        let post_process = self
+            .ui
+            .as_ref()
+            .map(|ui| ui.lighting.post_process.clone())
+            .unwrap_or_default();
+        let fxaa = anti_aliasing == AntiAliasingMode::Fxaa;
+        let config = &wgpu_state.renderer.surface_config;
+        let (surface_format, surface_size) = (config.format, (config.width, config.height));
+
+        let mut graph = RenderGraph::new();
+        graph.import_framebuffer("scene", &wgpu_state.framebuffer);
+        graph.import_view("depth", &wgpu_state.depth_texture);
+        graph.import_view("surface", &surface_view);
+
+        // TAA resolves the HDR scene against its history before bloom
+        let taa = &wgpu_state.taa;
+        let scene_color = if taa_enabled {
+            let motion = ObjectMotion {
+                meshes: moved_meshes
+                    .iter()
+                    .filter_map(|(handle, instance)| Some((wgpu_state.mesh_manager.get_mesh(*handle)?, *instance)))
+                    .collect(),
+                skinned: wgpu_state.skinned_renderer.as_ref().map(|renderer| (renderer, skinned_draws.as_slice())),
+            };
+            graph.import_framebuffer("taa", taa.output());
+            graph.add_pass(
+                GraphPass::new("TAA", move |ctx| {
+                    let (scene_target, depth) = (ctx.framebuffer("scene"), ctx.view("depth"));
+                    taa.record(ctx.device, ctx.encoder, scene_target, depth, &motion);
+                })
+                .with_read("scene")
+                .with_read("depth")
+                .with_write("taa"),
+            );
+            "taa"
+        } else {
+            "scene"
+        };
+
+        // Bloom and tone map the HDR scene into the swapchain (through FXAA if enabled)
+        let post_process_pipeline = &wgpu_state.post_process_pipeline;
+        let bloom_chain = &wgpu_state.bloom_chain;
+        let tone_mapped = if fxaa { "tone_mapped" } else { "surface" };
+        if fxaa {
+            graph.create_transient("tone_mapped", TransientDesc::new(surface_size.0, surface_size.1, surface_format));
+        }
+        graph.add_pass(
+            GraphPass::new("Post Process", move |ctx| {
+                let (source, target) = (ctx.framebuffer(scene_color), ctx.view(tone_mapped));
+                post_process_pipeline.render(ctx.device, ctx.encoder, source, bloom_chain, target, &post_process);
+            })
+            .with_read(scene_color)
+            .with_write(tone_mapped),
+        );
+        if fxaa {
+            graph.add_pass(
+                GraphPass::new("FXAA", move |ctx| {
+                    let (source, target) = (ctx.framebuffer("tone_mapped"), ctx.view("surface"));
+                    post_process_pipeline.render_fxaa(ctx.device, ctx.encoder, source, target);
+                })
+                .with_read("tone_mapped")
+                .with_write("surface"),
+            );
+        }
+
+        // Plugin render passes draw over the finished scene
+        if !self.plugins.render_passes.is_empty() {
+            let plugin_passes = &mut self.plugins.render_passes;
+            let camera_position = render_camera.position;
+            let plugin_scene: &Scene = scene;
+            graph.add_pass(
+                GraphPass::new("Plugins", move |ctx| {
+                    let target = ctx.view("surface");
+                    let mut context = engine_plugin::RenderContext {
+                        device: ctx.device,
+                        queue: ctx.queue,
+                        encoder: ctx.encoder,
+                        target,
+                        format: surface_format,
+                        size: surface_size,
+                        view_proj: camera_view_proj,
+                        camera_position,
+                        scene: plugin_scene,
+                    };
+                    for pass in plugin_passes.iter_mut() {
+                        pass.render(&mut context);
+                    }
+                })
+                .with_read("surface")
+                .with_write("surface"),
+            );
+        }
+
+        let gpu_profiler = &mut wgpu_state.gpu_profiler;
+        if let Err(e) = graph.execute(
+            &wgpu_state.renderer.device,
+            &wgpu_state.renderer.queue,
+            &mut encoder,
+            &mut wgpu_state.render_targets,
+            |name, encoder| gpu_profiler.mark(encoder, name),
+        ) {
+            log::error!("Render graph failed: {}", e);
+        }
+        drop(render_span);
+        frame_timer.record("Scene Render", render_start);
+
         // Render egui UI and capture editor changes (skip in player mode)
-        let (paint_jobs, textures_delta, screen_descriptor, editor_result) = if self.camera_mode != CameraMode::Player {
+        let ui_start = std::time::Instant::now();
+        let ui_span = tracing::info_span!("editor_ui").entered();
+        // The selected entity before the inspector edits it, for undo
+        let inspected_entity = self
+            .ui
+            .as_ref()
+            .filter(|_| self.camera_mode != CameraMode::Player && self.game.is_none())
+            .and_then(|ui| ui.selected_entity)
+            .and_then(|id| scene.get_entity(id).cloned());
+        let (paint_jobs, textures_delta, screen_descriptor, editor_result) = if self.camera_mode != CameraMode::Player && self.game.is_none() {
             let ui = self.ui.as_mut().unwrap();
             let egui_state = self.egui_state.as_mut().unwrap();
             let can_undo = self.undo_history.can_undo();
@@ -2193,8 +5091,14 @@ impl EditorApp {
 
             let raw_input = egui_state.winit_state.take_egui_input(window);
             let mut editor_result = EditorResult::default();
+            let plugin_panels = &mut self.plugins.editor_panels;
             let full_output = egui_state.context.run(raw_input, |ctx| {
                 editor_result = ui.render(ctx, scene, can_undo, can_redo, undo_count, redo_count);
+                for panel in plugin_panels.iter_mut() {
+                    egui::Window::new(panel.title().to_string())
+                        .default_open(false)
+                        .show(ctx, |ui| panel.ui(ui, scene));
+                }
             });
 
             egui_state.winit_state.handle_platform_output(
@@ -2235,12 +5139,258 @@ impl EditorApp {
             )
         };
 
+        // Handle foliage scattering from the brush panel
+        if editor_result.brush.scatter {
+            if let (Some(heightmap), Some(config), Some(ui)) = (&wgpu_state.terrain_heightmap, &wgpu_state.terrain_config, self.ui.as_mut()) {
+                // Water body bounds are in grid cells; the scatter works in world space
+                let cell_size = config.scale / config.width as f32;
+                let to_world = |cell: f32| -config.scale * 0.5 + cell * cell_size;
+                let water: Vec<scatter::WaterZone> = wgpu_state
+                    .terrain_water_bodies
+                    .iter()
+                    .map(|body| scatter::WaterZone {
+                        min: [to_world(body.bounds_min.x), to_world(body.bounds_min.z)],
+                        max: [to_world(body.bounds_max.x), to_world(body.bounds_max.z)],
+                        surface_level: body.surface_level,
+                    })
+                    .collect();
+                let terrain = scatter::ScatterTerrain {
+                    heightmap,
+                    config,
+                    splatmap: wgpu_state.terrain_splatmap.as_ref(),
+                    water: &water,
+                };
+                // Scatter edits Foliage entities and creates new ones
+                let foliage: Vec<EntityId> = scene.entities().filter(|e| e.has_component::<Foliage>()).map(|e| e.id).collect();
+                self.undo_history.record_entities(scene, &foliage);
+                let added = scatter::apply_scatter(scene, &ui.scatter_settings, &terrain);
+                ui.log_info(format!("Scattered {} foliage instances", added));
+                ui.mark_scene_modified();
+            } else if let Some(ui) = self.ui.as_mut() {
+                ui.log_warning("Scatter needs a terrain (add a TerrainGenerator component)".to_string());
+            }
+        }
+
+        // Handle whole-terrain erosion from the brush panel
+        if editor_result.brush.erode_terrain {
+            if let (Some(heightmap), Some(config), Some(ui)) = (wgpu_state.terrain_heightmap.as_mut(), &wgpu_state.terrain_config, self.ui.as_mut()) {
+                // One undo step covering the whole map
+                self.undo_history.begin_terrain_stroke(scene);
+                let bounds = (0, 0, heightmap.width - 1, heightmap.depth - 1);
+                self.undo_history.track_terrain_region(heightmap, wgpu_state.terrain_splatmap.as_ref(), bounds);
+
+                let settings = ErosionSettings {
+                    seed: config.seed.wrapping_add(1),
+                    droplets: ui.brush_tool.erosion_droplets,
+                    ..Default::default()
+                };
+                let start = std::time::Instant::now();
+                heightmap.erode(&settings, config.scale);
+                self.undo_history.end_terrain_stroke(heightmap, wgpu_state.terrain_splatmap.as_ref());
+                ui.log_info(format!("Eroded terrain with {} droplets ({:.0} ms)", settings.droplets, start.elapsed().as_secs_f32() * 1000.0));
+                ui.mark_scene_modified();
+                rebuild_terrain_chunks(wgpu_state);
+                wgpu_state.water_needs_regeneration = true;
+            } else if let Some(ui) = self.ui.as_mut() {
+                ui.log_warning("Erosion needs a terrain (add a TerrainGenerator component)".to_string());
+            }
+        }
+
+        // Handle spline carving from the brush panel
+        if editor_result.brush.apply_splines {
+            if let (Some(heightmap), Some(config), Some(ui)) = (wgpu_state.terrain_heightmap.as_mut(), &wgpu_state.terrain_config, self.ui.as_mut()) {
+                // One undo step covering the whole map
+                self.undo_history.begin_terrain_stroke(scene);
+                let bounds = (0, 0, heightmap.width - 1, heightmap.depth - 1);
+                self.undo_history.track_terrain_region(heightmap, wgpu_state.terrain_splatmap.as_ref(), bounds);
+                let carved = spline_edit::carve_splines(scene, heightmap, config);
+                self.undo_history.end_terrain_stroke(heightmap, wgpu_state.terrain_splatmap.as_ref());
+                if carved {
+                    ui.log_info("Carved rivers and roads into the terrain".to_string());
+                    ui.mark_scene_modified();
+                    rebuild_terrain_chunks(wgpu_state);
+                } else {
+                    ui.log_info("Splines left the terrain unchanged".to_string());
+                }
+                wgpu_state.water_needs_regeneration = true;
+            } else if let Some(ui) = self.ui.as_mut() {
+                ui.log_warning("Splines need a terrain (add a TerrainGenerator component)".to_string());
+            }
+        }
+
+        // Handle heightmap import/export from the File menu (paths relative to the asset root)
+        if let (Some(request), Some(ui)) = (&editor_result.heightmap_request, self.ui.as_mut()) {
+            match (request, wgpu_state.terrain_heightmap.as_mut(), wgpu_state.terrain_config.as_mut()) {
+                (HeightmapRequest::Import { path, min_height, max_height }, Some(heightmap), Some(config)) => {
+                    let imported = HeightMap::import(&asset_manager.asset_root().join(path), *min_height, *max_height)
+                        .and_then(|imported| {
+                            // Saved into the project so the scene loads it again
+                            let saved = terrain_tools::save_imported_heightmap(&imported, path, asset_manager.asset_root())?;
+                            Ok((imported, saved))
+                        });
+                    match imported {
+                        Ok((imported, saved)) => {
+                            let generator_id = scene
+                                .entities()
+                                .find(|entity| entity.has_component::<TerrainGenerator>())
+                                .map(|entity| entity.id);
+                            let generator = generator_id
+                                .and_then(|id| scene.get_entity_mut(id))
+                                .and_then(|entity| entity.get_component_mut::<TerrainGenerator>());
+                            if imported.width == heightmap.width && imported.depth == heightmap.depth {
+                                if let Some(generator) = generator {
+                                    generator.heightmap = Some(saved.clone());
+                                }
+                                // Same size: one undo step, painted layers kept
+                                self.undo_history.begin_terrain_stroke(scene);
+                                let bounds = (0, 0, heightmap.width - 1, heightmap.depth - 1);
+                                self.undo_history.track_terrain_region(heightmap, wgpu_state.terrain_splatmap.as_ref(), bounds);
+                                *heightmap = imported;
+                                self.undo_history.end_terrain_stroke(heightmap, wgpu_state.terrain_splatmap.as_ref());
+                            } else {
+                                config.width = imported.width;
+                                config.depth = imported.depth;
+                                // The generator regenerates the terrain at its own size
+                                if let Some(generator) = generator {
+                                    generator.width = imported.width;
+                                    generator.depth = imported.depth;
+                                    generator.heightmap = Some(saved.clone());
+                                }
+                                wgpu_state.terrain_splatmap = Some(SplatMap::new(imported.width, imported.depth, wgpu_state.terrain_layers.len()));
+                                wgpu_state.terrain_splatmap_dirty = true;
+                                *heightmap = imported;
+                                self.undo_history.forget_terrain();
+                            }
+                            ui.log_info(format!("Imported {}x{} heightmap from {} as {}", heightmap.width, heightmap.depth, path.display(), saved));
+                            ui.mark_scene_modified();
+                            rebuild_terrain_chunks(wgpu_state);
+                            wgpu_state.water_needs_regeneration = true;
+                        }
+                        Err(e) => ui.log_error(format!("Heightmap import failed: {:#}", e)),
+                    }
+                }
+                (HeightmapRequest::Export { path, range }, Some(heightmap), _) => {
+                    let (min_height, max_height) = range.unwrap_or_else(|| heightmap.height_range());
+                    match heightmap.export(&asset_manager.asset_root().join(path), min_height, max_height) {
+                        Ok(()) => ui.log_info(format!("Exported heightmap to {} (heights {:.2} to {:.2})", path.display(), min_height, max_height)),
+                        Err(e) => ui.log_error(format!("Heightmap export failed: {:#}", e)),
+                    }
+                }
+                _ => ui.log_warning("Heightmap import/export needs a terrain (add a TerrainGenerator component)".to_string()),
+            }
+        }
+
+        // Handle navmesh baking from the Navigation panel
+        if editor_result.navigation.bake {
+            if let Some(ui) = self.ui.as_mut() {
+                let obstacles = navigation::collect_obstacles(scene);
+                let input = NavMeshInput {
+                    terrain: placement::terrain_ref(&wgpu_state.terrain_heightmap, &wgpu_state.terrain_config),
+                    obstacles: &obstacles,
+                };
+                let start = std::time::Instant::now();
+                let navmesh = NavMesh::bake(&input, &ui.navigation.agent);
+                if navmesh.polygons.is_empty() {
+                    ui.log_warning("Navmesh bake found no walkable area".to_string());
+                } else {
+                    ui.log_info(format!(
+                        "Baked navmesh: {} polygons, {:.0} m² walkable ({:.0} ms)",
+                        navmesh.polygons.len(),
+                        navmesh.walkable_area(),
+                        start.elapsed().as_secs_f32() * 1000.0
+                    ));
+                }
+                ui.navigation.navmesh = Some(navmesh);
+                ui.mark_scene_modified();
+            }
+        }
+        if editor_result.navigation.clear {
+            if let Some(ui) = self.ui.as_mut() {
+                ui.navigation.navmesh = None;
+                ui.mark_scene_modified();
+            }
+        }
+
+        // Handle ambient occlusion baking from the Lighting panel
+        if editor_result.lighting.bake {
+            if let Some(ui) = self.ui.as_mut() {
+                let start = std::time::Instant::now();
+                let output_dir = lighting::lightmap_dir(ui.current_scene_path.as_deref());
+                match lighting::bake_scene_ao(
+                    scene,
+                    asset_manager,
+                    placement::terrain_ref(&wgpu_state.terrain_heightmap, &wgpu_state.terrain_config),
+                    &ui.lighting.ao_settings,
+                    &output_dir,
+                ) {
+                    Ok(baked) if baked.is_empty() => {
+                        ui.log_warning("No static meshes to bake".to_string());
+                    }
+                    Ok(baked) => {
+                        let ids: Vec<EntityId> = baked.iter().map(|b| b.entity).collect();
+                        self.undo_history.record_entities(scene, &ids);
+                        let count = baked.len();
+                        for baked_mesh in baked {
+                            upload_baked_mesh(&mut wgpu_state.mesh_manager, &wgpu_state.renderer.device, &baked_mesh.lightmap_path, baked_mesh.mesh);
+                            if let Some(mesh_renderer) = scene
+                                .get_entity_mut(baked_mesh.entity)
+                                .and_then(|e| e.get_component_mut::<MeshRenderer>())
+                            {
+                                mesh_renderer.lightmap_path = Some(baked_mesh.lightmap_path);
+                            }
+                        }
+                        ui.log_info(format!(
+                            "Baked ambient occlusion for {} meshes ({:.0} ms)",
+                            count,
+                            start.elapsed().as_secs_f32() * 1000.0
+                        ));
+                        ui.mark_scene_modified();
+                    }
+                    Err(e) => ui.log_error(format!("Ambient occlusion bake failed: {}", e)),
+                }
+            }
+        }
+        if editor_result.lighting.clear {
+            if let Some(ui) = self.ui.as_mut() {
+                let ids: Vec<_> = scene
+                    .entities()
+                    .filter(|e| e.get_component::<MeshRenderer>().is_some_and(|m| m.lightmap_path.is_some()))
+                    .map(|e| e.id)
+                    .collect();
+                self.undo_history.record_entities(scene, &ids);
+                for id in ids {
+                    if let Some(mesh_renderer) = scene.get_entity_mut(id).and_then(|e| e.get_component_mut::<MeshRenderer>()) {
+                        mesh_renderer.lightmap_path = None;
+                    }
+                }
+                ui.log_info("Cleared baked lighting".to_string());
+                ui.mark_scene_modified();
+            }
+        }
+
         // Handle entity creation from hierarchy panel
         if let Some((entity_name, parent_id)) = editor_result.hierarchy.create_entity {
-            self.undo_history.push_state(scene);
+            self.undo_history.record_entities(scene, parent_id.as_slice());
             let new_id = scene.create_entity(entity_name);
             if let Some(parent) = parent_id {
                 scene.set_parent(new_id, Some(parent));
+            } else if let Some(ui) = &self.ui {
+                // Root entities go where the viewport center meets the scene
+                let screen_size = (
+                    wgpu_state.renderer.surface_config.width as f32,
+                    wgpu_state.renderer.surface_config.height as f32,
+                );
+                let position = placement::cursor_placement(
+                    scene,
+                    camera,
+                    (screen_size.0 / 2.0, screen_size.1 / 2.0),
+                    screen_size,
+                    placement::terrain_ref(&wgpu_state.terrain_heightmap, &wgpu_state.terrain_config),
+                    &ui.inspector_state,
+                );
+                if let Some(entity) = scene.get_entity_mut(new_id) {
+                    entity.transform.position = position;
+                }
             }
             if let Some(ui) = self.ui.as_mut() {
                 ui.selected_entity = Some(new_id);
@@ -2250,35 +5400,22 @@ impl EditorApp {
 
         // Handle quick entity creation from hierarchy panel
         if let Some(quick_type) = editor_result.hierarchy.create_quick_entity {
-            use ui::hierarchy::QuickEntityType;
-            self.undo_history.push_state(scene);
-
-            let (name, add_components): (&str, Box<dyn FnOnce(&mut engine_scene::entity::Entity)>) = match quick_type {
-                QuickEntityType::Empty => ("Empty", Box::new(|_| {})),
-                QuickEntityType::Cube => ("Cube", Box::new(|entity| {
-                    entity.add_component(MeshRenderer::new("cube".to_string()));
-                })),
-                QuickEntityType::Sphere => ("Sphere", Box::new(|entity| {
-                    entity.add_component(MeshRenderer::new("sphere".to_string()));
-                })),
-                QuickEntityType::PointLight => ("Point Light", Box::new(|entity| {
-                    entity.add_component(Light::point([1.0, 1.0, 1.0], 1.0, 10.0));
-                })),
-                QuickEntityType::DirectionalLight => ("Directional Light", Box::new(|entity| {
-                    entity.add_component(Light::directional([0.0, -1.0, 0.0], [1.0, 1.0, 1.0], 1.0));
-                })),
-                QuickEntityType::Camera => ("Camera", Box::new(|entity| {
-                    entity.add_component(CameraComponent::default());
-                })),
-                QuickEntityType::ParticleEmitter => ("Particles", Box::new(|entity| {
-                    entity.add_component(ParticleEmitter::default());
-                })),
-            };
-
-            let new_id = scene.create_entity(name.to_string());
-            if let Some(entity) = scene.get_entity_mut(new_id) {
-                add_components(entity);
-            }
+            self.undo_history.record_entities(scene, &[]);
+            let position = self.ui.as_ref().map(|ui| {
+                let screen_size = (
+                    wgpu_state.renderer.surface_config.width as f32,
+                    wgpu_state.renderer.surface_config.height as f32,
+                );
+                placement::cursor_placement(
+                    scene,
+                    camera,
+                    (screen_size.0 / 2.0, screen_size.1 / 2.0),
+                    screen_size,
+                    placement::terrain_ref(&wgpu_state.terrain_heightmap, &wgpu_state.terrain_config),
+                    &ui.inspector_state,
+                )
+            });
+            let (new_id, name) = create_quick_entity(scene, quick_type, position.unwrap_or(Vec3::ZERO));
             if let Some(ui) = self.ui.as_mut() {
                 ui.selected_entity = Some(new_id);
                 ui.mark_scene_modified();
@@ -2286,9 +5423,160 @@ impl EditorApp {
             }
         }
 
+        // Handle commands entered in the console
+        if let Some(command) = editor_result.console_command.clone() {
+            use console_commands::{ConsoleCommand, SpawnTarget, TeleportTarget};
+            match command {
+                ConsoleCommand::Spawn { target, position } => {
+                    let position = position.unwrap_or_else(|| {
+                        let screen_size = (
+                            wgpu_state.renderer.surface_config.width as f32,
+                            wgpu_state.renderer.surface_config.height as f32,
+                        );
+                        let inspector_state = self
+                            .ui
+                            .as_ref()
+                            .map(|ui| ui.inspector_state.clone())
+                            .unwrap_or_default();
+                        placement::cursor_placement(
+                            scene,
+                            camera,
+                            (screen_size.0 / 2.0, screen_size.1 / 2.0),
+                            screen_size,
+                            placement::terrain_ref(&wgpu_state.terrain_heightmap, &wgpu_state.terrain_config),
+                            &inspector_state,
+                        )
+                    });
+                    let spawned = match &target {
+                        SpawnTarget::Preset(quick_type) => {
+                            self.undo_history.record_entities(scene, &[]);
+                            Ok(create_quick_entity(scene, *quick_type, position).0)
+                        }
+                        SpawnTarget::Model(model_path)
+                            if load_skinned_model(asset_manager, &mut self.skeletal_animation, model_path) =>
+                        {
+                            // Skinned models get their bones and an animator
+                            self.undo_history.record_entities(scene, &[]);
+                            self.skeletal_animation
+                                .spawn(scene, model_path, position)
+                                .ok_or_else(|| anyhow::anyhow!("'{}' has no skin", model_path))
+                        }
+                        SpawnTarget::Model(model_path) => upload_model_meshes(
+                            asset_manager,
+                            &mut wgpu_state.mesh_manager,
+                            &wgpu_state.renderer.device,
+                            model_path,
+                            &mut wgpu_state.pending_model_lods,
+                        )
+                        .map(|meshes| {
+                            self.undo_history.record_entities(scene, &[]);
+                            create_model_entity(scene, model_path, &meshes, position)
+                        }),
+                    };
+                    if let Some(ui) = self.ui.as_mut() {
+                        match spawned {
+                            Ok(entity_id) => {
+                                let name = scene.get_entity(entity_id).map(|e| e.name.clone()).unwrap_or_default();
+                                ui.select_only(entity_id);
+                                ui.mark_scene_modified();
+                                ui.log_info(format!(
+                                    "Spawned {} at ({:.2}, {:.2}, {:.2})",
+                                    name, position.x, position.y, position.z
+                                ));
+                            }
+                            Err(e) => ui.log_error(format!("Failed to spawn: {}", e)),
+                        }
+                    }
+                }
+                ConsoleCommand::Teleport(target) => {
+                    let destination = match &target {
+                        TeleportTarget::Position(position) => Some(*position),
+                        TeleportTarget::Entity(name) => scene
+                            .entities()
+                            .find(|entity| entity.name.eq_ignore_ascii_case(name))
+                            .map(|entity| scene.world_matrix(entity.id).w_axis.truncate()),
+                    };
+                    match destination {
+                        Some(position) => {
+                            match self.camera_mode {
+                                CameraMode::Editor => self.viewport_controls.pan_offset = position,
+                                CameraMode::TopDown => self.topdown_center = position,
+                                CameraMode::Player => {
+                                    self.player_position = position;
+                                    self.player_velocity = Vec3::ZERO;
+                                }
+                            }
+                            if let Some(ui) = self.ui.as_mut() {
+                                ui.log_info(format!(
+                                    "Teleported to ({:.2}, {:.2}, {:.2})",
+                                    position.x, position.y, position.z
+                                ));
+                            }
+                        }
+                        None => {
+                            if let (Some(ui), TeleportTarget::Entity(name)) = (self.ui.as_mut(), &target) {
+                                ui.log_error(format!("No entity named '{}'", name));
+                            }
+                        }
+                    }
+                }
+                ConsoleCommand::FpsCap(cap) => {
+                    self.frame_pacer.set_fps_cap(cap);
+                    if let Some(ui) = self.ui.as_mut() {
+                        ui.frame_pacing.fps_cap = cap;
+                        match cap {
+                            Some(cap) => ui.log_info(format!("Frame rate capped at {} FPS", cap)),
+                            None => ui.log_info("Frame rate cap removed".to_string()),
+                        }
+                    }
+                }
+                ConsoleCommand::Shadows(enabled) => {
+                    self.shadows_enabled = enabled.unwrap_or(!self.shadows_enabled);
+                    if let Some(ui) = self.ui.as_mut() {
+                        let state = if self.shadows_enabled { "on" } else { "off" };
+                        ui.log_info(format!("Shadows {}", state));
+                    }
+                }
+                ConsoleCommand::Rhai(source) => {
+                    let result = script_system.runtime_mut().eval(&source);
+                    if let Some(ui) = self.ui.as_mut() {
+                        match result {
+                            Ok(value) if value.is_unit() => {}
+                            Ok(value) => ui.log_info(value.to_string()),
+                            Err(e) => ui.log_error(e.to_string()),
+                        }
+                    }
+                }
+                ConsoleCommand::Log { module, level } => {
+                    let message = engine_core::logging::logger().map(|logger| {
+                        if let Some(level) = level {
+                            logger.set_level(module.as_deref(), level);
+                        }
+                        let filter = logger.filter();
+                        match &module {
+                            Some(module) => format!(
+                                "Log level for {}: {}",
+                                module,
+                                filter.level_for(module).as_str().to_lowercase()
+                            ),
+                            None => format!("Log filter: {}", filter),
+                        }
+                    });
+                    if let Some(ui) = self.ui.as_mut() {
+                        match message {
+                            Some(message) => ui.log_info(message),
+                            None => ui.log_error("Logging is not set up".to_string()),
+                        }
+                    }
+                }
+                // Handled by the console panel itself
+                ConsoleCommand::Help(_) | ConsoleCommand::Clear => {}
+            }
+        }
+
         // Handle entity deletion from hierarchy panel
         if let Some(entity_id) = editor_result.hierarchy.delete_entity {
-            self.undo_history.push_state(scene);
+            self.undo_history.record_entities(scene, &[entity_id]);
             scene.remove_entity(entity_id);
             if let Some(ui) = self.ui.as_mut() {
                 if ui.selected_entity == Some(entity_id) {
@@ -2300,7 +5588,7 @@ impl EditorApp {
 
         // Handle entity duplication from hierarchy panel
         if let Some(entity_id) = editor_result.hierarchy.duplicate_entity {
-            self.undo_history.push_state(scene);
+            self.undo_history.record_entities(scene, &[entity_id]);
             if let Some(new_id) = scene.duplicate_entity(entity_id) {
                 if let Some(ui) = self.ui.as_mut() {
                     ui.selected_entity = Some(new_id);
@@ -2309,9 +5597,46 @@ impl EditorApp {
             }
         }
 
+        // Handle group operations on the multi-selection
+        if let Some(ui) = self.ui.as_mut() {
+            if editor_result.hierarchy.delete_selected {
+                selection::delete_selection(scene, ui, &mut self.undo_history);
+            }
+            if editor_result.hierarchy.duplicate_selected {
+                selection::duplicate_selection(scene, ui, &mut self.undo_history);
+            }
+            if editor_result.hierarchy.group_selected {
+                selection::group_selection(scene, ui, &mut self.undo_history);
+            }
+            if let Some(parent_id) = editor_result.hierarchy.parent_selection_to {
+                selection::parent_selection_to(scene, ui, &mut self.undo_history, parent_id);
+            }
+            let terrain = placement::terrain_ref(&wgpu_state.terrain_heightmap, &wgpu_state.terrain_config);
+            if editor_result.hierarchy.snap_selected_to_ground {
+                grounding::snap_selection_to_ground(
+                    scene,
+                    ui,
+                    &mut self.undo_history,
+                    asset_manager,
+                    &mut self.mesh_picker,
+                    terrain,
+                );
+            }
+            if editor_result.hierarchy.drop_selected {
+                grounding::drop_selection(scene, ui, &mut self.undo_history, asset_manager, terrain);
+            }
+            if let Some(axis) = editor_result.hierarchy.align_selection {
+                selection::align_selection(scene, ui, &mut self.undo_history, axis);
+            }
+            if let Some(axis) = editor_result.hierarchy.distribute_selection {
+                selection::distribute_selection(scene, ui, &mut self.undo_history, axis);
+            }
+        }
+
         // Handle entity reparenting from hierarchy panel
         if let Some((child_id, new_parent_id)) = editor_result.hierarchy.reparent {
-            self.undo_history.push_state(scene);
+            let affected: Vec<EntityId> = std::iter::once(child_id).chain(new_parent_id).collect();
+            self.undo_history.record_entities(scene, &affected);
             scene.set_parent(child_id, new_parent_id);
             if let Some(ui) = self.ui.as_mut() {
                 ui.mark_scene_modified();
@@ -2320,7 +5645,7 @@ impl EditorApp {
 
         // Handle entity renaming from hierarchy panel
         if let Some((entity_id, new_name)) = editor_result.hierarchy.rename_entity {
-            self.undo_history.push_state(scene);
+            self.undo_history.record_entities(scene, &[entity_id]);
             if let Some(entity) = scene.get_entity_mut(entity_id) {
                 entity.name = new_name;
             }
@@ -2363,7 +5688,6 @@ impl EditorApp {
 
         // Handle terrain/water regeneration from inspector changes
         if editor_result.inspector.terrain_changed {
-            self.undo_history.push_state(scene);
             wgpu_state.terrain_needs_regeneration = true;
             wgpu_state.water_needs_regeneration = true; // Water depends on terrain
             if let Some(ui) = self.ui.as_mut() {
@@ -2371,63 +5695,205 @@ impl EditorApp {
             }
         }
         if editor_result.inspector.water_changed {
-            self.undo_history.push_state(scene);
             wgpu_state.water_needs_regeneration = true;
             if let Some(ui) = self.ui.as_mut() {
                 ui.mark_scene_modified();
             }
         }
 
-        // Handle component changes from inspector
-        if editor_result.inspector.components_changed {
-            self.undo_history.push_state(scene);
+        // Handle multi-selection transform edits from inspector
+        if let Some(edit) = editor_result.inspector.group_transform {
+            if let Some(ui) = self.ui.as_ref().filter(|_| editor_result.inspector.group_edit_started) {
+                let ids: Vec<EntityId> = ui.selected_entities.iter().copied().collect();
+                self.undo_history.record_transforms(scene, &ids);
+            } else {
+                // The rest of a drag stays in the same undo step
+                self.undo_history.continue_edit();
+            }
             if let Some(ui) = self.ui.as_mut() {
+                // Moves act on selection roots so children follow their parent once
+                let targets: Vec<EntityId> = match edit {
+                    ui::inspector::GroupTransformEdit::Translate(_) => ui.selection_roots(scene),
+                    _ => ui.selected_entities.iter().copied().collect(),
+                };
+                let targets: Vec<EntityId> = targets
+                    .into_iter()
+                    .filter(|id| !ui.is_entity_locked(*id))
+                    .collect();
+                ui::inspector::apply_group_transform(scene, &targets, edit);
                 ui.mark_scene_modified();
             }
         }
 
-        // Handle undo/redo requests from Edit menu
-        if editor_result.undo_requested {
-            if self.undo_history.undo(scene) {
-                if let Some(ui) = self.ui.as_mut() {
-                    ui.selected_entity = None; // Selection may be invalid after undo
-                    ui.mark_scene_modified();
-                }
-                log::info!("Undo (remaining: {})", self.undo_history.undo_count());
+        // Inspector and timeline edits happen in place while the UI renders;
+        // record them against the selected entity as it was before
+        let ui_dragging = self.egui_state.as_ref().is_some_and(|egui_state| egui_state.context.dragged_id().is_some());
+        let entity_edited = editor_result.inspector.transform_changed
+            || editor_result.inspector.terrain_changed
+            || editor_result.inspector.water_changed
+            || editor_result.inspector.components_changed
+            || editor_result.timeline.clip_changed
+            || editor_result.timeline.pose_changed;
+        if entity_edited {
+            if self.inspector_edit_dragging {
+                self.undo_history.continue_edit();
+            } else if let Some(before) = inspected_entity {
+                self.undo_history.record_entity_edit(scene, before);
+            }
+            if let Some(ui) = self.ui.as_mut() {
+                ui.mark_scene_modified();
             }
         }
-        if editor_result.redo_requested {
-            if self.undo_history.redo(scene) {
+        // Value drags and timeline previews merge into one step until the
+        // pointer is released or the preview stops
+        let merging = ui_dragging || editor_result.timeline.previewing;
+        self.inspector_edit_dragging = merging && (entity_edited || self.inspector_edit_dragging);
+
+        // Handle undo/redo requests from Edit menu
+        for (requested, redo) in [(editor_result.undo_requested, false), (editor_result.redo_requested, true)] {
+            if !requested {
+                continue;
+            }
+            if let Some(target) = step_undo_history(&mut self.undo_history, scene, wgpu_state, redo) {
                 if let Some(ui) = self.ui.as_mut() {
-                    ui.selected_entity = None; // Selection may be invalid after redo
+                    if target == UndoTarget::Scene {
+                        ui.selected_entity = None; // Selection may be invalid after undo/redo
+                    }
                     ui.mark_scene_modified();
                 }
-                log::info!("Redo (remaining: {})", self.undo_history.redo_count());
+                if redo {
+                    log::info!("Redo (remaining: {})", self.undo_history.redo_count());
+                } else {
+                    log::info!("Undo (remaining: {})", self.undo_history.undo_count());
+                }
             }
         }
 
+        // Play/Pause/Stop from the toolbar is applied next frame
+        if editor_result.play_request.is_some() {
+            self.pending_play_request = editor_result.play_request;
+        }
+
+        // Captures start next frame
+        if editor_result.capture_request.is_some() {
+            self.pending_capture = editor_result.capture_request.clone();
+        }
+
         // Clear undo history when scene is loaded or new scene created
         if editor_result.scene_changed {
             self.undo_history.clear();
+            // A newly loaded scene replaces any running play session
+            self.play_session = None;
+            self.edit_undo_history = None;
+            self.play_state = PlayState::Editing;
+            if let Some(ui) = self.ui.as_mut() {
+                ui.play_state = PlayState::Editing;
+            }
+            upload_scene_models(
+                scene,
+                asset_manager,
+                &mut wgpu_state.mesh_manager,
+                &wgpu_state.renderer.device,
+                &mut wgpu_state.pending_model_lods,
+            );
+            // Rebuild the new scene's terrain with its saved splatmap
+            wgpu_state.terrain_needs_regeneration = true;
             log::info!("Undo history cleared (scene changed)");
         }
 
+        // The painted splatmap is saved next to the scene
+        if let Some(path) = &editor_result.scene_saved {
+            save_scene_splatmap(wgpu_state.terrain_splatmap.as_ref(), path);
+        }
+
+        // Handle model dragged from the asset browser into the viewport
+        if let Some((model_path, drop_pos)) = editor_result.spawn_model {
+            // Place on the surface (or ground plane) under the cursor, else at the camera target
+            let screen_size = (
+                wgpu_state.renderer.surface_config.width as f32,
+                wgpu_state.renderer.surface_config.height as f32,
+            );
+            let inspector_state = self
+                .ui
+                .as_ref()
+                .map(|ui| ui.inspector_state.clone())
+                .unwrap_or_default();
+            let spawn_position = placement::cursor_placement(
+                scene,
+                camera,
+                drop_pos.unwrap_or((screen_size.0 / 2.0, screen_size.1 / 2.0)),
+                screen_size,
+                placement::terrain_ref(&wgpu_state.terrain_heightmap, &wgpu_state.terrain_config),
+                &inspector_state,
+            );
+
+            // Skinned models get their bones and an animator
+            let placed = if load_skinned_model(asset_manager, &mut self.skeletal_animation, &model_path) {
+                self.undo_history.record_entities(scene, &[]);
+                self.skeletal_animation
+                    .spawn(scene, &model_path, spawn_position)
+                    .ok_or_else(|| anyhow::anyhow!("'{}' has no skin", model_path))
+            } else {
+                upload_model_meshes(
+                    asset_manager,
+                    &mut wgpu_state.mesh_manager,
+                    &wgpu_state.renderer.device,
+                    &model_path,
+                    &mut wgpu_state.pending_model_lods,
+                )
+                .map(|meshes| {
+                    self.undo_history.record_entities(scene, &[]);
+                    create_model_entity(scene, &model_path, &meshes, spawn_position)
+                })
+            };
+            match placed {
+                Ok(entity_id) => {
+                    if let Some(ui) = self.ui.as_mut() {
+                        ui.select_only(entity_id);
+                        ui.mark_scene_modified();
+                        ui.log_info(format!("Placed model {}", model_path));
+                    }
+                }
+                Err(e) => {
+                    if let Some(ui) = self.ui.as_mut() {
+                        ui.log_error(format!("Failed to load model {}: {}", model_path, e));
+                    }
+                }
+            }
+        }
+
+        // Finish this frame's undo step now that all edits are applied
+        self.undo_history.commit(scene);
+
         // Handle opening recent file
         if let Some(path) = editor_result.open_recent_file {
-            if std::path::Path::new(&path).exists() {
-                match Scene::load_from_file(&path) {
+            if asset_manager.file_exists(&path) {
+                match build_export::load_scene(asset_manager, &path) {
                     Ok(loaded_scene) => {
+                        upload_scene_models(
+                            &loaded_scene,
+                            asset_manager,
+                            &mut wgpu_state.mesh_manager,
+                            &wgpu_state.renderer.device,
+                            &mut wgpu_state.pending_model_lods,
+                        );
                         if let Some(scene) = &mut self.scene {
                             *scene = loaded_scene;
                         }
+                        self.play_session = None;
+                        self.edit_undo_history = None;
+                        self.play_state = PlayState::Editing;
                         if let Some(ui) = &mut self.ui {
                             ui.log_info(format!("Scene loaded from: {}", path));
                             ui.add_recent_file(path.clone());
+                            ui.load_navmesh(asset_manager, &path);
                             ui.current_scene_path = Some(path);
                             ui.scene_modified = false;
                             ui.selected_entity = None;
+                            ui.play_state = PlayState::Editing;
                         }
                         self.undo_history.clear();
+                        wgpu_state.terrain_needs_regeneration = true;
                         log::info!("Loaded recent file, undo history cleared");
                     }
                     Err(e) => {
@@ -2445,7 +5911,26 @@ impl EditorApp {
             }
         }
 
+        drop(ui_span);
+        frame_timer.record("Editor UI", ui_start);
+
+        // Captures copy the scene before the editor UI is drawn over it
+        if let Some(job) = &mut self.capture_job {
+            let surface = &wgpu_state.renderer.surface_config;
+            let [x, y, width, height] = wgpu_state.renderer.scene_viewport
+                .unwrap_or([0.0, 0.0, surface.width as f32, surface.height as f32]);
+            job.record(
+                &wgpu_state.renderer.device,
+                &mut encoder,
+                &output.texture,
+                [x as u32, y as u32],
+                [width as u32, height as u32],
+            );
+        }
+
         // Update buffers and render - egui_state borrow is ended
+        let submit_start = std::time::Instant::now();
+        let submit_span = tracing::info_span!("render_submit").entered();
         {
             let egui_state = self.egui_state.as_mut().unwrap();
             egui_state.renderer.update_buffers(
@@ -2463,7 +5948,7 @@ impl EditorApp {
             let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("egui render pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view: &surface_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Load, // Don't clear - preserve 3D scene
@@ -2484,8 +5969,15 @@ impl EditorApp {
             );
         } // render_pass consumed by forget_lifetime()
 
+        wgpu_state.gpu_profiler.mark(&mut encoder, "Editor UI");
+        wgpu_state.gpu_profiler.resolve(&mut encoder);
+
         // Submit all rendering work
         wgpu_state.renderer.queue.submit(std::iter::once(encoder.finish()));
+        wgpu_state.gpu_profiler.after_submit();
+        if let Some(job) = &mut self.capture_job {
+            job.after_submit();
+        }
 
         {
             let egui_state = self.egui_state.as_mut().unwrap();
@@ -2508,6 +6000,80 @@ impl EditorApp {
 
         // Present
         output.present();
+        drop(submit_span);
+        frame_timer.record("Render Submit", submit_start);
+
+        if let Some(ui) = &mut self.ui {
+            ui.profiler.end_frame(frame_timer, wgpu_state.gpu_profiler.timings());
+        }
+        if let Some(result) = engine_core::trace::end_frame() {
+            if let Some(ui) = &mut self.ui {
+                match result {
+                    Ok(path) => ui.log_info(format!("Trace saved to {}", path.display())),
+                    Err(e) => ui.log_error(format!("Failed to save trace: {:#}", e)),
+                }
+            }
+        }
+
+        // Save captured images as their readbacks arrive
+        if let Some(job) = &mut self.capture_job {
+            job.poll(&wgpu_state.renderer.device);
+            if job.is_finished() {
+                job.restore_view(&mut self.viewport_controls);
+                if let Some(ui) = &mut self.ui {
+                    if let Some(error) = job.error() {
+                        ui.log_error(format!("Capture failed: {}", error));
+                    } else if let [path] = job.saved() {
+                        ui.log_info(format!("Screenshot saved to {}", path.display()));
+                    } else if let Some(folder) = job.saved().first().and_then(|path| path.parent()) {
+                        ui.log_info(format!("Saved {} images to {}", job.saved().len(), folder.display()));
+                    }
+                }
+                if job.output() == capture::mcp_capture_path() {
+                    if let Some(file_ipc) = &mut self.file_ipc {
+                        match job.error() {
+                            Some(error) => file_ipc.finish_capture(Err(error.to_string())),
+                            None => file_ipc.finish_capture(Ok(job.output())),
+                        }
+                    }
+                }
+                self.capture_job = None;
+            }
+        }
+
+        // Bring streamed textures to the mips this frame asked for; materials sampling
+        // a re-created texture rebind
+        let changed_textures = wgpu_state.texture_manager.update_streaming(&wgpu_state.renderer.device, &wgpu_state.renderer.queue);
+        wgpu_state.material_manager.rebind_textures(&wgpu_state.renderer.device, &wgpu_state.texture_manager, &changed_textures);
+
+        // Over the budget, unload streamed resources not drawn lately
+        let evictions = wgpu_state.memory_budget.enforce(
+            &mut wgpu_state.mesh_manager,
+            &mut wgpu_state.texture_manager,
+            &mut wgpu_state.material_manager,
+        );
+        if !evictions.is_empty() {
+            log::debug!(
+                "Memory budget evicted {} meshes, {} textures and {} materials ({})",
+                evictions.meshes,
+                evictions.textures,
+                evictions.materials,
+                engine_render::format_bytes(evictions.freed_bytes)
+            );
+        }
+        if let Some(ui) = &mut self.ui {
+            ui.capture_status = self.capture_job.as_ref().map(|job| job.status());
+            ui.loading_assets = asset_manager.pending_loads();
+            render_stats.texture_bytes = wgpu_state.texture_manager.texture_bytes();
+            render_stats.buffer_bytes = wgpu_state.mesh_manager.buffer_bytes()
+                + wgpu_state.material_manager.buffer_bytes();
+            render_stats.meshes = wgpu_state.mesh_manager.memory();
+            render_stats.textures = wgpu_state.texture_manager.memory();
+            render_stats.materials = wgpu_state.material_manager.memory();
+            render_stats.budget_bytes = wgpu_state.memory_budget.limit_bytes;
+            render_stats.evicted = wgpu_state.memory_budget.evicted;
+            ui.render_stats = render_stats;
+        }
 
         Ok(())
     }
@@ -2516,9 +6082,22 @@ impl EditorApp {
 impl ApplicationHandler for EditorApp {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         if self.window.is_none() {
-            let window_attributes = Window::default_attributes()
+            let mut window_attributes = Window::default_attributes()
                 .with_title("Causality Engine - Editor")
                 .with_inner_size(winit::dpi::LogicalSize::new(1280, 720));
+            if let Some(game) = &self.game {
+                let [width, height] = game.resolution;
+                let icon = game.icon.as_ref().and_then(|icon| {
+                    build_export::load_icon(std::path::Path::new(icon))
+                        .map_err(|e| log::warn!("Window icon not set: {:#}", e))
+                        .ok()
+                });
+                window_attributes = window_attributes
+                    .with_title(game.name.clone())
+                    .with_inner_size(winit::dpi::LogicalSize::new(width, height))
+                    .with_fullscreen(game.fullscreen.then_some(winit::window::Fullscreen::Borderless(None)))
+                    .with_window_icon(icon);
+            }
 
             match event_loop.create_window(window_attributes) {
                 Ok(window) => {
@@ -2526,6 +6105,12 @@ impl ApplicationHandler for EditorApp {
                     if let Err(e) = self.initialize(window) {
                         log::error!("Failed to initialize: {}", e);
                         event_loop.exit();
+                    } else if self.game.is_some() {
+                        // An exported build plays through the scene's camera from the first frame
+                        if let Some(ui) = &mut self.ui {
+                            ui.use_game_camera = true;
+                        }
+                        self.pending_play_request = Some(PlayRequest::Play);
                     }
                 }
                 Err(e) => {
@@ -2542,21 +6127,21 @@ impl ApplicationHandler for EditorApp {
         _window_id: WindowId,
         event: WindowEvent,
     ) {
-        // Let egui handle the event first (but not in player mode)
-        if self.camera_mode != CameraMode::Player {
+        // Let egui handle the event first (but not in player mode or a game)
+        if self.camera_mode != CameraMode::Player && self.game.is_none() {
             if let (Some(egui_state), Some(window)) = (&mut self.egui_state, &self.window) {
                 let response = egui_state.winit_state.on_window_event(
                     window,
                     &event,
                 );
-                if response.consumed {
+                if response.consumed && !self.viewport_receives(&event) {
                     return; // Event was consumed by egui, don't process further
                 }
             }
         }
 
         // Handle viewport controls (camera) - only in editor mode
-        if self.camera_mode == CameraMode::Editor {
+        if self.camera_mode == CameraMode::Editor && self.game.is_none() {
             match &event {
                 WindowEvent::MouseInput { state, button, .. } => {
                     self.viewport_controls.handle_mouse_button(*button, *state);
@@ -2568,6 +6153,11 @@ impl ApplicationHandler for EditorApp {
             }
         }
 
+        // While playing, what gets past egui is also the game's input
+        if self.play_state.in_session() {
+            self.feed_game_input(&event);
+        }
+
         // Update current mouse position for all modes (needed for top-down edge scrolling)
         if let WindowEvent::CursorMoved { position, .. } = &event {
             self.viewport_controls.current_mouse_pos = (position.x as f32, position.y as f32);
@@ -2599,7 +6189,7 @@ impl ApplicationHandler for EditorApp {
                     };
                     self.topdown_height -= zoom_amount * 1.5;
                     self.topdown_height = self.topdown_height.clamp(5.0, 100.0);
-                } else if self.camera_mode == CameraMode::Editor {
+                } else if self.camera_mode == CameraMode::Editor && self.game.is_none() {
                     // Editor mode: use viewport controls
                     self.viewport_controls.handle_mouse_wheel(*delta);
                 }
@@ -2654,28 +6244,72 @@ impl ApplicationHandler for EditorApp {
             }
         }
 
-        // Handle keyboard shortcuts
-        if let WindowEvent::KeyboardInput {
+        // Handle keyboard shortcuts (an exported game has none)
+        if let (false, WindowEvent::KeyboardInput {
             event: KeyEvent {
                 state: ElementState::Pressed,
                 physical_key: PhysicalKey::Code(key_code),
                 ..
             },
             ..
-        } = event {
-            // Ctrl+D - Duplicate entity
+        }) = (self.game.is_some(), &event) {
+            let key_code = *key_code;
+            // Ctrl+D - Duplicate selected entities
             if self.modifiers.control_key() && key_code == KeyCode::KeyD {
-                // Push undo state before duplicating
-                if let Some(scene) = &self.scene {
-                    self.undo_history.push_state(scene);
+                if let (Some(scene), Some(ui)) = (&mut self.scene, &mut self.ui) {
+                    selection::duplicate_selection(scene, ui, &mut self.undo_history);
+                }
+            }
+            // Ctrl+A - Select all visible entities
+            if self.modifiers.control_key() && key_code == KeyCode::KeyA {
+                if let (Some(scene), Some(ui)) = (&self.scene, &mut self.ui) {
+                    let mut visible: Vec<EntityId> = scene
+                        .entities()
+                        .map(|e| e.id)
+                        .filter(|id| ui.is_entity_visible(*id))
+                        .collect();
+                    visible.sort_by_key(|id| id.0);
+                    ui.set_selection(&visible);
+                    log::info!("Selected {} entities", visible.len());
                 }
+            }
+            // Ctrl+Shift+G - Group selected entities under a new parent
+            if self.modifiers.control_key()
+                && self.modifiers.shift_key()
+                && key_code == KeyCode::KeyG
+            {
                 if let (Some(scene), Some(ui)) = (&mut self.scene, &mut self.ui) {
-                    if let Some(entity_id) = ui.selected_entity {
-                        if let Some(new_id) = scene.duplicate_entity(entity_id) {
-                            ui.selected_entity = Some(new_id);
-                            ui.mark_scene_modified();
-                            log::info!("Duplicated entity {:?} -> {:?}", entity_id, new_id);
-                        }
+                    selection::group_selection(scene, ui, &mut self.undo_history);
+                }
+            }
+            // Ctrl+P - Parent selected entities to the active (primary) entity
+            if self.modifiers.control_key() && key_code == KeyCode::KeyP {
+                if let (Some(scene), Some(ui)) = (&mut self.scene, &mut self.ui) {
+                    if let Some(parent_id) = ui.selected_entity {
+                        selection::parent_selection_to(
+                            scene,
+                            ui,
+                            &mut self.undo_history,
+                            parent_id,
+                        );
+                    }
+                }
+            }
+            // End - Snap selected entities to the ground
+            if key_code == KeyCode::End && !self.modifiers.control_key() {
+                if let (Some(scene), Some(ui), Some(asset_manager), Some(wgpu_state)) =
+                    (&mut self.scene, &mut self.ui, &mut self.asset_manager, &self.wgpu_state)
+                {
+                    if ui.hierarchy_state.editing_entity.is_none() {
+                        let terrain = placement::terrain_ref(&wgpu_state.terrain_heightmap, &wgpu_state.terrain_config);
+                        grounding::snap_selection_to_ground(
+                            scene,
+                            ui,
+                            &mut self.undo_history,
+                            asset_manager,
+                            &mut self.mesh_picker,
+                            terrain,
+                        );
                     }
                 }
             }
@@ -2994,33 +6628,8 @@ impl ApplicationHandler for EditorApp {
                         if let Some(entity) = scene.get_entity(entity_id) {
                             // Serialize the entity to clipboard
                             use engine_scene::scene_data::{SerializedEntity, SerializedComponent};
-                            use engine_scene::components::*;
 
-                            let mut components = Vec::new();
-                            if let Some(c) = entity.get_component::<MeshRenderer>() {
-                                components.push(SerializedComponent::MeshRenderer(c.clone()));
-                            }
-                            if let Some(c) = entity.get_component::<Camera>() {
-                                components.push(SerializedComponent::Camera(c.clone()));
-                            }
-                            if let Some(c) = entity.get_component::<Light>() {
-                                components.push(SerializedComponent::Light(c.clone()));
-                            }
-                            if let Some(c) = entity.get_component::<ParticleEmitter>() {
-                                components.push(SerializedComponent::ParticleEmitter(c.clone()));
-                            }
-                            if let Some(c) = entity.get_component::<Water>() {
-                                components.push(SerializedComponent::Water(c.clone()));
-                            }
-                            if let Some(c) = entity.get_component::<TerrainWater>() {
-                                components.push(SerializedComponent::TerrainWater(c.clone()));
-                            }
-                            if let Some(c) = entity.get_component::<TerrainGenerator>() {
-                                components.push(SerializedComponent::TerrainGenerator(c.clone()));
-                            }
-                            if let Some(c) = entity.get_component::<Foliage>() {
-                                components.push(SerializedComponent::Foliage(c.clone()));
-                            }
+                            let components = SerializedComponent::from_entity(entity);
 
                             self.clipboard = Some(SerializedEntity {
                                 id: entity.id,
@@ -3038,13 +6647,11 @@ impl ApplicationHandler for EditorApp {
             // Ctrl+V - Paste entity from clipboard
             if self.modifiers.control_key() && key_code == KeyCode::KeyV {
                 if let Some(ref clipboard) = self.clipboard.clone() {
-                    // Push undo state before pasting
+                    // Record the undo step before pasting
                     if let Some(scene) = &self.scene {
-                        self.undo_history.push_state(scene);
+                        self.undo_history.record_entities(scene, &[]);
                     }
                     if let (Some(scene), Some(ui)) = (&mut self.scene, &mut self.ui) {
-                        use engine_scene::scene_data::SerializedComponent;
-
                         // Create a new entity with offset position
                         let new_name = format!("{} (Copy)", clipboard.name);
                         let new_id = scene.create_entity(new_name);
@@ -3056,17 +6663,7 @@ impl ApplicationHandler for EditorApp {
 
                             // Add components from clipboard
                             for component in &clipboard.components {
-                                match component {
-                                    SerializedComponent::MeshRenderer(c) => entity.add_component(c.clone()),
-                                    SerializedComponent::Camera(c) => entity.add_component(c.clone()),
-                                    SerializedComponent::Light(c) => entity.add_component(c.clone()),
-                                    SerializedComponent::ParticleEmitter(c) => entity.add_component(c.clone()),
-                                    SerializedComponent::Water(c) => entity.add_component(c.clone()),
-                                    SerializedComponent::TerrainWater(c) => entity.add_component(c.clone()),
-                                    SerializedComponent::TerrainGenerator(c) => entity.add_component(c.clone()),
-                                    SerializedComponent::Foliage(c) => entity.add_component(c.clone()),
-                                    SerializedComponent::Generic { .. } => {}
-                                }
+                                component.clone().insert_into(entity);
                             }
                         }
 
@@ -3076,93 +6673,32 @@ impl ApplicationHandler for EditorApp {
                     }
                 }
             }
-            // Delete key - Delete selected entity
+            // Delete key - Delete selected entities
             if key_code == KeyCode::Delete {
-                if let Some(scene) = &self.scene {
-                    self.undo_history.push_state(scene);
-                }
                 if let (Some(scene), Some(ui)) = (&mut self.scene, &mut self.ui) {
-                    if let Some(entity_id) = ui.selected_entity {
-                        scene.remove_entity(entity_id);
-                        ui.selected_entity = None;
-                        ui.mark_scene_modified();
-                        log::info!("Deleted entity {:?}", entity_id);
-                    }
-                }
-            }
-            // Ctrl+Z - Undo (handles both scene and terrain)
-            if self.modifiers.control_key() && key_code == KeyCode::KeyZ && !self.modifiers.shift_key() {
-                // Check if we're in terrain mode and have terrain undo states
-                let is_terrain_mode = self.ui.as_ref().map(|ui| ui.brush_tool.mode.is_terrain_mode()).unwrap_or(false);
-
-                if is_terrain_mode && !self.terrain_undo_stack.is_empty() {
-                    // Terrain undo
-                    if let Some(wgpu_state) = &mut self.wgpu_state {
-                        if let (Some(ref mut heightmap), Some(ref config)) = (&mut wgpu_state.terrain_heightmap, &wgpu_state.terrain_config) {
-                            // Save current state to redo stack
-                            self.terrain_redo_stack.push(heightmap.heights.clone());
-                            // Restore from undo stack
-                            if let Some(old_heights) = self.terrain_undo_stack.pop() {
-                                heightmap.heights = old_heights;
-                                // Regenerate terrain mesh
-                                let terrain_mesh = Terrain::generate_mesh_from_heightmap(heightmap, config);
-                                let gpu_vertices = convert_mesh_to_gpu(&terrain_mesh);
-                                wgpu_state.mesh_manager.upload_mesh(
-                                    &wgpu_state.renderer.device,
-                                    "terrain".to_string(),
-                                    &gpu_vertices,
-                                    &terrain_mesh.indices,
-                                );
-                                log::info!("Terrain undo (remaining: {})", self.terrain_undo_stack.len());
-                            }
-                        }
-                    }
-                } else {
-                    // Scene undo
-                    if let (Some(scene), Some(ui)) = (&mut self.scene, &mut self.ui) {
-                        if self.undo_history.undo(scene) {
-                            ui.selected_entity = None;
-                            ui.mark_scene_modified();
-                            log::info!("Undo (remaining: {})", self.undo_history.undo_count());
-                        }
+                    // Don't delete if currently editing entity name
+                    if ui.hierarchy_state.editing_entity.is_none() {
+                        selection::delete_selection(scene, ui, &mut self.undo_history);
                     }
                 }
             }
-            // Ctrl+Y or Ctrl+Shift+Z - Redo (handles both scene and terrain)
-            if (self.modifiers.control_key() && key_code == KeyCode::KeyY) ||
-               (self.modifiers.control_key() && self.modifiers.shift_key() && key_code == KeyCode::KeyZ) {
-                // Check if we're in terrain mode and have terrain redo states
-                let is_terrain_mode = self.ui.as_ref().map(|ui| ui.brush_tool.mode.is_terrain_mode()).unwrap_or(false);
-
-                if is_terrain_mode && !self.terrain_redo_stack.is_empty() {
-                    // Terrain redo
-                    if let Some(wgpu_state) = &mut self.wgpu_state {
-                        if let (Some(ref mut heightmap), Some(ref config)) = (&mut wgpu_state.terrain_heightmap, &wgpu_state.terrain_config) {
-                            // Save current state to undo stack
-                            self.terrain_undo_stack.push(heightmap.heights.clone());
-                            // Restore from redo stack
-                            if let Some(new_heights) = self.terrain_redo_stack.pop() {
-                                heightmap.heights = new_heights;
-                                // Regenerate terrain mesh
-                                let terrain_mesh = Terrain::generate_mesh_from_heightmap(heightmap, config);
-                                let gpu_vertices = convert_mesh_to_gpu(&terrain_mesh);
-                                wgpu_state.mesh_manager.upload_mesh(
-                                    &wgpu_state.renderer.device,
-                                    "terrain".to_string(),
-                                    &gpu_vertices,
-                                    &terrain_mesh.indices,
-                                );
-                                log::info!("Terrain redo (remaining: {})", self.terrain_redo_stack.len());
+            // Ctrl+Z - Undo, Ctrl+Y or Ctrl+Shift+Z - Redo (scene edits and terrain strokes share one history)
+            let undo = self.modifiers.control_key() && key_code == KeyCode::KeyZ && !self.modifiers.shift_key();
+            let redo = (self.modifiers.control_key() && key_code == KeyCode::KeyY) ||
+               (self.modifiers.control_key() && self.modifiers.shift_key() && key_code == KeyCode::KeyZ);
+            if undo || redo {
+                if let (Some(scene), Some(wgpu_state)) = (&mut self.scene, &mut self.wgpu_state) {
+                    if let Some(target) = step_undo_history(&mut self.undo_history, scene, wgpu_state, redo) {
+                        if let Some(ui) = self.ui.as_mut() {
+                            if target == UndoTarget::Scene {
+                                ui.selected_entity = None;
                             }
-                        }
-                    }
-                } else {
-                    // Scene redo
-                    if let (Some(scene), Some(ui)) = (&mut self.scene, &mut self.ui) {
-                        if self.undo_history.redo(scene) {
-                            ui.selected_entity = None;
                             ui.mark_scene_modified();
+                        }
+                        if redo {
                             log::info!("Redo (remaining: {})", self.undo_history.redo_count());
+                        } else {
+                            log::info!("Undo (remaining: {})", self.undo_history.undo_count());
                         }
                     }
                 }
@@ -3173,7 +6709,7 @@ impl ApplicationHandler for EditorApp {
                     ui.show_shortcuts_help = !ui.show_shortcuts_help;
                 }
             }
-            // F3 - Toggle Statistics panel
+            // F3 - Toggle Profiler panel
             if key_code == KeyCode::F3 {
                 if let Some(ui) = &mut self.ui {
                     ui.show_statistics = !ui.show_statistics;
@@ -3185,6 +6721,27 @@ impl ApplicationHandler for EditorApp {
                     ui.show_console = !ui.show_console;
                 }
             }
+            // F5 - Toggle Assets panel
+            if key_code == KeyCode::F5 {
+                if let Some(ui) = &mut self.ui {
+                    ui.show_asset_browser = !ui.show_asset_browser;
+                }
+            }
+            // F6 - Play/Pause scene, Shift+F6 - Stop and restore the edited scene
+            if key_code == KeyCode::F6 {
+                let request = if self.modifiers.shift_key() {
+                    PlayRequest::Stop
+                } else if self.play_state == PlayState::Playing {
+                    PlayRequest::Pause
+                } else {
+                    PlayRequest::Play
+                };
+                self.handle_play_request(request);
+            }
+            // F12 - Take a screenshot of the scene
+            if key_code == KeyCode::F12 {
+                self.pending_capture = Some(capture::CaptureRequest::Screenshot(None));
+            }
             // F2 - Rename selected entity
             if key_code == KeyCode::F2 {
                 if let (Some(scene), Some(ui)) = (&self.scene, &mut self.ui) {
@@ -3202,29 +6759,48 @@ impl ApplicationHandler for EditorApp {
                 self.viewport_controls.orbit_yaw = 0.3;
                 self.viewport_controls.orbit_pitch = 0.4;
                 self.viewport_controls.pan_offset = glam::Vec3::new(0.0, 5.0, 0.0);
+                self.viewport_controls.orthographic = false;
                 log::info!("Camera view reset");
             }
-            // Numpad 1 - Front view
-            if key_code == KeyCode::Numpad1 {
-                self.viewport_controls.orbit_yaw = 0.0;
-                self.viewport_controls.orbit_pitch = 0.0;
-                log::info!("Front view");
-            }
-            // Numpad 3 - Right view
-            if key_code == KeyCode::Numpad3 {
-                self.viewport_controls.orbit_yaw = std::f32::consts::FRAC_PI_2;
-                self.viewport_controls.orbit_pitch = 0.0;
-                log::info!("Right view");
-            }
-            // Numpad 7 - Top view
-            if key_code == KeyCode::Numpad7 {
-                self.viewport_controls.orbit_yaw = 0.0;
-                self.viewport_controls.orbit_pitch = std::f32::consts::FRAC_PI_2 - 0.01;
-                log::info!("Top view");
-            }
-            // Numpad 5 - Toggle perspective/orthographic (just log for now)
+            // Numpad 1/3/7 - Front/Right/Top orthographic views
+            let view_preset = match key_code {
+                KeyCode::Numpad1 => Some(ViewPreset::Front),
+                KeyCode::Numpad3 => Some(ViewPreset::Right),
+                KeyCode::Numpad7 => Some(ViewPreset::Top),
+                _ => None,
+            };
+            if let Some(preset) = view_preset {
+                self.viewport_controls.set_view_preset(preset);
+                log::info!("{} view", preset.name());
+            }
+            // Numpad 5 - Toggle perspective/orthographic
             if key_code == KeyCode::Numpad5 {
-                log::info!("Perspective view (orthographic not yet implemented)");
+                self.viewport_controls.orthographic = !self.viewport_controls.orthographic;
+                log::info!(
+                    "{} view",
+                    if self.viewport_controls.orthographic {
+                        "Orthographic"
+                    } else {
+                        "Perspective"
+                    }
+                );
+            }
+            // 1-9 - Restore camera bookmark, Ctrl+1-9 - Save camera bookmark
+            if let Some(slot) = ui::viewport::bookmark_slot(key_code) {
+                if self.modifiers.control_key() {
+                    self.viewport_controls.save_bookmark(slot);
+                    if let Some(ui) = &mut self.ui {
+                        ui.log_info(format!("Saved camera bookmark {}", slot + 1));
+                    }
+                } else if self.viewport_controls.restore_bookmark(slot) {
+                    log::info!("Restored camera bookmark {}", slot + 1);
+                } else if let Some(ui) = &mut self.ui {
+                    ui.log_warning(format!(
+                        "Camera bookmark {} is empty (Ctrl+{} to save)",
+                        slot + 1,
+                        slot + 1
+                    ));
+                }
             }
             // Delete - Delete selected entity
             if key_code == KeyCode::Delete {
@@ -3232,7 +6808,7 @@ impl ApplicationHandler for EditorApp {
                     if let Some(entity_id) = ui.selected_entity {
                         // Don't delete if currently editing entity name
                         if ui.hierarchy_state.editing_entity.is_none() {
-                            self.undo_history.push_state(scene);
+                            self.undo_history.record_entities(scene, &[entity_id]);
                             let entity_name = scene.get_entity(entity_id)
                                 .map(|e| e.name.clone())
                                 .unwrap_or_default();
@@ -3244,12 +6820,19 @@ impl ApplicationHandler for EditorApp {
                     }
                 }
             }
-            // Escape - Deselect entity or exit
+            // Escape - Clear the measurement, deselect entity or exit
             if key_code == KeyCode::Escape {
                 if let Some(ui) = &mut self.ui {
-                    if ui.selected_entity.is_some() {
-                        // Deselect the entity
-                        ui.selected_entity = None;
+                    if ui.measure.active {
+                        // First clear the measurement, then leave the measure tool
+                        if ui.measure.has_points() {
+                            ui.measure.clear();
+                        } else {
+                            ui.measure.active = false;
+                        }
+                    } else if ui.selected_entity.is_some() {
+                        // Deselect all entities
+                        ui.clear_selection();
                         log::info!("Entity deselected");
                     } else if ui.scene_modified {
                         // Show exit confirmation if scene has unsaved changes
@@ -3275,9 +6858,6 @@ impl ApplicationHandler for EditorApp {
                 if let Err(e) = self.render() {
                     log::error!("Render error: {}", e);
                 }
-                if let Some(window) = &self.window {
-                    window.request_redraw();
-                }
             }
             _ => {}
         }
@@ -3289,6 +6869,15 @@ impl ApplicationHandler for EditorApp {
         _device_id: winit::event::DeviceId,
         event: winit::event::DeviceEvent,
     ) {
+        if self.play_state.in_session() {
+            if let winit::event::DeviceEvent::MouseMotion { delta } = &event {
+                self.game_input
+                    .lock()
+                    .unwrap()
+                    .add_mouse_delta(delta.0 as f32, delta.1 as f32);
+            }
+        }
+
         // Handle raw mouse motion for player camera control
         if self.camera_mode == CameraMode::Player {
             if let winit::event::DeviceEvent::MouseMotion { delta } = event {
@@ -3337,6 +6926,8 @@ impl ApplicationHandler for EditorApp {
             }
         }
 
+        self.sync_prefs();
+
         // Process IPC commands from MCP server
         if let Some(ipc) = &self.ipc_channel {
             if let Ok(Some(command)) = ipc.try_recv_command() {
@@ -3358,30 +6949,124 @@ impl ApplicationHandler for EditorApp {
             }
         }
 
-        if let Some(window) = &self.window {
-            window.request_redraw();
+        // Redraw now, or under a frame rate cap wait (without spinning)
+        // until the next frame is due
+        match self.frame_pacer.next_frame_at() {
+            Some(next_frame) if next_frame > std::time::Instant::now() => {
+                event_loop.set_control_flow(ControlFlow::WaitUntil(next_frame));
+            }
+            _ => {
+                event_loop.set_control_flow(ControlFlow::Poll);
+                if let Some(window) = &self.window {
+                    window.request_redraw();
+                }
+            }
+        }
+    }
+
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        // Save any preference changes still waiting on the save throttle
+        if let Some(ui) = &self.ui {
+            let prefs = EditorPrefs::capture(ui);
+            if prefs != self.prefs {
+                self.save_prefs(prefs);
+            }
         }
     }
 }
 
 fn main() -> Result<()> {
-    env_logger::init();
-    log::info!("Causality Engine - Editor starting...");
+    // Next to a game.json (an exported build) the app plays that game, from
+    // the build folder so logs/ and crashes/ land there. Failing to get
+    // there is reported once logging is up.
+    let game_dir = build_export::exported_build_dir();
+    let entered = match &game_dir {
+        Some(dir) => std::env::set_current_dir(dir)
+            .map_err(|e| anyhow::anyhow!("Failed to enter {}: {}", dir.display(), e)),
+        None => Ok(()),
+    };
+
+    // Log to stderr, logs/ and the console; panics leave a report in crashes/
+    if let Err(e) = engine_core::logging::init(&engine_core::logging::LogConfig::default()) {
+        eprintln!("Logging disabled: {:#}", e);
+    }
+    engine_core::crash::install(engine_core::crash::DEFAULT_CRASH_DIR);
+    engine_core::trace::install();
+    log::info!("Causality Engine - {} starting...", env!("CARGO_BIN_NAME"));
+
+    let game = match game_dir {
+        Some(dir) => {
+            let game = entered
+                .and_then(|()| build_export::GameManifest::load(&dir))
+                .inspect_err(|e| log::error!("Failed to start the game: {:#}", e))?;
+            log::info!("Playing {}", game.name);
+            Some(game)
+        }
+        None if env!("CARGO_BIN_NAME") == build_export::RUNTIME_NAME => {
+            log::error!("No {} next to {}", build_export::MANIFEST_FILE, build_export::RUNTIME_NAME);
+            anyhow::bail!("{} only plays exported builds", build_export::RUNTIME_NAME);
+        }
+        None => None,
+    };
 
     // Parse command line arguments
     let args = Args::parse();
 
-    // Determine scene file path (use provided or default to castle scene)
-    let scene_file = args.scene.or_else(|| Some("assets/scenes/castle.ron".to_string()));
+    // Determine scene file path (use provided, the game's or the project's start scene)
+    let project = engine_core::project::current();
+    log::info!("Project: {}", project.name);
+    let scene_file = args
+        .scene
+        .clone()
+        .or_else(|| game.as_ref().map(|game| game.start_scene.clone()))
+        .or_else(|| Some(project.start_scene.clone()));
 
     if let Some(ref path) = scene_file {
         log::info!("Will load scene from: {}", path);
     }
 
+    if let Some(addr) = args.serve {
+        return headless::serve(&scene_file.unwrap_or_default(), &addr);
+    }
+
+    // Bakes and headless runs work on the scene they're given (clap requires --scene)
+    if let (Some(target), Some(scene)) = (args.bake, &args.scene) {
+        return bake::run(scene, target);
+    }
+
+    if let Some(path) = args.replay {
+        return headless::run_replay(&path, args.output.as_deref());
+    }
+
+    if let (true, Some(scene)) = (args.headless, &args.scene) {
+        return headless::run(&headless::HeadlessOptions {
+            scene_path: scene.clone(),
+            frames: args.frames,
+            snapshot_every: args.snapshot_every,
+            output: args.output,
+            trace: args.trace,
+            record: args.record,
+            screenshot: args.screenshot,
+            screenshot_size: [args.screenshot_width, args.screenshot_height],
+        });
+    }
+
     let event_loop = EventLoop::new()?;
     event_loop.set_control_flow(ControlFlow::Poll);
 
+    if let Some(path) = args.trace {
+        engine_core::trace::start_capture(args.frames, path);
+    }
     let mut app = EditorApp::new(scene_file);
+    app.net_launch = match (args.host, args.connect) {
+        (Some(addr), _) => net_session::NetLaunch::Host(addr),
+        (None, Some(addr)) => net_session::NetLaunch::Join {
+            addr,
+            name: args.name,
+        },
+        (None, None) => net_session::NetLaunch::Offline,
+    };
+    app.game = game;
     event_loop.run_app(&mut app)?;
 
     Ok(())
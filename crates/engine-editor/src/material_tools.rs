@@ -0,0 +1,260 @@
+// Material tools - editing .mat files and texture assignment for MCP commands
+//
+// Texture arguments are either paths relative to the asset root or the IDs
+// of textures made by generate_texture. Generated textures live in the AI
+// asset cache outside the asset root, so they are copied into
+// `textures/generated/` first; materials only ever reference asset paths.
+
+use anyhow::{anyhow, bail, Result};
+use engine_ai_assets::AssetCache;
+use engine_assets::loaders::{load_material, save_material};
+use engine_assets::{AlphaMode, Material};
+use serde_json::Value;
+use std::path::{Component, Path, PathBuf};
+
+/// Where generate_texture stores its output
+pub const GENERATED_ASSETS_DIR: &str = "./generated_assets";
+
+/// Material texture slots, as named in tool arguments
+pub const TEXTURE_SLOTS: [&str; 4] = ["albedo", "normal", "metallic_roughness", "ao"];
+
+/// Turn a texture argument into a path relative to the asset root,
+/// importing it from the AI asset cache when it is a generated asset ID
+pub fn resolve_texture(asset_root: &Path, texture: &str) -> Result<String> {
+    if texture.is_empty() {
+        bail!("Empty texture path");
+    }
+    if asset_file(asset_root, texture).is_ok_and(|file| file.is_file()) {
+        return Ok(texture.to_string());
+    }
+
+    let cache = AssetCache::new(GENERATED_ASSETS_DIR)?;
+    if !cache.has_asset(texture) {
+        bail!(
+            "Texture '{}' is neither a file under {} nor a generated asset ID",
+            texture,
+            asset_root.display()
+        );
+    }
+    let (bytes, metadata) = cache.get_asset(texture)?;
+    let relative = format!("textures/generated/{}.{}", metadata.id, metadata.format);
+    let destination = asset_root.join(&relative);
+    if !destination.exists() {
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&destination, bytes)?;
+        log::info!("Imported generated texture {} as {}", texture, relative);
+    }
+    Ok(relative)
+}
+
+/// Set the texture in `slot` ("albedo", "normal", "metallic_roughness" or "ao")
+pub fn set_texture_slot(material: &mut Material, slot: &str, path: Option<String>) -> Result<()> {
+    let target = match slot {
+        "albedo" | "base_color" => &mut material.albedo_texture,
+        "normal" => &mut material.normal_texture,
+        "metallic_roughness" => &mut material.metallic_roughness_texture,
+        "ao" | "occlusion" => &mut material.ao_texture,
+        _ => bail!(
+            "Unknown texture slot '{}' (expected one of: {})",
+            slot,
+            TEXTURE_SLOTS.join(", ")
+        ),
+    };
+    *target = path;
+    Ok(())
+}
+
+/// Apply set_material arguments to `material`. Fields that are not given keep
+/// their values; a texture given as an empty string clears its slot.
+pub fn apply_material_args(material: &mut Material, args: &Value, asset_root: &Path) -> Result<()> {
+    let number = |key: &str| -> Result<Option<f32>> {
+        match args.get(key) {
+            None | Some(Value::Null) => Ok(None),
+            Some(value) => value
+                .as_f64()
+                .map(|v| Some(v as f32))
+                .ok_or_else(|| anyhow!("'{}' must be a number", key)),
+        }
+    };
+    let color = |key: &str| -> Result<Option<Vec<f32>>> {
+        match args.get(key) {
+            None | Some(Value::Null) => Ok(None),
+            Some(value) => value
+                .as_array()
+                .and_then(|arr| arr.iter().map(|v| v.as_f64().map(|v| v as f32)).collect())
+                .map(Some)
+                .ok_or_else(|| anyhow!("'{}' must be an array of numbers", key)),
+        }
+    };
+
+    if let Some(name) = args.get("name").and_then(|v| v.as_str()) {
+        material.name = name.to_string();
+    }
+    if let Some(base_color) = color("base_color")? {
+        match base_color.as_slice() {
+            [r, g, b] => material.base_color = [*r, *g, *b, material.base_color[3]],
+            [r, g, b, a] => material.base_color = [*r, *g, *b, *a],
+            _ => bail!("'base_color' needs 3 or 4 components"),
+        }
+    }
+    if let Some(emissive) = color("emissive_color")? {
+        match emissive.as_slice() {
+            [r, g, b] => material.emissive_color = [*r, *g, *b],
+            _ => bail!("'emissive_color' needs 3 components"),
+        }
+    }
+    if let Some(metallic) = number("metallic")? {
+        material.metallic = metallic.clamp(0.0, 1.0);
+    }
+    if let Some(roughness) = number("roughness")? {
+        material.roughness = roughness.clamp(0.0, 1.0);
+    }
+    if let Some(ao_factor) = number("ao_factor")? {
+        material.ao_factor = ao_factor.clamp(0.0, 1.0);
+    }
+    if let Some(strength) = number("emissive_strength")? {
+        material.emissive_strength = strength.max(0.0);
+    }
+    if let Some(cutoff) = number("alpha_cutoff")? {
+        material.alpha_cutoff = cutoff.clamp(0.0, 1.0);
+    }
+    if let Some(double_sided) = args.get("double_sided").and_then(|v| v.as_bool()) {
+        material.double_sided = double_sided;
+    }
+    if let Some(alpha_mode) = args.get("alpha_mode").and_then(|v| v.as_str()) {
+        material.alpha_mode = match alpha_mode.to_lowercase().as_str() {
+            "opaque" => AlphaMode::Opaque,
+            "mask" => AlphaMode::Mask,
+            "blend" => AlphaMode::Blend,
+            _ => bail!(
+                "Unknown alpha_mode '{}' (expected opaque, mask or blend)",
+                alpha_mode
+            ),
+        };
+    }
+
+    for slot in TEXTURE_SLOTS {
+        let key = format!("{}_texture", slot);
+        if let Some(texture) = args.get(&key).and_then(|v| v.as_str()) {
+            let path = if texture.is_empty() {
+                None
+            } else {
+                Some(resolve_texture(asset_root, texture)?)
+            };
+            set_texture_slot(material, slot, path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Full path of an asset-relative path, refusing anything that would
+/// escape the asset root
+pub fn asset_file(asset_root: &Path, path: &str) -> Result<PathBuf> {
+    let relative = Path::new(path);
+    if path.is_empty()
+        || relative.is_absolute()
+        || relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        bail!("'{}' is not a path inside the asset directory", path);
+    }
+    Ok(asset_root.join(relative))
+}
+
+/// Load the material at `path`, or start a new one named after the file
+/// (the flag is true when the file did not exist yet)
+pub fn load_or_new(asset_root: &Path, path: &str) -> Result<(Material, bool)> {
+    let file = asset_file(asset_root, path)?;
+    if file.is_file() {
+        return Ok((load_material(&file)?, false));
+    }
+    let name = file
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("Material")
+        .to_string();
+    Ok((Material::new(name), true))
+}
+
+/// Write the material to `path`, creating its directory if needed
+pub fn save(asset_root: &Path, path: &str, material: &Material) -> Result<()> {
+    let file = asset_file(asset_root, path)?;
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    save_material(material, &file)
+}
+
+/// The material file assign_texture gives an entity of its own
+pub fn entity_material_path(entity_name: &str) -> String {
+    let file_name: String = entity_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("materials/entities/{}.mat", file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_apply_material_args() {
+        let root =
+            std::env::temp_dir().join(format!("causality_material_test_{}", std::process::id()));
+        std::fs::create_dir_all(root.join("textures")).unwrap();
+        std::fs::write(root.join("textures/brick.png"), b"png").unwrap();
+
+        let mut material = Material::new("Brick".to_string());
+        apply_material_args(
+            &mut material,
+            &json!({
+                "base_color": [0.8, 0.4, 0.2],
+                "roughness": 1.5,
+                "alpha_mode": "blend",
+                "albedo_texture": "textures/brick.png"
+            }),
+            &root,
+        )
+        .unwrap();
+        assert_eq!(material.base_color, [0.8, 0.4, 0.2, 1.0]);
+        assert_eq!(material.roughness, 1.0);
+        assert_eq!(material.alpha_mode, AlphaMode::Blend);
+        assert_eq!(
+            material.albedo_texture.as_deref(),
+            Some("textures/brick.png")
+        );
+
+        apply_material_args(&mut material, &json!({ "albedo_texture": "" }), &root).unwrap();
+        assert!(material.albedo_texture.is_none());
+
+        assert!(
+            apply_material_args(&mut material, &json!({ "metallic": "shiny" }), &root).is_err()
+        );
+        assert!(set_texture_slot(&mut material, "height", None).is_err());
+        assert_eq!(
+            entity_material_path("Old Crate #2"),
+            "materials/entities/Old_Crate__2.mat"
+        );
+
+        // Saved materials load back as written, and paths stay inside the root
+        save(&root, "materials/brick.mat", &material).unwrap();
+        let (loaded, created) = load_or_new(&root, "materials/brick.mat").unwrap();
+        assert!(!created);
+        assert_eq!(loaded, material);
+        assert!(load_or_new(&root, "materials/new.mat").unwrap().1);
+        assert!(asset_file(&root, "../outside.mat").is_err());
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
@@ -0,0 +1,187 @@
+// Measure tool - distance and angles between two points picked in the viewport
+//
+// While the tool is active, viewport clicks drop measurement points on the
+// surface under the cursor instead of selecting. The first click starts a
+// measurement, the second finishes it, and a third starts over. Until the
+// second click the measurement follows the cursor.
+
+use engine_assets::AssetManager;
+use engine_scene::scene::Scene;
+use glam::{Mat4, Vec2, Vec3};
+
+use crate::picking::MeshPicker;
+use crate::placement::{ray_plane_y, TerrainRef};
+use crate::water_edit::project_to_screen;
+
+/// Measurement points picked in the viewport
+#[derive(Debug, Clone, Default)]
+pub struct MeasureState {
+    /// Clicks in the viewport place measurement points instead of selecting
+    pub active: bool,
+    pub start: Option<Vec3>,
+    pub end: Option<Vec3>,
+}
+
+impl MeasureState {
+    /// Place the next measurement point (a finished measurement starts over)
+    pub fn click(&mut self, point: Vec3) {
+        if self.start.is_some() && self.end.is_none() {
+            self.end = Some(point);
+        } else {
+            self.start = Some(point);
+            self.end = None;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.start = None;
+        self.end = None;
+    }
+
+    pub fn has_points(&self) -> bool {
+        self.start.is_some()
+    }
+
+    /// The current measurement, ending at `cursor` while the end point is unset
+    pub fn measurement(&self, cursor: Option<Vec3>) -> Option<(Vec3, Vec3)> {
+        Some((self.start?, self.end.or(cursor)?))
+    }
+}
+
+/// Distance and angles from one point to another
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    pub distance: f32,
+    /// Distance in the XZ plane
+    pub horizontal: f32,
+    /// Height difference (positive when the end is higher)
+    pub vertical: f32,
+    /// Angle above (positive) or below the horizontal, in degrees
+    pub slope_degrees: f32,
+    /// Compass heading in degrees, 0 along -Z (forward) and 90 along +X
+    pub heading_degrees: f32,
+}
+
+impl Measurement {
+    pub fn between(start: Vec3, end: Vec3) -> Self {
+        let delta = end - start;
+        let horizontal = Vec2::new(delta.x, delta.z).length();
+        Self {
+            distance: delta.length(),
+            horizontal,
+            vertical: delta.y,
+            slope_degrees: delta.y.atan2(horizontal).to_degrees(),
+            heading_degrees: delta.x.atan2(-delta.z).to_degrees().rem_euclid(360.0),
+        }
+    }
+
+    /// Viewport label, e.g. "4.24 m  (H 3.00, V 3.00)  45.0° up, heading 90°"
+    pub fn label(&self) -> String {
+        let direction = if self.slope_degrees >= 0.0 {
+            "up"
+        } else {
+            "down"
+        };
+        format!(
+            "{:.2} m  (H {:.2}, V {:.2})  {:.1}° {}, heading {:.0}°",
+            self.distance,
+            self.horizontal,
+            self.vertical,
+            self.slope_degrees.abs(),
+            direction,
+            self.heading_degrees
+        )
+    }
+}
+
+/// Screen-space (pixel) measurement line drawn over the viewport
+#[derive(Debug, Clone)]
+pub struct MeasureOverlay {
+    pub start: Vec2,
+    pub end: Vec2,
+    pub measurement: Measurement,
+    /// False while the end point still follows the cursor
+    pub complete: bool,
+}
+
+/// Point under the cursor ray: the nearest mesh or terrain hit, else the ground plane
+pub fn surface_point(
+    scene: &Scene,
+    asset_manager: &mut AssetManager,
+    picker: &mut MeshPicker,
+    terrain: TerrainRef,
+    ray_origin: Vec3,
+    ray_direction: Vec3,
+) -> Option<Vec3> {
+    let mesh_hit = picker
+        .pick(scene, asset_manager, ray_origin, ray_direction)
+        .map(|(_, t)| ray_origin + ray_direction * t);
+    let terrain_hit = terrain.and_then(|(heightmap, config)| {
+        crate::raycast_terrain(ray_origin, ray_direction, heightmap, config)
+    });
+    let nearest = match (mesh_hit, terrain_hit) {
+        (Some(a), Some(b)) => {
+            if a.distance_squared(ray_origin) <= b.distance_squared(ray_origin) {
+                Some(a)
+            } else {
+                Some(b)
+            }
+        }
+        (hit, None) | (None, hit) => hit,
+    };
+    nearest.or_else(|| ray_plane_y(ray_origin, ray_direction, 0.0))
+}
+
+/// Project the current measurement into the viewport
+pub fn build_overlay(
+    state: &MeasureState,
+    cursor: Option<Vec3>,
+    view_proj: Mat4,
+    screen_size: Vec2,
+) -> Option<MeasureOverlay> {
+    let (start, end) = state.measurement(cursor)?;
+    Some(MeasureOverlay {
+        start: project_to_screen(view_proj, start, screen_size)?,
+        end: project_to_screen(view_proj, end, screen_size)?,
+        measurement: Measurement::between(start, end),
+        complete: state.end.is_some(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clicks_cycle_through_points() {
+        let mut state = MeasureState::default();
+        let hovered = Vec3::new(5.0, 0.0, 0.0);
+        let cursor = Some(hovered);
+        assert_eq!(state.measurement(cursor), None);
+
+        state.click(Vec3::ZERO);
+        assert_eq!(state.measurement(cursor), Some((Vec3::ZERO, hovered)));
+
+        state.click(Vec3::X);
+        assert_eq!(state.measurement(cursor), Some((Vec3::ZERO, Vec3::X)));
+
+        // A third click starts a new measurement
+        state.click(Vec3::Y);
+        assert_eq!(state.start, Some(Vec3::Y));
+        assert_eq!(state.end, None);
+    }
+
+    #[test]
+    fn test_measurement_angles() {
+        let m = Measurement::between(Vec3::ZERO, Vec3::new(3.0, 3.0, 0.0));
+        assert!((m.distance - 18.0f32.sqrt()).abs() < 1e-5);
+        assert!((m.horizontal - 3.0).abs() < 1e-5);
+        assert!((m.slope_degrees - 45.0).abs() < 1e-3);
+        assert!((m.heading_degrees - 90.0).abs() < 1e-3);
+
+        let m = Measurement::between(Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 0.0, 4.0));
+        assert!(m.slope_degrees < 0.0);
+        assert!((m.heading_degrees - 180.0).abs() < 1e-3);
+        assert!(m.label().contains("down"));
+    }
+}
@@ -0,0 +1,107 @@
+// Navigation - navmesh bake inputs from the scene and the viewport overlay
+//
+// Static meshes are baked as their rough bounds (scale as half-extents, the
+// same boxes viewport picking uses). Moving bodies, characters, water and the
+// terrain mesh itself are left out.
+
+use engine_assets::{NavMesh, NavObstacle};
+use engine_physics::{CharacterController, RigidBody, RigidBodyType};
+use engine_render::{camera::Camera, frustum::AABB};
+use engine_scene::{
+    components::{MeshRenderer, TerrainGenerator, Water},
+    entity::Entity,
+    scene::Scene,
+};
+use glam::{Vec2, Vec3};
+
+use crate::selection::world_position;
+use crate::water_edit::project_to_screen;
+
+/// Lift the drawn polygons off the ground they lie on
+const OVERLAY_LIFT: f32 = 0.05;
+
+/// Meshes that never move: no dynamic or kinematic body, not a character,
+/// water or the terrain. Navmesh and lighting bakes only use these.
+pub fn is_static_mesh(entity: &Entity) -> bool {
+    entity
+        .get_component::<MeshRenderer>()
+        .is_some_and(|mesh| mesh.mesh_path != "terrain")
+        && !entity.has_component::<TerrainGenerator>()
+        && !entity.has_component::<Water>()
+        && !entity.has_component::<CharacterController>()
+        && entity
+            .get_component::<RigidBody>()
+            .is_none_or(|body| body.body_type == RigidBodyType::Static)
+}
+
+/// Bounds of the scene's static meshes
+pub fn collect_obstacles(scene: &Scene) -> Vec<NavObstacle> {
+    scene
+        .entities()
+        .filter(|e| is_static_mesh(e))
+        .map(|e| {
+            let aabb =
+                AABB::from_center_extents(world_position(scene, e.id), e.transform.scale.abs());
+            NavObstacle {
+                min: aabb.min,
+                max: aabb.max,
+            }
+        })
+        .collect()
+}
+
+/// Walkable polygons projected to pixels. Polygons with a corner behind the
+/// camera are skipped.
+pub fn build_overlay(navmesh: &NavMesh, camera: &Camera, screen_size: Vec2) -> Vec<Vec<Vec2>> {
+    let view_proj = camera.view_projection_matrix();
+    navmesh
+        .polygons
+        .iter()
+        .filter_map(|polygon| {
+            polygon
+                .vertices
+                .iter()
+                .map(|&[x, y, z]| {
+                    project_to_screen(view_proj, Vec3::new(x, y + OVERLAY_LIFT, z), screen_size)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_obstacles_keeps_static_meshes() {
+        let mut scene = Scene::new("Test".to_string());
+        let wall = scene.create_entity("Wall".to_string());
+        let crate_id = scene.create_entity("Crate".to_string());
+        let pillar = scene.create_entity("Pillar".to_string());
+        scene.create_entity("Empty".to_string());
+
+        let entity = scene.get_entity_mut(wall).unwrap();
+        entity.add_component(MeshRenderer::new("cube".to_string()));
+        entity.transform.position = Vec3::new(4.0, 1.0, 0.0);
+        entity.transform.scale = Vec3::new(0.5, 1.0, 2.0);
+
+        // Dynamic bodies move, so they aren't baked
+        let entity = scene.get_entity_mut(crate_id).unwrap();
+        entity.add_component(MeshRenderer::new("cube".to_string()));
+        entity.add_component(RigidBody::dynamic(1.0));
+
+        let entity = scene.get_entity_mut(pillar).unwrap();
+        entity.add_component(MeshRenderer::new("cube".to_string()));
+        entity.add_component(RigidBody::static_body());
+
+        let obstacles = collect_obstacles(&scene);
+        assert_eq!(obstacles.len(), 2);
+        let wall_bounds = obstacles
+            .iter()
+            .find(|o| o.min.x == 3.5)
+            .expect("wall is baked");
+        assert_eq!(wall_bounds.min, Vec3::new(3.5, 0.0, -2.0));
+        assert_eq!(wall_bounds.max, Vec3::new(4.5, 2.0, 2.0));
+    }
+}
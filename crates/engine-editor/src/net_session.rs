@@ -0,0 +1,249 @@
+// Networked play - hosting or joining a session from play mode or headless
+//
+// The server is authoritative: it runs scripts and physics and replicates
+// entities with a Replicated component to clients. A client applies the
+// snapshots to its scene instead of stepping physics itself, and its
+// scripts talk to the server's with net_send/net_receive. Clients joining
+// and leaving reach the server's scripts as "client_connected" and
+// "client_disconnected" messages. A client predicts its own character from
+// what its scripts pass to net_input, and interpolates everything else.
+
+use std::net::ToSocketAddrs;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use engine_net::{ClientEvent, NetClient, NetServer, RpcMessage, ServerEvent};
+use engine_physics::PhysicsWorld;
+use engine_scene::scene::Scene;
+use engine_scripting::{
+    IncomingMessage, NetRole, NetStateHandle, OutgoingMessage, SavedValue, ScriptSystem,
+};
+use glam::Vec3;
+
+/// What play mode does on the network, from the command line
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum NetLaunch {
+    #[default]
+    Offline,
+    Host(String),
+    Join {
+        addr: String,
+        name: String,
+    },
+}
+
+enum Link {
+    Server(NetServer),
+    /// Boxed: prediction and interpolation state make it much the larger
+    Client(Box<NetClient>),
+}
+
+/// The network link, if any, plus the state shared with scripts
+#[derive(Default)]
+pub struct NetSession {
+    state: NetStateHandle,
+    link: Option<Link>,
+    last_replicated: Option<Instant>,
+    /// The client's physics world is never stepped, so its raycasts (for
+    /// the predicted character's ground) need the query pipeline built once
+    queries_ready: bool,
+}
+
+impl NetSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give a (new) script system the net API
+    pub fn register(&self, scripts: &mut ScriptSystem) {
+        scripts.register_net_api(self.state.clone());
+    }
+
+    /// Accept clients on `addr` and replicate to them
+    pub fn host(&mut self, addr: impl ToSocketAddrs) -> Result<()> {
+        self.close();
+        self.link = Some(Link::Server(NetServer::bind(addr)?));
+        self.state.lock().unwrap().role = NetRole::Server;
+        Ok(())
+    }
+
+    /// Connect to a server; the scene follows its snapshots from then on
+    pub fn join(&mut self, addr: impl ToSocketAddrs, name: &str) -> Result<()> {
+        self.close();
+        self.link = Some(Link::Client(Box::new(NetClient::connect(addr, name)?)));
+        self.state.lock().unwrap().role = NetRole::Client;
+        Ok(())
+    }
+
+    /// Host or join as the command line asked (nothing when offline)
+    pub fn start(&mut self, launch: &NetLaunch) -> Result<()> {
+        match launch {
+            NetLaunch::Offline => Ok(()),
+            NetLaunch::Host(addr) => self.host(addr.as_str()),
+            NetLaunch::Join { addr, name } => self.join(addr.as_str(), name),
+        }
+    }
+
+    /// Leave the session (clients say goodbye; a server just stops answering)
+    pub fn close(&mut self) {
+        if let Some(Link::Client(client)) = &mut self.link {
+            client.disconnect();
+        }
+        self.link = None;
+        self.last_replicated = None;
+        self.queries_ready = false;
+        let mut state = self.state.lock().unwrap();
+        state.role = NetRole::Offline;
+        state.client_id = None;
+        state.clear();
+    }
+
+    /// True while the scene is driven by a server's snapshots
+    pub fn is_client(&self) -> bool {
+        matches!(self.link, Some(Link::Client(_)))
+    }
+
+    /// Where this client is looking from, for the server's interest radius
+    pub fn set_view(&mut self, position: Vec3) {
+        if let Some(Link::Client(client)) = &mut self.link {
+            client.set_view(position);
+        }
+    }
+
+    /// Exchange messages: hand scripts what arrived, send what they queued,
+    /// and on a server apply clients' input and replicate the scene at its
+    /// tick rate; on a client, apply the server's snapshots to the scene,
+    /// predict the player's character and interpolate the rest. Call once
+    /// per frame after the simulation has stepped.
+    pub fn update(&mut self, scene: &mut Scene, physics: &mut PhysicsWorld, dt: f32) {
+        let Some(link) = &mut self.link else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        match link {
+            Link::Server(server) => {
+                for event in server.poll() {
+                    let message = match event {
+                        ServerEvent::ClientConnected { client, name } => IncomingMessage {
+                            from: Some(client.0),
+                            name: "client_connected".to_string(),
+                            data: SavedValue::String(name),
+                        },
+                        ServerEvent::ClientDisconnected { client, reason } => IncomingMessage {
+                            from: Some(client.0),
+                            name: "client_disconnected".to_string(),
+                            data: SavedValue::String(reason),
+                        },
+                        ServerEvent::Rpc { client, message } => from_rpc(Some(client.0), message),
+                    };
+                    state.incoming.push(message);
+                }
+                for message in state.outgoing.drain(..) {
+                    let target = message.target;
+                    let rpc = to_rpc(message);
+                    match target {
+                        Some(client) => {
+                            if !server.send_rpc(engine_net::ClientId(client), rpc) {
+                                log::warn!("net_send_to: no client {}", client);
+                            }
+                        }
+                        None => server.broadcast_rpc(rpc),
+                    }
+                }
+
+                server.apply_inputs(scene, physics);
+
+                let interval = Duration::from_secs_f32(1.0 / server.tick_rate() as f32);
+                let now = Instant::now();
+                if self
+                    .last_replicated
+                    .is_none_or(|last| now - last >= interval)
+                {
+                    self.last_replicated = Some(now);
+                    server.replicate(scene);
+                } else {
+                    server.flush();
+                }
+            }
+            Link::Client(client) => {
+                for event in client.poll(scene) {
+                    match event {
+                        ClientEvent::Connected { client_id } => {
+                            state.client_id = Some(client_id.0);
+                        }
+                        ClientEvent::Rejected { reason } => {
+                            log::warn!("Server rejected the connection: {}", reason);
+                        }
+                        ClientEvent::Disconnected { reason } => {
+                            log::warn!("Disconnected from server: {}", reason);
+                            state.client_id = None;
+                        }
+                        ClientEvent::Rpc(message) => state.incoming.push(from_rpc(None, message)),
+                    }
+                }
+                for message in state.outgoing.drain(..) {
+                    client.send_rpc(to_rpc(message));
+                }
+
+                if !self.queries_ready {
+                    physics.query_pipeline.update(&physics.collider_set);
+                    self.queries_ready = true;
+                }
+                // Without input the character still falls and is corrected
+                let input = state.input.take().unwrap_or_default();
+                client.predict(scene, input.movement, input.jump, input.sprint, dt, physics);
+                client.interpolate(scene, dt);
+                client.flush();
+            }
+        }
+    }
+}
+
+impl Drop for NetSession {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+fn to_rpc(message: OutgoingMessage) -> RpcMessage {
+    let data = serde_json::to_value(&message.data).unwrap_or_default();
+    RpcMessage::new(message.name, data)
+}
+
+fn from_rpc(from: Option<u32>, message: RpcMessage) -> IncomingMessage {
+    let data = serde_json::from_value(message.data).unwrap_or_else(|e| {
+        log::warn!(
+            "Ignoring malformed data in '{}' message: {}",
+            message.name,
+            e
+        );
+        SavedValue::Unit
+    });
+    IncomingMessage {
+        from,
+        name: message.name,
+        data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_data_survives_the_wire() {
+        let mut data = std::collections::BTreeMap::new();
+        data.insert("aim".to_string(), SavedValue::Vec3([1.0, 0.0, -1.0]));
+        data.insert("power".to_string(), SavedValue::Float(0.5));
+        let sent = OutgoingMessage {
+            target: None,
+            name: "shoot".to_string(),
+            data: SavedValue::Map(data.clone()),
+        };
+
+        let received = from_rpc(Some(2), to_rpc(sent));
+        assert_eq!(received.from, Some(2));
+        assert_eq!(received.name, "shoot");
+        assert_eq!(received.data, SavedValue::Map(data));
+    }
+}
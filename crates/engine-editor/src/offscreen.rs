@@ -0,0 +1,353 @@
+// Offscreen rendering - draw a scene with no window, for headless screenshots
+//
+// The scene's models, materials and textures load synchronously, then each
+// capture renders the sun's shadow map, the procedural sky and the scene's
+// MeshRenderers through its Camera component into the HDR framebuffer, tone
+// maps that into an offscreen target and reads it back with FrameCapture.
+// Terrain, water, foliage, particles and skinned meshes are not drawn.
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use engine_assets::AssetManager;
+use engine_render::{
+    camera::Camera,
+    capture::FrameCapture,
+    gpu_material::MaterialHandle,
+    instancing::DrawBatches,
+    material_manager::MaterialManager,
+    mesh_manager::MeshManager,
+    postprocess::{BloomChain, Framebuffer, PostProcessPipeline, PostProcessSettings, HDR_FORMAT},
+    renderer::Renderer,
+    shadow::{ShadowMap, ShadowPushConstants},
+    sky::ProceduralSky,
+    skybox::Skybox,
+    texture_manager::TextureManager,
+};
+use engine_scene::components::MeshRenderer;
+use engine_scene::scene::Scene;
+use wgpu::util::DeviceExt;
+
+use crate::{build_export, capture, play_mode};
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct CameraUniforms {
+    view_proj: [[f32; 4]; 4],
+    view_proj_inverse: [[f32; 4]; 4],
+}
+
+/// Renderer and GPU resources for drawing one scene offscreen
+pub struct OffscreenRenderer {
+    renderer: Renderer,
+    asset_manager: AssetManager,
+    mesh_manager: MeshManager,
+    texture_manager: TextureManager,
+    material_manager: MaterialManager,
+    skybox: Skybox,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    shadow_map: ShadowMap,
+    shadow_bind_group: wgpu::BindGroup,
+    framebuffer: Framebuffer,
+    bloom_chain: BloomChain,
+    post_process: PostProcessPipeline,
+    depth_texture: wgpu::TextureView,
+    target: wgpu::Texture,
+    batches: DrawBatches,
+}
+
+impl OffscreenRenderer {
+    /// Set up a `width` x `height` renderer on the first adapter wgpu finds
+    pub fn new(width: u32, height: u32) -> Result<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+        // Single-sampled, so the scene draws straight into the HDR framebuffer
+        let renderer = pollster::block_on(Renderer::new_offscreen(&instance, width, height, 1))
+            .context("No GPU adapter for offscreen rendering")?;
+        let device = &renderer.device;
+
+        let texture_manager = TextureManager::new(device, &renderer.queue);
+        let material_manager = MaterialManager::new(device, &texture_manager);
+        let asset_manager = build_export::project_assets()?;
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Offscreen Camera Buffer"),
+            contents: bytemuck::cast_slice(&[CameraUniforms {
+                view_proj: glam::Mat4::IDENTITY.to_cols_array_2d(),
+                view_proj_inverse: glam::Mat4::IDENTITY.to_cols_array_2d(),
+            }]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let camera_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Offscreen Camera Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Offscreen Camera Bind Group"),
+            layout: &camera_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+        let skybox = Skybox::new(device, &renderer.queue, HDR_FORMAT, renderer.sample_count, &camera_layout)?;
+
+        let shadow_map = ShadowMap::with_size(device, engine_core::project::current().rendering.shadow_resolution)?;
+        let shadow_layout = ShadowMap::create_sampling_bind_group_layout(device);
+        let shadow_bind_group = shadow_map.create_sampling_bind_group(device, &shadow_layout);
+
+        let framebuffer = Framebuffer::new(device, width, height, HDR_FORMAT, false)?;
+        let bloom_chain = BloomChain::new(device, width, height)?;
+        let post_process = PostProcessPipeline::new(device, renderer.surface_config.format)?;
+        let depth_texture = renderer.create_depth_texture(width, height);
+        let target = renderer.create_offscreen_target();
+
+        Ok(Self {
+            renderer,
+            asset_manager,
+            mesh_manager: MeshManager::new(),
+            texture_manager,
+            material_manager,
+            skybox,
+            camera_buffer,
+            camera_bind_group,
+            shadow_map,
+            shadow_bind_group,
+            framebuffer,
+            bloom_chain,
+            post_process,
+            depth_texture,
+            target,
+            batches: DrawBatches::new(),
+        })
+    }
+
+    /// Upload the models the scene's MeshRenderers draw
+    pub fn load_scene(&mut self, scene: &Scene) {
+        crate::upload_scene_models(
+            scene,
+            &mut self.asset_manager,
+            &mut self.mesh_manager,
+            &self.renderer.device,
+            // Captures draw the full meshes, so their LODs are never uploaded
+            &mut Vec::new(),
+        );
+    }
+
+    /// Render the scene through its active Camera component and save the frame as a PNG
+    pub fn capture(&mut self, scene: &Scene, path: &Path) -> Result<()> {
+        let config = &self.renderer.surface_config;
+        let (width, height) = (config.width, config.height);
+        let mut camera = Camera::new(width, height);
+        if !play_mode::apply_game_camera(scene, &mut camera) {
+            bail!("Scene '{}' has no active Camera to take a screenshot with", scene.name);
+        }
+        let view_proj = camera.view_projection_matrix();
+        self.renderer.queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&[CameraUniforms {
+                view_proj: view_proj.to_cols_array_2d(),
+                view_proj_inverse: view_proj.inverse().to_cols_array_2d(),
+            }]),
+        );
+
+        // Sky and sunlight as the editor draws them
+        let sun_light = engine_scene::time_of_day::find_sun_light(scene).map(|(_, sun)| sun.clone());
+        let sky = sun_light.as_ref().map_or_else(ProceduralSky::default, |sun| ProceduralSky {
+            sun_direction: sun.sun_direction(),
+            turbidity: sun.turbidity,
+            intensity: sun.intensity,
+        });
+        self.skybox.set_procedural_sky(&self.renderer.queue, &sky);
+        self.skybox.wait_for_lighting(&self.renderer.queue);
+        self.renderer.set_sun(sky.sun_direction, sky.sun_radiance());
+
+        self.batches.clear();
+        for entity in scene.entities() {
+            let Some(mesh_renderer) = entity.get_component::<MeshRenderer>() else {
+                continue;
+            };
+            let Some(mesh_handle) = crate::mesh_renderer_handle(&self.mesh_manager, mesh_renderer) else {
+                continue;
+            };
+            let material_path = mesh_renderer
+                .material_path
+                .as_deref()
+                .unwrap_or("materials/default.mat");
+            let material = self.material(material_path);
+            self.batches.push(mesh_handle, material, scene.world_matrix(entity.id), 1.0);
+        }
+
+        let device = &self.renderer.device;
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Offscreen Encoder"),
+        });
+
+        self.shadow_map.update_uniforms(
+            &self.renderer.queue,
+            crate::shadow_light_space_matrix(scene, sun_light.is_some(), &sky),
+        );
+        {
+            let mut shadow_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Offscreen Shadow Pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.shadow_map.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            shadow_pass.set_pipeline(&self.shadow_map.render_pipeline);
+            shadow_pass.set_bind_group(0, &self.shadow_map.bind_group, &[]);
+            let casts_shadows = crate::sun_casts_shadows(scene);
+            for batch in self.batches.batches().filter(|_| casts_shadows) {
+                let Some(gpu_mesh) = self.mesh_manager.get_mesh(batch.mesh) else {
+                    continue;
+                };
+                shadow_pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+                shadow_pass.set_index_buffer(gpu_mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                for instance in &batch.instances {
+                    let push_constants = ShadowPushConstants { model: instance.model };
+                    shadow_pass.set_push_constants(
+                        wgpu::ShaderStages::VERTEX,
+                        0,
+                        bytemuck::cast_slice(&[push_constants]),
+                    );
+                    shadow_pass.draw_indexed(0..gpu_mesh.num_indices, 0, 0..1);
+                }
+            }
+        }
+
+        {
+            let mut sky_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Offscreen Skybox Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.framebuffer.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_texture,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            sky_pass.set_pipeline(&self.skybox.render_pipeline);
+            sky_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            sky_pass.set_bind_group(1, &self.skybox.bind_group, &[]);
+            sky_pass.draw(0..3, 0..1);
+        }
+
+        let environment = self.skybox.ibl.bind_group.clone();
+        self.renderer.render_batches(
+            &mut encoder,
+            &self.framebuffer.view,
+            None,
+            &self.depth_texture,
+            &self.batches,
+            &self.mesh_manager,
+            &self.material_manager,
+            view_proj,
+            camera.position,
+            &self.shadow_bind_group,
+            &environment,
+            false,
+        );
+
+        let device = &self.renderer.device;
+        let target_view = self.target.create_view(&wgpu::TextureViewDescriptor::default());
+        self.post_process.render(
+            device,
+            &mut encoder,
+            &self.framebuffer,
+            &self.bloom_chain,
+            &target_view,
+            &PostProcessSettings::default(),
+        );
+        let mut frame = FrameCapture::record(device, &mut encoder, &self.target, [0, 0], [width, height])
+            .context("The offscreen target can't be read back")?;
+        self.renderer.queue.submit(Some(encoder.finish()));
+        frame.after_submit();
+
+        device.poll(wgpu::PollType::wait_indefinitely())?;
+        let image = loop {
+            if let Some(image) = frame.poll(device) {
+                break image.context("Failed to read the frame back from the GPU")?;
+            }
+        };
+        capture::save_png(&image, path)
+    }
+
+    /// A scene material's GPU handle, loading it and its textures on first use
+    fn material(&mut self, material_path: &str) -> MaterialHandle {
+        if let Some(handle) = self.material_manager.get_handle(material_path) {
+            return handle;
+        }
+        let material = match self.asset_manager.load_material(material_path) {
+            Ok(handle) => handle.get().clone(),
+            Err(e) => {
+                log::warn!("Failed to load material '{}': {}, using default", material_path, e);
+                return self.material_manager.default_material_handle();
+            }
+        };
+
+        let (device, queue) = (&self.renderer.device, &self.renderer.queue);
+        let mut texture = |path: &Option<String>| {
+            let white = self.texture_manager.white_texture_handle();
+            let Some(path) = path.as_deref() else {
+                return white;
+            };
+            match self.asset_manager.load_texture(path) {
+                Ok(handle) => self
+                    .texture_manager
+                    .upload_texture(device, queue, path.to_string(), handle.get()),
+                Err(e) => {
+                    log::warn!("Failed to load texture '{}': {}", path, e);
+                    white
+                }
+            }
+        };
+        let albedo = texture(&material.albedo_texture);
+        let normal = texture(&material.normal_texture);
+        let metallic_roughness = texture(&material.metallic_roughness_texture);
+        let ao = texture(&material.ao_texture);
+
+        self.material_manager.upload_material(
+            device,
+            &self.texture_manager,
+            material_path.to_string(),
+            &material,
+            albedo,
+            Some(normal),
+            Some(metallic_roughness),
+            Some(ao),
+        )
+    }
+}
@@ -0,0 +1,284 @@
+// Placement helpers - grid and surface snapping for new and dragged entities
+//
+// Placement casts the cursor ray against terrain and mesh bounds (when surface
+// snapping is on), falling back to a horizontal plane, then snaps X/Z to the
+// inspector's position grid.
+
+use std::collections::HashSet;
+
+use engine_assets::{HeightMap, TerrainConfig};
+use engine_render::{camera::Camera, frustum::AABB};
+use engine_scene::{components::MeshRenderer, entity::EntityId, scene::Scene};
+use glam::Vec3;
+
+use crate::selection::{is_ancestor, world_position};
+use crate::ui::InspectorState;
+
+/// Terrain to raycast against, if the scene has one
+pub type TerrainRef<'a> = Option<(&'a HeightMap, &'a TerrainConfig)>;
+
+/// Pair up the editor's optional terrain heightmap and config
+pub fn terrain_ref<'a>(
+    heightmap: &'a Option<HeightMap>,
+    config: &'a Option<TerrainConfig>,
+) -> TerrainRef<'a> {
+    heightmap.as_ref().zip(config.as_ref())
+}
+
+/// Intersect a ray with the horizontal plane at height `y`
+pub fn ray_plane_y(ray_origin: Vec3, ray_direction: Vec3, y: f32) -> Option<Vec3> {
+    if ray_direction.y.abs() < 1e-4 {
+        return None;
+    }
+    let t = (y - ray_origin.y) / ray_direction.y;
+    (t > 0.0).then(|| ray_origin + ray_direction * t)
+}
+
+/// Closest point where the ray hits terrain or a mesh's bounds, ignoring `exclude`
+pub fn raycast_surface(
+    scene: &Scene,
+    ray_origin: Vec3,
+    ray_direction: Vec3,
+    terrain: TerrainRef,
+    exclude: &HashSet<EntityId>,
+) -> Option<Vec3> {
+    let mesh_hit = scene
+        .entities()
+        .filter(|e| e.has_component::<MeshRenderer>() && !exclude.contains(&e.id))
+        .filter_map(|e| {
+            // Same rough bounds as viewport picking: scale as half-extents
+            let aabb = AABB::from_center_extents(world_position(scene, e.id), e.transform.scale);
+            aabb.ray_intersect(ray_origin, ray_direction)
+        })
+        .filter(|&t| t > 0.0)
+        .min_by(|a, b| a.total_cmp(b))
+        .map(|t| ray_origin + ray_direction * t);
+
+    let terrain_hit = terrain.and_then(|(heightmap, config)| {
+        crate::raycast_terrain(ray_origin, ray_direction, heightmap, config)
+    });
+
+    match (mesh_hit, terrain_hit) {
+        (Some(a), Some(b)) => {
+            if a.distance_squared(ray_origin) <= b.distance_squared(ray_origin) {
+                Some(a)
+            } else {
+                Some(b)
+            }
+        }
+        (hit, None) | (None, hit) => hit,
+    }
+}
+
+/// Snap X/Z to the position grid if position snapping is enabled (Y follows the surface)
+pub fn snap_to_grid(position: Vec3, settings: &InspectorState) -> Vec3 {
+    if !settings.snap_position {
+        return position;
+    }
+    Vec3::new(
+        InspectorState::snap_value(position.x, settings.position_grid),
+        position.y,
+        InspectorState::snap_value(position.z, settings.position_grid),
+    )
+}
+
+/// Resolve where the cursor ray places an entity: on the surface under the
+/// cursor (if surface snapping is on), else on the plane at `plane_y`
+pub fn placement_point(
+    scene: &Scene,
+    ray_origin: Vec3,
+    ray_direction: Vec3,
+    terrain: TerrainRef,
+    exclude: &HashSet<EntityId>,
+    settings: &InspectorState,
+    plane_y: f32,
+) -> Option<Vec3> {
+    let surface_hit = if settings.snap_surface {
+        raycast_surface(scene, ray_origin, ray_direction, terrain, exclude)
+    } else {
+        None
+    };
+    surface_hit
+        .or_else(|| ray_plane_y(ray_origin, ray_direction, plane_y))
+        .map(|point| snap_to_grid(point, settings))
+}
+
+/// Where to place a new entity dropped at `cursor` (window pixels), on the
+/// ground plane if nothing is hit. Falls back to the snapped camera target.
+pub fn cursor_placement(
+    scene: &Scene,
+    camera: &Camera,
+    cursor: (f32, f32),
+    screen_size: (f32, f32),
+    terrain: TerrainRef,
+    settings: &InspectorState,
+) -> Vec3 {
+    let (ray_origin, ray_direction) =
+        camera.screen_to_ray(cursor.0, cursor.1, screen_size.0, screen_size.1);
+    placement_point(
+        scene,
+        ray_origin,
+        ray_direction,
+        terrain,
+        &HashSet::new(),
+        settings,
+        0.0,
+    )
+    .unwrap_or_else(|| snap_to_grid(camera.target, settings))
+}
+
+/// Move an entity so its world position is `world`, keeping its parent
+pub fn set_world_position(scene: &mut Scene, entity_id: EntityId, world: Vec3) {
+    let parent_world = scene
+        .get_entity(entity_id)
+        .and_then(|e| e.parent)
+        .map(|parent_id| scene.world_matrix(parent_id))
+        .unwrap_or(glam::Mat4::IDENTITY);
+    let local = parent_world.inverse().transform_point3(world);
+    if let Some(entity) = scene.get_entity_mut(entity_id) {
+        entity.transform.position = local;
+    }
+}
+
+/// An in-progress viewport drag of the selected entities
+pub struct EntityDrag {
+    /// World-space offset of each dragged root from the anchor (the entity clicked)
+    offsets: Vec<(EntityId, Vec3)>,
+    /// Dragged entities and their descendants (never used as a surface)
+    excluded: HashSet<EntityId>,
+    /// Height of the fallback drag plane (the anchor's starting height)
+    pub plane_y: f32,
+    /// Set once the entities have actually moved (undo step recorded)
+    pub moved: bool,
+}
+
+impl EntityDrag {
+    /// Start dragging `roots`, with `anchor` following the cursor
+    pub fn begin(scene: &Scene, anchor: EntityId, roots: &[EntityId]) -> Self {
+        let anchor_position = world_position(scene, anchor);
+        let offsets = roots
+            .iter()
+            .map(|&id| (id, world_position(scene, id) - anchor_position))
+            .collect();
+        let excluded = scene
+            .entities()
+            .map(|e| e.id)
+            .filter(|&id| {
+                roots
+                    .iter()
+                    .any(|&root| root == id || is_ancestor(scene, root, id))
+            })
+            .collect();
+        Self {
+            offsets,
+            excluded,
+            plane_y: anchor_position.y,
+            moved: false,
+        }
+    }
+
+    /// Entities the placement ray should ignore
+    pub fn excluded(&self) -> &HashSet<EntityId> {
+        &self.excluded
+    }
+
+    /// The dragged roots
+    pub fn roots(&self) -> Vec<EntityId> {
+        self.offsets.iter().map(|&(id, _)| id).collect()
+    }
+
+    /// Move the dragged entities so the anchor sits at `anchor_world`
+    pub fn move_to(&self, scene: &mut Scene, anchor_world: Vec3) {
+        for &(entity_id, offset) in &self.offsets {
+            set_world_position(scene, entity_id, anchor_world + offset);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_scene::transform::Transform;
+
+    #[test]
+    fn test_snap_keeps_height() {
+        let mut settings = InspectorState::default();
+        let point = Vec3::new(1.4, 2.3, -0.6);
+        assert_eq!(snap_to_grid(point, &settings), point);
+
+        settings.snap_position = true;
+        settings.position_grid = 0.5;
+        assert_eq!(snap_to_grid(point, &settings), Vec3::new(1.5, 2.3, -0.5));
+    }
+
+    #[test]
+    fn test_placement_falls_back_to_plane() {
+        let scene = Scene::new("Test".to_string());
+        let settings = InspectorState::default();
+        let origin = Vec3::new(0.0, 10.0, 0.0);
+        let hit = placement_point(
+            &scene,
+            origin,
+            Vec3::NEG_Y,
+            None,
+            &HashSet::new(),
+            &settings,
+            2.0,
+        );
+        assert_eq!(hit, Some(Vec3::new(0.0, 2.0, 0.0)));
+        assert!(ray_plane_y(origin, Vec3::Y, 2.0).is_none());
+    }
+
+    #[test]
+    fn test_placement_lands_on_mesh_unless_excluded() {
+        let mut scene = Scene::new("Test".to_string());
+        let block = scene.create_entity_with_transform(
+            "Block".to_string(),
+            Transform::from_position(Vec3::new(0.0, 1.0, 0.0)),
+        );
+        scene
+            .get_entity_mut(block)
+            .unwrap()
+            .add_component(MeshRenderer::new("cube".to_string()));
+
+        let settings = InspectorState::default();
+        let origin = Vec3::new(0.0, 10.0, 0.0);
+        let hit = placement_point(
+            &scene,
+            origin,
+            Vec3::NEG_Y,
+            None,
+            &HashSet::new(),
+            &settings,
+            0.0,
+        );
+        assert!((hit.unwrap() - Vec3::new(0.0, 2.0, 0.0)).length() < 1e-4);
+
+        let exclude: HashSet<EntityId> = [block].into_iter().collect();
+        let hit = placement_point(&scene, origin, Vec3::NEG_Y, None, &exclude, &settings, 0.0);
+        assert_eq!(hit, Some(Vec3::ZERO));
+    }
+
+    #[test]
+    fn test_drag_moves_children_with_parent_space() {
+        let mut scene = Scene::new("Test".to_string());
+        let parent = scene.create_entity_with_transform(
+            "Parent".to_string(),
+            Transform::from_position(Vec3::new(10.0, 0.0, 0.0)),
+        );
+        let child = scene.create_entity("Child".to_string());
+        scene.set_parent(child, Some(parent));
+        let other = scene.create_entity_with_transform(
+            "Other".to_string(),
+            Transform::from_position(Vec3::new(12.0, 0.0, 0.0)),
+        );
+
+        let drag = EntityDrag::begin(&scene, child, &[child, other]);
+        assert!(drag.excluded().contains(&child) && drag.excluded().contains(&other));
+        drag.move_to(&mut scene, Vec3::new(0.0, 0.0, 5.0));
+
+        assert!((world_position(&scene, child) - Vec3::new(0.0, 0.0, 5.0)).length() < 1e-4);
+        assert!((world_position(&scene, other) - Vec3::new(2.0, 0.0, 5.0)).length() < 1e-4);
+        assert_eq!(scene.get_entity(child).unwrap().parent, Some(parent));
+    }
+}
@@ -0,0 +1,215 @@
+// Play-in-editor mode - run the game simulation on a copy of the edited scene
+//
+// Pressing Play snapshots the authored scene and starts physics, scripts and
+// particles. Stop restores the snapshot so nothing the simulation did leaks
+// back into the scene being edited.
+
+use engine_assets::{AssetManager, HeightMap, TerrainConfig};
+use engine_physics::ColliderGeometry;
+use engine_render::camera::Camera;
+use engine_scene::{
+    components::Camera as CameraComponent, entity::EntityId, scene::Scene,
+    scene_data::SerializedScene,
+};
+use glam::{Quat, Vec3};
+
+/// Length of one stepped simulation frame: the project's physics timestep
+/// (1/60 s unless project.ron changes it)
+pub fn fixed_dt() -> f32 {
+    engine_core::project::current().physics.timestep
+}
+
+/// Geometry the scene's mesh and heightfield colliders are built from when
+/// physics is set up: meshes from the assets, the heightfield from `terrain`
+pub fn collider_geometry(
+    scene: &Scene,
+    asset_manager: &mut AssetManager,
+    terrain: impl FnOnce() -> Option<(HeightMap, TerrainConfig)>,
+) -> ColliderGeometry {
+    ColliderGeometry::for_scene(scene, |path| crate::lighting::cpu_mesh(asset_manager, path), terrain)
+}
+
+/// Simulation state of the editor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlayState {
+    /// Editing the authored scene, simulation stopped
+    #[default]
+    Editing,
+    /// Simulation running on a copy of the scene
+    Playing,
+    /// Simulation frozen, Stop still restores the authored scene
+    Paused,
+}
+
+impl PlayState {
+    /// True while physics, scripts and particles should advance
+    pub fn is_simulating(&self) -> bool {
+        *self == PlayState::Playing
+    }
+
+    /// True while a play session exists (playing or paused)
+    pub fn in_session(&self) -> bool {
+        *self != PlayState::Editing
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlayState::Editing => "editing",
+            PlayState::Playing => "playing",
+            PlayState::Paused => "paused",
+        }
+    }
+}
+
+/// Play mode transition requested from the toolbar or keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayRequest {
+    /// Start a session, or resume a paused one
+    Play,
+    /// Freeze the running simulation
+    Pause,
+    /// End the session and restore the authored scene
+    Stop,
+}
+
+/// State saved when entering play mode and restored on Stop
+pub struct PlaySession {
+    /// Authored scene at the moment Play was pressed
+    snapshot: SerializedScene,
+    /// Primary selection when Play was pressed
+    selected_entity: Option<EntityId>,
+    /// Editor camera lens (fov, near, far, orthographic), overridden by the game camera
+    editor_lens: (f32, f32, f32, bool),
+    /// Simulated seconds since Play
+    pub simulated_time: f32,
+    /// Simulation frames run since Play
+    pub simulated_frames: u64,
+}
+
+impl PlaySession {
+    /// Snapshot the scene and editor camera before the simulation starts
+    pub fn begin(scene: &Scene, camera: &Camera, selected_entity: Option<EntityId>) -> Self {
+        Self {
+            snapshot: scene.to_serialized(),
+            selected_entity,
+            editor_lens: (camera.fov, camera.near, camera.far, camera.orthographic),
+            simulated_time: 0.0,
+            simulated_frames: 0,
+        }
+    }
+
+    /// Count one simulation frame of `dt` seconds
+    pub fn advance(&mut self, dt: f32) {
+        self.simulated_time += dt;
+        self.simulated_frames += 1;
+    }
+
+    /// Restore the authored scene and editor camera lens.
+    /// Returns the selection to restore if the entity still exists.
+    pub fn end(self, scene: &mut Scene, camera: &mut Camera) -> Option<EntityId> {
+        *scene = Scene::from_serialized(self.snapshot);
+        (camera.fov, camera.near, camera.far, camera.orthographic) = self.editor_lens;
+        self.selected_entity
+            .filter(|&id| scene.get_entity(id).is_some())
+    }
+}
+
+/// Find the first active Camera component, returning its entity's world
+/// position and rotation
+pub fn find_game_camera(scene: &Scene) -> Option<(EntityId, Vec3, Quat)> {
+    let mut cameras: Vec<EntityId> = scene
+        .entities()
+        .filter(|e| {
+            e.get_component::<CameraComponent>()
+                .map(|c| c.is_active)
+                .unwrap_or(false)
+        })
+        .map(|e| e.id)
+        .collect();
+    cameras.sort_by_key(|id| id.0);
+
+    let id = *cameras.first()?;
+    let (_, rotation, position) = scene.world_matrix(id).to_scale_rotation_translation();
+    Some((id, position, rotation))
+}
+
+/// Point the render camera through the scene's active game camera.
+/// Returns false if the scene has no active Camera component.
+pub fn apply_game_camera(scene: &Scene, camera: &mut Camera) -> bool {
+    let Some((id, position, rotation)) = find_game_camera(scene) else {
+        return false;
+    };
+    let Some(lens) = scene
+        .get_entity(id)
+        .and_then(|e| e.get_component::<CameraComponent>())
+    else {
+        return false;
+    };
+
+    // Cameras look down -Z in local space
+    camera.position = position;
+    camera.target = position + rotation * Vec3::NEG_Z;
+    camera.up = rotation * Vec3::Y;
+    camera.fov = lens.fov;
+    camera.near = lens.near;
+    camera.far = lens.far;
+    camera.orthographic = false;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_scene::transform::Transform;
+
+    #[test]
+    fn test_session_restores_scene() {
+        let mut scene = Scene::new("Test".to_string());
+        let id = scene.create_entity_with_transform(
+            "Crate".to_string(),
+            Transform::from_position(Vec3::new(0.0, 5.0, 0.0)),
+        );
+        let mut camera = Camera::new(800, 600);
+        let session = PlaySession::begin(&scene, &camera, Some(id));
+
+        // Simulation moves the entity and adds a new one
+        scene.get_entity_mut(id).unwrap().transform.position.y = 0.0;
+        scene.create_entity("Spawned".to_string());
+        camera.fov = 1.0;
+
+        assert_eq!(session.end(&mut scene, &mut camera), Some(id));
+        assert_eq!(scene.entity_count(), 1);
+        assert_eq!(scene.get_entity(id).unwrap().transform.position.y, 5.0);
+        assert_eq!(camera.fov, 60.0_f32.to_radians());
+    }
+
+    #[test]
+    fn test_game_camera_uses_active_component() {
+        let mut scene = Scene::new("Test".to_string());
+        assert!(find_game_camera(&scene).is_none());
+
+        let inactive = scene.create_entity("Inactive".to_string());
+        let component = CameraComponent {
+            is_active: false,
+            ..Default::default()
+        };
+        scene
+            .get_entity_mut(inactive)
+            .unwrap()
+            .add_component(component);
+
+        let active = scene.create_entity_with_transform(
+            "Main Camera".to_string(),
+            Transform::from_position(Vec3::new(0.0, 2.0, 10.0)),
+        );
+        scene
+            .get_entity_mut(active)
+            .unwrap()
+            .add_component(CameraComponent::default());
+
+        let mut camera = Camera::new(800, 600);
+        assert!(apply_game_camera(&scene, &mut camera));
+        assert_eq!(camera.position, Vec3::new(0.0, 2.0, 10.0));
+        assert!((camera.target - Vec3::new(0.0, 2.0, 9.0)).length() < 1e-5);
+    }
+}
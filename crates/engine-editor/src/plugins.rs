@@ -0,0 +1,35 @@
+// Engine plugins linked into the editor
+//
+// Add a plugin to loader() and the editor, headless runs and dedicated
+// servers all load it:
+//
+//   pub fn loader() -> PluginLoader {
+//       PluginLoader::new().with_plugin(my_game::WeatherPlugin)
+//   }
+//
+// Its components become available to the MCP component tools, its systems
+// run every simulation step, its render passes draw over the viewport, its
+// panels appear as editor windows and its MCP tools are listed next to the
+// built-in ones. plugins.json in the project directory disables plugins by
+// name.
+//
+// Gameplay code can also live in native modules: dylibs listed under
+// native_modules in project.ron. They are loaded at startup and the editor
+// reloads one whenever it is rebuilt, without restarting.
+
+use engine_plugin::{NativeModules, PluginLoader, PluginRegistry};
+
+/// Every plugin this build knows about
+pub fn loader() -> PluginLoader {
+    PluginLoader::new()
+}
+
+/// Build the plugins enabled for the project in the working directory
+pub fn load() -> PluginRegistry {
+    loader().load_project(".")
+}
+
+/// Load the project's native modules
+pub fn native_modules() -> NativeModules {
+    NativeModules::load_all(&engine_core::project::current().native_modules)
+}
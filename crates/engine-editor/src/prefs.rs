@@ -0,0 +1,367 @@
+// Editor preferences - settings that persist between sessions
+//
+// Stored as JSON in the platform config directory, e.g.
+// ~/.config/causality/editor_prefs.json on Linux. Missing or unknown fields
+// fall back to defaults so older files keep loading.
+
+use anyhow::Result;
+use engine_core::frame_pacing::{FramePacing, PresentMode};
+use engine_render::{AntiAliasingMode, WaterReflectionQuality};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::ui::{BrushTool, EditorUi, GameViewState, InspectorState};
+
+const PREFS_FILE: &str = "editor_prefs.json";
+
+/// Which editor panels are open
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PanelLayout {
+    pub hierarchy: bool,
+    pub inspector: bool,
+    pub console: bool,
+    pub asset_browser: bool,
+    pub brush_panel: bool,
+    pub statistics: bool,
+    pub game_view: bool,
+    pub navigation: bool,
+    pub lighting: bool,
+    pub timeline: bool,
+    pub grid: bool,
+    pub entity_icons: bool,
+    pub render_stats: bool,
+}
+
+impl Default for PanelLayout {
+    fn default() -> Self {
+        Self {
+            hierarchy: true,
+            inspector: true,
+            console: true,
+            asset_browser: false,
+            brush_panel: false,
+            statistics: false,
+            game_view: true,
+            navigation: false,
+            lighting: false,
+            timeline: false,
+            grid: true,
+            entity_icons: true,
+            render_stats: false,
+        }
+    }
+}
+
+/// Brush settings restored on startup (the brush mode always starts at Select)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BrushDefaults {
+    pub radius: f32,
+    pub density: f32,
+    pub scale_min: f32,
+    pub scale_max: f32,
+    pub random_rotation: bool,
+    pub terrain_strength: f32,
+    pub terrain_hardness: f32,
+    pub paint_layer: usize,
+    pub paint_opacity: f32,
+    pub paint_falloff: f32,
+    pub water_flow_speed: f32,
+}
+
+impl Default for BrushDefaults {
+    fn default() -> Self {
+        Self::from(&BrushTool::default())
+    }
+}
+
+impl From<&BrushTool> for BrushDefaults {
+    fn from(brush: &BrushTool) -> Self {
+        Self {
+            radius: brush.radius,
+            density: brush.density,
+            scale_min: brush.scale_min,
+            scale_max: brush.scale_max,
+            random_rotation: brush.random_rotation,
+            terrain_strength: brush.terrain_strength,
+            terrain_hardness: brush.terrain_hardness,
+            paint_layer: brush.paint_layer,
+            paint_opacity: brush.paint_opacity,
+            paint_falloff: brush.paint_falloff,
+            water_flow_speed: brush.water_flow_speed,
+        }
+    }
+}
+
+impl BrushDefaults {
+    fn apply(&self, brush: &mut BrushTool) {
+        brush.radius = self.radius;
+        brush.density = self.density;
+        brush.scale_min = self.scale_min;
+        brush.scale_max = self.scale_max;
+        brush.random_rotation = self.random_rotation;
+        brush.terrain_strength = self.terrain_strength;
+        brush.terrain_hardness = self.terrain_hardness;
+        brush.paint_layer = self.paint_layer;
+        brush.paint_opacity = self.paint_opacity;
+        brush.paint_falloff = self.paint_falloff;
+        brush.water_flow_speed = self.water_flow_speed;
+    }
+}
+
+/// Persistent editor preferences
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EditorPrefs {
+    pub recent_files: Vec<String>,
+    pub panels: PanelLayout,
+    pub brush: BrushDefaults,
+    pub snap: InspectorState,
+    pub game_view: GameViewState,
+    /// Multiplier for viewport pan/zoom and player movement
+    pub camera_speed: f32,
+    /// MSAA sample count (1 = off), applied on the next launch
+    pub msaa_samples: u32,
+    /// MSAA, FXAA or TAA (switching MSAA on or off applies on the next launch)
+    pub anti_aliasing: AntiAliasingMode,
+    /// Resolution of the planar water reflections
+    pub water_reflections: WaterReflectionQuality,
+    /// Present mode, frame rate cap and fixed-update settings
+    pub frame_pacing: FramePacing,
+    /// Docked panel arrangement (egui_dock state as RON text, which keeps the
+    /// infinite rects of panels that haven't been laid out yet)
+    pub dock_layout: Option<serde_json::Value>,
+}
+
+impl Default for EditorPrefs {
+    fn default() -> Self {
+        let project = engine_core::project::current();
+        Self {
+            recent_files: Vec::new(),
+            panels: PanelLayout::default(),
+            brush: BrushDefaults::default(),
+            snap: InspectorState::default(),
+            game_view: GameViewState::default(),
+            camera_speed: 1.0,
+            // New installs start from the project's settings
+            msaa_samples: project.rendering.msaa_samples,
+            anti_aliasing: AntiAliasingMode::default(),
+            water_reflections: WaterReflectionQuality::default(),
+            frame_pacing: FramePacing::default(),
+            dock_layout: None,
+        }
+    }
+}
+
+impl EditorPrefs {
+    /// Preferences file in the platform config directory
+    pub fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("causality").join(PREFS_FILE))
+    }
+
+    /// Load preferences, falling back to defaults if the file is missing or invalid
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        if !path.exists() {
+            return Self::default();
+        }
+        match Self::load_from(&path) {
+            Ok(prefs) => {
+                log::info!("Loaded editor preferences from {:?}", path);
+                prefs
+            }
+            Err(e) => {
+                log::warn!("Failed to load editor preferences from {:?}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let value: serde_json::Value = serde_json::from_str(&contents)?;
+        // Files from before frame pacing only had a vsync flag; off presented immediately
+        let old_vsync = value
+            .get("vsync")
+            .and_then(serde_json::Value::as_bool)
+            .filter(|_| value.get("frame_pacing").is_none());
+        let mut prefs: Self = serde_json::from_value(value)?;
+        if old_vsync == Some(false) {
+            prefs.frame_pacing.present_mode = PresentMode::Immediate;
+        }
+        Ok(prefs)
+    }
+
+    /// Save to the platform config directory
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path().ok_or_else(|| anyhow::anyhow!("No config directory found"))?;
+        self.save_to(&path)
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Collect the persistent settings from the editor UI
+    pub fn capture(ui: &EditorUi) -> Self {
+        Self {
+            recent_files: ui.recent_files.clone(),
+            panels: PanelLayout {
+                hierarchy: ui.show_hierarchy,
+                inspector: ui.show_inspector,
+                console: ui.show_console,
+                asset_browser: ui.show_asset_browser,
+                brush_panel: ui.show_brush_panel,
+                statistics: ui.show_statistics,
+                game_view: ui.show_game_view,
+                navigation: ui.show_navigation,
+                lighting: ui.show_lighting,
+                timeline: ui.show_timeline,
+                grid: ui.show_grid,
+                entity_icons: ui.show_entity_icons,
+                render_stats: ui.show_render_stats,
+            },
+            brush: BrushDefaults::from(&ui.brush_tool),
+            snap: ui.inspector_state.clone(),
+            game_view: ui.game_view_state.clone(),
+            camera_speed: ui.camera_speed,
+            msaa_samples: ui.msaa_samples,
+            anti_aliasing: ui.anti_aliasing,
+            water_reflections: ui.water_reflections,
+            frame_pacing: ui.frame_pacing,
+            dock_layout: ron::to_string(&ui.dock_state).ok().map(serde_json::Value::String),
+        }
+    }
+
+    /// Restore the settings into the editor UI
+    pub fn apply(&self, ui: &mut EditorUi) {
+        ui.recent_files = self.recent_files.clone();
+        ui.recent_files.truncate(ui.max_recent_files);
+        ui.show_hierarchy = self.panels.hierarchy;
+        ui.show_inspector = self.panels.inspector;
+        ui.show_console = self.panels.console;
+        ui.show_asset_browser = self.panels.asset_browser;
+        ui.show_brush_panel = self.panels.brush_panel;
+        ui.show_statistics = self.panels.statistics;
+        ui.show_game_view = self.panels.game_view;
+        ui.show_navigation = self.panels.navigation;
+        ui.show_lighting = self.panels.lighting;
+        ui.show_timeline = self.panels.timeline;
+        ui.show_grid = self.panels.grid;
+        ui.show_entity_icons = self.panels.entity_icons;
+        ui.show_render_stats = self.panels.render_stats;
+        self.brush.apply(&mut ui.brush_tool);
+        ui.inspector_state = self.snap.clone();
+        ui.game_view_state = self.game_view.clone();
+        ui.camera_speed = self.camera_speed;
+        ui.msaa_samples = self.msaa_samples;
+        ui.anti_aliasing = self.anti_aliasing;
+        ui.water_reflections = self.water_reflections;
+        ui.frame_pacing = self.frame_pacing;
+        if let Some(layout) = &self.dock_layout {
+            let dock_state = match layout {
+                serde_json::Value::String(text) => ron::from_str(text).map_err(|e| e.to_string()),
+                _ => serde_json::from_value(layout.clone()).map_err(|e| e.to_string()),
+            };
+            match dock_state {
+                Ok(dock_state) => ui.dock_state = dock_state,
+                Err(e) => log::warn!("Ignoring saved panel layout: {}", e),
+            }
+        }
+    }
+}
+
+/// Platform config directory (%APPDATA%, ~/Library/Application Support, or $XDG_CONFIG_HOME)
+fn config_dir() -> Option<PathBuf> {
+    let env_dir = |name: &str| {
+        std::env::var_os(name)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    };
+    if cfg!(target_os = "windows") {
+        env_dir("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env_dir("XDG_CONFIG_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".config")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::EditorTab;
+
+    #[test]
+    fn test_prefs_round_trip_through_ui() {
+        let mut ui = EditorUi::new();
+        ui.add_recent_file("assets/scenes/castle.ron".to_string());
+        ui.show_console = false;
+        ui.brush_tool.radius = 12.0;
+        ui.inspector_state.snap_position = true;
+        ui.camera_speed = 2.5;
+        ui.frame_pacing.present_mode = PresentMode::Immediate;
+        ui.frame_pacing.fps_cap = Some(144);
+        ui.anti_aliasing = AntiAliasingMode::Taa;
+        ui.water_reflections = WaterReflectionQuality::Low;
+        ui.show_statistics = true;
+        crate::ui::dock::set_tab_open(&mut ui.dock_state, EditorTab::Profiler, true);
+
+        let prefs = EditorPrefs::capture(&ui);
+        let path =
+            std::env::temp_dir().join(format!("causality_prefs_test_{}", std::process::id()));
+        prefs.save_to(&path.join(PREFS_FILE)).unwrap();
+        let loaded = EditorPrefs::load_from(&path.join(PREFS_FILE)).unwrap();
+        std::fs::remove_dir_all(&path).ok();
+        assert_eq!(loaded, prefs);
+
+        let mut restored = EditorUi::new();
+        loaded.apply(&mut restored);
+        assert_eq!(
+            restored.recent_files,
+            vec!["assets/scenes/castle.ron".to_string()]
+        );
+        assert!(!restored.show_console);
+        assert_eq!(restored.brush_tool.radius, 12.0);
+        assert!(restored.inspector_state.snap_position);
+        assert_eq!(restored.camera_speed, 2.5);
+        assert_eq!(restored.frame_pacing.present_mode, PresentMode::Immediate);
+        assert_eq!(restored.frame_pacing.fps_cap, Some(144));
+        assert_eq!(restored.anti_aliasing, AntiAliasingMode::Taa);
+        assert_eq!(restored.water_reflections, WaterReflectionQuality::Low);
+        assert!(crate::ui::dock::is_tab_open(&restored.dock_state, EditorTab::Profiler));
+    }
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        let prefs: EditorPrefs = serde_json::from_str(r#"{ "camera_speed": 3.0 }"#).unwrap();
+        assert_eq!(prefs.camera_speed, 3.0);
+        assert_eq!(prefs.panels, PanelLayout::default());
+        assert_eq!(prefs.msaa_samples, engine_render::MSAA_SAMPLE_COUNT);
+        assert_eq!(prefs.anti_aliasing, AntiAliasingMode::Msaa);
+        assert_eq!(prefs.frame_pacing, FramePacing::default());
+    }
+
+    #[test]
+    fn test_old_vsync_pref_becomes_present_mode() {
+        let path = std::env::temp_dir().join(format!(
+            "causality_prefs_vsync_test_{}.json",
+            std::process::id()
+        ));
+        std::fs::write(&path, r#"{ "camera_speed": 2.0, "vsync": false }"#).unwrap();
+        let prefs = EditorPrefs::load_from(&path).unwrap();
+        assert_eq!(prefs.frame_pacing.present_mode, PresentMode::Immediate);
+
+        std::fs::write(&path, r#"{ "vsync": true }"#).unwrap();
+        let prefs = EditorPrefs::load_from(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(prefs.frame_pacing.present_mode, PresentMode::Vsync);
+    }
+}
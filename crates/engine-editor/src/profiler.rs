@@ -0,0 +1,305 @@
+// Profiler - per-system CPU timings and GPU pass timings
+//
+// The app times its systems each frame with a FrameTimer and hands the spans,
+// together with the latest GPU timings read back from the renderer, to the
+// Profiler. It keeps a rolling history for the Profiler tab; pausing it
+// captures the current history so frames can be inspected and exported as
+// Chrome trace JSON (chrome://tracing, Perfetto). For deeper digging, a
+// trace capture (engine_core::trace) records every tracing span on every
+// thread over a frame range.
+
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use engine_render::GpuTiming;
+use serde_json::json;
+
+/// Frames kept in the rolling history
+pub const HISTORY_FRAMES: usize = 240;
+
+/// Frames a trace capture records unless changed in the panel
+pub const DEFAULT_TRACE_FRAMES: u32 = 120;
+
+/// One timed section of a frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub name: &'static str,
+    /// Offset from the start of the frame
+    pub start_ms: f32,
+    pub duration_ms: f32,
+    /// Nesting level (0 = top level)
+    pub depth: u32,
+}
+
+/// Timings recorded for one frame
+#[derive(Debug, Clone)]
+pub struct FrameProfile {
+    pub index: u64,
+    pub cpu_ms: f32,
+    pub cpu: Vec<Span>,
+    pub gpu_ms: f32,
+    pub gpu: Vec<Span>,
+}
+
+/// Collects CPU spans while a frame runs
+pub struct FrameTimer {
+    start: Instant,
+    spans: Vec<Span>,
+}
+
+impl Default for FrameTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameTimer {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            spans: Vec::new(),
+        }
+    }
+
+    /// Record a span from `start` until now. Spans recorded inside another
+    /// span's time range are nested under it.
+    pub fn record(&mut self, name: &'static str, start: Instant) {
+        self.record_duration(name, start, start.elapsed());
+    }
+
+    /// Record a span that has already been timed (e.g. a system that ran
+    /// on another thread)
+    pub fn record_duration(&mut self, name: &'static str, start: Instant, duration: Duration) {
+        self.spans.push(Span {
+            name,
+            start_ms: start.saturating_duration_since(self.start).as_secs_f32() * 1000.0,
+            duration_ms: duration.as_secs_f32() * 1000.0,
+            depth: 0,
+        });
+    }
+
+    /// Total frame time and the spans sorted by start, with nesting depths
+    fn finish(mut self) -> (f32, Vec<Span>) {
+        let total_ms = self.start.elapsed().as_secs_f32() * 1000.0;
+        // Outer spans first when two start together
+        self.spans.sort_by(|a, b| {
+            a.start_ms
+                .total_cmp(&b.start_ms)
+                .then(b.duration_ms.total_cmp(&a.duration_ms))
+        });
+        for i in 0..self.spans.len() {
+            let (before, rest) = self.spans.split_at_mut(i);
+            let span = &mut rest[0];
+            let end = span.start_ms + span.duration_ms;
+            span.depth = before
+                .iter()
+                .filter(|outer| outer.start_ms + outer.duration_ms >= end)
+                .count() as u32;
+        }
+        (total_ms, self.spans)
+    }
+}
+
+/// Rolling history of frame profiles
+pub struct Profiler {
+    history: VecDeque<FrameProfile>,
+    next_index: u64,
+    /// Stop recording so the captured frames can be inspected
+    pub paused: bool,
+    /// Frame shown in the flame view (None = latest)
+    pub selected: Option<u64>,
+    /// Length of the next trace capture
+    pub trace_frames: u32,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            history: VecDeque::with_capacity(HISTORY_FRAMES),
+            next_index: 0,
+            paused: false,
+            selected: None,
+            trace_frames: DEFAULT_TRACE_FRAMES,
+        }
+    }
+
+    /// Store a finished frame (dropped while paused)
+    pub fn end_frame(&mut self, timer: FrameTimer, gpu: &[GpuTiming]) {
+        if self.paused {
+            return;
+        }
+        let (cpu_ms, cpu) = timer.finish();
+        let gpu: Vec<Span> = gpu
+            .iter()
+            .map(|timing| Span {
+                name: timing.label,
+                start_ms: timing.start_ms,
+                duration_ms: timing.duration_ms,
+                depth: 0,
+            })
+            .collect();
+        let gpu_ms = gpu.iter().map(|span| span.duration_ms).sum();
+
+        if self.history.len() == HISTORY_FRAMES {
+            self.history.pop_front();
+        }
+        self.history.push_back(FrameProfile {
+            index: self.next_index,
+            cpu_ms,
+            cpu,
+            gpu_ms,
+            gpu,
+        });
+        self.next_index += 1;
+    }
+
+    pub fn history(&self) -> &VecDeque<FrameProfile> {
+        &self.history
+    }
+
+    /// The selected frame, or the latest one
+    pub fn current_frame(&self) -> Option<&FrameProfile> {
+        self.selected
+            .and_then(|index| self.history.iter().find(|frame| frame.index == index))
+            .or_else(|| self.history.back())
+    }
+
+    /// Average CPU time per system over the history, slowest first
+    pub fn averages(&self) -> Vec<(&'static str, f32)> {
+        let mut totals: Vec<(&'static str, f32)> = Vec::new();
+        for span in self.history.iter().flat_map(|frame| &frame.cpu) {
+            match totals.iter_mut().find(|(name, _)| *name == span.name) {
+                Some((_, total)) => *total += span.duration_ms,
+                None => totals.push((span.name, span.duration_ms)),
+            }
+        }
+        let frames = self.history.len().max(1) as f32;
+        for (_, total) in &mut totals {
+            *total /= frames;
+        }
+        totals.sort_by(|a, b| b.1.total_cmp(&a.1));
+        totals
+    }
+
+    pub fn clear(&mut self) {
+        self.history.clear();
+        self.selected = None;
+    }
+
+    /// The history as Chrome trace events (CPU on thread 1, GPU on thread 2)
+    pub fn to_chrome_trace(&self) -> serde_json::Value {
+        let mut events = Vec::new();
+        let mut frame_start_us = 0.0_f64;
+        for frame in &self.history {
+            let spans = frame
+                .cpu
+                .iter()
+                .map(|span| (1, span))
+                .chain(frame.gpu.iter().map(|span| (2, span)));
+            for (tid, span) in spans {
+                events.push(json!({
+                    "name": span.name,
+                    "cat": if tid == 1 { "cpu" } else { "gpu" },
+                    "ph": "X",
+                    "ts": frame_start_us + span.start_ms as f64 * 1000.0,
+                    "dur": span.duration_ms as f64 * 1000.0,
+                    "pid": 1,
+                    "tid": tid,
+                    "args": { "frame": frame.index },
+                }));
+            }
+            frame_start_us += frame.cpu_ms.max(frame.gpu_ms) as f64 * 1000.0;
+        }
+        json!({ "traceEvents": events, "displayTimeUnit": "ms" })
+    }
+
+    /// Write the history to a Chrome trace JSON file
+    pub fn export(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&self.to_chrome_trace())?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn span(name: &'static str, start_ms: f32, duration_ms: f32) -> Span {
+        Span {
+            name,
+            start_ms,
+            duration_ms,
+            depth: 0,
+        }
+    }
+
+    #[test]
+    fn test_nested_spans_get_depth() {
+        let timer = FrameTimer {
+            start: Instant::now() - Duration::from_millis(12),
+            spans: vec![
+                span("Scripts", 1.0, 2.0),
+                span("Simulation", 1.0, 5.0),
+                span("Physics", 3.0, 3.0),
+                span("Render", 7.0, 4.0),
+            ],
+        };
+        let (total_ms, spans) = timer.finish();
+        assert!(total_ms >= 12.0);
+
+        let depth = |name| spans.iter().find(|s| s.name == name).unwrap().depth;
+        assert_eq!(depth("Simulation"), 0);
+        assert_eq!(depth("Scripts"), 1);
+        assert_eq!(depth("Physics"), 1);
+        assert_eq!(depth("Render"), 0);
+    }
+
+    #[test]
+    fn test_history_is_bounded_and_pausable() {
+        let mut profiler = Profiler::new();
+        let gpu = [GpuTiming {
+            label: "Meshes",
+            start_ms: 0.0,
+            duration_ms: 1.5,
+        }];
+        for _ in 0..HISTORY_FRAMES + 10 {
+            profiler.end_frame(FrameTimer::new(), &gpu);
+        }
+        assert_eq!(profiler.history().len(), HISTORY_FRAMES);
+        assert_eq!(profiler.history().front().unwrap().index, 10);
+        assert_eq!(profiler.current_frame().unwrap().gpu_ms, 1.5);
+
+        profiler.paused = true;
+        profiler.end_frame(FrameTimer::new(), &[]);
+        assert_eq!(
+            profiler.current_frame().unwrap().index,
+            HISTORY_FRAMES as u64 + 9
+        );
+    }
+
+    #[test]
+    fn test_chrome_trace_export() {
+        let mut profiler = Profiler::new();
+        let mut timer = FrameTimer::new();
+        timer.record("Physics", Instant::now());
+        profiler.end_frame(timer, &[]);
+
+        let trace = profiler.to_chrome_trace();
+        let events = trace["traceEvents"].as_array().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["name"], "Physics");
+        assert_eq!(events[0]["ph"], "X");
+    }
+}
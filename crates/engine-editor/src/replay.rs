@@ -0,0 +1,239 @@
+// Replays - record a deterministic play session and reproduce it
+//
+// With determinism on in project.ron, Play records a Replay: the scene as
+// it was when Play was pressed, the session's random seed, the fixed
+// timestep and, for every simulation frame, the game seconds it stepped,
+// the game input scripts saw and a checksum of every entity's transform
+// after the frame. Stop writes it to replays/. Headless runs can record one
+// too (--record). Scripts and physics components, which scene files don't
+// carry, go into the replay's scene as Generic components.
+//
+//   editor --replay replays/replay_1712345678.ron
+//
+// runs the recorded scene again with the recorded seed and input and
+// reports the first frame whose checksum differs, so a bug report can
+// carry a file that reproduces the session exactly.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use engine_scene::scene::Scene;
+use engine_scene::scene_data::{SerializedComponent, SerializedScene};
+use engine_scripting::GameInput;
+use serde::{Deserialize, Serialize};
+
+use crate::component_registry::ComponentRegistry;
+
+/// Format version written into replays (2 added each frame's dt)
+pub const REPLAY_VERSION: u32 = 2;
+
+/// Components outside engine-scene's scene format that a replay needs to
+/// run its scene again, by component registry name
+const EXTENSION_COMPONENTS: [&str; 4] = ["Script", "RigidBody", "Collider", "CharacterController"];
+
+/// Where the editor saves replays, relative to the project root
+pub const REPLAY_DIR: &str = "replays";
+
+/// One simulation frame of a replay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    /// Game seconds the frame stepped (0 while the game was paused)
+    #[serde(default)]
+    pub dt: f32,
+    pub input: GameInput,
+    /// scene_checksum() after the frame
+    pub checksum: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    /// Seed of the session's SimRng
+    pub seed: u64,
+    /// The project's physics timestep when the session was recorded
+    pub timestep: f32,
+    /// The scene when the session started
+    pub scene: SerializedScene,
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    /// Start recording a session that begins with `scene`
+    pub fn new(scene: &Scene, seed: u64, timestep: f32) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            seed,
+            timestep,
+            scene: serialize_with_extensions(scene),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Add a frame that stepped `dt` seconds with `input` and left the scene
+    /// as it is
+    pub fn record(&mut self, dt: f32, input: &GameInput, scene: &Scene) {
+        self.frames.push(ReplayFrame {
+            dt,
+            input: input.clone(),
+            checksum: scene_checksum(scene),
+        });
+    }
+
+    /// The scene the session started from, scripts and physics included
+    pub fn start_scene(&self) -> Result<Scene> {
+        let mut scene = Scene::from_serialized(self.scene.clone());
+        let registry = ComponentRegistry::with_engine_components();
+        for serialized in self.scene.entities.values() {
+            let entity = scene
+                .get_entity_mut(serialized.id)
+                .ok_or_else(|| anyhow!("Replay entity {} is missing", serialized.id.0))?;
+            for component in &serialized.components {
+                if let SerializedComponent::Generic { component_type, data } = component {
+                    let value: serde_json::Value = ron::de::from_str(data)
+                        .with_context(|| format!("Invalid {} in replay", component_type))?;
+                    registry.find(component_type)?.set(entity, value)?;
+                }
+            }
+        }
+        Ok(scene)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read replay {}", path.display()))?;
+        let mut replay: Replay = ron::de::from_str(&text)
+            .with_context(|| format!("Failed to parse replay {}", path.display()))?;
+        if replay.version < 2 {
+            // Version 1 frames all stepped the timestep
+            for frame in &mut replay.frames {
+                frame.dt = replay.timestep;
+            }
+        }
+        if replay.version > REPLAY_VERSION {
+            bail!(
+                "Replay format version {} is newer than this engine supports ({})",
+                replay.version,
+                REPLAY_VERSION
+            );
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, ron::ser::to_string(self)?)
+            .with_context(|| format!("Failed to write replay {}", path.display()))
+    }
+
+    /// Save under REPLAY_DIR with a timestamped name
+    pub fn save_new(&self) -> Result<PathBuf> {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let path = Path::new(REPLAY_DIR).join(format!("replay_{}.ron", timestamp));
+        self.save(&path)?;
+        Ok(path)
+    }
+}
+
+/// `scene` in scene format, with EXTENSION_COMPONENTS added as Generic
+/// components
+fn serialize_with_extensions(scene: &Scene) -> SerializedScene {
+    let registry = ComponentRegistry::with_engine_components();
+    let mut serialized = scene.to_serialized();
+    for (id, entity) in &mut serialized.entities {
+        let Some(source) = scene.get_entity(*id) else {
+            continue;
+        };
+        for name in EXTENSION_COMPONENTS {
+            let value = registry
+                .find(name)
+                .ok()
+                .and_then(|component_type| component_type.get(source));
+            if let Some(data) = value.and_then(|value| ron::ser::to_string(&value).ok()) {
+                entity.components.push(SerializedComponent::Generic {
+                    component_type: name.to_string(),
+                    data,
+                });
+            }
+        }
+    }
+    serialized
+}
+
+/// Hash of every entity's transform, exact to the bit
+pub fn scene_checksum(scene: &Scene) -> u64 {
+    // FNV-1a
+    let mut hash: u64 = 0xCBF2_9CE4_8422_2325;
+    let mut feed = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01B3);
+        }
+    };
+    let mut entities: Vec<_> = scene.entities().collect();
+    entities.sort_by_key(|entity| entity.id.0);
+    for entity in entities {
+        let t = &entity.transform;
+        feed(&entity.id.0.to_le_bytes());
+        for value in t
+            .position
+            .to_array()
+            .into_iter()
+            .chain(t.rotation.to_array())
+            .chain(t.scale.to_array())
+        {
+            feed(&value.to_bits().to_le_bytes());
+        }
+    }
+    hash
+}
+
+/// How a replay's playback compared with the recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayCheck {
+    pub frames: usize,
+    /// First frame (from 1) whose checksum differed
+    pub first_mismatch: Option<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_scene::transform::Transform;
+    use glam::Vec3;
+
+    #[test]
+    fn test_checksum_and_save_round_trip() {
+        let mut scene = Scene::new("Test".to_string());
+        let id = scene.create_entity_with_transform(
+            "Box".to_string(),
+            Transform::from_position(Vec3::new(1.0, 2.0, 3.0)),
+        );
+        let before = scene_checksum(&scene);
+        assert_eq!(before, scene_checksum(&scene));
+
+        let mut replay = Replay::new(&scene, 7, 1.0 / 60.0);
+        let mut input = GameInput::default();
+        input.press_key("KeyW");
+        scene.get_entity_mut(id).unwrap().transform.position.x += 1e-6;
+        replay.record(1.0 / 60.0, &input, &scene);
+        assert_ne!(replay.frames[0].checksum, before);
+
+        let path =
+            std::env::temp_dir().join(format!("causality-replay-{}.ron", std::process::id()));
+        replay.save(&path).unwrap();
+        let loaded = Replay::load(&path).unwrap();
+        assert_eq!(loaded.seed, 7);
+        assert_eq!(loaded.frames[0].checksum, replay.frames[0].checksum);
+        assert!(loaded.frames[0].input.keys_down.contains("KeyW"));
+        assert_eq!(
+            scene_checksum(&Scene::from_serialized(loaded.scene)),
+            before
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+}
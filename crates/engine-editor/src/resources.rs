@@ -0,0 +1,195 @@
+// MCP resources - scene files, scripts and generated images by URI
+//
+// The editor maps `causality://` URIs to files and the MCP server reads
+// them, the same split capture_viewport uses for screenshots:
+//   causality://assets/<path>     a file under the asset root
+//   causality://scripts/<path>    a file under ./scripts
+//   causality://generated/<id>    an image generate_texture or generate_skybox made
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Result};
+use engine_ai_assets::AssetCache;
+use serde_json::{json, Value};
+
+use crate::material_tools::{asset_file, GENERATED_ASSETS_DIR};
+use crate::ui::asset_browser::{search_assets, AssetKind};
+
+pub const RESOURCE_SCHEME: &str = "causality://";
+
+/// Where resources live on disk
+pub struct ResourceRoots<'a> {
+    pub asset_root: &'a Path,
+    pub scripts_dir: PathBuf,
+}
+
+impl<'a> ResourceRoots<'a> {
+    /// The asset root plus `scripts/` in the working directory
+    pub fn new(asset_root: &'a Path) -> Self {
+        Self {
+            asset_root,
+            scripts_dir: PathBuf::from("scripts"),
+        }
+    }
+}
+
+/// MIME type reported for a file
+pub fn mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+    match extension.as_deref() {
+        Some("ron") => "text/x-ron",
+        Some("rhai") => "text/x-rhai",
+        Some("json") => "application/json",
+        Some("mat" | "yaml" | "yml") => "text/yaml",
+        Some("txt" | "md") => "text/plain",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("wav") => "audio/wav",
+        Some("ogg") => "audio/ogg",
+        Some("mp3") => "audio/mpeg",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Scene files and scripts under the asset root, scripts in `scripts/`
+/// and generated images, each as an MCP resource description
+pub fn list_resources(roots: &ResourceRoots) -> Result<Vec<Value>> {
+    let mut resources = Vec::new();
+
+    for entry in search_assets(roots.asset_root, "") {
+        if matches!(entry.kind, AssetKind::Scene | AssetKind::Script) {
+            let path = entry.path_string();
+            resources.push(json!({
+                "uri": format!("{}assets/{}", RESOURCE_SCHEME, path),
+                "name": path,
+                "description": format!("{} file", entry.kind.label()),
+                "mimeType": mime_type(&entry.path),
+            }));
+        }
+    }
+
+    if roots.scripts_dir.is_dir() {
+        for entry in search_assets(&roots.scripts_dir, "") {
+            if entry.kind == AssetKind::Script {
+                let path = entry.path_string();
+                resources.push(json!({
+                    "uri": format!("{}scripts/{}", RESOURCE_SCHEME, path),
+                    "name": format!("scripts/{}", path),
+                    "description": "Script file",
+                    "mimeType": mime_type(&entry.path),
+                }));
+            }
+        }
+    }
+
+    if Path::new(GENERATED_ASSETS_DIR).is_dir() {
+        let mut generated = AssetCache::new(GENERATED_ASSETS_DIR)?.list_assets("")?;
+        generated.sort_by(|a, b| a.id.cmp(&b.id));
+        for asset in generated {
+            resources.push(json!({
+                "uri": format!("{}generated/{}", RESOURCE_SCHEME, asset.id),
+                "name": format!("Generated: {}", asset.prompt),
+                "description": format!(
+                    "{}x{} image generated from '{}'",
+                    asset.dimensions.0, asset.dimensions.1, asset.prompt
+                ),
+                "mimeType": mime_type(Path::new(&asset.file_path)),
+            }));
+        }
+    }
+
+    Ok(resources)
+}
+
+/// The file behind a resource URI
+pub fn resource_file(roots: &ResourceRoots, uri: &str) -> Result<PathBuf> {
+    let rest = uri
+        .strip_prefix(RESOURCE_SCHEME)
+        .ok_or_else(|| anyhow!("'{}' is not a {} URI", uri, RESOURCE_SCHEME))?;
+    let (kind, path) = rest
+        .split_once('/')
+        .ok_or_else(|| anyhow!("Unknown resource '{}'", uri))?;
+
+    let file = match kind {
+        "assets" => asset_file(roots.asset_root, path)?,
+        "scripts" => asset_file(&roots.scripts_dir, path)?,
+        "generated" => {
+            let cache = AssetCache::new(GENERATED_ASSETS_DIR)?;
+            let metadata = cache
+                .list_assets("")?
+                .into_iter()
+                .find(|asset| asset.id == path)
+                .ok_or_else(|| anyhow!("No generated asset '{}'", path))?;
+            Path::new(GENERATED_ASSETS_DIR).join(metadata.file_path)
+        }
+        _ => bail!("Unknown resource '{}'", uri),
+    };
+    if !file.is_file() {
+        bail!("Resource '{}' not found", uri);
+    }
+    Ok(file)
+}
+
+/// The URI of a file under the asset root or `scripts/` (as reported by the
+/// hot-reload watcher, usually absolute)
+pub fn resource_uri(roots: &ResourceRoots, file: &Path) -> Option<String> {
+    let relative_to = |root: &Path| {
+        let root = root.canonicalize().ok()?;
+        let file = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+        let relative = file.strip_prefix(&root).ok()?;
+        Some(relative.to_string_lossy().replace('\\', "/"))
+    };
+    if let Some(path) = relative_to(roots.asset_root) {
+        return Some(format!("{}assets/{}", RESOURCE_SCHEME, path));
+    }
+    relative_to(&roots.scripts_dir).map(|path| format!("{}scripts/{}", RESOURCE_SCHEME, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_uris_map_to_files() {
+        let root =
+            std::env::temp_dir().join(format!("causality_resources_test_{}", std::process::id()));
+        std::fs::create_dir_all(root.join("assets/scenes")).unwrap();
+        std::fs::create_dir_all(root.join("scripts")).unwrap();
+        std::fs::write(root.join("assets/scenes/castle.ron"), "(name: \"Castle\")").unwrap();
+        std::fs::write(root.join("scripts/door.rhai"), "fn update(ctx) {}").unwrap();
+
+        let asset_root = root.join("assets");
+        let roots = ResourceRoots {
+            asset_root: &asset_root,
+            scripts_dir: root.join("scripts"),
+        };
+        let uris: Vec<String> = list_resources(&roots)
+            .unwrap()
+            .iter()
+            .filter_map(|r| r["uri"].as_str().map(str::to_string))
+            .filter(|uri| !uri.contains("generated/"))
+            .collect();
+        assert_eq!(
+            uris,
+            vec![
+                "causality://assets/scenes/castle.ron",
+                "causality://scripts/door.rhai"
+            ]
+        );
+
+        let scene = resource_file(&roots, "causality://assets/scenes/castle.ron").unwrap();
+        assert_eq!(mime_type(&scene), "text/x-ron");
+        assert_eq!(
+            resource_uri(&roots, &root.join("scripts/door.rhai")).as_deref(),
+            Some("causality://scripts/door.rhai")
+        );
+        assert!(resource_file(&roots, "causality://assets/../secret.txt").is_err());
+        assert!(resource_file(&roots, "causality://assets/scenes/missing.ron").is_err());
+        assert!(resource_file(&roots, "file:///etc/passwd").is_err());
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
@@ -0,0 +1,7 @@
+// Causality Engine - Runtime
+//
+// The executable Export Build ships with a game. It is the editor built
+// under another name: started next to the build's game.json it plays that
+// game without the editor UI, and it refuses to start without one.
+
+include!("main.rs");
@@ -0,0 +1,227 @@
+// Save games in play mode - carries out scripts' save_game/load_game calls
+//
+// engine_scripting captures and restores transforms, script variables and
+// game data; this adds what only the editor has: body velocities from the
+// physics world and the music the audio system is playing. Loading
+// rebuilds the physics world from the restored transforms.
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use engine_audio::PlayingMusic;
+use engine_physics::{from_rapier_vec, to_rapier_vec, ColliderGeometry, PhysicsSync, PhysicsWorld};
+use engine_scene::entity::EntityId;
+use engine_scene::scene::Scene;
+use engine_scripting::{
+    AudioCommand, AudioCommandQueue, SaveGame, SaveRequest, SaveSlots, SaveStateHandle, SavedMusic,
+    ScriptSystem,
+};
+use glam::Vec3;
+
+/// Save slots plus the state shared with scripts
+#[derive(Default)]
+pub struct SaveGames {
+    pub slots: SaveSlots,
+    state: SaveStateHandle,
+}
+
+impl SaveGames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give a (new) script system the save API
+    pub fn register(&self, scripts: &mut ScriptSystem) {
+        scripts.register_save_api(self.state.clone(), self.slots.clone());
+    }
+
+    /// Forget requests and game data, for a new play session
+    pub fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.requests.clear();
+        state.data.clear();
+    }
+
+    /// Write the current play state to a slot
+    pub fn save(
+        &self,
+        slot: &str,
+        scene: &Scene,
+        physics: &PhysicsWorld,
+        scripts: &ScriptSystem,
+        music: Option<&PlayingMusic>,
+    ) -> Result<PathBuf> {
+        let mut save =
+            SaveGame::capture(scene, scripts.runtime(), &self.state.lock().unwrap().data);
+
+        for entity in &mut save.entities {
+            let body = physics
+                .get_body_handle(EntityId(entity.id))
+                .and_then(|handle| physics.get_rigid_body(handle))
+                .filter(|body| body.is_dynamic());
+            if let Some(body) = body {
+                entity.linear_velocity = Some(from_rapier_vec(*body.linvel()).to_array());
+                entity.angular_velocity = Some(from_rapier_vec(*body.angvel()).to_array());
+            }
+        }
+        save.music = music.map(|music| SavedMusic {
+            path: music.path.clone(),
+            volume: music.volume,
+            looping: music.looping,
+        });
+
+        self.slots.save(slot, &save)
+    }
+
+    /// Restore a slot: transforms, script variables and game data, then a
+    /// fresh physics world (colliders built from `geometry`) with the saved
+    /// velocities, then the music
+    pub fn load(
+        &self,
+        slot: &str,
+        scene: &mut Scene,
+        physics: &mut PhysicsWorld,
+        scripts: &mut ScriptSystem,
+        audio_commands: &AudioCommandQueue,
+        geometry: &ColliderGeometry,
+    ) -> Result<()> {
+        let save = self.slots.load(slot)?;
+        if save.scene != scene.name {
+            log::warn!(
+                "Save slot '{}' is from scene '{}', loading into '{}'",
+                slot,
+                save.scene,
+                scene.name
+            );
+        }
+        save.restore(
+            scene,
+            scripts.runtime_mut(),
+            &mut self.state.lock().unwrap().data,
+        );
+
+        *physics = PhysicsWorld::default();
+        PhysicsSync::initialize_physics(physics, scene, geometry)?;
+        for entity in &save.entities {
+            let Some(handle) = physics.get_body_handle(EntityId(entity.id)) else {
+                continue;
+            };
+            let Some(body) = physics.get_rigid_body_mut(handle) else {
+                continue;
+            };
+            if let Some(v) = entity.linear_velocity {
+                body.set_linvel(to_rapier_vec(Vec3::from_array(v)), true);
+            }
+            if let Some(v) = entity.angular_velocity {
+                body.set_angvel(to_rapier_vec(Vec3::from_array(v)), true);
+            }
+        }
+
+        let command = match save.music {
+            Some(music) => AudioCommand::PlayMusic {
+                path: music.path,
+                volume: music.volume,
+                looping: music.looping,
+                fade_in: 0.0,
+            },
+            None => AudioCommand::StopMusic { fade_out: 0.0 },
+        };
+        audio_commands.lock().unwrap().push(command);
+
+        log::info!("Loaded game from slot '{}'", slot);
+        Ok(())
+    }
+
+    /// Carry out what scripts asked for since the last frame, returning
+    /// the errors
+    pub fn process_requests(
+        &self,
+        scene: &mut Scene,
+        physics: &mut PhysicsWorld,
+        scripts: &mut ScriptSystem,
+        music: Option<&PlayingMusic>,
+        audio_commands: &AudioCommandQueue,
+        geometry: &ColliderGeometry,
+    ) -> Vec<anyhow::Error> {
+        let requests = std::mem::take(&mut self.state.lock().unwrap().requests);
+        requests
+            .into_iter()
+            .filter_map(|request| match request {
+                SaveRequest::Save { slot } => {
+                    self.save(&slot, scene, physics, scripts, music).err()
+                }
+                SaveRequest::Load { slot } => self
+                    .load(&slot, scene, physics, scripts, audio_commands, geometry)
+                    .err(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_physics::{Collider, RigidBody};
+    use engine_scene::transform::Transform;
+
+    #[test]
+    fn test_save_and_load_keep_body_velocity() {
+        let dir =
+            std::env::temp_dir().join(format!("causality_save_games_test_{}", std::process::id()));
+        let saves = SaveGames {
+            slots: SaveSlots::new(&dir),
+            state: SaveStateHandle::default(),
+        };
+
+        let mut scene = Scene::new("Test".to_string());
+        let ball = scene.create_entity_with_transform(
+            "Ball".to_string(),
+            Transform::from_position(Vec3::new(0.0, 10.0, 0.0)),
+        );
+        let entity = scene.get_entity_mut(ball).unwrap();
+        entity.add_component(RigidBody::dynamic(1.0));
+        entity.add_component(Collider::sphere(0.5));
+
+        let mut physics = PhysicsWorld::default();
+        PhysicsSync::initialize_physics(&mut physics, &scene, &ColliderGeometry::default()).unwrap();
+        let mut scripts = ScriptSystem::new();
+        saves.register(&mut scripts);
+        for _ in 0..20 {
+            physics.step(1.0 / 60.0);
+        }
+        PhysicsSync::sync_to_scene(&physics, &mut scene).unwrap();
+        let saved_y = scene.get_entity(ball).unwrap().transform.position.y;
+
+        saves
+            .save("quick", &scene, &physics, &scripts, None)
+            .unwrap();
+        scene.get_entity_mut(ball).unwrap().transform.position = Vec3::ZERO;
+
+        // A script asks for the load; it happens when requests are processed
+        let queued = scripts.runtime_mut().eval(r#"load_game("quick")"#).unwrap();
+        assert!(queued.as_bool().unwrap());
+        let audio_commands = AudioCommandQueue::default();
+        let errors = saves.process_requests(
+            &mut scene,
+            &mut physics,
+            &mut scripts,
+            None,
+            &audio_commands,
+            &ColliderGeometry::default(),
+        );
+        assert!(errors.is_empty());
+
+        assert_eq!(
+            scene.get_entity(ball).unwrap().transform.position.y,
+            saved_y
+        );
+        let handle = physics.get_body_handle(ball).unwrap();
+        assert!(physics.get_rigid_body(handle).unwrap().linvel().y < 0.0);
+        assert!(matches!(
+            audio_commands.lock().unwrap().as_slice(),
+            [AudioCommand::StopMusic { .. }]
+        ));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,639 @@
+// Foliage scattering - rule-based bulk placement of vegetation on terrain
+//
+// Each rule scatters one vegetation type at a given density. A candidate point
+// is kept only if it lies in the scatter region (the whole terrain or the area
+// painted with a terrain texture layer), its altitude and slope fall inside the
+// rule's ranges, it is on the rule's own painted layer (if any), it passes the
+// rule's noise mask, it is clear of water, and it is at least the rule's
+// minimum spacing away from every other foliage instance. Rules run in order,
+// so earlier rules claim space first. The same seed always gives the same result.
+
+use std::collections::HashMap;
+
+use engine_assets::{HeightMap, SplatMap, TerrainConfig};
+use engine_core::determinism::SimRng;
+use noise::{NoiseFn, Perlin};
+use engine_scene::{
+    components::{Foliage, FoliageInstance},
+    entity::EntityId,
+    grass::{find_grass, Grass},
+    scene::Scene,
+};
+
+use crate::ui::VegetationType;
+
+/// Upper bound on candidate points tried per rule, so huge terrains stay responsive
+const MAX_CANDIDATES_PER_RULE: usize = 200_000;
+/// Painted-layer weight above which a point counts as inside the region
+const REGION_LAYER_THRESHOLD: f32 = 0.5;
+
+/// How one vegetation type is scattered
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScatterRule {
+    pub vegetation_type: VegetationType,
+    pub enabled: bool,
+    /// Instances per 100 m² (10 x 10 m) of region
+    pub density: f32,
+    /// Allowed slope range in degrees
+    pub slope_min: f32,
+    pub slope_max: f32,
+    /// Allowed terrain height range
+    pub altitude_min: f32,
+    pub altitude_max: f32,
+    /// Minimum distance to any other foliage instance
+    pub min_spacing: f32,
+    pub scale_min: f32,
+    pub scale_max: f32,
+    /// Only where this splatmap layer is painted, within the scatter region
+    pub layer: Option<usize>,
+    /// Fraction of the terrain the noise mask lets through; 1 disables it
+    pub noise_coverage: f32,
+    /// Size of the noise mask's patches in world units
+    pub noise_scale: f32,
+}
+
+impl ScatterRule {
+    pub fn new(vegetation_type: VegetationType) -> Self {
+        Self {
+            vegetation_type,
+            enabled: true,
+            density: 1.0,
+            slope_min: 0.0,
+            slope_max: 35.0,
+            altitude_min: -100.0,
+            altitude_max: 100.0,
+            min_spacing: 2.0,
+            scale_min: 0.7,
+            scale_max: 1.3,
+            layer: None,
+            noise_coverage: 1.0,
+            noise_scale: 30.0,
+        }
+    }
+}
+
+/// Area of the terrain to scatter over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScatterRegion {
+    WholeTerrain,
+    /// Where the given splatmap layer has been painted
+    PaintedLayer(usize),
+}
+
+/// Scatter tool settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScatterSettings {
+    pub rules: Vec<ScatterRule>,
+    pub region: ScatterRegion,
+    /// Keep instances this far (horizontally and above the surface) from water
+    pub water_clearance: f32,
+    pub seed: u32,
+    pub random_rotation: bool,
+    /// Remove existing instances of the scattered types in the region first
+    pub replace_existing: bool,
+}
+
+impl Default for ScatterSettings {
+    fn default() -> Self {
+        let mut rules: Vec<ScatterRule> = VegetationType::all()
+            .iter()
+            .map(|&vegetation_type| ScatterRule::new(vegetation_type))
+            .collect();
+        // Trees first with wide spacing, undergrowth fills the gaps
+        for rule in &mut rules {
+            match rule.vegetation_type {
+                VegetationType::PineTree => {
+                    rule.density = 0.5;
+                    rule.min_spacing = 4.0;
+                }
+                VegetationType::OakTree => {
+                    rule.enabled = false;
+                    rule.density = 0.3;
+                    rule.slope_max = 20.0;
+                    rule.min_spacing = 5.0;
+                }
+                VegetationType::Bush => {
+                    rule.density = 1.0;
+                    rule.min_spacing = 1.5;
+                    // Clumps rather than an even spread
+                    rule.noise_coverage = 0.5;
+                    rule.noise_scale = 20.0;
+                }
+                VegetationType::Shrub => {
+                    rule.enabled = false;
+                    rule.density = 2.0;
+                    rule.slope_max = 45.0;
+                    rule.min_spacing = 1.0;
+                }
+            }
+        }
+        Self {
+            rules,
+            region: ScatterRegion::WholeTerrain,
+            water_clearance: 1.0,
+            seed: 1,
+            random_rotation: true,
+            replace_existing: false,
+        }
+    }
+}
+
+/// XZ bounds and surface level of a body of water
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WaterZone {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+    pub surface_level: f32,
+}
+
+/// Terrain data the scatter rules are evaluated against
+pub struct ScatterTerrain<'a> {
+    pub heightmap: &'a HeightMap,
+    pub config: &'a TerrainConfig,
+    pub splatmap: Option<&'a SplatMap>,
+    pub water: &'a [WaterZone],
+}
+
+impl ScatterTerrain<'_> {
+    /// World-space XZ extents of the terrain grid (min, max)
+    fn bounds(&self) -> ([f32; 2], [f32; 2]) {
+        let cell_size = self.config.scale / self.config.width as f32;
+        let min = [-self.config.scale * 0.5, -self.config.scale * 0.5];
+        let max = [
+            min[0] + self.config.width.saturating_sub(1) as f32 * cell_size,
+            min[1] + self.config.depth.saturating_sub(1) as f32 * cell_size,
+        ];
+        (min, max)
+    }
+
+    fn in_region(&self, region: ScatterRegion, x: f32, z: f32) -> bool {
+        match region {
+            ScatterRegion::WholeTerrain => true,
+            ScatterRegion::PaintedLayer(layer) => {
+                let Some(splatmap) = self.splatmap else {
+                    return false;
+                };
+                if layer >= splatmap.layer_count {
+                    return false;
+                }
+                let (grid_x, grid_z) = self.heightmap.world_to_grid(x, z, self.config.scale);
+                let (grid_x, grid_z) = (grid_x.round(), grid_z.round());
+                if grid_x < 0.0 || grid_z < 0.0 {
+                    return false;
+                }
+                let (grid_x, grid_z) = (grid_x as usize, grid_z as usize);
+                grid_x < splatmap.width
+                    && grid_z < splatmap.depth
+                    && splatmap.weights(grid_x, grid_z)[layer] >= REGION_LAYER_THRESHOLD
+            }
+        }
+    }
+
+    /// Terrain slope in degrees at a world position
+    fn slope_degrees(&self, x: f32, z: f32) -> f32 {
+        let step = self.config.scale / self.config.width as f32;
+        let height = |x, z| self.heightmap.sample_height(x, z, self.config.scale);
+        let dh_dx = (height(x + step, z) - height(x - step, z)) / (2.0 * step);
+        let dh_dz = (height(x, z + step) - height(x, z - step)) / (2.0 * step);
+        (dh_dx * dh_dx + dh_dz * dh_dz).sqrt().atan().to_degrees()
+    }
+
+    fn on_layer(&self, layer: Option<usize>, x: f32, z: f32) -> bool {
+        layer.is_none_or(|layer| self.in_region(ScatterRegion::PaintedLayer(layer), x, z))
+    }
+
+    fn near_water(&self, x: f32, y: f32, z: f32, clearance: f32) -> bool {
+        self.water.iter().any(|zone| {
+            x >= zone.min[0] - clearance
+                && x <= zone.max[0] + clearance
+                && z >= zone.min[1] - clearance
+                && z <= zone.max[1] + clearance
+                && y < zone.surface_level + clearance
+        })
+    }
+}
+
+/// Placed positions bucketed on a grid for fast spacing checks
+struct SpacingGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<[f32; 2]>>,
+}
+
+impl SpacingGrid {
+    fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size: cell_size.max(0.1),
+            cells: HashMap::new(),
+        }
+    }
+
+    fn cell(&self, x: f32, z: f32) -> (i32, i32) {
+        (
+            (x / self.cell_size).floor() as i32,
+            (z / self.cell_size).floor() as i32,
+        )
+    }
+
+    fn insert(&mut self, x: f32, z: f32) {
+        let cell = self.cell(x, z);
+        self.cells.entry(cell).or_default().push([x, z]);
+    }
+
+    /// True if no placed point is closer than `spacing` (at most the cell size)
+    fn is_clear(&self, x: f32, z: f32, spacing: f32) -> bool {
+        let (cx, cz) = self.cell(x, z);
+        let spacing_sq = spacing * spacing;
+        (cz - 1..=cz + 1).all(|gz| {
+            (cx - 1..=cx + 1).all(|gx| {
+                self.cells.get(&(gx, gz)).is_none_or(|points| {
+                    points.iter().all(|p| {
+                        let dx = p[0] - x;
+                        let dz = p[1] - z;
+                        dx * dx + dz * dz >= spacing_sq
+                    })
+                })
+            })
+        })
+    }
+}
+
+/// Patchy mask over the terrain covering about `coverage` of it
+struct NoiseMask {
+    perlin: Perlin,
+    coverage: f32,
+    scale: f32,
+}
+
+impl NoiseMask {
+    fn new(seed: u32, coverage: f32, scale: f32) -> Self {
+        Self {
+            perlin: Perlin::new(seed),
+            coverage,
+            scale: scale.max(0.1),
+        }
+    }
+
+    fn passes(&self, x: f32, z: f32) -> bool {
+        if self.coverage >= 1.0 {
+            return true;
+        }
+        let value = self
+            .perlin
+            .get([(x / self.scale) as f64, (z / self.scale) as f64]) as f32;
+        value * 0.5 + 0.5 < self.coverage
+    }
+}
+
+/// Generate instances for every enabled rule. `existing` holds the positions
+/// of foliage already in the scene, which new instances keep their spacing from.
+pub fn scatter(
+    settings: &ScatterSettings,
+    terrain: &ScatterTerrain,
+    existing: &[[f32; 3]],
+) -> Vec<(VegetationType, Vec<FoliageInstance>)> {
+    let rules: Vec<&ScatterRule> = settings.rules.iter().filter(|rule| rule.enabled).collect();
+    let max_spacing = rules
+        .iter()
+        .map(|rule| rule.min_spacing)
+        .fold(0.0, f32::max);
+    let mut grid = SpacingGrid::new(max_spacing);
+    for position in existing {
+        grid.insert(position[0], position[2]);
+    }
+
+    let (min, max) = terrain.bounds();
+    let area = (max[0] - min[0]).max(0.0) * (max[1] - min[1]).max(0.0);
+    // Each rule draws from its own stream, so changing one rule's density
+    // doesn't move the instances of the others
+    let seed_rng = SimRng::new(settings.seed as u64);
+
+    rules
+        .into_iter()
+        .enumerate()
+        .map(|(rule_index, rule)| {
+            let mut rng = seed_rng.fork(rule_index as u64);
+            let mask = NoiseMask::new(
+                settings.seed.wrapping_add(rule_index as u32),
+                rule.noise_coverage,
+                rule.noise_scale,
+            );
+            let candidates =
+                ((area / 100.0 * rule.density.max(0.0)) as usize).min(MAX_CANDIDATES_PER_RULE);
+            let mut instances = Vec::new();
+            for _ in 0..candidates {
+                let x = min[0] + rng.next_f32() * (max[0] - min[0]);
+                let z = min[1] + rng.next_f32() * (max[1] - min[1]);
+                let scale_t = rng.next_f32();
+                let rotation_t = rng.next_f32();
+
+                if !terrain.in_region(settings.region, x, z)
+                    || !terrain.on_layer(rule.layer, x, z)
+                    || !mask.passes(x, z)
+                {
+                    continue;
+                }
+                let y = terrain.heightmap.sample_height(x, z, terrain.config.scale);
+                if y < rule.altitude_min || y > rule.altitude_max {
+                    continue;
+                }
+                let slope = terrain.slope_degrees(x, z);
+                if slope < rule.slope_min || slope > rule.slope_max {
+                    continue;
+                }
+                if terrain.near_water(x, y, z, settings.water_clearance) {
+                    continue;
+                }
+                if !grid.is_clear(x, z, rule.min_spacing) {
+                    continue;
+                }
+
+                grid.insert(x, z);
+                let scale = rule.scale_min + scale_t * (rule.scale_max - rule.scale_min);
+                let rotation_y = if settings.random_rotation {
+                    rotation_t * std::f32::consts::TAU
+                } else {
+                    0.0
+                };
+                instances.push(
+                    FoliageInstance::new([x, y, z])
+                        .with_rotation(rotation_y)
+                        .with_scale(scale),
+                );
+            }
+            (rule.vegetation_type, instances)
+        })
+        .collect()
+}
+
+/// The Foliage entity holding a vegetation type, created if the scene has none
+pub fn foliage_entity(scene: &mut Scene, vegetation_type: VegetationType) -> EntityId {
+    let existing = scene.entities().find_map(|entity| {
+        entity
+            .get_component::<Foliage>()
+            .filter(|foliage| foliage.vegetation_type == vegetation_type.mesh_name())
+            .map(|_| entity.id)
+    });
+    if let Some(id) = existing {
+        return id;
+    }
+
+    let id = scene.create_entity(format!("Foliage - {}", vegetation_type.name()));
+    if let Some(entity) = scene.get_entity_mut(id) {
+        let mut foliage = Foliage::new(vegetation_type.mesh_name().to_string());
+        foliage.stiffness = vegetation_type.stiffness();
+        entity.add_component(foliage);
+    }
+    id
+}
+
+/// The Grass entity, created over the terrain if the scene has none
+pub fn grass_entity(scene: &mut Scene, terrain_size: f32) -> EntityId {
+    if let Some((id, _)) = find_grass(scene) {
+        return id;
+    }
+
+    let id = scene.create_entity("Grass".to_string());
+    if let Some(entity) = scene.get_entity_mut(id) {
+        entity.add_component(Grass::new(terrain_size, 128));
+    }
+    id
+}
+
+/// Scatter foliage into the scene. Returns the number of instances added.
+pub fn apply_scatter(
+    scene: &mut Scene,
+    settings: &ScatterSettings,
+    terrain: &ScatterTerrain,
+) -> usize {
+    if settings.replace_existing {
+        let mesh_names: Vec<&str> = settings
+            .rules
+            .iter()
+            .filter(|rule| rule.enabled)
+            .map(|rule| rule.vegetation_type.mesh_name())
+            .collect();
+        let ids: Vec<EntityId> = scene
+            .entities()
+            .filter(|entity| {
+                entity
+                    .get_component::<Foliage>()
+                    .is_some_and(|foliage| mesh_names.contains(&foliage.vegetation_type.as_str()))
+            })
+            .map(|entity| entity.id)
+            .collect();
+        for id in ids {
+            if let Some(foliage) = scene
+                .get_entity_mut(id)
+                .and_then(|e| e.get_component_mut::<Foliage>())
+            {
+                foliage.instances.retain(|instance| {
+                    !terrain.in_region(settings.region, instance.position[0], instance.position[2])
+                });
+            }
+        }
+    }
+
+    let existing: Vec<[f32; 3]> = scene
+        .entities()
+        .filter_map(|entity| entity.get_component::<Foliage>())
+        .flat_map(|foliage| foliage.instances.iter().map(|instance| instance.position))
+        .collect();
+
+    let mut added = 0;
+    for (vegetation_type, instances) in scatter(settings, terrain, &existing) {
+        if instances.is_empty() {
+            continue;
+        }
+        added += instances.len();
+        let id = foliage_entity(scene, vegetation_type);
+        if let Some(foliage) = scene
+            .get_entity_mut(id)
+            .and_then(|e| e.get_component_mut::<Foliage>())
+        {
+            foliage.instances.extend(instances);
+        }
+    }
+    added
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat_terrain(heights: impl Fn(usize, usize) -> f32) -> (HeightMap, TerrainConfig) {
+        let config = TerrainConfig {
+            width: 33,
+            depth: 33,
+            scale: 64.0,
+            ..Default::default()
+        };
+        let mut heights_vec = Vec::new();
+        for z in 0..config.depth {
+            for x in 0..config.width {
+                heights_vec.push(heights(x, z));
+            }
+        }
+        let heightmap = HeightMap {
+            width: config.width,
+            depth: config.depth,
+            heights: heights_vec,
+            holes: Vec::new(),
+        };
+        (heightmap, config)
+    }
+
+    fn single_rule(rule: ScatterRule) -> ScatterSettings {
+        ScatterSettings {
+            rules: vec![rule],
+            water_clearance: 0.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_scatter_respects_spacing_and_is_deterministic() {
+        let (heightmap, config) = flat_terrain(|_, _| 0.0);
+        let terrain = ScatterTerrain {
+            heightmap: &heightmap,
+            config: &config,
+            splatmap: None,
+            water: &[],
+        };
+        let mut rule = ScatterRule::new(VegetationType::Bush);
+        rule.density = 5.0;
+        rule.min_spacing = 3.0;
+        let settings = single_rule(rule);
+
+        let result = scatter(&settings, &terrain, &[]);
+        let instances = &result[0].1;
+        assert!(!instances.is_empty());
+        for (i, a) in instances.iter().enumerate() {
+            for b in &instances[i + 1..] {
+                let dx = a.position[0] - b.position[0];
+                let dz = a.position[2] - b.position[2];
+                assert!((dx * dx + dz * dz).sqrt() >= 3.0);
+            }
+        }
+
+        let again = scatter(&settings, &terrain, &[]);
+        assert_eq!(again[0].1.len(), instances.len());
+        assert_eq!(again[0].1[0].position, instances[0].position);
+    }
+
+    #[test]
+    fn test_scatter_filters_altitude_slope_and_water() {
+        // Left half is a steep ramp, right half is a flat plateau at height 2
+        let (heightmap, config) =
+            flat_terrain(|x, _| if x < 16 { x as f32 * 4.0 - 62.0 } else { 2.0 });
+        let water = [WaterZone {
+            min: [16.0, -32.0],
+            max: [32.0, 0.0],
+            surface_level: 3.0,
+        }];
+        let terrain = ScatterTerrain {
+            heightmap: &heightmap,
+            config: &config,
+            splatmap: None,
+            water: &water,
+        };
+        let mut rule = ScatterRule::new(VegetationType::PineTree);
+        rule.density = 5.0;
+        rule.min_spacing = 0.5;
+        rule.slope_max = 10.0;
+        rule.altitude_min = 1.0;
+        let settings = single_rule(rule);
+
+        let result = scatter(&settings, &terrain, &[]);
+        let instances = &result[0].1;
+        assert!(!instances.is_empty());
+        for instance in instances {
+            let [x, y, z] = instance.position;
+            assert!(y >= 1.0);
+            assert!(terrain.slope_degrees(x, z) <= 10.0);
+            assert!(
+                !(x >= 16.0 && z <= 0.0),
+                "instance in water at {:?}",
+                instance.position
+            );
+        }
+    }
+
+    #[test]
+    fn test_scatter_noise_mask_and_rule_layer() {
+        let (heightmap, config) = flat_terrain(|_, _| 0.0);
+        let mut splatmap = SplatMap::new(config.width, config.depth, 2);
+        splatmap.paint(0.0, 0.0, config.scale, 12.0, 1, 1.0, 0.0);
+        let terrain = ScatterTerrain {
+            heightmap: &heightmap,
+            config: &config,
+            splatmap: Some(&splatmap),
+            water: &[],
+        };
+        let mut rule = ScatterRule::new(VegetationType::Bush);
+        rule.density = 10.0;
+        rule.min_spacing = 0.5;
+        let unmasked = scatter(&single_rule(rule.clone()), &terrain, &[])[0]
+            .1
+            .len();
+
+        // The mask thins the scatter out, but only where it's closed
+        rule.noise_coverage = 0.4;
+        rule.noise_scale = 10.0;
+        let settings = single_rule(rule.clone());
+        let masked = scatter(&settings, &terrain, &[]);
+        let mask = NoiseMask::new(settings.seed, 0.4, 10.0);
+        assert!(!masked[0].1.is_empty() && masked[0].1.len() < unmasked);
+        assert!(masked[0]
+            .1
+            .iter()
+            .all(|instance| mask.passes(instance.position[0], instance.position[2])));
+
+        // A rule layer limits it to the painted area like a region does
+        rule.noise_coverage = 1.0;
+        rule.layer = Some(1);
+        let layered = scatter(&single_rule(rule), &terrain, &[]);
+        let cell_size = config.scale / config.width as f32;
+        assert!(!layered[0].1.is_empty());
+        for instance in &layered[0].1 {
+            let [x, _, z] = instance.position;
+            assert!((x * x + z * z).sqrt() <= 12.0 + cell_size);
+        }
+    }
+
+    #[test]
+    fn test_scatter_painted_region() {
+        let (heightmap, config) = flat_terrain(|_, _| 0.0);
+        let mut splatmap = SplatMap::new(config.width, config.depth, 2);
+        splatmap.paint(0.0, 0.0, config.scale, 8.0, 1, 1.0, 0.0);
+        let terrain = ScatterTerrain {
+            heightmap: &heightmap,
+            config: &config,
+            splatmap: Some(&splatmap),
+            water: &[],
+        };
+        let mut rule = ScatterRule::new(VegetationType::Shrub);
+        rule.density = 10.0;
+        rule.min_spacing = 0.5;
+        let settings = ScatterSettings {
+            region: ScatterRegion::PaintedLayer(1),
+            ..single_rule(rule)
+        };
+
+        let mut scene = Scene::new("Test".to_string());
+        let added = apply_scatter(&mut scene, &settings, &terrain);
+        assert!(added > 0);
+        let foliage_id = foliage_entity(&mut scene, VegetationType::Shrub);
+        let foliage = scene
+            .get_entity(foliage_id)
+            .unwrap()
+            .get_component::<Foliage>()
+            .unwrap();
+        assert_eq!(foliage.instances.len(), added);
+        // Painted radius plus up to half a cell diagonal to the nearest grid point
+        let cell_size = config.scale / config.width as f32;
+        for instance in &foliage.instances {
+            let [x, _, z] = instance.position;
+            assert!((x * x + z * z).sqrt() <= 8.0 + cell_size);
+        }
+    }
+}
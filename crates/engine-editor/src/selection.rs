@@ -0,0 +1,388 @@
+// Multi-selection helpers - group operations on sets of entities
+//
+// The editor keeps a primary `selected_entity` (shown in the inspector) plus a
+// set of all selected entities. Group operations act on the selection roots so
+// that a child is never moved, copied or reparented twice along with its parent.
+
+use std::collections::HashSet;
+
+use engine_render::camera::Camera;
+use engine_scene::{components::MeshRenderer, entity::EntityId, scene::Scene, transform::Transform};
+use glam::{Vec2, Vec3};
+
+use crate::entity_icons::IconKind;
+use crate::placement::set_world_position;
+use crate::ui::EditorUi;
+use crate::undo::UndoHistory;
+
+/// Check if `entity_id` has `ancestor_id` somewhere above it in the hierarchy
+pub fn is_ancestor(scene: &Scene, ancestor_id: EntityId, entity_id: EntityId) -> bool {
+    let mut current = scene.get_entity(entity_id).and_then(|e| e.parent);
+    while let Some(parent_id) = current {
+        if parent_id == ancestor_id {
+            return true;
+        }
+        current = scene.get_entity(parent_id).and_then(|e| e.parent);
+    }
+    false
+}
+
+/// Selected entities whose ancestors are not also selected, sorted by ID
+pub fn selection_roots(scene: &Scene, selected: &HashSet<EntityId>) -> Vec<EntityId> {
+    let mut roots: Vec<EntityId> = selected
+        .iter()
+        .copied()
+        .filter(|&id| scene.get_entity(id).is_some())
+        .filter(|&id| {
+            !selected
+                .iter()
+                .any(|&other| other != id && is_ancestor(scene, other, id))
+        })
+        .collect();
+    roots.sort_by_key(|id| id.0);
+    roots
+}
+
+/// World-space position of an entity (including parent transforms)
+pub fn world_position(scene: &Scene, entity_id: EntityId) -> Vec3 {
+    scene.world_matrix(entity_id).w_axis.truncate()
+}
+
+/// Reparent an entity while keeping its world transform unchanged
+pub fn set_parent_keep_world(scene: &mut Scene, child_id: EntityId, parent_id: Option<EntityId>) {
+    let child_world = scene.world_matrix(child_id);
+    let parent_world = parent_id
+        .map(|id| scene.world_matrix(id))
+        .unwrap_or(glam::Mat4::IDENTITY);
+    let local = parent_world.inverse() * child_world;
+    let (scale, rotation, position) = local.to_scale_rotation_translation();
+
+    scene.set_parent(child_id, parent_id);
+    if let Some(child) = scene.get_entity_mut(child_id) {
+        child.transform.position = position;
+        child.transform.rotation = rotation;
+        child.transform.scale = scale;
+    }
+}
+
+/// Remove all entities (children are removed with their parents)
+pub fn delete_entities(scene: &mut Scene, ids: &[EntityId]) -> usize {
+    ids.iter().filter(|&&id| scene.remove_entity(id)).count()
+}
+
+/// Duplicate all entities, returning the IDs of the copies
+pub fn duplicate_entities(scene: &mut Scene, ids: &[EntityId]) -> Vec<EntityId> {
+    ids.iter()
+        .filter_map(|&id| scene.duplicate_entity(id))
+        .collect()
+}
+
+/// Parent entities under `parent_id`, skipping the parent itself and its ancestors
+/// (which would create a cycle). Returns the number of entities reparented.
+pub fn parent_entities(scene: &mut Scene, ids: &[EntityId], parent_id: EntityId) -> usize {
+    let mut count = 0;
+    for &id in ids {
+        if id == parent_id || is_ancestor(scene, id, parent_id) {
+            continue;
+        }
+        set_parent_keep_world(scene, id, Some(parent_id));
+        count += 1;
+    }
+    count
+}
+
+/// Create a new empty entity at the centroid of `ids` and parent them under it.
+/// The group is created under the first entity's parent so it stays in place.
+pub fn group_entities(scene: &mut Scene, ids: &[EntityId], name: String) -> Option<EntityId> {
+    if ids.is_empty() {
+        return None;
+    }
+
+    let centroid = ids
+        .iter()
+        .map(|&id| world_position(scene, id))
+        .sum::<Vec3>()
+        / ids.len() as f32;
+    let group_parent = scene.get_entity(ids[0]).and_then(|e| e.parent);
+
+    let group_id = scene.create_entity_with_transform(name, Transform::from_position(centroid));
+    if group_parent.is_some() {
+        set_parent_keep_world(scene, group_id, group_parent);
+    }
+    parent_entities(scene, ids, group_id);
+    Some(group_id)
+}
+
+/// Name of a world axis index (0 = X, 1 = Y, 2 = Z)
+pub fn axis_name(axis: usize) -> &'static str {
+    ["X", "Y", "Z"][axis.min(2)]
+}
+
+/// Move entities so their world position on `axis` (0 = X, 1 = Y, 2 = Z)
+/// matches `target`'s. Returns the number of entities moved.
+pub fn align_entities(scene: &mut Scene, ids: &[EntityId], target: EntityId, axis: usize) -> usize {
+    let value = world_position(scene, target)[axis];
+    let mut count = 0;
+    for &id in ids {
+        let mut position = world_position(scene, id);
+        if id == target || position[axis] == value {
+            continue;
+        }
+        position[axis] = value;
+        set_world_position(scene, id, position);
+        count += 1;
+    }
+    count
+}
+
+/// Space entities evenly along a world axis, keeping the two outermost in
+/// place. Returns the number of entities moved.
+pub fn distribute_entities(scene: &mut Scene, ids: &[EntityId], axis: usize) -> usize {
+    if ids.len() < 3 {
+        return 0;
+    }
+    let mut ordered: Vec<(EntityId, Vec3)> = ids
+        .iter()
+        .map(|&id| (id, world_position(scene, id)))
+        .collect();
+    ordered.sort_by(|a, b| a.1[axis].total_cmp(&b.1[axis]));
+
+    let first = ordered[0].1[axis];
+    let step = (ordered[ordered.len() - 1].1[axis] - first) / (ordered.len() - 1) as f32;
+    let mut count = 0;
+    for (i, (id, mut position)) in ordered.into_iter().enumerate() {
+        let value = first + step * i as f32;
+        if (position[axis] - value).abs() > 1e-6 {
+            position[axis] = value;
+            set_world_position(scene, id, position);
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Delete the selected entities as a single undo step
+pub fn delete_selection(scene: &mut Scene, ui: &mut EditorUi, undo_history: &mut UndoHistory) {
+    let roots = ui.selection_roots(scene);
+    if roots.is_empty() {
+        return;
+    }
+    undo_history.record_entities(scene, &roots);
+    let count = delete_entities(scene, &roots);
+    ui.clear_selection();
+    ui.mark_scene_modified();
+    log::info!("Deleted {} entities", count);
+}
+
+/// Duplicate the selected entities as a single undo step and select the copies
+pub fn duplicate_selection(scene: &mut Scene, ui: &mut EditorUi, undo_history: &mut UndoHistory) {
+    let roots = ui.selection_roots(scene);
+    if roots.is_empty() {
+        return;
+    }
+    undo_history.record_entities(scene, &roots);
+    let copies = duplicate_entities(scene, &roots);
+    ui.set_selection(&copies);
+    ui.mark_scene_modified();
+    log::info!("Duplicated {} entities", copies.len());
+}
+
+/// Parent the selected entities under a new empty "Group" entity
+pub fn group_selection(scene: &mut Scene, ui: &mut EditorUi, undo_history: &mut UndoHistory) {
+    let roots = ui.selection_roots(scene);
+    if roots.is_empty() {
+        return;
+    }
+    undo_history.record_entities(scene, &roots);
+    if let Some(group_id) = group_entities(scene, &roots, "Group".to_string()) {
+        ui.hierarchy_state.expanded_entities.insert(group_id);
+        ui.select_only(group_id);
+        ui.mark_scene_modified();
+        ui.log_info(format!("Grouped {} entities", roots.len()));
+    }
+}
+
+/// Parent the selected entities (except `parent_id`) under `parent_id`
+pub fn parent_selection_to(
+    scene: &mut Scene,
+    ui: &mut EditorUi,
+    undo_history: &mut UndoHistory,
+    parent_id: EntityId,
+) {
+    let roots: Vec<EntityId> = ui
+        .selection_roots(scene)
+        .into_iter()
+        .filter(|&id| id != parent_id)
+        .collect();
+    if roots.is_empty() {
+        return;
+    }
+    let affected: Vec<EntityId> = roots.iter().copied().chain([parent_id]).collect();
+    undo_history.record_entities(scene, &affected);
+    let count = parent_entities(scene, &roots, parent_id);
+    ui.hierarchy_state.expanded_entities.insert(parent_id);
+    ui.mark_scene_modified();
+    if count < roots.len() {
+        ui.log_warning(format!(
+            "Skipped {} entities that are ancestors of the new parent",
+            roots.len() - count
+        ));
+    }
+    log::info!("Parented {} entities", count);
+}
+
+/// Align the selected entities with the active (primary) entity on one axis
+pub fn align_selection(
+    scene: &mut Scene,
+    ui: &mut EditorUi,
+    undo_history: &mut UndoHistory,
+    axis: usize,
+) {
+    let roots = ui.selection_roots(scene);
+    let Some(target) = ui.selected_entity else {
+        return;
+    };
+    if roots.len() < 2 {
+        return;
+    }
+    undo_history.record_transforms(scene, &roots);
+    let count = align_entities(scene, &roots, target, axis);
+    if count > 0 {
+        ui.mark_scene_modified();
+    }
+    log::info!("Aligned {} entities on {}", count, axis_name(axis));
+}
+
+/// Space the selected entities evenly along one axis
+pub fn distribute_selection(
+    scene: &mut Scene,
+    ui: &mut EditorUi,
+    undo_history: &mut UndoHistory,
+    axis: usize,
+) {
+    let roots = ui.selection_roots(scene);
+    if roots.len() < 3 {
+        ui.log_warning("Select at least three entities to distribute".to_string());
+        return;
+    }
+    undo_history.record_transforms(scene, &roots);
+    let count = distribute_entities(scene, &roots, axis);
+    if count > 0 {
+        ui.mark_scene_modified();
+    }
+    log::info!("Distributed {} entities along {}", roots.len(), axis_name(axis));
+}
+
+/// Find visible mesh or icon entities whose origin projects inside a screen rectangle (in pixels)
+pub fn entities_in_screen_rect(
+    scene: &Scene,
+    camera: &Camera,
+    corner_a: Vec2,
+    corner_b: Vec2,
+    screen_size: Vec2,
+    hidden_entities: &HashSet<EntityId>,
+) -> Vec<EntityId> {
+    let min = corner_a.min(corner_b);
+    let max = corner_a.max(corner_b);
+    let view_proj = camera.view_projection_matrix();
+
+    let mut result: Vec<EntityId> = scene
+        .entities()
+        .filter(|e| {
+            (e.has_component::<MeshRenderer>() || IconKind::for_entity(e).is_some())
+                && !hidden_entities.contains(&e.id)
+        })
+        .filter(|e| {
+            let clip = view_proj * world_position(scene, e.id).extend(1.0);
+            if clip.w <= 0.0 {
+                return false; // Behind the camera
+            }
+            let ndc = clip.truncate() / clip.w;
+            let screen = Vec2::new(
+                (ndc.x + 1.0) * 0.5 * screen_size.x,
+                (1.0 - ndc.y) * 0.5 * screen_size.y,
+            );
+            screen.cmpge(min).all() && screen.cmple(max).all()
+        })
+        .map(|e| e.id)
+        .collect();
+    result.sort_by_key(|id| id.0);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene_with_hierarchy() -> (Scene, EntityId, EntityId, EntityId) {
+        let mut scene = Scene::new("Test".to_string());
+        let parent = scene.create_entity_with_transform(
+            "Parent".to_string(),
+            Transform::from_position(Vec3::new(10.0, 0.0, 0.0)),
+        );
+        let child = scene.create_entity_with_transform(
+            "Child".to_string(),
+            Transform::from_position(Vec3::new(1.0, 0.0, 0.0)),
+        );
+        let other = scene.create_entity_with_transform(
+            "Other".to_string(),
+            Transform::from_position(Vec3::new(0.0, 0.0, 4.0)),
+        );
+        scene.set_parent(child, Some(parent));
+        (scene, parent, child, other)
+    }
+
+    #[test]
+    fn test_selection_roots_skip_selected_descendants() {
+        let (scene, parent, child, other) = scene_with_hierarchy();
+        let selected: HashSet<EntityId> = [parent, child, other].into_iter().collect();
+        assert_eq!(selection_roots(&scene, &selected), vec![parent, other]);
+
+        let selected: HashSet<EntityId> = [child, other].into_iter().collect();
+        assert_eq!(selection_roots(&scene, &selected), vec![child, other]);
+    }
+
+    #[test]
+    fn test_reparent_keeps_world_position() {
+        let (mut scene, parent, child, other) = scene_with_hierarchy();
+        set_parent_keep_world(&mut scene, child, None);
+        assert!((world_position(&scene, child) - Vec3::new(11.0, 0.0, 0.0)).length() < 1e-4);
+
+        assert_eq!(parent_entities(&mut scene, &[other, parent], parent), 1);
+        assert_eq!(scene.get_entity(other).unwrap().parent, Some(parent));
+        assert!((world_position(&scene, other) - Vec3::new(0.0, 0.0, 4.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn test_parent_entities_prevents_cycles() {
+        let (mut scene, parent, child, _) = scene_with_hierarchy();
+        assert_eq!(parent_entities(&mut scene, &[parent], child), 0);
+        assert_eq!(scene.get_entity(parent).unwrap().parent, None);
+    }
+
+    #[test]
+    fn test_align_and_distribute_in_world_space() {
+        let (mut scene, parent, child, other) = scene_with_hierarchy();
+        assert_eq!(align_entities(&mut scene, &[child, other], parent, 0), 2);
+        assert!((world_position(&scene, child) - Vec3::new(10.0, 0.0, 0.0)).length() < 1e-4);
+        assert!((world_position(&scene, other) - Vec3::new(10.0, 0.0, 4.0)).length() < 1e-4);
+
+        let last = scene.create_entity_with_transform(
+            "Last".to_string(),
+            Transform::from_position(Vec3::new(10.0, 0.0, 10.0)),
+        );
+        // Z positions 0 (parent), 0 (child), 4 (other), 10 (last)
+        assert_eq!(distribute_entities(&mut scene, &[parent, other, last], 2), 1);
+        assert!((world_position(&scene, other).z - 5.0).abs() < 1e-4);
+        assert_eq!(distribute_entities(&mut scene, &[parent, last], 2), 0);
+    }
+
+    #[test]
+    fn test_group_entities_at_centroid() {
+        let (mut scene, parent, _, other) = scene_with_hierarchy();
+        let group = group_entities(&mut scene, &[parent, other], "Group".to_string()).unwrap();
+        assert!((world_position(&scene, group) - Vec3::new(5.0, 0.0, 2.0)).length() < 1e-4);
+        assert_eq!(scene.get_entity(parent).unwrap().parent, Some(group));
+        assert!((world_position(&scene, parent) - Vec3::new(10.0, 0.0, 0.0)).length() < 1e-4);
+    }
+}
@@ -0,0 +1,239 @@
+// Spatial query tools - raycasts and proximity searches for MCP commands
+//
+// Queries run against the scene as the viewport shows it, not the physics
+// world (which only holds bodies while playing): meshes are hit exactly
+// through the mesh picker, terrain through its heightmap, and entities
+// without a mesh count as a point at their position.
+
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Result};
+use engine_assets::{AssetManager, HeightMap, TerrainConfig};
+use engine_render::frustum::AABB;
+use engine_scene::{entity::EntityId, scene::Scene};
+use glam::Vec3;
+use serde_json::Value;
+
+use crate::picking::MeshPicker;
+use crate::placement::TerrainRef;
+use crate::selection::{is_ancestor, world_position};
+
+/// Default reach of raycast (terrain hits are found up to this far)
+pub const DEFAULT_MAX_DISTANCE: f32 = 200.0;
+
+/// First surface a ray hits
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    /// The mesh entity hit, or None for terrain
+    pub entity: Option<EntityId>,
+    pub point: Vec3,
+    pub distance: f32,
+    /// Surface normal (terrain hits only)
+    pub normal: Option<Vec3>,
+}
+
+/// Read a required or optional [x, y, z] argument
+pub fn vec3_arg(args: &Value, key: &str) -> Result<Option<Vec3>> {
+    match args.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => {
+            let components: Option<Vec<f32>> = value
+                .as_array()
+                .and_then(|arr| arr.iter().map(|v| v.as_f64().map(|v| v as f32)).collect());
+            match components.as_deref() {
+                Some(&[x, y, z]) => Ok(Some(Vec3::new(x, y, z))),
+                _ => Err(anyhow!("'{}' must be an array [x, y, z]", key)),
+            }
+        }
+    }
+}
+
+/// Closest mesh or terrain hit along a ray within `max_distance`
+pub fn raycast(
+    scene: &Scene,
+    asset_manager: &mut AssetManager,
+    picker: &mut MeshPicker,
+    terrain: TerrainRef,
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+) -> Option<RayHit> {
+    let direction = direction.normalize_or_zero();
+    if direction == Vec3::ZERO {
+        return None;
+    }
+
+    let mesh_hit = picker
+        .pick_excluding(scene, asset_manager, origin, direction, &HashSet::new())
+        .map(|(entity, distance)| RayHit {
+            entity: Some(entity),
+            point: origin + direction * distance,
+            distance,
+            normal: None,
+        });
+    let terrain_hit = terrain.and_then(|(heightmap, config)| {
+        crate::raycast_terrain(origin, direction, heightmap, config).map(|point| RayHit {
+            entity: None,
+            point,
+            distance: point.distance(origin),
+            normal: Some(terrain_normal(heightmap, config, point.x, point.z)),
+        })
+    });
+
+    [mesh_hit, terrain_hit]
+        .into_iter()
+        .flatten()
+        .filter(|hit| hit.distance <= max_distance)
+        .min_by(|a, b| a.distance.total_cmp(&b.distance))
+}
+
+/// Terrain surface normal at a world X/Z position
+pub fn terrain_normal(heightmap: &HeightMap, config: &TerrainConfig, x: f32, z: f32) -> Vec3 {
+    // One grid cell either side
+    let step = (config.scale / heightmap.width.max(1) as f32).max(0.01);
+    let height = |x: f32, z: f32| heightmap.sample_height(x, z, config.scale);
+    Vec3::new(
+        height(x - step, z) - height(x + step, z),
+        2.0 * step,
+        height(x, z - step) - height(x, z + step),
+    )
+    .normalize()
+}
+
+/// Entities whose mesh bounds (or position, without a mesh) touch the
+/// sphere, closest first, with their distance from the center to the bounds
+pub fn overlap_sphere(
+    scene: &Scene,
+    asset_manager: &mut AssetManager,
+    picker: &mut MeshPicker,
+    center: Vec3,
+    radius: f32,
+) -> Vec<(EntityId, f32)> {
+    let ids: Vec<EntityId> = scene.entities().map(|e| e.id).collect();
+    let mut hits: Vec<(EntityId, f32)> = ids
+        .into_iter()
+        .filter_map(|id| {
+            let closest = match picker.world_bounds(scene, asset_manager, id) {
+                Some(bounds) => center.clamp(bounds.min, bounds.max),
+                None => world_position(scene, id),
+            };
+            let distance = closest.distance(center);
+            (distance <= radius).then_some((id, distance))
+        })
+        .collect();
+    hits.sort_by(|a, b| a.1.total_cmp(&b.1));
+    hits
+}
+
+/// Bounds of an entity's meshes and those of its descendants (a point at
+/// its position if none of them has a mesh)
+pub fn entity_bounds(
+    scene: &Scene,
+    asset_manager: &mut AssetManager,
+    picker: &mut MeshPicker,
+    entity_id: EntityId,
+) -> AABB {
+    let ids: Vec<EntityId> = scene
+        .entities()
+        .map(|e| e.id)
+        .filter(|&id| id == entity_id || is_ancestor(scene, entity_id, id))
+        .collect();
+    ids.into_iter()
+        .filter_map(|id| picker.world_bounds(scene, asset_manager, id))
+        .reduce(|a, b| AABB::new(a.min.min(b.min), a.max.max(b.max)))
+        .unwrap_or_else(|| {
+            let position = world_position(scene, entity_id);
+            AABB::new(position, position)
+        })
+}
+
+/// Entities whose position is within `radius` of `center`, closest first.
+/// With `horizontal`, height differences are ignored.
+pub fn entities_near(
+    scene: &Scene,
+    center: Vec3,
+    radius: f32,
+    horizontal: bool,
+) -> Vec<(EntityId, f32)> {
+    let mut hits: Vec<(EntityId, f32)> = scene
+        .entities()
+        .filter_map(|e| {
+            let mut offset = world_position(scene, e.id) - center;
+            if horizontal {
+                offset.y = 0.0;
+            }
+            let distance = offset.length();
+            (distance <= radius).then_some((e.id, distance))
+        })
+        .collect();
+    hits.sort_by(|a, b| a.1.total_cmp(&b.1));
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_scene::components::MeshRenderer;
+    use engine_scene::transform::Transform;
+    use serde_json::json;
+
+    #[test]
+    fn test_queries_find_nearby_meshes() {
+        let mut scene = Scene::new("Test".to_string());
+        let block = scene.create_entity_with_transform(
+            "Block".to_string(),
+            Transform::from_position(Vec3::new(10.0, 1.0, 5.0)),
+        );
+        scene
+            .get_entity_mut(block)
+            .unwrap()
+            .add_component(MeshRenderer::new("stone_cube".to_string()));
+        let marker = scene.create_entity_with_transform(
+            "Marker".to_string(),
+            Transform::from_position(Vec3::new(10.0, 20.0, 8.0)),
+        );
+        let mut asset_manager = AssetManager::new(std::env::temp_dir());
+        let mut picker = MeshPicker::new();
+
+        // Straight down onto the top of the block
+        let hit = raycast(
+            &scene,
+            &mut asset_manager,
+            &mut picker,
+            None,
+            Vec3::new(10.0, 10.0, 5.0),
+            Vec3::NEG_Y,
+            DEFAULT_MAX_DISTANCE,
+        )
+        .unwrap();
+        assert_eq!(hit.entity, Some(block));
+        assert!((hit.point.y - 1.5).abs() < 1e-4);
+
+        // The sphere touches the block's side but not the marker
+        let overlaps = overlap_sphere(
+            &scene,
+            &mut asset_manager,
+            &mut picker,
+            Vec3::new(11.2, 1.0, 5.0),
+            1.0,
+        );
+        assert_eq!(overlaps.len(), 1);
+        assert_eq!(overlaps[0].0, block);
+
+        let near = entities_near(&scene, Vec3::new(10.0, 0.0, 6.0), 3.0, true);
+        assert_eq!(
+            near.iter().map(|&(id, _)| id).collect::<Vec<_>>(),
+            vec![block, marker]
+        );
+        assert_eq!(
+            entities_near(&scene, Vec3::new(10.0, 0.0, 6.0), 3.0, false).len(),
+            1
+        );
+
+        assert_eq!(
+            vec3_arg(&json!({ "origin": [1, 2, 3] }), "origin").unwrap(),
+            Some(Vec3::new(1.0, 2.0, 3.0))
+        );
+        assert!(vec3_arg(&json!({ "origin": [1, 2] }), "origin").is_err());
+    }
+}
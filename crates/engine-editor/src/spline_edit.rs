@@ -0,0 +1,217 @@
+// Spline editing - rivers and roads from Spline components
+//
+// The spline tool edits the selected Spline: clicking the terrain adds a
+// control point at the end, dragging a point moves it over the terrain and
+// Shift + click removes it. River points sit on the water surface. Carving
+// is applied to the heightmap when the terrain is generated and from the
+// brush panel; meshes are rebuilt along with the terrain water.
+
+use engine_assets::{flow_downhill, river_mesh, road_mesh, HeightMap, Mesh, TerrainConfig};
+use engine_render::camera::Camera;
+use engine_scene::{entity::EntityId, scene::Scene, Spline, SplineKind};
+use glam::{Mat4, Vec2, Vec3};
+
+use crate::water_edit::project_to_screen;
+
+/// How close (pixels) a press must be to a control point to grab it
+pub const POINT_GRAB_RADIUS: f32 = 10.0;
+/// Curve samples per heightmap cell
+const SAMPLES_PER_CELL: f32 = 2.0;
+
+/// The curve as the terrain sees it: a river's water surface, never running
+/// uphill, or a road on the ground smoothed along its length
+pub fn spline_path(spline: &Spline, heightmap: &HeightMap, config: &TerrainConfig) -> Vec<Vec3> {
+    let spacing = config.scale / config.width as f32 / SAMPLES_PER_CELL;
+    let mut path = spline.sample(spacing);
+    match spline.kind {
+        SplineKind::River => flow_downhill(&mut path),
+        SplineKind::Road => {
+            let ground: Vec<f32> = path
+                .iter()
+                .map(|point| heightmap.sample_height(point.x, point.z, config.scale))
+                .collect();
+            // Average over about one road width so the road doesn't follow every bump
+            let window = (spline.width / spacing).ceil() as usize;
+            for (i, point) in path.iter_mut().enumerate() {
+                let nearby = &ground[i.saturating_sub(window)..(i + window + 1).min(ground.len())];
+                point.y = nearby.iter().sum::<f32>() / nearby.len() as f32;
+            }
+        }
+    }
+    path
+}
+
+/// Shape the heightmap along every spline that carves: riverbeds are cut
+/// below the water surface and road beds leveled. Returns true if any height changed.
+pub fn carve_splines(scene: &Scene, heightmap: &mut HeightMap, config: &TerrainConfig) -> bool {
+    let splines: Vec<Spline> = scene
+        .entities()
+        .filter_map(|entity| entity.get_component::<Spline>())
+        .filter(|spline| spline.carve_terrain && spline.points.len() >= 2)
+        .cloned()
+        .collect();
+    let mut modified = false;
+    for spline in &splines {
+        let mut path = spline_path(spline, heightmap, config);
+        let half_width = spline.width * 0.5;
+        modified |= match spline.kind {
+            SplineKind::River => {
+                for point in &mut path {
+                    point.y -= spline.depth;
+                }
+                heightmap.carve_path(&path, half_width, spline.falloff, config.scale, true)
+            }
+            SplineKind::Road => {
+                heightmap.carve_path(&path, half_width, spline.falloff, config.scale, false)
+            }
+        };
+    }
+    modified
+}
+
+/// Water surface or road mesh built along a spline
+pub struct SplineMesh {
+    pub mesh: Mesh,
+    pub kind: SplineKind,
+    pub flow_speed: f32,
+    pub material_path: Option<String>,
+}
+
+/// Meshes for every spline that generates one, named after its entity
+pub fn build_spline_meshes(
+    scene: &Scene,
+    heightmap: &HeightMap,
+    config: &TerrainConfig,
+) -> Vec<SplineMesh> {
+    scene
+        .entities()
+        .filter_map(|entity| Some((entity.id, entity.get_component::<Spline>()?)))
+        .filter(|(_, spline)| spline.generate_mesh && spline.points.len() >= 2)
+        .map(|(EntityId(id), spline)| {
+            let path = spline_path(spline, heightmap, config);
+            let mesh = match spline.kind {
+                // The surface reaches over the banks; the terrain hides what's above them
+                SplineKind::River => river_mesh(
+                    &format!("spline_river_{}", id),
+                    &path,
+                    spline.width + spline.falloff,
+                ),
+                SplineKind::Road => road_mesh(
+                    &format!("spline_road_{}", id),
+                    &path,
+                    spline.width,
+                    heightmap,
+                    config.scale,
+                ),
+            };
+            SplineMesh {
+                mesh,
+                kind: spline.kind,
+                flow_speed: spline.flow_speed,
+                material_path: spline.material_path.clone(),
+            }
+        })
+        .collect()
+}
+
+/// Spline tool state kept between frames
+#[derive(Default)]
+pub struct SplineEditState {
+    /// Control point being dragged
+    pub dragging: Option<usize>,
+}
+
+/// Screen-space (pixel) shapes drawn over the viewport by the spline tool
+#[derive(Debug, Clone, Default)]
+pub struct SplineOverlay {
+    pub curve: Vec<Vec2>,
+    /// Control points with their index
+    pub points: Vec<(usize, Vec2)>,
+    /// Point being dragged
+    pub active: Option<usize>,
+}
+
+/// Index of the control point under the cursor, if any
+pub fn pick_point(
+    spline: &Spline,
+    view_proj: Mat4,
+    screen_size: Vec2,
+    cursor: Vec2,
+) -> Option<usize> {
+    spline
+        .points
+        .iter()
+        .enumerate()
+        .filter_map(|(i, point)| {
+            let distance =
+                project_to_screen(view_proj, Vec3::from(*point), screen_size)?.distance(cursor);
+            (distance <= POINT_GRAB_RADIUS).then_some((i, distance))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+}
+
+pub fn build_overlay(
+    spline: &Spline,
+    camera: &Camera,
+    screen_size: Vec2,
+    spacing: f32,
+) -> SplineOverlay {
+    let view_proj = camera.view_projection_matrix();
+    // Slightly above the points so the line isn't hidden by the terrain
+    let project = |point: Vec3| project_to_screen(view_proj, point + Vec3::Y * 0.1, screen_size);
+    SplineOverlay {
+        curve: spline
+            .sample(spacing)
+            .into_iter()
+            .filter_map(project)
+            .collect(),
+        points: spline
+            .points
+            .iter()
+            .enumerate()
+            .filter_map(|(i, point)| Some((i, project(Vec3::from(*point))?)))
+            .collect(),
+        active: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_carve_splines_cuts_river_below_its_surface() {
+        let config = TerrainConfig {
+            width: 32,
+            depth: 32,
+            scale: 32.0,
+            ..Default::default()
+        };
+        let mut heightmap = HeightMap {
+            width: 32,
+            depth: 32,
+            heights: vec![5.0; 32 * 32],
+            holes: Vec::new(),
+        };
+        let mut scene = Scene::new("Test".to_string());
+        let id = scene.create_entity("River".to_string());
+        scene.get_entity_mut(id).unwrap().add_component(
+            Spline::new(SplineKind::River)
+                .with_points([Vec3::new(-10.0, 5.0, 0.0), Vec3::new(10.0, 5.0, 0.0)]),
+        );
+
+        assert!(carve_splines(&scene, &mut heightmap, &config));
+        assert!((heightmap.get_height(16, 16) - 4.0).abs() < 1e-4);
+        assert_eq!(heightmap.get_height(16, 28), 5.0);
+
+        let meshes = build_spline_meshes(&scene, &heightmap, &config);
+        assert_eq!(meshes.len(), 1);
+        assert_eq!(meshes[0].mesh.name, format!("spline_river_{}", id.0));
+        assert!(meshes[0]
+            .mesh
+            .vertices
+            .iter()
+            .all(|vertex| vertex.position.y == 5.0));
+    }
+}
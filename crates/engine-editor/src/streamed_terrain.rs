@@ -0,0 +1,292 @@
+// Streamed terrain - the editor side of a TerrainStreaming component
+//
+// A TerrainStreamer follows the camera; tiles that finish loading get their
+// chunk meshes uploaded under per-tile names and a heightfield collider in the
+// physics world, and tiles that unload have both removed. Sculpt brushes edit
+// the loaded tiles directly. Edited tiles are
+// written back when they unload or the scene is saved; tile edits are not in
+// the undo history, and streamed tiles have no painted layers.
+
+use engine_assets::{
+    TerrainChunk, TerrainChunks, TerrainStreamConfig, TerrainStreamer, TileCoord, TileEvent,
+    TERRAIN_LOD_LEVELS,
+};
+use engine_render::{
+    culling::CullingStats, frustum::Frustum, gpu_mesh::MeshHandle, mesh_manager::MeshManager,
+};
+use engine_physics::{terrain_heightfield, PhysicsWorld};
+use engine_scene::{components::TerrainStreaming, scene::Scene};
+use glam::{Mat4, Vec3};
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::{terrain_chunk_meshes, upload_terrain_chunks};
+
+/// Distance between height samples when raycasting the tiles
+const RAY_STEP: f32 = 0.5;
+
+/// Name prefix of the tiles' colliders in the physics world
+const COLLIDER_PREFIX: &str = "terrain_tile:";
+
+fn collider_name(coord: TileCoord) -> String {
+    format!("{}{}_{}", COLLIDER_PREFIX, coord.x, coord.z)
+}
+
+pub struct StreamedTerrain {
+    /// Component the streamer was started from, to notice edits
+    settings: TerrainStreaming,
+    streamer: TerrainStreamer,
+    /// Sculpted tiles whose colliders are out of date
+    stale_colliders: HashSet<TileCoord>,
+}
+
+impl StreamedTerrain {
+    /// Start, restart or stop streaming to match the scene's first
+    /// TerrainStreaming component
+    pub fn sync(
+        current: &mut Option<Self>,
+        scene: &Scene,
+        asset_root: &Path,
+        mesh_manager: &mut MeshManager,
+    ) {
+        let settings = scene
+            .entities()
+            .find_map(|entity| entity.get_component::<TerrainStreaming>());
+        if current.as_ref().map(|streamed| &streamed.settings) == settings {
+            return;
+        }
+        // Dropping the old streamer writes its edited tiles
+        if let Some(old) = current.take() {
+            old.evict_all(mesh_manager);
+        }
+        let Some(settings) = settings else {
+            return;
+        };
+
+        let mut config = TerrainStreamConfig::new(asset_root.join(&settings.directory));
+        config.tile_resolution = settings.tile_resolution.max(2);
+        config.tile_size = settings.tile_size.max(1.0);
+        config.load_radius = settings.load_radius.max(0.0);
+        config.max_loaded_tiles = settings.max_loaded_tiles.max(1);
+        let streamer = match TerrainStreamer::new(config) {
+            Ok(streamer) => streamer,
+            Err(e) => {
+                log::error!("Failed to start terrain streaming: {:#}", e);
+                return;
+            }
+        };
+        log::info!("Streaming terrain tiles from {}", settings.directory);
+        *current = Some(Self {
+            settings: settings.clone(),
+            streamer,
+            stale_colliders: HashSet::new(),
+        });
+    }
+
+    /// Give every loaded tile a heightfield collider in `physics_world` and
+    /// remove those of tiles that unloaded (all of them without streaming).
+    /// The physics world is rebuilt when play starts and stops, so this
+    /// checks it every frame rather than following load events.
+    pub fn sync_colliders(current: Option<&mut Self>, physics_world: &mut PhysicsWorld) {
+        let loaded: HashSet<String> = current
+            .as_ref()
+            .map(|streamed| streamed.streamer.tiles().map(|(coord, _)| collider_name(coord)).collect())
+            .unwrap_or_default();
+        let unloaded: Vec<String> = physics_world
+            .static_collider_names()
+            .filter(|name| name.starts_with(COLLIDER_PREFIX) && !loaded.contains(*name))
+            .map(str::to_string)
+            .collect();
+        for name in unloaded {
+            physics_world.remove_static_collider(&name);
+        }
+
+        let Some(streamed) = current else {
+            return;
+        };
+        let config = streamed.streamer.config();
+        let tile_config = config.tile_config();
+        for (coord, height_map) in streamed.streamer.tiles() {
+            let name = collider_name(coord);
+            if physics_world.has_static_collider(&name) && !streamed.stale_colliders.contains(&coord) {
+                continue;
+            }
+            let Some(collider) = terrain_heightfield(height_map, &tile_config, config.tile_offset(coord)) else {
+                continue;
+            };
+            physics_world.set_static_collider(name, collider.build());
+        }
+        streamed.stale_colliders.clear();
+    }
+
+    /// Stream tiles around the camera and upload or drop their meshes
+    pub fn update(
+        &mut self,
+        camera_position: Vec3,
+        mesh_manager: &mut MeshManager,
+        device: &wgpu::Device,
+    ) {
+        let layout = self.layout();
+        for event in self.streamer.update(camera_position) {
+            match event {
+                TileEvent::Loaded(coord) => {
+                    self.upload_tile(coord, layout.chunks(), mesh_manager, device)
+                }
+                TileEvent::Unloaded(coord) => evict_tile(coord, &layout, mesh_manager),
+            }
+        }
+    }
+
+    /// Write edited tiles to disk now rather than when they unload
+    pub fn save_dirty(&mut self) -> usize {
+        self.streamer.save_dirty()
+    }
+
+    pub fn material_path(&self) -> &str {
+        self.settings
+            .material_path
+            .as_deref()
+            .unwrap_or("materials/default.mat")
+    }
+
+    /// Chunk meshes of every loaded tile with their world matrices, each at
+    /// the LOD level for its distance. With a frustum, chunks outside it are culled.
+    pub fn chunk_meshes(
+        &self,
+        mesh_manager: &MeshManager,
+        camera_position: Vec3,
+        mut frustum: Option<(&Frustum, &mut CullingStats)>,
+    ) -> Vec<(MeshHandle, Mat4)> {
+        let config = self.streamer.config();
+        let tile_config = config.tile_config();
+        let layout = self.layout();
+        let mut meshes = Vec::new();
+        for (coord, _) in self.streamer.tiles() {
+            let world_matrix = Mat4::from_translation(config.tile_offset(coord));
+            let chunks = terrain_chunk_meshes(
+                mesh_manager,
+                &layout,
+                &tile_config,
+                world_matrix,
+                camera_position,
+                frustum
+                    .as_mut()
+                    .map(|(frustum, culling)| (*frustum, &mut **culling)),
+                |chunk, lod| coord.chunk_mesh_name(*chunk, lod),
+            );
+            meshes.extend(chunks.into_iter().map(|chunk| (chunk, world_matrix)));
+        }
+        meshes
+    }
+
+    /// First point a ray hits on the loaded tiles, marching out to the load radius
+    pub fn raycast(&self, ray_origin: Vec3, ray_direction: Vec3) -> Option<Vec3> {
+        // Last sample over a loaded tile, while it was above the ground
+        let mut above: Option<(Vec3, f32)> = None;
+        let mut t = 0.0;
+        while t < self.streamer.config().load_radius {
+            let point = ray_origin + ray_direction * t;
+            match self.streamer.height_at(point.x, point.z) {
+                Some(height) if point.y <= height => {
+                    // Interpolate between the last sample above and this one
+                    if let Some((prev, prev_height)) = above {
+                        let prev_gap = prev.y - prev_height;
+                        let blend = prev_gap / (prev_gap + height - point.y);
+                        return Some(prev + (point - prev) * blend);
+                    }
+                    above = None;
+                }
+                Some(height) => above = Some((point, height)),
+                None => above = None,
+            }
+            t += RAY_STEP;
+        }
+        None
+    }
+
+    /// Apply a sculpt brush (see `HeightMap::apply_brush`) to every tile under
+    /// it and re-upload the chunks it changed. Returns true if any height changed.
+    pub fn sculpt(
+        &mut self,
+        hit_point: Vec3,
+        radius: f32,
+        strength: f32,
+        brush_mode: u8,
+        mesh_manager: &mut MeshManager,
+        device: &wgpu::Device,
+    ) -> bool {
+        let config = self.streamer.config().clone();
+        let scale = config.tile_config().scale;
+        let layout = self.layout();
+        let min = config.tile_at(hit_point.x - radius, hit_point.z - radius);
+        let max = config.tile_at(hit_point.x + radius, hit_point.z + radius);
+
+        let mut modified = false;
+        for coord in
+            (min.z..=max.z).flat_map(|z| (min.x..=max.x).map(move |x| TileCoord::new(x, z)))
+        {
+            // Brushes work in the tile's own coordinates, centered on the tile
+            let offset = config.tile_offset(coord);
+            let (local_x, local_z) = (hit_point.x - offset.x, hit_point.z - offset.z);
+            let Some(bounds) = self
+                .streamer
+                .tile(coord)
+                .and_then(|height_map| height_map.brush_bounds(local_x, local_z, scale, radius))
+            else {
+                continue;
+            };
+            let Some(height_map) = self.streamer.tile_mut(coord) else {
+                continue;
+            };
+            if height_map.apply_brush(local_x, local_z, scale, radius, strength, brush_mode) {
+                modified = true;
+                self.stale_colliders.insert(coord);
+                self.upload_tile(coord, layout.chunks_touching(bounds), mesh_manager, device);
+            }
+        }
+        modified
+    }
+
+    fn layout(&self) -> TerrainChunks {
+        let resolution = self.streamer.config().tile_resolution;
+        TerrainChunks::new(resolution, resolution)
+    }
+
+    fn upload_tile(
+        &self,
+        coord: TileCoord,
+        chunks: impl IntoIterator<Item = TerrainChunk>,
+        mesh_manager: &mut MeshManager,
+        device: &wgpu::Device,
+    ) {
+        if let Some(height_map) = self.streamer.tile(coord) {
+            upload_terrain_chunks(
+                mesh_manager,
+                device,
+                height_map,
+                &self.streamer.config().tile_config(),
+                None,
+                &[],
+                chunks,
+                |chunk, lod| coord.chunk_mesh_name(*chunk, lod),
+            );
+        }
+    }
+
+    fn evict_all(&self, mesh_manager: &mut MeshManager) {
+        let layout = self.layout();
+        for (coord, _) in self.streamer.tiles() {
+            evict_tile(coord, &layout, mesh_manager);
+        }
+    }
+}
+
+fn evict_tile(coord: TileCoord, layout: &TerrainChunks, mesh_manager: &mut MeshManager) {
+    for chunk in layout.chunks() {
+        for lod in 0..TERRAIN_LOD_LEVELS {
+            if let Some(handle) = mesh_manager.get_handle(&coord.chunk_mesh_name(chunk, lod)) {
+                mesh_manager.evict(handle);
+            }
+        }
+    }
+}
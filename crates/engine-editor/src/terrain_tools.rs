@@ -0,0 +1,581 @@
+// Terrain tools - procedural terrain parameters and scripted sculpting
+//
+// generate_terrain edits the TerrainGenerator component and rebuilds the
+// heightmap from it; sculpt_terrain applies shape operations at world
+// coordinates so a landscape can be blocked out without the brush. Sculpted
+// heights live in the editor's heightmap, the same as brush strokes.
+
+use anyhow::{anyhow, bail, Result};
+use engine_assets::terrain_streaming::{read_tile, write_tile};
+use engine_assets::{AssetManager, HeightMap, SplatMap, TerrainConfig, Texture};
+use engine_scene::components::TerrainGenerator;
+use engine_scene::scene::Scene;
+use serde_json::Value;
+use std::path::Path;
+
+use crate::material_tools::asset_file;
+
+/// Largest grid resolution generate_terrain accepts per side
+pub const MAX_RESOLUTION: usize = 1024;
+
+/// The editor's terrain, as the IPC commands see it
+pub struct TerrainData<'a> {
+    pub heightmap: &'a mut Option<HeightMap>,
+    pub config: &'a mut Option<TerrainConfig>,
+    /// Texture paint (restored by undo steps)
+    pub splatmap: &'a mut Option<SplatMap>,
+}
+
+/// Noise settings of a TerrainGenerator as a TerrainConfig
+pub fn terrain_config(generator: &TerrainGenerator) -> TerrainConfig {
+    TerrainConfig {
+        width: generator.width,
+        depth: generator.depth,
+        scale: generator.scale,
+        height_scale: generator.height_scale,
+        seed: generator.seed,
+        octaves: generator.octaves,
+        frequency: generator.frequency,
+        lacunarity: generator.lacunarity,
+        persistence: generator.persistence,
+    }
+}
+
+/// Build the heightmap a TerrainGenerator describes: its imported heightmap
+/// if it has one that fits the grid, else the noise
+pub fn generate_heightmap(generator: &TerrainGenerator, asset_root: &Path) -> (HeightMap, TerrainConfig) {
+    let config = terrain_config(generator);
+    if let Some(path) = &generator.heightmap {
+        match read_tile(&asset_root.join(path)) {
+            Ok(heightmap) if heightmap.width == config.width && heightmap.depth == config.depth => {
+                return (heightmap, config);
+            }
+            Ok(heightmap) => log::warn!(
+                "Heightmap {} is {}x{}, not the generator's {}x{}; using the noise",
+                path, heightmap.width, heightmap.depth, config.width, config.depth
+            ),
+            Err(e) => log::warn!("Failed to load heightmap {}: {:#}", path, e),
+        }
+    }
+    let heightmap = if generator.moat_enabled {
+        HeightMap::generate_with_moat(
+            &config,
+            generator.moat_inner_radius,
+            generator.moat_outer_radius,
+            generator.moat_depth,
+        )
+    } else {
+        HeightMap::generate(&config)
+    };
+    (heightmap, config)
+}
+
+/// Heightmap of the scene's first TerrainGenerator with its splines carved
+/// in, as the editor builds it when it opens the scene (None without one)
+pub fn scene_terrain(scene: &Scene, asset_root: &Path) -> Option<(HeightMap, TerrainConfig)> {
+    let generator = scene.entities().find_map(|e| e.get_component::<TerrainGenerator>())?;
+    let (mut heightmap, config) = generate_heightmap(generator, asset_root);
+    crate::spline_edit::carve_splines(scene, &mut heightmap, &config);
+    Some((heightmap, config))
+}
+
+/// Save an imported heightmap under the asset root as
+/// `terrain/<file name>.height` and return the path a TerrainGenerator
+/// references it by
+pub fn save_imported_heightmap(heightmap: &HeightMap, source: &Path, asset_root: &Path) -> Result<String> {
+    let name = source.file_stem().and_then(|stem| stem.to_str()).unwrap_or("heightmap");
+    let path = format!("terrain/{}.height", name);
+    write_tile(&asset_root.join(&path), heightmap)?;
+    Ok(path)
+}
+
+/// The splatmap saved next to `scene_path` if it fits the terrain grid,
+/// else a fresh one covered by the first layer
+pub fn scene_splatmap(asset_manager: &AssetManager, scene_path: Option<&str>, config: &TerrainConfig, layer_count: usize) -> SplatMap {
+    let saved = scene_path
+        .map(SplatMap::path_for_scene)
+        .filter(|path| asset_manager.file_exists(path))
+        .and_then(|path| match asset_manager.read_file(&path).and_then(|bytes| SplatMap::from_bytes(&bytes)) {
+            Ok(splatmap) => Some(splatmap),
+            Err(e) => {
+                log::warn!("{:#}", e);
+                None
+            }
+        });
+    match saved {
+        Some(splatmap) if splatmap.width == config.width && splatmap.depth == config.depth && splatmap.layer_count == layer_count => splatmap,
+        Some(_) => {
+            log::warn!("Saved splatmap doesn't match the {}x{} terrain, starting a new one", config.width, config.depth);
+            SplatMap::new(config.width, config.depth, layer_count)
+        }
+        None => SplatMap::new(config.width, config.depth, layer_count),
+    }
+}
+
+/// Apply generate_terrain arguments to a generator. `size` is the world size
+/// (the generator's `scale`); `moat` is a bool or an object with
+/// `inner_radius`, `outer_radius` and `depth`.
+pub fn apply_generator_args(generator: &mut TerrainGenerator, args: &Value) -> Result<()> {
+    let mut updated = generator.clone();
+    // The terrain is generated from the noise again, not the import
+    updated.heightmap = None;
+    let number = |key: &str| args.get(key).and_then(|v| v.as_f64());
+
+    if let Some(width) = args.get("width").and_then(|v| v.as_u64()) {
+        updated.width = width as usize;
+    }
+    if let Some(depth) = args.get("depth").and_then(|v| v.as_u64()) {
+        updated.depth = depth as usize;
+    }
+    if let Some(size) = number("size").or_else(|| number("scale")) {
+        updated.scale = size as f32;
+    }
+    if let Some(height_scale) = number("height_scale") {
+        updated.height_scale = height_scale as f32;
+    }
+    if let Some(seed) = args.get("seed").and_then(|v| v.as_u64()) {
+        updated.seed = seed as u32;
+    }
+    if let Some(octaves) = args.get("octaves").and_then(|v| v.as_u64()) {
+        updated.octaves = octaves as usize;
+    }
+    if let Some(frequency) = number("frequency") {
+        updated.frequency = frequency;
+    }
+    if let Some(lacunarity) = number("lacunarity") {
+        updated.lacunarity = lacunarity;
+    }
+    if let Some(persistence) = number("persistence") {
+        updated.persistence = persistence;
+    }
+    match args.get("moat") {
+        Some(Value::Bool(enabled)) => updated.moat_enabled = *enabled,
+        Some(Value::Object(moat)) => {
+            updated.moat_enabled = moat
+                .get("enabled")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(true);
+            let field = |key: &str| moat.get(key).and_then(|v| v.as_f64());
+            if let Some(inner) = field("inner_radius") {
+                updated.moat_inner_radius = inner;
+            }
+            if let Some(outer) = field("outer_radius") {
+                updated.moat_outer_radius = outer;
+            }
+            if let Some(depth) = field("depth") {
+                updated.moat_depth = depth;
+            }
+        }
+        None | Some(Value::Null) => {}
+        Some(_) => bail!("'moat' must be true, false or an object"),
+    }
+
+    if !(2..=MAX_RESOLUTION).contains(&updated.width)
+        || !(2..=MAX_RESOLUTION).contains(&updated.depth)
+    {
+        bail!("width and depth must be between 2 and {}", MAX_RESOLUTION);
+    }
+    if updated.scale <= 0.0 {
+        bail!("size must be positive");
+    }
+    if updated.octaves == 0 || updated.octaves > 16 {
+        bail!("octaves must be between 1 and 16");
+    }
+    if updated.moat_enabled && updated.moat_inner_radius >= updated.moat_outer_radius {
+        bail!("moat inner_radius must be smaller than outer_radius");
+    }
+
+    *generator = updated;
+    Ok(())
+}
+
+/// How a stamp combines with the heights under it
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StampBlend {
+    Add,
+    Max,
+    Replace,
+}
+
+/// Grayscale stamp, row-major with the first row at -Z
+struct Stamp {
+    width: usize,
+    depth: usize,
+    values: Vec<f32>,
+}
+
+impl Stamp {
+    /// Bilinear sample at (u, v) in 0-1
+    fn sample(&self, u: f32, v: f32) -> f32 {
+        let x = u.clamp(0.0, 1.0) * (self.width - 1) as f32;
+        let z = v.clamp(0.0, 1.0) * (self.depth - 1) as f32;
+        let (x0, z0) = (x.floor() as usize, z.floor() as usize);
+        let (x1, z1) = ((x0 + 1).min(self.width - 1), (z0 + 1).min(self.depth - 1));
+        let (fx, fz) = (x - x0 as f32, z - z0 as f32);
+        let at = |x: usize, z: usize| self.values[z * self.width + x];
+        let top = at(x0, z0) * (1.0 - fx) + at(x1, z0) * fx;
+        let bottom = at(x0, z1) * (1.0 - fx) + at(x1, z1) * fx;
+        top * (1.0 - fz) + bottom * fz
+    }
+}
+
+enum SculptOp {
+    /// Raise (negative: lower) by `amount` at the center, easing out to the radius
+    Raise { amount: f32 },
+    /// Blend toward `height` with `strength` (0-1)
+    Flatten { height: f32, strength: f32 },
+    /// Blend toward the neighbour average with `strength` (0-1)
+    Smooth { strength: f32 },
+    /// Stamp a grayscale image scaled to `height` over a `size` square
+    Stamp {
+        stamp: Stamp,
+        size: f32,
+        height: f32,
+        blend: StampBlend,
+    },
+}
+
+/// Outcome of one sculpt operation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SculptResult {
+    /// Grid points whose height changed
+    pub modified: usize,
+    /// Height at the operation's center afterwards
+    pub center_height: f32,
+}
+
+/// Apply one sculpt_terrain operation. `position` is [x, z] in world units;
+/// `radius`, `amount`, `height` and `size` are in world units too.
+pub fn sculpt(
+    heightmap: &mut HeightMap,
+    config: &TerrainConfig,
+    args: &Value,
+    asset_root: &Path,
+) -> Result<SculptResult> {
+    let operation = args
+        .get("operation")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Missing operation"))?;
+    let position = args
+        .get("position")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| anyhow!("Missing position [x, z]"))?;
+    let coordinate = |index: usize| {
+        position
+            .get(index)
+            .and_then(|v| v.as_f64())
+            .map(|v| v as f32)
+            .ok_or_else(|| anyhow!("position must be [x, z]"))
+    };
+    let (center_x, center_z) = (coordinate(0)?, coordinate(1)?);
+    let number = |key: &str| args.get(key).and_then(|v| v.as_f64()).map(|v| v as f32);
+    let radius = number("radius").unwrap_or(5.0);
+    let strength = number("strength").unwrap_or(1.0).clamp(0.0, 1.0);
+    if radius <= 0.0 {
+        bail!("radius must be positive");
+    }
+
+    let op = match operation {
+        "raise" => SculptOp::Raise {
+            amount: number("amount").unwrap_or(1.0),
+        },
+        "lower" => SculptOp::Raise {
+            amount: -number("amount").unwrap_or(1.0),
+        },
+        "flatten" => SculptOp::Flatten {
+            height: number("height")
+                .unwrap_or_else(|| heightmap.sample_height(center_x, center_z, config.scale)),
+            strength,
+        },
+        "smooth" => SculptOp::Smooth { strength },
+        "stamp" => SculptOp::Stamp {
+            stamp: load_stamp(args, asset_root)?,
+            size: number("size").unwrap_or(radius * 2.0),
+            height: number("height").unwrap_or(config.height_scale),
+            blend: match args.get("blend").and_then(|v| v.as_str()).unwrap_or("add") {
+                "add" => StampBlend::Add,
+                "max" => StampBlend::Max,
+                "replace" => StampBlend::Replace,
+                other => bail!("Unknown blend '{}' (expected add, max or replace)", other),
+            },
+        },
+        other => bail!(
+            "Unknown operation '{}' (expected raise, lower, flatten, smooth or stamp)",
+            other
+        ),
+    };
+
+    // Stamps cover a square; everything else a circle
+    let half_extent = match &op {
+        SculptOp::Stamp { size, .. } => size * 0.5,
+        _ => radius,
+    };
+    let (min_x, max_x) = grid_range(
+        center_x - half_extent,
+        center_x + half_extent,
+        heightmap.width,
+        config.scale,
+    );
+    let (min_z, max_z) = grid_range(
+        center_z - half_extent,
+        center_z + half_extent,
+        heightmap.depth,
+        config.scale,
+    );
+    if min_x > max_x || min_z > max_z {
+        bail!("({}, {}) is off the terrain", center_x, center_z);
+    }
+
+    let (width, depth) = (heightmap.width, heightmap.depth);
+    let before = heightmap.heights.clone();
+    let average = |x: usize, z: usize| {
+        let mut sum = 0.0;
+        let mut count = 0;
+        for nz in z.saturating_sub(1)..=(z + 1).min(depth - 1) {
+            for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                sum += before[nz * width + nx];
+                count += 1;
+            }
+        }
+        sum / count as f32
+    };
+
+    let mut modified = 0;
+    for z in min_z..=max_z {
+        for x in min_x..=max_x {
+            let world_x = grid_to_world(x, width, config.scale);
+            let world_z = grid_to_world(z, depth, config.scale);
+            let current = before[z * width + x];
+            let weight = falloff((world_x - center_x).hypot(world_z - center_z), radius);
+
+            let new_height = match &op {
+                SculptOp::Raise { amount } => current + amount * weight,
+                SculptOp::Flatten { height, strength } => {
+                    current + (height - current) * weight * strength
+                }
+                SculptOp::Smooth { strength } => {
+                    current + (average(x, z) - current) * weight * strength
+                }
+                SculptOp::Stamp {
+                    stamp,
+                    size,
+                    height,
+                    blend,
+                } => {
+                    let u = (world_x - (center_x - size * 0.5)) / size;
+                    let v = (world_z - (center_z - size * 0.5)) / size;
+                    if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+                        continue;
+                    }
+                    let value = stamp.sample(u, v) * height;
+                    match blend {
+                        StampBlend::Add => current + value,
+                        StampBlend::Max => current.max(value),
+                        StampBlend::Replace => value,
+                    }
+                }
+            };
+
+            if new_height != current {
+                heightmap.set_height(x, z, new_height);
+                modified += 1;
+            }
+        }
+    }
+
+    Ok(SculptResult {
+        modified,
+        center_height: heightmap.sample_height(center_x, center_z, config.scale),
+    })
+}
+
+/// Stamp from `heights` (rows of 0-1 values) or `image` (a grayscale image
+/// path relative to the asset root)
+fn load_stamp(args: &Value, asset_root: &Path) -> Result<Stamp> {
+    if let Some(rows) = args.get("heights").and_then(|v| v.as_array()) {
+        let rows: Vec<Vec<f32>> = rows
+            .iter()
+            .map(|row| {
+                row.as_array()
+                    .and_then(|row| row.iter().map(|v| v.as_f64().map(|v| v as f32)).collect())
+                    .ok_or_else(|| anyhow!("heights must be an array of rows of numbers"))
+            })
+            .collect::<Result<_>>()?;
+        let width = rows.first().map_or(0, |row| row.len());
+        if rows.len() < 2 || width < 2 || rows.iter().any(|row| row.len() != width) {
+            bail!("heights must be at least 2x2 with rows of equal length");
+        }
+        return Ok(Stamp {
+            width,
+            depth: rows.len(),
+            values: rows.into_iter().flatten().collect(),
+        });
+    }
+
+    let image = args
+        .get("image")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("A stamp needs heights or image"))?;
+    let texture = Texture::from_file(asset_file(asset_root, image)?)?;
+    if texture.width < 2 || texture.height < 2 {
+        bail!("Stamp image '{}' is smaller than 2x2", image);
+    }
+    let channels = texture.bytes_per_pixel() as usize;
+    Ok(Stamp {
+        width: texture.width as usize,
+        depth: texture.height as usize,
+        values: texture
+            .data
+            .chunks_exact(channels)
+            .map(|pixel| pixel[0] as f32 / 255.0)
+            .collect(),
+    })
+}
+
+/// Smooth 1-to-0 falloff over `radius`
+fn falloff(distance: f32, radius: f32) -> f32 {
+    let t = (1.0 - distance / radius).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// World coordinate of a grid line (the terrain is centered on the origin)
+fn grid_to_world(index: usize, resolution: usize, scale: f32) -> f32 {
+    (index as f32 - resolution as f32 * 0.5) / resolution as f32 * scale
+}
+
+/// Grid indices covering world coordinates min..=max (empty when off the map)
+fn grid_range(min: f32, max: f32, resolution: usize, scale: f32) -> (usize, usize) {
+    let to_grid = |world: f32| world / scale * resolution as f32 + resolution as f32 * 0.5;
+    let low = to_grid(min).ceil().max(0.0);
+    let high = to_grid(max).floor().min(resolution as f32 - 1.0);
+    if low > high {
+        return (1, 0);
+    }
+    (low as usize, high as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn flat_terrain() -> (HeightMap, TerrainConfig) {
+        let config = TerrainConfig {
+            width: 32,
+            depth: 32,
+            scale: 32.0,
+            ..TerrainConfig::default()
+        };
+        let heightmap = HeightMap {
+            width: 32,
+            depth: 32,
+            heights: vec![0.0; 32 * 32],
+            holes: Vec::new(),
+        };
+        (heightmap, config)
+    }
+
+    #[test]
+    fn test_sculpt_operations() {
+        let (mut heightmap, config) = flat_terrain();
+        let root = Path::new(".");
+
+        let raised = sculpt(
+            &mut heightmap,
+            &config,
+            &json!({ "operation": "raise", "position": [0.0, 0.0], "radius": 4.0, "amount": 2.0 }),
+            root,
+        )
+        .unwrap();
+        assert_eq!(raised.center_height, 2.0);
+        assert!(raised.modified > 0);
+        assert_eq!(heightmap.sample_height(10.0, 10.0, config.scale), 0.0);
+
+        let flattened = sculpt(
+            &mut heightmap,
+            &config,
+            &json!({ "operation": "flatten", "position": [0.0, 0.0], "radius": 2.0, "height": 1.0 }),
+            root,
+        )
+        .unwrap();
+        assert_eq!(flattened.center_height, 1.0);
+
+        let stamped = sculpt(
+            &mut heightmap,
+            &config,
+            &json!({
+                "operation": "stamp",
+                "position": [8.0, 8.0],
+                "size": 4.0,
+                "height": 3.0,
+                "blend": "replace",
+                "heights": [[1.0, 1.0], [1.0, 1.0]]
+            }),
+            root,
+        )
+        .unwrap();
+        assert_eq!(stamped.center_height, 3.0);
+        assert_eq!(heightmap.sample_height(-8.0, -8.0, config.scale), 0.0);
+
+        assert!(sculpt(
+            &mut heightmap,
+            &config,
+            &json!({ "operation": "raise", "position": [100.0, 0.0] }),
+            root,
+        )
+        .is_err());
+        assert!(sculpt(
+            &mut heightmap,
+            &config,
+            &json!({ "operation": "dig", "position": [0.0, 0.0] }),
+            root,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_generator_args() {
+        let mut generator = TerrainGenerator::default();
+        apply_generator_args(
+            &mut generator,
+            &json!({ "size": 120.0, "seed": 7, "moat": { "inner_radius": 0.1, "outer_radius": 0.3 } }),
+        )
+        .unwrap();
+        assert_eq!(generator.scale, 120.0);
+        assert_eq!(generator.seed, 7);
+        assert!(generator.moat_enabled);
+        assert_eq!(generator.moat_outer_radius, 0.3);
+
+        // Invalid values leave the generator unchanged
+        assert!(apply_generator_args(&mut generator, &json!({ "width": 1, "seed": 9 })).is_err());
+        assert_eq!(generator.seed, 7);
+
+        let (heightmap, config) = generate_heightmap(&generator, Path::new("."));
+        assert_eq!(heightmap.heights.len(), config.width * config.depth);
+    }
+
+    #[test]
+    fn test_generator_loads_imported_heightmap() {
+        let asset_root = std::env::temp_dir().join(format!("causality_terrain_test_{}", std::process::id()));
+        let mut generator = TerrainGenerator { width: 8, depth: 8, ..Default::default() };
+        let mut imported = HeightMap { width: 8, depth: 8, heights: vec![0.0; 64], holes: Vec::new() };
+        imported.set_height(3, 4, 12.5);
+        let path = save_imported_heightmap(&imported, Path::new("/elsewhere/hills.png"), &asset_root).unwrap();
+        assert_eq!(path, "terrain/hills.height");
+
+        generator.heightmap = Some(path);
+        let (heightmap, _) = generate_heightmap(&generator, &asset_root);
+        assert_eq!(heightmap.get_height(3, 4), 12.5);
+
+        // A heightmap that no longer fits the grid falls back to the noise
+        generator.width = 16;
+        let (heightmap, config) = generate_heightmap(&generator, &asset_root);
+        assert_eq!(heightmap.width, config.width);
+
+        // Regenerating from arguments drops the import
+        apply_generator_args(&mut generator, &json!({ "seed": 3 })).unwrap();
+        assert_eq!(generator.heightmap, None);
+
+        let _ = std::fs::remove_dir_all(&asset_root);
+    }
+}
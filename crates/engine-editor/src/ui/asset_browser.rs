@@ -0,0 +1,487 @@
+// Asset browser panel - browse the assets directory and drag models into the scene
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use egui::{Context, ScrollArea};
+use engine_assets::texture::{Texture, TextureFormat};
+
+/// Thumbnail edge length in pixels
+const THUMBNAIL_SIZE: u32 = 64;
+/// Maximum thumbnails decoded per frame (keeps the UI responsive on large folders)
+const THUMBNAILS_PER_FRAME: usize = 2;
+
+/// Asset category, determined by file extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssetKind {
+    Folder,
+    Texture,
+    Model,
+    Material,
+    Scene,
+    Script,
+    Audio,
+    Other,
+}
+
+impl AssetKind {
+    /// Classify a path relative to the asset root
+    pub fn from_path(path: &Path) -> Self {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "png" | "jpg" | "jpeg" | "bmp" | "tga" | "hdr" => AssetKind::Texture,
+            "gltf" | "glb" => AssetKind::Model,
+            "mat" => AssetKind::Material,
+            // Other .ron files (e.g. music/moments.ron) are config, not scenes
+            "ron" if path.starts_with("scenes") => AssetKind::Scene,
+            "rhai" => AssetKind::Script,
+            "wav" | "ogg" | "mp3" | "flac" => AssetKind::Audio,
+            _ => AssetKind::Other,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AssetKind::Folder => "Folder",
+            AssetKind::Texture => "Texture",
+            AssetKind::Model => "Model",
+            AssetKind::Material => "Material",
+            AssetKind::Scene => "Scene",
+            AssetKind::Script => "Script",
+            AssetKind::Audio => "Audio",
+            AssetKind::Other => "File",
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            AssetKind::Folder => "📁",
+            AssetKind::Texture => "🖼",
+            AssetKind::Model => "◆",
+            AssetKind::Material => "●",
+            AssetKind::Scene => "🗺",
+            AssetKind::Script => "📜",
+            AssetKind::Audio => "♫",
+            AssetKind::Other => "📄",
+        }
+    }
+}
+
+/// A file or folder in the asset browser
+#[derive(Debug, Clone)]
+pub struct AssetEntry {
+    /// Path relative to the asset root
+    pub path: PathBuf,
+    pub name: String,
+    pub kind: AssetKind,
+}
+
+impl AssetEntry {
+    /// Relative path with forward slashes (as used by MeshRenderer and scenes)
+    pub fn path_string(&self) -> String {
+        self.path.to_string_lossy().replace('\\', "/")
+    }
+}
+
+/// Drag-and-drop payload for assets dragged out of the browser
+#[derive(Debug, Clone)]
+pub struct AssetDragPayload {
+    pub path: String,
+    pub kind: AssetKind,
+}
+
+/// Actions returned from the asset browser
+#[derive(Default)]
+pub struct AssetBrowserAction {
+    /// Model to place (double-clicked), at the viewport center
+    pub spawn_model: Option<(String, Option<(f32, f32)>)>,
+    /// Scene to open (double-clicked)
+    pub open_scene: Option<String>,
+}
+
+/// State for the asset browser (stored in EditorUi)
+pub struct AssetBrowserState {
+    /// Asset root directory on disk
+    pub root: PathBuf,
+    /// Current folder relative to the root
+    pub current_dir: PathBuf,
+    /// Search filter (searches all folders when non-empty)
+    pub search: String,
+    /// Only show assets of this kind
+    pub kind_filter: Option<AssetKind>,
+    /// Cached listing of the current folder or search results
+    entries: Vec<AssetEntry>,
+    needs_rescan: bool,
+    /// Texture thumbnails (None = failed to load)
+    thumbnails: HashMap<PathBuf, Option<egui::TextureHandle>>,
+}
+
+impl Default for AssetBrowserState {
+    fn default() -> Self {
+        Self::new("assets")
+    }
+}
+
+impl AssetBrowserState {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            current_dir: PathBuf::new(),
+            search: String::new(),
+            kind_filter: None,
+            entries: Vec::new(),
+            needs_rescan: true,
+            thumbnails: HashMap::new(),
+        }
+    }
+
+    /// Re-scan the assets directory on the next frame (e.g. after hot reload)
+    pub fn refresh(&mut self) {
+        self.needs_rescan = true;
+        self.thumbnails.clear();
+    }
+
+    /// Navigate into a folder (relative to the asset root)
+    pub fn open_folder(&mut self, dir: PathBuf) {
+        self.current_dir = dir;
+        self.search.clear();
+        self.needs_rescan = true;
+    }
+
+    fn rescan(&mut self) {
+        self.entries = if self.search.is_empty() {
+            list_directory(&self.root, &self.current_dir)
+        } else {
+            search_assets(&self.root, &self.search)
+        };
+        if let Some(kind) = self.kind_filter {
+            self.entries
+                .retain(|e| e.kind == kind || e.kind == AssetKind::Folder);
+        }
+        self.needs_rescan = false;
+    }
+
+    /// Get (loading if needed) the thumbnail for a texture asset
+    fn thumbnail(
+        &mut self,
+        ctx: &Context,
+        entry: &AssetEntry,
+        budget: &mut usize,
+    ) -> Option<egui::TextureHandle> {
+        if let Some(handle) = self.thumbnails.get(&entry.path) {
+            return handle.clone();
+        }
+        if *budget == 0 {
+            return None;
+        }
+        *budget -= 1;
+
+        let handle = Texture::from_file(self.root.join(&entry.path))
+            .ok()
+            .map(|texture| {
+                let image = make_thumbnail(&texture, THUMBNAIL_SIZE);
+                ctx.load_texture(
+                    format!("asset_thumb:{}", entry.path_string()),
+                    image,
+                    egui::TextureOptions::LINEAR,
+                )
+            });
+        self.thumbnails.insert(entry.path.clone(), handle.clone());
+        handle
+    }
+}
+
+/// List a folder: subfolders first, then files, each sorted by name
+pub fn list_directory(root: &Path, dir: &Path) -> Vec<AssetEntry> {
+    let Ok(read_dir) = std::fs::read_dir(root.join(dir)) else {
+        return Vec::new();
+    };
+
+    let mut entries: Vec<AssetEntry> = read_dir
+        .flatten()
+        .filter_map(|item| {
+            let name = item.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') {
+                return None;
+            }
+            let path = dir.join(&name);
+            let kind = if item.file_type().ok()?.is_dir() {
+                AssetKind::Folder
+            } else {
+                AssetKind::from_path(&path)
+            };
+            Some(AssetEntry { path, name, kind })
+        })
+        .collect();
+
+    entries.sort_by(|a, b| {
+        (a.kind != AssetKind::Folder)
+            .cmp(&(b.kind != AssetKind::Folder))
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+    entries
+}
+
+/// Recursively find files whose name contains the query (case-insensitive)
+pub fn search_assets(root: &Path, query: &str) -> Vec<AssetEntry> {
+    let query = query.to_lowercase();
+    let mut results = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+
+    while let Some(dir) = pending.pop() {
+        for entry in list_directory(root, &dir) {
+            if entry.kind == AssetKind::Folder {
+                pending.push(entry.path);
+            } else if entry.name.to_lowercase().contains(&query) {
+                results.push(entry);
+            }
+        }
+    }
+
+    results.sort_by(|a, b| a.path.cmp(&b.path));
+    results
+}
+
+/// Downscale a texture to a square-bounded egui image (nearest neighbour)
+fn make_thumbnail(texture: &Texture, max_size: u32) -> egui::ColorImage {
+    let scale = (max_size as f32 / texture.width.max(texture.height).max(1) as f32).min(1.0);
+    let width = ((texture.width as f32 * scale) as u32).max(1);
+    let height = ((texture.height as f32 * scale) as u32).max(1);
+
+    let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let src_x = (x * texture.width / width).min(texture.width - 1);
+            let src_y = (y * texture.height / height).min(texture.height - 1);
+            let idx = (src_y * texture.width + src_x) as usize;
+            match texture.format {
+                TextureFormat::Rgba8 => rgba.extend_from_slice(&texture.data[idx * 4..idx * 4 + 4]),
+                TextureFormat::Rgb8 => {
+                    rgba.extend_from_slice(&texture.data[idx * 3..idx * 3 + 3]);
+                    rgba.push(255);
+                }
+                TextureFormat::R8 => {
+                    let v = texture.data[idx];
+                    rgba.extend_from_slice(&[v, v, v, 255]);
+                }
+            }
+        }
+    }
+
+    egui::ColorImage::from_rgba_unmultiplied([width as usize, height as usize], &rgba)
+}
+
+pub fn render_asset_browser_panel(
+    ui: &mut egui::Ui,
+    state: &mut AssetBrowserState,
+) -> AssetBrowserAction {
+    let mut action = AssetBrowserAction::default();
+
+    if state.needs_rescan {
+        state.rescan();
+    }
+
+    ui.horizontal(|ui| {
+        ui.heading("Assets");
+
+        // Breadcrumb navigation
+        if ui.small_button("assets").clicked() {
+            state.open_folder(PathBuf::new());
+        }
+        let mut crumb = PathBuf::new();
+        for component in state.current_dir.clone().components() {
+            crumb.push(component);
+            ui.label("/");
+            if ui
+                .small_button(component.as_os_str().to_string_lossy().to_string())
+                .clicked()
+            {
+                state.open_folder(crumb.clone());
+            }
+        }
+
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            if ui
+                .small_button("⟳")
+                .on_hover_text("Rescan assets")
+                .clicked()
+            {
+                state.refresh();
+            }
+            egui::ComboBox::from_id_salt("asset_kind_filter")
+                .selected_text(state.kind_filter.map(|k| k.label()).unwrap_or("All"))
+                .width(80.0)
+                .show_ui(ui, |ui| {
+                    let mut changed = ui
+                        .selectable_value(&mut state.kind_filter, None, "All")
+                        .changed();
+                    for kind in [
+                        AssetKind::Model,
+                        AssetKind::Texture,
+                        AssetKind::Material,
+                        AssetKind::Scene,
+                        AssetKind::Script,
+                        AssetKind::Audio,
+                    ] {
+                        changed |= ui
+                            .selectable_value(
+                                &mut state.kind_filter,
+                                Some(kind),
+                                kind.label(),
+                            )
+                            .changed();
+                    }
+                    if changed {
+                        state.needs_rescan = true;
+                    }
+                });
+            if ui.small_button("X").clicked() && !state.search.is_empty() {
+                state.search.clear();
+                state.needs_rescan = true;
+            }
+            if ui
+                .add(
+                    egui::TextEdit::singleline(&mut state.search)
+                        .hint_text("Search...")
+                        .desired_width(140.0),
+                )
+                .changed()
+            {
+                state.needs_rescan = true;
+            }
+        });
+    });
+    ui.separator();
+
+    if state.entries.is_empty() {
+        ui.label(if state.search.is_empty() {
+            "Folder is empty"
+        } else {
+            "No matching assets"
+        });
+        return action;
+    }
+
+    let mut thumbnail_budget = THUMBNAILS_PER_FRAME;
+    let entries = state.entries.clone();
+    ScrollArea::vertical()
+        .auto_shrink([false, false])
+        .show(ui, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for entry in &entries {
+                    let tile = render_asset_tile(ui, state, entry, &mut thumbnail_budget);
+
+                    if tile.double_clicked() {
+                        match entry.kind {
+                            AssetKind::Folder => state.open_folder(entry.path.clone()),
+                            AssetKind::Scene => {
+                                action.open_scene = Some(
+                                    state
+                                        .root
+                                        .join(&entry.path)
+                                        .to_string_lossy()
+                                        .to_string(),
+                                );
+                            }
+                            AssetKind::Model => {
+                                action.spawn_model = Some((entry.path_string(), None))
+                            }
+                            _ => {}
+                        }
+                    }
+                    tile.on_hover_text(format!(
+                        "{}\n{}",
+                        entry.path_string(),
+                        entry.kind.label()
+                    ));
+                }
+            });
+        });
+
+    action
+}
+
+/// Model dragged from the asset browser and released over the viewport:
+/// (path relative to asset root, drop position in window pixels)
+pub fn take_viewport_drop(
+    ctx: &Context,
+    viewport_rect: egui::Rect,
+) -> Option<(String, Option<(f32, f32)>)> {
+    let released = ctx.input(|i| i.pointer.any_released());
+    let pointer = ctx.pointer_latest_pos()?;
+    if !released || !viewport_rect.contains(pointer) {
+        return None;
+    }
+    let payload = egui::DragAndDrop::take_payload::<AssetDragPayload>(ctx)?;
+    if payload.kind != AssetKind::Model {
+        return None;
+    }
+    let pixels_per_point = ctx.pixels_per_point();
+    Some((
+        payload.path.clone(),
+        Some((pointer.x * pixels_per_point, pointer.y * pixels_per_point)),
+    ))
+}
+
+/// Render one asset tile (thumbnail/icon + name). Files are drag sources.
+fn render_asset_tile(
+    ui: &mut egui::Ui,
+    state: &mut AssetBrowserState,
+    entry: &AssetEntry,
+    thumbnail_budget: &mut usize,
+) -> egui::Response {
+    let thumbnail = if entry.kind == AssetKind::Texture {
+        state.thumbnail(ui.ctx(), entry, thumbnail_budget)
+    } else {
+        None
+    };
+
+    let contents = |ui: &mut egui::Ui| {
+        ui.vertical(|ui| {
+            ui.set_width(72.0);
+            let icon_size = egui::vec2(THUMBNAIL_SIZE as f32, THUMBNAIL_SIZE as f32);
+            match &thumbnail {
+                Some(texture) => {
+                    ui.add(egui::Image::new(texture).fit_to_exact_size(icon_size));
+                }
+                None => {
+                    ui.add_sized(
+                        icon_size,
+                        egui::Label::new(egui::RichText::new(entry.kind.icon()).size(32.0))
+                            .selectable(false),
+                    );
+                }
+            }
+            ui.add(
+                egui::Label::new(egui::RichText::new(&entry.name).small())
+                    .truncate()
+                    .selectable(false),
+            );
+        })
+        .response
+    };
+
+    if entry.kind == AssetKind::Folder {
+        let response = ui.scope(contents).inner;
+        ui.interact(
+            response.rect,
+            ui.id().with(&entry.path),
+            egui::Sense::click(),
+        )
+    } else {
+        let payload = AssetDragPayload {
+            path: entry.path_string(),
+            kind: entry.kind,
+        };
+        let inner = ui.dnd_drag_source(ui.id().with(&entry.path), payload, contents);
+        ui.interact(
+            inner.response.rect,
+            ui.id().with(&entry.path).with("click"),
+            egui::Sense::click(),
+        )
+    }
+}
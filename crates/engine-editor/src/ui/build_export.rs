@@ -0,0 +1,136 @@
+// Export Build dialog - build settings and the export button
+
+use crate::build_export::{find_runtime, BuildPlatform, BuildSettings, RUNTIME_NAME};
+use std::path::Path;
+
+#[derive(Default)]
+pub struct BuildDialogState {
+    pub settings: BuildSettings,
+    /// Scene path typed into the "Add" field
+    new_scene: String,
+}
+
+/// Requests from the Export Build dialog
+#[derive(Default)]
+pub struct BuildDialogAction {
+    pub export: bool,
+    pub close: bool,
+}
+
+pub fn render_build_dialog(
+    ctx: &egui::Context,
+    state: &mut BuildDialogState,
+    current_scene: Option<&str>,
+    project_root: &Path,
+) -> BuildDialogAction {
+    let mut action = BuildDialogAction::default();
+    let mut open = true;
+
+    egui::Window::new("Export Build")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            let settings = &mut state.settings;
+
+            egui::Grid::new("build_settings").num_columns(2).show(ui, |ui| {
+                ui.label("Game name:");
+                ui.text_edit_singleline(&mut settings.game_name);
+                ui.end_row();
+
+                ui.label("Resolution:");
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut settings.resolution[0]).range(320..=7680));
+                    ui.label("×");
+                    ui.add(egui::DragValue::new(&mut settings.resolution[1]).range(240..=4320));
+                    ui.checkbox(&mut settings.fullscreen, "Fullscreen");
+                });
+                ui.end_row();
+
+                ui.label("Icon:");
+                let mut icon = settings.icon.clone().unwrap_or_default();
+                if ui.text_edit_singleline(&mut icon).on_hover_text("PNG file, e.g. assets/textures/icon.png").changed() {
+                    settings.icon = if icon.is_empty() { None } else { Some(icon) };
+                }
+                ui.end_row();
+
+                ui.label("Output folder:");
+                ui.text_edit_singleline(&mut settings.output_dir);
+                ui.end_row();
+            });
+
+            ui.separator();
+            ui.strong("Scenes");
+            ui.weak("The selected scene is loaded when the game starts");
+            let mut remove = None;
+            for (index, scene) in settings.scenes.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut settings.start_scene, index, scene.as_str());
+                    if ui.small_button("✖").on_hover_text("Remove from the build").clicked() {
+                        remove = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = remove {
+                settings.scenes.remove(index);
+                if settings.start_scene > index {
+                    settings.start_scene -= 1;
+                }
+                settings.start_scene = settings.start_scene.min(settings.scenes.len().saturating_sub(1));
+            }
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut state.new_scene);
+                let valid = state.new_scene.ends_with(".ron") && !settings.scenes.contains(&state.new_scene);
+                if ui.add_enabled(valid, egui::Button::new("Add")).clicked() {
+                    settings.scenes.push(std::mem::take(&mut state.new_scene));
+                }
+            });
+            if let Some(current) = current_scene.filter(|path| !settings.scenes.iter().any(|s| s == path)) {
+                if ui.button("Add Current Scene").clicked() {
+                    settings.scenes.push(current.to_string());
+                }
+            }
+
+            ui.separator();
+            ui.strong("Platforms");
+            for platform in BuildPlatform::ALL {
+                let mut selected = settings.platforms.contains(&platform);
+                ui.horizontal(|ui| {
+                    if ui.checkbox(&mut selected, platform.label()).changed() {
+                        if selected {
+                            settings.platforms.push(platform);
+                        } else {
+                            settings.platforms.retain(|p| *p != platform);
+                        }
+                    }
+                    if find_runtime(project_root, platform).is_none() {
+                        ui.colored_label(egui::Color32::YELLOW, "⚠ runtime not found")
+                            .on_hover_text(format!(
+                                "Build {} next to the editor, or place a {} build of it in runtimes/{}/",
+                                RUNTIME_NAME,
+                                platform.label(),
+                                platform.dir_name()
+                            ));
+                    }
+                });
+            }
+
+            ui.add_space(10.0);
+            let validation = settings.validate();
+            if let Err(e) = &validation {
+                ui.colored_label(egui::Color32::RED, format!("⚠ {}", e));
+            }
+            ui.horizontal(|ui| {
+                if ui.add_enabled(validation.is_ok(), egui::Button::new("Export")).clicked() {
+                    action.export = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    action.close = true;
+                }
+            });
+        });
+
+    action.close |= !open;
+    action
+}
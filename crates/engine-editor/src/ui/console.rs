@@ -1,48 +1,194 @@
-// Console panel - displays logs and messages
+// Console panel - displays logs and messages, with a command line underneath
 
 use super::{ConsoleLevel, ConsoleMessage};
-use egui::{Color32, Context, ScrollArea};
+use crate::console_commands;
+use egui::{Color32, ScrollArea};
 
 /// Maximum number of console messages to keep
 const MAX_CONSOLE_MESSAGES: usize = 500;
 
-pub fn render_console_panel(ctx: &Context, messages: &mut Vec<ConsoleMessage>) {
+/// Maximum number of commands kept in the history
+const MAX_HISTORY: usize = 100;
+
+/// Command line input, history and completions
+#[derive(Default)]
+pub struct ConsoleState {
+    pub input: String,
+    history: Vec<String>,
+    /// History entry shown while browsing with Up/Down (None = a new line)
+    history_index: Option<usize>,
+    /// Completions listed under the input after an ambiguous Tab
+    suggestions: Vec<String>,
+}
+
+impl ConsoleState {
+    /// Take the entered line, remembering it in the history
+    pub fn submit(&mut self) -> Option<String> {
+        let line = std::mem::take(&mut self.input).trim().to_string();
+        self.history_index = None;
+        self.suggestions.clear();
+        if line.is_empty() {
+            return None;
+        }
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+            if self.history.len() > MAX_HISTORY {
+                self.history.remove(0);
+            }
+        }
+        Some(line)
+    }
+
+    /// Step back (Up) or forward (Down) through earlier commands
+    pub fn browse_history(&mut self, older: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+        self.history_index = match (self.history_index, older) {
+            (None, true) => Some(self.history.len() - 1),
+            (Some(index), true) => Some(index.saturating_sub(1)),
+            (Some(index), false) if index + 1 < self.history.len() => Some(index + 1),
+            (_, false) => None,
+        };
+        self.input = self
+            .history_index
+            .map(|index| self.history[index].clone())
+            .unwrap_or_default();
+    }
+
+    /// Complete the input as far as it is unambiguous, listing the options otherwise
+    pub fn autocomplete(&mut self) {
+        let candidates = console_commands::complete(&self.input);
+        let prefix = console_commands::common_prefix(&candidates);
+        if prefix.len() > self.input.len() {
+            self.input = prefix;
+        }
+        self.suggestions = if candidates.len() > 1 {
+            candidates
+        } else {
+            Vec::new()
+        };
+    }
+}
+
+/// Draw the console. Returns a command line the user entered.
+pub fn render_console_panel(
+    ui: &mut egui::Ui,
+    messages: &mut Vec<ConsoleMessage>,
+    state: &mut ConsoleState,
+) -> Option<String> {
     // Auto-prune old messages
     if messages.len() > MAX_CONSOLE_MESSAGES {
         let excess = messages.len() - MAX_CONSOLE_MESSAGES;
         messages.drain(0..excess);
     }
 
-    egui::TopBottomPanel::bottom("console_panel")
-        .default_height(200.0)
-        .show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                ui.heading("Console");
-                ui.label(format!("({} messages)", messages.len()));
-                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    if ui.button("Clear").clicked() || ui.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::L)) {
-                        messages.clear();
-                    }
+    ui.horizontal(|ui| {
+        ui.heading("Console");
+        ui.label(format!("({} messages)", messages.len()));
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            if ui.button("Clear").clicked() || ui.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::L)) {
+                messages.clear();
+            }
+        });
+    });
+    ui.separator();
+
+    // Command line at the bottom, laid out first so the log fills the space above it
+    let mut submitted = None;
+    egui::TopBottomPanel::bottom(ui.id().with("console_input"))
+        .frame(egui::Frame::NONE)
+        .show_inside(ui, |ui| {
+            if !state.suggestions.is_empty() {
+                ui.label(
+                    egui::RichText::new(state.suggestions.join("   "))
+                        .weak()
+                        .monospace(),
+                );
+            }
+            let input_id = ui.id().with("console_command_line");
+            // Tab and Up/Down belong to the command line while it has focus
+            if ui.memory(|memory| memory.has_focus(input_id)) {
+                let (tab, up, down) = ui.input_mut(|input| {
+                    (
+                        input.consume_key(egui::Modifiers::NONE, egui::Key::Tab),
+                        input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                        input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                    )
                 });
-            });
-            ui.separator();
-
-            ScrollArea::vertical()
-                .auto_shrink([false, false])
-                .stick_to_bottom(true)
-                .show(ui, |ui| {
-                    for msg in messages.iter() {
-                        let (icon, color) = match msg.level {
-                            ConsoleLevel::Info => ("ℹ", Color32::LIGHT_BLUE),
-                            ConsoleLevel::Warning => ("⚠", Color32::YELLOW),
-                            ConsoleLevel::Error => ("❌", Color32::RED),
-                        };
-
-                        ui.horizontal(|ui| {
-                            ui.colored_label(color, icon);
-                            ui.label(&msg.message);
-                        });
-                    }
+                if tab {
+                    state.autocomplete();
+                }
+                if up || down {
+                    state.browse_history(up);
+                }
+            }
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut state.input)
+                    .id(input_id)
+                    .desired_width(f32::INFINITY)
+                    .font(egui::TextStyle::Monospace)
+                    .hint_text("Command (Tab completes, `help` lists commands)")
+                    .lock_focus(true),
+            );
+            if response.changed() {
+                state.suggestions.clear();
+            }
+            if response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                submitted = state.submit();
+                response.request_focus();
+            }
+        });
+
+    ScrollArea::vertical()
+        .auto_shrink([false, false])
+        .stick_to_bottom(true)
+        .show(ui, |ui| {
+            for msg in messages.iter() {
+                let (icon, color) = match msg.level {
+                    ConsoleLevel::Info => ("ℹ", Color32::LIGHT_BLUE),
+                    ConsoleLevel::Warning => ("⚠", Color32::YELLOW),
+                    ConsoleLevel::Error => ("❌", Color32::RED),
+                };
+
+                ui.horizontal(|ui| {
+                    ui.colored_label(color, icon);
+                    ui.label(&msg.message);
                 });
+            }
         });
+
+    submitted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_and_completion() {
+        let mut state = ConsoleState::default();
+        for line in ["fps 60", "shadows off", "shadows off"] {
+            state.input = line.to_string();
+            assert_eq!(state.submit().as_deref(), Some(line));
+        }
+        // Repeated commands are remembered once
+        state.browse_history(true);
+        assert_eq!(state.input, "shadows off");
+        state.browse_history(true);
+        assert_eq!(state.input, "fps 60");
+        state.browse_history(true);
+        assert_eq!(state.input, "fps 60");
+        state.browse_history(false);
+        state.browse_history(false);
+        assert_eq!(state.input, "");
+
+        state.input = "spawn s".to_string();
+        state.autocomplete();
+        assert_eq!(state.input, "spawn sphere");
+        state.input = "s".to_string();
+        state.autocomplete();
+        assert_eq!(state.input, "s");
+        assert_eq!(state.suggestions.len(), 2);
+    }
 }
@@ -0,0 +1,144 @@
+// Dock layout - editor panels as rearrangeable, tabbable egui_dock tabs
+
+use egui_dock::{DockState, NodeIndex};
+use serde::{Deserialize, Serialize};
+
+/// A dockable editor panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EditorTab {
+    /// Transparent tab the 3D scene shows through
+    Viewport,
+    /// Scene seen through its active Camera component
+    Game,
+    Hierarchy,
+    Inspector,
+    Console,
+    AssetBrowser,
+    Profiler,
+    Navigation,
+    Lighting,
+    Timeline,
+}
+
+impl EditorTab {
+    pub fn title(&self) -> &'static str {
+        match self {
+            EditorTab::Viewport => "Viewport",
+            EditorTab::Game => "Game",
+            EditorTab::Hierarchy => "Hierarchy",
+            EditorTab::Inspector => "Inspector",
+            EditorTab::Console => "Console",
+            EditorTab::AssetBrowser => "Assets",
+            EditorTab::Profiler => "Profiler",
+            EditorTab::Navigation => "Navigation",
+            EditorTab::Lighting => "Lighting",
+            EditorTab::Timeline => "Timeline",
+        }
+    }
+}
+
+/// Default layout: hierarchy left, inspector right, console below the viewport
+/// (with the game view as a second tab next to it)
+pub fn default_dock_state() -> DockState<EditorTab> {
+    let mut state = DockState::new(vec![EditorTab::Viewport, EditorTab::Game]);
+    let surface = state.main_surface_mut();
+    // Split fractions are the share kept by the node being split
+    let [viewport, _] = surface.split_left(NodeIndex::root(), 0.8, vec![EditorTab::Hierarchy]);
+    let [viewport, _] = surface.split_right(viewport, 0.75, vec![EditorTab::Inspector]);
+    surface.split_below(viewport, 0.75, vec![EditorTab::Console]);
+    state
+}
+
+/// True if the tab is docked anywhere (including floating windows)
+pub fn is_tab_open(state: &DockState<EditorTab>, tab: EditorTab) -> bool {
+    state.iter_all_tabs().any(|(_, t)| *t == tab)
+}
+
+/// Open or close a tab. Reopened tabs go back to their default side of the layout.
+pub fn set_tab_open(state: &mut DockState<EditorTab>, tab: EditorTab, open: bool) {
+    if is_tab_open(state, tab) == open {
+        return;
+    }
+    if !open {
+        state.retain_tabs(|t| *t != tab);
+        return;
+    }
+    if state.main_surface().is_empty() {
+        *state = DockState::new(vec![tab]);
+        return;
+    }
+    // The game view goes next to the editor viewport
+    if tab == EditorTab::Game {
+        if let Some((surface, node, _)) = state.find_tab(&EditorTab::Viewport) {
+            state.set_focused_node_and_surface((surface, node));
+            state.push_to_focused_leaf(tab);
+            return;
+        }
+    }
+
+    let surface = state.main_surface_mut();
+    match tab {
+        EditorTab::Viewport | EditorTab::Game => surface.push_to_first_leaf(tab),
+        EditorTab::Hierarchy => {
+            surface.split_left(NodeIndex::root(), 0.8, vec![tab]);
+        }
+        EditorTab::Inspector | EditorTab::Profiler | EditorTab::Navigation | EditorTab::Lighting => {
+            surface.split_right(NodeIndex::root(), 0.75, vec![tab]);
+        }
+        EditorTab::Console | EditorTab::AssetBrowser | EditorTab::Timeline => {
+            surface.split_below(NodeIndex::root(), 0.75, vec![tab]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_layout_tabs() {
+        let state = default_dock_state();
+        assert!(is_tab_open(&state, EditorTab::Viewport));
+        assert!(is_tab_open(&state, EditorTab::Game));
+        assert!(is_tab_open(&state, EditorTab::Hierarchy));
+        assert!(is_tab_open(&state, EditorTab::Inspector));
+        assert!(is_tab_open(&state, EditorTab::Console));
+        assert!(!is_tab_open(&state, EditorTab::AssetBrowser));
+    }
+
+    #[test]
+    fn test_close_and_reopen_tab() {
+        let mut state = default_dock_state();
+        set_tab_open(&mut state, EditorTab::Console, false);
+        assert!(!is_tab_open(&state, EditorTab::Console));
+
+        set_tab_open(&mut state, EditorTab::Console, true);
+        set_tab_open(&mut state, EditorTab::Console, true);
+        let count = state
+            .iter_all_tabs()
+            .filter(|(_, t)| **t == EditorTab::Console)
+            .count();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_game_view_reopens_next_to_viewport() {
+        let mut state = default_dock_state();
+        set_tab_open(&mut state, EditorTab::Game, false);
+        assert!(!is_tab_open(&state, EditorTab::Game));
+
+        set_tab_open(&mut state, EditorTab::Game, true);
+        let viewport = state.find_tab(&EditorTab::Viewport).unwrap();
+        let game = state.find_tab(&EditorTab::Game).unwrap();
+        assert_eq!((viewport.0, viewport.1), (game.0, game.1));
+    }
+
+    #[test]
+    fn test_layout_serializes() {
+        let state = default_dock_state();
+        // RON, as prefs saves it: JSON would turn the unset rects' infinities into nulls
+        let text = ron::to_string(&state).unwrap();
+        let restored: DockState<EditorTab> = ron::from_str(&text).unwrap();
+        assert!(is_tab_open(&restored, EditorTab::Inspector));
+    }
+}
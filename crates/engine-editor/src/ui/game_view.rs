@@ -0,0 +1,214 @@
+// Game view - preview of the scene through its active Camera component
+//
+// The tab reserves a letterboxed frame at the chosen aspect ratio. The editor
+// renders the game camera into that frame and the safe-area guides are drawn
+// on top of it.
+
+use egui::{pos2, Color32, Rect, Stroke};
+use serde::{Deserialize, Serialize};
+
+/// Action-safe area (fraction of the frame kept clear of important action)
+const ACTION_SAFE: f32 = 0.9;
+/// Title-safe area (fraction of the frame text and HUD should stay inside)
+const TITLE_SAFE: f32 = 0.8;
+
+/// Aspect ratio the game view is letterboxed to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AspectPreset {
+    /// Fill the whole tab
+    #[default]
+    Free,
+    Wide16x9,
+    Wide16x10,
+    Standard4x3,
+    Ultrawide21x9,
+    Square,
+    Portrait9x16,
+}
+
+impl AspectPreset {
+    pub const ALL: [AspectPreset; 7] = [
+        AspectPreset::Free,
+        AspectPreset::Wide16x9,
+        AspectPreset::Wide16x10,
+        AspectPreset::Standard4x3,
+        AspectPreset::Ultrawide21x9,
+        AspectPreset::Square,
+        AspectPreset::Portrait9x16,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AspectPreset::Free => "Free",
+            AspectPreset::Wide16x9 => "16:9",
+            AspectPreset::Wide16x10 => "16:10",
+            AspectPreset::Standard4x3 => "4:3",
+            AspectPreset::Ultrawide21x9 => "21:9",
+            AspectPreset::Square => "1:1",
+            AspectPreset::Portrait9x16 => "9:16 (Portrait)",
+        }
+    }
+
+    /// Width / height, or None to follow the tab size
+    pub fn ratio(&self) -> Option<f32> {
+        match self {
+            AspectPreset::Free => None,
+            AspectPreset::Wide16x9 => Some(16.0 / 9.0),
+            AspectPreset::Wide16x10 => Some(16.0 / 10.0),
+            AspectPreset::Standard4x3 => Some(4.0 / 3.0),
+            AspectPreset::Ultrawide21x9 => Some(21.0 / 9.0),
+            AspectPreset::Square => Some(1.0),
+            AspectPreset::Portrait9x16 => Some(9.0 / 16.0),
+        }
+    }
+}
+
+/// Game view settings
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameViewState {
+    pub aspect: AspectPreset,
+    pub show_safe_area: bool,
+}
+
+/// Largest rect with the given aspect ratio centered in `available`
+pub fn fit_aspect(available: Rect, ratio: Option<f32>) -> Rect {
+    let Some(ratio) = ratio else {
+        return available;
+    };
+    let mut size = available.size();
+    if size.x / size.y.max(1.0) > ratio {
+        size.x = size.y * ratio;
+    } else {
+        size.y = size.x / ratio;
+    }
+    Rect::from_center_size(available.center(), size)
+}
+
+/// Shrink a rect about its center to `fraction` of its size
+pub fn safe_rect(frame: Rect, fraction: f32) -> Rect {
+    Rect::from_center_size(frame.center(), frame.size() * fraction)
+}
+
+/// Bars filling `outer` around `inner` (left, right, top, bottom)
+fn letterbox_bars(outer: Rect, inner: Rect) -> [Rect; 4] {
+    [
+        Rect::from_min_max(outer.min, pos2(inner.min.x, outer.max.y)),
+        Rect::from_min_max(pos2(inner.max.x, outer.min.y), outer.max),
+        Rect::from_min_max(
+            pos2(inner.min.x, outer.min.y),
+            pos2(inner.max.x, inner.min.y),
+        ),
+        Rect::from_min_max(
+            pos2(inner.min.x, inner.max.y),
+            pos2(inner.max.x, outer.max.y),
+        ),
+    ]
+}
+
+/// Draw the game view tab. `live` is false while another tab shows the scene.
+/// Returns the frame the game camera should render into, or None if the tab
+/// shows a placeholder instead.
+pub fn render_game_view(
+    ui: &mut egui::Ui,
+    state: &mut GameViewState,
+    has_camera: bool,
+    live: bool,
+) -> Option<Rect> {
+    // Toolbar gets its own background; the rest of the tab is see-through
+    egui::Frame::NONE
+        .fill(ui.visuals().panel_fill)
+        .inner_margin(4.0)
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            ui.horizontal(|ui| {
+                ui.label("Aspect:");
+                egui::ComboBox::from_id_salt("game_view_aspect")
+                    .selected_text(state.aspect.label())
+                    .show_ui(ui, |ui| {
+                        for preset in AspectPreset::ALL {
+                            ui.selectable_value(&mut state.aspect, preset, preset.label());
+                        }
+                    });
+                ui.checkbox(&mut state.show_safe_area, "Safe Area")
+                    .on_hover_text("Action-safe (90%) and title-safe (80%) guides");
+            });
+        });
+
+    let available = ui.available_rect_before_wrap();
+    ui.allocate_rect(available, egui::Sense::hover());
+    let frame = fit_aspect(available, state.aspect.ratio());
+    let painter = ui.painter_at(available);
+    for bar in letterbox_bars(available, frame) {
+        painter.rect_filled(bar, 0.0, Color32::BLACK);
+    }
+
+    if !has_camera || !live {
+        let message = if !has_camera {
+            "No active Camera component in the scene"
+        } else {
+            "Click this tab to preview the game camera"
+        };
+        painter.rect_filled(frame, 0.0, ui.visuals().extreme_bg_color);
+        painter.text(
+            frame.center(),
+            egui::Align2::CENTER_CENTER,
+            message,
+            egui::FontId::proportional(14.0),
+            ui.visuals().weak_text_color(),
+        );
+        return None;
+    }
+
+    if state.show_safe_area {
+        painter.rect_stroke(
+            safe_rect(frame, ACTION_SAFE),
+            0.0,
+            Stroke::new(1.0, Color32::from_rgba_unmultiplied(255, 255, 255, 100)),
+            egui::StrokeKind::Inside,
+        );
+        painter.rect_stroke(
+            safe_rect(frame, TITLE_SAFE),
+            0.0,
+            Stroke::new(1.0, Color32::from_rgba_unmultiplied(255, 200, 0, 120)),
+            egui::StrokeKind::Inside,
+        );
+    }
+
+    Some(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::vec2;
+
+    #[test]
+    fn test_fit_aspect_letterboxes() {
+        let available = Rect::from_min_size(pos2(0.0, 0.0), vec2(1000.0, 400.0));
+        assert_eq!(fit_aspect(available, None), available);
+
+        // Wider than 16:9 - pillarboxed, full height
+        let frame = fit_aspect(available, AspectPreset::Wide16x9.ratio());
+        assert!((frame.height() - 400.0).abs() < 1e-3);
+        assert!((frame.width() / frame.height() - 16.0 / 9.0).abs() < 1e-4);
+        assert_eq!(frame.center(), available.center());
+
+        // Portrait in a tall tab - letterboxed, full width
+        let tall = Rect::from_min_size(pos2(0.0, 0.0), vec2(300.0, 900.0));
+        let frame = fit_aspect(tall, AspectPreset::Square.ratio());
+        assert_eq!(frame.size(), vec2(300.0, 300.0));
+    }
+
+    #[test]
+    fn test_safe_rect_and_bars_cover_tab() {
+        let frame = Rect::from_min_size(pos2(100.0, 0.0), vec2(200.0, 100.0));
+        let safe = safe_rect(frame, TITLE_SAFE);
+        assert_eq!(safe.size(), vec2(160.0, 80.0));
+        assert_eq!(safe.center(), frame.center());
+
+        let outer = Rect::from_min_size(pos2(0.0, 0.0), vec2(400.0, 100.0));
+        let bar_area: f32 = letterbox_bars(outer, frame).iter().map(|r| r.area()).sum();
+        assert!((bar_area + frame.area() - outer.area()).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,96 @@
+// Heightmap import/export dialog - file path and the height range of 16-bit formats
+//
+// Paths are relative to the project's asset root, so the dialog remembers the
+// same file whichever directory the editor was started from.
+
+use engine_assets::HeightMapFormat;
+use std::path::{Path, PathBuf};
+
+pub struct HeightmapDialogState {
+    /// Import rather than export
+    pub import: bool,
+    path: String,
+    min_height: f32,
+    max_height: f32,
+    /// Export over the terrain's own height range instead of min..max
+    fit_to_terrain: bool,
+}
+
+impl Default for HeightmapDialogState {
+    fn default() -> Self {
+        Self {
+            import: true,
+            path: "terrain/heightmap.png".to_string(),
+            min_height: 0.0,
+            max_height: 50.0,
+            fit_to_terrain: true,
+        }
+    }
+}
+
+/// Import or export requested from the dialog; paths are relative to the
+/// asset root
+#[derive(Debug, Clone)]
+pub enum HeightmapRequest {
+    Import { path: PathBuf, min_height: f32, max_height: f32 },
+    /// A range of None exports over the terrain's lowest and highest points
+    Export { path: PathBuf, range: Option<(f32, f32)> },
+}
+
+/// Returns the request and whether the dialog should stay open
+pub fn render_heightmap_dialog(ctx: &egui::Context, state: &mut HeightmapDialogState) -> (Option<HeightmapRequest>, bool) {
+    let mut request = None;
+    let mut open = true;
+    let mut cancelled = false;
+    let title = if state.import { "Import Heightmap" } else { "Export Heightmap" };
+
+    egui::Window::new(title)
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.label("File path (in the asset folder):");
+            ui.text_edit_singleline(&mut state.path);
+            let format = HeightMapFormat::from_path(Path::new(&state.path));
+            if format.is_none() {
+                ui.colored_label(egui::Color32::RED, "⚠ Use a .png, .raw, .r16 or .exr file");
+            }
+
+            // EXR holds heights in world units, so only the 16-bit formats need a range
+            if format.is_some_and(HeightMapFormat::is_normalized) {
+                if !state.import {
+                    ui.checkbox(&mut state.fit_to_terrain, "Fit range to terrain")
+                        .on_hover_text("Map the lowest and highest points to black and white");
+                }
+                ui.add_enabled_ui(state.import || !state.fit_to_terrain, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Height range:");
+                        ui.add(egui::DragValue::new(&mut state.min_height).speed(0.5));
+                        ui.label("to");
+                        ui.add(egui::DragValue::new(&mut state.max_height).speed(0.5));
+                    });
+                });
+            }
+
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                let label = if state.import { "Import" } else { "Export" };
+                if ui.add_enabled(format.is_some(), egui::Button::new(label)).clicked() {
+                    let path = PathBuf::from(&state.path);
+                    request = Some(if state.import {
+                        HeightmapRequest::Import { path, min_height: state.min_height, max_height: state.max_height }
+                    } else {
+                        let range = (!state.fit_to_terrain).then_some((state.min_height, state.max_height));
+                        HeightmapRequest::Export { path, range }
+                    });
+                }
+                if ui.button("Cancel").clicked() {
+                    cancelled = true;
+                }
+            });
+        });
+
+    let keep_open = open && !cancelled && request.is_none();
+    (request, keep_open)
+}
@@ -1,11 +1,11 @@
 // Hierarchy panel - shows scene graph tree
 
 use std::collections::HashSet;
-use egui::{Context, ScrollArea};
+use egui::ScrollArea;
 use engine_scene::{entity::EntityId, scene::Scene};
 
 /// Quick entity type for creation
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum QuickEntityType {
     Empty,
     Cube,
@@ -23,12 +23,22 @@ pub struct HierarchyAction {
     pub create_quick_entity: Option<QuickEntityType>,       // Quick creation with preset
     pub delete_entity: Option<EntityId>,
     pub duplicate_entity: Option<EntityId>,
-    pub reparent: Option<(EntityId, Option<EntityId>)>,     // (child, new_parent)
-    pub rename_entity: Option<(EntityId, String)>,          // (entity, new_name)
-    pub toggle_visibility: Option<EntityId>,                // Toggle entity visibility
-    pub show_all_hidden: bool,                              // Show all hidden entities
-    pub toggle_lock: Option<EntityId>,                      // Toggle entity lock
-    pub unlock_all: bool,                                   // Unlock all entities
+    pub reparent: Option<(EntityId, Option<EntityId>)>, // (child, new_parent)
+    pub rename_entity: Option<(EntityId, String)>,      // (entity, new_name)
+    pub toggle_visibility: Option<EntityId>,            // Toggle entity visibility
+    pub show_all_hidden: bool,                          // Show all hidden entities
+    pub toggle_lock: Option<EntityId>,                  // Toggle entity lock
+    pub unlock_all: bool,                               // Unlock all entities
+    pub toggle_selected: Option<EntityId>,              // Ctrl+click add/remove from selection
+    pub select_range: Option<Vec<EntityId>>, // Replace selection (click, Shift+click range)
+    pub delete_selected: bool,               // Delete all selected entities
+    pub duplicate_selected: bool,            // Duplicate all selected entities
+    pub group_selected: bool,                // Parent selection under a new empty entity
+    pub parent_selection_to: Option<EntityId>, // Parent selection under this entity
+    pub snap_selected_to_ground: bool,       // Rest the selection on the surface below
+    pub drop_selected: bool,                 // Drop the selection with physics and bake the result
+    pub align_selection: Option<usize>,      // Align selection with the active entity on this axis
+    pub distribute_selection: Option<usize>, // Space selection evenly along this axis
 }
 
 /// Component type filter for hierarchy
@@ -101,196 +111,206 @@ impl Default for HierarchyState {
 }
 
 pub fn render_hierarchy_panel(
-    ctx: &Context,
+    ui: &mut egui::Ui,
     scene: &Scene,
     selected_entity: &mut Option<EntityId>,
+    selected_entities: &HashSet<EntityId>,
     state: &mut HierarchyState,
     hidden_entities: &HashSet<EntityId>,
     locked_entities: &HashSet<EntityId>,
 ) -> HierarchyAction {
     let mut action = HierarchyAction::default();
 
-    egui::SidePanel::left("hierarchy_panel")
-        .default_width(250.0)
-        .show(ctx, |ui| {
-            ui.heading("Hierarchy");
-            ui.separator();
-
-            // Search box
-            ui.horizontal(|ui| {
-                ui.label("Search:");
-                let response = ui.text_edit_singleline(&mut state.search_filter);
-                if response.changed() && !state.search_filter.is_empty() {
-                    // Auto-expand all matches when searching
-                    expand_matching_entities(scene, &state.search_filter, &mut state.expanded_entities);
-                }
-                if ui.small_button("X").clicked() {
-                    state.search_filter.clear();
-                }
-            });
+    ui.heading("Hierarchy");
+    ui.separator();
 
-            // Show entity count and match count
-            let total_count = scene.entity_count();
-            let hidden_count = hidden_entities.len();
-            let locked_count = locked_entities.len();
-            ui.horizontal(|ui| {
-                if state.search_filter.is_empty() {
-                    let mut status_parts = Vec::new();
-                    if hidden_count > 0 {
-                        status_parts.push(format!("{} hidden", hidden_count));
-                    }
-                    if locked_count > 0 {
-                        status_parts.push(format!("{} locked", locked_count));
-                    }
-                    if status_parts.is_empty() {
-                        ui.label(format!("{} entities", total_count));
-                    } else {
-                        ui.label(format!("{} entities ({})", total_count, status_parts.join(", ")));
-                    }
-                } else {
-                    let match_count = count_matching_entities(scene, &state.search_filter);
-                    ui.label(format!("{} / {} entities", match_count, total_count));
-                }
-            });
+    // Search box
+    ui.horizontal(|ui| {
+        ui.label("Search:");
+        let response = ui.text_edit_singleline(&mut state.search_filter);
+        if response.changed() && !state.search_filter.is_empty() {
+            // Auto-expand all matches when searching
+            expand_matching_entities(scene, &state.search_filter, &mut state.expanded_entities);
+        }
+        if ui.small_button("X").clicked() {
+            state.search_filter.clear();
+        }
+    });
 
-            // Show All / Unlock All buttons when needed
-            if hidden_count > 0 || locked_count > 0 {
-                ui.horizontal(|ui| {
-                    if hidden_count > 0 {
-                        if ui.small_button("Show All").clicked() {
-                            action.show_all_hidden = true;
-                        }
-                    }
-                    if locked_count > 0 {
-                        if ui.small_button("Unlock All").clicked() {
-                            action.unlock_all = true;
-                        }
-                    }
-                });
+    // Show entity count and match count
+    let total_count = scene.entity_count();
+    let hidden_count = hidden_entities.len();
+    let locked_count = locked_entities.len();
+    ui.horizontal(|ui| {
+        if state.search_filter.is_empty() {
+            let mut status_parts = Vec::new();
+            if hidden_count > 0 {
+                status_parts.push(format!("{} hidden", hidden_count));
+            }
+            if locked_count > 0 {
+                status_parts.push(format!("{} locked", locked_count));
+            }
+            if status_parts.is_empty() {
+                ui.label(format!("{} entities", total_count));
+            } else {
+                ui.label(format!("{} entities ({})", total_count, status_parts.join(", ")));
             }
+        } else {
+            let match_count = count_matching_entities(scene, &state.search_filter);
+            ui.label(format!("{} / {} entities", match_count, total_count));
+        }
+    });
 
-            // Expand/Collapse all buttons and component filter
-            ui.horizontal(|ui| {
-                if ui.small_button("Expand All").clicked() {
-                    for entity in scene.entities() {
-                        if !entity.children.is_empty() {
-                            expand_all_children(scene, entity.id, &mut state.expanded_entities);
-                        }
-                    }
-                }
-                if ui.small_button("Collapse All").clicked() {
-                    state.expanded_entities.clear();
+    // Show All / Unlock All buttons when needed
+    if hidden_count > 0 || locked_count > 0 {
+        ui.horizontal(|ui| {
+            if hidden_count > 0 && ui.small_button("Show All").clicked() {
+                action.show_all_hidden = true;
+            }
+            if locked_count > 0 && ui.small_button("Unlock All").clicked() {
+                action.unlock_all = true;
+            }
+        });
+    }
+
+    // Expand/Collapse all buttons and component filter
+    ui.horizontal(|ui| {
+        if ui.small_button("Expand All").clicked() {
+            for entity in scene.entities() {
+                if !entity.children.is_empty() {
+                    expand_all_children(scene, entity.id, &mut state.expanded_entities);
                 }
+            }
+        }
+        if ui.small_button("Collapse All").clicked() {
+            state.expanded_entities.clear();
+        }
+        ui.separator();
+        // Component type filter dropdown
+        egui::ComboBox::from_id_salt("component_filter")
+            .selected_text(state.component_filter.label())
+            .width(80.0)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut state.component_filter, ComponentFilter::All, "All");
                 ui.separator();
-                // Component type filter dropdown
-                egui::ComboBox::from_id_salt("component_filter")
-                    .selected_text(state.component_filter.label())
-                    .width(80.0)
-                    .show_ui(ui, |ui| {
-                        ui.selectable_value(&mut state.component_filter, ComponentFilter::All, "All");
-                        ui.separator();
-                        ui.selectable_value(&mut state.component_filter, ComponentFilter::Meshes, "Meshes");
-                        ui.selectable_value(&mut state.component_filter, ComponentFilter::Lights, "Lights");
-                        ui.selectable_value(&mut state.component_filter, ComponentFilter::Cameras, "Cameras");
-                        ui.selectable_value(&mut state.component_filter, ComponentFilter::Particles, "Particles");
-                        ui.selectable_value(&mut state.component_filter, ComponentFilter::Water, "Water");
-                        ui.selectable_value(&mut state.component_filter, ComponentFilter::Terrain, "Terrain");
-                        ui.selectable_value(&mut state.component_filter, ComponentFilter::Foliage, "Foliage");
-                        ui.selectable_value(&mut state.component_filter, ComponentFilter::Empty, "Empty");
-                    });
+                ui.selectable_value(&mut state.component_filter, ComponentFilter::Meshes, "Meshes");
+                ui.selectable_value(&mut state.component_filter, ComponentFilter::Lights, "Lights");
+                ui.selectable_value(&mut state.component_filter, ComponentFilter::Cameras, "Cameras");
+                ui.selectable_value(&mut state.component_filter, ComponentFilter::Particles, "Particles");
+                ui.selectable_value(&mut state.component_filter, ComponentFilter::Water, "Water");
+                ui.selectable_value(&mut state.component_filter, ComponentFilter::Terrain, "Terrain");
+                ui.selectable_value(&mut state.component_filter, ComponentFilter::Foliage, "Foliage");
+                ui.selectable_value(&mut state.component_filter, ComponentFilter::Empty, "Empty");
             });
+    });
 
-            ui.separator();
+    ui.separator();
 
-            // Build list of visible entities for keyboard navigation
-            let visible_entities = get_visible_entities(scene, state);
+    // Build list of visible entities for keyboard navigation
+    let visible_entities = get_visible_entities(scene, state);
 
-            // Handle keyboard navigation (only when not editing)
-            if state.editing_entity.is_none() {
-                if ui.input(|i| i.key_pressed(egui::Key::ArrowDown)) {
-                    if let Some(current) = *selected_entity {
-                        // Find current index and move to next
-                        if let Some(idx) = visible_entities.iter().position(|&id| id == current) {
-                            if idx + 1 < visible_entities.len() {
-                                *selected_entity = Some(visible_entities[idx + 1]);
-                            }
-                        }
-                    } else if !visible_entities.is_empty() {
-                        *selected_entity = Some(visible_entities[0]);
+    // Handle keyboard navigation (only when not editing)
+    if state.editing_entity.is_none() {
+        if ui.input(|i| i.key_pressed(egui::Key::ArrowDown)) {
+            if let Some(current) = *selected_entity {
+                // Find current index and move to next
+                if let Some(idx) = visible_entities.iter().position(|&id| id == current) {
+                    if idx + 1 < visible_entities.len() {
+                        *selected_entity = Some(visible_entities[idx + 1]);
                     }
                 }
-                if ui.input(|i| i.key_pressed(egui::Key::ArrowUp)) {
-                    if let Some(current) = *selected_entity {
-                        if let Some(idx) = visible_entities.iter().position(|&id| id == current) {
-                            if idx > 0 {
-                                *selected_entity = Some(visible_entities[idx - 1]);
-                            }
-                        }
-                    } else if !visible_entities.is_empty() {
-                        *selected_entity = Some(visible_entities[visible_entities.len() - 1]);
-                    }
-                }
-                // Right arrow to expand
-                if ui.input(|i| i.key_pressed(egui::Key::ArrowRight)) {
-                    if let Some(current) = *selected_entity {
-                        state.expanded_entities.insert(current);
-                    }
-                }
-                // Left arrow to collapse
-                if ui.input(|i| i.key_pressed(egui::Key::ArrowLeft)) {
-                    if let Some(current) = *selected_entity {
-                        state.expanded_entities.remove(&current);
+            } else if !visible_entities.is_empty() {
+                *selected_entity = Some(visible_entities[0]);
+            }
+        }
+        if ui.input(|i| i.key_pressed(egui::Key::ArrowUp)) {
+            if let Some(current) = *selected_entity {
+                if let Some(idx) = visible_entities.iter().position(|&id| id == current) {
+                    if idx > 0 {
+                        *selected_entity = Some(visible_entities[idx - 1]);
                     }
                 }
+            } else if !visible_entities.is_empty() {
+                *selected_entity = Some(visible_entities[visible_entities.len() - 1]);
             }
+        }
+        // Right arrow to expand
+        if ui.input(|i| i.key_pressed(egui::Key::ArrowRight)) {
+            if let Some(current) = *selected_entity {
+                state.expanded_entities.insert(current);
+            }
+        }
+        // Left arrow to collapse
+        if ui.input(|i| i.key_pressed(egui::Key::ArrowLeft)) {
+            if let Some(current) = *selected_entity {
+                state.expanded_entities.remove(&current);
+            }
+        }
+    }
 
-            ScrollArea::vertical().show(ui, |ui| {
-                // Show all root entities (entities without parents)
-                for entity in scene.entities() {
-                    if entity.parent.is_none() {
-                        render_entity_tree(ui, scene, entity.id, selected_entity, state, hidden_entities, locked_entities, &mut action, 0);
-                    }
-                }
-            });
+    ScrollArea::vertical().show(ui, |ui| {
+        // Show all root entities (entities without parents)
+        for entity in scene.entities() {
+            if entity.parent.is_none() {
+                render_entity_tree(
+                    ui,
+                    scene,
+                    entity.id,
+                    selected_entity,
+                    selected_entities,
+                    &visible_entities,
+                    state,
+                    hidden_entities,
+                    locked_entities,
+                    &mut action,
+                    0,
+                );
+            }
+        }
+    });
 
-            ui.separator();
+    ui.separator();
 
-            // Quick creation buttons
-            ui.horizontal(|ui| {
-                ui.label("Quick Add:");
-            });
-            ui.horizontal_wrapped(|ui| {
-                if ui.small_button("Cube").clicked() {
-                    action.create_quick_entity = Some(QuickEntityType::Cube);
-                }
-                if ui.small_button("Sphere").clicked() {
-                    action.create_quick_entity = Some(QuickEntityType::Sphere);
-                }
-                if ui.small_button("Light").clicked() {
-                    action.create_quick_entity = Some(QuickEntityType::PointLight);
-                }
-                if ui.small_button("Camera").clicked() {
-                    action.create_quick_entity = Some(QuickEntityType::Camera);
-                }
-            });
+    // Quick creation buttons
+    ui.horizontal(|ui| {
+        ui.label("Quick Add:");
+    });
+    ui.horizontal_wrapped(|ui| {
+        if ui.small_button("Cube").clicked() {
+            action.create_quick_entity = Some(QuickEntityType::Cube);
+        }
+        if ui.small_button("Sphere").clicked() {
+            action.create_quick_entity = Some(QuickEntityType::Sphere);
+        }
+        if ui.small_button("Light").clicked() {
+            action.create_quick_entity = Some(QuickEntityType::PointLight);
+        }
+        if ui.small_button("Camera").clicked() {
+            action.create_quick_entity = Some(QuickEntityType::Camera);
+        }
+    });
 
-            ui.add_space(5.0);
+    ui.add_space(5.0);
 
-            // Create entity button
-            if ui.button("+ Create Empty Entity").clicked() {
-                state.show_create_dialog = true;
-                state.new_entity_parent = None;
-                state.new_entity_name = "New Entity".to_string();
-            }
+    // Create entity button
+    if ui.button("+ Create Empty Entity").clicked() {
+        state.show_create_dialog = true;
+        state.new_entity_parent = None;
+        state.new_entity_name = "New Entity".to_string();
+    }
 
-            // Delete selected button (only show if something selected)
-            if let Some(entity_id) = *selected_entity {
-                if ui.button("- Delete Selected").clicked() {
-                    state.show_delete_confirm = true;
-                    state.entity_to_delete = Some(entity_id);
-                }
-            }
-        });
+    // Delete selected button (only show if something selected)
+    if let Some(entity_id) = *selected_entity {
+        let label = if selected_entities.len() > 1 {
+            format!("- Delete Selected ({})", selected_entities.len())
+        } else {
+            "- Delete Selected".to_string()
+        };
+        if ui.button(label).clicked() {
+            state.show_delete_confirm = true;
+            state.entity_to_delete = Some(entity_id);
+        }
+    }
 
     // Create entity dialog
     if state.show_create_dialog {
@@ -298,7 +318,7 @@ pub fn render_hierarchy_panel(
             .collapsible(false)
             .resizable(false)
             .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
-            .show(ctx, |ui| {
+            .show(ui.ctx(), |ui| {
                 ui.label("Entity name:");
                 ui.text_edit_singleline(&mut state.new_entity_name);
 
@@ -334,12 +354,24 @@ pub fn render_hierarchy_panel(
                 .map(|e| e.name.clone())
                 .unwrap_or_else(|| "Unknown".to_string());
 
+            // Deleting any entity of a multi-selection deletes the whole selection
+            let group_count =
+                if selected_entities.len() > 1 && selected_entities.contains(&entity_id) {
+                    selected_entities.len()
+                } else {
+                    1
+                };
+
             egui::Window::new("Delete Entity")
                 .collapsible(false)
                 .resizable(false)
                 .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
-                .show(ctx, |ui| {
-                    ui.label(format!("Delete \"{}\"?", entity_name));
+                .show(ui.ctx(), |ui| {
+                    if group_count > 1 {
+                        ui.label(format!("Delete {} selected entities?", group_count));
+                    } else {
+                        ui.label(format!("Delete \"{}\"?", entity_name));
+                    }
 
                     if let Some(entity) = scene.get_entity(entity_id) {
                         if !entity.children.is_empty() {
@@ -353,7 +385,11 @@ pub fn render_hierarchy_panel(
                     ui.add_space(10.0);
                     ui.horizontal(|ui| {
                         if ui.button("Delete").clicked() {
-                            action.delete_entity = Some(entity_id);
+                            if group_count > 1 {
+                                action.delete_selected = true;
+                            } else {
+                                action.delete_entity = Some(entity_id);
+                            }
                             state.show_delete_confirm = false;
                             state.entity_to_delete = None;
                         }
@@ -374,6 +410,8 @@ fn render_entity_tree(
     scene: &Scene,
     entity_id: EntityId,
     selected_entity: &mut Option<EntityId>,
+    selected_entities: &HashSet<EntityId>,
+    visible_entities: &[EntityId],
     state: &mut HierarchyState,
     hidden_entities: &HashSet<EntityId>,
     locked_entities: &HashSet<EntityId>,
@@ -402,7 +440,8 @@ fn render_entity_tree(
         return;
     }
 
-    let is_selected = *selected_entity == Some(entity_id);
+    let is_selected = *selected_entity == Some(entity_id) || selected_entities.contains(&entity_id);
+    let in_multi_selection = selected_entities.len() > 1 && selected_entities.contains(&entity_id);
     let has_children = !entity.children.is_empty();
     let entity_name = entity.name.clone();
     let children = entity.children.clone();
@@ -543,9 +582,20 @@ fn render_entity_tree(
                 ui.label("Double-click to rename");
             });
 
-            // Single click to select
+            // Click to select, Ctrl+click to add/remove, Shift+click to select a range
             if response.clicked() {
-                *selected_entity = Some(entity_id);
+                let modifiers = ui.input(|i| i.modifiers);
+                if modifiers.command {
+                    action.toggle_selected = Some(entity_id);
+                } else if modifiers.shift {
+                    action.select_range = Some(selection_range(
+                        visible_entities,
+                        *selected_entity,
+                        entity_id,
+                    ));
+                } else {
+                    action.select_range = Some(vec![entity_id]);
+                }
             }
 
             // Double-click to start editing
@@ -562,7 +612,11 @@ fn render_entity_tree(
                     ui.close_menu();
                 }
                 if ui.button("Duplicate").clicked() {
-                    action.duplicate_entity = Some(entity_id);
+                    if in_multi_selection {
+                        action.duplicate_selected = true;
+                    } else {
+                        action.duplicate_entity = Some(entity_id);
+                    }
                     ui.close_menu();
                 }
                 if ui.button("Create Child").clicked() {
@@ -588,6 +642,22 @@ fn render_entity_tree(
                     state.entity_to_delete = Some(entity_id);
                     ui.close_menu();
                 }
+                if in_multi_selection {
+                    ui.separator();
+                    if ui
+                        .button(format!("Group Selected ({})", selected_entities.len()))
+                        .clicked()
+                    {
+                        action.group_selected = true;
+                        ui.close();
+                    }
+                } else if selected_entities.len() > 1 {
+                    ui.separator();
+                    if ui.button("Parent Selection Here").clicked() {
+                        action.parent_selection_to = Some(entity_id);
+                        ui.close();
+                    }
+                }
                 ui.separator();
                 ui.menu_button("Set Parent", |ui| {
                     if ui.button("None (root)").clicked() {
@@ -612,11 +682,39 @@ fn render_entity_tree(
     // Render children if expanded (or if searching and children match)
     if has_children && (is_expanded || !search_filter.is_empty()) {
         for child_id in children {
-            render_entity_tree(ui, scene, child_id, selected_entity, state, hidden_entities, locked_entities, action, depth + 1);
+            render_entity_tree(
+                ui,
+                scene,
+                child_id,
+                selected_entity,
+                selected_entities,
+                visible_entities,
+                state,
+                hidden_entities,
+                locked_entities,
+                action,
+                depth + 1,
+            );
         }
     }
 }
 
+/// Entities between the anchor (current selection) and the clicked entity, in display order
+fn selection_range(
+    visible_entities: &[EntityId],
+    anchor: Option<EntityId>,
+    clicked: EntityId,
+) -> Vec<EntityId> {
+    let clicked_idx = visible_entities.iter().position(|&id| id == clicked);
+    let anchor_idx = anchor.and_then(|a| visible_entities.iter().position(|&id| id == a));
+    match (anchor_idx, clicked_idx) {
+        (Some(a), Some(c)) if a <= c => visible_entities[a..=c].to_vec(),
+        // Reverse so the clicked entity ends up as the primary selection
+        (Some(a), Some(c)) => visible_entities[c..=a].iter().rev().copied().collect(),
+        _ => vec![clicked],
+    }
+}
+
 /// Check if potential_child is a descendant of potential_parent
 fn is_descendant(scene: &Scene, entity_id: EntityId, potential_parent: EntityId) -> bool {
     if let Some(entity) = scene.get_entity(entity_id) {
@@ -1,13 +1,24 @@
 // Inspector panel - shows entity properties
 
-use egui::{Context, ScrollArea};
-use glam::Quat;
+use egui::ScrollArea;
+use engine_physics::{Collider, ColliderShape};
+use glam::{Quat, Vec3};
+use serde::{Deserialize, Serialize};
 use engine_scene::{
+    animation::AnimationClip,
+    animator::{Animator, AnimatorLayer, AnimatorParameter, AnimatorState},
     components::{
-        Camera, Light, LightType, MeshRenderer, ParticleEmitter, TerrainGenerator, TerrainWater, Water,
+        BehaviorAgent, Camera, Foliage, Light, LightType, MeshLod, MeshRenderer, ParticleEmitter,
+        RagdollRig, TerrainGenerator, TerrainStreaming, TerrainWater, Water,
     },
     entity::EntityId,
+    grass::Grass,
+    ik::{FootPlacement, LegIk, LookAt},
+    navigation::NavAgent,
     scene::Scene,
+    time_of_day::SunLight,
+    wind::Wind,
+    Spline, SplineKind,
 };
 
 /// Result of rendering the inspector panel - indicates what changed
@@ -16,10 +27,48 @@ pub struct InspectorResult {
     pub terrain_changed: bool,
     pub water_changed: bool,
     pub components_changed: bool,
+    /// The selected entity's transform was edited
+    pub transform_changed: bool,
+    /// Transform edit to apply to every selected entity (multi-selection)
+    pub group_transform: Option<GroupTransformEdit>,
+    /// True when a group edit begins (drag start or typed value), for undo
+    pub group_edit_started: bool,
+}
+
+/// Transform edit shared by all entities in a multi-selection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupTransformEdit {
+    /// Move every entity by an offset
+    Translate(Vec3),
+    /// Set one scale axis (0 = X, 1 = Y, 2 = Z)
+    SetScaleAxis(usize, f32),
+    /// Set one Euler rotation axis in degrees (0 = X, 1 = Y, 2 = Z)
+    SetRotationAxis(usize, f32),
+}
+
+/// Apply a group transform edit to the given entities
+pub fn apply_group_transform(scene: &mut Scene, entity_ids: &[EntityId], edit: GroupTransformEdit) {
+    for &entity_id in entity_ids {
+        let Some(entity) = scene.get_entity_mut(entity_id) else {
+            continue;
+        };
+        let transform = &mut entity.transform;
+        match edit {
+            GroupTransformEdit::Translate(delta) => transform.position += delta,
+            GroupTransformEdit::SetScaleAxis(axis, value) => transform.scale[axis] = value,
+            GroupTransformEdit::SetRotationAxis(axis, degrees) => {
+                let (roll, pitch, yaw) = quat_to_euler(transform.rotation);
+                let mut euler = [roll, pitch, yaw];
+                euler[axis] = degrees.to_radians();
+                transform.rotation = euler_to_quat(euler[0], euler[1], euler[2]);
+            }
+        }
+    }
 }
 
 /// State for the inspector panel including snapping settings
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct InspectorState {
     /// Enable snapping for position
     pub snap_position: bool,
@@ -33,6 +82,8 @@ pub struct InspectorState {
     pub scale_grid: f32,
     /// Rotation snap angle (degrees)
     pub rotation_grid: f32,
+    /// Place new and dragged entities on the surface under the cursor
+    pub snap_surface: bool,
 }
 
 impl Default for InspectorState {
@@ -44,13 +95,14 @@ impl Default for InspectorState {
             position_grid: 1.0,
             scale_grid: 0.25,
             rotation_grid: 15.0,
+            snap_surface: true,
         }
     }
 }
 
 impl InspectorState {
     /// Snap a value to the grid
-    fn snap_value(value: f32, grid: f32) -> f32 {
+    pub fn snap_value(value: f32, grid: f32) -> f32 {
         if grid > 0.0 {
             (value / grid).round() * grid
         } else {
@@ -60,9 +112,10 @@ impl InspectorState {
 }
 
 pub fn render_inspector_panel(
-    ctx: &Context,
+    ui: &mut egui::Ui,
     scene: &mut Scene,
     selected_entity: &mut Option<EntityId>,
+    selection_count: usize,
     inspector_state: &mut InspectorState,
     is_locked: bool,
 ) -> InspectorResult {
@@ -70,281 +123,475 @@ pub fn render_inspector_panel(
     let mut components_to_remove: Vec<ComponentType> = Vec::new();
     let mut component_to_add: Option<ComponentType> = None;
 
-    egui::SidePanel::right("inspector_panel")
-        .default_width(300.0)
-        .show(ctx, |ui| {
-            ui.heading("Inspector");
-            ui.separator();
+    ui.heading("Inspector");
+    ui.separator();
 
-            // Show locked warning
-            if is_locked {
-                ui.horizontal(|ui| {
-                    ui.colored_label(egui::Color32::from_rgb(255, 180, 100), "🔒 Entity is locked");
-                });
-                ui.separator();
-            }
+    // Show locked warning
+    if is_locked {
+        ui.horizontal(|ui| {
+            ui.colored_label(egui::Color32::from_rgb(255, 180, 100), "🔒 Entity is locked");
+        });
+        ui.separator();
+    }
 
-            ScrollArea::vertical().show(ui, |ui| {
-                if let Some(entity_id) = *selected_entity {
-                    if let Some(entity) = scene.get_entity_mut(entity_id) {
-                        // Entity name
-                        ui.label("Name:");
-                        ui.text_edit_singleline(&mut entity.name);
-                        ui.add_space(10.0);
-
-                        // Transform component (always present)
-                        ui.collapsing("Transform", |ui| {
-                            // Snapping controls
-                            ui.horizontal(|ui| {
-                                ui.label("Snap:");
-                                ui.checkbox(&mut inspector_state.snap_position, "Pos");
-                                ui.checkbox(&mut inspector_state.snap_scale, "Scale");
-                                ui.checkbox(&mut inspector_state.snap_rotation, "Rot");
-                            });
-
-                            // Grid size settings (collapsible)
-                            ui.collapsing("Snap Settings", |ui| {
-                                ui.horizontal(|ui| {
-                                    ui.label("Position Grid:");
-                                    ui.add(egui::DragValue::new(&mut inspector_state.position_grid)
-                                        .speed(0.1)
-                                        .range(0.01..=10.0));
-                                });
-                                ui.horizontal(|ui| {
-                                    ui.label("Scale Grid:");
-                                    ui.add(egui::DragValue::new(&mut inspector_state.scale_grid)
-                                        .speed(0.05)
-                                        .range(0.01..=1.0));
-                                });
-                                ui.horizontal(|ui| {
-                                    ui.label("Rotation Grid:");
-                                    ui.add(egui::DragValue::new(&mut inspector_state.rotation_grid)
-                                        .speed(1.0)
-                                        .range(1.0..=90.0)
-                                        .suffix("°"));
-                                });
-                            });
-
-                            ui.separator();
-
-                            ui.label("Position:");
-                            ui.horizontal(|ui| {
-                                ui.label("X:");
-                                let mut x = entity.transform.position.x;
-                                if ui.add(egui::DragValue::new(&mut x).speed(0.1)).changed() {
-                                    entity.transform.position.x = if inspector_state.snap_position {
-                                        InspectorState::snap_value(x, inspector_state.position_grid)
-                                    } else {
-                                        x
-                                    };
-                                }
-                                ui.label("Y:");
-                                let mut y = entity.transform.position.y;
-                                if ui.add(egui::DragValue::new(&mut y).speed(0.1)).changed() {
-                                    entity.transform.position.y = if inspector_state.snap_position {
-                                        InspectorState::snap_value(y, inspector_state.position_grid)
-                                    } else {
-                                        y
-                                    };
-                                }
-                                ui.label("Z:");
-                                let mut z = entity.transform.position.z;
-                                if ui.add(egui::DragValue::new(&mut z).speed(0.1)).changed() {
-                                    entity.transform.position.z = if inspector_state.snap_position {
-                                        InspectorState::snap_value(z, inspector_state.position_grid)
-                                    } else {
-                                        z
-                                    };
-                                }
-                            });
-
-                            ui.add_space(5.0);
-                            ui.label("Scale:");
-                            ui.horizontal(|ui| {
-                                ui.label("X:");
-                                let mut sx = entity.transform.scale.x;
-                                if ui.add(egui::DragValue::new(&mut sx).speed(0.01)).changed() {
-                                    entity.transform.scale.x = if inspector_state.snap_scale {
-                                        InspectorState::snap_value(sx, inspector_state.scale_grid)
-                                    } else {
-                                        sx
-                                    };
-                                }
-                                ui.label("Y:");
-                                let mut sy = entity.transform.scale.y;
-                                if ui.add(egui::DragValue::new(&mut sy).speed(0.01)).changed() {
-                                    entity.transform.scale.y = if inspector_state.snap_scale {
-                                        InspectorState::snap_value(sy, inspector_state.scale_grid)
-                                    } else {
-                                        sy
-                                    };
-                                }
-                                ui.label("Z:");
-                                let mut sz = entity.transform.scale.z;
-                                if ui.add(egui::DragValue::new(&mut sz).speed(0.01)).changed() {
-                                    entity.transform.scale.z = if inspector_state.snap_scale {
-                                        InspectorState::snap_value(sz, inspector_state.scale_grid)
-                                    } else {
-                                        sz
-                                    };
-                                }
-                            });
-
-                            ui.add_space(5.0);
-                            ui.label("Rotation (Euler Degrees):");
-                            // Convert quaternion to euler angles for easier editing
-                            let (roll, pitch, yaw) = quat_to_euler(entity.transform.rotation);
-                            let mut euler_x = roll.to_degrees();
-                            let mut euler_y = pitch.to_degrees();
-                            let mut euler_z = yaw.to_degrees();
-
-                            let mut rotation_changed = false;
-                            ui.horizontal(|ui| {
-                                ui.label("X:");
-                                if ui.add(egui::DragValue::new(&mut euler_x).speed(1.0).suffix("°")).changed() {
-                                    if inspector_state.snap_rotation {
-                                        euler_x = InspectorState::snap_value(euler_x, inspector_state.rotation_grid);
-                                    }
-                                    rotation_changed = true;
-                                }
-                                ui.label("Y:");
-                                if ui.add(egui::DragValue::new(&mut euler_y).speed(1.0).suffix("°")).changed() {
-                                    if inspector_state.snap_rotation {
-                                        euler_y = InspectorState::snap_value(euler_y, inspector_state.rotation_grid);
-                                    }
-                                    rotation_changed = true;
-                                }
-                                ui.label("Z:");
-                                if ui.add(egui::DragValue::new(&mut euler_z).speed(1.0).suffix("°")).changed() {
-                                    if inspector_state.snap_rotation {
-                                        euler_z = InspectorState::snap_value(euler_z, inspector_state.rotation_grid);
-                                    }
-                                    rotation_changed = true;
-                                }
-                            });
-
-                            if rotation_changed {
-                                entity.transform.rotation = euler_to_quat(
-                                    euler_x.to_radians(),
-                                    euler_y.to_radians(),
-                                    euler_z.to_radians(),
-                                );
-                            }
+    ScrollArea::vertical().show(ui, |ui| {
+        if selection_count > 1 && selected_entity.is_some() {
+            if let Some(entity) = selected_entity.and_then(|id| scene.get_entity(id)) {
+                render_multi_edit_ui(
+                    ui,
+                    entity.transform,
+                    selection_count,
+                    inspector_state,
+                    &mut result,
+                );
+            }
+        } else if let Some(entity_id) = *selected_entity {
+            if let Some(entity) = scene.get_entity_mut(entity_id) {
+                // Entity name
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut entity.name);
+                ui.add_space(10.0);
+
+                // Transform component (always present)
+                let transform_before = entity.transform;
+                ui.collapsing("Transform", |ui| {
+                    // Snapping controls
+                    ui.horizontal(|ui| {
+                        ui.label("Snap:");
+                        ui.checkbox(&mut inspector_state.snap_position, "Pos");
+                        ui.checkbox(&mut inspector_state.snap_scale, "Scale");
+                        ui.checkbox(&mut inspector_state.snap_rotation, "Rot");
+                        ui.checkbox(&mut inspector_state.snap_surface, "Surface")
+                            .on_hover_text("Place and drag entities onto terrain and meshes");
+                    });
+
+                    // Grid size settings (collapsible)
+                    ui.collapsing("Snap Settings", |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Position Grid:");
+                            ui.add(egui::DragValue::new(&mut inspector_state.position_grid)
+                                .speed(0.1)
+                                .range(0.01..=10.0));
                         });
-
-                        ui.add_space(10.0);
-
-                        // Track which components exist for "Add Component" dropdown
-                        let has_mesh_renderer = entity.has_component::<MeshRenderer>();
-                        let has_camera = entity.has_component::<Camera>();
-                        let has_light = entity.has_component::<Light>();
-                        let has_water = entity.has_component::<Water>();
-                        let has_terrain_water = entity.has_component::<TerrainWater>();
-                        let has_terrain_gen = entity.has_component::<TerrainGenerator>();
-                        let has_particle = entity.has_component::<ParticleEmitter>();
-
-                        // MeshRenderer component
-                        if let Some(mesh_renderer) = entity.get_component_mut::<MeshRenderer>() {
-                            if render_component_header(ui, "MeshRenderer") {
-                                components_to_remove.push(ComponentType::MeshRenderer);
-                            }
-                            render_mesh_renderer_ui(ui, mesh_renderer);
-                            ui.add_space(5.0);
+                        ui.horizontal(|ui| {
+                            ui.label("Scale Grid:");
+                            ui.add(egui::DragValue::new(&mut inspector_state.scale_grid)
+                                .speed(0.05)
+                                .range(0.01..=1.0));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Rotation Grid:");
+                            ui.add(egui::DragValue::new(&mut inspector_state.rotation_grid)
+                                .speed(1.0)
+                                .range(1.0..=90.0)
+                                .suffix("°"));
+                        });
+                    });
+
+                    ui.separator();
+
+                    ui.label("Position:");
+                    ui.horizontal(|ui| {
+                        ui.label("X:");
+                        let mut x = entity.transform.position.x;
+                        if ui.add(egui::DragValue::new(&mut x).speed(0.1)).changed() {
+                            entity.transform.position.x = if inspector_state.snap_position {
+                                InspectorState::snap_value(x, inspector_state.position_grid)
+                            } else {
+                                x
+                            };
                         }
-
-                        // Camera component
-                        if let Some(camera) = entity.get_component_mut::<Camera>() {
-                            if render_component_header(ui, "Camera") {
-                                components_to_remove.push(ComponentType::Camera);
-                            }
-                            render_camera_ui(ui, camera);
-                            ui.add_space(5.0);
+                        ui.label("Y:");
+                        let mut y = entity.transform.position.y;
+                        if ui.add(egui::DragValue::new(&mut y).speed(0.1)).changed() {
+                            entity.transform.position.y = if inspector_state.snap_position {
+                                InspectorState::snap_value(y, inspector_state.position_grid)
+                            } else {
+                                y
+                            };
                         }
-
-                        // Light component
-                        if let Some(light) = entity.get_component_mut::<Light>() {
-                            if render_component_header(ui, "Light") {
-                                components_to_remove.push(ComponentType::Light);
-                            }
-                            render_light_ui(ui, light);
-                            ui.add_space(5.0);
+                        ui.label("Z:");
+                        let mut z = entity.transform.position.z;
+                        if ui.add(egui::DragValue::new(&mut z).speed(0.1)).changed() {
+                            entity.transform.position.z = if inspector_state.snap_position {
+                                InspectorState::snap_value(z, inspector_state.position_grid)
+                            } else {
+                                z
+                            };
                         }
-
-                        // TerrainGenerator component
-                        if let Some(terrain_gen) = entity.get_component_mut::<TerrainGenerator>() {
-                            if render_component_header(ui, "Terrain Generator") {
-                                components_to_remove.push(ComponentType::TerrainGenerator);
-                            }
-                            result.terrain_changed |= render_terrain_generator_ui(ui, terrain_gen);
-                            ui.add_space(5.0);
+                    });
+
+                    ui.add_space(5.0);
+                    ui.label("Scale:");
+                    ui.horizontal(|ui| {
+                        ui.label("X:");
+                        let mut sx = entity.transform.scale.x;
+                        if ui.add(egui::DragValue::new(&mut sx).speed(0.01)).changed() {
+                            entity.transform.scale.x = if inspector_state.snap_scale {
+                                InspectorState::snap_value(sx, inspector_state.scale_grid)
+                            } else {
+                                sx
+                            };
                         }
-
-                        // TerrainWater component
-                        if let Some(terrain_water) = entity.get_component_mut::<TerrainWater>() {
-                            if render_component_header(ui, "Terrain Water") {
-                                components_to_remove.push(ComponentType::TerrainWater);
+                        ui.label("Y:");
+                        let mut sy = entity.transform.scale.y;
+                        if ui.add(egui::DragValue::new(&mut sy).speed(0.01)).changed() {
+                            entity.transform.scale.y = if inspector_state.snap_scale {
+                                InspectorState::snap_value(sy, inspector_state.scale_grid)
+                            } else {
+                                sy
+                            };
+                        }
+                        ui.label("Z:");
+                        let mut sz = entity.transform.scale.z;
+                        if ui.add(egui::DragValue::new(&mut sz).speed(0.01)).changed() {
+                            entity.transform.scale.z = if inspector_state.snap_scale {
+                                InspectorState::snap_value(sz, inspector_state.scale_grid)
+                            } else {
+                                sz
+                            };
+                        }
+                    });
+
+                    ui.add_space(5.0);
+                    ui.label("Rotation (Euler Degrees):");
+                    // Convert quaternion to euler angles for easier editing
+                    let (roll, pitch, yaw) = quat_to_euler(entity.transform.rotation);
+                    let mut euler_x = roll.to_degrees();
+                    let mut euler_y = pitch.to_degrees();
+                    let mut euler_z = yaw.to_degrees();
+
+                    let mut rotation_changed = false;
+                    ui.horizontal(|ui| {
+                        ui.label("X:");
+                        if ui.add(egui::DragValue::new(&mut euler_x).speed(1.0).suffix("°")).changed() {
+                            if inspector_state.snap_rotation {
+                                euler_x = InspectorState::snap_value(euler_x, inspector_state.rotation_grid);
                             }
-                            result.water_changed |= render_terrain_water_ui(ui, terrain_water);
-                            ui.add_space(5.0);
+                            rotation_changed = true;
                         }
-
-                        // Water component
-                        if let Some(water) = entity.get_component_mut::<Water>() {
-                            if render_component_header(ui, "Water") {
-                                components_to_remove.push(ComponentType::Water);
+                        ui.label("Y:");
+                        if ui.add(egui::DragValue::new(&mut euler_y).speed(1.0).suffix("°")).changed() {
+                            if inspector_state.snap_rotation {
+                                euler_y = InspectorState::snap_value(euler_y, inspector_state.rotation_grid);
                             }
-                            render_water_ui(ui, water);
-                            ui.add_space(5.0);
+                            rotation_changed = true;
                         }
-
-                        // ParticleEmitter component
-                        if let Some(particle) = entity.get_component_mut::<ParticleEmitter>() {
-                            if render_component_header(ui, "Particle Emitter") {
-                                components_to_remove.push(ComponentType::ParticleEmitter);
+                        ui.label("Z:");
+                        if ui.add(egui::DragValue::new(&mut euler_z).speed(1.0).suffix("°")).changed() {
+                            if inspector_state.snap_rotation {
+                                euler_z = InspectorState::snap_value(euler_z, inspector_state.rotation_grid);
                             }
-                            render_particle_emitter_ui(ui, particle);
-                            ui.add_space(5.0);
+                            rotation_changed = true;
                         }
+                    });
+
+                    if rotation_changed {
+                        entity.transform.rotation = euler_to_quat(
+                            euler_x.to_radians(),
+                            euler_y.to_radians(),
+                            euler_z.to_radians(),
+                        );
+                    }
+                });
+                result.transform_changed = entity.transform != transform_before;
+
+                ui.add_space(10.0);
+
+                // Track which components exist for "Add Component" dropdown
+                let has_mesh_renderer = entity.has_component::<MeshRenderer>();
+                let has_camera = entity.has_component::<Camera>();
+                let has_light = entity.has_component::<Light>();
+                let has_collider = entity.has_component::<Collider>();
+                let has_water = entity.has_component::<Water>();
+                let has_terrain_water = entity.has_component::<TerrainWater>();
+                let has_terrain_gen = entity.has_component::<TerrainGenerator>();
+                let has_terrain_streaming = entity.has_component::<TerrainStreaming>();
+                let has_spline = entity.has_component::<Spline>();
+                let has_particle = entity.has_component::<ParticleEmitter>();
+                let has_animation = entity.has_component::<AnimationClip>();
+                let has_animator = entity.has_component::<Animator>();
+                let has_look_at = entity.has_component::<LookAt>();
+                let has_foot_placement = entity.has_component::<FootPlacement>();
+                let has_ragdoll = entity.has_component::<RagdollRig>();
+                let has_nav_agent = entity.has_component::<NavAgent>();
+                let has_behavior = entity.has_component::<BehaviorAgent>();
+                let has_sun_light = entity.has_component::<SunLight>();
+                let has_wind = entity.has_component::<Wind>();
+                let has_grass = entity.has_component::<Grass>();
+                let clip_for_state = entity.get_component::<AnimationClip>().cloned();
+                let mesh_path = entity.get_component::<MeshRenderer>().map(|m| m.mesh_path.clone());
+
+                // MeshRenderer component
+                if let Some(mesh_renderer) = entity.get_component_mut::<MeshRenderer>() {
+                    if render_component_header(ui, "MeshRenderer") {
+                        components_to_remove.push(ComponentType::MeshRenderer);
+                    }
+                    render_mesh_renderer_ui(ui, mesh_renderer);
+                    ui.add_space(5.0);
+                }
 
-                        // Add Component dropdown
-                        ui.separator();
-                        ui.add_space(5.0);
-                        egui::ComboBox::from_label("Add Component")
-                            .selected_text("Select...")
-                            .show_ui(ui, |ui| {
-                                if !has_mesh_renderer && ui.selectable_label(false, "MeshRenderer").clicked() {
-                                    component_to_add = Some(ComponentType::MeshRenderer);
-                                }
-                                if !has_camera && ui.selectable_label(false, "Camera").clicked() {
-                                    component_to_add = Some(ComponentType::Camera);
-                                }
-                                if !has_light && ui.selectable_label(false, "Light").clicked() {
-                                    component_to_add = Some(ComponentType::Light);
-                                }
-                                if !has_water && ui.selectable_label(false, "Water").clicked() {
-                                    component_to_add = Some(ComponentType::Water);
-                                }
-                                if !has_terrain_water && ui.selectable_label(false, "TerrainWater").clicked() {
-                                    component_to_add = Some(ComponentType::TerrainWater);
-                                }
-                                if !has_terrain_gen && ui.selectable_label(false, "TerrainGenerator").clicked() {
-                                    component_to_add = Some(ComponentType::TerrainGenerator);
-                                }
-                                if !has_particle && ui.selectable_label(false, "ParticleEmitter").clicked() {
-                                    component_to_add = Some(ComponentType::ParticleEmitter);
-                                }
-                            });
-                    } else {
-                        ui.label("Entity not found");
-                        *selected_entity = None;
-                    }
-                } else {
-                    ui.label("No entity selected");
-                    ui.add_space(10.0);
-                    ui.label("Select an entity from the Hierarchy panel to view its properties.");
+                // Camera component
+                if let Some(camera) = entity.get_component_mut::<Camera>() {
+                    if render_component_header(ui, "Camera") {
+                        components_to_remove.push(ComponentType::Camera);
+                    }
+                    render_camera_ui(ui, camera);
+                    ui.add_space(5.0);
+                }
+
+                // Light component
+                if let Some(light) = entity.get_component_mut::<Light>() {
+                    if render_component_header(ui, "Light") {
+                        components_to_remove.push(ComponentType::Light);
+                    }
+                    render_light_ui(ui, light);
+                    ui.add_space(5.0);
+                }
+
+                // Collider component
+                if let Some(collider) = entity.get_component_mut::<Collider>() {
+                    if render_component_header(ui, "Collider") {
+                        components_to_remove.push(ComponentType::Collider);
+                    }
+                    render_collider_ui(ui, collider, mesh_path.as_deref());
+                    ui.add_space(5.0);
+                }
+
+                // TerrainGenerator component
+                if let Some(terrain_gen) = entity.get_component_mut::<TerrainGenerator>() {
+                    if render_component_header(ui, "Terrain Generator") {
+                        components_to_remove.push(ComponentType::TerrainGenerator);
+                    }
+                    result.terrain_changed |= render_terrain_generator_ui(ui, terrain_gen);
+                    ui.add_space(5.0);
+                }
+
+                // TerrainStreaming component
+                if let Some(streaming) = entity.get_component_mut::<TerrainStreaming>() {
+                    if render_component_header(ui, "Terrain Streaming") {
+                        components_to_remove.push(ComponentType::TerrainStreaming);
+                    }
+                    render_terrain_streaming_ui(ui, streaming);
+                    ui.add_space(5.0);
+                }
+
+                // TerrainWater component
+                if let Some(terrain_water) = entity.get_component_mut::<TerrainWater>() {
+                    if render_component_header(ui, "Terrain Water") {
+                        components_to_remove.push(ComponentType::TerrainWater);
+                    }
+                    result.water_changed |= render_terrain_water_ui(ui, terrain_water);
+                    ui.add_space(5.0);
+                }
+
+                // Water component
+                if let Some(water) = entity.get_component_mut::<Water>() {
+                    if render_component_header(ui, "Water") {
+                        components_to_remove.push(ComponentType::Water);
+                    }
+                    render_water_ui(ui, water);
+                    ui.add_space(5.0);
+                }
+
+                // Spline component
+                if let Some(spline) = entity.get_component_mut::<Spline>() {
+                    if render_component_header(ui, "Spline") {
+                        components_to_remove.push(ComponentType::Spline);
+                    }
+                    result.water_changed |= render_spline_ui(ui, spline);
+                    ui.add_space(5.0);
+                }
+
+                // ParticleEmitter component
+                if let Some(particle) = entity.get_component_mut::<ParticleEmitter>() {
+                    if render_component_header(ui, "Particle Emitter") {
+                        components_to_remove.push(ComponentType::ParticleEmitter);
+                    }
+                    render_particle_emitter_ui(ui, particle);
+                    ui.add_space(5.0);
+                }
+
+                // AnimationClip component
+                if let Some(clip) = entity.get_component_mut::<AnimationClip>() {
+                    if render_component_header(ui, "Animation Clip") {
+                        components_to_remove.push(ComponentType::AnimationClip);
+                    }
+                    render_animation_clip_ui(ui, clip);
+                    ui.add_space(5.0);
+                }
+
+                // Animator component
+                if let Some(animator) = entity.get_component_mut::<Animator>() {
+                    if render_component_header(ui, "Animator") {
+                        components_to_remove.push(ComponentType::Animator);
+                    }
+                    render_animator_ui(ui, animator, clip_for_state.as_ref());
+                    ui.add_space(5.0);
+                }
+
+                // LookAt component
+                if let Some(look_at) = entity.get_component_mut::<LookAt>() {
+                    if render_component_header(ui, "Look At") {
+                        components_to_remove.push(ComponentType::LookAt);
+                    }
+                    render_look_at_ui(ui, look_at);
+                    ui.add_space(5.0);
+                }
+
+                // FootPlacement component
+                if let Some(placement) = entity.get_component_mut::<FootPlacement>() {
+                    if render_component_header(ui, "Foot Placement") {
+                        components_to_remove.push(ComponentType::FootPlacement);
+                    }
+                    render_foot_placement_ui(ui, placement);
+                    ui.add_space(5.0);
+                }
+
+                // RagdollRig component
+                if let Some(rig) = entity.get_component_mut::<RagdollRig>() {
+                    if render_component_header(ui, "Ragdoll Rig") {
+                        components_to_remove.push(ComponentType::RagdollRig);
+                    }
+                    render_ragdoll_rig_ui(ui, rig);
+                    ui.add_space(5.0);
+                }
+
+                // NavAgent component
+                if let Some(agent) = entity.get_component_mut::<NavAgent>() {
+                    if render_component_header(ui, "Nav Agent") {
+                        components_to_remove.push(ComponentType::NavAgent);
+                    }
+                    render_nav_agent_ui(ui, agent);
+                    ui.add_space(5.0);
+                }
+
+                // BehaviorAgent component
+                if let Some(agent) = entity.get_component_mut::<BehaviorAgent>() {
+                    if render_component_header(ui, "Behavior Agent") {
+                        components_to_remove.push(ComponentType::BehaviorAgent);
+                    }
+                    render_behavior_agent_ui(ui, agent);
+                    ui.add_space(5.0);
+                }
+
+                // SunLight component
+                if let Some(sun) = entity.get_component_mut::<SunLight>() {
+                    if render_component_header(ui, "Sun Light") {
+                        components_to_remove.push(ComponentType::SunLight);
+                    }
+                    render_sun_light_ui(ui, sun);
+                    ui.add_space(5.0);
+                }
+
+                // Wind component
+                if let Some(wind) = entity.get_component_mut::<Wind>() {
+                    if render_component_header(ui, "Wind") {
+                        components_to_remove.push(ComponentType::Wind);
+                    }
+                    render_wind_ui(ui, wind);
+                    ui.add_space(5.0);
+                }
+
+                // Grass component (density is painted with the grass brush)
+                if let Some(grass) = entity.get_component_mut::<Grass>() {
+                    if render_component_header(ui, "Grass") {
+                        components_to_remove.push(ComponentType::Grass);
+                    }
+                    render_grass_ui(ui, grass);
+                    ui.add_space(5.0);
+                }
+
+                // Foliage component (instances are painted or scattered, not edited here)
+                if let Some(foliage) = entity.get_component_mut::<Foliage>() {
+                    if render_component_header(ui, "Foliage") {
+                        components_to_remove.push(ComponentType::Foliage);
+                    }
+                    render_foliage_ui(ui, foliage);
+                    ui.add_space(5.0);
                 }
-            });
-        });
+
+                // Add Component dropdown
+                ui.separator();
+                ui.add_space(5.0);
+                egui::ComboBox::from_label("Add Component")
+                    .selected_text("Select...")
+                    .show_ui(ui, |ui| {
+                        if !has_mesh_renderer && ui.selectable_label(false, "MeshRenderer").clicked() {
+                            component_to_add = Some(ComponentType::MeshRenderer);
+                        }
+                        if !has_camera && ui.selectable_label(false, "Camera").clicked() {
+                            component_to_add = Some(ComponentType::Camera);
+                        }
+                        if !has_light && ui.selectable_label(false, "Light").clicked() {
+                            component_to_add = Some(ComponentType::Light);
+                        }
+                        if !has_collider && ui.selectable_label(false, "Collider").clicked() {
+                            component_to_add = Some(ComponentType::Collider);
+                        }
+                        if !has_water && ui.selectable_label(false, "Water").clicked() {
+                            component_to_add = Some(ComponentType::Water);
+                        }
+                        if !has_terrain_water && ui.selectable_label(false, "TerrainWater").clicked() {
+                            component_to_add = Some(ComponentType::TerrainWater);
+                        }
+                        if !has_terrain_gen && ui.selectable_label(false, "TerrainGenerator").clicked() {
+                            component_to_add = Some(ComponentType::TerrainGenerator);
+                        }
+                        if !has_terrain_streaming
+                            && ui.selectable_label(false, "TerrainStreaming").clicked()
+                        {
+                            component_to_add = Some(ComponentType::TerrainStreaming);
+                        }
+                        if !has_spline && ui.selectable_label(false, "Spline").clicked() {
+                            component_to_add = Some(ComponentType::Spline);
+                        }
+                        if !has_particle && ui.selectable_label(false, "ParticleEmitter").clicked() {
+                            component_to_add = Some(ComponentType::ParticleEmitter);
+                        }
+                        if !has_animation && ui.selectable_label(false, "AnimationClip").clicked() {
+                            component_to_add = Some(ComponentType::AnimationClip);
+                        }
+                        if !has_animator && ui.selectable_label(false, "Animator").clicked() {
+                            component_to_add = Some(ComponentType::Animator);
+                        }
+                        if !has_look_at && ui.selectable_label(false, "LookAt").clicked() {
+                            component_to_add = Some(ComponentType::LookAt);
+                        }
+                        if !has_foot_placement
+                            && ui.selectable_label(false, "FootPlacement").clicked()
+                        {
+                            component_to_add = Some(ComponentType::FootPlacement);
+                        }
+                        if !has_ragdoll && ui.selectable_label(false, "RagdollRig").clicked() {
+                            component_to_add = Some(ComponentType::RagdollRig);
+                        }
+                        if !has_nav_agent && ui.selectable_label(false, "NavAgent").clicked() {
+                            component_to_add = Some(ComponentType::NavAgent);
+                        }
+                        if !has_behavior && ui.selectable_label(false, "BehaviorAgent").clicked() {
+                            component_to_add = Some(ComponentType::BehaviorAgent);
+                        }
+                        if !has_sun_light && ui.selectable_label(false, "SunLight").clicked() {
+                            component_to_add = Some(ComponentType::SunLight);
+                        }
+                        if !has_wind && ui.selectable_label(false, "Wind").clicked() {
+                            component_to_add = Some(ComponentType::Wind);
+                        }
+                        if !has_grass && ui.selectable_label(false, "Grass").clicked() {
+                            component_to_add = Some(ComponentType::Grass);
+                        }
+                    });
+            } else {
+                ui.label("Entity not found");
+                *selected_entity = None;
+            }
+        } else {
+            ui.label("No entity selected");
+            ui.add_space(10.0);
+            ui.label("Select an entity from the Hierarchy panel to view its properties.");
+        }
+    });
 
     // Process component removals (after UI rendering to avoid borrow issues)
     if let Some(entity_id) = *selected_entity {
@@ -354,10 +601,28 @@ pub fn render_inspector_panel(
                     ComponentType::MeshRenderer => { entity.remove_component::<MeshRenderer>(); }
                     ComponentType::Camera => { entity.remove_component::<Camera>(); }
                     ComponentType::Light => { entity.remove_component::<Light>(); }
+                    ComponentType::Collider => { entity.remove_component::<Collider>(); }
                     ComponentType::Water => { entity.remove_component::<Water>(); }
                     ComponentType::TerrainWater => { entity.remove_component::<TerrainWater>(); }
                     ComponentType::TerrainGenerator => { entity.remove_component::<TerrainGenerator>(); }
+                    ComponentType::TerrainStreaming => { entity.remove_component::<TerrainStreaming>(); }
+                    ComponentType::Spline => {
+                        entity.remove_component::<Spline>();
+                        // Drop its river or road mesh
+                        result.water_changed = true;
+                    }
                     ComponentType::ParticleEmitter => { entity.remove_component::<ParticleEmitter>(); }
+                    ComponentType::AnimationClip => { entity.remove_component::<AnimationClip>(); }
+                    ComponentType::Animator => { entity.remove_component::<Animator>(); }
+                    ComponentType::LookAt => { entity.remove_component::<LookAt>(); }
+                    ComponentType::FootPlacement => { entity.remove_component::<FootPlacement>(); }
+                    ComponentType::RagdollRig => { entity.remove_component::<RagdollRig>(); }
+                    ComponentType::NavAgent => { entity.remove_component::<NavAgent>(); }
+                    ComponentType::BehaviorAgent => { entity.remove_component::<BehaviorAgent>(); }
+                    ComponentType::SunLight => { entity.remove_component::<SunLight>(); }
+                    ComponentType::Wind => { entity.remove_component::<Wind>(); }
+                    ComponentType::Foliage => { entity.remove_component::<Foliage>(); }
+                    ComponentType::Grass => { entity.remove_component::<Grass>(); }
                 }
                 result.components_changed = true;
             }
@@ -374,6 +639,9 @@ pub fn render_inspector_panel(
                     ComponentType::Light => {
                         entity.add_component(Light::directional([0.0, -1.0, 0.0], [1.0, 1.0, 1.0], 1.0));
                     }
+                    ComponentType::Collider => {
+                        entity.add_component(Collider::box_collider(Vec3::splat(0.5)));
+                    }
                     ComponentType::Water => {
                         entity.add_component(Water::default());
                     }
@@ -383,9 +651,49 @@ pub fn render_inspector_panel(
                     ComponentType::TerrainGenerator => {
                         entity.add_component(TerrainGenerator::default());
                     }
+                    ComponentType::TerrainStreaming => {
+                        entity.add_component(TerrainStreaming::default());
+                    }
+                    ComponentType::Spline => {
+                        entity.add_component(Spline::default());
+                    }
                     ComponentType::ParticleEmitter => {
                         entity.add_component(ParticleEmitter::default());
                     }
+                    ComponentType::AnimationClip => {
+                        entity.add_component(AnimationClip::default());
+                    }
+                    ComponentType::Animator => {
+                        entity
+                            .add_component(Animator::new().with_layer(AnimatorLayer::new("Base")));
+                    }
+                    ComponentType::LookAt => {
+                        entity.add_component(LookAt::default());
+                    }
+                    ComponentType::FootPlacement => {
+                        entity.add_component(FootPlacement::default());
+                    }
+                    ComponentType::RagdollRig => {
+                        entity.add_component(RagdollRig::default());
+                    }
+                    ComponentType::NavAgent => {
+                        entity.add_component(NavAgent::default());
+                    }
+                    ComponentType::BehaviorAgent => {
+                        entity.add_component(BehaviorAgent::default());
+                    }
+                    ComponentType::SunLight => {
+                        entity.add_component(SunLight::default());
+                    }
+                    ComponentType::Wind => {
+                        entity.add_component(Wind::default());
+                    }
+                    ComponentType::Foliage => {
+                        entity.add_component(Foliage::default());
+                    }
+                    ComponentType::Grass => {
+                        entity.add_component(Grass::default());
+                    }
                 }
                 result.components_changed = true;
             }
@@ -395,15 +703,103 @@ pub fn render_inspector_panel(
     result
 }
 
+/// Render shared transform fields for a multi-selection.
+/// Values shown are those of the primary selection; position edits move
+/// the whole group, scale and rotation edits set that axis on every entity.
+fn render_multi_edit_ui(
+    ui: &mut egui::Ui,
+    transform: engine_scene::transform::Transform,
+    selection_count: usize,
+    inspector_state: &InspectorState,
+    result: &mut InspectorResult,
+) {
+    ui.strong(format!("{} entities selected", selection_count));
+    ui.label("Editing shared transform (values from active entity)");
+    ui.add_space(10.0);
+
+    let mut track = |response: egui::Response| -> bool {
+        if response.drag_started() || (response.changed() && !response.dragged()) {
+            result.group_edit_started = true;
+        }
+        response.changed()
+    };
+    let mut edit: Option<GroupTransformEdit> = None;
+    let labels = ["X:", "Y:", "Z:"];
+
+    ui.label("Position (moves group):");
+    ui.horizontal(|ui| {
+        for (axis, label) in labels.iter().enumerate() {
+            ui.label(*label);
+            let mut value = transform.position[axis];
+            if track(ui.add(egui::DragValue::new(&mut value).speed(0.1))) {
+                if inspector_state.snap_position {
+                    value = InspectorState::snap_value(value, inspector_state.position_grid);
+                }
+                let mut delta = Vec3::ZERO;
+                delta[axis] = value - transform.position[axis];
+                edit = Some(GroupTransformEdit::Translate(delta));
+            }
+        }
+    });
+
+    ui.add_space(5.0);
+    ui.label("Scale:");
+    ui.horizontal(|ui| {
+        for (axis, label) in labels.iter().enumerate() {
+            ui.label(*label);
+            let mut value = transform.scale[axis];
+            if track(ui.add(egui::DragValue::new(&mut value).speed(0.01))) {
+                if inspector_state.snap_scale {
+                    value = InspectorState::snap_value(value, inspector_state.scale_grid);
+                }
+                edit = Some(GroupTransformEdit::SetScaleAxis(axis, value));
+            }
+        }
+    });
+
+    ui.add_space(5.0);
+    ui.label("Rotation (Euler Degrees):");
+    let (roll, pitch, yaw) = quat_to_euler(transform.rotation);
+    let euler = [roll.to_degrees(), pitch.to_degrees(), yaw.to_degrees()];
+    ui.horizontal(|ui| {
+        for (axis, label) in labels.iter().enumerate() {
+            ui.label(*label);
+            let mut value = euler[axis];
+            if track(ui.add(egui::DragValue::new(&mut value).speed(1.0).suffix("°"))) {
+                if inspector_state.snap_rotation {
+                    value = InspectorState::snap_value(value, inspector_state.rotation_grid);
+                }
+                edit = Some(GroupTransformEdit::SetRotationAxis(axis, value));
+            }
+        }
+    });
+
+    result.group_transform = edit;
+}
+
 #[derive(Clone, Copy)]
 enum ComponentType {
     MeshRenderer,
     Camera,
     Light,
+    Collider,
     Water,
     TerrainWater,
     TerrainGenerator,
+    TerrainStreaming,
+    Spline,
     ParticleEmitter,
+    AnimationClip,
+    Animator,
+    LookAt,
+    FootPlacement,
+    RagdollRig,
+    NavAgent,
+    BehaviorAgent,
+    SunLight,
+    Wind,
+    Foliage,
+    Grass,
 }
 
 /// Render a component header with remove button. Returns true if remove was clicked.
@@ -433,6 +829,71 @@ fn render_mesh_renderer_ui(ui: &mut egui::Ui, mesh: &mut MeshRenderer) {
             mesh.material_path = if mat_path.is_empty() { None } else { Some(mat_path) };
         }
     });
+    if let Some(lightmap_path) = &mesh.lightmap_path {
+        let mut clear = false;
+        ui.horizontal(|ui| {
+            ui.label("Baked AO:");
+            ui.weak(lightmap_path.as_str());
+            clear = ui.small_button("✖").on_hover_text("Remove the baked lighting").clicked();
+        });
+        if clear {
+            mesh.lightmap_path = None;
+        }
+    }
+
+    let mut remove = None;
+    for (index, lod) in mesh.lods.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.label(format!("LOD {}:", index + 1));
+            ui.text_edit_singleline(&mut lod.mesh_path);
+            if let Some(screen_size) = &mut lod.screen_size {
+                let mut percent = *screen_size * 100.0;
+                let response = ui.add(
+                    egui::DragValue::new(&mut percent)
+                        .speed(0.5)
+                        .range(0.1..=100.0)
+                        .suffix("% screen"),
+                );
+                if response.changed() {
+                    *screen_size = percent / 100.0;
+                }
+            } else {
+                ui.add(
+                    egui::DragValue::new(&mut lod.distance)
+                        .speed(1.0)
+                        .range(0.0..=10000.0)
+                        .suffix(" m"),
+                );
+            }
+            if ui.small_button("X").clicked() {
+                remove = Some(index);
+            }
+        });
+    }
+    if let Some(index) = remove {
+        mesh.lods.remove(index);
+    }
+    ui.horizontal(|ui| {
+        if ui.small_button("Add LOD").clicked() {
+            // Continue the way the last level switches, at twice the distance or half the size
+            let last = mesh.lods.last();
+            let lod = MeshLod {
+                mesh_path: String::new(),
+                distance: last.map_or(25.0, |lod| lod.distance * 2.0),
+                screen_size: last.and_then(|lod| lod.screen_size).map(|size| size * 0.5),
+            };
+            mesh.lods.push(lod);
+        }
+        if !mesh.lods.is_empty() {
+            ui.label("Crossfade:");
+            ui.add(
+                egui::DragValue::new(&mut mesh.lod_transition)
+                    .speed(0.1)
+                    .range(0.0..=50.0)
+                    .suffix(" m"),
+            );
+        }
+    });
 }
 
 /// Render UI for Camera component
@@ -529,6 +990,111 @@ fn render_light_ui(ui: &mut egui::Ui, light: &mut Light) {
         ui.label("Intensity:");
         ui.add(egui::DragValue::new(&mut light.intensity).speed(0.1).range(0.0..=10.0));
     });
+    ui.checkbox(&mut light.cast_shadows, "Cast Shadows")
+        .on_hover_text("The sun, or without one the first directional light, casts the shadow map");
+}
+
+/// Render UI for Collider component. Mesh shapes start from the entity's
+/// MeshRenderer mesh (`mesh_path`); they and the terrain heightfield are
+/// built when physics is set up.
+fn render_collider_ui(ui: &mut egui::Ui, collider: &mut Collider, mesh_path: Option<&str>) {
+    let current_shape = match &collider.shape {
+        ColliderShape::Box { .. } => "Box",
+        ColliderShape::Sphere { .. } => "Sphere",
+        ColliderShape::Capsule { .. } => "Capsule",
+        ColliderShape::Cylinder { .. } => "Cylinder",
+        ColliderShape::TriMesh { .. } => "Triangle Mesh",
+        ColliderShape::ConvexDecomposition { .. } => "Convex Decomposition",
+        ColliderShape::HeightField => "Terrain Heightfield",
+    };
+    let mesh_path = mesh_path.unwrap_or_default().to_string();
+
+    egui::ComboBox::from_label("Shape")
+        .selected_text(current_shape)
+        .show_ui(ui, |ui| {
+            if ui.selectable_label(matches!(collider.shape, ColliderShape::Box { .. }), "Box").clicked() {
+                collider.shape = ColliderShape::Box { half_extents: Vec3::splat(0.5) };
+            }
+            if ui.selectable_label(matches!(collider.shape, ColliderShape::Sphere { .. }), "Sphere").clicked() {
+                collider.shape = ColliderShape::Sphere { radius: 0.5 };
+            }
+            if ui.selectable_label(matches!(collider.shape, ColliderShape::Capsule { .. }), "Capsule").clicked() {
+                collider.shape = ColliderShape::Capsule { half_height: 0.5, radius: 0.5 };
+            }
+            if ui.selectable_label(matches!(collider.shape, ColliderShape::Cylinder { .. }), "Cylinder").clicked() {
+                collider.shape = ColliderShape::Cylinder { half_height: 0.5, radius: 0.5 };
+            }
+            if ui.selectable_label(matches!(collider.shape, ColliderShape::TriMesh { .. }), "Triangle Mesh")
+                .on_hover_text("Exact but hollow, for static and kinematic bodies")
+                .clicked()
+            {
+                collider.shape = ColliderShape::TriMesh { mesh_path: mesh_path.clone() };
+            }
+            if ui.selectable_label(matches!(collider.shape, ColliderShape::ConvexDecomposition { .. }), "Convex Decomposition")
+                .on_hover_text("Approximate but solid, works on dynamic bodies")
+                .clicked()
+            {
+                collider.shape = ColliderShape::ConvexDecomposition { mesh_path: mesh_path.clone() };
+            }
+            if ui.selectable_label(matches!(collider.shape, ColliderShape::HeightField), "Terrain Heightfield").clicked() {
+                collider.shape = ColliderShape::HeightField;
+            }
+        });
+
+    // Shape-specific properties
+    match &mut collider.shape {
+        ColliderShape::Box { half_extents } => {
+            ui.horizontal(|ui| {
+                ui.label("Half X:");
+                ui.add(egui::DragValue::new(&mut half_extents.x).speed(0.05).range(0.01..=1000.0));
+                ui.label("Y:");
+                ui.add(egui::DragValue::new(&mut half_extents.y).speed(0.05).range(0.01..=1000.0));
+                ui.label("Z:");
+                ui.add(egui::DragValue::new(&mut half_extents.z).speed(0.05).range(0.01..=1000.0));
+            });
+        }
+        ColliderShape::Sphere { radius } => {
+            ui.horizontal(|ui| {
+                ui.label("Radius:");
+                ui.add(egui::DragValue::new(radius).speed(0.05).range(0.01..=1000.0));
+            });
+        }
+        ColliderShape::Capsule { half_height, radius } | ColliderShape::Cylinder { half_height, radius } => {
+            ui.horizontal(|ui| {
+                ui.label("Half Height:");
+                ui.add(egui::DragValue::new(half_height).speed(0.05).range(0.0..=1000.0));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Radius:");
+                ui.add(egui::DragValue::new(radius).speed(0.05).range(0.01..=1000.0));
+            });
+        }
+        ColliderShape::TriMesh { mesh_path } | ColliderShape::ConvexDecomposition { mesh_path } => {
+            ui.horizontal(|ui| {
+                ui.label("Mesh:");
+                ui.text_edit_singleline(mesh_path);
+            });
+        }
+        ColliderShape::HeightField => {
+            ui.weak("Follows the scene's terrain");
+        }
+    }
+
+    // Common properties
+    ui.horizontal(|ui| {
+        ui.label("Friction:");
+        ui.add(egui::DragValue::new(&mut collider.friction).speed(0.01).range(0.0..=2.0));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Restitution:");
+        ui.add(egui::DragValue::new(&mut collider.restitution).speed(0.01).range(0.0..=1.0));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Density:");
+        ui.add(egui::DragValue::new(&mut collider.density).speed(0.05).range(0.01..=100.0));
+    });
+    ui.checkbox(&mut collider.is_sensor, "Sensor")
+        .on_hover_text("Detects overlaps without pushing bodies apart");
 }
 
 /// Render UI for TerrainGenerator component, returns true if changed
@@ -712,6 +1278,587 @@ fn render_water_ui(ui: &mut egui::Ui, water: &mut Water) {
     });
 }
 
+/// Render UI for AnimationClip component (keys are edited in the Timeline panel)
+fn render_animation_clip_ui(ui: &mut egui::Ui, clip: &mut AnimationClip) {
+    ui.horizontal(|ui| {
+        ui.label("Length:");
+        ui.add(egui::DragValue::new(&mut clip.duration).speed(0.05).range(0.1..=600.0).suffix(" s"));
+    });
+    ui.checkbox(&mut clip.looping, "Loop");
+    ui.checkbox(&mut clip.autoplay, "Autoplay");
+    let keys: usize = clip.tracks.iter().map(|t| t.keyframes.len()).sum();
+    ui.weak(format!("{} tracks, {} keys", clip.tracks.len(), keys));
+}
+
+/// Render UI for Animator component. States take their clips from the
+/// entity's AnimationClip, which is authored in the Timeline panel; the
+/// transitions' conditions are edited in the scene file or over MCP.
+fn render_animator_ui(ui: &mut egui::Ui, animator: &mut Animator, clip: Option<&AnimationClip>) {
+    ui.label("Parameters:");
+    for (name, value) in animator.parameters.iter_mut() {
+        ui.horizontal(|ui| {
+            ui.label(name);
+            match value {
+                AnimatorParameter::Float(v) => {
+                    ui.add(egui::DragValue::new(v).speed(0.05));
+                }
+                AnimatorParameter::Bool(v) => {
+                    ui.checkbox(v, "");
+                }
+                AnimatorParameter::Trigger(v) => {
+                    ui.checkbox(v, "trigger");
+                }
+            }
+        });
+    }
+
+    for (index, layer) in animator.layers.iter_mut().enumerate() {
+        ui.add_space(5.0);
+        egui::CollapsingHeader::new(format!("Layer: {}", layer.name))
+            .id_salt(("animator_layer", index))
+            .default_open(true)
+            .show(ui, |ui| {
+                if index > 0 {
+                    ui.horizontal(|ui| {
+                        ui.label("Weight:");
+                        ui.add(egui::Slider::new(&mut layer.weight, 0.0..=1.0));
+                    });
+                }
+                let current = layer.current_state().map(str::to_string);
+                for state in &mut layer.states {
+                    ui.horizontal(|ui| {
+                        let active = current.as_deref() == Some(state.name.as_str());
+                        let default = layer.default_state == state.name;
+                        ui.label(if active { "▶" } else { " " });
+                        ui.text_edit_singleline(&mut state.name);
+                        ui.add(
+                            egui::DragValue::new(&mut state.speed)
+                                .speed(0.05)
+                                .prefix("x"),
+                        );
+                        if ui.selectable_label(default, "default").clicked() {
+                            layer.default_state = state.name.clone();
+                        }
+                    });
+                }
+                for transition in &layer.transitions {
+                    ui.weak(format!(
+                        "{} → {} ({} conditions, {:.2} s)",
+                        transition.from.as_deref().unwrap_or("Any"),
+                        transition.to,
+                        transition.conditions.len(),
+                        transition.blend_time
+                    ));
+                }
+                let add_state = ui.add_enabled(
+                    clip.is_some(),
+                    egui::Button::new("Add State from Animation Clip"),
+                );
+                if let (true, Some(clip)) = (add_state.clicked(), clip) {
+                    let name = format!("State {}", layer.states.len() + 1);
+                    if layer.states.is_empty() {
+                        layer.default_state = name.clone();
+                    }
+                    layer.states.push(AnimatorState::new(&name, clip.clone()));
+                }
+            });
+    }
+    if ui.small_button("Add Layer").clicked() {
+        let name = format!("Layer {}", animator.layers.len() + 1);
+        animator.layers.push(AnimatorLayer::new(&name));
+    }
+}
+
+/// Edit an optional entity reference as an id (0 = none)
+fn entity_id_field(ui: &mut egui::Ui, label: &str, id: &mut Option<EntityId>) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        let mut value = id.map_or(0, |id| id.0);
+        if ui.add(egui::DragValue::new(&mut value)).changed() {
+            *id = (value != 0).then_some(EntityId(value));
+        }
+    });
+}
+
+/// Render UI for LookAt component
+fn render_look_at_ui(ui: &mut egui::Ui, look_at: &mut LookAt) {
+    entity_id_field(ui, "Target Entity:", &mut look_at.target);
+    if look_at.target.is_none() {
+        ui.horizontal(|ui| {
+            ui.label("Point:");
+            ui.add(
+                egui::DragValue::new(&mut look_at.point.x)
+                    .speed(0.1)
+                    .prefix("x "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut look_at.point.y)
+                    .speed(0.1)
+                    .prefix("y "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut look_at.point.z)
+                    .speed(0.1)
+                    .prefix("z "),
+            );
+        });
+    }
+    ui.horizontal(|ui| {
+        ui.label("Max Angle:");
+        ui.add(
+            egui::DragValue::new(&mut look_at.max_angle)
+                .speed(1.0)
+                .range(0.0..=180.0)
+                .suffix("°"),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.label("Weight:");
+        ui.add(egui::Slider::new(&mut look_at.weight, 0.0..=1.0));
+    });
+}
+
+/// Render UI for FootPlacement component. Legs are the ids of their thigh,
+/// shin and foot entities.
+fn render_foot_placement_ui(ui: &mut egui::Ui, placement: &mut FootPlacement) {
+    entity_id_field(ui, "Pelvis:", &mut placement.pelvis);
+    ui.horizontal(|ui| {
+        ui.label("Ground Offset:");
+        ui.add(
+            egui::DragValue::new(&mut placement.ground_offset)
+                .speed(0.01)
+                .suffix(" m"),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.label("Probe Up/Down:");
+        ui.add(
+            egui::DragValue::new(&mut placement.probe_height)
+                .speed(0.01)
+                .range(0.0..=5.0),
+        );
+        ui.add(
+            egui::DragValue::new(&mut placement.probe_depth)
+                .speed(0.01)
+                .range(0.0..=5.0),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.label("Max Pelvis Drop:");
+        ui.add(
+            egui::DragValue::new(&mut placement.max_pelvis_drop)
+                .speed(0.01)
+                .range(0.0..=2.0),
+        );
+    });
+    ui.checkbox(&mut placement.align_feet, "Align Feet to Slope");
+    ui.horizontal(|ui| {
+        ui.label("Weight:");
+        ui.add(egui::Slider::new(&mut placement.weight, 0.0..=1.0));
+    });
+
+    let mut remove = None;
+    for (index, leg) in placement.legs.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.label(format!("Leg {}:", index + 1));
+            for bone in [&mut leg.upper, &mut leg.lower, &mut leg.foot] {
+                ui.add(egui::DragValue::new(&mut bone.0));
+            }
+            if ui.small_button("X").clicked() {
+                remove = Some(index);
+            }
+        });
+    }
+    if let Some(index) = remove {
+        placement.legs.remove(index);
+    }
+    if ui.small_button("Add Leg").clicked() {
+        placement
+            .legs
+            .push(LegIk::new(EntityId(0), EntityId(0), EntityId(0)));
+    }
+}
+
+/// Render UI for RagdollRig component. Bones are entity ids; 0 means none.
+fn render_ragdoll_rig_ui(ui: &mut egui::Ui, rig: &mut RagdollRig) {
+    entity_id_field(ui, "Root Bone:", &mut rig.root_bone);
+    entity_id_field(ui, "Upper Body Bone:", &mut rig.upper_body_bone);
+    ui.horizontal(|ui| {
+        ui.label("Total Mass:");
+        ui.add(
+            egui::DragValue::new(&mut rig.total_mass)
+                .speed(0.5)
+                .range(0.1..=1000.0)
+                .suffix(" kg"),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.label("Thickness:");
+        ui.add(egui::Slider::new(&mut rig.thickness, 0.05..=0.5));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Get Up Blend:");
+        ui.add(
+            egui::DragValue::new(&mut rig.blend_time)
+                .speed(0.05)
+                .range(0.0..=5.0)
+                .suffix(" s"),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.label("Impact Threshold:");
+        ui.add(
+            egui::DragValue::new(&mut rig.impact_threshold)
+                .speed(1.0)
+                .range(0.0..=10000.0),
+        )
+        .on_hover_text("Contact impulse that knocks the character into full ragdoll (0 = never)");
+    });
+    ui.label(format!("Mode: {:?}", rig.mode));
+}
+
+/// Render UI for Spline component. Returns true if its mesh needs rebuilding.
+fn render_spline_ui(ui: &mut egui::Ui, spline: &mut Spline) -> bool {
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        ui.label("Kind:");
+        egui::ComboBox::from_id_salt("spline_kind")
+            .selected_text(format!("{:?}", spline.kind))
+            .show_ui(ui, |ui| {
+                changed |= ui.selectable_value(&mut spline.kind, SplineKind::River, "River").changed();
+                changed |= ui.selectable_value(&mut spline.kind, SplineKind::Road, "Road").changed();
+            });
+    });
+    changed |= ui.checkbox(&mut spline.closed, "Closed Loop").changed();
+
+    ui.horizontal(|ui| {
+        ui.label("Width:");
+        changed |= ui.add(egui::DragValue::new(&mut spline.width).speed(0.1).range(0.5..=100.0)).changed();
+    });
+    if spline.kind == SplineKind::River {
+        ui.horizontal(|ui| {
+            ui.label("Depth:");
+            changed |= ui.add(egui::DragValue::new(&mut spline.depth).speed(0.05).range(0.0..=20.0)).changed();
+        });
+    }
+    ui.horizontal(|ui| {
+        ui.label("Falloff:");
+        changed |= ui.add(egui::DragValue::new(&mut spline.falloff).speed(0.1).range(0.0..=50.0)).changed();
+    });
+
+    ui.checkbox(&mut spline.carve_terrain, "Carve Terrain")
+        .on_hover_text("Applied with Carve Splines in the brush panel");
+    changed |= ui.checkbox(&mut spline.generate_mesh, "Generate Mesh").changed();
+
+    match spline.kind {
+        SplineKind::River => {
+            ui.horizontal(|ui| {
+                ui.label("Flow Speed:");
+                changed |= ui.add(egui::DragValue::new(&mut spline.flow_speed).speed(0.05).range(0.0..=10.0)).changed();
+            });
+        }
+        SplineKind::Road => {
+            ui.horizontal(|ui| {
+                ui.label("Material:");
+                let mut path = spline.material_path.clone().unwrap_or_default();
+                if ui.text_edit_singleline(&mut path).changed() {
+                    spline.material_path = (!path.is_empty()).then_some(path);
+                    changed = true;
+                }
+            });
+        }
+    }
+
+    ui.horizontal(|ui| {
+        ui.label(format!("Points: {}", spline.points.len()));
+        if ui.small_button("Clear").clicked() {
+            spline.points.clear();
+            changed = true;
+        }
+    });
+
+    changed
+}
+
+/// Render UI for TerrainStreaming component
+fn render_terrain_streaming_ui(ui: &mut egui::Ui, streaming: &mut TerrainStreaming) {
+    ui.horizontal(|ui| {
+        ui.label("Directory:");
+        ui.text_edit_singleline(&mut streaming.directory);
+    });
+    ui.horizontal(|ui| {
+        ui.label("Tile Resolution:");
+        ui.add(egui::DragValue::new(&mut streaming.tile_resolution).range(2..=1025));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Tile Size:");
+        ui.add(
+            egui::DragValue::new(&mut streaming.tile_size)
+                .speed(1.0)
+                .range(1.0..=4096.0),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.label("Load Radius:");
+        ui.add(
+            egui::DragValue::new(&mut streaming.load_radius)
+                .speed(1.0)
+                .range(0.0..=10000.0),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.label("Tile Budget:");
+        ui.add(egui::DragValue::new(&mut streaming.max_loaded_tiles).range(1..=1024))
+            .on_hover_text("Most tiles kept in memory; the farthest unload first");
+    });
+    let mut material = streaming.material_path.clone().unwrap_or_default();
+    ui.horizontal(|ui| {
+        ui.label("Material:");
+        if ui.text_edit_singleline(&mut material).changed() {
+            streaming.material_path = (!material.is_empty()).then(|| material.clone());
+        }
+    });
+}
+
+/// Render UI for SunLight component
+fn render_sun_light_ui(ui: &mut egui::Ui, sun: &mut SunLight) {
+    ui.horizontal(|ui| {
+        ui.label("Time of Day:");
+        ui.add(egui::Slider::new(&mut sun.time_of_day, 0.0..=24.0).suffix(" h"));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Day Length:");
+        ui.add(
+            egui::DragValue::new(&mut sun.day_length)
+                .speed(1.0)
+                .range(0.0..=86400.0)
+                .suffix(" s"),
+        );
+    });
+    if sun.day_length <= 0.0 {
+        ui.label("Clock paused (day length 0)");
+    }
+    ui.horizontal(|ui| {
+        ui.label("Latitude:");
+        ui.add(egui::Slider::new(&mut sun.latitude, -90.0..=90.0).suffix("°"));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Turbidity:");
+        ui.add(egui::Slider::new(&mut sun.turbidity, 1.7..=10.0));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Intensity:");
+        ui.add(egui::Slider::new(&mut sun.intensity, 0.0..=5.0));
+    });
+}
+
+/// Render UI for Wind component
+fn render_wind_ui(ui: &mut egui::Ui, wind: &mut Wind) {
+    let mut heading = wind.direction[1].atan2(wind.direction[0]).to_degrees();
+    ui.horizontal(|ui| {
+        ui.label("Direction:");
+        if ui.add(egui::Slider::new(&mut heading, -180.0..=180.0).suffix("°")).changed() {
+            let radians = heading.to_radians();
+            wind.direction = [radians.cos(), radians.sin()];
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Strength:");
+        ui.add(egui::Slider::new(&mut wind.strength, 0.0..=10.0));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Gust Strength:");
+        ui.add(egui::Slider::new(&mut wind.gust_strength, 0.0..=10.0));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Gust Frequency:");
+        ui.add(egui::Slider::new(&mut wind.gust_frequency, 0.0..=2.0).suffix(" Hz"));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Gust Size:");
+        ui.add(
+            egui::DragValue::new(&mut wind.gust_scale)
+                .speed(0.5)
+                .range(1.0..=500.0)
+                .suffix(" m"),
+        );
+    });
+}
+
+/// Render UI for Grass component
+fn render_grass_ui(ui: &mut egui::Ui, grass: &mut Grass) {
+    let painted = grass.density.iter().filter(|&&d| d > 0).count();
+    ui.label(format!(
+        "{}x{} density map over {:.0} m ({} cells painted)",
+        grass.resolution, grass.resolution, grass.size, painted
+    ));
+    ui.horizontal(|ui| {
+        ui.label("Blades per m²:");
+        ui.add(egui::Slider::new(&mut grass.blades_per_m2, 0.5..=64.0));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Height:");
+        ui.add(egui::Slider::new(&mut grass.blade_height, 0.05..=2.0).suffix(" m"));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Width:");
+        ui.add(egui::Slider::new(&mut grass.blade_width, 0.01..=0.3).suffix(" m"));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Base Color:");
+        ui.color_edit_button_rgb(&mut grass.base_color);
+        ui.label("Tip:");
+        ui.color_edit_button_rgb(&mut grass.tip_color);
+    });
+    ui.horizontal(|ui| {
+        ui.label("Stiffness:");
+        ui.add(egui::Slider::new(&mut grass.stiffness, 0.05..=5.0));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Draw Distance:");
+        ui.add(
+            egui::DragValue::new(&mut grass.draw_distance)
+                .speed(1.0)
+                .range(1.0..=500.0),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.label("Fade Width:");
+        ui.add(
+            egui::DragValue::new(&mut grass.fade_width)
+                .speed(0.5)
+                .range(0.0..=200.0),
+        );
+    });
+}
+
+/// Render UI for Foliage component
+fn render_foliage_ui(ui: &mut egui::Ui, foliage: &mut Foliage) {
+    ui.label(format!("{} ({} instances)", foliage.vegetation_type, foliage.instance_count()));
+    ui.checkbox(&mut foliage.cast_shadows, "Cast Shadows");
+    ui.horizontal(|ui| {
+        ui.label("Tint:");
+        ui.color_edit_button_rgb(&mut foliage.color_tint);
+    });
+    ui.horizontal(|ui| {
+        ui.label("Stiffness:");
+        ui.add(egui::Slider::new(&mut foliage.stiffness, 0.1..=5.0));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Draw Distance:");
+        ui.add(
+            egui::DragValue::new(&mut foliage.draw_distance)
+                .speed(1.0)
+                .range(1.0..=2000.0),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.label("LOD Distance:");
+        ui.add(
+            egui::DragValue::new(&mut foliage.lod_distance)
+                .speed(1.0)
+                .range(0.0..=2000.0),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.label("Impostor Distance:");
+        ui.add(
+            egui::DragValue::new(&mut foliage.impostor_distance)
+                .speed(1.0)
+                .range(0.0..=2000.0),
+        );
+    });
+}
+
+/// Render UI for NavAgent component
+fn render_nav_agent_ui(ui: &mut egui::Ui, agent: &mut NavAgent) {
+    ui.horizontal(|ui| {
+        ui.label("Speed:");
+        ui.add(
+            egui::DragValue::new(&mut agent.speed)
+                .speed(0.1)
+                .range(0.0..=50.0)
+                .suffix(" m/s"),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.label("Acceleration:");
+        ui.add(
+            egui::DragValue::new(&mut agent.acceleration)
+                .speed(0.1)
+                .range(0.1..=100.0),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.label("Turn Speed:");
+        ui.add(
+            egui::DragValue::new(&mut agent.angular_speed)
+                .speed(5.0)
+                .range(0.0..=1440.0)
+                .suffix("°/s"),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.label("Radius:");
+        ui.add(egui::Slider::new(&mut agent.radius, 0.1..=3.0));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Stopping Distance:");
+        ui.add(
+            egui::DragValue::new(&mut agent.stopping_distance)
+                .speed(0.05)
+                .range(0.0..=10.0),
+        );
+    });
+    ui.checkbox(&mut agent.avoidance, "Avoid Other Agents");
+    if agent.avoidance {
+        ui.horizontal(|ui| {
+            ui.label("Look Ahead:");
+            ui.add(
+                egui::DragValue::new(&mut agent.time_horizon)
+                    .speed(0.1)
+                    .range(0.1..=10.0)
+                    .suffix(" s"),
+            )
+            .on_hover_text("How far ahead agents look for collisions with each other");
+        });
+    }
+    match agent.destination {
+        Some(destination) => ui.label(format!(
+            "Walking to ({:.1}, {:.1}, {:.1})",
+            destination.x, destination.y, destination.z
+        )),
+        None if agent.arrived => ui.label("Arrived"),
+        None => ui.label("Idle"),
+    };
+}
+
+/// Render UI for BehaviorAgent component. While playing, shows the branch
+/// of the tree that ran last tick, the leaf with its status.
+fn render_behavior_agent_ui(ui: &mut egui::Ui, agent: &mut BehaviorAgent) {
+    ui.checkbox(&mut agent.enabled, "Enabled");
+    ui.horizontal(|ui| {
+        ui.label("Tree:");
+        ui.text_edit_singleline(&mut agent.tree)
+            .on_hover_text("Path of the behavior tree's .ron file");
+    });
+    if agent.active_branch.is_empty() {
+        return;
+    }
+    ui.label("Active branch:");
+    for (depth, label) in agent.active_branch.iter().enumerate() {
+        ui.horizontal(|ui| {
+            ui.add_space(depth as f32 * 12.0);
+            if depth + 1 == agent.active_branch.len() {
+                ui.strong(label);
+            } else {
+                ui.label(label);
+            }
+        });
+    }
+}
+
 /// Render UI for ParticleEmitter component
 fn render_particle_emitter_ui(ui: &mut egui::Ui, particle: &mut ParticleEmitter) {
     ui.checkbox(&mut particle.enabled, "Enabled");
@@ -0,0 +1,111 @@
+// Lighting panel - ambient occlusion bake for static geometry, and the
+// exposure, tone mapping and bloom applied to the HDR scene
+
+use engine_assets::AoBakeSettings;
+use engine_render::{PostProcessSettings, ToneMapper};
+
+#[derive(Default)]
+pub struct LightingState {
+    pub ao_settings: AoBakeSettings,
+    pub post_process: PostProcessSettings,
+}
+
+/// Requests from the lighting panel
+#[derive(Default)]
+pub struct LightingAction {
+    /// Bake ambient occlusion for the static meshes
+    pub bake: bool,
+    /// Drop the baked lighting from every mesh
+    pub clear: bool,
+}
+
+pub fn render_lighting_panel(ui: &mut egui::Ui, state: &mut LightingState, baked_meshes: usize) -> LightingAction {
+    let mut action = LightingAction::default();
+
+    ui.heading("Ambient Occlusion");
+    let settings = &mut state.ao_settings;
+    egui::Grid::new("ao_settings").num_columns(2).show(ui, |ui| {
+        ui.label("Samples");
+        ui.add(egui::DragValue::new(&mut settings.samples).range(4..=1024))
+            .on_hover_text("Rays per vertex. More rays give smoother shading but bake slower.");
+        ui.end_row();
+
+        ui.label("Max Distance");
+        ui.add(egui::DragValue::new(&mut settings.max_distance).range(0.1..=50.0).speed(0.05).suffix(" m"))
+            .on_hover_text("Geometry further away than this doesn't darken");
+        ui.end_row();
+
+        ui.label("Bias");
+        ui.add(egui::DragValue::new(&mut settings.bias).range(0.0..=0.5).speed(0.001))
+            .on_hover_text("Ray start offset from the surface, raise it if surfaces shade themselves");
+        ui.end_row();
+    });
+
+    ui.add_space(4.0);
+    ui.horizontal(|ui| {
+        if ui.button("Bake").on_hover_text("Bake occlusion for all static meshes").clicked() {
+            action.bake = true;
+        }
+        if ui.add_enabled(baked_meshes > 0, egui::Button::new("Clear")).clicked() {
+            action.clear = true;
+        }
+    });
+
+    ui.separator();
+    if baked_meshes > 0 {
+        ui.label(format!("Baked meshes: {}", baked_meshes));
+    } else {
+        ui.weak("Not baked");
+    }
+    ui.weak("Only static meshes are baked: moving rigid bodies and characters keep dynamic lighting.");
+
+    ui.separator();
+    render_post_process_settings(ui, &mut state.post_process);
+
+    action
+}
+
+fn render_post_process_settings(ui: &mut egui::Ui, settings: &mut PostProcessSettings) {
+    ui.heading("Post Processing");
+    egui::Grid::new("post_process_settings").num_columns(2).show(ui, |ui| {
+        ui.label("Exposure");
+        ui.add(egui::DragValue::new(&mut settings.exposure).range(0.01..=16.0).speed(0.01))
+            .on_hover_text("Scales scene brightness before tone mapping");
+        ui.end_row();
+
+        ui.label("Tone Mapper");
+        egui::ComboBox::from_id_salt("tone_mapper")
+            .selected_text(settings.tone_mapper.name())
+            .show_ui(ui, |ui| {
+                for tone_mapper in ToneMapper::ALL {
+                    ui.selectable_value(&mut settings.tone_mapper, tone_mapper, tone_mapper.name());
+                }
+            });
+        ui.end_row();
+
+        ui.label("Bloom");
+        ui.checkbox(&mut settings.enable_bloom, "Enabled");
+        ui.end_row();
+
+        if settings.enable_bloom {
+            ui.label("Threshold");
+            ui.add(egui::DragValue::new(&mut settings.bloom_threshold).range(0.0..=16.0).speed(0.01))
+                .on_hover_text("Brightness above which pixels bloom");
+            ui.end_row();
+
+            ui.label("Knee");
+            ui.add(egui::DragValue::new(&mut settings.bloom_knee).range(0.0..=4.0).speed(0.01))
+                .on_hover_text("Fades bloom in over this much brightness below the threshold");
+            ui.end_row();
+
+            ui.label("Intensity");
+            ui.add(egui::DragValue::new(&mut settings.bloom_intensity).range(0.0..=4.0).speed(0.01));
+            ui.end_row();
+
+            ui.label("Radius");
+            ui.add(egui::DragValue::new(&mut settings.bloom_radius).range(0.25..=4.0).speed(0.01))
+                .on_hover_text("Spread of the blur, in texels of each bloom mip");
+            ui.end_row();
+        }
+    });
+}
@@ -1,17 +1,47 @@
 // Editor UI module
 
+pub mod asset_browser;
+pub mod build_export;
 pub mod console;
+pub mod dock;
+pub mod game_view;
+pub mod heightmap_dialog;
 pub mod hierarchy;
 pub mod inspector;
+pub mod lighting;
+pub mod navigation;
+pub mod profiler;
+pub mod scatter;
+pub mod timeline;
 pub mod viewport;
 
 use std::collections::HashSet;
 use egui::Context;
+use egui_dock::{DockArea, DockState};
 use engine_scene::{entity::EntityId, scene::Scene};
+use engine_assets::{AssetManager, NavMesh, TerrainLayer};
+use engine_core::frame_pacing::{FramePacing, PresentMode};
+
+use crate::play_mode::{PlayRequest, PlayState};
+use crate::profiler::Profiler;
+use crate::scatter::ScatterSettings;
+use crate::entity_icons::IconOverlay;
+use crate::capture::{CaptureRequest, CaptureSettings, MAX_SUPERSAMPLE};
+use crate::console_commands::{self, ConsoleCommand};
+use crate::measure::{MeasureOverlay, MeasureState};
+use crate::spline_edit::SplineOverlay;
+use crate::water_edit::WaterOverlay;
 
 // Re-export types for use in main.rs
 pub use inspector::{InspectorResult, InspectorState};
 pub use hierarchy::{HierarchyAction, HierarchyState};
+pub use asset_browser::AssetBrowserState;
+pub use dock::EditorTab;
+pub use game_view::GameViewState;
+pub use heightmap_dialog::HeightmapRequest;
+pub use lighting::{LightingAction, LightingState};
+pub use navigation::{NavigationAction, NavigationState};
+pub use timeline::{TimelineAction, TimelineState};
 
 /// Brush action to apply in the scene
 #[derive(Default)]
@@ -20,6 +50,12 @@ pub struct BrushAction {
     pub place_at: Option<glam::Vec3>,
     /// If Some, erase foliage near this world position
     pub erase_at: Option<glam::Vec3>,
+    /// Run the foliage scatter rules
+    pub scatter: bool,
+    /// Erode the whole terrain once
+    pub erode_terrain: bool,
+    /// Carve every spline into the terrain
+    pub apply_splines: bool,
 }
 
 /// Combined result from all editor UI panels
@@ -28,11 +64,20 @@ pub struct EditorResult {
     pub inspector: InspectorResult,
     pub hierarchy: HierarchyAction,
     pub brush: BrushAction,
+    pub navigation: NavigationAction,
+    pub lighting: LightingAction,
+    pub timeline: TimelineAction,
     pub scene_modified: bool,
     pub undo_requested: bool,
     pub redo_requested: bool,
     pub scene_changed: bool, // True when scene loaded or new scene created
     pub open_recent_file: Option<String>, // Path to recent file to open
+    pub spawn_model: Option<(String, Option<(f32, f32)>)>, // Model path and viewport drop position (pixels)
+    pub play_request: Option<PlayRequest>, // Play/Pause/Stop pressed in the toolbar
+    pub capture_request: Option<CaptureRequest>, // Screenshot or sequence from the File menu
+    pub heightmap_request: Option<HeightmapRequest>, // Heightmap import/export from the File menu
+    pub scene_saved: Option<String>, // Path the scene was just saved to
+    pub console_command: Option<ConsoleCommand>, // Command entered in the console
 }
 
 /// Brush tool mode
@@ -41,10 +86,20 @@ pub enum BrushMode {
     Select,         // Default selection mode
     Place,          // Place vegetation
     Erase,          // Remove vegetation
+    GrassPaint,     // Paint grass density
+    GrassErase,     // Clear grass density
     TerrainRaise,   // Raise terrain height
     TerrainLower,   // Lower terrain height
     TerrainSmooth,  // Smooth terrain
     TerrainFlatten, // Flatten terrain to uniform height
+    TerrainErode,   // Hydraulic and thermal erosion under the brush
+    TerrainHole,    // Cut holes for cave entrances
+    TerrainFill,    // Fill holes back in
+    TerrainPaint,   // Paint terrain texture layers
+    WaterLevel,     // Drag the water level handle
+    WaterFlow,      // Paint water flow direction
+    WaterExclude,   // Add/remove water exclusion zones
+    SplineEdit,     // Add and move the selected spline's points
 }
 
 impl BrushMode {
@@ -55,18 +110,31 @@ impl BrushMode {
             BrushMode::TerrainLower => Some(1),
             BrushMode::TerrainSmooth => Some(2),
             BrushMode::TerrainFlatten => Some(3),
+            BrushMode::TerrainErode => Some(4),
+            BrushMode::TerrainHole => Some(5),
+            BrushMode::TerrainFill => Some(6),
             _ => None,
         }
     }
 
     /// Check if this is a terrain sculpting mode
     pub fn is_terrain_mode(&self) -> bool {
-        matches!(self, BrushMode::TerrainRaise | BrushMode::TerrainLower | BrushMode::TerrainSmooth | BrushMode::TerrainFlatten)
+        matches!(self, BrushMode::TerrainRaise | BrushMode::TerrainLower | BrushMode::TerrainSmooth | BrushMode::TerrainFlatten | BrushMode::TerrainErode | BrushMode::TerrainHole | BrushMode::TerrainFill | BrushMode::TerrainPaint)
+    }
+
+    /// Check if this is a water editing mode
+    pub fn is_water_mode(&self) -> bool {
+        matches!(self, BrushMode::WaterLevel | BrushMode::WaterFlow | BrushMode::WaterExclude)
     }
 
     /// Check if this is a vegetation mode
     pub fn is_vegetation_mode(&self) -> bool {
-        matches!(self, BrushMode::Place | BrushMode::Erase)
+        matches!(self, BrushMode::Place | BrushMode::Erase | BrushMode::GrassPaint | BrushMode::GrassErase)
+    }
+
+    /// Check if this is a grass painting mode
+    pub fn is_grass_mode(&self) -> bool {
+        matches!(self, BrushMode::GrassPaint | BrushMode::GrassErase)
     }
 }
 
@@ -112,6 +180,16 @@ impl VegetationType {
             VegetationType::Shrub => "vegetation_shrub",
         }
     }
+
+    /// Default resistance to bending in the wind
+    pub fn stiffness(&self) -> f32 {
+        match self {
+            VegetationType::PineTree => 1.2,
+            VegetationType::OakTree => 1.0,
+            VegetationType::Bush => 0.5,
+            VegetationType::Shrub => 0.6,
+        }
+    }
 }
 
 /// Brush tool state for painting vegetation and sculpting terrain
@@ -124,9 +202,18 @@ pub struct BrushTool {
     pub scale_min: f32,
     pub scale_max: f32,
     pub random_rotation: bool,
+    pub grass_opacity: f32,      // Grass density change per stroke step (0-1)
     // Terrain sculpting settings
     pub terrain_strength: f32,   // How fast terrain is modified
     pub terrain_hardness: f32,   // Edge falloff (0=soft, 1=hard)
+    // Terrain texture painting settings
+    pub paint_layer: usize,      // Splatmap layer to paint
+    pub paint_opacity: f32,      // Blend amount per stroke (0-1)
+    pub paint_falloff: f32,      // Edge falloff (0=hard, 1=soft)
+    // Water editing settings
+    pub water_flow_speed: f32,   // Speed of painted flow vectors
+    // Terrain erosion settings
+    pub erosion_droplets: usize, // Droplets for a whole-terrain erosion pass
 }
 
 impl Default for BrushTool {
@@ -139,8 +226,14 @@ impl Default for BrushTool {
             scale_min: 0.5,  // More size variation
             scale_max: 1.5,
             random_rotation: true,
+            grass_opacity: 0.2,
             terrain_strength: 1.0,
             terrain_hardness: 0.5,
+            paint_layer: 1,
+            paint_opacity: 0.3,
+            paint_falloff: 0.5,
+            water_flow_speed: 1.0,
+            erosion_droplets: 50_000,
         }
     }
 }
@@ -187,16 +280,75 @@ impl PerformanceMetrics {
     }
 }
 
+/// Renders docked tabs with access to the editor state
+struct EditorTabViewer<'a> {
+    editor: &'a mut EditorUi,
+    scene: &'a mut Scene,
+    result: &'a mut EditorResult,
+}
+
+impl egui_dock::TabViewer for EditorTabViewer<'_> {
+    type Tab = EditorTab;
+
+    fn title(&mut self, tab: &mut EditorTab) -> egui::WidgetText {
+        tab.title().into()
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, tab: &mut EditorTab) {
+        self.editor.render_tab(ui, *tab, self.scene, self.result);
+    }
+
+    // Leave the scene views unfilled so the 3D render shows through
+    fn clear_background(&self, tab: &EditorTab) -> bool {
+        !matches!(tab, EditorTab::Viewport | EditorTab::Game)
+    }
+}
+
 /// UI state for the editor
 pub struct EditorUi {
     pub selected_entity: Option<EntityId>,
+    // All selected entities (always includes selected_entity, the primary selection)
+    pub selected_entities: HashSet<EntityId>,
+    // Box selection drag rectangle in window pixels (drawn over the viewport)
+    pub box_select_rect: Option<(glam::Vec2, glam::Vec2)>,
+    // Water tool handles and guides in window pixels (drawn over the viewport)
+    pub water_overlay: Option<WaterOverlay>,
+    pub spline_overlay: Option<SplineOverlay>,
+    // Baked navmesh polygons in window pixels (drawn in the viewport tab)
+    pub navmesh_overlay: Vec<Vec<glam::Vec2>>,
+    // Icons for lights, cameras, audio and particles in window pixels (drawn in the viewport tab)
+    pub icon_overlay: IconOverlay,
+    // Measure tool points and the measurement line in window pixels (drawn in the viewport tab)
+    pub measure: MeasureState,
+    pub measure_overlay: Option<MeasureOverlay>,
+    // Screenshot options and the progress of a running capture (set by the app each frame)
+    pub capture_settings: CaptureSettings,
+    pub capture_status: Option<String>,
+    // Assets loading in the background (set by the app each frame)
+    pub loading_assets: usize,
+    // Renderer counters from the last frame (set by the app each frame)
+    pub render_stats: engine_render::RenderStats,
     pub show_hierarchy: bool,
     pub show_inspector: bool,
     pub show_console: bool,
+    pub show_asset_browser: bool,
     pub show_brush_panel: bool,
     pub show_shortcuts_help: bool,
     pub show_statistics: bool,
+    pub show_game_view: bool,
+    pub show_navigation: bool,
+    pub show_lighting: bool,
+    pub show_timeline: bool,
+    pub show_grid: bool,
+    pub show_entity_icons: bool,
+    pub show_render_stats: bool,
+    pub show_preferences: bool,
+    pub show_build_dialog: bool,
+    pub build_dialog: build_export::BuildDialogState,
+    pub show_heightmap_dialog: bool,
+    pub heightmap_dialog: heightmap_dialog::HeightmapDialogState,
     pub console_messages: Vec<ConsoleMessage>,
+    pub console_state: console::ConsoleState,
     pub show_save_dialog: bool,
     pub show_save_as_dialog: bool,
     pub show_load_dialog: bool,
@@ -214,10 +366,20 @@ pub struct EditorUi {
     pub hierarchy_state: HierarchyState,
     // Brush tool state
     pub brush_tool: BrushTool,
+    /// Rules for the foliage scatter tool
+    pub scatter_settings: ScatterSettings,
+    // Navmesh agent settings and the baked navmesh
+    pub navigation: NavigationState,
+    // Ambient occlusion bake settings
+    pub lighting: LightingState,
+    // Keyframe timeline playhead and selection
+    pub timeline: TimelineState,
     // Inspector state (snapping settings)
     pub inspector_state: InspectorState,
     // Performance metrics
     pub performance: PerformanceMetrics,
+    // Per-system CPU/GPU timings (recorded by the app each frame)
+    pub profiler: Profiler,
     // Camera info for status bar
     pub camera_position: glam::Vec3,
     pub camera_distance: f32,
@@ -231,6 +393,33 @@ pub struct EditorUi {
     // Go To dialog state
     pub show_goto_dialog: bool,
     pub goto_search: String,
+    // Asset browser state
+    pub asset_browser_state: AssetBrowserState,
+    // Play-in-editor state (set by the app each frame)
+    pub play_state: PlayState,
+    // View the game through the scene's active Camera component while playing
+    pub use_game_camera: bool,
+    // Viewport camera speed multiplier (pan/zoom and player movement)
+    pub camera_speed: f32,
+    // MSAA sample count (1 = off), takes effect on restart
+    pub msaa_samples: u32,
+    // MSAA, FXAA or TAA; switching MSAA on or off takes effect on restart
+    pub anti_aliasing: engine_render::AntiAliasingMode,
+    // Resolution of the planar water reflections (Off skips the pass)
+    pub water_reflections: engine_render::WaterReflectionQuality,
+    // Present mode, frame rate cap and fixed/variable simulation updates
+    pub frame_pacing: FramePacing,
+    // Docked panel layout (tabs follow the show_* flags)
+    pub dock_state: DockState<EditorTab>,
+    // Pointer is over the viewport tab (set each frame; viewport gets mouse input)
+    pub viewport_hovered: bool,
+    pub game_view_state: GameViewState,
+    // Pixel rect the game camera renders into (set each frame while the Game tab shows it)
+    pub game_view_viewport: Option<[f32; 4]>,
+    // Game tab is the active scene view (focused, or the Viewport tab is hidden)
+    game_view_live: bool,
+    // Viewport tab was drawn last frame
+    viewport_tab_visible: bool,
 }
 
 #[derive(Clone)]
@@ -250,13 +439,39 @@ impl EditorUi {
     pub fn new() -> Self {
         Self {
             selected_entity: None,
+            selected_entities: HashSet::new(),
+            box_select_rect: None,
+            water_overlay: None,
+            spline_overlay: None,
+            navmesh_overlay: Vec::new(),
+            icon_overlay: IconOverlay::default(),
+            measure: MeasureState::default(),
+            measure_overlay: None,
+            capture_settings: CaptureSettings::default(),
+            capture_status: None,
+            loading_assets: 0,
+            render_stats: engine_render::RenderStats::default(),
             show_hierarchy: true,
             show_inspector: true,
             show_console: true,
+            show_asset_browser: false,
             show_brush_panel: false,
             show_shortcuts_help: false,
             show_statistics: false,
+            show_game_view: true,
+            show_navigation: false,
+            show_lighting: false,
+            show_timeline: false,
+            show_grid: true,
+            show_entity_icons: true,
+            show_render_stats: false,
+            show_preferences: false,
+            show_build_dialog: false,
+            build_dialog: build_export::BuildDialogState::default(),
+            show_heightmap_dialog: false,
+            heightmap_dialog: heightmap_dialog::HeightmapDialogState::default(),
             console_messages: Vec::new(),
+            console_state: console::ConsoleState::default(),
             show_save_dialog: false,
             show_save_as_dialog: false,
             show_load_dialog: false,
@@ -272,8 +487,13 @@ impl EditorUi {
             exit_requested: false,
             hierarchy_state: HierarchyState::default(),
             brush_tool: BrushTool::default(),
+            scatter_settings: ScatterSettings::default(),
+            navigation: NavigationState::default(),
+            lighting: LightingState::default(),
+            timeline: TimelineState::default(),
             inspector_state: InspectorState::default(),
             performance: PerformanceMetrics::new(),
+            profiler: Profiler::new(),
             camera_position: glam::Vec3::ZERO,
             camera_distance: 15.0,
             undo_count: 0,
@@ -282,9 +502,100 @@ impl EditorUi {
             locked_entities: HashSet::new(),
             show_goto_dialog: false,
             goto_search: String::new(),
+            asset_browser_state: AssetBrowserState::new("assets"),
+            play_state: PlayState::Editing,
+            use_game_camera: false,
+            camera_speed: 1.0,
+            msaa_samples: engine_render::MSAA_SAMPLE_COUNT,
+            anti_aliasing: engine_render::AntiAliasingMode::default(),
+            water_reflections: engine_render::WaterReflectionQuality::default(),
+            frame_pacing: FramePacing::default(),
+            dock_state: dock::default_dock_state(),
+            viewport_hovered: false,
+            game_view_state: GameViewState::default(),
+            game_view_viewport: None,
+            game_view_live: false,
+            viewport_tab_visible: true,
+        }
+    }
+
+    /// Keep the selection set consistent with the primary selection.
+    /// Assigning `selected_entity` directly acts as a single selection.
+    pub fn sync_selection(&mut self, scene: &Scene) {
+        self.selected_entities
+            .retain(|id| scene.get_entity(*id).is_some());
+        match self.selected_entity {
+            Some(id) if scene.get_entity(id).is_none() => {
+                self.selected_entity = self.selected_entities.iter().next().copied();
+            }
+            Some(id) if !self.selected_entities.contains(&id) => {
+                self.selected_entities.clear();
+                self.selected_entities.insert(id);
+            }
+            None => self.selected_entities.clear(),
+            _ => {}
+        }
+    }
+
+    /// Select a single entity, replacing the current selection
+    pub fn select_only(&mut self, entity_id: EntityId) {
+        self.selected_entities.clear();
+        self.selected_entities.insert(entity_id);
+        self.selected_entity = Some(entity_id);
+    }
+
+    /// Add or remove an entity from the selection (Ctrl+click)
+    pub fn toggle_selection(&mut self, entity_id: EntityId) {
+        if self.selected_entities.remove(&entity_id) {
+            if self.selected_entity == Some(entity_id) {
+                self.selected_entity = self.selected_entities.iter().next().copied();
+            }
+        } else {
+            self.selected_entities.insert(entity_id);
+            self.selected_entity = Some(entity_id);
         }
     }
 
+    /// Add entities to the selection; the last one becomes the primary selection
+    pub fn add_to_selection(&mut self, entity_ids: &[EntityId]) {
+        for &entity_id in entity_ids {
+            self.selected_entities.insert(entity_id);
+            self.selected_entity = Some(entity_id);
+        }
+    }
+
+    /// Replace the selection with the given entities
+    pub fn set_selection(&mut self, entity_ids: &[EntityId]) {
+        self.clear_selection();
+        self.add_to_selection(entity_ids);
+    }
+
+    /// Deselect everything
+    pub fn clear_selection(&mut self) {
+        self.selected_entities.clear();
+        self.selected_entity = None;
+    }
+
+    /// Check if an entity is part of the selection
+    pub fn is_selected(&self, entity_id: EntityId) -> bool {
+        self.selected_entity == Some(entity_id) || self.selected_entities.contains(&entity_id)
+    }
+
+    /// Get number of selected entities
+    pub fn selection_count(&self) -> usize {
+        self.selected_entities
+            .len()
+            .max(self.selected_entity.is_some() as usize)
+    }
+
+    /// Selected entities that group operations should act on
+    /// (descendants of other selected entities are skipped)
+    pub fn selection_roots(&self, scene: &Scene) -> Vec<EntityId> {
+        let mut selected = self.selected_entities.clone();
+        selected.extend(self.selected_entity);
+        crate::selection::selection_roots(scene, &selected)
+    }
+
     /// Toggle visibility of an entity
     pub fn toggle_entity_visibility(&mut self, entity_id: EntityId) {
         if self.hidden_entities.contains(&entity_id) {
@@ -353,6 +664,120 @@ impl EditorUi {
         self.scene_modified = true;
     }
 
+    /// Write the baked navmesh next to a saved scene (removing a stale one
+    /// when nothing is baked)
+    fn save_navmesh(&mut self, scene_path: &str) {
+        let path = NavMesh::path_for_scene(scene_path);
+        let saved = match &self.navigation.navmesh {
+            Some(navmesh) => navmesh.save(&path),
+            None if path.exists() => std::fs::remove_file(&path).map_err(Into::into),
+            None => Ok(()),
+        };
+        if let Err(e) = saved {
+            self.log_error(format!("Failed to save navmesh: {}", e));
+        }
+    }
+
+    /// Load the navmesh saved next to a scene, if it has one
+    pub fn load_navmesh(&mut self, asset_manager: &AssetManager, scene_path: &str) {
+        self.navigation.navmesh = None;
+        let path = NavMesh::path_for_scene(scene_path);
+        if !asset_manager.file_exists(&path) {
+            return;
+        }
+        match asset_manager.read_file(&path).and_then(|json| NavMesh::from_bytes(&json)) {
+            Ok(navmesh) => {
+                self.navigation.agent = navmesh.agent;
+                self.navigation.navmesh = Some(navmesh);
+            }
+            Err(e) => self.log_warning(format!("Failed to load navmesh: {}", e)),
+        }
+    }
+
+    /// Draw the baked navmesh polygons into the viewport tab
+    fn paint_navmesh_overlay(&self, ui: &egui::Ui) {
+        let scale = ui.ctx().pixels_per_point();
+        let fill = egui::Color32::from_rgba_unmultiplied(60, 200, 120, 50);
+        let stroke = egui::Stroke::new(1.0, egui::Color32::from_rgba_unmultiplied(60, 200, 120, 160));
+        for polygon in &self.navmesh_overlay {
+            let points = polygon.iter().map(|p| egui::pos2(p.x / scale, p.y / scale)).collect();
+            ui.painter().add(egui::Shape::convex_polygon(points, fill, stroke));
+        }
+    }
+
+    /// Draw the entity icons and the selected entities' wireframes into the viewport tab
+    fn paint_entity_icons(&self, ui: &egui::Ui) {
+        let scale = ui.ctx().pixels_per_point();
+        let to_pos = |p: &glam::Vec2| egui::pos2(p.x / scale, p.y / scale);
+        let color = |kind: crate::entity_icons::IconKind| {
+            let [r, g, b] = kind.color();
+            egui::Color32::from_rgb(r, g, b)
+        };
+        for (line, kind) in &self.icon_overlay.wires {
+            let points = line.iter().map(to_pos).collect();
+            ui.painter().add(egui::Shape::line(points, egui::Stroke::new(1.5, color(*kind))));
+        }
+        for icon in &self.icon_overlay.icons {
+            let pos = to_pos(&icon.position);
+            let ring = if icon.selected { egui::Color32::from_rgb(255, 170, 50) } else { color(icon.kind) };
+            ui.painter().circle(pos, 11.0, egui::Color32::from_black_alpha(160), egui::Stroke::new(1.5, ring));
+            ui.painter().text(pos, egui::Align2::CENTER_CENTER, icon.kind.glyph(), egui::FontId::proportional(13.0), egui::Color32::WHITE);
+        }
+    }
+
+    /// Draw the measure tool's line and label into the viewport tab
+    fn paint_measurement(&self, ui: &egui::Ui) {
+        let Some(overlay) = &self.measure_overlay else {
+            return;
+        };
+        let scale = ui.ctx().pixels_per_point();
+        let start = egui::pos2(overlay.start.x / scale, overlay.start.y / scale);
+        let end = egui::pos2(overlay.end.x / scale, overlay.end.y / scale);
+        let color = egui::Color32::from_rgb(255, 210, 60);
+        let stroke = egui::Stroke::new(2.0, color);
+        if overlay.complete {
+            ui.painter().line_segment([start, end], stroke);
+        } else {
+            ui.painter().add(egui::Shape::dashed_line(&[start, end], stroke, 6.0, 4.0));
+        }
+        ui.painter().circle(start, 4.0, color, egui::Stroke::new(1.0, egui::Color32::BLACK));
+        ui.painter().circle(end, 4.0, color, egui::Stroke::new(1.0, egui::Color32::BLACK));
+
+        let galley = ui.painter().layout_no_wrap(
+            overlay.measurement.label(),
+            egui::FontId::proportional(13.0),
+            egui::Color32::WHITE,
+        );
+        let label_rect = egui::Rect::from_center_size(start.lerp(end, 0.5) - egui::vec2(0.0, 14.0), galley.size()).expand(4.0);
+        ui.painter().rect_filled(label_rect, 3.0, egui::Color32::from_black_alpha(180));
+        ui.painter().galley(label_rect.shrink(4.0).min, galley, egui::Color32::WHITE);
+    }
+
+    /// Draw the renderer counters into the top-left corner of the viewport tab
+    fn paint_render_stats(&self, ui: &egui::Ui, rect: egui::Rect) {
+        let stats = &self.render_stats;
+        let text = format!(
+            "{:.0} FPS  {:.2} ms\nDraw calls: {}\nTriangles: {}\nInstances: {}\nVisible: {} / {}\nVRAM: {}",
+            self.performance.fps,
+            self.performance.frame_time_ms,
+            stats.draw_calls,
+            stats.triangles,
+            stats.instances,
+            stats.culling.visible_renderables,
+            stats.culling.total_renderables,
+            engine_render::format_bytes(stats.vram_bytes()),
+        );
+        let galley = ui.painter().layout_no_wrap(text, egui::FontId::monospace(12.0), egui::Color32::WHITE);
+        let panel = egui::Rect::from_min_size(rect.min + egui::vec2(8.0, 8.0), galley.size()).expand(6.0);
+        ui.painter().rect_filled(panel, 4.0, egui::Color32::from_black_alpha(170));
+        ui.painter().galley(panel.shrink(6.0).min, galley, egui::Color32::WHITE);
+    }
+
+    /// Viewport clicks place measurement points (the measure tool is on and no brush is active)
+    pub fn is_measuring(&self) -> bool {
+        self.measure.active && (self.brush_tool.mode == BrushMode::Select || !self.show_brush_panel)
+    }
+
     pub fn is_exit_requested(&self) -> bool {
         self.exit_requested
     }
@@ -378,6 +803,26 @@ impl EditorUi {
         });
     }
 
+    /// Echo and parse a console line. Help and clear are handled here; other
+    /// commands are passed on to the app.
+    fn run_console_line(&mut self, line: &str, result: &mut EditorResult) {
+        self.log_info(format!("> {}", line));
+        match console_commands::parse(line) {
+            Ok(ConsoleCommand::Help(None)) => {
+                for command in console_commands::COMMANDS {
+                    self.log_info(format!("{:<34} {}", command.usage, command.help));
+                }
+            }
+            Ok(ConsoleCommand::Help(Some(name))) => match console_commands::find_command(&name) {
+                Some(command) => self.log_info(format!("{} - {}", command.usage, command.help)),
+                None => self.log_error(format!("Unknown command '{}'", name)),
+            },
+            Ok(ConsoleCommand::Clear) => self.console_messages.clear(),
+            Ok(command) => result.console_command = Some(command),
+            Err(message) => self.log_error(message),
+        }
+    }
+
     /// Add a file to the recent files list
     pub fn add_recent_file(&mut self, path: String) {
         // Remove if already exists (to move it to the front)
@@ -399,58 +844,140 @@ impl EditorUi {
         self.undo_count = undo_count;
         self.redo_count = redo_count;
 
+        // Drop deleted entities from the selection
+        self.sync_selection(scene);
+
         // Menu bar
         self.render_menu_bar(ctx, scene, can_undo, can_redo, &mut result);
 
         // Status bar at the bottom (before other panels to reserve space)
         self.render_status_bar(ctx, scene);
 
-        // Left panel - Hierarchy
-        if self.show_hierarchy {
-            result.hierarchy = hierarchy::render_hierarchy_panel(
-                ctx,
-                scene,
-                &mut self.selected_entity,
-                &mut self.hierarchy_state,
-                &self.hidden_entities,
-                &self.locked_entities,
+        // Docked panels around the viewport (Hierarchy, Inspector, Console, Assets, Profiler)
+        self.sync_dock_tabs();
+        self.viewport_hovered = false;
+        let game_focused = self.dock_state
+            .find_active_focused()
+            .is_some_and(|(_, tab)| *tab == EditorTab::Game);
+        self.game_view_live = game_focused || !self.viewport_tab_visible;
+        self.viewport_tab_visible = false;
+        self.game_view_viewport = None;
+        let mut dock_state = std::mem::replace(&mut self.dock_state, DockState::new(Vec::new()));
+        egui::CentralPanel::default()
+            .frame(egui::Frame::NONE)
+            .show(ctx, |ui| {
+                let mut tab_viewer = EditorTabViewer {
+                    editor: self,
+                    scene,
+                    result: &mut result,
+                };
+                DockArea::new(&mut dock_state)
+                    .style(egui_dock::Style::from_egui(ui.style().as_ref()))
+                    .show_inside(ui, &mut tab_viewer);
+            });
+        self.dock_state = dock_state;
+        // Tabs closed from the dock area hide their panel
+        self.show_hierarchy = dock::is_tab_open(&self.dock_state, EditorTab::Hierarchy);
+        self.show_inspector = dock::is_tab_open(&self.dock_state, EditorTab::Inspector);
+        self.show_console = dock::is_tab_open(&self.dock_state, EditorTab::Console);
+        self.show_asset_browser = dock::is_tab_open(&self.dock_state, EditorTab::AssetBrowser);
+        self.show_statistics = dock::is_tab_open(&self.dock_state, EditorTab::Profiler);
+        self.show_game_view = dock::is_tab_open(&self.dock_state, EditorTab::Game);
+        self.show_navigation = dock::is_tab_open(&self.dock_state, EditorTab::Navigation);
+        self.show_lighting = dock::is_tab_open(&self.dock_state, EditorTab::Lighting);
+        self.show_timeline = dock::is_tab_open(&self.dock_state, EditorTab::Timeline);
+
+        // Box selection rectangle over the viewport
+        if let Some((start, end)) = self.box_select_rect {
+            let scale = ctx.pixels_per_point();
+            let rect = egui::Rect::from_two_pos(
+                egui::pos2(start.x / scale, start.y / scale),
+                egui::pos2(end.x / scale, end.y / scale),
+            );
+            let painter = ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("box_select"),
+            ));
+            painter.rect_filled(
+                rect,
+                0.0,
+                egui::Color32::from_rgba_unmultiplied(100, 150, 255, 30),
+            );
+            painter.rect_stroke(
+                rect,
+                0.0,
+                egui::Stroke::new(1.0, egui::Color32::from_rgb(100, 150, 255)),
+                egui::StrokeKind::Inside,
             );
         }
 
-        // Right panel - Inspector (captures changes)
-        if self.show_inspector {
-            let is_locked = self.selected_entity
-                .map(|id| self.locked_entities.contains(&id))
-                .unwrap_or(false);
-            result.inspector = inspector::render_inspector_panel(ctx, scene, &mut self.selected_entity, &mut self.inspector_state, is_locked);
+        // Water tool overlay
+        if let Some(overlay) = &self.water_overlay {
+            let scale = ctx.pixels_per_point();
+            let to_pos = |p: &glam::Vec2| egui::pos2(p.x / scale, p.y / scale);
+            let painter = ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("water_overlay"),
+            ));
+            let water_color = egui::Color32::from_rgb(120, 200, 255);
+
+            for zone in &overlay.zones {
+                let points: Vec<egui::Pos2> = zone.iter().map(to_pos).collect();
+                painter.add(egui::Shape::line(points, egui::Stroke::new(2.0, egui::Color32::from_rgb(255, 140, 60))));
+            }
+            for (tail, head) in &overlay.flow_arrows {
+                let tail = to_pos(tail);
+                painter.arrow(tail, to_pos(head) - tail, egui::Stroke::new(2.0, water_color));
+            }
+            if let Some((pos, label)) = &overlay.handle {
+                let pos = to_pos(pos);
+                let fill = if overlay.handle_active { egui::Color32::WHITE } else { water_color };
+                painter.line_segment([pos - egui::vec2(0.0, 24.0), pos + egui::vec2(0.0, 24.0)], egui::Stroke::new(2.0, water_color));
+                painter.circle(pos, 7.0, fill, egui::Stroke::new(1.5, egui::Color32::BLACK));
+                painter.text(
+                    pos + egui::vec2(12.0, 0.0),
+                    egui::Align2::LEFT_CENTER,
+                    label,
+                    egui::FontId::proportional(13.0),
+                    egui::Color32::WHITE,
+                );
+            }
         }
 
-        // Bottom panel - Console
-        if self.show_console {
-            console::render_console_panel(ctx, &mut self.console_messages);
+        // Spline tool overlay
+        if let Some(overlay) = &self.spline_overlay {
+            let scale = ctx.pixels_per_point();
+            let to_pos = |p: &glam::Vec2| egui::pos2(p.x / scale, p.y / scale);
+            let painter = ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("spline_overlay"),
+            ));
+            let spline_color = egui::Color32::from_rgb(255, 210, 80);
+
+            let curve: Vec<egui::Pos2> = overlay.curve.iter().map(to_pos).collect();
+            painter.add(egui::Shape::line(curve, egui::Stroke::new(2.0, spline_color)));
+            for (index, pos) in &overlay.points {
+                let fill = if overlay.active == Some(*index) { egui::Color32::WHITE } else { spline_color };
+                painter.circle(to_pos(pos), 6.0, fill, egui::Stroke::new(1.5, egui::Color32::BLACK));
+            }
         }
 
         // Brush tool panel (floating window)
         if self.show_brush_panel {
-            self.render_brush_panel(ctx);
-        }
-
-        // Statistics window
-        if self.show_statistics {
-            self.render_statistics_window(ctx, scene);
+            result.brush = self.render_brush_panel(ctx);
         }
 
         // Dialogs
         if self.show_save_dialog {
-            self.render_save_dialog(ctx, scene);
+            self.render_save_dialog(ctx, scene, &mut result);
         }
 
         if self.show_save_as_dialog {
-            self.render_save_as_dialog(ctx, scene);
+            self.render_save_as_dialog(ctx, scene, &mut result);
         }
 
         if self.show_load_dialog {
-            self.render_load_dialog(ctx, scene, &mut result);
+            self.render_load_dialog(ctx, &mut result);
         }
 
         if self.show_new_scene_confirm {
@@ -473,6 +1000,20 @@ impl EditorUi {
             self.render_goto_dialog(ctx, scene);
         }
 
+        if self.show_preferences {
+            self.render_preferences_window(ctx);
+        }
+
+        if self.show_build_dialog {
+            self.render_build_dialog(ctx);
+        }
+
+        if self.show_heightmap_dialog {
+            let (request, open) = heightmap_dialog::render_heightmap_dialog(ctx, &mut self.heightmap_dialog);
+            result.heightmap_request = request;
+            self.show_heightmap_dialog = open;
+        }
+
         result
     }
 
@@ -538,6 +1079,62 @@ impl EditorUi {
 
                     ui.separator();
 
+                    if ui.button("Export Build...").clicked() {
+                        let project_root = std::env::current_dir().unwrap_or_default();
+                        let mut settings = crate::build_export::BuildSettings::load(&project_root);
+                        if settings.scenes.is_empty() {
+                            settings.scenes.extend(self.current_scene_path.clone());
+                        }
+                        self.build_dialog.settings = settings;
+                        self.show_build_dialog = true;
+                        ui.close();
+                    }
+
+                    ui.menu_button("Terrain Heightmap", |ui| {
+                        if ui.button("Import...").on_hover_text("Replace the terrain with a 16-bit PNG, RAW or EXR heightmap").clicked() {
+                            self.heightmap_dialog.import = true;
+                            self.show_heightmap_dialog = true;
+                            ui.close();
+                        }
+                        if ui.button("Export...").on_hover_text("Save the sculpted terrain for other terrain tools").clicked() {
+                            self.heightmap_dialog.import = false;
+                            self.show_heightmap_dialog = true;
+                            ui.close();
+                        }
+                    });
+
+                    ui.menu_button("Capture", |ui| {
+                        if ui.add(egui::Button::new("Take Screenshot").shortcut_text("F12")).clicked() {
+                            result.capture_request = Some(CaptureRequest::Screenshot(None));
+                            ui.close();
+                        }
+                        ui.add(
+                            egui::Slider::new(&mut self.capture_settings.supersample, 1..=MAX_SUPERSAMPLE)
+                                .text("Supersample"),
+                        )
+                        .on_hover_text("Render the view as N x N tiles for anti-aliasing or a larger image");
+                        ui.add_enabled(
+                            self.capture_settings.supersample > 1,
+                            egui::Checkbox::new(&mut self.capture_settings.high_resolution, "Keep full resolution"),
+                        );
+                        ui.separator();
+                        ui.add(
+                            egui::DragValue::new(&mut self.capture_settings.sequence_frames)
+                                .range(2..=3600)
+                                .prefix("Sequence frames: "),
+                        );
+                        if ui.button("Turntable").on_hover_text("Orbit once around the camera target").clicked() {
+                            result.capture_request = Some(CaptureRequest::Turntable);
+                            ui.close();
+                        }
+                        if ui.button("Flythrough").on_hover_text("Move through the camera bookmarks (1-9)").clicked() {
+                            result.capture_request = Some(CaptureRequest::Flythrough);
+                            ui.close();
+                        }
+                    });
+
+                    ui.separator();
+
                     if ui.add(egui::Button::new("Exit").shortcut_text("Alt+F4")).clicked() {
                         if self.scene_modified {
                             self.show_exit_confirm = true;
@@ -568,18 +1165,89 @@ impl EditorUi {
                     ui.separator();
 
                     let has_selection = self.selected_entity.is_some();
+                    let multi_selection = self.selection_count() > 1;
                     if ui.add_enabled(has_selection, egui::Button::new("Duplicate").shortcut_text("Ctrl+D")).clicked() {
-                        if let Some(entity_id) = self.selected_entity {
+                        if multi_selection {
+                            result.hierarchy.duplicate_selected = true;
+                        } else if let Some(entity_id) = self.selected_entity {
                             result.hierarchy.duplicate_entity = Some(entity_id);
                         }
                         ui.close();
                     }
                     if ui.add_enabled(has_selection, egui::Button::new("Delete").shortcut_text("Delete")).clicked() {
-                        if let Some(entity_id) = self.selected_entity {
+                        if multi_selection {
+                            result.hierarchy.delete_selected = true;
+                        } else if let Some(entity_id) = self.selected_entity {
                             result.hierarchy.delete_entity = Some(entity_id);
                         }
                         ui.close();
                     }
+                    if ui
+                        .add_enabled(
+                            has_selection,
+                            egui::Button::new("Group").shortcut_text("Ctrl+Shift+G"),
+                        )
+                        .clicked()
+                    {
+                        result.hierarchy.group_selected = true;
+                        ui.close();
+                    }
+                    if ui
+                        .add_enabled(
+                            multi_selection,
+                            egui::Button::new("Parent to Active").shortcut_text("Ctrl+P"),
+                        )
+                        .clicked()
+                    {
+                        result.hierarchy.parent_selection_to = self.selected_entity;
+                        ui.close();
+                    }
+                    ui.add_enabled_ui(multi_selection, |ui| {
+                        ui.menu_button("Align", |ui| {
+                            for (axis, name) in ["X", "Y", "Z"].into_iter().enumerate() {
+                                if ui.button(format!("Align {} to Active", name)).clicked() {
+                                    result.hierarchy.align_selection = Some(axis);
+                                    ui.close();
+                                }
+                            }
+                            ui.separator();
+                            for (axis, name) in ["X", "Y", "Z"].into_iter().enumerate() {
+                                if ui.button(format!("Distribute Along {}", name)).clicked() {
+                                    result.hierarchy.distribute_selection = Some(axis);
+                                    ui.close();
+                                }
+                            }
+                        });
+                    });
+                    if ui
+                        .add_enabled(has_selection, egui::Button::new("Snap to Ground").shortcut_text("End"))
+                        .clicked()
+                    {
+                        result.hierarchy.snap_selected_to_ground = true;
+                        ui.close();
+                    }
+                    if ui
+                        .add_enabled(has_selection, egui::Button::new("Simulate Drop"))
+                        .on_hover_text("Drop the selection with physics and keep where it lands")
+                        .clicked()
+                    {
+                        result.hierarchy.drop_selected = true;
+                        ui.close();
+                    }
+                    if ui
+                        .add_enabled(has_selection, egui::Button::new("Select None"))
+                        .clicked()
+                    {
+                        self.clear_selection();
+                        ui.close();
+                    }
+
+                    ui.separator();
+
+                    if ui.button("Preferences...").clicked() {
+                        self.show_preferences = true;
+                        ui.close();
+                    }
                 });
 
                 ui.menu_button("View", |ui| {
@@ -592,11 +1260,35 @@ impl EditorUi {
                     if ui.checkbox(&mut self.show_console, "Console").changed() {
                         ui.close();
                     }
+                    if ui.checkbox(&mut self.show_asset_browser, "Assets").changed() {
+                        ui.close();
+                    }
+                    if ui.checkbox(&mut self.show_statistics, "Profiler").changed() {
+                        ui.close();
+                    }
+                    if ui.checkbox(&mut self.show_game_view, "Game View").changed() {
+                        ui.close();
+                    }
+                    if ui.checkbox(&mut self.show_navigation, "Navigation").changed() {
+                        ui.close();
+                    }
+                    if ui.checkbox(&mut self.show_lighting, "Lighting").changed() {
+                        ui.close();
+                    }
+                    if ui.checkbox(&mut self.show_timeline, "Timeline").changed() {
+                        ui.close();
+                    }
                     ui.separator();
                     if ui.checkbox(&mut self.show_brush_panel, "Brush Tool").changed() {
                         ui.close();
                     }
-                    if ui.checkbox(&mut self.show_statistics, "Statistics").changed() {
+                    if ui.checkbox(&mut self.show_grid, "Grid").changed() {
+                        ui.close();
+                    }
+                    if ui.checkbox(&mut self.show_entity_icons, "Entity Icons").on_hover_text("Icons for lights, cameras, audio sources and particle emitters").changed() {
+                        ui.close();
+                    }
+                    if ui.checkbox(&mut self.show_render_stats, "Render Stats").on_hover_text("Draw calls, triangles and memory in the viewport corner").changed() {
                         ui.close();
                     }
                     ui.separator();
@@ -604,8 +1296,17 @@ impl EditorUi {
                         self.show_hierarchy = true;
                         self.show_inspector = true;
                         self.show_console = true;
+                        self.show_asset_browser = false;
                         self.show_brush_panel = false;
                         self.show_statistics = false;
+                        self.show_game_view = true;
+                        self.show_navigation = false;
+                        self.show_lighting = false;
+                        self.show_timeline = false;
+                        self.show_grid = true;
+                        self.show_entity_icons = true;
+                        self.show_render_stats = false;
+                        self.dock_state = dock::default_dock_state();
                         ui.close();
                     }
                 });
@@ -621,11 +1322,225 @@ impl EditorUi {
                         ui.close();
                     }
                 });
+
+                ui.separator();
+                self.render_play_controls(ui, result);
+
+                ui.separator();
+                if ui
+                    .toggle_value(&mut self.measure.active, "📏 Measure")
+                    .on_hover_text("Click two points in the viewport to measure distance and angle (Esc clears)")
+                    .changed()
+                {
+                    self.measure.clear();
+                    self.measure_overlay = None;
+                }
             });
         });
     }
 
-    fn render_save_dialog(&mut self, ctx: &Context, scene: &mut Scene) {
+    /// Open/close dock tabs to match the panel visibility flags (menu, shortcuts, prefs)
+    fn sync_dock_tabs(&mut self) {
+        let tabs = [
+            (EditorTab::Viewport, true),
+            (EditorTab::Hierarchy, self.show_hierarchy),
+            (EditorTab::Inspector, self.show_inspector),
+            (EditorTab::Console, self.show_console),
+            (EditorTab::AssetBrowser, self.show_asset_browser),
+            (EditorTab::Profiler, self.show_statistics),
+            (EditorTab::Game, self.show_game_view),
+            (EditorTab::Navigation, self.show_navigation),
+            (EditorTab::Lighting, self.show_lighting),
+            (EditorTab::Timeline, self.show_timeline),
+        ];
+        for (tab, open) in tabs {
+            dock::set_tab_open(&mut self.dock_state, tab, open);
+        }
+    }
+
+    fn render_tab(
+        &mut self,
+        ui: &mut egui::Ui,
+        tab: EditorTab,
+        scene: &mut Scene,
+        result: &mut EditorResult,
+    ) {
+        match tab {
+            EditorTab::Viewport => {
+                let rect = ui.max_rect();
+                let response = ui.interact(rect, ui.id().with("viewport"), egui::Sense::hover());
+                self.viewport_hovered = response.hovered();
+                self.viewport_tab_visible = true;
+                if let Some(drop) = asset_browser::take_viewport_drop(ui.ctx(), rect) {
+                    result.spawn_model = Some(drop);
+                }
+                self.paint_navmesh_overlay(ui);
+                self.paint_entity_icons(ui);
+                self.paint_measurement(ui);
+                if self.show_render_stats {
+                    self.paint_render_stats(ui, rect);
+                }
+                // The scene is drawn into the Game tab instead while it is focused
+                if self.game_view_live && crate::play_mode::find_game_camera(scene).is_some() {
+                    ui.painter().rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+                    ui.painter().text(
+                        rect.center(),
+                        egui::Align2::CENTER_CENTER,
+                        "Click this tab to return to the editor view",
+                        egui::FontId::proportional(14.0),
+                        ui.visuals().weak_text_color(),
+                    );
+                }
+            }
+            EditorTab::Game => {
+                let has_camera = crate::play_mode::find_game_camera(scene).is_some();
+                let frame = game_view::render_game_view(
+                    ui,
+                    &mut self.game_view_state,
+                    has_camera,
+                    self.game_view_live,
+                );
+                let pixels_per_point = ui.ctx().pixels_per_point();
+                self.game_view_viewport = frame.map(|rect| {
+                    [
+                        rect.min.x * pixels_per_point,
+                        rect.min.y * pixels_per_point,
+                        rect.width() * pixels_per_point,
+                        rect.height() * pixels_per_point,
+                    ]
+                });
+            }
+            EditorTab::Hierarchy => {
+                result.hierarchy = hierarchy::render_hierarchy_panel(
+                    ui,
+                    scene,
+                    &mut self.selected_entity,
+                    &self.selected_entities,
+                    &mut self.hierarchy_state,
+                    &self.hidden_entities,
+                    &self.locked_entities,
+                );
+
+                // Ctrl+click and Shift+click selection from the hierarchy
+                if let Some(entity_id) = result.hierarchy.toggle_selected.take() {
+                    self.toggle_selection(entity_id);
+                }
+                if let Some(range) = result.hierarchy.select_range.take() {
+                    self.set_selection(&range);
+                }
+                self.sync_selection(scene);
+            }
+            EditorTab::Inspector => {
+                let is_locked = self.selected_entity
+                    .map(|id| self.locked_entities.contains(&id))
+                    .unwrap_or(false);
+                let selection_count = self.selection_count();
+                result.inspector = inspector::render_inspector_panel(
+                    ui,
+                    scene,
+                    &mut self.selected_entity,
+                    selection_count,
+                    &mut self.inspector_state,
+                    is_locked,
+                );
+            }
+            EditorTab::Console => {
+                if let Some(line) = console::render_console_panel(ui, &mut self.console_messages, &mut self.console_state) {
+                    self.run_console_line(&line, result);
+                }
+            }
+            EditorTab::AssetBrowser => {
+                let action = asset_browser::render_asset_browser_panel(ui, &mut self.asset_browser_state);
+                if action.spawn_model.is_some() {
+                    result.spawn_model = action.spawn_model;
+                }
+                if let Some(path) = action.open_scene {
+                    result.open_recent_file = Some(path);
+                }
+            }
+            EditorTab::Profiler => {
+                egui::ScrollArea::vertical().show(ui, |ui| self.render_profiler(ui, scene));
+            }
+            EditorTab::Navigation => {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    result.navigation = navigation::render_navigation_panel(ui, &mut self.navigation);
+                });
+            }
+            EditorTab::Lighting => {
+                let baked_meshes = scene
+                    .entities()
+                    .filter_map(|e| e.get_component::<engine_scene::components::MeshRenderer>())
+                    .filter(|mesh| mesh.lightmap_path.is_some())
+                    .count();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    result.lighting = lighting::render_lighting_panel(ui, &mut self.lighting, baked_meshes);
+                });
+            }
+            EditorTab::Timeline => {
+                let editable = !self.selected_entity.is_some_and(|id| self.locked_entities.contains(&id));
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.add_enabled_ui(editable, |ui| {
+                        result.timeline = timeline::render_timeline_panel(
+                            ui,
+                            scene,
+                            self.selected_entity,
+                            &mut self.timeline,
+                            self.play_state.in_session(),
+                        );
+                    });
+                });
+            }
+        }
+    }
+
+    fn render_play_controls(&mut self, ui: &mut egui::Ui, result: &mut EditorResult) {
+        match self.play_state {
+            PlayState::Playing => {
+                if ui
+                    .button("⏸ Pause")
+                    .on_hover_text("Pause simulation (F6)")
+                    .clicked()
+                {
+                    result.play_request = Some(PlayRequest::Pause);
+                }
+            }
+            PlayState::Editing | PlayState::Paused => {
+                let label = if self.play_state == PlayState::Paused {
+                    "▶ Resume"
+                } else {
+                    "▶ Play"
+                };
+                if ui
+                    .button(label)
+                    .on_hover_text("Run the scene (F6)")
+                    .clicked()
+                {
+                    result.play_request = Some(PlayRequest::Play);
+                }
+            }
+        }
+        if ui
+            .add_enabled(self.play_state.in_session(), egui::Button::new("⏹ Stop"))
+            .on_hover_text("Stop and restore the edited scene (Shift+F6)")
+            .clicked()
+        {
+            result.play_request = Some(PlayRequest::Stop);
+        }
+        ui.checkbox(&mut self.use_game_camera, "Game Camera")
+            .on_hover_text("View through the scene's active Camera while playing");
+
+        match self.play_state {
+            PlayState::Playing => {
+                ui.colored_label(egui::Color32::from_rgb(100, 220, 100), "PLAYING");
+            }
+            PlayState::Paused => {
+                ui.colored_label(egui::Color32::YELLOW, "PAUSED");
+            }
+            PlayState::Editing => {}
+        }
+    }
+
+    fn render_save_dialog(&mut self, ctx: &Context, scene: &mut Scene, result: &mut EditorResult) {
         egui::Window::new("Save Scene")
             .collapsible(false)
             .resizable(false)
@@ -648,6 +1563,8 @@ impl EditorUi {
                         match scene.save_to_file(&self.save_path) {
                             Ok(_) => {
                                 self.log_info(format!("Scene saved to: {}", self.save_path));
+                                self.save_navmesh(&self.save_path.clone());
+                                result.scene_saved = Some(self.save_path.clone());
                                 self.add_recent_file(self.save_path.clone());
                                 self.current_scene_path = Some(self.save_path.clone());
                                 self.scene_modified = false;
@@ -666,7 +1583,7 @@ impl EditorUi {
             });
     }
 
-    fn render_save_as_dialog(&mut self, ctx: &Context, scene: &mut Scene) {
+    fn render_save_as_dialog(&mut self, ctx: &Context, scene: &mut Scene, result: &mut EditorResult) {
         egui::Window::new("Save Scene As")
             .collapsible(false)
             .resizable(false)
@@ -691,6 +1608,8 @@ impl EditorUi {
                         match scene.save_to_file(&self.save_path) {
                             Ok(_) => {
                                 self.log_info(format!("Scene saved to: {}", self.save_path));
+                                self.save_navmesh(&self.save_path.clone());
+                                result.scene_saved = Some(self.save_path.clone());
                                 self.add_recent_file(self.save_path.clone());
                                 self.current_scene_path = Some(self.save_path.clone());
                                 self.scene_modified = false;
@@ -709,7 +1628,7 @@ impl EditorUi {
             });
     }
 
-    fn render_load_dialog(&mut self, ctx: &Context, scene: &mut Scene, result: &mut EditorResult) {
+    fn render_load_dialog(&mut self, ctx: &Context, result: &mut EditorResult) {
         egui::Window::new("Load Scene")
             .collapsible(false)
             .resizable(false)
@@ -735,21 +1654,9 @@ impl EditorUi {
                 ui.add_space(10.0);
                 ui.horizontal(|ui| {
                     if ui.add_enabled(is_valid && exists, egui::Button::new("Load")).clicked() {
-                        match Scene::load_from_file(&self.load_path) {
-                            Ok(loaded_scene) => {
-                                *scene = loaded_scene;
-                                self.log_info(format!("Scene loaded from: {}", self.load_path));
-                                self.add_recent_file(self.load_path.clone());
-                                self.current_scene_path = Some(self.load_path.clone());
-                                self.scene_modified = false;
-                                self.show_load_dialog = false;
-                                self.selected_entity = None;
-                                result.scene_changed = true; // Signal to clear undo history
-                            }
-                            Err(e) => {
-                                self.log_error(format!("Failed to load scene: {}", e));
-                            }
-                        }
+                        // Loaded like a recent file, through the asset manager
+                        result.open_recent_file = Some(self.load_path.clone());
+                        self.show_load_dialog = false;
                     }
 
                     if ui.button("Cancel").clicked() {
@@ -782,6 +1689,7 @@ impl EditorUi {
                         *scene = Scene::new("Untitled Scene".to_string());
                         self.log_info("Created new scene".to_string());
                         self.current_scene_path = None;
+                        self.navigation.navmesh = None;
                         self.scene_modified = false;
                         self.selected_entity = None;
                         self.show_new_scene_confirm = false;
@@ -822,7 +1730,8 @@ impl EditorUi {
             });
     }
 
-    fn render_brush_panel(&mut self, ctx: &Context) {
+    fn render_brush_panel(&mut self, ctx: &Context) -> BrushAction {
+        let mut action = BrushAction::default();
         egui::Window::new("Brush Tool")
             .default_width(220.0)
             .resizable(true)
@@ -832,13 +1741,21 @@ impl EditorUi {
                 ui.horizontal(|ui| {
                     let is_vegetation = self.brush_tool.mode.is_vegetation_mode() || self.brush_tool.mode == BrushMode::Select;
                     let is_terrain = self.brush_tool.mode.is_terrain_mode();
+                    let is_water = self.brush_tool.mode.is_water_mode();
+                    let is_spline = self.brush_tool.mode == BrushMode::SplineEdit;
 
-                    if ui.selectable_label(!is_terrain, "Vegetation").clicked() && is_terrain {
+                    if ui.selectable_label(!is_terrain && !is_water && !is_spline, "Vegetation").clicked() && (is_terrain || is_water || is_spline) {
                         self.brush_tool.mode = BrushMode::Select;
                     }
                     if ui.selectable_label(is_terrain, "Terrain").clicked() && !is_terrain {
                         self.brush_tool.mode = BrushMode::TerrainRaise;
                     }
+                    if ui.selectable_label(is_water, "Water").clicked() && !is_water {
+                        self.brush_tool.mode = BrushMode::WaterLevel;
+                    }
+                    if ui.selectable_label(is_spline, "Spline").clicked() {
+                        self.brush_tool.mode = BrushMode::SplineEdit;
+                    }
                 });
 
                 ui.separator();
@@ -855,6 +1772,14 @@ impl EditorUi {
                         ui.selectable_value(&mut self.brush_tool.mode, BrushMode::TerrainSmooth, "Smooth");
                         ui.selectable_value(&mut self.brush_tool.mode, BrushMode::TerrainFlatten, "Flatten");
                     });
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(&mut self.brush_tool.mode, BrushMode::TerrainErode, "Erode");
+                        ui.selectable_value(&mut self.brush_tool.mode, BrushMode::TerrainPaint, "Paint Texture");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(&mut self.brush_tool.mode, BrushMode::TerrainHole, "Cut Hole");
+                        ui.selectable_value(&mut self.brush_tool.mode, BrushMode::TerrainFill, "Fill Hole");
+                    });
 
                     ui.separator();
                     ui.heading("Brush Settings");
@@ -866,12 +1791,45 @@ impl EditorUi {
                             .speed(0.2));
                     });
 
-                    ui.horizontal(|ui| {
-                        ui.label("Strength:");
-                        ui.add(egui::DragValue::new(&mut self.brush_tool.terrain_strength)
-                            .range(0.1..=5.0)
-                            .speed(0.05));
-                    });
+                    if self.brush_tool.mode == BrushMode::TerrainPaint {
+                        ui.horizontal(|ui| {
+                            ui.label("Layer:");
+                            let layers = TerrainLayer::defaults();
+                            let selected = layers
+                                .get(self.brush_tool.paint_layer)
+                                .map(|layer| layer.name.clone())
+                                .unwrap_or_default();
+                            egui::ComboBox::from_id_salt("terrain_paint_layer")
+                                .selected_text(selected)
+                                .show_ui(ui, |ui| {
+                                    for (i, layer) in layers.iter().enumerate() {
+                                        let [r, g, b] = layer.color;
+                                        ui.horizontal(|ui| {
+                                            let (swatch, _) = ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+                                            ui.painter().rect_filled(swatch, 2.0, egui::Rgba::from_rgb(r, g, b));
+                                            ui.selectable_value(&mut self.brush_tool.paint_layer, i, &layer.name);
+                                        });
+                                    }
+                                });
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("Opacity:");
+                            ui.add(egui::Slider::new(&mut self.brush_tool.paint_opacity, 0.01..=1.0));
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("Falloff:");
+                            ui.add(egui::Slider::new(&mut self.brush_tool.paint_falloff, 0.0..=1.0));
+                        });
+                    } else {
+                        ui.horizontal(|ui| {
+                            ui.label("Strength:");
+                            ui.add(egui::DragValue::new(&mut self.brush_tool.terrain_strength)
+                                .range(0.1..=5.0)
+                                .speed(0.05));
+                        });
+                    }
 
                     ui.separator();
 
@@ -889,8 +1847,94 @@ impl EditorUi {
                         BrushMode::TerrainFlatten => {
                             ui.label("Click and drag to flatten to level");
                         }
+                        BrushMode::TerrainErode => {
+                            ui.label("Click and drag to wear channels into slopes");
+                        }
+                        BrushMode::TerrainHole => {
+                            ui.label("Click and drag to cut cave entrances");
+                        }
+                        BrushMode::TerrainFill => {
+                            ui.label("Click and drag next to a hole to close it");
+                        }
+                        BrushMode::TerrainPaint => {
+                            ui.label("Click and drag to paint the selected layer");
+                        }
+                        _ => {}
+                    }
+
+                    ui.separator();
+                    ui.heading("Erosion");
+                    ui.horizontal(|ui| {
+                        ui.label("Droplets:");
+                        ui.add(egui::DragValue::new(&mut self.brush_tool.erosion_droplets)
+                            .range(1000..=500_000)
+                            .speed(500.0));
+                    });
+                    if ui.button("Erode Terrain")
+                        .on_hover_text("Run hydraulic and thermal erosion over the whole terrain")
+                        .clicked()
+                    {
+                        action.erode_terrain = true;
+                    }
+                } else if self.brush_tool.mode.is_water_mode() {
+                    // Water editing tools (act on the scene's TerrainWater)
+                    ui.heading("Water Tool");
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(&mut self.brush_tool.mode, BrushMode::WaterLevel, "Level");
+                        ui.selectable_value(&mut self.brush_tool.mode, BrushMode::WaterFlow, "Flow");
+                        ui.selectable_value(&mut self.brush_tool.mode, BrushMode::WaterExclude, "Exclude");
+                    });
+
+                    ui.separator();
+
+                    if self.brush_tool.mode != BrushMode::WaterLevel {
+                        ui.heading("Brush Settings");
+                        ui.horizontal(|ui| {
+                            ui.label("Radius:");
+                            ui.add(egui::DragValue::new(&mut self.brush_tool.radius)
+                                .range(1.0..=30.0)
+                                .speed(0.2));
+                        });
+                    }
+                    if self.brush_tool.mode == BrushMode::WaterFlow {
+                        ui.horizontal(|ui| {
+                            ui.label("Flow Speed:");
+                            ui.add(egui::DragValue::new(&mut self.brush_tool.water_flow_speed)
+                                .range(0.0..=5.0)
+                                .speed(0.05));
+                        });
+                    }
+
+                    ui.separator();
+
+                    match self.brush_tool.mode {
+                        BrushMode::WaterLevel => {
+                            ui.label("Drag the handle up or down to set the water level");
+                        }
+                        BrushMode::WaterFlow => {
+                            ui.label("Drag across water to paint its flow direction");
+                        }
+                        BrushMode::WaterExclude => {
+                            ui.label("Click to keep an area dry");
+                            ui.label("Shift + click to remove a zone");
+                        }
                         _ => {}
                     }
+                } else if self.brush_tool.mode == BrushMode::SplineEdit {
+                    // Spline tool (acts on the selected entity's Spline)
+                    ui.heading("Spline Tool");
+                    ui.label("Select an entity with a Spline component");
+                    ui.label("Click on terrain to add a point");
+                    ui.label("Drag a point to move it");
+                    ui.label("Shift + click a point to remove it");
+
+                    ui.separator();
+                    if ui.button("Carve Splines")
+                        .on_hover_text("Cut riverbeds and level roads along every spline into the terrain")
+                        .clicked()
+                    {
+                        action.apply_splines = true;
+                    }
                 } else {
                     // Vegetation modes
                     ui.heading("Tool Mode");
@@ -899,6 +1943,10 @@ impl EditorUi {
                         ui.selectable_value(&mut self.brush_tool.mode, BrushMode::Place, "Place");
                         ui.selectable_value(&mut self.brush_tool.mode, BrushMode::Erase, "Erase");
                     });
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(&mut self.brush_tool.mode, BrushMode::GrassPaint, "Grass");
+                        ui.selectable_value(&mut self.brush_tool.mode, BrushMode::GrassErase, "Erase Grass");
+                    });
 
                     ui.separator();
 
@@ -957,6 +2005,13 @@ impl EditorUi {
 
                             ui.checkbox(&mut self.brush_tool.random_rotation, "Random Rotation");
                         }
+
+                        if self.brush_tool.mode.is_grass_mode() {
+                            ui.horizontal(|ui| {
+                                ui.label("Strength:");
+                                ui.add(egui::Slider::new(&mut self.brush_tool.grass_opacity, 0.01..=1.0));
+                            });
+                        }
                     }
 
                     ui.separator();
@@ -967,9 +2022,17 @@ impl EditorUi {
                         if self.brush_tool.mode == BrushMode::Place {
                             ui.label("Hold Shift + click to erase");
                         }
+                        if self.brush_tool.mode.is_grass_mode() {
+                            ui.label("Drag to paint; blade settings are on the Grass component");
+                        }
                     }
+
+                    ui.collapsing("Scatter", |ui| {
+                        action.scatter = scatter::render_scatter_section(ui, &mut self.scatter_settings);
+                    });
                 }
             });
+        action
     }
 
     fn render_about_dialog(&mut self, ctx: &Context) {
@@ -1058,10 +2121,28 @@ impl EditorUi {
                 ));
                 ui.separator();
 
+                if let Some(status) = &self.capture_status {
+                    ui.colored_label(egui::Color32::from_rgb(100, 200, 255), status);
+                    ui.separator();
+                }
+
+                if self.loading_assets > 0 {
+                    ui.spinner();
+                    let plural = if self.loading_assets == 1 { "" } else { "s" };
+                    ui.label(format!("Loading {} asset{}", self.loading_assets, plural));
+                    ui.separator();
+                }
+
                 // Selected entity indicator or help tip
                 if let Some(entity_id) = self.selected_entity {
                     if let Some(entity) = scene.get_entity(entity_id) {
-                        ui.colored_label(egui::Color32::from_rgb(255, 200, 100), format!("Selected: {}", entity.name));
+                        let count = self.selection_count();
+                        let text = if count > 1 {
+                            format!("Selected: {} (+{} more)", entity.name, count - 1)
+                        } else {
+                            format!("Selected: {}", entity.name)
+                        };
+                        ui.colored_label(egui::Color32::from_rgb(255, 200, 100), text);
                         ui.separator();
                     }
                 } else if self.brush_tool.mode == BrushMode::Select {
@@ -1074,14 +2155,32 @@ impl EditorUi {
                     let mode_text = match self.brush_tool.mode {
                         BrushMode::Place => format!("Brush: Place {}", self.brush_tool.vegetation_type.name()),
                         BrushMode::Erase => "Brush: Erase".to_string(),
+                        BrushMode::GrassPaint => "Brush: Grass".to_string(),
+                        BrushMode::GrassErase => "Brush: Erase Grass".to_string(),
                         BrushMode::TerrainRaise => "Terrain: Raise".to_string(),
                         BrushMode::TerrainLower => "Terrain: Lower".to_string(),
                         BrushMode::TerrainSmooth => "Terrain: Smooth".to_string(),
                         BrushMode::TerrainFlatten => "Terrain: Flatten".to_string(),
+                        BrushMode::TerrainErode => "Terrain: Erode".to_string(),
+                        BrushMode::TerrainHole => "Terrain: Cut Hole".to_string(),
+                        BrushMode::TerrainFill => "Terrain: Fill Hole".to_string(),
+                        BrushMode::TerrainPaint => {
+                            let layer = TerrainLayer::defaults()
+                                .get(self.brush_tool.paint_layer)
+                                .map(|layer| layer.name.clone())
+                                .unwrap_or_default();
+                            format!("Terrain: Paint {}", layer)
+                        }
+                        BrushMode::WaterLevel => "Water: Level".to_string(),
+                        BrushMode::WaterFlow => "Water: Flow".to_string(),
+                        BrushMode::WaterExclude => "Water: Exclude".to_string(),
+                        BrushMode::SplineEdit => "Spline".to_string(),
                         BrushMode::Select => "".to_string(),
                     };
                     let color = if self.brush_tool.mode.is_terrain_mode() {
                         egui::Color32::LIGHT_GREEN
+                    } else if self.brush_tool.mode.is_water_mode() {
+                        egui::Color32::from_rgb(120, 200, 255)
                     } else {
                         egui::Color32::LIGHT_BLUE
                     };
@@ -1111,108 +2210,344 @@ impl EditorUi {
         });
     }
 
-    fn render_statistics_window(&self, ctx: &Context, scene: &Scene) {
-        egui::Window::new("Statistics")
-            .default_width(280.0)
-            .resizable(true)
+    fn render_build_dialog(&mut self, ctx: &Context) {
+        let project_root = std::env::current_dir().unwrap_or_default();
+        let action = build_export::render_build_dialog(
+            ctx,
+            &mut self.build_dialog,
+            self.current_scene_path.as_deref(),
+            &project_root,
+        );
+        if action.export {
+            let settings = self.build_dialog.settings.clone();
+            if let Err(e) = settings.save(&project_root) {
+                self.log_warning(format!("Failed to save build settings: {}", e));
+            }
+            for &platform in &settings.platforms {
+                match crate::build_export::export_platform(&settings, &project_root, platform) {
+                    Ok(summary) => self.log_info(format!(
+                        "Exported {} build to {} ({} files, {:.1} MB)",
+                        summary.platform.label(),
+                        summary.dir.display(),
+                        summary.files,
+                        summary.bytes as f64 / (1024.0 * 1024.0)
+                    )),
+                    Err(e) => self.log_error(format!("{} export failed: {:#}", platform.label(), e)),
+                }
+            }
+        }
+        if action.export || action.close {
+            self.show_build_dialog = false;
+        }
+    }
+
+    fn render_preferences_window(&mut self, ctx: &Context) {
+        let mut open = self.show_preferences;
+        egui::Window::new("Preferences")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
             .show(ctx, |ui| {
-                ui.heading("Performance");
-                ui.horizontal(|ui| {
-                    ui.label("FPS:");
-                    let fps_color = if self.performance.fps >= 55.0 {
-                        egui::Color32::GREEN
-                    } else if self.performance.fps >= 30.0 {
-                        egui::Color32::YELLOW
-                    } else {
-                        egui::Color32::RED
-                    };
-                    ui.colored_label(fps_color, format!("{:.1}", self.performance.fps));
-                });
+                ui.heading("Camera");
                 ui.horizontal(|ui| {
-                    ui.label("Frame Time:");
-                    ui.label(format!("{:.2} ms", self.performance.frame_time_ms));
+                    ui.label("Speed:");
+                    ui.add(egui::Slider::new(&mut self.camera_speed, 0.1..=5.0).suffix("x"));
                 });
 
                 ui.separator();
-                ui.heading("Scene");
+                ui.heading("Display");
                 ui.horizontal(|ui| {
-                    ui.label("Entities:");
-                    ui.label(format!("{}", scene.entity_count()));
-                });
-
-                // Count components
-                let mut mesh_count = 0;
-                let mut light_count = 0;
-                let mut particle_count = 0;
-                let mut foliage_instances = 0;
-
-                use engine_scene::components::{MeshRenderer, Light, ParticleEmitter, Foliage};
-                for entity in scene.entities() {
-                    if entity.has_component::<MeshRenderer>() {
-                        mesh_count += 1;
-                    }
-                    if entity.has_component::<Light>() {
-                        light_count += 1;
-                    }
-                    if entity.has_component::<ParticleEmitter>() {
-                        particle_count += 1;
-                    }
-                    if let Some(foliage) = entity.get_component::<Foliage>() {
-                        foliage_instances += foliage.instances.len();
-                    }
-                }
-
-                ui.horizontal(|ui| {
-                    ui.label("Meshes:");
-                    ui.label(format!("{}", mesh_count));
+                    ui.label("Present mode:");
+                    egui::ComboBox::from_id_salt("present_mode")
+                        .selected_text(self.frame_pacing.present_mode.label())
+                        .show_ui(ui, |ui| {
+                            for mode in PresentMode::ALL {
+                                ui.selectable_value(
+                                    &mut self.frame_pacing.present_mode,
+                                    mode,
+                                    mode.label(),
+                                );
+                            }
+                        });
                 });
                 ui.horizontal(|ui| {
-                    ui.label("Lights:");
-                    ui.label(format!("{}", light_count));
+                    let mut capped = self.frame_pacing.fps_cap.is_some();
+                    ui.checkbox(&mut capped, "FPS cap:");
+                    let mut cap = self.frame_pacing.fps_cap.unwrap_or(60);
+                    ui.add_enabled(capped, egui::DragValue::new(&mut cap).range(10..=1000));
+                    self.frame_pacing.fps_cap = capped.then_some(cap);
                 });
                 ui.horizontal(|ui| {
-                    ui.label("Particle Emitters:");
-                    ui.label(format!("{}", particle_count));
+                    ui.label("Anti-aliasing:");
+                    egui::ComboBox::from_id_salt("anti_aliasing")
+                        .selected_text(self.anti_aliasing.name())
+                        .show_ui(ui, |ui| {
+                            for mode in engine_render::AntiAliasingMode::ALL {
+                                ui.selectable_value(&mut self.anti_aliasing, mode, mode.name());
+                            }
+                        });
                 });
+                if self.anti_aliasing == engine_render::AntiAliasingMode::Msaa {
+                    ui.horizontal(|ui| {
+                        ui.label("MSAA:");
+                        let label = |samples: u32| match samples {
+                            1 => "Off".to_string(),
+                            n => format!("{}x", n),
+                        };
+                        egui::ComboBox::from_id_salt("msaa_samples")
+                            .selected_text(label(self.msaa_samples))
+                            .show_ui(ui, |ui| {
+                                for samples in [1, 2, 4, 8] {
+                                    ui.selectable_value(&mut self.msaa_samples, samples, label(samples));
+                                }
+                            });
+                    });
+                }
+                ui.colored_label(egui::Color32::GRAY, "Turning MSAA on or off applies after restarting the editor");
                 ui.horizontal(|ui| {
-                    ui.label("Foliage Instances:");
-                    ui.label(format!("{}", foliage_instances));
+                    ui.label("Water reflections:");
+                    egui::ComboBox::from_id_salt("water_reflections")
+                        .selected_text(self.water_reflections.name())
+                        .show_ui(ui, |ui| {
+                            for quality in engine_render::WaterReflectionQuality::ALL {
+                                ui.selectable_value(&mut self.water_reflections, quality, quality.name());
+                            }
+                        });
                 });
 
                 ui.separator();
-                ui.heading("Camera");
-                ui.horizontal(|ui| {
-                    ui.label("Position:");
+                ui.heading("Simulation");
+                ui.checkbox(&mut self.frame_pacing.fixed_update, "Fixed update")
+                    .on_hover_text(
+                        "Step physics and scripts at a fixed rate, independent of the frame rate",
+                    );
+                ui.add_enabled_ui(self.frame_pacing.fixed_update, |ui| {
                     ui.label(format!(
-                        "({:.1}, {:.1}, {:.1})",
-                        self.camera_position.x,
-                        self.camera_position.y,
-                        self.camera_position.z
+                        "Rate: {} Hz (physics.timestep in project.ron)",
+                        engine_core::project::current().physics.step_rate()
                     ));
                 });
                 ui.horizontal(|ui| {
-                    ui.label("Distance:");
-                    ui.label(format!("{:.1}", self.camera_distance));
+                    ui.label("Frame time smoothing:");
+                    ui.add(
+                        egui::Slider::new(&mut self.frame_pacing.smoothing_frames, 1..=16)
+                            .suffix(" frames"),
+                    );
                 });
 
                 ui.separator();
-                ui.heading("History");
-                ui.horizontal(|ui| {
-                    ui.label("Undo Stack:");
-                    ui.label(format!("{}", self.undo_count));
-                });
-                ui.horizontal(|ui| {
-                    ui.label("Redo Stack:");
-                    ui.label(format!("{}", self.redo_count));
-                });
+                ui.colored_label(
+                    egui::Color32::GRAY,
+                    "Panels, brush, snap settings and recent files are saved automatically",
+                );
+            });
+        self.show_preferences = open;
+    }
 
-                ui.separator();
-                ui.heading("Session");
-                ui.horizontal(|ui| {
-                    ui.label("Recent Files:");
-                    ui.label(format!("{}", self.recent_files.len()));
-                });
+    fn render_profiler(&mut self, ui: &mut egui::Ui, scene: &Scene) {
+        ui.heading("Performance");
+        ui.horizontal(|ui| {
+            ui.label("FPS:");
+            let fps_color = if self.performance.fps >= 55.0 {
+                egui::Color32::GREEN
+            } else if self.performance.fps >= 30.0 {
+                egui::Color32::YELLOW
+            } else {
+                egui::Color32::RED
+            };
+            ui.colored_label(fps_color, format!("{:.1}", self.performance.fps));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Frame Time:");
+            ui.label(format!("{:.2} ms", self.performance.frame_time_ms));
+        });
+
+        ui.separator();
+        ui.heading("Frame Profiler");
+        let action = profiler::render_profiler_panel(ui, &mut self.profiler);
+        if action.export {
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            let path = std::path::PathBuf::from(format!("profiles/profile_{}.json", timestamp));
+            match self.profiler.export(&path) {
+                Ok(()) => self.log_info(format!("Exported profile to {}", path.display())),
+                Err(e) => self.log_error(format!("Failed to export profile: {}", e)),
+            }
+        }
+        if action.trace {
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            let path = std::path::PathBuf::from(format!("profiles/trace_{}.json", timestamp));
+            engine_core::trace::start_capture(self.profiler.trace_frames, &path);
+            self.log_info(format!(
+                "Tracing {} frames to {}",
+                self.profiler.trace_frames,
+                path.display()
+            ));
+        }
+
+        ui.separator();
+        ui.heading("Renderer");
+        let stats = self.render_stats;
+        ui.horizontal(|ui| {
+            ui.label("Draw Calls:");
+            ui.label(format!("{}", stats.draw_calls));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Instances:");
+            ui.label(format!("{}", stats.instances));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Triangles:");
+            ui.label(format!("{}", stats.triangles));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Visible Meshes:");
+            ui.label(format!(
+                "{} ({} culled, {:.0}%)",
+                stats.culling.visible_renderables,
+                stats.culling.culled_renderables,
+                stats.culling.cull_efficiency()
+            ));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Texture Memory:");
+            ui.label(engine_render::format_bytes(stats.texture_bytes));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Buffer Memory:");
+            ui.label(engine_render::format_bytes(stats.buffer_bytes));
+        });
+        ui.checkbox(&mut self.show_render_stats, "Show in viewport");
+
+        ui.separator();
+        ui.heading("GPU Memory");
+        egui::Grid::new("gpu_memory_grid")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("");
+                ui.label("Count");
+                ui.label("Memory");
+                ui.label("Peak");
+                ui.end_row();
+                for (kind, memory) in [
+                    ("Meshes", stats.meshes),
+                    ("Textures", stats.textures),
+                    ("Materials", stats.materials),
+                ] {
+                    ui.label(kind);
+                    ui.label(format!("{}", memory.count));
+                    ui.label(engine_render::format_bytes(memory.bytes));
+                    ui.label(engine_render::format_bytes(memory.peak_bytes));
+                    ui.end_row();
+                }
             });
+        let total = stats.meshes.bytes + stats.textures.bytes + stats.materials.bytes;
+        ui.horizontal(|ui| {
+            ui.label("Budget:");
+            if stats.budget_bytes == 0 {
+                ui.label(format!("{} (no limit)", engine_render::format_bytes(total)));
+            } else {
+                let text = format!(
+                    "{} of {}",
+                    engine_render::format_bytes(total),
+                    engine_render::format_bytes(stats.budget_bytes)
+                );
+                if total > stats.budget_bytes {
+                    ui.colored_label(egui::Color32::from_rgb(230, 90, 80), text);
+                } else {
+                    ui.label(text);
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Evicted:");
+            ui.label(format!("{}", stats.evicted));
+        });
+
+        ui.separator();
+        ui.heading("Scene");
+        ui.horizontal(|ui| {
+            ui.label("Entities:");
+            ui.label(format!("{}", scene.entity_count()));
+        });
+
+        // Count components
+        let mut mesh_count = 0;
+        let mut light_count = 0;
+        let mut particle_count = 0;
+        let mut foliage_instances = 0;
+
+        use engine_scene::components::{MeshRenderer, Light, ParticleEmitter, Foliage};
+        for entity in scene.entities() {
+            if entity.has_component::<MeshRenderer>() {
+                mesh_count += 1;
+            }
+            if entity.has_component::<Light>() {
+                light_count += 1;
+            }
+            if entity.has_component::<ParticleEmitter>() {
+                particle_count += 1;
+            }
+            if let Some(foliage) = entity.get_component::<Foliage>() {
+                foliage_instances += foliage.instances.len();
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Meshes:");
+            ui.label(format!("{}", mesh_count));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Lights:");
+            ui.label(format!("{}", light_count));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Particle Emitters:");
+            ui.label(format!("{}", particle_count));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Foliage Instances:");
+            ui.label(format!("{}", foliage_instances));
+        });
+
+        ui.separator();
+        ui.heading("Camera");
+        ui.horizontal(|ui| {
+            ui.label("Position:");
+            ui.label(format!(
+                "({:.1}, {:.1}, {:.1})",
+                self.camera_position.x,
+                self.camera_position.y,
+                self.camera_position.z
+            ));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Distance:");
+            ui.label(format!("{:.1}", self.camera_distance));
+        });
+
+        ui.separator();
+        ui.heading("History");
+        ui.horizontal(|ui| {
+            ui.label("Undo Stack:");
+            ui.label(format!("{}", self.undo_count));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Redo Stack:");
+            ui.label(format!("{}", self.redo_count));
+        });
+
+        ui.separator();
+        ui.heading("Session");
+        ui.horizontal(|ui| {
+            ui.label("Recent Files:");
+            ui.label(format!("{}", self.recent_files.len()));
+        });
     }
 
     fn render_shortcuts_help(&mut self, ctx: &Context) {
@@ -1240,29 +2575,68 @@ impl EditorUi {
 
                 ui.separator();
                 ui.heading("Edit Operations");
-                egui::Grid::new("edit_shortcuts").striped(true).show(ui, |ui| {
-                    ui.label("Ctrl+Z");
-                    ui.label("Undo");
-                    ui.end_row();
-                    ui.label("Ctrl+Y / Ctrl+Shift+Z");
-                    ui.label("Redo");
-                    ui.end_row();
-                    ui.label("Ctrl+C");
-                    ui.label("Copy Entity");
-                    ui.end_row();
-                    ui.label("Ctrl+V");
-                    ui.label("Paste Entity");
-                    ui.end_row();
-                    ui.label("Ctrl+D");
-                    ui.label("Duplicate Entity");
-                    ui.end_row();
-                    ui.label("Delete");
-                    ui.label("Delete Selected Entity");
-                    ui.end_row();
-                    ui.label("Ctrl+G");
-                    ui.label("Go To Entity");
-                    ui.end_row();
-                });
+                egui::Grid::new("edit_shortcuts")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Ctrl+Z");
+                        ui.label("Undo");
+                        ui.end_row();
+                        ui.label("Ctrl+Y / Ctrl+Shift+Z");
+                        ui.label("Redo");
+                        ui.end_row();
+                        ui.label("Ctrl+C");
+                        ui.label("Copy Entity");
+                        ui.end_row();
+                        ui.label("Ctrl+V");
+                        ui.label("Paste Entity");
+                        ui.end_row();
+                        ui.label("Ctrl+D");
+                        ui.label("Duplicate Entity");
+                        ui.end_row();
+                        ui.label("Delete");
+                        ui.label("Delete Selected Entity");
+                        ui.end_row();
+                        ui.label("Ctrl+G");
+                        ui.label("Go To Entity");
+                        ui.end_row();
+                        ui.label("Ctrl+Shift+G");
+                        ui.label("Group Selected Entities");
+                        ui.end_row();
+                        ui.label("Ctrl+P");
+                        ui.label("Parent Selection to Active Entity");
+                        ui.end_row();
+                        ui.label("End");
+                        ui.label("Snap Selection to Ground");
+                        ui.end_row();
+                        ui.label("Esc (Measure)");
+                        ui.label("Clear Measurement / Exit Measure Tool");
+                        ui.end_row();
+                        ui.label("Ctrl+A");
+                        ui.label("Select All Visible Entities");
+                        ui.end_row();
+                    });
+
+                ui.separator();
+                ui.heading("Selection");
+                egui::Grid::new("selection_shortcuts")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Click");
+                        ui.label("Select Entity");
+                        ui.end_row();
+                        ui.label("Ctrl+Click");
+                        ui.label("Add/Remove from Selection");
+                        ui.end_row();
+                        ui.label("Shift+Click (Hierarchy)");
+                        ui.label("Select Range");
+                        ui.end_row();
+                        ui.label("Left Mouse Drag");
+                        ui.label("Box Select (Ctrl to add)");
+                        ui.end_row();
+                        ui.label("Drag Selected Entity");
+                        ui.label("Move Selection (snaps to grid/surface)");
+                        ui.end_row();
+                    });
 
                 ui.separator();
                 ui.heading("Viewport Controls");
@@ -1289,13 +2663,22 @@ impl EditorUi {
                     ui.label("Reset Camera View");
                     ui.end_row();
                     ui.label("Numpad 1");
-                    ui.label("Front View");
+                    ui.label("Front View (Orthographic)");
                     ui.end_row();
                     ui.label("Numpad 3");
-                    ui.label("Right View");
+                    ui.label("Right View (Orthographic)");
                     ui.end_row();
                     ui.label("Numpad 7");
-                    ui.label("Top View");
+                    ui.label("Top View (Orthographic)");
+                    ui.end_row();
+                    ui.label("Numpad 5");
+                    ui.label("Toggle Orthographic");
+                    ui.end_row();
+                    ui.label("Ctrl+1..9");
+                    ui.label("Save Camera Bookmark");
+                    ui.end_row();
+                    ui.label("1..9");
+                    ui.label("Restore Camera Bookmark");
                     ui.end_row();
                     ui.label("Right Mouse Drag");
                     ui.label("Orbit Camera");
@@ -1350,11 +2733,23 @@ impl EditorUi {
                     ui.label("Toggle Shortcuts Help");
                     ui.end_row();
                     ui.label("F3");
-                    ui.label("Toggle Statistics Panel");
+                    ui.label("Toggle Profiler Panel");
                     ui.end_row();
                     ui.label("F4");
                     ui.label("Toggle Console Panel");
                     ui.end_row();
+                    ui.label("F5");
+                    ui.label("Toggle Assets Panel");
+                    ui.end_row();
+                    ui.label("F6");
+                    ui.label("Play / Pause Scene");
+                    ui.end_row();
+                    ui.label("Shift+F6");
+                    ui.label("Stop Scene (restore edits)");
+                    ui.end_row();
+                    ui.label("F12");
+                    ui.label("Take Screenshot");
+                    ui.end_row();
                 });
 
                 ui.separator();
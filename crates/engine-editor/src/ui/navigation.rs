@@ -0,0 +1,87 @@
+// Navigation panel - agent settings, navmesh baking and display
+
+use engine_assets::{NavAgentSettings, NavMesh};
+
+/// Navmesh baking state (the baked navmesh is saved with the scene)
+pub struct NavigationState {
+    pub agent: NavAgentSettings,
+    pub navmesh: Option<NavMesh>,
+    /// Draw the walkable polygons over the viewport
+    pub show_navmesh: bool,
+}
+
+impl Default for NavigationState {
+    fn default() -> Self {
+        Self {
+            agent: NavAgentSettings::default(),
+            navmesh: None,
+            show_navmesh: true,
+        }
+    }
+}
+
+/// Requests from the navigation panel
+#[derive(Default)]
+pub struct NavigationAction {
+    /// Bake the navmesh from terrain and static meshes
+    pub bake: bool,
+    pub clear: bool,
+}
+
+pub fn render_navigation_panel(ui: &mut egui::Ui, state: &mut NavigationState) -> NavigationAction {
+    let mut action = NavigationAction::default();
+
+    ui.heading("Agent");
+    let agent = &mut state.agent;
+    egui::Grid::new("nav_agent").num_columns(2).show(ui, |ui| {
+        ui.label("Radius");
+        ui.add(egui::DragValue::new(&mut agent.radius).range(0.0..=5.0).speed(0.01).suffix(" m"));
+        ui.end_row();
+
+        ui.label("Height");
+        ui.add(egui::DragValue::new(&mut agent.height).range(0.1..=10.0).speed(0.01).suffix(" m"));
+        ui.end_row();
+
+        ui.label("Max Slope");
+        ui.add(egui::DragValue::new(&mut agent.max_slope).range(0.0..=89.0).suffix("°"));
+        ui.end_row();
+
+        ui.label("Step Height");
+        ui.add(egui::DragValue::new(&mut agent.max_climb).range(0.0..=2.0).speed(0.01).suffix(" m"))
+            .on_hover_text("Highest ledge the agent can step up or down");
+        ui.end_row();
+
+        ui.label("Cell Size");
+        ui.add(egui::DragValue::new(&mut agent.cell_size).range(0.1..=4.0).speed(0.01).suffix(" m"))
+            .on_hover_text("Sampling resolution. Smaller cells follow obstacles more closely but bake slower.");
+        ui.end_row();
+    });
+
+    ui.add_space(4.0);
+    ui.horizontal(|ui| {
+        if ui.button("Bake").on_hover_text("Bake from the terrain and static meshes").clicked() {
+            action.bake = true;
+        }
+        if ui.add_enabled(state.navmesh.is_some(), egui::Button::new("Clear")).clicked() {
+            action.clear = true;
+        }
+    });
+    ui.checkbox(&mut state.show_navmesh, "Show in Viewport");
+
+    ui.separator();
+    match &state.navmesh {
+        Some(navmesh) => {
+            ui.label(format!("Polygons: {}", navmesh.polygons.len()));
+            ui.label(format!("Walkable area: {:.1} m²", navmesh.walkable_area()));
+            if navmesh.agent != state.agent {
+                ui.colored_label(egui::Color32::YELLOW, "⚠ Agent settings changed, bake again to apply");
+            }
+        }
+        None => {
+            ui.weak("Not baked");
+        }
+    }
+    ui.weak("The navmesh is saved next to the scene file.");
+
+    action
+}
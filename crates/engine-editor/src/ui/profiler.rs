@@ -0,0 +1,213 @@
+// Profiler panel - frame timeline, CPU/GPU flame view and per-system averages
+
+use egui::{pos2, vec2, Color32, Rect, Sense, Stroke};
+
+use crate::profiler::{Profiler, Span, HISTORY_FRAMES};
+
+/// Frame budget lines drawn on the timeline (60 and 30 fps)
+const BUDGET_60_FPS_MS: f32 = 1000.0 / 60.0;
+const BUDGET_30_FPS_MS: f32 = 1000.0 / 30.0;
+const TIMELINE_HEIGHT: f32 = 60.0;
+const FLAME_ROW_HEIGHT: f32 = 18.0;
+
+/// Requests from the profiler panel
+#[derive(Default)]
+pub struct ProfilerAction {
+    /// Export the captured frames as a Chrome trace
+    pub export: bool,
+    /// Start a trace capture of the next `trace_frames` frames
+    pub trace: bool,
+}
+
+/// Stable color for a span name
+fn span_color(name: &str) -> Color32 {
+    let hash = name
+        .bytes()
+        .fold(2166136261_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(16777619));
+    let hue = (hash % 360) as f32 / 360.0;
+    egui::ecolor::Hsva::new(hue, 0.5, 0.75, 1.0).into()
+}
+
+fn frame_color(ms: f32) -> Color32 {
+    if ms > BUDGET_30_FPS_MS {
+        Color32::from_rgb(220, 80, 80)
+    } else if ms > BUDGET_60_FPS_MS {
+        Color32::from_rgb(220, 190, 70)
+    } else {
+        Color32::from_rgb(90, 180, 90)
+    }
+}
+
+pub fn render_profiler_panel(ui: &mut egui::Ui, profiler: &mut Profiler) -> ProfilerAction {
+    let mut action = ProfilerAction::default();
+
+    ui.horizontal(|ui| {
+        let capture_label = if profiler.paused { "Resume" } else { "Capture" };
+        if ui
+            .button(capture_label)
+            .on_hover_text("Freeze the frame history to inspect it")
+            .clicked()
+        {
+            profiler.paused = !profiler.paused;
+            if !profiler.paused {
+                profiler.selected = None;
+            }
+        }
+        if ui.button("Clear").clicked() {
+            profiler.clear();
+        }
+        if ui
+            .add_enabled(!profiler.history().is_empty(), egui::Button::new("Export..."))
+            .on_hover_text("Save the frame history as Chrome trace JSON (chrome://tracing, Perfetto)")
+            .clicked()
+        {
+            action.export = true;
+        }
+        if profiler.paused {
+            ui.colored_label(Color32::YELLOW, "Captured");
+        }
+    });
+
+    ui.horizontal(|ui| {
+        let tracing = engine_core::trace::is_capturing();
+        if ui
+            .add_enabled(!tracing, egui::Button::new("Trace"))
+            .on_hover_text(
+                "Record every tracing span, on all threads, over the next frames as Chrome trace JSON",
+            )
+            .clicked()
+        {
+            action.trace = true;
+        }
+        ui.add(
+            egui::DragValue::new(&mut profiler.trace_frames)
+                .range(1..=3600)
+                .suffix(" frames"),
+        );
+        if tracing {
+            ui.colored_label(Color32::YELLOW, "Tracing...");
+        }
+    });
+
+    render_timeline(ui, profiler);
+
+    let Some(frame) = profiler.current_frame().cloned() else {
+        ui.weak("No frames recorded yet");
+        return action;
+    };
+
+    ui.label(format!(
+        "Frame {}: CPU {:.2} ms, GPU {:.2} ms",
+        frame.index, frame.cpu_ms, frame.gpu_ms
+    ));
+    let scale_ms = frame.cpu_ms.max(frame.gpu_ms).max(0.001);
+
+    ui.label("CPU");
+    render_flame(ui, &frame.cpu, scale_ms);
+    ui.label("GPU");
+    if frame.gpu.is_empty() {
+        ui.weak("No GPU timings (timestamp queries unsupported or still reading back)");
+    } else {
+        render_flame(ui, &frame.gpu, scale_ms);
+    }
+
+    ui.collapsing("System Averages", |ui| {
+        egui::Grid::new("profiler_averages")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                for (name, ms) in profiler.averages() {
+                    ui.colored_label(span_color(name), name);
+                    ui.label(format!("{:.3} ms", ms));
+                    ui.end_row();
+                }
+            });
+    });
+
+    action
+}
+
+/// Bar per frame in the history; clicking a bar captures and selects that frame
+fn render_timeline(ui: &mut egui::Ui, profiler: &mut Profiler) {
+    let (rect, response) =
+        ui.allocate_exact_size(vec2(ui.available_width(), TIMELINE_HEIGHT), Sense::click());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+    let history = profiler.history();
+    let max_ms = history
+        .iter()
+        .map(|frame| frame.cpu_ms)
+        .fold(BUDGET_30_FPS_MS, f32::max);
+    let bar_width = rect.width() / HISTORY_FRAMES as f32;
+    let selected = profiler.current_frame().map(|frame| frame.index);
+
+    for (i, frame) in history.iter().enumerate() {
+        let x = rect.left() + i as f32 * bar_width;
+        let height = frame.cpu_ms / max_ms * rect.height();
+        let color = if profiler.selected.is_some() && selected == Some(frame.index) {
+            Color32::WHITE
+        } else {
+            frame_color(frame.cpu_ms)
+        };
+        painter.rect_filled(
+            Rect::from_min_max(
+                pos2(x, rect.bottom() - height),
+                pos2(x + bar_width.max(1.0), rect.bottom()),
+            ),
+            0.0,
+            color,
+        );
+    }
+
+    for budget in [BUDGET_60_FPS_MS, BUDGET_30_FPS_MS] {
+        let y = rect.bottom() - budget / max_ms * rect.height();
+        painter.hline(rect.x_range(), y, Stroke::new(1.0, Color32::from_white_alpha(60)));
+    }
+
+    if response.clicked() {
+        if let Some(pos) = response.interact_pointer_pos() {
+            let i = ((pos.x - rect.left()) / bar_width) as usize;
+            if let Some(index) = profiler.history().get(i).map(|frame| frame.index) {
+                profiler.selected = Some(index);
+                profiler.paused = true;
+            }
+        }
+    }
+}
+
+/// Spans laid out left to right by time, one row per nesting depth
+fn render_flame(ui: &mut egui::Ui, spans: &[Span], scale_ms: f32) {
+    let rows = spans.iter().map(|span| span.depth + 1).max().unwrap_or(1);
+    let (rect, response) = ui.allocate_exact_size(
+        vec2(ui.available_width(), rows as f32 * FLAME_ROW_HEIGHT),
+        Sense::hover(),
+    );
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+    let mut hovered = None;
+    for span in spans {
+        let x = rect.left() + span.start_ms / scale_ms * rect.width();
+        let width = (span.duration_ms / scale_ms * rect.width()).max(1.0);
+        let y = rect.top() + span.depth as f32 * FLAME_ROW_HEIGHT;
+        let span_rect = Rect::from_min_size(pos2(x, y), vec2(width, FLAME_ROW_HEIGHT - 1.0));
+        painter.rect_filled(span_rect, 2.0, span_color(span.name));
+        if width > 40.0 {
+            ui.painter_at(span_rect).text(
+                span_rect.left_center() + vec2(4.0, 0.0),
+                egui::Align2::LEFT_CENTER,
+                span.name,
+                egui::FontId::proportional(11.0),
+                Color32::BLACK,
+            );
+        }
+        if response.hover_pos().is_some_and(|pos| span_rect.contains(pos)) {
+            hovered = Some(span);
+        }
+    }
+
+    if let Some(span) = hovered {
+        response.on_hover_text_at_pointer(format!("{}: {:.3} ms", span.name, span.duration_ms));
+    }
+}
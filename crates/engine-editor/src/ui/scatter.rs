@@ -0,0 +1,133 @@
+// Scatter section of the brush panel - rules for bulk foliage placement
+
+use engine_assets::TerrainLayer;
+
+use crate::scatter::{ScatterRegion, ScatterSettings};
+
+fn layer_label(layer: Option<usize>, layers: &[TerrainLayer]) -> String {
+    match layer {
+        None => "Any".to_string(),
+        Some(layer) => match layers.get(layer) {
+            Some(layer) => layer.name.clone(),
+            None => format!("Layer {}", layer),
+        },
+    }
+}
+
+fn region_label(region: ScatterRegion, layers: &[TerrainLayer]) -> String {
+    match region {
+        ScatterRegion::WholeTerrain => "Whole Terrain".to_string(),
+        ScatterRegion::PaintedLayer(layer) => match layers.get(layer) {
+            Some(layer) => format!("Painted: {}", layer.name),
+            None => format!("Painted: Layer {}", layer),
+        },
+    }
+}
+
+/// Draw the scatter settings. Returns true if Scatter was pressed.
+pub fn render_scatter_section(ui: &mut egui::Ui, settings: &mut ScatterSettings) -> bool {
+    let layers = TerrainLayer::defaults();
+    let mut scatter = false;
+
+    ui.horizontal(|ui| {
+        ui.label("Region:");
+        egui::ComboBox::from_id_salt("scatter_region")
+            .selected_text(region_label(settings.region, &layers))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(
+                    &mut settings.region,
+                    ScatterRegion::WholeTerrain,
+                    region_label(ScatterRegion::WholeTerrain, &layers),
+                );
+                // Layer 0 is the base surface that covers everything unpainted
+                for layer in 1..layers.len() {
+                    let region = ScatterRegion::PaintedLayer(layer);
+                    ui.selectable_value(&mut settings.region, region, region_label(region, &layers));
+                }
+            });
+    })
+    .response
+    .on_hover_text("Painted regions come from the terrain Paint Texture brush");
+
+    for (i, rule) in settings.rules.iter_mut().enumerate() {
+        ui.push_id(i, |ui| {
+            ui.checkbox(&mut rule.enabled, rule.vegetation_type.name());
+            if !rule.enabled {
+                return;
+            }
+            egui::Grid::new("scatter_rule").num_columns(3).show(ui, |ui| {
+                ui.label("Density");
+                ui.add(egui::DragValue::new(&mut rule.density).range(0.0..=20.0).speed(0.05))
+                    .on_hover_text("Instances per 100 m²");
+                ui.end_row();
+
+                ui.label("Slope");
+                ui.add(egui::DragValue::new(&mut rule.slope_min).range(0.0..=rule.slope_max).suffix("°"));
+                ui.add(egui::DragValue::new(&mut rule.slope_max).range(rule.slope_min..=90.0).suffix("°"));
+                ui.end_row();
+
+                ui.label("Altitude");
+                ui.add(egui::DragValue::new(&mut rule.altitude_min).range(-1000.0..=rule.altitude_max).speed(0.1));
+                ui.add(egui::DragValue::new(&mut rule.altitude_max).range(rule.altitude_min..=1000.0).speed(0.1));
+                ui.end_row();
+
+                ui.label("Spacing");
+                ui.add(egui::DragValue::new(&mut rule.min_spacing).range(0.1..=20.0).speed(0.05))
+                    .on_hover_text("Minimum distance to any other foliage");
+                ui.end_row();
+
+                ui.label("Scale");
+                ui.add(egui::DragValue::new(&mut rule.scale_min).range(0.1..=rule.scale_max).speed(0.05));
+                ui.add(egui::DragValue::new(&mut rule.scale_max).range(rule.scale_min..=3.0).speed(0.05));
+                ui.end_row();
+
+                ui.label("Layer");
+                egui::ComboBox::from_id_salt("scatter_rule_layer")
+                    .selected_text(layer_label(rule.layer, &layers))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut rule.layer, None, layer_label(None, &layers));
+                        for layer in 1..layers.len() {
+                            ui.selectable_value(&mut rule.layer, Some(layer), layer_label(Some(layer), &layers));
+                        }
+                    })
+                    .response
+                    .on_hover_text("Only where this terrain texture is painted");
+                ui.end_row();
+
+                ui.label("Noise");
+                ui.add(egui::DragValue::new(&mut rule.noise_coverage).range(0.0..=1.0).speed(0.01))
+                    .on_hover_text("Fraction of the terrain the noise mask covers (1 = no mask)");
+                ui.add(egui::DragValue::new(&mut rule.noise_scale).range(1.0..=500.0).speed(0.5).suffix(" m"))
+                    .on_hover_text("Size of the noise patches");
+                ui.end_row();
+            });
+        });
+    }
+
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.label("Water Clearance:");
+        ui.add(egui::DragValue::new(&mut settings.water_clearance).range(0.0..=20.0).speed(0.1))
+            .on_hover_text("Keep foliage this far from water, sideways and above the surface");
+    });
+    ui.horizontal(|ui| {
+        ui.label("Seed:");
+        ui.add(egui::DragValue::new(&mut settings.seed));
+        if ui.small_button("🎲").on_hover_text("Random seed").clicked() {
+            settings.seed = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.subsec_nanos())
+                .unwrap_or(0);
+        }
+    });
+    ui.checkbox(&mut settings.random_rotation, "Random Rotation");
+    ui.checkbox(&mut settings.replace_existing, "Replace Existing")
+        .on_hover_text("Remove foliage of the enabled types in the region before scattering");
+
+    let any_enabled = settings.rules.iter().any(|rule| rule.enabled);
+    if ui.add_enabled(any_enabled, egui::Button::new("Scatter")).clicked() {
+        scatter = true;
+    }
+
+    scatter
+}
@@ -0,0 +1,309 @@
+// Timeline panel - keyframe authoring for the selected entity's AnimationClip
+
+use egui::{Color32, Pos2, Rect, Sense, Stroke, Vec2};
+use engine_scene::{
+    animation::{self, AnimatedProperty, AnimationClip},
+    components::Light,
+    entity::EntityId,
+    scene::Scene,
+};
+
+const LABEL_WIDTH: f32 = 110.0;
+const ROW_HEIGHT: f32 = 22.0;
+const RULER_HEIGHT: f32 = 18.0;
+const KEY_SIZE: f32 = 5.0;
+
+/// Playhead and key selection of the timeline
+#[derive(Default)]
+pub struct TimelineState {
+    /// Playhead position in seconds
+    pub time: f32,
+    /// Preview playback in the editor
+    pub playing: bool,
+    /// Selected key as (track property, key index)
+    pub selected_key: Option<(AnimatedProperty, usize)>,
+    /// Time the selected key is being dragged to
+    drag_time: Option<f32>,
+    /// Entity the playhead and selection belong to
+    entity: Option<EntityId>,
+}
+
+/// Result of the timeline panel
+#[derive(Default)]
+pub struct TimelineAction {
+    /// The clip or its keys were edited
+    pub clip_changed: bool,
+    /// Scrubbing or preview playback posed the entity at the playhead
+    pub pose_changed: bool,
+    /// Preview playback is running, so its pose changes merge into one undo step
+    pub previewing: bool,
+}
+
+pub fn render_timeline_panel(
+    ui: &mut egui::Ui,
+    scene: &mut Scene,
+    selected_entity: Option<EntityId>,
+    state: &mut TimelineState,
+    in_play_session: bool,
+) -> TimelineAction {
+    let mut action = TimelineAction::default();
+    if state.entity != selected_entity {
+        *state = TimelineState {
+            entity: selected_entity,
+            ..Default::default()
+        };
+    }
+
+    let Some(entity) = selected_entity.and_then(|id| scene.get_entity_mut(id)) else {
+        state.playing = false;
+        ui.weak("Select an entity to animate it.");
+        return action;
+    };
+    let entity_id = entity.id;
+    let has_light = entity.has_component::<Light>();
+    let Some(clip) = entity.get_component_mut::<AnimationClip>() else {
+        state.playing = false;
+        ui.weak(format!("{} has no animation.", entity.name));
+        if ui.add_enabled(!in_play_session, egui::Button::new("Add Animation Clip")).clicked() {
+            entity.add_component(AnimationClip::default());
+            state.time = 0.0;
+            state.selected_key = None;
+            action.clip_changed = true;
+        }
+        return action;
+    };
+
+    // While playing the game the clip runs from the animation system
+    if in_play_session {
+        state.playing = false;
+        state.time = clip.time;
+    }
+    let mut playhead_moved = false;
+    let mut new_key = None;
+
+    ui.add_enabled_ui(!in_play_session, |ui| {
+        ui.horizontal(|ui| {
+            if ui.button("⏮").on_hover_text("Go to start").clicked() {
+                state.time = 0.0;
+                playhead_moved = true;
+            }
+            let play_label = if state.playing { "⏸" } else { "▶" };
+            if ui.button(play_label).on_hover_text("Preview the animation").clicked() {
+                state.playing = !state.playing;
+            }
+            playhead_moved |= ui
+                .add(egui::DragValue::new(&mut state.time).range(0.0..=clip.duration).speed(0.01).suffix(" s"))
+                .changed();
+
+            ui.separator();
+            ui.label("Length");
+            let response = ui.add(egui::DragValue::new(&mut clip.duration).range(0.1..=600.0).speed(0.05).suffix(" s"));
+            action.clip_changed |= response.drag_stopped() || (response.changed() && !response.dragged());
+            action.clip_changed |= ui.checkbox(&mut clip.looping, "Loop").changed();
+            action.clip_changed |= ui
+                .checkbox(&mut clip.autoplay, "Autoplay")
+                .on_hover_text("Play when the game starts")
+                .changed();
+
+            ui.separator();
+            ui.menu_button("◆ Key", |ui| {
+                if ui.button("Transform").clicked() {
+                    new_key = Some(vec![AnimatedProperty::Position, AnimatedProperty::Rotation, AnimatedProperty::Scale]);
+                    ui.close();
+                }
+                ui.separator();
+                for property in AnimatedProperty::ALL {
+                    let enabled = property != AnimatedProperty::LightIntensity || has_light;
+                    if ui.add_enabled(enabled, egui::Button::new(property.label())).clicked() {
+                        new_key = Some(vec![property]);
+                        ui.close();
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Key the entity's current values at the playhead");
+            if ui
+                .add_enabled(state.selected_key.is_some(), egui::Button::new("Delete Key"))
+                .clicked()
+            {
+                if let Some((property, index)) = state.selected_key.take() {
+                    clip.remove_key(property, index);
+                    action.clip_changed = true;
+                }
+            }
+        });
+    });
+
+    // Preview playback
+    if state.playing {
+        let dt = ui.input(|i| i.stable_dt);
+        state.time += dt;
+        if state.time >= clip.duration {
+            if clip.looping {
+                state.time %= clip.duration.max(f32::EPSILON);
+            } else {
+                state.time = clip.duration;
+                state.playing = false;
+            }
+        }
+        playhead_moved = true;
+        ui.ctx().request_repaint();
+    }
+
+    ui.separator();
+
+    // Rows: every track in the clip plus empty rows for the other properties
+    let properties: Vec<AnimatedProperty> = AnimatedProperty::ALL
+        .into_iter()
+        .filter(|&p| p != AnimatedProperty::LightIntensity || has_light || clip.track(p).is_some())
+        .collect();
+
+    let width = ui.available_width().max(LABEL_WIDTH + 50.0);
+    let height = RULER_HEIGHT + ROW_HEIGHT * properties.len() as f32;
+    let (rect, _) = ui.allocate_exact_size(Vec2::new(width, height), Sense::hover());
+    let track_rect = Rect::from_min_max(Pos2::new(rect.left() + LABEL_WIDTH, rect.top()), rect.max);
+    let duration = clip.duration.max(f32::EPSILON);
+    let time_to_x = |t: f32| track_rect.left() + (t / duration).clamp(0.0, 1.0) * track_rect.width();
+    let x_to_time = |x: f32| ((x - track_rect.left()) / track_rect.width()).clamp(0.0, 1.0) * duration;
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals().clone();
+
+    // Ruler: click or drag to scrub
+    let ruler_rect = Rect::from_min_size(track_rect.min, Vec2::new(track_rect.width(), RULER_HEIGHT));
+    let ruler = ui.interact(ruler_rect, ui.id().with("timeline_ruler"), Sense::click_and_drag());
+    if !in_play_session && (ruler.clicked() || ruler.dragged()) {
+        if let Some(pos) = ruler.interact_pointer_pos() {
+            state.time = x_to_time(pos.x);
+            state.playing = false;
+            playhead_moved = true;
+        }
+    }
+    painter.rect_filled(ruler_rect, 0.0, visuals.faint_bg_color);
+    let step = ruler_step(duration, track_rect.width());
+    let mut tick = 0.0;
+    while tick <= duration + 1e-4 {
+        let x = time_to_x(tick);
+        painter.line_segment(
+            [Pos2::new(x, ruler_rect.bottom() - 5.0), Pos2::new(x, rect.bottom())],
+            Stroke::new(1.0, visuals.widgets.noninteractive.bg_stroke.color),
+        );
+        painter.text(
+            Pos2::new(x + 2.0, ruler_rect.top()),
+            egui::Align2::LEFT_TOP,
+            format!("{:.1}", tick),
+            egui::FontId::proportional(10.0),
+            visuals.weak_text_color(),
+        );
+        tick += step;
+    }
+
+    // Track rows with their keys
+    for (row, &property) in properties.iter().enumerate() {
+        let top = ruler_rect.bottom() + ROW_HEIGHT * row as f32;
+        let row_rect = Rect::from_min_max(Pos2::new(rect.left(), top), Pos2::new(rect.right(), top + ROW_HEIGHT));
+        if row % 2 == 1 {
+            painter.rect_filled(row_rect, 0.0, visuals.faint_bg_color);
+        }
+        painter.text(
+            Pos2::new(rect.left() + 4.0, row_rect.center().y),
+            egui::Align2::LEFT_CENTER,
+            property.label(),
+            egui::FontId::proportional(12.0),
+            visuals.text_color(),
+        );
+
+        let key_times: Vec<f32> = clip
+            .track(property)
+            .map(|track| track.keyframes.iter().map(|k| k.time).collect())
+            .unwrap_or_default();
+        for (index, key_time) in key_times.into_iter().enumerate() {
+            let selected = state.selected_key == Some((property, index));
+            let shown_time = if selected { state.drag_time.unwrap_or(key_time) } else { key_time };
+            let center = Pos2::new(time_to_x(shown_time), row_rect.center().y);
+            let key_rect = Rect::from_center_size(center, Vec2::splat(KEY_SIZE * 2.5));
+            let response = ui.interact(
+                key_rect,
+                ui.id().with(("timeline_key", property, index)),
+                Sense::click_and_drag(),
+            );
+            if !in_play_session {
+                if response.clicked() || response.drag_started() {
+                    state.selected_key = Some((property, index));
+                    state.time = key_time;
+                    playhead_moved = true;
+                }
+                if response.dragged() {
+                    if let Some(pos) = response.interact_pointer_pos() {
+                        state.drag_time = Some(x_to_time(pos.x));
+                    }
+                }
+                if response.drag_stopped() {
+                    if let (Some(time), Some(track)) = (state.drag_time.take(), clip.track_mut(property)) {
+                        let new_index = track.move_key(index, time);
+                        state.selected_key = Some((property, new_index));
+                        state.time = time;
+                        playhead_moved = true;
+                        action.clip_changed = true;
+                    }
+                }
+            }
+
+            let fill = if selected {
+                Color32::from_rgb(255, 200, 60)
+            } else if response.hovered() {
+                visuals.strong_text_color()
+            } else {
+                visuals.text_color()
+            };
+            let diamond = vec![
+                center + Vec2::new(0.0, -KEY_SIZE),
+                center + Vec2::new(KEY_SIZE, 0.0),
+                center + Vec2::new(0.0, KEY_SIZE),
+                center + Vec2::new(-KEY_SIZE, 0.0),
+            ];
+            painter.add(egui::Shape::convex_polygon(diamond, fill, Stroke::new(1.0, Color32::BLACK)));
+        }
+    }
+
+    // Playhead
+    let playhead_x = time_to_x(state.time);
+    painter.line_segment(
+        [Pos2::new(playhead_x, rect.top()), Pos2::new(playhead_x, rect.bottom())],
+        Stroke::new(1.5, Color32::from_rgb(230, 80, 80)),
+    );
+
+    // Key the current values after the rows so the new key shows next frame
+    if let Some(properties) = new_key {
+        let time = state.time;
+        for property in properties {
+            if let Some(value) = animation::current_value(scene, entity_id, property) {
+                if let Some(clip) = scene
+                    .get_entity_mut(entity_id)
+                    .and_then(|e| e.get_component_mut::<AnimationClip>())
+                {
+                    let index = clip.set_key(property, time, value);
+                    state.selected_key = Some((property, index));
+                    action.clip_changed = true;
+                }
+            }
+        }
+    }
+
+    // Scrubbing previews the pose on the entity, as an undoable edit
+    if playhead_moved && !in_play_session {
+        animation::apply_clip_at(scene, entity_id, state.time);
+        action.pose_changed = true;
+    }
+    action.previewing = state.playing;
+
+    action
+}
+
+/// Seconds between ruler ticks, keeping labels at least 40 px apart
+fn ruler_step(duration: f32, width: f32) -> f32 {
+    let min_step = duration * 40.0 / width.max(1.0);
+    [0.1, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 30.0, 60.0]
+        .into_iter()
+        .find(|&step| step >= min_step)
+        .unwrap_or(120.0)
+}
@@ -3,6 +3,63 @@
 use engine_render::camera::Camera;
 use glam::Vec3;
 use winit::event::{ElementState, MouseButton, MouseScrollDelta};
+use winit::keyboard::KeyCode;
+
+/// Number of camera bookmark slots (bound to keys 1-9)
+pub const BOOKMARK_SLOTS: usize = 9;
+
+/// Saved orbit camera view
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraBookmark {
+    pub orbit_distance: f32,
+    pub orbit_pitch: f32,
+    pub orbit_yaw: f32,
+    pub pan_offset: Vec3,
+    pub orthographic: bool,
+}
+
+/// Axis-aligned orthographic view presets (numpad 1/3/7)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewPreset {
+    Front,
+    Right,
+    Top,
+}
+
+impl ViewPreset {
+    /// Orbit (yaw, pitch) for this view
+    pub fn orientation(&self) -> (f32, f32) {
+        match self {
+            ViewPreset::Front => (0.0, 0.0),
+            ViewPreset::Right => (std::f32::consts::FRAC_PI_2, 0.0),
+            ViewPreset::Top => (0.0, std::f32::consts::FRAC_PI_2),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ViewPreset::Front => "Front",
+            ViewPreset::Right => "Right",
+            ViewPreset::Top => "Top",
+        }
+    }
+}
+
+/// Map number keys 1-9 to bookmark slots 0-8
+pub fn bookmark_slot(key_code: KeyCode) -> Option<usize> {
+    match key_code {
+        KeyCode::Digit1 => Some(0),
+        KeyCode::Digit2 => Some(1),
+        KeyCode::Digit3 => Some(2),
+        KeyCode::Digit4 => Some(3),
+        KeyCode::Digit5 => Some(4),
+        KeyCode::Digit6 => Some(5),
+        KeyCode::Digit7 => Some(6),
+        KeyCode::Digit8 => Some(7),
+        KeyCode::Digit9 => Some(8),
+        _ => None,
+    }
+}
 
 pub struct ViewportControls {
     pub orbit_active: bool,
@@ -17,8 +74,21 @@ pub struct ViewportControls {
     pub pan_offset: Vec3,
     /// Last position where terrain was modified (to throttle updates)
     pub last_terrain_sculpt_pos: Option<(f32, f32)>,
+    /// Position where the left mouse button was pressed (for box selection)
+    pub drag_start: Option<(f32, f32)>,
+    /// Completed left-drag rectangle (start, end), set on release
+    pub completed_drag: Option<((f32, f32), (f32, f32))>,
+    /// Orthographic projection (numpad 5 / view presets)
+    pub orthographic: bool,
+    /// Saved camera views (Ctrl+1..9 to save, 1..9 to restore)
+    pub bookmarks: [Option<CameraBookmark>; BOOKMARK_SLOTS],
+    /// Pan/zoom speed multiplier (editor preference)
+    pub camera_speed: f32,
 }
 
+/// Minimum drag distance (pixels) before a left-drag becomes a box selection
+const BOX_SELECT_THRESHOLD: f32 = 4.0;
+
 impl ViewportControls {
     pub fn new() -> Self {
         Self {
@@ -33,6 +103,11 @@ impl ViewportControls {
             orbit_yaw: 45.0_f32.to_radians(),
             pan_offset: Vec3::ZERO,
             last_terrain_sculpt_pos: None,
+            drag_start: None,
+            completed_drag: None,
+            orthographic: false,
+            bookmarks: [None; BOOKMARK_SLOTS],
+            camera_speed: 1.0,
         }
     }
 
@@ -46,6 +121,10 @@ impl ViewportControls {
                 // Clear last sculpt position when button is released
                 if state == ElementState::Released {
                     self.last_terrain_sculpt_pos = None;
+                    self.completed_drag = self.drag_rect();
+                    self.drag_start = None;
+                } else if !was_held {
+                    self.drag_start = Some(self.current_mouse_pos);
                 }
             }
             MouseButton::Right => {
@@ -64,6 +143,18 @@ impl ViewportControls {
         }
     }
 
+    /// Current left-drag rectangle (start, current), once past the drag threshold
+    pub fn drag_rect(&self) -> Option<((f32, f32), (f32, f32))> {
+        let start = self.drag_start?;
+        let end = self.current_mouse_pos;
+        let moved = (end.0 - start.0).abs().max((end.1 - start.1).abs());
+        if moved >= BOX_SELECT_THRESHOLD {
+            Some((start, end))
+        } else {
+            None
+        }
+    }
+
     pub fn handle_mouse_motion(&mut self, x: f32, y: f32) {
         self.current_mouse_pos = (x, y);
         if let Some((last_x, last_y)) = self.last_mouse_pos {
@@ -77,7 +168,7 @@ impl ViewportControls {
                 self.orbit_pitch = self.orbit_pitch.clamp(-1.5, 1.5);
             } else if self.pan_active {
                 // Pan camera
-                let pan_speed = 0.01;
+                let pan_speed = 0.01 * self.camera_speed;
                 self.pan_offset.x -= delta_x * pan_speed;
                 self.pan_offset.y += delta_y * pan_speed;
             }
@@ -91,7 +182,7 @@ impl ViewportControls {
             MouseScrollDelta::PixelDelta(pos) => pos.y as f32 * 0.01,
         };
 
-        self.orbit_distance -= zoom_amount;
+        self.orbit_distance -= zoom_amount * self.camera_speed;
         self.orbit_distance = self.orbit_distance.clamp(1.0, 100.0);
     }
 
@@ -103,6 +194,80 @@ impl ViewportControls {
 
         camera.position = Vec3::new(x, y, z) + self.pan_offset;
         camera.target = self.pan_offset;
+        // Orbit-relative up vector stays valid when looking straight down (top view)
+        camera.up = Vec3::new(
+            -self.orbit_pitch.sin() * self.orbit_yaw.sin(),
+            self.orbit_pitch.cos(),
+            -self.orbit_pitch.sin() * self.orbit_yaw.cos(),
+        );
+
+        // Orthographic view covers the same area the perspective view shows at the target
+        camera.orthographic = self.orthographic;
+        camera.ortho_size = self.orbit_distance * (camera.fov * 0.5).tan();
+    }
+
+    /// Orbit around `target`, viewed from `position`
+    pub fn look_at(&mut self, position: Vec3, target: Vec3) {
+        let offset = position - target;
+        let distance = offset.length();
+        if distance > f32::EPSILON {
+            self.orbit_yaw = offset.x.atan2(offset.z);
+            self.orbit_pitch = (offset.y / distance).asin().clamp(-1.5, 1.5);
+        }
+        self.orbit_distance = distance.max(1.0);
+        self.pan_offset = target;
+    }
+
+    /// Orbit around `target` at `distance`, keeping the view direction
+    pub fn orbit_around(&mut self, target: Vec3, distance: f32) {
+        self.orbit_distance = distance.max(1.0);
+        self.pan_offset = target;
+    }
+
+    /// Capture the current view as a bookmark
+    pub fn bookmark(&self) -> CameraBookmark {
+        CameraBookmark {
+            orbit_distance: self.orbit_distance,
+            orbit_pitch: self.orbit_pitch,
+            orbit_yaw: self.orbit_yaw,
+            pan_offset: self.pan_offset,
+            orthographic: self.orthographic,
+        }
+    }
+
+    /// Jump to a bookmarked view
+    pub fn apply_bookmark(&mut self, bookmark: &CameraBookmark) {
+        self.orbit_distance = bookmark.orbit_distance;
+        self.orbit_pitch = bookmark.orbit_pitch;
+        self.orbit_yaw = bookmark.orbit_yaw;
+        self.pan_offset = bookmark.pan_offset;
+        self.orthographic = bookmark.orthographic;
+    }
+
+    /// Save the current view into a bookmark slot
+    pub fn save_bookmark(&mut self, slot: usize) {
+        if slot < BOOKMARK_SLOTS {
+            self.bookmarks[slot] = Some(self.bookmark());
+        }
+    }
+
+    /// Restore the view from a bookmark slot. Returns false if the slot is empty.
+    pub fn restore_bookmark(&mut self, slot: usize) -> bool {
+        match self.bookmarks.get(slot).copied().flatten() {
+            Some(bookmark) => {
+                self.apply_bookmark(&bookmark);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Switch to an axis-aligned orthographic view around the current target
+    pub fn set_view_preset(&mut self, preset: ViewPreset) {
+        let (yaw, pitch) = preset.orientation();
+        self.orbit_yaw = yaw;
+        self.orbit_pitch = pitch;
+        self.orthographic = true;
     }
 }
 
@@ -111,3 +276,54 @@ impl Default for ViewportControls {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bookmark_roundtrip() {
+        let mut controls = ViewportControls::new();
+        controls.orbit_distance = 42.0;
+        controls.pan_offset = Vec3::new(1.0, 2.0, 3.0);
+        controls.save_bookmark(2);
+
+        controls.orbit_distance = 5.0;
+        controls.pan_offset = Vec3::ZERO;
+        assert!(!controls.restore_bookmark(0));
+        assert!(controls.restore_bookmark(2));
+        assert_eq!(controls.orbit_distance, 42.0);
+        assert_eq!(controls.pan_offset, Vec3::new(1.0, 2.0, 3.0));
+        assert!(!controls.restore_bookmark(BOOKMARK_SLOTS));
+    }
+
+    #[test]
+    fn test_top_view_is_not_degenerate() {
+        let mut controls = ViewportControls::new();
+        controls.set_view_preset(ViewPreset::Top);
+        let mut camera = Camera::new(800, 600);
+        controls.update_camera(&mut camera);
+
+        assert!(camera.orthographic);
+        let forward = (camera.target - camera.position).normalize();
+        assert!((forward - Vec3::NEG_Y).length() < 1e-5);
+        assert!(camera.up.dot(forward).abs() < 1e-5);
+        assert!(camera.view_matrix().is_finite());
+    }
+
+    #[test]
+    fn test_look_at_places_camera() {
+        let mut controls = ViewportControls::new();
+        let position = Vec3::new(20.0, 15.0, -10.0);
+        let target = Vec3::new(5.0, 0.0, 5.0);
+        controls.look_at(position, target);
+
+        let mut camera = Camera::new(800, 600);
+        controls.update_camera(&mut camera);
+        assert!((camera.position - position).length() < 1e-3);
+        assert_eq!(camera.target, target);
+
+        controls.orbit_around(Vec3::ZERO, 0.2);
+        assert_eq!(controls.orbit_distance, 1.0);
+    }
+}
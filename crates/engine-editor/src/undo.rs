@@ -1,18 +1,107 @@
 // Undo/Redo history system for the editor
 //
-// Uses a snapshot-based approach: stores serialized scene states in stacks.
-// Simple and robust - works for all operations automatically.
+// Command-based: every undo step stores only what its operation touched.
+// Transform edits keep before/after transforms, entity edits keep copies of
+// the affected entities (plus the root order), and terrain brush strokes keep
+// the heightmap/splatmap tiles they painted over.
+//
+// Edits are recorded *before* the scene is modified (`record_*`); the state
+// after the edit is captured when the step is committed, which happens at the
+// end of the frame or when the next edit starts. Drags call `continue_edit`
+// each frame so the whole drag stays one undo step.
+
+use std::collections::HashSet;
+
+use engine_assets::{HeightMap, SplatMap};
+use engine_scene::{entity::Entity, entity::EntityId, scene::Scene, transform::Transform};
+
+/// Side length (in grid points) of the terrain tiles saved by brush strokes
+const TERRAIN_TILE: usize = 32;
+
+/// What an undo or redo step changed, so the caller can refresh it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UndoTarget {
+    Scene,
+    Terrain,
+}
+
+/// Mutable terrain data undo steps can restore
+pub type TerrainMut<'a> = Option<(&'a mut HeightMap, Option<&'a mut SplatMap>)>;
+
+/// A point in the history to return to (see `checkpoint`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint(u64);
 
-use engine_scene::{scene::Scene, scene_data::SerializedScene};
+/// One undo step
+enum UndoCommand {
+    /// Transforms of existing entities: (entity, before, after)
+    Transforms(Vec<(EntityId, Transform, Transform)>),
+    /// Entities added, removed or edited
+    Entities(EntityDelta),
+    /// Terrain tiles painted by a brush stroke
+    Terrain(Vec<TerrainTile>),
+    /// Steps undone and redone together (an MCP terrain command's entity
+    /// and terrain edits)
+    Group(Vec<UndoCommand>),
+}
+
+/// Entity states before and after an edit; None where the entity didn't exist
+struct EntityDelta {
+    before: Vec<(EntityId, Option<Entity>)>,
+    after: Vec<(EntityId, Option<Entity>)>,
+    roots_before: Vec<EntityId>,
+    roots_after: Vec<EntityId>,
+}
+
+/// Edit recorded this frame, waiting for its after state
+enum PendingEdit {
+    Transforms(Vec<(EntityId, Transform)>),
+    Entities {
+        before: Vec<(EntityId, Option<Entity>)>,
+        roots_before: Vec<EntityId>,
+        /// Entities created by the edit get IDs from here on
+        first_new_id: EntityId,
+    },
+    /// The last committed step continues (drags); its after state is refreshed
+    Continue,
+}
+
+/// Heights, holes and splat weights of one tile
+#[derive(Clone, PartialEq)]
+struct TileData {
+    heights: Vec<f32>,
+    /// Empty while the heightmap has no holes
+    holes: Vec<bool>,
+    weights: Vec<f32>,
+}
 
-/// Manages undo/redo history using scene snapshots
+struct TerrainTile {
+    tile: (usize, usize),
+    before: TileData,
+    after: TileData,
+}
+
+/// Brush stroke in progress: the tiles it touched, as they were before
+#[derive(Default)]
+struct TerrainStroke {
+    tiles: Vec<((usize, usize), TileData)>,
+    saved: HashSet<(usize, usize)>,
+}
+
+/// Manages undo/redo history as a stack of commands
 pub struct UndoHistory {
-    /// Stack of previous states (for undo)
-    undo_stack: Vec<SerializedScene>,
-    /// Stack of future states (for redo, populated after undo)
-    redo_stack: Vec<SerializedScene>,
-    /// Maximum number of snapshots to keep
+    /// Steps that can be undone, oldest first, with their serial numbers
+    undo_stack: Vec<(u64, UndoCommand)>,
+    /// Undone steps that can be redone
+    redo_stack: Vec<(u64, UndoCommand)>,
+    pending: Option<PendingEdit>,
+    stroke: Option<TerrainStroke>,
+    /// Maximum number of steps to keep
     max_history: usize,
+    /// Serial of the next step pushed
+    next_serial: u64,
+    /// Serial standing for the state below the oldest undo step
+    base_serial: u64,
 }
 
 impl UndoHistory {
@@ -21,56 +110,239 @@ impl UndoHistory {
         Self {
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
+            pending: None,
+            stroke: None,
             max_history,
+            next_serial: 1,
+            base_serial: 0,
         }
     }
 
-    /// Save the current scene state before making a change.
+    /// Record a transform edit of `ids` (moves, rotations, scales).
+    /// Call this BEFORE modifying the transforms.
+    pub fn record_transforms(&mut self, scene: &Scene, ids: &[EntityId]) {
+        self.commit(scene);
+        let before = ids
+            .iter()
+            .filter_map(|&id| scene.get_entity(id).map(|e| (id, e.transform)))
+            .collect();
+        self.pending = Some(PendingEdit::Transforms(before));
+    }
+
+    /// Record an edit of `ids`: component changes, renames, deletion,
+    /// reparenting or duplication. Descendants and parents of `ids` are saved
+    /// too, and entities the edit creates are picked up automatically (pass
+    /// the parent a new entity goes under).
     /// Call this BEFORE modifying the scene.
-    pub fn push_state(&mut self, scene: &Scene) {
-        let snapshot = scene.to_serialized();
-        self.undo_stack.push(snapshot);
+    pub fn record_entities(&mut self, scene: &Scene, ids: &[EntityId]) {
+        self.commit(scene);
+        let before = affected_entities(scene, ids)
+            .into_iter()
+            .map(|id| (id, scene.get_entity(id).cloned()))
+            .collect();
+        self.pending = Some(PendingEdit::Entities {
+            before,
+            roots_before: scene.root_entities().to_vec(),
+            first_new_id: scene.next_entity_id(),
+        });
+    }
 
-        // New action clears redo stack
-        self.redo_stack.clear();
+    /// Record an edit that already happened to a single entity, given its
+    /// state before the edit (inspector and timeline panels edit in place)
+    pub fn record_entity_edit(&mut self, scene: &Scene, before: Entity) {
+        self.commit(scene);
+        let id = before.id;
+        let after = scene.get_entity(id).cloned();
+        let roots = scene.root_entities().to_vec();
+        self.push(UndoCommand::Entities(EntityDelta {
+            before: vec![(id, Some(before))],
+            after: vec![(id, after)],
+            roots_before: roots.clone(),
+            roots_after: roots,
+        }));
+    }
 
-        // Limit history size to prevent unbounded memory growth
-        if self.undo_stack.len() > self.max_history {
-            self.undo_stack.remove(0);
+    /// Drop the recorded edit without pushing a step (the edit was abandoned
+    /// and the scene is as it was when it was recorded)
+    pub fn cancel(&mut self) {
+        self.pending = None;
+    }
+
+    /// Keep the last step open: its after state is captured again at the next
+    /// commit. Used by drags that record once and then edit over many frames.
+    pub fn continue_edit(&mut self) {
+        if self.pending.is_none() && !self.undo_stack.is_empty() {
+            self.pending = Some(PendingEdit::Continue);
         }
     }
 
-    /// Undo the last change: restore previous state, save current to redo stack.
-    /// Returns true if undo was performed.
-    pub fn undo(&mut self, scene: &mut Scene) -> bool {
-        if let Some(prev_state) = self.undo_stack.pop() {
-            // Save current state to redo stack before restoring
-            self.redo_stack.push(scene.to_serialized());
-            // Restore previous state
-            *scene = Scene::from_serialized(prev_state);
-            true
-        } else {
-            false
+    /// Capture the after state of the recorded edit and push it as an undo step
+    pub fn commit(&mut self, scene: &Scene) {
+        match self.pending.take() {
+            None => {}
+            Some(PendingEdit::Transforms(before)) => {
+                let changes: Vec<_> = before
+                    .into_iter()
+                    .filter_map(|(id, before)| {
+                        let after = scene.get_entity(id)?.transform;
+                        Some((id, before, after))
+                    })
+                    .collect();
+                if !changes.is_empty() {
+                    self.push(UndoCommand::Transforms(changes));
+                }
+            }
+            Some(PendingEdit::Entities {
+                before,
+                roots_before,
+                first_new_id,
+            }) => {
+                let mut ids: Vec<EntityId> = before.iter().map(|(id, _)| *id).collect();
+                let mut before = before;
+                for entity in scene.entities().filter(|e| e.id.0 >= first_new_id.0) {
+                    ids.push(entity.id);
+                    before.push((entity.id, None));
+                }
+                let after = ids
+                    .into_iter()
+                    .map(|id| (id, scene.get_entity(id).cloned()))
+                    .collect();
+                self.push(UndoCommand::Entities(EntityDelta {
+                    before,
+                    after,
+                    roots_before,
+                    roots_after: scene.root_entities().to_vec(),
+                }));
+            }
+            Some(PendingEdit::Continue) => match self.undo_stack.last_mut().map(|(_, command)| command) {
+                Some(UndoCommand::Transforms(changes)) => {
+                    for (id, _, after) in changes.iter_mut() {
+                        if let Some(entity) = scene.get_entity(*id) {
+                            *after = entity.transform;
+                        }
+                    }
+                }
+                Some(UndoCommand::Entities(delta)) => {
+                    for (id, after) in delta.after.iter_mut() {
+                        *after = scene.get_entity(*id).cloned();
+                    }
+                    delta.roots_after = scene.root_entities().to_vec();
+                }
+                Some(UndoCommand::Terrain(_) | UndoCommand::Group(_)) | None => {}
+            },
+        }
+    }
+
+    /// Start a terrain brush stroke (sculpting or texture painting)
+    pub fn begin_terrain_stroke(&mut self, scene: &Scene) {
+        self.commit(scene);
+        self.stroke = Some(TerrainStroke::default());
+    }
+
+    /// Save the tiles a brush application is about to modify. `bounds` is the
+    /// inclusive grid rectangle (min_x, min_z, max_x, max_z).
+    pub fn track_terrain_region(
+        &mut self,
+        heightmap: &HeightMap,
+        splatmap: Option<&SplatMap>,
+        bounds: (usize, usize, usize, usize),
+    ) {
+        let Some(stroke) = self.stroke.as_mut() else {
+            return;
+        };
+        let (min_x, min_z, max_x, max_z) = bounds;
+        for tz in min_z / TERRAIN_TILE..=max_z / TERRAIN_TILE {
+            for tx in min_x / TERRAIN_TILE..=max_x / TERRAIN_TILE {
+                if stroke.saved.insert((tx, tz)) {
+                    stroke
+                        .tiles
+                        .push(((tx, tz), read_tile(heightmap, splatmap, (tx, tz))));
+                }
+            }
+        }
+    }
+
+    /// Finish the brush stroke and push it as an undo step
+    pub fn end_terrain_stroke(&mut self, heightmap: &HeightMap, splatmap: Option<&SplatMap>) {
+        let Some(stroke) = self.stroke.take() else {
+            return;
+        };
+        let tiles: Vec<TerrainTile> = stroke
+            .tiles
+            .into_iter()
+            .map(|(tile, before)| TerrainTile {
+                tile,
+                after: read_tile(heightmap, splatmap, tile),
+                before,
+            })
+            .filter(|tile| tile.before != tile.after)
+            .collect();
+        if !tiles.is_empty() {
+            self.push(UndoCommand::Terrain(tiles));
         }
     }
 
-    /// Redo the last undone change: restore next state, save current to undo stack.
-    /// Returns true if redo was performed.
-    pub fn redo(&mut self, scene: &mut Scene) -> bool {
-        if let Some(next_state) = self.redo_stack.pop() {
-            // Save current state to undo stack before restoring
-            self.undo_stack.push(scene.to_serialized());
-            // Restore next state
-            *scene = Scene::from_serialized(next_state);
+    /// Undo the last step. Returns what changed, or None if there was nothing to undo.
+    pub fn undo(&mut self, scene: &mut Scene, terrain: TerrainMut) -> Option<UndoTarget> {
+        self.commit(scene);
+        let (serial, command) = self.undo_stack.pop()?;
+        let target = command.apply(scene, terrain, false);
+        self.redo_stack.push((serial, command));
+        Some(target)
+    }
+
+    /// Redo the last undone step. Returns what changed, or None if there was nothing to redo.
+    pub fn redo(&mut self, scene: &mut Scene, terrain: TerrainMut) -> Option<UndoTarget> {
+        self.commit(scene);
+        let (serial, command) = self.redo_stack.pop()?;
+        let target = command.apply(scene, terrain, true);
+        self.undo_stack.push((serial, command));
+        Some(target)
+    }
+
+    /// The current point in the history, for `restore_checkpoint`
+    pub fn checkpoint(&mut self, scene: &Scene) -> Checkpoint {
+        self.commit(scene);
+        Checkpoint(self.undo_stack.last().map_or(self.base_serial, |(serial, _)| *serial))
+    }
+
+    /// Undo or redo until the history is back at `checkpoint`. Returns what
+    /// each step changed, or None if the checkpoint is no longer reachable
+    /// (an edit made after undoing past it, trimmed history, or a cleared one).
+    pub fn restore_checkpoint(
+        &mut self,
+        checkpoint: Checkpoint,
+        scene: &mut Scene,
+        mut terrain: TerrainMut,
+    ) -> Option<Vec<UndoTarget>> {
+        self.commit(scene);
+        let Checkpoint(serial) = checkpoint;
+        let redo = if serial == self.base_serial || self.undo_stack.iter().any(|(s, _)| *s == serial) {
+            false
+        } else if self.redo_stack.iter().any(|(s, _)| *s == serial) {
             true
         } else {
-            false
+            return None;
+        };
+
+        let mut targets = Vec::new();
+        while self.checkpoint(scene) != checkpoint {
+            let terrain = terrain
+                .as_mut()
+                .map(|(heightmap, splatmap)| (&mut **heightmap, splatmap.as_deref_mut()));
+            let target = if redo {
+                self.redo(scene, terrain)
+            } else {
+                self.undo(scene, terrain)
+            };
+            targets.push(target?);
         }
+        Some(targets)
     }
 
     /// Check if undo is available
     pub fn can_undo(&self) -> bool {
-        !self.undo_stack.is_empty()
+        !self.undo_stack.is_empty() || self.pending.is_some()
     }
 
     /// Check if redo is available
@@ -92,6 +364,53 @@ impl UndoHistory {
     pub fn clear(&mut self) {
         self.undo_stack.clear();
         self.redo_stack.clear();
+        self.pending = None;
+        self.stroke = None;
+        // Checkpoints from before are unreachable
+        self.base_serial = self.next_serial;
+        self.next_serial += 1;
+    }
+
+    /// Drop the terrain steps, keeping scene edits (e.g., when the terrain
+    /// is replaced by one of another size its tiles no longer fit)
+    pub fn forget_terrain(&mut self) {
+        let is_scene = |(_, command): &(u64, UndoCommand)| !command.touches_terrain();
+        self.undo_stack.retain(is_scene);
+        self.redo_stack.retain(is_scene);
+        self.stroke = None;
+    }
+
+    /// Join the steps pushed since `checkpoint` into one undo step
+    pub fn merge_since(&mut self, scene: &Scene, checkpoint: Checkpoint) {
+        self.commit(scene);
+        let Checkpoint(serial) = checkpoint;
+        let start = self
+            .undo_stack
+            .iter()
+            .position(|(s, _)| *s > serial)
+            .unwrap_or(self.undo_stack.len());
+        if self.undo_stack.len() - start < 2 {
+            return;
+        }
+        let steps = self.undo_stack.split_off(start);
+        // The group keeps the last serial, so a checkpoint after it still matches
+        let serial = steps.last().map_or(serial, |(s, _)| *s);
+        let commands = steps.into_iter().map(|(_, command)| command).collect();
+        self.undo_stack.push((serial, UndoCommand::Group(commands)));
+    }
+
+    fn push(&mut self, command: UndoCommand) {
+        self.undo_stack.push((self.next_serial, command));
+        self.next_serial += 1;
+
+        // New action clears redo stack
+        self.redo_stack.clear();
+
+        // Limit history size to prevent unbounded memory growth
+        if self.undo_stack.len() > self.max_history {
+            let (serial, _) = self.undo_stack.remove(0);
+            self.base_serial = serial;
+        }
     }
 }
 
@@ -100,3 +419,347 @@ impl Default for UndoHistory {
         Self::new(50)
     }
 }
+
+impl UndoCommand {
+    /// Restore the after state (redo) or the before state (undo)
+    fn apply(&self, scene: &mut Scene, terrain: TerrainMut, redo: bool) -> UndoTarget {
+        match self {
+            UndoCommand::Transforms(changes) => {
+                for (id, before, after) in changes {
+                    if let Some(entity) = scene.get_entity_mut(*id) {
+                        entity.transform = if redo { *after } else { *before };
+                    }
+                }
+                UndoTarget::Scene
+            }
+            UndoCommand::Entities(delta) => {
+                let (states, roots) = if redo {
+                    (&delta.after, &delta.roots_after)
+                } else {
+                    (&delta.before, &delta.roots_before)
+                };
+                for (id, state) in states {
+                    scene.take_entity(*id);
+                    if let Some(entity) = state {
+                        scene.insert_entity(entity.clone());
+                    }
+                }
+                scene.set_root_entities(roots.clone());
+                UndoTarget::Scene
+            }
+            UndoCommand::Terrain(tiles) => {
+                if let Some((heightmap, mut splatmap)) = terrain {
+                    for tile in tiles {
+                        let data = if redo { &tile.after } else { &tile.before };
+                        write_tile(heightmap, splatmap.as_deref_mut(), tile.tile, data);
+                    }
+                }
+                UndoTarget::Terrain
+            }
+            UndoCommand::Group(commands) => {
+                let mut terrain = terrain;
+                let ordered: Vec<&UndoCommand> = if redo {
+                    commands.iter().collect()
+                } else {
+                    commands.iter().rev().collect()
+                };
+                let mut target = UndoTarget::Scene;
+                for command in ordered {
+                    let terrain = terrain
+                        .as_mut()
+                        .map(|(heightmap, splatmap)| (&mut **heightmap, splatmap.as_deref_mut()));
+                    if command.apply(scene, terrain, redo) == UndoTarget::Terrain {
+                        target = UndoTarget::Terrain;
+                    }
+                }
+                target
+            }
+        }
+    }
+
+    fn touches_terrain(&self) -> bool {
+        match self {
+            UndoCommand::Terrain(_) => true,
+            UndoCommand::Group(commands) => commands.iter().any(UndoCommand::touches_terrain),
+            _ => false,
+        }
+    }
+}
+
+/// `ids` with their descendants and parents
+fn affected_entities(scene: &Scene, ids: &[EntityId]) -> Vec<EntityId> {
+    let mut affected = Vec::new();
+    let mut seen = HashSet::new();
+    let mut stack: Vec<EntityId> = ids.to_vec();
+    while let Some(id) = stack.pop() {
+        if !seen.insert(id) {
+            continue;
+        }
+        affected.push(id);
+        if let Some(entity) = scene.get_entity(id) {
+            stack.extend(entity.children.iter().copied());
+            if let Some(parent) = entity.parent.filter(|p| !seen.contains(p)) {
+                // Parents are saved for their children lists, not their subtrees
+                seen.insert(parent);
+                affected.push(parent);
+            }
+        }
+    }
+    affected
+}
+
+/// Grid rectangle of a tile, clamped to the map: (x0, z0, x1, z1) exclusive
+fn tile_rect(width: usize, depth: usize, (tx, tz): (usize, usize)) -> (usize, usize, usize, usize) {
+    let x0 = (tx * TERRAIN_TILE).min(width);
+    let z0 = (tz * TERRAIN_TILE).min(depth);
+    (
+        x0,
+        z0,
+        (x0 + TERRAIN_TILE).min(width),
+        (z0 + TERRAIN_TILE).min(depth),
+    )
+}
+
+/// The splatmap if it covers the same grid as the heightmap
+fn matching_splatmap<'a>(
+    heightmap: &HeightMap,
+    splatmap: Option<&'a SplatMap>,
+) -> Option<&'a SplatMap> {
+    splatmap.filter(|s| s.width == heightmap.width && s.depth == heightmap.depth)
+}
+
+fn read_tile(heightmap: &HeightMap, splatmap: Option<&SplatMap>, tile: (usize, usize)) -> TileData {
+    let (x0, z0, x1, z1) = tile_rect(heightmap.width, heightmap.depth, tile);
+    let splatmap = matching_splatmap(heightmap, splatmap);
+    let mut data = TileData {
+        heights: Vec::with_capacity((x1 - x0) * (z1 - z0)),
+        holes: Vec::new(),
+        weights: Vec::new(),
+    };
+    for z in z0..z1 {
+        let row = z * heightmap.width;
+        data.heights
+            .extend_from_slice(&heightmap.heights[row + x0..row + x1]);
+        if heightmap.holes.len() == heightmap.heights.len() {
+            data.holes
+                .extend_from_slice(&heightmap.holes[row + x0..row + x1]);
+        }
+        if let Some(splatmap) = splatmap {
+            let layers = splatmap.layer_count;
+            data.weights
+                .extend_from_slice(&splatmap.weights[(row + x0) * layers..(row + x1) * layers]);
+        }
+    }
+    data
+}
+
+fn write_tile(
+    heightmap: &mut HeightMap,
+    splatmap: Option<&mut SplatMap>,
+    tile: (usize, usize),
+    data: &TileData,
+) {
+    let (x0, z0, x1, z1) = tile_rect(heightmap.width, heightmap.depth, tile);
+    let tile_width = x1 - x0;
+    if data.heights.len() != tile_width * (z1 - z0) {
+        return;
+    }
+    for (i, z) in (z0..z1).enumerate() {
+        let row = z * heightmap.width;
+        heightmap.heights[row + x0..row + x1]
+            .copy_from_slice(&data.heights[i * tile_width..(i + 1) * tile_width]);
+        for x in x0..x1 {
+            let hole = data
+                .holes
+                .get(i * tile_width + x - x0)
+                .copied()
+                .unwrap_or(false);
+            heightmap.set_hole(x, z, hole);
+        }
+    }
+    let Some(splatmap) =
+        splatmap.filter(|s| s.width == heightmap.width && s.depth == heightmap.depth)
+    else {
+        return;
+    };
+    let layers = splatmap.layer_count;
+    if data.weights.len() != tile_width * (z1 - z0) * layers {
+        return;
+    }
+    let row_len = tile_width * layers;
+    for (i, z) in (z0..z1).enumerate() {
+        let start = (z * heightmap.width + x0) * layers;
+        splatmap.weights[start..start + row_len]
+            .copy_from_slice(&data.weights[i * row_len..(i + 1) * row_len]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_scene::components::MeshRenderer;
+    use glam::Vec3;
+
+    #[test]
+    fn test_drag_is_one_step() {
+        let mut scene = Scene::new("Test".to_string());
+        let id = scene.create_entity("Cube".to_string());
+        let mut history = UndoHistory::default();
+
+        history.record_transforms(&scene, &[id]);
+        for x in 1..=5 {
+            history.continue_edit();
+            scene.get_entity_mut(id).unwrap().transform.position.x = x as f32;
+            history.commit(&scene);
+        }
+        assert_eq!(history.undo_count(), 1);
+
+        history.undo(&mut scene, None);
+        assert_eq!(scene.get_entity(id).unwrap().transform.position, Vec3::ZERO);
+        history.redo(&mut scene, None);
+        assert_eq!(scene.get_entity(id).unwrap().transform.position.x, 5.0);
+    }
+
+    #[test]
+    fn test_undo_create_and_delete() {
+        let mut scene = Scene::new("Test".to_string());
+        let parent = scene.create_entity("Parent".to_string());
+        let mut history = UndoHistory::default();
+
+        // Create a child under the parent
+        history.record_entities(&scene, &[parent]);
+        let child = scene.create_entity("Child".to_string());
+        scene.set_parent(child, Some(parent));
+        scene
+            .get_entity_mut(child)
+            .unwrap()
+            .add_component(MeshRenderer::new("stone_cube".to_string()));
+
+        // Delete the parent (and with it the child)
+        history.record_entities(&scene, &[parent]);
+        scene.remove_entity(parent);
+        history.commit(&scene);
+        assert_eq!(scene.entity_count(), 0);
+
+        history.undo(&mut scene, None);
+        assert_eq!(scene.get_entity(parent).unwrap().children, vec![child]);
+        assert!(scene
+            .get_entity(child)
+            .unwrap()
+            .has_component::<MeshRenderer>());
+        assert_eq!(scene.root_entities(), &[parent]);
+
+        history.undo(&mut scene, None);
+        assert!(scene.get_entity(child).is_none());
+        assert!(scene.get_entity(parent).unwrap().children.is_empty());
+
+        history.redo(&mut scene, None);
+        assert_eq!(scene.get_entity(child).unwrap().parent, Some(parent));
+    }
+
+    #[test]
+    fn test_restore_checkpoint() {
+        let mut scene = Scene::new("Test".to_string());
+        let mut history = UndoHistory::default();
+        let start = history.checkpoint(&scene);
+
+        let mut ids = Vec::new();
+        for name in ["A", "B", "C"] {
+            history.record_entities(&scene, &[]);
+            ids.push(scene.create_entity(name.to_string()));
+            history.commit(&scene);
+        }
+        let after_a = {
+            history.undo(&mut scene, None);
+            history.undo(&mut scene, None);
+            let checkpoint = history.checkpoint(&scene);
+            history.redo(&mut scene, None);
+            checkpoint
+        };
+
+        // Back past several steps, then forward again through redo
+        assert_eq!(history.restore_checkpoint(start, &mut scene, None).map(|t| t.len()), Some(2));
+        assert_eq!(scene.entity_count(), 0);
+        assert!(history.restore_checkpoint(after_a, &mut scene, None).is_some());
+        assert_eq!(scene.entity_count(), 1);
+        assert!(scene.get_entity(ids[0]).is_some());
+
+        // A new edit drops the redo steps, and with them checkpoints ahead of it
+        let ahead = {
+            history.redo(&mut scene, None);
+            let checkpoint = history.checkpoint(&scene);
+            history.undo(&mut scene, None);
+            checkpoint
+        };
+        history.record_entities(&scene, &[]);
+        scene.create_entity("D".to_string());
+        history.commit(&scene);
+        assert!(history.restore_checkpoint(ahead, &mut scene, None).is_none());
+
+        history.clear();
+        assert!(history.restore_checkpoint(start, &mut scene, None).is_none());
+    }
+
+    #[test]
+    fn test_terrain_stroke_restores_tiles() {
+        let mut heightmap = HeightMap {
+            width: 40,
+            depth: 40,
+            heights: vec![0.0; 1600],
+            holes: Vec::new(),
+        };
+        let mut scene = Scene::new("Test".to_string());
+        let mut history = UndoHistory::default();
+
+        history.begin_terrain_stroke(&scene);
+        history.track_terrain_region(&heightmap, None, (30, 30, 35, 35));
+        heightmap.set_height(33, 33, 2.0);
+        history.end_terrain_stroke(&heightmap, None);
+        assert_eq!(history.undo_count(), 1);
+
+        let target = history.undo(&mut scene, Some((&mut heightmap, None)));
+        assert_eq!(target, Some(UndoTarget::Terrain));
+        assert_eq!(heightmap.get_height(33, 33), 0.0);
+        history.redo(&mut scene, Some((&mut heightmap, None)));
+        assert_eq!(heightmap.get_height(33, 33), 2.0);
+
+        history.forget_terrain();
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn test_merged_steps_undo_together() {
+        let mut heightmap = HeightMap {
+            width: 8,
+            depth: 8,
+            heights: vec![0.0; 64],
+            holes: Vec::new(),
+        };
+        let mut scene = Scene::new("Test".to_string());
+        let mut history = UndoHistory::default();
+        let start = history.checkpoint(&scene);
+
+        history.begin_terrain_stroke(&scene);
+        history.track_terrain_region(&heightmap, None, (0, 0, 7, 7));
+        history.record_entities(&scene, &[]);
+        let id = scene.create_entity("Terrain".to_string());
+        heightmap.set_height(4, 4, 3.0);
+        history.commit(&scene);
+        history.end_terrain_stroke(&heightmap, None);
+        assert_eq!(history.undo_count(), 2);
+
+        history.merge_since(&scene, start);
+        assert_eq!(history.undo_count(), 1);
+        let target = history.undo(&mut scene, Some((&mut heightmap, None)));
+        assert_eq!(target, Some(UndoTarget::Terrain));
+        assert!(scene.get_entity(id).is_none());
+        assert_eq!(heightmap.get_height(4, 4), 0.0);
+
+        history.redo(&mut scene, Some((&mut heightmap, None)));
+        assert!(scene.get_entity(id).is_some());
+        assert_eq!(heightmap.get_height(4, 4), 3.0);
+
+        history.forget_terrain();
+        assert!(!history.can_undo());
+    }
+}
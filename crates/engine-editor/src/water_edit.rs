@@ -0,0 +1,254 @@
+// Water editing - viewport tools for TerrainWater
+//
+// The level tool drags a handle to set the ground water level, the flow tool
+// paints flow vectors onto water bodies, and the exclusion tool adds (or with
+// Shift removes) circular zones kept dry. Every edit recomputes the fill with
+// compute_water_fill so the result shows up while dragging.
+
+use engine_assets::{
+    apply_flow_overrides, compute_water_fill_masked, exclusion_mask, FlowSample, HeightMap,
+    TerrainConfig, WaterFillResult,
+};
+use engine_render::camera::Camera;
+use engine_scene::components::{TerrainWater, WaterExclusionZone, WaterFlowStroke};
+use glam::{Mat4, Vec2, Vec3};
+
+/// How close (pixels) a press must be to the level handle to grab it
+pub const HANDLE_GRAB_RADIUS: f32 = 14.0;
+/// Length of the drawn flow arrows in world units
+const FLOW_ARROW_LENGTH: f32 = 2.0;
+const ZONE_OUTLINE_SEGMENTS: usize = 32;
+
+/// Water fill for a TerrainWater component, with its exclusion zones and
+/// painted flow applied
+pub fn compute_terrain_water(
+    heightmap: &HeightMap,
+    config: &TerrainConfig,
+    terrain_water: &TerrainWater,
+) -> WaterFillResult {
+    let circles: Vec<([f32; 2], f32)> = terrain_water
+        .exclusion_zones
+        .iter()
+        .map(|zone| (zone.center, zone.radius))
+        .collect();
+    let mask = exclusion_mask(heightmap, config.scale, &circles);
+    let mut result = compute_water_fill_masked(
+        heightmap,
+        terrain_water.ground_water_level,
+        terrain_water.min_water_depth,
+        terrain_water.min_water_area,
+        &mask,
+    );
+    let samples: Vec<FlowSample> = terrain_water
+        .flow_strokes
+        .iter()
+        .map(|stroke| FlowSample {
+            position: stroke.position,
+            direction: stroke.direction,
+            speed: stroke.speed,
+        })
+        .collect();
+    apply_flow_overrides(&mut result.water_bodies, heightmap, config.scale, &samples);
+    result
+}
+
+/// Water level under the cursor while dragging the handle at `anchor`: the
+/// ray is intersected with the vertical plane through the anchor facing the camera
+pub fn level_from_ray(
+    anchor: Vec3,
+    camera: &Camera,
+    ray_origin: Vec3,
+    ray_direction: Vec3,
+) -> Option<f32> {
+    let facing = camera.position - anchor;
+    let normal = Vec3::new(facing.x, 0.0, facing.z).try_normalize()?;
+    let denom = ray_direction.dot(normal);
+    if denom.abs() < 1e-4 {
+        return None;
+    }
+    let t = (anchor - ray_origin).dot(normal) / denom;
+    (t > 0.0).then(|| (ray_origin + ray_direction * t).y)
+}
+
+/// Add a painted flow vector, replacing strokes within `radius` of it
+pub fn paint_flow(
+    strokes: &mut Vec<WaterFlowStroke>,
+    position: [f32; 2],
+    direction: Vec2,
+    speed: f32,
+    radius: f32,
+) {
+    let Some(direction) = direction.try_normalize() else {
+        return;
+    };
+    let radius_sq = radius * radius;
+    strokes.retain(|stroke| {
+        Vec2::from(stroke.position).distance_squared(Vec2::from(position)) > radius_sq
+    });
+    strokes.push(WaterFlowStroke {
+        position,
+        direction: direction.into(),
+        speed,
+    });
+}
+
+/// Remove the exclusion zones containing a point. Returns true if any were removed.
+pub fn remove_exclusion_zones_at(zones: &mut Vec<WaterExclusionZone>, point: [f32; 2]) -> bool {
+    let count = zones.len();
+    zones.retain(|zone| Vec2::from(zone.center).distance(Vec2::from(point)) > zone.radius);
+    zones.len() != count
+}
+
+/// Water edit tool state kept between frames
+#[derive(Default)]
+pub struct WaterEditState {
+    /// The level handle is being dragged
+    pub dragging_level: bool,
+    /// Handle position (XZ) held while the level is dragged
+    pub anchor: Option<Vec3>,
+    /// Last terrain point painted by the flow tool during this stroke
+    pub last_flow_point: Option<Vec3>,
+}
+
+/// Screen-space (pixel) shapes drawn over the viewport by the water tools
+#[derive(Debug, Clone, Default)]
+pub struct WaterOverlay {
+    /// Level handle position and its label
+    pub handle: Option<(Vec2, String)>,
+    pub handle_active: bool,
+    /// Flow arrows (tail, head)
+    pub flow_arrows: Vec<(Vec2, Vec2)>,
+    /// Exclusion zone outlines
+    pub zones: Vec<Vec<Vec2>>,
+}
+
+/// Project a world point to pixels, or None if it is behind the camera
+pub fn project_to_screen(view_proj: Mat4, point: Vec3, screen_size: Vec2) -> Option<Vec2> {
+    let clip = view_proj * point.extend(1.0);
+    if clip.w <= 0.0 {
+        return None;
+    }
+    let ndc = clip.truncate() / clip.w;
+    Some(Vec2::new(
+        (ndc.x + 1.0) * 0.5 * screen_size.x,
+        (1.0 - ndc.y) * 0.5 * screen_size.y,
+    ))
+}
+
+/// Build the overlay for a TerrainWater component. `handle_anchor` is where
+/// the level handle sits; flow arrows and zones are drawn on the terrain.
+pub fn build_overlay(
+    terrain_water: &TerrainWater,
+    heightmap: &HeightMap,
+    config: &TerrainConfig,
+    handle_anchor: Vec3,
+    camera: &Camera,
+    screen_size: Vec2,
+) -> WaterOverlay {
+    let view_proj = camera.view_projection_matrix();
+    let project = |point: Vec3| project_to_screen(view_proj, point, screen_size);
+    // Slightly above the terrain so outlines aren't hidden by it
+    let ground =
+        |x: f32, z: f32| Vec3::new(x, heightmap.sample_height(x, z, config.scale) + 0.1, z);
+
+    let handle = project(handle_anchor).map(|pos| {
+        (
+            pos,
+            format!("Water level {:.2}", terrain_water.ground_water_level),
+        )
+    });
+
+    let flow_arrows = terrain_water
+        .flow_strokes
+        .iter()
+        .filter_map(|stroke| {
+            let [x, z] = stroke.position;
+            // Arrows over water sit on its surface
+            let mut tail = ground(x, z);
+            tail.y = tail.y.max(terrain_water.ground_water_level);
+            let head =
+                tail + Vec3::new(stroke.direction[0], 0.0, stroke.direction[1]) * FLOW_ARROW_LENGTH;
+            Some((project(tail)?, project(head)?))
+        })
+        .collect();
+
+    let zones = terrain_water
+        .exclusion_zones
+        .iter()
+        .map(|zone| {
+            (0..=ZONE_OUTLINE_SEGMENTS)
+                .filter_map(|i| {
+                    let angle = i as f32 / ZONE_OUTLINE_SEGMENTS as f32 * std::f32::consts::TAU;
+                    let x = zone.center[0] + angle.cos() * zone.radius;
+                    let z = zone.center[1] + angle.sin() * zone.radius;
+                    project(ground(x, z))
+                })
+                .collect()
+        })
+        .collect();
+
+    WaterOverlay {
+        handle,
+        handle_active: false,
+        flow_arrows,
+        zones,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_from_ray_follows_cursor_height() {
+        let mut camera = Camera::new(800, 600);
+        camera.position = Vec3::new(0.0, 5.0, 10.0);
+        camera.target = Vec3::ZERO;
+        let anchor = Vec3::new(0.0, 2.0, 0.0);
+
+        // A ray aimed at (0, 3, 0) hits the handle plane at height 3
+        let target = Vec3::new(0.0, 3.0, 0.0);
+        let direction = (target - camera.position).normalize();
+        let level = level_from_ray(anchor, &camera, camera.position, direction).unwrap();
+        assert!((level - 3.0).abs() < 1e-4);
+
+        // Pointing away from the plane gives nothing
+        assert!(level_from_ray(anchor, &camera, camera.position, -direction).is_none());
+    }
+
+    #[test]
+    fn test_paint_flow_replaces_nearby_strokes() {
+        let mut strokes = Vec::new();
+        paint_flow(&mut strokes, [0.0, 0.0], Vec2::new(3.0, 0.0), 1.0, 1.0);
+        paint_flow(&mut strokes, [5.0, 0.0], Vec2::new(0.0, 1.0), 1.0, 1.0);
+        assert_eq!(strokes.len(), 2);
+        assert_eq!(strokes[0].direction, [1.0, 0.0]);
+
+        // Repainting near the first stroke replaces it
+        paint_flow(&mut strokes, [0.5, 0.0], Vec2::new(0.0, -1.0), 2.0, 1.0);
+        assert_eq!(strokes.len(), 2);
+        assert!(strokes.iter().all(|stroke| stroke.position != [0.0, 0.0]));
+
+        // Zero-length drags add nothing
+        paint_flow(&mut strokes, [9.0, 9.0], Vec2::ZERO, 1.0, 1.0);
+        assert_eq!(strokes.len(), 2);
+    }
+
+    #[test]
+    fn test_remove_exclusion_zones_at_point() {
+        let mut zones = vec![
+            WaterExclusionZone {
+                center: [0.0, 0.0],
+                radius: 2.0,
+            },
+            WaterExclusionZone {
+                center: [10.0, 0.0],
+                radius: 2.0,
+            },
+        ];
+        assert!(!remove_exclusion_zones_at(&mut zones, [5.0, 0.0]));
+        assert!(remove_exclusion_zones_at(&mut zones, [1.0, 1.0]));
+        assert_eq!(zones.len(), 1);
+        assert_eq!(zones[0].center, [10.0, 0.0]);
+    }
+}
@@ -2,25 +2,67 @@
 // Implements Model Context Protocol for Claude Code integration
 
 mod protocol;
+mod resources;
 mod tools;
 
 use anyhow::Result;
-use protocol::{McpRequest, McpResponse};
+use crossbeam_channel::RecvTimeoutError;
+use protocol::{CancelledRequests, McpNotification, McpRequest, McpResponse};
+use resources::ResourceRegistry;
 use std::io::{self, BufRead, Write};
+use std::time::{Duration, Instant};
 use tools::ToolRegistry;
 
+/// How often subscribed resources are checked for changes
+const RESOURCE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 fn main() -> Result<()> {
     env_logger::init();
     log::info!("Causality Engine MCP Server starting...");
 
-    let mut tool_registry = ToolRegistry::new();
-    let stdin = io::stdin();
+    let cancelled = CancelledRequests::default();
+    let mut tool_registry = ToolRegistry::new(cancelled.clone());
+    let mut resource_registry = ResourceRegistry::new();
     let mut stdout = io::stdout();
 
+    // stdin is read on its own thread so a cancellation can reach a tool
+    // that is still waiting on the editor
+    let (line_tx, line_rx) = crossbeam_channel::unbounded();
+    let reader_cancelled = cancelled.clone();
+    std::thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if let Some(request_id) = cancelled_request(&line) {
+                log::info!("Request {} cancelled", request_id);
+                reader_cancelled.cancel(&request_id);
+                continue;
+            }
+            if line_tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
     log::info!("MCP Server ready, listening on stdin...");
 
-    for line in stdin.lock().lines() {
-        let line = line?;
+    let mut last_resource_poll = Instant::now();
+    loop {
+        if last_resource_poll.elapsed() >= RESOURCE_POLL_INTERVAL {
+            last_resource_poll = Instant::now();
+            for uri in resource_registry.poll_updates() {
+                McpNotification::new(
+                    "notifications/resources/updated",
+                    serde_json::json!({ "uri": uri }),
+                )
+                .send()?;
+            }
+        }
+
+        let line = match line_rx.recv_timeout(RESOURCE_POLL_INTERVAL) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
         if line.trim().is_empty() {
             continue;
         }
@@ -44,7 +86,9 @@ fn main() -> Result<()> {
             }
         };
 
-        // Handle request
+        // Handle request; the id is kept to check for a cancellation that
+        // arrives while it runs
+        let request_id = request.id.clone();
         let response = match request.method.as_str() {
             "initialize" => {
                 log::info!("Initializing MCP server");
@@ -57,7 +101,10 @@ fn main() -> Result<()> {
                             "version": "0.1.0"
                         },
                         "capabilities": {
-                            "tools": {}
+                            "tools": {},
+                            "resources": {
+                                "subscribe": true
+                            }
                         }
                     }),
                 )
@@ -69,7 +116,7 @@ fn main() -> Result<()> {
             }
             "tools/call" => {
                 log::info!("Calling tool");
-                match tool_registry.call_tool(&request.params) {
+                match tool_registry.call_tool(&request.params, request.id.as_ref()) {
                     Ok(result) => McpResponse::success(request.id, result),
                     Err(e) => McpResponse::error(
                         request.id,
@@ -78,6 +125,20 @@ fn main() -> Result<()> {
                     ),
                 }
             }
+            "resources/list" | "resources/read" | "resources/subscribe"
+            | "resources/unsubscribe" => {
+                log::info!("{}", request.method);
+                let result = match request.method.as_str() {
+                    "resources/list" => resource_registry.list(),
+                    "resources/read" => resource_registry.read(&request.params),
+                    "resources/subscribe" => resource_registry.subscribe(&request.params),
+                    _ => resource_registry.unsubscribe(&request.params),
+                };
+                match result {
+                    Ok(result) => McpResponse::success(request.id, result),
+                    Err(e) => McpResponse::error(request.id, -32603, e.to_string()),
+                }
+            }
             _ => McpResponse::error(
                 request.id,
                 -32601,
@@ -85,6 +146,11 @@ fn main() -> Result<()> {
             ),
         };
 
+        // Cancelled requests get no response
+        if request_id.as_ref().is_some_and(|id| cancelled.take(id)) {
+            continue;
+        }
+
         // Send response
         let response_json = serde_json::to_string(&response)?;
         writeln!(stdout, "{}", response_json)?;
@@ -94,3 +160,12 @@ fn main() -> Result<()> {
     log::info!("MCP Server shutting down");
     Ok(())
 }
+
+/// The ID a notifications/cancelled line cancels
+fn cancelled_request(line: &str) -> Option<serde_json::Value> {
+    let request: McpRequest = serde_json::from_str(line).ok()?;
+    if request.method != "notifications/cancelled" {
+        return None;
+    }
+    request.params.get("requestId").cloned()
+}
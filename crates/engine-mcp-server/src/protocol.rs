@@ -1,7 +1,10 @@
 // MCP Protocol - JSON-RPC message handling
 
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
 
 /// JSON-RPC request from Claude Code
 #[derive(Debug, Deserialize)]
@@ -50,3 +53,63 @@ impl McpResponse {
         }
     }
 }
+
+/// JSON-RPC notification to Claude Code (no response expected)
+#[derive(Debug, Serialize)]
+pub struct McpNotification {
+    jsonrpc: String,
+    method: String,
+    params: Value,
+}
+
+impl McpNotification {
+    pub fn new(method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params,
+        }
+    }
+
+    /// Progress of the request that asked for it with `progress_token`
+    pub fn progress(progress_token: Value, progress: f64, total: f64, message: &str) -> Self {
+        Self::new(
+            "notifications/progress",
+            json!({
+                "progressToken": progress_token,
+                "progress": progress,
+                "total": total,
+                "message": message
+            }),
+        )
+    }
+
+    /// Write the notification to stdout, between responses
+    pub fn send(&self) -> anyhow::Result<()> {
+        let mut stdout = io::stdout().lock();
+        writeln!(stdout, "{}", serde_json::to_string(self)?)?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+/// IDs of requests Claude Code has cancelled (notifications/cancelled).
+/// Shared between the stdin reader and tools that wait a long time, so a
+/// tool can notice while it is still running.
+#[derive(Debug, Clone, Default)]
+pub struct CancelledRequests(Arc<Mutex<HashSet<String>>>);
+
+impl CancelledRequests {
+    pub fn cancel(&self, id: &Value) {
+        self.0.lock().unwrap().insert(id.to_string());
+    }
+
+    pub fn is_cancelled(&self, id: &Value) -> bool {
+        self.0.lock().unwrap().contains(&id.to_string())
+    }
+
+    /// Whether the request was cancelled, forgetting it
+    pub fn take(&self, id: &Value) -> bool {
+        self.0.lock().unwrap().remove(&id.to_string())
+    }
+}
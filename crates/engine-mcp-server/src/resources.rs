@@ -0,0 +1,111 @@
+// MCP Resources - scene files, scripts and generated assets by URI
+//
+// The editor lists resources and resolves a URI to its file; the server
+// reads the file itself, as it does for viewport screenshots. Subscribed
+// URIs are polled for changes the editor's hot-reload watcher saw.
+
+use anyhow::{anyhow, Result};
+use engine_core::ipc::{self, IpcClient};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+
+use crate::tools::base64_encode;
+
+/// Resource access through the editor
+pub struct ResourceRegistry {
+    client: IpcClient,
+    subscribed: BTreeSet<String>,
+}
+
+impl ResourceRegistry {
+    pub fn new() -> Self {
+        Self {
+            client: IpcClient::new(ipc::address()),
+            subscribed: BTreeSet::new(),
+        }
+    }
+
+    fn send_command(&mut self, command: &str, args: Value) -> Result<Value> {
+        let response = self.client.request(command, args)?;
+        if response.success {
+            Ok(response.result)
+        } else {
+            Err(anyhow!("Editor error: {:?}", response.result))
+        }
+    }
+
+    /// resources/list
+    pub fn list(&mut self) -> Result<Value> {
+        let result = self.send_command("list_resources", json!({}))?;
+        Ok(json!({ "resources": result.get("resources").cloned().unwrap_or(json!([])) }))
+    }
+
+    /// resources/read: text files as text, everything else base64-encoded
+    pub fn read(&mut self, params: &Value) -> Result<Value> {
+        let uri = uri_param(params)?;
+        let result = self.send_command("read_resource", json!({ "uri": uri }))?;
+        let path = result
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Editor did not return a path for {}", uri))?;
+        let mime_type = result
+            .get("mimeType")
+            .and_then(|v| v.as_str())
+            .unwrap_or("application/octet-stream");
+
+        let bytes = std::fs::read(path).map_err(|e| anyhow!("Failed to read {}: {}", uri, e))?;
+        let is_text = mime_type.starts_with("text/") || mime_type == "application/json";
+        let content = match std::str::from_utf8(&bytes) {
+            Ok(text) if is_text => json!({ "uri": uri, "mimeType": mime_type, "text": text }),
+            _ => json!({ "uri": uri, "mimeType": mime_type, "blob": base64_encode(&bytes) }),
+        };
+        Ok(json!({ "contents": [content] }))
+    }
+
+    /// resources/subscribe
+    pub fn subscribe(&mut self, params: &Value) -> Result<Value> {
+        let uri = uri_param(params)?;
+        self.send_command("subscribe_resource", json!({ "uri": uri }))?;
+        self.subscribed.insert(uri.to_string());
+        log::info!("Subscribed to {}", uri);
+        Ok(json!({}))
+    }
+
+    /// resources/unsubscribe
+    pub fn unsubscribe(&mut self, params: &Value) -> Result<Value> {
+        let uri = uri_param(params)?;
+        self.subscribed.remove(uri);
+        self.send_command("unsubscribe_resource", json!({ "uri": uri }))?;
+        Ok(json!({}))
+    }
+
+    /// Subscribed resources that changed since the last poll (none if the
+    /// editor can't be reached)
+    pub fn poll_updates(&mut self) -> Vec<String> {
+        if self.subscribed.is_empty() {
+            return Vec::new();
+        }
+        match self.send_command("take_resource_updates", json!({})) {
+            Ok(result) => result
+                .get("uris")
+                .and_then(|v| v.as_array())
+                .map(|uris| {
+                    uris.iter()
+                        .filter_map(|uri| uri.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            Err(e) => {
+                log::debug!("Could not poll resource updates: {}", e);
+                Vec::new()
+            }
+        }
+    }
+}
+
+fn uri_param(params: &Value) -> Result<&str> {
+    params
+        .get("uri")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Missing uri"))
+}
@@ -1,88 +1,97 @@
 // MCP Tools - Operations that Claude Code can call
 
 use anyhow::{anyhow, Result};
-use serde::{Deserialize, Serialize};
+use engine_core::ipc::{self, IpcClient};
 use serde_json::{json, Value};
-use std::fs;
-use std::path::PathBuf;
-use std::time::{Duration, SystemTime};
+use std::cell::RefCell;
+use std::time::Duration;
 
-/// File-based IPC for communication with the editor
-pub struct ToolRegistry {
-    command_file: PathBuf,
-    response_file: PathBuf,
-}
+use crate::protocol::{CancelledRequests, McpNotification};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct IpcCommand {
-    id: u64,
-    command: String,
-    args: Value,
-}
+/// Slowest editor frame rate step_simulation waits for
+const MIN_STEP_FPS: f32 = 15.0;
+
+/// Longest step_simulation the editor runs (1800 frames at 60 per second)
+const MAX_STEP_SECONDS: f32 = 30.0;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct IpcResponse {
-    id: u64,
-    success: bool,
-    result: Value,
+/// How often generation tools poll their job
+const JOB_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long tools/list waits for the editor's plugin tools
+const PLUGIN_TOOLS_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Socket IPC for communication with the editor
+pub struct ToolRegistry {
+    client: RefCell<IpcClient>,
+    cancelled: CancelledRequests,
+    /// ID of the tools/call being handled
+    request_id: Option<Value>,
+    /// Token the client gave for progress notifications on that call
+    progress_token: Option<Value>,
 }
 
 impl ToolRegistry {
-    pub fn new() -> Self {
-        let tmp_dir = std::env::temp_dir();
+    pub fn new(cancelled: CancelledRequests) -> Self {
         Self {
-            command_file: tmp_dir.join("game-engine-mcp-command.json"),
-            response_file: tmp_dir.join("game-engine-mcp-response.json"),
+            client: RefCell::new(IpcClient::new(ipc::address())),
+            cancelled,
+            request_id: None,
+            progress_token: None,
         }
     }
 
     /// Send a command to the editor and wait for response
     fn send_command(&self, command: &str, args: Value) -> Result<Value> {
-        let id = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)?
-            .as_micros() as u64;
-
-        let ipc_command = IpcCommand {
-            id,
-            command: command.to_string(),
-            args,
-        };
-
-        // Write command to file
-        let command_json = serde_json::to_string(&ipc_command)?;
-        fs::write(&self.command_file, command_json)?;
-        log::debug!("Wrote command to {:?}", self.command_file);
-
-        // Wait for response (with timeout)
-        let timeout = Duration::from_secs(5);
-        let start = SystemTime::now();
-
-        loop {
-            if start.elapsed()? > timeout {
-                return Err(anyhow!("IPC timeout waiting for editor response"));
-            }
+        let response = self.client.borrow_mut().request(command, args)?;
+        Self::command_result(response)
+    }
 
-            if self.response_file.exists() {
-                let response_json = fs::read_to_string(&self.response_file)?;
-                let response: IpcResponse = serde_json::from_str(&response_json)?;
+    /// Like `send_command`, for commands that take longer than the usual timeout
+    fn send_command_with_timeout(&self, command: &str, args: Value, timeout: Duration) -> Result<Value> {
+        let response = self
+            .client
+            .borrow_mut()
+            .request_with_timeout(command, args, timeout)?;
+        Self::command_result(response)
+    }
 
-                if response.id == id {
-                    // Remove response file for next command
-                    let _ = fs::remove_file(&self.response_file);
+    fn command_result(response: ipc::IpcResponse) -> Result<Value> {
+        if response.success {
+            Ok(response.result)
+        } else {
+            Err(anyhow!("Editor error: {:?}", response.result))
+        }
+    }
 
-                    if response.success {
-                        return Ok(response.result);
-                    } else {
-                        return Err(anyhow!("Editor error: {:?}", response.result));
+    /// The built-in tools plus, when the editor is running, the tools its
+    /// plugins add
+    pub fn list_tools(&self) -> Vec<Value> {
+        let mut tools = Self::builtin_tools();
+        match self.send_command_with_timeout("list_plugin_tools", json!({}), PLUGIN_TOOLS_TIMEOUT) {
+            Ok(result) => {
+                let plugin_tools = result
+                    .get("tools")
+                    .and_then(|v| v.as_array())
+                    .cloned()
+                    .unwrap_or_default();
+                for tool in plugin_tools {
+                    let name = tool.get("name").and_then(|v| v.as_str());
+                    if tools
+                        .iter()
+                        .any(|t| t.get("name").and_then(|v| v.as_str()) == name)
+                    {
+                        log::warn!("Plugin tool {:?} has the name of a built-in tool", name);
+                        continue;
                     }
+                    tools.push(tool);
                 }
             }
-
-            std::thread::sleep(Duration::from_millis(50));
+            Err(e) => log::debug!("No plugin tools: {}", e),
         }
+        tools
     }
 
-    pub fn list_tools(&self) -> Vec<Value> {
+    fn builtin_tools() -> Vec<Value> {
         vec![
             json!({
                 "name": "create_entity",
@@ -167,7 +176,7 @@ impl ToolRegistry {
             }),
             json!({
                 "name": "add_script",
-                "description": "Add or update a Rhai script on an entity",
+                "description": "Add or update a Rhai script on an entity. Scripts that don't compile are refused with the line and column of the error.",
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -183,6 +192,38 @@ impl ToolRegistry {
                     "required": ["entity_name", "script"]
                 }
             }),
+            json!({
+                "name": "attach_script_file",
+                "description": "Attach a .rhai file as an entity's script. Later edits to the file are picked up by the editor.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "entity_name": {
+                            "type": "string",
+                            "description": "Name of the entity"
+                        },
+                        "path": {
+                            "type": "string",
+                            "description": "Script path relative to the project directory, e.g. 'scripts/rotate.rhai'"
+                        }
+                    },
+                    "required": ["entity_name", "path"]
+                }
+            }),
+            json!({
+                "name": "run_script_snippet",
+                "description": "Evaluate a Rhai snippet in a sandbox and return its value and printed output. The snippet can read the scene through 'entities' (id, name, position, rotation, scale, parent, children, has_script) and find_entity(name), but cannot change it.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "script": {
+                            "type": "string",
+                            "description": "Rhai code; the value of the last expression is returned"
+                        }
+                    },
+                    "required": ["script"]
+                }
+            }),
             json!({
                 "name": "load_model",
                 "description": "Load a 3D model (GLTF) into the scene",
@@ -214,6 +255,156 @@ impl ToolRegistry {
                     "properties": {}
                 }
             }),
+            json!({
+                "name": "validate_scene",
+                "description": "Check the scene for problems: missing assets, broken parent/child links, and NaN or zero-scale transforms. Run before save_scene to make sure the scene is clean.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {}
+                }
+            }),
+            json!({
+                "name": "capture_viewport",
+                "description": "Take a screenshot of the editor viewport (the rendered scene without editor UI) and return it as a PNG image",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {}
+                }
+            }),
+            json!({
+                "name": "set_editor_camera",
+                "description": "Move the editor viewport camera: frame an entity, look at a point, or view from a position. Use it before capture_viewport or to show the user what was built.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "entity_name": {
+                            "type": "string",
+                            "description": "Entity to frame (with its children)"
+                        },
+                        "target": {
+                            "type": "array",
+                            "description": "Point [x, y, z] to look at (ignored with entity_name)",
+                            "items": { "type": "number" },
+                            "minItems": 3,
+                            "maxItems": 3
+                        },
+                        "position": {
+                            "type": "array",
+                            "description": "Camera position [x, y, z]; without it the current view direction is kept",
+                            "items": { "type": "number" },
+                            "minItems": 3,
+                            "maxItems": 3
+                        },
+                        "distance": {
+                            "type": "number",
+                            "description": "Distance from the target"
+                        }
+                    }
+                }
+            }),
+            json!({
+                "name": "undo",
+                "description": "Undo the last scene edits. The undo history is shared with edits made by hand in the editor.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "steps": {
+                            "type": "integer",
+                            "description": "Number of steps to undo (default: 1)"
+                        }
+                    }
+                }
+            }),
+            json!({
+                "name": "redo",
+                "description": "Redo edits that were undone",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "steps": {
+                            "type": "integer",
+                            "description": "Number of steps to redo (default: 1)"
+                        }
+                    }
+                }
+            }),
+            json!({
+                "name": "create_checkpoint",
+                "description": "Remember the current point in the undo history under a name, to return to with restore_checkpoint if an experiment goes wrong",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "name": {
+                            "type": "string",
+                            "description": "Checkpoint name (an existing checkpoint with this name is replaced)"
+                        }
+                    },
+                    "required": ["name"]
+                }
+            }),
+            json!({
+                "name": "restore_checkpoint",
+                "description": "Undo (or redo) back to a checkpoint made with create_checkpoint",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "name": {
+                            "type": "string",
+                            "description": "Checkpoint name"
+                        }
+                    },
+                    "required": ["name"]
+                }
+            }),
+            json!({
+                "name": "play_simulation",
+                "description": "Start play-in-editor mode (physics, scripts, animation) on a copy of the scene, or resume it when paused",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {}
+                }
+            }),
+            json!({
+                "name": "pause_simulation",
+                "description": "Pause the running simulation",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {}
+                }
+            }),
+            json!({
+                "name": "step_simulation",
+                "description": "Run the simulation for a number of frames (60 per second), then pause and report. Starts play mode if needed. Use get_entity_info or capture_viewport afterwards to check the results.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "frames": {
+                            "type": "integer",
+                            "description": "Frames to run (default: 1, at most 1800)"
+                        },
+                        "seconds": {
+                            "type": "number",
+                            "description": "Simulated seconds to run, instead of frames (at most 30)"
+                        }
+                    }
+                }
+            }),
+            json!({
+                "name": "stop_simulation",
+                "description": "Stop play mode and restore the scene as it was before play started",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {}
+                }
+            }),
+            json!({
+                "name": "get_simulation_state",
+                "description": "Report whether the editor is editing, playing or paused, and how much time has been simulated",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {}
+                }
+            }),
             json!({
                 "name": "add_rigidbody",
                 "description": "Add a physics rigid body component to an entity",
@@ -248,7 +439,7 @@ impl ToolRegistry {
                         },
                         "shape_type": {
                             "type": "string",
-                            "description": "Shape: 'box', 'sphere', or 'capsule' (default: box)"
+                            "description": "Shape: 'box', 'sphere', 'capsule', 'trimesh' or 'convex' (generated from the entity's mesh), or 'heightfield' (from the terrain) (default: box)"
                         },
                         "size": {
                             "type": "array",
@@ -268,255 +459,1386 @@ impl ToolRegistry {
                 }
             }),
             json!({
-                "name": "generate_texture",
-                "description": "Generate a texture from a text prompt using AI (Stable Diffusion)",
+                "name": "add_light",
+                "description": "Add a light to an entity or change its existing light (creates the entity if it does not exist)",
                 "inputSchema": {
                     "type": "object",
                     "properties": {
-                        "prompt": {
+                        "entity_name": {
                             "type": "string",
-                            "description": "Text description of the texture to generate"
+                            "description": "Name of the entity"
                         },
-                        "width": {
-                            "type": "integer",
-                            "description": "Texture width in pixels (default: 512, must be multiple of 64)"
+                        "light_type": {
+                            "type": "string",
+                            "description": "'directional', 'point' or 'spot' (default: point for new lights)"
                         },
-                        "height": {
-                            "type": "integer",
-                            "description": "Texture height in pixels (default: 512, must be multiple of 64)"
+                        "color": {
+                            "type": "array",
+                            "description": "Color [r, g, b] in 0-1",
+                            "items": { "type": "number" }
                         },
-                        "quality": {
-                            "type": "string",
-                            "description": "Quality level: 'fast', 'standard', 'high', or 'best' (default: 'high')"
+                        "intensity": {
+                            "type": "number",
+                            "description": "Brightness multiplier"
                         },
-                        "seed": {
-                            "type": "integer",
-                            "description": "Random seed for reproducibility (optional)"
+                        "range": {
+                            "type": "number",
+                            "description": "Reach of point and spot lights in world units"
+                        },
+                        "direction": {
+                            "type": "array",
+                            "description": "Direction [x, y, z] of directional and spot lights",
+                            "items": { "type": "number" }
+                        },
+                        "angle": {
+                            "type": "number",
+                            "description": "Spot light cone angle in degrees"
+                        },
+                        "cast_shadows": {
+                            "type": "boolean",
+                            "description": "Whether the light casts shadows"
+                        },
+                        "position": {
+                            "type": "array",
+                            "description": "Entity position [x, y, z]",
+                            "items": { "type": "number" }
                         }
                     },
-                    "required": ["prompt"]
+                    "required": ["entity_name"]
                 }
             }),
             json!({
-                "name": "generate_skybox",
-                "description": "Generate a 360-degree skybox from a text prompt using AI",
+                "name": "add_component",
+                "description": "Add any component type to an entity, starting from its defaults",
                 "inputSchema": {
                     "type": "object",
                     "properties": {
-                        "prompt": {
+                        "entity_name": {
                             "type": "string",
-                            "description": "Text description of the skybox environment"
+                            "description": "Name of the entity"
                         },
-                        "quality": {
+                        "component": {
                             "type": "string",
-                            "description": "Quality level: 'fast', 'standard', 'high', or 'best' (default: 'high')"
+                            "description": "Component type, e.g. 'Light', 'Water', 'RigidBody', 'ParticleEmitter'"
                         },
-                        "seed": {
-                            "type": "integer",
-                            "description": "Random seed for reproducibility (optional)"
+                        "fields": {
+                            "type": "object",
+                            "description": "Field values to use instead of the defaults (required for components without defaults, e.g. MeshRenderer)"
                         }
                     },
-                    "required": ["prompt"]
+                    "required": ["entity_name", "component"]
                 }
             }),
             json!({
-                "name": "save_scene",
-                "description": "Save the current scene to a file",
+                "name": "remove_component",
+                "description": "Remove a component from an entity",
                 "inputSchema": {
                     "type": "object",
                     "properties": {
-                        "file_path": {
+                        "entity_name": {
                             "type": "string",
-                            "description": "Path to save the scene file (e.g., 'assets/scenes/my_scene.ron')"
+                            "description": "Name of the entity"
+                        },
+                        "component": {
+                            "type": "string",
+                            "description": "Component type, e.g. 'Light', 'Water', 'RigidBody', 'ParticleEmitter'"
                         }
                     },
-                    "required": ["file_path"]
+                    "required": ["entity_name", "component"]
                 }
             }),
             json!({
-                "name": "load_scene",
-                "description": "Load a scene from a file",
+                "name": "get_component",
+                "description": "Get all fields of a component on an entity as JSON",
                 "inputSchema": {
                     "type": "object",
                     "properties": {
-                        "file_path": {
+                        "entity_name": {
                             "type": "string",
-                            "description": "Path to the scene file to load (e.g., 'assets/scenes/castle.ron')"
+                            "description": "Name of the entity"
+                        },
+                        "component": {
+                            "type": "string",
+                            "description": "Component type, e.g. 'Light', 'Water', 'RigidBody', 'ParticleEmitter'"
                         }
                     },
-                    "required": ["file_path"]
+                    "required": ["entity_name", "component"]
                 }
             }),
             json!({
-                "name": "generate_music",
-                "description": "Generate music from a text description using AI. Claude should use external tools (like ACE-Step) to generate the actual audio, then return it to the game engine.",
+                "name": "set_component_field",
+                "description": "Set one field of a component, e.g. Light 'color' or Water 'flow_speed'",
                 "inputSchema": {
                     "type": "object",
                     "properties": {
-                        "prompt": {
+                        "entity_name": {
                             "type": "string",
-                            "description": "Text description of the music to generate (e.g., 'Epic orchestral battle music with drums')"
+                            "description": "Name of the entity"
                         },
-                        "duration": {
+                        "component": {
                             "type": "string",
-                            "description": "Duration: 'short' (~15s), 'medium' (~30s), 'long' (~60s), or 'extended' (~2min). Default: 'medium'"
+                            "description": "Component type, e.g. 'Light', 'Water', 'RigidBody', 'ParticleEmitter'"
                         },
-                        "style": {
+                        "field": {
                             "type": "string",
-                            "description": "Music style/genre: 'rock', 'pop', 'electronic', 'jazz', 'classical', 'cinematic', 'ambient', etc. Default: based on prompt"
-                        },
-                        "tempo": {
-                            "type": "integer",
-                            "description": "Tempo in BPM (e.g., 120). Optional."
+                            "description": "Field path; nested fields and array elements use dots, e.g. 'color.0' or 'light_type.Point.range'"
                         },
-                        "instrumental": {
-                            "type": "boolean",
-                            "description": "True for instrumental only (no vocals). Default: true"
-                        },
-                        "output_path": {
-                            "type": "string",
-                            "description": "Where to save the generated music file (e.g., 'assets/music/battle.wav')"
+                        "value": {
+                            "description": "New value, in the same JSON shape get_component returns"
                         }
                     },
-                    "required": ["prompt", "output_path"]
+                    "required": ["entity_name", "component", "field", "value"]
                 }
             }),
             json!({
-                "name": "play_music",
-                "description": "Play a music file in the game engine",
+                "name": "find_entities_by_component",
+                "description": "Find all entities that have a component, optionally only those whose fields pass filters (e.g. Light where intensity > 2). Use the names with the component tools or execute_batch.",
                 "inputSchema": {
                     "type": "object",
                     "properties": {
-                        "file_path": {
+                        "component": {
                             "type": "string",
-                            "description": "Path to the music file to play"
-                        },
-                        "loop": {
-                            "type": "boolean",
-                            "description": "Whether to loop the music. Default: true"
+                            "description": "Component type, e.g. 'Light', 'Water', 'RigidBody', 'MeshRenderer'"
                         },
-                        "volume": {
-                            "type": "number",
-                            "description": "Volume level (0.0 to 1.0). Default: 0.8"
+                        "filters": {
+                            "type": "array",
+                            "description": "Conditions every match must meet",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "field": {
+                                        "type": "string",
+                                        "description": "Field path, as for set_component_field, e.g. 'intensity' or 'color.0'"
+                                    },
+                                    "op": {
+                                        "type": "string",
+                                        "enum": ["==", "!=", ">", ">=", "<", "<=", "contains"],
+                                        "description": "Comparison (default: ==)"
+                                    },
+                                    "value": {
+                                        "description": "Value to compare the field with"
+                                    }
+                                },
+                                "required": ["field", "value"]
+                            }
                         },
-                        "fade_in": {
-                            "type": "number",
-                            "description": "Fade in duration in seconds. Default: 0.0 (no fade)"
+                        "include_data": {
+                            "type": "boolean",
+                            "description": "Include each match's component fields (default: true)"
                         }
                     },
-                    "required": ["file_path"]
+                    "required": ["component"]
                 }
             }),
             json!({
-                "name": "stop_music",
-                "description": "Stop currently playing music",
+                "name": "set_parent",
+                "description": "Make an entity the child of another, or move it back to the scene root. By default the entity stays where it is in the world.",
                 "inputSchema": {
                     "type": "object",
                     "properties": {
-                        "fade_out": {
+                        "entity_name": {
+                            "type": "string",
+                            "description": "Entity to reparent"
+                        },
+                        "parent_name": {
+                            "type": "string",
+                            "description": "New parent (omit to move the entity to the scene root)"
+                        },
+                        "keep_world_transform": {
+                            "type": "boolean",
+                            "description": "Keep the world position, rotation and scale (default: true). With false the local transform is kept, so the entity moves with its new parent."
+                        }
+                    },
+                    "required": ["entity_name"]
+                }
+            }),
+            json!({
+                "name": "get_children",
+                "description": "List the children of an entity with their local transforms and components",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "entity_name": {
+                            "type": "string",
+                            "description": "Name of the parent entity"
+                        },
+                        "recursive": {
+                            "type": "boolean",
+                            "description": "Include all descendants, nested (default: false)"
+                        },
+                        "max_depth": {
+                            "type": "integer",
+                            "description": "With recursive, how many levels to include"
+                        }
+                    },
+                    "required": ["entity_name"]
+                }
+            }),
+            json!({
+                "name": "get_scene_tree",
+                "description": "Get the scene hierarchy as a nested tree: each entity's id, name, local transform, component names and children",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "root_name": {
+                            "type": "string",
+                            "description": "Only the subtree under this entity (default: the whole scene)"
+                        },
+                        "max_depth": {
+                            "type": "integer",
+                            "description": "Levels to include below each root; deeper entities are summarised as child_count"
+                        }
+                    }
+                }
+            }),
+            json!({
+                "name": "raycast",
+                "description": "Cast a ray against meshes and terrain and report the first surface hit, e.g. to find the ground height below a point before placing something",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "origin": {
+                            "type": "array",
+                            "description": "Ray start [x, y, z]",
+                            "items": { "type": "number" },
+                            "minItems": 3,
+                            "maxItems": 3
+                        },
+                        "direction": {
+                            "type": "array",
+                            "description": "Ray direction [x, y, z] (default: straight down)",
+                            "items": { "type": "number" },
+                            "minItems": 3,
+                            "maxItems": 3
+                        },
+                        "max_distance": {
                             "type": "number",
-                            "description": "Fade out duration in seconds. Default: 0.0 (stop immediately)"
+                            "description": "Longest hit distance (default: 200)"
+                        }
+                    },
+                    "required": ["origin"]
+                }
+            }),
+            json!({
+                "name": "overlap_sphere",
+                "description": "List the entities whose bounds touch a sphere, e.g. to check that a spot is free before placing something",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "center": {
+                            "type": "array",
+                            "description": "Sphere center [x, y, z]",
+                            "items": { "type": "number" },
+                            "minItems": 3,
+                            "maxItems": 3
+                        },
+                        "radius": {
+                            "type": "number",
+                            "description": "Sphere radius"
+                        },
+                        "max_results": {
+                            "type": "integer",
+                            "description": "Most entities to list, closest first (default: 50)"
+                        }
+                    },
+                    "required": ["center", "radius"]
+                }
+            }),
+            json!({
+                "name": "find_entities_near",
+                "description": "List the entities positioned within a radius of a point, closest first",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "position": {
+                            "type": "array",
+                            "description": "Point [x, y, z]",
+                            "items": { "type": "number" },
+                            "minItems": 3,
+                            "maxItems": 3
+                        },
+                        "radius": {
+                            "type": "number",
+                            "description": "Search radius"
+                        },
+                        "horizontal": {
+                            "type": "boolean",
+                            "description": "Ignore height differences (default: false)"
+                        },
+                        "max_results": {
+                            "type": "integer",
+                            "description": "Most entities to list (default: 50)"
+                        }
+                    },
+                    "required": ["position", "radius"]
+                }
+            }),
+            json!({
+                "name": "execute_batch",
+                "description": "Run many scene commands in one call, e.g. to build a whole village at once. The batch is atomic: if any command fails, none of its changes are kept. A successful batch is a single undo step in the editor.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "commands": {
+                            "type": "array",
+                            "description": "Commands to run in order. Allowed: create_entity, delete_entity, set_transform, add_script, load_model, add_rigidbody, add_collider, add_light, add_component, remove_component, set_component_field, list_entities, get_scene_info, get_entity_info, get_component, raycast, overlap_sphere, find_entities_near",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "command": {
+                                        "type": "string",
+                                        "description": "Tool name"
+                                    },
+                                    "args": {
+                                        "type": "object",
+                                        "description": "The same arguments the tool takes on its own"
+                                    }
+                                },
+                                "required": ["command"]
+                            }
+                        }
+                    },
+                    "required": ["commands"]
+                }
+            }),
+            json!({
+                "name": "generate_terrain",
+                "description": "Create the terrain or change its generation parameters (size, seed, noise, moat) and regenerate it. Regenerating discards sculpting and texture paint.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "entity_name": {
+                            "type": "string",
+                            "description": "Terrain entity (default: the entity with a TerrainGenerator, or a new 'Terrain')"
+                        },
+                        "size": {
+                            "type": "number",
+                            "description": "World size of the terrain in units"
+                        },
+                        "width": {
+                            "type": "integer",
+                            "description": "Grid resolution in X (2-1024)"
+                        },
+                        "depth": {
+                            "type": "integer",
+                            "description": "Grid resolution in Z (2-1024)"
+                        },
+                        "height_scale": {
+                            "type": "number",
+                            "description": "Height of the noise hills"
+                        },
+                        "seed": {
+                            "type": "integer",
+                            "description": "Noise seed"
+                        },
+                        "octaves": {
+                            "type": "integer",
+                            "description": "Noise octaves (1-16)"
+                        },
+                        "frequency": {
+                            "type": "number",
+                            "description": "Base noise frequency"
+                        },
+                        "lacunarity": {
+                            "type": "number",
+                            "description": "Frequency multiplier per octave"
+                        },
+                        "persistence": {
+                            "type": "number",
+                            "description": "Amplitude multiplier per octave"
+                        },
+                        "moat": {
+                            "description": "true/false, or { inner_radius, outer_radius, depth } as fractions of the terrain size to enable a ring-shaped moat",
+                            "type": ["boolean", "object"]
                         }
                     }
                 }
             }),
-        ]
+            json!({
+                "name": "sculpt_terrain",
+                "description": "Sculpt the terrain at a world position: raise, lower, flatten, smooth, or stamp a heightmap",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "operation": {
+                            "type": "string",
+                            "description": "'raise', 'lower', 'flatten', 'smooth' or 'stamp'"
+                        },
+                        "position": {
+                            "type": "array",
+                            "description": "World position [x, z] of the center",
+                            "items": { "type": "number" }
+                        },
+                        "radius": {
+                            "type": "number",
+                            "description": "Radius in world units (default: 5)"
+                        },
+                        "amount": {
+                            "type": "number",
+                            "description": "raise/lower: height change at the center (default: 1)"
+                        },
+                        "height": {
+                            "type": "number",
+                            "description": "flatten: target height (default: height at the center); stamp: height of a white pixel (default: the terrain's height_scale)"
+                        },
+                        "strength": {
+                            "type": "number",
+                            "description": "flatten/smooth: blend 0-1 (default: 1)"
+                        },
+                        "heights": {
+                            "type": "array",
+                            "description": "stamp: rows of 0-1 values, first row at -Z",
+                            "items": { "type": "array", "items": { "type": "number" } }
+                        },
+                        "image": {
+                            "type": "string",
+                            "description": "stamp: grayscale image path relative to the assets directory (instead of heights)"
+                        },
+                        "size": {
+                            "type": "number",
+                            "description": "stamp: side of the stamped square in world units (default: 2 * radius)"
+                        },
+                        "blend": {
+                            "type": "string",
+                            "description": "stamp: 'add', 'max' or 'replace' (default: add)"
+                        }
+                    },
+                    "required": ["operation", "position"]
+                }
+            }),
+            json!({
+                "name": "set_material",
+                "description": "Create or edit a material (.mat) file and optionally assign it to an entity's MeshRenderer. Texture fields take an asset path or the asset_id returned by generate_texture.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "material_path": {
+                            "type": "string",
+                            "description": "Material file relative to the assets directory, e.g. 'materials/brick.mat' (created if missing; default: the entity's own material)"
+                        },
+                        "entity_name": {
+                            "type": "string",
+                            "description": "Entity whose MeshRenderer should use the material"
+                        },
+                        "base_color": {
+                            "type": "array",
+                            "description": "Base color [r, g, b] or [r, g, b, a] in 0-1",
+                            "items": { "type": "number" }
+                        },
+                        "metallic": {
+                            "type": "number",
+                            "description": "Metallic factor 0-1"
+                        },
+                        "roughness": {
+                            "type": "number",
+                            "description": "Roughness factor 0-1"
+                        },
+                        "emissive_color": {
+                            "type": "array",
+                            "description": "Emissive color [r, g, b]",
+                            "items": { "type": "number" }
+                        },
+                        "emissive_strength": {
+                            "type": "number",
+                            "description": "Emissive multiplier"
+                        },
+                        "albedo_texture": {
+                            "type": "string",
+                            "description": "Albedo texture path or generated asset ID (empty string clears it)"
+                        },
+                        "normal_texture": {
+                            "type": "string",
+                            "description": "Normal map path or generated asset ID (empty string clears it)"
+                        },
+                        "metallic_roughness_texture": {
+                            "type": "string",
+                            "description": "Metallic-roughness texture path or generated asset ID (empty string clears it)"
+                        },
+                        "ao_texture": {
+                            "type": "string",
+                            "description": "Ambient occlusion texture path or generated asset ID (empty string clears it)"
+                        },
+                        "alpha_mode": {
+                            "type": "string",
+                            "description": "'opaque', 'mask' or 'blend'"
+                        },
+                        "double_sided": {
+                            "type": "boolean",
+                            "description": "Render both faces"
+                        }
+                    }
+                }
+            }),
+            json!({
+                "name": "assign_texture",
+                "description": "Put a texture on an entity. The entity gets a material of its own (copied from its current one) with the texture in the given slot.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "entity_name": {
+                            "type": "string",
+                            "description": "Name of an entity with a MeshRenderer"
+                        },
+                        "texture": {
+                            "type": "string",
+                            "description": "Texture path relative to the assets directory, or the asset_id returned by generate_texture"
+                        },
+                        "slot": {
+                            "type": "string",
+                            "description": "'albedo', 'normal', 'metallic_roughness' or 'ao' (default: albedo)"
+                        }
+                    },
+                    "required": ["entity_name", "texture"]
+                }
+            }),
+            json!({
+                "name": "list_assets",
+                "description": "List files in the assets directory, to find models, textures and materials that can be reused",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "type": {
+                            "type": "string",
+                            "description": "Only list this kind of asset: 'model', 'texture', 'material', 'scene', 'script' or 'audio'"
+                        },
+                        "pattern": {
+                            "type": "string",
+                            "description": "Glob the path must match, e.g. 'models/**/*.glb' ('*' stays within a folder, '**' crosses folders). Patterns without '/' match the file name."
+                        },
+                        "max_results": {
+                            "type": "integer",
+                            "description": "Maximum number of assets to return (default: 200)"
+                        }
+                    }
+                }
+            }),
+            json!({
+                "name": "get_asset_info",
+                "description": "Details of one asset: texture dimensions, model mesh counts and bounds, or material fields",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "path": {
+                            "type": "string",
+                            "description": "Path relative to the assets directory"
+                        }
+                    },
+                    "required": ["path"]
+                }
+            }),
+            json!({
+                "name": "list_generated_assets",
+                "description": "List images made by generate_texture and generate_skybox with their prompts and seeds, newest first. Reuse an asset_id instead of generating a similar texture again.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "query": {
+                            "type": "string",
+                            "description": "Only list assets whose prompt contains this text"
+                        }
+                    }
+                }
+            }),
+            json!({
+                "name": "generate_texture",
+                "description": "Generate a texture from a text prompt using AI (Stable Diffusion). Waits for the result, sending progress notifications; use start_generation to run it in the background instead.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "prompt": {
+                            "type": "string",
+                            "description": "Text description of the texture to generate"
+                        },
+                        "width": {
+                            "type": "integer",
+                            "description": "Texture width in pixels (default: 512, must be multiple of 64)"
+                        },
+                        "height": {
+                            "type": "integer",
+                            "description": "Texture height in pixels (default: 512, must be multiple of 64)"
+                        },
+                        "quality": {
+                            "type": "string",
+                            "description": "Quality level: 'fast', 'standard', 'high', or 'best' (default: 'high')"
+                        },
+                        "seed": {
+                            "type": "integer",
+                            "description": "Random seed for reproducibility (optional)"
+                        }
+                    },
+                    "required": ["prompt"]
+                }
+            }),
+            json!({
+                "name": "generate_skybox",
+                "description": "Generate a 360-degree skybox from a text prompt using AI. Waits for the result, sending progress notifications; use start_generation to run it in the background instead.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "prompt": {
+                            "type": "string",
+                            "description": "Text description of the skybox environment"
+                        },
+                        "quality": {
+                            "type": "string",
+                            "description": "Quality level: 'fast', 'standard', 'high', or 'best' (default: 'high')"
+                        },
+                        "seed": {
+                            "type": "integer",
+                            "description": "Random seed for reproducibility (optional)"
+                        }
+                    },
+                    "required": ["prompt"]
+                }
+            }),
+            json!({
+                "name": "save_scene",
+                "description": "Save the current scene to a file",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "file_path": {
+                            "type": "string",
+                            "description": "Path to save the scene file (e.g., 'assets/scenes/my_scene.ron')"
+                        }
+                    },
+                    "required": ["file_path"]
+                }
+            }),
+            json!({
+                "name": "load_scene",
+                "description": "Load a scene from a file",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "file_path": {
+                            "type": "string",
+                            "description": "Path to the scene file to load (e.g., 'assets/scenes/castle.ron')"
+                        }
+                    },
+                    "required": ["file_path"]
+                }
+            }),
+            json!({
+                "name": "generate_music",
+                "description": "Generate music from a text description using AI (ACE-Step). Waits for the result, sending progress notifications; use start_generation to run it in the background instead.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "prompt": {
+                            "type": "string",
+                            "description": "Text description of the music to generate (e.g., 'Epic orchestral battle music with drums')"
+                        },
+                        "duration": {
+                            "type": "string",
+                            "description": "Duration: 'short' (~15s), 'medium' (~30s), 'long' (~60s), or 'extended' (~2min). Default: 'medium'"
+                        },
+                        "style": {
+                            "type": "string",
+                            "description": "Music style/genre: 'rock', 'pop', 'electronic', 'jazz', 'classical', 'cinematic', 'ambient', etc. Default: based on prompt"
+                        },
+                        "tempo": {
+                            "type": "integer",
+                            "description": "Tempo in BPM (e.g., 120). Optional."
+                        },
+                        "instrumental": {
+                            "type": "boolean",
+                            "description": "True for instrumental only (no vocals). Default: true"
+                        },
+                        "output_path": {
+                            "type": "string",
+                            "description": "Where to save the generated music, relative to the asset directory (e.g., 'music/battle.wav')"
+                        }
+                    },
+                    "required": ["prompt", "output_path"]
+                }
+            }),
+            json!({
+                "name": "start_generation",
+                "description": "Start generating a texture, skybox or music track in the background and return a job ID right away. Poll it with get_job_status; cancel it with cancel_job.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "kind": {
+                            "type": "string",
+                            "enum": ["texture", "skybox", "music"],
+                            "description": "What to generate"
+                        },
+                        "prompt": {
+                            "type": "string",
+                            "description": "Text description of the asset"
+                        },
+                        "width": {
+                            "type": "integer",
+                            "description": "Texture width in pixels (texture only, default: 512)"
+                        },
+                        "height": {
+                            "type": "integer",
+                            "description": "Texture height in pixels (texture only, default: 512)"
+                        },
+                        "quality": {
+                            "type": "string",
+                            "description": "Quality level: 'fast', 'standard', 'high', or 'best' (texture only, default: 'high')"
+                        },
+                        "seed": {
+                            "type": "integer",
+                            "description": "Random seed for reproducibility (optional)"
+                        },
+                        "output_path": {
+                            "type": "string",
+                            "description": "Where to save the track, relative to the asset directory (music only, required)"
+                        },
+                        "duration": {
+                            "type": "string",
+                            "description": "Music duration: 'short', 'medium', 'long' or 'extended' (default: 'medium')"
+                        },
+                        "style": {
+                            "type": "string",
+                            "description": "Music style/genre (optional)"
+                        },
+                        "tempo": {
+                            "type": "integer",
+                            "description": "Music tempo in BPM (optional)"
+                        },
+                        "instrumental": {
+                            "type": "boolean",
+                            "description": "Music without vocals (default: true)"
+                        }
+                    },
+                    "required": ["kind", "prompt"]
+                }
+            }),
+            json!({
+                "name": "get_job_status",
+                "description": "Status of a generation job: running, completed, failed or cancelled, its estimated progress and, once done, its result",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "job_id": {
+                            "type": "integer",
+                            "description": "Job ID from start_generation"
+                        }
+                    },
+                    "required": ["job_id"]
+                }
+            }),
+            json!({
+                "name": "cancel_job",
+                "description": "Cancel a running generation job",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "job_id": {
+                            "type": "integer",
+                            "description": "Job ID from start_generation"
+                        }
+                    },
+                    "required": ["job_id"]
+                }
+            }),
+            json!({
+                "name": "list_jobs",
+                "description": "List recent generation jobs and their status",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {}
+                }
+            }),
+            json!({
+                "name": "play_music",
+                "description": "Play a music file in the game engine",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "file_path": {
+                            "type": "string",
+                            "description": "Path to the music file to play"
+                        },
+                        "loop": {
+                            "type": "boolean",
+                            "description": "Whether to loop the music. Default: true"
+                        },
+                        "volume": {
+                            "type": "number",
+                            "description": "Volume level (0.0 to 1.0). Default: 0.8"
+                        },
+                        "fade_in": {
+                            "type": "number",
+                            "description": "Fade in duration in seconds. Default: 0.0 (no fade)"
+                        }
+                    },
+                    "required": ["file_path"]
+                }
+            }),
+            json!({
+                "name": "stop_music",
+                "description": "Stop currently playing music",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "fade_out": {
+                            "type": "number",
+                            "description": "Fade out duration in seconds. Default: 0.0 (stop immediately)"
+                        }
+                    }
+                }
+            }),
+        ]
+    }
+
+    pub fn call_tool(&mut self, params: &Value, request_id: Option<&Value>) -> Result<Value> {
+        self.request_id = request_id.cloned();
+        self.progress_token = params
+            .get("_meta")
+            .and_then(|meta| meta.get("progressToken"))
+            .cloned();
+
+        let tool_name = params
+            .get("name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing tool name"))?;
+
+        let arguments = params
+            .get("arguments")
+            .ok_or_else(|| anyhow!("Missing arguments"))?;
+
+        log::info!("Executing tool: {}", tool_name);
+        log::debug!("Arguments: {}", arguments);
+
+        match tool_name {
+            "create_entity" => self.create_entity(arguments),
+            "set_transform" => self.set_transform(arguments),
+            "list_entities" => self.list_entities(arguments),
+            "get_entity_info" => self.get_entity_info(arguments),
+            "delete_entity" => self.delete_entity(arguments),
+            "add_script" => self.add_script(arguments),
+            "attach_script_file" => self.attach_script_file(arguments),
+            "run_script_snippet" => self.run_script_snippet(arguments),
+            "load_model" => self.load_model(arguments),
+            "get_scene_info" => self.get_scene_info(arguments),
+            "validate_scene" => self.validate_scene(arguments),
+            "capture_viewport" => self.capture_viewport(arguments),
+            "set_editor_camera" => self.set_editor_camera(arguments),
+            "undo" | "redo" | "create_checkpoint" | "restore_checkpoint" => {
+                self.history_tool(tool_name, arguments)
+            }
+            "play_simulation" | "pause_simulation" | "step_simulation" | "stop_simulation"
+            | "get_simulation_state" => self.simulation_tool(tool_name, arguments),
+            "add_rigidbody" => self.add_rigidbody(arguments),
+            "add_collider" => self.add_collider(arguments),
+            "add_light" => self.add_light(arguments),
+            "add_component" | "remove_component" | "get_component" | "set_component_field" => {
+                self.component_tool(tool_name, arguments)
+            }
+            "find_entities_by_component" => self.find_entities_by_component(arguments),
+            "set_parent" => self.set_parent(arguments),
+            "get_children" | "get_scene_tree" => self.hierarchy_query(tool_name, arguments),
+            "raycast" => self.raycast(arguments),
+            "overlap_sphere" | "find_entities_near" => self.entity_query(tool_name, arguments),
+            "execute_batch" => self.execute_batch(arguments),
+            "generate_terrain" => self.generate_terrain(arguments),
+            "sculpt_terrain" => self.sculpt_terrain(arguments),
+            "set_material" => self.set_material(arguments),
+            "assign_texture" => self.assign_texture(arguments),
+            "list_assets" => self.list_assets(arguments),
+            "get_asset_info" => self.get_asset_info(arguments),
+            "list_generated_assets" => self.list_generated_assets(arguments),
+            "generate_texture" => self.generate_texture(arguments),
+            "generate_skybox" => self.generate_skybox(arguments),
+            "save_scene" => self.save_scene(arguments),
+            "load_scene" => self.load_scene(arguments),
+            "generate_music" => self.generate_music(arguments),
+            "start_generation" => self.start_generation(arguments),
+            "get_job_status" | "cancel_job" | "list_jobs" => self.job_tool(tool_name, arguments),
+            "play_music" => self.play_music(arguments),
+            "stop_music" => self.stop_music(arguments),
+            _ => self.plugin_tool(tool_name, arguments),
+        }
+    }
+
+    /// A tool added by an editor plugin; the editor runs it
+    fn plugin_tool(&self, tool_name: &str, args: &Value) -> Result<Value> {
+        let result = self.send_command(
+            "call_plugin_tool",
+            json!({ "name": tool_name, "arguments": args }),
+        )?;
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            }]
+        }))
+    }
+
+    fn create_entity(&self, args: &Value) -> Result<Value> {
+        let name = args
+            .get("name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing entity name"))?;
+
+        let position = args
+            .get("position")
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|v| v.as_f64())
+                    .map(|v| v as f32)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_else(|| vec![0.0, 0.0, 0.0]);
+
+        log::info!(
+            "Creating entity '{}' at position {:?}",
+            name,
+            position
+        );
+
+        let result = self.send_command("create_entity", json!({
+            "name": name,
+            "position": position
+        }))?;
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": format!("Created entity '{}' at position [{}, {}, {}]", name, position[0], position[1], position[2])
+            }]
+        }))
+    }
+
+    fn set_transform(&self, args: &Value) -> Result<Value> {
+        let entity_name = args
+            .get("entity_name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing entity_name"))?;
+
+        log::info!("Setting transform for entity '{}'", entity_name);
+
+        let result = self.send_command("set_transform", json!({
+            "entity_name": entity_name,
+            "position": args.get("position"),
+            "scale": args.get("scale"),
+        }))?;
+
+        let success = result.get("updated").and_then(|v| v.as_bool()).unwrap_or(false);
+        let message = if success {
+            format!("Updated transform for entity '{}'", entity_name)
+        } else {
+            result.get("error")
+                .and_then(|v| v.as_str())
+                .unwrap_or("Unknown error")
+                .to_string()
+        };
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": message
+            }]
+        }))
+    }
+
+    fn list_entities(&self, _args: &Value) -> Result<Value> {
+        log::info!("Listing all entities");
+
+        let result = self.send_command("list_entities", json!({}))?;
+
+        if let Some(entities) = result.get("entities").and_then(|v| v.as_array()) {
+            let entity_names: Vec<String> = entities
+                .iter()
+                .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                .collect();
+
+            Ok(json!({
+                "content": [{
+                    "type": "text",
+                    "text": if entity_names.is_empty() {
+                        "No entities in scene".to_string()
+                    } else {
+                        format!("Entities in scene:\n- {}", entity_names.join("\n- "))
+                    }
+                }]
+            }))
+        } else {
+            Ok(json!({
+                "content": [{
+                    "type": "text",
+                    "text": "No entities in scene"
+                }]
+            }))
+        }
+    }
+
+    fn get_entity_info(&self, args: &Value) -> Result<Value> {
+        let entity_name = args
+            .get("entity_name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing entity_name"))?;
+
+        log::info!("Getting info for entity '{}'", entity_name);
+
+        let result = self.send_command("get_entity_info", json!({
+            "entity_name": entity_name,
+        }))?;
+
+        if result.get("position").is_some() {
+            let pos = result.get("position").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+            let rot = result.get("rotation").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+            let scl = result.get("scale").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+
+            let info = format!(
+                "Entity: {}\nPosition: {:?}\nRotation: {:?}\nScale: {:?}",
+                entity_name, pos, rot, scl
+            );
+
+            Ok(json!({
+                "content": [{
+                    "type": "text",
+                    "text": info
+                }]
+            }))
+        } else {
+            let error = result.get("error")
+                .and_then(|v| v.as_str())
+                .unwrap_or("Unknown error");
+
+            Ok(json!({
+                "content": [{
+                    "type": "text",
+                    "text": format!("Error: {}", error)
+                }]
+            }))
+        }
+    }
+
+    fn delete_entity(&self, args: &Value) -> Result<Value> {
+        let entity_name = args
+            .get("entity_name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing entity_name"))?;
+
+        log::info!("Deleting entity '{}'", entity_name);
+
+        let result = self.send_command("delete_entity", json!({
+            "name": entity_name,
+        }))?;
+
+        let success = result.get("deleted").and_then(|v| v.as_bool()).unwrap_or(false);
+        let message = if success {
+            format!("Deleted entity '{}'", entity_name)
+        } else {
+            result.get("error")
+                .and_then(|v| v.as_str())
+                .unwrap_or("Unknown error")
+                .to_string()
+        };
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": message
+            }]
+        }))
+    }
+
+    fn add_script(&self, args: &Value) -> Result<Value> {
+        let entity_name = args
+            .get("entity_name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing entity_name"))?;
+
+        let script = args
+            .get("script")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing script"))?;
+
+        log::info!("Adding script to entity '{}'", entity_name);
+        log::debug!("Script length: {} bytes", script.len());
+
+        let result = self.send_command("add_script", json!({
+            "entity_name": entity_name,
+            "script": script,
+        }))?;
+
+        let success = result.get("script_added").and_then(|v| v.as_bool()).unwrap_or(false);
+        let message = if success {
+            let size = result.get("script_size").and_then(|v| v.as_u64()).unwrap_or(0);
+            format!(
+                "Successfully added script ({} bytes) to entity '{}'\n{}",
+                size,
+                entity_name,
+                Self::script_functions(&result)
+            )
+        } else {
+            result.get("error")
+                .and_then(|v| v.as_str())
+                .unwrap_or("Unknown error")
+                .to_string()
+        };
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": message
+            }]
+        }))
+    }
+
+    fn attach_script_file(&self, args: &Value) -> Result<Value> {
+        let entity_name = args
+            .get("entity_name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing entity_name"))?;
+
+        let path = args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing path"))?;
+
+        log::info!("Attaching script '{}' to entity '{}'", path, entity_name);
+
+        let result = self.send_command("attach_script_file", args.clone())?;
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": format!(
+                    "Attached '{}' to entity '{}'\n{}",
+                    path,
+                    entity_name,
+                    Self::script_functions(&result)
+                )
+            }]
+        }))
+    }
+
+    fn run_script_snippet(&self, args: &Value) -> Result<Value> {
+        log::info!("Running script snippet");
+
+        let result = self.send_command("run_script_snippet", args.clone())?;
+
+        let mut lines: Vec<String> = result
+            .get("output")
+            .and_then(|v| v.as_array())
+            .map(|output| output.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+            .unwrap_or_default();
+        match result.get("value").and_then(|v| v.as_str()) {
+            Some(value) => lines.push(format!("=> {}", value)),
+            None if lines.is_empty() => lines.push("(no output)".to_string()),
+            None => {}
+        }
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": lines.join("\n")
+            }]
+        }))
+    }
+
+    /// "Defines: start, update" line for a compiled script
+    fn script_functions(result: &Value) -> String {
+        let functions: Vec<&str> = result
+            .get("functions")
+            .and_then(|v| v.as_array())
+            .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect())
+            .unwrap_or_default();
+        if functions.is_empty() {
+            "The script defines no functions (start and update are called if defined)".to_string()
+        } else {
+            format!("Defines: {}", functions.join(", "))
+        }
+    }
+
+    fn load_model(&self, args: &Value) -> Result<Value> {
+        let entity_name = args
+            .get("entity_name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing entity_name"))?;
+
+        let model_path = args
+            .get("model_path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing model_path"))?;
+
+        let position = args
+            .get("position")
+            .and_then(|v| v.as_array())
+            .cloned();
+
+        log::info!(
+            "Loading model '{}' as entity '{}'",
+            model_path,
+            entity_name
+        );
+
+        let mut load_args = json!({
+            "entity_name": entity_name,
+            "model_path": model_path,
+        });
+
+        if let Some(pos) = position {
+            load_args["position"] = serde_json::Value::Array(pos);
+        }
+
+        let result = self.send_command("load_model", load_args)?;
+
+        let success = result.get("model_loaded").and_then(|v| v.as_bool()).unwrap_or(false);
+        let message = if success {
+            let pos = result.get("position").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+            format!("Successfully loaded model '{}' as entity '{}' at position {:?}", model_path, entity_name, pos)
+        } else {
+            result.get("error")
+                .and_then(|v| v.as_str())
+                .unwrap_or("Unknown error")
+                .to_string()
+        };
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": message
+            }]
+        }))
+    }
+
+    /// Undo history tools
+    fn history_tool(&self, tool_name: &str, args: &Value) -> Result<Value> {
+        log::info!("{}", tool_name);
+
+        let result = self.send_command(tool_name, args.clone())?;
+
+        let steps = result.get("steps").and_then(|v| v.as_u64()).unwrap_or(0);
+        let name = result.get("name").and_then(|v| v.as_str()).unwrap_or("");
+        let mut message = match tool_name {
+            "undo" => format!("Undid {} steps", steps),
+            "redo" => format!("Redid {} steps", steps),
+            "create_checkpoint" => format!("Created checkpoint '{}'", name),
+            _ => format!("Restored checkpoint '{}' ({} steps)", name, steps),
+        };
+        let undo_count = result.get("undo_count").and_then(|v| v.as_u64()).unwrap_or(0);
+        let redo_count = result.get("redo_count").and_then(|v| v.as_u64()).unwrap_or(0);
+        message.push_str(&format!("\n{} steps to undo, {} to redo", undo_count, redo_count));
+        if let Some(checkpoints) = result.get("checkpoints").and_then(|v| v.as_array()) {
+            let names: Vec<&str> = checkpoints.iter().filter_map(|v| v.as_str()).collect();
+            if !names.is_empty() {
+                message.push_str(&format!("\nCheckpoints: {}", names.join(", ")));
+            }
+        }
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": message
+            }]
+        }))
     }
 
-    pub fn call_tool(&mut self, params: &Value) -> Result<Value> {
-        let tool_name = params
-            .get("name")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow!("Missing tool name"))?;
+    /// Play mode control; every tool answers with the simulation state
+    fn simulation_tool(&self, tool_name: &str, args: &Value) -> Result<Value> {
+        log::info!("{}", tool_name);
 
-        let arguments = params
-            .get("arguments")
-            .ok_or_else(|| anyhow!("Missing arguments"))?;
+        let result = if tool_name == "step_simulation" {
+            // The editor answers once the frames have run, in real time
+            let seconds = match args.get("frames").and_then(|v| v.as_u64()) {
+                Some(frames) => frames as f32 / 60.0,
+                None => args.get("seconds").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32,
+            };
+            if !(0.0..=MAX_STEP_SECONDS).contains(&seconds) {
+                return Err(anyhow!(
+                    "A step must run 0 to {} seconds ({} frames)",
+                    MAX_STEP_SECONDS,
+                    MAX_STEP_SECONDS * 60.0
+                ));
+            }
+            let timeout = Duration::from_secs(5) + Duration::from_secs_f32(seconds * 60.0 / MIN_STEP_FPS);
+            self.send_command_with_timeout(tool_name, args.clone(), timeout)?
+        } else {
+            self.send_command(tool_name, args.clone())?
+        };
 
-        log::info!("Executing tool: {}", tool_name);
-        log::debug!("Arguments: {}", arguments);
+        let state = result.get("state").and_then(|v| v.as_str()).unwrap_or("unknown");
+        let time = result.get("simulated_time").and_then(|v| v.as_f64()).unwrap_or(0.0);
+        let frames = result.get("frames").and_then(|v| v.as_u64()).unwrap_or(0);
+        let message = if state == "editing" {
+            "Simulation stopped; the editor is editing the authored scene".to_string()
+        } else {
+            format!("Simulation {} at {:.2}s ({} frames simulated)", state, time, frames)
+        };
 
-        match tool_name {
-            "create_entity" => self.create_entity(arguments),
-            "set_transform" => self.set_transform(arguments),
-            "list_entities" => self.list_entities(arguments),
-            "get_entity_info" => self.get_entity_info(arguments),
-            "delete_entity" => self.delete_entity(arguments),
-            "add_script" => self.add_script(arguments),
-            "load_model" => self.load_model(arguments),
-            "get_scene_info" => self.get_scene_info(arguments),
-            "add_rigidbody" => self.add_rigidbody(arguments),
-            "add_collider" => self.add_collider(arguments),
-            "generate_texture" => self.generate_texture(arguments),
-            "generate_skybox" => self.generate_skybox(arguments),
-            "save_scene" => self.save_scene(arguments),
-            "load_scene" => self.load_scene(arguments),
-            "generate_music" => self.generate_music(arguments),
-            "play_music" => self.play_music(arguments),
-            "stop_music" => self.stop_music(arguments),
-            _ => Err(anyhow!("Unknown tool: {}", tool_name)),
-        }
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": message
+            }]
+        }))
     }
 
-    fn create_entity(&self, args: &Value) -> Result<Value> {
-        let name = args
-            .get("name")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow!("Missing entity name"))?;
-
-        let position = args
-            .get("position")
-            .and_then(|v| v.as_array())
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|v| v.as_f64())
-                    .map(|v| v as f32)
-                    .collect::<Vec<_>>()
-            })
-            .unwrap_or_else(|| vec![0.0, 0.0, 0.0]);
+    fn set_editor_camera(&self, args: &Value) -> Result<Value> {
+        log::info!("Moving editor camera");
 
-        log::info!(
-            "Creating entity '{}' at position {:?}",
-            name,
-            position
-        );
+        let result = self.send_command("set_editor_camera", args.clone())?;
 
-        let result = self.send_command("create_entity", json!({
-            "name": name,
-            "position": position
-        }))?;
+        let position = result.get("position").cloned().unwrap_or(Value::Null);
+        let target = result.get("target").cloned().unwrap_or(Value::Null);
+        let message = format!("Editor camera at {} looking at {}", position, target);
 
         Ok(json!({
             "content": [{
                 "type": "text",
-                "text": format!("Created entity '{}' at position [{}, {}, {}]", name, position[0], position[1], position[2])
+                "text": message
             }]
         }))
     }
 
-    fn set_transform(&self, args: &Value) -> Result<Value> {
-        let entity_name = args
-            .get("entity_name")
+    fn capture_viewport(&self, _args: &Value) -> Result<Value> {
+        log::info!("Capturing viewport");
+
+        let result = self.send_command("capture_viewport", json!({}))?;
+        let path = result
+            .get("path")
             .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow!("Missing entity_name"))?;
+            .ok_or_else(|| anyhow!("Editor did not return a screenshot path"))?;
 
-        log::info!("Setting transform for entity '{}'", entity_name);
+        let png = std::fs::read(path)
+            .map_err(|e| anyhow!("Failed to read screenshot {}: {}", path, e))?;
+        let _ = std::fs::remove_file(path);
 
-        let result = self.send_command("set_transform", json!({
-            "entity_name": entity_name,
-            "position": args.get("position"),
-            "scale": args.get("scale"),
-        }))?;
+        let size = png_dimensions(&png)
+            .map(|(width, height)| format!("{}x{}", width, height))
+            .unwrap_or_else(|| "unknown size".to_string());
 
-        let success = result.get("updated").and_then(|v| v.as_bool()).unwrap_or(false);
-        let message = if success {
-            format!("Updated transform for entity '{}'", entity_name)
+        Ok(json!({
+            "content": [
+                {
+                    "type": "image",
+                    "data": base64_encode(&png),
+                    "mimeType": "image/png"
+                },
+                {
+                    "type": "text",
+                    "text": format!("Viewport screenshot ({})", size)
+                }
+            ]
+        }))
+    }
+
+    fn validate_scene(&self, _args: &Value) -> Result<Value> {
+        log::info!("Validating scene");
+
+        let result = self.send_command("validate_scene", json!({}))?;
+
+        let issues = result.get("issues").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+        let mut message = if issues.is_empty() {
+            "Scene is valid: no issues found".to_string()
         } else {
-            result.get("error")
-                .and_then(|v| v.as_str())
-                .unwrap_or("Unknown error")
-                .to_string()
+            format!("Found {} issues:", issues.len())
         };
+        for issue in &issues {
+            let kind = issue.get("kind").and_then(|v| v.as_str()).unwrap_or("issue");
+            let text = issue.get("message").and_then(|v| v.as_str()).unwrap_or("");
+            message.push_str(&format!("\n- [{}] {}", kind, text));
+        }
 
         Ok(json!({
             "content": [{
@@ -526,100 +1848,236 @@ impl ToolRegistry {
         }))
     }
 
-    fn list_entities(&self, _args: &Value) -> Result<Value> {
-        log::info!("Listing all entities");
+    fn get_scene_info(&self, _args: &Value) -> Result<Value> {
+        log::info!("Getting scene info");
 
-        let result = self.send_command("list_entities", json!({}))?;
+        let result = self.send_command("get_scene_info", json!({}))?;
 
-        if let Some(entities) = result.get("entities").and_then(|v| v.as_array()) {
-            let entity_names: Vec<String> = entities
-                .iter()
-                .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                .collect();
+        if let Some(entity_count) = result.get("entity_count").and_then(|v| v.as_u64()) {
+            let entities: Vec<String> = result
+                .get("entities")
+                .and_then(|v| v.as_array())
+                .map(|arr| {
+                    arr.iter()
+                        .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            let entity_list = if entities.is_empty() {
+                "No entities".to_string()
+            } else {
+                entities.join(", ")
+            };
 
             Ok(json!({
                 "content": [{
                     "type": "text",
-                    "text": if entity_names.is_empty() {
-                        "No entities in scene".to_string()
-                    } else {
-                        format!("Entities in scene:\n- {}", entity_names.join("\n- "))
-                    }
+                    "text": format!("Scene Info:\nEntities: {}\n- {}", entity_count, entity_list)
                 }]
             }))
         } else {
             Ok(json!({
                 "content": [{
                     "type": "text",
-                    "text": "No entities in scene"
+                    "text": "Scene: Unknown\nEntities: 0"
                 }]
             }))
         }
     }
 
-    fn get_entity_info(&self, args: &Value) -> Result<Value> {
+    fn add_rigidbody(&self, args: &Value) -> Result<Value> {
         let entity_name = args
             .get("entity_name")
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow!("Missing entity_name"))?;
 
-        log::info!("Getting info for entity '{}'", entity_name);
+        let body_type = args
+            .get("body_type")
+            .and_then(|v| v.as_str())
+            .unwrap_or("dynamic");
 
-        let result = self.send_command("get_entity_info", json!({
-            "entity_name": entity_name,
-        }))?;
+        let mass = args
+            .get("mass")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(1.0);
 
-        if result.get("position").is_some() {
-            let pos = result.get("position").and_then(|v| v.as_array()).cloned().unwrap_or_default();
-            let rot = result.get("rotation").and_then(|v| v.as_array()).cloned().unwrap_or_default();
-            let scl = result.get("scale").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+        log::info!("Adding {} rigidbody to entity '{}'", body_type, entity_name);
 
-            let info = format!(
-                "Entity: {}\nPosition: {:?}\nRotation: {:?}\nScale: {:?}",
-                entity_name, pos, rot, scl
-            );
+        let result = self.send_command("add_rigidbody", json!({
+            "entity_name": entity_name,
+            "body_type": body_type,
+            "mass": mass,
+        }))?;
 
-            Ok(json!({
-                "content": [{
-                    "type": "text",
-                    "text": info
-                }]
-            }))
+        let success = result.get("rigidbody_added").and_then(|v| v.as_bool()).unwrap_or(false);
+        let message = if success {
+            format!("Successfully added {} rigidbody to entity '{}'", body_type, entity_name)
         } else {
-            let error = result.get("error")
+            result.get("error")
                 .and_then(|v| v.as_str())
-                .unwrap_or("Unknown error");
+                .unwrap_or("Unknown error")
+                .to_string()
+        };
 
-            Ok(json!({
-                "content": [{
-                    "type": "text",
-                    "text": format!("Error: {}", error)
-                }]
-            }))
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": message
+            }]
+        }))
+    }
+
+    fn add_light(&self, args: &Value) -> Result<Value> {
+        let entity_name = args
+            .get("entity_name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing entity_name"))?;
+
+        log::info!("Setting light on entity '{}'", entity_name);
+
+        let result = self.send_command("add_light", args.clone())?;
+
+        let created = result.get("created").and_then(|v| v.as_bool()).unwrap_or(false);
+        let light = result.get("light").cloned().unwrap_or(Value::Null);
+        let message = format!(
+            "{} light on entity '{}':\n{}",
+            if created { "Created" } else { "Updated" },
+            entity_name,
+            serde_json::to_string_pretty(&light)?
+        );
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": message
+            }]
+        }))
+    }
+
+    fn generate_terrain(&self, args: &Value) -> Result<Value> {
+        log::info!("Generating terrain");
+
+        let result = self.send_command("generate_terrain", args.clone())?;
+
+        let entity_name = result.get("entity_name").and_then(|v| v.as_str()).unwrap_or("Terrain");
+        let created = result.get("created").and_then(|v| v.as_bool()).unwrap_or(false);
+        let generator = result.get("generator").cloned().unwrap_or(Value::Null);
+        let height_range = result.get("height_range").cloned().unwrap_or(Value::Null);
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": format!(
+                    "{} terrain on entity '{}' (heights {}):\n{}",
+                    if created { "Created" } else { "Regenerated" },
+                    entity_name,
+                    height_range,
+                    serde_json::to_string_pretty(&generator)?
+                )
+            }]
+        }))
+    }
+
+    fn sculpt_terrain(&self, args: &Value) -> Result<Value> {
+        let operation = args
+            .get("operation")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing operation"))?;
+
+        log::info!("Sculpting terrain: {}", operation);
+
+        let result = self.send_command("sculpt_terrain", args.clone())?;
+
+        let modified = result.get("modified").and_then(|v| v.as_u64()).unwrap_or(0);
+        let center_height = result.get("center_height").and_then(|v| v.as_f64()).unwrap_or(0.0);
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": format!(
+                    "Applied '{}': {} terrain points changed, height at center is now {:.2}",
+                    operation, modified, center_height
+                )
+            }]
+        }))
+    }
+
+    fn set_material(&self, args: &Value) -> Result<Value> {
+        if args.get("material_path").is_none() && args.get("entity_name").is_none() {
+            return Err(anyhow!("Give material_path, entity_name or both"));
+        }
+
+        let result = self.send_command("set_material", args.clone())?;
+
+        let material_path = result.get("material_path").and_then(|v| v.as_str()).unwrap_or("");
+        let created = result.get("created").and_then(|v| v.as_bool()).unwrap_or(false);
+        let material = result.get("material").cloned().unwrap_or(Value::Null);
+        let mut message = format!(
+            "{} material '{}'",
+            if created { "Created" } else { "Updated" },
+            material_path
+        );
+        if let Some(entity_name) = result.get("entity_name").and_then(|v| v.as_str()) {
+            message.push_str(&format!(" and assigned it to '{}'", entity_name));
         }
+        message.push_str(&format!(":\n{}", serde_json::to_string_pretty(&material)?));
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": message
+            }]
+        }))
     }
 
-    fn delete_entity(&self, args: &Value) -> Result<Value> {
+    fn assign_texture(&self, args: &Value) -> Result<Value> {
         let entity_name = args
             .get("entity_name")
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow!("Missing entity_name"))?;
 
-        log::info!("Deleting entity '{}'", entity_name);
+        let texture = args
+            .get("texture")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing texture"))?;
+
+        let slot = args.get("slot").and_then(|v| v.as_str()).unwrap_or("albedo");
+
+        log::info!("Assigning texture '{}' to {} of entity '{}'", texture, slot, entity_name);
+
+        let result = self.send_command("assign_texture", args.clone())?;
+
+        let material_path = result.get("material_path").and_then(|v| v.as_str()).unwrap_or("");
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": format!(
+                    "Assigned '{}' as the {} texture of '{}' (material: {})",
+                    texture, slot, entity_name, material_path
+                )
+            }]
+        }))
+    }
+
+    fn list_assets(&self, args: &Value) -> Result<Value> {
+        log::info!("Listing assets");
 
-        let result = self.send_command("delete_entity", json!({
-            "name": entity_name,
-        }))?;
+        let result = self.send_command("list_assets", args.clone())?;
 
-        let success = result.get("deleted").and_then(|v| v.as_bool()).unwrap_or(false);
-        let message = if success {
-            format!("Deleted entity '{}'", entity_name)
-        } else {
-            result.get("error")
-                .and_then(|v| v.as_str())
-                .unwrap_or("Unknown error")
-                .to_string()
-        };
+        let count = result.get("count").and_then(|v| v.as_u64()).unwrap_or(0);
+        let mut message = format!("Found {} assets", count);
+        if let Some(assets) = result.get("assets").and_then(|v| v.as_array()) {
+            for asset in assets {
+                let path = asset.get("path").and_then(|v| v.as_str()).unwrap_or("?");
+                let kind = asset.get("type").and_then(|v| v.as_str()).unwrap_or("file");
+                message.push_str(&format!("\n- {} ({})", path, kind));
+            }
+        }
+        if result.get("truncated").and_then(|v| v.as_bool()).unwrap_or(false) {
+            message.push_str("\n(list truncated; narrow it with type or pattern, or raise max_results)");
+        }
 
         Ok(json!({
             "content": [{
@@ -629,35 +2087,46 @@ impl ToolRegistry {
         }))
     }
 
-    fn add_script(&self, args: &Value) -> Result<Value> {
-        let entity_name = args
-            .get("entity_name")
+    fn get_asset_info(&self, args: &Value) -> Result<Value> {
+        let path = args
+            .get("path")
             .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow!("Missing entity_name"))?;
+            .ok_or_else(|| anyhow!("Missing path"))?;
 
-        let script = args
-            .get("script")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow!("Missing script"))?;
+        log::info!("Getting info for asset '{}'", path);
 
-        log::info!("Adding script to entity '{}'", entity_name);
-        log::debug!("Script length: {} bytes", script.len());
+        let result = self.send_command("get_asset_info", args.clone())?;
 
-        let result = self.send_command("add_script", json!({
-            "entity_name": entity_name,
-            "script": script,
-        }))?;
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            }]
+        }))
+    }
 
-        let success = result.get("script_added").and_then(|v| v.as_bool()).unwrap_or(false);
-        let message = if success {
-            let size = result.get("script_size").and_then(|v| v.as_u64()).unwrap_or(0);
-            format!("Successfully added script ({} bytes) to entity '{}'", size, entity_name)
-        } else {
-            result.get("error")
-                .and_then(|v| v.as_str())
-                .unwrap_or("Unknown error")
-                .to_string()
-        };
+    fn list_generated_assets(&self, args: &Value) -> Result<Value> {
+        log::info!("Listing generated assets");
+
+        let result = self.send_command("list_generated_assets", args.clone())?;
+
+        let count = result.get("count").and_then(|v| v.as_u64()).unwrap_or(0);
+        let mut message = format!("Found {} generated assets", count);
+        if let Some(assets) = result.get("assets").and_then(|v| v.as_array()) {
+            for asset in assets {
+                let id = asset.get("id").and_then(|v| v.as_str()).unwrap_or("?");
+                let prompt = asset.get("prompt").and_then(|v| v.as_str()).unwrap_or("");
+                let width = asset.get("width").and_then(|v| v.as_u64()).unwrap_or(0);
+                let height = asset.get("height").and_then(|v| v.as_u64()).unwrap_or(0);
+                message.push_str(&format!("\n- {} ({}x{}): \"{}\"", id, width, height, prompt));
+                if let Some(seed) = asset.get("seed").and_then(|v| v.as_u64()) {
+                    message.push_str(&format!(", seed {}", seed));
+                }
+                if let Some(path) = asset.get("asset_path").and_then(|v| v.as_str()) {
+                    message.push_str(&format!(", imported as {}", path));
+                }
+            }
+        }
 
         Ok(json!({
             "content": [{
@@ -667,48 +2136,62 @@ impl ToolRegistry {
         }))
     }
 
-    fn load_model(&self, args: &Value) -> Result<Value> {
+    fn set_parent(&self, args: &Value) -> Result<Value> {
         let entity_name = args
             .get("entity_name")
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow!("Missing entity_name"))?;
 
-        let model_path = args
-            .get("model_path")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow!("Missing model_path"))?;
+        log::info!("Reparenting entity '{}'", entity_name);
 
-        let position = args
-            .get("position")
-            .and_then(|v| v.as_array())
-            .cloned();
+        let result = self.send_command("set_parent", args.clone())?;
 
-        log::info!(
-            "Loading model '{}' as entity '{}'",
-            model_path,
-            entity_name
-        );
+        let message = match result.get("parent_name").and_then(|v| v.as_str()) {
+            Some(parent) => format!("'{}' is now a child of '{}'", entity_name, parent),
+            None => format!("'{}' is now at the scene root", entity_name),
+        };
 
-        let mut load_args = json!({
-            "entity_name": entity_name,
-            "model_path": model_path,
-        });
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": format!("{} (local position {})", message, result.get("local_position").cloned().unwrap_or(Value::Null))
+            }]
+        }))
+    }
 
-        if let Some(pos) = position {
-            load_args["position"] = serde_json::Value::Array(pos);
-        }
+    /// get_children and get_scene_tree, returned as the editor's JSON tree
+    fn hierarchy_query(&self, tool_name: &str, args: &Value) -> Result<Value> {
+        log::info!("{}", tool_name);
 
-        let result = self.send_command("load_model", load_args)?;
+        let result = self.send_command(tool_name, args.clone())?;
 
-        let success = result.get("model_loaded").and_then(|v| v.as_bool()).unwrap_or(false);
-        let message = if success {
-            let pos = result.get("position").and_then(|v| v.as_array()).cloned().unwrap_or_default();
-            format!("Successfully loaded model '{}' as entity '{}' at position {:?}", model_path, entity_name, pos)
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            }]
+        }))
+    }
+
+    fn raycast(&self, args: &Value) -> Result<Value> {
+        log::info!("Raycasting from {:?}", args.get("origin"));
+
+        let result = self.send_command("raycast", args.clone())?;
+
+        let message = if result.get("hit").and_then(|v| v.as_bool()).unwrap_or(false) {
+            let point = result.get("point").cloned().unwrap_or(Value::Null);
+            let distance = result.get("distance").and_then(|v| v.as_f64()).unwrap_or(0.0);
+            let surface = match result.get("entity_name").and_then(|v| v.as_str()) {
+                Some(name) => format!("entity '{}'", name),
+                None => "terrain".to_string(),
+            };
+            let mut message = format!("Hit {} at {} (distance {:.2})", surface, point, distance);
+            if let Some(normal) = result.get("normal").filter(|n| !n.is_null()) {
+                message.push_str(&format!(", surface normal {}", normal));
+            }
+            message
         } else {
-            result.get("error")
-                .and_then(|v| v.as_str())
-                .unwrap_or("Unknown error")
-                .to_string()
+            "No hit".to_string()
         };
 
         Ok(json!({
@@ -719,82 +2202,117 @@ impl ToolRegistry {
         }))
     }
 
-    fn get_scene_info(&self, _args: &Value) -> Result<Value> {
-        log::info!("Getting scene info");
+    /// overlap_sphere and find_entities_near, which both answer with an entity list
+    fn entity_query(&self, tool_name: &str, args: &Value) -> Result<Value> {
+        log::info!("{} with radius {:?}", tool_name, args.get("radius"));
 
-        let result = self.send_command("get_scene_info", json!({}))?;
+        let result = self.send_command(tool_name, args.clone())?;
 
-        if let Some(entity_count) = result.get("entity_count").and_then(|v| v.as_u64()) {
-            let entities: Vec<String> = result
-                .get("entities")
-                .and_then(|v| v.as_array())
-                .map(|arr| {
-                    arr.iter()
-                        .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                        .collect()
-                })
-                .unwrap_or_default();
+        let count = result.get("count").and_then(|v| v.as_u64()).unwrap_or(0);
+        let entities = result.get("entities").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+        let mut message = format!("Found {} entities", count);
+        if (entities.len() as u64) < count {
+            message.push_str(&format!(" (showing the closest {})", entities.len()));
+        }
+        for entity in &entities {
+            let name = entity.get("name").and_then(|v| v.as_str()).unwrap_or("?");
+            let position = entity.get("position").cloned().unwrap_or(Value::Null);
+            let distance = entity.get("distance").and_then(|v| v.as_f64()).unwrap_or(0.0);
+            message.push_str(&format!("\n- {} at {} (distance {:.2})", name, position, distance));
+        }
 
-            let entity_list = if entities.is_empty() {
-                "No entities".to_string()
-            } else {
-                entities.join(", ")
-            };
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": message
+            }]
+        }))
+    }
 
-            Ok(json!({
-                "content": [{
-                    "type": "text",
-                    "text": format!("Scene Info:\nEntities: {}\n- {}", entity_count, entity_list)
-                }]
-            }))
-        } else {
-            Ok(json!({
-                "content": [{
-                    "type": "text",
-                    "text": "Scene: Unknown\nEntities: 0"
-                }]
-            }))
+    fn execute_batch(&self, args: &Value) -> Result<Value> {
+        let count = args
+            .get("commands")
+            .and_then(|v| v.as_array())
+            .map(|commands| commands.len())
+            .ok_or_else(|| anyhow!("Missing commands array"))?;
+
+        log::info!("Executing batch of {} commands", count);
+
+        let result = self.send_command("execute_batch", args.clone())?;
+
+        let results = result.get("results").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+        let mut message = format!("Executed {} commands:", results.len());
+        for (index, entry) in results.iter().enumerate() {
+            let command = entry.get("command").and_then(|v| v.as_str()).unwrap_or("?");
+            let detail = entry.get("result").cloned().unwrap_or(Value::Null);
+            message.push_str(&format!("\n{}. {}: {}", index + 1, command, detail));
         }
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": message
+            }]
+        }))
     }
 
-    fn add_rigidbody(&self, args: &Value) -> Result<Value> {
+    /// Generic component tools, forwarded to the editor's component registry
+    fn component_tool(&self, tool_name: &str, args: &Value) -> Result<Value> {
         let entity_name = args
             .get("entity_name")
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow!("Missing entity_name"))?;
 
-        let body_type = args
-            .get("body_type")
+        let component = args
+            .get("component")
             .and_then(|v| v.as_str())
-            .unwrap_or("dynamic");
+            .ok_or_else(|| anyhow!("Missing component"))?;
+
+        log::info!("{} {} on entity '{}'", tool_name, component, entity_name);
+
+        let result = self.send_command(tool_name, args.clone())?;
+
+        let message = match tool_name {
+            "remove_component" => format!("Removed {} from entity '{}'", component, entity_name),
+            _ => {
+                let data = result.get("data").cloned().unwrap_or(Value::Null);
+                let verb = match tool_name {
+                    "add_component" => "Added",
+                    "set_component_field" => "Updated",
+                    _ => "Current",
+                };
+                format!(
+                    "{} {} on entity '{}':\n{}",
+                    verb,
+                    component,
+                    entity_name,
+                    serde_json::to_string_pretty(&data)?
+                )
+            }
+        };
 
-        let mass = args
-            .get("mass")
-            .and_then(|v| v.as_f64())
-            .unwrap_or(1.0);
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": message
+            }]
+        }))
+    }
 
-        log::info!("Adding {} rigidbody to entity '{}'", body_type, entity_name);
+    fn find_entities_by_component(&self, args: &Value) -> Result<Value> {
+        let component = args
+            .get("component")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing component"))?;
 
-        let result = self.send_command("add_rigidbody", json!({
-            "entity_name": entity_name,
-            "body_type": body_type,
-            "mass": mass,
-        }))?;
+        log::info!("Finding entities with {}", component);
 
-        let success = result.get("rigidbody_added").and_then(|v| v.as_bool()).unwrap_or(false);
-        let message = if success {
-            format!("Successfully added {} rigidbody to entity '{}'", body_type, entity_name)
-        } else {
-            result.get("error")
-                .and_then(|v| v.as_str())
-                .unwrap_or("Unknown error")
-                .to_string()
-        };
+        let result = self.send_command("find_entities_by_component", args.clone())?;
 
         Ok(json!({
             "content": [{
                 "type": "text",
-                "text": message
+                "text": serde_json::to_string_pretty(&result)?
             }]
         }))
     }
@@ -881,7 +2399,8 @@ impl ToolRegistry {
             quality
         );
 
-        let result = self.send_command("generate_texture", json!({
+        let result = self.generate(json!({
+            "kind": "texture",
             "prompt": prompt,
             "width": width,
             "height": height,
@@ -889,20 +2408,12 @@ impl ToolRegistry {
             "seed": seed,
         }))?;
 
-        let success = result.get("generated").and_then(|v| v.as_bool()).unwrap_or(false);
-        let asset_id = result.get("asset_id").and_then(|v| v.as_str());
-
-        let message = if success {
-            if let Some(id) = asset_id {
-                format!("Successfully generated texture '{}' (ID: {})", prompt, id)
-            } else {
-                format!("Successfully generated texture '{}'", prompt)
-            }
-        } else {
-            result.get("error")
-                .and_then(|v| v.as_str())
-                .unwrap_or("Unknown error")
-                .to_string()
+        let message = match result.get("asset_id").and_then(|v| v.as_str()) {
+            Some(id) => format!(
+                "Successfully generated texture '{}' (ID: {}). Pass the ID to assign_texture or set_material to use it.",
+                prompt, id
+            ),
+            None => format!("Successfully generated texture '{}'", prompt),
         };
 
         Ok(json!({
@@ -928,26 +2439,16 @@ impl ToolRegistry {
 
         log::info!("Generating skybox '{}' (quality: {})", prompt, quality);
 
-        let result = self.send_command("generate_skybox", json!({
+        let result = self.generate(json!({
+            "kind": "skybox",
             "prompt": prompt,
             "quality": quality,
             "seed": seed,
         }))?;
 
-        let success = result.get("generated").and_then(|v| v.as_bool()).unwrap_or(false);
-        let asset_id = result.get("asset_id").and_then(|v| v.as_str());
-
-        let message = if success {
-            if let Some(id) = asset_id {
-                format!("Successfully generated skybox '{}' (ID: {})", prompt, id)
-            } else {
-                format!("Successfully generated skybox '{}'", prompt)
-            }
-        } else {
-            result.get("error")
-                .and_then(|v| v.as_str())
-                .unwrap_or("Unknown error")
-                .to_string()
+        let message = match result.get("asset_id").and_then(|v| v.as_str()) {
+            Some(id) => format!("Successfully generated skybox '{}' (ID: {})", prompt, id),
+            None => format!("Successfully generated skybox '{}'", prompt),
         };
 
         Ok(json!({
@@ -1032,48 +2533,122 @@ impl ToolRegistry {
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow!("Missing output_path"))?;
 
-        let duration = args
-            .get("duration")
+        log::info!("Generating music '{}' -> '{}'", prompt, output_path);
+
+        let mut job_args = args.clone();
+        job_args["kind"] = json!("music");
+        let result = self.generate(job_args)?;
+
+        let duration = result.get("duration_secs").and_then(|v| v.as_u64()).unwrap_or(0);
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": format!(
+                    "Generated music '{}' ({}s) and saved it to '{}'. Play it with play_music.",
+                    prompt, duration, output_path
+                )
+            }]
+        }))
+    }
+
+    fn start_generation(&self, args: &Value) -> Result<Value> {
+        let kind = args
+            .get("kind")
             .and_then(|v| v.as_str())
-            .unwrap_or("medium");
+            .ok_or_else(|| anyhow!("Missing kind"))?;
 
-        let style = args.get("style").and_then(|v| v.as_str());
-        let tempo = args.get("tempo").and_then(|v| v.as_u64());
-        let instrumental = args.get("instrumental").and_then(|v| v.as_bool()).unwrap_or(true);
+        log::info!("Starting {} generation", kind);
 
-        log::info!(
-            "Music generation requested: '{}' -> '{}'",
-            prompt,
-            output_path
-        );
+        let result = self.send_command("start_generation", args.clone())?;
+        let job_id = result.get("job_id").and_then(|v| v.as_u64()).unwrap_or(0);
 
-        // Return a message for Claude to handle
-        // Claude will see this and know to use WebFetch or other tools to call ACE-Step
         Ok(json!({
             "content": [{
                 "type": "text",
                 "text": format!(
-                    "Music generation request received:\n\
-                     Prompt: {}\n\
-                     Duration: {}\n\
-                     Style: {}\n\
-                     Tempo: {}\n\
-                     Instrumental: {}\n\
-                     Output: {}\n\n\
-                     Please use the ACE-Step API at http://localhost:7865 to generate this music.\n\
-                     Then save the generated audio to '{}' and confirm completion.",
-                    prompt,
-                    duration,
-                    style.unwrap_or("auto"),
-                    tempo.map(|t| t.to_string()).unwrap_or_else(|| "auto".to_string()),
-                    instrumental,
-                    output_path,
-                    output_path
+                    "Started {} generation (job {}). Poll it with get_job_status.",
+                    kind, job_id
                 )
             }]
         }))
     }
 
+    fn job_tool(&self, tool_name: &str, args: &Value) -> Result<Value> {
+        log::info!("{}", tool_name);
+
+        let result = self.send_command(tool_name, args.clone())?;
+
+        Ok(json!({
+            "content": [{
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            }]
+        }))
+    }
+
+    /// Start a generation job and wait for it, returning its result (or an
+    /// error if it failed or was cancelled)
+    fn generate(&self, args: Value) -> Result<Value> {
+        let started = self.send_command("start_generation", args)?;
+        let job_id = started
+            .get("job_id")
+            .and_then(|v| v.as_u64())
+            .ok_or_else(|| anyhow!("Editor did not return a job ID"))?;
+
+        let status = self.wait_for_job(job_id)?;
+        match status.get("status").and_then(|v| v.as_str()) {
+            Some("completed") => Ok(status.get("result").cloned().unwrap_or(Value::Null)),
+            Some("cancelled") => Err(anyhow!("Generation job {} was cancelled", job_id)),
+            _ => Err(anyhow!(
+                "{}",
+                status
+                    .get("error")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("Generation failed")
+            )),
+        }
+    }
+
+    /// Poll a job until it finishes, sending progress notifications when the
+    /// client asked for them and cancelling the job if the client cancels
+    /// the request
+    fn wait_for_job(&self, job_id: u64) -> Result<Value> {
+        let job = json!({ "job_id": job_id });
+        loop {
+            let status = self.send_command("get_job_status", job.clone())?;
+            if status.get("status").and_then(|v| v.as_str()) != Some("running") {
+                return Ok(status);
+            }
+
+            if self
+                .request_id
+                .as_ref()
+                .is_some_and(|id| self.cancelled.is_cancelled(id))
+            {
+                log::info!("Request cancelled, cancelling generation job {}", job_id);
+                // The job may have finished in the meantime
+                return self
+                    .send_command("cancel_job", job.clone())
+                    .or_else(|_| self.send_command("get_job_status", job.clone()));
+            }
+
+            if let Some(token) = &self.progress_token {
+                let progress = status.get("progress").and_then(|v| v.as_f64()).unwrap_or(0.0);
+                let kind = status.get("kind").and_then(|v| v.as_str()).unwrap_or("asset");
+                let elapsed = status.get("elapsed_secs").and_then(|v| v.as_f64()).unwrap_or(0.0);
+                McpNotification::progress(
+                    token.clone(),
+                    progress,
+                    1.0,
+                    &format!("Generating {} ({:.0}s elapsed)", kind, elapsed),
+                )
+                .send()?;
+            }
+
+            std::thread::sleep(JOB_POLL_INTERVAL);
+        }
+    }
+
     fn play_music(&self, args: &Value) -> Result<Value> {
         let file_path = args
             .get("file_path")
@@ -1142,3 +2717,47 @@ impl ToolRegistry {
         }))
     }
 }
+
+/// Standard base64 with padding, for image content
+pub(crate) fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let triple = ((chunk[0] as u32) << 16)
+            | ((chunk.get(1).copied().unwrap_or(0) as u32) << 8)
+            | chunk.get(2).copied().unwrap_or(0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[((triple >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Width and height from a PNG's IHDR chunk
+fn png_dimensions(png: &[u8]) -> Option<(u32, u32)> {
+    if png.len() < 24 || &png[..8] != b"\x89PNG\r\n\x1a\n" || &png[12..16] != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(png[16..20].try_into().ok()?);
+    let height = u32::from_be_bytes(png[20..24].try_into().ok()?);
+    Some((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_encode(&[0xff, 0xfe]), "//4=");
+    }
+}
@@ -0,0 +1,377 @@
+// Net client - connects to a server, mirrors its snapshots into the local
+// scene and exchanges RPC messages. Between snapshots, interpolate() moves
+// remote entities smoothly and predict() moves the client's own character.
+
+use std::net::{SocketAddr, ToSocketAddrs};
+
+use anyhow::{anyhow, Result};
+use engine_physics::{CharacterController, CharacterSweep, PhysicsWorld};
+use engine_scene::components::{NetSmoothing, Replicated};
+use engine_scene::entity::EntityId;
+use engine_scene::scene::Scene;
+use glam::Vec3;
+
+use crate::interpolation::Interpolator;
+use crate::prediction::{step_character, CharacterState, Predictor};
+use crate::protocol::{ClientId, ClientMessage, RpcMessage, ServerMessage, PROTOCOL_VERSION};
+use crate::replication::SnapshotReceiver;
+use crate::transport::{Connection, Endpoint};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientEvent {
+    Connected { client_id: ClientId },
+    /// The server turned the connection down; the client is closed
+    Rejected { reason: String },
+    /// The server said goodbye or went quiet; the client is closed
+    Disconnected { reason: String },
+    Rpc(RpcMessage),
+}
+
+pub struct NetClient {
+    endpoint: Endpoint,
+    connection: Connection<ClientMessage, ServerMessage>,
+    client_id: Option<ClientId>,
+    tick_rate: u32,
+    snapshots: SnapshotReceiver,
+    interpolator: Interpolator,
+    predictor: Predictor,
+    /// Server id of the character this client predicts
+    character: Option<u64>,
+    /// The server's latest state of that character, not yet reconciled
+    unreconciled: Option<CharacterState>,
+    closed: bool,
+}
+
+impl NetClient {
+    /// Start connecting; ClientEvent::Connected arrives from poll() once
+    /// the server has answered
+    pub fn connect(server: impl ToSocketAddrs, name: &str) -> Result<Self> {
+        let server_addr: SocketAddr = server
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| anyhow!("No address to connect to"))?;
+        let local = if server_addr.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let endpoint = Endpoint::bind(local)?;
+        let mut connection = Connection::new(server_addr);
+        connection.send_reliable(ClientMessage::Hello {
+            protocol: PROTOCOL_VERSION,
+            name: name.to_string(),
+        });
+        connection.flush(&endpoint)?;
+        log::info!("Connecting to {} as '{}'", server_addr, name);
+        Ok(Self {
+            endpoint,
+            connection,
+            client_id: None,
+            tick_rate: 0,
+            snapshots: SnapshotReceiver::default(),
+            interpolator: Interpolator::new(),
+            predictor: Predictor::default(),
+            character: None,
+            unreconciled: None,
+            closed: false,
+        })
+    }
+
+    /// How far in the past remote entities are shown (seconds)
+    pub fn with_interpolation_delay(mut self, delay: f64) -> Self {
+        self.interpolator = Interpolator::new().with_delay(delay);
+        self
+    }
+
+    pub fn server_addr(&self) -> SocketAddr {
+        self.connection.addr
+    }
+
+    /// Id the server gave this client (None until connected)
+    pub fn client_id(&self) -> Option<ClientId> {
+        self.client_id
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.client_id.is_some() && !self.closed
+    }
+
+    /// False once the server rejected, dropped or timed out the connection
+    pub fn is_open(&self) -> bool {
+        !self.closed
+    }
+
+    /// Snapshots per second the server sends (0 until connected)
+    pub fn tick_rate(&self) -> u32 {
+        self.tick_rate
+    }
+
+    /// Newest snapshot applied to the scene
+    pub fn latest_tick(&self) -> Option<u64> {
+        self.snapshots.latest_tick()
+    }
+
+    /// The local entity mirroring a server entity
+    pub fn local_entity(&self, server_id: u64) -> Option<EntityId> {
+        self.snapshots.local_entity(server_id)
+    }
+
+    /// The local entity of the character this client predicts
+    pub fn predicted_entity(&self) -> Option<EntityId> {
+        self.local_entity(self.character?)
+    }
+
+    /// Take in what the server sent, applying snapshots to `scene`, and
+    /// send acknowledgements and anything queued
+    pub fn poll(&mut self, scene: &mut Scene) -> Vec<ClientEvent> {
+        let mut events = Vec::new();
+        if self.closed {
+            return events;
+        }
+
+        for (addr, packet) in self.endpoint.receive::<ServerMessage>() {
+            if addr != self.connection.addr {
+                continue;
+            }
+            for message in self.connection.receive(packet) {
+                match message {
+                    ServerMessage::Welcome {
+                        client_id,
+                        tick_rate,
+                    } => {
+                        log::info!("Connected to {} as client {}", addr, client_id.0);
+                        self.client_id = Some(client_id);
+                        self.tick_rate = tick_rate;
+                        events.push(ClientEvent::Connected { client_id });
+                    }
+                    ServerMessage::Rejected { reason } => {
+                        self.closed = true;
+                        events.push(ClientEvent::Rejected { reason });
+                    }
+                    ServerMessage::Snapshot(snapshot) => {
+                        let tick = snapshot.tick;
+                        let character = snapshot.character.clone();
+                        self.snapshots
+                            .set_predicted(character.as_ref().map(|state| state.entity));
+                        if self.snapshots.apply(snapshot, scene) {
+                            self.connection
+                                .send_unreliable(ClientMessage::AckSnapshot { tick });
+                            if let Some(world) = self.snapshots.world() {
+                                let time = tick as f64 / self.tick_rate.max(1) as f64;
+                                self.interpolator.record(time, world);
+                            }
+                            self.character = character.as_ref().map(|state| state.entity);
+                            self.unreconciled = character;
+                        }
+                    }
+                    ServerMessage::Rpc(message) => events.push(ClientEvent::Rpc(message)),
+                    ServerMessage::Goodbye { reason } => {
+                        self.closed = true;
+                        events.push(ClientEvent::Disconnected { reason });
+                    }
+                }
+            }
+        }
+
+        if !self.closed && self.connection.timed_out() {
+            self.closed = true;
+            events.push(ClientEvent::Disconnected {
+                reason: "server timed out".to_string(),
+            });
+        }
+        if !self.closed {
+            self.flush();
+        }
+        events
+    }
+
+    /// Move remote entities to where they were a moment ago, blending
+    /// between snapshots. Entities with NetSmoothing::Snap and the
+    /// predicted character are left alone. Call every frame.
+    pub fn interpolate(&mut self, scene: &mut Scene, dt: f32) {
+        if self.interpolator.advance(dt as f64).is_none() {
+            return;
+        }
+        let predicted = self.predicted_entity();
+        for (server_id, local) in self.snapshots.mirrored() {
+            if Some(local) == predicted {
+                continue;
+            }
+            let Some(entity) = scene.get_entity_mut(local) else {
+                continue;
+            };
+            let smoothing = entity
+                .get_component::<Replicated>()
+                .map_or(NetSmoothing::default(), |replicated| replicated.smoothing);
+            if smoothing == NetSmoothing::Snap {
+                continue;
+            }
+            if let Some(transform) = self.interpolator.sample(server_id) {
+                entity.transform = transform;
+            }
+        }
+    }
+
+    /// Move the predicted character by this frame's input, after
+    /// reconciling with the server's newest state, and send the input to
+    /// the server. The character moves against `physics`. Returns false
+    /// while there is no character to predict.
+    pub fn predict(
+        &mut self,
+        scene: &mut Scene,
+        movement: Vec3,
+        jump: bool,
+        sprint: bool,
+        dt: f32,
+        physics: &PhysicsWorld,
+    ) -> bool {
+        let Some(local) = self.predicted_entity() else {
+            return false;
+        };
+        let Some(entity) = scene.get_entity_mut(local) else {
+            return false;
+        };
+        let mut transform = entity.transform;
+        let mut controller = entity.get_component::<CharacterController>().cloned();
+        if let Some(state) = self.unreconciled.take() {
+            let controller = controller.get_or_insert_with(|| state.controller.clone());
+            let sweep = CharacterSweep::new(physics, local, controller);
+            let correction = self
+                .predictor
+                .reconcile(&state, controller, &mut transform, &sweep);
+            if correction > 0.01 {
+                log::debug!("Corrected predicted character by {:.3}", correction);
+            }
+        }
+        // Nothing from the server to start from yet
+        let Some(mut controller) = controller else {
+            return false;
+        };
+
+        let input = self.predictor.push(movement, jump, sprint, dt);
+        let sweep = CharacterSweep::new(physics, local, &controller);
+        step_character(&mut controller, &mut transform, &input, &sweep);
+        entity.transform = transform;
+        match entity.get_component_mut::<CharacterController>() {
+            Some(stored) => *stored = controller,
+            None => entity.add_component(controller),
+        }
+
+        self.connection.send_unreliable(ClientMessage::Input(
+            self.predictor.pending().copied().collect(),
+        ));
+        true
+    }
+
+    /// Queue an RPC for the server (sent on the next poll or flush)
+    pub fn send_rpc(&mut self, message: RpcMessage) {
+        self.connection.send_reliable(ClientMessage::Rpc(message));
+    }
+
+    /// Where the client is looking from; the server sends entities near it
+    pub fn set_view(&mut self, position: Vec3) {
+        self.connection.send_unreliable(ClientMessage::View {
+            position: position.to_array(),
+        });
+    }
+
+    pub fn flush(&mut self) {
+        if let Err(e) = self.connection.flush(&self.endpoint) {
+            log::warn!("Failed to send to server: {}", e);
+        }
+    }
+
+    /// Tell the server the client is leaving
+    pub fn disconnect(&mut self) {
+        if self.closed {
+            return;
+        }
+        self.connection.send_reliable(ClientMessage::Goodbye);
+        self.flush();
+        self.closed = true;
+    }
+}
+
+impl Drop for NetClient {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{NetServer, ServerEvent};
+    use engine_scene::components::Replicated;
+    use engine_scene::transform::Transform;
+    use std::time::Duration;
+
+    #[test]
+    fn test_client_mirrors_server_and_exchanges_rpcs() {
+        let mut server = NetServer::bind("127.0.0.1:0").unwrap();
+        let mut server_scene = Scene::new("Arena".to_string());
+        let ball = server_scene.create_entity_with_transform(
+            "Ball".to_string(),
+            Transform::from_position(Vec3::new(0.0, 2.0, 0.0)),
+        );
+        server_scene
+            .get_entity_mut(ball)
+            .unwrap()
+            .add_component(Replicated::new());
+
+        let mut client = NetClient::connect(server.local_addr().unwrap(), "alice").unwrap();
+        let mut client_scene = Scene::new("Arena".to_string());
+        let mut server_events = Vec::new();
+        let mut client_events = Vec::new();
+        let mut sent_rpc = false;
+
+        for _ in 0..200 {
+            server_events.extend(server.poll());
+            server.replicate(&server_scene);
+            client_events.extend(client.poll(&mut client_scene));
+            if client.is_connected() && !sent_rpc {
+                client.send_rpc(RpcMessage::new("jump", serde_json::json!({ "height": 2 })));
+                sent_rpc = true;
+                server.broadcast_rpc(RpcMessage::new("round_start", serde_json::Value::Null));
+            }
+            let got_rpc = server_events
+                .iter()
+                .any(|event| matches!(event, ServerEvent::Rpc { .. }));
+            if got_rpc && client.local_entity(ball.0).is_some() && client_events.len() >= 2 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        assert!(matches!(
+            server_events[0],
+            ServerEvent::ClientConnected { client: ClientId(1), .. }
+        ));
+        assert!(server_events.contains(&ServerEvent::Rpc {
+            client: ClientId(1),
+            message: RpcMessage::new("jump", serde_json::json!({ "height": 2 })),
+        }));
+        assert_eq!(
+            client_events[0],
+            ClientEvent::Connected {
+                client_id: ClientId(1)
+            }
+        );
+        assert!(client_events.contains(&ClientEvent::Rpc(RpcMessage::new(
+            "round_start",
+            serde_json::Value::Null
+        ))));
+        let mirrored = client.local_entity(ball.0).unwrap();
+        assert_eq!(
+            client_scene.get_entity(mirrored).unwrap().transform.position,
+            Vec3::new(0.0, 2.0, 0.0)
+        );
+
+        client.disconnect();
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(server.poll().contains(&ServerEvent::ClientDisconnected {
+            client: ClientId(1),
+            reason: "left".to_string(),
+        }));
+    }
+}
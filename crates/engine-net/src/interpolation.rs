@@ -0,0 +1,200 @@
+// Snapshot interpolation - smooth motion for entities the server moves
+//
+// Snapshots arrive a few times a second and never quite evenly. Rather than
+// jumping to each one, a client shows remote entities a little in the past
+// (the interpolation delay), blending between the two snapshots either side
+// of that moment. The client's clock runs at real time and is only pulled
+// back into line when it drifts too far from the snapshots' ticks.
+
+use std::collections::{HashMap, VecDeque};
+
+use engine_scene::transform::Transform;
+
+use crate::replication::{WorldState, SNAPSHOT_HISTORY};
+
+/// How far in the past remote entities are shown by default (seconds);
+/// about three snapshots at the default tick rate
+pub const DEFAULT_INTERPOLATION_DELAY: f64 = 0.1;
+
+/// The clock is reset rather than eased when it is this far behind the
+/// newest snapshot (seconds)
+pub const MAX_CLOCK_LAG: f64 = 0.25;
+
+/// Recent transforms of one entity with the server time of each
+#[derive(Debug, Clone, Default)]
+pub struct InterpolationBuffer {
+    samples: VecDeque<(f64, Transform)>,
+}
+
+impl InterpolationBuffer {
+    /// Add the entity's transform at `time`; out-of-order samples are ignored
+    pub fn push(&mut self, time: f64, transform: Transform) {
+        if self.samples.back().is_some_and(|(last, _)| *last >= time) {
+            return;
+        }
+        self.samples.push_back((time, transform));
+        while self.samples.len() > SNAPSHOT_HISTORY {
+            self.samples.pop_front();
+        }
+    }
+
+    /// The transform at `time`, blended between the samples either side.
+    /// Before the oldest sample or after the newest, the nearest one.
+    pub fn sample(&self, time: f64) -> Option<Transform> {
+        let after = self.samples.iter().position(|(t, _)| *t >= time);
+        match after {
+            None => self.samples.back().map(|(_, transform)| *transform),
+            Some(0) => self.samples.front().map(|(_, transform)| *transform),
+            Some(index) => {
+                let (t0, a) = self.samples[index - 1];
+                let (t1, b) = self.samples[index];
+                let alpha = ((time - t0) / (t1 - t0)) as f32;
+                Some(Transform {
+                    position: a.position.lerp(b.position, alpha),
+                    rotation: a.rotation.slerp(b.rotation, alpha),
+                    scale: a.scale.lerp(b.scale, alpha),
+                })
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
+
+/// Interpolation buffers for every replicated entity, and the clock they
+/// are sampled at
+pub struct Interpolator {
+    delay: f64,
+    clock: Option<f64>,
+    latest: f64,
+    buffers: HashMap<u64, InterpolationBuffer>,
+}
+
+impl Interpolator {
+    pub fn new() -> Self {
+        Self {
+            delay: DEFAULT_INTERPOLATION_DELAY,
+            clock: None,
+            latest: 0.0,
+            buffers: HashMap::new(),
+        }
+    }
+
+    pub fn with_delay(mut self, delay: f64) -> Self {
+        self.delay = delay.max(0.0);
+        self
+    }
+
+    pub fn delay(&self) -> f64 {
+        self.delay
+    }
+
+    /// Record a snapshot's world state at its server time. Entities no
+    /// longer in the world lose their buffers.
+    pub fn record(&mut self, time: f64, world: &WorldState) {
+        self.buffers.retain(|id, _| world.contains_key(id));
+        for (id, state) in world {
+            self.buffers
+                .entry(*id)
+                .or_default()
+                .push(time, state.transform);
+        }
+        self.latest = self.latest.max(time);
+        if self.clock.is_none() {
+            self.clock = Some(time);
+        }
+    }
+
+    /// Move the clock on by `dt` real seconds, keeping it within reach of
+    /// the newest snapshot. Returns the time entities are shown at.
+    pub fn advance(&mut self, dt: f64) -> Option<f64> {
+        let clock = self.clock.as_mut()?;
+        *clock += dt.max(0.0);
+        if *clock < self.latest - MAX_CLOCK_LAG {
+            *clock = self.latest;
+        }
+        // Snapshots stopped coming: hold at the newest rather than run on
+        *clock = clock.min(self.latest + self.delay);
+        self.render_time()
+    }
+
+    /// Server time entities are shown at
+    pub fn render_time(&self) -> Option<f64> {
+        self.clock.map(|clock| clock - self.delay)
+    }
+
+    /// Where an entity is at the render time
+    pub fn sample(&self, id: u64) -> Option<Transform> {
+        self.buffers.get(&id)?.sample(self.render_time()?)
+    }
+
+    /// Forget everything, e.g. after reconnecting
+    pub fn clear(&mut self) {
+        self.clock = None;
+        self.latest = 0.0;
+        self.buffers.clear();
+    }
+}
+
+impl Default for Interpolator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replication::EntityState;
+    use glam::Vec3;
+
+    fn world(x: f32) -> WorldState {
+        let mut world = WorldState::new();
+        world.insert(
+            7,
+            EntityState {
+                name: "Ball".to_string(),
+                parent: None,
+                transform: Transform::from_position(Vec3::new(x, 0.0, 0.0)),
+                components: None,
+            },
+        );
+        world
+    }
+
+    #[test]
+    fn test_entities_are_shown_between_snapshots() {
+        let mut interpolator = Interpolator::new().with_delay(0.1);
+        interpolator.record(1.0, &world(0.0));
+        interpolator.record(1.1, &world(10.0));
+
+        // The clock starts at the first snapshot; 0.15s later the render
+        // time (0.1s behind) is halfway between the two
+        interpolator.advance(0.15);
+        let x = interpolator.sample(7).unwrap().position.x;
+        assert!((x - 5.0).abs() < 1e-3, "{}", x);
+
+        // No more snapshots: the entity stops at the newest one
+        interpolator.advance(5.0);
+        assert_eq!(interpolator.sample(7).unwrap().position.x, 10.0);
+
+        // A clock far behind the snapshots is reset to the newest
+        let mut late = Interpolator::new();
+        late.record(0.0, &world(0.0));
+        late.record(2.0, &world(1.0));
+        late.advance(0.0);
+        assert_eq!(late.render_time(), Some(2.0 - DEFAULT_INTERPOLATION_DELAY));
+
+        // Out-of-order samples are dropped
+        let mut buffer = InterpolationBuffer::default();
+        buffer.push(2.0, Transform::new());
+        buffer.push(1.0, Transform::new());
+        assert_eq!(buffer.len(), 1);
+    }
+}
@@ -0,0 +1,26 @@
+// Engine Net - Client-server entity replication over UDP
+//
+// The server runs the simulation and is the authority on every entity with
+// a Replicated component. Each tick it sends every client a snapshot of the
+// entities relevant to it; clients mirror them into their own scene and
+// talk back with RPC messages (see engine_scripting::net for the script side).
+// Remote entities are interpolated between snapshots; a client's own
+// character is predicted from its input and reconciled with the server.
+
+pub mod client;
+pub mod interpolation;
+pub mod prediction;
+pub mod protocol;
+pub mod replication;
+pub mod server;
+pub mod transport;
+
+pub use client::{ClientEvent, NetClient};
+pub use interpolation::{InterpolationBuffer, Interpolator, DEFAULT_INTERPOLATION_DELAY};
+pub use prediction::{
+    predicted_character, step_character, CharacterState, PlayerInput, Predictor, MAX_INPUT_DT,
+};
+pub use protocol::{ClientId, ClientMessage, RpcMessage, ServerMessage, PROTOCOL_VERSION};
+pub use replication::{EntityState, EntityUpdate, InterestSettings, Snapshot};
+pub use server::{NetServer, ServerEvent};
+pub use transport::{Connection, Endpoint, Fragment, Packet};
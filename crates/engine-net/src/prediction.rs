@@ -0,0 +1,303 @@
+// Client prediction - the local character moves as soon as a key is pressed
+//
+// Waiting a round trip for the server to move the player's own character
+// feels sluggish, so the owning client runs the same movement the server
+// will (step_character, driven by the CharacterController) on its own
+// input right away. Every input is numbered and sent to the server, which
+// applies them in order and reports, with each snapshot, the character's
+// state and the last input it applied. The client then reconciles: it
+// takes the server's state and replays the inputs the server hasn't seen
+// yet on top. When both sides agree nothing visible happens; when they
+// don't (the server knew about a wall, or another player shoved), the
+// character is corrected.
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use engine_physics::{CharacterCollision, CharacterController};
+use engine_scene::components::{NetSmoothing, Replicated};
+use engine_scene::entity::EntityId;
+use engine_scene::scene::Scene;
+use engine_scene::transform::Transform;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+/// Inputs a client keeps (and resends) until the server applies them
+pub const MAX_PENDING_INPUTS: usize = 64;
+
+/// Longest step one input may cover; anything longer is clamped so a
+/// client can't move further than the server would let it
+pub const MAX_INPUT_DT: f32 = 0.1;
+
+/// Seconds of input a client can bank: late inputs arriving in a burst
+/// still apply, but a client can't get ahead of the server's clock by more
+pub const MAX_INPUT_BUDGET: f32 = 0.5;
+
+/// One step of player input
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PlayerInput {
+    /// Numbered from 1 per client, in the order the inputs were made
+    pub sequence: u64,
+    /// Desired direction on the ground plane (length up to 1)
+    pub movement: Vec3,
+    pub jump: bool,
+    pub sprint: bool,
+    /// Seconds this input lasted
+    pub dt: f32,
+}
+
+impl PlayerInput {
+    /// The input with its movement and duration limited to what a real
+    /// client could send
+    pub fn sanitized(mut self) -> Self {
+        if !self.movement.is_finite() {
+            self.movement = Vec3::ZERO;
+        }
+        self.movement = Vec3::new(self.movement.x, 0.0, self.movement.z).clamp_length_max(1.0);
+        self.dt = if self.dt.is_finite() {
+            self.dt.clamp(0.0, MAX_INPUT_DT)
+        } else {
+            0.0
+        };
+        self
+    }
+}
+
+/// Input time the server still lets a client spend: the wall time that
+/// has passed, less the dt of the inputs applied. Stops a client moving
+/// faster by sending more inputs (each within MAX_INPUT_DT) than time
+/// allows.
+#[derive(Debug, Clone)]
+pub struct InputBudget {
+    available: f32,
+    last_refill: Instant,
+}
+
+impl InputBudget {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            available: 0.0,
+            last_refill: now,
+        }
+    }
+
+    /// Add the wall time since the last refill, up to MAX_INPUT_BUDGET
+    pub fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f32();
+        self.available = (self.available + elapsed).min(MAX_INPUT_BUDGET);
+        self.last_refill = now;
+    }
+
+    /// Spend `input`'s dt, clamped to what is left; None (drop the input)
+    /// once the budget is used up
+    pub fn spend(&mut self, mut input: PlayerInput) -> Option<PlayerInput> {
+        if self.available <= 0.0 {
+            return None;
+        }
+        input.dt = input.dt.min(self.available);
+        self.available -= input.dt;
+        Some(input)
+    }
+}
+
+/// The server's state of a client's predicted character
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CharacterState {
+    /// Server id of the character entity
+    pub entity: u64,
+    /// Last input the server applied (0 = none yet)
+    pub input_sequence: u64,
+    pub position: Vec3,
+    pub controller: CharacterController,
+}
+
+/// Advance a character by one input against `collision` (a CharacterSweep
+/// of the physics world)
+pub fn step_character(
+    controller: &mut CharacterController,
+    transform: &mut Transform,
+    input: &PlayerInput,
+    collision: &impl CharacterCollision,
+) {
+    let input = input.sanitized();
+    transform.position = controller.advance(
+        transform.position,
+        input.movement,
+        input.jump,
+        input.sprint,
+        input.dt,
+        collision,
+    );
+}
+
+/// The character a client predicts: an entity it owns with a
+/// CharacterController and NetSmoothing::Predict
+pub fn predicted_character(scene: &Scene, client: u32) -> Option<EntityId> {
+    scene
+        .entities()
+        .find(|entity| {
+            entity.has_component::<CharacterController>()
+                && entity
+                    .get_component::<Replicated>()
+                    .is_some_and(|replicated| {
+                        replicated.owner == Some(client)
+                            && replicated.smoothing == NetSmoothing::Predict
+                    })
+        })
+        .map(|entity| entity.id)
+}
+
+/// Client side: inputs made but not yet applied by the server
+#[derive(Debug, Default)]
+pub struct Predictor {
+    next_sequence: u64,
+    pending: VecDeque<PlayerInput>,
+}
+
+impl Predictor {
+    /// Number a new input and keep it until the server has applied it
+    pub fn push(&mut self, movement: Vec3, jump: bool, sprint: bool, dt: f32) -> PlayerInput {
+        self.next_sequence += 1;
+        let input = PlayerInput {
+            sequence: self.next_sequence,
+            movement,
+            jump,
+            sprint,
+            dt,
+        }
+        .sanitized();
+        self.pending.push_back(input);
+        while self.pending.len() > MAX_PENDING_INPUTS {
+            self.pending.pop_front();
+        }
+        input
+    }
+
+    /// Inputs the server hasn't acknowledged, oldest first
+    pub fn pending(&self) -> impl Iterator<Item = &PlayerInput> {
+        self.pending.iter()
+    }
+
+    /// Take the server's state and replay the inputs it hasn't applied yet.
+    /// Returns how far the character was corrected.
+    pub fn reconcile(
+        &mut self,
+        state: &CharacterState,
+        controller: &mut CharacterController,
+        transform: &mut Transform,
+        collision: &impl CharacterCollision,
+    ) -> f32 {
+        while self
+            .pending
+            .front()
+            .is_some_and(|input| input.sequence <= state.input_sequence)
+        {
+            self.pending.pop_front();
+        }
+
+        let predicted = transform.position;
+        *controller = state.controller.clone();
+        transform.position = state.position;
+        for input in &self.pending {
+            step_character(controller, transform, input, collision);
+        }
+        predicted.distance(transform.position)
+    }
+
+    /// Forget pending inputs, e.g. after reconnecting
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_physics::Ground;
+
+    fn flat(_: Vec3) -> Option<Ground> {
+        Some(Ground::flat(0.0))
+    }
+
+    #[test]
+    fn test_reconcile_replays_unacknowledged_inputs() {
+        let mut client_controller = CharacterController::new();
+        client_controller.grounded = true;
+        let mut server_controller = client_controller.clone();
+        let mut client = Transform::new();
+        let mut server = Transform::new();
+        let mut predictor = Predictor::default();
+
+        // The client predicts ten steps forward, with a jump in the middle;
+        // the server has only received the first six
+        let inputs: Vec<_> = (0..10)
+            .map(|i| predictor.push(Vec3::Z, i == 3, false, 1.0 / 60.0))
+            .collect();
+        for input in &inputs {
+            step_character(&mut client_controller, &mut client, input, &flat);
+        }
+        for input in &inputs[..6] {
+            step_character(&mut server_controller, &mut server, input, &flat);
+        }
+        assert!(client.position.z > server.position.z);
+        assert!(client.position.y > 0.0);
+
+        // Same inputs, same movement: reconciling changes nothing
+        let state = CharacterState {
+            entity: 1,
+            input_sequence: 6,
+            position: server.position,
+            controller: server_controller.clone(),
+        };
+        let correction = predictor.reconcile(&state, &mut client_controller, &mut client, &flat);
+        assert!(correction < 1e-5, "{}", correction);
+        assert_eq!(predictor.pending().count(), 4);
+
+        // The server moved the character (a shove); the client ends up
+        // shoved too, with its four newer inputs still applied
+        let shoved = CharacterState {
+            position: server.position + Vec3::X,
+            ..state
+        };
+        let correction = predictor.reconcile(&shoved, &mut client_controller, &mut client, &flat);
+        assert!((correction - 1.0).abs() < 1e-4);
+        assert!((client.position.x - 1.0).abs() < 1e-4);
+
+        // Oversized inputs are clamped
+        let cheat = PlayerInput {
+            sequence: 11,
+            movement: Vec3::new(50.0, 9.0, 0.0),
+            jump: false,
+            sprint: false,
+            dt: 10.0,
+        }
+        .sanitized();
+        assert_eq!(cheat.movement, Vec3::X);
+        assert_eq!(cheat.dt, MAX_INPUT_DT);
+    }
+
+    #[test]
+    fn test_input_budget_follows_wall_time() {
+        let start = Instant::now();
+        let mut budget = InputBudget::new(start);
+        let input = |sequence| PlayerInput {
+            sequence,
+            movement: Vec3::Z,
+            jump: false,
+            sprint: false,
+            dt: 0.1,
+        };
+
+        // 0.25 seconds pass: two full inputs, a clamped one, then nothing
+        budget.refill(start + std::time::Duration::from_millis(250));
+        assert_eq!(budget.spend(input(1)).unwrap().dt, 0.1);
+        assert_eq!(budget.spend(input(2)).unwrap().dt, 0.1);
+        assert!((budget.spend(input(3)).unwrap().dt - 0.05).abs() < 1e-5);
+        assert!(budget.spend(input(4)).is_none());
+
+        // A long wait only banks MAX_INPUT_BUDGET
+        budget.refill(start + std::time::Duration::from_secs(10));
+        let spent: f32 = (5..20).filter_map(|i| budget.spend(input(i))).map(|i| i.dt).sum();
+        assert!((spent - MAX_INPUT_BUDGET).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,54 @@
+// Messages between clients and the server
+
+use serde::{Deserialize, Serialize};
+
+use crate::prediction::PlayerInput;
+use crate::replication::Snapshot;
+
+/// Bumped whenever the messages change; clients with another version are
+/// turned away
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// A connected client, numbered from 1 in the order they joined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ClientId(pub u32);
+
+/// A named message with a JSON payload, sent between scripts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcMessage {
+    pub name: String,
+    pub data: serde_json::Value,
+}
+
+impl RpcMessage {
+    pub fn new(name: impl Into<String>, data: serde_json::Value) -> Self {
+        Self {
+            name: name.into(),
+            data,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ClientMessage {
+    /// First message of a connection
+    Hello { protocol: u32, name: String },
+    Rpc(RpcMessage),
+    /// Where the client is looking from, for interest management
+    View { position: [f32; 3] },
+    /// Latest snapshot the client has applied
+    AckSnapshot { tick: u64 },
+    /// Inputs for the client's predicted character the server hasn't
+    /// acknowledged yet (resent until it has, so losing one packet is fine)
+    Input(Vec<PlayerInput>),
+    Goodbye,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ServerMessage {
+    Welcome { client_id: ClientId, tick_rate: u32 },
+    Rejected { reason: String },
+    Snapshot(Snapshot),
+    Rpc(RpcMessage),
+    Goodbye { reason: String },
+}
@@ -0,0 +1,429 @@
+// Replication - what each client is sent, and mirroring it on the client
+//
+// The server captures every Replicated entity once per tick, filters the
+// list for each client (interest management: entities near the client's
+// view, ones it owns and ones marked always relevant) and sends what
+// differs from the last snapshot that client acknowledged. Because deltas
+// are always against an acknowledged baseline, a lost snapshot only costs
+// latency - the next one still applies. The client keeps the states of
+// recent snapshots so it can rebuild each new one from its baseline.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use engine_scene::components::Replicated;
+use engine_scene::entity::EntityId;
+use engine_scene::scene::Scene;
+use engine_scene::scene_data::SerializedComponent;
+use engine_scene::transform::Transform;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::prediction::CharacterState;
+
+/// Snapshots a sender remembers for acknowledgements, and a receiver
+/// remembers as baselines
+pub const SNAPSHOT_HISTORY: usize = 32;
+
+/// Which entities a client is sent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InterestSettings {
+    /// Entities further than this from the client's view are left out
+    pub radius: f32,
+}
+
+impl Default for InterestSettings {
+    fn default() -> Self {
+        Self { radius: 100.0 }
+    }
+}
+
+impl InterestSettings {
+    /// Clients that haven't sent a view get everything
+    pub fn is_relevant(&self, entity: &ReplicatedEntity, client: u32, view: Option<Vec3>) -> bool {
+        entity.always_relevant
+            || entity.owner == Some(client)
+            || view.is_none_or(|view| view.distance(entity.position) <= self.radius)
+    }
+}
+
+/// Replicated state of one entity
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntityState {
+    pub name: String,
+    /// Server id of the parent entity
+    pub parent: Option<u64>,
+    /// Local transform (relative to the parent)
+    pub transform: Transform,
+    /// Serialized components as JSON, if the entity replicates them
+    pub components: Option<serde_json::Value>,
+}
+
+/// Entity states by server entity id
+pub type WorldState = BTreeMap<u64, EntityState>;
+
+/// A Replicated entity as captured this tick
+#[derive(Debug, Clone)]
+pub struct ReplicatedEntity {
+    pub id: u64,
+    pub state: EntityState,
+    /// World-space position, for interest management
+    pub position: Vec3,
+    pub owner: Option<u32>,
+    pub always_relevant: bool,
+}
+
+/// Every Replicated entity in the scene
+pub fn capture(scene: &Scene) -> Vec<ReplicatedEntity> {
+    scene
+        .entities()
+        .filter_map(|entity| {
+            let replicated = entity.get_component::<Replicated>()?;
+            let components = replicated.components.then(|| {
+                serde_json::to_value(SerializedComponent::from_entity(entity))
+                    .unwrap_or(serde_json::Value::Null)
+            });
+            Some(ReplicatedEntity {
+                id: entity.id.0,
+                state: EntityState {
+                    name: entity.name.clone(),
+                    parent: entity.parent.map(|parent| parent.0),
+                    transform: entity.transform,
+                    components,
+                },
+                position: scene.world_matrix(entity.id).w_axis.truncate(),
+                owner: replicated.owner,
+                always_relevant: replicated.always_relevant,
+            })
+        })
+        .collect()
+}
+
+/// An entity that changed since the baseline (or is new to the client)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityUpdate {
+    pub id: u64,
+    pub name: String,
+    pub parent: Option<u64>,
+    pub transform: Transform,
+    /// Left out when the components haven't changed since the baseline
+    pub components: Option<serde_json::Value>,
+}
+
+/// What changed for one client since its baseline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub tick: u64,
+    /// Acknowledged snapshot this one is relative to (None = relative to nothing)
+    pub baseline: Option<u64>,
+    pub entities: Vec<EntityUpdate>,
+    /// Entities that were in the baseline but are gone or no longer relevant
+    pub removed: Vec<u64>,
+    /// The client's predicted character as the server has it (sent whole,
+    /// every snapshot)
+    #[serde(default)]
+    pub character: Option<CharacterState>,
+}
+
+/// Server side, per client: snapshots sent and the acknowledged baseline
+#[derive(Default)]
+pub struct SnapshotSender {
+    sent: VecDeque<(u64, WorldState)>,
+    baseline: Option<(u64, WorldState)>,
+}
+
+impl SnapshotSender {
+    /// Snapshot of `world` (the entities relevant to the client) relative
+    /// to the baseline
+    pub fn build(&mut self, tick: u64, world: WorldState) -> Snapshot {
+        let empty = WorldState::new();
+        let (baseline_tick, base) = match &self.baseline {
+            Some((tick, state)) => (Some(*tick), state),
+            None => (None, &empty),
+        };
+
+        let entities = world
+            .iter()
+            .filter(|(id, state)| base.get(id) != Some(state))
+            .map(|(id, state)| EntityUpdate {
+                id: *id,
+                name: state.name.clone(),
+                parent: state.parent,
+                transform: state.transform,
+                components: match base.get(id) {
+                    Some(old) if old.components == state.components => None,
+                    _ => state.components.clone(),
+                },
+            })
+            .collect();
+        let removed = base
+            .keys()
+            .filter(|id| !world.contains_key(id))
+            .copied()
+            .collect();
+        let snapshot = Snapshot {
+            tick,
+            baseline: baseline_tick,
+            entities,
+            removed,
+            character: None,
+        };
+
+        self.sent.push_back((tick, world));
+        while self.sent.len() > SNAPSHOT_HISTORY {
+            self.sent.pop_front();
+        }
+        snapshot
+    }
+
+    /// The client applied snapshot `tick`; later snapshots build on it
+    pub fn acknowledge(&mut self, tick: u64) {
+        if self.baseline.as_ref().is_some_and(|(baseline, _)| *baseline >= tick) {
+            return;
+        }
+        while let Some((sent_tick, state)) = self.sent.pop_front() {
+            if sent_tick == tick {
+                self.baseline = Some((sent_tick, state));
+                return;
+            }
+            if sent_tick > tick {
+                self.sent.push_front((sent_tick, state));
+                return;
+            }
+        }
+    }
+}
+
+/// Client side: rebuilds snapshots from their baselines and mirrors the
+/// result into the scene
+#[derive(Default)]
+pub struct SnapshotReceiver {
+    history: VecDeque<(u64, WorldState)>,
+    /// Server entity id -> entity in the local scene
+    entities: HashMap<u64, EntityId>,
+    /// Server id of the entity the client predicts; its transform is left
+    /// to the predictor once it exists
+    predicted: Option<u64>,
+}
+
+impl SnapshotReceiver {
+    /// Tick of the newest snapshot applied
+    pub fn latest_tick(&self) -> Option<u64> {
+        self.history.back().map(|(tick, _)| *tick)
+    }
+
+    /// State of every replicated entity as of the newest snapshot
+    pub fn world(&self) -> Option<&WorldState> {
+        self.history.back().map(|(_, world)| world)
+    }
+
+    /// The local entity mirroring a server entity
+    pub fn local_entity(&self, server_id: u64) -> Option<EntityId> {
+        self.entities.get(&server_id).copied()
+    }
+
+    /// Every mirrored entity: server id and local entity
+    pub fn mirrored(&self) -> impl Iterator<Item = (u64, EntityId)> + '_ {
+        self.entities.iter().map(|(id, local)| (*id, *local))
+    }
+
+    pub fn set_predicted(&mut self, server_id: Option<u64>) {
+        self.predicted = server_id;
+    }
+
+    /// Rebuild the snapshot from its baseline and mirror it into the scene.
+    /// Returns false for snapshots that are stale or whose baseline is no
+    /// longer known; those must not be acknowledged.
+    pub fn apply(&mut self, snapshot: Snapshot, scene: &mut Scene) -> bool {
+        if self.latest_tick().is_some_and(|latest| latest >= snapshot.tick) {
+            return false;
+        }
+        let mut world = match snapshot.baseline {
+            None => WorldState::new(),
+            Some(baseline) => match self.history.iter().find(|(tick, _)| *tick == baseline) {
+                Some((_, state)) => state.clone(),
+                None => return false,
+            },
+        };
+        for id in &snapshot.removed {
+            world.remove(id);
+        }
+        for update in snapshot.entities {
+            let components = update
+                .components
+                .or_else(|| world.get(&update.id).and_then(|old| old.components.clone()));
+            world.insert(
+                update.id,
+                EntityState {
+                    name: update.name,
+                    parent: update.parent,
+                    transform: update.transform,
+                    components,
+                },
+            );
+        }
+
+        let previous = self.world().cloned().unwrap_or_default();
+        self.mirror(&previous, &world, scene);
+
+        self.history.push_back((snapshot.tick, world));
+        while self.history.len() > SNAPSHOT_HISTORY {
+            self.history.pop_front();
+        }
+        true
+    }
+
+    fn mirror(&mut self, previous: &WorldState, world: &WorldState, scene: &mut Scene) {
+        for id in previous.keys().filter(|id| !world.contains_key(id)) {
+            if let Some(local) = self.entities.remove(id) {
+                scene.remove_entity(local);
+            }
+        }
+
+        let mut reparent = Vec::new();
+        for (id, state) in world {
+            let mapped = self
+                .local_entity(*id)
+                .filter(|local| scene.get_entity(*local).is_some());
+            if mapped.is_some() && previous.get(id) == Some(state) {
+                continue;
+            }
+            let local = mapped.unwrap_or_else(|| self.adopt_or_create(*id, &state.name, scene));
+            let Some(entity) = scene.get_entity_mut(local) else {
+                continue;
+            };
+            entity.name = state.name.clone();
+            if mapped.is_none() || self.predicted != Some(*id) {
+                entity.transform = state.transform;
+            }
+            if let Some(components) = &state.components {
+                match serde_json::from_value::<Vec<SerializedComponent>>(components.clone()) {
+                    Ok(components) => {
+                        for component in components {
+                            component.insert_into(entity);
+                        }
+                    }
+                    Err(e) => log::warn!("Bad replicated components for '{}': {}", state.name, e),
+                }
+            }
+            reparent.push((local, state.parent));
+        }
+
+        // Parents may have been created after their children above
+        for (local, parent) in reparent {
+            let parent = parent.and_then(|parent| self.local_entity(parent));
+            if scene.get_entity(local).is_some_and(|entity| entity.parent != parent) {
+                scene.set_parent(local, parent);
+            }
+        }
+    }
+
+    /// The scene's own copy of the entity if it loaded the same scene as
+    /// the server, otherwise a new entity
+    fn adopt_or_create(&mut self, id: u64, name: &str, scene: &mut Scene) -> EntityId {
+        let same_id = EntityId(id);
+        let already_mirrors = self.entities.values().any(|local| *local == same_id);
+        let local = if !already_mirrors
+            && scene.get_entity(same_id).is_some_and(|entity| entity.name == name)
+        {
+            same_id
+        } else {
+            scene.create_entity(name.to_string())
+        };
+        self.entities.insert(id, local);
+        local
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_scene::components::MeshRenderer;
+
+    fn server_scene() -> (Scene, EntityId) {
+        let mut scene = Scene::new("Server".to_string());
+        let near = scene.create_entity_with_transform(
+            "Crate".to_string(),
+            Transform::from_position(Vec3::new(1.0, 0.0, 0.0)),
+        );
+        let entity = scene.get_entity_mut(near).unwrap();
+        entity.add_component(Replicated::new());
+        entity.add_component(MeshRenderer::new("cube".to_string()));
+
+        let far = scene.create_entity_with_transform(
+            "Far".to_string(),
+            Transform::from_position(Vec3::new(500.0, 0.0, 0.0)),
+        );
+        scene.get_entity_mut(far).unwrap().add_component(Replicated::new());
+
+        let flag = scene.create_entity_with_transform(
+            "Flag".to_string(),
+            Transform::from_position(Vec3::new(-500.0, 0.0, 0.0)),
+        );
+        scene
+            .get_entity_mut(flag)
+            .unwrap()
+            .add_component(Replicated::new().with_always_relevant(true));
+
+        // Not replicated at all
+        scene.create_entity("Editor Gizmo".to_string());
+        (scene, near)
+    }
+
+    fn relevant(scene: &Scene) -> WorldState {
+        let interest = InterestSettings { radius: 10.0 };
+        capture(scene)
+            .into_iter()
+            .filter(|entity| interest.is_relevant(entity, 1, Some(Vec3::ZERO)))
+            .map(|entity| (entity.id, entity.state))
+            .collect()
+    }
+
+    #[test]
+    fn test_deltas_rebuild_from_acknowledged_baseline() {
+        let (mut server, crate_id) = server_scene();
+        let mut sender = SnapshotSender::default();
+        let mut receiver = SnapshotReceiver::default();
+        let mut client = Scene::new("Client".to_string());
+
+        let first = sender.build(1, relevant(&server));
+        assert_eq!(first.entities.len(), 2); // Crate and Flag, not Far
+        assert!(receiver.apply(first, &mut client));
+        sender.acknowledge(1);
+        let names: Vec<_> = client.entities().map(|e| e.name.clone()).collect();
+        assert_eq!(names.len(), 2);
+        let local = receiver.local_entity(crate_id.0).unwrap();
+        assert!(client
+            .get_entity(local)
+            .unwrap()
+            .has_component::<MeshRenderer>());
+
+        // Nothing changed: an empty delta
+        let unchanged = sender.build(2, relevant(&server));
+        assert!(unchanged.entities.is_empty() && unchanged.removed.is_empty());
+
+        // The crate moves and the client applies it, but the ack is lost...
+        server.get_entity_mut(crate_id).unwrap().transform.position.x = 3.0;
+        let moved = sender.build(3, relevant(&server));
+        assert!(moved.entities[0].components.is_none());
+        assert!(receiver.apply(moved, &mut client));
+        assert_eq!(client.get_entity(local).unwrap().transform.position.x, 3.0);
+
+        // ...then moves back. The delta against tick 1 is empty, and the
+        // client rebuilds from tick 1 rather than keeping tick 3's position.
+        server.get_entity_mut(crate_id).unwrap().transform.position.x = 1.0;
+        let back = sender.build(4, relevant(&server));
+        assert_eq!(back.baseline, Some(1));
+        assert!(back.entities.is_empty());
+        assert!(receiver.apply(back, &mut client));
+        assert_eq!(client.get_entity(local).unwrap().transform.position.x, 1.0);
+        sender.acknowledge(4);
+
+        // Leaving the interest radius removes the mirror
+        server.get_entity_mut(crate_id).unwrap().transform.position.x = 50.0;
+        let gone = sender.build(5, relevant(&server));
+        assert_eq!(gone.removed, vec![crate_id.0]);
+        assert!(receiver.apply(gone.clone(), &mut client));
+        assert!(client.get_entity(local).is_none());
+        assert!(!receiver.apply(gone, &mut client)); // stale
+    }
+}
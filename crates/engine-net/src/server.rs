@@ -0,0 +1,352 @@
+// Net server - accepts clients, replicates the scene to them and relays
+// their RPC messages to the game. Input for predicted characters is applied
+// by the server too (apply_inputs), so it stays the authority on where
+// they are.
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::time::Instant;
+
+use anyhow::Result;
+use engine_physics::{CharacterController, CharacterSweep, PhysicsWorld};
+use engine_scene::scene::Scene;
+use glam::Vec3;
+
+use crate::prediction::{
+    predicted_character, step_character, CharacterState, InputBudget, PlayerInput,
+    MAX_PENDING_INPUTS,
+};
+use crate::protocol::{ClientId, ClientMessage, RpcMessage, ServerMessage, PROTOCOL_VERSION};
+use crate::replication::{self, InterestSettings, SnapshotSender, WorldState};
+use crate::transport::{first_message, Connection, Endpoint, Fragment};
+
+/// Default number of clients a server accepts
+pub const DEFAULT_MAX_CLIENTS: usize = 16;
+
+/// Snapshots sent per second by default
+pub const DEFAULT_TICK_RATE: u32 = 30;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerEvent {
+    ClientConnected { client: ClientId, name: String },
+    ClientDisconnected { client: ClientId, reason: String },
+    Rpc { client: ClientId, message: RpcMessage },
+}
+
+struct ClientState {
+    id: ClientId,
+    name: String,
+    connection: Connection<ServerMessage, ClientMessage>,
+    view: Option<Vec3>,
+    snapshots: SnapshotSender,
+    /// Inputs received but not yet applied, by sequence number
+    inputs: BTreeMap<u64, PlayerInput>,
+    /// Last input applied (0 = none)
+    last_input: u64,
+    /// Input time the client may still use, see InputBudget
+    input_budget: InputBudget,
+}
+
+pub struct NetServer {
+    endpoint: Endpoint,
+    clients: HashMap<SocketAddr, ClientState>,
+    next_client_id: u32,
+    tick: u64,
+    tick_rate: u32,
+    max_clients: usize,
+    interest: InterestSettings,
+}
+
+impl NetServer {
+    pub fn bind(addr: impl ToSocketAddrs) -> Result<Self> {
+        let endpoint = Endpoint::bind(addr)?;
+        log::info!("Net server listening on {}", endpoint.local_addr()?);
+        Ok(Self {
+            endpoint,
+            clients: HashMap::new(),
+            next_client_id: 1,
+            tick: 0,
+            tick_rate: DEFAULT_TICK_RATE,
+            max_clients: DEFAULT_MAX_CLIENTS,
+            interest: InterestSettings::default(),
+        })
+    }
+
+    pub fn with_max_clients(mut self, max_clients: usize) -> Self {
+        self.max_clients = max_clients;
+        self
+    }
+
+    pub fn with_interest(mut self, interest: InterestSettings) -> Self {
+        self.interest = interest;
+        self
+    }
+
+    /// Snapshots per second the game intends to send (told to clients)
+    pub fn with_tick_rate(mut self, tick_rate: u32) -> Self {
+        self.tick_rate = tick_rate.max(1);
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.endpoint.local_addr()
+    }
+
+    pub fn tick_rate(&self) -> u32 {
+        self.tick_rate
+    }
+
+    /// Connected clients and their names
+    pub fn clients(&self) -> impl Iterator<Item = (ClientId, &str)> {
+        self.clients
+            .values()
+            .map(|client| (client.id, client.name.as_str()))
+    }
+
+    /// Take in what clients sent: new connections, RPCs, views and
+    /// acknowledgements. Drops clients that went quiet.
+    pub fn poll(&mut self) -> Vec<ServerEvent> {
+        let mut events = Vec::new();
+        for (addr, packet) in self.endpoint.receive::<ClientMessage>() {
+            if !self.clients.contains_key(&addr) {
+                self.accept(addr, &packet.reliable, &mut events);
+            }
+            let Some(client) = self.clients.get_mut(&addr) else {
+                continue;
+            };
+            let mut left = false;
+            for message in client.connection.receive(packet) {
+                match message {
+                    ClientMessage::Hello { .. } => {}
+                    ClientMessage::Rpc(message) => events.push(ServerEvent::Rpc {
+                        client: client.id,
+                        message,
+                    }),
+                    ClientMessage::View { position } => {
+                        client.view = Some(Vec3::from_array(position));
+                    }
+                    ClientMessage::AckSnapshot { tick } => client.snapshots.acknowledge(tick),
+                    ClientMessage::Input(inputs) => {
+                        for input in inputs {
+                            if input.sequence > client.last_input {
+                                client.inputs.insert(input.sequence, input.sanitized());
+                            }
+                        }
+                        while client.inputs.len() > MAX_PENDING_INPUTS {
+                            client.inputs.pop_first();
+                        }
+                    }
+                    ClientMessage::Goodbye => {
+                        left = true;
+                        break;
+                    }
+                }
+            }
+            if left {
+                events.push(ServerEvent::ClientDisconnected {
+                    client: client.id,
+                    reason: "left".to_string(),
+                });
+                self.clients.remove(&addr);
+            }
+        }
+
+        self.clients.retain(|_, client| {
+            let alive = !client.connection.timed_out();
+            if !alive {
+                events.push(ServerEvent::ClientDisconnected {
+                    client: client.id,
+                    reason: "timed out".to_string(),
+                });
+            }
+            alive
+        });
+        for event in &events {
+            match event {
+                ServerEvent::ClientConnected { client, name } => {
+                    log::info!("Client {} ({}) connected", client.0, name)
+                }
+                ServerEvent::ClientDisconnected { client, reason } => {
+                    log::info!("Client {} disconnected: {}", client.0, reason)
+                }
+                ServerEvent::Rpc { .. } => {}
+            }
+        }
+        events
+    }
+
+    /// Answer a Hello from an unknown address
+    fn accept(
+        &mut self,
+        addr: SocketAddr,
+        reliable: &[Fragment],
+        events: &mut Vec<ServerEvent>,
+    ) {
+        let Some(ClientMessage::Hello { protocol, name }) = first_message(reliable) else {
+            return;
+        };
+
+        let rejection = if protocol != PROTOCOL_VERSION {
+            Some(format!(
+                "protocol version {} is not supported (server uses {})",
+                protocol, PROTOCOL_VERSION
+            ))
+        } else if self.clients.len() >= self.max_clients {
+            Some("server is full".to_string())
+        } else {
+            None
+        };
+        if let Some(reason) = rejection {
+            log::info!("Rejected {} ({}): {}", addr, name, reason);
+            let mut connection: Connection<ServerMessage, ClientMessage> = Connection::new(addr);
+            connection.send_reliable(ServerMessage::Rejected { reason });
+            if let Err(e) = connection.flush(&self.endpoint) {
+                log::warn!("Failed to reject {}: {}", addr, e);
+            }
+            return;
+        }
+
+        let id = ClientId(self.next_client_id);
+        self.next_client_id += 1;
+        let mut connection = Connection::new(addr);
+        connection.send_reliable(ServerMessage::Welcome {
+            client_id: id,
+            tick_rate: self.tick_rate,
+        });
+        self.clients.insert(
+            addr,
+            ClientState {
+                id,
+                name: name.clone(),
+                connection,
+                view: None,
+                snapshots: SnapshotSender::default(),
+                inputs: BTreeMap::new(),
+                last_input: 0,
+                input_budget: InputBudget::new(Instant::now()),
+            },
+        );
+        events.push(ServerEvent::ClientConnected { client: id, name });
+    }
+
+    fn client_mut(&mut self, id: ClientId) -> Option<&mut ClientState> {
+        self.clients.values_mut().find(|client| client.id == id)
+    }
+
+    /// Queue an RPC for one client (sent on the next flush)
+    pub fn send_rpc(&mut self, client: ClientId, message: RpcMessage) -> bool {
+        match self.client_mut(client) {
+            Some(client) => {
+                client.connection.send_reliable(ServerMessage::Rpc(message));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Queue an RPC for every client
+    pub fn broadcast_rpc(&mut self, message: RpcMessage) {
+        for client in self.clients.values_mut() {
+            client
+                .connection
+                .send_reliable(ServerMessage::Rpc(message.clone()));
+        }
+    }
+
+    /// Tell a client to go and forget it
+    pub fn kick(&mut self, client: ClientId, reason: &str) {
+        let Some(addr) = self
+            .clients
+            .iter()
+            .find(|(_, state)| state.id == client)
+            .map(|(addr, _)| *addr)
+        else {
+            return;
+        };
+        if let Some(mut state) = self.clients.remove(&addr) {
+            state.connection.send_reliable(ServerMessage::Goodbye {
+                reason: reason.to_string(),
+            });
+            if let Err(e) = state.connection.flush(&self.endpoint) {
+                log::warn!("Failed to tell client {} goodbye: {}", client.0, e);
+            }
+        }
+    }
+
+    /// Move each client's predicted character by the inputs it sent, in
+    /// order, against the physics world. Inputs from clients
+    /// without a character are dropped, and so are inputs adding up to more
+    /// time than has passed on the server (see InputBudget).
+    pub fn apply_inputs(&mut self, scene: &mut Scene, physics: &PhysicsWorld) {
+        let now = Instant::now();
+        for client in self.clients.values_mut() {
+            client.input_budget.refill(now);
+            if client.inputs.is_empty() {
+                continue;
+            }
+            let inputs = std::mem::take(&mut client.inputs);
+            client.last_input = inputs.keys().last().copied().unwrap_or(client.last_input);
+
+            let Some(id) = predicted_character(scene, client.id.0) else {
+                continue;
+            };
+            let Some(entity) = scene.get_entity_mut(id) else {
+                continue;
+            };
+            let Some(mut controller) = entity.get_component::<CharacterController>().cloned()
+            else {
+                continue;
+            };
+            let mut transform = entity.transform;
+            let sweep = CharacterSweep::new(physics, id, &controller);
+            for input in inputs.into_values() {
+                let Some(input) = client.input_budget.spend(input) else {
+                    break;
+                };
+                step_character(&mut controller, &mut transform, &input, &sweep);
+            }
+            entity.transform = transform;
+            if let Some(stored) = entity.get_component_mut::<CharacterController>() {
+                *stored = controller;
+            }
+        }
+    }
+
+    /// Send every client a snapshot of the entities relevant to it, then
+    /// flush. Call at the tick rate, after the simulation has stepped.
+    pub fn replicate(&mut self, scene: &Scene) {
+        self.tick += 1;
+        let entities = replication::capture(scene);
+        for client in self.clients.values_mut() {
+            let world: WorldState = entities
+                .iter()
+                .filter(|entity| self.interest.is_relevant(entity, client.id.0, client.view))
+                .map(|entity| (entity.id, entity.state.clone()))
+                .collect();
+            let mut snapshot = client.snapshots.build(self.tick, world);
+            snapshot.character = predicted_character(scene, client.id.0)
+                .and_then(|id| scene.get_entity(id))
+                .and_then(|entity| {
+                    Some(CharacterState {
+                        entity: entity.id.0,
+                        input_sequence: client.last_input,
+                        position: entity.transform.position,
+                        controller: entity.get_component::<CharacterController>()?.clone(),
+                    })
+                });
+            client
+                .connection
+                .send_unreliable(ServerMessage::Snapshot(snapshot));
+        }
+        self.flush();
+    }
+
+    /// Send queued messages to every client
+    pub fn flush(&mut self) {
+        for client in self.clients.values_mut() {
+            if let Err(e) = client.connection.flush(&self.endpoint) {
+                log::warn!("Failed to send to client {}: {}", client.id.0, e);
+            }
+        }
+    }
+}
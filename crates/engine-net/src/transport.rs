@@ -0,0 +1,428 @@
+// UDP transport - unreliable datagrams with a reliable, ordered channel
+//
+// Packets carry the reliable messages the peer hasn't acknowledged yet
+// (resent until it does), a cumulative acknowledgement of the reliable
+// messages received, and unreliable messages (snapshots) that are sent once
+// and may be lost. Messages are JSON encoded. Reliable messages are split
+// into fragments of at most FRAGMENT_SIZE bytes, each with its own id, and
+// spread over as many MTU sized packets as they need; the receiver puts them
+// back together in order. Unreliable messages go in the first packet, which
+// has to fit in one UDP datagram.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::marker::PhantomData;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Largest UDP payload over IPv4
+pub const MAX_PACKET_SIZE: usize = 65_507;
+
+/// Packets of reliable fragments are kept under this many bytes, so they
+/// aren't fragmented by IP on the way
+pub const MTU: usize = 1200;
+
+/// Bytes of a reliable message's JSON per fragment
+pub const FRAGMENT_SIZE: usize = 512;
+
+/// Reliable fragments more than this far past the next expected one are
+/// dropped (the sender resends them), which caps what is buffered out of order
+pub const RECEIVE_WINDOW: u64 = 1024;
+
+/// Largest reliable message put back together; bigger ones are dropped
+pub const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// How long unacknowledged reliable messages wait before being resent
+pub const RESEND_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An empty packet goes out if nothing else has for this long, so the
+/// peer knows the connection is alive
+pub const KEEPALIVE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A peer that hasn't been heard from for this long is gone
+pub const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One datagram
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Packet<M> {
+    /// Every reliable fragment with a lower id has been received
+    pub ack: u64,
+    pub reliable: Vec<Fragment>,
+    pub unreliable: Vec<M>,
+}
+
+/// Part of a reliable message's JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fragment {
+    pub id: u64,
+    /// The message's last fragment
+    pub last: bool,
+    pub data: String,
+}
+
+/// The first reliable message a peer sent, if all of its fragments are
+/// among `fragments` (how a server reads a new client's hello)
+pub fn first_message<M: DeserializeOwned>(fragments: &[Fragment]) -> Option<M> {
+    let mut json = String::new();
+    for id in 0..fragments.len() as u64 {
+        let fragment = fragments.iter().find(|fragment| fragment.id == id)?;
+        json.push_str(&fragment.data);
+        if fragment.last {
+            return serde_json::from_str(&json).ok();
+        }
+    }
+    None
+}
+
+/// Split a message's JSON into fragments of at most FRAGMENT_SIZE bytes
+/// (on char boundaries), numbered from `first_id`
+fn fragment(json: &str, first_id: u64) -> Vec<Fragment> {
+    let mut fragments = Vec::new();
+    let mut rest = json;
+    loop {
+        let mut end = rest.len().min(FRAGMENT_SIZE);
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (data, tail) = rest.split_at(end);
+        fragments.push(Fragment {
+            id: first_id + fragments.len() as u64,
+            last: tail.is_empty(),
+            data: data.to_string(),
+        });
+        if tail.is_empty() {
+            return fragments;
+        }
+        rest = tail;
+    }
+}
+
+/// A non-blocking UDP socket sending and receiving packets
+pub struct Endpoint {
+    socket: UdpSocket,
+}
+
+impl Endpoint {
+    pub fn bind(addr: impl ToSocketAddrs) -> Result<Self> {
+        let socket = UdpSocket::bind(addr).context("Failed to bind UDP socket")?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    pub fn send<M: Serialize>(&self, addr: SocketAddr, packet: &Packet<M>) -> Result<()> {
+        let bytes = serde_json::to_vec(packet)?;
+        if bytes.len() > MAX_PACKET_SIZE {
+            bail!(
+                "Packet of {} bytes is larger than a UDP datagram ({} bytes)",
+                bytes.len(),
+                MAX_PACKET_SIZE
+            );
+        }
+        self.socket.send_to(&bytes, addr)?;
+        Ok(())
+    }
+
+    /// Every packet waiting on the socket. Malformed packets are skipped.
+    pub fn receive<M: DeserializeOwned>(&self) -> Vec<(SocketAddr, Packet<M>)> {
+        let mut packets = Vec::new();
+        let mut buffer = vec![0u8; MAX_PACKET_SIZE];
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((len, addr)) => match serde_json::from_slice(&buffer[..len]) {
+                    Ok(packet) => packets.push((addr, packet)),
+                    Err(e) => log::debug!("Ignoring malformed packet from {}: {}", addr, e),
+                },
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                // A previous send to a closed port (reported on some platforms)
+                Err(e) if e.kind() == std::io::ErrorKind::ConnectionReset => continue,
+                Err(e) => {
+                    log::warn!("UDP receive failed: {}", e);
+                    break;
+                }
+            }
+        }
+        packets
+    }
+}
+
+/// Reliable/unreliable message state for one peer. `Out` is what this side
+/// sends, `In` what it receives.
+pub struct Connection<Out, In> {
+    pub addr: SocketAddr,
+    next_reliable_id: u64,
+    /// Fragments of sent reliable messages not yet acknowledged, oldest first
+    unacked: VecDeque<Fragment>,
+    /// Reliable messages queued since the last flush (sent right away)
+    unsent_reliable: bool,
+    unreliable: Vec<Out>,
+    /// Next reliable fragment id to deliver
+    next_expected: u64,
+    /// Fragments that arrived ahead of a missing one, within RECEIVE_WINDOW
+    out_of_order: BTreeMap<u64, Fragment>,
+    /// JSON of the reliable message being put back together
+    partial: String,
+    /// The message being put back together grew past MAX_MESSAGE_SIZE;
+    /// its remaining fragments are skipped
+    oversized: bool,
+    ack_pending: bool,
+    last_sent: Option<Instant>,
+    last_resend: Option<Instant>,
+    last_heard: Instant,
+    incoming: PhantomData<fn() -> In>,
+}
+
+impl<Out: Serialize, In: DeserializeOwned> Connection<Out, In> {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            next_reliable_id: 0,
+            unacked: VecDeque::new(),
+            unsent_reliable: false,
+            unreliable: Vec::new(),
+            next_expected: 0,
+            out_of_order: BTreeMap::new(),
+            partial: String::new(),
+            oversized: false,
+            ack_pending: false,
+            last_sent: None,
+            last_resend: None,
+            last_heard: Instant::now(),
+            incoming: PhantomData,
+        }
+    }
+
+    /// Queue a message that is resent until it arrives, in order
+    pub fn send_reliable(&mut self, message: Out) {
+        let json = match serde_json::to_string(&message) {
+            Ok(json) => json,
+            Err(e) => {
+                log::warn!("Failed to encode a reliable message: {}", e);
+                return;
+            }
+        };
+        let fragments = fragment(&json, self.next_reliable_id);
+        self.next_reliable_id += fragments.len() as u64;
+        self.unacked.extend(fragments);
+        self.unsent_reliable = true;
+    }
+
+    /// Queue a message that is sent once and may be lost
+    pub fn send_unreliable(&mut self, message: Out) {
+        self.unreliable.push(message);
+    }
+
+    /// Take in a packet from the peer, returning its messages in order
+    /// (reliable ones first)
+    pub fn receive(&mut self, packet: Packet<In>) -> Vec<In> {
+        self.last_heard = Instant::now();
+        while self.unacked.front().is_some_and(|fragment| fragment.id < packet.ack) {
+            self.unacked.pop_front();
+        }
+
+        let mut messages = Vec::new();
+        let window = self.next_expected..self.next_expected + RECEIVE_WINDOW;
+        for fragment in packet.reliable {
+            self.ack_pending = true;
+            if window.contains(&fragment.id) {
+                self.out_of_order.entry(fragment.id).or_insert(fragment);
+            }
+        }
+        while let Some(fragment) = self.out_of_order.remove(&self.next_expected) {
+            self.next_expected += 1;
+            if let Some(message) = self.reassemble(fragment) {
+                messages.push(message);
+            }
+        }
+        messages.extend(packet.unreliable);
+        messages
+    }
+
+    /// Add the next fragment in order to the message being put back
+    /// together, returning the message once its last fragment is in
+    fn reassemble(&mut self, fragment: Fragment) -> Option<In> {
+        if !self.oversized {
+            self.partial.push_str(&fragment.data);
+            if self.partial.len() > MAX_MESSAGE_SIZE {
+                log::warn!(
+                    "Dropping a reliable message from {} larger than {} bytes",
+                    self.addr,
+                    MAX_MESSAGE_SIZE
+                );
+                self.partial = String::new();
+                self.oversized = true;
+            }
+        }
+        if !fragment.last {
+            return None;
+        }
+        if std::mem::take(&mut self.oversized) {
+            return None;
+        }
+        let json = std::mem::take(&mut self.partial);
+        match serde_json::from_str(&json) {
+            Ok(message) => Some(message),
+            Err(e) => {
+                log::debug!("Ignoring malformed reliable message from {}: {}", self.addr, e);
+                None
+            }
+        }
+    }
+
+    /// Send what is queued, resend unacknowledged reliable messages when
+    /// they are due, and keep the connection alive
+    pub fn flush(&mut self, endpoint: &Endpoint) -> Result<()> {
+        let now = Instant::now();
+        let resend_due = !self.unacked.is_empty()
+            && self
+                .last_resend
+                .is_none_or(|last| now - last >= RESEND_INTERVAL);
+        let send_reliable = self.unsent_reliable || resend_due;
+        let keepalive_due = self
+            .last_sent
+            .is_none_or(|last| now - last >= KEEPALIVE_INTERVAL);
+        if !send_reliable && self.unreliable.is_empty() && !self.ack_pending && !keepalive_due {
+            return Ok(());
+        }
+
+        // The acknowledgement and unreliable messages go in the first packet,
+        // the fragments in as many MTU sized packets as they fill
+        let mut packets = vec![Packet {
+            ack: self.next_expected,
+            reliable: Vec::new(),
+            unreliable: std::mem::take(&mut self.unreliable),
+        }];
+        let mut size = serde_json::to_vec(&packets[0])?.len();
+        for fragment in self.unacked.iter().filter(|_| send_reliable) {
+            // Each fragment adds its JSON and a comma
+            let fragment_size = serde_json::to_vec(fragment)?.len() + 1;
+            let full = packets
+                .last()
+                .is_some_and(|packet| !packet.reliable.is_empty() && size + fragment_size > MTU);
+            if full {
+                let packet = Packet {
+                    ack: self.next_expected,
+                    reliable: Vec::new(),
+                    unreliable: Vec::new(),
+                };
+                size = serde_json::to_vec(&packet)?.len();
+                packets.push(packet);
+            }
+            packets
+                .last_mut()
+                .expect("there is always a first packet")
+                .reliable
+                .push(fragment.clone());
+            size += fragment_size;
+        }
+        if send_reliable {
+            self.last_resend = Some(now);
+            self.unsent_reliable = false;
+        }
+        self.ack_pending = false;
+        self.last_sent = Some(now);
+        for packet in &packets {
+            endpoint.send(self.addr, packet)?;
+        }
+        Ok(())
+    }
+
+    /// True once the peer has been silent for CONNECTION_TIMEOUT
+    pub fn timed_out(&self) -> bool {
+        self.last_heard.elapsed() >= CONNECTION_TIMEOUT
+    }
+
+    /// Reliable messages still waiting for an acknowledgement
+    pub fn unacked_count(&self) -> usize {
+        self.unacked.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reliable_messages_arrive_in_order_once() {
+        let addr: SocketAddr = "127.0.0.1:9".parse().unwrap();
+        let mut sender: Connection<String, String> = Connection::new(addr);
+        let mut receiver: Connection<String, String> = Connection::new(addr);
+
+        sender.send_reliable("a".to_string());
+        sender.send_reliable("b".to_string());
+        sender.send_reliable("c".to_string());
+        let all: Vec<Fragment> = sender.unacked.iter().cloned().collect();
+        assert_eq!(all.len(), 3);
+
+        // "b" arrives late and "c" twice
+        let packet = |reliable: &[Fragment], ack| Packet {
+            ack,
+            reliable: reliable.to_vec(),
+            unreliable: vec!["snapshot".to_string()],
+        };
+        let first = receiver.receive(packet(&[all[0].clone(), all[2].clone()], 0));
+        assert_eq!(first, vec!["a", "snapshot"]);
+        let second = receiver.receive(packet(&[all[1].clone(), all[2].clone()], 0));
+        assert_eq!(second, vec!["b", "c", "snapshot"]);
+
+        // The acknowledgement clears the sender's queue
+        sender.receive(Packet {
+            ack: receiver.next_expected,
+            reliable: Vec::new(),
+            unreliable: Vec::new(),
+        });
+        assert_eq!(sender.unacked_count(), 0);
+    }
+
+    #[test]
+    fn test_large_reliable_messages_are_fragmented_and_reassembled() {
+        let addr: SocketAddr = "127.0.0.1:9".parse().unwrap();
+        let mut sender: Connection<String, String> = Connection::new(addr);
+        let mut receiver: Connection<String, String> = Connection::new(addr);
+
+        // Multi-byte chars and quotes straddle fragment boundaries
+        let message = "é\"".repeat(100_000);
+        sender.send_reliable(message.clone());
+        sender.send_reliable("after".to_string());
+        let fragments: Vec<Fragment> = sender.unacked.iter().cloned().collect();
+        assert!(fragments.len() > 100);
+        assert!(fragments.iter().all(|f| f.data.len() <= FRAGMENT_SIZE));
+
+        // Delivered backwards, nothing comes out until the first fragment is in
+        let packet = |reliable: Vec<Fragment>| Packet {
+            ack: 0,
+            reliable,
+            unreliable: Vec::new(),
+        };
+        for fragment in fragments[1..].iter().rev() {
+            assert!(receiver.receive(packet(vec![fragment.clone()])).is_empty());
+        }
+        let delivered = receiver.receive(packet(vec![fragments[0].clone()]));
+        assert_eq!(delivered, vec![message, "after".to_string()]);
+        assert!(receiver.out_of_order.is_empty());
+    }
+
+    #[test]
+    fn test_fragments_past_the_receive_window_are_dropped() {
+        let addr: SocketAddr = "127.0.0.1:9".parse().unwrap();
+        let mut receiver: Connection<String, String> = Connection::new(addr);
+        let far = Fragment {
+            id: RECEIVE_WINDOW + 5,
+            last: true,
+            data: "\"x\"".to_string(),
+        };
+        let near = Fragment { id: 5, ..far.clone() };
+        receiver.receive(Packet {
+            ack: 0,
+            reliable: vec![far, near],
+            unreliable: Vec::new(),
+        });
+        assert_eq!(receiver.out_of_order.len(), 1);
+        assert!(receiver.out_of_order.contains_key(&5));
+    }
+}
@@ -1,5 +1,6 @@
 // Particle emitter shapes and properties
 
+use engine_core::determinism::SimRng;
 use glam::Vec3;
 use serde::{Deserialize, Serialize};
 
@@ -30,18 +31,16 @@ impl Default for EmitterShape {
 
 impl EmitterShape {
     /// Get a random position within the emitter shape
-    pub fn sample_position(&self) -> Vec3 {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
+    pub fn sample_position(&self, rng: &mut SimRng) -> Vec3 {
 
         match self {
             EmitterShape::Point => Vec3::ZERO,
 
             EmitterShape::Sphere { radius } => {
                 // Uniform sampling in sphere
-                let theta = rng.gen_range(0.0..std::f32::consts::TAU);
-                let phi = rng.gen_range(0.0..std::f32::consts::PI);
-                let r = rng.gen_range(0.0..*radius);
+                let theta = rng.range(0.0, std::f32::consts::TAU);
+                let phi = rng.range(0.0, std::f32::consts::PI);
+                let r = rng.range(0.0, *radius);
 
                 Vec3::new(
                     r * phi.sin() * theta.cos(),
@@ -52,10 +51,10 @@ impl EmitterShape {
 
             EmitterShape::Cone { angle, radius } => {
                 // Sample within cone
-                let theta = rng.gen_range(0.0..std::f32::consts::TAU);
-                let cone_height = rng.gen_range(0.0..*radius);
+                let theta = rng.range(0.0, std::f32::consts::TAU);
+                let cone_height = rng.range(0.0, *radius);
                 let cone_radius = cone_height * angle.tan();
-                let r = rng.gen_range(0.0..cone_radius);
+                let r = rng.range(0.0, cone_radius);
 
                 Vec3::new(r * theta.cos(), cone_height, r * theta.sin())
             }
@@ -63,16 +62,16 @@ impl EmitterShape {
             EmitterShape::Box { size } => {
                 // Uniform sampling in box
                 Vec3::new(
-                    rng.gen_range(-size.x / 2.0..size.x / 2.0),
-                    rng.gen_range(-size.y / 2.0..size.y / 2.0),
-                    rng.gen_range(-size.z / 2.0..size.z / 2.0),
+                    rng.range(-size.x / 2.0, size.x / 2.0),
+                    rng.range(-size.y / 2.0, size.y / 2.0),
+                    rng.range(-size.z / 2.0, size.z / 2.0),
                 )
             }
 
             EmitterShape::Circle { radius } => {
                 // Uniform sampling in circle
-                let theta = rng.gen_range(0.0..std::f32::consts::TAU);
-                let r = rng.gen_range(0.0..*radius);
+                let theta = rng.range(0.0, std::f32::consts::TAU);
+                let r = rng.range(0.0, *radius);
 
                 Vec3::new(r * theta.cos(), 0.0, r * theta.sin())
             }
@@ -141,9 +140,7 @@ impl Default for EmitterProperties {
 
 impl EmitterProperties {
     /// Get initial velocity with randomness applied
-    pub fn sample_velocity(&self) -> Vec3 {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
+    pub fn sample_velocity(&self, rng: &mut SimRng) -> Vec3 {
 
         let base = self.initial_velocity;
         let randomness = self.velocity_randomness;
@@ -153,23 +150,21 @@ impl EmitterProperties {
         }
 
         let random_vec = Vec3::new(
-            rng.gen_range(-1.0..1.0),
-            rng.gen_range(-1.0..1.0),
-            rng.gen_range(-1.0..1.0),
+            rng.range(-1.0, 1.0),
+            rng.range(-1.0, 1.0),
+            rng.range(-1.0, 1.0),
         ) * randomness;
 
         base + random_vec
     }
 
     /// Sample lifetime with randomness
-    pub fn sample_lifetime(&self) -> f32 {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
+    pub fn sample_lifetime(&self, rng: &mut SimRng) -> f32 {
 
         let base = self.lifetime;
         let randomness = self.lifetime_randomness;
 
-        base + rng.gen_range(-randomness..randomness)
+        base + rng.range(-randomness, randomness)
     }
 
     /// Evaluate size at given life ratio (0.0 to 1.0)
@@ -2,6 +2,7 @@
 
 use crate::emitter::EmitterProperties;
 use crate::particle::GpuParticle;
+use engine_core::determinism::SimRng;
 use glam::Vec3;
 use std::collections::VecDeque;
 
@@ -27,6 +28,9 @@ pub struct ParticleSystem {
 
     /// Is emitter enabled
     pub enabled: bool,
+
+    /// Draws spawn positions, velocities and lifetimes
+    rng: SimRng,
 }
 
 impl ParticleSystem {
@@ -47,9 +51,16 @@ impl ParticleSystem {
             properties,
             position: Vec3::ZERO,
             enabled: true,
+            rng: SimRng::from_time(),
         }
     }
 
+    /// Spawn from a seeded generator so the emitter replays exactly
+    pub fn with_rng(mut self, rng: SimRng) -> Self {
+        self.rng = rng;
+        self
+    }
+
     /// Update particle system (spawn new particles)
     pub fn update(&mut self, delta_time: f32) {
         if !self.enabled {
@@ -84,14 +95,14 @@ impl ParticleSystem {
         };
 
         // Sample position within emitter shape
-        let local_pos = self.properties.shape.sample_position();
+        let local_pos = self.properties.shape.sample_position(&mut self.rng);
         let world_pos = self.position + local_pos;
 
         // Sample velocity
-        let velocity = self.properties.sample_velocity();
+        let velocity = self.properties.sample_velocity(&mut self.rng);
 
         // Sample lifetime
-        let lifetime = self.properties.sample_lifetime();
+        let lifetime = self.properties.sample_lifetime(&mut self.rng);
 
         // Create particle
         let particle = GpuParticle::new(
@@ -1,11 +1,92 @@
 // Character controller for player movement
+//
+// Characters are moved kinematically over the ground below them: steps up
+// to `step_offset` are climbed and higher ones block, slopes steeper than
+// `max_slope_angle` can't be walked up and are slid down, the character
+// snaps down onto ground within `snap_distance` so it stays planted walking
+// down slopes and stairs, and it rides along with whatever it stands on.
+// Against a physics world (CharacterSweep) the ground is found by sweeping
+// the character's capsule down, walls stop it and it slides along them,
+// and ceilings end a jump; sensors don't get in the way. CharacterSystem
+// moves every character in the scene by its input, in fixed steps.
 
-use engine_scene::entity::Component;
+use crate::raycast::RaycastHit;
+use crate::shape_query::{QueryShape, ShapeCastQuery};
+use crate::world::{to_rapier_quat, to_rapier_vec, PhysicsWorld};
+use engine_scene::components::{NetSmoothing, Replicated};
+use engine_scene::entity::{Component, EntityId};
 use engine_scene::impl_component;
+use engine_scene::scene::Scene;
 use glam::Vec3;
+use rapier3d::prelude::{Collider, Isometry, Point, QueryFilter, Ray, Vector};
 use serde::{Deserialize, Serialize};
 use std::any::Any;
 
+/// Downward acceleration for characters in the air (times gravity_scale)
+pub const CHARACTER_GRAVITY: f32 = 9.81;
+
+/// How far below a character the ground is looked for
+pub const GROUND_PROBE_DISTANCE: f32 = 50.0;
+
+/// Gap a character keeps from the walls and ceilings it runs into
+const SKIN: f32 = 0.01;
+
+/// Most surfaces a character slides along in one step
+const MAX_SLIDES: usize = 3;
+
+/// Most fixed steps CharacterSystem runs for one update, so a long frame
+/// doesn't snowball into longer ones
+const MAX_CHARACTER_STEPS: u32 = 8;
+
+/// The ground below a point, as a character sees it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ground {
+    pub height: f32,
+    pub normal: Vec3,
+    /// Velocity of the surface there (moving platforms)
+    pub velocity: Vec3,
+}
+
+impl Ground {
+    /// Level, still ground at `height`
+    pub fn flat(height: f32) -> Self {
+        Self {
+            height,
+            normal: Vec3::Y,
+            velocity: Vec3::ZERO,
+        }
+    }
+}
+
+/// What moves a character on its next step
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CharacterInput {
+    /// Desired direction on the ground plane (length up to 1)
+    pub movement: Vec3,
+    pub jump: bool,
+    pub sprint: bool,
+}
+
+/// What a character moves against: the ground below a point and what its
+/// capsule runs into
+pub trait CharacterCollision {
+    /// The ground below `position` (the character's feet), if there is any
+    fn ground(&self, position: Vec3) -> Option<Ground>;
+
+    /// First surface the capsule hits moving by `motion` with its feet at
+    /// `position`; `distance` is how far it gets
+    fn sweep(&self, _position: Vec3, _motion: Vec3) -> Option<RaycastHit> {
+        None
+    }
+}
+
+/// Ground alone, with nothing in the way
+impl<F: Fn(Vec3) -> Option<Ground>> CharacterCollision for F {
+    fn ground(&self, position: Vec3) -> Option<Ground> {
+        self(position)
+    }
+}
+
 /// Character controller component for FPS/TPS movement
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CharacterController {
@@ -25,6 +106,54 @@ pub struct CharacterController {
     pub velocity: Vec3,
     /// Air control factor (0.0 = no air control, 1.0 = full control)
     pub air_control: f32,
+    /// Steepest slope the character can stand on and walk up, in degrees
+    #[serde(default = "default_max_slope_angle")]
+    pub max_slope_angle: f32,
+    /// Highest step the character walks up
+    #[serde(default = "default_step_offset")]
+    pub step_offset: f32,
+    /// How far below it the character snaps down to ground it walks onto
+    #[serde(default = "default_snap_distance")]
+    pub snap_distance: f32,
+    /// Normal of the ground below (up when in the air)
+    #[serde(default = "default_ground_normal")]
+    pub ground_normal: Vec3,
+    /// Velocity of the platform the character is standing on
+    #[serde(default)]
+    pub platform_velocity: Vec3,
+    /// Radius of the character's capsule
+    #[serde(default = "default_radius")]
+    pub radius: f32,
+    /// Height of the character's capsule, feet to top
+    #[serde(default = "default_height")]
+    pub height: f32,
+    /// Input CharacterSystem moves the character by (set by scripts)
+    #[serde(skip)]
+    pub input: CharacterInput,
+}
+
+fn default_max_slope_angle() -> f32 {
+    45.0
+}
+
+fn default_step_offset() -> f32 {
+    0.3
+}
+
+fn default_snap_distance() -> f32 {
+    0.2
+}
+
+fn default_ground_normal() -> Vec3 {
+    Vec3::Y
+}
+
+fn default_radius() -> f32 {
+    0.4
+}
+
+fn default_height() -> f32 {
+    1.8
 }
 
 impl CharacterController {
@@ -38,6 +167,14 @@ impl CharacterController {
             ground_distance: 0.1,
             velocity: Vec3::ZERO,
             air_control: 0.3,
+            max_slope_angle: default_max_slope_angle(),
+            step_offset: default_step_offset(),
+            snap_distance: default_snap_distance(),
+            ground_normal: Vec3::Y,
+            platform_velocity: Vec3::ZERO,
+            radius: default_radius(),
+            height: default_height(),
+            input: CharacterInput::default(),
         }
     }
 
@@ -51,6 +188,37 @@ impl CharacterController {
         self
     }
 
+    pub fn with_max_slope_angle(mut self, degrees: f32) -> Self {
+        self.max_slope_angle = degrees;
+        self
+    }
+
+    pub fn with_step_offset(mut self, height: f32) -> Self {
+        self.step_offset = height;
+        self
+    }
+
+    pub fn with_snap_distance(mut self, distance: f32) -> Self {
+        self.snap_distance = distance;
+        self
+    }
+
+    pub fn with_capsule(mut self, radius: f32, height: f32) -> Self {
+        self.radius = radius;
+        self.height = height;
+        self
+    }
+
+    /// Whether ground with this normal is gentle enough to stand on
+    pub fn is_walkable(&self, normal: Vec3) -> bool {
+        normal.y >= self.max_slope_angle.to_radians().cos() - 1e-4
+    }
+
+    /// Whether the character is on ground too steep to stand on
+    pub fn is_sliding(&self) -> bool {
+        !self.grounded && !self.is_walkable(self.ground_normal)
+    }
+
     /// Calculate movement for this frame
     pub fn calculate_movement(&mut self, input: Vec3, delta_time: f32, is_sprinting: bool) -> Vec3 {
         let speed = if is_sprinting {
@@ -72,6 +240,110 @@ impl CharacterController {
         self.velocity * delta_time
     }
 
+    /// Move a character at `position` for one step of `movement` input
+    /// (a direction on the ground plane) against `collision`. Returns the
+    /// new position.
+    pub fn advance(
+        &mut self,
+        position: Vec3,
+        movement: Vec3,
+        jump: bool,
+        sprint: bool,
+        dt: f32,
+        collision: &impl CharacterCollision,
+    ) -> Vec3 {
+        let was_grounded = self.grounded;
+        let sliding = self.is_sliding();
+
+        // calculate_movement smooths the whole velocity toward the target; only
+        // its horizontal part is used, the vertical speed is kept here
+        let vertical_speed = self.velocity.y;
+        let moved = self.calculate_movement(movement, dt, sprint);
+        self.velocity.y = vertical_speed;
+        let mut step = Vec3::new(moved.x, 0.0, moved.z);
+
+        let mut position = position;
+        if was_grounded {
+            position += self.platform_velocity * dt;
+        }
+
+        // Walls stop the capsule and it slides along them; it is swept
+        // step_offset up so steps it can climb don't count
+        let lift = Vec3::Y * self.step_offset;
+        step = slide(collision, position + lift, step);
+
+        // Steps higher than step_offset block, and slopes too steep to stand
+        // on can't be walked up
+        if let Some(ahead) = collision.ground(position + step) {
+            if ahead.height > position.y + self.step_offset {
+                step = Vec3::ZERO;
+            } else if !self.is_walkable(ahead.normal) && ahead.height > position.y {
+                let downhill = Vec3::new(ahead.normal.x, 0.0, ahead.normal.z).normalize_or_zero();
+                step -= downhill * step.dot(downhill).min(0.0);
+            }
+        }
+        position += step;
+
+        if jump {
+            if let Some(force) = self.jump() {
+                self.velocity.y = force;
+            }
+        }
+        let gravity = CHARACTER_GRAVITY * self.gravity_scale;
+        if sliding && self.velocity.y <= 0.0 {
+            // Down the slope as if frictionless: the vertical part of gravity
+            // along it, moving downhill as far as the slope falls
+            let normal = self.ground_normal;
+            let sin_squared = (1.0 - normal.y * normal.y).max(1e-4);
+            self.velocity.y -= gravity * sin_squared * dt;
+            let fall = -self.velocity.y * dt;
+            let downhill = Vec3::new(normal.x, 0.0, normal.z).normalize_or_zero();
+            position += downhill * fall * normal.y / sin_squared.sqrt();
+            position.y -= fall;
+        } else {
+            if !self.grounded {
+                self.velocity.y -= gravity * dt;
+            }
+            let mut rise = self.velocity.y * dt;
+            // A ceiling ends the jump
+            if rise > 0.0 {
+                if let Some(hit) = collision.sweep(position, Vec3::Y * rise) {
+                    if hit.normal.y < 0.0 {
+                        rise = (hit.distance - SKIN).max(0.0);
+                        self.velocity.y = 0.0;
+                    }
+                }
+            }
+            position.y += rise;
+        }
+
+        let reach = if was_grounded && self.velocity.y <= 0.0 {
+            self.ground_distance + self.snap_distance
+        } else {
+            self.ground_distance
+        };
+        match collision.ground(position) {
+            Some(below) if self.velocity.y <= 0.0 && position.y <= below.height + reach => {
+                position.y = below.height;
+                self.ground_normal = below.normal;
+                if self.is_walkable(below.normal) {
+                    self.velocity.y = 0.0;
+                    self.grounded = true;
+                    self.platform_velocity = below.velocity;
+                } else {
+                    self.grounded = false;
+                    self.platform_velocity = Vec3::ZERO;
+                }
+            }
+            _ => {
+                self.grounded = false;
+                self.ground_normal = Vec3::Y;
+                self.platform_velocity = Vec3::ZERO;
+            }
+        }
+        position
+    }
+
     /// Initiate a jump
     pub fn jump(&mut self) -> Option<f32> {
         if self.grounded {
@@ -90,3 +362,356 @@ impl Default for CharacterController {
 }
 
 impl_component!(CharacterController);
+
+/// How far `motion` gets from `position` before the capsule hits
+/// something, sliding along what it hits for the rest. Stays on the ground
+/// plane.
+fn slide(collision: &impl CharacterCollision, position: Vec3, motion: Vec3) -> Vec3 {
+    let mut moved = Vec3::ZERO;
+    let mut motion = motion;
+    for _ in 0..MAX_SLIDES {
+        let distance = motion.length();
+        if distance <= 1e-6 {
+            break;
+        }
+        let direction = motion / distance;
+        let Some(hit) = collision
+            .sweep(position + moved, motion)
+            .filter(|hit| hit.normal.dot(direction) < 0.0)
+        else {
+            moved += motion;
+            break;
+        };
+        let travel = (hit.distance - SKIN).max(0.0);
+        moved += direction * travel;
+        let rest = direction * (distance - travel);
+        let normal = Vec3::new(hit.normal.x, 0.0, hit.normal.z).normalize_or_zero();
+        motion = rest - normal * rest.dot(normal);
+    }
+    moved
+}
+
+/// A character's capsule against a physics world: casts skip the
+/// character's own body and sensors
+pub struct CharacterSweep<'a> {
+    physics: &'a PhysicsWorld,
+    character: EntityId,
+    shape: QueryShape,
+    /// Feet to capsule center
+    center: f32,
+    /// How far above the feet the ground probe starts: steps the character
+    /// can climb are found, anything higher starts inside the capsule
+    lift: f32,
+}
+
+impl<'a> CharacterSweep<'a> {
+    pub fn new(
+        physics: &'a PhysicsWorld,
+        character: EntityId,
+        controller: &CharacterController,
+    ) -> Self {
+        let radius = controller.radius.max(0.01);
+        let half_height = (controller.height * 0.5 - radius).max(0.0);
+        Self {
+            physics,
+            character,
+            shape: QueryShape::Capsule {
+                half_height,
+                radius,
+            },
+            center: half_height + radius,
+            lift: controller.step_offset + SKIN,
+        }
+    }
+
+    fn cast(&self, feet: Vec3, direction: Vec3, distance: f32) -> Option<RaycastHit> {
+        let query = ShapeCastQuery::new(
+            self.shape,
+            feet + Vec3::Y * self.center,
+            direction,
+            distance,
+        )
+        .with_ignored(&[self.character]);
+        self.physics.shape_cast(&query)
+    }
+
+    /// Normal of the surface straight down from `origin`. The capsule's
+    /// contact normal bends round edges, which would make the edge of a
+    /// step look like a steep slope.
+    fn surface_normal(&self, origin: Vec3, max_distance: f32) -> Option<Vec3> {
+        let ray = Ray::new(Point::from(to_rapier_vec(origin)), -Vector::y());
+        let not_character = |_, collider: &Collider| {
+            collider
+                .parent()
+                .and_then(|body| self.physics.get_entity_id(body))
+                .is_some_and(|entity| entity != self.character)
+        };
+        let filter = QueryFilter::default()
+            .exclude_sensors()
+            .predicate(&not_character);
+        let (_, hit) = self.physics.query_pipeline.cast_ray_and_get_normal(
+            &self.physics.rigid_body_set,
+            &self.physics.collider_set,
+            &ray,
+            max_distance,
+            true,
+            filter,
+        )?;
+        Some(Vec3::new(hit.normal.x, hit.normal.y, hit.normal.z))
+    }
+}
+
+impl CharacterCollision for CharacterSweep<'_> {
+    fn ground(&self, position: Vec3) -> Option<Ground> {
+        let start = position + Vec3::Y * self.lift;
+        let hit = self.cast(start, Vec3::NEG_Y, GROUND_PROBE_DISTANCE)?;
+        let normal = self
+            .surface_normal(start, hit.distance + self.lift)
+            .unwrap_or(hit.normal);
+        let velocity = self
+            .physics
+            .get_body_handle(hit.entity_id)
+            .and_then(|handle| self.physics.get_rigid_body(handle))
+            .map(|body| {
+                let velocity =
+                    body.velocity_at_point(&Point::new(hit.point.x, hit.point.y, hit.point.z));
+                Vec3::new(velocity.x, velocity.y, velocity.z)
+            })
+            .unwrap_or(Vec3::ZERO);
+        Some(Ground {
+            height: start.y - hit.distance,
+            normal,
+            velocity,
+        })
+    }
+
+    fn sweep(&self, position: Vec3, motion: Vec3) -> Option<RaycastHit> {
+        let distance = motion.length();
+        if distance <= 0.0 {
+            return None;
+        }
+        self.cast(position, motion / distance, distance)
+    }
+}
+
+/// Moves every CharacterController in the scene by its `input` against the
+/// physics world, in fixed steps, and takes kinematic bodies along.
+/// Characters a network client predicts are left to the inputs it sends.
+pub struct CharacterSystem {
+    step: f32,
+    accumulator: f32,
+}
+
+impl CharacterSystem {
+    pub fn new(step: f32) -> Self {
+        Self {
+            step,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Run the fixed steps `delta_time` adds up to. Each character's input
+    /// applies to all of them and is then cleared.
+    pub fn update(&mut self, scene: &mut Scene, physics: &mut PhysicsWorld, delta_time: f32) {
+        if self.step <= 0.0 {
+            return;
+        }
+        self.accumulator =
+            (self.accumulator + delta_time).min(self.step * MAX_CHARACTER_STEPS as f32);
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        if steps == 0 {
+            return;
+        }
+
+        let mut characters: Vec<EntityId> = scene
+            .entities()
+            .filter(|e| e.has_component::<CharacterController>())
+            .filter(|e| {
+                !e.get_component::<Replicated>().is_some_and(|replicated| {
+                    replicated.owner.is_some() && replicated.smoothing == NetSmoothing::Predict
+                })
+            })
+            .map(|e| e.id)
+            .collect();
+        characters.sort_by_key(|id| id.0);
+
+        for id in characters {
+            let Some(entity) = scene.get_entity_mut(id) else {
+                continue;
+            };
+            let Some(mut controller) = entity.get_component::<CharacterController>().cloned()
+            else {
+                continue;
+            };
+            let input = std::mem::take(&mut controller.input);
+            let mut position = entity.transform.position;
+            let sweep = CharacterSweep::new(physics, id, &controller);
+            for step in 0..steps {
+                position = controller.advance(
+                    position,
+                    input.movement,
+                    input.jump && step == 0,
+                    input.sprint,
+                    self.step,
+                    &sweep,
+                );
+            }
+            entity.transform.position = position;
+            let rotation = entity.transform.rotation;
+            if let Some(stored) = entity.get_component_mut::<CharacterController>() {
+                *stored = controller;
+            }
+
+            if let Some(body) = physics
+                .get_body_handle(id)
+                .and_then(|handle| physics.get_rigid_body_mut(handle))
+                .filter(|body| body.is_kinematic())
+            {
+                body.set_next_kinematic_position(Isometry::from_parts(
+                    to_rapier_vec(position).into(),
+                    to_rapier_quat(rotation),
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn run(
+        controller: &mut CharacterController,
+        mut position: Vec3,
+        movement: Vec3,
+        steps: usize,
+        ground: impl Fn(Vec3) -> Option<Ground>,
+    ) -> Vec3 {
+        for _ in 0..steps {
+            position = controller.advance(position, movement, false, false, DT, &ground);
+        }
+        position
+    }
+
+    #[test]
+    fn test_steps_climbed_and_walls_block() {
+        // A 0.2 step at z = 1 and a 1.0 ledge at z = 3
+        let stairs = |p: Vec3| {
+            Some(Ground::flat(if p.z >= 3.0 {
+                1.2
+            } else if p.z >= 1.0 {
+                0.2
+            } else {
+                0.0
+            }))
+        };
+        let mut controller = CharacterController::new();
+        controller.grounded = true;
+        let end = run(&mut controller, Vec3::ZERO, Vec3::Z, 120, stairs);
+        assert!(controller.grounded);
+        assert_eq!(end.y, 0.2);
+        assert!(end.z > 2.5 && end.z < 3.0, "{}", end.z);
+    }
+
+    #[test]
+    fn test_slides_down_steep_slopes_and_rides_platforms() {
+        // 60 degree slope falling toward +x
+        let normal = Vec3::new(60f32.to_radians().sin(), 60f32.to_radians().cos(), 0.0);
+        let slope = |p: Vec3| {
+            Some(Ground {
+                height: -p.x * 60f32.to_radians().tan(),
+                normal,
+                velocity: Vec3::ZERO,
+            })
+        };
+        let mut controller = CharacterController::new();
+        let end = run(
+            &mut controller,
+            Vec3::new(0.0, 0.5, 0.0),
+            Vec3::ZERO,
+            60,
+            slope,
+        );
+        assert!(controller.is_sliding());
+        assert!(!controller.grounded);
+        assert!(end.x > 0.5, "{}", end.x);
+        assert!((end.y - slope(end).unwrap().height).abs() < 1e-3);
+
+        // Standing on a platform moving along x carries the character
+        let platform = |_: Vec3| {
+            Some(Ground {
+                velocity: Vec3::X * 2.0,
+                ..Ground::flat(0.0)
+            })
+        };
+        let mut controller = CharacterController::new();
+        let end = run(&mut controller, Vec3::ZERO, Vec3::ZERO, 61, platform);
+        assert!(controller.grounded);
+        assert_eq!(controller.ground_normal, Vec3::Y);
+        assert!((end.x - 2.0).abs() < 1e-3, "{}", end.x);
+
+        // Walking off a small drop snaps down instead of falling
+        let drop = |p: Vec3| Some(Ground::flat(if p.z > 0.1 { -0.15 } else { 0.0 }));
+        let mut controller = CharacterController::new();
+        controller.grounded = true;
+        let end = run(&mut controller, Vec3::ZERO, Vec3::Z, 30, drop);
+        assert!(controller.grounded);
+        assert_eq!(end.y, -0.15);
+    }
+
+    #[test]
+    fn test_capsule_climbs_steps_and_stops_at_walls_not_sensors() {
+        use rapier3d::prelude::{ColliderBuilder, RigidBodyBuilder};
+
+        // A floor with its top at y = 0, a trigger volume across z = 1..2,
+        // a 0.2 step from z = 2 and a wall from z = 3
+        let mut physics = PhysicsWorld::new(Vec3::new(0.0, -9.81, 0.0));
+        let boxes = [
+            ([0.0, -0.5, 0.0], [10.0, 0.5, 10.0], false),
+            ([0.0, 1.0, 1.5], [2.0, 1.0, 0.5], true),
+            ([0.0, 0.1, 2.5], [2.0, 0.1, 0.5], false),
+            ([0.0, 1.0, 3.5], [2.0, 1.0, 0.5], false),
+        ];
+        for (i, (center, half_extents, sensor)) in boxes.into_iter().enumerate() {
+            let body = physics.create_rigid_body(
+                EntityId(100 + i as u64),
+                RigidBodyBuilder::fixed()
+                    .translation(to_rapier_vec(Vec3::from_array(center)))
+                    .build(),
+            );
+            let [x, y, z] = half_extents;
+            physics.create_collider(
+                body,
+                ColliderBuilder::cuboid(x, y, z).sensor(sensor).build(),
+            );
+        }
+        physics.query_pipeline.update(&physics.collider_set);
+
+        let mut scene = Scene::new("Test".to_string());
+        let id = scene.create_entity("Player".to_string());
+        scene
+            .get_entity_mut(id)
+            .unwrap()
+            .add_component(CharacterController::new());
+        let mut system = CharacterSystem::new(DT);
+        for _ in 0..120 {
+            let entity = scene.get_entity_mut(id).unwrap();
+            let controller = entity.get_component_mut::<CharacterController>().unwrap();
+            controller.input.movement = Vec3::Z;
+            system.update(&mut scene, &mut physics, DT);
+        }
+
+        let entity = scene.get_entity(id).unwrap();
+        let controller = entity.get_component::<CharacterController>().unwrap();
+        let end = entity.transform.position;
+        assert!(controller.grounded);
+        assert!((end.y - 0.2).abs() < 1e-3, "{}", end.y);
+        assert!(end.z > 2.5 && end.z < 2.6, "{}", end.z);
+    }
+}
@@ -1,10 +1,16 @@
 // Physics components
 
+use engine_assets::{HeightMap, Mesh, TerrainConfig};
 use engine_scene::entity::Component;
 use engine_scene::impl_component;
+use engine_scene::scene::Scene;
 use glam::Vec3;
+use rapier3d::na::DMatrix;
+use rapier3d::parry::shape::{HeightField, HeightFieldCellStatus};
+use rapier3d::prelude::{ColliderBuilder, Point, Real, SharedShape};
 use serde::{Deserialize, Serialize};
 use std::any::Any;
+use std::collections::HashMap;
 
 /// Rigid body type
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -100,6 +106,89 @@ pub enum ColliderShape {
     Capsule { half_height: f32, radius: f32 },
     /// Cylinder collider
     Cylinder { half_height: f32, radius: f32 },
+    /// Triangle mesh of a mesh asset (`path` or `path#index`) at the
+    /// entity's scale - exact but hollow, for static and kinematic geometry
+    TriMesh { mesh_path: String },
+    /// Mesh asset split into convex parts when the collider is built -
+    /// approximate, but solid, so it works on dynamic bodies
+    ConvexDecomposition { mesh_path: String },
+    /// Heightfield of the scene's terrain, laid out like the terrain mesh,
+    /// holes included
+    HeightField,
+}
+
+/// Meshes and terrain that mesh and heightfield colliders are built from
+/// when physics is set up
+#[derive(Clone, Default)]
+pub struct ColliderGeometry {
+    meshes: HashMap<String, Mesh>,
+    terrain: Option<(HeightMap, TerrainConfig)>,
+}
+
+impl ColliderGeometry {
+    /// Geometry for a scene's colliders (see `for_colliders`)
+    pub fn for_scene(
+        scene: &Scene,
+        load_mesh: impl FnMut(&str) -> Option<Mesh>,
+        terrain: impl FnOnce() -> Option<(HeightMap, TerrainConfig)>,
+    ) -> Self {
+        let colliders = scene
+            .entities()
+            .filter_map(|e| e.get_component::<Collider>());
+        Self::for_colliders(colliders, load_mesh, terrain)
+    }
+
+    /// Geometry for some colliders: `load_mesh` is asked once for each mesh
+    /// they reference, and `terrain` only if one is a heightfield
+    pub fn for_colliders<'a>(
+        colliders: impl IntoIterator<Item = &'a Collider>,
+        mut load_mesh: impl FnMut(&str) -> Option<Mesh>,
+        terrain: impl FnOnce() -> Option<(HeightMap, TerrainConfig)>,
+    ) -> Self {
+        let mut geometry = Self::default();
+        let mut wants_terrain = false;
+        for collider in colliders {
+            match &collider.shape {
+                ColliderShape::TriMesh { mesh_path }
+                | ColliderShape::ConvexDecomposition { mesh_path }
+                    if !geometry.meshes.contains_key(mesh_path) =>
+                {
+                    match load_mesh(mesh_path) {
+                        Some(mesh) => {
+                            geometry.meshes.insert(mesh_path.clone(), mesh);
+                        }
+                        None => log::warn!("Collider mesh '{}' could not be loaded", mesh_path),
+                    }
+                }
+                ColliderShape::HeightField => wants_terrain = true,
+                _ => {}
+            }
+        }
+        if wants_terrain {
+            geometry.terrain = terrain();
+        }
+        geometry
+    }
+
+    pub fn with_mesh(mut self, path: impl Into<String>, mesh: Mesh) -> Self {
+        self.meshes.insert(path.into(), mesh);
+        self
+    }
+
+    pub fn with_terrain(mut self, height_map: HeightMap, config: TerrainConfig) -> Self {
+        self.terrain = Some((height_map, config));
+        self
+    }
+
+    pub fn mesh(&self, path: &str) -> Option<&Mesh> {
+        self.meshes.get(path)
+    }
+
+    pub fn terrain(&self) -> Option<(&HeightMap, &TerrainConfig)> {
+        self.terrain
+            .as_ref()
+            .map(|(height_map, config)| (height_map, config))
+    }
 }
 
 /// Collider component - defines collision shape
@@ -114,28 +203,42 @@ pub struct Collider {
 
 impl Collider {
     pub fn box_collider(half_extents: Vec3) -> Self {
-        Self {
-            shape: ColliderShape::Box { half_extents },
-            friction: 0.5,
-            restitution: 0.0,
-            density: 1.0,
-            is_sensor: false,
-        }
+        Self::with_shape(ColliderShape::Box { half_extents })
     }
 
     pub fn sphere(radius: f32) -> Self {
-        Self {
-            shape: ColliderShape::Sphere { radius },
-            friction: 0.5,
-            restitution: 0.0,
-            density: 1.0,
-            is_sensor: false,
-        }
+        Self::with_shape(ColliderShape::Sphere { radius })
     }
 
     pub fn capsule(half_height: f32, radius: f32) -> Self {
+        Self::with_shape(ColliderShape::Capsule {
+            half_height,
+            radius,
+        })
+    }
+
+    /// Triangle mesh collider of a mesh asset, built at the entity's scale
+    pub fn trimesh(mesh_path: impl Into<String>) -> Self {
+        Self::with_shape(ColliderShape::TriMesh {
+            mesh_path: mesh_path.into(),
+        })
+    }
+
+    /// Convex decomposition of a mesh asset, built at the entity's scale
+    pub fn convex_decomposition(mesh_path: impl Into<String>) -> Self {
+        Self::with_shape(ColliderShape::ConvexDecomposition {
+            mesh_path: mesh_path.into(),
+        })
+    }
+
+    /// Heightfield collider of the scene's terrain
+    pub fn heightfield() -> Self {
+        Self::with_shape(ColliderShape::HeightField)
+    }
+
+    fn with_shape(shape: ColliderShape) -> Self {
         Self {
-            shape: ColliderShape::Capsule { half_height, radius },
+            shape,
             friction: 0.5,
             restitution: 0.0,
             density: 1.0,
@@ -162,6 +265,211 @@ impl Collider {
         self.is_sensor = true;
         self
     }
+
+    /// Convert to a Rapier collider builder, with mesh shapes built from
+    /// `geometry` at `scale` and heightfields from its terrain. None if
+    /// `geometry` doesn't have what the shape references.
+    pub fn to_rapier(&self, geometry: &ColliderGeometry, scale: Vec3) -> Option<ColliderBuilder> {
+        let builder = match &self.shape {
+            ColliderShape::Box { half_extents } => {
+                ColliderBuilder::cuboid(half_extents.x, half_extents.y, half_extents.z)
+            }
+            ColliderShape::Sphere { radius } => ColliderBuilder::ball(*radius),
+            ColliderShape::Capsule { half_height, radius } => {
+                ColliderBuilder::capsule_y(*half_height, *radius)
+            }
+            ColliderShape::Cylinder { half_height, radius } => {
+                ColliderBuilder::cylinder(*half_height, *radius)
+            }
+            ColliderShape::TriMesh { mesh_path } => {
+                let (points, triangles) = rapier_mesh(geometry.mesh(mesh_path)?, scale)?;
+                ColliderBuilder::trimesh(points, triangles)
+            }
+            ColliderShape::ConvexDecomposition { mesh_path } => {
+                let (points, triangles) = rapier_mesh(geometry.mesh(mesh_path)?, scale)?;
+                ColliderBuilder::convex_decomposition(&points, &triangles)
+            }
+            ColliderShape::HeightField => {
+                let (height_map, config) = geometry.terrain()?;
+                terrain_heightfield(height_map, config, Vec3::ZERO)?
+            }
+        };
+        Some(
+            builder
+                .friction(self.friction)
+                .restitution(self.restitution)
+                .density(self.density)
+                .sensor(self.is_sensor),
+        )
+    }
 }
 
 impl_component!(Collider);
+
+/// Rapier vertex and index buffers of a triangle mesh
+type RapierMesh = (Vec<Point<Real>>, Vec<[u32; 3]>);
+
+/// Rapier buffers of a mesh with `scale` applied to its vertices, leaving
+/// out triangles with indices past the vertices (None if no triangles are
+/// left, which Rapier can't build)
+fn rapier_mesh(mesh: &Mesh, scale: Vec3) -> Option<RapierMesh> {
+    let points: Vec<Point<Real>> = mesh
+        .vertices
+        .iter()
+        .map(|v| v.position * scale)
+        .map(|p| Point::new(p.x, p.y, p.z))
+        .collect();
+    let triangles: Vec<[u32; 3]> = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|t| [t[0], t[1], t[2]])
+        .filter(|t| t.iter().all(|&i| (i as usize) < points.len()))
+        .collect();
+    (!triangles.is_empty()).then_some((points, triangles))
+}
+
+/// Heightfield collider laid out like the terrain mesh generated from
+/// `height_map` and `config`, moved by `offset`, holes included. None if the
+/// height map has fewer than 2x2 samples.
+pub fn terrain_heightfield(
+    height_map: &HeightMap,
+    config: &TerrainConfig,
+    offset: Vec3,
+) -> Option<ColliderBuilder> {
+    let (width, depth) = (height_map.width, height_map.depth);
+    if width < 2 || depth < 2 || height_map.heights.len() < width * depth {
+        return None;
+    }
+    let origin = Vec3::new(-config.scale * 0.5, 0.0, -config.scale * 0.5) + offset;
+    Some(heightfield_builder(
+        &height_map.heights,
+        &height_map.holes,
+        width,
+        depth,
+        origin,
+        config.scale / config.width as f32,
+    ))
+}
+
+/// Heightfield collider builder for a grid of `depth` rows of `width`
+/// heights, `cell_size` apart along X and Z, with sample (0, 0) at `origin`.
+/// `holes` (empty for none) is indexed like the heights and removes the cell
+/// each sample is the lowest corner of. Missing heights are 0 and the grid
+/// is at least 2x2 samples.
+pub(crate) fn heightfield_builder(
+    heights: &[f32],
+    holes: &[bool],
+    width: usize,
+    depth: usize,
+    origin: Vec3,
+    cell_size: f32,
+) -> ColliderBuilder {
+    let (columns, rows) = (width.max(2), depth.max(2));
+    // Rapier heightfields are centered, with rows along Z and columns along X
+    let matrix = DMatrix::from_fn(rows, columns, |row, col| {
+        let sample = (row < depth && col < width).then(|| row * width + col);
+        sample.and_then(|i| heights.get(i)).copied().unwrap_or(0.0)
+    });
+    let size = Vec3::new(
+        (columns - 1) as f32 * cell_size,
+        1.0,
+        (rows - 1) as f32 * cell_size,
+    );
+    let center = origin + Vec3::new(size.x * 0.5, 0.0, size.z * 0.5);
+    let mut heightfield = HeightField::new(matrix, crate::world::to_rapier_vec(size));
+    for (index, _) in holes.iter().enumerate().filter(|(_, &hole)| hole) {
+        let (row, col) = (index / columns, index % columns);
+        if row + 1 < rows && col + 1 < columns {
+            heightfield.set_cell_status(row, col, HeightFieldCellStatus::CELL_REMOVED);
+        }
+    }
+    ColliderBuilder::new(SharedShape::new(heightfield))
+        .translation(crate::world::to_rapier_vec(center))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raycast::RaycastQuery;
+    use crate::world::PhysicsWorld;
+    use engine_scene::entity::EntityId;
+    use rapier3d::prelude::RigidBodyBuilder;
+
+    fn static_collider(collider: ColliderBuilder) -> PhysicsWorld {
+        let mut world = PhysicsWorld::default();
+        let body = world.create_rigid_body(EntityId(1), RigidBodyBuilder::fixed().build());
+        world.create_collider(body, collider.build());
+        world.step(1.0 / 60.0);
+        world
+    }
+
+    #[test]
+    fn test_mesh_colliders_follow_the_mesh() {
+        let geometry = ColliderGeometry::default()
+            .with_mesh("cube", Mesh::cube())
+            .with_mesh(
+                "empty",
+                Mesh::new("Empty".to_string(), Vec::new(), Vec::new()),
+            );
+        let trimesh = Collider::trimesh("cube").to_rapier(&geometry, Vec3::splat(2.0));
+        let world = static_collider(trimesh.unwrap());
+        let hit = world
+            .raycast(&RaycastQuery::new(
+                Vec3::new(0.0, 5.0, 0.0),
+                Vec3::NEG_Y,
+                10.0,
+            ))
+            .unwrap();
+        assert!((hit.point.y - 1.0).abs() < 1e-4, "{}", hit.point.y);
+
+        let hull = Collider::convex_decomposition("cube").to_rapier(&geometry, Vec3::ONE);
+        let world = static_collider(hull.unwrap());
+        let hit = world
+            .raycast(&RaycastQuery::new(
+                Vec3::new(5.0, 0.0, 0.0),
+                Vec3::NEG_X,
+                10.0,
+            ))
+            .unwrap();
+        assert!((hit.point.x - 0.5).abs() < 1e-3, "{}", hit.point.x);
+
+        assert!(Collider::trimesh("empty")
+            .to_rapier(&geometry, Vec3::ONE)
+            .is_none());
+        assert!(Collider::trimesh("missing")
+            .to_rapier(&geometry, Vec3::ONE)
+            .is_none());
+    }
+
+    #[test]
+    fn test_heightfield_matches_terrain_layout() {
+        let config = TerrainConfig {
+            width: 4,
+            depth: 4,
+            scale: 12.0,
+            ..Default::default()
+        };
+        let mut height_map = HeightMap {
+            width: 4,
+            depth: 4,
+            heights: vec![2.0; 16],
+            holes: Vec::new(),
+        };
+        height_map.set_hole(0, 0, true);
+        assert!(Collider::heightfield()
+            .to_rapier(&ColliderGeometry::default(), Vec3::ONE)
+            .is_none());
+        let geometry = ColliderGeometry::default().with_terrain(height_map, config);
+        let collider = Collider::heightfield().to_rapier(&geometry, Vec3::ONE);
+        let world = static_collider(collider.unwrap());
+
+        // Cell size is 3 from -6, so the grid spans -6..3
+        let down = |x: f32, z: f32| {
+            world.raycast(&RaycastQuery::new(Vec3::new(x, 10.0, z), Vec3::NEG_Y, 20.0))
+        };
+        let hit = down(1.0, 1.0).unwrap();
+        assert!((hit.point.y - 2.0).abs() < 1e-4, "{}", hit.point.y);
+        assert!(down(-4.5, -4.5).is_none());
+        assert!(down(4.0, 0.0).is_none());
+    }
+}
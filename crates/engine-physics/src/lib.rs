@@ -7,18 +7,27 @@ pub mod joints;
 pub mod layers;
 pub mod ragdoll;
 pub mod raycast;
+pub mod settle;
+pub mod shape_query;
 pub mod sync;
 pub mod world;
 
 pub use buoyancy::{BuoyancySystem, WaterVolume};
-pub use character::CharacterController;
-pub use components::{Collider, ColliderShape, RigidBody, RigidBodyType};
+pub use character::{
+    CharacterCollision, CharacterController, CharacterInput, CharacterSweep, CharacterSystem,
+    Ground, CHARACTER_GRAVITY, GROUND_PROBE_DISTANCE,
+};
+pub use components::{
+    terrain_heightfield, Collider, ColliderGeometry, ColliderShape, RigidBody, RigidBodyType,
+};
 pub use joints::{JointConfig, JointHandle, JointManager, JointType};
 pub use layers::CollisionGroups;
 pub mod collision_layers {
     pub use crate::layers::layers::*;
 }
-pub use ragdoll::{Ragdoll, RagdollConfig, RagdollPart};
+pub use ragdoll::{Ragdoll, RagdollConfig, RagdollPart, RagdollSystem};
 pub use raycast::{RaycastHit, RaycastQuery};
+pub use shape_query::{OverlapQuery, QueryShape, ShapeCastQuery};
+pub use settle::{DropSimulation, SettledBody};
 pub use sync::PhysicsSync;
 pub use world::{from_rapier_quat, from_rapier_vec, to_rapier_quat, to_rapier_vec, PhysicsWorld};
@@ -1,11 +1,24 @@
 // Ragdoll physics system - hierarchical rigidbody chains with joints
+//
+// A RagdollRig component builds a ragdoll from the bone entities under its
+// root bone. Its bones follow animation on kinematic bodies until a mode is
+// requested: full ragdoll (death, hard impacts), upper body only (hit
+// reactions), or getting up, which blends each bone from where the ragdoll
+// left it back to the animated pose.
+
+use std::collections::hash_map::{Entry, HashMap};
 
 use glam::{Quat, Vec3};
 use rapier3d::prelude::*;
+use engine_scene::components::{RagdollMode, RagdollRig};
 use engine_scene::entity::EntityId;
+use engine_scene::Scene;
 
-use crate::components::ColliderShape;
+use crate::components::{Collider, ColliderGeometry, ColliderShape};
 use crate::joints::{JointConfig, JointHandle};
+use crate::layers::{layers, CollisionGroups};
+use crate::sync::set_world_pose;
+use crate::world::{to_rapier_quat, to_rapier_vec, PhysicsWorld};
 
 /// Ragdoll body part definition
 #[derive(Debug, Clone)]
@@ -24,6 +37,12 @@ pub struct RagdollPart {
     pub parent_index: Option<usize>,
     /// Joint configuration connecting to parent
     pub joint_config: Option<JointConfig>,
+    /// Bone entity the part's body follows and moves
+    pub bone: Option<EntityId>,
+    /// Collider position in the part's frame
+    pub collider_position: Vec3,
+    /// Collider rotation in the part's frame
+    pub collider_rotation: Quat,
 }
 
 impl RagdollPart {
@@ -37,6 +56,9 @@ impl RagdollPart {
             local_rotation: Quat::IDENTITY,
             parent_index: None,
             joint_config: None,
+            bone: None,
+            collider_position: Vec3::ZERO,
+            collider_rotation: Quat::IDENTITY,
         }
     }
 
@@ -63,6 +85,19 @@ impl RagdollPart {
         self.joint_config = Some(joint_config);
         self
     }
+
+    /// Attach the part to a bone entity
+    pub fn with_bone(mut self, bone: EntityId) -> Self {
+        self.bone = Some(bone);
+        self
+    }
+
+    /// Offset the collider within the part
+    pub fn with_collider_offset(mut self, position: Vec3, rotation: Quat) -> Self {
+        self.collider_position = position;
+        self.collider_rotation = rotation;
+        self
+    }
 }
 
 /// Complete ragdoll configuration
@@ -242,6 +277,85 @@ impl RagdollConfig {
     }
 }
 
+impl RagdollConfig {
+    /// Generate parts for `root_bone` and every bone below it, parents
+    /// first. Each bone gets a capsule reaching to its first child (leaf
+    /// bones get a sphere) with a radius of `thickness` times its length,
+    /// a share of `total_mass` by length, and a ball joint to its parent.
+    pub fn from_skeleton(
+        scene: &Scene,
+        root_bone: EntityId,
+        total_mass: f32,
+        thickness: f32,
+    ) -> Self {
+        let mut bones = vec![(root_bone, None)];
+        let mut i = 0;
+        while i < bones.len() {
+            if let Some(entity) = scene.get_entity(bones[i].0) {
+                bones.extend(entity.children.iter().map(|&child| (child, Some(i))));
+            }
+            i += 1;
+        }
+
+        let mut config = Self::new();
+        let mut lengths = Vec::new();
+        for &(bone, parent_index) in &bones {
+            let Some(entity) = scene.get_entity(bone) else {
+                continue;
+            };
+            let (position, rotation) = world_pose(scene, bone);
+            let child = entity
+                .children
+                .first()
+                .map(|&child| world_pose(scene, child).0);
+            let parent_length = parent_index.map_or(0.0, |index| lengths[index]);
+
+            let mut part = match child.map(|child| rotation.inverse() * (child - position)) {
+                Some(to_child) if to_child.length() > 1e-4 => {
+                    let length = to_child.length();
+                    let radius = length * thickness;
+                    lengths.push(length);
+                    RagdollPart::new(
+                        entity.name.clone(),
+                        ColliderShape::Capsule {
+                            half_height: (length * 0.5 - radius).max(0.0),
+                            radius,
+                        },
+                        0.0,
+                    )
+                    .with_collider_offset(
+                        to_child * 0.5,
+                        Quat::from_rotation_arc(Vec3::Y, to_child / length),
+                    )
+                }
+                _ => {
+                    let radius = (parent_length * thickness).max(0.05);
+                    lengths.push(radius * 2.0);
+                    RagdollPart::new(entity.name.clone(), ColliderShape::Sphere { radius }, 0.0)
+                }
+            }
+            .with_bone(bone)
+            .with_position(entity.transform.position)
+            .with_rotation(entity.transform.rotation);
+
+            if let Some(index) = parent_index {
+                let (parent_position, parent_rotation) = world_pose(scene, bones[index].0);
+                let anchor = parent_rotation.inverse() * (position - parent_position);
+                part = part
+                    .with_parent(index)
+                    .with_joint(JointConfig::spherical(anchor, Vec3::ZERO));
+            }
+            config.parts.push(part);
+        }
+
+        let total_length: f32 = lengths.iter().sum();
+        for (part, length) in config.parts.iter_mut().zip(&lengths) {
+            part.mass = total_mass * length / total_length.max(1e-4);
+        }
+        config
+    }
+}
+
 impl Default for RagdollConfig {
     fn default() -> Self {
         Self::new()
@@ -356,6 +470,146 @@ impl Ragdoll {
     pub fn part_count(&self) -> usize {
         self.part_entities.len()
     }
+
+    /// Create bodies, colliders and joints for the parts attached to bones,
+    /// posed like the bones are now. Parts start kinematic unless the
+    /// config starts active. Jointed parts don't collide with each other.
+    pub fn spawn(config: &RagdollConfig, scene: &Scene, physics: &mut PhysicsWorld) -> Self {
+        let mut ragdoll = Self::new();
+        let bit = CollisionGroups::from_layer(layers::RAGDOLL).memberships;
+        let groups = CollisionGroups::new(bit, !bit).to_rapier();
+
+        for part in &config.parts {
+            let Some(bone) = part.bone else {
+                continue;
+            };
+            let collider = Collider {
+                shape: part.shape.clone(),
+                ..Collider::sphere(0.0)
+            }
+            .to_rapier(&ColliderGeometry::default(), Vec3::ONE);
+            // Parts are primitives; mesh and terrain shapes have nothing to build from
+            let Some(collider) = collider else {
+                continue;
+            };
+            let (position, rotation) = world_pose(scene, bone);
+            let builder = if config.start_active {
+                RigidBodyBuilder::dynamic()
+            } else {
+                RigidBodyBuilder::kinematic_position_based()
+            };
+            let body = builder
+                .position(Isometry::from_parts(
+                    to_rapier_vec(position).into(),
+                    to_rapier_quat(rotation),
+                ))
+                .linear_damping(config.linear_damping)
+                .angular_damping(config.angular_damping)
+                .build();
+            let body_handle = physics.create_rigid_body(bone, body);
+
+            let collider = collider
+                .position(Isometry::from_parts(
+                    to_rapier_vec(part.collider_position).into(),
+                    to_rapier_quat(part.collider_rotation),
+                ))
+                .mass(part.mass)
+                .collision_groups(groups)
+                .build();
+            physics.create_collider(body_handle, collider);
+
+            ragdoll.part_entities.push(bone);
+            ragdoll.part_bodies.push(body_handle);
+        }
+
+        for part in &config.parts {
+            let parent = part.parent_index.and_then(|index| config.parts[index].bone);
+            if let (Some(parent), Some(bone), Some(joint)) = (parent, part.bone, &part.joint_config)
+            {
+                if let Some(handle) = physics.create_joint(parent, bone, joint.clone()) {
+                    if let Some(joint) = physics.impulse_joint_set.get_mut(handle.0) {
+                        joint.data.set_contacts_enabled(false);
+                    }
+                    ragdoll.joint_handles.push(handle);
+                }
+            }
+        }
+
+        ragdoll.is_active = config.start_active;
+        ragdoll
+    }
+
+    /// Switch one part between simulated and kinematic
+    pub fn set_part_dynamic(
+        &self,
+        rigid_body_set: &mut RigidBodySet,
+        part_index: usize,
+        dynamic: bool,
+    ) {
+        let body_type = if dynamic {
+            rapier3d::prelude::RigidBodyType::Dynamic
+        } else {
+            rapier3d::prelude::RigidBodyType::KinematicPositionBased
+        };
+        if let Some(body) = self
+            .part_bodies
+            .get(part_index)
+            .and_then(|handle| rigid_body_set.get_mut(*handle))
+        {
+            if body.body_type() != body_type {
+                body.set_body_type(body_type, true);
+            }
+        }
+    }
+
+    /// Move kinematic parts to where their bones are in the scene
+    pub fn follow_bones(&self, scene: &Scene, rigid_body_set: &mut RigidBodySet) {
+        for (bone, handle) in self.part_entities.iter().zip(&self.part_bodies) {
+            if let Some(body) = rigid_body_set.get_mut(*handle) {
+                if body.is_kinematic() {
+                    let (position, rotation) = world_pose(scene, *bone);
+                    body.set_next_kinematic_position(Isometry::from_parts(
+                        to_rapier_vec(position).into(),
+                        to_rapier_quat(rotation),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Largest contact impulse any part took in the last physics step
+    pub fn max_contact_impulse(&self, physics: &PhysicsWorld) -> f32 {
+        let mut max = 0.0f32;
+        for handle in &self.part_bodies {
+            let Some(body) = physics.rigid_body_set.get(*handle) else {
+                continue;
+            };
+            for collider in body.colliders() {
+                for pair in physics.narrow_phase.contact_pairs_with(*collider) {
+                    max = max.max(pair.total_impulse_magnitude());
+                }
+            }
+        }
+        max
+    }
+
+    /// Whether the ragdoll's bodies are in this physics world
+    pub fn is_live(&self, physics: &PhysicsWorld) -> bool {
+        self.part_entities
+            .first()
+            .zip(self.part_bodies.first())
+            .is_some_and(|(bone, body)| physics.get_body_handle(*bone) == Some(*body))
+    }
+
+    /// Remove the ragdoll's bodies, colliders and joints
+    pub fn despawn(&self, physics: &mut PhysicsWorld) {
+        for joint in &self.joint_handles {
+            physics.remove_joint(*joint);
+        }
+        for bone in &self.part_entities {
+            physics.remove_rigid_body(*bone);
+        }
+    }
 }
 
 impl Default for Ragdoll {
@@ -364,9 +618,314 @@ impl Default for Ragdoll {
     }
 }
 
+/// Blend from the ragdoll pose back to animation
+struct GetUp {
+    elapsed: f32,
+    /// Bone local poses when getting up started
+    from: Vec<(Vec3, Quat)>,
+    /// Bone local poses the blend last wrote
+    written: Vec<(Vec3, Quat)>,
+}
+
+/// Runtime ragdoll for one RagdollRig
+struct RigInstance {
+    ragdoll: Ragdoll,
+    /// Parts at or below the rig's upper body bone
+    upper_body: Vec<bool>,
+    /// Bone local poses from before the ragdoll took over, for bones the
+    /// animation doesn't move
+    rest: Vec<(Vec3, Quat)>,
+    get_up: Option<GetUp>,
+}
+
+/// Builds ragdolls for RagdollRig components and moves them between
+/// animation and simulation. Run it before the physics step; the step's
+/// scene sync writes simulated bones back.
+#[derive(Default)]
+pub struct RagdollSystem {
+    rigs: HashMap<EntityId, RigInstance>,
+}
+
+impl RagdollSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, scene: &mut Scene, physics: &mut PhysicsWorld, delta_time: f32) {
+        let mut entities: Vec<EntityId> = scene
+            .entities()
+            .filter(|e| e.has_component::<RagdollRig>())
+            .map(|e| e.id)
+            .collect();
+        entities.sort_by_key(|id| id.0);
+
+        self.rigs.retain(|id, instance| {
+            let keep = entities.contains(id);
+            if !keep && instance.ragdoll.is_live(physics) {
+                instance.ragdoll.despawn(physics);
+            }
+            keep
+        });
+
+        for id in entities {
+            let Some(mut rig) = scene
+                .get_entity(id)
+                .and_then(|e| e.get_component::<RagdollRig>())
+                .cloned()
+            else {
+                continue;
+            };
+            // Rebuild after the physics world was reset or the root bone changed
+            if let Entry::Occupied(entry) = self.rigs.entry(id) {
+                let live = entry.get().ragdoll.is_live(physics);
+                if !live || entry.get().ragdoll.part_entities.first() != rig.root_bone.as_ref() {
+                    let instance = entry.remove();
+                    if live {
+                        instance.ragdoll.despawn(physics);
+                    }
+                }
+            }
+            let instance = match self.rigs.entry(id) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let Some(root_bone) = rig
+                        .root_bone
+                        .filter(|bone| scene.get_entity(*bone).is_some())
+                    else {
+                        continue;
+                    };
+                    rig.mode = RagdollMode::Animated;
+                    entry.insert(spawn_rig(scene, physics, id, root_bone, &rig))
+                }
+            };
+
+            if rig.mode == RagdollMode::Animated
+                && rig.requested.is_none()
+                && rig.impact_threshold > 0.0
+                && instance.ragdoll.max_contact_impulse(physics) > rig.impact_threshold
+            {
+                rig.requested = Some(RagdollMode::Full);
+            }
+
+            if let Some(mode) = rig.requested.take() {
+                change_mode(scene, physics, id, &mut rig, instance, mode);
+            }
+            if let Some(get_up) = &mut instance.get_up {
+                get_up.elapsed += delta_time;
+                let t = (get_up.elapsed / rig.blend_time.max(1e-4)).min(1.0);
+                blend_to_animation(
+                    scene,
+                    &instance.ragdoll,
+                    &instance.rest,
+                    get_up,
+                    t * t * (3.0 - 2.0 * t),
+                );
+                if t >= 1.0 {
+                    instance.get_up = None;
+                    rig.mode = RagdollMode::Animated;
+                }
+            }
+            instance
+                .ragdoll
+                .follow_bones(scene, &mut physics.rigid_body_set);
+
+            if let Some(component) = scene
+                .get_entity_mut(id)
+                .and_then(|e| e.get_component_mut::<RagdollRig>())
+            {
+                *component = rig;
+            }
+        }
+    }
+}
+
+fn spawn_rig(
+    scene: &Scene,
+    physics: &mut PhysicsWorld,
+    id: EntityId,
+    root_bone: EntityId,
+    rig: &RagdollRig,
+) -> RigInstance {
+    let config = RagdollConfig::from_skeleton(scene, root_bone, rig.total_mass, rig.thickness);
+    let ragdoll = Ragdoll::spawn(&config, scene, physics);
+    let upper_body = ragdoll
+        .part_entities
+        .iter()
+        .map(|&bone| {
+            rig.upper_body_bone
+                .is_some_and(|upper| is_descendant(scene, bone, upper))
+        })
+        .collect();
+
+    // The character's own collider shouldn't push its bones around
+    let bit = Group::from_bits_truncate(CollisionGroups::from_layer(layers::RAGDOLL).memberships);
+    if let Some(body) = physics
+        .get_body_handle(id)
+        .and_then(|handle| physics.rigid_body_set.get(handle))
+    {
+        for handle in body.colliders().to_vec() {
+            if let Some(collider) = physics.collider_set.get_mut(handle) {
+                let groups = collider.collision_groups();
+                collider.set_collision_groups(InteractionGroups::new(
+                    groups.memberships,
+                    groups.filter.difference(bit),
+                ));
+            }
+        }
+    }
+
+    RigInstance {
+        rest: local_poses(scene, &ragdoll),
+        ragdoll,
+        upper_body,
+        get_up: None,
+    }
+}
+
+fn change_mode(
+    scene: &mut Scene,
+    physics: &mut PhysicsWorld,
+    id: EntityId,
+    rig: &mut RagdollRig,
+    instance: &mut RigInstance,
+    mode: RagdollMode,
+) {
+    let ragdoll = &mut instance.ragdoll;
+    match mode {
+        RagdollMode::Full | RagdollMode::UpperBody => {
+            if rig.mode == RagdollMode::Animated {
+                instance.rest = local_poses(scene, ragdoll);
+            }
+            for index in 0..ragdoll.part_count() {
+                let dynamic = mode == RagdollMode::Full || instance.upper_body[index];
+                ragdoll.set_part_dynamic(&mut physics.rigid_body_set, index, dynamic);
+            }
+            ragdoll.is_active = mode == RagdollMode::Full;
+            instance.get_up = None;
+        }
+        RagdollMode::GettingUp => {
+            if !rig.is_ragdoll() {
+                return;
+            }
+            // Bring the character to where its hips landed, keeping the bones in place
+            let poses: Vec<_> = ragdoll
+                .part_entities
+                .iter()
+                .map(|&bone| world_pose(scene, bone))
+                .collect();
+            if let (Some(&(hips, _)), Some(entity)) = (poses.first(), scene.get_entity_mut(id)) {
+                if entity.parent.is_none() {
+                    entity.transform.position.x = hips.x;
+                    entity.transform.position.z = hips.z;
+                    let position = entity.transform.position;
+                    if let Some(body) = physics
+                        .get_body_handle(id)
+                        .and_then(|handle| physics.rigid_body_set.get_mut(handle))
+                    {
+                        body.set_translation(to_rapier_vec(position), true);
+                    }
+                }
+            }
+            for (&bone, &(position, rotation)) in ragdoll.part_entities.iter().zip(&poses) {
+                set_world_pose(scene, bone, position, rotation);
+            }
+            for index in 0..ragdoll.part_count() {
+                ragdoll.set_part_dynamic(&mut physics.rigid_body_set, index, false);
+            }
+            ragdoll.is_active = false;
+            let from = local_poses(scene, ragdoll);
+            instance.get_up = Some(GetUp {
+                elapsed: 0.0,
+                written: from.clone(),
+                from,
+            });
+        }
+        RagdollMode::Animated => {
+            for index in 0..ragdoll.part_count() {
+                ragdoll.set_part_dynamic(&mut physics.rigid_body_set, index, false);
+            }
+            ragdoll.is_active = false;
+            if rig.mode != RagdollMode::Animated {
+                for (&bone, &(position, rotation)) in
+                    ragdoll.part_entities.iter().zip(&instance.rest)
+                {
+                    if let Some(entity) = scene.get_entity_mut(bone) {
+                        entity.transform.position = position;
+                        entity.transform.rotation = rotation;
+                    }
+                }
+            }
+            instance.get_up = None;
+        }
+    }
+    rig.mode = mode;
+}
+
+/// Blend each bone's local pose from where getting up started to the
+/// animated pose. A bone still holding the blend's last write wasn't
+/// animated since, so it heads for its rest pose instead.
+fn blend_to_animation(
+    scene: &mut Scene,
+    ragdoll: &Ragdoll,
+    rest: &[(Vec3, Quat)],
+    get_up: &mut GetUp,
+    weight: f32,
+) {
+    for (index, &bone) in ragdoll.part_entities.iter().enumerate() {
+        let Some(entity) = scene.get_entity_mut(bone) else {
+            continue;
+        };
+        let current = (entity.transform.position, entity.transform.rotation);
+        let target = if current == get_up.written[index] {
+            rest[index]
+        } else {
+            current
+        };
+        let (from_position, from_rotation) = get_up.from[index];
+        let blended = (
+            from_position.lerp(target.0, weight),
+            from_rotation.slerp(target.1, weight),
+        );
+        entity.transform.position = blended.0;
+        entity.transform.rotation = blended.1;
+        get_up.written[index] = blended;
+    }
+}
+
+fn local_poses(scene: &Scene, ragdoll: &Ragdoll) -> Vec<(Vec3, Quat)> {
+    ragdoll
+        .part_entities
+        .iter()
+        .map(|&bone| {
+            scene
+                .get_entity(bone)
+                .map_or((Vec3::ZERO, Quat::IDENTITY), |e| {
+                    (e.transform.position, e.transform.rotation)
+                })
+        })
+        .collect()
+}
+
+fn world_pose(scene: &Scene, id: EntityId) -> (Vec3, Quat) {
+    let (_, rotation, position) = scene.world_matrix(id).to_scale_rotation_translation();
+    (position, rotation)
+}
+
+fn is_descendant(scene: &Scene, id: EntityId, ancestor: EntityId) -> bool {
+    let mut current = Some(id);
+    while let Some(id) = current {
+        if id == ancestor {
+            return true;
+        }
+        current = scene.get_entity(id).and_then(|e| e.parent);
+    }
+    false
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sync::PhysicsSync;
 
     #[test]
     fn test_ragdoll_part() {
@@ -419,4 +978,104 @@ mod tests {
         assert_eq!(ragdoll.part_count(), 0);
         assert!(!ragdoll.is_active);
     }
+
+    /// Character root with hips -> spine -> head bones
+    fn skeleton() -> (Scene, EntityId, [EntityId; 3]) {
+        use engine_scene::transform::Transform;
+
+        let mut scene = Scene::new("Test".to_string());
+        let character = scene.create_entity("Character".to_string());
+        let mut parent = character;
+        let mut bones = [character; 3];
+        for (i, (name, y)) in [("Hips", 1.0), ("Spine", 0.5), ("Head", 0.4)]
+            .into_iter()
+            .enumerate()
+        {
+            bones[i] = scene.create_entity_with_transform(
+                name.to_string(),
+                Transform::from_position(Vec3::new(0.0, y, 0.0)),
+            );
+            scene.set_parent(bones[i], Some(parent));
+            parent = bones[i];
+        }
+        (scene, character, bones)
+    }
+
+    #[test]
+    fn test_ragdoll_from_skeleton() {
+        let (scene, _, bones) = skeleton();
+        let config = RagdollConfig::from_skeleton(&scene, bones[0], 60.0, 0.25);
+
+        assert_eq!(config.parts.len(), 3);
+        assert!(matches!(
+            config.parts[0].shape,
+            ColliderShape::Capsule { .. }
+        ));
+        assert!(matches!(
+            config.parts[2].shape,
+            ColliderShape::Sphere { .. }
+        ));
+        assert!((config.parts[0].collider_position - Vec3::new(0.0, 0.25, 0.0)).length() < 1e-5);
+        assert_eq!(config.parts[2].parent_index, Some(1));
+        let joint = config.parts[1].joint_config.as_ref().unwrap();
+        assert!((joint.anchor1 - Vec3::new(0.0, 0.5, 0.0)).length() < 1e-5);
+        let mass: f32 = config.parts.iter().map(|part| part.mass).sum();
+        assert!((mass - 60.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_rig_ragdolls_and_gets_up() {
+        let (mut scene, character, bones) = skeleton();
+        scene
+            .get_entity_mut(character)
+            .unwrap()
+            .add_component(RagdollRig::new(bones[0]).with_upper_body(bones[1]));
+        let mut physics = PhysicsWorld::new(Vec3::new(0.0, -9.81, 0.0));
+        let mut system = RagdollSystem::new();
+        let dt = 1.0 / 60.0;
+        let is_dynamic = |physics: &PhysicsWorld, bone| {
+            physics.rigid_body_set[physics.get_body_handle(bone).unwrap()].is_dynamic()
+        };
+        let rig = |scene: &Scene| -> RagdollRig {
+            scene
+                .get_entity(character)
+                .unwrap()
+                .get_component::<RagdollRig>()
+                .unwrap()
+                .clone()
+        };
+        let set_rig = |scene: &mut Scene, f: fn(&mut RagdollRig)| {
+            f(scene
+                .get_entity_mut(character)
+                .unwrap()
+                .get_component_mut::<RagdollRig>()
+                .unwrap());
+        };
+
+        system.update(&mut scene, &mut physics, dt);
+        assert!(bones.iter().all(|&bone| !is_dynamic(&physics, bone)));
+
+        set_rig(&mut scene, RagdollRig::activate_upper_body);
+        system.update(&mut scene, &mut physics, dt);
+        assert!(!is_dynamic(&physics, bones[0]));
+        assert!(is_dynamic(&physics, bones[1]) && is_dynamic(&physics, bones[2]));
+
+        set_rig(&mut scene, RagdollRig::activate);
+        for _ in 0..30 {
+            system.update(&mut scene, &mut physics, dt);
+            physics.step(dt);
+            PhysicsSync::sync_to_scene(&physics, &mut scene).unwrap();
+        }
+        assert_eq!(rig(&scene).mode, RagdollMode::Full);
+        assert!(world_pose(&scene, bones[0]).0.y < 0.9);
+
+        set_rig(&mut scene, RagdollRig::get_up);
+        system.update(&mut scene, &mut physics, dt);
+        assert_eq!(rig(&scene).mode, RagdollMode::GettingUp);
+        system.update(&mut scene, &mut physics, 1.0);
+        assert_eq!(rig(&scene).mode, RagdollMode::Animated);
+        assert!(bones.iter().all(|&bone| !is_dynamic(&physics, bone)));
+        let hips = scene.get_entity(bones[0]).unwrap();
+        assert!((hips.transform.position - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-4);
+    }
 }
@@ -5,7 +5,7 @@ use rapier3d::prelude::*;
 use engine_scene::entity::EntityId;
 use crate::layers::CollisionGroups;
 
-/// Result of a raycast query
+/// Result of a raycast or shape cast query
 #[derive(Debug, Clone)]
 pub struct RaycastHit {
     /// The entity that was hit
@@ -149,6 +149,19 @@ impl super::PhysicsWorld {
         hits
     }
 
+    /// First hit straight down from `origin` within `max_distance`, skipping
+    /// the `ignore`d entities (a character's own colliders, for foot IK)
+    pub fn ground_below(
+        &self,
+        origin: Vec3,
+        max_distance: f32,
+        ignore: &[EntityId],
+    ) -> Option<RaycastHit> {
+        self.raycast_all(&RaycastQuery::new(origin, Vec3::NEG_Y, max_distance))
+            .into_iter()
+            .find(|hit| !ignore.contains(&hit.entity_id))
+    }
+
     /// Check if a ray intersects anything (no detailed hit info)
     pub fn raycast_any(&self, query: &RaycastQuery) -> bool {
         let ray = Ray::new(
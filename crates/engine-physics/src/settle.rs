@@ -0,0 +1,273 @@
+// Drop simulation - settle objects onto static geometry
+//
+// A throwaway physics world used by editor tools: static terrain and obstacle
+// hulls, plus the dynamic bodies being dropped. The world is stepped until
+// every body has fallen asleep (or time runs out), and the resting poses are
+// handed back to be baked into the scene.
+
+use crate::components::{heightfield_builder, Collider, ColliderGeometry};
+use crate::world::{from_rapier_quat, from_rapier_vec, to_rapier_quat, to_rapier_vec, PhysicsWorld};
+use engine_scene::entity::EntityId;
+use glam::{Quat, Vec3};
+use rapier3d::prelude::*;
+
+/// Fixed timestep of the drop simulation
+const DROP_TIMESTEP: f32 = 1.0 / 60.0;
+
+/// Final pose of a dropped body
+#[derive(Debug, Clone, Copy)]
+pub struct SettledBody {
+    pub entity_id: EntityId,
+    pub position: Vec3,
+    pub rotation: Quat,
+    /// False if the body was still moving when the time limit was reached
+    pub settled: bool,
+}
+
+/// Physics world for dropping a few bodies onto static geometry
+pub struct DropSimulation {
+    world: PhysicsWorld,
+    bodies: Vec<(EntityId, RigidBodyHandle)>,
+}
+
+impl DropSimulation {
+    pub fn new() -> Self {
+        Self {
+            world: PhysicsWorld::default(),
+            bodies: Vec::new(),
+        }
+    }
+
+    /// Add a static heightfield. `heights` is row-major with `depth` rows of
+    /// `width` samples; sample (0, 0) sits at `origin` and samples are
+    /// `cell_size` apart along X and Z. `holes` (empty for none) is indexed
+    /// like `heights` and removes the cell each sample is the lowest corner of.
+    pub fn add_terrain(
+        &mut self,
+        heights: &[f32],
+        holes: &[bool],
+        width: usize,
+        depth: usize,
+        origin: Vec3,
+        cell_size: f32,
+    ) {
+        if width < 2 || depth < 2 || heights.len() < width * depth {
+            return;
+        }
+        let collider = heightfield_builder(heights, holes, width, depth, origin, cell_size).build();
+        self.world.collider_set.insert(collider);
+    }
+
+    /// Add a static obstacle shaped like the convex hull of world-space points.
+    /// Returns false if the points don't span a volume.
+    pub fn add_static_hull(&mut self, points: &[Vec3]) -> bool {
+        let Some(builder) = hull_builder(points) else {
+            return false;
+        };
+        self.world.collider_set.insert(builder.build());
+        true
+    }
+
+    /// Add a dynamic body using the entity's own collider, with mesh shapes
+    /// built from `geometry` at `scale`. Returns false if `geometry` doesn't
+    /// have what the collider references.
+    pub fn add_body(
+        &mut self,
+        entity_id: EntityId,
+        position: Vec3,
+        rotation: Quat,
+        collider: &Collider,
+        geometry: &ColliderGeometry,
+        scale: Vec3,
+    ) -> bool {
+        let Some(builder) = collider.to_rapier(geometry, scale) else {
+            return false;
+        };
+        self.insert_body(entity_id, position, rotation, builder.sensor(false));
+        true
+    }
+
+    /// Add a dynamic body shaped like the convex hull of `points` (in the body's
+    /// space, relative to `position` and `rotation`). Returns false if the points
+    /// don't span a volume.
+    pub fn add_hull_body(
+        &mut self,
+        entity_id: EntityId,
+        position: Vec3,
+        rotation: Quat,
+        points: &[Vec3],
+    ) -> bool {
+        let Some(builder) = hull_builder(points) else {
+            return false;
+        };
+        self.insert_body(entity_id, position, rotation, builder);
+        true
+    }
+
+    fn insert_body(
+        &mut self,
+        entity_id: EntityId,
+        position: Vec3,
+        rotation: Quat,
+        collider: ColliderBuilder,
+    ) {
+        let body = RigidBodyBuilder::dynamic()
+            .position(Isometry::from_parts(
+                to_rapier_vec(position).into(),
+                to_rapier_quat(rotation),
+            ))
+            .ccd_enabled(true)
+            .build();
+        let handle = self.world.create_rigid_body(entity_id, body);
+        self.world.create_collider(handle, collider.build());
+        self.bodies.push((entity_id, handle));
+    }
+
+    /// Number of dynamic bodies being dropped
+    pub fn body_count(&self) -> usize {
+        self.bodies.len()
+    }
+
+    /// Step until every body is asleep or `max_time` seconds have been simulated
+    pub fn run(mut self, max_time: f32) -> Vec<SettledBody> {
+        let steps = (max_time / DROP_TIMESTEP).ceil() as usize;
+        for _ in 0..steps {
+            self.world.step(DROP_TIMESTEP);
+            if self
+                .bodies
+                .iter()
+                .all(|&(_, handle)| self.is_sleeping(handle))
+            {
+                break;
+            }
+        }
+
+        self.bodies
+            .iter()
+            .filter_map(|&(entity_id, handle)| {
+                let body = self.world.get_rigid_body(handle)?;
+                Some(SettledBody {
+                    entity_id,
+                    position: from_rapier_vec(*body.translation()),
+                    rotation: from_rapier_quat(*body.rotation()),
+                    settled: body.is_sleeping(),
+                })
+            })
+            .collect()
+    }
+
+    fn is_sleeping(&self, handle: RigidBodyHandle) -> bool {
+        match self.world.get_rigid_body(handle) {
+            Some(body) => body.is_sleeping(),
+            None => true,
+        }
+    }
+}
+
+impl Default for DropSimulation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn hull_builder(points: &[Vec3]) -> Option<ColliderBuilder> {
+    if points.len() < 4 {
+        return None;
+    }
+    let points: Vec<Point<Real>> = points.iter().map(|p| point![p.x, p.y, p.z]).collect();
+    ColliderBuilder::convex_hull(&points).map(|builder| builder.friction(0.7))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_cube() -> Vec<Vec3> {
+        let mut corners = Vec::new();
+        for x in [-0.5, 0.5] {
+            for y in [-0.5, 0.5] {
+                for z in [-0.5, 0.5] {
+                    corners.push(Vec3::new(x, y, z));
+                }
+            }
+        }
+        corners
+    }
+
+    #[test]
+    fn test_box_comes_to_rest_on_terrain() {
+        let mut sim = DropSimulation::new();
+        sim.add_terrain(
+            &[1.0; 16],
+            &[],
+            4,
+            4,
+            Vec3::new(-5.0, 0.0, -5.0),
+            10.0 / 3.0,
+        );
+        assert!(sim.add_hull_body(
+            EntityId(1),
+            Vec3::new(0.0, 4.0, 0.0),
+            Quat::IDENTITY,
+            &unit_cube()
+        ));
+
+        let settled = sim.run(10.0);
+        assert_eq!(settled.len(), 1);
+        assert!(settled[0].settled);
+        assert!(
+            (settled[0].position.y - 1.5).abs() < 0.05,
+            "{}",
+            settled[0].position.y
+        );
+    }
+
+    #[test]
+    fn test_box_falls_through_a_terrain_hole() {
+        let mut sim = DropSimulation::new();
+        // Cut out the middle cell of a 3x3 cell terrain
+        let mut holes = [false; 16];
+        holes[5] = true;
+        sim.add_terrain(
+            &[1.0; 16],
+            &holes,
+            4,
+            4,
+            Vec3::new(-5.0, 0.0, -5.0),
+            10.0 / 3.0,
+        );
+        assert!(sim.add_hull_body(
+            EntityId(1),
+            Vec3::new(0.0, 4.0, 0.0),
+            Quat::IDENTITY,
+            &unit_cube()
+        ));
+
+        let settled = sim.run(2.0);
+        assert!(settled[0].position.y < 0.0, "{}", settled[0].position.y);
+    }
+
+    #[test]
+    fn test_box_stacks_on_static_hull() {
+        let mut sim = DropSimulation::new();
+        let table: Vec<Vec3> = unit_cube().iter().map(|p| *p * 2.0).collect();
+        assert!(sim.add_static_hull(&table));
+        assert!(sim.add_body(
+            EntityId(1),
+            Vec3::new(0.0, 3.0, 0.0),
+            Quat::IDENTITY,
+            &Collider::box_collider(Vec3::splat(0.5)),
+            &ColliderGeometry::default(),
+            Vec3::ONE,
+        ));
+        // Degenerate hulls are rejected
+        assert!(!sim.add_static_hull(&[Vec3::ZERO, Vec3::X]));
+
+        let settled = sim.run(10.0);
+        assert!(
+            (settled[0].position.y - 1.5).abs() < 0.05,
+            "{}",
+            settled[0].position.y
+        );
+    }
+}
@@ -0,0 +1,358 @@
+// Shape casts and overlap queries
+//
+// A shape cast sweeps a sphere, box or capsule along a direction and reports
+// the first collider it touches, like a ray with thickness (ground probes,
+// checking a character fits through a gap). An overlap query returns every
+// entity whose colliders touch a shape placed in the world (melee hits,
+// explosions, trigger checks).
+
+use crate::layers::CollisionGroups;
+use crate::raycast::RaycastHit;
+use crate::world::{from_rapier_vec, to_rapier_quat, to_rapier_vec};
+use engine_scene::entity::EntityId;
+use glam::{Quat, Vec3};
+use rapier3d::parry::query::ShapeCastOptions;
+use rapier3d::prelude::*;
+
+/// Shape swept or placed by a query
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueryShape {
+    Sphere {
+        radius: f32,
+    },
+    Box {
+        half_extents: Vec3,
+    },
+    /// Capsule along its local Y axis
+    Capsule {
+        half_height: f32,
+        radius: f32,
+    },
+}
+
+impl QueryShape {
+    fn to_rapier(self) -> SharedShape {
+        match self {
+            QueryShape::Sphere { radius } => SharedShape::ball(radius),
+            QueryShape::Box { half_extents } => {
+                SharedShape::cuboid(half_extents.x, half_extents.y, half_extents.z)
+            }
+            QueryShape::Capsule {
+                half_height,
+                radius,
+            } => SharedShape::capsule_y(half_height, radius),
+        }
+    }
+}
+
+/// Shape cast query interface
+pub struct ShapeCastQuery {
+    pub shape: QueryShape,
+    /// Where the shape starts
+    pub origin: Vec3,
+    pub rotation: Quat,
+    /// Direction to sweep in (should be normalized)
+    pub direction: Vec3,
+    /// Maximum distance to sweep
+    pub max_distance: f32,
+    /// Whether to hit triggers/sensors
+    pub hit_triggers: bool,
+    /// Collision layer filtering (None = hit all layers)
+    pub collision_groups: Option<CollisionGroups>,
+    /// Entities swept through (e.g. the character doing the sweep)
+    pub ignore: Vec<EntityId>,
+}
+
+impl ShapeCastQuery {
+    pub fn new(shape: QueryShape, origin: Vec3, direction: Vec3, max_distance: f32) -> Self {
+        Self {
+            shape,
+            origin,
+            rotation: Quat::IDENTITY,
+            direction: direction.normalize(),
+            max_distance,
+            hit_triggers: false,
+            collision_groups: None,
+            ignore: Vec::new(),
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: Quat) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_triggers(mut self, hit_triggers: bool) -> Self {
+        self.hit_triggers = hit_triggers;
+        self
+    }
+
+    pub fn with_collision_groups(mut self, groups: CollisionGroups) -> Self {
+        self.collision_groups = Some(groups);
+        self
+    }
+
+    pub fn with_layers(mut self, layers: &[u32]) -> Self {
+        self.collision_groups = Some(CollisionGroups::all().with_filter(layers));
+        self
+    }
+
+    pub fn with_ignored(mut self, entities: &[EntityId]) -> Self {
+        self.ignore = entities.to_vec();
+        self
+    }
+}
+
+/// Overlap query interface
+pub struct OverlapQuery {
+    pub shape: QueryShape,
+    pub position: Vec3,
+    pub rotation: Quat,
+    /// Whether to include triggers/sensors
+    pub hit_triggers: bool,
+    /// Collision layer filtering (None = all layers)
+    pub collision_groups: Option<CollisionGroups>,
+}
+
+impl OverlapQuery {
+    pub fn new(shape: QueryShape, position: Vec3) -> Self {
+        Self {
+            shape,
+            position,
+            rotation: Quat::IDENTITY,
+            hit_triggers: false,
+            collision_groups: None,
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: Quat) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_triggers(mut self, hit_triggers: bool) -> Self {
+        self.hit_triggers = hit_triggers;
+        self
+    }
+
+    pub fn with_collision_groups(mut self, groups: CollisionGroups) -> Self {
+        self.collision_groups = Some(groups);
+        self
+    }
+
+    pub fn with_layers(mut self, layers: &[u32]) -> Self {
+        self.collision_groups = Some(CollisionGroups::all().with_filter(layers));
+        self
+    }
+}
+
+fn query_filter(
+    hit_triggers: bool,
+    collision_groups: Option<CollisionGroups>,
+) -> QueryFilter<'static> {
+    let mut filter = QueryFilter::default();
+    if !hit_triggers {
+        filter = filter.exclude_sensors();
+    }
+    if let Some(groups) = collision_groups {
+        filter = filter.groups(groups.to_rapier());
+    }
+    filter
+}
+
+impl super::PhysicsWorld {
+    /// Sweep a shape and return the first hit. A shape that starts out
+    /// touching something hits it at distance 0. Colliders without an entity,
+    /// and those of the ignored entities, are passed through.
+    pub fn shape_cast(&self, query: &ShapeCastQuery) -> Option<RaycastHit> {
+        let shape = query.shape.to_rapier();
+        let position = Isometry::from_parts(
+            to_rapier_vec(query.origin).into(),
+            to_rapier_quat(query.rotation),
+        );
+        let options = ShapeCastOptions {
+            stop_at_penetration: true,
+            compute_impact_geometry_on_penetration: true,
+            ..ShapeCastOptions::with_max_time_of_impact(query.max_distance)
+        };
+
+        let has_entity = |handle, _: &Collider| {
+            self.collider_entity(handle)
+                .is_some_and(|entity| !query.ignore.contains(&entity))
+        };
+        let (collider_handle, hit) = self.query_pipeline.cast_shape(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &position,
+            &to_rapier_vec(query.direction),
+            &*shape,
+            options,
+            query_filter(query.hit_triggers, query.collision_groups).predicate(&has_entity),
+        )?;
+
+        Some(RaycastHit {
+            entity_id: self.collider_entity(collider_handle)?,
+            point: Vec3::new(hit.witness1.x, hit.witness1.y, hit.witness1.z),
+            normal: from_rapier_vec(*hit.normal1),
+            distance: hit.time_of_impact,
+        })
+    }
+
+    /// Entities with a collider touching the shape, each listed once, in
+    /// entity order
+    pub fn overlap(&self, query: &OverlapQuery) -> Vec<EntityId> {
+        let shape = query.shape.to_rapier();
+        let position = Isometry::from_parts(
+            to_rapier_vec(query.position).into(),
+            to_rapier_quat(query.rotation),
+        );
+
+        let mut entities = Vec::new();
+        self.query_pipeline.intersections_with_shape(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &position,
+            &*shape,
+            query_filter(query.hit_triggers, query.collision_groups),
+            |collider_handle| {
+                entities.extend(self.collider_entity(collider_handle));
+                true // Continue checking
+            },
+        );
+
+        entities.sort_by_key(|entity| entity.0);
+        entities.dedup();
+        entities
+    }
+
+    /// Entity owning a collider, from its parent rigid body
+    fn collider_entity(&self, handle: ColliderHandle) -> Option<EntityId> {
+        self.collider_set
+            .get(handle)
+            .and_then(|collider| collider.parent())
+            .and_then(|body_handle| self.get_entity_id(body_handle))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::layers;
+    use crate::world::PhysicsWorld;
+
+    /// A 10x1x10 floor (world layer) with its top at y = 0, a unit crate (enemy
+    /// layer) resting on it at x = 3, a trigger sphere at x = -3 and a box
+    /// without an entity at x = -1
+    fn world() -> PhysicsWorld {
+        let mut world = PhysicsWorld::new(Vec3::new(0.0, -9.81, 0.0));
+        let floor = world.create_rigid_body(
+            EntityId(1),
+            RigidBodyBuilder::fixed()
+                .translation(vector![0.0, -0.5, 0.0])
+                .build(),
+        );
+        world.create_collider(
+            floor,
+            ColliderBuilder::cuboid(5.0, 0.5, 5.0)
+                .collision_groups(CollisionGroups::from_layer(layers::WORLD).to_rapier())
+                .build(),
+        );
+        let crate_body = world.create_rigid_body(
+            EntityId(2),
+            RigidBodyBuilder::fixed()
+                .translation(vector![3.0, 0.5, 0.0])
+                .build(),
+        );
+        world.create_collider(
+            crate_body,
+            ColliderBuilder::cuboid(0.5, 0.5, 0.5)
+                .collision_groups(CollisionGroups::from_layer(layers::ENEMY).to_rapier())
+                .build(),
+        );
+        let trigger = world.create_rigid_body(
+            EntityId(3),
+            RigidBodyBuilder::fixed()
+                .translation(vector![-3.0, 0.5, 0.0])
+                .build(),
+        );
+        world.create_collider(trigger, ColliderBuilder::ball(0.5).sensor(true).build());
+        // A collider with no entity, on top of the floor at x = -1
+        world.collider_set.insert(
+            ColliderBuilder::cuboid(0.5, 0.5, 0.5)
+                .translation(vector![-1.0, 0.5, 0.0])
+                .build(),
+        );
+        world.query_pipeline.update(&world.collider_set);
+        world
+    }
+
+    #[test]
+    fn test_shape_casts_stop_at_the_first_surface() {
+        let world = world();
+
+        // A sphere dropped onto the floor stops with its bottom on it
+        let sphere = QueryShape::Sphere { radius: 0.5 };
+        let hit = world
+            .shape_cast(&ShapeCastQuery::new(
+                sphere,
+                Vec3::new(0.0, 5.0, 0.0),
+                Vec3::NEG_Y,
+                10.0,
+            ))
+            .unwrap();
+        assert_eq!(hit.entity_id, EntityId(1));
+        assert!((hit.distance - 4.5).abs() < 1e-3, "{}", hit.distance);
+        assert!((hit.point.y).abs() < 1e-3);
+        assert!(hit.normal.y > 0.99);
+
+        // Swept sideways a capsule hits the crate, unless the crate's layer is filtered out
+        let capsule = QueryShape::Capsule {
+            half_height: 0.3,
+            radius: 0.2,
+        };
+        let sweep = ShapeCastQuery::new(capsule, Vec3::new(0.0, 0.6, 0.0), Vec3::X, 10.0);
+        let hit = world.shape_cast(&sweep).unwrap();
+        assert_eq!(hit.entity_id, EntityId(2));
+        assert!((hit.distance - 2.3).abs() < 1e-3, "{}", hit.distance);
+        let sweep = sweep.with_layers(&[layers::WORLD]);
+        assert!(world.shape_cast(&sweep).is_none());
+        let sweep = ShapeCastQuery::new(capsule, Vec3::new(0.0, 0.6, 0.0), Vec3::X, 10.0)
+            .with_ignored(&[EntityId(2)]);
+        assert!(world.shape_cast(&sweep).is_none());
+
+        // Colliders that belong to no entity are swept through
+        let sweep = ShapeCastQuery::new(capsule, Vec3::new(0.0, 0.6, 0.0), Vec3::NEG_X, 10.0);
+        assert!(world.shape_cast(&sweep).is_none());
+    }
+
+    #[test]
+    fn test_overlaps_list_every_entity_in_the_volume() {
+        let world = world();
+
+        let around_crate = OverlapQuery::new(
+            QueryShape::Box {
+                half_extents: Vec3::splat(1.0),
+            },
+            Vec3::new(3.0, 0.5, 0.0),
+        );
+        assert_eq!(world.overlap(&around_crate), vec![EntityId(1), EntityId(2)]);
+        let enemies = around_crate.with_layers(&[layers::ENEMY]);
+        assert_eq!(world.overlap(&enemies), vec![EntityId(2)]);
+
+        // Triggers only count when asked for
+        let around_trigger = OverlapQuery::new(
+            QueryShape::Sphere { radius: 0.6 },
+            Vec3::new(-3.0, 1.2, 0.0),
+        );
+        assert!(world.overlap(&around_trigger).is_empty());
+        let around_trigger = around_trigger.with_triggers(true);
+        assert_eq!(world.overlap(&around_trigger), vec![EntityId(3)]);
+
+        // Colliders that belong to no entity are left out
+        let around_orphan = OverlapQuery::new(
+            QueryShape::Sphere { radius: 0.6 },
+            Vec3::new(-1.0, 0.5, 0.0),
+        );
+        assert_eq!(world.overlap(&around_orphan), vec![EntityId(1)]);
+    }
+}
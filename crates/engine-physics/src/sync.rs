@@ -1,18 +1,28 @@
 // Physics synchronization - sync physics world to scene transforms
 
-use crate::components::{Collider, ColliderShape, RigidBody, RigidBodyType};
+use crate::components::{Collider, ColliderGeometry, RigidBody, RigidBodyType};
 use crate::world::{from_rapier_quat, from_rapier_vec, to_rapier_quat, to_rapier_vec, PhysicsWorld};
 use anyhow::Result;
+use engine_scene::entity::EntityId;
 use engine_scene::scene::Scene;
+use glam::{Quat, Vec3};
 use rapier3d::prelude::*;
 
 /// Sync system - manages synchronization between physics and scene
 pub struct PhysicsSync;
 
 impl PhysicsSync {
-    /// Initialize physics bodies for entities that have RigidBody and Collider components
-    pub fn initialize_physics(physics_world: &mut PhysicsWorld, scene: &Scene) -> Result<()> {
-        for entity in scene.entities() {
+    /// Initialize physics bodies for entities that have RigidBody and Collider components,
+    /// building mesh and heightfield colliders from `geometry`
+    pub fn initialize_physics(
+        physics_world: &mut PhysicsWorld,
+        scene: &Scene,
+        geometry: &ColliderGeometry,
+    ) -> Result<()> {
+        // Bodies are added in entity order so the solver sees them the same way every run
+        let mut entities: Vec<_> = scene.entities().collect();
+        entities.sort_by_key(|entity| entity.id.0);
+        for entity in entities {
             // Skip if already has physics body
             if physics_world.get_body_handle(entity.id).is_some() {
                 continue;
@@ -23,6 +33,16 @@ impl PhysicsSync {
                 entity.get_component::<RigidBody>(),
                 entity.get_component::<Collider>(),
             ) {
+                let Some(collider_builder) =
+                    col_component.to_rapier(geometry, entity.transform.scale)
+                else {
+                    log::warn!(
+                        "Collider of '{}' has no geometry to build; skipping it",
+                        entity.name
+                    );
+                    continue;
+                };
+
                 // Create Rapier rigid body
                 let position = to_rapier_vec(entity.transform.position);
                 let rotation = to_rapier_quat(entity.transform.rotation);
@@ -47,39 +67,25 @@ impl PhysicsSync {
 
                 let body_handle = physics_world.create_rigid_body(entity.id, rapier_body);
 
-                // Create Rapier collider
-                let rapier_collider = match &col_component.shape {
-                    ColliderShape::Box { half_extents } => {
-                        let he = to_rapier_vec(*half_extents);
-                        ColliderBuilder::cuboid(he.x, he.y, he.z)
-                    }
-                    ColliderShape::Sphere { radius } => ColliderBuilder::ball(*radius),
-                    ColliderShape::Capsule { half_height, radius } => {
-                        ColliderBuilder::capsule_y(*half_height, *radius)
-                    }
-                    ColliderShape::Cylinder { half_height, radius } => {
-                        ColliderBuilder::cylinder(*half_height, *radius)
-                    }
-                }
-                .friction(col_component.friction)
-                .restitution(col_component.restitution)
-                .density(col_component.density)
-                .sensor(col_component.is_sensor)
-                .build();
-
-                physics_world.create_collider(body_handle, rapier_collider);
+                physics_world.create_collider(body_handle, collider_builder.build());
             }
         }
 
         Ok(())
     }
 
-    /// Sync physics world state back to scene transforms (for dynamic bodies)
+    /// Sync physics world state back to scene transforms (for dynamic bodies).
+    /// Parented entities (e.g. ragdoll bones) get the local transform that
+    /// puts them at the body's pose, parents before children.
     pub fn sync_to_scene(physics_world: &PhysicsWorld, scene: &mut Scene) -> Result<()> {
         // Collect entity IDs first to avoid borrow checker issues
-        let entity_ids: Vec<_> = scene.entities().map(|e| e.id).collect();
+        let mut entity_ids: Vec<_> = scene
+            .entities()
+            .map(|e| (depth(scene, e.id), e.id))
+            .collect();
+        entity_ids.sort_by_key(|&(depth, id)| (depth, id.0));
 
-        for entity_id in entity_ids {
+        for (_, entity_id) in entity_ids {
             // Get the physics body handle for this entity
             if let Some(body_handle) = physics_world.get_body_handle(entity_id) {
                 if let Some(rapier_body) = physics_world.get_rigid_body(body_handle) {
@@ -89,10 +95,12 @@ impl PhysicsSync {
                         let rotation = rapier_body.rotation();
 
                         // Update the entity's transform
-                        if let Some(entity_mut) = scene.get_entity_mut(entity_id) {
-                            entity_mut.transform.position = from_rapier_vec(*position);
-                            entity_mut.transform.rotation = from_rapier_quat(*rotation);
-                        }
+                        set_world_pose(
+                            scene,
+                            entity_id,
+                            from_rapier_vec(*position),
+                            from_rapier_quat(*rotation),
+                        );
                     }
                 }
             }
@@ -122,3 +130,33 @@ impl PhysicsSync {
         Ok(())
     }
 }
+
+/// Set an entity's local transform so it ends up at a world pose
+pub(crate) fn set_world_pose(scene: &mut Scene, id: EntityId, position: Vec3, rotation: Quat) {
+    let Some(parent) = scene.get_entity(id).map(|e| e.parent) else {
+        return;
+    };
+    let Some(parent) = parent else {
+        if let Some(entity) = scene.get_entity_mut(id) {
+            entity.transform.position = position;
+            entity.transform.rotation = rotation;
+        }
+        return;
+    };
+    let parent_world = scene.world_matrix(parent);
+    let (_, parent_rotation, _) = parent_world.to_scale_rotation_translation();
+    if let Some(entity) = scene.get_entity_mut(id) {
+        entity.transform.position = parent_world.inverse().transform_point3(position);
+        entity.transform.rotation = (parent_rotation.inverse() * rotation).normalize();
+    }
+}
+
+fn depth(scene: &Scene, id: EntityId) -> usize {
+    let mut depth = 0;
+    let mut parent = scene.get_entity(id).and_then(|e| e.parent);
+    while let Some(id) = parent {
+        depth += 1;
+        parent = scene.get_entity(id).and_then(|e| e.parent);
+    }
+    depth
+}
@@ -29,6 +29,9 @@ pub struct PhysicsWorld {
 
     // Joint management
     joint_manager: JointManager,
+
+    // Fixed colliders that belong to no entity (e.g. streamed terrain tiles), by name
+    static_colliders: HashMap<String, ColliderHandle>,
 }
 
 impl PhysicsWorld {
@@ -49,11 +52,13 @@ impl PhysicsWorld {
             entity_to_body: HashMap::new(),
             body_to_entity: HashMap::new(),
             joint_manager: JointManager::new(),
+            static_colliders: HashMap::new(),
         }
     }
 
     /// Step the physics simulation
     pub fn step(&mut self, delta_time: f32) {
+        let _span = tracing::info_span!("physics_step").entered();
         self.integration_parameters.dt = delta_time;
 
         let gravity = vector![self.gravity.x, self.gravity.y, self.gravity.z];
@@ -128,6 +133,36 @@ impl PhysicsWorld {
         self.collider_set.remove(handle, &mut self.island_manager, &mut self.rigid_body_set, true);
     }
 
+    /// Add a fixed collider that belongs to no entity under `name`,
+    /// replacing the one already there
+    pub fn set_static_collider(&mut self, name: impl Into<String>, collider: Collider) {
+        let name = name.into();
+        self.remove_static_collider(&name);
+        let handle = self.collider_set.insert(collider);
+        self.static_colliders.insert(name, handle);
+    }
+
+    /// Remove the fixed collider added under `name`. Returns false if there was none.
+    pub fn remove_static_collider(&mut self, name: &str) -> bool {
+        match self.static_colliders.remove(name) {
+            Some(handle) => {
+                self.remove_collider(handle);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether there is a fixed collider under `name`
+    pub fn has_static_collider(&self, name: &str) -> bool {
+        self.static_colliders.contains_key(name)
+    }
+
+    /// Names of the fixed colliders
+    pub fn static_collider_names(&self) -> impl Iterator<Item = &str> {
+        self.static_colliders.keys().map(String::as_str)
+    }
+
     /// Create a joint between two entities
     pub fn create_joint(
         &mut self,
@@ -177,11 +212,13 @@ impl PhysicsWorld {
     pub fn joint_count(&self) -> usize {
         self.joint_manager.joint_count()
     }
+
 }
 
+/// A world with the project's gravity (project.ron)
 impl Default for PhysicsWorld {
     fn default() -> Self {
-        Self::new(Vec3::new(0.0, -9.81, 0.0))
+        Self::new(engine_core::project::current().physics.gravity)
     }
 }
 
@@ -204,3 +241,22 @@ pub fn to_rapier_quat(q: Quat) -> UnitQuaternion<f32> {
 pub fn from_rapier_quat(q: UnitQuaternion<f32>) -> Quat {
     Quat::from_xyzw(q.i, q.j, q.k, q.w)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_static_colliders_are_replaced_and_removed_by_name() {
+        let mut world = PhysicsWorld::new(Vec3::ZERO);
+        world.set_static_collider("tile", ColliderBuilder::cuboid(1.0, 1.0, 1.0).build());
+        world.set_static_collider("tile", ColliderBuilder::ball(1.0).build());
+        assert_eq!(world.collider_set.len(), 1);
+        assert!(world.has_static_collider("tile"));
+
+        assert!(world.remove_static_collider("tile"));
+        assert!(!world.remove_static_collider("tile"));
+        assert_eq!(world.collider_set.len(), 0);
+        assert_eq!(world.static_collider_names().count(), 0);
+    }
+}
@@ -0,0 +1,35 @@
+// Engine Plugin - extending the engine without forking the editor
+//
+// A plugin is a type implementing EnginePlugin. Its build() is handed a
+// PluginRegistry and adds what the plugin brings: component types (reachable
+// by name from the MCP tools like the engine's own), systems run every
+// simulation step, render passes drawn over the scene, editor panels and
+// MCP tools. The editor and the headless runtime both get their plugins
+// from a PluginLoader, so a plugin added once works in play mode, headless
+// runs and dedicated servers alike; each takes the parts it can use.
+//
+// Gameplay code that should change without restarting the editor goes in a
+// native module instead (see native.rs): a dylib behind a C ABI, reloaded
+// when it is rebuilt.
+
+pub mod loader;
+pub mod native;
+pub mod registry;
+pub mod render;
+
+pub use loader::{PluginConfig, PluginLoader, PLUGIN_CONFIG_FILE};
+pub use native::{
+    NativeComponentDesc, NativeComponents, NativeHost, NativeModule, NativeModuleDesc,
+    NativeModules, NativeSystemDesc, NativeTransform, NATIVE_ABI_VERSION,
+};
+pub use registry::{EditorPanel, PluginComponent, PluginRegistry, PluginSystem, PluginTool};
+pub use render::{RenderContext, RenderPass};
+
+/// An engine extension
+pub trait EnginePlugin: Send + Sync {
+    /// Unique name, used in logs and to disable the plugin in plugins.json
+    fn name(&self) -> &'static str;
+
+    /// Register what the plugin provides
+    fn build(&self, registry: &mut PluginRegistry);
+}
@@ -0,0 +1,207 @@
+// Plugin loader - builds the registry from the plugins a host is linked with
+//
+// Plugins are compiled in: the host lists them (the editor in its
+// plugins.rs) and the loader builds the ones plugins.json doesn't disable.
+// The file lives in the project directory; without it every plugin loads.
+//
+//   { "disabled": ["debug_overlay"] }
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::registry::PluginRegistry;
+use crate::EnginePlugin;
+
+/// Plugin settings file, relative to the project directory
+pub const PLUGIN_CONFIG_FILE: &str = "plugins.json";
+
+/// Which plugins to load
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginConfig {
+    /// Names of plugins not to load
+    pub disabled: Vec<String>,
+}
+
+impl PluginConfig {
+    /// Read the config, or the default (everything enabled) if there is no file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("Invalid {}", path.display()))
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        !self.disabled.iter().any(|disabled| disabled == name)
+    }
+}
+
+/// The plugins a host knows about
+#[derive(Default)]
+pub struct PluginLoader {
+    plugins: Vec<Box<dyn EnginePlugin>>,
+}
+
+impl PluginLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_plugin(mut self, plugin: impl EnginePlugin + 'static) -> Self {
+        self.add(plugin);
+        self
+    }
+
+    pub fn add(&mut self, plugin: impl EnginePlugin + 'static) {
+        self.plugins.push(Box::new(plugin));
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.plugins.iter().map(|plugin| plugin.name()).collect()
+    }
+
+    /// Build every enabled plugin into one registry. A plugin listed twice
+    /// is built once.
+    pub fn load(&self, config: &PluginConfig) -> PluginRegistry {
+        let mut registry = PluginRegistry::new();
+        let mut loaded: Vec<&str> = Vec::new();
+        for plugin in &self.plugins {
+            let name = plugin.name();
+            if !config.is_enabled(name) {
+                log::info!("Plugin '{}' is disabled", name);
+                continue;
+            }
+            if loaded.contains(&name) {
+                log::warn!("Plugin '{}' is listed more than once", name);
+                continue;
+            }
+            plugin.build(&mut registry);
+            loaded.push(name);
+            log::info!("Loaded plugin '{}'", name);
+        }
+        for tool in &registry.tools {
+            if registry
+                .tools
+                .iter()
+                .filter(|t| t.name == tool.name)
+                .count()
+                > 1
+            {
+                log::warn!("More than one plugin adds the MCP tool '{}'", tool.name);
+            }
+        }
+        registry
+    }
+
+    /// Load with the project's plugins.json (a broken file disables nothing
+    /// and is reported)
+    pub fn load_project(&self, project_dir: impl AsRef<Path>) -> PluginRegistry {
+        let config = PluginConfig::load(project_dir.as_ref().join(PLUGIN_CONFIG_FILE))
+            .unwrap_or_else(|e| {
+                log::error!("{:#}", e);
+                PluginConfig::default()
+            });
+        self.load(&config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_scene::entity::Component;
+    use engine_scene::impl_component;
+    use std::any::Any;
+    use engine_scene::scene::Scene;
+    use serde_json::json;
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    struct Spin {
+        speed: f32,
+    }
+    impl_component!(Spin);
+
+    struct SpinPlugin;
+
+    impl EnginePlugin for SpinPlugin {
+        fn name(&self) -> &'static str {
+            "spin"
+        }
+
+        fn build(&self, registry: &mut PluginRegistry) {
+            registry
+                .add_component::<Spin>("Spin", || Spin { speed: 1.0 })
+                .add_system("Spin", |scene, dt| {
+                    let spinners: Vec<_> = scene
+                        .entities()
+                        .filter_map(|entity| {
+                            Some((entity.id, entity.get_component::<Spin>()?.speed))
+                        })
+                        .collect();
+                    for (id, speed) in spinners {
+                        if let Some(entity) = scene.get_entity_mut(id) {
+                            entity.transform.rotation *= glam::Quat::from_rotation_y(speed * dt);
+                        }
+                    }
+                    Ok(())
+                })
+                .add_mcp_tool(
+                    "count_spinners",
+                    "Count entities with a Spin component",
+                    json!({ "type": "object", "properties": {} }),
+                    |_, scene| {
+                        let count = scene
+                            .entities()
+                            .filter(|entity| entity.has_component::<Spin>())
+                            .count();
+                        Ok(json!({ "count": count }))
+                    },
+                );
+        }
+    }
+
+    #[test]
+    fn test_loaded_plugin_extends_the_scene() {
+        let loader = PluginLoader::new()
+            .with_plugin(SpinPlugin)
+            .with_plugin(SpinPlugin);
+        let mut registry = loader.load(&PluginConfig::default());
+        assert_eq!(registry.components.len(), 1);
+        assert_eq!(registry.systems.len(), 1);
+
+        let mut scene = Scene::new("Test".to_string());
+        let id = scene.create_entity("Top".to_string());
+        let component = &registry.components[0];
+        let entity = scene.get_entity_mut(id).unwrap();
+        let default = component.default.as_ref().unwrap()();
+        (component.set)(entity, default).unwrap();
+        assert_eq!((component.get)(entity), Some(json!({ "speed": 1.0 })));
+
+        registry.run_systems(&mut scene, 0.5).unwrap();
+        let (_, angle) = scene
+            .get_entity(id)
+            .unwrap()
+            .transform
+            .rotation
+            .to_axis_angle();
+        assert!((angle - 0.5).abs() < 1e-5);
+
+        let tool = registry.tool("count_spinners").unwrap();
+        assert_eq!(
+            tool.call(&json!({}), &mut scene).unwrap(),
+            json!({ "count": 1 })
+        );
+
+        // Disabled plugins add nothing
+        let config = PluginConfig {
+            disabled: vec!["spin".to_string()],
+        };
+        let registry = loader.load(&config);
+        assert!(registry.components.is_empty() && registry.tools.is_empty());
+    }
+}
@@ -0,0 +1,570 @@
+// Native game modules - compiled Rust gameplay code reloaded while the editor runs
+//
+// A module is a cdylib exporting `causality_module`, which returns a
+// NativeModuleDesc: the ABI version it was built against, its systems and
+// its component types. Everything crossing the boundary is #[repr(C)], and
+// modules reach the scene only through the NativeHost function table, so a
+// module built with a different compiler, or with changed structs, still
+// loads. Component data is kept in the scene as JSON (the NativeComponents
+// component), which is what lets it survive a reload.
+//
+//   #[no_mangle]
+//   pub extern "C" fn causality_module() -> *const NativeModuleDesc { &MODULE }
+//
+// The library is copied before it is opened, so cargo can overwrite the
+// original; the editor reloads the module when HotReloadWatcher reports the
+// original changed. Systems must not unwind across the boundary: return
+// non-zero from `run` to report an error instead.
+
+use std::collections::BTreeMap;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use anyhow::{bail, Context, Result};
+use engine_scene::entity::{Component, EntityId};
+use engine_scene::impl_component;
+use engine_scene::scene::Scene;
+use engine_scene::transform::Transform;
+use glam::{Quat, Vec3};
+use libloading::Library;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::any::Any;
+
+/// Version of the types below; modules built against another are refused
+pub const NATIVE_ABI_VERSION: u32 = 1;
+
+/// Symbol every module exports
+pub const NATIVE_ENTRY_SYMBOL: &str = "causality_module";
+
+/// The module's entry point
+pub type NativeEntryFn = unsafe extern "C" fn() -> *const NativeModuleDesc;
+
+/// Body of a native system: the host and the step's delta time. Returns 0
+/// on success.
+pub type NativeSystemFn = extern "C" fn(host: *const NativeHost, dt: f32) -> i32;
+
+/// What a module provides. The pointers must stay valid while the library
+/// is loaded (statics, in practice).
+#[repr(C)]
+pub struct NativeModuleDesc {
+    pub abi_version: u32,
+    pub name: *const c_char,
+    pub systems: *const NativeSystemDesc,
+    pub system_count: usize,
+    pub components: *const NativeComponentDesc,
+    pub component_count: usize,
+}
+
+// Only ever statics pointing at 'static data
+unsafe impl Sync for NativeModuleDesc {}
+
+#[repr(C)]
+pub struct NativeSystemDesc {
+    pub name: *const c_char,
+    pub run: NativeSystemFn,
+}
+
+unsafe impl Sync for NativeSystemDesc {}
+
+/// A component type; `default_json` holds its starting values
+#[repr(C)]
+pub struct NativeComponentDesc {
+    pub name: *const c_char,
+    pub default_json: *const c_char,
+}
+
+unsafe impl Sync for NativeComponentDesc {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NativeTransform {
+    pub position: [f32; 3],
+    /// x, y, z, w
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+}
+
+/// The scene as a module sees it during a system's run
+#[repr(C)]
+pub struct NativeHost {
+    pub scene: *mut c_void,
+    /// Copy up to `len` entity ids into `out`; returns the entity count
+    pub entity_ids: extern "C" fn(scene: *mut c_void, out: *mut u64, len: usize) -> usize,
+    pub get_transform:
+        extern "C" fn(scene: *mut c_void, entity: u64, out: *mut NativeTransform) -> bool,
+    pub set_transform:
+        extern "C" fn(scene: *mut c_void, entity: u64, transform: *const NativeTransform) -> bool,
+    /// Copy a component's JSON into `out` if it fits in `len` bytes; returns
+    /// its length, or -1 if the entity doesn't have it
+    pub get_component: extern "C" fn(
+        scene: *mut c_void,
+        entity: u64,
+        name: *const c_char,
+        out: *mut u8,
+        len: usize,
+    ) -> isize,
+    /// Add or replace a component from `len` bytes of JSON
+    pub set_component: extern "C" fn(
+        scene: *mut c_void,
+        entity: u64,
+        name: *const c_char,
+        json: *const u8,
+        len: usize,
+    ) -> bool,
+    /// Log through the host (0 error, 1 warn, 2 info, 3 debug)
+    pub log: extern "C" fn(level: u32, message: *const c_char),
+}
+
+impl NativeHost {
+    fn new(scene: &mut Scene) -> Self {
+        Self {
+            scene: scene as *mut Scene as *mut c_void,
+            entity_ids: host_entity_ids,
+            get_transform: host_get_transform,
+            set_transform: host_set_transform,
+            get_component: host_get_component,
+            set_component: host_set_component,
+            log: host_log,
+        }
+    }
+
+    // Safe wrappers for module code
+
+    pub fn entities(&self) -> Vec<u64> {
+        let count = (self.entity_ids)(self.scene, std::ptr::null_mut(), 0);
+        let mut ids = vec![0; count];
+        let count = (self.entity_ids)(self.scene, ids.as_mut_ptr(), ids.len());
+        ids.truncate(count);
+        ids
+    }
+
+    pub fn transform(&self, entity: u64) -> Option<NativeTransform> {
+        let mut out = NativeTransform::from(Transform::new());
+        (self.get_transform)(self.scene, entity, &mut out).then_some(out)
+    }
+
+    pub fn set_transform(&self, entity: u64, transform: &NativeTransform) -> bool {
+        (self.set_transform)(self.scene, entity, transform)
+    }
+
+    pub fn component(&self, entity: u64, name: &str) -> Option<Value> {
+        let name = CString::new(name).ok()?;
+        let mut buffer = vec![0u8; 256];
+        loop {
+            let len = (self.get_component)(
+                self.scene,
+                entity,
+                name.as_ptr(),
+                buffer.as_mut_ptr(),
+                buffer.len(),
+            );
+            let len = usize::try_from(len).ok()?;
+            if len <= buffer.len() {
+                return serde_json::from_slice(&buffer[..len]).ok();
+            }
+            buffer.resize(len, 0);
+        }
+    }
+
+    pub fn set_component(&self, entity: u64, name: &str, value: &Value) -> bool {
+        let (Ok(name), Ok(json)) = (CString::new(name), serde_json::to_vec(value)) else {
+            return false;
+        };
+        (self.set_component)(self.scene, entity, name.as_ptr(), json.as_ptr(), json.len())
+    }
+
+    pub fn log(&self, level: u32, message: &str) {
+        if let Ok(message) = CString::new(message) {
+            (self.log)(level, message.as_ptr());
+        }
+    }
+}
+
+impl From<Transform> for NativeTransform {
+    fn from(transform: Transform) -> Self {
+        Self {
+            position: transform.position.to_array(),
+            rotation: transform.rotation.to_array(),
+            scale: transform.scale.to_array(),
+        }
+    }
+}
+
+impl From<NativeTransform> for Transform {
+    fn from(transform: NativeTransform) -> Self {
+        Self {
+            position: Vec3::from_array(transform.position),
+            rotation: Quat::from_array(transform.rotation).normalize(),
+            scale: Vec3::from_array(transform.scale),
+        }
+    }
+}
+
+/// Native module component data on an entity, by component name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NativeComponents(pub BTreeMap<String, Value>);
+impl_component!(NativeComponents);
+
+// The host functions. `scene` is the NativeHost's, which only lives for
+// a run_systems call holding the scene mutably.
+
+fn scene_mut<'a>(scene: *mut c_void) -> &'a mut Scene {
+    unsafe { &mut *(scene as *mut Scene) }
+}
+
+fn c_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+    unsafe { CStr::from_ptr(s) }.to_str().ok()
+}
+
+extern "C" fn host_entity_ids(scene: *mut c_void, out: *mut u64, len: usize) -> usize {
+    let scene = scene_mut(scene);
+    // Ascending, so modules visit entities in the same order every run
+    let mut ids: Vec<u64> = scene.entities().map(|entity| entity.id.0).collect();
+    ids.sort_unstable();
+    if !out.is_null() {
+        for (i, id) in ids.iter().take(len).enumerate() {
+            unsafe { *out.add(i) = *id };
+        }
+    }
+    ids.len()
+}
+
+extern "C" fn host_get_transform(
+    scene: *mut c_void,
+    entity: u64,
+    out: *mut NativeTransform,
+) -> bool {
+    match scene_mut(scene).get_entity(EntityId(entity)) {
+        Some(entity) if !out.is_null() => {
+            unsafe { *out = entity.transform.into() };
+            true
+        }
+        _ => false,
+    }
+}
+
+extern "C" fn host_set_transform(
+    scene: *mut c_void,
+    entity: u64,
+    transform: *const NativeTransform,
+) -> bool {
+    match scene_mut(scene).get_entity_mut(EntityId(entity)) {
+        Some(entity) if !transform.is_null() => {
+            entity.transform = unsafe { *transform }.into();
+            true
+        }
+        _ => false,
+    }
+}
+
+extern "C" fn host_get_component(
+    scene: *mut c_void,
+    entity: u64,
+    name: *const c_char,
+    out: *mut u8,
+    len: usize,
+) -> isize {
+    let value = c_str(name).and_then(|name| {
+        scene_mut(scene)
+            .get_entity(EntityId(entity))?
+            .get_component::<NativeComponents>()?
+            .0
+            .get(name)
+    });
+    let Some(json) = value.and_then(|value| serde_json::to_vec(value).ok()) else {
+        return -1;
+    };
+    if json.len() <= len && !out.is_null() {
+        unsafe { std::ptr::copy_nonoverlapping(json.as_ptr(), out, json.len()) };
+    }
+    json.len() as isize
+}
+
+extern "C" fn host_set_component(
+    scene: *mut c_void,
+    entity: u64,
+    name: *const c_char,
+    json: *const u8,
+    len: usize,
+) -> bool {
+    let Some(name) = c_str(name) else {
+        return false;
+    };
+    if json.is_null() {
+        return false;
+    }
+    let json = unsafe { std::slice::from_raw_parts(json, len) };
+    let Ok(value) = serde_json::from_slice::<Value>(json) else {
+        return false;
+    };
+    let Some(entity) = scene_mut(scene).get_entity_mut(EntityId(entity)) else {
+        return false;
+    };
+    match entity.get_component_mut::<NativeComponents>() {
+        Some(components) => {
+            components.0.insert(name.to_string(), value);
+        }
+        None => entity.add_component(NativeComponents(BTreeMap::from([(
+            name.to_string(),
+            value,
+        )]))),
+    }
+    true
+}
+
+extern "C" fn host_log(level: u32, message: *const c_char) {
+    let message = c_str(message).unwrap_or("<invalid message>");
+    match level {
+        0 => log::error!("{}", message),
+        1 => log::warn!("{}", message),
+        2 => log::info!("{}", message),
+        _ => log::debug!("{}", message),
+    }
+}
+
+/// Distinguishes the copies of one module loaded by this process
+static LOAD_COUNT: AtomicU32 = AtomicU32::new(0);
+
+/// A loaded module library
+pub struct NativeModule {
+    pub name: String,
+    /// The library as built (not the loaded copy)
+    pub path: PathBuf,
+    systems: Vec<(String, NativeSystemFn)>,
+    /// Component names and default values
+    pub components: Vec<(String, Value)>,
+    library: Option<Library>,
+    loaded_copy: PathBuf,
+}
+
+impl NativeModule {
+    /// Copy the library aside and load the copy
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let path = path
+            .canonicalize()
+            .with_context(|| format!("Native module {} not found", path.display()))?;
+        let dir = std::env::temp_dir().join("causality-modules");
+        std::fs::create_dir_all(&dir)?;
+        let loaded_copy = dir.join(format!(
+            "{}-{}-{}.{}",
+            path.file_stem().unwrap_or_default().to_string_lossy(),
+            std::process::id(),
+            LOAD_COUNT.fetch_add(1, Ordering::Relaxed),
+            path.extension().unwrap_or_default().to_string_lossy()
+        ));
+        std::fs::copy(&path, &loaded_copy)
+            .with_context(|| format!("Failed to copy {}", path.display()))?;
+
+        let library = unsafe { Library::new(&loaded_copy) };
+        let mut module = Self {
+            name: String::new(),
+            path,
+            systems: Vec::new(),
+            components: Vec::new(),
+            library: None,
+            loaded_copy,
+        };
+        module.library =
+            Some(library.with_context(|| format!("Failed to load {}", module.path.display()))?);
+        module.read_desc()?;
+        Ok(module)
+    }
+
+    fn read_desc(&mut self) -> Result<()> {
+        let library = self.library.as_ref().expect("library is loaded");
+        let desc = unsafe {
+            let entry = library
+                .get::<NativeEntryFn>(NATIVE_ENTRY_SYMBOL.as_bytes())
+                .with_context(|| {
+                    format!("{} has no {}", self.path.display(), NATIVE_ENTRY_SYMBOL)
+                })?;
+            entry().as_ref()
+        };
+        let Some(desc) = desc else {
+            bail!("{} returned no module", self.path.display());
+        };
+        if desc.abi_version != NATIVE_ABI_VERSION {
+            bail!(
+                "{} was built for module ABI {}, the engine uses {}",
+                self.path.display(),
+                desc.abi_version,
+                NATIVE_ABI_VERSION
+            );
+        }
+
+        self.name = c_str(desc.name).unwrap_or("unnamed").to_string();
+        for system in desc_slice(desc.systems, desc.system_count) {
+            let name = c_str(system.name).unwrap_or("unnamed");
+            self.systems.push((name.to_string(), system.run));
+        }
+        for component in desc_slice(desc.components, desc.component_count) {
+            let Some(name) = c_str(component.name) else {
+                continue;
+            };
+            let default = c_str(component.default_json)
+                .and_then(|json| serde_json::from_str(json).ok())
+                .unwrap_or(Value::Null);
+            self.components.push((name.to_string(), default));
+        }
+        Ok(())
+    }
+
+    pub fn system_names(&self) -> impl Iterator<Item = &str> {
+        self.systems.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Run the module's systems for one step, stopping at the first error
+    pub fn run_systems(&self, scene: &mut Scene, dt: f32) -> Result<()> {
+        let host = NativeHost::new(scene);
+        for (name, run) in &self.systems {
+            let _span = tracing::info_span!("native_system", name = name.as_str()).entered();
+            let status = run(&host, dt);
+            if status != 0 {
+                bail!("Native system '{}' failed ({})", name, status);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for NativeModule {
+    fn drop(&mut self) {
+        drop(self.library.take());
+        let _ = std::fs::remove_file(&self.loaded_copy);
+    }
+}
+
+fn desc_slice<'a, T>(items: *const T, count: usize) -> &'a [T] {
+    if items.is_null() || count == 0 {
+        return &[];
+    }
+    unsafe { std::slice::from_raw_parts(items, count) }
+}
+
+/// The native modules a host has loaded
+#[derive(Default)]
+pub struct NativeModules {
+    modules: Vec<NativeModule>,
+}
+
+impl NativeModules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load every library in `paths`, reporting the ones that fail
+    pub fn load_all<P: AsRef<Path>>(paths: &[P]) -> Self {
+        let mut modules = Self::new();
+        for path in paths {
+            if let Err(e) = modules.load(path) {
+                log::error!("{:#}", e);
+            }
+        }
+        modules
+    }
+
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let module = NativeModule::load(path)?;
+        log::info!(
+            "Loaded native module '{}' ({} systems, {} components)",
+            module.name,
+            module.systems.len(),
+            module.components.len()
+        );
+        self.modules.push(module);
+        Ok(())
+    }
+
+    pub fn modules(&self) -> &[NativeModule] {
+        &self.modules
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    /// If `path` is a loaded module's library, load it again and swap it in.
+    /// Returns the module's name, or None if `path` isn't a module. If the
+    /// new build fails to load, the old one keeps running.
+    pub fn reload(&mut self, path: &Path) -> Result<Option<String>> {
+        let Ok(path) = path.canonicalize() else {
+            return Ok(None);
+        };
+        let Some(index) = self.modules.iter().position(|m| m.path == path) else {
+            return Ok(None);
+        };
+        let module = NativeModule::load(&path)?;
+        let name = module.name.clone();
+        self.modules[index] = module;
+        log::info!("Reloaded native module '{}'", name);
+        Ok(Some(name))
+    }
+
+    /// Run every module's systems for one step
+    pub fn run_systems(&self, scene: &mut Scene, dt: f32) -> Result<()> {
+        for module in &self.modules {
+            module.run_systems(scene, dt)?;
+        }
+        Ok(())
+    }
+
+    /// Starting value of a module component, by name
+    pub fn component_default(&self, name: &str) -> Option<&Value> {
+        self.modules
+            .iter()
+            .flat_map(|module| &module.components)
+            .find(|(component, _)| component == name)
+            .map(|(_, default)| default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_host_exposes_the_scene() {
+        let mut scene = Scene::new("Test".to_string());
+        let id = scene.create_entity("Crate".to_string());
+        let host = NativeHost::new(&mut scene);
+
+        assert_eq!(host.entities(), [id.0]);
+        let mut transform = host.transform(id.0).unwrap();
+        assert_eq!(transform.scale, [1.0; 3]);
+        transform.position = [1.0, 2.0, 3.0];
+        assert!(host.set_transform(id.0, &transform));
+        assert!(host.transform(u64::MAX).is_none());
+
+        assert_eq!(host.component(id.0, "Health"), None);
+        let long_name = "x".repeat(1000);
+        assert!(host.set_component(id.0, "Health", &json!({ "hp": 10, "name": long_name })));
+        assert_eq!(
+            host.component(id.0, "Health"),
+            Some(json!({ "hp": 10, "name": long_name }))
+        );
+
+        let entity = scene.get_entity(id).unwrap();
+        assert_eq!(entity.transform.position, Vec3::new(1.0, 2.0, 3.0));
+        assert!(entity.get_component::<NativeComponents>().is_some());
+    }
+
+    #[test]
+    fn test_missing_library_fails_to_load() {
+        let mut modules = NativeModules::new();
+        assert!(modules.load("/nonexistent/libgame.so").is_err());
+        assert!(modules.is_empty());
+        assert_eq!(
+            modules
+                .reload(Path::new("/nonexistent/libgame.so"))
+                .unwrap(),
+            None
+        );
+    }
+}
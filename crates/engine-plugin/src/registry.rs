@@ -0,0 +1,169 @@
+// Plugin registry - what plugins add to the engine
+//
+// Plugins register into a PluginRegistry in their build(). The host then
+// takes the parts it uses: the editor all of them, the headless runtime
+// only the systems. Components are described the way the
+// editor's component registry reflects engine types, as JSON through serde.
+
+use anyhow::Result;
+use engine_scene::entity::{Component, Entity};
+use engine_scene::scene::Scene;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::render::RenderPass;
+
+/// Body of a plugin system: the scene and the step's delta time
+pub type SystemFn = Box<dyn FnMut(&mut Scene, f32) -> Result<()> + Send>;
+
+/// Body of a plugin MCP tool: the tool's arguments and the scene
+pub type ToolHandler = Box<dyn Fn(&Value, &mut Scene) -> Result<Value> + Send + Sync>;
+
+/// A component type a plugin adds, readable and writable as JSON
+pub struct PluginComponent {
+    pub name: &'static str,
+    pub get: fn(&Entity) -> Option<Value>,
+    pub set: fn(&mut Entity, Value) -> Result<()>,
+    pub remove: fn(&mut Entity) -> bool,
+    /// Starting values when the component is added without all its fields
+    pub default: Option<Box<dyn Fn() -> Value + Send + Sync>>,
+}
+
+/// A system a plugin runs every simulation step, after scripts and
+/// animation and before physics
+pub struct PluginSystem {
+    pub name: &'static str,
+    pub run: SystemFn,
+}
+
+impl PluginSystem {
+    pub fn run(&mut self, scene: &mut Scene, dt: f32) -> Result<()> {
+        let _span = tracing::info_span!("plugin_system", name = self.name).entered();
+        (self.run)(scene, dt)
+    }
+}
+
+/// An MCP tool a plugin adds; the editor answers it against its scene
+pub struct PluginTool {
+    pub name: String,
+    pub description: String,
+    /// JSON schema of the tool's arguments
+    pub input_schema: Value,
+    pub handler: ToolHandler,
+}
+
+impl PluginTool {
+    pub fn call(&self, args: &Value, scene: &mut Scene) -> Result<Value> {
+        (self.handler)(args, scene)
+    }
+}
+
+/// An editor window added by a plugin
+pub trait EditorPanel: Send {
+    fn title(&self) -> &str;
+
+    fn ui(&mut self, ui: &mut egui::Ui, scene: &mut Scene);
+}
+
+/// Everything the loaded plugins registered
+#[derive(Default)]
+pub struct PluginRegistry {
+    pub components: Vec<PluginComponent>,
+    pub systems: Vec<PluginSystem>,
+    pub render_passes: Vec<Box<dyn RenderPass>>,
+    pub editor_panels: Vec<Box<dyn EditorPanel>>,
+    pub tools: Vec<PluginTool>,
+}
+
+impl PluginRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a component type; `default` gives the values it starts with
+    pub fn add_component<T>(&mut self, name: &'static str, default: fn() -> T) -> &mut Self
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        self.components.push(PluginComponent {
+            name,
+            get: get_json::<T>,
+            set: set_json::<T>,
+            remove: remove::<T>,
+            default: Some(Box::new(move || {
+                serde_json::to_value(default()).unwrap_or(Value::Null)
+            })),
+        });
+        self
+    }
+
+    /// Run `system` every simulation step with the step's delta time
+    pub fn add_system(
+        &mut self,
+        name: &'static str,
+        system: impl FnMut(&mut Scene, f32) -> Result<()> + Send + 'static,
+    ) -> &mut Self {
+        self.systems.push(PluginSystem {
+            name,
+            run: Box::new(system),
+        });
+        self
+    }
+
+    pub fn add_render_pass(&mut self, pass: impl RenderPass + 'static) -> &mut Self {
+        self.render_passes.push(Box::new(pass));
+        self
+    }
+
+    pub fn add_editor_panel(&mut self, panel: impl EditorPanel + 'static) -> &mut Self {
+        self.editor_panels.push(Box::new(panel));
+        self
+    }
+
+    /// Add an MCP tool; `handler` gets the tool's arguments and the scene
+    pub fn add_mcp_tool(
+        &mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        input_schema: Value,
+        handler: impl Fn(&Value, &mut Scene) -> Result<Value> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.tools.push(PluginTool {
+            name: name.into(),
+            description: description.into(),
+            input_schema,
+            handler: Box::new(handler),
+        });
+        self
+    }
+
+    pub fn tool(&self, name: &str) -> Option<&PluginTool> {
+        self.tools.iter().find(|tool| tool.name == name)
+    }
+
+    /// Run every plugin system for one step, stopping at the first error
+    pub fn run_systems(&mut self, scene: &mut Scene, dt: f32) -> Result<()> {
+        for system in &mut self.systems {
+            system.run(scene, dt)?;
+        }
+        Ok(())
+    }
+}
+
+fn get_json<T: Component + Serialize>(entity: &Entity) -> Option<Value> {
+    let component = entity.get_component::<T>()?;
+    serde_json::to_value(component).ok()
+}
+
+fn set_json<T: Component + DeserializeOwned>(entity: &mut Entity, value: Value) -> Result<()> {
+    let component: T = serde_json::from_value(value)?;
+    match entity.get_component_mut::<T>() {
+        Some(existing) => *existing = component,
+        None => entity.add_component(component),
+    }
+    Ok(())
+}
+
+fn remove<T: Component>(entity: &mut Entity) -> bool {
+    entity.remove_component::<T>()
+}
@@ -0,0 +1,31 @@
+// Plugin render passes - drawing over the scene
+//
+// Passes run every frame after the scene (and particles) and before the
+// editor UI, in the order they were registered. Each records into the
+// frame's command encoder; pipelines and buffers are the pass's own to
+// create, typically on the first call once the device and format are known.
+
+use engine_scene::scene::Scene;
+use glam::{Mat4, Vec3};
+
+/// What a plugin render pass draws with
+pub struct RenderContext<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    pub encoder: &'a mut wgpu::CommandEncoder,
+    /// The frame's color target (single-sampled, already holding the scene)
+    pub target: &'a wgpu::TextureView,
+    pub format: wgpu::TextureFormat,
+    /// Target size in pixels
+    pub size: (u32, u32),
+    pub view_proj: Mat4,
+    pub camera_position: Vec3,
+    pub scene: &'a Scene,
+}
+
+/// A render pass added by a plugin
+pub trait RenderPass: Send {
+    fn name(&self) -> &str;
+
+    fn render(&mut self, context: &mut RenderContext);
+}
@@ -11,6 +11,10 @@ pub struct Camera {
     pub aspect: f32,
     pub near: f32,
     pub far: f32,
+    /// Use an orthographic projection instead of perspective
+    pub orthographic: bool,
+    /// Half-height of the orthographic view volume in world units
+    pub ortho_size: f32,
 }
 
 impl Camera {
@@ -23,6 +27,8 @@ impl Camera {
             aspect: width as f32 / height as f32,
             near: 0.1,
             far: 100.0,
+            orthographic: false,
+            ortho_size: 10.0,
         }
     }
 
@@ -31,7 +37,20 @@ impl Camera {
     }
 
     pub fn projection_matrix(&self) -> Mat4 {
-        Mat4::perspective_rh(self.fov, self.aspect, self.near, self.far)
+        if self.orthographic {
+            let half_height = self.ortho_size;
+            let half_width = half_height * self.aspect;
+            Mat4::orthographic_rh(
+                -half_width,
+                half_width,
+                -half_height,
+                half_height,
+                self.near,
+                self.far,
+            )
+        } else {
+            Mat4::perspective_rh(self.fov, self.aspect, self.near, self.far)
+        }
     }
 
     pub fn view_projection_matrix(&self) -> Mat4 {
@@ -65,6 +84,11 @@ impl Camera {
 
         let direction = (far_point - near_point).normalize();
 
-        (self.position, direction)
+        // Orthographic rays are parallel, so each starts on the near plane under the cursor
+        if self.orthographic {
+            (near_point, direction)
+        } else {
+            (self.position, direction)
+        }
     }
 }
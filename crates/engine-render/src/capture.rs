@@ -0,0 +1,283 @@
+// Frame capture - read rendered pixels back to the CPU
+//
+// A region of a texture (usually the swapchain image, cropped to the scene
+// viewport) is copied into a mappable buffer while the frame is encoded. Once
+// the frame is submitted the buffer is mapped asynchronously and picked up on
+// a later frame, like the GPU profiler's timestamps, so capturing never
+// stalls the render loop. Supersampled captures render the view as a grid of
+// tiles with `tile_projection` and stitch them together.
+
+use glam::{Mat4, Vec3};
+use std::sync::{Arc, Mutex};
+
+/// RGBA8 image read back from the GPU
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturedImage {
+    pub width: u32,
+    pub height: u32,
+    /// Tightly packed rows, top to bottom
+    pub rgba: Vec<u8>,
+}
+
+impl CapturedImage {
+    /// Transparent black image
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            rgba: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    /// Copy `tile` into this image with its top-left corner at (x, y), clipped to the image
+    pub fn blit(&mut self, tile: &CapturedImage, x: u32, y: u32) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let columns = tile.width.min(self.width - x) as usize * 4;
+        for row in 0..tile.height.min(self.height - y) {
+            let src = row as usize * tile.width as usize * 4;
+            let dst = ((y + row) as usize * self.width as usize + x as usize) * 4;
+            self.rgba[dst..dst + columns].copy_from_slice(&tile.rgba[src..src + columns]);
+        }
+    }
+
+    /// Box-filter down by `factor` in each direction (partial edge blocks are dropped)
+    pub fn downsample(&self, factor: u32) -> CapturedImage {
+        if factor <= 1 {
+            return self.clone();
+        }
+        let mut out = CapturedImage::new(self.width / factor, self.height / factor);
+        let samples = factor * factor;
+        for y in 0..out.height {
+            for x in 0..out.width {
+                let mut sum = [0u32; 4];
+                for sy in 0..factor {
+                    for sx in 0..factor {
+                        let i = (((y * factor + sy) * self.width + x * factor + sx) * 4) as usize;
+                        for (channel, total) in sum.iter_mut().enumerate() {
+                            *total += self.rgba[i + channel] as u32;
+                        }
+                    }
+                }
+                let o = ((y * out.width + x) * 4) as usize;
+                for (channel, total) in sum.iter().enumerate() {
+                    out.rgba[o + channel] = ((total + samples / 2) / samples) as u8;
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Matrix applied after the projection so the view renders one tile of a
+/// `tiles` x `tiles` grid. Column 0 is the left edge and row 0 the top.
+pub fn tile_projection(tiles: u32, column: u32, row: u32) -> Mat4 {
+    let n = tiles.max(1) as f32;
+    let offset_x = n - 1.0 - 2.0 * column as f32;
+    let offset_y = -(n - 1.0 - 2.0 * row as f32);
+    Mat4::from_translation(Vec3::new(offset_x, offset_y, 0.0))
+        * Mat4::from_scale(Vec3::new(n, n, 1.0))
+}
+
+/// Copy of a texture region waiting to be read back
+pub struct FrameCapture {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    /// Source texels are BGRA and need swizzling
+    bgra: bool,
+    /// map_async result, once the readback has been started
+    status: Option<Arc<Mutex<Option<bool>>>>,
+}
+
+impl FrameCapture {
+    /// True for the 8-bit color formats a capture can read back
+    pub fn supports_format(format: wgpu::TextureFormat) -> bool {
+        matches!(
+            format,
+            wgpu::TextureFormat::Rgba8Unorm
+                | wgpu::TextureFormat::Rgba8UnormSrgb
+                | wgpu::TextureFormat::Bgra8Unorm
+                | wgpu::TextureFormat::Bgra8UnormSrgb
+        )
+    }
+
+    /// Record a copy of the region `origin` / `size` of `texture` (which needs
+    /// COPY_SRC usage). Returns None for unsupported formats or empty regions.
+    pub fn record(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        origin: [u32; 2],
+        size: [u32; 2],
+    ) -> Option<Self> {
+        let format = texture.format();
+        if !Self::supports_format(format) {
+            return None;
+        }
+        let [x, y] = origin;
+        let width = size[0].min(texture.width().saturating_sub(x));
+        let height = size[1].min(texture.height().saturating_sub(y));
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let padded_bytes_per_row = padded_bytes_per_row(width);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Frame Capture Buffer"),
+            size: padded_bytes_per_row as u64 * height as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        Some(Self {
+            buffer,
+            width,
+            height,
+            padded_bytes_per_row,
+            bgra: matches!(
+                format,
+                wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+            ),
+            status: None,
+        })
+    }
+
+    /// Start reading back the copy (after the encoder was submitted)
+    pub fn after_submit(&mut self) {
+        if self.status.is_some() {
+            return;
+        }
+        let status = Arc::new(Mutex::new(None));
+        let callback_status = status.clone();
+        self.buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                *callback_status.lock().unwrap() = Some(result.is_ok());
+            });
+        self.status = Some(status);
+    }
+
+    /// Pick up the finished readback. None while it is still in flight;
+    /// Some(None) if mapping failed.
+    pub fn poll(&mut self, device: &wgpu::Device) -> Option<Option<CapturedImage>> {
+        let status = self.status.as_ref()?;
+        let _ = device.poll(wgpu::PollType::Poll);
+        let mapped = (*status.lock().unwrap())?;
+        if !mapped {
+            return Some(None);
+        }
+        let image = {
+            let data = self.buffer.slice(..).get_mapped_range();
+            unpad_rows(
+                &data,
+                self.width,
+                self.height,
+                self.padded_bytes_per_row,
+                self.bgra,
+            )
+        };
+        self.buffer.unmap();
+        Some(Some(image))
+    }
+}
+
+/// Row pitch of a readback buffer, aligned as copies require
+fn padded_bytes_per_row(width: u32) -> u32 {
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    (width * 4).div_ceil(align) * align
+}
+
+/// Strip row padding from read-back texels, converting BGRA to RGBA
+fn unpad_rows(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    bgra: bool,
+) -> CapturedImage {
+    let row_bytes = width as usize * 4;
+    let mut rgba = Vec::with_capacity(row_bytes * height as usize);
+    for row in data
+        .chunks(padded_bytes_per_row as usize)
+        .take(height as usize)
+    {
+        rgba.extend_from_slice(&row[..row_bytes]);
+    }
+    if bgra {
+        for texel in rgba.chunks_exact_mut(4) {
+            texel.swap(0, 2);
+        }
+    }
+    CapturedImage {
+        width,
+        height,
+        rgba,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec4;
+
+    #[test]
+    fn test_tiles_cover_the_full_view() {
+        // Top-left tile of a 2x2 grid shows the view's top-left quadrant
+        let tile = tile_projection(2, 0, 0);
+        let corner = tile * Vec4::new(-1.0, 1.0, 0.5, 1.0);
+        assert!((corner.truncate() - Vec3::new(-1.0, 1.0, 0.5)).length() < 1e-6);
+        let center = tile * Vec4::new(0.0, 0.0, 0.5, 1.0);
+        assert!((center.truncate() - Vec3::new(1.0, -1.0, 0.5)).length() < 1e-6);
+
+        // Bottom-right tile of a 3x3 grid
+        let tile = tile_projection(3, 2, 2);
+        let corner = tile * Vec4::new(1.0, -1.0, 0.0, 1.0);
+        assert!((corner.truncate() - Vec3::new(1.0, -1.0, 0.0)).length() < 1e-6);
+        assert_eq!(tile_projection(1, 0, 0), Mat4::IDENTITY);
+    }
+
+    #[test]
+    fn test_unpad_blit_and_downsample() {
+        // 2x1 BGRA image with padded rows
+        let mut data = vec![0u8; 512];
+        data[..8].copy_from_slice(&[10, 20, 30, 255, 40, 50, 60, 255]);
+        let tile = unpad_rows(&data, 2, 1, 256, true);
+        assert_eq!(tile.rgba, vec![30, 20, 10, 255, 60, 50, 40, 255]);
+        assert_eq!(padded_bytes_per_row(2), 256);
+
+        let mut image = CapturedImage::new(2, 2);
+        image.blit(&tile, 0, 1);
+        assert_eq!(&image.rgba[8..], &tile.rgba[..]);
+        // Clipped at the right edge
+        image.blit(&tile, 1, 0);
+        assert_eq!(&image.rgba[4..8], &[30, 20, 10, 255]);
+
+        let small = image.downsample(2);
+        assert_eq!((small.width, small.height), (1, 1));
+        assert_eq!(small.rgba, vec![30, 23, 15, 191]);
+    }
+}
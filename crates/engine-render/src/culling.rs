@@ -186,6 +186,7 @@ impl CullingSystem {
     pub fn stats(&self) -> CullingStats {
         CullingStats {
             total_renderables: self.renderables.len(),
+            ..CullingStats::default()
         }
     }
 
@@ -213,10 +214,36 @@ impl Default for CullingSystem {
 }
 
 /// Culling statistics
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct CullingStats {
     /// Total number of registered renderables
     pub total_renderables: usize,
+    /// Renderables that passed the last frustum test (counted with `record`)
+    pub visible_renderables: usize,
+    /// Renderables rejected by the last frustum test (counted with `record`)
+    pub culled_renderables: usize,
+}
+
+impl CullingStats {
+    /// Count one renderable tested against the frustum
+    pub fn record(&mut self, visible: bool) {
+        self.total_renderables += 1;
+        if visible {
+            self.visible_renderables += 1;
+        } else {
+            self.culled_renderables += 1;
+        }
+    }
+
+    /// Percentage of tested renderables that were culled
+    pub fn cull_efficiency(&self) -> f32 {
+        let tested = self.visible_renderables + self.culled_renderables;
+        if tested == 0 {
+            0.0
+        } else {
+            (self.culled_renderables as f32 / tested as f32) * 100.0
+        }
+    }
 }
 
 /// Visibility result from culling
@@ -0,0 +1,181 @@
+// Foliage culling - chunked frustum culling and detail levels for vegetation
+//
+// Instances are bucketed into square chunks on the XZ plane. Each frame a
+// chunk is tested against the view frustum and draw distance as a whole, and
+// only the instances of chunks that pass are tested one by one. Visible
+// instances are sorted by camera distance into the full mesh, the low detail
+// mesh and billboard impostors.
+
+use crate::foliage_renderer::{FoliageDraw, FoliageInstanceGpu};
+use crate::frustum::Frustum;
+use glam::Vec3;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Side length of a culling chunk in world units
+pub const FOLIAGE_CHUNK_SIZE: f32 = 32.0;
+
+/// Distances from the camera at which an instance drops a detail level
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FoliageLodRange {
+    /// Low detail mesh from this distance
+    pub lod_distance: f32,
+    /// Billboard impostor from this distance
+    pub impostor_distance: f32,
+}
+
+struct FoliageChunk {
+    /// Bounds of the instance origins
+    min: Vec3,
+    max: Vec3,
+    max_scale: f32,
+    max_draw_distance: f32,
+    instances: Vec<(FoliageInstanceGpu, FoliageLodRange)>,
+}
+
+/// Instances of one vegetation type bucketed for culling
+#[derive(Default)]
+pub struct FoliageChunks {
+    chunks: HashMap<(i32, i32), FoliageChunk>,
+    len: usize,
+}
+
+/// Instances that passed culling, by detail level
+#[derive(Default)]
+pub struct FoliageVisible {
+    pub full: Vec<FoliageInstanceGpu>,
+    pub lod: Vec<FoliageInstanceGpu>,
+    pub impostor: Vec<FoliageInstanceGpu>,
+}
+
+impl FoliageChunks {
+    pub fn insert(&mut self, instance: FoliageInstanceGpu, range: FoliageLodRange) {
+        let position = instance.position();
+        let key = (
+            (position.x / FOLIAGE_CHUNK_SIZE).floor() as i32,
+            (position.z / FOLIAGE_CHUNK_SIZE).floor() as i32,
+        );
+        let chunk = self.chunks.entry(key).or_insert_with(|| FoliageChunk {
+            min: position,
+            max: position,
+            max_scale: 0.0,
+            max_draw_distance: 0.0,
+            instances: Vec::new(),
+        });
+        chunk.min = chunk.min.min(position);
+        chunk.max = chunk.max.max(position);
+        chunk.max_scale = chunk.max_scale.max(instance.scale());
+        chunk.max_draw_distance = chunk.max_draw_distance.max(instance.fade_range[0]);
+        chunk.instances.push((instance, range));
+        self.len += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Instances in view and within their draw distance. `radius` bounds
+    /// the mesh around the instance origin at scale 1.
+    pub fn cull(&self, frustum: &Frustum, camera_pos: Vec3, radius: f32) -> FoliageVisible {
+        let mut visible = FoliageVisible::default();
+        for chunk in self.chunks.values() {
+            let reach = Vec3::splat(radius * chunk.max_scale);
+            let (min, max) = (chunk.min - reach, chunk.max + reach);
+            if camera_pos.clamp(min, max).distance(camera_pos) > chunk.max_draw_distance
+                || !frustum.contains_aabb(min, max)
+            {
+                continue;
+            }
+
+            for (instance, range) in &chunk.instances {
+                let position = instance.position();
+                let distance = position.distance(camera_pos);
+                if distance >= instance.fade_range[0]
+                    || !frustum.contains_sphere(position, radius * instance.scale())
+                {
+                    continue;
+                }
+                let level = if distance >= range.impostor_distance {
+                    &mut visible.impostor
+                } else if distance >= range.lod_distance {
+                    &mut visible.lod
+                } else {
+                    &mut visible.full
+                };
+                level.push(*instance);
+            }
+        }
+        visible
+    }
+}
+
+/// Visible instances of every type packed into one instance buffer, with the
+/// range of it each draw uses
+#[derive(Default)]
+pub struct FoliageBatches {
+    pub instances: Vec<FoliageInstanceGpu>,
+    pub draws: Vec<(FoliageDraw, Range<u32>)>,
+}
+
+impl FoliageBatches {
+    pub fn push(&mut self, draw: FoliageDraw, instances: &[FoliageInstanceGpu]) {
+        if instances.is_empty() {
+            return;
+        }
+        let start = self.instances.len() as u32;
+        self.instances.extend_from_slice(instances);
+        self.draws.push((draw, start..self.instances.len() as u32));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Mat4;
+
+    #[test]
+    fn test_cull_by_frustum_and_distance() {
+        // Looking down -Z from the origin
+        let view = Mat4::look_at_rh(Vec3::ZERO, Vec3::NEG_Z, Vec3::Y);
+        let proj = Mat4::perspective_rh(60f32.to_radians(), 1.0, 0.1, 1000.0);
+        let frustum = Frustum::from_view_projection(proj * view);
+        let range = FoliageLodRange {
+            lod_distance: 20.0,
+            impostor_distance: 50.0,
+        };
+
+        let mut chunks = FoliageChunks::default();
+        let tree = |z: f32| {
+            FoliageInstanceGpu::new(Vec3::new(0.0, 0.0, z), 0.0, 1.0, Vec3::ONE)
+                .with_fade_range(100.0, 10.0)
+        };
+        for z in [-10.0, -30.0, -60.0, -150.0, 40.0] {
+            chunks.insert(tree(z), range);
+        }
+        assert_eq!(chunks.len(), 5);
+
+        // Behind the camera and past the draw distance are dropped
+        let visible = chunks.cull(&frustum, Vec3::ZERO, 1.0);
+        let z = |instances: &[FoliageInstanceGpu]| -> Vec<f32> {
+            instances
+                .iter()
+                .map(|instance| instance.position().z)
+                .collect()
+        };
+        assert_eq!(z(&visible.full), [-10.0]);
+        assert_eq!(z(&visible.lod), [-30.0]);
+        assert_eq!(z(&visible.impostor), [-60.0]);
+
+        let mut batches = FoliageBatches::default();
+        batches.push(FoliageDraw::Impostor("tree".to_string()), &visible.impostor);
+        batches.push(FoliageDraw::Impostor("tree".to_string()), &visible.lod);
+        batches.push(FoliageDraw::Impostor("bush".to_string()), &[]);
+        assert_eq!(batches.instances.len(), 2);
+        assert_eq!(batches.draws.len(), 2);
+        assert_eq!(batches.draws[1].1, 1..2);
+    }
+}
@@ -1,14 +1,21 @@
 // Foliage Renderer - Instanced mesh rendering for vegetation
 //
 // Renders many instances of the same mesh efficiently using GPU instancing.
+// Distant instances are drawn as camera-facing billboards (impostors) whose
+// texture is baked once from the mesh, seen from the side.
 
-use crate::gpu_mesh::GpuMesh;
-use crate::MSAA_SAMPLE_COUNT;
+use crate::gpu_mesh::{GpuMesh, MeshHandle};
 use anyhow::Result;
 use bytemuck::{Pod, Zeroable};
-use glam::{Mat4, Vec3};
+use glam::{Mat4, Vec2, Vec3};
+use std::collections::HashMap;
+use std::ops::Range;
 use wgpu::util::DeviceExt;
 
+/// Impostor texture size in pixels
+const IMPOSTOR_RESOLUTION: u32 = 256;
+const IMPOSTOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
 /// Per-instance foliage data sent to GPU
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
@@ -17,6 +24,11 @@ pub struct FoliageInstanceGpu {
     pub model: [[f32; 4]; 4],
     /// Color tint (RGB + padding)
     pub color_tint: [f32; 4],
+    /// Draw distance and fade width; the instance dithers out over the
+    /// fade width before the draw distance
+    pub fade_range: [f32; 2],
+    /// Resistance to bending in the wind
+    pub stiffness: f32,
 }
 
 impl FoliageInstanceGpu {
@@ -30,6 +42,82 @@ impl FoliageInstanceGpu {
         Self {
             model: model.to_cols_array_2d(),
             color_tint: [color_tint.x, color_tint.y, color_tint.z, 1.0],
+            fade_range: [f32::MAX, 0.0],
+            stiffness: 1.0,
+        }
+    }
+
+    /// Fade the instance out over `fade_width` up to `draw_distance`
+    pub fn with_fade_range(mut self, draw_distance: f32, fade_width: f32) -> Self {
+        self.fade_range = [draw_distance, fade_width];
+        self
+    }
+
+    /// Sway less (above 1) or more (below 1) in the wind
+    pub fn with_stiffness(mut self, stiffness: f32) -> Self {
+        self.stiffness = stiffness;
+        self
+    }
+
+    /// World position of the instance origin
+    pub fn position(&self) -> Vec3 {
+        Vec3::new(self.model[3][0], self.model[3][1], self.model[3][2])
+    }
+
+    /// Uniform scale of the instance
+    pub fn scale(&self) -> f32 {
+        Vec3::new(self.model[0][0], self.model[0][1], self.model[0][2]).length()
+    }
+}
+
+/// What a range of foliage instances is drawn with
+#[derive(Debug, Clone, PartialEq)]
+pub enum FoliageDraw {
+    Mesh(MeshHandle),
+    /// The impostor baked for the named mesh
+    Impostor(String),
+}
+
+/// Billboard extents around the instance origin, matching the baked texture
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct ImpostorUniforms {
+    half_width: f32,
+    bottom: f32,
+    top: f32,
+    _padding: f32,
+}
+
+struct Impostor {
+    bind_group: wgpu::BindGroup,
+    // Kept alive for the bind group
+    _texture: wgpu::Texture,
+    _uniform_buffer: wgpu::Buffer,
+}
+
+/// Wind that foliage sways in. The gusts follow `Wind::gust` in the scene
+/// crate, which the shader mirrors.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FoliageWind {
+    /// Direction the wind blows toward, on the XZ plane
+    pub direction: Vec2,
+    pub strength: f32,
+    pub gust_strength: f32,
+    /// Gusts passing a point per second
+    pub gust_frequency: f32,
+    /// Distance between gusts in world units
+    pub gust_scale: f32,
+}
+
+impl Default for FoliageWind {
+    /// Still air
+    fn default() -> Self {
+        Self {
+            direction: Vec2::X,
+            strength: 0.0,
+            gust_strength: 0.0,
+            gust_frequency: 0.0,
+            gust_scale: 1.0,
         }
     }
 }
@@ -40,35 +128,203 @@ impl FoliageInstanceGpu {
 pub struct FoliageCameraUniforms {
     pub view_proj: [[f32; 4]; 4],
     pub camera_pos: [f32; 3],
-    pub _padding: f32,
+    /// Seconds, for animating the wind
+    pub time: f32,
+    /// Wind direction (x, z), strength and gust strength
+    pub wind: [f32; 4],
+    /// Gust frequency and scale
+    pub gust: [f32; 4],
+}
+
+impl FoliageCameraUniforms {
+    fn new(view_proj: Mat4, camera_pos: Vec3, wind: &FoliageWind, time: f32) -> Self {
+        let direction = wind.direction.normalize_or(Vec2::X);
+        Self {
+            view_proj: view_proj.to_cols_array_2d(),
+            camera_pos: camera_pos.to_array(),
+            time,
+            wind: [direction.x, direction.y, wind.strength, wind.gust_strength],
+            gust: [wind.gust_frequency, wind.gust_scale.max(0.01), 0.0, 0.0],
+        }
+    }
 }
 
 /// Foliage renderer for instanced vegetation
 pub struct FoliageRenderer {
     /// Render pipeline
     pipeline: wgpu::RenderPipeline,
+    /// Pipeline drawing meshes into impostor textures
+    bake_pipeline: wgpu::RenderPipeline,
+    /// Billboard pipeline for impostors
+    impostor_pipeline: wgpu::RenderPipeline,
     /// Camera uniform buffer
     camera_buffer: wgpu::Buffer,
     /// Camera bind group
     camera_bind_group: wgpu::BindGroup,
+    camera_bind_group_layout: wgpu::BindGroupLayout,
+    impostor_bind_group_layout: wgpu::BindGroupLayout,
+    impostor_sampler: wgpu::Sampler,
+    /// Baked impostors by mesh name
+    impostors: HashMap<String, Impostor>,
     /// Instance buffer (resized as needed)
     instance_buffer: Option<wgpu::Buffer>,
     /// Current instance buffer capacity
     instance_capacity: usize,
 }
 
+/// Per-instance vertex attributes (locations 4-10) shared by every foliage pipeline
+const INSTANCE_ATTRIBUTES: [wgpu::VertexAttribute; 7] = [
+    // Model matrix columns 0-3
+    wgpu::VertexAttribute {
+        offset: 0,
+        shader_location: 4,
+        format: wgpu::VertexFormat::Float32x4,
+    },
+    wgpu::VertexAttribute {
+        offset: 16,
+        shader_location: 5,
+        format: wgpu::VertexFormat::Float32x4,
+    },
+    wgpu::VertexAttribute {
+        offset: 32,
+        shader_location: 6,
+        format: wgpu::VertexFormat::Float32x4,
+    },
+    wgpu::VertexAttribute {
+        offset: 48,
+        shader_location: 7,
+        format: wgpu::VertexFormat::Float32x4,
+    },
+    // Color tint
+    wgpu::VertexAttribute {
+        offset: 64,
+        shader_location: 8,
+        format: wgpu::VertexFormat::Float32x4,
+    },
+    // Draw distance and fade width
+    wgpu::VertexAttribute {
+        offset: 80,
+        shader_location: 9,
+        format: wgpu::VertexFormat::Float32x2,
+    },
+    // Wind stiffness
+    wgpu::VertexAttribute {
+        offset: 88,
+        shader_location: 10,
+        format: wgpu::VertexFormat::Float32,
+    },
+];
+
+/// Mesh vertex attributes used by the foliage shader (subset of GpuVertex)
+const VERTEX_ATTRIBUTES: [wgpu::VertexAttribute; 4] = [
+    // Position
+    wgpu::VertexAttribute {
+        offset: 0,
+        shader_location: 0,
+        format: wgpu::VertexFormat::Float32x3,
+    },
+    // Normal
+    wgpu::VertexAttribute {
+        offset: 12,
+        shader_location: 1,
+        format: wgpu::VertexFormat::Float32x3,
+    },
+    // TexCoord
+    wgpu::VertexAttribute {
+        offset: 24,
+        shader_location: 2,
+        format: wgpu::VertexFormat::Float32x2,
+    },
+    // Color
+    wgpu::VertexAttribute {
+        offset: 32,
+        shader_location: 3,
+        format: wgpu::VertexFormat::Float32x3,
+    },
+];
+
+fn instance_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+    wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<FoliageInstanceGpu>() as u64,
+        step_mode: wgpu::VertexStepMode::Instance,
+        attributes: &INSTANCE_ATTRIBUTES,
+    }
+}
+
+/// Instanced mesh pipeline drawing into `format` (the scene or an impostor texture)
+fn create_mesh_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let vertex_buffer_layout = wgpu::VertexBufferLayout {
+        array_stride: 80, // GpuVertex size
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &VERTEX_ATTRIBUTES,
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[vertex_buffer_layout, instance_buffer_layout()],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back), // Enable backface culling to reduce flickering
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState {
+                constant: 1, // Small depth bias to prevent Z-fighting
+                slope_scale: 1.0,
+                clamp: 0.0,
+            },
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
 impl FoliageRenderer {
     /// Create a new foliage renderer
     pub fn new(
         device: &wgpu::Device,
         surface_format: wgpu::TextureFormat,
+        sample_count: u32,
     ) -> Result<Self> {
         // Create camera uniform buffer
-        let camera_uniforms = FoliageCameraUniforms {
-            view_proj: Mat4::IDENTITY.to_cols_array_2d(),
-            camera_pos: [0.0; 3],
-            _padding: 0.0,
-        };
+        let camera_uniforms =
+            FoliageCameraUniforms::new(Mat4::IDENTITY, Vec3::ZERO, &FoliageWind::default(), 0.0);
 
         let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Foliage Camera Uniform Buffer"),
@@ -115,118 +371,102 @@ impl FoliageRenderer {
             push_constant_ranges: &[],
         });
 
-        // Vertex buffer layout for mesh vertices
-        let vertex_buffer_layout = wgpu::VertexBufferLayout {
-            array_stride: 80, // GpuVertex size
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                // Position
-                wgpu::VertexAttribute {
-                    offset: 0,
-                    shader_location: 0,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-                // Normal
-                wgpu::VertexAttribute {
-                    offset: 12,
-                    shader_location: 1,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-                // TexCoord
-                wgpu::VertexAttribute {
-                    offset: 24,
-                    shader_location: 2,
-                    format: wgpu::VertexFormat::Float32x2,
-                },
-                // Color
-                wgpu::VertexAttribute {
-                    offset: 32,
-                    shader_location: 3,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-            ],
-        };
+        let pipeline = create_mesh_pipeline(
+            device,
+            "Foliage Render Pipeline",
+            &pipeline_layout,
+            &shader,
+            surface_format,
+            sample_count,
+        );
+        let bake_pipeline = create_mesh_pipeline(
+            device,
+            "Foliage Impostor Bake Pipeline",
+            &pipeline_layout,
+            &shader,
+            IMPOSTOR_FORMAT,
+            1,
+        );
 
-        // Instance buffer layout
-        let instance_buffer_layout = wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<FoliageInstanceGpu>() as u64,
-            step_mode: wgpu::VertexStepMode::Instance,
-            attributes: &[
-                // Model matrix column 0
-                wgpu::VertexAttribute {
-                    offset: 0,
-                    shader_location: 4,
-                    format: wgpu::VertexFormat::Float32x4,
-                },
-                // Model matrix column 1
-                wgpu::VertexAttribute {
-                    offset: 16,
-                    shader_location: 5,
-                    format: wgpu::VertexFormat::Float32x4,
-                },
-                // Model matrix column 2
-                wgpu::VertexAttribute {
-                    offset: 32,
-                    shader_location: 6,
-                    format: wgpu::VertexFormat::Float32x4,
-                },
-                // Model matrix column 3
-                wgpu::VertexAttribute {
-                    offset: 48,
-                    shader_location: 7,
-                    format: wgpu::VertexFormat::Float32x4,
-                },
-                // Color tint
-                wgpu::VertexAttribute {
-                    offset: 64,
-                    shader_location: 8,
-                    format: wgpu::VertexFormat::Float32x4,
-                },
-            ],
-        };
+        // Impostor billboards: extents, texture and sampler in group 1
+        let impostor_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Foliage Impostor Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
 
-        // Create render pipeline
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Foliage Render Pipeline"),
-            layout: Some(&pipeline_layout),
+        let impostor_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Foliage Impostor Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                include_str!("shaders/foliage_impostor.wgsl").into(),
+            ),
+        });
+        let impostor_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Foliage Impostor Pipeline Layout"),
+                bind_group_layouts: &[&camera_bind_group_layout, &impostor_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let impostor_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Foliage Impostor Pipeline"),
+            layout: Some(&impostor_pipeline_layout),
             vertex: wgpu::VertexState {
-                module: &shader,
+                module: &impostor_shader,
                 entry_point: Some("vs_main"),
-                buffers: &[vertex_buffer_layout, instance_buffer_layout],
+                buffers: &[instance_buffer_layout()],
                 compilation_options: Default::default(),
             },
             fragment: Some(wgpu::FragmentState {
-                module: &shader,
+                module: &impostor_shader,
                 entry_point: Some("fs_main"),
                 targets: &[Some(wgpu::ColorTargetState {
                     format: surface_format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    blend: None,
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
                 compilation_options: Default::default(),
             }),
             primitive: wgpu::PrimitiveState {
                 topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back), // Enable backface culling to reduce flickering
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
+                cull_mode: None,
+                ..Default::default()
             },
             depth_stencil: Some(wgpu::DepthStencilState {
                 format: wgpu::TextureFormat::Depth32Float,
                 depth_write_enabled: true,
                 depth_compare: wgpu::CompareFunction::Less,
                 stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState {
-                    constant: 1,  // Small depth bias to prevent Z-fighting
-                    slope_scale: 1.0,
-                    clamp: 0.0,
-                },
+                bias: wgpu::DepthBiasState::default(),
             }),
             multisample: wgpu::MultisampleState {
-                count: MSAA_SAMPLE_COUNT,
+                count: sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
@@ -234,32 +474,207 @@ impl FoliageRenderer {
             cache: None,
         });
 
+        let impostor_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Foliage Impostor Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
         Ok(Self {
             pipeline,
+            bake_pipeline,
+            impostor_pipeline,
             camera_buffer,
             camera_bind_group,
+            camera_bind_group_layout,
+            impostor_bind_group_layout,
+            impostor_sampler,
+            impostors: HashMap::new(),
             instance_buffer: None,
             instance_capacity: 0,
         })
     }
 
-    /// Update camera uniforms
+    /// Render `mesh` from the side into an impostor texture for billboards
+    /// drawn with `name`. The billboard turns about the vertical axis, so it
+    /// looks the same from every side.
+    pub fn bake_impostor(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        name: &str,
+        mesh: &GpuMesh,
+    ) {
+        let bounds = mesh.bounds;
+        let half_width = [bounds.min.x, bounds.max.x, bounds.min.z, bounds.max.z]
+            .iter()
+            .fold(0.01f32, |width, extent| width.max(extent.abs()));
+        let extents = ImpostorUniforms {
+            half_width,
+            bottom: bounds.min.y,
+            top: bounds.max.y.max(bounds.min.y + 0.01),
+            _padding: 0.0,
+        };
+
+        // Orthographic view along -Z that fits the billboard exactly
+        let eye = Vec3::new(0.0, 0.0, half_width + 1.0);
+        let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+        let projection = Mat4::orthographic_rh(
+            -half_width,
+            half_width,
+            extents.bottom,
+            extents.top,
+            0.0,
+            half_width * 2.0 + 2.0,
+        );
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Foliage Impostor Bake Camera"),
+            // Baked in still air
+            contents: bytemuck::cast_slice(&[FoliageCameraUniforms::new(
+                projection * view,
+                eye,
+                &FoliageWind::default(),
+                0.0,
+            )]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Foliage Impostor Bake Camera Bind Group"),
+            layout: &self.camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+        let instance = FoliageInstanceGpu::new(Vec3::ZERO, 0.0, 1.0, Vec3::ONE);
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Foliage Impostor Bake Instance"),
+            contents: bytemuck::cast_slice(&[instance]),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let size = wgpu::Extent3d {
+            width: IMPOSTOR_RESOLUTION,
+            height: IMPOSTOR_RESOLUTION,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(&format!("{} Impostor", name)),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: IMPOSTOR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let depth_view = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Foliage Impostor Bake Depth"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Depth32Float,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Foliage Impostor Bake"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Foliage Impostor Bake Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &texture_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        // Transparent around the silhouette
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.bake_pipeline);
+            pass.set_bind_group(0, &camera_bind_group, &[]);
+            pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            pass.set_vertex_buffer(1, instance_buffer.slice(..));
+            pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            pass.draw_indexed(0..mesh.num_indices, 0, 0..1);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Foliage Impostor Uniforms"),
+            contents: bytemuck::cast_slice(&[extents]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{} Impostor Bind Group", name)),
+            layout: &self.impostor_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.impostor_sampler),
+                },
+            ],
+        });
+        self.impostors.insert(
+            name.to_string(),
+            Impostor {
+                bind_group,
+                _texture: texture,
+                _uniform_buffer: uniform_buffer,
+            },
+        );
+    }
+
+    /// Whether an impostor has been baked for `name`
+    pub fn has_impostor(&self, name: &str) -> bool {
+        self.impostors.contains_key(name)
+    }
+
+    /// Update camera and wind uniforms. `time` is in seconds.
     pub fn update_camera(
         &self,
         queue: &wgpu::Queue,
         view_proj: Mat4,
         camera_pos: Vec3,
+        wind: &FoliageWind,
+        time: f32,
     ) {
-        let uniforms = FoliageCameraUniforms {
-            view_proj: view_proj.to_cols_array_2d(),
-            camera_pos: camera_pos.to_array(),
-            _padding: 0.0,
-        };
+        let uniforms = FoliageCameraUniforms::new(view_proj, camera_pos, wind, time);
 
         queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[uniforms]));
     }
 
-    /// Update instance buffer with new data
+    /// Update instance buffer with new data. Every draw of a frame reads
+    /// from the one buffer, so write all of the frame's instances at once.
     pub fn update_instances(
         &mut self,
         device: &wgpu::Device,
@@ -292,14 +707,14 @@ impl FoliageRenderer {
         }
     }
 
-    /// Render foliage instances with the given mesh
+    /// Render a range of the instance buffer with the given mesh
     pub fn render<'a>(
         &'a self,
         render_pass: &mut wgpu::RenderPass<'a>,
         mesh: &'a GpuMesh,
-        instance_count: u32,
+        instances: Range<u32>,
     ) {
-        if instance_count == 0 {
+        if instances.is_empty() {
             return;
         }
 
@@ -313,7 +728,33 @@ impl FoliageRenderer {
         render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
         render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
 
-        render_pass.draw_indexed(0..mesh.num_indices, 0, 0..instance_count);
+        render_pass.draw_indexed(0..mesh.num_indices, 0, instances);
+    }
+
+    /// Render a range of the instance buffer as impostor billboards of `name`
+    pub fn render_impostors<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        name: &str,
+        instances: Range<u32>,
+    ) {
+        if instances.is_empty() {
+            return;
+        }
+
+        let (Some(instance_buffer), Some(impostor)) =
+            (&self.instance_buffer, self.impostors.get(name))
+        else {
+            return;
+        };
+
+        render_pass.set_pipeline(&self.impostor_pipeline);
+        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &impostor.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, instance_buffer.slice(..));
+
+        // Two triangles per billboard, built in the vertex shader
+        render_pass.draw(0..6, instances);
     }
 }
 
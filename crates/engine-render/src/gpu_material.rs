@@ -165,4 +165,11 @@ impl GpuMaterial {
             ],
         })
     }
+    /// Every texture the material samples
+    pub fn textures(&self) -> impl Iterator<Item = TextureHandle> + '_ {
+        std::iter::once(self.albedo_texture)
+            .chain(self.normal_texture)
+            .chain(self.metallic_roughness_texture)
+            .chain(self.ao_texture)
+    }
 }
@@ -1,8 +1,11 @@
 // GPU-side mesh representation
 
 use anyhow::Result;
+use glam::Vec3;
 use wgpu::util::DeviceExt;
 
+use crate::frustum::AABB;
+
 /// Vertex format for GPU rendering
 /// Total size: 80 bytes (aligned to 16 bytes)
 #[repr(C)]
@@ -69,6 +72,8 @@ pub struct GpuMesh {
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
     pub num_indices: u32,
+    /// Local-space bounds of the vertices (for frustum culling)
+    pub bounds: AABB,
 }
 
 impl GpuMesh {
@@ -90,10 +95,23 @@ impl GpuMesh {
             usage: wgpu::BufferUsages::INDEX,
         });
 
+        let bounds = if vertices.is_empty() {
+            AABB::new(Vec3::ZERO, Vec3::ZERO)
+        } else {
+            vertices.iter().fold(
+                AABB::new(Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+                |bounds, vertex| {
+                    let position = Vec3::from(vertex.position);
+                    AABB::new(bounds.min.min(position), bounds.max.max(position))
+                },
+            )
+        };
+
         Self {
             vertex_buffer,
             index_buffer,
             num_indices: indices.len() as u32,
+            bounds,
         }
     }
 }
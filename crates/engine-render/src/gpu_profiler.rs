@@ -0,0 +1,226 @@
+// GPU profiler - pass timings from timestamp queries
+//
+// A timestamp is written after each group of passes; a section's duration is
+// the gap between its timestamp and the previous one. Results are read back
+// asynchronously and show up a frame or two after they were recorded. On
+// devices without timestamp support every call is a no-op.
+
+use std::sync::{Arc, Mutex};
+
+/// Most timestamps written per frame (including the frame start)
+const MAX_TIMESTAMPS: u32 = 32;
+
+/// Timing of one GPU section
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GpuTiming {
+    pub label: &'static str,
+    /// Offset from the first timestamp of the frame
+    pub start_ms: f32,
+    pub duration_ms: f32,
+}
+
+struct TimestampQueries {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    /// Nanoseconds per timestamp tick
+    period_ns: f32,
+}
+
+/// Readback of a submitted frame: its labels and the map_async result
+type PendingReadback = (Vec<&'static str>, Arc<Mutex<Option<bool>>>);
+
+pub struct GpuProfiler {
+    queries: Option<TimestampQueries>,
+    /// Labels of the timestamps written this frame (index 0 is the frame start)
+    labels: Vec<&'static str>,
+    pending: Option<PendingReadback>,
+    timings: Vec<GpuTiming>,
+}
+
+impl GpuProfiler {
+    /// Device features needed for timestamps between passes
+    pub fn features() -> wgpu::Features {
+        wgpu::Features::TIMESTAMP_QUERY | wgpu::Features::TIMESTAMP_QUERY_INSIDE_ENCODERS
+    }
+
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let queries = device.features().contains(Self::features()).then(|| {
+            let size = MAX_TIMESTAMPS as u64 * std::mem::size_of::<u64>() as u64;
+            TimestampQueries {
+                query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                    label: Some("GPU Profiler Queries"),
+                    ty: wgpu::QueryType::Timestamp,
+                    count: MAX_TIMESTAMPS,
+                }),
+                resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("GPU Profiler Resolve Buffer"),
+                    size,
+                    usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                    mapped_at_creation: false,
+                }),
+                readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("GPU Profiler Readback Buffer"),
+                    size,
+                    usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+                period_ns: queue.get_timestamp_period(),
+            }
+        });
+        if queries.is_none() {
+            log::info!("GPU timestamp queries not supported, GPU profiling disabled");
+        }
+
+        Self {
+            queries,
+            labels: Vec::new(),
+            pending: None,
+            timings: Vec::new(),
+        }
+    }
+
+    /// True if the device supports timestamp queries
+    pub fn is_supported(&self) -> bool {
+        self.queries.is_some()
+    }
+
+    /// Start timing a frame (skipped while the previous readback is in flight)
+    pub fn begin_frame(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        self.labels.clear();
+        let Some(queries) = &self.queries else {
+            return;
+        };
+        if self.pending.is_some() {
+            return;
+        }
+        encoder.write_timestamp(&queries.query_set, 0);
+        self.labels.push("Frame Start");
+    }
+
+    /// End the current section (everything since the previous timestamp) as `label`
+    pub fn mark(&mut self, encoder: &mut wgpu::CommandEncoder, label: &'static str) {
+        let Some(queries) = &self.queries else {
+            return;
+        };
+        let index = self.labels.len() as u32;
+        if index == 0 || index >= MAX_TIMESTAMPS {
+            return;
+        }
+        encoder.write_timestamp(&queries.query_set, index);
+        self.labels.push(label);
+    }
+
+    /// Copy this frame's timestamps to the readback buffer (before finishing the encoder)
+    pub fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let Some(queries) = &self.queries else {
+            return;
+        };
+        if self.labels.len() < 2 {
+            self.labels.clear();
+            return;
+        }
+        let count = self.labels.len() as u32;
+        encoder.resolve_query_set(&queries.query_set, 0..count, &queries.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &queries.resolve_buffer,
+            0,
+            &queries.readback_buffer,
+            0,
+            count as u64 * std::mem::size_of::<u64>() as u64,
+        );
+    }
+
+    /// Start reading back the frame's timestamps (after the encoder was submitted)
+    pub fn after_submit(&mut self) {
+        let Some(queries) = &self.queries else {
+            return;
+        };
+        if self.labels.len() < 2 {
+            return;
+        }
+        let status = Arc::new(Mutex::new(None));
+        let callback_status = status.clone();
+        let size = self.labels.len() as u64 * std::mem::size_of::<u64>() as u64;
+        queries
+            .readback_buffer
+            .slice(..size)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                *callback_status.lock().unwrap() = Some(result.is_ok());
+            });
+        self.pending = Some((std::mem::take(&mut self.labels), status));
+    }
+
+    /// Pick up a finished readback, if any
+    pub fn poll(&mut self, device: &wgpu::Device) {
+        let Some(queries) = &self.queries else {
+            return;
+        };
+        let Some((_, status)) = &self.pending else {
+            return;
+        };
+        let _ = device.poll(wgpu::PollType::Poll);
+        let Some(mapped) = *status.lock().unwrap() else {
+            return;
+        };
+        let Some((labels, _)) = self.pending.take() else {
+            return;
+        };
+        if mapped {
+            let size = labels.len() as u64 * std::mem::size_of::<u64>() as u64;
+            {
+                let data = queries.readback_buffer.slice(..size).get_mapped_range();
+                let ticks: &[u64] = bytemuck::cast_slice(&data);
+                self.timings = timings_from_ticks(&labels, ticks, queries.period_ns);
+            }
+            queries.readback_buffer.unmap();
+        }
+    }
+
+    /// Most recent section timings read back from the GPU
+    pub fn timings(&self) -> &[GpuTiming] {
+        &self.timings
+    }
+}
+
+/// Turn raw timestamps into section timings. `labels[i]` names the section
+/// ending at `ticks[i]`; `labels[0]` is the frame start.
+fn timings_from_ticks(labels: &[&'static str], ticks: &[u64], period_ns: f32) -> Vec<GpuTiming> {
+    let Some(&frame_start) = ticks.first() else {
+        return Vec::new();
+    };
+    let to_ms = |ticks: u64| ticks as f32 * period_ns / 1_000_000.0;
+    (1..labels.len().min(ticks.len()))
+        .map(|i| GpuTiming {
+            label: labels[i],
+            start_ms: to_ms(ticks[i - 1].saturating_sub(frame_start)),
+            duration_ms: to_ms(ticks[i].saturating_sub(ticks[i - 1])),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timings_from_ticks() {
+        let labels = ["Frame Start", "Shadows", "Meshes"];
+        // 1 tick = 1000 ns, so 1000 ticks = 1 ms
+        let timings = timings_from_ticks(&labels, &[5_000, 7_000, 10_000], 1000.0);
+        assert_eq!(timings.len(), 2);
+        assert_eq!(timings[0].label, "Shadows");
+        assert_eq!(timings[0].start_ms, 0.0);
+        assert_eq!(timings[0].duration_ms, 2.0);
+        assert_eq!(timings[1].label, "Meshes");
+        assert_eq!(timings[1].start_ms, 2.0);
+        assert_eq!(timings[1].duration_ms, 3.0);
+    }
+
+    #[test]
+    fn test_timings_ignore_out_of_order_ticks() {
+        let timings = timings_from_ticks(&["Frame Start", "Skybox"], &[100, 50], 1.0);
+        assert_eq!(timings[0].duration_ms, 0.0);
+        assert!(timings_from_ticks(&["Frame Start"], &[], 1.0).is_empty());
+    }
+}
@@ -32,7 +32,22 @@
 //! render_pass.set_bind_group(1, &gpu_texture.bind_group, &[]);
 //! ```
 
-use engine_assets::Texture;
+use engine_assets::{CompressedFormat, CompressedTexture, MipLevel, Texture};
+use wgpu::util::DeviceExt;
+
+/// GPU format for a block-compressed texture
+pub(crate) fn compressed_texture_format(format: CompressedFormat) -> wgpu::TextureFormat {
+    match format {
+        CompressedFormat::Bc7 => wgpu::TextureFormat::Bc7RgbaUnormSrgb,
+        // Normal map X and Y are linear
+        CompressedFormat::Bc5 => wgpu::TextureFormat::Bc5RgUnorm,
+        CompressedFormat::Bc4 => wgpu::TextureFormat::Bc4RUnorm,
+        CompressedFormat::Astc4x4 => wgpu::TextureFormat::Astc {
+            block: wgpu::AstcBlock::B4x4,
+            channel: wgpu::AstcChannel::UnormSrgb,
+        },
+    }
+}
 
 /// Handle to a GPU texture.
 ///
@@ -237,10 +252,66 @@ impl GpuTexture {
             current_height = new_height;
         }
 
+        Self::with_bindings(device, gpu_texture, &texture.name, bind_group_layout)
+    }
+
+    /// Upload a block-compressed texture with its mip chain. The device needs
+    /// the matching compression feature (see `TextureManager::compressed_format`).
+    pub fn from_compressed(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &CompressedTexture,
+        bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let format = compressed_texture_format(texture.format);
+        Self::from_mip_chain(device, queue, &texture.name, format, &texture.levels, bind_group_layout)
+    }
+
+    /// Upload prepared mip levels, largest first, each half the one before
+    /// down to 1x1
+    pub fn from_mip_chain(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        name: &str,
+        format: wgpu::TextureFormat,
+        levels: &[MipLevel],
+        bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let data: Vec<u8> = levels.iter().flat_map(|level| level.data.iter().copied()).collect();
+        let gpu_texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some(name),
+                size: wgpu::Extent3d {
+                    width: levels.first().map_or(1, |level| level.width),
+                    height: levels.first().map_or(1, |level| level.height),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: levels.len().max(1) as u32,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            &data,
+        );
+        Self::with_bindings(device, gpu_texture, name, bind_group_layout)
+    }
+
+    /// View, repeating trilinear sampler and bind group for an uploaded texture
+    fn with_bindings(
+        device: &wgpu::Device,
+        gpu_texture: wgpu::Texture,
+        name: &str,
+        bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let mip_level_count = gpu_texture.mip_level_count();
         let view = gpu_texture.create_view(&wgpu::TextureViewDescriptor::default());
 
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            label: Some(&format!("{} Sampler", texture.name)),
+            label: Some(&format!("{} Sampler", name)),
             address_mode_u: wgpu::AddressMode::Repeat,
             address_mode_v: wgpu::AddressMode::Repeat,
             address_mode_w: wgpu::AddressMode::Repeat,
@@ -255,7 +326,7 @@ impl GpuTexture {
         });
 
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some(&format!("{} Bind Group", texture.name)),
+            label: Some(&format!("{} Bind Group", name)),
             layout: bind_group_layout,
             entries: &[
                 wgpu::BindGroupEntry {
@@ -0,0 +1,644 @@
+// Grass Renderer - blades grown on the GPU from a painted density map
+//
+// Separate from the instanced foliage path: no blade is stored on the CPU.
+// Each frame a compute pass fills a grid of cells around the camera with
+// blades, keeping those the density map allows that are in view and within
+// the draw distance, and writes the count into indirect draw arguments. The
+// render pass then draws every kept blade with one indirect draw.
+
+use crate::foliage_renderer::FoliageWind;
+use crate::frustum::Frustum;
+use anyhow::Result;
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec2, Vec3};
+use wgpu::util::DeviceExt;
+
+/// Most blades drawn in a frame
+pub const MAX_GRASS_BLADES: u32 = 262_144;
+/// Most grid cells per side; sparser cells are used past this
+const MAX_GRID_CELLS: u32 = 1024;
+/// Vertices per blade (two quads and the tip triangle)
+const BLADE_VERTICES: u32 = 15;
+const WORKGROUP_SIZE: u32 = 8;
+
+/// How grass looks and where it stops being drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GrassSettings {
+    /// Blades per square meter at full coverage
+    pub blades_per_m2: f32,
+    pub blade_height: f32,
+    pub blade_width: f32,
+    pub base_color: Vec3,
+    pub tip_color: Vec3,
+    pub draw_distance: f32,
+    /// Distance over which blades shrink away before `draw_distance`
+    pub fade_width: f32,
+    /// Resistance to bending in the wind
+    pub stiffness: f32,
+}
+
+impl Default for GrassSettings {
+    fn default() -> Self {
+        Self {
+            blades_per_m2: 16.0,
+            blade_height: 0.6,
+            blade_width: 0.05,
+            base_color: Vec3::new(0.13, 0.3, 0.06),
+            tip_color: Vec3::new(0.45, 0.62, 0.2),
+            draw_distance: 60.0,
+            fade_width: 15.0,
+            stiffness: 0.3,
+        }
+    }
+}
+
+/// Grid of cells the compute pass fills around the camera, one blade per cell
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GrassGrid {
+    /// World cell index of the grid corner
+    pub first_cell: [i32; 2],
+    pub cells_per_side: u32,
+    pub cell_size: f32,
+}
+
+impl GrassGrid {
+    /// Cells covering the draw distance around the camera. Cells are fixed in
+    /// the world so the same blades grow as the camera moves.
+    pub fn around(camera_pos: Vec3, settings: &GrassSettings) -> Self {
+        let diameter = settings.draw_distance.max(0.0) * 2.0;
+        let cell_size =
+            (1.0 / settings.blades_per_m2.max(0.01).sqrt()).max(diameter / MAX_GRID_CELLS as f32);
+        let cells_per_side = ((diameter / cell_size).ceil() as u32 + 1).min(MAX_GRID_CELLS + 1);
+        let center = Vec2::new(camera_pos.x, camera_pos.z) / cell_size;
+        let half = (cells_per_side / 2) as i32;
+        Self {
+            first_cell: [
+                center.x.floor() as i32 - half,
+                center.y.floor() as i32 - half,
+            ],
+            cells_per_side,
+            cell_size,
+        }
+    }
+}
+
+/// A blade written by the compute pass (matches `Blade` in grass_generate.wgsl)
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct GrassBladeGpu {
+    position: [f32; 3],
+    facing: f32,
+    height: f32,
+    width: f32,
+    shade: f32,
+    lean: f32,
+}
+
+/// Uniforms shared by the generation and render passes
+#[repr(C, align(16))]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct GrassUniforms {
+    view_proj: [[f32; 4]; 4],
+    camera_pos: [f32; 3],
+    time: f32,
+    /// Wind direction (x, z), strength and gust strength
+    wind: [f32; 4],
+    /// Gust frequency and scale
+    gust: [f32; 4],
+    /// Frustum planes as (normal, distance)
+    planes: [[f32; 4]; 6],
+    /// First cell x and z, cells per side, cell size
+    grid: [f32; 4],
+    /// Density map size, terrain scale, max blades
+    area: [f32; 4],
+    /// Blade height and width, draw distance, fade width
+    blade: [f32; 4],
+    /// Base color and stiffness
+    base_color: [f32; 4],
+    tip_color: [f32; 4],
+}
+
+/// GPU grass renderer
+pub struct GrassRenderer {
+    generate_pipeline: wgpu::ComputePipeline,
+    render_pipeline: wgpu::RenderPipeline,
+    generate_bind_group_layout: wgpu::BindGroupLayout,
+    generate_bind_group: wgpu::BindGroup,
+    render_bind_group: wgpu::BindGroup,
+    uniform_buffer: wgpu::Buffer,
+    /// Blades written by the compute pass, read as instances
+    blade_buffer: wgpu::Buffer,
+    /// Indirect draw arguments; the compute pass counts the instances
+    draw_args_buffer: wgpu::Buffer,
+    density_texture: wgpu::Texture,
+    terrain_texture: wgpu::Texture,
+    /// Generation of the last uploaded density map, to skip unchanged uploads
+    density_generation: Option<u64>,
+    density_size: f32,
+    terrain_scale: f32,
+    grid: GrassGrid,
+}
+
+/// Blade attributes read per instance by grass.wgsl
+const BLADE_ATTRIBUTES: [wgpu::VertexAttribute; 2] = [
+    // Position and facing
+    wgpu::VertexAttribute {
+        offset: 0,
+        shader_location: 0,
+        format: wgpu::VertexFormat::Float32x4,
+    },
+    // Height, width, shade, lean
+    wgpu::VertexAttribute {
+        offset: 16,
+        shader_location: 1,
+        format: wgpu::VertexFormat::Float32x4,
+    },
+];
+
+fn create_map_texture(
+    device: &wgpu::Device,
+    label: &str,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    })
+}
+
+impl GrassRenderer {
+    /// Create a grass renderer with no grass painted and flat ground
+    pub fn new(
+        device: &wgpu::Device,
+        surface_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Result<Self> {
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Grass Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[GrassUniforms::zeroed()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let blade_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Grass Blade Buffer"),
+            size: MAX_GRASS_BLADES as u64 * std::mem::size_of::<GrassBladeGpu>() as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+            mapped_at_creation: false,
+        });
+        let draw_args_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Grass Draw Args Buffer"),
+            contents: bytemuck::cast_slice(&[BLADE_VERTICES, 0, 0, 0]),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::INDIRECT
+                | wgpu::BufferUsages::COPY_DST,
+        });
+        let density_texture = create_map_texture(
+            device,
+            "Grass Density Map",
+            1,
+            1,
+            wgpu::TextureFormat::R8Unorm,
+        );
+        let terrain_texture = create_map_texture(
+            device,
+            "Grass Terrain Map",
+            1,
+            1,
+            wgpu::TextureFormat::Rg32Float,
+        );
+
+        let map_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let storage_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let uniform_entry = |visibility| wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let generate_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Grass Generate Bind Group Layout"),
+                entries: &[
+                    uniform_entry(wgpu::ShaderStages::COMPUTE),
+                    map_entry(1),
+                    map_entry(2),
+                    storage_entry(3),
+                    storage_entry(4),
+                ],
+            });
+        let render_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Grass Render Bind Group Layout"),
+                entries: &[uniform_entry(wgpu::ShaderStages::VERTEX)],
+            });
+        let render_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Grass Render Bind Group"),
+            layout: &render_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let generate_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Grass Generate Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/grass_generate.wgsl").into()),
+        });
+        let generate_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Grass Generate Pipeline Layout"),
+            bind_group_layouts: &[&generate_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let generate_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Grass Generate Pipeline"),
+            layout: Some(&generate_layout),
+            module: &generate_shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Grass Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/grass.wgsl").into()),
+        });
+        let render_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Grass Pipeline Layout"),
+            bind_group_layouts: &[&render_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Grass Pipeline"),
+            layout: Some(&render_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<GrassBladeGpu>() as u64,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &BLADE_ATTRIBUTES,
+                }],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: surface_format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                // Blades are seen from both sides
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        let generate_bind_group = Self::create_generate_bind_group(
+            device,
+            &generate_bind_group_layout,
+            &uniform_buffer,
+            &density_texture,
+            &terrain_texture,
+            &blade_buffer,
+            &draw_args_buffer,
+        );
+
+        Ok(Self {
+            generate_pipeline,
+            render_pipeline,
+            generate_bind_group_layout,
+            generate_bind_group,
+            render_bind_group,
+            uniform_buffer,
+            blade_buffer,
+            draw_args_buffer,
+            density_texture,
+            terrain_texture,
+            density_generation: None,
+            density_size: 1.0,
+            terrain_scale: 1.0,
+            grid: GrassGrid {
+                first_cell: [0, 0],
+                cells_per_side: 0,
+                cell_size: 1.0,
+            },
+        })
+    }
+
+    fn create_generate_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        uniform_buffer: &wgpu::Buffer,
+        density_texture: &wgpu::Texture,
+        terrain_texture: &wgpu::Texture,
+        blade_buffer: &wgpu::Buffer,
+        draw_args_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        let density_view = density_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let terrain_view = terrain_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Grass Generate Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&density_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&terrain_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: blade_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: draw_args_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    fn rebuild_bind_group(&mut self, device: &wgpu::Device) {
+        self.generate_bind_group = Self::create_generate_bind_group(
+            device,
+            &self.generate_bind_group_layout,
+            &self.uniform_buffer,
+            &self.density_texture,
+            &self.terrain_texture,
+            &self.blade_buffer,
+            &self.draw_args_buffer,
+        );
+    }
+
+    /// Upload the density map: `resolution` x `resolution` coverage bytes
+    /// over a square of world `size` centered on the origin. `generation`
+    /// identifies the map; nothing is uploaded if it was the last one.
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_density(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        density: &[u8],
+        resolution: usize,
+        size: f32,
+        generation: u64,
+    ) {
+        self.density_size = size.max(0.01);
+        if resolution == 0 || density.len() != resolution * resolution || self.density_generation == Some(generation) {
+            return;
+        }
+
+        let extent = self.density_texture.size();
+        if extent.width != resolution as u32 || extent.height != resolution as u32 {
+            self.density_texture = create_map_texture(
+                device,
+                "Grass Density Map",
+                resolution as u32,
+                resolution as u32,
+                wgpu::TextureFormat::R8Unorm,
+            );
+            self.rebuild_bind_group(device);
+        }
+        queue.write_texture(
+            self.density_texture.as_image_copy(),
+            density,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(resolution as u32),
+                rows_per_image: Some(resolution as u32),
+            },
+            self.density_texture.size(),
+        );
+        self.density_generation = Some(generation);
+    }
+
+    /// Upload the terrain the grass grows on: a `width` x `depth` heightmap
+    /// spanning `scale` world units, with its cut out cells (may be empty).
+    /// Call it when the terrain changes.
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_terrain(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        heights: &[f32],
+        holes: &[bool],
+        width: usize,
+        depth: usize,
+        scale: f32,
+    ) {
+        if width == 0 || depth == 0 || heights.len() != width * depth {
+            return;
+        }
+        self.terrain_scale = scale.max(0.01);
+        let extent = self.terrain_texture.size();
+        let resized = extent.width != width as u32 || extent.height != depth as u32;
+
+        let texels: Vec<[f32; 2]> = heights
+            .iter()
+            .enumerate()
+            .map(|(i, &height)| {
+                let hole = holes.get(i).copied().unwrap_or(false);
+                [height, if hole { 1.0 } else { 0.0 }]
+            })
+            .collect();
+
+        if resized {
+            self.terrain_texture = create_map_texture(
+                device,
+                "Grass Terrain Map",
+                width as u32,
+                depth as u32,
+                wgpu::TextureFormat::Rg32Float,
+            );
+            self.rebuild_bind_group(device);
+        }
+        queue.write_texture(
+            self.terrain_texture.as_image_copy(),
+            bytemuck::cast_slice(&texels),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(width as u32 * 8),
+                rows_per_image: Some(depth as u32),
+            },
+            self.terrain_texture.size(),
+        );
+    }
+
+    /// Update the camera, wind and blade settings for this frame and reset
+    /// the blade count. `time` is in seconds.
+    pub fn update(
+        &mut self,
+        queue: &wgpu::Queue,
+        view_proj: Mat4,
+        camera_pos: Vec3,
+        wind: &FoliageWind,
+        time: f32,
+        settings: &GrassSettings,
+    ) {
+        self.grid = GrassGrid::around(camera_pos, settings);
+
+        let frustum = Frustum::from_view_projection(view_proj);
+        let planes = [
+            frustum.left,
+            frustum.right,
+            frustum.bottom,
+            frustum.top,
+            frustum.near,
+            frustum.far,
+        ]
+        .map(|plane| plane.normal.extend(plane.distance).to_array());
+        let direction = wind.direction.normalize_or(Vec2::X);
+
+        let uniforms = GrassUniforms {
+            view_proj: view_proj.to_cols_array_2d(),
+            camera_pos: camera_pos.to_array(),
+            time,
+            wind: [direction.x, direction.y, wind.strength, wind.gust_strength],
+            gust: [wind.gust_frequency, wind.gust_scale.max(0.01), 0.0, 0.0],
+            planes,
+            grid: [
+                self.grid.first_cell[0] as f32,
+                self.grid.first_cell[1] as f32,
+                self.grid.cells_per_side as f32,
+                self.grid.cell_size,
+            ],
+            area: [
+                self.density_size,
+                self.terrain_scale,
+                MAX_GRASS_BLADES as f32,
+                0.0,
+            ],
+            blade: [
+                settings.blade_height,
+                settings.blade_width,
+                settings.draw_distance,
+                settings.fade_width,
+            ],
+            base_color: settings.base_color.extend(settings.stiffness).to_array(),
+            tip_color: settings.tip_color.extend(1.0).to_array(),
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+        queue.write_buffer(
+            &self.draw_args_buffer,
+            0,
+            bytemuck::cast_slice(&[BLADE_VERTICES, 0, 0, 0]),
+        );
+    }
+
+    /// Grow this frame's blades. Record before the pass that draws them.
+    pub fn generate(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.grid.cells_per_side == 0 {
+            return;
+        }
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Grass Generate Pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.generate_pipeline);
+        compute_pass.set_bind_group(0, &self.generate_bind_group, &[]);
+        let groups = self.grid.cells_per_side.div_ceil(WORKGROUP_SIZE);
+        compute_pass.dispatch_workgroups(groups, groups, 1);
+    }
+
+    /// Draw the blades grown by `generate`
+    pub fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.render_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.blade_buffer.slice(..));
+        render_pass.draw_indirect(&self.draw_args_buffer, 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_covers_draw_distance() {
+        let settings = GrassSettings {
+            blades_per_m2: 4.0,
+            draw_distance: 10.0,
+            ..Default::default()
+        };
+        let grid = GrassGrid::around(Vec3::new(3.1, 5.0, -7.9), &settings);
+        assert_eq!(grid.cell_size, 0.5);
+        let min = Vec2::from(grid.first_cell.map(|c| c as f32)) * grid.cell_size;
+        let max = min + grid.cells_per_side as f32 * grid.cell_size;
+        assert!(min.x <= 3.1 - 10.0 && max.x >= 3.1 + 10.0);
+        assert!(min.y <= -7.9 - 10.0 && max.y >= -7.9 + 10.0);
+
+        // Dense grass over a long distance gets sparser cells, not more of them
+        let far = GrassSettings {
+            blades_per_m2: 100.0,
+            draw_distance: 500.0,
+            ..Default::default()
+        };
+        let grid = GrassGrid::around(Vec3::ZERO, &far);
+        assert!(grid.cells_per_side <= MAX_GRID_CELLS + 1);
+        assert!(grid.cells_per_side as f32 * grid.cell_size >= 1000.0);
+    }
+}
@@ -0,0 +1,160 @@
+// Editor grid - infinite reference grid on the ground plane
+
+use anyhow::Result;
+use glam::{Mat4, Vec3};
+use wgpu::util::DeviceExt;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GridUniforms {
+    pub view_proj: [[f32; 4]; 4],         // 64 bytes @ 0
+    pub view_proj_inverse: [[f32; 4]; 4], // 64 bytes @ 64
+    pub camera_pos: [f32; 3],             // 12 bytes @ 128
+    pub cell_size: f32,                   // 4 bytes @ 140
+    pub fade_distance: f32,               // 4 bytes @ 144
+    pub major_every: f32,                 // 4 bytes @ 148
+    pub _padding: [f32; 2],               // 8 bytes @ 152, total 160
+}
+
+/// Grid renderer (fullscreen pass, alpha blended, depth tested against the scene)
+pub struct GridRenderer {
+    pub render_pipeline: wgpu::RenderPipeline,
+    pub uniform_buffer: wgpu::Buffer,
+    pub uniform_bind_group: wgpu::BindGroup,
+}
+
+impl GridRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        surface_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Result<Self> {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Grid Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/grid.wgsl").into()),
+        });
+
+        let uniforms = GridUniforms {
+            view_proj: Mat4::IDENTITY.to_cols_array_2d(),
+            view_proj_inverse: Mat4::IDENTITY.to_cols_array_2d(),
+            camera_pos: [0.0; 3],
+            cell_size: 1.0,
+            fade_distance: 100.0,
+            major_every: 10.0,
+            _padding: [0.0; 2],
+        };
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Grid Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[uniforms]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Grid Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Grid Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Grid Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Grid Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: surface_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: false, // Grid never occludes scene geometry
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        Ok(Self {
+            render_pipeline,
+            uniform_buffer,
+            uniform_bind_group,
+        })
+    }
+
+    /// Update camera matrices and grid spacing
+    pub fn update_uniforms(
+        &self,
+        queue: &wgpu::Queue,
+        view_proj: Mat4,
+        camera_pos: Vec3,
+        cell_size: f32,
+        fade_distance: f32,
+    ) {
+        let uniforms = GridUniforms {
+            view_proj: view_proj.to_cols_array_2d(),
+            view_proj_inverse: view_proj.inverse().to_cols_array_2d(),
+            camera_pos: camera_pos.to_array(),
+            cell_size: cell_size.max(0.001),
+            fade_distance,
+            major_every: 10.0,
+            _padding: [0.0; 2],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+    }
+
+    /// Draw the grid into an existing render pass
+    pub fn render(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+        render_pass.draw(0..3, 0..1); // Fullscreen triangle
+    }
+}
@@ -0,0 +1,557 @@
+// Image based lighting - ambient light and reflections from the sky
+//
+// The skybox cubemap is convolved on the CPU into:
+// - an irradiance cubemap (cosine-weighted average of the sky around each
+//   normal) for diffuse ambient light
+// - a pre-filtered specular cubemap whose mips blur the sky for rising
+//   roughness (GGX importance sampling)
+// - a BRDF lookup table (scale and bias applied to F0 by NdotV and
+//   roughness), the same for every sky
+// The maps are small, but the convolution still takes longer than a frame,
+// so a sky that keeps changing is convolved on a worker thread (ConvolvedSky)
+// and only uploaded here.
+
+use glam::{Vec2, Vec3};
+
+/// Face size of the irradiance cubemap
+pub const IRRADIANCE_SIZE: u32 = 16;
+/// Face size of the pre-filtered cubemap's first mip
+pub const PREFILTER_SIZE: u32 = 64;
+/// Mips of the pre-filtered cubemap, roughness 0 to 1
+pub const PREFILTER_MIPS: u32 = 5;
+/// Width and height of the BRDF lookup table
+pub const BRDF_LUT_SIZE: u32 = 64;
+/// Radiance of the flat environment used until a sky is loaded
+pub const DEFAULT_AMBIENT: f32 = 0.03;
+
+const PREFILTER_SAMPLES: u32 = 64;
+const BRDF_SAMPLES: u32 = 128;
+
+/// Linear radiance cubemap on the CPU. Faces are +X, -X, +Y, -Y, +Z, -Z,
+/// rows top to bottom, as wgpu samples them.
+#[derive(Debug, Clone)]
+pub struct EnvironmentMap {
+    pub size: u32,
+    pub faces: [Vec<Vec3>; 6],
+}
+
+impl EnvironmentMap {
+    /// Fill each texel with the radiance in its direction
+    pub fn from_fn(size: u32, radiance: impl Fn(Vec3) -> Vec3) -> Self {
+        let faces = std::array::from_fn(|face| {
+            (0..size * size)
+                .map(|i| radiance(texel_direction(face, i % size, i / size, size)))
+                .collect()
+        });
+        Self { size, faces }
+    }
+
+    /// The same radiance in every direction
+    pub fn uniform(radiance: Vec3) -> Self {
+        Self::from_fn(1, |_| radiance)
+    }
+
+    /// Decode sRGB RGBA8 faces of `size` pixels, box-filtered down to `out_size`
+    pub fn from_rgba8_srgb(faces: &[&[u8]], size: u32, out_size: u32) -> Self {
+        let lut: Vec<f32> = (0..256).map(|v| srgb_to_linear(v as f32 / 255.0)).collect();
+        let block = (size / out_size.max(1)).max(1);
+        let out_size = size / block;
+        let faces = std::array::from_fn(|face| {
+            let data = faces.get(face).copied().unwrap_or(&[]);
+            (0..out_size * out_size)
+                .map(|i| {
+                    let (bx, by) = ((i % out_size) * block, (i / out_size) * block);
+                    let mut sum = Vec3::ZERO;
+                    for y in by..by + block {
+                        for x in bx..bx + block {
+                            let idx = ((y * size + x) * 4) as usize;
+                            if let Some(p) = data.get(idx..idx + 3) {
+                                sum += Vec3::new(
+                                    lut[p[0] as usize],
+                                    lut[p[1] as usize],
+                                    lut[p[2] as usize],
+                                );
+                            }
+                        }
+                    }
+                    sum / (block * block) as f32
+                })
+                .collect()
+        });
+        Self {
+            size: out_size,
+            faces,
+        }
+    }
+
+    /// Radiance in a direction (nearest texel)
+    pub fn sample(&self, direction: Vec3) -> Vec3 {
+        let (face, uv) = direction_texel(direction);
+        let x = ((uv.x * self.size as f32) as u32).min(self.size - 1);
+        let y = ((uv.y * self.size as f32) as u32).min(self.size - 1);
+        self.faces[face][(y * self.size + x) as usize]
+    }
+
+    /// The map at another face size: box-filtered when shrinking by a whole
+    /// factor, point-sampled otherwise
+    pub fn resized(&self, size: u32) -> Self {
+        if size < self.size && self.size.is_multiple_of(size) {
+            let block = self.size / size;
+            let faces = std::array::from_fn(|face| {
+                (0..size * size)
+                    .map(|i| {
+                        let (bx, by) = ((i % size) * block, (i / size) * block);
+                        let mut sum = Vec3::ZERO;
+                        for y in by..by + block {
+                            for x in bx..bx + block {
+                                sum += self.faces[face][(y * self.size + x) as usize];
+                            }
+                        }
+                        sum / (block * block) as f32
+                    })
+                    .collect()
+            });
+            Self { size, faces }
+        } else {
+            Self::from_fn(size, |direction| self.sample(direction))
+        }
+    }
+
+    /// Diffuse irradiance around each normal, divided by pi so a uniform
+    /// sky gives back its own radiance
+    pub fn irradiance(&self, size: u32) -> Self {
+        // Every source texel with its direction and solid angle
+        let texels: Vec<(Vec3, Vec3)> = (0..6)
+            .flat_map(|face| {
+                (0..self.size * self.size).map(move |i| {
+                    let (x, y) = (i % self.size, i / self.size);
+                    let weight = texel_solid_angle(x, y, self.size);
+                    (
+                        texel_direction(face, x, y, self.size),
+                        self.faces[face][i as usize] * weight,
+                    )
+                })
+            })
+            .collect();
+        Self::from_fn(size, |normal| {
+            let sum = texels
+                .iter()
+                .map(|(direction, radiance)| *radiance * normal.dot(*direction).max(0.0))
+                .fold(Vec3::ZERO, |a, b| a + b);
+            sum / std::f32::consts::PI
+        })
+    }
+
+    /// Specular reflection at `roughness`, integrated over a GGX lobe
+    /// around each direction (viewed straight on)
+    pub fn prefiltered(&self, size: u32, roughness: f32) -> Self {
+        if roughness <= 0.0 {
+            return self.resized(size);
+        }
+        Self::from_fn(size, |normal| {
+            let mut sum = Vec3::ZERO;
+            let mut weight = 0.0;
+            for i in 0..PREFILTER_SAMPLES {
+                let h = importance_sample_ggx(hammersley(i, PREFILTER_SAMPLES), normal, roughness);
+                let l = 2.0 * normal.dot(h) * h - normal;
+                let n_dot_l = normal.dot(l);
+                if n_dot_l > 0.0 {
+                    sum += self.sample(l) * n_dot_l;
+                    weight += n_dot_l;
+                }
+            }
+            sum / weight.max(f32::EPSILON)
+        })
+    }
+}
+
+/// Split-sum BRDF table: row by roughness, column by NdotV, each the
+/// (scale, bias) applied to F0
+pub fn brdf_lut(size: u32) -> Vec<[f32; 2]> {
+    (0..size * size)
+        .map(|i| {
+            let n_dot_v = ((i % size) as f32 + 0.5) / size as f32;
+            let roughness = ((i / size) as f32 + 0.5) / size as f32;
+            let v = Vec3::new((1.0 - n_dot_v * n_dot_v).sqrt(), 0.0, n_dot_v);
+            let (mut a, mut b) = (0.0, 0.0);
+            for s in 0..BRDF_SAMPLES {
+                let h = importance_sample_ggx(hammersley(s, BRDF_SAMPLES), Vec3::Z, roughness);
+                let l = 2.0 * v.dot(h) * h - v;
+                let (n_dot_l, n_dot_h, v_dot_h) = (l.z, h.z.max(0.0), v.dot(h).max(0.0));
+                if n_dot_l > 0.0 {
+                    let g = geometry_smith_ibl(n_dot_v, n_dot_l, roughness);
+                    let g_vis = g * v_dot_h / (n_dot_h * n_dot_v).max(f32::EPSILON);
+                    let fc = (1.0 - v_dot_h).powi(5);
+                    a += (1.0 - fc) * g_vis;
+                    b += fc * g_vis;
+                }
+            }
+            [a / BRDF_SAMPLES as f32, b / BRDF_SAMPLES as f32]
+        })
+        .collect()
+}
+
+/// GPU textures of the maps, bound at group 3 of the PBR pipeline
+pub struct IblMaps {
+    pub irradiance: wgpu::Texture,
+    pub prefiltered: wgpu::Texture,
+    pub brdf_lut: wgpu::Texture,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl IblMaps {
+    /// Create the maps lit by a flat ambient environment
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let cube = |label, size, mips| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width: size,
+                    height: size,
+                    depth_or_array_layers: 6,
+                },
+                mip_level_count: mips,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba16Float,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            })
+        };
+        let irradiance = cube("IBL Irradiance Cubemap", IRRADIANCE_SIZE, 1);
+        let prefiltered = cube("IBL Prefiltered Cubemap", PREFILTER_SIZE, PREFILTER_MIPS);
+        let brdf_lut = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("IBL BRDF LUT"),
+            size: wgpu::Extent3d {
+                width: BRDF_LUT_SIZE,
+                height: BRDF_LUT_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rg16Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let lut: Vec<u16> = self::brdf_lut(BRDF_LUT_SIZE)
+            .into_iter()
+            .flat_map(|[a, b]| [f16_bits(a), f16_bits(b)])
+            .collect();
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &brdf_lut,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            bytemuck::cast_slice(&lut),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * BRDF_LUT_SIZE),
+                rows_per_image: Some(BRDF_LUT_SIZE),
+            },
+            wgpu::Extent3d {
+                width: BRDF_LUT_SIZE,
+                height: BRDF_LUT_SIZE,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        let cube_view = |texture: &wgpu::Texture| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                dimension: Some(wgpu::TextureViewDimension::Cube),
+                ..Default::default()
+            })
+        };
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("IBL Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("IBL Bind Group"),
+            layout: &Self::create_bind_group_layout(device),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&cube_view(&irradiance)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&cube_view(&prefiltered)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(
+                        &brdf_lut.create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let maps = Self {
+            irradiance,
+            prefiltered,
+            brdf_lut,
+            bind_group,
+        };
+        maps.update(
+            queue,
+            &EnvironmentMap::uniform(Vec3::splat(DEFAULT_AMBIENT)),
+        );
+        maps
+    }
+
+    /// Bind group layout: irradiance cube, pre-filtered cube, BRDF LUT, sampler
+    pub fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let texture = |binding, view_dimension| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension,
+                multisampled: false,
+            },
+            count: None,
+        };
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("IBL Bind Group Layout"),
+            entries: &[
+                texture(0, wgpu::TextureViewDimension::Cube),
+                texture(1, wgpu::TextureViewDimension::Cube),
+                texture(2, wgpu::TextureViewDimension::D2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        })
+    }
+
+    /// Convolve a sky into the irradiance and pre-filtered maps
+    pub fn update(&self, queue: &wgpu::Queue, environment: &EnvironmentMap) {
+        self.upload(queue, &ConvolvedSky::new(environment));
+    }
+
+    /// Write maps convolved ahead of time
+    pub fn upload(&self, queue: &wgpu::Queue, sky: &ConvolvedSky) {
+        write_cube(queue, &self.irradiance, 0, &sky.irradiance);
+        for (mip, filtered) in sky.prefiltered.iter().enumerate() {
+            write_cube(queue, &self.prefiltered, mip as u32, filtered);
+        }
+    }
+}
+
+/// A sky's irradiance map and pre-filtered mips, convolved on the CPU
+pub struct ConvolvedSky {
+    irradiance: EnvironmentMap,
+    prefiltered: Vec<EnvironmentMap>,
+}
+
+impl ConvolvedSky {
+    pub fn new(environment: &EnvironmentMap) -> Self {
+        let irradiance = environment
+            .resized(IRRADIANCE_SIZE.min(environment.size))
+            .irradiance(IRRADIANCE_SIZE);
+
+        let source = environment.resized(PREFILTER_SIZE);
+        let prefiltered = (0..PREFILTER_MIPS)
+            .map(|mip| {
+                let size = PREFILTER_SIZE >> mip;
+                let roughness = mip as f32 / (PREFILTER_MIPS - 1) as f32;
+                // Sample a copy about the mip's resolution, so rough mips don't alias
+                source.resized(size * 2).prefiltered(size, roughness)
+            })
+            .collect();
+        Self {
+            irradiance,
+            prefiltered,
+        }
+    }
+}
+
+fn write_cube(queue: &wgpu::Queue, texture: &wgpu::Texture, mip_level: u32, map: &EnvironmentMap) {
+    for (face, texels) in map.faces.iter().enumerate() {
+        let data: Vec<u16> = texels
+            .iter()
+            .flat_map(|c| [c.x, c.y, c.z, 1.0].map(f16_bits))
+            .collect();
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture,
+                mip_level,
+                origin: wgpu::Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: face as u32,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            bytemuck::cast_slice(&data),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(8 * map.size),
+                rows_per_image: Some(map.size),
+            },
+            wgpu::Extent3d {
+                width: map.size,
+                height: map.size,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+fn f16_bits(value: f32) -> u16 {
+    half::f16::from_f32(value).to_bits()
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Direction through the center of a cubemap texel
+fn texel_direction(face: usize, x: u32, y: u32, size: u32) -> Vec3 {
+    let s = 2.0 * (x as f32 + 0.5) / size as f32 - 1.0;
+    let t = 2.0 * (y as f32 + 0.5) / size as f32 - 1.0;
+    let direction = match face {
+        0 => Vec3::new(1.0, -t, -s),
+        1 => Vec3::new(-1.0, -t, s),
+        2 => Vec3::new(s, 1.0, t),
+        3 => Vec3::new(s, -1.0, -t),
+        4 => Vec3::new(s, -t, 1.0),
+        _ => Vec3::new(-s, -t, -1.0),
+    };
+    direction.normalize()
+}
+
+/// Face and 0..1 texture coordinates a direction samples
+fn direction_texel(d: Vec3) -> (usize, Vec2) {
+    let a = d.abs();
+    let (face, sc, tc, ma) = if a.x >= a.y && a.x >= a.z {
+        if d.x > 0.0 {
+            (0, -d.z, -d.y, a.x)
+        } else {
+            (1, d.z, -d.y, a.x)
+        }
+    } else if a.y >= a.z {
+        if d.y > 0.0 {
+            (2, d.x, d.z, a.y)
+        } else {
+            (3, d.x, -d.z, a.y)
+        }
+    } else if d.z > 0.0 {
+        (4, d.x, -d.y, a.z)
+    } else {
+        (5, -d.x, -d.y, a.z)
+    };
+    let ma = ma.max(f32::EPSILON);
+    (face, Vec2::new(sc / ma, tc / ma) * 0.5 + 0.5)
+}
+
+/// Solid angle a cubemap texel covers
+fn texel_solid_angle(x: u32, y: u32, size: u32) -> f32 {
+    let s = 2.0 * (x as f32 + 0.5) / size as f32 - 1.0;
+    let t = 2.0 * (y as f32 + 0.5) / size as f32 - 1.0;
+    let texel_area = (2.0 / size as f32).powi(2);
+    texel_area / (1.0 + s * s + t * t).powf(1.5)
+}
+
+/// Low-discrepancy sample `i` of `count`
+fn hammersley(i: u32, count: u32) -> Vec2 {
+    Vec2::new(
+        i as f32 / count as f32,
+        i.reverse_bits() as f32 * 2.328_306_4e-10,
+    )
+}
+
+/// Half vector around `normal` distributed by GGX at `roughness`
+fn importance_sample_ggx(xi: Vec2, normal: Vec3, roughness: f32) -> Vec3 {
+    let a = roughness * roughness;
+    let phi = std::f32::consts::TAU * xi.x;
+    let cos_theta = ((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y)).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+    let up = if normal.z.abs() < 0.999 {
+        Vec3::Z
+    } else {
+        Vec3::X
+    };
+    let tangent = up.cross(normal).normalize();
+    let bitangent = normal.cross(tangent);
+    (tangent * phi.cos() * sin_theta + bitangent * phi.sin() * sin_theta + normal * cos_theta)
+        .normalize()
+}
+
+/// Smith geometry term with the IBL remapping k = a / 2
+fn geometry_smith_ibl(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let k = roughness * roughness / 2.0;
+    let ggx = |n_dot_x: f32| n_dot_x / (n_dot_x * (1.0 - k) + k);
+    ggx(n_dot_v) * ggx(n_dot_l)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cube_directions_round_trip() {
+        let size = 8;
+        for face in 0..6 {
+            for (x, y) in [(0, 0), (3, 5), (7, 7)] {
+                let (sampled_face, uv) = direction_texel(texel_direction(face, x, y, size));
+                assert_eq!(sampled_face, face);
+                assert_eq!((uv * size as f32).as_uvec2(), glam::UVec2::new(x, y));
+            }
+        }
+
+        // The solid angles of all texels cover the sphere
+        let total: f32 = (0..size * size)
+            .map(|i| texel_solid_angle(i % size, i / size, size))
+            .sum::<f32>()
+            * 6.0;
+        assert!((total - 4.0 * std::f32::consts::PI).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_convolution_keeps_uniform_sky_and_follows_the_sun_side() {
+        let sky = EnvironmentMap::uniform(Vec3::splat(0.5)).resized(8);
+        let irradiance = sky.irradiance(4);
+        assert!(irradiance
+            .sample(Vec3::Y)
+            .abs_diff_eq(Vec3::splat(0.5), 0.02));
+        let rough = sky.prefiltered(4, 0.8);
+        assert!(rough.sample(Vec3::X).abs_diff_eq(Vec3::splat(0.5), 1e-4));
+
+        // A bright upper hemisphere lights upward normals more than downward ones
+        let half_sky =
+            EnvironmentMap::from_fn(8, |d| Vec3::splat(if d.y > 0.0 { 1.0 } else { 0.0 }));
+        let irradiance = half_sky.irradiance(4);
+        assert!(irradiance.sample(Vec3::Y).x > 0.9);
+        assert!(irradiance.sample(Vec3::NEG_Y).x < 0.1);
+
+        // The split-sum terms stay within 0..1
+        let lut = brdf_lut(8);
+        assert!(lut
+            .iter()
+            .all(|[a, b]| (0.0..=1.0).contains(a) && (0.0..=1.0).contains(b)));
+        let smooth_facing = lut[7];
+        assert!(smooth_facing[0] + smooth_facing[1] > 0.9);
+    }
+}
@@ -0,0 +1,182 @@
+// Instancing - groups mesh draws by (mesh, material) so each group is one
+// instanced draw call with a per-instance transform
+//
+// Batches are filled once per frame in draw order and then uploaded into a
+// single instance buffer; each batch draws its slice of it.
+
+use crate::gpu_material::MaterialHandle;
+use crate::gpu_mesh::MeshHandle;
+use glam::Mat4;
+use std::collections::HashMap;
+
+/// Per-instance data read by `vs_instanced` in pbr_advanced_nm.wgsl (80 bytes)
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct InstanceRaw {
+    pub model: [[f32; 4]; 4],
+    /// x = LOD crossfade, see `LodDraw::fade`
+    pub lod_fade: [f32; 4],
+}
+
+impl InstanceRaw {
+    pub fn new(model: Mat4, lod_fade: f32) -> Self {
+        Self {
+            model: model.to_cols_array_2d(),
+            lod_fade: [lod_fade, 0.0, 0.0, 0.0],
+        }
+    }
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+            // Model matrix columns (locations 6-9)
+            6 => Float32x4,
+            7 => Float32x4,
+            8 => Float32x4,
+            9 => Float32x4,
+            // LOD fade (location 10)
+            10 => Float32x4,
+        ];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRIBUTES,
+        }
+    }
+}
+
+/// Instances of one mesh drawn with one material
+#[derive(Debug, Clone)]
+pub struct DrawBatch {
+    pub mesh: MeshHandle,
+    pub material: MaterialHandle,
+    pub instances: Vec<InstanceRaw>,
+}
+
+/// A frame's mesh draws, grouped by (mesh, material)
+#[derive(Debug, Default)]
+pub struct DrawBatches {
+    batches: Vec<DrawBatch>,
+    lookup: HashMap<(MeshHandle, MaterialHandle), usize>,
+}
+
+impl DrawBatches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Empty the batches for the next frame (keeping their allocations)
+    pub fn clear(&mut self) {
+        for batch in &mut self.batches {
+            batch.instances.clear();
+        }
+    }
+
+    /// Add an instance of `mesh` drawn with `material`
+    pub fn push(&mut self, mesh: MeshHandle, material: MaterialHandle, model: Mat4, lod_fade: f32) {
+        let index = *self.lookup.entry((mesh, material)).or_insert_with(|| {
+            self.batches.push(DrawBatch {
+                mesh,
+                material,
+                instances: Vec::new(),
+            });
+            self.batches.len() - 1
+        });
+        self.batches[index].instances.push(InstanceRaw::new(model, lod_fade));
+    }
+
+    /// Batches with at least one instance, in the order they were first used
+    pub fn batches(&self) -> impl Iterator<Item = &DrawBatch> {
+        self.batches.iter().filter(|batch| !batch.instances.is_empty())
+    }
+
+    pub fn instance_count(&self) -> usize {
+        self.batches.iter().map(|batch| batch.instances.len()).sum()
+    }
+}
+
+/// Vertex buffer holding every batch's instances, grown as needed
+pub struct InstanceBuffer {
+    pub buffer: wgpu::Buffer,
+    capacity: usize,
+}
+
+impl InstanceBuffer {
+    pub fn new(device: &wgpu::Device, capacity: usize) -> Self {
+        Self {
+            buffer: Self::create_buffer(device, capacity),
+            capacity,
+        }
+    }
+
+    fn create_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Instance Buffer"),
+            size: (capacity.max(1) * std::mem::size_of::<InstanceRaw>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Upload the batches' instances back to back; returns each batch's
+    /// range of instances in the buffer
+    pub fn write(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        batches: &DrawBatches,
+    ) -> Vec<std::ops::Range<u32>> {
+        let count = batches.instance_count();
+        if count > self.capacity {
+            self.capacity = count.next_power_of_two();
+            self.buffer = Self::create_buffer(device, self.capacity);
+        }
+
+        let mut instances = Vec::with_capacity(count);
+        let mut ranges = Vec::new();
+        for batch in batches.batches() {
+            let start = instances.len() as u32;
+            instances.extend_from_slice(&batch.instances);
+            ranges.push(start..instances.len() as u32);
+        }
+        if !instances.is_empty() {
+            queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&instances));
+        }
+        ranges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec3;
+
+    #[test]
+    fn test_draws_group_by_mesh_and_material() {
+        let (cube, sphere) = (MeshHandle(0), MeshHandle(1));
+        let (stone, wood) = (MaterialHandle(0), MaterialHandle(1));
+        let at = |x: f32| Mat4::from_translation(Vec3::new(x, 0.0, 0.0));
+
+        let mut batches = DrawBatches::new();
+        batches.push(cube, stone, at(0.0), 1.0);
+        batches.push(sphere, stone, at(1.0), 1.0);
+        batches.push(cube, stone, at(2.0), 1.0);
+        batches.push(cube, wood, at(3.0), 0.5);
+
+        let grouped: Vec<_> = batches
+            .batches()
+            .map(|b| (b.mesh, b.material, b.instances.len()))
+            .collect();
+        assert_eq!(
+            grouped,
+            vec![(cube, stone, 2), (sphere, stone, 1), (cube, wood, 1)]
+        );
+        assert_eq!(batches.instance_count(), 4);
+        assert_eq!(batches.batches().next().unwrap().instances[1], InstanceRaw::new(at(2.0), 1.0));
+
+        // Cleared batches are skipped until used again
+        batches.clear();
+        batches.push(cube, wood, at(0.0), 1.0);
+        assert_eq!(batches.batches().count(), 1);
+        assert_eq!(batches.instance_count(), 1);
+    }
+}
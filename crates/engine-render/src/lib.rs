@@ -1,40 +1,68 @@
 // Engine Render - 3D rendering with wgpu
 
-/// MSAA sample count for anti-aliasing (1 = off, 4 = 4x MSAA)
+/// Default MSAA sample count for anti-aliasing (1 = off, 4 = 4x MSAA)
 pub const MSAA_SAMPLE_COUNT: u32 = 4;
 
 pub mod camera;
+pub mod capture;
 pub mod culling;
+pub mod foliage_culling;
 pub mod foliage_renderer;
 pub mod frustum;
 pub mod gpu_material;
 pub mod gpu_mesh;
+pub mod gpu_profiler;
 pub mod gpu_texture;
+pub mod grass_renderer;
+pub mod grid;
+pub mod ibl;
+pub mod instancing;
 pub mod lod;
 pub mod material_manager;
+pub mod memory_budget;
 pub mod mesh_manager;
 pub mod particle_renderer;
 pub mod postprocess;
+pub mod render_graph;
+pub mod render_stats;
 pub mod renderer;
 pub mod shadow;
+pub mod skinned_renderer;
+pub mod sky;
 pub mod skybox;
+pub mod terrain_renderer;
 pub mod texture_manager;
+pub mod texture_streaming;
 pub mod water;
 
 pub use camera::Camera;
+pub use capture::{tile_projection, CapturedImage, FrameCapture};
 pub use culling::{CullingStats, CullingSystem, Renderable, RenderableId, VisibilityResult};
-pub use foliage_renderer::{FoliageInstanceGpu, FoliageRenderData, FoliageRenderer};
+pub use foliage_culling::{FoliageBatches, FoliageChunks, FoliageLodRange, FoliageVisible};
+pub use foliage_renderer::{FoliageDraw, FoliageInstanceGpu, FoliageRenderData, FoliageRenderer, FoliageWind};
 pub use frustum::{Frustum, Plane, AABB};
 pub use gpu_material::{GpuMaterial, MaterialHandle, MaterialUniforms};
 pub use gpu_mesh::{GpuMesh, GpuVertex, MeshHandle};
+pub use gpu_profiler::{GpuProfiler, GpuTiming};
 pub use gpu_texture::{GpuTexture, TextureHandle};
-pub use lod::{distance_squared, LodBias, LodConfig, LodLevel};
+pub use grass_renderer::{GrassGrid, GrassRenderer, GrassSettings, MAX_GRASS_BLADES};
+pub use grid::{GridRenderer, GridUniforms};
+pub use ibl::{EnvironmentMap, IblMaps};
+pub use instancing::{DrawBatch, DrawBatches, InstanceBuffer, InstanceRaw};
+pub use lod::{distance_fade, distance_squared, screen_size_distance, LodBias, LodConfig, LodDraw, LodLevel};
 pub use material_manager::MaterialManager;
+pub use memory_budget::{EvictionReport, MemoryBudget, ResourceMemory, ResourceTracker};
 pub use mesh_manager::MeshManager;
 pub use particle_renderer::{ParticleBlendMode, ParticleCameraUniforms, ParticleRenderer};
-pub use postprocess::{CompositePushConstants, Framebuffer, PostProcessPipeline, PostProcessSettings};
+pub use postprocess::{AntiAliasingMode, BloomChain, CompositePushConstants, Framebuffer, PostProcessPipeline, PostProcessSettings, TemporalAa, ToneMapper, HDR_FORMAT};
+pub use render_graph::{GraphPass, PassContext, RenderGraph, TransientDesc, TransientPool};
+pub use render_stats::{format_bytes, RenderStats};
 pub use renderer::Renderer;
 pub use shadow::{ShadowMap, ShadowUniforms, ShadowPushConstants};
+pub use skinned_renderer::{GpuSkinnedMesh, SkinnedRenderer, SkinnedVertexGpu};
+pub use sky::{ProceduralSky, SkyUniforms};
 pub use skybox::Skybox;
+pub use terrain_renderer::{TerrainPushConstants, TerrainRenderer, TerrainUniforms};
 pub use texture_manager::TextureManager;
-pub use water::{WaterRenderer, WaterUniforms, WaterPushConstants};
+pub use texture_streaming::{mip_for_screen_size, StreamedMips, MAX_STREAM_UPLOADS, STREAM_BASE_SIZE, STREAM_RELEASE_FRAMES};
+pub use water::{WaterReflection, WaterReflectionQuality, WaterRenderer, WaterUniforms, WaterPushConstants};
@@ -36,6 +36,20 @@ pub struct LodConfig {
     pub levels: Vec<LodLevel>,
     /// Whether LOD is enabled
     pub enabled: bool,
+    /// Distance over which neighbouring levels are dithered into each other
+    /// before a switch, 0.0 switches instantly
+    pub transition_width: f32,
+}
+
+/// One mesh to draw for a LOD crossfade
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LodDraw {
+    pub mesh: MeshHandle,
+    pub level: usize,
+    /// Share of pixels drawn, for the dither in the mesh shader. Negative
+    /// values draw the complementary pattern, so a level with `f` and the
+    /// next with `-f` cover every pixel exactly once.
+    pub fade: f32,
 }
 
 impl LodConfig {
@@ -44,9 +58,16 @@ impl LodConfig {
         Self {
             levels: Vec::new(),
             enabled: true,
+            transition_width: 0.0,
         }
     }
 
+    /// Set the crossfade distance before each switch
+    pub fn with_transition_width(mut self, width: f32) -> Self {
+        self.transition_width = width.max(0.0);
+        self
+    }
+
     /// Add a LOD level
     pub fn add_level(mut self, mesh: MeshHandle, min_distance: f32) -> Self {
         self.levels.push(LodLevel::new(mesh, min_distance));
@@ -58,6 +79,18 @@ impl LodConfig {
         self
     }
 
+    /// Add a LOD level used once a bounding sphere of `radius` covers less
+    /// than `screen_size` of the viewport height
+    pub fn add_level_at_screen_size(
+        self,
+        mesh: MeshHandle,
+        screen_size: f32,
+        radius: f32,
+        fov_y: f32,
+    ) -> Self {
+        self.add_level(mesh, screen_size_distance(radius, screen_size, fov_y))
+    }
+
     /// Create a simple 2-level LOD (high detail, low detail)
     pub fn two_level(high_detail: MeshHandle, low_detail: MeshHandle, switch_distance: f32) -> Self {
         Self::new()
@@ -101,6 +134,44 @@ impl LodConfig {
             .map(|level| (level.mesh, 0))
     }
 
+    /// Select the meshes to draw, crossfading in the `transition_width`
+    /// before each switch distance. The second draw is the incoming level.
+    pub fn select_lod_crossfade(
+        &self,
+        distance_squared: f32,
+    ) -> Option<(LodDraw, Option<LodDraw>)> {
+        let (mesh, level) = self.select_lod(distance_squared)?;
+        let current = LodDraw {
+            mesh,
+            level,
+            fade: 1.0,
+        };
+
+        let Some(next) = self.levels.get(level + 1) else {
+            return Some((current, None));
+        };
+        if self.transition_width <= 0.0 {
+            return Some((current, None));
+        }
+
+        let switch_distance = next.min_distance_squared.sqrt();
+        let progress = (distance_squared.sqrt() - (switch_distance - self.transition_width))
+            / self.transition_width;
+        if progress <= 0.0 {
+            return Some((current, None));
+        }
+
+        let fade = 1.0 - progress.min(1.0);
+        Some((
+            LodDraw { fade, ..current },
+            Some(LodDraw {
+                mesh: next.mesh,
+                level: level + 1,
+                fade: -fade,
+            }),
+        ))
+    }
+
     /// Get the number of LOD levels
     pub fn level_count(&self) -> usize {
         self.levels.len()
@@ -176,6 +247,22 @@ pub fn distance_squared(camera_pos: Vec3, object_pos: Vec3) -> f32 {
     (camera_pos - object_pos).length_squared()
 }
 
+/// Distance at which a sphere of `radius` covers `screen_size` (0..1) of the
+/// viewport height, for a vertical field of view of `fov_y` radians
+#[inline]
+pub fn screen_size_distance(radius: f32, screen_size: f32, fov_y: f32) -> f32 {
+    radius / (screen_size.max(1e-4) * (fov_y * 0.5).tan())
+}
+
+/// Visibility of something fading out over `fade_width` up to `max_distance`
+#[inline]
+pub fn distance_fade(distance: f32, max_distance: f32, fade_width: f32) -> f32 {
+    if fade_width <= 0.0 {
+        return if distance < max_distance { 1.0 } else { 0.0 };
+    }
+    ((max_distance - distance) / fade_width).clamp(0.0, 1.0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -238,6 +325,50 @@ mod tests {
         assert_eq!(dist_sq, 25.0); // 3^2 + 4^2 = 25
     }
 
+    #[test]
+    fn test_crossfade_between_levels() {
+        let lod =
+            LodConfig::two_level(MeshHandle(0), MeshHandle(1), 50.0).with_transition_width(10.0);
+
+        let (near, next) = lod.select_lod_crossfade(30.0 * 30.0).unwrap();
+        assert_eq!((near.mesh, near.fade), (MeshHandle(0), 1.0));
+        assert!(next.is_none());
+
+        // Halfway through the transition each level draws half the pixels
+        let (outgoing, incoming) = lod.select_lod_crossfade(45.0 * 45.0).unwrap();
+        let incoming = incoming.unwrap();
+        assert_eq!(outgoing.mesh, MeshHandle(0));
+        assert_eq!(incoming.mesh, MeshHandle(1));
+        assert!((outgoing.fade - 0.5).abs() < 1e-5);
+        assert_eq!(incoming.fade, -outgoing.fade);
+
+        let (far, next) = lod.select_lod_crossfade(60.0 * 60.0).unwrap();
+        assert_eq!((far.mesh, far.level, far.fade), (MeshHandle(1), 1, 1.0));
+        assert!(next.is_none());
+    }
+
+    #[test]
+    fn test_screen_size_levels() {
+        let fov = 90.0_f32.to_radians();
+        // A 1m sphere fills the whole view height at 1m with a 90 degree fov
+        assert!((screen_size_distance(1.0, 1.0, fov) - 1.0).abs() < 1e-5);
+        assert!((screen_size_distance(1.0, 0.1, fov) - 10.0).abs() < 1e-4);
+
+        let lod = LodConfig::new()
+            .add_level(MeshHandle(0), 0.0)
+            .add_level_at_screen_size(MeshHandle(1), 0.5, 2.0, fov);
+        assert_eq!(lod.select_lod(3.9 * 3.9).unwrap().0, MeshHandle(0));
+        assert_eq!(lod.select_lod(4.1 * 4.1).unwrap().0, MeshHandle(1));
+    }
+
+    #[test]
+    fn test_distance_fade() {
+        assert_eq!(distance_fade(100.0, 200.0, 25.0), 1.0);
+        assert_eq!(distance_fade(187.5, 200.0, 25.0), 0.5);
+        assert_eq!(distance_fade(250.0, 200.0, 25.0), 0.0);
+        assert_eq!(distance_fade(199.0, 200.0, 0.0), 1.0);
+    }
+
     #[test]
     fn test_disabled_lod() {
         let mut lod = LodConfig::two_level(MeshHandle(0), MeshHandle(1), 10.0);
@@ -2,6 +2,7 @@
 
 use crate::gpu_material::{GpuMaterial, MaterialHandle, MaterialUniforms};
 use crate::gpu_texture::TextureHandle;
+use crate::memory_budget::{ResourceMemory, ResourceTracker};
 use crate::texture_manager::TextureManager;
 use engine_assets::Material;
 use std::collections::HashMap;
@@ -9,10 +10,12 @@ use wgpu::util::DeviceExt;
 
 /// Manages material uploading, caching, and GPU resources
 pub struct MaterialManager {
-    materials: Vec<GpuMaterial>,
+    /// None once evicted
+    materials: Vec<Option<GpuMaterial>>,
     material_map: HashMap<String, MaterialHandle>,
     bind_group_layout: wgpu::BindGroupLayout,
     default_material_handle: MaterialHandle,
+    tracker: ResourceTracker,
 }
 
 impl MaterialManager {
@@ -40,8 +43,14 @@ impl MaterialManager {
             None,
         );
 
-        let mut materials = Vec::new();
-        materials.push(gpu_material);
+        let mut tracker = ResourceTracker::new();
+        tracker.insert(
+            0,
+            "default".to_string(),
+            gpu_material.uniform_buffer.size(),
+            false,
+        );
+        let materials = vec![Some(gpu_material)];
 
         let default_handle = MaterialHandle(0);
         let mut material_map = HashMap::new();
@@ -52,6 +61,7 @@ impl MaterialManager {
             material_map,
             bind_group_layout,
             default_material_handle: default_handle,
+            tracker,
         }
     }
 
@@ -103,7 +113,13 @@ impl MaterialManager {
         );
 
         let handle = MaterialHandle(self.materials.len());
-        self.materials.push(gpu_material);
+        self.tracker.insert(
+            handle.0,
+            name.clone(),
+            gpu_material.uniform_buffer.size(),
+            false,
+        );
+        self.materials.push(Some(gpu_material));
         self.material_map.insert(name, handle);
 
         handle
@@ -131,15 +147,54 @@ impl MaterialManager {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
+        let white_handle = texture_manager.white_texture_handle();
+        let textures = [
+            albedo_handle,
+            normal_handle.unwrap_or(white_handle),
+            metallic_roughness_handle.unwrap_or(white_handle),
+            ao_handle.unwrap_or(white_handle),
+        ];
+        let bind_group = Self::create_bind_group(device, texture_manager, bind_group_layout, &name, &uniform_buffer, textures);
+
+        GpuMaterial {
+            name,
+            albedo_texture: albedo_handle,
+            normal_texture: normal_handle,
+            metallic_roughness_texture: metallic_roughness_handle,
+            ao_texture: ao_handle,
+            uniforms,
+            uniform_buffer,
+            bind_group,
+            alpha_mode: material.alpha_mode,
+            double_sided: material.double_sided,
+        }
+    }
+
+    /// Internal: Bind group for a material's uniforms and its albedo, normal,
+    /// metallic-roughness and AO textures. Missing textures bind white.
+    fn create_bind_group(
+        device: &wgpu::Device,
+        texture_manager: &TextureManager,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        name: &str,
+        uniform_buffer: &wgpu::Buffer,
+        [albedo_handle, normal_handle, metallic_roughness_handle, ao_handle]: [TextureHandle; 4],
+    ) -> wgpu::BindGroup {
         // Get textures (use white as fallback)
         let white_handle = texture_manager.white_texture_handle();
-        let albedo_texture = texture_manager.get_texture(albedo_handle).unwrap();
-        let normal_texture = texture_manager.get_texture(normal_handle.unwrap_or(white_handle)).unwrap();
-        let metallic_roughness_texture = texture_manager.get_texture(metallic_roughness_handle.unwrap_or(white_handle)).unwrap();
-        let ao_texture = texture_manager.get_texture(ao_handle.unwrap_or(white_handle)).unwrap();
+        let texture = |handle: TextureHandle| {
+            texture_manager
+                .get_texture(handle)
+                .or_else(|| texture_manager.get_texture(white_handle))
+                .unwrap()
+        };
+        let albedo_texture = texture(albedo_handle);
+        let normal_texture = texture(normal_handle);
+        let metallic_roughness_texture = texture(metallic_roughness_handle);
+        let ao_texture = texture(ao_handle);
 
         // Create bind group with all textures and uniforms
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some(&format!("Material Bind Group: {}", name)),
             layout: bind_group_layout,
             entries: &[
@@ -189,25 +244,13 @@ impl MaterialManager {
                     resource: wgpu::BindingResource::Sampler(&ao_texture.sampler),
                 },
             ],
-        });
-
-        GpuMaterial {
-            name,
-            albedo_texture: albedo_handle,
-            normal_texture: normal_handle,
-            metallic_roughness_texture: metallic_roughness_handle,
-            ao_texture: ao_handle,
-            uniforms,
-            uniform_buffer,
-            bind_group,
-            alpha_mode: material.alpha_mode,
-            double_sided: material.double_sided,
-        }
+        })
     }
 
-    /// Get a material by handle
+    /// Get a material by handle (counts as a use for eviction)
     pub fn get_material(&self, handle: MaterialHandle) -> Option<&GpuMaterial> {
-        self.materials.get(handle.0)
+        self.tracker.touch(handle.0);
+        self.materials.get(handle.0)?.as_ref()
     }
 
     /// Get a material handle by name
@@ -215,6 +258,20 @@ impl MaterialManager {
         self.material_map.get(name).copied()
     }
 
+    /// Forget the upload for `name` so the next lookup misses and the material
+    /// is uploaded again from its current file. Handles already given out stay
+    /// valid and keep drawing with the old values until the memory budget
+    /// evicts the old upload.
+    pub fn invalidate(&mut self, name: &str) -> bool {
+        let Some(handle) = self.material_map.remove(name) else {
+            return false;
+        };
+        if handle != self.default_material_handle {
+            self.tracker.set_streamed(handle.0, true);
+        }
+        true
+    }
+
     /// Get the default material handle
     pub fn default_material_handle(&self) -> MaterialHandle {
         self.default_material_handle
@@ -227,6 +284,90 @@ impl MaterialManager {
 
     /// Get material count
     pub fn material_count(&self) -> usize {
-        self.materials.len()
+        self.tracker.memory().count
+    }
+
+    /// Total size of all material uniform buffers
+    pub fn buffer_bytes(&self) -> u64 {
+        self.tracker.memory().bytes
+    }
+
+    pub fn memory(&self) -> ResourceMemory {
+        self.tracker.memory()
+    }
+
+    pub fn tracker(&self) -> &ResourceTracker {
+        &self.tracker
+    }
+
+    pub(crate) fn tracker_mut(&mut self) -> &mut ResourceTracker {
+        &mut self.tracker
+    }
+
+    /// Mark a material as streamed: the memory budget may evict it, and the
+    /// next lookup by name uploads it again
+    pub fn set_streamed(&mut self, handle: MaterialHandle, streamed: bool) {
+        self.tracker.set_streamed(handle.0, streamed);
+    }
+
+    /// Materials that sample `texture`
+    pub fn texture_users(
+        &self,
+        texture: TextureHandle,
+    ) -> impl Iterator<Item = MaterialHandle> + '_ {
+        self.materials
+            .iter()
+            .enumerate()
+            .filter_map(move |(index, material)| {
+                let material = material.as_ref()?;
+                let samples = material.albedo_texture == texture
+                    || material.normal_texture == Some(texture)
+                    || material.metallic_roughness_texture == Some(texture)
+                    || material.ao_texture == Some(texture);
+                samples.then_some(MaterialHandle(index))
+            })
+    }
+
+    /// Rebuild the bind groups of materials sampling any of `textures`, after
+    /// those were re-created (streamed mips)
+    pub fn rebind_textures(&mut self, device: &wgpu::Device, texture_manager: &TextureManager, textures: &[TextureHandle]) {
+        if textures.is_empty() {
+            return;
+        }
+        let white_handle = texture_manager.white_texture_handle();
+        for material in self.materials.iter_mut().flatten() {
+            let handles = [
+                material.albedo_texture,
+                material.normal_texture.unwrap_or(white_handle),
+                material.metallic_roughness_texture.unwrap_or(white_handle),
+                material.ao_texture.unwrap_or(white_handle),
+            ];
+            if handles.iter().any(|handle| textures.contains(handle)) {
+                material.bind_group = Self::create_bind_group(
+                    device,
+                    texture_manager,
+                    &self.bind_group_layout,
+                    &material.name,
+                    &material.uniform_buffer,
+                    handles,
+                );
+            }
+        }
+    }
+
+    /// Drop a material's buffer and bind group. The default material is
+    /// never evicted.
+    pub fn evict(&mut self, handle: MaterialHandle) -> bool {
+        if handle == self.default_material_handle {
+            return false;
+        }
+        let Some(name) = self.tracker.remove(handle.0) else {
+            return false;
+        };
+        self.materials[handle.0] = None;
+        if self.material_map.get(&name) == Some(&handle) {
+            self.material_map.remove(&name);
+        }
+        true
     }
 }
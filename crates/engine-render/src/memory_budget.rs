@@ -0,0 +1,381 @@
+// Memory budget - GPU memory tracking and eviction of streamed resources
+//
+// The mesh, texture and material managers each keep a ResourceTracker: the
+// bytes of every resource they hold, the frame it was last drawn with and
+// whether it is streamed (can be dropped and uploaded again from its asset
+// when next needed). Once a frame MemoryBudget::enforce() totals them, warns
+// when the total crosses the warning level and evicts the least recently
+// used streamed resources until it fits. Built-in resources are never
+// evicted, so a budget smaller than those is only warned about. A texture
+// stays while a material still samples it.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::gpu_material::MaterialHandle;
+use crate::gpu_mesh::MeshHandle;
+use crate::gpu_texture::TextureHandle;
+use crate::material_manager::MaterialManager;
+use crate::mesh_manager::MeshManager;
+use crate::render_stats::format_bytes;
+use crate::texture_manager::TextureManager;
+
+/// Streamed resources used within this many frames are kept even over budget
+pub const MIN_IDLE_FRAMES: u64 = 60;
+
+/// Share of the budget at which the first warning is logged
+pub const WARN_FRACTION: f64 = 0.9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ResourceKind {
+    Material,
+    Mesh,
+    Texture,
+}
+
+/// Memory held by one manager
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ResourceMemory {
+    pub count: usize,
+    pub bytes: u64,
+    /// Highest `bytes` has been
+    pub peak_bytes: u64,
+    /// Part of `bytes` that can be evicted
+    pub streamed_bytes: u64,
+}
+
+struct TrackedResource {
+    name: String,
+    bytes: u64,
+    streamed: bool,
+    last_used: AtomicU64,
+}
+
+/// Sizes and last use of a manager's resources, by handle index
+#[derive(Default)]
+pub struct ResourceTracker {
+    resources: Vec<Option<TrackedResource>>,
+    frame: u64,
+    bytes: u64,
+    peak_bytes: u64,
+}
+
+impl ResourceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track the resource at `index`, replacing what was there
+    pub fn insert(&mut self, index: usize, name: String, bytes: u64, streamed: bool) {
+        self.remove(index);
+        if self.resources.len() <= index {
+            self.resources.resize_with(index + 1, || None);
+        }
+        self.resources[index] = Some(TrackedResource {
+            name,
+            bytes,
+            streamed,
+            last_used: AtomicU64::new(self.frame),
+        });
+        self.bytes += bytes;
+        self.peak_bytes = self.peak_bytes.max(self.bytes);
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<String> {
+        let resource = self.resources.get_mut(index)?.take()?;
+        self.bytes -= resource.bytes;
+        Some(resource.name)
+    }
+
+    /// Change a resource's size in place, e.g. when a texture streams mips
+    pub fn resize(&mut self, index: usize, bytes: u64) {
+        if let Some(Some(resource)) = self.resources.get_mut(index) {
+            self.bytes = self.bytes - resource.bytes + bytes;
+            resource.bytes = bytes;
+            self.peak_bytes = self.peak_bytes.max(self.bytes);
+        }
+    }
+
+    pub fn set_streamed(&mut self, index: usize, streamed: bool) {
+        if let Some(Some(resource)) = self.resources.get_mut(index) {
+            resource.streamed = streamed;
+        }
+    }
+
+    /// Note that the resource is used this frame
+    pub fn touch(&self, index: usize) {
+        if let Some(Some(resource)) = self.resources.get(index) {
+            resource.last_used.store(self.frame, Ordering::Relaxed);
+        }
+    }
+
+    pub fn memory(&self) -> ResourceMemory {
+        let live = self.resources.iter().flatten();
+        ResourceMemory {
+            count: live.clone().count(),
+            bytes: self.bytes,
+            peak_bytes: self.peak_bytes,
+            streamed_bytes: live.filter(|r| r.streamed).map(|r| r.bytes).sum(),
+        }
+    }
+
+    /// The `count` largest resources, largest first
+    pub fn largest(&self, count: usize) -> Vec<(&str, u64)> {
+        let mut resources: Vec<(&str, u64)> = self
+            .resources
+            .iter()
+            .flatten()
+            .map(|r| (r.name.as_str(), r.bytes))
+            .collect();
+        resources.sort_by_key(|r| std::cmp::Reverse(r.1));
+        resources.truncate(count);
+        resources
+    }
+
+    fn next_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    /// Streamed resources not used for `min_idle` frames
+    fn idle_streamed(
+        &self,
+        kind: ResourceKind,
+        min_idle: u64,
+    ) -> impl Iterator<Item = Candidate> + '_ {
+        self.resources
+            .iter()
+            .enumerate()
+            .filter_map(move |(index, resource)| {
+                let resource = resource.as_ref().filter(|r| r.streamed)?;
+                let last_used = resource.last_used.load(Ordering::Relaxed);
+                (self.frame.saturating_sub(last_used) >= min_idle).then_some(Candidate {
+                    kind,
+                    index,
+                    last_used,
+                    bytes: resource.bytes,
+                })
+            })
+    }
+}
+
+/// A resource that could be evicted
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate {
+    kind: ResourceKind,
+    index: usize,
+    last_used: u64,
+    bytes: u64,
+}
+
+/// Least recently used candidates first, taken until `excess` bytes are
+/// freed; `can_evict` is asked as each is reached
+fn choose_evictions(
+    mut candidates: Vec<Candidate>,
+    excess: u64,
+    mut can_evict: impl FnMut(&Candidate, &[Candidate]) -> bool,
+) -> Vec<Candidate> {
+    // Materials before the textures they sample when equally old
+    candidates.sort_by_key(|c| (c.last_used, c.kind));
+    let mut chosen: Vec<Candidate> = Vec::new();
+    let mut freed = 0;
+    for candidate in candidates {
+        if freed >= excess {
+            break;
+        }
+        if can_evict(&candidate, &chosen) {
+            freed += candidate.bytes;
+            chosen.push(candidate);
+        }
+    }
+    chosen
+}
+
+/// What one enforce() did
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EvictionReport {
+    pub meshes: usize,
+    pub textures: usize,
+    pub materials: usize,
+    pub freed_bytes: u64,
+}
+
+impl EvictionReport {
+    pub fn is_empty(&self) -> bool {
+        self.meshes + self.textures + self.materials == 0
+    }
+}
+
+/// GPU memory limit for the managed resources
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBudget {
+    /// 0 = no limit
+    pub limit_bytes: u64,
+    over_warning: bool,
+    /// Resources evicted since startup
+    pub evicted: usize,
+}
+
+impl MemoryBudget {
+    pub fn new(limit_bytes: u64) -> Self {
+        Self {
+            limit_bytes,
+            ..Default::default()
+        }
+    }
+
+    /// End the frame: warn when close to the budget and evict idle streamed
+    /// resources while over it
+    pub fn enforce(
+        &mut self,
+        meshes: &mut MeshManager,
+        textures: &mut TextureManager,
+        materials: &mut MaterialManager,
+    ) -> EvictionReport {
+        meshes.tracker_mut().next_frame();
+        textures.tracker_mut().next_frame();
+        materials.tracker_mut().next_frame();
+        let mut report = EvictionReport::default();
+        if self.limit_bytes == 0 {
+            return report;
+        }
+
+        let total = meshes.memory().bytes + textures.memory().bytes + materials.memory().bytes;
+        let warn_at = (self.limit_bytes as f64 * WARN_FRACTION) as u64;
+        if total >= warn_at && !self.over_warning {
+            log::warn!(
+                "GPU memory {} is near the budget of {}",
+                format_bytes(total),
+                format_bytes(self.limit_bytes)
+            );
+        }
+        self.over_warning = total >= warn_at;
+        if total <= self.limit_bytes {
+            return report;
+        }
+
+        let candidates: Vec<Candidate> = meshes
+            .tracker()
+            .idle_streamed(ResourceKind::Mesh, MIN_IDLE_FRAMES)
+            .chain(
+                textures
+                    .tracker()
+                    .idle_streamed(ResourceKind::Texture, MIN_IDLE_FRAMES),
+            )
+            .chain(
+                materials
+                    .tracker()
+                    .idle_streamed(ResourceKind::Material, MIN_IDLE_FRAMES),
+            )
+            .collect();
+        let chosen = choose_evictions(candidates, total - self.limit_bytes, |candidate, chosen| {
+            candidate.kind != ResourceKind::Texture
+                || materials
+                    .texture_users(TextureHandle(candidate.index))
+                    .all(|material| {
+                        chosen.iter().any(|c| {
+                            c.kind == ResourceKind::Material && MaterialHandle(c.index) == material
+                        })
+                    })
+        });
+
+        for candidate in chosen {
+            let evicted = match candidate.kind {
+                ResourceKind::Mesh => meshes.evict(MeshHandle(candidate.index)),
+                ResourceKind::Texture => textures.evict(TextureHandle(candidate.index)),
+                ResourceKind::Material => materials.evict(MaterialHandle(candidate.index)),
+            };
+            if !evicted {
+                continue;
+            }
+            match candidate.kind {
+                ResourceKind::Mesh => report.meshes += 1,
+                ResourceKind::Texture => report.textures += 1,
+                ResourceKind::Material => report.materials += 1,
+            }
+            report.freed_bytes += candidate.bytes;
+        }
+        self.evicted += report.meshes + report.textures + report.materials;
+        if total - report.freed_bytes > self.limit_bytes {
+            log::warn!(
+                "GPU memory {} is over the budget of {} with nothing left to evict",
+                format_bytes(total - report.freed_bytes),
+                format_bytes(self.limit_bytes)
+            );
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracker_totals_and_peak() {
+        let mut tracker = ResourceTracker::new();
+        tracker.insert(0, "cube".to_string(), 100, false);
+        tracker.insert(2, "tree.glb".to_string(), 300, true);
+        tracker.insert(2, "tree.glb".to_string(), 200, true);
+        assert_eq!(
+            tracker.memory(),
+            ResourceMemory {
+                count: 2,
+                bytes: 300,
+                peak_bytes: 400,
+                streamed_bytes: 200,
+            }
+        );
+        assert_eq!(tracker.largest(1), [("tree.glb", 200)]);
+
+        // Only idle streamed resources are candidates
+        for _ in 0..MIN_IDLE_FRAMES {
+            tracker.next_frame();
+        }
+        assert_eq!(
+            tracker
+                .idle_streamed(ResourceKind::Mesh, MIN_IDLE_FRAMES)
+                .count(),
+            1
+        );
+        tracker.touch(2);
+        assert_eq!(
+            tracker
+                .idle_streamed(ResourceKind::Mesh, MIN_IDLE_FRAMES)
+                .count(),
+            0
+        );
+
+        assert_eq!(tracker.remove(2).as_deref(), Some("tree.glb"));
+        assert_eq!(tracker.memory().bytes, 100);
+        assert_eq!(tracker.memory().peak_bytes, 400);
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used_first() {
+        let candidate = |kind, index, last_used, bytes| Candidate {
+            kind,
+            index,
+            last_used,
+            bytes,
+        };
+        let candidates = vec![
+            candidate(ResourceKind::Mesh, 0, 30, 100),
+            candidate(ResourceKind::Texture, 1, 10, 500),
+            candidate(ResourceKind::Material, 1, 10, 10),
+            candidate(ResourceKind::Mesh, 1, 20, 100),
+        ];
+        // The texture only goes once the material sampling it is chosen
+        let chosen = choose_evictions(candidates, 550, |c, chosen| {
+            c.kind != ResourceKind::Texture
+                || chosen.iter().any(|c| c.kind == ResourceKind::Material)
+        });
+        let order: Vec<(ResourceKind, usize)> = chosen.iter().map(|c| (c.kind, c.index)).collect();
+        assert_eq!(
+            order,
+            [
+                (ResourceKind::Material, 1),
+                (ResourceKind::Texture, 1),
+                (ResourceKind::Mesh, 1),
+            ]
+        );
+    }
+}
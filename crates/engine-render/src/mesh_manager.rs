@@ -1,11 +1,16 @@
 // Mesh manager - handles uploading and managing GPU meshes
 
 use crate::gpu_mesh::{GpuMesh, GpuVertex, MeshHandle};
-use std::collections::HashMap;
+use crate::memory_budget::{ResourceMemory, ResourceTracker};
+use std::collections::{HashMap, HashSet};
 
 pub struct MeshManager {
-    meshes: Vec<GpuMesh>,
+    /// None once evicted
+    meshes: Vec<Option<GpuMesh>>,
     mesh_map: HashMap<String, MeshHandle>,
+    tracker: ResourceTracker,
+    /// Streamed meshes evicted and not uploaded again yet
+    evicted: HashSet<String>,
 }
 
 impl MeshManager {
@@ -13,9 +18,23 @@ impl MeshManager {
         Self {
             meshes: Vec::new(),
             mesh_map: HashMap::new(),
+            tracker: ResourceTracker::new(),
+            evicted: HashSet::new(),
         }
     }
 
+    fn store(&mut self, handle: MeshHandle, name: String, gpu_mesh: GpuMesh) {
+        let bytes = gpu_mesh.vertex_buffer.size() + gpu_mesh.index_buffer.size();
+        self.tracker.insert(handle.0, name.clone(), bytes, false);
+        self.evicted.remove(&name);
+        if handle.0 == self.meshes.len() {
+            self.meshes.push(Some(gpu_mesh));
+        } else {
+            self.meshes[handle.0] = Some(gpu_mesh);
+        }
+        self.mesh_map.insert(name, handle);
+    }
+
     /// Upload a mesh to the GPU and return a handle
     pub fn upload_mesh(
         &mut self,
@@ -31,15 +50,33 @@ impl MeshManager {
 
         let gpu_mesh = GpuMesh::from_cpu_mesh(device, vertices, indices);
         let handle = MeshHandle(self.meshes.len());
-        self.meshes.push(gpu_mesh);
-        self.mesh_map.insert(name, handle);
+        self.store(handle, name, gpu_mesh);
+
+        handle
+    }
 
+    /// Upload a mesh, replacing any mesh already registered under the name
+    pub fn replace_mesh(
+        &mut self,
+        device: &wgpu::Device,
+        name: String,
+        vertices: &[GpuVertex],
+        indices: &[u32],
+    ) -> MeshHandle {
+        let gpu_mesh = GpuMesh::from_cpu_mesh(device, vertices, indices);
+        let handle = self
+            .mesh_map
+            .get(&name)
+            .copied()
+            .unwrap_or(MeshHandle(self.meshes.len()));
+        self.store(handle, name, gpu_mesh);
         handle
     }
 
-    /// Get a mesh by handle
+    /// Get a mesh by handle (counts as a use for eviction)
     pub fn get_mesh(&self, handle: MeshHandle) -> Option<&GpuMesh> {
-        self.meshes.get(handle.0)
+        self.tracker.touch(handle.0);
+        self.meshes.get(handle.0)?.as_ref()
     }
 
     /// Get a mesh handle by name
@@ -49,13 +86,54 @@ impl MeshManager {
 
     /// Get mesh count
     pub fn mesh_count(&self) -> usize {
-        self.meshes.len()
+        self.tracker.memory().count
+    }
+
+    /// Total size of all vertex and index buffers
+    pub fn buffer_bytes(&self) -> u64 {
+        self.tracker.memory().bytes
+    }
+
+    pub fn memory(&self) -> ResourceMemory {
+        self.tracker.memory()
+    }
+
+    pub fn tracker(&self) -> &ResourceTracker {
+        &self.tracker
+    }
+
+    pub(crate) fn tracker_mut(&mut self) -> &mut ResourceTracker {
+        &mut self.tracker
+    }
+
+    /// Mark a mesh as streamed: the memory budget may evict it, to be
+    /// uploaded again from its asset when needed
+    pub fn set_streamed(&mut self, handle: MeshHandle, streamed: bool) {
+        self.tracker.set_streamed(handle.0, streamed);
+    }
+
+    /// Drop a mesh's buffers; its name is forgotten until uploaded again
+    pub fn evict(&mut self, handle: MeshHandle) -> bool {
+        let Some(name) = self.tracker.remove(handle.0) else {
+            return false;
+        };
+        self.meshes[handle.0] = None;
+        self.mesh_map.remove(&name);
+        self.evicted.insert(name);
+        true
+    }
+
+    /// Whether `name` was evicted and needs uploading again before drawing
+    pub fn was_evicted(&self, name: &str) -> bool {
+        self.evicted.contains(name)
     }
 
     /// Clear all meshes
     pub fn clear(&mut self) {
         self.meshes.clear();
         self.mesh_map.clear();
+        self.tracker = ResourceTracker::new();
+        self.evicted.clear();
     }
 }
 
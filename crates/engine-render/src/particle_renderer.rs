@@ -1,6 +1,5 @@
 // Particle Renderer - Instanced billboard rendering
 
-use crate::MSAA_SAMPLE_COUNT;
 use anyhow::Result;
 use bytemuck::{Pod, Zeroable};
 use glam::{Mat4, Vec3};
@@ -57,6 +56,7 @@ impl ParticleRenderer {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         surface_format: wgpu::TextureFormat,
+        sample_count: u32,
         blend_mode: ParticleBlendMode,
     ) -> Result<Self> {
         // Create camera uniform buffer
@@ -297,7 +297,7 @@ impl ParticleRenderer {
                 bias: wgpu::DepthBiasState::default(),
             }),
             multisample: wgpu::MultisampleState {
-                count: MSAA_SAMPLE_COUNT,
+                count: sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
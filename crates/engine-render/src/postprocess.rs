@@ -1,6 +1,11 @@
-// Post-processing pipeline - framebuffer effects
+// Post-processing pipeline - HDR framebuffer, bloom, tone mapping and
+// anti-aliasing (TAA on the HDR scene, FXAA on the tone mapped image)
 
+use crate::gpu_mesh::{GpuMesh, GpuVertex};
+use crate::skinned_renderer::{joint_palette_layout, SkinnedRenderer, SkinnedVertexGpu};
 use anyhow::Result;
+use glam::{Mat4, Vec2};
+use serde::{Deserialize, Serialize};
 use wgpu::util::DeviceExt;
 
 /// Framebuffer for post-processing
@@ -89,20 +94,57 @@ impl Framebuffer {
     }
 }
 
-/// Post-processing pipeline
+/// Format of the scene color target and bloom chain
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Most mips in the bloom chain (the first is half the screen size)
+pub const BLOOM_MIPS: usize = 6;
+
+/// Sizes of the bloom chain's mips: half the screen, halved again down to
+/// BLOOM_MIPS levels or until a side would drop below 8 pixels
+pub fn bloom_mip_sizes(width: u32, height: u32) -> Vec<(u32, u32)> {
+    let mut sizes = Vec::new();
+    let (mut w, mut h) = ((width / 2).max(1), (height / 2).max(1));
+    while sizes.len() < BLOOM_MIPS && (sizes.is_empty() || (w >= 8 && h >= 8)) {
+        sizes.push((w, h));
+        w /= 2;
+        h /= 2;
+    }
+    sizes
+}
+
+/// Render targets of the bloom chain, recreated when the window resizes
+pub struct BloomChain {
+    pub mips: Vec<Framebuffer>,
+}
+
+impl BloomChain {
+    pub fn new(device: &wgpu::Device, width: u32, height: u32) -> Result<Self> {
+        let mips = bloom_mip_sizes(width, height)
+            .into_iter()
+            .map(|(w, h)| Framebuffer::new(device, w, h, HDR_FORMAT, false))
+            .collect::<Result<_>>()?;
+        Ok(Self { mips })
+    }
+}
+
+/// Post-processing pipeline: bloom over the HDR scene, then exposure and
+/// tone mapping into the surface
 pub struct PostProcessPipeline {
     /// Bind group layout for source texture
     pub bind_group_layout: wgpu::BindGroupLayout,
-    /// Tone mapping pipeline
-    pub tonemap_pipeline: wgpu::RenderPipeline,
-    /// Bloom bright pass pipeline
-    pub bloom_bright_pipeline: wgpu::RenderPipeline,
-    /// Bloom blur pipeline (horizontal)
-    pub bloom_blur_h_pipeline: wgpu::RenderPipeline,
-    /// Bloom blur pipeline (vertical)
-    pub bloom_blur_v_pipeline: wgpu::RenderPipeline,
-    /// Final composite pipeline
+    /// Bloom threshold pass (scene to the first mip)
+    pub bloom_prefilter_pipeline: wgpu::RenderPipeline,
+    /// Bloom downsample pass (each mip to the next)
+    pub bloom_downsample_pipeline: wgpu::RenderPipeline,
+    /// Bloom upsample pass (each mip added onto the one above)
+    pub bloom_upsample_pipeline: wgpu::RenderPipeline,
+    /// Final composite and tone mapping pipeline
     pub composite_pipeline: wgpu::RenderPipeline,
+    /// FXAA pass over the composited image
+    pub fxaa_pipeline: wgpu::RenderPipeline,
+    /// Encode gamma in the shader (surfaces without an sRGB format)
+    apply_gamma: bool,
 }
 
 impl PostProcessPipeline {
@@ -137,11 +179,6 @@ impl PostProcessPipeline {
         });
 
         // Create shaders
-        let tonemap_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Tonemap Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/tonemap.wgsl").into()),
-        });
-
         let bloom_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Bloom Shader"),
             source: wgpu::ShaderSource::Wgsl(include_str!("shaders/bloom.wgsl").into()),
@@ -152,47 +189,53 @@ impl PostProcessPipeline {
             source: wgpu::ShaderSource::Wgsl(include_str!("shaders/composite.wgsl").into()),
         });
 
-        // Pipeline layout
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Post-Process Pipeline Layout"),
+        // Bloom passes take their threshold and filter radius as push constants
+        let bloom_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Bloom Pipeline Layout"),
             bind_group_layouts: &[&bind_group_layout],
-            push_constant_ranges: &[],
+            push_constant_ranges: &[wgpu::PushConstantRange {
+                stages: wgpu::ShaderStages::FRAGMENT,
+                range: 0..16, // BloomPushConstants (4 floats = 16 bytes)
+            }],
         });
 
-        // Create pipelines
-        let tonemap_pipeline = Self::create_fullscreen_pipeline(
+        let bloom_prefilter_pipeline = Self::create_fullscreen_pipeline_with_entry(
             device,
-            &pipeline_layout,
-            &tonemap_shader,
-            surface_format,
-            "Tonemap Pipeline",
-        );
-
-        let bloom_bright_pipeline = Self::create_fullscreen_pipeline_with_entry(
-            device,
-            &pipeline_layout,
+            &bloom_layout,
             &bloom_shader,
-            wgpu::TextureFormat::Rgba16Float,
-            "Bloom Bright Pipeline",
-            "fs_bright_pass",
+            HDR_FORMAT,
+            "Bloom Prefilter Pipeline",
+            "fs_prefilter",
+            None,
         );
 
-        let bloom_blur_h_pipeline = Self::create_fullscreen_pipeline_with_entry(
+        let bloom_downsample_pipeline = Self::create_fullscreen_pipeline_with_entry(
             device,
-            &pipeline_layout,
+            &bloom_layout,
             &bloom_shader,
-            wgpu::TextureFormat::Rgba16Float,
-            "Bloom Blur H Pipeline",
-            "fs_blur_h",
+            HDR_FORMAT,
+            "Bloom Downsample Pipeline",
+            "fs_downsample",
+            None,
         );
 
-        let bloom_blur_v_pipeline = Self::create_fullscreen_pipeline_with_entry(
+        // Upsampled light is added onto the larger mip
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::REPLACE,
+        };
+        let bloom_upsample_pipeline = Self::create_fullscreen_pipeline_with_entry(
             device,
-            &pipeline_layout,
+            &bloom_layout,
             &bloom_shader,
-            wgpu::TextureFormat::Rgba16Float,
-            "Bloom Blur V Pipeline",
-            "fs_blur_v",
+            HDR_FORMAT,
+            "Bloom Upsample Pipeline",
+            "fs_upsample",
+            Some(additive),
         );
 
         // Composite pipeline needs two bind groups (scene + bloom) and push constants
@@ -201,7 +244,7 @@ impl PostProcessPipeline {
             bind_group_layouts: &[&bind_group_layout, &bind_group_layout], // Two bind groups
             push_constant_ranges: &[wgpu::PushConstantRange {
                 stages: wgpu::ShaderStages::FRAGMENT,
-                range: 0..16, // Settings struct (4 floats = 16 bytes)
+                range: 0..32, // CompositePushConstants (8 floats = 32 bytes)
             }],
         });
 
@@ -213,13 +256,31 @@ impl PostProcessPipeline {
             "Composite Pipeline",
         );
 
+        let fxaa_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("FXAA Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/fxaa.wgsl").into()),
+        });
+        let fxaa_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("FXAA Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let fxaa_pipeline = Self::create_fullscreen_pipeline(
+            device,
+            &fxaa_layout,
+            &fxaa_shader,
+            surface_format,
+            "FXAA Pipeline",
+        );
+
         Ok(Self {
             bind_group_layout,
-            tonemap_pipeline,
-            bloom_bright_pipeline,
-            bloom_blur_h_pipeline,
-            bloom_blur_v_pipeline,
+            bloom_prefilter_pipeline,
+            bloom_downsample_pipeline,
+            bloom_upsample_pipeline,
             composite_pipeline,
+            fxaa_pipeline,
+            apply_gamma: !surface_format.is_srgb(),
         })
     }
 
@@ -231,7 +292,9 @@ impl PostProcessPipeline {
         format: wgpu::TextureFormat,
         label: &str,
     ) -> wgpu::RenderPipeline {
-        Self::create_fullscreen_pipeline_with_entry(device, layout, shader, format, label, "fs_main")
+        Self::create_fullscreen_pipeline_with_entry(
+            device, layout, shader, format, label, "fs_main", None,
+        )
     }
 
     /// Create a fullscreen quad pipeline with custom entry point
@@ -242,6 +305,7 @@ impl PostProcessPipeline {
         format: wgpu::TextureFormat,
         label: &str,
         fs_entry: &str,
+        blend: Option<wgpu::BlendState>,
     ) -> wgpu::RenderPipeline {
         device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some(label),
@@ -257,7 +321,7 @@ impl PostProcessPipeline {
                 entry_point: Some(fs_entry),
                 targets: &[Some(wgpu::ColorTargetState {
                     format,
-                    blend: None,
+                    blend,
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
                 compilation_options: wgpu::PipelineCompilationOptions::default(),
@@ -304,6 +368,154 @@ impl PostProcessPipeline {
             ],
         })
     }
+
+    /// Bloom the HDR `scene` through the chain, then tone map it into `output`
+    pub fn render(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        scene: &Framebuffer,
+        bloom: &BloomChain,
+        output: &wgpu::TextureView,
+        settings: &PostProcessSettings,
+    ) {
+        let bloom_enabled = settings.enable_bloom && !bloom.mips.is_empty();
+        if bloom_enabled {
+            let push = BloomPushConstants {
+                threshold: settings.bloom_threshold,
+                knee: settings.bloom_knee,
+                radius: settings.bloom_radius,
+                _padding: 0.0,
+            };
+            let push = bytemuck::bytes_of(&push);
+
+            // Bright parts of the scene into the first mip, then halve down the chain
+            let source = self.create_bind_group(device, &scene.view, &scene.sampler);
+            self.fullscreen_pass(
+                encoder,
+                "Bloom Prefilter",
+                &self.bloom_prefilter_pipeline,
+                &bloom.mips[0].view,
+                true,
+                &[&source],
+                push,
+            );
+            for pair in bloom.mips.windows(2) {
+                let source = self.create_bind_group(device, &pair[0].view, &pair[0].sampler);
+                self.fullscreen_pass(
+                    encoder,
+                    "Bloom Downsample",
+                    &self.bloom_downsample_pipeline,
+                    &pair[1].view,
+                    true,
+                    &[&source],
+                    push,
+                );
+            }
+            // Blur back up, each mip adding its light onto the one above
+            for pair in bloom.mips.windows(2).rev() {
+                let source = self.create_bind_group(device, &pair[1].view, &pair[1].sampler);
+                self.fullscreen_pass(
+                    encoder,
+                    "Bloom Upsample",
+                    &self.bloom_upsample_pipeline,
+                    &pair[0].view,
+                    false,
+                    &[&source],
+                    push,
+                );
+            }
+        }
+
+        let scene_group = self.create_bind_group(device, &scene.view, &scene.sampler);
+        // Without bloom the scene stands in for it and is weighted out
+        let bloom_group = match bloom.mips.first().filter(|_| bloom_enabled) {
+            Some(mip) => self.create_bind_group(device, &mip.view, &mip.sampler),
+            None => self.create_bind_group(device, &scene.view, &scene.sampler),
+        };
+        let push = CompositePushConstants::new(settings, bloom_enabled, self.apply_gamma);
+        self.fullscreen_pass(
+            encoder,
+            "Composite",
+            &self.composite_pipeline,
+            output,
+            true,
+            &[&scene_group, &bloom_group],
+            bytemuck::bytes_of(&push),
+        );
+    }
+
+    /// Anti-alias the composited `source` (a surface format framebuffer) into `output`
+    pub fn render_fxaa(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &Framebuffer,
+        output: &wgpu::TextureView,
+    ) {
+        let source = self.create_bind_group(device, &source.view, &source.sampler);
+        self.fullscreen_pass(
+            encoder,
+            "FXAA",
+            &self.fxaa_pipeline,
+            output,
+            true,
+            &[&source],
+            &[],
+        );
+    }
+
+    /// Draw a fullscreen triangle into `target`
+    #[allow(clippy::too_many_arguments)]
+    fn fullscreen_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        label: &str,
+        pipeline: &wgpu::RenderPipeline,
+        target: &wgpu::TextureView,
+        clear: bool,
+        bind_groups: &[&wgpu::BindGroup],
+        push_constants: &[u8],
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: if clear {
+                        wgpu::LoadOp::Clear(wgpu::Color::BLACK)
+                    } else {
+                        wgpu::LoadOp::Load
+                    },
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(pipeline);
+        for (index, bind_group) in bind_groups.iter().enumerate() {
+            pass.set_bind_group(index as u32, *bind_group, &[]);
+        }
+        if !push_constants.is_empty() {
+            pass.set_push_constants(wgpu::ShaderStages::FRAGMENT, 0, push_constants);
+        }
+        pass.draw(0..3, 0..1);
+    }
+}
+
+/// Push constants for bloom shader
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BloomPushConstants {
+    pub threshold: f32,
+    pub knee: f32,
+    /// Upsample filter radius in texels
+    pub radius: f32,
+    pub _padding: f32,
 }
 
 /// Push constants for composite shader
@@ -312,32 +524,739 @@ impl PostProcessPipeline {
 pub struct CompositePushConstants {
     pub bloom_intensity: f32,
     pub bloom_enabled: f32, // 0.0 or 1.0
-    pub _padding: [f32; 2],
+    pub exposure: f32,
+    /// ToneMapper as a float (see `ToneMapper::shader_index`)
+    pub tone_mapper: f32,
+    pub apply_gamma: f32, // 0.0 or 1.0
+    pub _padding: [f32; 3],
+}
+
+impl CompositePushConstants {
+    pub fn new(settings: &PostProcessSettings, bloom_enabled: bool, apply_gamma: bool) -> Self {
+        Self {
+            bloom_intensity: settings.bloom_intensity,
+            bloom_enabled: if bloom_enabled { 1.0 } else { 0.0 },
+            exposure: settings.exposure,
+            tone_mapper: settings.tone_mapper.shader_index(),
+            apply_gamma: if apply_gamma { 1.0 } else { 0.0 },
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+/// Curve mapping HDR scene color into the displayable range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToneMapper {
+    /// ACES filmic curve: contrasty, rolls off highlights
+    #[default]
+    Aces,
+    /// Reinhard: soft, keeps hues in highlights
+    Reinhard,
+    /// No curve, colors over 1 clip
+    None,
+}
+
+impl ToneMapper {
+    pub const ALL: [ToneMapper; 3] = [ToneMapper::Aces, ToneMapper::Reinhard, ToneMapper::None];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ToneMapper::Aces => "ACES",
+            ToneMapper::Reinhard => "Reinhard",
+            ToneMapper::None => "None",
+        }
+    }
+
+    /// Matches the branches in composite.wgsl
+    pub fn shader_index(&self) -> f32 {
+        match self {
+            ToneMapper::Aces => 0.0,
+            ToneMapper::Reinhard => 1.0,
+            ToneMapper::None => 2.0,
+        }
+    }
 }
 
 /// Post-processing settings
 #[derive(Debug, Clone)]
 pub struct PostProcessSettings {
-    /// Enable tone mapping
-    pub enable_tonemap: bool,
+    /// Tone mapping curve
+    pub tone_mapper: ToneMapper,
     /// Tone mapping exposure
     pub exposure: f32,
     /// Enable bloom
     pub enable_bloom: bool,
     /// Bloom threshold
     pub bloom_threshold: f32,
+    /// Softens the threshold over this much brightness below it
+    pub bloom_knee: f32,
     /// Bloom intensity
     pub bloom_intensity: f32,
+    /// Bloom spread, in texels of each mip
+    pub bloom_radius: f32,
 }
 
 impl Default for PostProcessSettings {
     fn default() -> Self {
         Self {
-            enable_tonemap: true,
+            tone_mapper: ToneMapper::Aces,
             exposure: 1.0,
             enable_bloom: false,
             bloom_threshold: 1.0,
+            bloom_knee: 0.5,
             bloom_intensity: 0.3,
+            bloom_radius: 1.0,
+        }
+    }
+}
+
+/// How edges are anti-aliased
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AntiAliasingMode {
+    /// No anti-aliasing
+    None,
+    /// Multisampling: smooths geometry edges, not specular or shader aliasing
+    #[default]
+    Msaa,
+    /// Fast approximate AA: a cheap edge blur after tone mapping
+    Fxaa,
+    /// Temporal AA: accumulates jittered frames, smooths shading too
+    Taa,
+}
+
+impl AntiAliasingMode {
+    pub const ALL: [AntiAliasingMode; 4] = [
+        AntiAliasingMode::None,
+        AntiAliasingMode::Msaa,
+        AntiAliasingMode::Fxaa,
+        AntiAliasingMode::Taa,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AntiAliasingMode::None => "Off",
+            AntiAliasingMode::Msaa => "MSAA",
+            AntiAliasingMode::Fxaa => "FXAA",
+            AntiAliasingMode::Taa => "TAA",
+        }
+    }
+
+    /// Sample count to create the renderer with
+    pub fn sample_count(&self, msaa_samples: u32) -> u32 {
+        match self {
+            AntiAliasingMode::Msaa => msaa_samples,
+            _ => 1,
+        }
+    }
+
+    /// The mode in effect for a renderer created with `sample_count`:
+    /// turning MSAA on or off needs the pipelines recreated, so until then
+    /// a multisampled renderer keeps MSAA and a single-sampled one has none
+    pub fn effective(&self, sample_count: u32) -> Self {
+        match (self, sample_count > 1) {
+            (_, true) => AntiAliasingMode::Msaa,
+            (AntiAliasingMode::Msaa, false) => AntiAliasingMode::None,
+            (mode, false) => *mode,
+        }
+    }
+}
+
+/// Frames in the TAA jitter cycle
+pub const TAA_JITTER_SAMPLES: u64 = 8;
+
+/// Weight of the history in each TAA frame
+pub const TAA_FEEDBACK: f32 = 0.9;
+
+/// Format of the TAA velocity buffer (UV offset to last frame's position)
+pub const VELOCITY_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rg16Float;
+
+/// Halton low-discrepancy sequence in `base`, `index` from 1
+pub fn halton(mut index: u32, base: u32) -> f32 {
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+/// Sub-pixel projection offset of TAA frame `frame`, in pixels within [-0.5, 0.5)
+pub fn taa_jitter(frame: u64) -> Vec2 {
+    let index = (frame % TAA_JITTER_SAMPLES) as u32 + 1;
+    Vec2::new(halton(index, 2) - 0.5, halton(index, 3) - 0.5)
+}
+
+/// Matrix that moves a view projection by `jitter` pixels of a `viewport`-sized view
+pub fn jitter_matrix(jitter: Vec2, viewport: Vec2) -> Mat4 {
+    Mat4::from_translation((jitter * 2.0 / viewport.max(Vec2::ONE)).extend(0.0))
+}
+
+/// Uniforms shared by the TAA velocity and resolve passes
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TaaUniforms {
+    pub inverse_view_proj: [[f32; 4]; 4],
+    pub previous_view_proj: [[f32; 4]; 4],
+    /// This frame, jittered
+    pub view_proj: [[f32; 4]; 4],
+    /// x, y, width, height in pixels
+    pub viewport: [f32; 4],
+    /// NDC offset of this frame's projection
+    pub jitter: [f32; 2],
+    pub target_size: [f32; 2],
+    pub feedback: f32,
+    pub reset: f32, // 0.0 or 1.0
+    pub _padding: [f32; 2],
+}
+
+/// This frame's and last frame's model matrix of a moving object, per
+/// instance of the TAA motion pass
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MotionInstance {
+    pub model: [[f32; 4]; 4],
+    pub previous_model: [[f32; 4]; 4],
+}
+
+impl MotionInstance {
+    pub fn new(model: Mat4, previous_model: Mat4) -> Self {
+        Self {
+            model: model.to_cols_array_2d(),
+            previous_model: previous_model.to_cols_array_2d(),
+        }
+    }
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 8] = wgpu::vertex_attr_array![
+            6 => Float32x4, 7 => Float32x4, 8 => Float32x4, 9 => Float32x4,
+            10 => Float32x4, 11 => Float32x4, 12 => Float32x4, 13 => Float32x4,
+        ];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<MotionInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRIBUTES,
+        }
+    }
+}
+
+/// Objects drawn over the camera's motion in the velocity buffer: meshes
+/// whose model matrix changed since last frame, and skinned instances
+#[derive(Default)]
+pub struct ObjectMotion<'a> {
+    pub meshes: Vec<(&'a GpuMesh, MotionInstance)>,
+    /// Model path and instance of each skinned draw
+    pub skinned: Option<(&'a SkinnedRenderer, &'a [(String, u64)])>,
+}
+
+impl ObjectMotion<'_> {
+    fn is_empty(&self) -> bool {
+        self.meshes.is_empty() && self.skinned.is_none_or(|(_, draws)| draws.is_empty())
+    }
+}
+
+/// Temporal anti-aliasing: the scene is drawn with a jittered projection,
+/// then blended with the previous frames (reprojected through a velocity
+/// buffer) into a history buffer that replaces the scene for bloom and
+/// tone mapping
+pub struct TemporalAa {
+    velocity_layout: wgpu::BindGroupLayout,
+    resolve_layout: wgpu::BindGroupLayout,
+    motion_layout: wgpu::BindGroupLayout,
+    velocity_pipeline: wgpu::RenderPipeline,
+    resolve_pipeline: wgpu::RenderPipeline,
+    mesh_motion_pipeline: wgpu::RenderPipeline,
+    skinned_motion_pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    sampler: wgpu::Sampler,
+    /// Per-pixel motion since last frame
+    pub velocity: Framebuffer,
+    history: [Framebuffer; 2],
+    /// History written last frame
+    current: usize,
+    frame: u64,
+    /// Unjittered view projection and viewport of last frame (None = no usable history)
+    previous: Option<(Mat4, [f32; 4])>,
+    /// Viewport of this frame
+    viewport: [f32; 4],
+}
+
+impl TemporalAa {
+    pub fn new(device: &wgpu::Device, width: u32, height: u32) -> Result<Self> {
+        let uniform_entry = wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let texture_entry = |binding, sample_type| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let color = wgpu::TextureSampleType::Float { filterable: true };
+
+        // Bindings match taa.wgsl: 1-4 for the resolve, 5 for the velocity pass
+        let velocity_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("TAA Velocity Bind Group Layout"),
+            entries: &[
+                uniform_entry,
+                texture_entry(5, wgpu::TextureSampleType::Depth),
+            ],
+        });
+        let resolve_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("TAA Resolve Bind Group Layout"),
+            entries: &[
+                uniform_entry,
+                texture_entry(1, color),
+                texture_entry(2, color),
+                texture_entry(3, color),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("TAA Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/taa.wgsl").into()),
+        });
+        let pipeline = |layout: &wgpu::BindGroupLayout, format, label: &str, entry: &str| {
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(label),
+                bind_group_layouts: &[layout],
+                push_constant_ranges: &[],
+            });
+            PostProcessPipeline::create_fullscreen_pipeline_with_entry(
+                device,
+                &pipeline_layout,
+                &shader,
+                format,
+                label,
+                entry,
+                None,
+            )
+        };
+        let velocity_pipeline = pipeline(
+            &velocity_layout,
+            VELOCITY_FORMAT,
+            "TAA Velocity Pipeline",
+            "fs_velocity",
+        );
+        let resolve_pipeline = pipeline(
+            &resolve_layout,
+            HDR_FORMAT,
+            "TAA Resolve Pipeline",
+            "fs_resolve",
+        );
+
+        // Moving objects are drawn again against the scene's depth
+        let motion_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("TAA Motion Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ..uniform_entry
+            }],
+        });
+        let joint_layout = joint_palette_layout(device);
+        let motion_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("TAA Motion Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/taa_motion.wgsl").into()),
+        });
+        let motion_pipeline = |layouts: &[&wgpu::BindGroupLayout],
+                               label: &str,
+                               entry: &str,
+                               buffers: &[wgpu::VertexBufferLayout]| {
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(label),
+                bind_group_layouts: layouts,
+                push_constant_ranges: &[],
+            });
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &motion_shader,
+                    entry_point: Some(entry),
+                    buffers,
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &motion_shader,
+                    entry_point: Some("fs_motion"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: VELOCITY_FORMAT,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    cull_mode: None,
+                    ..Default::default()
+                },
+                // Equal depth up to the rounding of a different shader's transform
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::LessEqual,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState {
+                        constant: -2,
+                        slope_scale: -1.0,
+                        clamp: 0.0,
+                    },
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        };
+        let mesh_motion_pipeline = motion_pipeline(
+            &[&motion_layout],
+            "TAA Mesh Motion Pipeline",
+            "vs_mesh",
+            &[GpuVertex::desc(), MotionInstance::desc()],
+        );
+        let skinned_motion_pipeline = motion_pipeline(
+            &[&motion_layout, &joint_layout],
+            "TAA Skinned Motion Pipeline",
+            "vs_skinned",
+            &[SkinnedVertexGpu::desc()],
+        );
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("TAA Uniform Buffer"),
+            size: std::mem::size_of::<TaaUniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("TAA History Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Ok(Self {
+            velocity_layout,
+            resolve_layout,
+            motion_layout,
+            velocity_pipeline,
+            resolve_pipeline,
+            mesh_motion_pipeline,
+            skinned_motion_pipeline,
+            uniform_buffer,
+            sampler,
+            velocity: Framebuffer::new(device, width, height, VELOCITY_FORMAT, false)?,
+            history: [
+                Framebuffer::new(device, width, height, HDR_FORMAT, false)?,
+                Framebuffer::new(device, width, height, HDR_FORMAT, false)?,
+            ],
+            current: 0,
+            frame: 0,
+            previous: None,
+            viewport: [0.0, 0.0, width as f32, height as f32],
+        })
+    }
+
+    /// Recreate the buffers for a new target size, dropping the history
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) -> Result<()> {
+        self.velocity = Framebuffer::new(device, width, height, VELOCITY_FORMAT, false)?;
+        self.history = [
+            Framebuffer::new(device, width, height, HDR_FORMAT, false)?,
+            Framebuffer::new(device, width, height, HDR_FORMAT, false)?,
+        ];
+        self.reset();
+        Ok(())
+    }
+
+    /// Drop the history (after a camera cut or when TAA was off)
+    pub fn reset(&mut self) {
+        self.previous = None;
+    }
+
+    /// This frame's projection offset in pixels
+    pub fn jitter(&self) -> Vec2 {
+        taa_jitter(self.frame)
+    }
+
+    /// Set up this frame's resolve of a scene drawn with
+    /// `jitter_matrix(self.jitter(), ..) * view_proj` into `viewport` (x, y,
+    /// width, height), and advance to the next jitter
+    pub fn prepare(
+        &mut self,
+        queue: &wgpu::Queue,
+        scene: &Framebuffer,
+        view_proj: Mat4,
+        viewport: [f32; 4],
+    ) {
+        let viewport_size = Vec2::new(viewport[2], viewport[3]);
+        let jitter = self.jitter();
+        let jittered = jitter_matrix(jitter, viewport_size) * view_proj;
+        // A changed viewport (the Game tab opening) invalidates the history
+        let previous = self
+            .previous
+            .filter(|(_, previous_viewport)| *previous_viewport == viewport);
+        let uniforms = TaaUniforms {
+            inverse_view_proj: jittered.inverse().to_cols_array_2d(),
+            previous_view_proj: previous.map_or(view_proj, |(m, _)| m).to_cols_array_2d(),
+            view_proj: jittered.to_cols_array_2d(),
+            viewport,
+            jitter: (jitter * 2.0 / viewport_size.max(Vec2::ONE)).to_array(),
+            target_size: [scene.texture.width() as f32, scene.texture.height() as f32],
+            feedback: TAA_FEEDBACK,
+            reset: if previous.is_some() { 0.0 } else { 1.0 },
+            _padding: [0.0; 2],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniforms));
+
+        self.current = 1 - self.current;
+        self.frame += 1;
+        self.previous = Some((view_proj, viewport));
+        self.viewport = viewport;
+    }
+
+    /// The anti-aliased scene `record` writes this frame
+    pub fn output(&self) -> &Framebuffer {
+        &self.history[self.current]
+    }
+
+    /// Record the velocity, object motion and resolve passes for `scene` with
+    /// `depth` (not multisampled), blending into `output()` against the history
+    pub fn record(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        scene: &Framebuffer,
+        depth: &wgpu::TextureView,
+        motion: &ObjectMotion,
+    ) {
+        let velocity_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("TAA Velocity Bind Group"),
+            layout: &self.velocity_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(depth),
+                },
+            ],
+        });
+        Self::pass(
+            encoder,
+            "TAA Velocity",
+            &self.velocity_pipeline,
+            &self.velocity.view,
+            &velocity_group,
+        );
+        self.record_motion(device, encoder, depth, motion);
+
+        let (history, output) = (&self.history[1 - self.current], &self.history[self.current]);
+        let resolve_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("TAA Resolve Bind Group"),
+            layout: &self.resolve_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&scene.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&history.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&self.velocity.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+        Self::pass(
+            encoder,
+            "TAA Resolve",
+            &self.resolve_pipeline,
+            &output.view,
+            &resolve_group,
+        );
+    }
+
+    /// Draw the objects that moved by themselves over the camera's velocity
+    fn record_motion(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        depth: &wgpu::TextureView,
+        motion: &ObjectMotion,
+    ) {
+        if motion.is_empty() {
+            return;
+        }
+        let instances: Vec<MotionInstance> = motion.meshes.iter().map(|(_, instance)| *instance).collect();
+        let instance_buffer = (!instances.is_empty()).then(|| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("TAA Motion Instance Buffer"),
+                contents: bytemuck::cast_slice(&instances),
+                usage: wgpu::BufferUsages::VERTEX,
+            })
+        });
+        let motion_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("TAA Motion Bind Group"),
+            layout: &self.motion_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: self.uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("TAA Motion"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.velocity.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        let [x, y, width, height] = self.viewport;
+        pass.set_viewport(x, y, width, height, 0.0, 1.0);
+        pass.set_bind_group(0, &motion_group, &[]);
+
+        if let Some(instance_buffer) = &instance_buffer {
+            pass.set_pipeline(&self.mesh_motion_pipeline);
+            pass.set_vertex_buffer(1, instance_buffer.slice(..));
+            for (i, (mesh, _)) in motion.meshes.iter().enumerate() {
+                let instance = i as u32;
+                pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                pass.draw_indexed(0..mesh.num_indices, 0, instance..instance + 1);
+            }
+        }
+        if let Some((skinned_renderer, draws)) = motion.skinned {
+            pass.set_pipeline(&self.skinned_motion_pipeline);
+            for (path, instance) in draws {
+                skinned_renderer.draw_with_joints(&mut pass, path, *instance);
+            }
+        }
+    }
+
+    fn pass(
+        encoder: &mut wgpu::CommandEncoder,
+        label: &str,
+        pipeline: &wgpu::RenderPipeline,
+        target: &wgpu::TextureView,
+        bind_group: &wgpu::BindGroup,
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bloom_chain_halves_down_to_small_mips() {
+        assert_eq!(
+            bloom_mip_sizes(1920, 1080),
+            vec![
+                (960, 540),
+                (480, 270),
+                (240, 135),
+                (120, 67),
+                (60, 33),
+                (30, 16)
+            ]
+        );
+        // Small targets stop before a side drops under 8 pixels, but keep one mip
+        assert_eq!(bloom_mip_sizes(64, 32), vec![(32, 16), (16, 8)]);
+        assert_eq!(bloom_mip_sizes(4, 4), vec![(2, 2)]);
+    }
+
+    #[test]
+    fn test_taa_jitter_covers_the_pixel() {
+        assert_eq!(halton(1, 2), 0.5);
+        assert_eq!(halton(3, 3), 1.0 / 9.0);
+
+        let jitters: Vec<Vec2> = (0..TAA_JITTER_SAMPLES).map(taa_jitter).collect();
+        assert!(jitters.iter().all(|j| j.abs().max_element() <= 0.5));
+        for (i, a) in jitters.iter().enumerate() {
+            assert!(jitters[i + 1..].iter().all(|b| a != b));
         }
+        assert_eq!(taa_jitter(TAA_JITTER_SAMPLES), jitters[0]);
+
+        // Half a pixel of a 100 pixel wide view is 0.01 in NDC
+        let offset = jitter_matrix(Vec2::new(0.5, 0.0), Vec2::new(100.0, 50.0))
+            .project_point3(glam::Vec3::ZERO);
+        assert!((offset.x - 0.01).abs() < 1e-6);
+
+        // Switching MSAA on or off waits for the renderer to be recreated
+        assert_eq!(AntiAliasingMode::Taa.effective(4), AntiAliasingMode::Msaa);
+        assert_eq!(AntiAliasingMode::Msaa.effective(1), AntiAliasingMode::None);
+        assert_eq!(AntiAliasingMode::Fxaa.effective(1), AntiAliasingMode::Fxaa);
+        assert_eq!(AntiAliasingMode::Taa.sample_count(4), 1);
+    }
+
+    #[test]
+    fn test_taa_buffers_match_the_shader_layouts() {
+        // Three matrices, the viewport, then two vec2s and two scalars padded to 16 bytes
+        assert_eq!(std::mem::size_of::<TaaUniforms>(), 3 * 64 + 16 + 32);
+        let desc = MotionInstance::desc();
+        assert_eq!(desc.array_stride, 128);
+        assert_eq!(desc.attributes.last().map(|a| (a.shader_location, a.offset)), Some((13, 112)));
     }
 }
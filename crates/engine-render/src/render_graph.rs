@@ -0,0 +1,442 @@
+// Render graph - passes declare the resources they read and write, and the
+// graph orders them, provides transient targets and records them
+//
+// Resources are named. Imported resources (the swapchain view, the HDR scene
+// target) are owned by the caller; transient ones are framebuffers the graph
+// takes from a `TransientPool`, shared by passes whose lifetimes don't
+// overlap and kept across frames while they're still asked for.
+//
+// Ordering: for each resource, passes that only write it run first, then
+// passes that read and write it (in the order they were added), then passes
+// that only read it.
+
+use crate::postprocess::Framebuffer;
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+
+/// Size and format of a transient target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransientDesc {
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+}
+
+impl TransientDesc {
+    pub fn new(width: u32, height: u32, format: wgpu::TextureFormat) -> Self {
+        Self {
+            width,
+            height,
+            format,
+        }
+    }
+}
+
+enum GraphResource<'a> {
+    View(&'a wgpu::TextureView),
+    Framebuffer(&'a Framebuffer),
+    Transient(TransientDesc),
+}
+
+type PassFn<'a> = Box<dyn FnOnce(&mut PassContext) + 'a>;
+
+/// A pass: what it reads and writes, and the commands it records
+pub struct GraphPass<'a> {
+    name: &'static str,
+    reads: Vec<&'static str>,
+    writes: Vec<&'static str>,
+    execute: PassFn<'a>,
+}
+
+impl<'a> GraphPass<'a> {
+    pub fn new(name: &'static str, execute: impl FnOnce(&mut PassContext) + 'a) -> Self {
+        Self {
+            name,
+            reads: Vec::new(),
+            writes: Vec::new(),
+            execute: Box::new(execute),
+        }
+    }
+
+    pub fn with_read(mut self, resource: &'static str) -> Self {
+        self.reads.push(resource);
+        self
+    }
+
+    pub fn with_write(mut self, resource: &'static str) -> Self {
+        self.writes.push(resource);
+        self
+    }
+
+    fn uses(&self, resource: &str) -> bool {
+        self.reads.contains(&resource) || self.writes.contains(&resource)
+    }
+}
+
+/// What a pass records with: the GPU, the frame's encoder and the
+/// resources it declared
+pub struct PassContext<'r> {
+    pub device: &'r wgpu::Device,
+    pub queue: &'r wgpu::Queue,
+    pub encoder: &'r mut wgpu::CommandEncoder,
+    resources: &'r HashMap<&'static str, (&'r wgpu::TextureView, Option<&'r Framebuffer>)>,
+}
+
+impl<'r> PassContext<'r> {
+    /// View of a resource the pass declared
+    pub fn view(&self, name: &str) -> &'r wgpu::TextureView {
+        match self.resources.get(name) {
+            Some((view, _)) => view,
+            None => panic!("render graph pass used undeclared resource '{}'", name),
+        }
+    }
+
+    /// Framebuffer (view and sampler) of a transient or imported framebuffer
+    pub fn framebuffer(&self, name: &str) -> &'r Framebuffer {
+        match self.resources.get(name) {
+            Some((_, Some(framebuffer))) => framebuffer,
+            _ => panic!(
+                "render graph resource '{}' is not a declared framebuffer",
+                name
+            ),
+        }
+    }
+}
+
+/// One frame's passes and resources
+#[derive(Default)]
+pub struct RenderGraph<'a> {
+    passes: Vec<GraphPass<'a>>,
+    resources: HashMap<&'static str, GraphResource<'a>>,
+}
+
+impl<'a> RenderGraph<'a> {
+    pub fn new() -> Self {
+        Self {
+            passes: Vec::new(),
+            resources: HashMap::new(),
+        }
+    }
+
+    /// Use a texture view owned outside the graph (e.g. the swapchain)
+    pub fn import_view(&mut self, name: &'static str, view: &'a wgpu::TextureView) {
+        self.resources.insert(name, GraphResource::View(view));
+    }
+
+    /// Use a framebuffer owned outside the graph, so passes can sample it
+    pub fn import_framebuffer(&mut self, name: &'static str, framebuffer: &'a Framebuffer) {
+        self.resources
+            .insert(name, GraphResource::Framebuffer(framebuffer));
+    }
+
+    /// Declare a target that only lives for this frame's passes
+    pub fn create_transient(&mut self, name: &'static str, desc: TransientDesc) {
+        self.resources.insert(name, GraphResource::Transient(desc));
+    }
+
+    pub fn add_pass(&mut self, pass: GraphPass<'a>) {
+        self.passes.push(pass);
+    }
+
+    /// Order the passes by their dependencies (indices into the added passes)
+    pub fn compile(&self) -> Result<Vec<usize>> {
+        for pass in &self.passes {
+            for resource in pass.reads.iter().chain(&pass.writes) {
+                if !self.resources.contains_key(resource) {
+                    bail!("pass '{}' uses unknown resource '{}'", pass.name, resource);
+                }
+            }
+        }
+
+        // Edges from each pass to the passes that must run after it
+        let count = self.passes.len();
+        let mut after: Vec<Vec<usize>> = vec![Vec::new(); count];
+        let mut incoming = vec![0usize; count];
+        for resource in self.resources.keys() {
+            let stage = |pass: &GraphPass| match (
+                pass.reads.contains(resource),
+                pass.writes.contains(resource),
+            ) {
+                (false, true) => 0,
+                (true, true) => 1,
+                _ => 2,
+            };
+            let users: Vec<usize> = (0..count)
+                .filter(|&i| self.passes[i].uses(resource))
+                .collect();
+            for (n, &a) in users.iter().enumerate() {
+                for &b in &users[n + 1..] {
+                    let (stage_a, stage_b) = (stage(&self.passes[a]), stage(&self.passes[b]));
+                    // Readers don't depend on each other; read-writes keep their order
+                    let edge = match stage_a.cmp(&stage_b) {
+                        std::cmp::Ordering::Less => Some((a, b)),
+                        std::cmp::Ordering::Greater => Some((b, a)),
+                        std::cmp::Ordering::Equal if stage_a == 1 => Some((a, b)),
+                        std::cmp::Ordering::Equal => None,
+                    };
+                    if let Some((from, to)) = edge {
+                        if !after[from].contains(&to) {
+                            after[from].push(to);
+                            incoming[to] += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Topological sort, taking the earliest-added ready pass first
+        let mut order = Vec::with_capacity(count);
+        let mut done = vec![false; count];
+        while order.len() < count {
+            let Some(next) = (0..count).find(|&i| !done[i] && incoming[i] == 0) else {
+                let stuck: Vec<&str> = (0..count)
+                    .filter(|&i| !done[i])
+                    .map(|i| self.passes[i].name)
+                    .collect();
+                bail!("render graph has a cycle between passes {:?}", stuck);
+            };
+            done[next] = true;
+            order.push(next);
+            for &to in &after[next] {
+                incoming[to] -= 1;
+            }
+        }
+        Ok(order)
+    }
+
+    /// Which pooled target each transient uses: transients with the same
+    /// desc share a slot when one's last pass comes before the other's first
+    fn transient_slots(&self, order: &[usize]) -> HashMap<&'static str, (TransientDesc, usize)> {
+        let mut lifetimes: Vec<(&'static str, TransientDesc, usize, usize)> = Vec::new();
+        for (&name, resource) in &self.resources {
+            let GraphResource::Transient(desc) = resource else {
+                continue;
+            };
+            let steps: Vec<usize> = order
+                .iter()
+                .enumerate()
+                .filter(|(_, &pass)| self.passes[pass].uses(name))
+                .map(|(step, _)| step)
+                .collect();
+            if let (Some(&first), Some(&last)) = (steps.first(), steps.last()) {
+                lifetimes.push((name, *desc, first, last));
+            }
+        }
+        lifetimes.sort_by_key(|&(name, _, first, _)| (first, name));
+
+        // Per desc, the step each slot is busy until
+        let mut busy_until: HashMap<TransientDesc, Vec<usize>> = HashMap::new();
+        let mut slots = HashMap::new();
+        for (name, desc, first, last) in lifetimes {
+            let busy = busy_until.entry(desc).or_default();
+            let slot = match busy.iter().position(|&until| until < first) {
+                Some(slot) => {
+                    busy[slot] = last;
+                    slot
+                }
+                None => {
+                    busy.push(last);
+                    busy.len() - 1
+                }
+            };
+            slots.insert(name, (desc, slot));
+        }
+        slots
+    }
+
+    /// Record every pass in dependency order. `on_pass_end` runs after each
+    /// pass (for profiler marks).
+    pub fn execute(
+        self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        pool: &mut TransientPool,
+        mut on_pass_end: impl FnMut(&'static str, &mut wgpu::CommandEncoder),
+    ) -> Result<()> {
+        let order = self.compile()?;
+        let slots = self.transient_slots(&order);
+        pool.prepare(device, slots.values().copied())?;
+
+        let mut views = HashMap::new();
+        for (&name, resource) in &self.resources {
+            let entry = match resource {
+                GraphResource::View(view) => (*view, None),
+                GraphResource::Framebuffer(framebuffer) => (&framebuffer.view, Some(*framebuffer)),
+                GraphResource::Transient(_) => {
+                    // Transients no pass uses have no slot
+                    let Some(&(desc, slot)) = slots.get(name) else {
+                        continue;
+                    };
+                    let framebuffer = &pool.targets[&desc][slot];
+                    (&framebuffer.view, Some(framebuffer))
+                }
+            };
+            views.insert(name, entry);
+        }
+
+        let mut passes: Vec<Option<GraphPass>> = self.passes.into_iter().map(Some).collect();
+        for index in order {
+            let Some(pass) = passes[index].take() else {
+                continue;
+            };
+            let resources = pass
+                .reads
+                .iter()
+                .chain(&pass.writes)
+                .filter_map(|&name| Some((name, *views.get(name)?)))
+                .collect();
+            let mut context = PassContext {
+                device,
+                queue,
+                encoder: &mut *encoder,
+                resources: &resources,
+            };
+            (pass.execute)(&mut context);
+            on_pass_end(pass.name, encoder);
+        }
+        Ok(())
+    }
+}
+
+/// Transient targets kept between frames, by desc
+#[derive(Default)]
+pub struct TransientPool {
+    targets: HashMap<TransientDesc, Vec<Framebuffer>>,
+}
+
+impl TransientPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make sure every slot exists, and drop targets this frame doesn't use
+    /// (e.g. the old size after a resize)
+    fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        slots: impl Iterator<Item = (TransientDesc, usize)>,
+    ) -> Result<()> {
+        let mut needed: HashMap<TransientDesc, usize> = HashMap::new();
+        for (desc, slot) in slots {
+            let count = needed.entry(desc).or_default();
+            *count = (*count).max(slot + 1);
+        }
+        self.targets.retain(|desc, _| needed.contains_key(desc));
+        for (desc, count) in needed {
+            let targets = self.targets.entry(desc).or_default();
+            while targets.len() < count {
+                targets.push(Framebuffer::new(
+                    device,
+                    desc.width,
+                    desc.height,
+                    desc.format,
+                    false,
+                )?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of targets held
+    pub fn len(&self) -> usize {
+        self.targets.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(graph: &RenderGraph) -> Vec<&'static str> {
+        graph
+            .compile()
+            .unwrap()
+            .into_iter()
+            .map(|i| graph.passes[i].name)
+            .collect()
+    }
+
+    #[test]
+    fn test_passes_order_by_dependencies() {
+        let desc = TransientDesc::new(64, 64, wgpu::TextureFormat::Rgba16Float);
+        let mut graph = RenderGraph::new();
+        graph.create_transient("scene", desc);
+        graph.create_transient("bloom", desc);
+        graph.create_transient("surface", desc);
+        // Added out of order: the graph sorts them
+        graph.add_pass(
+            GraphPass::new("Overlay", |_| {})
+                .with_read("surface")
+                .with_write("surface"),
+        );
+        graph.add_pass(
+            GraphPass::new("Tonemap", |_| {})
+                .with_read("scene")
+                .with_read("bloom")
+                .with_write("surface"),
+        );
+        graph.add_pass(
+            GraphPass::new("Bloom", |_| {})
+                .with_read("scene")
+                .with_write("bloom"),
+        );
+        graph.add_pass(GraphPass::new("Scene", |_| {}).with_write("scene"));
+        graph.add_pass(
+            GraphPass::new("Gizmos", |_| {})
+                .with_read("surface")
+                .with_write("surface"),
+        );
+        assert_eq!(
+            names(&graph),
+            vec!["Scene", "Bloom", "Tonemap", "Overlay", "Gizmos"]
+        );
+
+        graph.add_pass(
+            GraphPass::new("Feedback", |_| {})
+                .with_read("surface")
+                .with_write("scene"),
+        );
+        assert!(graph.compile().unwrap_err().to_string().contains("cycle"));
+    }
+
+    #[test]
+    fn test_transients_share_targets_when_lifetimes_dont_overlap() {
+        let desc = TransientDesc::new(64, 64, wgpu::TextureFormat::Rgba16Float);
+        let mut graph = RenderGraph::new();
+        for name in ["a", "b", "c"] {
+            graph.create_transient(name, desc);
+        }
+        graph.create_transient("half", TransientDesc::new(32, 32, desc.format));
+        graph.add_pass(GraphPass::new("First", |_| {}).with_write("a"));
+        graph.add_pass(
+            GraphPass::new("Second", |_| {})
+                .with_read("a")
+                .with_write("b"),
+        );
+        graph.add_pass(
+            GraphPass::new("Third", |_| {})
+                .with_read("b")
+                .with_write("c")
+                .with_write("half"),
+        );
+
+        let slots = graph.transient_slots(&graph.compile().unwrap());
+        // "a" is done by the time "c" is written; "b" overlaps both
+        assert_eq!(slots["a"], slots["c"]);
+        assert_ne!(slots["a"], slots["b"]);
+        assert_eq!(slots["half"], (TransientDesc::new(32, 32, desc.format), 0));
+    }
+
+    #[test]
+    fn test_unknown_resource_is_an_error() {
+        let mut graph = RenderGraph::new();
+        graph.add_pass(GraphPass::new("SSAO", |_| {}).with_read("depth"));
+        let error = graph.compile().unwrap_err().to_string();
+        assert!(error.contains("SSAO") && error.contains("depth"));
+    }
+}
@@ -0,0 +1,131 @@
+// Render statistics - per-frame draw counters and GPU memory estimates
+//
+// The frame loop records every draw it issues; memory figures come from the
+// sizes of the buffers and textures the managers hold, so they cover what
+// the engine allocated rather than what the driver reports.
+
+use crate::culling::CullingStats;
+use crate::memory_budget::ResourceMemory;
+
+/// Counters for one rendered frame
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RenderStats {
+    /// Draw calls issued across all passes
+    pub draw_calls: u32,
+    /// Instances drawn (a non-instanced draw counts as one)
+    pub instances: u32,
+    /// Triangles submitted, including shadow passes
+    pub triangles: u64,
+    /// Mesh renderables tested against the camera frustum
+    pub culling: CullingStats,
+    /// Texture memory held by the texture manager, mip chains included
+    pub texture_bytes: u64,
+    /// Vertex, index and uniform buffer memory held by the managers
+    pub buffer_bytes: u64,
+    pub meshes: ResourceMemory,
+    pub textures: ResourceMemory,
+    pub materials: ResourceMemory,
+    /// GPU memory budget (0 = none)
+    pub budget_bytes: u64,
+    /// Streamed resources the budget has evicted since startup
+    pub evicted: usize,
+}
+
+impl RenderStats {
+    /// Record an indexed triangle-list draw
+    pub fn record_draw(&mut self, index_count: u32, instance_count: u32) {
+        self.draw_calls += 1;
+        self.instances += instance_count;
+        self.triangles += (index_count / 3) as u64 * instance_count as u64;
+    }
+
+    /// Estimated GPU memory in use
+    pub fn vram_bytes(&self) -> u64 {
+        self.texture_bytes + self.buffer_bytes
+    }
+}
+
+/// Bytes taken by a texture and all of its mip levels and array layers.
+/// Compressed and depth-stencil formats without a fixed texel size count as 4 bytes.
+pub fn texture_bytes(texture: &wgpu::Texture) -> u64 {
+    let format = texture.format();
+    let bytes_per_block = format.block_copy_size(None).unwrap_or(4);
+    let (block_width, block_height) = format.block_dimensions();
+    let layer_bytes = if (block_width, block_height) == (1, 1) {
+        mip_chain_bytes(
+            texture.width(),
+            texture.height(),
+            texture.mip_level_count(),
+            bytes_per_block,
+        )
+    } else {
+        // Compressed: every level takes whole blocks
+        (0..texture.mip_level_count())
+            .map(|level| {
+                let w = (texture.width() >> level).max(1).div_ceil(block_width) as u64;
+                let h = (texture.height() >> level).max(1).div_ceil(block_height) as u64;
+                w * h * bytes_per_block as u64
+            })
+            .sum()
+    };
+    layer_bytes * texture.depth_or_array_layers() as u64
+}
+
+/// Bytes for `mip_levels` levels of a 2D image starting at width x height
+pub fn mip_chain_bytes(width: u32, height: u32, mip_levels: u32, bytes_per_texel: u32) -> u64 {
+    (0..mip_levels)
+        .map(|level| {
+            let w = (width >> level).max(1) as u64;
+            let h = (height >> level).max(1) as u64;
+            w * h * bytes_per_texel as u64
+        })
+        .sum()
+}
+
+/// Human-readable byte count ("512 B", "3.2 KB", "48.0 MB")
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_draws() {
+        let mut stats = RenderStats::default();
+        stats.record_draw(36, 1);
+        stats.record_draw(6, 100);
+        stats.culling.record(true);
+        stats.culling.record(false);
+        stats.culling.record(false);
+
+        assert_eq!(stats.draw_calls, 2);
+        assert_eq!(stats.instances, 101);
+        assert_eq!(stats.triangles, 12 + 200);
+        assert_eq!(stats.culling.total_renderables, 3);
+        assert_eq!(stats.culling.visible_renderables, 1);
+        assert_eq!(stats.culling.culled_renderables, 2);
+    }
+
+    #[test]
+    fn test_memory_sizes() {
+        // 4x2 RGBA8: 32 + 8 (2x1) + 4 (1x1)
+        assert_eq!(mip_chain_bytes(4, 2, 3, 4), 44);
+        assert_eq!(mip_chain_bytes(256, 256, 1, 4), 262_144);
+
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(48 * 1024 * 1024), "48.0 MB");
+    }
+}
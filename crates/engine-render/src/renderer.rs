@@ -2,10 +2,16 @@
 
 use crate::gpu_material::GpuMaterial;
 use crate::gpu_mesh::{GpuMesh, GpuVertex};
+use crate::gpu_profiler::GpuProfiler;
+use crate::ibl::IblMaps;
+use crate::instancing::{DrawBatches, InstanceBuffer, InstanceRaw};
+use crate::material_manager::MaterialManager;
+use crate::mesh_manager::MeshManager;
 use crate::texture_manager::TextureManager;
 use crate::shadow::ShadowMap;
 use crate::MSAA_SAMPLE_COUNT;
 use anyhow::Result;
+use engine_core::frame_pacing::PresentMode;
 use glam::Mat4;
 use wgpu::util::DeviceExt;
 
@@ -13,9 +19,32 @@ pub struct Renderer {
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub surface_config: wgpu::SurfaceConfiguration,
+    /// MSAA sample count used by the depth/color targets and all scene pipelines (1 = off)
+    pub sample_count: u32,
+    /// Present mode asked for; the surface uses the closest one it supports
+    present_mode: PresentMode,
+    supported_present_modes: Vec<wgpu::PresentMode>,
     pub render_pipeline: wgpu::RenderPipeline,
+    /// Same shading as `render_pipeline`, with the model matrix per instance
+    pub instanced_pipeline: wgpu::RenderPipeline,
+    instance_buffer: InstanceBuffer,
+    /// Reflection instances, written in the same frame as the main view's
+    reflection_instance_buffer: InstanceBuffer,
+    /// Instanced pipeline for the single-sampled water reflection target
+    reflection_pipeline: wgpu::RenderPipeline,
     pub uniform_buffer: wgpu::Buffer,
     pub uniform_bind_group: wgpu::BindGroup,
+    /// Camera of the reflection pass, which shares the frame's encoder with
+    /// the main pass and so can't reuse its uniform buffer
+    reflection_uniform_buffer: wgpu::Buffer,
+    pub reflection_uniform_bind_group: wgpu::BindGroup,
+    /// Flat ambient lighting for scenes without a skybox
+    pub default_environment: IblMaps,
+    /// Pixel rect (x, y, width, height) scene passes draw into; None = whole surface
+    pub scene_viewport: Option<[f32; 4]>,
+    /// Direction toward the sun and the radiance of its light, see `set_sun`
+    sun_direction: glam::Vec3,
+    sun_radiance: glam::Vec3,
 }
 
 #[repr(C)]
@@ -24,12 +53,20 @@ pub struct Uniforms {
     pub view_proj: [[f32; 4]; 4],
     pub camera_pos: [f32; 3],
     pub _padding: f32,
+    /// Direction toward the sun
+    pub sun_direction: [f32; 3],
+    pub _padding2: f32,
+    /// Color times intensity of the sunlight
+    pub sun_radiance: [f32; 3],
+    pub _padding3: f32,
 }
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct PushConstants {
     pub model: [[f32; 4]; 4],
+    /// x = LOD crossfade, see `LodDraw::fade`
+    pub lod_fade: [f32; 4],
 }
 
 impl Renderer {
@@ -38,12 +75,87 @@ impl Renderer {
         surface: &wgpu::Surface<'_>,
         width: u32,
         height: u32,
+        sample_count: u32,
+        present_mode: PresentMode,
     ) -> Result<Self> {
+        let (adapter, device, queue) = Self::request_device(instance, Some(surface)).await?;
+
+        // Configure surface
+        let surface_caps = surface.get_capabilities(&adapter);
+        let surface_format = surface_caps
+            .formats
+            .iter()
+            .copied()
+            .find(|f| f.is_srgb())
+            .unwrap_or(surface_caps.formats[0]);
+
+        // Copying out of the swapchain (screenshots) needs COPY_SRC, where supported
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | (surface_caps.usages & wgpu::TextureUsages::COPY_SRC),
+            format: surface_format,
+            width,
+            height,
+            present_mode: wgpu_present_mode(present_mode, &surface_caps.present_modes),
+            alpha_mode: surface_caps.alpha_modes[0],
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+
+        surface.configure(&device, &surface_config);
+
+        Self::build(
+            &adapter,
+            device,
+            queue,
+            surface_config,
+            sample_count,
+            present_mode,
+            surface_caps.present_modes,
+        )
+    }
+
+    /// Renderer without a window. `surface_config` describes the targets from
+    /// `create_offscreen_target` that post-processing draws into, so the
+    /// frame can be read back (headless screenshots).
+    pub async fn new_offscreen(
+        instance: &wgpu::Instance,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+    ) -> Result<Self> {
+        let (adapter, device, queue) = Self::request_device(instance, None).await?;
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+        Self::build(
+            &adapter,
+            device,
+            queue,
+            surface_config,
+            sample_count,
+            PresentMode::Vsync,
+            vec![wgpu::PresentMode::Fifo],
+        )
+    }
+
+    /// Adapter (able to present to `surface`, if given) and a device on it
+    async fn request_device(
+        instance: &wgpu::Instance,
+        surface: Option<&wgpu::Surface<'_>>,
+    ) -> Result<(wgpu::Adapter, wgpu::Device, wgpu::Queue)> {
         // Request adapter
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
                 power_preference: wgpu::PowerPreference::HighPerformance,
-                compatible_surface: Some(surface),
+                compatible_surface: surface,
                 force_fallback_adapter: false,
             })
             .await?;
@@ -53,50 +165,74 @@ impl Renderer {
         let mut limits = adapter.limits();
         limits.max_push_constant_size = 128; // Enough for a 4x4 matrix (64 bytes) with headroom
 
+        // Timestamp queries are optional (GPU profiling is disabled without them)
+        let profiler_features = adapter.features() & GpuProfiler::features();
+        // Block-compressed textures when the GPU has them (BC on desktop, ASTC on mobile)
+        let compression_features = adapter.features()
+            & (wgpu::Features::TEXTURE_COMPRESSION_BC | wgpu::Features::TEXTURE_COMPRESSION_ASTC);
+
         let (device, queue) = adapter
             .request_device(&wgpu::DeviceDescriptor {
                 label: Some("Main Device"),
-                required_features: wgpu::Features::PUSH_CONSTANTS,
+                required_features: wgpu::Features::PUSH_CONSTANTS | profiler_features | compression_features,
                 required_limits: limits,
                 memory_hints: Default::default(),
                 experimental_features: Default::default(),
                 trace: Default::default(),
             })
             .await?;
+        Ok((adapter, device, queue))
+    }
 
-        // Configure surface
-        let surface_caps = surface.get_capabilities(&adapter);
-        let surface_format = surface_caps
-            .formats
-            .iter()
-            .copied()
-            .find(|f| f.is_srgb())
-            .unwrap_or(surface_caps.formats[0]);
-
-        let surface_config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
-            width,
-            height,
-            present_mode: wgpu::PresentMode::Fifo,
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
-            desired_maximum_frame_latency: 2,
+    /// Pipelines and buffers shared by windowed and offscreen renderers
+    fn build(
+        adapter: &wgpu::Adapter,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        surface_config: wgpu::SurfaceConfiguration,
+        sample_count: u32,
+        present_mode: PresentMode,
+        supported_present_modes: Vec<wgpu::PresentMode>,
+    ) -> Result<Self> {
+        // Fall back to the default MSAA level if the adapter can't multisample at the requested
+        // count. Scenes are multisampled in HDR and resolved before tone mapping to the surface.
+        let color_flags = adapter
+            .get_texture_format_features(crate::postprocess::HDR_FORMAT)
+            .flags;
+        let depth_flags = adapter
+            .get_texture_format_features(wgpu::TextureFormat::Depth32Float)
+            .flags;
+        let sample_count = if color_flags.sample_count_supported(sample_count)
+            && depth_flags.sample_count_supported(sample_count)
+        {
+            sample_count
+        } else {
+            log::warn!(
+                "{}x MSAA not supported by this adapter, using {}x",
+                sample_count,
+                MSAA_SAMPLE_COUNT
+            );
+            MSAA_SAMPLE_COUNT
         };
 
-        surface.configure(&device, &surface_config);
-
         // Create shader module (advanced PBR with normal mapping)
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader"),
             source: wgpu::ShaderSource::Wgsl(include_str!("shaders/pbr_advanced_nm.wgsl").into()),
         });
 
-        // Create uniform buffer (view_proj and camera position)
+        // Create uniform buffer (view_proj, camera position and sun)
+        // The sun starts as the fixed white light used before `set_sun`
+        let sun_direction = glam::Vec3::new(0.5, 1.0, 0.3).normalize();
+        let sun_radiance = glam::Vec3::splat(3.0);
         let uniforms = Uniforms {
             view_proj: Mat4::IDENTITY.to_cols_array_2d(),
             camera_pos: [0.0, 0.0, 0.0],
             _padding: 0.0,
+            sun_direction: sun_direction.to_array(),
+            _padding2: 0.0,
+            sun_radiance: sun_radiance.to_array(),
+            _padding3: 0.0,
         };
 
         let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -106,19 +242,7 @@ impl Renderer {
         });
 
         // Create bind group layout
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("Uniform Bind Group Layout"),
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-        });
+        let bind_group_layout = Self::create_uniform_bind_group_layout(&device);
 
         // Create bind group
         let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
@@ -129,6 +253,20 @@ impl Renderer {
                 resource: uniform_buffer.as_entire_binding(),
             }],
         });
+        let reflection_uniform_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Reflection Uniform Buffer"),
+                contents: bytemuck::cast_slice(&[uniforms]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+        let reflection_uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Reflection Uniform Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: reflection_uniform_buffer.as_entire_binding(),
+            }],
+        });
 
         // Create material bind group layout (replaces old texture bind group layout)
         let material_bind_group_layout = GpuMaterial::create_bind_group_layout(&device);
@@ -136,13 +274,22 @@ impl Renderer {
         // Create shadow bind group layout
         let shadow_bind_group_layout = ShadowMap::create_sampling_bind_group_layout(&device);
 
+        // Image based lighting from the skybox
+        let environment_bind_group_layout = IblMaps::create_bind_group_layout(&device);
+        let default_environment = IblMaps::new(&device, &queue);
+
         // Create pipeline layout with push constants for model matrix
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Pipeline Layout"),
-            bind_group_layouts: &[&bind_group_layout, &material_bind_group_layout, &shadow_bind_group_layout],
+            bind_group_layouts: &[
+                &bind_group_layout,
+                &material_bind_group_layout,
+                &shadow_bind_group_layout,
+                &environment_bind_group_layout,
+            ],
             push_constant_ranges: &[wgpu::PushConstantRange {
                 stages: wgpu::ShaderStages::VERTEX,
-                range: 0..64, // mat4x4<f32> = 64 bytes
+                range: 0..80, // mat4x4<f32> + vec4<f32> = 80 bytes
             }],
         });
 
@@ -155,53 +302,106 @@ impl Renderer {
             bias: wgpu::DepthBiasState::default(),
         };
 
-        // Create render pipeline
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                buffers: &[GpuVertex::desc()],
-                compilation_options: Default::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                // No culling - render both sides of faces (meshes may not be watertight)
-                cull_mode: None,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: Some(depth_stencil),
-            multisample: wgpu::MultisampleState {
-                count: MSAA_SAMPLE_COUNT,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-            cache: None,
-        });
+        // Create render pipelines: one draw per mesh, or instanced batches
+        let create_pipeline = |label, entry_point, buffers: &[wgpu::VertexBufferLayout], samples| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some(entry_point),
+                    buffers,
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        // Lit in HDR, tone mapped into the surface by post-processing
+                        format: crate::postprocess::HDR_FORMAT,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    // No culling - render both sides of faces (meshes may not be watertight)
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: Some(depth_stencil.clone()),
+                multisample: wgpu::MultisampleState {
+                    count: samples,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+                cache: None,
+            })
+        };
+        let render_pipeline = create_pipeline(
+            "Render Pipeline",
+            "vs_main",
+            &[GpuVertex::desc()],
+            sample_count,
+        );
+        let instanced_pipeline = create_pipeline(
+            "Instanced Render Pipeline",
+            "vs_instanced",
+            &[GpuVertex::desc(), InstanceRaw::desc()],
+            sample_count,
+        );
+        let reflection_pipeline = create_pipeline(
+            "Reflection Render Pipeline",
+            "vs_instanced",
+            &[GpuVertex::desc(), InstanceRaw::desc()],
+            1,
+        );
+        let instance_buffer = InstanceBuffer::new(&device, 256);
+        let reflection_instance_buffer = InstanceBuffer::new(&device, 256);
 
         Ok(Self {
             device,
             queue,
             surface_config,
+            sample_count,
+            present_mode,
+            supported_present_modes,
             render_pipeline,
+            instanced_pipeline,
+            instance_buffer,
+            reflection_instance_buffer,
+            reflection_pipeline,
             uniform_buffer,
             uniform_bind_group,
+            reflection_uniform_buffer,
+            reflection_uniform_bind_group,
+            default_environment,
+            scene_viewport: None,
+            sun_direction,
+            sun_radiance,
+        })
+    }
+
+    /// Layout of the camera and sun uniforms (group 0 of the PBR pipelines)
+    pub fn create_uniform_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Uniform Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
         })
     }
 
@@ -213,6 +413,67 @@ impl Renderer {
         }
     }
 
+    /// Change how frames are presented (vsync, mailbox, immediate)
+    pub fn set_present_mode(&mut self, surface: &wgpu::Surface, present_mode: PresentMode) {
+        self.present_mode = present_mode;
+        self.surface_config.present_mode =
+            wgpu_present_mode(present_mode, &self.supported_present_modes);
+        surface.configure(&self.device, &self.surface_config);
+    }
+
+    /// Present mode asked for (see `surface_config.present_mode` for the one in use)
+    pub fn present_mode(&self) -> PresentMode {
+        self.present_mode
+    }
+
+    /// True if rendered frames can be read back (see `capture::FrameCapture`)
+    pub fn can_capture(&self) -> bool {
+        self.surface_config
+            .usage
+            .contains(wgpu::TextureUsages::COPY_SRC)
+            && crate::capture::FrameCapture::supports_format(self.surface_config.format)
+    }
+
+    /// Restrict scene passes to a pixel rect (x, y, width, height), clamped to the surface.
+    /// `None` draws to the whole surface.
+    pub fn set_scene_viewport(&mut self, viewport: Option<[f32; 4]>) {
+        let surface_width = self.surface_config.width as f32;
+        let surface_height = self.surface_config.height as f32;
+        self.scene_viewport = viewport.and_then(|[x, y, width, height]| {
+            let x = x.clamp(0.0, surface_width);
+            let y = y.clamp(0.0, surface_height);
+            let width = width.min(surface_width - x);
+            let height = height.min(surface_height - y);
+            (width >= 1.0 && height >= 1.0).then_some([x, y, width, height])
+        });
+    }
+
+    /// Apply the scene viewport (if set) to a render pass
+    pub fn apply_scene_viewport(&self, render_pass: &mut wgpu::RenderPass) {
+        if let Some([x, y, width, height]) = self.scene_viewport {
+            render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+        }
+    }
+
+    /// Light the scene with a sun, `direction` pointing toward it (see `ProceduralSky`)
+    pub fn set_sun(&mut self, direction: glam::Vec3, radiance: glam::Vec3) {
+        self.sun_direction = direction.normalize_or(glam::Vec3::Y);
+        self.sun_radiance = radiance;
+    }
+
+    /// Uniforms for a camera, lit by the current sun
+    fn uniforms(&self, view_proj: Mat4, camera_pos: glam::Vec3) -> Uniforms {
+        Uniforms {
+            view_proj: view_proj.to_cols_array_2d(),
+            camera_pos: camera_pos.to_array(),
+            _padding: 0.0,
+            sun_direction: self.sun_direction.to_array(),
+            _padding2: 0.0,
+            sun_radiance: self.sun_radiance.to_array(),
+            _padding3: 0.0,
+        }
+    }
+
     /// Begin a render pass
     pub fn begin_frame(
         &self,
@@ -235,6 +496,8 @@ impl Renderer {
 
     /// Render a mesh with a given transform, texture, and shadows
     /// When using MSAA, `view` should be the MSAA texture and `resolve_target` should be the swapchain view
+    /// `lod_fade` dithers the mesh out during a LOD crossfade, 1.0 draws it whole
+    /// `environment_bind_group` is the sky's `IblMaps` (or `default_environment`)
     pub fn render_mesh(
         &self,
         encoder: &mut wgpu::CommandEncoder,
@@ -245,22 +508,21 @@ impl Renderer {
         view_proj: Mat4,
         camera_pos: glam::Vec3,
         model: Mat4,
+        lod_fade: f32,
         texture_bind_group: &wgpu::BindGroup,
         shadow_bind_group: &wgpu::BindGroup,
+        environment_bind_group: &wgpu::BindGroup,
         clear: bool,
     ) {
         // Update uniforms (view_proj and camera position for specular calculations)
-        let uniforms = Uniforms {
-            view_proj: view_proj.to_cols_array_2d(),
-            camera_pos: camera_pos.to_array(),
-            _padding: 0.0,
-        };
+        let uniforms = self.uniforms(view_proj, camera_pos);
         self.queue
             .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
 
         // Prepare push constants for this mesh
         let push_constants = PushConstants {
             model: model.to_cols_array_2d(),
+            lod_fade: [lod_fade, 0.0, 0.0, 0.0],
         };
 
         // Begin render pass
@@ -303,10 +565,12 @@ impl Renderer {
             occlusion_query_set: None,
         });
 
+        self.apply_scene_viewport(&mut render_pass);
         render_pass.set_pipeline(&self.render_pipeline);
         render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
         render_pass.set_bind_group(1, texture_bind_group, &[]);
         render_pass.set_bind_group(2, shadow_bind_group, &[]);
+        render_pass.set_bind_group(3, environment_bind_group, &[]);
         // Set push constants for model matrix
         render_pass.set_push_constants(
             wgpu::ShaderStages::VERTEX,
@@ -319,12 +583,202 @@ impl Renderer {
         render_pass.draw_indexed(0..mesh.num_indices, 0, 0..1);
     }
 
+    /// Render every batch in one pass, one instanced draw call per (mesh,
+    /// material). `clear` clears the targets first; the pass runs even
+    /// with no batches so they still get cleared. Returns the index count
+    /// and instance count of each draw, for render stats.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_batches(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        resolve_target: Option<&wgpu::TextureView>,
+        depth_texture: &wgpu::TextureView,
+        batches: &DrawBatches,
+        mesh_manager: &MeshManager,
+        material_manager: &MaterialManager,
+        view_proj: Mat4,
+        camera_pos: glam::Vec3,
+        shadow_bind_group: &wgpu::BindGroup,
+        environment_bind_group: &wgpu::BindGroup,
+        clear: bool,
+    ) -> Vec<(u32, u32)> {
+        let uniforms = self.uniforms(view_proj, camera_pos);
+        self.queue
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+        let ranges = self
+            .instance_buffer
+            .write(&self.device, &self.queue, batches);
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Instanced Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: if clear {
+                        wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.1,
+                            g: 0.2,
+                            b: 0.3,
+                            a: 1.0,
+                        })
+                    } else {
+                        wgpu::LoadOp::Load
+                    },
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_texture,
+                depth_ops: Some(wgpu::Operations {
+                    load: if clear {
+                        wgpu::LoadOp::Clear(1.0)
+                    } else {
+                        wgpu::LoadOp::Load
+                    },
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        self.apply_scene_viewport(&mut render_pass);
+        render_pass.set_pipeline(&self.instanced_pipeline);
+        render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+        render_pass.set_bind_group(2, shadow_bind_group, &[]);
+        render_pass.set_bind_group(3, environment_bind_group, &[]);
+        self.draw_batches(
+            &mut render_pass,
+            batches,
+            &self.instance_buffer,
+            ranges,
+            mesh_manager,
+            material_manager,
+        )
+    }
+
+    /// Render the batches (culled against the mirrored view) into a water
+    /// reflection target (single-sampled, see `WaterReflection`) with the
+    /// mirrored `view_proj` and camera position. Clears to transparent so the
+    /// water knows where nothing was drawn.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_reflection(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        depth_texture: &wgpu::TextureView,
+        batches: &DrawBatches,
+        mesh_manager: &MeshManager,
+        material_manager: &MaterialManager,
+        view_proj: Mat4,
+        camera_pos: glam::Vec3,
+        shadow_bind_group: &wgpu::BindGroup,
+        environment_bind_group: &wgpu::BindGroup,
+    ) -> Vec<(u32, u32)> {
+        let uniforms = self.uniforms(view_proj, camera_pos);
+        self.queue.write_buffer(
+            &self.reflection_uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[uniforms]),
+        );
+        let ranges = self
+            .reflection_instance_buffer
+            .write(&self.device, &self.queue, batches);
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Reflection Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_texture,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    // Kept for the terrain, drawn into the reflection after
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        render_pass.set_pipeline(&self.reflection_pipeline);
+        render_pass.set_bind_group(0, &self.reflection_uniform_bind_group, &[]);
+        render_pass.set_bind_group(2, shadow_bind_group, &[]);
+        render_pass.set_bind_group(3, environment_bind_group, &[]);
+        self.draw_batches(
+            &mut render_pass,
+            batches,
+            &self.reflection_instance_buffer,
+            ranges,
+            mesh_manager,
+            material_manager,
+        )
+    }
+
+    fn draw_batches(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        batches: &DrawBatches,
+        instance_buffer: &InstanceBuffer,
+        ranges: Vec<std::ops::Range<u32>>,
+        mesh_manager: &MeshManager,
+        material_manager: &MaterialManager,
+    ) -> Vec<(u32, u32)> {
+        render_pass.set_vertex_buffer(1, instance_buffer.buffer.slice(..));
+        let mut draws = Vec::new();
+        for (batch, instances) in batches.batches().zip(ranges) {
+            let (Some(mesh), Some(material)) = (
+                mesh_manager.get_mesh(batch.mesh),
+                material_manager.get_material(batch.material),
+            ) else {
+                continue;
+            };
+            render_pass.set_bind_group(1, &material.bind_group, &[]);
+            render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            draws.push((mesh.num_indices, instances.len() as u32));
+            render_pass.draw_indexed(0..mesh.num_indices, 0, instances);
+        }
+        draws
+    }
+
     /// Finish the frame and present
     pub fn end_frame(&self, encoder: wgpu::CommandEncoder, output: wgpu::SurfaceTexture) {
         self.queue.submit(std::iter::once(encoder.finish()));
         output.present();
     }
 
+    /// Texture an offscreen renderer's frame is drawn into instead of a
+    /// swapchain image, sized and formatted as `surface_config`
+    pub fn create_offscreen_target(&self) -> wgpu::Texture {
+        self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Target"),
+            size: wgpu::Extent3d {
+                width: self.surface_config.width,
+                height: self.surface_config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.surface_config.format,
+            usage: self.surface_config.usage,
+            view_formats: &[],
+        })
+    }
+
     pub fn create_depth_texture(&self, width: u32, height: u32) -> wgpu::TextureView {
         let size = wgpu::Extent3d {
             width,
@@ -336,10 +790,11 @@ impl Renderer {
             label: Some("Depth Texture"),
             size,
             mip_level_count: 1,
-            sample_count: MSAA_SAMPLE_COUNT,
+            sample_count: self.sample_count,
             dimension: wgpu::TextureDimension::D2,
             format: wgpu::TextureFormat::Depth32Float,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            // Sampled by the TAA velocity pass
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
             view_formats: &[],
         };
 
@@ -359,7 +814,7 @@ impl Renderer {
             label: Some("MSAA Color Texture"),
             size,
             mip_level_count: 1,
-            sample_count: MSAA_SAMPLE_COUNT,
+            sample_count: self.sample_count,
             dimension: wgpu::TextureDimension::D2,
             format,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
@@ -370,3 +825,40 @@ impl Renderer {
         texture.create_view(&wgpu::TextureViewDescriptor::default())
     }
 }
+
+/// Closest supported wgpu present mode; every surface supports Fifo
+fn wgpu_present_mode(mode: PresentMode, supported: &[wgpu::PresentMode]) -> wgpu::PresentMode {
+    let preferred: &[wgpu::PresentMode] = match mode {
+        PresentMode::Vsync => &[],
+        PresentMode::AdaptiveVsync => &[wgpu::PresentMode::FifoRelaxed],
+        PresentMode::Mailbox => &[wgpu::PresentMode::Mailbox],
+        PresentMode::Immediate => &[wgpu::PresentMode::Immediate, wgpu::PresentMode::Mailbox],
+    };
+    preferred
+        .iter()
+        .copied()
+        .find(|mode| supported.contains(mode))
+        .unwrap_or(wgpu::PresentMode::Fifo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_present_mode_falls_back_to_supported() {
+        let supported = [wgpu::PresentMode::Fifo, wgpu::PresentMode::Mailbox];
+        assert_eq!(
+            wgpu_present_mode(PresentMode::Immediate, &supported),
+            wgpu::PresentMode::Mailbox
+        );
+        assert_eq!(
+            wgpu_present_mode(PresentMode::AdaptiveVsync, &supported),
+            wgpu::PresentMode::Fifo
+        );
+        assert_eq!(
+            wgpu_present_mode(PresentMode::Mailbox, &supported),
+            wgpu::PresentMode::Mailbox
+        );
+    }
+}
@@ -1,4 +1,9 @@
-// Bloom shader - bright pass extraction and gaussian blur
+// Bloom shader - threshold, downsample chain and upsample over HDR color
+//
+// The prefilter keeps the light above the threshold (with a soft knee) at
+// half resolution, the downsample pass halves it down a mip chain, and the
+// upsample pass blurs each mip back onto the one above, so the bloom spreads
+// wider the further down the chain the light got.
 
 @group(0) @binding(0)
 var input_texture: texture_2d<f32>;
@@ -6,6 +11,15 @@ var input_texture: texture_2d<f32>;
 @group(0) @binding(1)
 var input_sampler: sampler;
 
+struct BloomSettings {
+    threshold: f32,
+    knee: f32,
+    radius: f32, // upsample filter radius in texels
+    _padding: f32,
+}
+
+var<push_constant> settings: BloomSettings;
+
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
@@ -32,64 +46,71 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
     return out;
 }
 
-// Extract bright areas (threshold-based)
-@fragment
-fn fs_bright_pass(in: VertexOutput) -> @location(0) vec4<f32> {
-    let color = textureSample(input_texture, input_sampler, in.tex_coords);
-
-    // Calculate luminance
-    let luminance = dot(color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+fn texel_size() -> vec2<f32> {
+    return 1.0 / vec2<f32>(textureDimensions(input_texture));
+}
 
-    // Threshold for bloom
-    let threshold = 1.0;
+fn sample_offset(uv: vec2<f32>, offset: vec2<f32>) -> vec3<f32> {
+    return textureSample(input_texture, input_sampler, uv + offset * texel_size()).rgb;
+}
 
-    if luminance > threshold {
-        return color;
-    } else {
-        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
-    }
+// 13-tap box filter, weighted so it doesn't flicker as bright pixels move
+fn downsample(uv: vec2<f32>) -> vec3<f32> {
+    let a = sample_offset(uv, vec2<f32>(-2.0, -2.0));
+    let b = sample_offset(uv, vec2<f32>(0.0, -2.0));
+    let c = sample_offset(uv, vec2<f32>(2.0, -2.0));
+    let d = sample_offset(uv, vec2<f32>(-2.0, 0.0));
+    let e = sample_offset(uv, vec2<f32>(0.0, 0.0));
+    let f = sample_offset(uv, vec2<f32>(2.0, 0.0));
+    let g = sample_offset(uv, vec2<f32>(-2.0, 2.0));
+    let h = sample_offset(uv, vec2<f32>(0.0, 2.0));
+    let i = sample_offset(uv, vec2<f32>(2.0, 2.0));
+    let j = sample_offset(uv, vec2<f32>(-1.0, -1.0));
+    let k = sample_offset(uv, vec2<f32>(1.0, -1.0));
+    let l = sample_offset(uv, vec2<f32>(-1.0, 1.0));
+    let m = sample_offset(uv, vec2<f32>(1.0, 1.0));
+
+    return e * 0.125
+        + (a + c + g + i) * 0.03125
+        + (b + d + f + h) * 0.0625
+        + (j + k + l + m) * 0.125;
 }
 
-// Gaussian blur - horizontal pass
+// Threshold with a quadratic knee below it, so light fades in instead of popping
 @fragment
-fn fs_blur_h(in: VertexOutput) -> @location(0) vec4<f32> {
-    let tex_size = textureDimensions(input_texture);
-    let texel_size = 1.0 / vec2<f32>(f32(tex_size.x), f32(tex_size.y));
+fn fs_prefilter(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Clamp so single very bright pixels don't blow up into squares
+    let color = min(downsample(in.tex_coords), vec3<f32>(64.0));
+    let brightness = max(color.r, max(color.g, color.b));
 
-    // 9-tap gaussian blur
-    let weights = array<f32, 5>(
-        0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216
-    );
+    let knee = max(settings.knee, 0.0001);
+    var soft = clamp(brightness - settings.threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee);
+    let contribution = max(soft, brightness - settings.threshold) / max(brightness, 0.0001);
 
-    var result = textureSample(input_texture, input_sampler, in.tex_coords).rgb * weights[0];
-
-    for (var i: i32 = 1; i < 5; i++) {
-        let offset = vec2<f32>(f32(i) * texel_size.x, 0.0);
-        result += textureSample(input_texture, input_sampler, in.tex_coords + offset).rgb * weights[i];
-        result += textureSample(input_texture, input_sampler, in.tex_coords - offset).rgb * weights[i];
-    }
-
-    return vec4<f32>(result, 1.0);
+    return vec4<f32>(color * contribution, 1.0);
 }
 
-// Gaussian blur - vertical pass
 @fragment
-fn fs_blur_v(in: VertexOutput) -> @location(0) vec4<f32> {
-    let tex_size = textureDimensions(input_texture);
-    let texel_size = 1.0 / vec2<f32>(f32(tex_size.x), f32(tex_size.y));
-
-    // 9-tap gaussian blur
-    let weights = array<f32, 5>(
-        0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216
-    );
-
-    var result = textureSample(input_texture, input_sampler, in.tex_coords).rgb * weights[0];
-
-    for (var i: i32 = 1; i < 5; i++) {
-        let offset = vec2<f32>(0.0, f32(i) * texel_size.y);
-        result += textureSample(input_texture, input_sampler, in.tex_coords + offset).rgb * weights[i];
-        result += textureSample(input_texture, input_sampler, in.tex_coords - offset).rgb * weights[i];
-    }
+fn fs_downsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(downsample(in.tex_coords), 1.0);
+}
 
-    return vec4<f32>(result, 1.0);
+// 9-tap tent filter, added onto the larger mip by the pipeline's blend
+@fragment
+fn fs_upsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    let r = settings.radius;
+    let uv = in.tex_coords;
+
+    var color = sample_offset(uv, vec2<f32>(0.0, 0.0)) * 4.0;
+    color += (sample_offset(uv, vec2<f32>(-r, 0.0))
+        + sample_offset(uv, vec2<f32>(r, 0.0))
+        + sample_offset(uv, vec2<f32>(0.0, -r))
+        + sample_offset(uv, vec2<f32>(0.0, r))) * 2.0;
+    color += sample_offset(uv, vec2<f32>(-r, -r))
+        + sample_offset(uv, vec2<f32>(r, -r))
+        + sample_offset(uv, vec2<f32>(-r, r))
+        + sample_offset(uv, vec2<f32>(r, r));
+
+    return vec4<f32>(color / 16.0, 1.0);
 }
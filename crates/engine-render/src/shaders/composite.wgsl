@@ -1,4 +1,5 @@
-// Composite shader - combines scene and bloom
+// Composite shader - combines HDR scene and bloom, then applies exposure
+// and tone mapping for the surface
 
 // Scene texture (bind group 0)
 @group(0) @binding(0)
@@ -18,7 +19,12 @@ var bloom_sampler: sampler;
 struct Settings {
     bloom_intensity: f32,
     bloom_enabled: f32, // 0.0 or 1.0
-    _padding: vec2<f32>,
+    exposure: f32,
+    tone_mapper: f32, // 0 = ACES, 1 = Reinhard, 2 = none
+    apply_gamma: f32, // 1.0 when the surface isn't sRGB
+    _padding0: f32,
+    _padding1: f32,
+    _padding2: f32,
 }
 
 var<push_constant> settings: Settings;
@@ -49,6 +55,20 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
     return out;
 }
 
+// ACES filmic curve (Narkowicz's fit)
+fn aces(x: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+fn reinhard(x: vec3<f32>) -> vec3<f32> {
+    return x / (x + vec3<f32>(1.0));
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     // Sample scene color
@@ -60,7 +80,21 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     // Additive blending: scene + bloom * intensity
     // Only add bloom if enabled
     let bloom_contribution = bloom_color.rgb * settings.bloom_intensity * settings.bloom_enabled;
-    let final_color = scene_color.rgb + bloom_contribution;
+    let hdr_color = (scene_color.rgb + bloom_contribution) * settings.exposure;
+
+    var color: vec3<f32>;
+    if settings.tone_mapper < 0.5 {
+        color = aces(hdr_color);
+    } else if settings.tone_mapper < 1.5 {
+        color = reinhard(hdr_color);
+    } else {
+        color = clamp(hdr_color, vec3<f32>(0.0), vec3<f32>(1.0));
+    }
+
+    // sRGB surfaces encode on write
+    if settings.apply_gamma > 0.5 {
+        color = pow(color, vec3<f32>(1.0 / 2.2));
+    }
 
-    return vec4<f32>(final_color, scene_color.a);
+    return vec4<f32>(color, 1.0);
 }
@@ -1,16 +1,39 @@
 // Foliage shader - Instanced mesh rendering for vegetation
 //
-// Renders meshes with per-instance transforms and color tints.
+// Renders meshes with per-instance transforms and color tints. Instances
+// dither out approaching their draw distance instead of popping, and bend
+// in the wind by their stiffness.
 
 struct CameraUniforms {
     view_proj: mat4x4<f32>,
     camera_pos: vec3<f32>,
-    _padding: f32,
+    time: f32,
+    wind: vec4<f32>,  // direction (x, z), strength, gust strength
+    gust: vec4<f32>,  // gust frequency, gust scale
 }
 
 @group(0) @binding(0)
 var<uniform> camera: CameraUniforms;
 
+// Gust amount at a position, 0..1. Mirrors Wind::gust in engine-scene.
+fn wind_gust(position: vec3<f32>) -> f32 {
+    let along = dot(position.xz, camera.wind.xy) / camera.gust.y;
+    let phase = (along - camera.time * camera.gust.x) * 6.2831853;
+    let wave = sin(phase) * 0.6 + sin(phase * 2.3 + 1.7) * 0.4;
+    return clamp(wave * 0.5 + 0.5, 0.0, 1.0);
+}
+
+// Downwind offset of a point `height` above an instance origin. The bend
+// grows with the square of the height so the base stays planted.
+fn wind_sway(origin: vec3<f32>, height: f32, stiffness: f32) -> vec3<f32> {
+    let speed = camera.wind.z + camera.wind.w * wind_gust(origin);
+    // Flutter a little out of step between instances
+    let flutter = 1.0 + 0.15 * sin(camera.time * 2.0 + dot(origin.xz, vec2<f32>(0.37, 0.61)));
+    let h = max(height, 0.0);
+    let bend = h * h * 0.01 * speed * flutter / max(stiffness, 0.05);
+    return vec3<f32>(camera.wind.x, 0.0, camera.wind.y) * bend;
+}
+
 struct VertexInput {
     // Mesh vertex data
     @location(0) position: vec3<f32>,
@@ -23,6 +46,8 @@ struct VertexInput {
     @location(6) model_col2: vec4<f32>,
     @location(7) model_col3: vec4<f32>,
     @location(8) color_tint: vec4<f32>,
+    @location(9) fade_range: vec2<f32>,  // draw distance, fade width
+    @location(10) stiffness: f32,
 }
 
 struct VertexOutput {
@@ -31,6 +56,7 @@ struct VertexOutput {
     @location(1) world_normal: vec3<f32>,
     @location(2) tex_coord: vec2<f32>,
     @location(3) color: vec3<f32>,
+    @location(4) @interpolate(flat) fade: f32,
 }
 
 @vertex
@@ -43,8 +69,11 @@ fn vs_main(in: VertexInput) -> VertexOutput {
         in.model_col3
     );
 
-    // Transform position
-    let world_pos = model * vec4<f32>(in.position, 1.0);
+    // Transform position, then bend it in the wind
+    let origin = in.model_col3.xyz;
+    var world_pos = model * vec4<f32>(in.position, 1.0);
+    let sway = wind_sway(origin, world_pos.y - origin.y, in.stiffness);
+    world_pos = vec4<f32>(world_pos.xyz + sway, 1.0);
 
     // Transform normal (using upper 3x3 of model matrix)
     let normal_matrix = mat3x3<f32>(
@@ -57,18 +86,44 @@ fn vs_main(in: VertexInput) -> VertexOutput {
     // Apply color tint to vertex color
     let tinted_color = in.color * in.color_tint.rgb;
 
+    // Fade by the instance origin's distance so the whole instance dithers together
+    let distance = length(origin - camera.camera_pos);
+    var fade = select(0.0, 1.0, distance < in.fade_range.x);
+    if in.fade_range.y > 0.0 {
+        fade = clamp((in.fade_range.x - distance) / in.fade_range.y, 0.0, 1.0);
+    }
+
     var out: VertexOutput;
     out.clip_position = camera.view_proj * world_pos;
+    if fade <= 0.0 {
+        // Fully faded out: move outside the clip volume so it's culled
+        out.clip_position = vec4<f32>(2.0, 2.0, 2.0, 1.0);
+    }
     out.world_position = world_pos.xyz;
     out.world_normal = world_normal;
     out.tex_coord = in.tex_coord;
     out.color = tinted_color;
+    out.fade = fade;
 
     return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Screen-door dither while fading out
+    if in.fade < 1.0 {
+        var bayer = array<f32, 16>(
+            0.0, 8.0, 2.0, 10.0,
+            12.0, 4.0, 14.0, 6.0,
+            3.0, 11.0, 1.0, 9.0,
+            15.0, 7.0, 13.0, 5.0,
+        );
+        let pixel = vec2<u32>(in.clip_position.xy) % vec2<u32>(4u);
+        if (bayer[pixel.y * 4u + pixel.x] + 0.5) / 16.0 >= in.fade {
+            discard;
+        }
+    }
+
     // Simple directional lighting
     let light_dir = normalize(vec3<f32>(0.5, 1.0, 0.3));
     let ambient = 0.3;
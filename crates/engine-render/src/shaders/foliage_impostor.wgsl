@@ -0,0 +1,139 @@
+// Foliage impostor shader - camera-facing billboards for distant vegetation
+//
+// Each instance is a quad turned about the vertical axis toward the camera,
+// textured with the mesh as baked from the side. Uses the same instance data,
+// wind sway and distance fade as the foliage mesh shader.
+
+struct CameraUniforms {
+    view_proj: mat4x4<f32>,
+    camera_pos: vec3<f32>,
+    time: f32,
+    wind: vec4<f32>,  // direction (x, z), strength, gust strength
+    gust: vec4<f32>,  // gust frequency, gust scale
+}
+
+struct ImpostorUniforms {
+    half_width: f32,
+    bottom: f32,
+    top: f32,
+    _padding: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniforms;
+
+@group(1) @binding(0)
+var<uniform> impostor: ImpostorUniforms;
+@group(1) @binding(1)
+var impostor_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var impostor_sampler: sampler;
+
+// Gust amount at a position, 0..1. Mirrors Wind::gust in engine-scene.
+fn wind_gust(position: vec3<f32>) -> f32 {
+    let along = dot(position.xz, camera.wind.xy) / camera.gust.y;
+    let phase = (along - camera.time * camera.gust.x) * 6.2831853;
+    let wave = sin(phase) * 0.6 + sin(phase * 2.3 + 1.7) * 0.4;
+    return clamp(wave * 0.5 + 0.5, 0.0, 1.0);
+}
+
+// Downwind offset of a point `height` above an instance origin. The bend
+// grows with the square of the height so the base stays planted.
+fn wind_sway(origin: vec3<f32>, height: f32, stiffness: f32) -> vec3<f32> {
+    let speed = camera.wind.z + camera.wind.w * wind_gust(origin);
+    // Flutter a little out of step between instances
+    let flutter = 1.0 + 0.15 * sin(camera.time * 2.0 + dot(origin.xz, vec2<f32>(0.37, 0.61)));
+    let h = max(height, 0.0);
+    let bend = h * h * 0.01 * speed * flutter / max(stiffness, 0.05);
+    return vec3<f32>(camera.wind.x, 0.0, camera.wind.y) * bend;
+}
+
+struct InstanceInput {
+    @location(4) model_col0: vec4<f32>,
+    @location(5) model_col1: vec4<f32>,
+    @location(6) model_col2: vec4<f32>,
+    @location(7) model_col3: vec4<f32>,
+    @location(8) color_tint: vec4<f32>,
+    @location(9) fade_range: vec2<f32>,  // draw distance, fade width
+    @location(10) stiffness: f32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coord: vec2<f32>,
+    @location(1) tint: vec3<f32>,
+    @location(2) @interpolate(flat) fade: f32,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, in: InstanceInput) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    let corner = corners[vertex_index];
+
+    let origin = in.model_col3.xyz;
+    let scale = length(in.model_col0.xyz);
+
+    // Face the camera, staying upright
+    var to_camera = camera.camera_pos - origin;
+    to_camera.y = 0.0;
+    var forward = vec3<f32>(0.0, 0.0, 1.0);
+    if length(to_camera) > 0.001 {
+        forward = normalize(to_camera);
+    }
+    let right = cross(vec3<f32>(0.0, 1.0, 0.0), forward);
+
+    let offset = right * (corner.x * 2.0 - 1.0) * impostor.half_width
+        + vec3<f32>(0.0, mix(impostor.bottom, impostor.top, corner.y), 0.0);
+    var world_pos = origin + offset * scale;
+    world_pos += wind_sway(origin, world_pos.y - origin.y, in.stiffness);
+
+    // Fade by the instance origin's distance, as the mesh shader does
+    let distance = length(origin - camera.camera_pos);
+    var fade = select(0.0, 1.0, distance < in.fade_range.x);
+    if in.fade_range.y > 0.0 {
+        fade = clamp((in.fade_range.x - distance) / in.fade_range.y, 0.0, 1.0);
+    }
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(world_pos, 1.0);
+    if fade <= 0.0 {
+        out.clip_position = vec4<f32>(2.0, 2.0, 2.0, 1.0);
+    }
+    // Texture rows run top to bottom
+    out.tex_coord = vec2<f32>(corner.x, 1.0 - corner.y);
+    out.tint = in.color_tint.rgb;
+    out.fade = fade;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = textureSample(impostor_texture, impostor_sampler, in.tex_coord);
+    if texel.a < 0.5 {
+        discard;
+    }
+
+    // Screen-door dither while fading out
+    if in.fade < 1.0 {
+        var bayer = array<f32, 16>(
+            0.0, 8.0, 2.0, 10.0,
+            12.0, 4.0, 14.0, 6.0,
+            3.0, 11.0, 1.0, 9.0,
+            15.0, 7.0, 13.0, 5.0,
+        );
+        let pixel = vec2<u32>(in.clip_position.xy) % vec2<u32>(4u);
+        if (bayer[pixel.y * 4u + pixel.x] + 0.5) / 16.0 >= in.fade {
+            discard;
+        }
+    }
+
+    // Lighting was baked into the texture; the tint wasn't
+    return vec4<f32>(texel.rgb * in.tint, 1.0);
+}
@@ -0,0 +1,83 @@
+// Grass shader - draws the blades grown by grass_generate.wgsl
+//
+// Each blade is a tapered, curved strip of three segments built from the
+// vertex index. Blades lean forward, bend downwind with the same gusts as the
+// foliage, and shade from base to tip color.
+
+struct GrassUniforms {
+    view_proj: mat4x4<f32>,
+    camera_pos: vec3<f32>,
+    time: f32,
+    wind: vec4<f32>,        // direction (x, z), strength, gust strength
+    gust: vec4<f32>,        // gust frequency, gust scale
+    planes: array<vec4<f32>, 6>,
+    grid: vec4<f32>,        // first cell x, first cell z, cells per side, cell size
+    area: vec4<f32>,        // density map size, terrain scale, max blades
+    blade: vec4<f32>,       // height, width, draw distance, fade width
+    base_color: vec4<f32>,  // rgb, stiffness
+    tip_color: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> grass: GrassUniforms;
+
+struct BladeInput {
+    @location(0) position_facing: vec4<f32>,
+    @location(1) shape: vec4<f32>,  // height, width, shade, lean
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+}
+
+// Gust amount at a position, 0..1. Mirrors Wind::gust in engine-scene.
+fn wind_gust(position: vec3<f32>) -> f32 {
+    let along = dot(position.xz, grass.wind.xy) / grass.gust.y;
+    let phase = (along - grass.time * grass.gust.x) * 6.2831853;
+    let wave = sin(phase) * 0.6 + sin(phase * 2.3 + 1.7) * 0.4;
+    return clamp(wave * 0.5 + 0.5, 0.0, 1.0);
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, blade: BladeInput) -> VertexOutput {
+    // (level up the blade 0-3, side) for two quads and the tip triangle
+    var corners = array<vec2<f32>, 15>(
+        vec2<f32>(0.0, -1.0), vec2<f32>(0.0, 1.0), vec2<f32>(1.0, -1.0),
+        vec2<f32>(0.0, 1.0), vec2<f32>(1.0, 1.0), vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, -1.0), vec2<f32>(1.0, 1.0), vec2<f32>(2.0, -1.0),
+        vec2<f32>(1.0, 1.0), vec2<f32>(2.0, 1.0), vec2<f32>(2.0, -1.0),
+        vec2<f32>(2.0, -1.0), vec2<f32>(2.0, 1.0), vec2<f32>(3.0, 0.0),
+    );
+    let corner = corners[vertex_index];
+    let t = corner.x / 3.0;
+
+    let origin = blade.position_facing.xyz;
+    let facing = blade.position_facing.w;
+    let height = blade.shape.x;
+    let right = vec3<f32>(cos(facing), 0.0, sin(facing));
+    let forward = vec3<f32>(-sin(facing), 0.0, cos(facing));
+
+    // Taper to the tip and curve forward along the blade
+    var world_pos = origin
+        + right * corner.y * blade.shape.y * (1.0 - t)
+        + vec3<f32>(0.0, height * t, 0.0)
+        + forward * blade.shape.w * height * t * t;
+
+    // Bend downwind, most at the tip
+    let speed = grass.wind.z + grass.wind.w * wind_gust(origin);
+    let flutter = 1.0 + 0.3 * sin(grass.time * 3.0 + dot(origin.xz, vec2<f32>(1.7, 2.3)));
+    let bend = t * t * height * speed * flutter * 0.05 / max(grass.base_color.w, 0.05);
+    world_pos += vec3<f32>(grass.wind.x, 0.0, grass.wind.y) * bend;
+
+    var out: VertexOutput;
+    out.clip_position = grass.view_proj * vec4<f32>(world_pos, 1.0);
+    // Darker toward the roots, where the blades shade each other
+    out.color = mix(grass.base_color.rgb, grass.tip_color.rgb, t) * blade.shape.z * (0.6 + 0.4 * t);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.color, 1.0);
+}
@@ -0,0 +1,153 @@
+// Grass generation - grows the frame's blades on the GPU
+//
+// One thread per cell of a square grid around the camera. Each cell holds at
+// most one blade, jittered within it and kept with the painted density as
+// its probability. Cells are fixed in the world, so blades stay put as the
+// camera moves. Blades past the draw distance or outside the view frustum
+// are dropped; the rest are appended to the blade buffer, counted into the
+// indirect draw arguments, and shrink away approaching the draw distance.
+
+struct GrassUniforms {
+    view_proj: mat4x4<f32>,
+    camera_pos: vec3<f32>,
+    time: f32,
+    wind: vec4<f32>,        // direction (x, z), strength, gust strength
+    gust: vec4<f32>,        // gust frequency, gust scale
+    planes: array<vec4<f32>, 6>,
+    grid: vec4<f32>,        // first cell x, first cell z, cells per side, cell size
+    area: vec4<f32>,        // density map size, terrain scale, max blades
+    blade: vec4<f32>,       // height, width, draw distance, fade width
+    base_color: vec4<f32>,  // rgb, stiffness
+    tip_color: vec4<f32>,
+}
+
+struct Blade {
+    position: vec3<f32>,
+    facing: f32,
+    height: f32,
+    width: f32,
+    shade: f32,
+    lean: f32,
+}
+
+struct DrawArgs {
+    vertex_count: u32,
+    instance_count: atomic<u32>,
+    first_vertex: u32,
+    first_instance: u32,
+}
+
+@group(0) @binding(0)
+var<uniform> grass: GrassUniforms;
+@group(0) @binding(1)
+var density_map: texture_2d<f32>;
+// Heights in r, 1 in g where the cell is cut out
+@group(0) @binding(2)
+var terrain_map: texture_2d<f32>;
+@group(0) @binding(3)
+var<storage, read_write> blades: array<Blade>;
+@group(0) @binding(4)
+var<storage, read_write> draw_args: DrawArgs;
+
+fn pcg(value: u32) -> u32 {
+    let state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn random(seed: ptr<function, u32>) -> f32 {
+    *seed = pcg(*seed);
+    return f32(*seed) / 4294967295.0;
+}
+
+fn density_cell(cell: vec2<i32>) -> f32 {
+    let size = vec2<i32>(textureDimensions(density_map));
+    if any(cell < vec2<i32>(0)) || any(cell >= size) {
+        return 0.0;
+    }
+    return textureLoad(density_map, cell, 0).r;
+}
+
+// Coverage blended between cells. Mirrors Grass::density_at in engine-scene.
+fn density_at(xz: vec2<f32>) -> f32 {
+    let resolution = vec2<f32>(textureDimensions(density_map));
+    let cell = (xz / grass.area.x + 0.5) * resolution - 0.5;
+    let base = floor(cell);
+    let f = cell - base;
+    let c = vec2<i32>(base);
+    let top = mix(density_cell(c), density_cell(c + vec2<i32>(1, 0)), f.x);
+    let bottom = mix(density_cell(c + vec2<i32>(0, 1)), density_cell(c + vec2<i32>(1, 1)), f.x);
+    return mix(top, bottom, f.y);
+}
+
+// Height and hole flag. Mirrors HeightMap::sample_height in engine-assets.
+fn terrain_at(xz: vec2<f32>) -> vec2<f32> {
+    let size = vec2<i32>(textureDimensions(terrain_map));
+    let grid = xz / grass.area.y * vec2<f32>(size) + vec2<f32>(size) * 0.5;
+    let c0 = clamp(vec2<i32>(floor(grid)), vec2<i32>(0), size - 1);
+    let c1 = min(c0 + 1, size - 1);
+    let f = clamp(grid - vec2<f32>(c0), vec2<f32>(0.0), vec2<f32>(1.0));
+    let h00 = textureLoad(terrain_map, c0, 0);
+    let h10 = textureLoad(terrain_map, vec2<i32>(c1.x, c0.y), 0).r;
+    let h01 = textureLoad(terrain_map, vec2<i32>(c0.x, c1.y), 0).r;
+    let h11 = textureLoad(terrain_map, c1, 0).r;
+    let height = mix(mix(h00.r, h10, f.x), mix(h01, h11, f.x), f.y);
+    return vec2<f32>(height, h00.g);
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let cells = u32(grass.grid.z);
+    if id.x >= cells || id.y >= cells {
+        return;
+    }
+
+    // The same cell always rolls the same numbers
+    let cell = vec2<i32>(grass.grid.xy) + vec2<i32>(id.xy);
+    var seed = pcg(bitcast<u32>(cell.x) * 73856093u ^ bitcast<u32>(cell.y) * 19349663u);
+    let xz = (vec2<f32>(cell) + vec2<f32>(random(&seed), random(&seed))) * grass.grid.w;
+
+    if random(&seed) >= density_at(xz) {
+        return;
+    }
+    let terrain = terrain_at(xz);
+    if terrain.y > 0.5 {
+        return;
+    }
+    let position = vec3<f32>(xz.x, terrain.x, xz.y);
+
+    // Shrink away approaching the draw distance
+    let distance = length(position - grass.camera_pos);
+    var fade = select(0.0, 1.0, distance < grass.blade.z);
+    if grass.blade.w > 0.0 {
+        fade = clamp((grass.blade.z - distance) / grass.blade.w, 0.0, 1.0);
+    }
+    if fade <= 0.0 {
+        return;
+    }
+
+    let height = grass.blade.x * (0.7 + 0.6 * random(&seed));
+    let center = position + vec3<f32>(0.0, height * 0.5, 0.0);
+    for (var i = 0u; i < 6u; i++) {
+        let plane = grass.planes[i];
+        if dot(plane.xyz, center) + plane.w < -height {
+            return;
+        }
+    }
+
+    let index = atomicAdd(&draw_args.instance_count, 1u);
+    if index >= u32(grass.area.z) {
+        // Full: take the count back to the buffer size
+        atomicMin(&draw_args.instance_count, u32(grass.area.z));
+        return;
+    }
+
+    var blade: Blade;
+    blade.position = position;
+    blade.facing = random(&seed) * 6.2831853;
+    blade.height = height * fade;
+    blade.width = grass.blade.y * (0.8 + 0.4 * random(&seed));
+    blade.shade = 0.8 + 0.4 * random(&seed);
+    blade.lean = 0.1 + 0.3 * random(&seed);
+    blades[index] = blade;
+}
@@ -0,0 +1,100 @@
+// Editor grid shader - infinite reference grid on the y=0 plane
+//
+// A fullscreen triangle is unprojected to near/far points per pixel; the
+// fragment shader intersects that ray with the ground plane and draws
+// anti-aliased minor/major lines that fade out with distance.
+
+struct GridUniforms {
+    view_proj: mat4x4<f32>,
+    view_proj_inverse: mat4x4<f32>,
+    camera_pos: vec3<f32>,
+    cell_size: f32,
+    fade_distance: f32,
+    major_every: f32,
+    _padding: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> grid: GridUniforms;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) near_point: vec3<f32>,
+    @location(1) far_point: vec3<f32>,
+}
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    @builtin(frag_depth) depth: f32,
+}
+
+// Fullscreen triangle vertices
+const VERTICES = array<vec2<f32>, 3>(
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(3.0, -1.0),
+    vec2<f32>(-1.0, 3.0),
+);
+
+fn unproject(ndc: vec3<f32>) -> vec3<f32> {
+    let world = grid.view_proj_inverse * vec4<f32>(ndc, 1.0);
+    return world.xyz / world.w;
+}
+
+// Coverage of grid lines (1.0 on a line, 0.0 between), one pixel wide
+fn grid_lines(coord: vec2<f32>, cell: f32) -> f32 {
+    let scaled = coord / cell;
+    let width = fwidth(scaled);
+    let dist = abs(fract(scaled - 0.5) - 0.5) / width;
+    return 1.0 - min(min(dist.x, dist.y), 1.0);
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+
+    let pos = VERTICES[vertex_index];
+    out.clip_position = vec4<f32>(pos, 0.0, 1.0);
+    out.near_point = unproject(vec3<f32>(pos, 0.0));
+    out.far_point = unproject(vec3<f32>(pos, 1.0));
+
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    // Intersect the view ray with the y=0 plane
+    let ray = in.far_point - in.near_point;
+    let t = -in.near_point.y / ray.y;
+    let world = in.near_point + t * ray;
+
+    // Derivatives are taken before any discard
+    let minor = grid_lines(world.xz, grid.cell_size);
+    let major = grid_lines(world.xz, grid.cell_size * grid.major_every);
+    let axis_width = fwidth(world.xz);
+
+    var color = vec4<f32>(0.45, 0.45, 0.45, 0.35 * minor);
+    color = mix(color, vec4<f32>(0.6, 0.6, 0.6, 0.6), major);
+
+    // X axis (z = 0) in red, Z axis (x = 0) in blue
+    if (abs(world.z) < axis_width.y) {
+        color = vec4<f32>(0.85, 0.25, 0.25, 0.9);
+    }
+    if (abs(world.x) < axis_width.x) {
+        color = vec4<f32>(0.25, 0.45, 0.9, 0.9);
+    }
+
+    // Fade out towards the horizon
+    let distance_to_camera = distance(world, grid.camera_pos);
+    color.a *= 1.0 - smoothstep(grid.fade_distance * 0.5, grid.fade_distance, distance_to_camera);
+
+    if (t <= 0.0 || color.a < 0.01) {
+        discard;
+    }
+
+    let clip = grid.view_proj * vec4<f32>(world, 1.0);
+
+    var out: FragmentOutput;
+    out.color = color;
+    out.depth = clip.z / clip.w;
+    return out;
+}
@@ -6,6 +6,10 @@ struct Uniforms {
     view_proj: mat4x4<f32>,
     camera_pos: vec3<f32>,
     _padding: f32,
+    sun_direction: vec3<f32>,
+    _padding2: f32,
+    sun_radiance: vec3<f32>,
+    _padding3: f32,
 }
 
 @group(0) @binding(0)
@@ -62,9 +66,20 @@ struct ShadowUniforms {
     light_space_matrix: mat4x4<f32>,
 }
 
+// Image based lighting from the sky (see ibl.rs)
+@group(3) @binding(0)
+var irradiance_map: texture_cube<f32>;
+@group(3) @binding(1)
+var prefiltered_map: texture_cube<f32>;
+@group(3) @binding(2)
+var brdf_lut: texture_2d<f32>;
+@group(3) @binding(3)
+var ibl_sampler: sampler;
+
 // Push constants for per-object model matrix
 struct PushConstants {
     model: mat4x4<f32>,
+    lod_fade: vec4<f32>,  // x = share of pixels drawn, negative for the complementary pattern
 }
 var<push_constant> push: PushConstants;
 
@@ -79,6 +94,15 @@ struct VertexInput {
     @location(5) bitangent: vec3<f32>,
 }
 
+// Per-instance data for instanced draws (see instancing.rs)
+struct InstanceInput {
+    @location(6) model_0: vec4<f32>,
+    @location(7) model_1: vec4<f32>,
+    @location(8) model_2: vec4<f32>,
+    @location(9) model_3: vec4<f32>,
+    @location(10) lod_fade: vec4<f32>,
+}
+
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) world_position: vec3<f32>,
@@ -88,22 +112,34 @@ struct VertexOutput {
     @location(4) tangent: vec3<f32>,
     @location(5) bitangent: vec3<f32>,
     @location(6) shadow_position: vec4<f32>,
+    @location(7) @interpolate(flat) lod_fade: f32,
 }
 
 @vertex
 fn vs_main(in: VertexInput) -> VertexOutput {
+    // Model matrix from push constants, one draw per object
+    return transform_vertex(in, push.model, push.lod_fade.x);
+}
+
+@vertex
+fn vs_instanced(in: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    return transform_vertex(in, model, instance.lod_fade.x);
+}
+
+fn transform_vertex(in: VertexInput, model: mat4x4<f32>, lod_fade: f32) -> VertexOutput {
     var out: VertexOutput;
 
-    // Transform to world space using push constant model matrix
-    let world_position = push.model * vec4<f32>(in.position, 1.0);
+    // Transform to world space
+    let world_position = model * vec4<f32>(in.position, 1.0);
     out.clip_position = uniforms.view_proj * world_position;
     out.world_position = world_position.xyz;
 
     // Transform TBN basis to world space
     let normal_matrix = mat3x3<f32>(
-        push.model[0].xyz,
-        push.model[1].xyz,
-        push.model[2].xyz,
+        model[0].xyz,
+        model[1].xyz,
+        model[2].xyz,
     );
 
     out.normal = normalize(normal_matrix * in.normal);
@@ -115,6 +151,7 @@ fn vs_main(in: VertexInput) -> VertexOutput {
 
     // Calculate shadow position
     out.shadow_position = shadow_uniforms.light_space_matrix * world_position;
+    out.lod_fade = lod_fade;
 
     return out;
 }
@@ -125,7 +162,13 @@ fn sample_normal_map(uv: vec2<f32>, T: vec3<f32>, B: vec3<f32>, N: vec3<f32>) ->
     let normal_sample = textureSample(normal_texture, normal_sampler, uv).xyz;
 
     // Convert from [0,1] to [-1,1] range
-    let tangent_normal = normal_sample * 2.0 - 1.0;
+    var tangent_normal = normal_sample * 2.0 - 1.0;
+
+    // Two-channel (BC5) normal maps read back zero blue; rebuild Z
+    if (normal_sample.z < 0.01) {
+        let xy = tangent_normal.xy;
+        tangent_normal = vec3<f32>(xy, sqrt(max(1.0 - dot(xy, xy), 0.0)));
+    }
 
     // Construct TBN matrix
     let TBN = mat3x3<f32>(
@@ -178,6 +221,11 @@ fn fresnel_schlick(cos_theta: f32, F0: vec3<f32>) -> vec3<f32> {
     return F0 + (vec3<f32>(1.0) - F0) * pow(max(1.0 - cos_theta, 0.0), 5.0);
 }
 
+// Fresnel-Schlick with roughness, for ambient light from every direction
+fn fresnel_schlick_roughness(cos_theta: f32, F0: vec3<f32>, roughness: f32) -> vec3<f32> {
+    return F0 + (max(vec3<f32>(1.0 - roughness), F0) - F0) * pow(max(1.0 - cos_theta, 0.0), 5.0);
+}
+
 // Calculate shadow with PCF
 fn calculate_shadow(shadow_pos: vec4<f32>) -> f32 {
     // Perform perspective divide
@@ -195,7 +243,7 @@ fn calculate_shadow(shadow_pos: vec4<f32>) -> f32 {
 
     // PCF (Percentage Closer Filtering) for soft shadows
     var shadow = 0.0;
-    let texel_size = 1.0 / 2048.0; // SHADOW_MAP_SIZE
+    let texel_size = 1.0 / f32(textureDimensions(shadow_texture).x);
 
     for (var x = -1; x <= 1; x++) {
         for (var y = -1; y <= 1; y++) {
@@ -236,12 +284,8 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     var F0 = vec3<f32>(0.04);
     F0 = mix(F0, albedo, metallic);
 
-    // Directional light (simple for now - could be made configurable)
-    let light_dir = normalize(vec3<f32>(0.5, 1.0, 0.3));
-    let light_color = vec3<f32>(1.0, 1.0, 1.0);
-    let light_intensity = 3.0;
-
-    let L = light_dir;
+    // Directional light from the sun (see Renderer::set_sun)
+    let L = uniforms.sun_direction;
     let H = normalize(V + L);
 
     // Cook-Torrance BRDF
@@ -261,7 +305,7 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     kD = kD * (1.0 - metallic); // Metals have no diffuse
 
     // Calculate radiance
-    let radiance = light_color * light_intensity;
+    let radiance = uniforms.sun_radiance;
 
     // Apply shadow
     let shadow = calculate_shadow(in.shadow_position);
@@ -269,19 +313,39 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     // Outgoing light (diffuse + specular)
     var Lo = (kD * albedo / PI + specular) * radiance * NdotL * shadow;
 
-    // Ambient lighting (simplified IBL)
-    let ambient = vec3<f32>(0.03) * albedo * ao;
+    // Ambient lighting from the sky: diffuse irradiance plus the reflection
+    // pre-filtered for this roughness, scaled by the split-sum BRDF
+    let F_ambient = fresnel_schlick_roughness(NdotV, F0, roughness);
+    let kD_ambient = (vec3<f32>(1.0) - F_ambient) * (1.0 - metallic);
+    let irradiance = textureSample(irradiance_map, ibl_sampler, N).rgb;
+    let R = reflect(-V, N);
+    let max_lod = f32(textureNumLevels(prefiltered_map) - 1u);
+    let prefiltered = textureSampleLevel(prefiltered_map, ibl_sampler, R, roughness * max_lod).rgb;
+    let env_brdf = textureSample(brdf_lut, ibl_sampler, vec2<f32>(NdotV, roughness)).rg;
+    let specular_ambient = prefiltered * (F_ambient * env_brdf.x + env_brdf.y);
+    let ambient = (kD_ambient * irradiance * albedo + specular_ambient) * ao;
 
     // Add emissive
     let emissive = material.emissive_color * material.emissive_strength;
 
-    var color = ambient + Lo + emissive;
-
-    // Simple tone mapping (Reinhard)
-    color = color / (color + vec3<f32>(1.0));
-
-    // Gamma correction
-    color = pow(color, vec3<f32>(1.0 / 2.2));
+    // Linear HDR; exposure and tone mapping happen in post-processing
+    let color = ambient + Lo + emissive;
+
+    // LOD crossfade: screen-door dither so the outgoing and incoming levels
+    // share the pixels between them instead of popping
+    if in.lod_fade < 1.0 {
+        var bayer = array<f32, 16>(
+            0.0, 8.0, 2.0, 10.0,
+            12.0, 4.0, 14.0, 6.0,
+            3.0, 11.0, 1.0, 9.0,
+            15.0, 7.0, 13.0, 5.0,
+        );
+        let pixel = vec2<u32>(in.clip_position.xy) % vec2<u32>(4u);
+        let threshold = (bayer[pixel.y * 4u + pixel.x] + 0.5) / 16.0;
+        if (in.lod_fade >= 0.0 && threshold >= in.lod_fade) || (in.lod_fade < 0.0 && threshold < -in.lod_fade) {
+            discard;
+        }
+    }
 
     return vec4<f32>(color, albedo_sample.a * material.base_color.a);
 }
@@ -0,0 +1,73 @@
+// Skinned mesh shader - GPU skinning with a joint matrix palette
+//
+// Each vertex is moved by up to four joints, weighted. The joint matrices
+// already include the bone's world transform, so there is no model matrix.
+// The palette is a storage buffer that may have been sized for a larger
+// skeleton; only its first joint_count matrices are this skeleton's.
+
+struct CameraUniforms {
+    view_proj: mat4x4<f32>,
+    camera_pos: vec3<f32>,
+    _padding: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniforms;
+
+struct JointPalette {
+    joint_count: u32,
+    matrices: array<mat4x4<f32>>,
+}
+
+@group(1) @binding(0)
+var<storage, read> palette: JointPalette;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tex_coord: vec2<f32>,
+    @location(3) joint_indices: vec4<u32>,
+    @location(4) joint_weights: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) tex_coord: vec2<f32>,
+}
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    let indices = min(in.joint_indices, vec4<u32>(max(palette.joint_count, 1u) - 1u));
+    // Exporters don't always normalize the weights
+    let total = dot(in.joint_weights, vec4<f32>(1.0));
+    var weights = vec4<f32>(1.0, 0.0, 0.0, 0.0);
+    if total > 0.0 {
+        weights = in.joint_weights / total;
+    }
+    let skin = palette.matrices[indices.x] * weights.x
+        + palette.matrices[indices.y] * weights.y
+        + palette.matrices[indices.z] * weights.z
+        + palette.matrices[indices.w] * weights.w;
+
+    let world_pos = skin * vec4<f32>(in.position, 1.0);
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * world_pos;
+    out.world_position = world_pos.xyz;
+    out.world_normal = normalize((skin * vec4<f32>(in.normal, 0.0)).xyz);
+    out.tex_coord = in.tex_coord;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Simple directional lighting, as for foliage
+    let light_dir = normalize(vec3<f32>(0.5, 1.0, 0.3));
+    let ambient = 0.3;
+    let diffuse = max(dot(normalize(in.world_normal), light_dir), 0.0) * 0.7;
+
+    let albedo = vec3<f32>(0.8, 0.78, 0.75);
+    return vec4<f32>(albedo * (ambient + diffuse), 1.0);
+}
@@ -1,4 +1,4 @@
-// Skybox shader - renders cubemap at far plane
+// Skybox shader - renders the procedural sky or cubemap at far plane
 
 struct CameraUniforms {
     view_proj: mat4x4<f32>,
@@ -14,6 +14,22 @@ var skybox_texture: texture_cube<f32>;
 @group(1) @binding(1)
 var skybox_sampler: sampler;
 
+// Preetham sky, worked out on the CPU in sky.rs
+struct SkyUniforms {
+    sun_direction: vec4<f32>, // w = 1 for the procedural sky
+    perez: array<vec4<f32>, 5>, // A..E, xyz = Y, x, y
+    zenith: vec4<f32>,
+    sun_color: vec4<f32>, // w = daylight left
+}
+
+@group(1) @binding(2)
+var<uniform> sky: SkyUniforms;
+
+const NIGHT_SKY = vec3<f32>(0.002, 0.004, 0.012);
+// Cosine of the sun disc's angular radius, drawn a little large
+const SUN_DISC_COS: f32 = 0.99995;
+const SUN_DISC_RADIANCE: f32 = 40.0;
+
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec3<f32>,
@@ -42,7 +58,45 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
     return out;
 }
 
+fn perez(cos_theta: f32, gamma: f32) -> vec3<f32> {
+    let a = sky.perez[0].xyz;
+    let b = sky.perez[1].xyz;
+    let c = sky.perez[2].xyz;
+    let d = sky.perez[3].xyz;
+    let e = sky.perez[4].xyz;
+    let cos_gamma = cos(gamma);
+    return (1.0 + a * exp(b / cos_theta)) * (1.0 + c * exp(d * gamma) + e * cos_gamma * cos_gamma);
+}
+
+fn procedural_sky(direction: vec3<f32>) -> vec3<f32> {
+    let sun = sky.sun_direction.xyz;
+    let below = max(-direction.y, 0.0);
+    let gamma = acos(clamp(dot(direction, sun), -1.0, 1.0));
+    let yxy = sky.zenith.xyz * perez(max(direction.y, 0.01), gamma);
+
+    // xyY to XYZ to linear sRGB
+    let chroma_y = max(yxy.z, 1e-4);
+    let xyz = vec3<f32>(yxy.y / chroma_y * yxy.x, yxy.x, (1.0 - yxy.y - yxy.z) / chroma_y * yxy.x);
+    let rgb = vec3<f32>(
+        3.2406 * xyz.x - 1.5372 * xyz.y - 0.4986 * xyz.z,
+        -0.9689 * xyz.x + 1.8758 * xyz.y + 0.0415 * xyz.z,
+        0.0557 * xyz.x - 0.2040 * xyz.y + 1.0570 * xyz.z,
+    );
+    var color = max(rgb * (1.0 - below * 0.8) * sky.sun_color.w + NIGHT_SKY, vec3<f32>(0.0));
+
+    // Sun disc, hidden by the ground
+    let disc = smoothstep(SUN_DISC_COS - 0.00002, SUN_DISC_COS, dot(direction, sun));
+    if (direction.y > 0.0) {
+        color += sky.sun_color.rgb * sky.sun_color.w * SUN_DISC_RADIANCE * disc;
+    }
+    return color;
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(skybox_texture, skybox_sampler, normalize(in.tex_coords));
+    let direction = normalize(in.tex_coords);
+    if (sky.sun_direction.w > 0.5) {
+        return vec4<f32>(procedural_sky(direction), 1.0);
+    }
+    return textureSample(skybox_texture, skybox_sampler, direction);
 }
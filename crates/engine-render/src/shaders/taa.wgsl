@@ -0,0 +1,126 @@
+// Temporal anti-aliasing - velocity from depth, then history resolve
+//
+// Each frame the projection is jittered by a sub-pixel offset, so over
+// several frames every pixel is sampled at different positions. The velocity
+// pass reprojects each pixel's depth with last frame's camera to find where
+// it was (objects that moved themselves are drawn over it by the motion pass
+// in taa_motion.wgsl); the resolve pass blends the current frame with the history at that
+// position, clamped to the current pixel's neighbourhood so moving objects
+// and disocclusions don't leave ghosts.
+
+struct TaaUniforms {
+    inverse_view_proj: mat4x4<f32>, // this frame, jittered
+    previous_view_proj: mat4x4<f32>, // last frame, unjittered
+    view_proj: mat4x4<f32>, // this frame, jittered (for the motion pass)
+    viewport: vec4<f32>, // x, y, width, height in pixels
+    jitter: vec2<f32>, // NDC offset of this frame's projection
+    target_size: vec2<f32>,
+    feedback: f32, // history weight
+    reset: f32, // 1.0 when there is no usable history
+    _padding: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> taa: TaaUniforms;
+
+// Resolve inputs
+@group(0) @binding(1)
+var current_texture: texture_2d<f32>;
+
+@group(0) @binding(2)
+var history_texture: texture_2d<f32>;
+
+@group(0) @binding(3)
+var velocity_texture: texture_2d<f32>;
+
+@group(0) @binding(4)
+var linear_sampler: sampler;
+
+// Velocity input
+@group(0) @binding(5)
+var depth_texture: texture_depth_2d;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+}
+
+// Fullscreen triangle vertices
+const VERTICES = array<vec2<f32>, 3>(
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(3.0, -1.0),
+    vec2<f32>(-1.0, 3.0),
+);
+
+const TEX_COORDS = array<vec2<f32>, 3>(
+    vec2<f32>(0.0, 1.0),
+    vec2<f32>(2.0, 1.0),
+    vec2<f32>(0.0, -1.0),
+);
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(VERTICES[vertex_index], 0.0, 1.0);
+    out.tex_coords = TEX_COORDS[vertex_index];
+    return out;
+}
+
+// Screen-space motion of each pixel since last frame, in UV units
+@fragment
+fn fs_velocity(in: VertexOutput) -> @location(0) vec4<f32> {
+    let pixel = in.clip_position.xy;
+    let depth = textureLoad(depth_texture, vec2<i32>(pixel), 0);
+
+    let local = (pixel - taa.viewport.xy) / taa.viewport.zw;
+    let ndc = vec2<f32>(local.x * 2.0 - 1.0, 1.0 - local.y * 2.0);
+    let world = taa.inverse_view_proj * vec4<f32>(ndc, depth, 1.0);
+    let previous_clip = taa.previous_view_proj * vec4<f32>(world.xyz / world.w, 1.0);
+    let previous_ndc = previous_clip.xy / previous_clip.w;
+
+    // Compare unjittered positions, so a still camera has no velocity
+    let delta = (ndc - taa.jitter) - previous_ndc;
+    let velocity = vec2<f32>(delta.x, -delta.y) * 0.5 * taa.viewport.zw / taa.target_size;
+    return vec4<f32>(velocity, 0.0, 1.0);
+}
+
+fn luma(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+@fragment
+fn fs_resolve(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(current_texture));
+    let pixel = vec2<i32>(in.clip_position.xy);
+    let current = textureLoad(current_texture, pixel, 0).rgb;
+
+    // Colour range of the 3x3 neighbourhood
+    var box_min = current;
+    var box_max = current;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let neighbour = textureLoad(current_texture, clamp(pixel + vec2<i32>(x, y), vec2<i32>(0), size - 1), 0).rgb;
+            box_min = min(box_min, neighbour);
+            box_max = max(box_max, neighbour);
+        }
+    }
+
+    let velocity = textureLoad(velocity_texture, pixel, 0).xy;
+    let history_uv = in.tex_coords - velocity;
+    let off_screen = any(history_uv < vec2<f32>(0.0)) || any(history_uv > vec2<f32>(1.0));
+    if taa.reset > 0.5 || off_screen {
+        return vec4<f32>(current, 1.0);
+    }
+
+    let history = clamp(
+        textureSampleLevel(history_texture, linear_sampler, history_uv, 0.0).rgb,
+        box_min,
+        box_max,
+    );
+
+    // Weight by inverse brightness so bright HDR samples don't flicker
+    let current_weight = (1.0 - taa.feedback) / (1.0 + luma(current));
+    let history_weight = taa.feedback / (1.0 + luma(history));
+    let color = (current * current_weight + history * history_weight) / (current_weight + history_weight);
+    return vec4<f32>(color, 1.0);
+}
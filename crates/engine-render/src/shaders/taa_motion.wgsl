@@ -0,0 +1,112 @@
+// Temporal anti-aliasing - object motion
+//
+// The velocity pass only knows how the camera moved. Objects that moved
+// themselves are drawn again over it with this frame's and last frame's
+// model matrices (or joint matrices for skinned meshes), so their pixels are
+// reprojected to where the object was rather than where the background was.
+// The scene's depth is kept, so only the visible surface writes.
+
+struct TaaUniforms {
+    inverse_view_proj: mat4x4<f32>, // this frame, jittered
+    previous_view_proj: mat4x4<f32>, // last frame, unjittered
+    view_proj: mat4x4<f32>, // this frame, jittered
+    viewport: vec4<f32>, // x, y, width, height in pixels
+    jitter: vec2<f32>, // NDC offset of this frame's projection
+    target_size: vec2<f32>,
+    feedback: f32,
+    reset: f32,
+    _padding: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> taa: TaaUniforms;
+
+struct JointPalette {
+    joint_count: u32,
+    matrices: array<mat4x4<f32>>, // this frame's, then last frame's
+}
+
+@group(1) @binding(0)
+var<storage, read> palette: JointPalette;
+
+struct MeshInput {
+    @location(0) position: vec3<f32>,
+}
+
+struct MotionInstance {
+    @location(6) model_0: vec4<f32>,
+    @location(7) model_1: vec4<f32>,
+    @location(8) model_2: vec4<f32>,
+    @location(9) model_3: vec4<f32>,
+    @location(10) previous_model_0: vec4<f32>,
+    @location(11) previous_model_1: vec4<f32>,
+    @location(12) previous_model_2: vec4<f32>,
+    @location(13) previous_model_3: vec4<f32>,
+}
+
+struct SkinnedInput {
+    @location(0) position: vec3<f32>,
+    @location(3) joint_indices: vec4<u32>,
+    @location(4) joint_weights: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) current_clip: vec4<f32>,
+    @location(1) previous_clip: vec4<f32>,
+}
+
+fn motion_output(world: vec4<f32>, previous_world: vec4<f32>) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = taa.view_proj * world;
+    out.current_clip = out.clip_position;
+    out.previous_clip = taa.previous_view_proj * previous_world;
+    return out;
+}
+
+@vertex
+fn vs_mesh(in: MeshInput, instance: MotionInstance) -> VertexOutput {
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    let previous_model = mat4x4<f32>(
+        instance.previous_model_0,
+        instance.previous_model_1,
+        instance.previous_model_2,
+        instance.previous_model_3,
+    );
+    let position = vec4<f32>(in.position, 1.0);
+    return motion_output(model * position, previous_model * position);
+}
+
+@vertex
+fn vs_skinned(in: SkinnedInput) -> VertexOutput {
+    let count = max(palette.joint_count, 1u);
+    let indices = min(in.joint_indices, vec4<u32>(count - 1u));
+    // Exporters don't always normalize the weights
+    let total = dot(in.joint_weights, vec4<f32>(1.0));
+    var weights = vec4<f32>(1.0, 0.0, 0.0, 0.0);
+    if total > 0.0 {
+        weights = in.joint_weights / total;
+    }
+    let skin = palette.matrices[indices.x] * weights.x
+        + palette.matrices[indices.y] * weights.y
+        + palette.matrices[indices.z] * weights.z
+        + palette.matrices[indices.w] * weights.w;
+    let previous_indices = indices + vec4<u32>(count);
+    let previous_skin = palette.matrices[previous_indices.x] * weights.x
+        + palette.matrices[previous_indices.y] * weights.y
+        + palette.matrices[previous_indices.z] * weights.z
+        + palette.matrices[previous_indices.w] * weights.w;
+
+    let position = vec4<f32>(in.position, 1.0);
+    return motion_output(skin * position, previous_skin * position);
+}
+
+// Screen-space motion since last frame in UV units, as in the velocity pass
+@fragment
+fn fs_motion(in: VertexOutput) -> @location(0) vec4<f32> {
+    let ndc = in.current_clip.xy / in.current_clip.w;
+    let previous_ndc = in.previous_clip.xy / in.previous_clip.w;
+    let delta = (ndc - taa.jitter) - previous_ndc;
+    let velocity = vec2<f32>(delta.x, -delta.y) * 0.5 * taa.viewport.zw / taa.target_size;
+    return vec4<f32>(velocity, 0.0, 1.0);
+}
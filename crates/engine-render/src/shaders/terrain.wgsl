@@ -0,0 +1,209 @@
+// Terrain shader - blends up to 8 splat-mapped layers
+// Each layer is an albedo and normal texture tiled in world space; the splat
+// textures hold their weights on the heightmap grid (layers 0-3 and 4-7).
+// Lighting follows pbr_advanced_nm.wgsl: sun, shadow map and sky IBL.
+
+struct Uniforms {
+    view_proj: mat4x4<f32>,
+    camera_pos: vec3<f32>,
+    _padding: f32,
+    sun_direction: vec3<f32>,
+    _padding2: f32,
+    sun_radiance: vec3<f32>,
+    _padding3: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+struct TerrainUniforms {
+    layers: array<vec4<f32>, 8>, // rgb = tint, w = repeats per world unit
+    grid: vec4<f32>, // x = width, y = depth, z = layer count
+}
+
+@group(1) @binding(0)
+var<uniform> terrain: TerrainUniforms;
+@group(1) @binding(1)
+var splat_low: texture_2d<f32>;
+@group(1) @binding(2)
+var splat_high: texture_2d<f32>;
+@group(1) @binding(3)
+var splat_sampler: sampler;
+@group(1) @binding(4)
+var albedo_layers: texture_2d_array<f32>;
+@group(1) @binding(5)
+var normal_layers: texture_2d_array<f32>;
+@group(1) @binding(6)
+var layer_sampler: sampler;
+
+// Shadow map
+@group(2) @binding(0)
+var shadow_texture: texture_depth_2d;
+@group(2) @binding(1)
+var shadow_sampler: sampler_comparison;
+@group(2) @binding(2)
+var<uniform> shadow_uniforms: ShadowUniforms;
+
+struct ShadowUniforms {
+    light_space_matrix: mat4x4<f32>,
+}
+
+// Image based lighting from the sky (see ibl.rs)
+@group(3) @binding(0)
+var irradiance_map: texture_cube<f32>;
+@group(3) @binding(1)
+var prefiltered_map: texture_cube<f32>;
+@group(3) @binding(2)
+var brdf_lut: texture_2d<f32>;
+@group(3) @binding(3)
+var ibl_sampler: sampler;
+
+struct PushConstants {
+    model: mat4x4<f32>,
+}
+var<push_constant> push: PushConstants;
+
+const PI: f32 = 3.14159265359;
+// Terrain is matte and never metallic
+const ROUGHNESS: f32 = 0.9;
+const F0: vec3<f32> = vec3<f32>(0.04);
+// Layers below this weight are skipped
+const MIN_WEIGHT: f32 = 0.004;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tex_coord: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tex_coord: vec2<f32>,
+    @location(3) shadow_position: vec4<f32>,
+}
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    let world_position = push.model * vec4<f32>(in.position, 1.0);
+    out.clip_position = uniforms.view_proj * world_position;
+    out.world_position = world_position.xyz;
+    let normal_matrix = mat3x3<f32>(push.model[0].xyz, push.model[1].xyz, push.model[2].xyz);
+    out.normal = normalize(normal_matrix * in.normal);
+    out.tex_coord = in.tex_coord;
+    out.shadow_position = shadow_uniforms.light_space_matrix * world_position;
+    return out;
+}
+
+fn distribution_ggx(N: vec3<f32>, H: vec3<f32>, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let NdotH = max(dot(N, H), 0.0);
+    let denom = NdotH * NdotH * (a2 - 1.0) + 1.0;
+    return a2 / max(PI * denom * denom, 0.0001);
+}
+
+fn geometry_schlick_ggx(NdotV: f32, roughness: f32) -> f32 {
+    let r = roughness + 1.0;
+    let k = (r * r) / 8.0;
+    return NdotV / max(NdotV * (1.0 - k) + k, 0.0001);
+}
+
+fn fresnel_schlick_roughness(cos_theta: f32, f0: vec3<f32>, roughness: f32) -> vec3<f32> {
+    return f0 + (max(vec3<f32>(1.0 - roughness), f0) - f0) * pow(max(1.0 - cos_theta, 0.0), 5.0);
+}
+
+// Shadow with 3x3 PCF, as in the PBR shader
+fn calculate_shadow(shadow_pos: vec4<f32>) -> f32 {
+    var proj_coords = shadow_pos.xyz / shadow_pos.w;
+    proj_coords = proj_coords * 0.5 + 0.5;
+    if proj_coords.x < 0.0 || proj_coords.x > 1.0 ||
+       proj_coords.y < 0.0 || proj_coords.y > 1.0 ||
+       proj_coords.z > 1.0 {
+        return 1.0;
+    }
+
+    var shadow = 0.0;
+    let texel_size = 1.0 / f32(textureDimensions(shadow_texture).x);
+    for (var x = -1; x <= 1; x++) {
+        for (var y = -1; y <= 1; y++) {
+            let offset = vec2<f32>(f32(x), f32(y)) * texel_size;
+            shadow += textureSampleCompare(shadow_texture, shadow_sampler, proj_coords.xy + offset, proj_coords.z);
+        }
+    }
+    return shadow / 9.0;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Vertex UVs run 0..1 corner to corner of the grid; splat texels sit at cell centers
+    let grid = max(terrain.grid.xy, vec2<f32>(2.0));
+    let splat_uv = (in.tex_coord * (grid - 1.0) + 0.5) / grid;
+    let low = textureSample(splat_low, splat_sampler, splat_uv);
+    let high = textureSample(splat_high, splat_sampler, splat_uv);
+    var weights = array<f32, 8>(low.x, low.y, low.z, low.w, high.x, high.y, high.z, high.w);
+
+    // Layers tile in world space; gradients are taken here so samples can
+    // be skipped per layer below
+    let world_uv = in.world_position.xz;
+    let uv_dx = dpdx(world_uv);
+    let uv_dy = dpdy(world_uv);
+
+    var albedo = vec3<f32>(0.0);
+    var tangent_normal = vec3<f32>(0.0);
+    var total = 0.0;
+    let layer_count = u32(terrain.grid.z);
+    for (var i = 0u; i < layer_count; i++) {
+        let weight = weights[i];
+        if weight < MIN_WEIGHT {
+            continue;
+        }
+        let layer = terrain.layers[i];
+        let uv = world_uv * layer.w;
+        let color = textureSampleGrad(albedo_layers, layer_sampler, uv, i, uv_dx * layer.w, uv_dy * layer.w).rgb;
+        let normal = textureSampleGrad(normal_layers, layer_sampler, uv, i, uv_dx * layer.w, uv_dy * layer.w).xyz;
+        albedo += color * layer.rgb * weight;
+        tangent_normal += (normal * 2.0 - 1.0) * weight;
+        total += weight;
+    }
+    if total > 0.0 {
+        albedo /= total;
+        tangent_normal /= total;
+    } else {
+        albedo = terrain.layers[0].rgb;
+        tangent_normal = vec3<f32>(0.0, 0.0, 1.0);
+    }
+
+    // Tangent frame follows the world axes the layers tile along
+    let geometric_normal = normalize(in.normal);
+    let T = normalize(vec3<f32>(1.0, 0.0, 0.0) - geometric_normal * geometric_normal.x);
+    let B = cross(T, geometric_normal);
+    let N = normalize(mat3x3<f32>(T, B, geometric_normal) * tangent_normal);
+
+    let V = normalize(uniforms.camera_pos - in.world_position);
+    let L = uniforms.sun_direction;
+    let H = normalize(V + L);
+    let NdotL = max(dot(N, L), 0.0);
+    let NdotV = max(dot(N, V), 0.0);
+
+    // Cook-Torrance for the sun
+    let F = F0 + (vec3<f32>(1.0) - F0) * pow(max(1.0 - dot(H, V), 0.0), 5.0);
+    let G = geometry_schlick_ggx(NdotV, ROUGHNESS) * geometry_schlick_ggx(NdotL, ROUGHNESS);
+    let specular = distribution_ggx(N, H, ROUGHNESS) * G * F / (4.0 * NdotV * NdotL + 0.0001);
+    let kD = vec3<f32>(1.0) - F;
+    let shadow = calculate_shadow(in.shadow_position);
+    let Lo = (kD * albedo / PI + specular) * uniforms.sun_radiance * NdotL * shadow;
+
+    // Ambient light from the sky
+    let F_ambient = fresnel_schlick_roughness(NdotV, F0, ROUGHNESS);
+    let irradiance = textureSample(irradiance_map, ibl_sampler, N).rgb;
+    let R = reflect(-V, N);
+    let max_lod = f32(textureNumLevels(prefiltered_map) - 1u);
+    let prefiltered = textureSampleLevel(prefiltered_map, ibl_sampler, R, ROUGHNESS * max_lod).rgb;
+    let env_brdf = textureSample(brdf_lut, ibl_sampler, vec2<f32>(NdotV, ROUGHNESS)).rg;
+    let ambient = (vec3<f32>(1.0) - F_ambient) * irradiance * albedo + prefiltered * (F_ambient * env_brdf.x + env_brdf.y);
+
+    return vec4<f32>(ambient + Lo, 1.0);
+}
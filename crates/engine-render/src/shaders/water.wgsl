@@ -1,4 +1,4 @@
-// Water shader with transparency, waves, and fresnel
+// Water shader with transparency, waves, fresnel and planar reflections
 
 struct Uniforms {
     view_proj: mat4x4<f32>,
@@ -6,12 +6,20 @@ struct Uniforms {
     time: f32,
     flow_direction: vec2<f32>,
     flow_speed: f32,
-    _padding: vec2<f32>,
+    // 0 when there is no reflection target this frame
+    reflection_strength: f32,
+    reflection_view_proj: mat4x4<f32>,
 }
 
 @group(0) @binding(0)
 var<uniform> uniforms: Uniforms;
 
+// Planar reflection target
+@group(0) @binding(1)
+var reflection_texture: texture_2d<f32>;
+@group(0) @binding(2)
+var reflection_sampler: sampler;
+
 // Texture and sampler
 @group(1) @binding(0)
 var t_texture: texture_2d<f32>;
@@ -184,11 +192,18 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     // Base water color (turquoise)
     let water_color = tex_color.rgb * in.color;
 
-    // Reduced fresnel effect (water's reflectance, scaled down)
-    let fresnel_factor = fresnel(in.view_direction, in.normal, 0.02) * 0.3;
-
-    // Mix water color with subtle reflection
-    let reflection_color = vec3<f32>(0.7, 0.8, 0.9); // Subtle sky-like reflection
+    // Reduced fresnel effect (water's reflectance, scaled down less when
+    // there is a real reflection to show)
+    let fresnel_factor = fresnel(in.view_direction, in.normal, 0.02) * mix(0.3, 0.6, uniforms.reflection_strength);
+
+    // Reflected scene where the mirrored view saw this point, rippled by the
+    // waves; where it drew nothing (alpha 0), a subtle sky-like color
+    let reflected_clip = uniforms.reflection_view_proj * vec4<f32>(in.world_position, 1.0);
+    let reflection_uv = reflected_clip.xy / max(reflected_clip.w, 0.0001) * vec2<f32>(0.5, -0.5) + 0.5
+        + in.normal.xz * 0.03;
+    let reflected = textureSample(reflection_texture, reflection_sampler, clamp(reflection_uv, vec2<f32>(0.0), vec2<f32>(1.0)));
+    let sky_color = vec3<f32>(0.7, 0.8, 0.9);
+    let reflection_color = mix(sky_color, reflected.rgb, reflected.a * uniforms.reflection_strength);
     let final_color = mix(water_color, reflection_color, fresnel_factor);
 
     // Apply lighting
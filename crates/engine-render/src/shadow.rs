@@ -4,12 +4,14 @@ use anyhow::Result;
 use glam::{Mat4, Vec3};
 use wgpu::util::DeviceExt;
 
-/// Shadow map configuration
+/// Shadow map configuration (default resolution; see ShadowMap::with_size)
 pub const SHADOW_MAP_SIZE: u32 = 2048;
 pub const SHADOW_MAP_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
 
 /// Shadow map resources
 pub struct ShadowMap {
+    /// Width and height of the depth texture
+    pub size: u32,
     /// Shadow depth texture
     pub texture: wgpu::Texture,
     /// Shadow texture view
@@ -41,12 +43,18 @@ pub struct ShadowPushConstants {
 impl ShadowMap {
     /// Create a new shadow map
     pub fn new(device: &wgpu::Device) -> Result<Self> {
+        Self::with_size(device, SHADOW_MAP_SIZE)
+    }
+
+    /// Create a shadow map `size` texels on a side
+    pub fn with_size(device: &wgpu::Device, size: u32) -> Result<Self> {
+        let size = size.clamp(1, device.limits().max_texture_dimension_2d);
         // Create shadow depth texture
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label: Some("Shadow Map Texture"),
             size: wgpu::Extent3d {
-                width: SHADOW_MAP_SIZE,
-                height: SHADOW_MAP_SIZE,
+                width: size,
+                height: size,
                 depth_or_array_layers: 1,
             },
             mip_level_count: 1,
@@ -174,6 +182,7 @@ impl ShadowMap {
         });
 
         Ok(Self {
+            size,
             texture,
             view,
             sampler,
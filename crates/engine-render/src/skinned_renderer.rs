@@ -0,0 +1,423 @@
+// Skinned Renderer - GPU skinning for animated characters
+//
+// Skinned meshes are uploaded once per model; every instance of a model has
+// its own joint palette (a storage buffer of joint matrices, at least as large
+// as its skeleton) updated each frame from its posed bones. A palette starts
+// with the number of joints in use, so one kept from a larger skeleton never
+// skins with the matrices left past the end of the current one. Last frame's
+// matrices follow the current ones, for the TAA motion pass.
+
+use anyhow::Result;
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3};
+use std::collections::HashMap;
+use wgpu::util::DeviceExt;
+
+/// Skinned vertex sent to the GPU (64 bytes)
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct SkinnedVertexGpu {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub tex_coord: [f32; 2],
+    pub joints: [u32; 4],
+    pub weights: [f32; 4],
+}
+
+impl SkinnedVertexGpu {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<SkinnedVertexGpu>() as u64,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                // Position
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                // Normal
+                wgpu::VertexAttribute {
+                    offset: 12,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                // TexCoord
+                wgpu::VertexAttribute {
+                    offset: 24,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                // Joint indices
+                wgpu::VertexAttribute {
+                    offset: 32,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Uint32x4,
+                },
+                // Joint weights
+                wgpu::VertexAttribute {
+                    offset: 48,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// Camera uniforms for skinned rendering
+#[repr(C, align(16))]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct SkinnedCameraUniforms {
+    pub view_proj: [[f32; 4]; 4],
+    pub camera_pos: [f32; 3],
+    pub _padding: f32,
+}
+
+/// One uploaded primitive of a skinned model
+pub struct GpuSkinnedMesh {
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub num_indices: u32,
+}
+
+/// Bytes before a palette's matrices: the joint count, padded to a matrix's alignment
+const PALETTE_HEADER_SIZE: usize = 16;
+
+/// Joint matrices of one skinned instance
+struct JointPalette {
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    /// Joints the buffer holds (each with this and last frame's matrix)
+    capacity: usize,
+    /// Matrices written last frame
+    previous: Vec<Mat4>,
+}
+
+/// Whether a palette holding `capacity` matrices (None if there is none yet)
+/// has to be replaced to take a skeleton of `joints`
+fn needs_new_palette(capacity: Option<usize>, joints: usize) -> bool {
+    capacity.is_none_or(|capacity| capacity < joints)
+}
+
+/// Palette buffer contents: the joint count, the matrices, then last frame's
+/// matrices (the current ones again when the skeleton changed)
+fn palette_contents(matrices: &[Mat4], previous: &[Mat4]) -> Vec<u8> {
+    let previous = if previous.len() == matrices.len() {
+        previous
+    } else {
+        matrices
+    };
+    let mut contents = vec![0; PALETTE_HEADER_SIZE];
+    contents[..4].copy_from_slice(&(matrices.len() as u32).to_ne_bytes());
+    for matrix in matrices.iter().chain(previous) {
+        contents.extend_from_slice(bytemuck::cast_slice(&matrix.to_cols_array()));
+    }
+    contents
+}
+
+/// Layout of a joint palette bind group, shared with the TAA motion pass
+pub fn joint_palette_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Skinned Joint Bind Group Layout"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    })
+}
+
+/// Renderer for skinned meshes
+pub struct SkinnedRenderer {
+    pipeline: wgpu::RenderPipeline,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    joint_bind_group_layout: wgpu::BindGroupLayout,
+    /// Uploaded primitives by model path
+    models: HashMap<String, Vec<GpuSkinnedMesh>>,
+    /// Joint palettes by instance (usually the entity id)
+    palettes: HashMap<u64, JointPalette>,
+}
+
+impl SkinnedRenderer {
+    /// Create a new skinned renderer
+    pub fn new(
+        device: &wgpu::Device,
+        surface_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Result<Self> {
+        let camera_uniforms = SkinnedCameraUniforms {
+            view_proj: Mat4::IDENTITY.to_cols_array_2d(),
+            camera_pos: [0.0; 3],
+            _padding: 0.0,
+        };
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Skinned Camera Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[camera_uniforms]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let uniform_layout_entry = wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let camera_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Skinned Camera Bind Group Layout"),
+                entries: &[uniform_layout_entry],
+            });
+        let joint_bind_group_layout = joint_palette_layout(device);
+
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Skinned Camera Bind Group"),
+            layout: &camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Skinned Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/skinned.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Skinned Pipeline Layout"),
+            bind_group_layouts: &[&camera_bind_group_layout, &joint_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Skinned Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[SkinnedVertexGpu::desc()],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: surface_format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        Ok(Self {
+            pipeline,
+            camera_buffer,
+            camera_bind_group,
+            joint_bind_group_layout,
+            models: HashMap::new(),
+            palettes: HashMap::new(),
+        })
+    }
+
+    /// Update camera uniforms
+    pub fn update_camera(&self, queue: &wgpu::Queue, view_proj: Mat4, camera_pos: Vec3) {
+        let uniforms = SkinnedCameraUniforms {
+            view_proj: view_proj.to_cols_array_2d(),
+            camera_pos: camera_pos.to_array(),
+            _padding: 0.0,
+        };
+        queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+    }
+
+    /// Whether a model's primitives have been uploaded
+    pub fn has_model(&self, path: &str) -> bool {
+        self.models.contains_key(path)
+    }
+
+    /// Upload a model's primitives under its path
+    pub fn upload_model<'a>(
+        &mut self,
+        device: &wgpu::Device,
+        path: &str,
+        primitives: impl IntoIterator<Item = (&'a [SkinnedVertexGpu], &'a [u32])>,
+    ) {
+        let meshes = primitives
+            .into_iter()
+            .map(|(vertices, indices)| GpuSkinnedMesh {
+                vertex_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(&format!("Skinned Vertex Buffer: {}", path)),
+                    contents: bytemuck::cast_slice(vertices),
+                    usage: wgpu::BufferUsages::VERTEX,
+                }),
+                index_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(&format!("Skinned Index Buffer: {}", path)),
+                    contents: bytemuck::cast_slice(indices),
+                    usage: wgpu::BufferUsages::INDEX,
+                }),
+                num_indices: indices.len() as u32,
+            })
+            .collect();
+        self.models.insert(path.to_string(), meshes);
+    }
+
+    /// Write an instance's joint matrices, creating its palette the first time
+    /// and growing it when the skeleton has more joints than it holds. A
+    /// larger palette is kept for a smaller skeleton.
+    pub fn update_joints(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        instance: u64,
+        matrices: &[Mat4],
+    ) {
+        if matrices.is_empty() {
+            return;
+        }
+        let capacity = self.palettes.get(&instance).map(|palette| palette.capacity);
+        if needs_new_palette(capacity, matrices.len()) {
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Skinned Joint Palette"),
+                size: (PALETTE_HEADER_SIZE + 2 * std::mem::size_of_val(matrices)) as u64,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Skinned Joint Bind Group"),
+                layout: &self.joint_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+            });
+            let palette = JointPalette {
+                buffer,
+                bind_group,
+                capacity: matrices.len(),
+                previous: Vec::new(),
+            };
+            self.palettes.insert(instance, palette);
+        }
+
+        let palette = self.palettes.get_mut(&instance).expect("palette was just created");
+        queue.write_buffer(&palette.buffer, 0, &palette_contents(matrices, &palette.previous));
+        palette.previous = matrices.to_vec();
+    }
+
+    /// Drop the palettes of instances that no longer exist
+    pub fn retain_instances(&mut self, keep: impl Fn(u64) -> bool) {
+        self.palettes.retain(|&instance, _| keep(instance));
+    }
+
+    /// Draw every primitive of a model with an instance's joints. Returns the
+    /// number of indices drawn.
+    pub fn render<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        path: &str,
+        instance: u64,
+    ) -> u32 {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        self.draw_with_joints(render_pass, path, instance)
+    }
+
+    /// Draw every primitive of a model with an instance's joints (bind group
+    /// 1) under the pipeline already set. Returns the number of indices drawn.
+    pub fn draw_with_joints<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        path: &str,
+        instance: u64,
+    ) -> u32 {
+        let (Some(meshes), Some(palette)) = (self.models.get(path), self.palettes.get(&instance))
+        else {
+            return 0;
+        };
+        render_pass.set_bind_group(1, &palette.bind_group, &[]);
+        let mut indices = 0;
+        for mesh in meshes {
+            render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..mesh.num_indices, 0, 0..1);
+            indices += mesh.num_indices;
+        }
+        indices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reused_palette_only_skins_the_live_joints() {
+        let joints = |count: usize| -> Vec<Mat4> {
+            (0..count)
+                .map(|i| Mat4::from_translation(Vec3::splat(i as f32)))
+                .collect()
+        };
+        let count = |contents: &[u8]| u32::from_ne_bytes(contents[..4].try_into().unwrap());
+
+        // First update allocates, a larger skeleton grows the palette
+        assert!(needs_new_palette(None, 3));
+        assert!(needs_new_palette(Some(3), 5));
+        let five = palette_contents(&joints(5), &[]);
+        assert_eq!(five.len(), PALETTE_HEADER_SIZE + 2 * 5 * 64);
+        assert_eq!(count(&five), 5);
+
+        // A smaller skeleton reuses it, and the count hides the stale matrices
+        assert!(!needs_new_palette(Some(5), 2));
+        let two = palette_contents(&joints(2), &joints(5));
+        assert_eq!(count(&two), 2);
+        assert_eq!(two.len(), PALETTE_HEADER_SIZE + 2 * 2 * 64);
+        let second: &[f32] = bytemuck::cast_slice(&two[PALETTE_HEADER_SIZE + 64..]);
+        assert_eq!(&second[12..15], &[1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_palette_keeps_last_frames_matrices() {
+        let still = [Mat4::IDENTITY, Mat4::IDENTITY];
+        let moved = [Mat4::IDENTITY, Mat4::from_translation(Vec3::X)];
+        let contents = palette_contents(&moved, &still);
+        let matrices: &[f32] = bytemuck::cast_slice(&contents[PALETTE_HEADER_SIZE..]);
+
+        // This frame's second joint has moved, last frame's hasn't
+        assert_eq!(matrices[16 + 12], 1.0);
+        assert_eq!(matrices[3 * 16 + 12], 0.0);
+    }
+}
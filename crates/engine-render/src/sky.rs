@@ -0,0 +1,221 @@
+// Procedural sky - Preetham daylight model
+//
+// The sky's luminance and chromaticity (xyY) in a direction follow the Perez
+// distribution: five coefficients per channel, set by the turbidity, scale the
+// zenith value by how far the direction is from the zenith and from the sun.
+// The coefficients are worked out here once per frame and handed to the
+// skybox shader, which evaluates the same formula per pixel; the CPU copy
+// feeds the image based lighting. Past sunset the sky fades to a dim night
+// blue, and the sun's own light is reddened by the air it passes through.
+
+use glam::Vec3;
+
+/// Scales Preetham luminance (kcd/m^2) to scene radiance
+const LUMINANCE_SCALE: f32 = 0.05;
+/// Radiance of the sun light at full intensity and zenith
+const SUN_RADIANCE: f32 = 3.0;
+/// Sky left once the sun is well below the horizon
+const NIGHT_SKY: Vec3 = Vec3::new(0.002, 0.004, 0.012);
+/// Per-channel optical depth of one air mass, reddening low sunlight
+const EXTINCTION: Vec3 = Vec3::new(0.05, 0.11, 0.25);
+/// Highest the model's sun may sit from the zenith; past it the sky fades
+const MAX_SUN_ZENITH: f32 = 1.55;
+
+/// A daylight sky for a sun direction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProceduralSky {
+    /// Direction toward the sun
+    pub sun_direction: Vec3,
+    /// Haze in the air, 2 is a clear sky and 10 a hazy one
+    pub turbidity: f32,
+    /// Scales the sun's brightness
+    pub intensity: f32,
+}
+
+impl Default for ProceduralSky {
+    fn default() -> Self {
+        Self {
+            sun_direction: Vec3::new(0.5, 1.0, 0.3).normalize(),
+            turbidity: 3.0,
+            intensity: 1.0,
+        }
+    }
+}
+
+/// Sky parameters as the skybox shader reads them
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkyUniforms {
+    /// Direction toward the sun, w is 1 when the sky is procedural
+    pub sun_direction: [f32; 4],
+    /// Perez A to E, xyz for Y, x and y
+    pub perez: [[f32; 4]; 5],
+    /// Zenith Y, x and y over the Perez value at the zenith
+    pub zenith: [f32; 4],
+    /// Sunlight color, w is how much daylight is left
+    pub sun_color: [f32; 4],
+}
+
+impl SkyUniforms {
+    /// Uniforms for a skybox showing its cubemap instead
+    pub fn cubemap() -> Self {
+        bytemuck::Zeroable::zeroed()
+    }
+
+    /// Sky radiance in a direction, without the sun disc
+    pub fn radiance(&self, direction: Vec3) -> Vec3 {
+        let direction = direction.normalize_or(Vec3::Y);
+        let sun = Vec3::from_slice(&self.sun_direction[..3]);
+        let daylight = self.sun_color[3];
+
+        // Below the horizon, continue the horizon and darken toward the nadir
+        let below = (-direction.y).max(0.0);
+        let cos_theta = direction.y.max(0.01);
+        let gamma = direction.dot(sun).clamp(-1.0, 1.0).acos();
+        let channel = |c: usize| {
+            let [a, b, c_, d, e] = self.perez.map(|p| p[c]);
+            self.zenith[c] * perez(a, b, c_, d, e, cos_theta, gamma)
+        };
+        let day = xyy_to_rgb(channel(1), channel(2), channel(0)) * (1.0 - below * 0.8);
+        (day * daylight + NIGHT_SKY).max(Vec3::ZERO)
+    }
+}
+
+impl ProceduralSky {
+    /// How much daylight is left, 1 from just above the horizon, 0 at night
+    pub fn daylight(&self) -> f32 {
+        let y = self.sun_direction.normalize_or(Vec3::Y).y;
+        smoothstep(-0.15, 0.05, y)
+    }
+
+    /// Perez coefficients and zenith values for this sky
+    pub fn uniforms(&self) -> SkyUniforms {
+        let t = self.turbidity.clamp(1.7, 12.0);
+        let sun = self.sun_direction.normalize_or(Vec3::Y);
+        let theta_s = sun.y.clamp(-1.0, 1.0).acos().min(MAX_SUN_ZENITH);
+
+        // Distribution coefficients per channel (Preetham et al. 1999)
+        let coefficients = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        // Zenith luminance and chromaticity
+        let chi = (4.0 / 9.0 - t / 120.0) * (std::f32::consts::PI - 2.0 * theta_s);
+        let zenith_y = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+        let theta = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.0];
+        let dot4 = |a: [f32; 4]| a.iter().zip(theta).map(|(a, b)| a * b).sum::<f32>();
+        let zenith_x = t * t * dot4([0.00166, -0.00375, 0.00209, 0.0])
+            + t * dot4([-0.02903, 0.06377, -0.03202, 0.00394])
+            + dot4([0.11693, -0.21196, 0.06052, 0.25886]);
+        let zenith_yc = t * t * dot4([0.00275, -0.00610, 0.00317, 0.0])
+            + t * dot4([-0.04214, 0.08970, -0.04153, 0.00516])
+            + dot4([0.15346, -0.26756, 0.06670, 0.26688]);
+
+        let zenith = [zenith_y * LUMINANCE_SCALE, zenith_x, zenith_yc];
+        let mut uniforms = SkyUniforms::cubemap();
+        for (c, [a, b, c_, d, e]) in coefficients.into_iter().enumerate() {
+            for (i, value) in [a, b, c_, d, e].into_iter().enumerate() {
+                uniforms.perez[i][c] = value;
+            }
+            uniforms.zenith[c] = zenith[c] / perez(a, b, c_, d, e, 1.0, theta_s);
+        }
+        uniforms.sun_direction = [sun.x, sun.y, sun.z, 1.0];
+        let color = self.sun_color();
+        uniforms.sun_color = [color.x, color.y, color.z, self.daylight()];
+        uniforms
+    }
+
+    /// Color of the sunlight after the atmosphere, before the daylight fade
+    fn sun_color(&self) -> Vec3 {
+        let sun = self.sun_direction.normalize_or(Vec3::Y);
+        let elevation = sun.y.clamp(0.0, 1.0).asin().to_degrees();
+        // Kasten and Young's relative air mass
+        let air_mass = 1.0 / (sun.y.max(0.0) + 0.50572 * (elevation + 6.07995).powf(-1.6364));
+        (-EXTINCTION * air_mass).exp() * self.intensity
+    }
+
+    /// Radiance of the direct sunlight, zero at night
+    pub fn sun_radiance(&self) -> Vec3 {
+        self.sun_color() * SUN_RADIANCE * self.daylight()
+    }
+}
+
+/// Perez distribution for a direction `cos_theta` from the zenith and
+/// `gamma` radians from the sun
+fn perez(a: f32, b: f32, c: f32, d: f32, e: f32, cos_theta: f32, gamma: f32) -> f32 {
+    let cos_gamma = gamma.cos();
+    (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+}
+
+fn xyy_to_rgb(x: f32, y: f32, luminance: f32) -> Vec3 {
+    let y = y.max(1e-4);
+    let xyz = Vec3::new(x / y * luminance, luminance, (1.0 - x - y) / y * luminance);
+    // XYZ to linear sRGB
+    Vec3::new(
+        3.2406 * xyz.x - 1.5372 * xyz.y - 0.4986 * xyz.z,
+        -0.9689 * xyz.x + 1.8758 * xyz.y + 0.0415 * xyz.z,
+        0.0557 * xyz.x - 0.2040 * xyz.y + 1.0570 * xyz.z,
+    )
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sky_at(sun_direction: Vec3) -> ProceduralSky {
+        ProceduralSky {
+            sun_direction: sun_direction.normalize(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_day_is_blue_and_brighter_than_night() {
+        let noon = sky_at(Vec3::new(0.2, 1.0, 0.3)).uniforms();
+        let night = sky_at(Vec3::new(0.2, -1.0, 0.3)).uniforms();
+
+        let zenith = noon.radiance(Vec3::Y);
+        assert!(zenith.z > zenith.x, "noon zenith should be blue: {zenith}");
+        assert!(zenith.length() > 0.1 && zenith.length() < 2.0, "{zenith}");
+        assert!(night.radiance(Vec3::Y).length() < zenith.length() * 0.1);
+
+        // Brighter toward the sun than away from it
+        let toward = noon.radiance(Vec3::new(0.3, 0.5, 0.4));
+        let away = noon.radiance(Vec3::new(-0.3, 0.5, -0.4));
+        assert!(toward.length() > away.length());
+    }
+
+    #[test]
+    fn test_low_sun_is_dimmer_and_redder() {
+        let high = sky_at(Vec3::new(0.0, 1.0, 0.5)).sun_radiance();
+        let low = sky_at(Vec3::new(1.0, 0.05, 0.0)).sun_radiance();
+        assert!(low.length() < high.length());
+        assert!(low.x / low.z > high.x / high.z);
+        assert_eq!(sky_at(Vec3::new(1.0, -0.5, 0.0)).sun_radiance(), Vec3::ZERO);
+    }
+}
@@ -1,12 +1,30 @@
-// Skybox rendering - cubemap environment
+// Skybox rendering - procedural sky or cubemap environment
+//
+// Loading a sky also regenerates its image based lighting maps (see ibl.rs),
+// so PBR materials pick up ambient light and reflections from it. The
+// procedural sky (see sky.rs) is drawn by the shader every frame; its lighting
+// maps are only rebuilt once the sun has moved far enough to notice, on a
+// worker thread, and the old maps stay in use until the new ones are ready.
 
-use crate::MSAA_SAMPLE_COUNT;
+use crate::ibl::{ConvolvedSky, EnvironmentMap, IblMaps, PREFILTER_SIZE};
+use crate::sky::{ProceduralSky, SkyUniforms};
 use anyhow::Result;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
 use wgpu::util::DeviceExt;
 
 /// Skybox configuration
 pub const SKYBOX_SIZE: u32 = 1024;
 
+/// How far the sun may move (cosine of the angle) before the procedural
+/// sky's lighting maps are rebuilt
+const IBL_SUN_THRESHOLD: f32 = 0.9994;
+
+/// Lighting maps of a procedural sky being convolved on a worker thread
+struct IblJob {
+    sky: ProceduralSky,
+    result: Receiver<ConvolvedSky>,
+}
+
 /// Skybox renderer
 pub struct Skybox {
     /// Cubemap texture
@@ -19,13 +37,23 @@ pub struct Skybox {
     pub bind_group: wgpu::BindGroup,
     /// Render pipeline
     pub render_pipeline: wgpu::RenderPipeline,
+    /// Irradiance, pre-filtered specular and BRDF maps from the sky
+    pub ibl: IblMaps,
+    /// Procedural sky parameters for the shader
+    sky_buffer: wgpu::Buffer,
+    /// Procedural sky the lighting maps were last built from
+    ibl_sky: Option<ProceduralSky>,
+    /// Lighting maps being built for a procedural sky
+    ibl_job: Option<IblJob>,
 }
 
 impl Skybox {
     /// Create a new skybox
     pub fn new(
         device: &wgpu::Device,
+        queue: &wgpu::Queue,
         surface_format: wgpu::TextureFormat,
+        sample_count: u32,
         camera_bind_group_layout: &wgpu::BindGroupLayout,
     ) -> Result<Self> {
         // Create cubemap texture
@@ -83,9 +111,26 @@ impl Skybox {
                     ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                     count: None,
                 },
+                // Procedural sky
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
             ],
         });
 
+        let sky_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sky Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[SkyUniforms::cubemap()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Skybox Bind Group"),
             layout: &bind_group_layout,
@@ -98,6 +143,10 @@ impl Skybox {
                     binding: 1,
                     resource: wgpu::BindingResource::Sampler(&sampler),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: sky_buffer.as_entire_binding(),
+                },
             ],
         });
 
@@ -151,7 +200,7 @@ impl Skybox {
                 bias: wgpu::DepthBiasState::default(),
             }),
             multisample: wgpu::MultisampleState {
-                count: MSAA_SAMPLE_COUNT,
+                count: sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
@@ -165,12 +214,20 @@ impl Skybox {
             sampler,
             bind_group,
             render_pipeline,
+            ibl: IblMaps::new(device, queue),
+            sky_buffer,
+            ibl_sky: None,
+            ibl_job: None,
         })
     }
 
     /// Load cubemap faces from image data
     /// Order: +X, -X, +Y, -Y, +Z, -Z
-    pub fn load_cubemap(&self, queue: &wgpu::Queue, face_data: &[(&[u8], u32, u32)]) -> Result<()> {
+    pub fn load_cubemap(
+        &mut self,
+        queue: &wgpu::Queue,
+        face_data: &[(&[u8], u32, u32)],
+    ) -> Result<()> {
         if face_data.len() != 6 {
             return Err(anyhow::anyhow!("Cubemap must have exactly 6 faces"));
         }
@@ -211,55 +268,71 @@ impl Skybox {
             );
         }
 
+        let faces: Vec<&[u8]> = face_data.iter().map(|(data, _, _)| *data).collect();
+        let environment = EnvironmentMap::from_rgba8_srgb(&faces, SKYBOX_SIZE, PREFILTER_SIZE);
+        self.ibl.update(queue, &environment);
+        self.ibl_sky = None;
+        self.ibl_job = None;
+        queue.write_buffer(
+            &self.sky_buffer,
+            0,
+            bytemuck::cast_slice(&[SkyUniforms::cubemap()]),
+        );
+
         Ok(())
     }
 
-    /// Create a simple gradient skybox (blue to white)
-    pub fn create_gradient_skybox(queue: &wgpu::Queue, texture: &wgpu::Texture) {
-        let size = SKYBOX_SIZE as usize;
-        let mut data = vec![0u8; size * size * 4];
+    /// Show a procedural sky instead of the cubemap. Call it whenever the sun
+    /// moves; the lighting maps follow once it has moved about two degrees.
+    pub fn set_procedural_sky(&mut self, queue: &wgpu::Queue, sky: &ProceduralSky) {
+        let uniforms = sky.uniforms();
+        queue.write_buffer(&self.sky_buffer, 0, bytemuck::cast_slice(&[uniforms]));
 
-        for face in 0..6 {
-            for y in 0..size {
-                for x in 0..size {
-                    let idx = (y * size + x) * 4;
+        self.receive_lighting(queue, false);
+        let stale = self.ibl_sky.is_none_or(|last| {
+            last.turbidity != sky.turbidity
+                || last
+                    .sun_direction
+                    .normalize_or_zero()
+                    .dot(sky.sun_direction.normalize_or_zero())
+                    < IBL_SUN_THRESHOLD
+        });
+        // One job at a time; a sky that moved on meanwhile starts the next
+        if stale && self.ibl_job.is_none() {
+            let (sender, result) = mpsc::channel();
+            std::thread::spawn(move || {
+                let environment = EnvironmentMap::from_fn(PREFILTER_SIZE, |d| uniforms.radiance(d));
+                let _ = sender.send(ConvolvedSky::new(&environment));
+            });
+            self.ibl_job = Some(IblJob { sky: *sky, result });
+        }
+    }
 
-                    // Simple gradient from blue (bottom) to white (top)
-                    let t = y as f32 / size as f32;
-                    let r = (135.0 + (255.0 - 135.0) * t) as u8;
-                    let g = (206.0 + (255.0 - 206.0) * t) as u8;
-                    let b = (235.0 + (255.0 - 235.0) * t) as u8;
+    /// Block until the procedural sky's lighting maps are up to date (for
+    /// single frames, such as offscreen captures)
+    pub fn wait_for_lighting(&mut self, queue: &wgpu::Queue) {
+        self.receive_lighting(queue, true);
+    }
 
-                    data[idx] = r;
-                    data[idx + 1] = g;
-                    data[idx + 2] = b;
-                    data[idx + 3] = 255;
-                }
+    /// Upload the lighting maps of a finished job
+    fn receive_lighting(&mut self, queue: &wgpu::Queue, wait: bool) {
+        let Some(job) = self.ibl_job.take() else {
+            return;
+        };
+        let result = if wait {
+            job.result.recv().map_err(|_| TryRecvError::Disconnected)
+        } else {
+            job.result.try_recv()
+        };
+        match result {
+            Ok(maps) => self.ibl.upload(queue, &maps),
+            Err(TryRecvError::Empty) => {
+                self.ibl_job = Some(job);
+                return;
             }
-
-            queue.write_texture(
-                wgpu::TexelCopyTextureInfo {
-                    texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d {
-                        x: 0,
-                        y: 0,
-                        z: face as u32,
-                    },
-                    aspect: wgpu::TextureAspect::All,
-                },
-                &data,
-                wgpu::TexelCopyBufferLayout {
-                    offset: 0,
-                    bytes_per_row: Some(4 * SKYBOX_SIZE),
-                    rows_per_image: Some(SKYBOX_SIZE),
-                },
-                wgpu::Extent3d {
-                    width: SKYBOX_SIZE,
-                    height: SKYBOX_SIZE,
-                    depth_or_array_layers: 1,
-                },
-            );
+            // Not retried every frame; the next move of the sun tries again
+            Err(TryRecvError::Disconnected) => log::warn!("Sky lighting convolution failed"),
         }
+        self.ibl_sky = Some(job.sky);
     }
 }
@@ -0,0 +1,620 @@
+// Terrain Renderer - splat-mapped texture layers
+//
+// The terrain mesh is drawn with up to MAX_TERRAIN_LAYERS surface layers, each
+// an albedo and normal texture tiled across the ground in world space. The
+// layer images share two texture arrays at LAYER_TEXTURE_SIZE; the splat map's
+// weights are uploaded as two RGBA8 textures on the heightmap grid, so a paint
+// stroke only rewrites those. Camera, shadow and sky lighting bind groups are
+// the ones the PBR meshes use.
+
+use crate::gpu_mesh::{GpuMesh, GpuVertex};
+use crate::ibl::IblMaps;
+use crate::renderer::Renderer;
+use crate::shadow::ShadowMap;
+use anyhow::Result;
+use engine_assets::{AssetManager, SplatMap, TerrainLayer, Texture, MAX_TERRAIN_LAYERS};
+use glam::Mat4;
+use wgpu::util::DeviceExt;
+
+/// Width and height every layer texture is resampled to
+pub const LAYER_TEXTURE_SIZE: u32 = 512;
+const LAYER_MIPS: u32 = LAYER_TEXTURE_SIZE.ilog2() + 1;
+
+/// Per-layer tint and tiling, plus the splat grid size
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TerrainUniforms {
+    /// rgb = tint, w = texture repeats per world unit
+    pub layers: [[f32; 4]; MAX_TERRAIN_LAYERS],
+    /// x = grid width, y = grid depth, z = layer count
+    pub grid: [f32; 4],
+}
+
+impl TerrainUniforms {
+    pub fn new(layers: &[TerrainLayer], width: usize, depth: usize) -> Self {
+        let mut uniforms = Self {
+            layers: [[1.0, 1.0, 1.0, 0.25]; MAX_TERRAIN_LAYERS],
+            grid: [width as f32, depth as f32, 1.0, 0.0],
+        };
+        for (slot, layer) in uniforms.layers.iter_mut().zip(layers) {
+            let [r, g, b] = layer.color;
+            *slot = [r, g, b, 1.0 / layer.tiling.max(0.01)];
+        }
+        uniforms.grid[2] = layers.len().clamp(1, MAX_TERRAIN_LAYERS) as f32;
+        uniforms
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TerrainPushConstants {
+    pub model: [[f32; 4]; 4],
+}
+
+/// Draws the terrain mesh with its painted layers
+pub struct TerrainRenderer {
+    render_pipeline: wgpu::RenderPipeline,
+    /// Single-sampled pipeline for the water reflection target
+    reflection_pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    uniform_buffer: wgpu::Buffer,
+    uniforms: TerrainUniforms,
+    /// Weights of layers 0-3 and 4-7
+    splat_textures: [wgpu::Texture; 2],
+    splat_sampler: wgpu::Sampler,
+    albedo_layers: wgpu::Texture,
+    normal_layers: wgpu::Texture,
+    layer_sampler: wgpu::Sampler,
+}
+
+impl TerrainRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        color_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Result<Self> {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Terrain Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/terrain.wgsl").into()),
+        });
+
+        let texture_entry = |binding, view_dimension| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let sampler_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Terrain Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                texture_entry(1, wgpu::TextureViewDimension::D2),
+                texture_entry(2, wgpu::TextureViewDimension::D2),
+                sampler_entry(3),
+                texture_entry(4, wgpu::TextureViewDimension::D2Array),
+                texture_entry(5, wgpu::TextureViewDimension::D2Array),
+                sampler_entry(6),
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Terrain Pipeline Layout"),
+            bind_group_layouts: &[
+                &Renderer::create_uniform_bind_group_layout(device),
+                &bind_group_layout,
+                &ShadowMap::create_sampling_bind_group_layout(device),
+                &IblMaps::create_bind_group_layout(device),
+            ],
+            push_constant_ranges: &[wgpu::PushConstantRange {
+                stages: wgpu::ShaderStages::VERTEX,
+                range: 0..std::mem::size_of::<TerrainPushConstants>() as u32,
+            }],
+        });
+
+        let create_pipeline = |label, samples| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[GpuVertex::desc()],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: color_format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    // Same as the PBR meshes, so terrain seen from below still draws
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: samples,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+                cache: None,
+            })
+        };
+        let render_pipeline = create_pipeline("Terrain Render Pipeline", sample_count);
+        let reflection_pipeline = create_pipeline("Terrain Reflection Pipeline", 1);
+
+        let uniforms = TerrainUniforms::new(&[], 1, 1);
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Terrain Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[uniforms]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Until a splat map arrives the whole terrain is layer 0
+        let splat_textures = [
+            create_splat_texture(device, 1, 1),
+            create_splat_texture(device, 1, 1),
+        ];
+        queue.write_texture(
+            splat_textures[0].as_image_copy(),
+            &[255, 0, 0, 0],
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4),
+                rows_per_image: Some(1),
+            },
+            splat_textures[0].size(),
+        );
+        let splat_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Terrain Splat Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let create_layers = |label, format| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width: LAYER_TEXTURE_SIZE,
+                    height: LAYER_TEXTURE_SIZE,
+                    depth_or_array_layers: MAX_TERRAIN_LAYERS as u32,
+                },
+                mip_level_count: LAYER_MIPS,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            })
+        };
+        let albedo_layers =
+            create_layers("Terrain Albedo Layers", wgpu::TextureFormat::Rgba8UnormSrgb);
+        let normal_layers = create_layers("Terrain Normal Layers", wgpu::TextureFormat::Rgba8Unorm);
+        let layer_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Terrain Layer Sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            anisotropy_clamp: 8,
+            ..Default::default()
+        });
+
+        let bind_group = create_bind_group(
+            device,
+            &bind_group_layout,
+            &uniform_buffer,
+            &splat_textures,
+            &splat_sampler,
+            &albedo_layers,
+            &normal_layers,
+            &layer_sampler,
+        );
+
+        Ok(Self {
+            render_pipeline,
+            reflection_pipeline,
+            bind_group_layout,
+            bind_group,
+            uniform_buffer,
+            uniforms,
+            splat_textures,
+            splat_sampler,
+            albedo_layers,
+            normal_layers,
+            layer_sampler,
+        })
+    }
+
+    /// Load each layer's textures (untextured layers are plain white and
+    /// flat, showing their tint) and its tint and tiling
+    pub fn set_layers(
+        &mut self,
+        queue: &wgpu::Queue,
+        layers: &[TerrainLayer],
+        asset_manager: &mut AssetManager,
+    ) {
+        let mut load = |path: &Option<String>| {
+            let path = path.as_deref()?;
+            asset_manager
+                .load_texture(path)
+                .inspect_err(|e| {
+                    log::warn!("Failed to load terrain layer texture '{}': {}", path, e)
+                })
+                .ok()
+        };
+        for (index, layer) in layers.iter().take(MAX_TERRAIN_LAYERS).enumerate() {
+            let albedo = load(&layer.albedo_texture);
+            let normal = load(&layer.normal_texture);
+            let albedo = layer_image(
+                albedo.as_ref().map(|h| h.get()),
+                [255, 255, 255, 255],
+            );
+            let normal = layer_image(
+                normal.as_ref().map(|h| h.get()),
+                [128, 128, 255, 255],
+            );
+            write_layer(queue, &self.albedo_layers, index as u32, albedo);
+            write_layer(queue, &self.normal_layers, index as u32, normal);
+        }
+
+        let [width, depth, _, _] = self.uniforms.grid;
+        self.uniforms = TerrainUniforms::new(layers, width as usize, depth as usize);
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[self.uniforms]),
+        );
+    }
+
+    /// Upload the painted weights, resizing the splat textures to the grid
+    pub fn update_splatmap(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        splat_map: &SplatMap,
+    ) {
+        if splat_map.width == 0 || splat_map.depth == 0 {
+            return;
+        }
+        let (width, depth) = (splat_map.width as u32, splat_map.depth as u32);
+        if self.splat_textures[0].width() != width || self.splat_textures[0].height() != depth {
+            self.splat_textures = [
+                create_splat_texture(device, width, depth),
+                create_splat_texture(device, width, depth),
+            ];
+            self.bind_group = create_bind_group(
+                device,
+                &self.bind_group_layout,
+                &self.uniform_buffer,
+                &self.splat_textures,
+                &self.splat_sampler,
+                &self.albedo_layers,
+                &self.normal_layers,
+                &self.layer_sampler,
+            );
+        }
+
+        for (texture, data) in self.splat_textures.iter().zip(splat_map.pack_rgba8()) {
+            queue.write_texture(
+                texture.as_image_copy(),
+                &data,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * width),
+                    rows_per_image: Some(depth),
+                },
+                texture.size(),
+            );
+        }
+
+        self.uniforms.grid[0] = width as f32;
+        self.uniforms.grid[1] = depth as f32;
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[self.uniforms]),
+        );
+    }
+
+    /// Draw the terrain into the main (multisampled) scene targets
+    pub fn render(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        mesh: &GpuMesh,
+        model: Mat4,
+        camera_bind_group: &wgpu::BindGroup,
+        shadow_bind_group: &wgpu::BindGroup,
+        environment_bind_group: &wgpu::BindGroup,
+    ) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        self.draw(
+            render_pass,
+            mesh,
+            model,
+            camera_bind_group,
+            shadow_bind_group,
+            environment_bind_group,
+        );
+    }
+
+    /// Draw the terrain into the single-sampled water reflection target
+    pub fn render_reflection(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        mesh: &GpuMesh,
+        model: Mat4,
+        camera_bind_group: &wgpu::BindGroup,
+        shadow_bind_group: &wgpu::BindGroup,
+        environment_bind_group: &wgpu::BindGroup,
+    ) {
+        render_pass.set_pipeline(&self.reflection_pipeline);
+        self.draw(
+            render_pass,
+            mesh,
+            model,
+            camera_bind_group,
+            shadow_bind_group,
+            environment_bind_group,
+        );
+    }
+
+    fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        mesh: &GpuMesh,
+        model: Mat4,
+        camera_bind_group: &wgpu::BindGroup,
+        shadow_bind_group: &wgpu::BindGroup,
+        environment_bind_group: &wgpu::BindGroup,
+    ) {
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.set_bind_group(2, shadow_bind_group, &[]);
+        render_pass.set_bind_group(3, environment_bind_group, &[]);
+        let push_constants = TerrainPushConstants {
+            model: model.to_cols_array_2d(),
+        };
+        render_pass.set_push_constants(
+            wgpu::ShaderStages::VERTEX,
+            0,
+            bytemuck::cast_slice(&[push_constants]),
+        );
+        render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..mesh.num_indices, 0, 0..1);
+    }
+}
+
+fn create_splat_texture(device: &wgpu::Device, width: u32, depth: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Terrain Splat Texture"),
+        size: wgpu::Extent3d {
+            width,
+            height: depth,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &wgpu::Buffer,
+    splat_textures: &[wgpu::Texture; 2],
+    splat_sampler: &wgpu::Sampler,
+    albedo_layers: &wgpu::Texture,
+    normal_layers: &wgpu::Texture,
+    layer_sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    let splat_views = splat_textures
+        .each_ref()
+        .map(|t| t.create_view(&Default::default()));
+    let array_view = |texture: &wgpu::Texture| {
+        texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        })
+    };
+    let (albedo_view, normal_view) = (array_view(albedo_layers), array_view(normal_layers));
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Terrain Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&splat_views[0]),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&splat_views[1]),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Sampler(splat_sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(&albedo_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::TextureView(&normal_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: wgpu::BindingResource::Sampler(layer_sampler),
+            },
+        ],
+    })
+}
+
+/// A layer texture as RGBA8 at LAYER_TEXTURE_SIZE (nearest texel, the mips
+/// smooth it out), or `fallback` everywhere without one
+fn layer_image(texture: Option<&Texture>, fallback: [u8; 4]) -> Vec<u8> {
+    let size = LAYER_TEXTURE_SIZE as usize;
+    let Some(texture) = texture.filter(|t| t.width > 0 && t.height > 0) else {
+        return fallback.repeat(size * size);
+    };
+    let channels = texture.bytes_per_pixel() as usize;
+    let (width, height) = (texture.width as usize, texture.height as usize);
+    let mut image = Vec::with_capacity(size * size * 4);
+    for y in 0..size {
+        for x in 0..size {
+            let index = ((y * height / size) * width + x * width / size) * channels;
+            let texel = texture.data.get(index..index + channels).unwrap_or(&[]);
+            image.extend_from_slice(&match *texel {
+                [r, g, b, a] => [r, g, b, a],
+                [r, g, b] => [r, g, b, 255],
+                [v] => [v, v, v, 255],
+                _ => fallback,
+            });
+        }
+    }
+    image
+}
+
+/// Downsample a square RGBA8 image by half with a 2x2 box filter
+fn half_size(image: &[u8], size: usize) -> Vec<u8> {
+    let half = (size / 2).max(1);
+    let mut out = Vec::with_capacity(half * half * 4);
+    for y in 0..half {
+        for x in 0..half {
+            for c in 0..4 {
+                let texel = |dx: usize, dy: usize| {
+                    let (sx, sy) = ((x * 2 + dx).min(size - 1), (y * 2 + dy).min(size - 1));
+                    image[(sy * size + sx) * 4 + c] as u32
+                };
+                out.push(((texel(0, 0) + texel(1, 0) + texel(0, 1) + texel(1, 1) + 2) / 4) as u8);
+            }
+        }
+    }
+    out
+}
+
+/// Write a LAYER_TEXTURE_SIZE image and its mips into one array layer
+fn write_layer(queue: &wgpu::Queue, texture: &wgpu::Texture, layer: u32, mut image: Vec<u8>) {
+    let mut size = LAYER_TEXTURE_SIZE;
+    for mip_level in 0..LAYER_MIPS {
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture,
+                mip_level,
+                origin: wgpu::Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: layer,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &image,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * size),
+                rows_per_image: Some(size),
+            },
+            wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 1,
+            },
+        );
+        if size > 1 {
+            image = half_size(&image, size as usize);
+            size /= 2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layer_uniforms_and_images() {
+        let layers = vec![
+            TerrainLayer::new("Base", [1.0, 1.0, 1.0]),
+            TerrainLayer::new("Rock", [0.5, 0.4, 0.3]).with_tiling(8.0),
+        ];
+        let uniforms = TerrainUniforms::new(&layers, 64, 32);
+        assert_eq!(uniforms.layers[1], [0.5, 0.4, 0.3, 0.125]);
+        assert_eq!(uniforms.grid, [64.0, 32.0, 2.0, 0.0]);
+
+        // Without a texture the layer is its fallback color, and so are its mips
+        let flat = layer_image(None, [128, 128, 255, 255]);
+        assert_eq!(
+            flat.len(),
+            (LAYER_TEXTURE_SIZE * LAYER_TEXTURE_SIZE * 4) as usize
+        );
+        assert_eq!(
+            &half_size(&flat, LAYER_TEXTURE_SIZE as usize)[..4],
+            &[128, 128, 255, 255]
+        );
+
+        // A 2x1 RGB texture stretches over the whole layer
+        let texture = Texture::new(
+            "stripes".to_string(),
+            2,
+            1,
+            vec![255, 0, 0, 0, 0, 255],
+            engine_assets::TextureFormat::Rgb8,
+        );
+        let image = layer_image(Some(&texture), [0; 4]);
+        let right = (LAYER_TEXTURE_SIZE as usize - 1) * 4;
+        assert_eq!(&image[..4], &[255, 0, 0, 255]);
+        assert_eq!(&image[right..right + 4], &[0, 0, 255, 255]);
+    }
+}
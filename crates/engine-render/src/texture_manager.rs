@@ -34,7 +34,9 @@
 //! ```
 
 use crate::gpu_texture::{GpuTexture, TextureHandle};
-use engine_assets::Texture;
+use crate::memory_budget::{ResourceMemory, ResourceTracker};
+use crate::texture_streaming::{mip_for_screen_size, plan_streaming, StreamedMips, TextureStream, MAX_STREAM_UPLOADS};
+use engine_assets::{CompressedFormat, CompressedTexture, Texture, TextureKind};
 use std::collections::HashMap;
 
 /// Manages texture loading, caching, and GPU upload.
@@ -49,11 +51,19 @@ use std::collections::HashMap;
 /// - `texture_map`: Name to handle mapping for lookup
 /// - `bind_group_layout`: Shared bind group layout for all textures
 /// - `white_texture_handle`: Handle to default white fallback texture
+/// - `tracker`: Texture sizes and last use, for the memory budget
+/// - `streams`: Mip chains of streamed textures, by handle index
 pub struct TextureManager {
-    textures: Vec<GpuTexture>,
+    /// None once evicted
+    textures: Vec<Option<GpuTexture>>,
     texture_map: HashMap<String, TextureHandle>,
     bind_group_layout: wgpu::BindGroupLayout,
     white_texture_handle: TextureHandle,
+    tracker: ResourceTracker,
+    streams: HashMap<usize, TextureStream>,
+    /// GPU bytes streamed textures may use (0 = no limit)
+    stream_budget: u64,
+    stream_frame: u64,
 }
 
 impl TextureManager {
@@ -97,8 +107,14 @@ impl TextureManager {
 
         // Create default white texture
         let white_texture = GpuTexture::white_texture(device, queue, &bind_group_layout);
-        let mut textures = Vec::new();
-        textures.push(white_texture);
+        let mut tracker = ResourceTracker::new();
+        tracker.insert(
+            0,
+            "white".to_string(),
+            crate::render_stats::texture_bytes(&white_texture.texture),
+            false,
+        );
+        let textures = vec![Some(white_texture)];
 
         let white_handle = TextureHandle(0);
         let mut texture_map = HashMap::new();
@@ -109,6 +125,10 @@ impl TextureManager {
             texture_map,
             bind_group_layout,
             white_texture_handle: white_handle,
+            tracker,
+            streams: HashMap::new(),
+            stream_budget: 0,
+            stream_frame: 0,
         }
     }
 
@@ -169,16 +189,166 @@ impl TextureManager {
         }
 
         let gpu_texture = GpuTexture::from_cpu_texture(device, queue, texture, &self.bind_group_layout);
+        self.insert(name, gpu_texture)
+    }
+
+    /// Block-compressed format to upload `texture` as on this device: BC where
+    /// the GPU has it, else ASTC. None if neither is available or the texture
+    /// can't be compressed.
+    pub fn compressed_format(device: &wgpu::Device, texture: &Texture, kind: TextureKind) -> Option<CompressedFormat> {
+        let features = device.features();
+        if features.contains(wgpu::Features::TEXTURE_COMPRESSION_BC) {
+            CompressedFormat::choose(texture, kind, true)
+        } else if features.contains(wgpu::Features::TEXTURE_COMPRESSION_ASTC) {
+            CompressedFormat::choose(texture, kind, false)
+        } else {
+            None
+        }
+    }
+
+    /// Upload a block-compressed texture, cached by name like `upload_texture`
+    pub fn upload_compressed(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        name: String,
+        texture: &CompressedTexture,
+    ) -> TextureHandle {
+        if let Some(&handle) = self.texture_map.get(&name) {
+            return handle;
+        }
+
+        let gpu_texture = GpuTexture::from_compressed(device, queue, texture, &self.bind_group_layout);
+        self.insert(name, gpu_texture)
+    }
+
+    /// Upload a texture that streams its mips (see `texture_streaming`). Only
+    /// its small mips go to the GPU until `request_mips` asks for more.
+    pub fn upload_streamed(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        name: String,
+        mips: StreamedMips,
+    ) -> TextureHandle {
+        if let Some(&handle) = self.texture_map.get(&name) {
+            return handle;
+        }
+
+        let stream = TextureStream::new(mips);
+        let levels = &stream.mips.levels[stream.resident as usize..];
+        let gpu_texture = GpuTexture::from_mip_chain(device, queue, &name, stream.mips.format, levels, &self.bind_group_layout);
+        let handle = self.insert(name, gpu_texture);
+        self.tracker.set_streamed(handle.0, true);
+        self.streams.insert(handle.0, stream);
+        handle
+    }
+
+    /// Put `mips` (e.g. a texture's block-compressed encode, once that is
+    /// done) in place of a texture under the same handle, streamed from its
+    /// small mips again. Materials sampling it have to rebind
+    /// (`MaterialManager::rebind_textures`). False if the texture was evicted.
+    pub fn replace_streamed(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        handle: TextureHandle,
+        mips: StreamedMips,
+    ) -> bool {
+        let Some(name) = self.texture_map.iter().find(|(_, &h)| h == handle).map(|(name, _)| name.clone()) else {
+            return false;
+        };
+        let stream = TextureStream::new(mips);
+        let levels = &stream.mips.levels[stream.resident as usize..];
+        let gpu_texture = GpuTexture::from_mip_chain(device, queue, &name, stream.mips.format, levels, &self.bind_group_layout);
+        self.tracker.resize(handle.0, crate::render_stats::texture_bytes(&gpu_texture.texture));
+        self.textures[handle.0] = Some(gpu_texture);
+        self.tracker.set_streamed(handle.0, true);
+        self.streams.insert(handle.0, stream);
+        true
+    }
+
+    /// Ask for the mips a streamed texture needs when drawn `screen_pixels`
+    /// across. Ignored for textures that don't stream.
+    pub fn request_mips(&mut self, handle: TextureHandle, screen_pixels: f32) {
+        let frame = self.stream_frame;
+        if let Some(stream) = self.streams.get_mut(&handle.0) {
+            let level = mip_for_screen_size(stream.mips.width(), stream.mips.height(), screen_pixels);
+            stream.request(level, frame);
+        }
+    }
+
+    /// GPU bytes streamed textures may use; the least recently requested drop
+    /// their finest mips first while over it (0 = no limit)
+    pub fn set_stream_budget(&mut self, bytes: u64) {
+        self.stream_budget = bytes;
+    }
+
+    pub fn stream_budget(&self) -> u64 {
+        self.stream_budget
+    }
+
+    /// Number of streamed textures and the GPU bytes they use now
+    pub fn streaming_memory(&self) -> (usize, u64) {
+        let bytes = self.streams.values().map(|s| s.mips.bytes_from(s.resident)).sum();
+        (self.streams.len(), bytes)
+    }
+
+    /// End the frame for streaming: bring streamed textures to the mips they
+    /// asked for within the budget. Returns the textures that were re-created;
+    /// materials sampling them have to rebind (`MaterialManager::rebind_textures`).
+    pub fn update_streaming(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> Vec<TextureHandle> {
+        let frame = self.stream_frame;
+        self.stream_frame += 1;
+
+        let mut indices: Vec<usize> = self.streams.keys().copied().collect();
+        // Most recently requested first, so they get this frame's uploads
+        indices.sort_by_key(|index| std::cmp::Reverse(self.streams[index].last_requested));
+        let streams: Vec<&TextureStream> = indices.iter().map(|index| &self.streams[index]).collect();
+        let targets = plan_streaming(&streams, frame, self.stream_budget);
+
+        let mut changed = Vec::new();
+        let mut uploads = 0;
+        for (index, target) in indices.into_iter().zip(targets) {
+            let stream = &self.streams[&index];
+            if target == stream.resident {
+                continue;
+            }
+            if target < stream.resident {
+                if uploads >= MAX_STREAM_UPLOADS {
+                    continue;
+                }
+                uploads += 1;
+            }
+            let Some(name) = self.texture_map.iter().find(|(_, handle)| handle.0 == index).map(|(name, _)| name.clone()) else {
+                continue;
+            };
+            let levels = &stream.mips.levels[target as usize..];
+            let gpu_texture = GpuTexture::from_mip_chain(device, queue, &name, stream.mips.format, levels, &self.bind_group_layout);
+            self.tracker.resize(index, crate::render_stats::texture_bytes(&gpu_texture.texture));
+            self.textures[index] = Some(gpu_texture);
+            if let Some(stream) = self.streams.get_mut(&index) {
+                stream.resident = target;
+            }
+            changed.push(TextureHandle(index));
+        }
+        changed
+    }
+
+    fn insert(&mut self, name: String, gpu_texture: GpuTexture) -> TextureHandle {
         let handle = TextureHandle(self.textures.len());
-        self.textures.push(gpu_texture);
+        let bytes = crate::render_stats::texture_bytes(&gpu_texture.texture);
+        self.tracker.insert(handle.0, name.clone(), bytes, false);
+        self.textures.push(Some(gpu_texture));
         self.texture_map.insert(name, handle);
 
         handle
     }
 
-    /// Get a texture by handle
+    /// Get a texture by handle (counts as a use for eviction)
     pub fn get_texture(&self, handle: TextureHandle) -> Option<&GpuTexture> {
-        self.textures.get(handle.0)
+        self.tracker.touch(handle.0);
+        self.textures.get(handle.0)?.as_ref()
     }
 
     /// Get a texture handle by name
@@ -198,6 +368,44 @@ impl TextureManager {
 
     /// Get texture count
     pub fn texture_count(&self) -> usize {
-        self.textures.len()
+        self.tracker.memory().count
+    }
+
+    /// Total size of all textures, mip chains included
+    pub fn texture_bytes(&self) -> u64 {
+        self.tracker.memory().bytes
+    }
+
+    pub fn memory(&self) -> ResourceMemory {
+        self.tracker.memory()
+    }
+
+    pub fn tracker(&self) -> &ResourceTracker {
+        &self.tracker
+    }
+
+    pub(crate) fn tracker_mut(&mut self) -> &mut ResourceTracker {
+        &mut self.tracker
+    }
+
+    /// Mark a texture as streamed: the memory budget may evict it once no
+    /// material samples it
+    pub fn set_streamed(&mut self, handle: TextureHandle, streamed: bool) {
+        self.tracker.set_streamed(handle.0, streamed);
+    }
+
+    /// Drop a texture; the next upload under its name loads it again.
+    /// The white fallback texture is never evicted.
+    pub fn evict(&mut self, handle: TextureHandle) -> bool {
+        if handle == self.white_texture_handle {
+            return false;
+        }
+        let Some(name) = self.tracker.remove(handle.0) else {
+            return false;
+        };
+        self.textures[handle.0] = None;
+        self.texture_map.remove(&name);
+        self.streams.remove(&handle.0);
+        true
     }
 }
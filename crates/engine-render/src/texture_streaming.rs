@@ -0,0 +1,220 @@
+// Texture streaming - full mip chains on the CPU, the finest mips on the GPU
+// only while something on screen needs them
+//
+// A streamed texture starts with just its small mips resident (up to
+// STREAM_BASE_SIZE). Draws request the mip level their size on screen calls
+// for; once a frame the texture manager brings each texture to the level it
+// asked for, a few uploads a frame, and while the streamed textures are over
+// the stream budget drops the finest mips of the least recently requested
+// ones. Changing a texture's resident mips re-creates it, so the materials
+// sampling it rebind.
+
+use engine_assets::texture_compression::{generate_mipmaps, MipLevel};
+use engine_assets::{CompressedTexture, Texture, TextureFormat};
+
+use crate::gpu_texture::compressed_texture_format;
+
+/// Largest mip a streamed texture keeps resident when nothing asks for more
+pub const STREAM_BASE_SIZE: u32 = 64;
+
+/// Textures brought to finer mips per frame; dropping mips is not limited
+pub const MAX_STREAM_UPLOADS: usize = 4;
+
+/// Frames a texture keeps mips after the last request for them
+pub const STREAM_RELEASE_FRAMES: u64 = 120;
+
+/// A texture's whole mip chain, finest first, ready to upload from any level
+pub struct StreamedMips {
+    pub format: wgpu::TextureFormat,
+    pub levels: Vec<MipLevel>,
+}
+
+impl StreamedMips {
+    /// RGBA mips for an uncompressed texture. None for single-channel
+    /// textures, which upload whole.
+    pub fn from_texture(texture: &Texture) -> Option<Self> {
+        if texture.format == TextureFormat::R8 {
+            return None;
+        }
+        Some(Self {
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            levels: generate_mipmaps(texture),
+        })
+    }
+
+    pub fn from_compressed(texture: CompressedTexture) -> Self {
+        Self {
+            format: compressed_texture_format(texture.format),
+            levels: texture.levels,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.levels.first().map_or(1, |level| level.width)
+    }
+
+    pub fn height(&self) -> u32 {
+        self.levels.first().map_or(1, |level| level.height)
+    }
+
+    /// Whether a texture can start at `level`: block-compressed textures need
+    /// their largest level to be whole blocks
+    pub fn can_start_at(&self, level: u32) -> bool {
+        let (block_width, block_height) = self.format.block_dimensions();
+        self.levels
+            .get(level as usize)
+            .is_some_and(|l| l.width % block_width == 0 && l.height % block_height == 0)
+    }
+
+    /// Level resident when nothing asks for more: the largest one within
+    /// STREAM_BASE_SIZE that can start a texture
+    pub fn base_level(&self) -> u32 {
+        let small = (0..self.levels.len() as u32)
+            .find(|&level| {
+                let l = &self.levels[level as usize];
+                l.width.max(l.height) <= STREAM_BASE_SIZE
+            })
+            .unwrap_or(0);
+        self.start_level(small)
+    }
+
+    /// `level`, or the nearest finer level a texture can start at
+    pub fn start_level(&self, level: u32) -> u32 {
+        (0..=level.min(self.levels.len().saturating_sub(1) as u32))
+            .rev()
+            .find(|&l| self.can_start_at(l))
+            .unwrap_or(0)
+    }
+
+    /// GPU bytes with `level` and the smaller levels resident
+    pub fn bytes_from(&self, level: u32) -> u64 {
+        self.levels
+            .iter()
+            .skip(level as usize)
+            .map(|l| l.data.len() as u64)
+            .sum()
+    }
+}
+
+/// Mip level that matches a texture drawn `screen_pixels` across: each level
+/// halves the size, so the level is log2(texture size / screen size)
+pub fn mip_for_screen_size(width: u32, height: u32, screen_pixels: f32) -> u32 {
+    let size = width.max(height) as f32;
+    if screen_pixels >= size {
+        return 0;
+    }
+    (size / screen_pixels.max(1.0)).log2().floor().max(0.0) as u32
+}
+
+/// A streamed texture's mips and what has been asked of it
+pub(crate) struct TextureStream {
+    pub mips: StreamedMips,
+    /// Largest level on the GPU
+    pub resident: u32,
+    pub base: u32,
+    /// Finest level requested within the release window
+    requested: u32,
+    requested_at: u64,
+    pub last_requested: u64,
+}
+
+impl TextureStream {
+    pub fn new(mips: StreamedMips) -> Self {
+        let base = mips.base_level();
+        Self {
+            mips,
+            resident: base,
+            base,
+            requested: base,
+            requested_at: 0,
+            last_requested: 0,
+        }
+    }
+
+    pub fn request(&mut self, level: u32, frame: u64) {
+        let level = self.mips.start_level(level);
+        if level <= self.requested
+            || frame.saturating_sub(self.requested_at) > STREAM_RELEASE_FRAMES
+        {
+            self.requested = level;
+            self.requested_at = frame;
+        }
+        self.last_requested = frame;
+    }
+
+    /// Level the texture should have resident, budget aside
+    pub fn wanted(&self, frame: u64) -> u32 {
+        if frame.saturating_sub(self.requested_at) > STREAM_RELEASE_FRAMES {
+            self.base
+        } else {
+            self.requested.min(self.base)
+        }
+    }
+}
+
+/// Resident level for each stream: what it wants, less the finest mips of
+/// the least recently requested streams while over `budget` (0 = no limit)
+pub(crate) fn plan_streaming(streams: &[&TextureStream], frame: u64, budget: u64) -> Vec<u32> {
+    let mut targets: Vec<u32> = streams.iter().map(|s| s.wanted(frame)).collect();
+    if budget == 0 {
+        return targets;
+    }
+    let mut total: u64 = streams
+        .iter()
+        .zip(&targets)
+        .map(|(s, &level)| s.mips.bytes_from(level))
+        .sum();
+    let mut order: Vec<usize> = (0..streams.len()).collect();
+    order.sort_by_key(|&i| streams[i].last_requested);
+    for i in order {
+        let stream = streams[i];
+        while total > budget && targets[i] < stream.base {
+            let next = (targets[i] + 1..=stream.base)
+                .find(|&level| stream.mips.can_start_at(level))
+                .unwrap_or(stream.base);
+            total -= stream.mips.bytes_from(targets[i]) - stream.mips.bytes_from(next);
+            targets[i] = next;
+        }
+    }
+    targets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(size: u32) -> TextureStream {
+        let data = vec![128; (size * size * 4) as usize];
+        let texture = Texture::new("t".to_string(), size, size, data, TextureFormat::Rgba8);
+        TextureStream::new(StreamedMips::from_texture(&texture).unwrap())
+    }
+
+    #[test]
+    fn test_mip_for_screen_size() {
+        assert_eq!(mip_for_screen_size(1024, 1024, 2000.0), 0);
+        assert_eq!(mip_for_screen_size(1024, 512, 512.0), 1);
+        assert_eq!(mip_for_screen_size(1024, 1024, 100.0), 3);
+        assert_eq!(mip_for_screen_size(1024, 1024, 0.0), 10);
+    }
+
+    #[test]
+    fn test_requests_and_budget() {
+        let mut near = stream(256);
+        let mut far = stream(256);
+        assert_eq!((near.base, near.resident), (2, 2));
+
+        near.request(0, 10);
+        far.request(0, 5);
+        // Coarser requests don't undo a finer one until it is released
+        near.request(1, 11);
+        assert_eq!(near.wanted(11), 0);
+        assert_eq!(near.wanted(11 + STREAM_RELEASE_FRAMES + 1), 2);
+
+        let full = near.mips.bytes_from(0);
+        assert_eq!(plan_streaming(&[&near, &far], 11, 0), [0, 0]);
+        // Room for one full chain: the least recently requested gives way
+        let budget = full + far.mips.bytes_from(2);
+        assert_eq!(plan_streaming(&[&near, &far], 11, budget), [0, 2]);
+        assert_eq!(plan_streaming(&[&near, &far], 11, full), [1, 2]);
+    }
+}
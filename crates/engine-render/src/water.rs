@@ -1,9 +1,16 @@
-// Water renderer with transparency and animation
+// Water renderer with transparency, animation and planar reflections
+//
+// Reflections: the scene is drawn a second time, mirrored about the water
+// plane, into a reduced-resolution target. The mirrored projection's near
+// plane is the water plane (an oblique frustum), so nothing below the water
+// shows up in the reflection. The water shader samples the target where the
+// reflected view projects each fragment, offset by the wave normal.
 
 use crate::gpu_mesh::GpuMesh;
-use crate::MSAA_SAMPLE_COUNT;
+use crate::postprocess::{Framebuffer, HDR_FORMAT};
 use anyhow::Result;
-use glam::{Mat4, Vec3};
+use glam::{Mat4, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
 use wgpu::util::DeviceExt;
 
 // WGSL alignment: vec3 needs 16-byte alignment, vec2 needs 8-byte alignment
@@ -11,14 +18,13 @@ use wgpu::util::DeviceExt;
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct WaterUniforms {
-    pub view_proj: [[f32; 4]; 4],   // 64 bytes @ 0
-    pub camera_pos: [f32; 3],        // 12 bytes @ 64
-    pub time: f32,                   // 4 bytes @ 76
-    pub flow_direction: [f32; 2],    // 8 bytes @ 80
-    pub flow_speed: f32,             // 4 bytes @ 88
-    pub _pad1: f32,                  // 4 bytes @ 92 (align vec2 to 8)
-    pub _padding: [f32; 2],          // 8 bytes @ 96
-    pub _pad2: [f32; 2],             // 8 bytes @ 104, total 112
+    pub view_proj: [[f32; 4]; 4],            // 64 bytes @ 0
+    pub camera_pos: [f32; 3],                // 12 bytes @ 64
+    pub time: f32,                           // 4 bytes @ 76
+    pub flow_direction: [f32; 2],            // 8 bytes @ 80
+    pub flow_speed: f32,                     // 4 bytes @ 88
+    pub reflection_strength: f32,            // 4 bytes @ 92 (0 = no reflection target)
+    pub reflection_view_proj: [[f32; 4]; 4], // 64 bytes @ 96, total 160
 }
 
 #[repr(C)]
@@ -27,16 +33,134 @@ pub struct WaterPushConstants {
     pub model: [[f32; 4]; 4],
 }
 
+/// Resolution of the planar reflection target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum WaterReflectionQuality {
+    /// No reflection pass; water reflects a flat sky color
+    Off,
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl WaterReflectionQuality {
+    pub const ALL: [WaterReflectionQuality; 4] = [Self::Off, Self::Low, Self::Medium, Self::High];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Low => "Low (1/4)",
+            Self::Medium => "Medium (1/2)",
+            Self::High => "High (full)",
+        }
+    }
+
+    /// Reflection target size as a fraction of the screen
+    pub fn resolution_scale(&self) -> f32 {
+        match self {
+            Self::Off => 0.0,
+            Self::Low => 0.25,
+            Self::Medium => 0.5,
+            Self::High => 1.0,
+        }
+    }
+}
+
+/// Mirror about the horizontal plane y = `height`
+pub fn reflection_matrix(height: f32) -> Mat4 {
+    Mat4::from_translation(Vec3::new(0.0, 2.0 * height, 0.0))
+        * Mat4::from_scale(Vec3::new(1.0, -1.0, 1.0))
+}
+
+/// `projection` (0..1 depth) with its near plane moved onto `plane`, given in
+/// view space as (normal, distance); points with a negative distance are
+/// clipped
+pub fn oblique_projection(projection: Mat4, plane: Vec4) -> Mat4 {
+    // The far frustum corner on the plane's side keeps the far plane sensible
+    let corner = projection.inverse() * Vec4::new(plane.x.signum(), plane.y.signum(), 1.0, 1.0);
+    let near = plane / plane.dot(corner);
+    let mut rows = projection.transpose();
+    rows.z_axis = near;
+    rows.transpose()
+}
+
+/// View-projection that draws the scene mirrored in the water plane at
+/// `height`, clipped to what is above the water. None when the camera at
+/// `camera_pos` is under the water.
+pub fn reflection_view_proj(
+    view: Mat4,
+    projection: Mat4,
+    camera_pos: Vec3,
+    height: f32,
+) -> Option<Mat4> {
+    if camera_pos.y <= height {
+        return None;
+    }
+    // Mirrored geometry ends up below the plane; keep y <= height
+    let plane = view.inverse().transpose() * Vec4::new(0.0, -1.0, 0.0, height);
+    Some(oblique_projection(projection, plane) * view * reflection_matrix(height))
+}
+
+/// Color and depth targets of the reflection pass
+pub struct WaterReflection {
+    pub target: Framebuffer,
+    pub depth: wgpu::TextureView,
+    pub quality: WaterReflectionQuality,
+}
+
+impl WaterReflection {
+    /// Targets at `quality`'s fraction of a `width` x `height` screen
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        quality: WaterReflectionQuality,
+    ) -> Result<Self> {
+        let scale = quality.resolution_scale();
+        let width = ((width as f32 * scale) as u32).max(1);
+        let height = ((height as f32 * scale) as u32).max(1);
+        let target = Framebuffer::new(device, width, height, HDR_FORMAT, false)?;
+        let depth = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Water Reflection Depth"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Depth32Float,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        Ok(Self {
+            target,
+            depth,
+            quality,
+        })
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.quality != WaterReflectionQuality::Off
+    }
+}
+
 pub struct WaterRenderer {
     pub render_pipeline: wgpu::RenderPipeline,
     pub uniform_buffer: wgpu::Buffer,
     pub uniform_bind_group: wgpu::BindGroup,
+    uniform_bind_group_layout: wgpu::BindGroupLayout,
 }
 
 impl WaterRenderer {
     pub fn new(
         device: &wgpu::Device,
         surface_format: wgpu::TextureFormat,
+        sample_count: u32,
         texture_bind_group_layout: &wgpu::BindGroupLayout,
         shadow_bind_group_layout: &wgpu::BindGroupLayout,
     ) -> Result<Self> {
@@ -53,9 +177,8 @@ impl WaterRenderer {
             time: 0.0,
             flow_direction: [1.0, 0.0],
             flow_speed: 0.0,
-            _pad1: 0.0,
-            _padding: [0.0, 0.0],
-            _pad2: [0.0, 0.0],
+            reflection_strength: 0.0,
+            reflection_view_proj: Mat4::IDENTITY.to_cols_array_2d(),
         };
 
         let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -64,30 +187,43 @@ impl WaterRenderer {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
-        // Create bind group layout for uniforms
+        // Create bind group layout for uniforms and the reflection target
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Water Uniform Bind Group Layout"),
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
-                count: None,
-            }],
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
         });
 
-        // Create bind group
-        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Water Uniform Bind Group"),
-            layout: &bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: uniform_buffer.as_entire_binding(),
-            }],
-        });
+        // Create bind group, with a placeholder until a reflection target is set
+        let placeholder = Framebuffer::new(device, 1, 1, HDR_FORMAT, false)?;
+        let uniform_bind_group =
+            Self::create_bind_group(device, &bind_group_layout, &uniform_buffer, &placeholder);
 
         // Create pipeline layout
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -151,7 +287,7 @@ impl WaterRenderer {
             },
             depth_stencil: Some(depth_stencil),
             multisample: wgpu::MultisampleState {
-                count: MSAA_SAMPLE_COUNT,
+                count: sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
@@ -163,9 +299,49 @@ impl WaterRenderer {
             render_pipeline,
             uniform_buffer,
             uniform_bind_group,
+            uniform_bind_group_layout: bind_group_layout,
+        })
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        uniform_buffer: &wgpu::Buffer,
+        reflection: &Framebuffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Water Uniform Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&reflection.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&reflection.sampler),
+                },
+            ],
         })
     }
 
+    /// Sample reflections from `reflection` (call again when it is recreated)
+    pub fn set_reflection(&mut self, device: &wgpu::Device, reflection: &WaterReflection) {
+        self.uniform_bind_group = Self::create_bind_group(
+            device,
+            &self.uniform_bind_group_layout,
+            &self.uniform_buffer,
+            &reflection.target,
+        );
+    }
+
+    /// `reflection_view_proj` is the matrix the reflection target was drawn
+    /// with this frame, or None to skip reflections
+    #[allow(clippy::too_many_arguments)]
     pub fn update_uniforms(
         &self,
         queue: &wgpu::Queue,
@@ -174,6 +350,7 @@ impl WaterRenderer {
         time: f32,
         flow_direction: [f32; 2],
         flow_speed: f32,
+        reflection_view_proj: Option<Mat4>,
     ) {
         let uniforms = WaterUniforms {
             view_proj: view_proj.to_cols_array_2d(),
@@ -181,9 +358,14 @@ impl WaterRenderer {
             time,
             flow_direction,
             flow_speed,
-            _pad1: 0.0,
-            _padding: [0.0, 0.0],
-            _pad2: [0.0, 0.0],
+            reflection_strength: if reflection_view_proj.is_some() {
+                1.0
+            } else {
+                0.0
+            },
+            reflection_view_proj: reflection_view_proj
+                .unwrap_or(Mat4::IDENTITY)
+                .to_cols_array_2d(),
         };
         queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
     }
@@ -215,3 +397,29 @@ impl WaterRenderer {
         render_pass.draw_indexed(0..mesh.num_indices, 0, 0..1);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reflection_mirrors_and_clips_at_the_water() {
+        let eye = Vec3::new(0.0, 5.0, 10.0);
+        let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+        let projection = Mat4::perspective_rh(1.0, 1.5, 0.1, 100.0);
+        let reflected = reflection_view_proj(view, projection, eye, 1.0).unwrap();
+        let mirrored = projection * view * reflection_matrix(1.0);
+
+        // A point above the water lands where its mirror image would
+        let above = Vec4::new(0.5, 3.0, -2.0, 1.0);
+        let (a, b) = (reflected * above, mirrored * above);
+        assert!((a.truncate().truncate() / a.w).abs_diff_eq(b.truncate().truncate() / b.w, 1e-4));
+        assert!(a.z >= 0.0 && a.z <= a.w);
+
+        // Anything under the water is in front of the new near plane
+        let below = reflected * Vec4::new(0.5, 0.5, -2.0, 1.0);
+        assert!(below.z < 0.0);
+
+        assert!(reflection_view_proj(view, projection, Vec3::new(0.0, 0.5, 0.0), 1.0).is_none());
+    }
+}
@@ -0,0 +1,368 @@
+// Keyframe animation - property tracks on an entity and their playback
+//
+// An AnimationClip holds one track per animated property. Keys are linearly
+// interpolated (rotation is slerped); the animation system advances every
+// clip during play and writes the sampled pose back to the entity.
+
+use crate::components::{Light, LightType};
+use crate::entity::{Component, EntityId};
+use crate::impl_component;
+use crate::scene::Scene;
+use crate::transform::Transform;
+use glam::{Quat, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+
+/// Two keys closer than this (seconds) are the same key
+const KEY_TIME_EPSILON: f32 = 1.0e-3;
+
+/// Entity property a track animates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AnimatedProperty {
+    Position,
+    Rotation,
+    Scale,
+    LightIntensity,
+}
+
+impl AnimatedProperty {
+    pub const ALL: [AnimatedProperty; 4] = [
+        AnimatedProperty::Position,
+        AnimatedProperty::Rotation,
+        AnimatedProperty::Scale,
+        AnimatedProperty::LightIntensity,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AnimatedProperty::Position => "Position",
+            AnimatedProperty::Rotation => "Rotation",
+            AnimatedProperty::Scale => "Scale",
+            AnimatedProperty::LightIntensity => "Light Intensity",
+        }
+    }
+}
+
+/// A value at a point in time. Position and scale use xyz, rotation is a
+/// quaternion (xyzw) and light intensity uses x.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub time: f32,
+    pub value: Vec4,
+}
+
+/// Keys of one property, sorted by time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnimationTrack {
+    pub property: AnimatedProperty,
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl AnimationTrack {
+    pub fn new(property: AnimatedProperty) -> Self {
+        Self {
+            property,
+            keyframes: Vec::new(),
+        }
+    }
+
+    /// Set the key at `time`, replacing one already there.
+    /// Returns the key's index.
+    pub fn set_key(&mut self, time: f32, value: Vec4) -> usize {
+        if let Some(index) = self
+            .keyframes
+            .iter()
+            .position(|k| (k.time - time).abs() < KEY_TIME_EPSILON)
+        {
+            self.keyframes[index].value = value;
+            return index;
+        }
+        let index = self.keyframes.partition_point(|k| k.time < time);
+        self.keyframes.insert(index, Keyframe { time, value });
+        index
+    }
+
+    /// Move a key to a new time, keeping the keys sorted.
+    /// Returns the key's new index.
+    pub fn move_key(&mut self, index: usize, time: f32) -> usize {
+        let mut key = self.keyframes.remove(index);
+        key.time = time.max(0.0);
+        let index = self.keyframes.partition_point(|k| k.time < key.time);
+        self.keyframes.insert(index, key);
+        index
+    }
+
+    /// Interpolated value at `time`, holding the first and last keys outside
+    /// their range. None if the track has no keys.
+    pub fn sample(&self, time: f32) -> Option<Vec4> {
+        let first = self.keyframes.first()?;
+        let last = self.keyframes.last()?;
+        if time <= first.time {
+            return Some(first.value);
+        }
+        if time >= last.time {
+            return Some(last.value);
+        }
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        let (a, b) = (self.keyframes[next - 1], self.keyframes[next]);
+        let t = (time - a.time) / (b.time - a.time).max(f32::EPSILON);
+        Some(match self.property {
+            AnimatedProperty::Rotation => {
+                let rotation = Quat::from_vec4(a.value).slerp(Quat::from_vec4(b.value), t);
+                Vec4::from(rotation)
+            }
+            _ => a.value.lerp(b.value, t),
+        })
+    }
+}
+
+/// Keyframed property animation played on its entity at runtime
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnimationClip {
+    /// Length of the clip in seconds
+    pub duration: f32,
+    /// Restart from the beginning when the end is reached
+    pub looping: bool,
+    /// Start playing when the game starts
+    pub autoplay: bool,
+    pub tracks: Vec<AnimationTrack>,
+    /// Playback position in seconds (runtime only)
+    #[serde(skip)]
+    pub time: f32,
+}
+
+impl AnimationClip {
+    pub fn new(duration: f32) -> Self {
+        Self {
+            duration,
+            looping: true,
+            autoplay: true,
+            tracks: Vec::new(),
+            time: 0.0,
+        }
+    }
+
+    pub fn track(&self, property: AnimatedProperty) -> Option<&AnimationTrack> {
+        self.tracks.iter().find(|t| t.property == property)
+    }
+
+    pub fn track_mut(&mut self, property: AnimatedProperty) -> Option<&mut AnimationTrack> {
+        self.tracks.iter_mut().find(|t| t.property == property)
+    }
+
+    /// Set a key, creating the property's track if needed.
+    /// Returns the key's index in its track.
+    pub fn set_key(&mut self, property: AnimatedProperty, time: f32, value: Vec4) -> usize {
+        if self.track(property).is_none() {
+            self.tracks.push(AnimationTrack::new(property));
+        }
+        let track = self.track_mut(property).expect("track was just added");
+        track.set_key(time, value)
+    }
+
+    /// Remove a key, dropping its track once empty
+    pub fn remove_key(&mut self, property: AnimatedProperty, index: usize) {
+        if let Some(track) = self.track_mut(property) {
+            if index < track.keyframes.len() {
+                track.keyframes.remove(index);
+            }
+        }
+        self.tracks.retain(|t| !t.keyframes.is_empty());
+    }
+
+    /// Advance playback by `dt` seconds, wrapping or clamping at the end
+    pub fn advance(&mut self, dt: f32) {
+        let duration = self.duration.max(f32::EPSILON);
+        self.time += dt;
+        if self.time >= duration {
+            self.time = if self.looping {
+                self.time % duration
+            } else {
+                duration
+            };
+        }
+    }
+
+    /// Sample every track at `time`
+    pub fn pose_at(&self, time: f32) -> AnimationPose {
+        let sample = |property| self.track(property).and_then(|t| t.sample(time));
+        AnimationPose {
+            position: sample(AnimatedProperty::Position).map(|v| v.truncate()),
+            rotation: sample(AnimatedProperty::Rotation).map(|v| Quat::from_vec4(v).normalize()),
+            scale: sample(AnimatedProperty::Scale).map(|v| v.truncate()),
+            light_intensity: sample(AnimatedProperty::LightIntensity).map(|v| v.x),
+        }
+    }
+}
+
+impl Default for AnimationClip {
+    fn default() -> Self {
+        Self::new(2.0)
+    }
+}
+
+impl_component!(AnimationClip);
+
+/// Animated values at one point in time; None for properties without a track
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AnimationPose {
+    pub position: Option<Vec3>,
+    pub rotation: Option<Quat>,
+    pub scale: Option<Vec3>,
+    pub light_intensity: Option<f32>,
+}
+
+impl AnimationPose {
+    pub fn apply(&self, transform: &mut Transform, light: Option<&mut Light>) {
+        if let Some(position) = self.position {
+            transform.position = position;
+        }
+        if let Some(rotation) = self.rotation {
+            transform.rotation = rotation;
+        }
+        if let Some(scale) = self.scale {
+            transform.scale = scale;
+        }
+        if let (Some(intensity), Some(light)) = (self.light_intensity, light) {
+            light.intensity = intensity;
+            if let LightType::Point {
+                intensity: point_intensity,
+                ..
+            } = &mut light.light_type
+            {
+                *point_intensity = intensity;
+            }
+        }
+    }
+}
+
+/// Current value of an animatable property on an entity, for recording keys.
+/// None if the entity doesn't have it (light intensity without a Light).
+pub fn current_value(
+    scene: &Scene,
+    entity_id: EntityId,
+    property: AnimatedProperty,
+) -> Option<Vec4> {
+    let entity = scene.get_entity(entity_id)?;
+    let transform = &entity.transform;
+    match property {
+        AnimatedProperty::Position => Some(transform.position.extend(0.0)),
+        AnimatedProperty::Rotation => Some(Vec4::from(transform.rotation)),
+        AnimatedProperty::Scale => Some(transform.scale.extend(0.0)),
+        AnimatedProperty::LightIntensity => entity
+            .get_component::<Light>()
+            .map(|light| Vec4::new(light.intensity, 0.0, 0.0, 0.0)),
+    }
+}
+
+/// Write a clip's pose at `time` to its entity
+pub fn apply_clip_at(scene: &mut Scene, entity_id: EntityId, time: f32) {
+    let Some(entity) = scene.get_entity_mut(entity_id) else {
+        return;
+    };
+    let Some(pose) = entity
+        .get_component::<AnimationClip>()
+        .map(|clip| clip.pose_at(time))
+    else {
+        return;
+    };
+    let mut transform = entity.transform;
+    pose.apply(&mut transform, entity.get_component_mut::<Light>());
+    entity.transform = transform;
+}
+
+/// Animation system: reset clips for a new play session
+pub fn start_animations(scene: &mut Scene) {
+    for id in animated_entities(scene) {
+        if let Some(clip) = scene
+            .get_entity_mut(id)
+            .and_then(|e| e.get_component_mut::<AnimationClip>())
+        {
+            clip.time = 0.0;
+        }
+    }
+    crate::animator::start_animators(scene);
+}
+
+/// Animation system: advance every autoplaying clip and animator and apply
+/// their poses
+pub fn update_animations(scene: &mut Scene, dt: f32) {
+    for id in animated_entities(scene) {
+        let Some(clip) = scene
+            .get_entity_mut(id)
+            .and_then(|e| e.get_component_mut::<AnimationClip>())
+            .filter(|clip| clip.autoplay)
+        else {
+            continue;
+        };
+        clip.advance(dt);
+        let time = clip.time;
+        apply_clip_at(scene, id, time);
+    }
+    crate::animator::update_animators(scene, dt);
+}
+
+fn animated_entities(scene: &Scene) -> Vec<EntityId> {
+    scene
+        .entities()
+        .filter(|e| e.has_component::<AnimationClip>())
+        .map(|e| e.id)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_track_interpolates_and_holds_ends() {
+        let mut track = AnimationTrack::new(AnimatedProperty::Position);
+        track.set_key(1.0, Vec4::new(2.0, 0.0, 0.0, 0.0));
+        track.set_key(0.0, Vec4::ZERO);
+        assert_eq!(track.keyframes[0].time, 0.0);
+        assert_eq!(track.sample(0.5).unwrap().x, 1.0);
+        assert_eq!(track.sample(-1.0).unwrap().x, 0.0);
+        assert_eq!(track.sample(5.0).unwrap().x, 2.0);
+
+        // Setting a key at an existing time replaces it
+        track.set_key(1.0, Vec4::new(4.0, 0.0, 0.0, 0.0));
+        assert_eq!(track.keyframes.len(), 2);
+        assert_eq!(track.sample(0.5).unwrap().x, 2.0);
+    }
+
+    #[test]
+    fn test_update_animations_plays_clip() {
+        let mut scene = Scene::new("Test".to_string());
+        let id = scene.create_entity("Lamp".to_string());
+        let mut clip = AnimationClip::new(1.0);
+        clip.set_key(AnimatedProperty::Position, 0.0, Vec4::ZERO);
+        clip.set_key(
+            AnimatedProperty::Position,
+            1.0,
+            Vec4::new(0.0, 10.0, 0.0, 0.0),
+        );
+        clip.set_key(
+            AnimatedProperty::LightIntensity,
+            0.0,
+            Vec4::new(1.0, 0.0, 0.0, 0.0),
+        );
+        clip.set_key(
+            AnimatedProperty::LightIntensity,
+            1.0,
+            Vec4::new(3.0, 0.0, 0.0, 0.0),
+        );
+        let entity = scene.get_entity_mut(id).unwrap();
+        entity.add_component(clip);
+        entity.add_component(Light::point([1.0, 1.0, 1.0], 1.0, 10.0));
+
+        update_animations(&mut scene, 0.25);
+        let entity = scene.get_entity(id).unwrap();
+        assert!((entity.transform.position.y - 2.5).abs() < 1e-4);
+        assert!((entity.get_component::<Light>().unwrap().intensity - 1.5).abs() < 1e-4);
+
+        // Looping wraps back past the end
+        update_animations(&mut scene, 1.0);
+        let entity = scene.get_entity(id).unwrap();
+        assert!((entity.transform.position.y - 2.5).abs() < 1e-4);
+    }
+}
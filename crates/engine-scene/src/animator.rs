@@ -0,0 +1,793 @@
+// Animator - a state machine that blends animation clips
+//
+// An Animator holds layers of states, each state playing an AnimationClip.
+// Transitions move a layer from one state to another when their conditions
+// on the animator's parameters hold (parameters are set by scripts: a speed
+// float, a grounded bool, a jump trigger), cross-fading the two states' poses
+// over the transition's blend time. Layers are applied in order, each
+// blended over the ones below by its weight, so an upper layer animating only
+// rotation can run on top of a base locomotion layer.
+//
+// A state can also be a 1D blend tree: several clips (walk, jog, run) placed
+// along a float parameter, the two around the parameter's value mixed and
+// played in step, so a speed parameter blends gaits smoothly.
+//
+// Animators are updated by the animation system after plain clips, so on an
+// entity with both the animator's pose wins.
+
+use crate::animation::{AnimationClip, AnimationPose};
+use crate::entity::{Component, EntityId};
+use crate::impl_component;
+use crate::scene::Scene;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::BTreeMap;
+
+/// Value of an animator parameter
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AnimatorParameter {
+    Float(f32),
+    Bool(bool),
+    /// Set by scripts, cleared when a transition uses it
+    Trigger(bool),
+}
+
+impl AnimatorParameter {
+    pub fn as_f32(&self) -> f32 {
+        match *self {
+            AnimatorParameter::Float(value) => value,
+            AnimatorParameter::Bool(value) | AnimatorParameter::Trigger(value) => {
+                if value {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConditionOp {
+    Greater,
+    Less,
+    Equals,
+    NotEquals,
+    /// Bool is true or trigger is set
+    IsTrue,
+    IsFalse,
+}
+
+/// A test on one parameter. Conditions on missing parameters never hold.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnimatorCondition {
+    pub parameter: String,
+    pub op: ConditionOp,
+    /// Compared against for Greater/Less/Equals/NotEquals
+    #[serde(default)]
+    pub value: f32,
+}
+
+impl AnimatorCondition {
+    pub fn new(parameter: &str, op: ConditionOp, value: f32) -> Self {
+        Self {
+            parameter: parameter.to_string(),
+            op,
+            value,
+        }
+    }
+
+    fn holds(&self, parameters: &BTreeMap<String, AnimatorParameter>) -> bool {
+        let Some(parameter) = parameters.get(&self.parameter) else {
+            return false;
+        };
+        let value = parameter.as_f32();
+        match self.op {
+            ConditionOp::Greater => value > self.value,
+            ConditionOp::Less => value < self.value,
+            ConditionOp::Equals => (value - self.value).abs() < f32::EPSILON,
+            ConditionOp::NotEquals => (value - self.value).abs() >= f32::EPSILON,
+            ConditionOp::IsTrue => value != 0.0,
+            ConditionOp::IsFalse => value == 0.0,
+        }
+    }
+}
+
+/// Move from one state to another when every condition holds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnimatorTransition {
+    /// Source state; None transitions from any other state
+    pub from: Option<String>,
+    pub to: String,
+    pub conditions: Vec<AnimatorCondition>,
+    /// Cross-fade length in seconds
+    pub blend_time: f32,
+    /// Only leave once the source state has played this fraction of its
+    /// clip (0..1), e.g. 1.0 to let a landing finish
+    #[serde(default)]
+    pub exit_time: Option<f32>,
+}
+
+impl AnimatorTransition {
+    pub fn new(from: Option<&str>, to: &str, blend_time: f32) -> Self {
+        Self {
+            from: from.map(str::to_string),
+            to: to.to_string(),
+            conditions: Vec::new(),
+            blend_time,
+            exit_time: None,
+        }
+    }
+
+    pub fn with_condition(mut self, parameter: &str, op: ConditionOp, value: f32) -> Self {
+        self.conditions
+            .push(AnimatorCondition::new(parameter, op, value));
+        self
+    }
+
+    pub fn with_exit_time(mut self, exit_time: f32) -> Self {
+        self.exit_time = Some(exit_time);
+        self
+    }
+}
+
+/// A clip of a blend tree, used as is when the parameter is at `threshold`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlendMotion {
+    pub threshold: f32,
+    pub clip: AnimationClip,
+    /// Animation of the entity's SkinnedMesh model, as for states
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skeletal_clip: Option<String>,
+}
+
+/// Clips blended by where a float parameter falls between their thresholds
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BlendTree {
+    pub parameter: String,
+    /// Sorted by threshold
+    pub motions: Vec<BlendMotion>,
+}
+
+impl BlendTree {
+    pub fn new(parameter: &str) -> Self {
+        Self {
+            parameter: parameter.to_string(),
+            motions: Vec::new(),
+        }
+    }
+
+    pub fn with_motion(mut self, threshold: f32, clip: AnimationClip) -> Self {
+        self.push(BlendMotion {
+            threshold,
+            clip,
+            skeletal_clip: None,
+        });
+        self
+    }
+
+    pub fn with_skeletal_motion(mut self, threshold: f32, animation: &str) -> Self {
+        self.push(BlendMotion {
+            threshold,
+            clip: AnimationClip::new(1.0),
+            skeletal_clip: Some(animation.to_string()),
+        });
+        self
+    }
+
+    fn push(&mut self, motion: BlendMotion) {
+        let index = self
+            .motions
+            .partition_point(|m| m.threshold <= motion.threshold);
+        self.motions.insert(index, motion);
+    }
+
+    /// The two motions around `value` and how far it is from the first to
+    /// the second (0 to 1); past either end that end's motion alone
+    pub fn blend(&self, value: f32) -> Option<(usize, usize, f32)> {
+        let last = self.motions.len().checked_sub(1)?;
+        let upper = self.motions.partition_point(|m| m.threshold <= value);
+        if upper == 0 {
+            return Some((0, 0, 0.0));
+        }
+        if upper > last {
+            return Some((last, last, 0.0));
+        }
+        let (a, b) = (&self.motions[upper - 1], &self.motions[upper]);
+        let t = (value - a.threshold) / (b.threshold - a.threshold).max(f32::EPSILON);
+        Some((upper - 1, upper, t.clamp(0.0, 1.0)))
+    }
+
+    /// Length of the blended cycle, between the two motions' lengths
+    pub fn duration(&self, value: f32) -> Option<f32> {
+        let (a, b, t) = self.blend(value)?;
+        let (a, b) = (self.motions[a].clip.duration, self.motions[b].clip.duration);
+        Some(a + (b - a) * t)
+    }
+
+    /// Pose at `progress` (0 to 1) through the cycle; both motions are
+    /// sampled at the same fraction so their steps line up
+    pub fn pose_at(&self, value: f32, progress: f32) -> AnimationPose {
+        let Some((a, b, t)) = self.blend(value) else {
+            return AnimationPose::default();
+        };
+        let sample = |motion: &BlendMotion| motion.clip.pose_at(progress * motion.clip.duration);
+        blend_poses(&sample(&self.motions[a]), &sample(&self.motions[b]), t)
+    }
+}
+
+/// A clip the animator can be in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnimatorState {
+    pub name: String,
+    pub clip: AnimationClip,
+    /// Playback rate of the clip
+    #[serde(default = "default_speed")]
+    pub speed: f32,
+    /// Animation of the entity's SkinnedMesh model to pose its bones with;
+    /// `clip` then only sets the length and looping
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skeletal_clip: Option<String>,
+    /// Blend these clips by a parameter instead of playing `clip`, whose
+    /// length then follows the blend
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blend_tree: Option<BlendTree>,
+}
+
+fn default_speed() -> f32 {
+    1.0
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+impl AnimatorState {
+    pub fn new(name: &str, clip: AnimationClip) -> Self {
+        Self {
+            name: name.to_string(),
+            clip,
+            speed: 1.0,
+            skeletal_clip: None,
+            blend_tree: None,
+        }
+    }
+
+    pub fn with_skeletal_clip(mut self, animation: &str) -> Self {
+        self.skeletal_clip = Some(animation.to_string());
+        self
+    }
+
+    /// A looping blend tree state
+    pub fn blended(name: &str, blend_tree: BlendTree) -> Self {
+        let mut clip = AnimationClip::new(1.0);
+        clip.looping = true;
+        Self {
+            blend_tree: Some(blend_tree),
+            ..Self::new(name, clip)
+        }
+    }
+
+    /// Value of a blend tree's parameter (0 if unset)
+    pub fn blend_value(&self, parameters: &BTreeMap<String, AnimatorParameter>) -> f32 {
+        self.blend_tree
+            .as_ref()
+            .and_then(|tree| parameters.get(&tree.parameter))
+            .map_or(0.0, AnimatorParameter::as_f32)
+    }
+
+    /// The state's pose `time` seconds in
+    pub fn pose_at(
+        &self,
+        time: f32,
+        parameters: &BTreeMap<String, AnimatorParameter>,
+    ) -> AnimationPose {
+        match &self.blend_tree {
+            Some(tree) => tree.pose_at(
+                self.blend_value(parameters),
+                time / self.clip.duration.max(f32::EPSILON),
+            ),
+            None => self.clip.pose_at(time),
+        }
+    }
+
+    /// Follow the blend tree's cycle length, keeping how far through it
+    /// `time` is
+    fn sync_blend_duration(
+        &mut self,
+        time: f32,
+        parameters: &BTreeMap<String, AnimatorParameter>,
+    ) -> f32 {
+        let value = self.blend_value(parameters);
+        let Some(duration) = self
+            .blend_tree
+            .as_ref()
+            .and_then(|tree| tree.duration(value))
+        else {
+            return time;
+        };
+        let progress = time / self.clip.duration.max(f32::EPSILON);
+        self.clip.duration = duration;
+        progress * duration
+    }
+}
+
+/// Where a layer is in its state machine (runtime only)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LayerPlayback {
+    pub state: usize,
+    pub time: f32,
+    /// Clip lengths played since the state was entered, not wrapped, so a
+    /// looping state can reach an exit time of 1.0
+    pub progress: f32,
+    /// State being faded out and its time
+    pub previous: Option<(usize, f32)>,
+    pub blend_elapsed: f32,
+    pub blend_time: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnimatorLayer {
+    pub name: String,
+    /// How much of this layer's pose is blended over the layers below.
+    /// Ignored for the first layer.
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+    pub states: Vec<AnimatorState>,
+    pub transitions: Vec<AnimatorTransition>,
+    /// State entered when play starts; the first state if not found
+    pub default_state: String,
+    #[serde(skip)]
+    pub playback: LayerPlayback,
+}
+
+impl AnimatorLayer {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            weight: 1.0,
+            states: Vec::new(),
+            transitions: Vec::new(),
+            default_state: String::new(),
+            playback: LayerPlayback::default(),
+        }
+    }
+
+    /// Add a state; the first one added becomes the default
+    pub fn with_state(mut self, state: AnimatorState) -> Self {
+        if self.states.is_empty() && self.default_state.is_empty() {
+            self.default_state = state.name.clone();
+        }
+        self.states.push(state);
+        self
+    }
+
+    pub fn with_transition(mut self, transition: AnimatorTransition) -> Self {
+        self.transitions.push(transition);
+        self
+    }
+
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn state_index(&self, name: &str) -> Option<usize> {
+        self.states.iter().position(|s| s.name == name)
+    }
+
+    /// Name of the state the layer is in
+    pub fn current_state(&self) -> Option<&str> {
+        self.states
+            .get(self.playback.state)
+            .map(|s| s.name.as_str())
+    }
+
+    /// Cross-fade straight to a state, ignoring transitions. Returns false
+    /// if the layer has no such state.
+    pub fn play(&mut self, state: &str, blend_time: f32) -> bool {
+        let Some(to) = self.state_index(state) else {
+            return false;
+        };
+        let playback = self.playback;
+        self.playback = LayerPlayback {
+            state: to,
+            time: 0.0,
+            previous: (blend_time > 0.0).then_some((playback.state, playback.time)),
+            blend_elapsed: 0.0,
+            blend_time,
+            progress: 0.0,
+        };
+        true
+    }
+
+    fn reset(&mut self) {
+        self.playback = LayerPlayback {
+            state: self.state_index(&self.default_state).unwrap_or(0),
+            ..Default::default()
+        };
+    }
+
+    /// Advance playback, fading and taking the first transition whose
+    /// conditions hold. Returns the triggers the transition consumed.
+    fn advance(
+        &mut self,
+        dt: f32,
+        parameters: &BTreeMap<String, AnimatorParameter>,
+    ) -> Vec<String> {
+        if self.states.is_empty() {
+            return Vec::new();
+        }
+        let mut playback = self.playback;
+        playback.state = playback.state.min(self.states.len() - 1);
+        playback.time = self.states[playback.state].sync_blend_duration(playback.time, parameters);
+        let state = &self.states[playback.state];
+        playback.time = advance_time(state, playback.time, dt);
+        playback.progress += (dt * state.speed).abs() / state.clip.duration.max(f32::EPSILON);
+        if let Some((previous, time)) = playback.previous {
+            playback.blend_elapsed += dt;
+            playback.previous = self
+                .states
+                .get(previous)
+                .filter(|_| playback.blend_elapsed < playback.blend_time)
+                .map(|state| (previous, advance_time(state, time, dt)));
+        }
+
+        let current = &self.states[playback.state];
+        let next = self.transitions.iter().find_map(|t| {
+            let from_matches = t.from.as_deref().is_none_or(|from| from == current.name);
+            let to = self.state_index(&t.to)?;
+            let ready = from_matches
+                && to != playback.state
+                && t.exit_time.is_none_or(|exit| playback.progress >= exit)
+                && t.conditions.iter().all(|c| c.holds(parameters));
+            ready.then_some((to, t))
+        });
+        let Some((to, transition)) = next else {
+            self.playback = playback;
+            return Vec::new();
+        };
+
+        self.playback = LayerPlayback {
+            state: to,
+            time: 0.0,
+            previous: (transition.blend_time > 0.0).then_some((playback.state, playback.time)),
+            blend_elapsed: 0.0,
+            blend_time: transition.blend_time,
+            progress: 0.0,
+        };
+        transition
+            .conditions
+            .iter()
+            .filter(|c| {
+                matches!(
+                    parameters.get(&c.parameter),
+                    Some(AnimatorParameter::Trigger(_))
+                )
+            })
+            .map(|c| c.parameter.clone())
+            .collect()
+    }
+
+    /// The state being faded out and its time, with how far the fade to
+    /// the current state is (0 to 1)
+    pub fn fading_from(&self) -> Option<(&AnimatorState, f32, f32)> {
+        let playback = &self.playback;
+        let (previous, time) = playback.previous.filter(|_| playback.blend_time > 0.0)?;
+        let t = (playback.blend_elapsed / playback.blend_time).clamp(0.0, 1.0);
+        Some((self.states.get(previous)?, time, t))
+    }
+
+    /// The layer's pose, cross-faded while a transition blends
+    pub fn pose(&self, parameters: &BTreeMap<String, AnimatorParameter>) -> AnimationPose {
+        let Some(state) = self.states.get(self.playback.state) else {
+            return AnimationPose::default();
+        };
+        let pose = state.pose_at(self.playback.time, parameters);
+        match self.fading_from() {
+            Some((previous, time, t)) => blend_poses(&previous.pose_at(time, parameters), &pose, t),
+            None => pose,
+        }
+    }
+}
+
+/// Advance a state's time, wrapping or clamping at the end of its clip
+fn advance_time(state: &AnimatorState, time: f32, dt: f32) -> f32 {
+    let duration = state.clip.duration.max(f32::EPSILON);
+    let time = time + dt * state.speed;
+    if state.clip.looping {
+        time.rem_euclid(duration)
+    } else {
+        time.clamp(0.0, duration)
+    }
+}
+
+/// Blend from `a` to `b` by `t`; a property only one pose has is taken as is
+pub fn blend_poses(a: &AnimationPose, b: &AnimationPose, t: f32) -> AnimationPose {
+    fn mix<T: Copy>(a: Option<T>, b: Option<T>, f: impl Fn(T, T) -> T) -> Option<T> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(f(a, b)),
+            (a, b) => b.or(a),
+        }
+    }
+    AnimationPose {
+        position: mix(a.position, b.position, |a, b| a.lerp(b, t)),
+        rotation: mix(a.rotation, b.rotation, |a, b| a.slerp(b, t).normalize()),
+        scale: mix(a.scale, b.scale, |a, b| a.lerp(b, t)),
+        light_intensity: mix(a.light_intensity, b.light_intensity, |a, b| a + (b - a) * t),
+    }
+}
+
+/// State machine over animation clips, driven by parameters scripts set
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Animator {
+    pub parameters: BTreeMap<String, AnimatorParameter>,
+    pub layers: Vec<AnimatorLayer>,
+}
+
+impl Animator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_layer(mut self, layer: AnimatorLayer) -> Self {
+        self.layers.push(layer);
+        self
+    }
+
+    pub fn with_parameter(mut self, name: &str, value: AnimatorParameter) -> Self {
+        self.parameters.insert(name.to_string(), value);
+        self
+    }
+
+    pub fn set_float(&mut self, name: &str, value: f32) {
+        self.parameters
+            .insert(name.to_string(), AnimatorParameter::Float(value));
+    }
+
+    pub fn set_bool(&mut self, name: &str, value: bool) {
+        self.parameters
+            .insert(name.to_string(), AnimatorParameter::Bool(value));
+    }
+
+    pub fn set_trigger(&mut self, name: &str) {
+        self.parameters
+            .insert(name.to_string(), AnimatorParameter::Trigger(true));
+    }
+
+    /// Name of the state a layer is in
+    pub fn current_state(&self, layer: usize) -> Option<&str> {
+        self.layers.get(layer)?.current_state()
+    }
+
+    /// Cross-fade the first layer that has `state` to it. Returns the
+    /// state's clip length in seconds at its playback speed.
+    pub fn play(&mut self, state: &str, blend_time: f32) -> Option<f32> {
+        let layer = self
+            .layers
+            .iter_mut()
+            .find(|layer| layer.state_index(state).is_some())?;
+        layer.play(state, blend_time);
+        let state = &layer.states[layer.playback.state];
+        Some(state.clip.duration / state.speed.abs().max(f32::EPSILON))
+    }
+
+    /// Put every layer in its default state
+    pub fn reset(&mut self) {
+        for layer in &mut self.layers {
+            layer.reset();
+        }
+    }
+
+    /// Advance every layer by `dt` seconds
+    pub fn advance(&mut self, dt: f32) {
+        for layer in &mut self.layers {
+            for trigger in layer.advance(dt, &self.parameters) {
+                self.parameters
+                    .insert(trigger, AnimatorParameter::Trigger(false));
+            }
+        }
+    }
+
+    /// Every layer's pose, each blended over the ones below by its weight
+    pub fn pose(&self) -> AnimationPose {
+        self.layers
+            .iter()
+            .enumerate()
+            .fold(AnimationPose::default(), |pose, (index, layer)| {
+                let weight = if index == 0 {
+                    1.0
+                } else {
+                    layer.weight.clamp(0.0, 1.0)
+                };
+                blend_poses(&pose, &layer.pose(&self.parameters), weight)
+            })
+    }
+}
+
+impl_component!(Animator);
+
+/// Animation system: put every animator in its default states
+pub fn start_animators(scene: &mut Scene) {
+    for id in animator_entities(scene) {
+        if let Some(animator) = scene
+            .get_entity_mut(id)
+            .and_then(|e| e.get_component_mut::<Animator>())
+        {
+            animator.reset();
+        }
+    }
+}
+
+/// Animation system: advance every animator and apply its pose
+pub fn update_animators(scene: &mut Scene, dt: f32) {
+    for id in animator_entities(scene) {
+        let Some(entity) = scene.get_entity_mut(id) else {
+            continue;
+        };
+        let Some(pose) = entity.get_component_mut::<Animator>().map(|animator| {
+            animator.advance(dt);
+            animator.pose()
+        }) else {
+            continue;
+        };
+        let mut transform = entity.transform;
+        pose.apply(
+            &mut transform,
+            entity.get_component_mut::<crate::components::Light>(),
+        );
+        entity.transform = transform;
+    }
+}
+
+fn animator_entities(scene: &Scene) -> Vec<EntityId> {
+    let mut ids: Vec<EntityId> = scene
+        .entities()
+        .filter(|e| e.has_component::<Animator>())
+        .map(|e| e.id)
+        .collect();
+    ids.sort_by_key(|id| id.0);
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::AnimatedProperty;
+    use glam::Vec4;
+
+    /// A clip holding the entity at height `y`
+    fn hold(y: f32) -> AnimationClip {
+        let mut clip = AnimationClip::new(1.0);
+        clip.set_key(AnimatedProperty::Position, 0.0, Vec4::new(0.0, y, 0.0, 0.0));
+        clip
+    }
+
+    fn character() -> Animator {
+        Animator::new()
+            .with_parameter("speed", AnimatorParameter::Float(0.0))
+            .with_layer(
+                AnimatorLayer::new("Base")
+                    .with_state(AnimatorState::new("Idle", hold(0.0)))
+                    .with_state(AnimatorState::new("Walk", hold(10.0)))
+                    .with_state(AnimatorState::new("Jump", hold(20.0)))
+                    .with_transition(
+                        AnimatorTransition::new(Some("Idle"), "Walk", 0.5).with_condition(
+                            "speed",
+                            ConditionOp::Greater,
+                            0.1,
+                        ),
+                    )
+                    .with_transition(AnimatorTransition::new(None, "Jump", 0.0).with_condition(
+                        "jump",
+                        ConditionOp::IsTrue,
+                        0.0,
+                    )),
+            )
+    }
+
+    #[test]
+    fn test_transitions_blend_and_consume_triggers() {
+        let mut scene = Scene::new("Test".to_string());
+        let id = scene.create_entity("Hero".to_string());
+        scene.get_entity_mut(id).unwrap().add_component(character());
+        start_animators(&mut scene);
+
+        let height = |scene: &Scene| scene.get_entity(id).unwrap().transform.position.y;
+        fn animator(scene: &mut Scene) -> &mut Animator {
+            let id = scene.entities().next().unwrap().id;
+            scene
+                .get_entity_mut(id)
+                .unwrap()
+                .get_component_mut::<Animator>()
+                .unwrap()
+        }
+
+        update_animators(&mut scene, 0.1);
+        assert_eq!(animator(&mut scene).current_state(0), Some("Idle"));
+        assert_eq!(height(&scene), 0.0);
+
+        // Walking fades in over half a second
+        animator(&mut scene).set_float("speed", 1.0);
+        update_animators(&mut scene, 0.1);
+        assert_eq!(animator(&mut scene).current_state(0), Some("Walk"));
+        update_animators(&mut scene, 0.25);
+        assert!((height(&scene) - 5.0).abs() < 1e-4);
+        update_animators(&mut scene, 0.5);
+        assert_eq!(height(&scene), 10.0);
+
+        // A trigger fires its transition once, from any state
+        animator(&mut scene).set_trigger("jump");
+        update_animators(&mut scene, 0.1);
+        assert_eq!(animator(&mut scene).current_state(0), Some("Jump"));
+        assert_eq!(height(&scene), 20.0);
+        assert_eq!(
+            animator(&mut scene).parameters["jump"],
+            AnimatorParameter::Trigger(false)
+        );
+    }
+
+    #[test]
+    fn test_exit_time_waits_for_a_full_loop() {
+        let mut animator = Animator::new().with_layer(
+            AnimatorLayer::new("Base")
+                .with_state(AnimatorState::new("Land", hold(0.0)))
+                .with_state(AnimatorState::new("Idle", hold(1.0)))
+                .with_transition(
+                    AnimatorTransition::new(Some("Land"), "Idle", 0.0).with_exit_time(1.0),
+                ),
+        );
+        animator.advance(0.6);
+        assert_eq!(animator.current_state(0), Some("Land"));
+        // The looping clip has wrapped to 0.2s, but a whole loop has played
+        animator.advance(0.6);
+        assert_eq!(animator.current_state(0), Some("Idle"));
+    }
+
+    #[test]
+    fn test_upper_layer_blends_by_weight() {
+        let animator = Animator::new()
+            .with_layer(
+                AnimatorLayer::new("Base").with_state(AnimatorState::new("Idle", hold(0.0))),
+            )
+            .with_layer(
+                AnimatorLayer::new("Overlay")
+                    .with_weight(0.25)
+                    .with_state(AnimatorState::new("Raise", hold(4.0))),
+            );
+        assert_eq!(animator.pose().position.unwrap().y, 1.0);
+    }
+
+    #[test]
+    fn test_blend_tree_mixes_by_parameter() {
+        let mut walk = hold(2.0);
+        walk.duration = 1.0;
+        let mut run = hold(6.0);
+        run.duration = 0.5;
+        let mut animator = Animator::new()
+            .with_parameter("speed", AnimatorParameter::Float(0.0))
+            .with_layer(
+                AnimatorLayer::new("Base").with_state(AnimatorState::blended(
+                    "Locomotion",
+                    BlendTree::new("speed")
+                        .with_motion(4.0, run)
+                        .with_motion(1.0, walk),
+                )),
+            );
+        animator.reset();
+
+        // Below the lowest threshold the walk plays alone
+        animator.advance(0.1);
+        assert_eq!(animator.pose().position.unwrap().y, 2.0);
+
+        // Halfway from walk to run: half of each, on a cycle between theirs
+        animator.set_float("speed", 2.5);
+        animator.advance(0.1);
+        assert!((animator.pose().position.unwrap().y - 4.0).abs() < 1e-4);
+        assert!((animator.layers[0].states[0].clip.duration - 0.75).abs() < 1e-4);
+
+        animator.set_float("speed", 10.0);
+        animator.advance(0.1);
+        assert_eq!(animator.pose().position.unwrap().y, 6.0);
+    }
+}
@@ -1,6 +1,6 @@
 // Common components for entities
 
-use crate::entity::Component;
+use crate::entity::{Component, EntityId};
 use crate::impl_component;
 use serde::{Deserialize, Serialize};
 use std::any::Any;
@@ -10,6 +10,30 @@ use std::any::Any;
 pub struct MeshRenderer {
     pub mesh_path: String,
     pub material_path: Option<String>,
+    /// Baked ambient occlusion for this instance (static geometry only)
+    #[serde(default)]
+    pub lightmap_path: Option<String>,
+    /// Lower detail meshes swapped in with distance, nearest first
+    #[serde(default)]
+    pub lods: Vec<MeshLod>,
+    /// Distance over which LOD levels dither into each other before a switch
+    #[serde(default = "default_lod_transition")]
+    pub lod_transition: f32,
+}
+
+/// A lower detail mesh used from `distance` away from the camera
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeshLod {
+    pub mesh_path: String,
+    pub distance: f32,
+    /// When set, used instead of `distance`: the share of the view height
+    /// the mesh's bounds cover below which this level draws
+    #[serde(default)]
+    pub screen_size: Option<f32>,
+}
+
+fn default_lod_transition() -> f32 {
+    4.0
 }
 
 impl MeshRenderer {
@@ -17,6 +41,9 @@ impl MeshRenderer {
         Self {
             mesh_path,
             material_path: None,
+            lightmap_path: None,
+            lods: Vec::new(),
+            lod_transition: default_lod_transition(),
         }
     }
 
@@ -24,6 +51,26 @@ impl MeshRenderer {
         self.material_path = Some(material_path);
         self
     }
+
+    pub fn with_lod(mut self, mesh_path: String, distance: f32) -> Self {
+        self.lods.push(MeshLod {
+            mesh_path,
+            distance,
+            screen_size: None,
+        });
+        self.lods.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        self
+    }
+
+    /// Add a LOD drawn once the mesh covers less than `screen_size` of the view height
+    pub fn with_screen_lod(mut self, mesh_path: String, screen_size: f32) -> Self {
+        self.lods.push(MeshLod {
+            mesh_path,
+            distance: 0.0,
+            screen_size: Some(screen_size),
+        });
+        self
+    }
 }
 
 impl_component!(MeshRenderer);
@@ -69,6 +116,13 @@ pub struct Light {
     pub light_type: LightType,
     pub color: [f32; 3],
     pub intensity: f32,
+    /// Whether the light casts shadows (scenes saved without it load as true)
+    #[serde(default = "default_cast_shadows")]
+    pub cast_shadows: bool,
+}
+
+fn default_cast_shadows() -> bool {
+    true
 }
 
 impl Light {
@@ -77,6 +131,7 @@ impl Light {
             light_type: LightType::Directional { direction },
             color,
             intensity,
+            cast_shadows: true,
         }
     }
 
@@ -85,6 +140,7 @@ impl Light {
             light_type: LightType::Point { range, intensity },
             color,
             intensity,
+            cast_shadows: true,
         }
     }
 }
@@ -152,6 +208,166 @@ impl Default for AudioListener {
 
 impl_component!(AudioListener);
 
+/// Reverb zone - reverb and filtering for world sounds while the listener is
+/// inside `radius`, fading out over `blend_distance` past it. Overlapping
+/// zones blend by how far in the listener is.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReverbZone {
+    /// Radius of the full effect around the entity
+    pub radius: f32,
+    /// Distance past the radius over which the effect fades out
+    pub blend_distance: f32,
+    /// Reverb wet level (0 = dry, 1 = all reverb)
+    pub reverb_mix: f32,
+    /// Reverb tail length (0 = small room, 1 = hall)
+    pub room_size: f32,
+    /// How fast the tail loses its highs (0..1)
+    pub damping: f32,
+    /// Low-pass cutoff in Hz (20000 = open; a few hundred sounds underwater)
+    pub low_pass_hz: f32,
+    /// High-pass cutoff in Hz (20 = open)
+    pub high_pass_hz: f32,
+    /// Low shelf, mid and high shelf EQ gains in dB
+    pub eq_db: [f32; 3],
+}
+
+impl ReverbZone {
+    /// A room: moderate reverb, no filtering
+    pub fn indoor() -> Self {
+        Self {
+            radius: 10.0,
+            blend_distance: 3.0,
+            reverb_mix: 0.3,
+            room_size: 0.6,
+            damping: 0.5,
+            low_pass_hz: 20000.0,
+            high_pass_hz: 20.0,
+            eq_db: [0.0; 3],
+        }
+    }
+
+    /// Muffled and boomy, for a listener below the water
+    pub fn underwater() -> Self {
+        Self {
+            reverb_mix: 0.2,
+            room_size: 0.8,
+            damping: 0.9,
+            low_pass_hz: 600.0,
+            eq_db: [4.0, -2.0, -6.0],
+            ..Self::indoor()
+        }
+    }
+
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius.max(0.0);
+        self
+    }
+
+    pub fn with_blend_distance(mut self, blend_distance: f32) -> Self {
+        self.blend_distance = blend_distance.max(0.0);
+        self
+    }
+
+    /// How strongly the zone applies to a listener `distance` from its
+    /// centre: 1 inside the radius, fading to 0 over the blend distance
+    pub fn weight(&self, distance: f32) -> f32 {
+        if distance <= self.radius {
+            1.0
+        } else if self.blend_distance > 0.0 {
+            (1.0 - (distance - self.radius) / self.blend_distance).max(0.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Default for ReverbZone {
+    fn default() -> Self {
+        Self::indoor()
+    }
+}
+
+impl_component!(ReverbZone);
+
+/// Audio occluder - how much sound gets through the entity's colliders. A
+/// sound behind it is quieter by `occlusion` and muffled above
+/// `low_pass_hz`; colliders without one block like `AudioOccluder::default()`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioOccluder {
+    /// Fraction of the sound level blocked (0 = none, 1 = all)
+    pub occlusion: f32,
+    /// Low-pass cutoff in Hz of the sound that gets through
+    pub low_pass_hz: f32,
+}
+
+impl AudioOccluder {
+    /// Solid walls of no material in particular
+    pub fn generic() -> Self {
+        Self {
+            occlusion: 0.6,
+            low_pass_hz: 1200.0,
+        }
+    }
+
+    pub fn wood() -> Self {
+        Self {
+            occlusion: 0.5,
+            low_pass_hz: 1500.0,
+        }
+    }
+
+    pub fn glass() -> Self {
+        Self {
+            occlusion: 0.4,
+            low_pass_hz: 5000.0,
+        }
+    }
+
+    pub fn concrete() -> Self {
+        Self {
+            occlusion: 0.9,
+            low_pass_hz: 500.0,
+        }
+    }
+
+    pub fn metal() -> Self {
+        Self {
+            occlusion: 0.8,
+            low_pass_hz: 900.0,
+        }
+    }
+
+    pub fn fabric() -> Self {
+        Self {
+            occlusion: 0.3,
+            low_pass_hz: 3000.0,
+        }
+    }
+
+    /// Hedges and bushes
+    pub fn foliage() -> Self {
+        Self {
+            occlusion: 0.15,
+            low_pass_hz: 8000.0,
+        }
+    }
+
+    /// Fraction of the sound level let through
+    pub fn transmission(&self) -> f32 {
+        1.0 - self.occlusion.clamp(0.0, 1.0)
+    }
+}
+
+impl Default for AudioOccluder {
+    fn default() -> Self {
+        Self::generic()
+    }
+}
+
+impl_component!(AudioOccluder);
+
 /// Particle emitter component - emits particles with configurable properties
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParticleEmitter {
@@ -263,6 +479,25 @@ pub struct WaterBody {
     pub flow_speed: f32,
 }
 
+/// Circular area kept dry by a TerrainWater fill
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WaterExclusionZone {
+    /// World-space XZ center
+    pub center: [f32; 2],
+    pub radius: f32,
+}
+
+/// Flow vector painted onto terrain water; the water body containing it flows that way
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WaterFlowStroke {
+    /// World-space XZ position
+    pub position: [f32; 2],
+    /// Normalized XZ direction
+    pub direction: [f32; 2],
+    /// Flow speed in units per second
+    pub speed: f32,
+}
+
 /// Terrain-aware water that fills depressions via flood-fill
 /// Water is computed at scene load based on terrain heightmap
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -281,6 +516,12 @@ pub struct TerrainWater {
     pub color: [f32; 3],
     pub transparency: f32,
     pub texture_path: Option<String>,
+    /// Areas where no water is filled
+    #[serde(default)]
+    pub exclusion_zones: Vec<WaterExclusionZone>,
+    /// Painted flow directions, overriding the computed overflow flow
+    #[serde(default)]
+    pub flow_strokes: Vec<WaterFlowStroke>,
     /// Computed water bodies (populated at load time, not serialized)
     #[serde(skip)]
     pub water_bodies: Vec<WaterBody>,
@@ -298,6 +539,8 @@ impl TerrainWater {
             color: [0.2, 0.5, 0.8],
             transparency: 0.6,
             texture_path: Some("water".to_string()),
+            exclusion_zones: Vec::new(),
+            flow_strokes: Vec::new(),
             water_bodies: Vec::new(),
         }
     }
@@ -332,6 +575,10 @@ pub struct TerrainGenerator {
     pub moat_inner_radius: f64,
     pub moat_outer_radius: f64,
     pub moat_depth: f64,
+    /// Imported heightmap the terrain loads instead of the noise, a tile
+    /// file relative to the asset root
+    #[serde(default)]
+    pub heightmap: Option<String>,
 }
 
 impl TerrainGenerator {
@@ -364,12 +611,46 @@ impl Default for TerrainGenerator {
             moat_inner_radius: 0.2,
             moat_outer_radius: 0.35,
             moat_depth: 0.5,
+            heightmap: None,
         }
     }
 }
 
 impl_component!(TerrainGenerator);
 
+/// Terrain streaming component - an open world made of heightmap tiles on disk
+/// Tiles load around the camera and unload behind it; tile files are
+/// `tile_X_Z.height` in `directory`, relative to the asset root.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerrainStreaming {
+    pub directory: String,
+    /// Height samples along each tile edge
+    pub tile_resolution: usize,
+    /// World units along each tile edge
+    pub tile_size: f32,
+    /// Tiles within this distance of the camera are loaded
+    pub load_radius: f32,
+    /// Most tiles held in memory at once
+    pub max_loaded_tiles: usize,
+    pub material_path: Option<String>,
+}
+
+impl Default for TerrainStreaming {
+    fn default() -> Self {
+        Self {
+            directory: "terrain/world".to_string(),
+            tile_resolution: 65,
+            tile_size: 64.0,
+            load_radius: 160.0,
+            max_loaded_tiles: 25,
+            material_path: Some("materials/grass.mat".to_string()),
+        }
+    }
+}
+
+impl_component!(TerrainStreaming);
+
 /// A single foliage instance (tree, bush, etc.)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FoliageInstance {
@@ -413,6 +694,41 @@ pub struct Foliage {
     pub cast_shadows: bool,
     /// Color tint for the foliage (multiplied with mesh colors)
     pub color_tint: [f32; 3],
+    /// Distance from the camera at which instances have fully faded out
+    #[serde(default = "default_foliage_draw_distance")]
+    pub draw_distance: f32,
+    /// Distance over which instances dither out before `draw_distance`
+    #[serde(default = "default_foliage_fade_width")]
+    pub fade_width: f32,
+    /// Distance from the camera at which instances switch to the low detail mesh
+    #[serde(default = "default_foliage_lod_distance")]
+    pub lod_distance: f32,
+    /// Distance from the camera at which instances switch to billboard impostors
+    #[serde(default = "default_foliage_impostor_distance")]
+    pub impostor_distance: f32,
+    /// How much the foliage resists bending in the wind (1 = a typical tree)
+    #[serde(default = "default_foliage_stiffness")]
+    pub stiffness: f32,
+}
+
+fn default_foliage_draw_distance() -> f32 {
+    200.0
+}
+
+fn default_foliage_fade_width() -> f32 {
+    25.0
+}
+
+fn default_foliage_lod_distance() -> f32 {
+    40.0
+}
+
+fn default_foliage_impostor_distance() -> f32 {
+    90.0
+}
+
+fn default_foliage_stiffness() -> f32 {
+    1.0
 }
 
 impl Foliage {
@@ -422,6 +738,11 @@ impl Foliage {
             instances: Vec::new(),
             cast_shadows: true,
             color_tint: [1.0, 1.0, 1.0],
+            draw_distance: default_foliage_draw_distance(),
+            fade_width: default_foliage_fade_width(),
+            lod_distance: default_foliage_lod_distance(),
+            impostor_distance: default_foliage_impostor_distance(),
+            stiffness: default_foliage_stiffness(),
         }
     }
 
@@ -454,3 +775,229 @@ impl Default for Foliage {
 }
 
 impl_component!(Foliage);
+
+/// Network replication component - a server sends the entity's transform
+/// (and, optionally, its other components) to connected clients
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replicated {
+    /// Client that controls the entity (None = the server)
+    #[serde(default)]
+    pub owner: Option<u32>,
+    /// Sent to every client, however far away it is
+    #[serde(default)]
+    pub always_relevant: bool,
+    /// Replicate serializable components along with the transform
+    #[serde(default = "default_replicate_components")]
+    pub components: bool,
+    /// How clients show the entity between snapshots
+    #[serde(default)]
+    pub smoothing: NetSmoothing,
+}
+
+/// How a client moves a replicated entity between the server's snapshots
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NetSmoothing {
+    /// Jump to each snapshot as it arrives
+    Snap,
+    /// Play snapshots back slightly in the past, blending between them
+    #[default]
+    Interpolate,
+    /// The owning client moves it from its own input straight away and
+    /// corrects from the server (needs a CharacterController); other
+    /// clients interpolate it
+    Predict,
+}
+
+fn default_replicate_components() -> bool {
+    true
+}
+
+impl Replicated {
+    pub fn new() -> Self {
+        Self {
+            owner: None,
+            always_relevant: false,
+            components: true,
+            smoothing: NetSmoothing::Interpolate,
+        }
+    }
+
+    pub fn with_owner(mut self, owner: u32) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    pub fn with_always_relevant(mut self, always_relevant: bool) -> Self {
+        self.always_relevant = always_relevant;
+        self
+    }
+
+    pub fn with_smoothing(mut self, smoothing: NetSmoothing) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+}
+
+impl Default for Replicated {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl_component!(Replicated);
+
+/// How a RagdollRig's bones are currently driven
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RagdollMode {
+    /// Bones follow animation; their colliders are kinematic
+    #[default]
+    Animated,
+    /// Every bone is simulated
+    Full,
+    /// The upper body is simulated while the legs stay animated
+    UpperBody,
+    /// Blending from the last ragdoll pose back to animation
+    GettingUp,
+}
+
+/// Ragdoll built from a character's bone hierarchy. Each bone from
+/// `root_bone` down gets a collider reaching to its first child, jointed to
+/// its parent bone's collider. The physics ragdoll system switches the bones
+/// between animation and simulation when a mode is requested.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagdollRig {
+    /// First bone of the skeleton, usually the hips
+    pub root_bone: Option<EntityId>,
+    /// Bone the upper body starts at (usually the spine), for partial ragdolls
+    #[serde(default)]
+    pub upper_body_bone: Option<EntityId>,
+    /// Mass shared between the bones by length
+    pub total_mass: f32,
+    /// Collider radius as a fraction of bone length
+    pub thickness: f32,
+    /// Seconds to blend back to animation when getting up
+    pub blend_time: f32,
+    /// Contact impulse that knocks the character into full ragdoll (0 = never)
+    #[serde(default)]
+    pub impact_threshold: f32,
+    #[serde(skip)]
+    pub mode: RagdollMode,
+    #[serde(skip)]
+    pub requested: Option<RagdollMode>,
+}
+
+impl RagdollRig {
+    pub fn new(root_bone: EntityId) -> Self {
+        Self {
+            root_bone: Some(root_bone),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_upper_body(mut self, bone: EntityId) -> Self {
+        self.upper_body_bone = Some(bone);
+        self
+    }
+
+    pub fn with_mass(mut self, total_mass: f32) -> Self {
+        self.total_mass = total_mass;
+        self
+    }
+
+    pub fn with_impact_threshold(mut self, impulse: f32) -> Self {
+        self.impact_threshold = impulse;
+        self
+    }
+
+    /// Go fully limp, e.g. on death
+    pub fn activate(&mut self) {
+        self.requested = Some(RagdollMode::Full);
+    }
+
+    /// Simulate only the upper body, e.g. for hit reactions
+    pub fn activate_upper_body(&mut self) {
+        self.requested = Some(RagdollMode::UpperBody);
+    }
+
+    /// Blend back from the ragdoll pose to animation
+    pub fn get_up(&mut self) {
+        self.requested = Some(RagdollMode::GettingUp);
+    }
+
+    /// Whether any bones are currently simulated
+    pub fn is_ragdoll(&self) -> bool {
+        matches!(self.mode, RagdollMode::Full | RagdollMode::UpperBody)
+    }
+}
+
+impl Default for RagdollRig {
+    fn default() -> Self {
+        Self {
+            root_bone: None,
+            upper_body_bone: None,
+            total_mass: 70.0,
+            thickness: 0.25,
+            blend_time: 0.6,
+            impact_threshold: 0.0,
+            mode: RagdollMode::Animated,
+            requested: None,
+        }
+    }
+}
+
+impl_component!(RagdollRig);
+
+/// Runs a behavior tree asset (see engine_ai_behavior) on the entity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BehaviorAgent {
+    /// Path of the tree's RON file
+    pub tree: String,
+    pub enabled: bool,
+    /// Nodes from the root down to the one that ran last tick, for debugging
+    #[serde(skip)]
+    pub active_branch: Vec<String>,
+}
+
+impl BehaviorAgent {
+    pub fn new(tree: impl Into<String>) -> Self {
+        Self {
+            tree: tree.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for BehaviorAgent {
+    fn default() -> Self {
+        Self {
+            tree: String::new(),
+            enabled: true,
+            active_branch: Vec::new(),
+        }
+    }
+}
+
+impl_component!(BehaviorAgent);
+
+/// Mesh deformed by a skeleton imported from a glTF skin (see
+/// engine_animation). Each node of the skeleton is a child entity the
+/// Animator's skeletal clips, IK and ragdolls move like any other entity;
+/// the renderer skins the mesh to where they are.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SkinnedMesh {
+    /// glTF file with the skin, meshes and animations
+    pub model_path: String,
+    /// Entity of each skeleton node, in the model's skeleton order
+    pub bones: Vec<EntityId>,
+}
+
+impl SkinnedMesh {
+    pub fn new(model_path: impl Into<String>, bones: Vec<EntityId>) -> Self {
+        Self {
+            model_path: model_path.into(),
+            bones,
+        }
+    }
+}
+
+impl_component!(SkinnedMesh);
@@ -14,8 +14,9 @@ impl EntityId {
     }
 }
 
-/// Component trait - all components must implement this
-pub trait Component: Any {
+/// Component trait - all components must implement this. Components are
+/// plain data, so scenes can be read from several threads at once.
+pub trait Component: Any + Send + Sync {
     fn as_any(&self) -> &dyn Any;
     fn as_any_mut(&mut self) -> &mut dyn Any;
     fn clone_box(&self) -> Box<dyn Component>;
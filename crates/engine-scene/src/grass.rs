@@ -0,0 +1,214 @@
+// Grass - blades grown on the terrain from a painted density map
+//
+// The density map is a square grid over the terrain, centered on the origin
+// like the heightmap. Blades aren't stored: the renderer grows them on the
+// GPU each frame around the camera, as many as the density allows.
+
+use crate::entity::{Component, EntityId};
+use crate::impl_component;
+use crate::scene::Scene;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Source of density map generations, unique across every Grass
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+fn next_generation() -> u64 {
+    NEXT_GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Grass {
+    /// World size of the square the density map covers
+    pub size: f32,
+    /// Density map cells per side
+    pub resolution: usize,
+    /// Coverage per cell (0-255), row-major
+    pub density: Vec<u8>,
+    /// Blades per square meter at full coverage
+    pub blades_per_m2: f32,
+    pub blade_height: f32,
+    pub blade_width: f32,
+    pub base_color: [f32; 3],
+    pub tip_color: [f32; 3],
+    /// Distance from the camera at which blades are gone
+    pub draw_distance: f32,
+    /// Distance over which blades shrink away before `draw_distance`
+    pub fade_width: f32,
+    /// How much the blades resist bending in the wind
+    pub stiffness: f32,
+    /// Changes whenever the density map does, so the renderer knows when to
+    /// upload it again. Not saved; a loaded map gets a new one.
+    #[serde(skip, default = "next_generation")]
+    generation: u64,
+}
+
+impl Default for Grass {
+    fn default() -> Self {
+        Self::new(100.0, 128)
+    }
+}
+
+impl_component!(Grass);
+
+impl Grass {
+    /// Grass with an empty density map of `resolution` cells per side
+    pub fn new(size: f32, resolution: usize) -> Self {
+        Self {
+            size,
+            resolution,
+            density: vec![0; resolution * resolution],
+            blades_per_m2: 16.0,
+            blade_height: 0.6,
+            blade_width: 0.05,
+            base_color: [0.13, 0.3, 0.06],
+            tip_color: [0.45, 0.62, 0.2],
+            draw_distance: 60.0,
+            fade_width: 15.0,
+            stiffness: 0.3,
+            generation: next_generation(),
+        }
+    }
+
+    /// Identifies this version of the density map. Copies share it until
+    /// one of them is painted.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// World position to fractional cell coordinates
+    fn world_to_cell(&self, world_x: f32, world_z: f32) -> (f32, f32) {
+        let cells = self.resolution as f32;
+        (
+            (world_x / self.size + 0.5) * cells - 0.5,
+            (world_z / self.size + 0.5) * cells - 0.5,
+        )
+    }
+
+    fn cell(&self, x: i32, z: i32) -> f32 {
+        let r = self.resolution as i32;
+        if x < 0 || z < 0 || x >= r || z >= r {
+            return 0.0;
+        }
+        self.density
+            .get((z * r + x) as usize)
+            .map_or(0.0, |&d| d as f32 / 255.0)
+    }
+
+    /// Coverage at a world position, 0..1, blended between cells
+    pub fn density_at(&self, world_x: f32, world_z: f32) -> f32 {
+        let (x, z) = self.world_to_cell(world_x, world_z);
+        let (x0, z0) = (x.floor(), z.floor());
+        let (fx, fz) = (x - x0, z - z0);
+        let (x0, z0) = (x0 as i32, z0 as i32);
+        let top = self.cell(x0, z0) * (1.0 - fx) + self.cell(x0 + 1, z0) * fx;
+        let bottom = self.cell(x0, z0 + 1) * (1.0 - fx) + self.cell(x0 + 1, z0 + 1) * fx;
+        top * (1.0 - fz) + bottom * fz
+    }
+
+    /// Move coverage toward `target` (0..1) within `radius` of a world
+    /// position, by `opacity` at the center fading to nothing at the edge.
+    /// Returns true if any cells changed.
+    pub fn paint(
+        &mut self,
+        world_x: f32,
+        world_z: f32,
+        radius: f32,
+        target: f32,
+        opacity: f32,
+    ) -> bool {
+        let cells = self.resolution * self.resolution;
+        if self.density.len() != cells {
+            self.density.resize(cells, 0);
+        }
+
+        let (center_x, center_z) = self.world_to_cell(world_x, world_z);
+        let cell_radius = radius / self.size * self.resolution as f32;
+        let max = self.resolution as i32 - 1;
+        let min_x = ((center_x - cell_radius).floor() as i32).max(0);
+        let max_x = ((center_x + cell_radius).ceil() as i32).min(max);
+        let min_z = ((center_z - cell_radius).floor() as i32).max(0);
+        let max_z = ((center_z + cell_radius).ceil() as i32).min(max);
+
+        let target = target.clamp(0.0, 1.0) * 255.0;
+        let mut modified = false;
+        for z in min_z..=max_z {
+            for x in min_x..=max_x {
+                let distance = (x as f32 - center_x).hypot(z as f32 - center_z);
+                if distance > cell_radius {
+                    continue;
+                }
+                let edge = 1.0 - distance / cell_radius.max(f32::EPSILON);
+                let amount = (opacity * edge * edge).clamp(0.0, 1.0);
+
+                let index = z as usize * self.resolution + x as usize;
+                let current = self.density[index];
+                let mut next = (current as f32 + (target - current as f32) * amount).round() as u8;
+                // Move at least one step so low opacities still reach the target
+                if next == current && amount > 0.0 && target != current as f32 {
+                    next = if target > current as f32 {
+                        current + 1
+                    } else {
+                        current - 1
+                    };
+                }
+                if next != current {
+                    self.density[index] = next;
+                    modified = true;
+                }
+            }
+        }
+        if modified {
+            self.generation = next_generation();
+        }
+        modified
+    }
+}
+
+/// The scene's grass, if it has any. With several the lowest entity id wins.
+pub fn find_grass(scene: &Scene) -> Option<(EntityId, &Grass)> {
+    scene
+        .entities()
+        .filter_map(|e| e.get_component::<Grass>().map(|g| (e.id, g)))
+        .min_by_key(|(id, _)| id.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paint_and_erase_density() {
+        let mut grass = Grass::new(100.0, 100);
+        assert_eq!(grass.density_at(0.0, 0.0), 0.0);
+
+        // Paint until full around the origin
+        for _ in 0..20 {
+            grass.paint(0.0, 0.0, 10.0, 1.0, 0.5);
+        }
+        assert!(grass.density_at(0.0, 0.0) > 0.99);
+        assert!(grass.density_at(4.0, 0.0) > 0.0);
+        assert_eq!(grass.density_at(20.0, 0.0), 0.0);
+        // Off the map is bare
+        assert_eq!(grass.density_at(80.0, 0.0), 0.0);
+
+        // Painting the same value again changes nothing
+        let generation = grass.generation();
+        assert!(!grass.paint(30.0, 30.0, 5.0, 0.0, 1.0));
+        assert_eq!(grass.generation(), generation);
+
+        for _ in 0..20 {
+            grass.paint(0.0, 0.0, 10.0, 0.0, 0.5);
+        }
+        assert_eq!(grass.density_at(0.0, 0.0), 0.0);
+        assert_ne!(grass.generation(), generation);
+
+        // A loaded map never passes for the one it was saved from
+        let saved = ron::ser::to_string(&grass).unwrap();
+        let loaded: Grass = ron::de::from_str(&saved).unwrap();
+        assert_eq!(loaded.density, grass.density);
+        assert_ne!(loaded.generation(), grass.generation());
+    }
+}
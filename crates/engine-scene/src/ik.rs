@@ -0,0 +1,558 @@
+// Inverse kinematics - two-bone legs, foot placement and look-at
+//
+// Bones are entities in a parent/child chain (thigh → shin → foot). After
+// the animation system has posed them, IK bends them toward targets:
+// FootPlacement probes the ground under each foot, lowers the pelvis so the
+// lower foot can reach and solves each leg with the two-bone solver, so feet
+// rest on slopes and steps; LookAt turns a head or an eye toward a target
+// within an angle limit. Each frame the solvers start again from the pose
+// the bones had before IK (the animated pose, or the rest pose of bones
+// nothing animates), so solutions never accumulate.
+
+use crate::entity::{Component, EntityId};
+use crate::impl_component;
+use crate::scene::Scene;
+use glam::{Quat, Vec3};
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+
+/// Lengths and angles below this are treated as zero
+const EPSILON: f32 = 1.0e-5;
+
+/// A value IK overwrote: what it was before and what was written, so the
+/// next frame can tell whether animation has set it since
+type Written<T> = Option<(T, T)>;
+
+/// The pose to solve from: the value before IK, unless something else has
+/// written the value since
+fn base_of<T: Copy + PartialEq>(written: &Written<T>, current: T) -> T {
+    match written {
+        Some((base, output)) if *output == current => *base,
+        _ => current,
+    }
+}
+
+/// Two-bone IK: rotations that bring the chain's `end` as close to `target`
+/// as the bone lengths allow. The chain keeps bending in its current plane,
+/// or toward `pole` when straight. Returns world-space rotations for the
+/// upper bone (about `root`) and the lower bone (about `mid`): their new
+/// world rotations are `upper * upper_world` and `lower * lower_world`.
+pub fn solve_two_bone(root: Vec3, mid: Vec3, end: Vec3, target: Vec3, pole: Vec3) -> (Quat, Quat) {
+    let upper_len = (mid - root).length();
+    let lower_len = (end - mid).length();
+    if upper_len < EPSILON || lower_len < EPSILON {
+        return (Quat::IDENTITY, Quat::IDENTITY);
+    }
+    let reach = (target - root).length().clamp(
+        (upper_len - lower_len).abs() + EPSILON,
+        upper_len + lower_len - EPSILON,
+    );
+
+    // Bend the middle joint so the chain spans `reach`
+    let to_root = (root - mid) / upper_len;
+    let to_end = (end - mid) / lower_len;
+    let angle = to_root.dot(to_end).clamp(-1.0, 1.0).acos();
+    let wanted = ((upper_len * upper_len + lower_len * lower_len - reach * reach)
+        / (2.0 * upper_len * lower_len))
+        .clamp(-1.0, 1.0)
+        .acos();
+    let axis = to_root
+        .cross(to_end)
+        .try_normalize()
+        .or_else(|| to_end.cross(pole).try_normalize());
+    let bend = axis.map_or(Quat::IDENTITY, |axis| {
+        Quat::from_axis_angle(axis, wanted - angle)
+    });
+
+    // Swing the whole chain so its end points at the target
+    let bent_end = mid + bend * (end - mid);
+    let swing = match (
+        (bent_end - root).try_normalize(),
+        (target - root).try_normalize(),
+    ) {
+        (Some(from), Some(to)) => Quat::from_rotation_arc(from, to),
+        _ => Quat::IDENTITY,
+    };
+    (swing, swing * bend)
+}
+
+/// World rotation turning `rotation`'s local `forward` axis from `eye`
+/// toward `target`, by at most `max_angle` radians and `weight` of the way
+pub fn look_rotation(
+    rotation: Quat,
+    forward: Vec3,
+    eye: Vec3,
+    target: Vec3,
+    max_angle: f32,
+    weight: f32,
+) -> Quat {
+    let (Some(facing), Some(wanted)) = (
+        (rotation * forward).try_normalize(),
+        (target - eye).try_normalize(),
+    ) else {
+        return rotation;
+    };
+    let Some(axis) = facing.cross(wanted).try_normalize() else {
+        return rotation;
+    };
+    let angle = facing.angle_between(wanted).min(max_angle.max(0.0)) * weight.clamp(0.0, 1.0);
+    (Quat::from_axis_angle(axis, angle) * rotation).normalize()
+}
+
+/// Turns its entity (a head, an eye) to face a target
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LookAt {
+    /// Entity to look at; `point` when None or gone
+    pub target: Option<EntityId>,
+    /// World position to look at without a target entity
+    pub point: Vec3,
+    /// Local axis the entity looks along
+    pub forward: Vec3,
+    /// Largest turn away from the animated pose, in degrees
+    pub max_angle: f32,
+    /// 0 keeps the animated pose, 1 faces the target
+    pub weight: f32,
+    #[serde(skip)]
+    written: Written<Quat>,
+}
+
+impl Default for LookAt {
+    fn default() -> Self {
+        Self {
+            target: None,
+            point: Vec3::ZERO,
+            forward: Vec3::Z,
+            max_angle: 70.0,
+            weight: 1.0,
+            written: None,
+        }
+    }
+}
+
+impl LookAt {
+    pub fn at_entity(target: EntityId) -> Self {
+        Self {
+            target: Some(target),
+            ..Default::default()
+        }
+    }
+}
+
+impl_component!(LookAt);
+
+/// One leg: three bones, each the parent of the next
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegIk {
+    /// Thigh, rotating at the hip
+    pub upper: EntityId,
+    /// Shin, rotating at the knee
+    pub lower: EntityId,
+    /// Foot, at the ankle
+    pub foot: EntityId,
+    /// Direction the knee bends toward, in the character's space
+    #[serde(default = "default_pole")]
+    pub pole: Vec3,
+    #[serde(skip)]
+    written: [Written<Quat>; 3],
+}
+
+fn default_pole() -> Vec3 {
+    Vec3::Z
+}
+
+impl LegIk {
+    pub fn new(upper: EntityId, lower: EntityId, foot: EntityId) -> Self {
+        Self {
+            upper,
+            lower,
+            foot,
+            pole: default_pole(),
+            written: [None; 3],
+        }
+    }
+
+    fn bones(&self) -> [EntityId; 3] {
+        [self.upper, self.lower, self.foot]
+    }
+}
+
+/// Keeps a character's feet on the ground. Goes on the character's root
+/// entity; the animation is taken to be made on flat ground at the root's
+/// height (less `ground_offset`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FootPlacement {
+    pub legs: Vec<LegIk>,
+    /// Lowered so the lower foot can reach the ground
+    pub pelvis: Option<EntityId>,
+    /// How far below the root's origin the animation's ground is, e.g. half
+    /// the height of a capsule centred on the root
+    pub ground_offset: f32,
+    /// Probe the ground from this far above each foot...
+    pub probe_height: f32,
+    /// ...down to this far below it
+    pub probe_depth: f32,
+    /// Most the pelvis is lowered
+    pub max_pelvis_drop: f32,
+    /// Tilt the feet to the slope of the ground
+    pub align_feet: bool,
+    /// 0 keeps the animated pose, 1 plants the feet
+    pub weight: f32,
+    #[serde(skip)]
+    pelvis_written: Written<Vec3>,
+}
+
+impl Default for FootPlacement {
+    fn default() -> Self {
+        Self {
+            legs: Vec::new(),
+            pelvis: None,
+            ground_offset: 0.0,
+            probe_height: 0.5,
+            probe_depth: 0.75,
+            max_pelvis_drop: 0.5,
+            align_feet: true,
+            weight: 1.0,
+            pelvis_written: None,
+        }
+    }
+}
+
+impl FootPlacement {
+    pub fn with_leg(mut self, leg: LegIk) -> Self {
+        self.legs.push(leg);
+        self
+    }
+
+    pub fn with_pelvis(mut self, pelvis: EntityId) -> Self {
+        self.pelvis = Some(pelvis);
+        self
+    }
+}
+
+impl_component!(FootPlacement);
+
+/// IK system: plant feet, then turn heads and eyes. `ground` casts a ray
+/// straight down from a point for a distance, ignoring the given entities
+/// (the character's own colliders), and returns the hit point and normal.
+pub fn update_ik(
+    scene: &mut Scene,
+    ground: impl FnMut(Vec3, f32, &[EntityId]) -> Option<(Vec3, Vec3)>,
+) {
+    update_foot_placement(scene, ground);
+    update_look_at(scene);
+}
+
+/// IK system: solve every FootPlacement's legs
+pub fn update_foot_placement(
+    scene: &mut Scene,
+    mut ground: impl FnMut(Vec3, f32, &[EntityId]) -> Option<(Vec3, Vec3)>,
+) {
+    for id in entities_with::<FootPlacement>(scene) {
+        let Some(mut placement) = scene
+            .get_entity(id)
+            .and_then(|e| e.get_component::<FootPlacement>())
+            .cloned()
+        else {
+            continue;
+        };
+        place_feet(scene, id, &mut placement, &mut ground);
+        if let Some(component) = scene
+            .get_entity_mut(id)
+            .and_then(|e| e.get_component_mut::<FootPlacement>())
+        {
+            *component = placement;
+        }
+    }
+}
+
+fn place_feet(
+    scene: &mut Scene,
+    character: EntityId,
+    placement: &mut FootPlacement,
+    ground: &mut impl FnMut(Vec3, f32, &[EntityId]) -> Option<(Vec3, Vec3)>,
+) {
+    // Back to the pose from before IK
+    let pelvis_base = placement.pelvis.and_then(|pelvis| {
+        let transform = &mut scene.get_entity_mut(pelvis)?.transform;
+        transform.position = base_of(&placement.pelvis_written, transform.position);
+        Some(transform.position)
+    });
+    for leg in &placement.legs {
+        for (bone, written) in leg.bones().into_iter().zip(&leg.written) {
+            if let Some(entity) = scene.get_entity_mut(bone) {
+                entity.transform.rotation = base_of(written, entity.transform.rotation);
+            }
+        }
+    }
+
+    // How far the ground under each foot is above or below the ground the
+    // animation was made on
+    let weight = placement.weight.clamp(0.0, 1.0);
+    let (_, character_rotation, character_position) = scene
+        .world_matrix(character)
+        .to_scale_rotation_translation();
+    let animated_ground = character_position.y - placement.ground_offset;
+    let ignore = subtree(scene, character);
+    let probes: Vec<Option<(f32, Vec3, Vec3)>> = placement
+        .legs
+        .iter()
+        .map(|leg| {
+            let foot = world_position(scene, leg.foot);
+            let origin = foot + Vec3::Y * placement.probe_height;
+            ground(
+                origin,
+                placement.probe_height + placement.probe_depth,
+                &ignore,
+            )
+            .map(|(point, normal)| ((point.y - animated_ground) * weight, foot, normal))
+        })
+        .collect();
+
+    // Lower the pelvis by the lowest foot's drop
+    placement.pelvis_written = None;
+    let drop = probes
+        .iter()
+        .flatten()
+        .map(|(offset, _, _)| *offset)
+        .fold(0.0f32, f32::min)
+        .max(-placement.max_pelvis_drop.abs());
+    if let (Some(pelvis), Some(base)) = (placement.pelvis, pelvis_base) {
+        let parent = scene.get_entity(pelvis).and_then(|e| e.parent);
+        let local_drop = parent.map_or(Vec3::Y * drop, |parent| {
+            scene
+                .world_matrix(parent)
+                .inverse()
+                .transform_vector3(Vec3::Y * drop)
+        });
+        if let Some(entity) = scene.get_entity_mut(pelvis) {
+            entity.transform.position = base + local_drop;
+            placement.pelvis_written = Some((base, entity.transform.position));
+        }
+    }
+
+    for (leg, probe) in placement.legs.iter_mut().zip(probes) {
+        leg.written = [None; 3];
+        let Some((offset, foot, normal)) = probe else {
+            continue;
+        };
+        let bones = leg.bones();
+        let Some(bases) = bones
+            .iter()
+            .map(|&bone| scene.get_entity(bone).map(|e| e.transform.rotation))
+            .collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+        let target = foot + Vec3::Y * offset;
+        let rotations = bones.map(|bone| world_rotation(scene, bone));
+        let (upper, lower) = solve_two_bone(
+            world_position(scene, leg.upper),
+            world_position(scene, leg.lower),
+            world_position(scene, leg.foot),
+            target,
+            character_rotation * leg.pole,
+        );
+        let tilt = if placement.align_feet {
+            Quat::IDENTITY.slerp(Quat::from_rotation_arc(Vec3::Y, normal.normalize()), weight)
+        } else {
+            Quat::IDENTITY
+        };
+        let solved = [
+            upper * rotations[0],
+            lower * rotations[1],
+            tilt * rotations[2],
+        ];
+        for (i, (bone, rotation)) in bones.into_iter().zip(solved).enumerate() {
+            if let Some(local) = set_world_rotation(scene, bone, rotation) {
+                leg.written[i] = Some((bases[i], local));
+            }
+        }
+    }
+}
+
+/// IK system: turn every LookAt toward its target, parents before their
+/// children so an eye turns from where its head ends up
+pub fn update_look_at(scene: &mut Scene) {
+    let mut ids = entities_with::<LookAt>(scene);
+    ids.sort_by_key(|&id| depth(scene, id));
+    for id in ids {
+        let Some(entity) = scene.get_entity_mut(id) else {
+            continue;
+        };
+        let Some(mut look) = entity.get_component::<LookAt>().cloned() else {
+            continue;
+        };
+        let base = base_of(&look.written, entity.transform.rotation);
+        entity.transform.rotation = base;
+
+        let target = look
+            .target
+            .filter(|&target| scene.get_entity(target).is_some())
+            .map_or(look.point, |target| world_position(scene, target));
+        let rotation = look_rotation(
+            world_rotation(scene, id),
+            look.forward,
+            world_position(scene, id),
+            target,
+            look.max_angle.to_radians(),
+            look.weight,
+        );
+        look.written = set_world_rotation(scene, id, rotation).map(|local| (base, local));
+        if let Some(component) = scene
+            .get_entity_mut(id)
+            .and_then(|e| e.get_component_mut::<LookAt>())
+        {
+            *component = look;
+        }
+    }
+}
+
+fn world_position(scene: &Scene, id: EntityId) -> Vec3 {
+    scene.world_matrix(id).w_axis.truncate()
+}
+
+fn world_rotation(scene: &Scene, id: EntityId) -> Quat {
+    scene.world_matrix(id).to_scale_rotation_translation().1
+}
+
+/// Rotate an entity so its world rotation is `rotation`. Returns the new
+/// local rotation.
+fn set_world_rotation(scene: &mut Scene, id: EntityId, rotation: Quat) -> Option<Quat> {
+    let parent = scene.get_entity(id)?.parent;
+    let parent_rotation = parent.map_or(Quat::IDENTITY, |parent| world_rotation(scene, parent));
+    let local = (parent_rotation.inverse() * rotation).normalize();
+    scene.get_entity_mut(id)?.transform.rotation = local;
+    Some(local)
+}
+
+/// An entity and all its descendants
+fn subtree(scene: &Scene, id: EntityId) -> Vec<EntityId> {
+    let mut ids = vec![id];
+    let mut i = 0;
+    while i < ids.len() {
+        if let Some(entity) = scene.get_entity(ids[i]) {
+            ids.extend(&entity.children);
+        }
+        i += 1;
+    }
+    ids
+}
+
+fn depth(scene: &Scene, id: EntityId) -> usize {
+    let mut depth = 0;
+    let mut parent = scene.get_entity(id).and_then(|e| e.parent);
+    while let Some(id) = parent {
+        depth += 1;
+        parent = scene.get_entity(id).and_then(|e| e.parent);
+    }
+    depth
+}
+
+fn entities_with<T: Component>(scene: &Scene) -> Vec<EntityId> {
+    let mut ids: Vec<EntityId> = scene
+        .entities()
+        .filter(|e| e.has_component::<T>())
+        .map(|e| e.id)
+        .collect();
+    ids.sort_by_key(|id| id.0);
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::Transform;
+
+    #[test]
+    fn test_two_bone_reaches_target_and_look_turns() {
+        let (root, mid, end) = (
+            Vec3::new(0.0, 2.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.2),
+            Vec3::ZERO,
+        );
+        let solve = |target: Vec3| {
+            let (upper, lower) = solve_two_bone(root, mid, end, target, Vec3::Z);
+            let new_mid = root + upper * (mid - root);
+            new_mid + lower * (end - mid)
+        };
+        let target = Vec3::new(0.3, 0.6, 0.4);
+        assert!(solve(target).distance(target) < 1e-3);
+
+        // Out of reach: stretch straight toward it
+        let far = Vec3::new(0.0, -5.0, 0.0);
+        let reached = solve(far);
+        assert!((reached - root).normalize().dot((far - root).normalize()) > 0.999);
+        assert!((reached - root).length() <= (mid - root).length() + (end - mid).length() + 1e-3);
+
+        // Look-at turns toward the target, up to the limit
+        let eye = Vec3::ZERO;
+        let full = look_rotation(
+            Quat::IDENTITY,
+            Vec3::Z,
+            eye,
+            Vec3::X,
+            90f32.to_radians(),
+            1.0,
+        );
+        assert!((full * Vec3::Z).distance(Vec3::X) < 1e-4);
+        let limited = look_rotation(
+            Quat::IDENTITY,
+            Vec3::Z,
+            eye,
+            Vec3::X,
+            30f32.to_radians(),
+            1.0,
+        );
+        assert!(((limited * Vec3::Z).angle_between(Vec3::Z) - 30f32.to_radians()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_feet_plant_on_uneven_ground() {
+        let mut scene = Scene::new("Test".to_string());
+        let character = scene.create_entity("Character".to_string());
+        let pelvis = scene.create_entity_with_transform(
+            "Pelvis".to_string(),
+            Transform::from_position(Vec3::new(0.0, 1.0, 0.0)),
+        );
+        scene.set_parent(pelvis, Some(character));
+        let mut placement = FootPlacement::default().with_pelvis(pelvis);
+        for side in [-0.2f32, 0.2] {
+            let upper = scene.create_entity_with_transform(
+                "Thigh".to_string(),
+                Transform::from_position(Vec3::new(side, 0.0, 0.0)),
+            );
+            let lower = scene.create_entity_with_transform(
+                "Shin".to_string(),
+                Transform::from_position(Vec3::new(0.0, -0.5, 0.05)),
+            );
+            let foot = scene.create_entity_with_transform(
+                "Foot".to_string(),
+                Transform::from_position(Vec3::new(0.0, -0.5, -0.05)),
+            );
+            scene.set_parent(upper, Some(pelvis));
+            scene.set_parent(lower, Some(upper));
+            scene.set_parent(foot, Some(lower));
+            placement = placement.with_leg(LegIk::new(upper, lower, foot));
+        }
+        let feet: Vec<EntityId> = placement.legs.iter().map(|leg| leg.foot).collect();
+        scene
+            .get_entity_mut(character)
+            .unwrap()
+            .add_component(placement);
+
+        // A step: the left foot's ground is 0.2 lower, the right's 0.1 higher
+        let ground = |origin: Vec3, _length: f32, ignore: &[EntityId]| {
+            assert!(ignore.contains(&character));
+            let height = if origin.x < 0.0 { -0.2 } else { 0.1 };
+            Some((Vec3::new(origin.x, height, origin.z), Vec3::Y))
+        };
+        for _ in 0..3 {
+            update_foot_placement(&mut scene, ground);
+            assert!((world_position(&scene, feet[0]).y + 0.2).abs() < 1e-3);
+            assert!((world_position(&scene, feet[1]).y - 0.1).abs() < 1e-3);
+            assert!((scene.get_entity(pelvis).unwrap().transform.position.y - 0.8).abs() < 1e-4);
+        }
+    }
+}
@@ -1,13 +1,31 @@
 // Engine Scene - Scene graph and entity system
 
+pub mod animation;
+pub mod animator;
 pub mod components;
 pub mod entity;
+pub mod grass;
+pub mod ik;
+pub mod navigation;
 pub mod scene;
 pub mod scene_data;
+pub mod spline;
+pub mod time_of_day;
 pub mod transform;
+pub mod validation;
+pub mod wind;
 
-pub use components::{Camera as CameraComponent, Light, LightType, MeshRenderer, TerrainWater, Water, WaterBody};
+pub use animation::{AnimatedProperty, AnimationClip};
+pub use animator::{Animator, AnimatorLayer, AnimatorParameter, AnimatorState, AnimatorTransition, ConditionOp};
+pub use components::{BehaviorAgent, Camera as CameraComponent, Light, LightType, MeshLod, MeshRenderer, NetSmoothing, RagdollMode, RagdollRig, Replicated, SkinnedMesh, TerrainWater, Water, WaterBody};
 pub use entity::{Component, Entity, EntityId};
+pub use grass::Grass;
+pub use ik::{FootPlacement, LegIk, LookAt};
+pub use navigation::NavAgent;
 pub use scene::Scene;
-pub use scene_data::{SerializedComponent, SerializedEntity, SerializedScene};
+pub use scene_data::{SerializedComponent, SerializedEntity, SerializedScene, SCENE_FORMAT_VERSION};
+pub use spline::{Spline, SplineKind};
+pub use time_of_day::SunLight;
 pub use transform::Transform;
+pub use validation::{SceneIssue, SceneIssueKind};
+pub use wind::Wind;
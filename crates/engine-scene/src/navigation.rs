@@ -0,0 +1,390 @@
+// Navigation agents - path following with local avoidance
+//
+// A NavAgent given a destination asks for a path (the editor answers from the
+// scene's baked navmesh), then steers along it corner by corner, easing in to
+// stop at the end. Agents near each other pick velocities with reciprocal
+// velocity obstacles (RVO): each candidate velocity is scored by how soon it
+// would collide, assuming the other agent takes half the avoiding, plus how
+// far it strays from the velocity the agent wants.
+
+use crate::entity::{Component, EntityId};
+use crate::impl_component;
+use crate::scene::Scene;
+use glam::{Quat, Vec3};
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+
+/// Directions sampled around the preferred velocity when avoiding
+const AVOIDANCE_DIRECTIONS: usize = 16;
+/// Penalty weight for colliding soon, against straying from the preferred velocity
+const COLLISION_WEIGHT: f32 = 2.0;
+/// Penalty for veering left, so agents meeting head on both keep right
+/// instead of mirroring each other
+const KEEP_RIGHT: f32 = 0.3;
+/// Waypoints closer than this (horizontally) count as reached
+const WAYPOINT_REACH: f32 = 0.1;
+
+/// Moves its entity across the navmesh to a destination
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NavAgent {
+    /// Top speed in units per second
+    pub speed: f32,
+    pub acceleration: f32,
+    /// Turn rate in degrees per second while facing the way it walks
+    pub angular_speed: f32,
+    pub radius: f32,
+    /// Distance from the destination that counts as arrived
+    pub stopping_distance: f32,
+    /// Steer around other agents
+    pub avoidance: bool,
+    /// Seconds ahead that collisions with other agents are avoided
+    pub time_horizon: f32,
+    #[serde(skip)]
+    pub destination: Option<Vec3>,
+    /// Waypoints still to walk, the destination last
+    #[serde(skip)]
+    pub path: Vec<Vec3>,
+    #[serde(skip)]
+    pub velocity: Vec3,
+    #[serde(skip)]
+    pub arrived: bool,
+    #[serde(skip)]
+    needs_path: bool,
+}
+
+impl NavAgent {
+    pub fn new(speed: f32) -> Self {
+        Self {
+            speed,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    pub fn with_avoidance(mut self, avoidance: bool) -> Self {
+        self.avoidance = avoidance;
+        self
+    }
+
+    /// Walk to `destination`, finding a path on the next update
+    pub fn set_destination(&mut self, destination: Vec3) {
+        self.destination = Some(destination);
+        self.path.clear();
+        self.arrived = false;
+        self.needs_path = true;
+    }
+
+    /// Stop where the agent is
+    pub fn stop(&mut self) {
+        self.destination = None;
+        self.path.clear();
+        self.needs_path = false;
+    }
+
+    /// Whether the agent is still walking somewhere
+    pub fn is_moving(&self) -> bool {
+        self.destination.is_some()
+    }
+}
+
+impl Default for NavAgent {
+    fn default() -> Self {
+        Self {
+            speed: 3.5,
+            acceleration: 8.0,
+            angular_speed: 360.0,
+            radius: 0.5,
+            stopping_distance: 0.2,
+            avoidance: true,
+            time_horizon: 2.0,
+            destination: None,
+            path: Vec::new(),
+            velocity: Vec3::ZERO,
+            arrived: false,
+            needs_path: false,
+        }
+    }
+}
+
+impl_component!(NavAgent);
+
+/// Agent state the avoidance pass reads, taken before anyone moves
+#[derive(Clone, Copy)]
+struct Neighbor {
+    id: EntityId,
+    position: Vec3,
+    velocity: Vec3,
+    radius: f32,
+}
+
+/// Move every NavAgent for one step. `find_path(from, to)` returns the
+/// waypoints from `from` to `to` (both included), or None when the
+/// destination can't be reached, in which case the agent gives up on it.
+/// Agents move their own transform, so they should be root entities.
+pub fn update_nav_agents(
+    scene: &mut Scene,
+    delta_time: f32,
+    mut find_path: impl FnMut(Vec3, Vec3) -> Option<Vec<Vec3>>,
+) {
+    let mut ids: Vec<EntityId> = scene
+        .entities()
+        .filter(|e| e.has_component::<NavAgent>())
+        .map(|e| e.id)
+        .collect();
+    ids.sort_by_key(|id| id.0);
+
+    let neighbors: Vec<Neighbor> = ids
+        .iter()
+        .filter_map(|&id| {
+            let entity = scene.get_entity(id)?;
+            let agent = entity.get_component::<NavAgent>()?;
+            Some(Neighbor {
+                id,
+                position: entity.transform.position,
+                velocity: agent.velocity,
+                radius: agent.radius,
+            })
+        })
+        .collect();
+
+    for me in &neighbors {
+        let Some(entity) = scene.get_entity_mut(me.id) else {
+            continue;
+        };
+        let mut position = entity.transform.position;
+        let mut rotation = entity.transform.rotation;
+        let Some(agent) = entity.get_component_mut::<NavAgent>() else {
+            continue;
+        };
+
+        if agent.needs_path {
+            agent.needs_path = false;
+            match agent.destination.and_then(|to| find_path(position, to)) {
+                Some(mut path) => {
+                    if !path.is_empty() {
+                        path.remove(0);
+                    }
+                    agent.path = path;
+                }
+                None => agent.stop(),
+            }
+        }
+
+        let preferred = preferred_velocity(agent, position);
+        let chosen = if agent.avoidance {
+            avoid(agent, me, preferred, &neighbors)
+        } else {
+            preferred
+        };
+
+        // Accelerate toward the chosen velocity
+        let change = chosen - agent.velocity;
+        let max_change = agent.acceleration * delta_time;
+        agent.velocity += change.clamp_length_max(max_change);
+
+        let step = agent.velocity * delta_time;
+        if let Some(&next) = agent.path.first() {
+            // Follow the path's height along the current leg
+            let remaining = horizontal(next - position).length();
+            if remaining > 1e-4 {
+                let progress = (horizontal(step).length() / remaining).min(1.0);
+                position.y += (next.y - position.y) * progress;
+            }
+        }
+        position.x += step.x;
+        position.z += step.z;
+
+        let facing = horizontal(agent.velocity);
+        if facing.length() > 0.05 {
+            let target = Quat::from_rotation_y(facing.x.atan2(facing.z));
+            let angle = rotation.angle_between(target);
+            let max_turn = agent.angular_speed.to_radians() * delta_time;
+            rotation = if angle <= max_turn {
+                target
+            } else {
+                rotation.slerp(target, max_turn / angle)
+            };
+        }
+
+        entity.transform.position = position;
+        entity.transform.rotation = rotation;
+    }
+}
+
+/// Velocity toward the next waypoint, slowing down on the last one so the
+/// agent stops at it. Reached waypoints are dropped.
+fn preferred_velocity(agent: &mut NavAgent, position: Vec3) -> Vec3 {
+    while agent.path.len() > 1 && horizontal(agent.path[0] - position).length() <= WAYPOINT_REACH {
+        agent.path.remove(0);
+    }
+    let Some(&next) = agent.path.first() else {
+        if agent.destination.take().is_some() {
+            agent.arrived = true;
+        }
+        return Vec3::ZERO;
+    };
+
+    let to_next = horizontal(next - position);
+    let distance = to_next.length();
+    if agent.path.len() == 1 {
+        if distance <= agent.stopping_distance {
+            agent.path.clear();
+            agent.destination = None;
+            agent.arrived = true;
+            return Vec3::ZERO;
+        }
+        // Brake so the agent can stop at the destination
+        let braking = (2.0 * agent.acceleration.max(1e-3) * distance).sqrt();
+        return to_next / distance * agent.speed.min(braking);
+    }
+    to_next / distance.max(1e-4) * agent.speed
+}
+
+/// Pick the sampled velocity with the lowest RVO penalty
+fn avoid(agent: &NavAgent, me: &Neighbor, preferred: Vec3, neighbors: &[Neighbor]) -> Vec3 {
+    let range = agent.speed * agent.time_horizon + agent.radius * 2.0;
+    let nearby: Vec<&Neighbor> = neighbors
+        .iter()
+        .filter(|other| other.id != me.id)
+        .filter(|other| horizontal(other.position - me.position).length() < range + other.radius)
+        .collect();
+    if nearby.is_empty() {
+        return preferred;
+    }
+
+    let mut candidates = vec![preferred, Vec3::ZERO];
+    for i in 0..AVOIDANCE_DIRECTIONS {
+        let angle = i as f32 / AVOIDANCE_DIRECTIONS as f32 * std::f32::consts::TAU;
+        let direction = Vec3::new(angle.sin(), 0.0, angle.cos());
+        for fraction in [0.33, 0.66, 1.0] {
+            candidates.push(direction * agent.speed * fraction);
+        }
+    }
+
+    let mut best = (f32::INFINITY, preferred);
+    for candidate in candidates {
+        let mut soonest = f32::INFINITY;
+        for other in &nearby {
+            // Each agent takes half the avoiding, so judge the other against
+            // the velocity that would leave for it
+            let relative = 2.0 * candidate - me.velocity - other.velocity;
+            let time = time_to_collision(
+                horizontal(other.position - me.position),
+                horizontal(relative),
+                me.radius + other.radius,
+            );
+            soonest = soonest.min(time);
+        }
+        let collision = if soonest < agent.time_horizon {
+            COLLISION_WEIGHT / soonest.max(1e-3)
+        } else {
+            0.0
+        };
+        let left = preferred.cross(candidate).y > 1e-4;
+        let penalty =
+            collision + (candidate - preferred).length() + if left { KEEP_RIGHT } else { 0.0 };
+        if penalty < best.0 {
+            best = (penalty, candidate);
+        }
+    }
+    best.1
+}
+
+/// Seconds until circles `radius` apart touch when one moves at `velocity`
+/// toward the other at `offset`; 0 if they already overlap
+fn time_to_collision(offset: Vec3, velocity: Vec3, radius: f32) -> f32 {
+    let c = offset.length_squared() - radius * radius;
+    if c <= 0.0 {
+        return 0.0;
+    }
+    let a = velocity.length_squared();
+    let b = velocity.dot(offset);
+    let discriminant = b * b - a * c;
+    if a <= 1e-8 || b <= 0.0 || discriminant <= 0.0 {
+        return f32::INFINITY;
+    }
+    (b - discriminant.sqrt()) / a
+}
+
+fn horizontal(v: Vec3) -> Vec3 {
+    Vec3::new(v.x, 0.0, v.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::Transform;
+
+    fn spawn_agent(scene: &mut Scene, name: &str, position: Vec3) -> EntityId {
+        let id = scene
+            .create_entity_with_transform(name.to_string(), Transform::from_position(position));
+        scene
+            .get_entity_mut(id)
+            .unwrap()
+            .add_component(NavAgent::default());
+        id
+    }
+
+    fn agent(scene: &mut Scene, id: EntityId) -> &mut NavAgent {
+        scene
+            .get_entity_mut(id)
+            .unwrap()
+            .get_component_mut::<NavAgent>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_agent_follows_path_and_arrives() {
+        let mut scene = Scene::new("Test".to_string());
+        let id = spawn_agent(&mut scene, "Guard", Vec3::ZERO);
+        let corner = Vec3::new(4.0, 0.0, 0.0);
+        let destination = Vec3::new(4.0, 1.0, 4.0);
+        agent(&mut scene, id).set_destination(destination);
+
+        let mut requests = 0;
+        for _ in 0..400 {
+            update_nav_agents(&mut scene, 1.0 / 60.0, |from, to| {
+                requests += 1;
+                Some(vec![from, corner, to])
+            });
+        }
+
+        assert_eq!(requests, 1);
+        let position = scene.get_entity(id).unwrap().transform.position;
+        assert!(position.distance(destination) < 0.25);
+        let agent = agent(&mut scene, id);
+        assert!(agent.arrived && !agent.is_moving());
+
+        // Unreachable destinations are dropped
+        agent.set_destination(Vec3::new(50.0, 0.0, 0.0));
+        update_nav_agents(&mut scene, 1.0 / 60.0, |_, _| None);
+        let agent = self::agent(&mut scene, id);
+        assert!(!agent.arrived && !agent.is_moving());
+    }
+
+    #[test]
+    fn test_agents_avoid_each_other_head_on() {
+        let mut scene = Scene::new("Test".to_string());
+        let a = spawn_agent(&mut scene, "A", Vec3::new(-5.0, 0.0, 0.0));
+        let b = spawn_agent(&mut scene, "B", Vec3::new(5.0, 0.0, 0.0));
+        agent(&mut scene, a).set_destination(Vec3::new(5.0, 0.0, 0.0));
+        agent(&mut scene, b).set_destination(Vec3::new(-5.0, 0.0, 0.0));
+
+        let mut closest = f32::INFINITY;
+        for _ in 0..600 {
+            update_nav_agents(&mut scene, 1.0 / 60.0, |from, to| Some(vec![from, to]));
+            let pa = scene.get_entity(a).unwrap().transform.position;
+            let pb = scene.get_entity(b).unwrap().transform.position;
+            closest = closest.min(pa.distance(pb));
+        }
+
+        // Radii add up to 1
+        assert!(closest > 0.95, "agents came {closest} apart");
+        assert!(agent(&mut scene, a).arrived);
+        assert!(agent(&mut scene, b).arrived);
+    }
+}
@@ -1,8 +1,7 @@
 // Scene - manages a collection of entities
 
-use crate::components::*;
 use crate::entity::{Entity, EntityId};
-use crate::scene_data::{SerializedComponent, SerializedEntity, SerializedScene};
+use crate::scene_data::{SerializedComponent, SerializedEntity, SerializedScene, SCENE_FORMAT_VERSION};
 use crate::transform::Transform;
 use glam::Mat4;
 use std::collections::HashMap;
@@ -137,6 +136,29 @@ impl Scene {
         }
     }
 
+    /// ID the next created entity will get
+    pub fn next_entity_id(&self) -> EntityId {
+        EntityId::new(self.next_id)
+    }
+
+    /// Remove an entity as-is, leaving its parent and children untouched.
+    /// Used to restore saved entity states (undo); prefer `remove_entity`.
+    pub fn take_entity(&mut self, id: EntityId) -> Option<Entity> {
+        self.entities.remove(&id)
+    }
+
+    /// Insert an entity as-is, replacing any entity with the same ID and
+    /// leaving the hierarchy and root list untouched. Counterpart of `take_entity`.
+    pub fn insert_entity(&mut self, entity: Entity) {
+        self.next_id = self.next_id.max(entity.id.0 + 1);
+        self.entities.insert(entity.id, entity);
+    }
+
+    /// Replace the list of root entities (in hierarchy order)
+    pub fn set_root_entities(&mut self, roots: Vec<EntityId>) {
+        self.root_entities = roots;
+    }
+
     /// Get entity count
     pub fn entity_count(&self) -> usize {
         self.entities.len()
@@ -190,33 +212,7 @@ impl Scene {
         let mut serialized_entities = HashMap::new();
 
         for (id, entity) in &self.entities {
-            let mut components = Vec::new();
-
-            // Check for each component type and serialize it
-            if let Some(mesh_renderer) = entity.get_component::<MeshRenderer>() {
-                components.push(SerializedComponent::MeshRenderer(mesh_renderer.clone()));
-            }
-            if let Some(camera) = entity.get_component::<Camera>() {
-                components.push(SerializedComponent::Camera(camera.clone()));
-            }
-            if let Some(light) = entity.get_component::<Light>() {
-                components.push(SerializedComponent::Light(light.clone()));
-            }
-            if let Some(particle_emitter) = entity.get_component::<ParticleEmitter>() {
-                components.push(SerializedComponent::ParticleEmitter(particle_emitter.clone()));
-            }
-            if let Some(water) = entity.get_component::<Water>() {
-                components.push(SerializedComponent::Water(water.clone()));
-            }
-            if let Some(terrain_water) = entity.get_component::<TerrainWater>() {
-                components.push(SerializedComponent::TerrainWater(terrain_water.clone()));
-            }
-            if let Some(terrain_gen) = entity.get_component::<TerrainGenerator>() {
-                components.push(SerializedComponent::TerrainGenerator(terrain_gen.clone()));
-            }
-            if let Some(foliage) = entity.get_component::<Foliage>() {
-                components.push(SerializedComponent::Foliage(foliage.clone()));
-            }
+            let components = SerializedComponent::from_entity(entity);
 
             serialized_entities.insert(
                 *id,
@@ -232,6 +228,7 @@ impl Scene {
         }
 
         SerializedScene {
+            version: SCENE_FORMAT_VERSION,
             name: self.name.clone(),
             entities: serialized_entities,
             next_id: self.next_id,
@@ -253,20 +250,7 @@ impl Scene {
 
             // Add each serialized component
             for component in serialized_entity.components {
-                match component {
-                    SerializedComponent::MeshRenderer(c) => entity.add_component(c),
-                    SerializedComponent::Camera(c) => entity.add_component(c),
-                    SerializedComponent::Light(c) => entity.add_component(c),
-                    SerializedComponent::ParticleEmitter(c) => entity.add_component(c),
-                    SerializedComponent::Water(c) => entity.add_component(c),
-                    SerializedComponent::TerrainWater(c) => entity.add_component(c),
-                    SerializedComponent::TerrainGenerator(c) => entity.add_component(c),
-                    SerializedComponent::Foliage(c) => entity.add_component(c),
-                    SerializedComponent::Generic { .. } => {
-                        // Generic components are not deserialized at this level
-                        // They should be handled by extension systems
-                    }
-                }
+                component.insert_into(&mut entity);
             }
 
             entities.insert(id, entity);
@@ -282,16 +266,19 @@ impl Scene {
 
     /// Save scene to RON file
     pub fn save_to_file(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let serialized = self.to_serialized();
-        let ron_string = ron::ser::to_string_pretty(&serialized, Default::default())?;
-        std::fs::write(path, ron_string)?;
-        Ok(())
+        self.to_serialized().save(path)
     }
 
-    /// Load scene from RON file
+    /// Load scene from RON file, migrating older format versions
     pub fn load_from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let ron_string = std::fs::read_to_string(path)?;
-        let serialized: SerializedScene = ron::de::from_str(&ron_string)?;
+        Self::load_from_str(&std::fs::read_to_string(path)?)
+    }
+
+    /// Load scene from RON text (e.g. read from an asset pack), migrating
+    /// older format versions
+    pub fn load_from_str(ron: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut serialized: SerializedScene = ron::de::from_str(ron)?;
+        serialized.migrate()?;
         Ok(Self::from_serialized(serialized))
     }
 }
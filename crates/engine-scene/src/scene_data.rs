@@ -1,7 +1,15 @@
 // Serializable scene format for saving and loading scenes
 
+use crate::animation::AnimationClip;
+use crate::animator::Animator;
 use crate::components::*;
-use crate::entity::EntityId;
+use crate::entity::{Entity, EntityId};
+use crate::ik::{FootPlacement, LookAt};
+use crate::navigation::NavAgent;
+use crate::spline::Spline;
+use crate::time_of_day::SunLight;
+use crate::wind::Wind;
+use crate::grass::Grass;
 use crate::transform::Transform;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -18,7 +26,23 @@ pub enum SerializedComponent {
     Water(Water),
     TerrainWater(TerrainWater),
     TerrainGenerator(TerrainGenerator),
+    TerrainStreaming(TerrainStreaming),
     Foliage(Foliage),
+    AnimationClip(AnimationClip),
+    Animator(Animator),
+    LookAt(LookAt),
+    FootPlacement(FootPlacement),
+    NavAgent(NavAgent),
+    RagdollRig(RagdollRig),
+    BehaviorAgent(BehaviorAgent),
+    SkinnedMesh(SkinnedMesh),
+    Replicated(Replicated),
+    SunLight(SunLight),
+    Spline(Spline),
+    Wind(Wind),
+    Grass(Grass),
+    ReverbZone(ReverbZone),
+    AudioOccluder(AudioOccluder),
     // Generic component data for extensibility (e.g., physics components)
     Generic {
         component_type: String,
@@ -26,6 +50,122 @@ pub enum SerializedComponent {
     },
 }
 
+impl SerializedComponent {
+    /// The entity's serializable components
+    pub fn from_entity(entity: &Entity) -> Vec<Self> {
+        let mut components = Vec::new();
+        if let Some(c) = entity.get_component::<MeshRenderer>() {
+            components.push(Self::MeshRenderer(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<Camera>() {
+            components.push(Self::Camera(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<Light>() {
+            components.push(Self::Light(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<ParticleEmitter>() {
+            components.push(Self::ParticleEmitter(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<Water>() {
+            components.push(Self::Water(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<TerrainWater>() {
+            components.push(Self::TerrainWater(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<TerrainGenerator>() {
+            components.push(Self::TerrainGenerator(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<TerrainStreaming>() {
+            components.push(Self::TerrainStreaming(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<Foliage>() {
+            components.push(Self::Foliage(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<AnimationClip>() {
+            components.push(Self::AnimationClip(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<Animator>() {
+            components.push(Self::Animator(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<LookAt>() {
+            components.push(Self::LookAt(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<FootPlacement>() {
+            components.push(Self::FootPlacement(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<NavAgent>() {
+            components.push(Self::NavAgent(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<RagdollRig>() {
+            components.push(Self::RagdollRig(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<BehaviorAgent>() {
+            components.push(Self::BehaviorAgent(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<SkinnedMesh>() {
+            components.push(Self::SkinnedMesh(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<Replicated>() {
+            components.push(Self::Replicated(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<SunLight>() {
+            components.push(Self::SunLight(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<Spline>() {
+            components.push(Self::Spline(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<Wind>() {
+            components.push(Self::Wind(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<Grass>() {
+            components.push(Self::Grass(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<ReverbZone>() {
+            components.push(Self::ReverbZone(c.clone()));
+        }
+        if let Some(c) = entity.get_component::<AudioOccluder>() {
+            components.push(Self::AudioOccluder(c.clone()));
+        }
+        components
+    }
+
+    /// Add the component to an entity, replacing one of the same type.
+    /// Generic components are left to extension systems.
+    pub fn insert_into(self, entity: &mut Entity) {
+        fn replace<T: crate::entity::Component + 'static>(entity: &mut Entity, component: T) {
+            entity.remove_component::<T>();
+            entity.add_component(component);
+        }
+        match self {
+            Self::MeshRenderer(c) => replace(entity, c),
+            Self::Camera(c) => replace(entity, c),
+            Self::Light(c) => replace(entity, c),
+            Self::ParticleEmitter(c) => replace(entity, c),
+            Self::Water(c) => replace(entity, c),
+            Self::TerrainWater(c) => replace(entity, c),
+            Self::TerrainGenerator(c) => replace(entity, c),
+            Self::TerrainStreaming(c) => replace(entity, c),
+            Self::Foliage(c) => replace(entity, c),
+            Self::AnimationClip(c) => replace(entity, c),
+            Self::Animator(c) => replace(entity, c),
+            Self::LookAt(c) => replace(entity, c),
+            Self::FootPlacement(c) => replace(entity, c),
+            Self::NavAgent(c) => replace(entity, c),
+            Self::RagdollRig(c) => replace(entity, c),
+            Self::BehaviorAgent(c) => replace(entity, c),
+            Self::SkinnedMesh(c) => replace(entity, c),
+            Self::Replicated(c) => replace(entity, c),
+            Self::SunLight(c) => replace(entity, c),
+            Self::Spline(c) => replace(entity, c),
+            Self::Wind(c) => replace(entity, c),
+            Self::Grass(c) => replace(entity, c),
+            Self::ReverbZone(c) => replace(entity, c),
+            Self::AudioOccluder(c) => replace(entity, c),
+            Self::Generic { .. } => {}
+        }
+    }
+}
+
 /// Serializable entity data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SerializedEntity {
@@ -37,11 +177,72 @@ pub struct SerializedEntity {
     pub components: Vec<SerializedComponent>,
 }
 
+/// Format version written into saved scenes. Files from before the format
+/// was versioned read as version 0.
+pub const SCENE_FORMAT_VERSION: u32 = 1;
+
+/// Upgrade steps, indexed by the version they upgrade from
+const MIGRATIONS: [fn(&mut SerializedScene); SCENE_FORMAT_VERSION as usize] = [
+    // 0 -> 1: only the version field was added
+    |_| {},
+];
+
 /// Serializable scene data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SerializedScene {
+    #[serde(default)]
+    pub version: u32,
     pub name: String,
     pub entities: HashMap<EntityId, SerializedEntity>,
     pub next_id: u64,
     pub root_entities: Vec<EntityId>,
 }
+
+impl SerializedScene {
+    /// Read a RON scene file as written, without migrating it
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let ron_string = std::fs::read_to_string(path)?;
+        Ok(ron::de::from_str(&ron_string)?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let ron_string = ron::ser::to_string_pretty(self, Default::default())?;
+        std::fs::write(path, ron_string)?;
+        Ok(())
+    }
+
+    /// Upgrade data read from an older file to SCENE_FORMAT_VERSION.
+    /// Returns the version it was written with.
+    pub fn migrate(&mut self) -> Result<u32, String> {
+        let from = self.version;
+        if from > SCENE_FORMAT_VERSION {
+            return Err(format!(
+                "Scene format version {} is newer than this engine supports ({})",
+                from, SCENE_FORMAT_VERSION
+            ));
+        }
+        for step in &MIGRATIONS[from as usize..] {
+            step(self);
+        }
+        self.version = SCENE_FORMAT_VERSION;
+        Ok(from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unversioned_scene_migrates_to_current() {
+        let mut scene: SerializedScene =
+            ron::de::from_str("(name: \"Old\", entities: {}, next_id: 1, root_entities: [])")
+                .unwrap();
+        assert_eq!(scene.version, 0);
+        assert_eq!(scene.migrate(), Ok(0));
+        assert_eq!(scene.version, SCENE_FORMAT_VERSION);
+
+        scene.version = SCENE_FORMAT_VERSION + 1;
+        assert!(scene.migrate().is_err());
+    }
+}
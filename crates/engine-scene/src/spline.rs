@@ -0,0 +1,152 @@
+// Splines - rivers and roads drawn as curves over the terrain
+//
+// A Spline is a Catmull-Rom curve through world-space control points. Rivers
+// carve a bed into the terrain and get a flowing water surface; roads level
+// the ground across their width and get a strip mesh that follows it. The
+// editor rebuilds both whenever the terrain or the spline changes.
+
+use crate::entity::Component;
+use crate::impl_component;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SplineKind {
+    River,
+    Road,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Spline {
+    pub kind: SplineKind,
+    /// Control points in world space; the curve passes through each
+    pub points: Vec<[f32; 3]>,
+    /// Join the last point back to the first
+    pub closed: bool,
+    /// Width of the riverbed or road surface
+    pub width: f32,
+    /// Depth of the riverbed below the water surface
+    pub depth: f32,
+    /// Distance past the edges over which the carve blends into the terrain
+    pub falloff: f32,
+    /// Shape the heightmap along the curve
+    pub carve_terrain: bool,
+    /// Build the water surface or road mesh
+    pub generate_mesh: bool,
+    /// River flow speed in units per second
+    pub flow_speed: f32,
+    /// Road surface material
+    pub material_path: Option<String>,
+}
+
+impl Default for Spline {
+    fn default() -> Self {
+        Self {
+            kind: SplineKind::River,
+            points: Vec::new(),
+            closed: false,
+            width: 4.0,
+            depth: 1.0,
+            falloff: 3.0,
+            carve_terrain: true,
+            generate_mesh: true,
+            flow_speed: 1.0,
+            material_path: Some("materials/road.mat".to_string()),
+        }
+    }
+}
+
+impl_component!(Spline);
+
+impl Spline {
+    pub fn new(kind: SplineKind) -> Self {
+        Self {
+            kind,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_points(mut self, points: impl IntoIterator<Item = Vec3>) -> Self {
+        self.points = points.into_iter().map(|point| point.to_array()).collect();
+        self
+    }
+
+    /// Points along the curve no more than `spacing` apart, from the first
+    /// control point to the last (or back to the first if closed)
+    pub fn sample(&self, spacing: f32) -> Vec<Vec3> {
+        let points: Vec<Vec3> = self.points.iter().copied().map(Vec3::from).collect();
+        let count = points.len();
+        if count < 2 {
+            return points;
+        }
+        let closed = self.closed && count > 2;
+        let point = |i: isize| {
+            let index = if closed {
+                i.rem_euclid(count as isize)
+            } else {
+                i.clamp(0, count as isize - 1)
+            };
+            points[index as usize]
+        };
+
+        let segments = if closed { count } else { count - 1 };
+        let mut samples = vec![points[0]];
+        for segment in 0..segments as isize {
+            let (p0, p1, p2, p3) = (
+                point(segment - 1),
+                point(segment),
+                point(segment + 1),
+                point(segment + 2),
+            );
+            let steps = ((p2 - p1).length() / spacing.max(0.01)).ceil().max(1.0) as usize;
+            for step in 1..=steps {
+                let t = step as f32 / steps as f32;
+                samples.push(catmull_rom(p0, p1, p2, p3, t));
+            }
+        }
+        samples
+    }
+}
+
+/// Uniform Catmull-Rom between p1 and p2
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_passes_through_control_points() {
+        let spline = Spline::new(SplineKind::Road).with_points([
+            Vec3::ZERO,
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(10.0, 2.0, 10.0),
+        ]);
+        let samples = spline.sample(1.0);
+        let near = |a: &Vec3, b: Vec3| a.distance(b) < 1e-4;
+        for point in &spline.points {
+            assert!(samples
+                .iter()
+                .any(|sample| near(sample, Vec3::from(*point))));
+        }
+        assert!(near(&samples[0], Vec3::ZERO));
+        assert!(near(samples.last().unwrap(), Vec3::new(10.0, 2.0, 10.0)));
+        assert!(samples
+            .windows(2)
+            .all(|pair| pair[0].distance(pair[1]) <= 1.5));
+
+        // Closed curves come back to the start
+        let mut closed = spline.clone();
+        closed.closed = true;
+        assert!(near(closed.sample(1.0).last().unwrap(), Vec3::ZERO));
+    }
+}
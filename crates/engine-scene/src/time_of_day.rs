@@ -0,0 +1,135 @@
+// Time of day - a sun that moves across the sky
+//
+// A SunLight holds the hour of the day and turns it into a sun direction. The
+// sun rises in the east (+X), peaks at noon and sets in the west; latitude
+// tilts its arc toward the south (+Z), so at 0 degrees it passes straight
+// overhead. When day_length is set the clock runs during play, and the
+// renderer follows with the sky colors, shadow direction and ambient light.
+
+use crate::entity::{Component, EntityId};
+use crate::impl_component;
+use crate::scene::Scene;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SunLight {
+    /// Hour of the day, 0..24
+    pub time_of_day: f32,
+    /// Real seconds for a full 24 hours, 0 keeps the clock still
+    pub day_length: f32,
+    /// Degrees; tilts the sun's path away from overhead
+    pub latitude: f32,
+    /// Haze in the air, 2 is a clear sky and 10 a hazy one
+    pub turbidity: f32,
+    /// Scales the sun's brightness
+    pub intensity: f32,
+}
+
+impl Default for SunLight {
+    fn default() -> Self {
+        Self {
+            time_of_day: 14.0,
+            day_length: 0.0,
+            latitude: 30.0,
+            turbidity: 3.0,
+            intensity: 1.0,
+        }
+    }
+}
+
+impl_component!(SunLight);
+
+impl SunLight {
+    /// Direction from the ground toward the sun, below the horizon at night
+    pub fn sun_direction(&self) -> Vec3 {
+        let hour_angle = (self.time_of_day - 12.0) / 24.0 * std::f32::consts::TAU;
+        let latitude = self.latitude.to_radians();
+        Vec3::new(
+            -hour_angle.sin(),
+            hour_angle.cos() * latitude.cos(),
+            hour_angle.cos() * latitude.sin(),
+        )
+        .normalize()
+    }
+
+    /// Run the clock forward, wrapping past midnight
+    pub fn advance(&mut self, delta_time: f32) {
+        if self.day_length > 0.0 {
+            self.time_of_day += delta_time / self.day_length * 24.0;
+            self.time_of_day = self.time_of_day.rem_euclid(24.0);
+        }
+    }
+}
+
+/// The scene's sun, if it has one. With several the lowest entity id wins.
+pub fn find_sun_light(scene: &Scene) -> Option<(EntityId, &SunLight)> {
+    scene
+        .entities()
+        .filter_map(|e| e.get_component::<SunLight>().map(|s| (e.id, s)))
+        .min_by_key(|(id, _)| id.0)
+}
+
+/// Advance every SunLight's clock by one step
+pub fn update_sun_lights(scene: &mut Scene, delta_time: f32) {
+    let ids: Vec<EntityId> = scene
+        .entities()
+        .filter(|e| e.has_component::<SunLight>())
+        .map(|e| e.id)
+        .collect();
+    for id in ids {
+        if let Some(sun) = scene
+            .get_entity_mut(id)
+            .and_then(|e| e.get_component_mut::<SunLight>())
+        {
+            sun.advance(delta_time);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sun_rises_in_the_east_and_peaks_at_noon() {
+        let mut sun = SunLight {
+            latitude: 0.0,
+            ..Default::default()
+        };
+        sun.time_of_day = 6.0;
+        assert!(sun.sun_direction().x > 0.99);
+        sun.time_of_day = 12.0;
+        assert!(sun.sun_direction().abs_diff_eq(Vec3::Y, 1e-5));
+        sun.time_of_day = 0.0;
+        assert!(sun.sun_direction().y < -0.99);
+
+        sun.latitude = 40.0;
+        sun.time_of_day = 12.0;
+        let noon = sun.sun_direction();
+        assert!(noon.z > 0.5 && (noon.y - 40f32.to_radians().cos()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_clock_runs_and_wraps() {
+        let mut scene = Scene::new("Sky".to_string());
+        let id = scene.create_entity("Sun".to_string());
+        scene.get_entity_mut(id).unwrap().add_component(SunLight {
+            time_of_day: 23.0,
+            day_length: 240.0,
+            ..Default::default()
+        });
+
+        // 20 seconds is 2 hours at 240 seconds a day
+        update_sun_lights(&mut scene, 20.0);
+        let (found, sun) = find_sun_light(&scene).unwrap();
+        assert_eq!(found, id);
+        assert!((sun.time_of_day - 1.0).abs() < 1e-4);
+
+        let mut paused = SunLight::default();
+        paused.advance(100.0);
+        assert_eq!(paused.time_of_day, 14.0);
+    }
+}
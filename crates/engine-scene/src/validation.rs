@@ -0,0 +1,265 @@
+// Scene validation - finds problems that would break loading or rendering
+
+use std::collections::HashSet;
+
+use crate::components::{AudioSource, MeshRenderer, ParticleEmitter, TerrainWater, Water};
+use crate::entity::{Entity, EntityId};
+use crate::scene::Scene;
+
+/// Kind of problem `Scene::validate` reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SceneIssueKind {
+    /// A component references an asset that does not exist
+    MissingAsset,
+    /// An entity's parent is not in the scene
+    DanglingParent,
+    /// An entity lists a child that is not in the scene
+    DanglingChild,
+    /// Parent and child links (or the root list) disagree
+    HierarchyMismatch,
+    /// An entity is its own ancestor
+    ParentCycle,
+    /// A transform has NaN or infinite values, or a zero scale
+    InvalidTransform,
+}
+
+impl SceneIssueKind {
+    /// Snake-case name (as reported by the editor's MCP tools)
+    pub fn name(&self) -> &'static str {
+        match self {
+            SceneIssueKind::MissingAsset => "missing_asset",
+            SceneIssueKind::DanglingParent => "dangling_parent",
+            SceneIssueKind::DanglingChild => "dangling_child",
+            SceneIssueKind::HierarchyMismatch => "hierarchy_mismatch",
+            SceneIssueKind::ParentCycle => "parent_cycle",
+            SceneIssueKind::InvalidTransform => "invalid_transform",
+        }
+    }
+}
+
+/// A problem found by `Scene::validate`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneIssue {
+    pub kind: SceneIssueKind,
+    pub entity: EntityId,
+    pub message: String,
+}
+
+impl Scene {
+    /// Check the hierarchy, transforms and asset references of every entity.
+    /// `asset_exists` decides whether a referenced asset path (or mesh name)
+    /// resolves. Issues are sorted by entity ID.
+    pub fn validate(&self, asset_exists: impl Fn(&str) -> bool) -> Vec<SceneIssue> {
+        let mut issues = Vec::new();
+        let roots: HashSet<EntityId> = self.root_entities().iter().copied().collect();
+
+        for &root in &roots {
+            match self.get_entity(root) {
+                None => issues.push(SceneIssue {
+                    kind: SceneIssueKind::HierarchyMismatch,
+                    entity: root,
+                    message: format!(
+                        "Root list contains entity {} which is not in the scene",
+                        root.0
+                    ),
+                }),
+                Some(entity) if entity.parent.is_some() => issues.push(SceneIssue {
+                    kind: SceneIssueKind::HierarchyMismatch,
+                    entity: root,
+                    message: format!("'{}' has a parent but is in the root list", entity.name),
+                }),
+                Some(_) => {}
+            }
+        }
+
+        for entity in self.entities() {
+            let mut issue = |kind, message| {
+                issues.push(SceneIssue {
+                    kind,
+                    entity: entity.id,
+                    message,
+                })
+            };
+
+            match entity.parent {
+                Some(parent_id) => match self.get_entity(parent_id) {
+                    None => issue(
+                        SceneIssueKind::DanglingParent,
+                        format!(
+                            "'{}' has parent {} which is not in the scene",
+                            entity.name, parent_id.0
+                        ),
+                    ),
+                    Some(parent) if !parent.children.contains(&entity.id) => issue(
+                        SceneIssueKind::HierarchyMismatch,
+                        format!(
+                            "'{}' is not in the children of its parent '{}'",
+                            entity.name, parent.name
+                        ),
+                    ),
+                    Some(_) => {
+                        if self.is_own_ancestor(entity.id) {
+                            issue(
+                                SceneIssueKind::ParentCycle,
+                                format!("'{}' is its own ancestor", entity.name),
+                            );
+                        }
+                    }
+                },
+                None if !roots.contains(&entity.id) => issue(
+                    SceneIssueKind::HierarchyMismatch,
+                    format!(
+                        "'{}' has no parent but is not in the root list",
+                        entity.name
+                    ),
+                ),
+                None => {}
+            }
+
+            for &child_id in &entity.children {
+                match self.get_entity(child_id) {
+                    None => issue(
+                        SceneIssueKind::DanglingChild,
+                        format!(
+                            "'{}' lists child {} which is not in the scene",
+                            entity.name, child_id.0
+                        ),
+                    ),
+                    Some(child) if child.parent != Some(entity.id) => issue(
+                        SceneIssueKind::HierarchyMismatch,
+                        format!(
+                            "'{}' lists '{}' as a child, but its parent is elsewhere",
+                            entity.name, child.name
+                        ),
+                    ),
+                    Some(_) => {}
+                }
+            }
+
+            let transform = &entity.transform;
+            if !(transform.position.is_finite()
+                && transform.rotation.is_finite()
+                && transform.scale.is_finite())
+            {
+                issue(
+                    SceneIssueKind::InvalidTransform,
+                    format!("'{}' has a NaN or infinite transform", entity.name),
+                );
+            } else if transform.scale.cmpeq(glam::Vec3::ZERO).any() {
+                issue(
+                    SceneIssueKind::InvalidTransform,
+                    format!("'{}' has a zero scale {:?}", entity.name, transform.scale),
+                );
+            }
+
+            for (component, path) in asset_references(entity) {
+                if !asset_exists(path) {
+                    issue(
+                        SceneIssueKind::MissingAsset,
+                        format!(
+                            "{} of '{}' references missing asset '{}'",
+                            component, entity.name, path
+                        ),
+                    );
+                }
+            }
+        }
+
+        issues.sort_by(|a, b| a.entity.0.cmp(&b.entity.0).then(a.kind.cmp(&b.kind)));
+        issues
+    }
+
+    /// Whether following parents from `entity_id` leads back to it
+    fn is_own_ancestor(&self, entity_id: EntityId) -> bool {
+        let mut visited = HashSet::new();
+        let mut current = self.get_entity(entity_id).and_then(|e| e.parent);
+        while let Some(id) = current {
+            if id == entity_id {
+                return true;
+            }
+            if !visited.insert(id) {
+                // A cycle further up, reported on the entities in it
+                return false;
+            }
+            current = self.get_entity(id).and_then(|e| e.parent);
+        }
+        false
+    }
+}
+
+/// (component name, asset path) of every asset an entity references
+fn asset_references(entity: &Entity) -> Vec<(&'static str, &str)> {
+    let mut references = Vec::new();
+    if let Some(renderer) = entity.get_component::<MeshRenderer>() {
+        references.push(("MeshRenderer", renderer.mesh_path.as_str()));
+        references.extend(
+            renderer
+                .material_path
+                .as_deref()
+                .map(|path| ("MeshRenderer", path)),
+        );
+    }
+    if let Some(audio) = entity.get_component::<AudioSource>() {
+        references.push(("AudioSource", audio.audio_path.as_str()));
+    }
+    if let Some(emitter) = entity.get_component::<ParticleEmitter>() {
+        references.extend(
+            emitter
+                .texture_path
+                .as_deref()
+                .map(|path| ("ParticleEmitter", path)),
+        );
+    }
+    if let Some(water) = entity.get_component::<Water>() {
+        references.extend(water.texture_path.as_deref().map(|path| ("Water", path)));
+    }
+    if let Some(water) = entity.get_component::<TerrainWater>() {
+        references.extend(
+            water
+                .texture_path
+                .as_deref()
+                .map(|path| ("TerrainWater", path)),
+        );
+    }
+    references
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::Transform;
+    use glam::Vec3;
+
+    #[test]
+    fn test_validate_reports_issues() {
+        let mut scene = Scene::new("Test".to_string());
+        let house = scene.create_entity("House".to_string());
+        let door = scene.create_entity("Door".to_string());
+        scene.set_parent(door, Some(house));
+        scene
+            .get_entity_mut(door)
+            .unwrap()
+            .add_component(MeshRenderer::new("models/door.glb#1".to_string()));
+        assert!(scene.validate(|path| path == "models/door.glb#1").is_empty());
+
+        // A missing mesh, a NaN transform and a parent that was taken out
+        scene.create_entity_with_transform(
+            "Broken".to_string(),
+            Transform::from_position(Vec3::new(f32::NAN, 0.0, 0.0)),
+        );
+        scene.take_entity(house);
+        let issues = scene.validate(|_| false);
+        let kinds: Vec<SceneIssueKind> = issues.iter().map(|issue| issue.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                SceneIssueKind::HierarchyMismatch,
+                SceneIssueKind::MissingAsset,
+                SceneIssueKind::DanglingParent,
+                SceneIssueKind::InvalidTransform,
+            ]
+        );
+        assert_eq!(issues[0].entity, house);
+        assert_eq!(issues[1].entity, door);
+    }
+}
@@ -0,0 +1,101 @@
+// Wind - a steady breeze with gusts rolling through it
+//
+// A Wind blows along a horizontal direction at a base strength. Gusts are
+// bands of stronger wind that travel downwind, made of two sine waves so they
+// don't repeat evenly. The foliage shader evaluates the same formula to sway
+// trees and grass, so `velocity_at` matches what is drawn.
+
+use crate::entity::{Component, EntityId};
+use crate::impl_component;
+use crate::scene::Scene;
+use glam::{Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::f32::consts::TAU;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Wind {
+    /// Direction the wind blows toward, on the XZ plane
+    pub direction: [f32; 2],
+    /// Steady wind strength
+    pub strength: f32,
+    /// Extra strength at the peak of a gust
+    pub gust_strength: f32,
+    /// Gusts passing a point per second
+    pub gust_frequency: f32,
+    /// Distance between gusts in world units
+    pub gust_scale: f32,
+}
+
+impl Default for Wind {
+    fn default() -> Self {
+        Self {
+            direction: [1.0, 0.0],
+            strength: 1.0,
+            gust_strength: 1.0,
+            gust_frequency: 0.2,
+            gust_scale: 40.0,
+        }
+    }
+}
+
+impl_component!(Wind);
+
+impl Wind {
+    /// Normalized blowing direction (+X if unset)
+    pub fn direction(&self) -> Vec2 {
+        Vec2::from(self.direction).normalize_or(Vec2::X)
+    }
+
+    /// Gust amount at a position and time, 0..1
+    pub fn gust(&self, position: Vec3, time: f32) -> f32 {
+        let along =
+            Vec2::new(position.x, position.z).dot(self.direction()) / self.gust_scale.max(0.01);
+        let phase = (along - time * self.gust_frequency) * TAU;
+        let wave = phase.sin() * 0.6 + (phase * 2.3 + 1.7).sin() * 0.4;
+        (wave * 0.5 + 0.5).clamp(0.0, 1.0)
+    }
+
+    /// Wind velocity at a position and time
+    pub fn velocity_at(&self, position: Vec3, time: f32) -> Vec3 {
+        let direction = self.direction();
+        let speed = self.strength + self.gust_strength * self.gust(position, time);
+        Vec3::new(direction.x, 0.0, direction.y) * speed
+    }
+}
+
+/// The scene's wind, if it has one. With several the lowest entity id wins.
+pub fn find_wind(scene: &Scene) -> Option<(EntityId, &Wind)> {
+    scene
+        .entities()
+        .filter_map(|e| e.get_component::<Wind>().map(|w| (e.id, w)))
+        .min_by_key(|(id, _)| id.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gusts_travel_downwind() {
+        let wind = Wind {
+            direction: [0.0, 2.0],
+            ..Default::default()
+        };
+        assert_eq!(wind.direction(), Vec2::Y);
+
+        // A gust seen at the origin is seen downwind a period of travel later
+        let travel = wind.gust_scale / 4.0;
+        let delay = 0.25 / wind.gust_frequency;
+        for t in [0.0, 0.7, 3.1] {
+            let here = wind.gust(Vec3::ZERO, t);
+            let downwind = wind.gust(Vec3::new(0.0, 0.0, travel), t + delay);
+            assert!((here - downwind).abs() < 1e-4);
+            assert!((0.0..=1.0).contains(&here));
+        }
+
+        let velocity = wind.velocity_at(Vec3::ZERO, 0.0);
+        assert!(velocity.x.abs() < 1e-6 && velocity.z >= wind.strength);
+    }
+}
@@ -0,0 +1,169 @@
+// Animator API for scripts - set the parameters animator transitions test
+//
+//   set_anim_float(ctx.entity_id, "speed", speed);
+//   set_anim_bool(ctx.entity_id, "grounded", true);
+//   set_anim_trigger(ctx.entity_id, "jump");
+//   play_anim(ctx.entity_id, "Wave", 0.2);   // cross-fade straight to a state
+//
+// Calls are queued and applied to the entities' Animator components once the
+// scripts have run, before the animation system advances.
+
+use engine_scene::animator::Animator;
+use engine_scene::entity::EntityId;
+use engine_scene::scene::Scene;
+use rhai::Engine;
+use std::sync::{Arc, Mutex};
+
+/// Parameter change that scripts can issue
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnimatorCommand {
+    SetFloat {
+        entity: EntityId,
+        name: String,
+        value: f32,
+    },
+    SetBool {
+        entity: EntityId,
+        name: String,
+        value: bool,
+    },
+    SetTrigger {
+        entity: EntityId,
+        name: String,
+    },
+    Play {
+        entity: EntityId,
+        state: String,
+        blend_time: f32,
+    },
+}
+
+/// Thread-safe animator command queue
+pub type AnimatorCommandQueue = Arc<Mutex<Vec<AnimatorCommand>>>;
+
+/// Register animator functions with Rhai engine
+pub fn register_animator_api(engine: &mut Engine, command_queue: AnimatorCommandQueue) {
+    let queue_clone1 = command_queue.clone();
+    let queue_clone2 = command_queue.clone();
+    let queue_clone3 = command_queue.clone();
+
+    engine.register_fn(
+        "set_anim_float",
+        move |entity: i64, name: &str, value: f64| {
+            queue_clone1
+                .lock()
+                .unwrap()
+                .push(AnimatorCommand::SetFloat {
+                    entity: EntityId(entity as u64),
+                    name: name.to_string(),
+                    value: value as f32,
+                });
+        },
+    );
+
+    engine.register_fn(
+        "set_anim_bool",
+        move |entity: i64, name: &str, value: bool| {
+            queue_clone2.lock().unwrap().push(AnimatorCommand::SetBool {
+                entity: EntityId(entity as u64),
+                name: name.to_string(),
+                value,
+            });
+        },
+    );
+
+    engine.register_fn(
+        "play_anim",
+        move |entity: i64, state: &str, blend_time: f64| {
+            queue_clone3.lock().unwrap().push(AnimatorCommand::Play {
+                entity: EntityId(entity as u64),
+                state: state.to_string(),
+                blend_time: blend_time as f32,
+            });
+        },
+    );
+
+    engine.register_fn("set_anim_trigger", move |entity: i64, name: &str| {
+        command_queue
+            .lock()
+            .unwrap()
+            .push(AnimatorCommand::SetTrigger {
+                entity: EntityId(entity as u64),
+                name: name.to_string(),
+            });
+    });
+}
+
+/// Apply queued commands to the scene's animators, dropping any for
+/// entities without one
+pub fn apply_animator_commands(queue: &AnimatorCommandQueue, scene: &mut Scene) {
+    for command in queue.lock().unwrap().drain(..) {
+        let entity = match &command {
+            AnimatorCommand::SetFloat { entity, .. }
+            | AnimatorCommand::SetBool { entity, .. }
+            | AnimatorCommand::SetTrigger { entity, .. }
+            | AnimatorCommand::Play { entity, .. } => *entity,
+        };
+        let Some(animator) = scene
+            .get_entity_mut(entity)
+            .and_then(|e| e.get_component_mut::<Animator>())
+        else {
+            continue;
+        };
+        match command {
+            AnimatorCommand::SetFloat { name, value, .. } => animator.set_float(&name, value),
+            AnimatorCommand::SetBool { name, value, .. } => animator.set_bool(&name, value),
+            AnimatorCommand::SetTrigger { name, .. } => animator.set_trigger(&name),
+            AnimatorCommand::Play {
+                state, blend_time, ..
+            } => {
+                if animator.play(&state, blend_time).is_none() {
+                    log::warn!("play_anim: animator has no state '{}'", state);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_scene::animator::AnimatorParameter;
+
+    #[test]
+    fn test_script_sets_parameters() {
+        let mut scene = Scene::new("Test".to_string());
+        let id = scene.create_entity("Hero".to_string());
+        scene
+            .get_entity_mut(id)
+            .unwrap()
+            .add_component(Animator::new());
+
+        let queue = AnimatorCommandQueue::default();
+        let mut engine = Engine::new();
+        register_animator_api(&mut engine, queue.clone());
+        engine
+            .run(&format!(
+                r#"set_anim_float({0}, "speed", 2.5); set_anim_bool({0}, "grounded", true); set_anim_trigger({0}, "jump"); set_anim_trigger(999, "jump");"#,
+                id.0
+            ))
+            .unwrap();
+        apply_animator_commands(&queue, &mut scene);
+
+        let animator = scene
+            .get_entity(id)
+            .unwrap()
+            .get_component::<Animator>()
+            .unwrap();
+        assert_eq!(animator.parameters["speed"], AnimatorParameter::Float(2.5));
+        assert_eq!(
+            animator.parameters["grounded"],
+            AnimatorParameter::Bool(true)
+        );
+        assert_eq!(
+            animator.parameters["jump"],
+            AnimatorParameter::Trigger(true)
+        );
+        assert!(queue.lock().unwrap().is_empty());
+    }
+}
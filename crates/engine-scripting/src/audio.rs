@@ -1,5 +1,6 @@
 // Audio API for scripts
 
+use engine_scene::entity::EntityId;
 use rhai::Engine;
 use std::sync::{Arc, Mutex};
 
@@ -7,19 +8,81 @@ use std::sync::{Arc, Mutex};
 #[derive(Debug, Clone)]
 pub enum AudioCommand {
     PlaySound { path: String, volume: f32 },
-    PlayMusic { path: String, volume: f32, looping: bool },
-    StopMusic,
+    /// Play a track, crossfading from the current one over `fade_in`
+    /// seconds (0 = cut)
+    PlayMusic {
+        path: String,
+        volume: f32,
+        looping: bool,
+        fade_in: f32,
+    },
+    /// Stop the music, fading it out over `fade_out` seconds (0 = cut)
+    StopMusic {
+        fade_out: f32,
+    },
+    /// Play the track configured for a named music moment (e.g. "boss_fight")
+    PlayMoment {
+        name: String,
+    },
+    /// Play a named adaptive track (assets/music/adaptive.ron), crossfading
+    /// from the current music over `fade_in` seconds
+    PlayAdaptive {
+        name: String,
+        fade_in: f32,
+    },
+    /// Set a parameter adaptive tracks mix their layers by
+    SetMusicParameter {
+        name: String,
+        value: f32,
+    },
+    /// Play an entity's AudioSource from the start
+    PlaySource {
+        entity: EntityId,
+    },
+    StopSource {
+        entity: EntityId,
+    },
+    PauseSource {
+        entity: EntityId,
+    },
+    ResumeSource {
+        entity: EntityId,
+    },
+    /// Jump an entity's playing AudioSource to `seconds` in
+    SeekSource {
+        entity: EntityId,
+        seconds: f32,
+    },
 }
 
 /// Thread-safe audio command queue
 pub type AudioCommandQueue = Arc<Mutex<Vec<AudioCommand>>>;
 
+/// Script-facing `music` object (`music.play_moment("boss_fight")`,
+/// `music.set_parameter("combat_intensity", 0.8)`)
+#[derive(Clone)]
+pub struct MusicApi {
+    queue: AudioCommandQueue,
+}
+
+impl MusicApi {
+    pub fn new(queue: AudioCommandQueue) -> Self {
+        Self { queue }
+    }
+
+    fn push(&self, command: AudioCommand) {
+        self.queue.lock().unwrap().push(command);
+    }
+}
+
 /// Register audio functions with Rhai engine
 pub fn register_audio_api(engine: &mut Engine, command_queue: AudioCommandQueue) {
     // Clone for each closure
     let queue_clone1 = command_queue.clone();
     let queue_clone2 = command_queue.clone();
     let queue_clone3 = command_queue.clone();
+    let queue_clone4 = command_queue.clone();
+    let queue_clone5 = command_queue.clone();
 
     // Play sound (2D, no position)
     engine.register_fn("play_sound", move |path: &str, volume: f64| {
@@ -32,19 +95,116 @@ pub fn register_audio_api(engine: &mut Engine, command_queue: AudioCommandQueue)
     });
 
     // Play music (looping background music)
-    engine.register_fn("play_music", move |path: &str, volume: f64, looping: bool| {
-        let mut queue = queue_clone2.lock().unwrap();
-        queue.push(AudioCommand::PlayMusic {
-            path: path.to_string(),
-            volume: volume as f32,
-            looping,
-        });
-        true
-    });
+    engine.register_fn(
+        "play_music",
+        move |path: &str, volume: f64, looping: bool| {
+            let mut queue = queue_clone2.lock().unwrap();
+            queue.push(AudioCommand::PlayMusic {
+                path: path.to_string(),
+                volume: volume as f32,
+                looping,
+                fade_in: 0.0,
+            });
+            true
+        },
+    );
+
+    // Play music, crossfading from the current track
+    engine.register_fn(
+        "play_music",
+        move |path: &str, volume: f64, looping: bool, fade_in: f64| {
+            let mut queue = queue_clone4.lock().unwrap();
+            queue.push(AudioCommand::PlayMusic {
+                path: path.to_string(),
+                volume: volume as f32,
+                looping,
+                fade_in: fade_in as f32,
+            });
+            true
+        },
+    );
 
     // Stop music
     engine.register_fn("stop_music", move || {
         let mut queue = queue_clone3.lock().unwrap();
-        queue.push(AudioCommand::StopMusic);
+        queue.push(AudioCommand::StopMusic { fade_out: 0.0 });
+    });
+
+    // Fade music out, then stop it
+    engine.register_fn("stop_music", move |fade_out: f64| {
+        let mut queue = queue_clone5.lock().unwrap();
+        queue.push(AudioCommand::StopMusic {
+            fade_out: fade_out as f32,
+        });
     });
+
+    // Entity audio sources (play_audio(ctx.entity_id), seek_audio(ctx.entity_id, 2.5))
+    let queue = command_queue.clone();
+    engine.register_fn("play_audio", move |entity: i64| {
+        queue.lock().unwrap().push(AudioCommand::PlaySource {
+            entity: EntityId(entity as u64),
+        });
+    });
+    let queue = command_queue.clone();
+    engine.register_fn("stop_audio", move |entity: i64| {
+        queue.lock().unwrap().push(AudioCommand::StopSource {
+            entity: EntityId(entity as u64),
+        });
+    });
+    let queue = command_queue.clone();
+    engine.register_fn("pause_audio", move |entity: i64| {
+        queue.lock().unwrap().push(AudioCommand::PauseSource {
+            entity: EntityId(entity as u64),
+        });
+    });
+    let queue = command_queue.clone();
+    engine.register_fn("resume_audio", move |entity: i64| {
+        queue.lock().unwrap().push(AudioCommand::ResumeSource {
+            entity: EntityId(entity as u64),
+        });
+    });
+    let queue = command_queue.clone();
+    engine.register_fn("seek_audio", move |entity: i64, seconds: f64| {
+        queue.lock().unwrap().push(AudioCommand::SeekSource {
+            entity: EntityId(entity as u64),
+            seconds: seconds as f32,
+        });
+    });
+
+    // Music moments (exposed to scripts as the global `music` object).
+    // Methods take the object by value so they can be called on a constant.
+    engine
+        .register_type_with_name::<MusicApi>("Music")
+        .register_fn("play_moment", |music: MusicApi, name: &str| {
+            music.push(AudioCommand::PlayMoment {
+                name: name.to_string(),
+            });
+        })
+        .register_fn("play_adaptive", |music: MusicApi, name: &str| {
+            music.push(AudioCommand::PlayAdaptive {
+                name: name.to_string(),
+                fade_in: 0.0,
+            });
+        })
+        .register_fn(
+            "play_adaptive",
+            |music: MusicApi, name: &str, fade_in: f64| {
+                music.push(AudioCommand::PlayAdaptive {
+                    name: name.to_string(),
+                    fade_in: fade_in as f32,
+                });
+            },
+        )
+        .register_fn(
+            "set_parameter",
+            |music: MusicApi, name: &str, value: f64| {
+                music.push(AudioCommand::SetMusicParameter {
+                    name: name.to_string(),
+                    value: value as f32,
+                });
+            },
+        )
+        .register_fn("stop", |music: MusicApi| {
+            music.push(AudioCommand::StopMusic { fade_out: 0.0 });
+        });
 }
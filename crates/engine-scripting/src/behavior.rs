@@ -0,0 +1,49 @@
+// Behavior tree tasks written in scripts
+//
+//   fn can_see_player(ctx) {
+//       ctx.position.distance(player_position) < 10.0
+//   }
+//
+//   fn reload(ctx) {
+//       if reloading_done { "success" } else { "running" }
+//   }
+//
+// A Script leaf calls the named function with the same ctx update() gets.
+// true or "success" succeeds, "running" calls it again next tick and
+// anything else fails.
+
+use engine_ai_behavior::BehaviorStatus;
+use rhai::Dynamic;
+
+/// What a task function's return value means to the tree
+pub fn task_status(result: &Dynamic) -> BehaviorStatus {
+    if let Some(success) = result.clone().try_cast::<bool>() {
+        return if success {
+            BehaviorStatus::Success
+        } else {
+            BehaviorStatus::Failure
+        };
+    }
+    match result.clone().into_string().as_deref() {
+        Ok("success") => BehaviorStatus::Success,
+        Ok("running") => BehaviorStatus::Running,
+        _ => BehaviorStatus::Failure,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rhai::Engine;
+
+    #[test]
+    fn test_task_return_values() {
+        let engine = Engine::new();
+        let status = |script: &str| task_status(&engine.eval::<Dynamic>(script).unwrap());
+        assert_eq!(status("true"), BehaviorStatus::Success);
+        assert_eq!(status("false"), BehaviorStatus::Failure);
+        assert_eq!(status(r#""running""#), BehaviorStatus::Running);
+        assert_eq!(status(r#""success""#), BehaviorStatus::Success);
+        assert_eq!(status("42"), BehaviorStatus::Failure);
+    }
+}
@@ -0,0 +1,175 @@
+// Character API for scripts - what a CharacterController is standing on,
+// and moving it
+//
+//   if is_grounded(ctx.entity_id) { ... }
+//   if is_sliding(ctx.entity_id) { ... }   // on a slope too steep to stand on
+//   let normal = ground_normal(ctx.entity_id);
+//   move_character(ctx.entity_id, movement, jump, sprint);
+//
+// Reads a snapshot of the characters taken before the scripts run. Moves
+// are queued and become the controllers' input once the scripts have run;
+// the physics character system moves them by it next step.
+
+use engine_physics::{CharacterController, CharacterInput};
+use engine_scene::entity::EntityId;
+use engine_scene::scene::Scene;
+use glam::Vec3;
+use rhai::Engine;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// A character's ground, as of the start of the frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CharacterGround {
+    pub grounded: bool,
+    pub sliding: bool,
+    pub normal: Vec3,
+}
+
+/// Thread-safe character snapshot shared with scripts
+pub type CharacterStateHandle = Arc<Mutex<HashMap<EntityId, CharacterGround>>>;
+
+/// Thread-safe queue of character moves requested by scripts
+pub type CharacterCommandQueue = Arc<Mutex<Vec<(EntityId, CharacterInput)>>>;
+
+/// Register character functions with Rhai engine
+pub fn register_character_api(
+    engine: &mut Engine,
+    state: CharacterStateHandle,
+    command_queue: CharacterCommandQueue,
+) {
+    let state_clone1 = state.clone();
+    let state_clone2 = state.clone();
+
+    engine.register_fn("is_grounded", move |entity: i64| -> bool {
+        ground(&state_clone1, entity).is_some_and(|ground| ground.grounded)
+    });
+
+    engine.register_fn("is_sliding", move |entity: i64| -> bool {
+        ground(&state_clone2, entity).is_some_and(|ground| ground.sliding)
+    });
+
+    // Up for entities without a CharacterController
+    engine.register_fn("ground_normal", move |entity: i64| -> Vec3 {
+        ground(&state, entity).map_or(Vec3::Y, |ground| ground.normal)
+    });
+
+    engine.register_fn(
+        "move_character",
+        move |entity: i64, movement: Vec3, jump: bool, sprint: bool| {
+            command_queue.lock().unwrap().push((
+                EntityId(entity as u64),
+                CharacterInput {
+                    movement,
+                    jump,
+                    sprint,
+                },
+            ));
+        },
+    );
+}
+
+fn ground(state: &CharacterStateHandle, entity: i64) -> Option<CharacterGround> {
+    state.lock().unwrap().get(&EntityId(entity as u64)).copied()
+}
+
+/// Take the snapshot the character functions read; call before running scripts
+pub fn refresh_character_state(state: &CharacterStateHandle, scene: &Scene) {
+    let mut state = state.lock().unwrap();
+    state.clear();
+    for entity in scene.entities() {
+        if let Some(controller) = entity.get_component::<CharacterController>() {
+            state.insert(
+                entity.id,
+                CharacterGround {
+                    grounded: controller.grounded,
+                    sliding: controller.is_sliding(),
+                    normal: controller.ground_normal,
+                },
+            );
+        }
+    }
+}
+
+/// Apply queued moves to the scene's character controllers, dropping any
+/// for entities without one. Movement is kept on the ground plane and to
+/// length 1; a jump not yet taken is kept.
+pub fn apply_character_commands(queue: &CharacterCommandQueue, scene: &mut Scene) {
+    for (entity, mut input) in queue.lock().unwrap().drain(..) {
+        let Some(controller) = scene
+            .get_entity_mut(entity)
+            .and_then(|e| e.get_component_mut::<CharacterController>())
+        else {
+            continue;
+        };
+        if !input.movement.is_finite() {
+            input.movement = Vec3::ZERO;
+        }
+        input.movement = Vec3::new(input.movement.x, 0.0, input.movement.z).clamp_length_max(1.0);
+        input.jump |= controller.input.jump;
+        controller.input = input;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_reads_character_ground() {
+        let mut scene = Scene::new("Test".to_string());
+        let id = scene.create_entity("Player".to_string());
+        let mut controller = CharacterController::new();
+        controller.ground_normal = Vec3::new(0.8, 0.6, 0.0);
+        scene.get_entity_mut(id).unwrap().add_component(controller);
+
+        let state = CharacterStateHandle::default();
+        let mut engine = Engine::new();
+        register_character_api(&mut engine, state.clone(), CharacterCommandQueue::default());
+        refresh_character_state(&state, &scene);
+
+        let grounded: bool = engine.eval(&format!("is_grounded({})", id.0)).unwrap();
+        let sliding: bool = engine.eval(&format!("is_sliding({})", id.0)).unwrap();
+        assert!(!grounded && sliding);
+        let normal: Vec3 = engine.eval(&format!("ground_normal({})", id.0)).unwrap();
+        assert_eq!(normal, Vec3::new(0.8, 0.6, 0.0));
+        let missing: Vec3 = engine.eval("ground_normal(999)").unwrap();
+        assert_eq!(missing, Vec3::Y);
+    }
+
+    #[test]
+    fn test_script_moves_character() {
+        let mut scene = Scene::new("Test".to_string());
+        let id = scene.create_entity("Player".to_string());
+        scene
+            .get_entity_mut(id)
+            .unwrap()
+            .add_component(CharacterController::new());
+
+        let queue = CharacterCommandQueue::default();
+        let mut engine = Engine::new();
+        register_character_api(&mut engine, CharacterStateHandle::default(), queue.clone());
+        // Movement is flattened and clamped; moves for missing characters are dropped
+        let mut scope = rhai::Scope::new();
+        scope.push("movement", Vec3::new(0.0, 5.0, 2.0));
+        engine
+            .run_with_scope(
+                &mut scope,
+                &format!(
+                    "move_character({0}, movement, true, false); move_character(999, movement, false, false)",
+                    id.0
+                ),
+            )
+            .unwrap();
+        apply_character_commands(&queue, &mut scene);
+
+        let controller = scene
+            .get_entity(id)
+            .unwrap()
+            .get_component::<CharacterController>()
+            .unwrap();
+        assert_eq!(controller.input.movement, Vec3::Z);
+        assert!(controller.input.jump && !controller.input.sprint);
+        assert!(queue.lock().unwrap().is_empty());
+    }
+}
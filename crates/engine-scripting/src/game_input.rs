@@ -0,0 +1,238 @@
+// Game input for scripts - what the player pressed, as plain data
+//
+// The game loop fills a GameInput from window events while the game runs
+// and scripts read it. Keys are named like winit's KeyCode ("KeyW",
+// "Space", "ArrowLeft"), mouse buttons "Left", "Right" and "Middle". Being
+// plain data, one frame's input can be recorded into a replay and fed back
+// later to drive the same session again. Actions ("Jump", "Fire") go through
+// the project's input map (project.ron's input_map, or the default game
+// controls); its key and mouse bindings are looked up in the same data, so
+// replays need nothing extra. Gamepad and axis bindings aren't read here.
+
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use engine_input::{BindingType, InputAction, InputActionMap};
+use rhai::{Dynamic, Engine};
+use serde::{Deserialize, Serialize};
+
+/// Input for one simulation frame
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameInput {
+    /// Keys held down
+    pub keys_down: BTreeSet<String>,
+    /// Keys pressed since the last frame
+    pub keys_pressed: BTreeSet<String>,
+    /// Mouse buttons held down
+    pub mouse_down: BTreeSet<String>,
+    /// Mouse movement since the last frame, in pixels
+    pub mouse_delta: [f32; 2],
+    /// Wheel movement since the last frame, in lines
+    pub scroll: f32,
+}
+
+/// Input shared between the game loop and scripts
+pub type SharedGameInput = Arc<Mutex<GameInput>>;
+
+impl GameInput {
+    pub fn press_key(&mut self, key: &str) {
+        if self.keys_down.insert(key.to_string()) {
+            self.keys_pressed.insert(key.to_string());
+        }
+    }
+
+    pub fn release_key(&mut self, key: &str) {
+        self.keys_down.remove(key);
+    }
+
+    pub fn set_mouse_button(&mut self, button: &str, down: bool) {
+        if down {
+            self.mouse_down.insert(button.to_string());
+        } else {
+            self.mouse_down.remove(button);
+        }
+    }
+
+    pub fn add_mouse_delta(&mut self, x: f32, y: f32) {
+        self.mouse_delta[0] += x;
+        self.mouse_delta[1] += y;
+    }
+
+    pub fn add_scroll(&mut self, lines: f32) {
+        self.scroll += lines;
+    }
+
+    /// Forget what only lasts one frame (presses, movement), keeping held
+    /// keys and buttons
+    pub fn end_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.mouse_delta = [0.0; 2];
+        self.scroll = 0.0;
+    }
+
+    /// Release everything, e.g. when the window loses focus
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// True if a key or mouse binding of `action` in `map` is held, with its
+    /// modifier keys
+    pub fn action_down(&self, map: &InputActionMap, action: &str) -> bool {
+        map.get_bindings(&InputAction::new(action))
+            .into_iter()
+            .any(|binding| {
+                let held = match &binding.binding {
+                    BindingType::Key(key) => self.keys_down.contains(&format!("{:?}", key.0)),
+                    BindingType::MouseButton(button) => {
+                        self.mouse_down.contains(&format!("{:?}", button))
+                    }
+                    _ => false,
+                };
+                held && self.modifiers_down(binding)
+            })
+    }
+
+    /// True if a key binding of `action` in `map` was pressed since the last
+    /// frame, with its modifier keys held
+    pub fn action_just_pressed(&self, map: &InputActionMap, action: &str) -> bool {
+        map.get_bindings(&InputAction::new(action))
+            .into_iter()
+            .any(|binding| match &binding.binding {
+                BindingType::Key(key) => {
+                    self.keys_pressed.contains(&format!("{:?}", key.0))
+                        && self.modifiers_down(binding)
+                }
+                _ => false,
+            })
+    }
+
+    fn modifiers_down(&self, binding: &engine_input::ActionBinding) -> bool {
+        binding
+            .modifiers
+            .iter()
+            .all(|key| self.keys_down.contains(&format!("{:?}", key.0)))
+    }
+}
+
+/// The project's input map: the file project.ron's input_map names, or the
+/// default game controls if it names none or can't be read
+pub fn project_action_map() -> Arc<InputActionMap> {
+    static MAP: OnceLock<Arc<InputActionMap>> = OnceLock::new();
+    MAP.get_or_init(|| {
+        let map = match &engine_core::project::current().input_map {
+            Some(path) => InputActionMap::load_from_file(path).unwrap_or_else(|e| {
+                log::warn!("Failed to load input map {}: {}; using the default controls", path, e);
+                InputActionMap::default_game_controls()
+            }),
+            None => InputActionMap::default_game_controls(),
+        };
+        Arc::new(map)
+    })
+    .clone()
+}
+
+/// Register game input functions with Rhai engine
+pub fn register_game_input_api(engine: &mut Engine, input: SharedGameInput) {
+    let input_clone1 = input.clone();
+    let input_clone2 = input.clone();
+    let input_clone3 = input.clone();
+    let input_clone4 = input.clone();
+
+    engine
+        .register_fn("is_key_down", move |key: &str| {
+            input_clone1.lock().unwrap().keys_down.contains(key)
+        })
+        .register_fn("is_key_just_pressed", move |key: &str| {
+            input_clone2.lock().unwrap().keys_pressed.contains(key)
+        })
+        .register_fn("is_mouse_down", move |button: &str| {
+            input_clone3.lock().unwrap().mouse_down.contains(button)
+        });
+
+    engine
+        .register_fn("mouse_motion", move || {
+            let delta = input_clone4.lock().unwrap().mouse_delta;
+            Dynamic::from([delta[0] as f64, delta[1] as f64])
+        })
+        .register_fn("mouse_wheel", move || input.lock().unwrap().scroll as f64);
+}
+
+/// Register is_action_down() and is_action_just_pressed(), which look
+/// actions up in `map`
+pub fn register_game_action_api(
+    engine: &mut Engine,
+    input: SharedGameInput,
+    map: Arc<InputActionMap>,
+) {
+    let input_clone = input.clone();
+    let map_clone = map.clone();
+
+    engine
+        .register_fn("is_action_down", move |action: &str| {
+            input_clone.lock().unwrap().action_down(&map_clone, action)
+        })
+        .register_fn("is_action_just_pressed", move |action: &str| {
+            input.lock().unwrap().action_just_pressed(&map, action)
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presses_last_one_frame() {
+        let input = SharedGameInput::default();
+        let mut engine = Engine::new();
+        register_game_input_api(&mut engine, input.clone());
+
+        input.lock().unwrap().press_key("Space");
+        input.lock().unwrap().add_scroll(2.0);
+        assert!(engine
+            .eval::<bool>(r#"is_key_down("Space") && is_key_just_pressed("Space")"#)
+            .unwrap());
+        assert_eq!(engine.eval::<f64>("mouse_wheel()").unwrap(), 2.0);
+
+        input.lock().unwrap().end_frame();
+        assert!(engine.eval::<bool>(r#"is_key_down("Space")"#).unwrap());
+        assert!(!engine
+            .eval::<bool>(r#"is_key_just_pressed("Space")"#)
+            .unwrap());
+        assert_eq!(engine.eval::<f64>("mouse_wheel()").unwrap(), 0.0);
+
+        // Held keys don't count as pressed again
+        input.lock().unwrap().press_key("Space");
+        assert!(input.lock().unwrap().keys_pressed.is_empty());
+    }
+
+    #[test]
+    fn test_actions_follow_the_input_map() {
+        let input = SharedGameInput::default();
+        let mut map = InputActionMap::new();
+        map.bind(engine_input::ActionBinding::new(
+            "Jump",
+            BindingType::Key(engine_input::actions::SerializableKeyCode(
+                engine_input::KeyCode::KeyJ,
+            )),
+        ));
+        map.bind(engine_input::ActionBinding::new(
+            "Fire",
+            BindingType::MouseButton(engine_input::MouseButton::Left),
+        ));
+        let mut engine = Engine::new();
+        register_game_action_api(&mut engine, input.clone(), Arc::new(map));
+
+        input.lock().unwrap().press_key("Space");
+        assert!(!engine.eval::<bool>(r#"is_action_down("Jump")"#).unwrap());
+
+        input.lock().unwrap().press_key("KeyJ");
+        input.lock().unwrap().set_mouse_button("Left", true);
+        assert!(engine
+            .eval::<bool>(r#"is_action_just_pressed("Jump") && is_action_down("Fire")"#)
+            .unwrap());
+        input.lock().unwrap().end_frame();
+        assert!(!engine.eval::<bool>(r#"is_action_just_pressed("Jump")"#).unwrap());
+        assert!(engine.eval::<bool>(r#"is_action_down("Jump")"#).unwrap());
+    }
+}
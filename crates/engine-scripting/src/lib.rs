@@ -1,14 +1,47 @@
 // Engine Scripting - Rhai runtime
 
+pub mod animator;
 pub mod api;
 pub mod audio;
+pub mod behavior;
+pub mod character;
 pub mod components;
+pub mod game_input;
+pub mod navigation;
+pub mod net;
+pub mod physics;
+pub mod ragdoll;
+pub mod random;
 pub mod runtime;
+pub mod sandbox;
+pub mod save_game;
 pub mod system;
+pub mod time;
 pub mod input;
 
-pub use audio::{register_audio_api, AudioCommand, AudioCommandQueue};
+pub use animator::{register_animator_api, AnimatorCommand, AnimatorCommandQueue};
+pub use audio::{register_audio_api, AudioCommand, AudioCommandQueue, MusicApi};
+pub use character::{
+    register_character_api, CharacterCommandQueue, CharacterGround, CharacterStateHandle,
+};
 pub use components::Script;
+pub use ragdoll::{register_ragdoll_api, RagdollCommand, RagdollCommandQueue};
+pub use game_input::{
+    project_action_map, register_game_action_api, register_game_input_api, GameInput,
+    SharedGameInput,
+};
+pub use navigation::{register_navigation_api, NavCommand, NavScriptState, NavStateHandle};
+pub use net::{
+    register_net_api, IncomingMessage, NetInput, NetRole, NetScriptState, NetStateHandle, OutgoingMessage,
+};
+pub use physics::{register_physics_api, PhysicsQueryHandle};
+pub use random::register_random_api;
 pub use runtime::{CompiledScript, ScriptRuntime};
+pub use sandbox::{check_script, run_snippet, ScriptDiagnostic, SnippetOutput};
+pub use save_game::{
+    register_save_api, SaveGame, SaveRequest, SaveSlots, SaveState, SaveStateHandle, SavedEntity,
+    SavedMusic, SavedValue,
+};
 pub use system::ScriptSystem;
+pub use time::register_time_api;
 pub use input::{register_input_api, SharedInputManager};
@@ -0,0 +1,159 @@
+// Navigation API for scripts - send entities with a NavAgent somewhere
+//
+//   set_destination(ctx.entity_id, target_position);
+//   set_destination(ctx.entity_id, 10.0, 0.0, 4.0);
+//   stop_agent(ctx.entity_id);
+//   if arrived(ctx.entity_id) { ... }
+//
+// Commands are queued and applied to the agents once the scripts have run.
+// arrived() reads a snapshot taken before the scripts run, so an agent sent
+// somewhere this frame reports false until it gets there.
+
+use engine_scene::entity::EntityId;
+use engine_scene::navigation::NavAgent;
+use engine_scene::scene::Scene;
+use glam::Vec3;
+use rhai::Engine;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Navigation change that scripts can request
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NavCommand {
+    SetDestination { entity: EntityId, destination: Vec3 },
+    Stop(EntityId),
+}
+
+/// Commands from scripts and what they can read back about agents
+#[derive(Debug, Default)]
+pub struct NavScriptState {
+    pub commands: Vec<NavCommand>,
+    /// Whether each agent has arrived, as of the start of the frame
+    pub arrived: HashMap<EntityId, bool>,
+}
+
+/// Thread-safe navigation state shared with scripts
+pub type NavStateHandle = Arc<Mutex<NavScriptState>>;
+
+/// Register navigation functions with Rhai engine
+pub fn register_navigation_api(engine: &mut Engine, state: NavStateHandle) {
+    let state_clone1 = state.clone();
+    let state_clone2 = state.clone();
+    let state_clone3 = state.clone();
+
+    engine.register_fn("set_destination", move |entity: i64, destination: Vec3| {
+        set_destination(&state_clone1, entity, destination);
+    });
+
+    engine.register_fn(
+        "set_destination",
+        move |entity: i64, x: f64, y: f64, z: f64| {
+            set_destination(
+                &state_clone2,
+                entity,
+                Vec3::new(x as f32, y as f32, z as f32),
+            );
+        },
+    );
+
+    engine.register_fn("stop_agent", move |entity: i64| {
+        state_clone3
+            .lock()
+            .unwrap()
+            .commands
+            .push(NavCommand::Stop(EntityId(entity as u64)));
+    });
+
+    engine.register_fn("arrived", move |entity: i64| -> bool {
+        state
+            .lock()
+            .unwrap()
+            .arrived
+            .get(&EntityId(entity as u64))
+            .copied()
+            .unwrap_or(false)
+    });
+}
+
+fn set_destination(state: &NavStateHandle, entity: i64, destination: Vec3) {
+    let entity = EntityId(entity as u64);
+    let mut state = state.lock().unwrap();
+    state.commands.push(NavCommand::SetDestination {
+        entity,
+        destination,
+    });
+    state.arrived.insert(entity, false);
+}
+
+/// Take the snapshot arrived() reads; call before running scripts
+pub fn refresh_nav_state(state: &NavStateHandle, scene: &Scene) {
+    let mut state = state.lock().unwrap();
+    state.arrived.clear();
+    for entity in scene.entities() {
+        if let Some(agent) = entity.get_component::<NavAgent>() {
+            state.arrived.insert(entity.id, agent.arrived);
+        }
+    }
+}
+
+/// Apply queued commands to the scene's agents, dropping any for entities
+/// without one
+pub fn apply_nav_commands(state: &NavStateHandle, scene: &mut Scene) {
+    for command in state.lock().unwrap().commands.drain(..) {
+        let (NavCommand::SetDestination { entity, .. } | NavCommand::Stop(entity)) = command;
+        let Some(agent) = scene
+            .get_entity_mut(entity)
+            .and_then(|e| e.get_component_mut::<NavAgent>())
+        else {
+            continue;
+        };
+        match command {
+            NavCommand::SetDestination { destination, .. } => agent.set_destination(destination),
+            NavCommand::Stop(_) => agent.stop(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_sends_agent_and_reads_arrived() {
+        let mut scene = Scene::new("Test".to_string());
+        let id = scene.create_entity("Guard".to_string());
+        let mut agent = NavAgent::default();
+        agent.arrived = true;
+        scene.get_entity_mut(id).unwrap().add_component(agent);
+
+        let state = NavStateHandle::default();
+        let mut engine = Engine::new();
+        register_navigation_api(&mut engine, state.clone());
+        refresh_nav_state(&state, &scene);
+
+        let before: bool = engine.eval(&format!("arrived({})", id.0)).unwrap();
+        assert!(before);
+        let mut scope = rhai::Scope::new();
+        scope.push("target", Vec3::new(4.0, 0.0, 2.0));
+        let after: bool = engine
+            .eval_with_scope(
+                &mut scope,
+                &format!(
+                    "set_destination({0}, target); set_destination(999, 1.0, 0.0, 0.0); arrived({0})",
+                    id.0
+                ),
+            )
+            .unwrap();
+        assert!(!after);
+        apply_nav_commands(&state, &mut scene);
+
+        let agent = scene
+            .get_entity(id)
+            .unwrap()
+            .get_component::<NavAgent>()
+            .unwrap();
+        assert_eq!(agent.destination, Some(Vec3::new(4.0, 0.0, 2.0)));
+        assert!(!agent.arrived);
+        assert!(state.lock().unwrap().commands.is_empty());
+    }
+}
@@ -0,0 +1,236 @@
+// Networking API - scripts send and receive named messages
+//
+// Scripts don't see sockets. net_send queues a message for the game loop to
+// deliver (from a client to the server, from the server to every client),
+// and the loop puts what arrives into the inbox for net_receive. On the
+// server, messages say which client they came from. On a client,
+// net_input drives the player's predicted character.
+
+use std::sync::{Arc, Mutex};
+
+use glam::Vec3;
+use rhai::{Array, Dynamic, Engine, Map};
+
+use crate::save_game::SavedValue;
+
+/// What this instance of the game is in a networked session
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NetRole {
+    #[default]
+    Offline,
+    Server,
+    Client,
+}
+
+/// A message a script wants sent
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutgoingMessage {
+    /// Client to send to (server only; None = the server, or every client)
+    pub target: Option<u32>,
+    pub name: String,
+    pub data: SavedValue,
+}
+
+/// A message that arrived for scripts
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncomingMessage {
+    /// Client it came from (None when it came from the server)
+    pub from: Option<u32>,
+    pub name: String,
+    pub data: SavedValue,
+}
+
+/// Movement for the client's predicted character, set by net_input
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NetInput {
+    pub movement: Vec3,
+    pub jump: bool,
+    pub sprint: bool,
+}
+
+/// Session state shared between scripts and the game loop
+#[derive(Debug, Default)]
+pub struct NetScriptState {
+    pub role: NetRole,
+    /// This client's id once connected
+    pub client_id: Option<u32>,
+    pub outgoing: Vec<OutgoingMessage>,
+    pub incoming: Vec<IncomingMessage>,
+    /// Input for this frame; the game loop takes it
+    pub input: Option<NetInput>,
+}
+
+impl NetScriptState {
+    /// Forget queued messages, e.g. when a session ends
+    pub fn clear(&mut self) {
+        self.outgoing.clear();
+        self.incoming.clear();
+        self.input = None;
+    }
+}
+
+/// Thread-safe net state shared with the script engine
+pub type NetStateHandle = Arc<Mutex<NetScriptState>>;
+
+fn queue(state: &NetStateHandle, target: Option<u32>, name: &str, data: &Dynamic) -> bool {
+    let Some(data) = SavedValue::from_dynamic(data) else {
+        log::warn!("net_send: '{}' data can't be sent over the network", name);
+        return false;
+    };
+    let mut state = state.lock().unwrap();
+    if state.role == NetRole::Offline {
+        return false;
+    }
+    state.outgoing.push(OutgoingMessage {
+        target,
+        name: name.to_string(),
+        data,
+    });
+    true
+}
+
+/// Register net_role, is_server, is_client, net_client_id, net_send,
+/// net_send_to, net_receive and net_input
+pub fn register_net_api(engine: &mut Engine, state: NetStateHandle) {
+    let role_state = state.clone();
+    engine.register_fn("net_role", move || {
+        match role_state.lock().unwrap().role {
+            NetRole::Offline => "offline",
+            NetRole::Server => "server",
+            NetRole::Client => "client",
+        }
+        .to_string()
+    });
+
+    let server_state = state.clone();
+    engine.register_fn("is_server", move || {
+        server_state.lock().unwrap().role == NetRole::Server
+    });
+
+    let client_state = state.clone();
+    engine.register_fn("is_client", move || {
+        client_state.lock().unwrap().role == NetRole::Client
+    });
+
+    // -1 until connected
+    let id_state = state.clone();
+    engine.register_fn("net_client_id", move || {
+        id_state
+            .lock()
+            .unwrap()
+            .client_id
+            .map_or(-1, |id| id as i64)
+    });
+
+    let send_state = state.clone();
+    engine.register_fn("net_send", move |name: &str, data: Dynamic| {
+        queue(&send_state, None, name, &data)
+    });
+
+    let send_to_state = state.clone();
+    engine.register_fn(
+        "net_send_to",
+        move |client: i64, name: &str, data: Dynamic| {
+            if send_to_state.lock().unwrap().role != NetRole::Server || client < 0 {
+                return false;
+            }
+            queue(&send_to_state, Some(client as u32), name, &data)
+        },
+    );
+
+    // A jump asked for earlier in the frame isn't lost to a later call
+    let input_state = state.clone();
+    engine.register_fn(
+        "net_input",
+        move |movement: Vec3, jump: bool, sprint: bool| {
+            let mut state = input_state.lock().unwrap();
+            let jump = jump || state.input.is_some_and(|input| input.jump);
+            state.input = Some(NetInput {
+                movement,
+                jump,
+                sprint,
+            });
+        },
+    );
+
+    // Takes every waiting message with this name, as #{ from, data } maps
+    // (from is -1 for messages from the server)
+    engine.register_fn("net_receive", move |name: &str| {
+        let mut state = state.lock().unwrap();
+        let (matching, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut state.incoming)
+            .into_iter()
+            .partition(|message| message.name == name);
+        state.incoming = rest;
+        matching
+            .into_iter()
+            .map(|message| {
+                let mut map = Map::new();
+                map.insert(
+                    "from".into(),
+                    Dynamic::from(message.from.map_or(-1, |id| id as i64)),
+                );
+                map.insert("data".into(), message.data.to_dynamic());
+                Dynamic::from(map)
+            })
+            .collect::<Array>()
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scripts_queue_and_receive_messages() {
+        let state = NetStateHandle::default();
+        let mut engine = Engine::new();
+        register_net_api(&mut engine, state.clone());
+
+        // Nothing is queued while offline
+        assert!(!engine.eval::<bool>("net_send(\"hello\", 1)").unwrap());
+        assert_eq!(engine.eval::<String>("net_role()").unwrap(), "offline");
+
+        state.lock().unwrap().role = NetRole::Server;
+        assert!(engine
+            .eval::<bool>("net_send_to(3, \"score\", #{ points: 10 })")
+            .unwrap());
+        let sent = state.lock().unwrap().outgoing.clone();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].target, Some(3));
+        assert_eq!(sent[0].name, "score");
+
+        state.lock().unwrap().incoming = vec![
+            IncomingMessage {
+                from: Some(3),
+                name: "jump".to_string(),
+                data: SavedValue::Int(2),
+            },
+            IncomingMessage {
+                from: Some(4),
+                name: "chat".to_string(),
+                data: SavedValue::String("hi".to_string()),
+            },
+        ];
+        let received = engine
+            .eval::<i64>(
+                "let m = net_receive(\"jump\"); m.len() * 100 + m[0].from * 10 + m[0].data",
+            )
+            .unwrap();
+        assert_eq!(received, 132);
+        assert_eq!(state.lock().unwrap().incoming.len(), 1);
+
+        // A later call this frame keeps the earlier jump
+        let mut scope = rhai::Scope::new();
+        scope.push("forward", Vec3::Z);
+        scope.push("right", Vec3::X);
+        engine
+            .run_with_scope(
+                &mut scope,
+                "net_input(forward, true, false); net_input(right, false, true);",
+            )
+            .unwrap();
+        let input = state.lock().unwrap().input.unwrap();
+        assert_eq!(input.movement, Vec3::X);
+        assert!(input.jump && input.sprint);
+    }
+}
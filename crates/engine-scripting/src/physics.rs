@@ -0,0 +1,336 @@
+// Physics query API for scripts - sweep shapes and find what's in a volume
+//
+//   let hit = sphere_cast(ctx.position, down, 0.3, 2.0);
+//   if hit != () { print(hit.entity); print(hit.point); }
+//   let hit = box_cast(origin, direction, half_extents, max_distance);
+//   let hit = capsule_cast(origin, direction, radius, half_height, max_distance);
+//   for entity in overlap_sphere(center, 1.5) { ... }
+//   let entities = overlap_box(center, half_extents);
+//   let entities = overlap_capsule(center, radius, half_height);
+//
+// Hits are maps with entity, point, normal and distance, or () when nothing
+// was hit. Every query takes an optional last argument, the collision layers
+// to hit: overlap_sphere(center, 1.5, [3]) only finds enemies. Triggers are
+// never hit.
+//
+// The physics world is lent to the query functions while scripts run (see
+// `with_physics_world`), so queries see it as the last physics step left it:
+// bodies moved by scripts this frame are found where they were.
+
+use engine_physics::{OverlapQuery, PhysicsWorld, QueryShape, ShapeCastQuery};
+use glam::Vec3;
+use rhai::{Array, Dynamic, Engine, Map};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Thread-safe physics world, lent to scripts while they run
+pub type PhysicsQueryHandle = Arc<Mutex<Option<PhysicsWorld>>>;
+
+/// Register physics query functions with Rhai engine
+pub fn register_physics_api(engine: &mut Engine, state: PhysicsQueryHandle) {
+    let state_clone1 = state.clone();
+    let state_clone2 = state.clone();
+    let state_clone3 = state.clone();
+    let state_clone4 = state.clone();
+    let state_clone5 = state.clone();
+    let state_clone6 = state.clone();
+    let state_clone7 = state.clone();
+    let state_clone8 = state.clone();
+    let state_clone9 = state.clone();
+    let state_clone10 = state.clone();
+    let state_clone11 = state.clone();
+
+    engine.register_fn(
+        "sphere_cast",
+        move |origin: Vec3, direction: Vec3, radius: f64, max_distance: f64| {
+            let shape = QueryShape::Sphere {
+                radius: radius as f32,
+            };
+            shape_cast(&state_clone1, shape, origin, direction, max_distance, None)
+        },
+    );
+    engine.register_fn(
+        "sphere_cast",
+        move |origin: Vec3, direction: Vec3, radius: f64, max_distance: f64, layers: Array| {
+            let shape = QueryShape::Sphere {
+                radius: radius as f32,
+            };
+            shape_cast(
+                &state_clone2,
+                shape,
+                origin,
+                direction,
+                max_distance,
+                Some(layers),
+            )
+        },
+    );
+
+    engine.register_fn(
+        "box_cast",
+        move |origin: Vec3, direction: Vec3, half_extents: Vec3, max_distance: f64| {
+            let shape = QueryShape::Box { half_extents };
+            shape_cast(&state_clone3, shape, origin, direction, max_distance, None)
+        },
+    );
+    engine.register_fn(
+        "box_cast",
+        move |origin: Vec3,
+              direction: Vec3,
+              half_extents: Vec3,
+              max_distance: f64,
+              layers: Array| {
+            let shape = QueryShape::Box { half_extents };
+            shape_cast(
+                &state_clone4,
+                shape,
+                origin,
+                direction,
+                max_distance,
+                Some(layers),
+            )
+        },
+    );
+
+    engine.register_fn(
+        "capsule_cast",
+        move |origin: Vec3, direction: Vec3, radius: f64, half_height: f64, max_distance: f64| {
+            let shape = capsule(radius, half_height);
+            shape_cast(&state_clone5, shape, origin, direction, max_distance, None)
+        },
+    );
+    engine.register_fn(
+        "capsule_cast",
+        move |origin: Vec3,
+              direction: Vec3,
+              radius: f64,
+              half_height: f64,
+              max_distance: f64,
+              layers: Array| {
+            let shape = capsule(radius, half_height);
+            shape_cast(
+                &state_clone6,
+                shape,
+                origin,
+                direction,
+                max_distance,
+                Some(layers),
+            )
+        },
+    );
+
+    engine.register_fn("overlap_sphere", move |center: Vec3, radius: f64| {
+        let shape = QueryShape::Sphere {
+            radius: radius as f32,
+        };
+        overlap(&state_clone7, shape, center, None)
+    });
+    engine.register_fn(
+        "overlap_sphere",
+        move |center: Vec3, radius: f64, layers: Array| {
+            let shape = QueryShape::Sphere {
+                radius: radius as f32,
+            };
+            overlap(&state_clone8, shape, center, Some(layers))
+        },
+    );
+
+    engine.register_fn("overlap_box", move |center: Vec3, half_extents: Vec3| {
+        overlap(
+            &state_clone9,
+            QueryShape::Box { half_extents },
+            center,
+            None,
+        )
+    });
+    engine.register_fn(
+        "overlap_box",
+        move |center: Vec3, half_extents: Vec3, layers: Array| {
+            overlap(
+                &state_clone10,
+                QueryShape::Box { half_extents },
+                center,
+                Some(layers),
+            )
+        },
+    );
+
+    engine.register_fn(
+        "overlap_capsule",
+        move |center: Vec3, radius: f64, half_height: f64| {
+            overlap(&state_clone11, capsule(radius, half_height), center, None)
+        },
+    );
+    engine.register_fn(
+        "overlap_capsule",
+        move |center: Vec3, radius: f64, half_height: f64, layers: Array| {
+            overlap(&state, capsule(radius, half_height), center, Some(layers))
+        },
+    );
+}
+
+fn capsule(radius: f64, half_height: f64) -> QueryShape {
+    QueryShape::Capsule {
+        half_height: half_height as f32,
+        radius: radius as f32,
+    }
+}
+
+/// Layer numbers from a script array, leaving out anything that isn't a
+/// layer (1-32)
+fn layer_list(layers: &Array) -> Vec<u32> {
+    layers
+        .iter()
+        .filter_map(|layer| layer.as_int().ok())
+        .filter(|layer| (1..=32).contains(layer))
+        .map(|layer| layer as u32)
+        .collect()
+}
+
+fn shape_cast(
+    state: &PhysicsQueryHandle,
+    shape: QueryShape,
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f64,
+    layers: Option<Array>,
+) -> Dynamic {
+    let state = state.lock().unwrap();
+    let Some(physics) = state.as_ref() else {
+        return Dynamic::UNIT;
+    };
+    if direction.length_squared() == 0.0 {
+        return Dynamic::UNIT;
+    }
+
+    let mut query = ShapeCastQuery::new(shape, origin, direction, max_distance as f32);
+    if let Some(layers) = layers {
+        query = query.with_layers(&layer_list(&layers));
+    }
+    let Some(hit) = physics.shape_cast(&query) else {
+        return Dynamic::UNIT;
+    };
+
+    let mut map = Map::new();
+    map.insert("entity".into(), Dynamic::from(hit.entity_id.0 as i64));
+    map.insert("point".into(), Dynamic::from(hit.point));
+    map.insert("normal".into(), Dynamic::from(hit.normal));
+    map.insert("distance".into(), Dynamic::from(hit.distance as f64));
+    Dynamic::from(map)
+}
+
+fn overlap(
+    state: &PhysicsQueryHandle,
+    shape: QueryShape,
+    center: Vec3,
+    layers: Option<Array>,
+) -> Array {
+    let state = state.lock().unwrap();
+    let Some(physics) = state.as_ref() else {
+        return Array::new();
+    };
+
+    let mut query = OverlapQuery::new(shape, center);
+    if let Some(layers) = layers {
+        query = query.with_layers(&layer_list(&layers));
+    }
+    physics
+        .overlap(&query)
+        .into_iter()
+        .map(|entity| Dynamic::from(entity.0 as i64))
+        .collect()
+}
+
+/// Lend `physics` to the query functions while `f` runs scripts, then give
+/// it back (also if `f` panics). The world is moved, not copied, and is
+/// replaced by an empty one meanwhile, so nothing else may use it during `f`.
+pub fn with_physics_world<R>(
+    state: &PhysicsQueryHandle,
+    physics: &mut PhysicsWorld,
+    f: impl FnOnce() -> R,
+) -> R {
+    struct Lent<'a> {
+        state: &'a PhysicsQueryHandle,
+        physics: &'a mut PhysicsWorld,
+    }
+
+    impl Drop for Lent<'_> {
+        fn drop(&mut self) {
+            let lent = self
+                .state
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take();
+            if let Some(world) = lent {
+                *self.physics = world;
+            }
+        }
+    }
+
+    let gravity = physics.gravity;
+    *state.lock().unwrap() = Some(std::mem::replace(physics, PhysicsWorld::new(gravity)));
+    let _lent = Lent { state, physics };
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_physics::{Collider, ColliderGeometry, PhysicsSync, RigidBody};
+    use engine_scene::scene::Scene;
+
+    #[test]
+    fn test_script_sweeps_and_overlaps() {
+        // A floor with its top at y = 0 and a guard standing on it at x = 2
+        let mut scene = Scene::new("Test".to_string());
+        let floor = scene.create_entity("Floor".to_string());
+        let entity = scene.get_entity_mut(floor).unwrap();
+        entity.transform.position = Vec3::new(0.0, -0.5, 0.0);
+        entity.add_component(RigidBody::static_body());
+        entity.add_component(Collider::box_collider(Vec3::new(5.0, 0.5, 5.0)));
+        let guard = scene.create_entity("Guard".to_string());
+        let entity = scene.get_entity_mut(guard).unwrap();
+        entity.transform.position = Vec3::new(2.0, 1.0, 0.0);
+        entity.add_component(RigidBody::static_body());
+        entity.add_component(Collider::capsule(0.5, 0.5));
+        let mut physics = PhysicsWorld::new(Vec3::new(0.0, -9.81, 0.0));
+        PhysicsSync::initialize_physics(&mut physics, &scene, &ColliderGeometry::default()).unwrap();
+        physics.query_pipeline.update(&physics.collider_set);
+
+        let state = PhysicsQueryHandle::default();
+        let mut engine = Engine::new();
+        register_physics_api(&mut engine, state.clone());
+        let mut scope = rhai::Scope::new();
+        scope.push("above", Vec3::new(0.0, 5.0, 0.0));
+        scope.push("down", Vec3::NEG_Y);
+        scope.push("reach", Vec3::new(1.5, 1.0, 0.0));
+        scope.push("half_extents", Vec3::new(1.0, 0.5, 0.5));
+
+        // Nothing to query unless a world is lent
+        let hit: Dynamic = engine
+            .eval_with_scope(&mut scope, "sphere_cast(above, down, 0.5, 10.0)")
+            .unwrap();
+        assert!(hit.is_unit());
+
+        let mut eval = |script: &str| -> Dynamic {
+            with_physics_world(&state, &mut physics, || {
+                engine.eval_with_scope(&mut scope, script).unwrap()
+            })
+        };
+        let hit = eval("sphere_cast(above, down, 0.5, 10.0)").cast::<Map>();
+        assert_eq!(hit["entity"].as_int().unwrap(), floor.0 as i64);
+        assert!((hit["distance"].as_float().unwrap() - 4.5).abs() < 1e-3);
+        assert!(eval("capsule_cast(above, down, 0.3, 0.4, 2.0)").is_unit());
+
+        let hits = eval("overlap_box(reach, half_extents)").cast::<Array>();
+        let hits: Vec<i64> = hits.iter().map(|id| id.as_int().unwrap()).collect();
+        assert_eq!(hits, vec![guard.0 as i64]);
+        // Layer numbers that aren't layers filter everything out
+        assert!(eval("overlap_box(reach, half_extents, [0, 99])")
+            .cast::<Array>()
+            .is_empty());
+
+        // The world is handed back afterwards
+        assert!(state.lock().unwrap().is_none());
+        assert!(physics.get_body_handle(guard).is_some());
+    }
+}
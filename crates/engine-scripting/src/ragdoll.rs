@@ -0,0 +1,109 @@
+// Ragdoll API for scripts - switch a character with a RagdollRig between
+// animation and physics
+//
+//   ragdoll(ctx.entity_id);             // go limp, e.g. on death
+//   ragdoll_upper_body(ctx.entity_id);  // hit reaction, legs keep animating
+//   ragdoll_get_up(ctx.entity_id);      // blend back to animation
+//
+// Calls are queued and applied to the entities' RagdollRig components once
+// the scripts have run; the physics ragdoll system acts on them next step.
+
+use engine_scene::components::RagdollRig;
+use engine_scene::entity::EntityId;
+use engine_scene::scene::Scene;
+use rhai::Engine;
+use std::sync::{Arc, Mutex};
+
+/// Ragdoll change that scripts can request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RagdollCommand {
+    Activate(EntityId),
+    ActivateUpperBody(EntityId),
+    GetUp(EntityId),
+}
+
+/// Thread-safe ragdoll command queue
+pub type RagdollCommandQueue = Arc<Mutex<Vec<RagdollCommand>>>;
+
+/// Register ragdoll functions with Rhai engine
+pub fn register_ragdoll_api(engine: &mut Engine, command_queue: RagdollCommandQueue) {
+    let queue_clone1 = command_queue.clone();
+    let queue_clone2 = command_queue.clone();
+
+    engine.register_fn("ragdoll", move |entity: i64| {
+        queue_clone1
+            .lock()
+            .unwrap()
+            .push(RagdollCommand::Activate(EntityId(entity as u64)));
+    });
+
+    engine.register_fn("ragdoll_upper_body", move |entity: i64| {
+        queue_clone2
+            .lock()
+            .unwrap()
+            .push(RagdollCommand::ActivateUpperBody(EntityId(entity as u64)));
+    });
+
+    engine.register_fn("ragdoll_get_up", move |entity: i64| {
+        command_queue
+            .lock()
+            .unwrap()
+            .push(RagdollCommand::GetUp(EntityId(entity as u64)));
+    });
+}
+
+/// Apply queued commands to the scene's ragdoll rigs, dropping any for
+/// entities without one
+pub fn apply_ragdoll_commands(queue: &RagdollCommandQueue, scene: &mut Scene) {
+    for command in queue.lock().unwrap().drain(..) {
+        let (RagdollCommand::Activate(entity)
+        | RagdollCommand::ActivateUpperBody(entity)
+        | RagdollCommand::GetUp(entity)) = command;
+        let Some(rig) = scene
+            .get_entity_mut(entity)
+            .and_then(|e| e.get_component_mut::<RagdollRig>())
+        else {
+            continue;
+        };
+        match command {
+            RagdollCommand::Activate(_) => rig.activate(),
+            RagdollCommand::ActivateUpperBody(_) => rig.activate_upper_body(),
+            RagdollCommand::GetUp(_) => rig.get_up(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_scene::components::RagdollMode;
+
+    #[test]
+    fn test_script_requests_ragdoll() {
+        let mut scene = Scene::new("Test".to_string());
+        let id = scene.create_entity("Hero".to_string());
+        scene
+            .get_entity_mut(id)
+            .unwrap()
+            .add_component(RagdollRig::default());
+
+        let queue = RagdollCommandQueue::default();
+        let mut engine = Engine::new();
+        register_ragdoll_api(&mut engine, queue.clone());
+        engine
+            .run(&format!(
+                "ragdoll_upper_body({0}); ragdoll({0}); ragdoll(999);",
+                id.0
+            ))
+            .unwrap();
+        apply_ragdoll_commands(&queue, &mut scene);
+
+        let rig = scene
+            .get_entity(id)
+            .unwrap()
+            .get_component::<RagdollRig>()
+            .unwrap();
+        assert_eq!(rig.requested, Some(RagdollMode::Full));
+        assert!(queue.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,54 @@
+// Random API for scripts - seeded from the play session
+//
+// random(), random_range() and random_int() draw from the session's
+// SimRng (engine_core::determinism), so with a fixed seed a script rolls
+// the same numbers every run and a replay reproduces them.
+
+use engine_core::determinism::SharedRng;
+use rhai::Engine;
+
+/// Register random functions with Rhai engine
+pub fn register_random_api(engine: &mut Engine, rng: SharedRng) {
+    let rng_clone1 = rng.clone();
+    let rng_clone2 = rng.clone();
+
+    // Uniform in [0, 1)
+    engine.register_fn("random", move || {
+        rng_clone1.lock().unwrap().next_f32() as f64
+    });
+
+    // Uniform in [min, max)
+    engine.register_fn("random_range", move |min: f64, max: f64| {
+        rng_clone2.lock().unwrap().range(min as f32, max as f32) as f64
+    });
+
+    // Whole number in [min, max], e.g. random_int(1, 6) for a die
+    engine.register_fn("random_int", move |min: i64, max: i64| {
+        rng.lock().unwrap().range_i64(min, max)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_core::determinism::SimRng;
+
+    #[test]
+    fn test_same_seed_same_rolls() {
+        let roll = |seed| {
+            let mut engine = Engine::new();
+            register_random_api(&mut engine, SimRng::new(seed).shared());
+            engine
+                .eval::<rhai::Array>("[random(), random_range(5.0, 10.0), random_int(1, 6)]")
+                .unwrap()
+                .into_iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+        };
+        let first = roll(9);
+        assert_eq!(first, roll(9));
+        assert_ne!(first, roll(10));
+        let die: i64 = first[2].parse().unwrap();
+        assert!((1..=6).contains(&die));
+    }
+}
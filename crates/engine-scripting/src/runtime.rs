@@ -11,6 +11,8 @@ use engine_scene::entity::EntityId;
 pub struct ScriptRuntime {
     engine: Engine,
     scripts: HashMap<EntityId, CompiledScript>,
+    /// Constants pushed into every script scope (e.g. the `music` object)
+    globals: Vec<(String, Dynamic)>,
 }
 
 /// Compiled script with AST and scope
@@ -31,6 +33,7 @@ impl ScriptRuntime {
         Self {
             engine,
             scripts: HashMap::new(),
+            globals: Vec::new(),
         }
     }
 
@@ -40,7 +43,10 @@ impl ScriptRuntime {
             anyhow::anyhow!("Script compilation error for entity {:?}: {}", entity_id, e)
         })?;
 
-        let scope = Scope::new();
+        let mut scope = Scope::new();
+        for (name, value) in &self.globals {
+            scope.push_constant_dynamic(name.clone(), value.clone());
+        }
 
         self.scripts.insert(
             entity_id,
@@ -97,6 +103,32 @@ impl ScriptRuntime {
         Ok(result)
     }
 
+    /// Evaluate a standalone snippet (e.g. typed into the editor console)
+    /// with the global constants in scope
+    pub fn eval(&mut self, source: &str) -> Result<Dynamic> {
+        let mut scope = Scope::new();
+        for (name, value) in &self.globals {
+            scope.push_constant_dynamic(name.clone(), value.clone());
+        }
+
+        let result = self
+            .engine
+            .eval_with_scope::<Dynamic>(&mut scope, source)
+            .map_err(|e| anyhow::anyhow!("Script eval error: {}", e))?;
+
+        Ok(result)
+    }
+
+    /// Each script's scope, by entity
+    pub fn scopes(&self) -> impl Iterator<Item = (EntityId, &Scope<'static>)> {
+        self.scripts.iter().map(|(&id, script)| (id, &script.scope))
+    }
+
+    /// A script's scope, to set variables in
+    pub fn scope_mut(&mut self, entity_id: EntityId) -> Option<&mut Scope<'static>> {
+        self.scripts.get_mut(&entity_id).map(|script| &mut script.scope)
+    }
+
     /// Remove a script
     pub fn remove_script(&mut self, entity_id: EntityId) -> bool {
         self.scripts.remove(&entity_id).is_some()
@@ -107,6 +139,17 @@ impl ScriptRuntime {
         self.scripts.contains_key(&entity_id)
     }
 
+    /// Add a constant visible to all scripts, including already loaded ones
+    pub fn add_global(&mut self, name: &str, value: Dynamic) {
+        for script in self.scripts.values_mut() {
+            script
+                .scope
+                .push_constant_dynamic(name.to_string(), value.clone());
+        }
+        self.globals.retain(|(existing, _)| existing != name);
+        self.globals.push((name.to_string(), value));
+    }
+
     /// Get the engine reference (for registering custom types/functions)
     pub fn engine_mut(&mut self) -> &mut Engine {
         &mut self.engine
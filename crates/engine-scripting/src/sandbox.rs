@@ -0,0 +1,208 @@
+// Script sandbox - compile checks and one-off snippets run against a scene
+//
+// Snippets get a fresh engine with the math API and a read-only snapshot of
+// the scene (`entities` and `find_entity(name)`), so they can inspect the
+// scene but never change it. print() and debug() output is captured.
+
+use std::sync::{Arc, Mutex};
+
+use engine_scene::scene::Scene;
+use glam::{Quat, Vec3};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, ParseError, Position, Scope};
+
+use crate::api;
+use crate::components::Script;
+
+/// Operation limit for snippets (same budget as entity scripts)
+const MAX_SNIPPET_OPERATIONS: u64 = 100_000;
+
+/// A compile or runtime error and where in the source it happened
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptDiagnostic {
+    pub message: String,
+    /// 1-based line, if known
+    pub line: Option<usize>,
+    /// 1-based column, if known
+    pub column: Option<usize>,
+}
+
+impl ScriptDiagnostic {
+    fn new(message: String, position: Position) -> Self {
+        Self {
+            message,
+            line: position.line(),
+            column: position.position(),
+        }
+    }
+}
+
+impl From<ParseError> for ScriptDiagnostic {
+    fn from(error: ParseError) -> Self {
+        Self::new(error.err_type().to_string(), error.position())
+    }
+}
+
+impl From<Box<EvalAltResult>> for ScriptDiagnostic {
+    fn from(mut error: Box<EvalAltResult>) -> Self {
+        let position = error.position();
+        error.clear_position();
+        Self::new(error.to_string(), position)
+    }
+}
+
+impl std::fmt::Display for ScriptDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(f, "line {}, column {}: {}", line, column, self.message)
+            }
+            (Some(line), None) => write!(f, "line {}: {}", line, self.message),
+            _ => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for ScriptDiagnostic {}
+
+/// Result of a snippet: its value (None for unit) and everything it printed
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnippetOutput {
+    pub value: Option<String>,
+    pub output: Vec<String>,
+}
+
+/// Compile a script without running it, returning the names of the
+/// functions it defines (e.g. `start`, `update`), sorted
+pub fn check_script(source: &str) -> Result<Vec<String>, ScriptDiagnostic> {
+    let ast = sandbox_engine().compile(source)?;
+    let mut functions: Vec<String> = ast.iter_functions().map(|f| f.name.to_string()).collect();
+    functions.sort();
+    Ok(functions)
+}
+
+/// Evaluate a snippet against a read-only snapshot of the scene
+pub fn run_snippet(source: &str, scene: &Scene) -> Result<SnippetOutput, ScriptDiagnostic> {
+    let mut engine = sandbox_engine();
+
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let print_output = output.clone();
+    engine.on_print(move |text| print_output.lock().unwrap().push(text.to_string()));
+    let debug_output = output.clone();
+    engine.on_debug(move |text, _, _| debug_output.lock().unwrap().push(text.to_string()));
+
+    let entities = Arc::new(scene_snapshot(scene));
+    let lookup = entities.clone();
+    engine.register_fn("find_entity", move |name: &str| -> Dynamic {
+        lookup
+            .iter()
+            .find(|entity| {
+                entity
+                    .read_lock::<Map>()
+                    .and_then(|map| map.get("name").map(|n| n.to_string() == name))
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .unwrap_or(Dynamic::UNIT)
+    });
+
+    let mut scope = Scope::new();
+    scope.push_constant("entities", entities.as_ref().clone());
+    let value = engine.eval_with_scope::<Dynamic>(&mut scope, source)?;
+
+    let output = output.lock().unwrap().clone();
+    Ok(SnippetOutput {
+        value: (!value.is_unit()).then(|| display(&value)),
+        output,
+    })
+}
+
+/// Engine with the math API and limits, but nothing that affects the game
+fn sandbox_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_expr_depths(128, 128);
+    engine.set_max_operations(MAX_SNIPPET_OPERATIONS);
+    engine.set_max_string_size(1 << 20);
+    engine.set_max_array_size(100_000);
+    engine.disable_symbol("eval");
+    api::register_api(&mut engine);
+    engine
+}
+
+/// One map per entity: id, name, position, rotation, scale, parent,
+/// children and has_script
+fn scene_snapshot(scene: &Scene) -> Array {
+    let mut entities: Vec<_> = scene.entities().collect();
+    entities.sort_by_key(|entity| entity.id.0);
+    entities
+        .into_iter()
+        .map(|entity| {
+            let mut map = Map::new();
+            map.insert("id".into(), Dynamic::from(entity.id.0 as i64));
+            map.insert("name".into(), Dynamic::from(entity.name.clone()));
+            map.insert("position".into(), Dynamic::from(entity.transform.position));
+            map.insert("rotation".into(), Dynamic::from(entity.transform.rotation));
+            map.insert("scale".into(), Dynamic::from(entity.transform.scale));
+            map.insert(
+                "parent".into(),
+                entity
+                    .parent
+                    .map_or(Dynamic::UNIT, |parent| Dynamic::from(parent.0 as i64)),
+            );
+            let children: Array = entity
+                .children
+                .iter()
+                .map(|child| Dynamic::from(child.0 as i64))
+                .collect();
+            map.insert("children".into(), Dynamic::from(children));
+            map.insert(
+                "has_script".into(),
+                Dynamic::from(entity.has_component::<Script>()),
+            );
+            Dynamic::from(map)
+        })
+        .collect()
+}
+
+/// Text for a snippet's value, spelling out the engine's vector types
+fn display(value: &Dynamic) -> String {
+    if let Some(v) = value.clone().try_cast::<Vec3>() {
+        format!("Vec3({}, {}, {})", v.x, v.y, v.z)
+    } else if let Some(q) = value.clone().try_cast::<Quat>() {
+        format!("Quat({}, {}, {}, {})", q.x, q.y, q.z, q.w)
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine_scene::transform::Transform;
+
+    #[test]
+    fn test_snippets_and_diagnostics() {
+        let mut scene = Scene::new("Test".to_string());
+        scene.create_entity_with_transform(
+            "Tower".to_string(),
+            Transform::from_position(Vec3::new(0.0, 12.5, 0.0)),
+        );
+
+        let result = run_snippet(
+            r#"print(entities.len()); find_entity("Tower").position.y"#,
+            &scene,
+        )
+        .unwrap();
+        assert_eq!(result.output, vec!["1".to_string()]);
+        assert_eq!(result.value.as_deref(), Some("12.5"));
+
+        // Runtime and compile errors point at the offending line
+        let error = run_snippet("let a = 1;\nmissing_fn(a)", &scene).unwrap_err();
+        assert_eq!(error.line, Some(2));
+        let error = check_script("fn update(ctx) {\n  let x = ;\n}").unwrap_err();
+        assert_eq!(error.line, Some(2));
+        assert_eq!(
+            check_script("fn start(ctx) {}\nfn update(ctx) { ctx }").unwrap(),
+            vec!["start".to_string(), "update".to_string()]
+        );
+    }
+}
@@ -0,0 +1,487 @@
+// Save games - runtime state written to numbered or named slots
+//
+// Scene files hold what was authored; a save holds what happened since:
+// entity transforms (and body velocities, filled in by whoever owns the
+// physics world), variables in each script's scope, the music that was
+// playing and the game's own key/value data. Scripts ask for a save or a
+// load with save_game(slot)/load_game(slot); the game loop carries the
+// request out between frames, the same way it plays queued audio.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context, Result};
+use engine_scene::entity::EntityId;
+use engine_scene::scene::Scene;
+use glam::{Quat, Vec3};
+use rhai::{Array, Dynamic, Engine, Map, Scope};
+use serde::{Deserialize, Serialize};
+
+use crate::runtime::ScriptRuntime;
+
+/// Version written into new saves. Saves from a newer version are refused.
+pub const SAVE_FORMAT_VERSION: u32 = 1;
+
+/// Default directory for save slots
+pub const DEFAULT_SAVE_DIR: &str = "saves";
+
+/// A script value that can be written to a save
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SavedValue {
+    Unit,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Vec3([f32; 3]),
+    Quat([f32; 4]),
+    Array(Vec<SavedValue>),
+    Map(BTreeMap<String, SavedValue>),
+}
+
+impl SavedValue {
+    /// None for values a save can't hold (functions, engine handles, ...)
+    pub fn from_dynamic(value: &Dynamic) -> Option<Self> {
+        if value.is_unit() {
+            return Some(SavedValue::Unit);
+        }
+        if let Ok(b) = value.as_bool() {
+            return Some(SavedValue::Bool(b));
+        }
+        if let Ok(i) = value.as_int() {
+            return Some(SavedValue::Int(i));
+        }
+        if let Ok(f) = value.as_float() {
+            return Some(SavedValue::Float(f));
+        }
+        if let Ok(c) = value.as_char() {
+            return Some(SavedValue::String(c.to_string()));
+        }
+        if value.is_string() {
+            return Some(SavedValue::String(value.clone().into_string().ok()?));
+        }
+        if let Some(v) = value.clone().try_cast::<Vec3>() {
+            return Some(SavedValue::Vec3(v.to_array()));
+        }
+        if let Some(q) = value.clone().try_cast::<Quat>() {
+            return Some(SavedValue::Quat(q.to_array()));
+        }
+        if value.is_array() {
+            let array = value.read_lock::<Array>()?;
+            return array
+                .iter()
+                .map(Self::from_dynamic)
+                .collect::<Option<Vec<_>>>()
+                .map(SavedValue::Array);
+        }
+        if value.is_map() {
+            let map = value.read_lock::<Map>()?;
+            return map
+                .iter()
+                .map(|(key, value)| Some((key.to_string(), Self::from_dynamic(value)?)))
+                .collect::<Option<BTreeMap<_, _>>>()
+                .map(SavedValue::Map);
+        }
+        None
+    }
+
+    pub fn to_dynamic(&self) -> Dynamic {
+        match self {
+            SavedValue::Unit => Dynamic::UNIT,
+            SavedValue::Bool(b) => Dynamic::from(*b),
+            SavedValue::Int(i) => Dynamic::from(*i),
+            SavedValue::Float(f) => Dynamic::from(*f),
+            SavedValue::String(s) => Dynamic::from(s.clone()),
+            SavedValue::Vec3(v) => Dynamic::from(Vec3::from_array(*v)),
+            SavedValue::Quat(q) => Dynamic::from(Quat::from_array(*q)),
+            SavedValue::Array(items) => {
+                Dynamic::from(items.iter().map(Self::to_dynamic).collect::<Array>())
+            }
+            SavedValue::Map(entries) => Dynamic::from(
+                entries
+                    .iter()
+                    .map(|(key, value)| (key.as_str().into(), value.to_dynamic()))
+                    .collect::<Map>(),
+            ),
+        }
+    }
+}
+
+/// An entity's transform, plus its body's velocities if it has one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedEntity {
+    pub id: u64,
+    pub name: String,
+    pub position: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+    #[serde(default)]
+    pub linear_velocity: Option<[f32; 3]>,
+    #[serde(default)]
+    pub angular_velocity: Option<[f32; 3]>,
+}
+
+/// The music track that was playing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedMusic {
+    pub path: String,
+    pub volume: f32,
+    pub looping: bool,
+}
+
+/// Everything in one save slot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub version: u32,
+    /// Seconds since the Unix epoch
+    pub saved_at: u64,
+    pub scene: String,
+    pub entities: Vec<SavedEntity>,
+    /// Script scope variables, by entity ID
+    #[serde(default)]
+    pub script_variables: BTreeMap<u64, BTreeMap<String, SavedValue>>,
+    #[serde(default)]
+    pub music: Option<SavedMusic>,
+    /// Data the game stored with set_game_data()
+    #[serde(default)]
+    pub data: BTreeMap<String, SavedValue>,
+}
+
+impl SaveGame {
+    /// Snapshot the scene's transforms, the scripts' variables and the
+    /// game data. Velocities and music are left for the caller to fill in.
+    pub fn capture(
+        scene: &Scene,
+        runtime: &ScriptRuntime,
+        data: &BTreeMap<String, Dynamic>,
+    ) -> Self {
+        let mut entities: Vec<_> = scene.entities().collect();
+        entities.sort_by_key(|entity| entity.id.0);
+        let entities = entities
+            .into_iter()
+            .map(|entity| SavedEntity {
+                id: entity.id.0,
+                name: entity.name.clone(),
+                position: entity.transform.position.to_array(),
+                rotation: entity.transform.rotation.to_array(),
+                scale: entity.transform.scale.to_array(),
+                linear_velocity: None,
+                angular_velocity: None,
+            })
+            .collect();
+
+        let script_variables = runtime
+            .scopes()
+            .map(|(entity_id, scope)| (entity_id.0, scope_variables(scope)))
+            .filter(|(_, variables)| !variables.is_empty())
+            .collect();
+
+        Self {
+            version: SAVE_FORMAT_VERSION,
+            saved_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            scene: scene.name.clone(),
+            entities,
+            script_variables,
+            music: None,
+            data: saved_map(data),
+        }
+    }
+
+    /// Put the saved transforms, script variables and game data back.
+    /// Entities that no longer exist are skipped. Physics and music are
+    /// left for the caller.
+    pub fn restore(
+        &self,
+        scene: &mut Scene,
+        runtime: &mut ScriptRuntime,
+        data: &mut BTreeMap<String, Dynamic>,
+    ) {
+        for saved in &self.entities {
+            let Some(entity) = scene.get_entity_mut(EntityId(saved.id)) else {
+                log::warn!(
+                    "Save has entity {} ('{}') which is not in the scene",
+                    saved.id,
+                    saved.name
+                );
+                continue;
+            };
+            entity.transform.position = Vec3::from_array(saved.position);
+            entity.transform.rotation = Quat::from_array(saved.rotation);
+            entity.transform.scale = Vec3::from_array(saved.scale);
+        }
+
+        for (id, variables) in &self.script_variables {
+            let Some(scope) = runtime.scope_mut(EntityId(*id)) else {
+                continue;
+            };
+            for (name, value) in variables {
+                if scope.is_constant(name) != Some(true) {
+                    scope.set_value(name.clone(), value.to_dynamic());
+                }
+            }
+        }
+
+        *data = self
+            .data
+            .iter()
+            .map(|(key, value)| (key.clone(), value.to_dynamic()))
+            .collect();
+    }
+}
+
+/// A scope's non-constant variables that can be saved
+fn scope_variables(scope: &Scope) -> BTreeMap<String, SavedValue> {
+    scope
+        .iter()
+        .filter(|(_, is_constant, _)| !is_constant)
+        .filter_map(|(name, _, value)| Some((name.to_string(), SavedValue::from_dynamic(&value)?)))
+        .collect()
+}
+
+fn saved_map(data: &BTreeMap<String, Dynamic>) -> BTreeMap<String, SavedValue> {
+    data.iter()
+        .filter_map(|(key, value)| match SavedValue::from_dynamic(value) {
+            Some(saved) => Some((key.clone(), saved)),
+            None => {
+                log::warn!("Game data '{}' ({}) can't be saved", key, value.type_name());
+                None
+            }
+        })
+        .collect()
+}
+
+/// Save files in a directory, one per slot
+#[derive(Debug, Clone)]
+pub struct SaveSlots {
+    dir: PathBuf,
+}
+
+impl SaveSlots {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The file for a slot. Slot names are letters, digits, '-' and '_'.
+    pub fn path(&self, slot: &str) -> Result<PathBuf> {
+        let valid = !slot.is_empty()
+            && slot
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            bail!("Invalid save slot '{}'", slot);
+        }
+        Ok(self.dir.join(format!("{}.ron", slot)))
+    }
+
+    pub fn exists(&self, slot: &str) -> bool {
+        self.path(slot).is_ok_and(|path| path.is_file())
+    }
+
+    pub fn save(&self, slot: &str, save: &SaveGame) -> Result<PathBuf> {
+        let path = self.path(slot)?;
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let text = ron::ser::to_string_pretty(save, Default::default())?;
+        std::fs::write(&path, text)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        log::info!("Saved game to slot '{}'", slot);
+        Ok(path)
+    }
+
+    pub fn load(&self, slot: &str) -> Result<SaveGame> {
+        let path = self.path(slot)?;
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("No save in slot '{}'", slot))?;
+        let save: SaveGame = ron::de::from_str(&text)
+            .map_err(|e| anyhow!("Save slot '{}' is corrupt: {}", slot, e))?;
+        if save.version > SAVE_FORMAT_VERSION {
+            bail!(
+                "Save slot '{}' is version {}, newer than this build reads ({})",
+                slot,
+                save.version,
+                SAVE_FORMAT_VERSION
+            );
+        }
+        Ok(save)
+    }
+
+    /// Slot names with a save, sorted
+    pub fn list(&self) -> Vec<String> {
+        let mut slots: Vec<String> = std::fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                if path.extension()? != "ron" {
+                    return None;
+                }
+                Some(path.file_stem()?.to_str()?.to_string())
+            })
+            .collect();
+        slots.sort();
+        slots
+    }
+}
+
+impl Default for SaveSlots {
+    fn default() -> Self {
+        Self::new(DEFAULT_SAVE_DIR)
+    }
+}
+
+/// What a script asked for
+#[derive(Debug, Clone, PartialEq)]
+pub enum SaveRequest {
+    Save { slot: String },
+    Load { slot: String },
+}
+
+/// Requests from scripts plus the game data they've stored
+#[derive(Debug, Default)]
+pub struct SaveState {
+    pub requests: Vec<SaveRequest>,
+    pub data: BTreeMap<String, Dynamic>,
+}
+
+/// Thread-safe save state shared with the script engine
+pub type SaveStateHandle = Arc<Mutex<SaveState>>;
+
+/// Register save_game, load_game, has_save, set_game_data and get_game_data
+pub fn register_save_api(engine: &mut Engine, state: SaveStateHandle, slots: SaveSlots) {
+    let save_state = state.clone();
+    let save_slots = slots.clone();
+    engine.register_fn("save_game", move |slot: &str| {
+        if save_slots.path(slot).is_err() {
+            log::warn!("save_game: invalid slot '{}'", slot);
+            return false;
+        }
+        save_state.lock().unwrap().requests.push(SaveRequest::Save {
+            slot: slot.to_string(),
+        });
+        true
+    });
+
+    // Only queued if the slot has a save, so scripts can fall back
+    let load_state = state.clone();
+    let load_slots = slots.clone();
+    engine.register_fn("load_game", move |slot: &str| {
+        if !load_slots.exists(slot) {
+            return false;
+        }
+        load_state.lock().unwrap().requests.push(SaveRequest::Load {
+            slot: slot.to_string(),
+        });
+        true
+    });
+
+    engine.register_fn("has_save", move |slot: &str| slots.exists(slot));
+
+    let set_state = state.clone();
+    engine.register_fn("set_game_data", move |key: &str, value: Dynamic| {
+        set_state
+            .lock()
+            .unwrap()
+            .data
+            .insert(key.to_string(), value);
+    });
+
+    engine.register_fn("get_game_data", move |key: &str| {
+        state
+            .lock()
+            .unwrap()
+            .data
+            .get(key)
+            .cloned()
+            .unwrap_or(Dynamic::UNIT)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::Script;
+    use crate::system::ScriptSystem;
+
+    #[test]
+    fn test_save_and_restore_round_trip() {
+        let dir = std::env::temp_dir().join(format!("causality_saves_test_{}", std::process::id()));
+        let slots = SaveSlots::new(&dir);
+
+        let mut scene = Scene::new("Test".to_string());
+        let player = scene.create_entity("Player".to_string());
+        scene
+            .get_entity_mut(player)
+            .unwrap()
+            .add_component(Script::new("fn update(ctx) { ctx }".to_string()));
+
+        let state = SaveStateHandle::default();
+        let mut scripts = ScriptSystem::new();
+        scripts.register_save_api(state.clone(), slots.clone());
+        scripts.initialize(&scene).unwrap();
+        assert!(scripts
+            .runtime_mut()
+            .eval_with_scope(player, "let coins = 7;")
+            .unwrap()
+            .is_unit());
+        assert!(scripts
+            .runtime_mut()
+            .eval("save_game(\"slot1\"); set_game_data(\"level\", \"castle\"); set_game_data(\"deaths\", [1, 2])")
+            .unwrap()
+            .is_unit());
+        assert_eq!(
+            state.lock().unwrap().requests,
+            vec![SaveRequest::Save {
+                slot: "slot1".to_string()
+            }]
+        );
+
+        scene.get_entity_mut(player).unwrap().transform.position = Vec3::new(4.0, 5.0, 6.0);
+        let save = SaveGame::capture(&scene, scripts.runtime(), &state.lock().unwrap().data);
+        slots.save("slot1", &save).unwrap();
+        assert_eq!(slots.list(), vec!["slot1".to_string()]);
+        assert!(slots.path("../escape").is_err());
+
+        // Change everything, then load the slot back
+        scene.get_entity_mut(player).unwrap().transform.position = Vec3::ZERO;
+        assert!(scripts
+            .runtime_mut()
+            .eval_with_scope(player, "coins = 0;")
+            .unwrap()
+            .is_unit());
+        state.lock().unwrap().data.clear();
+
+        let loaded = slots.load("slot1").unwrap();
+        assert_eq!(loaded, save);
+        loaded.restore(
+            &mut scene,
+            scripts.runtime_mut(),
+            &mut state.lock().unwrap().data,
+        );
+        assert_eq!(
+            scene.get_entity(player).unwrap().transform.position,
+            Vec3::new(4.0, 5.0, 6.0)
+        );
+        let coins = scripts
+            .runtime_mut()
+            .eval_with_scope(player, "coins")
+            .unwrap();
+        assert_eq!(coins.as_int().unwrap(), 7);
+        let level = scripts
+            .runtime_mut()
+            .eval("get_game_data(\"level\")")
+            .unwrap();
+        assert_eq!(level.into_string().unwrap(), "castle");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
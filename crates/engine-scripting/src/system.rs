@@ -1,9 +1,16 @@
 // Script system - manages script execution in the game loop
 
+use crate::animator::{self, AnimatorCommandQueue};
 use crate::api;
+use crate::behavior;
+use crate::character::{self, CharacterCommandQueue, CharacterStateHandle};
 use crate::components::Script;
+use crate::navigation::{self, NavStateHandle};
+use crate::physics::{self, PhysicsQueryHandle};
+use crate::ragdoll::{self, RagdollCommandQueue};
 use crate::runtime::ScriptRuntime;
 use anyhow::Result;
+use engine_ai_behavior::BehaviorStatus;
 use engine_scene::scene::Scene;
 use glam::{Quat, Vec3};
 use rhai::Map;
@@ -11,6 +18,18 @@ use rhai::Map;
 /// Script system - handles script initialization and update
 pub struct ScriptSystem {
     runtime: ScriptRuntime,
+    /// Animator parameters set by scripts, applied after they run
+    animator_commands: AnimatorCommandQueue,
+    /// Ragdoll changes requested by scripts, applied after they run
+    ragdoll_commands: RagdollCommandQueue,
+    /// Agent destinations set by scripts, and whether agents have arrived
+    navigation: NavStateHandle,
+    /// Characters' ground, read by scripts
+    characters: CharacterStateHandle,
+    /// Character moves requested by scripts, applied after they run
+    character_commands: CharacterCommandQueue,
+    /// Physics world that scripts' shape casts and overlaps query, lent while they run
+    physics: PhysicsQueryHandle,
 }
 
 impl ScriptSystem {
@@ -19,13 +38,38 @@ impl ScriptSystem {
 
         // Register API bindings
         api::register_api(runtime.engine_mut());
+        let animator_commands = AnimatorCommandQueue::default();
+        animator::register_animator_api(runtime.engine_mut(), animator_commands.clone());
+        let ragdoll_commands = RagdollCommandQueue::default();
+        ragdoll::register_ragdoll_api(runtime.engine_mut(), ragdoll_commands.clone());
+        let navigation = NavStateHandle::default();
+        navigation::register_navigation_api(runtime.engine_mut(), navigation.clone());
+        let characters = CharacterStateHandle::default();
+        let character_commands = CharacterCommandQueue::default();
+        character::register_character_api(
+            runtime.engine_mut(),
+            characters.clone(),
+            character_commands.clone(),
+        );
+        let physics = PhysicsQueryHandle::default();
+        physics::register_physics_api(runtime.engine_mut(), physics.clone());
 
-        Self { runtime }
+        Self {
+            runtime,
+            animator_commands,
+            ragdoll_commands,
+            navigation,
+            characters,
+            character_commands,
+            physics,
+        }
     }
 
     /// Initialize scripts from scene entities
     pub fn initialize(&mut self, scene: &Scene) -> Result<()> {
-        for entity in scene.entities() {
+        let mut entities: Vec<_> = scene.entities().collect();
+        entities.sort_by_key(|entity| entity.id.0);
+        for entity in entities {
             if let Some(script) = entity.get_component::<Script>() {
                 if script.enabled {
                     self.runtime.load_script(entity.id, script.source.clone())?;
@@ -39,7 +83,11 @@ impl ScriptSystem {
 
     /// Call start() function on all scripts (called once after initialization)
     pub fn start(&mut self, scene: &mut Scene) -> Result<()> {
-        let entity_ids: Vec<_> = scene.entities().map(|e| e.id).collect();
+        // In entity order, so scripts that affect each other run the same way every time
+        let mut entity_ids: Vec<_> = scene.entities().map(|e| e.id).collect();
+        entity_ids.sort_by_key(|id| id.0);
+        navigation::refresh_nav_state(&self.navigation, scene);
+        character::refresh_character_state(&self.characters, scene);
 
         for entity_id in entity_ids {
             if !self.runtime.has_script(entity_id) {
@@ -62,17 +110,24 @@ impl ScriptSystem {
             }
         }
 
+        animator::apply_animator_commands(&self.animator_commands, scene);
+        ragdoll::apply_ragdoll_commands(&self.ragdoll_commands, scene);
+        navigation::apply_nav_commands(&self.navigation, scene);
+        character::apply_character_commands(&self.character_commands, scene);
         Ok(())
     }
 
     /// Update all scripts
     pub fn update(&mut self, scene: &mut Scene, delta_time: f32) -> Result<()> {
-        // Collect all entity IDs that have scripts
-        let entity_ids: Vec<_> = scene
+        // Collect all entity IDs that have scripts, in entity order
+        let mut entity_ids: Vec<_> = scene
             .entities()
             .filter(|e| self.runtime.has_script(e.id))
             .map(|e| e.id)
             .collect();
+        entity_ids.sort_by_key(|id| id.0);
+        navigation::refresh_nav_state(&self.navigation, scene);
+        character::refresh_character_state(&self.characters, scene);
 
         for entity_id in entity_ids {
             let entity = scene.get_entity(entity_id).unwrap();
@@ -129,14 +184,73 @@ impl ScriptSystem {
             }
         }
 
+        animator::apply_animator_commands(&self.animator_commands, scene);
+        ragdoll::apply_ragdoll_commands(&self.ragdoll_commands, scene);
+        navigation::apply_nav_commands(&self.navigation, scene);
+        character::apply_character_commands(&self.character_commands, scene);
         Ok(())
     }
 
+    /// Call a behavior tree task function in an entity's script, then apply
+    /// the animator, ragdoll, navigation and character changes it queued
+    pub fn run_behavior_task(
+        &mut self,
+        scene: &mut Scene,
+        entity_id: engine_scene::entity::EntityId,
+        function: &str,
+        delta_time: f32,
+    ) -> BehaviorStatus {
+        let Some(entity) = scene.get_entity(entity_id) else {
+            return BehaviorStatus::Failure;
+        };
+        let mut context = Map::new();
+        context.insert("entity_id".into(), rhai::Dynamic::from(entity_id.0 as i64));
+        context.insert(
+            "position".into(),
+            rhai::Dynamic::from(entity.transform.position),
+        );
+        context.insert(
+            "rotation".into(),
+            rhai::Dynamic::from(entity.transform.rotation),
+        );
+        context.insert("scale".into(), rhai::Dynamic::from(entity.transform.scale));
+        context.insert("dt".into(), rhai::Dynamic::from(delta_time));
+
+        let status = match self.runtime.call_function(entity_id, function, (context,)) {
+            Ok(result) => behavior::task_status(&result),
+            Err(e) => {
+                log::warn!(
+                    "Behavior task {}() in entity {}: {}",
+                    function,
+                    entity.name,
+                    e
+                );
+                BehaviorStatus::Failure
+            }
+        };
+        animator::apply_animator_commands(&self.animator_commands, scene);
+        ragdoll::apply_ragdoll_commands(&self.ragdoll_commands, scene);
+        navigation::apply_nav_commands(&self.navigation, scene);
+        character::apply_character_commands(&self.character_commands, scene);
+        status
+    }
+
     /// Reload a script
     pub fn reload_script(&mut self, entity_id: engine_scene::entity::EntityId, source: String) -> Result<()> {
         self.runtime.reload_script(entity_id, source)
     }
 
+    /// Run `f` (start() or update()) with the physics world lent to scripts'
+    /// queries; outside of it they find nothing
+    pub fn with_physics<R>(
+        &mut self,
+        physics_world: &mut engine_physics::PhysicsWorld,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        let state = self.physics.clone();
+        physics::with_physics_world(&state, physics_world, || f(self))
+    }
+
     /// Get runtime reference
     pub fn runtime(&self) -> &ScriptRuntime {
         &self.runtime
@@ -149,7 +263,46 @@ impl ScriptSystem {
 
     /// Register audio API with script engine
     pub fn register_audio_api(&mut self, command_queue: crate::audio::AudioCommandQueue) {
-        crate::audio::register_audio_api(self.runtime.engine_mut(), command_queue);
+        crate::audio::register_audio_api(self.runtime.engine_mut(), command_queue.clone());
+        self.runtime.add_global(
+            "music",
+            rhai::Dynamic::from(crate::audio::MusicApi::new(command_queue)),
+        );
+    }
+
+    /// Register time scale and pause functions with script engine
+    pub fn register_time_api(&mut self, time: engine_core::time::SharedTime) {
+        crate::time::register_time_api(self.runtime.engine_mut(), time);
+    }
+
+    /// Register random(), random_range() and random_int()
+    pub fn register_random_api(&mut self, rng: engine_core::determinism::SharedRng) {
+        crate::random::register_random_api(self.runtime.engine_mut(), rng);
+    }
+
+    /// Register is_key_down() and the other game input functions, with
+    /// actions bound by the project's input map
+    pub fn register_game_input_api(&mut self, input: crate::game_input::SharedGameInput) {
+        crate::game_input::register_game_input_api(self.runtime.engine_mut(), input.clone());
+        crate::game_input::register_game_action_api(
+            self.runtime.engine_mut(),
+            input,
+            crate::game_input::project_action_map(),
+        );
+    }
+
+    /// Register save_game/load_game and the game data functions
+    pub fn register_save_api(
+        &mut self,
+        state: crate::save_game::SaveStateHandle,
+        slots: crate::save_game::SaveSlots,
+    ) {
+        crate::save_game::register_save_api(self.runtime.engine_mut(), state, slots);
+    }
+
+    /// Register net_send/net_receive and the session role functions
+    pub fn register_net_api(&mut self, state: crate::net::NetStateHandle) {
+        crate::net::register_net_api(self.runtime.engine_mut(), state);
     }
 }
 
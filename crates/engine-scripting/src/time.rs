@@ -0,0 +1,36 @@
+// Time API for scripts - time scale (slow motion) and pausing
+
+use engine_core::time::SharedTime;
+use rhai::Engine;
+
+/// Register time functions with Rhai engine
+pub fn register_time_api(engine: &mut Engine, time: SharedTime) {
+    let time_clone1 = time.clone();
+    let time_clone2 = time.clone();
+    let time_clone3 = time.clone();
+    let time_clone4 = time.clone();
+    let time_clone5 = time.clone();
+    let time_clone6 = time.clone();
+
+    // Time scale: 1.0 normal, 0.25 slow motion, 0.0 frozen
+    engine
+        .register_fn("time_scale", move || time_clone1.lock().unwrap().time_scale() as f64)
+        .register_fn("set_time_scale", move |scale: f64| {
+            time_clone2.lock().unwrap().set_time_scale(scale as f32);
+        });
+
+    // Pausing stops physics, animation and particles; scripts keep
+    // running (with ctx.dt of 0) so they can resume
+    engine
+        .register_fn("pause_game", move || time_clone3.lock().unwrap().set_paused(true))
+        .register_fn("resume_game", move || time_clone4.lock().unwrap().set_paused(false))
+        .register_fn("is_game_paused", move || time_clone5.lock().unwrap().is_paused());
+
+    // Real frame time, for things that shouldn't slow down (menus, cameras)
+    engine.register_fn("unscaled_dt", move || {
+        time_clone6.lock().unwrap().unscaled_delta() as f64
+    });
+
+    // Scaled seconds since the game started
+    engine.register_fn("game_time", move || time.lock().unwrap().elapsed() as f64);
+}